@@ -0,0 +1,111 @@
+//! Ad Blocking Concurrency Benchmarks
+//!
+//! Measures 10,000 blocking decisions made:
+//! - On a single thread
+//! - From 4 threads sharing one engine (read-locked checks)
+//! - From 4 threads taking turns on one engine behind a mutex, the
+//!   exclusive access checks needed before the engine was `Sync`
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use adblock_engine::AdBlockEngine;
+use config_manager::AdBlockConfig;
+use message_bus::MessageSender;
+use shared_types::{BrowserMessage, ResourceType};
+
+/// Blocking decisions per benchmark iteration
+const CHECKS: usize = 10_000;
+
+/// Threads the checks are spread over
+const THREADS: usize = 4;
+
+/// Sender that drops every message
+struct NullSender;
+
+impl MessageSender for NullSender {
+    fn send(&self, _message: BrowserMessage) -> message_bus::Result<()> {
+        Ok(())
+    }
+}
+
+/// Engine with a handful of rules, half of which match the checked URLs
+fn engine() -> AdBlockEngine {
+    let config = AdBlockConfig {
+        enabled: true,
+        update_filters_on_startup: false,
+        custom_filters: vec![
+            "||ads.bench.example^".to_string(),
+            "||tracker.bench.example^$third-party".to_string(),
+            "/banner/*$image".to_string(),
+        ],
+        allowlisted_domains: vec![],
+    };
+    let mut engine = AdBlockEngine::new(config, Box::new(NullSender)).unwrap();
+    engine.initialize().unwrap();
+    engine
+}
+
+/// The `i`th URL checked, alternating between blocked and allowed
+fn url(i: usize) -> &'static str {
+    match i % 4 {
+        0 => "https://ads.bench.example/ad.js",
+        1 => "https://cdn.bench.example/app.js",
+        2 => "https://tracker.bench.example/pixel.js",
+        _ => "https://news.bench.example/story.js",
+    }
+}
+
+fn check_range(engine: &AdBlockEngine, range: std::ops::Range<usize>) {
+    for i in range {
+        black_box(engine.should_block(
+            black_box(url(i)),
+            "https://news.bench.example/",
+            ResourceType::Script,
+        ));
+    }
+}
+
+fn bench_concurrent_checks(c: &mut Criterion) {
+    let mut group = c.benchmark_group("adblock_10k_checks");
+    let per_thread = CHECKS / THREADS;
+
+    let shared = engine();
+    group.bench_function("single_thread", |b| {
+        b.iter(|| check_range(&shared, 0..CHECKS));
+    });
+
+    group.bench_function("four_threads_shared", |b| {
+        b.iter(|| {
+            thread::scope(|scope| {
+                for t in 0..THREADS {
+                    let shared = &shared;
+                    scope.spawn(move || check_range(shared, t * per_thread..(t + 1) * per_thread));
+                }
+            });
+        });
+    });
+
+    let exclusive = Arc::new(Mutex::new(engine()));
+    group.bench_function("four_threads_exclusive", |b| {
+        b.iter(|| {
+            thread::scope(|scope| {
+                for t in 0..THREADS {
+                    let exclusive = Arc::clone(&exclusive);
+                    scope.spawn(move || {
+                        for i in t * per_thread..(t + 1) * per_thread {
+                            let engine = exclusive.lock().unwrap();
+                            check_range(&engine, i..i + 1);
+                        }
+                    });
+                }
+            });
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_concurrent_checks);
+criterion_main!(benches);
@@ -2,8 +2,10 @@ use criterion::{black_box, criterion_group, criterion_main, Criterion, Benchmark
 use std::time::Duration;
 
 // Import components for benchmarking
-use message_bus::{MessageBus, MessageSender};
-use shared_types::{BrowserMessage, ResourceType};
+use message_bus::{MessageBus, MessageFilter, MessageHandler, MessageSender};
+use shared_types::{BrowserMessage, MessageKind, NavigationInitiator, ResourceType};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use config_manager::Config;
 use adblock_engine::AdBlockEngine;
 
@@ -50,6 +52,84 @@ fn bench_message_bus_handlers(c: &mut Criterion) {
     });
 }
 
+/// Handler that counts its calls
+struct CountingHandler {
+    calls: Arc<AtomicUsize>,
+}
+
+impl MessageHandler for CountingHandler {
+    fn handle(&self, message: BrowserMessage) -> message_bus::Result<()> {
+        black_box(message);
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Benchmark: Dispatch to Filtered vs Unfiltered Handlers
+///
+/// One handler per message kind, each only interested in its kind, with a
+/// stream mixing every kind. Unfiltered, every handler is called for every
+/// message; filtered, each message reaches one handler.
+fn bench_message_bus_filtered_dispatch(c: &mut Criterion) {
+    const MESSAGES: usize = 900;
+    let messages: Vec<BrowserMessage> = (0..MESSAGES as u32)
+        .map(|i| match i % 9 {
+            0 => BrowserMessage::Reload { tab_id: i },
+            1 => BrowserMessage::HttpResponse {
+                request_id: u64::from(i),
+                status: 200,
+                headers: std::collections::HashMap::new(),
+                final_url: "https://example.com/".parse().unwrap(),
+                from_cache: false,
+                body: Vec::new(),
+            },
+            2 => BrowserMessage::BlockDecision { block: false, reason: None },
+            3 => BrowserMessage::SwitchTab { tab_id: i },
+            4 => BrowserMessage::DownloadFailed { id: u64::from(i), reason: String::new() },
+            5 => BrowserMessage::OpenBookmarkFolder { folder: String::new(), confirmed: false },
+            6 => BrowserMessage::SetOffline { offline: false },
+            7 => BrowserMessage::ContentSettingsChanged { origin: String::new() },
+            _ => BrowserMessage::ResumeBackgroundTransfers,
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("message_bus_filtered_dispatch");
+    for filtered in [false, true] {
+        let name = if filtered { "filtered" } else { "unfiltered" };
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut bus = MessageBus::new();
+        for kind in MessageKind::ALL {
+            let handler = Box::new(CountingHandler { calls: Arc::clone(&calls) });
+            if filtered {
+                bus.register_handler_filtered(handler, MessageFilter::kind(kind));
+            } else {
+                bus.register_handler(handler);
+            }
+        }
+        bus.start().unwrap();
+        let sender = bus.sender();
+
+        let mut rounds = 0;
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for message in &messages {
+                    sender.send(message.clone()).unwrap();
+                }
+                // A request is answered once everything before it was handled
+                let _ = sender.request(BrowserMessage::Shutdown);
+                rounds += 1;
+            });
+        });
+        bus.shutdown().unwrap();
+
+        let per_message = calls.load(Ordering::Relaxed) as f64 / (rounds * (MESSAGES + 1)) as f64;
+        println!(
+            "message_bus_filtered_dispatch/{name}: {per_message:.2} handler calls per message"
+        );
+    }
+    group.finish();
+}
+
 /// Benchmark: Configuration Loading
 fn bench_config_loading(c: &mut Criterion) {
     c.bench_function("config_load_default", |b| {
@@ -122,6 +202,7 @@ fn bench_message_creation(c: &mut Criterion) {
             let msg = BrowserMessage::NavigateRequest {
                 tab_id: 1,
                 url: "https://example.com".parse().unwrap(),
+                initiator: NavigationInitiator::UserTyped,
             };
             black_box(msg);
         });
@@ -155,6 +236,7 @@ fn bench_message_serialization(c: &mut Criterion) {
     let msg = BrowserMessage::NavigateRequest {
         tab_id: 1,
         url: "https://example.com".parse().unwrap(),
+        initiator: NavigationInitiator::UserTyped,
     };
 
     c.bench_function("message_to_json", |b| {
@@ -251,6 +333,7 @@ criterion_group!(
     bench_message_bus_creation,
     bench_message_bus_throughput,
     bench_message_bus_handlers,
+    bench_message_bus_filtered_dispatch,
     bench_config_loading,
     bench_config_serialization,
     bench_adblock_init,
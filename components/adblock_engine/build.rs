@@ -2,16 +2,27 @@
 //!
 //! Automatically downloads the EasyList filter file if it doesn't exist.
 //! This ensures the ad blocker has up-to-date filter rules.
+//!
+//! With the `seed-filters` feature it also validates the embedded seed list
+//! and writes its rules to `OUT_DIR` for `include_str!`. A missing or
+//! malformed seed list fails the build.
 
+use std::env;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 const EASYLIST_URL: &str = "https://easylist.to/easylist/easylist.txt";
 const FILTER_PATH: &str = "../../resources/filters/easylist.txt";
+const SEED_PATH: &str = "resources/filters/seed.txt";
+const SEED_OUT: &str = "seed_filters.txt";
 
 fn main() {
     println!("cargo:rerun-if-changed=build.rs");
 
+    if env::var_os("CARGO_FEATURE_SEED_FILTERS").is_some() {
+        generate_seed_filters();
+    }
+
     let filter_path = Path::new(FILTER_PATH);
 
     // Create resources/filters directory if it doesn't exist
@@ -72,6 +83,72 @@ fn main() {
     }
 }
 
+/// Validate the seed list and write its rules to `OUT_DIR`
+///
+/// Panics (failing the build) if the list is missing, has no rules, or
+/// contains a line that is not a filter rule.
+fn generate_seed_filters() {
+    let manifest_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let seed_path = manifest_dir.join(SEED_PATH);
+    println!("cargo:rerun-if-changed={}", seed_path.display());
+
+    let content = fs::read_to_string(&seed_path).unwrap_or_else(|e| {
+        panic!(
+            "Seed filter list {} is missing ({}); restore it or build without the \
+             `seed-filters` feature",
+            seed_path.display(),
+            e
+        )
+    });
+
+    let mut rules = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let rule = line.trim();
+        if rule.is_empty() || rule.starts_with('!') || rule.starts_with('[') {
+            continue;
+        }
+        if let Err(reason) = validate_rule(rule) {
+            panic!(
+                "Seed filter list {} line {}: {} ({:?})",
+                seed_path.display(),
+                index + 1,
+                reason,
+                rule
+            );
+        }
+        rules.push(rule);
+    }
+    if rules.is_empty() {
+        panic!("Seed filter list {} has no rules", seed_path.display());
+    }
+
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap()).join(SEED_OUT);
+    fs::write(&out_path, rules.join("\n") + "\n").expect("Failed to write seed filter rules");
+}
+
+/// Syntax check for a single filter rule
+fn validate_rule(rule: &str) -> Result<(), &'static str> {
+    if rule.chars().any(|c| c.is_control()) {
+        return Err("control character in rule");
+    }
+    if let Some(index) = rule.find("##").or_else(|| rule.find("#@#")) {
+        let selector = rule[index..].trim_start_matches(['#', '@']);
+        if selector.is_empty() {
+            return Err("element hiding rule without a selector");
+        }
+        return Ok(());
+    }
+    if rule.contains(char::is_whitespace) {
+        return Err("whitespace in network rule");
+    }
+    let pattern = rule.split('$').next().unwrap_or_default();
+    let pattern = pattern.trim_start_matches("@@").trim_start_matches('|');
+    if pattern.trim_matches(['^', '*', '|']).is_empty() {
+        return Err("network rule matches everything");
+    }
+    Ok(())
+}
+
 fn download_easylist(dest_path: &Path) -> Result<u64, Box<dyn std::error::Error>> {
     // Use curl if available (more reliable in build scripts)
     if which("curl") {
@@ -0,0 +1,111 @@
+//! Cosmetic filtering
+//!
+//! Element hiding rules hide parts of a page instead of blocking requests:
+//! `##.ad-banner` hides matching elements on every site,
+//! `news.example##.promo` only on that site, and `news.example#@#.ad-banner`
+//! turns a generic rule off there. The adblock crate resolves the
+//! site-specific rules and exceptions, but only hands out generic class and
+//! id rules for classes and ids found in a page's DOM, so the generic
+//! selectors are collected here when the rules are compiled.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Selectors per CSS rule in [`CosmeticResult::as_stylesheet`]
+///
+/// Browsers handle a few thousand selectors per rule well.
+pub const SELECTORS_PER_RULE: usize = 1000;
+
+/// Procedural operators, which the adblock crate applies itself
+const PROCEDURAL_OPERATORS: &[&str] = &[
+    ":has-text(",
+    ":matches-css",
+    ":matches-path(",
+    ":min-text-length(",
+    ":upward(",
+    ":xpath(",
+    ":style(",
+    ":remove(",
+    ":-abp-",
+];
+
+/// Element hiding resources for one page
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CosmeticResult {
+    /// Selectors of generic (`##`) rules not excepted for the page, sorted
+    pub generic_selectors: Vec<String>,
+    /// Selectors of rules for the page's site, sorted
+    pub specific_selectors: Vec<String>,
+    /// Scriptlets (`##+js(...)`) to run in the page, as one script; empty
+    /// if none apply
+    pub injected_script: String,
+}
+
+impl CosmeticResult {
+    /// Whether there is nothing to hide or inject
+    pub fn is_empty(&self) -> bool {
+        self.generic_selectors.is_empty()
+            && self.specific_selectors.is_empty()
+            && self.injected_script.is_empty()
+    }
+
+    /// Generic and site-specific selectors together
+    pub fn selectors(&self) -> impl Iterator<Item = &String> {
+        self.generic_selectors
+            .iter()
+            .chain(&self.specific_selectors)
+    }
+
+    /// CSS hiding every selected element
+    ///
+    /// The selectors are grouped into rules of at most
+    /// [`SELECTORS_PER_RULE`], each setting `display: none !important`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the stylesheet, empty if there are no selectors.
+    pub fn as_stylesheet(&self) -> String {
+        let selectors: Vec<&str> = self.selectors().map(String::as_str).collect();
+        selectors
+            .chunks(SELECTORS_PER_RULE)
+            .map(|chunk| format!("{} {{ display: none !important; }}", chunk.join(", ")))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Selectors of the generic element hiding rules among `rules`
+///
+/// A generic `#@#selector` exception drops the selector everywhere.
+/// Scriptlets and procedural rules are left to the adblock crate.
+///
+/// # Arguments
+///
+/// * `rules` - Filter rules in Adblock Plus syntax
+pub(crate) fn generic_selectors(rules: &[String]) -> HashSet<String> {
+    let mut selectors = HashSet::new();
+    let mut exceptions = HashSet::new();
+    for rule in rules {
+        let (set, selector) = if let Some(selector) = rule.strip_prefix("##") {
+            (&mut selectors, selector)
+        } else if let Some(selector) = rule.strip_prefix("#@#") {
+            (&mut exceptions, selector)
+        } else {
+            continue;
+        };
+        let selector = selector.trim();
+        if !selector.is_empty() && !is_procedural(selector) {
+            set.insert(selector.to_string());
+        }
+    }
+    selectors.retain(|selector| !exceptions.contains(selector));
+    selectors
+}
+
+/// Whether a selector is a scriptlet or uses a procedural operator
+fn is_procedural(selector: &str) -> bool {
+    selector.starts_with("+js(")
+        || PROCEDURAL_OPERATORS
+            .iter()
+            .any(|operator| selector.contains(operator))
+}
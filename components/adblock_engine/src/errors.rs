@@ -1,5 +1,6 @@
 //! Error types for adblock_engine component
 
+use shared_types::SubscriptionId;
 use thiserror::Error;
 
 /// Errors that can occur in the adblock engine
@@ -25,6 +26,14 @@ pub enum Error {
     #[error("Failed to parse filter rule: {0}")]
     FilterParseError(String),
 
+    /// No filter list subscription with this identifier
+    #[error("Unknown filter list subscription {0}")]
+    UnknownSubscription(SubscriptionId),
+
+    /// No list fetcher was configured
+    #[error("No filter list fetcher configured")]
+    NoFetcher,
+
     /// IO error
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
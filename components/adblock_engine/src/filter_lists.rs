@@ -0,0 +1,252 @@
+//! Cached filter lists and first-run seeding
+//!
+//! Filter lists live in a directory, one `<name>.txt` file per list. A list
+//! written from the embedded seed data starts with [`SEED_HEADER`]; any other
+//! list counts as fetched. Seeding only happens while the store holds no
+//! lists at all, and installing a fetched list drops the seed lists, so the
+//! seed never outlives the first successful download.
+
+use crate::errors::{Error, Result};
+use shared_types::{FilterListInfo, FilterListSource};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// First line of a list written from the seed data
+pub const SEED_HEADER: &str = "! FrankenBrowser-Source: seed";
+
+/// Name of the list written by [`FilterListStore::seed`]
+pub const SEED_LIST_NAME: &str = "seed";
+
+/// Rules of the curated seed list, validated at build time
+#[cfg(feature = "seed-filters")]
+pub const SEED_FILTERS: Option<&str> =
+    Some(include_str!(concat!(env!("OUT_DIR"), "/seed_filters.txt")));
+
+/// Rules of the curated seed list (built without the `seed-filters` feature)
+#[cfg(not(feature = "seed-filters"))]
+pub const SEED_FILTERS: Option<&str> = None;
+
+/// A filter list held by a [`FilterListStore`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedList {
+    /// List name (file name without extension)
+    pub name: String,
+    /// Provenance of the rules
+    pub source: FilterListSource,
+    /// Filter rules, without comments
+    pub rules: Vec<String>,
+}
+
+/// Result of [`FilterListStore::seed`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeedOutcome {
+    /// The seed list was written
+    Seeded {
+        /// Number of rules written
+        rules: usize,
+    },
+    /// The seed list is already present (not the first run)
+    AlreadySeeded,
+    /// The store already holds fetched lists, which take precedence
+    HasFetchedLists,
+    /// This binary was built without seed data
+    NoSeedData,
+}
+
+/// Directory of cached filter lists
+#[derive(Debug, Clone, Default)]
+pub struct FilterListStore {
+    /// Backing directory; `None` keeps the lists in memory only
+    dir: Option<PathBuf>,
+    /// Lists, sorted by name
+    lists: Vec<CachedList>,
+}
+
+impl FilterListStore {
+    /// Open (creating if needed) a filter list directory
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Directory holding the `.txt` lists
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created or read.
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+
+        let mut lists = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("txt") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let content = fs::read_to_string(&path)?;
+            lists.push(parse_list(name, &content));
+        }
+        lists.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Self {
+            dir: Some(dir.to_path_buf()),
+            lists,
+        })
+    }
+
+    /// Create a store that is not backed by a directory
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Cached lists, sorted by name
+    pub fn lists(&self) -> &[CachedList] {
+        &self.lists
+    }
+
+    /// Name, provenance and size of every list, for `about:diagnostics`
+    pub fn summaries(&self) -> Vec<FilterListInfo> {
+        self.lists
+            .iter()
+            .map(|list| FilterListInfo {
+                name: list.name.clone(),
+                source: list.source,
+                rules: list.rules.len(),
+            })
+            .collect()
+    }
+
+    /// Whether any list came from a download
+    pub fn has_fetched(&self) -> bool {
+        self.lists
+            .iter()
+            .any(|list| list.source == FilterListSource::Fetched)
+    }
+
+    /// Rules of all lists
+    pub fn rules(&self) -> impl Iterator<Item = &str> {
+        self.lists
+            .iter()
+            .flat_map(|list| list.rules.iter().map(String::as_str))
+    }
+
+    /// Write the embedded seed list if this is a fresh store
+    ///
+    /// Does nothing when the seed is already present, when fetched lists
+    /// exist, or when the binary has no seed data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the seed list cannot be written.
+    pub fn seed(&mut self) -> Result<SeedOutcome> {
+        let Some(seed) = SEED_FILTERS else {
+            return Ok(SeedOutcome::NoSeedData);
+        };
+        if self.has_fetched() {
+            return Ok(SeedOutcome::HasFetchedLists);
+        }
+        if !self.lists.is_empty() {
+            return Ok(SeedOutcome::AlreadySeeded);
+        }
+
+        let content = format!("{}\n{}", SEED_HEADER, seed);
+        let list = parse_list(SEED_LIST_NAME, &content);
+        let rules = list.rules.len();
+        self.write(list, &content)?;
+        Ok(SeedOutcome::Seeded { rules })
+    }
+
+    /// Store a downloaded list, superseding the seed lists
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - List name (file name without extension)
+    /// * `content` - List in Adblock Plus syntax
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::FilterLoadError` if the name is not a plain file
+    /// name, or an I/O error if the list cannot be written.
+    pub fn install_fetched(&mut self, name: &str, content: &str) -> Result<()> {
+        if name.is_empty() || name.contains(['/', '\\', '.']) {
+            return Err(Error::FilterLoadError(format!(
+                "Invalid filter list name '{}'",
+                name
+            )));
+        }
+
+        let mut list = parse_list(name, content);
+        list.source = FilterListSource::Fetched;
+        let content = content
+            .strip_prefix(SEED_HEADER)
+            .unwrap_or(content)
+            .trim_start_matches('\n');
+        self.write(list, content)?;
+
+        let seeds: Vec<String> = self
+            .lists
+            .iter()
+            .filter(|list| list.source == FilterListSource::Seed)
+            .map(|list| list.name.clone())
+            .collect();
+        for seed in seeds {
+            self.remove(&seed)?;
+        }
+        Ok(())
+    }
+
+    /// Add or replace a list, writing it through to the directory
+    fn write(&mut self, list: CachedList, content: &str) -> Result<()> {
+        if let Some(dir) = &self.dir {
+            fs::write(list_path(dir, &list.name), content)?;
+        }
+        self.lists.retain(|existing| existing.name != list.name);
+        self.lists.push(list);
+        self.lists.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(())
+    }
+
+    /// Drop a list, deleting its file
+    ///
+    /// Dropping a list that is not in the store does nothing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the list file cannot be deleted.
+    pub fn remove(&mut self, name: &str) -> Result<()> {
+        if let Some(dir) = &self.dir {
+            match fs::remove_file(list_path(dir, name)) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+        }
+        self.lists.retain(|list| list.name != name);
+        Ok(())
+    }
+}
+
+/// Parse a list file: provenance from the header, then the rules
+fn parse_list(name: &str, content: &str) -> CachedList {
+    let source = if content.lines().next().map(str::trim) == Some(SEED_HEADER) {
+        FilterListSource::Seed
+    } else {
+        FilterListSource::Fetched
+    };
+    let rules = content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('!') && !line.starts_with('['))
+        .map(str::to_string)
+        .collect();
+
+    CachedList {
+        name: name.to_string(),
+        source,
+        rules,
+    }
+}
+
+fn list_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.txt", name))
+}
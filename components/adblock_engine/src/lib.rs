@@ -1,7 +1,11 @@
 //! Ad blocking filter engine using EasyList
 //!
 //! This component provides ad blocking functionality using the adblock crate
-//! and EasyList filter rules.
+//! and EasyList filter rules. A small curated seed list is compiled in (the
+//! default `seed-filters` feature) so a fresh profile is protected before
+//! any list has been downloaded; see [`FilterListStore`]. Downloaded lists
+//! are managed as subscriptions that can be enabled, disabled and refreshed
+//! individually, in the background if need be; see [`subscriptions`].
 //!
 //! # Component Overview
 //!
@@ -28,6 +32,7 @@
 //!     enabled: true,
 //!     update_filters_on_startup: false,
 //!     custom_filters: vec!["||ads.example.com^".to_string()],
+//!     allowlisted_domains: vec![],
 //! };
 //!
 //! // Create and initialize engine
@@ -37,6 +42,7 @@
 //! // Check if URL should be blocked
 //! let blocked = engine.should_block(
 //!     "https://ads.example.com/banner.js",
+//!     "https://news.example.org/",
 //!     ResourceType::Script
 //! );
 //!
@@ -45,9 +51,20 @@
 //! }
 //! ```
 
+pub mod cosmetic;
 pub mod errors;
+pub mod filter_lists;
+pub mod stats;
+pub mod subscriptions;
 pub mod types;
 
 // Re-export main types for convenience
+pub use cosmetic::{CosmeticResult, SELECTORS_PER_RULE};
 pub use errors::{Error, Result};
+pub use filter_lists::{CachedList, FilterListStore, SeedOutcome, SEED_FILTERS};
+pub use stats::{AdBlockStats, BlockStats, RuleCount, TOP_RULES};
+pub use subscriptions::{
+    Clock, FilterListSubscription, ListDownload, ListFetcher, ListMetadata, ListValidators,
+    SubscriptionId, SubscriptionSet, SystemClock, UpdateReport, SUBSCRIPTIONS_FILE,
+};
 pub use types::AdBlockEngine;
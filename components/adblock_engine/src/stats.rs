@@ -0,0 +1,142 @@
+//! Blocked request statistics
+//!
+//! [`BlockStats`] counts the requests the engine checks and blocks, the
+//! rules that blocked them and the blocked requests of each tab. Counters
+//! can be bumped from any thread; [`BlockStats::snapshot`] takes the
+//! serializable [`AdBlockStats`] that settings pages and the status bar
+//! show.
+
+use serde::{Deserialize, Serialize};
+use shared_types::TabId;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Number of rules listed in [`AdBlockStats::top_rules`]
+pub const TOP_RULES: usize = 10;
+
+/// A filter rule with the number of requests it blocked
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuleCount {
+    /// Rule in Adblock Plus syntax
+    pub rule: String,
+    /// Requests it blocked
+    pub blocked: u64,
+}
+
+/// Ad blocking statistics since the engine started or was last reset
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdBlockStats {
+    /// Requests checked against the filter rules
+    pub checked: u64,
+    /// Requests blocked
+    pub blocked: u64,
+    /// The [`TOP_RULES`] rules that blocked the most requests, most first
+    pub top_rules: Vec<RuleCount>,
+    /// Requests blocked in each tab
+    pub tabs: BTreeMap<TabId, u64>,
+}
+
+impl AdBlockStats {
+    /// Requests blocked in a tab, e.g. for "12 ads blocked on this page"
+    ///
+    /// # Arguments
+    ///
+    /// * `tab_id` - The tab
+    pub fn blocked_in_tab(&self, tab_id: TabId) -> u64 {
+        self.tabs.get(&tab_id).copied().unwrap_or(0)
+    }
+}
+
+/// Counters behind [`AdBlockStats`]
+///
+/// The totals are atomic; only blocked requests take the lock of the
+/// per-rule and per-tab counts.
+#[derive(Debug, Default)]
+pub struct BlockStats {
+    checked: AtomicU64,
+    blocked: AtomicU64,
+    breakdown: Mutex<Breakdown>,
+}
+
+#[derive(Debug, Default)]
+struct Breakdown {
+    rules: HashMap<String, u64>,
+    tabs: HashMap<TabId, u64>,
+}
+
+impl BlockStats {
+    /// Create counters starting at zero
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a checked request
+    ///
+    /// # Arguments
+    ///
+    /// * `tab_id` - Tab the request was made for, if known
+    /// * `blocked` - Whether the request was blocked
+    /// * `rule` - Rule that blocked it, if the engine reported one
+    pub fn record(&self, tab_id: Option<TabId>, blocked: bool, rule: Option<&str>) {
+        self.checked.fetch_add(1, Ordering::Relaxed);
+        if !blocked {
+            return;
+        }
+        self.blocked.fetch_add(1, Ordering::Relaxed);
+        if rule.is_none() && tab_id.is_none() {
+            return;
+        }
+        let mut breakdown = self.breakdown.lock().unwrap();
+        if let Some(rule) = rule {
+            *breakdown.rules.entry(rule.to_string()).or_insert(0) += 1;
+        }
+        if let Some(tab_id) = tab_id {
+            *breakdown.tabs.entry(tab_id).or_insert(0) += 1;
+        }
+    }
+
+    /// Current statistics
+    pub fn snapshot(&self) -> AdBlockStats {
+        let breakdown = self.breakdown.lock().unwrap();
+        let mut top_rules: Vec<RuleCount> = breakdown
+            .rules
+            .iter()
+            .map(|(rule, &blocked)| RuleCount {
+                rule: rule.clone(),
+                blocked,
+            })
+            .collect();
+        // Ties in rule order, so the list is stable
+        top_rules.sort_by(|a, b| b.blocked.cmp(&a.blocked).then_with(|| a.rule.cmp(&b.rule)));
+        top_rules.truncate(TOP_RULES);
+
+        AdBlockStats {
+            checked: self.checked.load(Ordering::Relaxed),
+            blocked: self.blocked.load(Ordering::Relaxed),
+            top_rules,
+            tabs: breakdown
+                .tabs
+                .iter()
+                .map(|(&tab, &count)| (tab, count))
+                .collect(),
+        }
+    }
+
+    /// Set every counter back to zero
+    pub fn reset(&self) {
+        let mut breakdown = self.breakdown.lock().unwrap();
+        *breakdown = Breakdown::default();
+        self.checked.store(0, Ordering::Relaxed);
+        self.blocked.store(0, Ordering::Relaxed);
+    }
+
+    /// Forget a tab's count, e.g. when it navigates or closes
+    ///
+    /// # Arguments
+    ///
+    /// * `tab_id` - The tab
+    pub fn reset_tab(&self, tab_id: TabId) {
+        self.breakdown.lock().unwrap().tabs.remove(&tab_id);
+    }
+}
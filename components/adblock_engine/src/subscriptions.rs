@@ -0,0 +1,477 @@
+//! Filter list subscriptions
+//!
+//! A subscription is a filter list URL the user follows (EasyList,
+//! EasyPrivacy, a regional list ...). The subscription records live in
+//! [`SUBSCRIPTIONS_FILE`] next to the cached lists; each list's content is
+//! cached in the [`FilterListStore`](crate::FilterListStore) under
+//! [`list_name`]. Lists are refreshed no more often than their
+//! `! Expires:` header asks for, unless an update is forced, and each
+//! download is conditional on the `ETag` and `Last-Modified` of the cached
+//! copy, so an unchanged list is not transferred again.
+
+use crate::errors::{Error, Result};
+use serde::{Deserialize, Serialize};
+pub use shared_types::{FilterListSubscription, SubscriptionId};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// File holding the subscription records, in the filter list directory
+pub const SUBSCRIPTIONS_FILE: &str = "subscriptions.json";
+
+/// Refresh interval for lists without an `! Expires:` header (4 days)
+pub const DEFAULT_EXPIRES_SECS: u64 = 4 * 24 * 60 * 60;
+
+/// Shortest refresh interval honoured from an `! Expires:` header (1 hour)
+pub const MIN_EXPIRES_SECS: u64 = 60 * 60;
+
+/// Metadata read from a filter list's header comments
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ListMetadata {
+    /// Value of `! Title:`
+    pub title: Option<String>,
+    /// Value of `! Expires:`, in seconds
+    pub expires: Option<u64>,
+    /// Number of rules in the list
+    pub rules: usize,
+}
+
+/// Validators of a downloaded list, sent back to make the next download
+/// conditional
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListValidators {
+    /// `ETag` response header
+    pub etag: Option<String>,
+    /// `Last-Modified` response header
+    pub last_modified: Option<String>,
+}
+
+impl ListValidators {
+    /// Validators stored with a subscription
+    pub fn of(subscription: &FilterListSubscription) -> Self {
+        Self {
+            etag: subscription.etag.clone(),
+            last_modified: subscription.last_modified.clone(),
+        }
+    }
+}
+
+/// Result of [`ListFetcher::fetch_if_modified`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListDownload {
+    /// The list was downloaded
+    Modified {
+        /// List in Adblock Plus syntax
+        content: String,
+        /// Validators the server sent with it
+        validators: ListValidators,
+    },
+    /// The server answered 304 Not Modified; the cached copy is current
+    NotModified,
+}
+
+/// Downloads filter lists for [`AdBlockEngine`](crate::AdBlockEngine)
+///
+/// Implemented by the application on top of its network stack, so this
+/// crate does not depend on one. Fetchers are shared with the thread of
+/// [`refresh_in_background`](crate::AdBlockEngine::refresh_in_background).
+pub trait ListFetcher: Send + Sync {
+    /// Download a filter list
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - List URL
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the list cannot be downloaded.
+    fn fetch(&self, url: &str) -> Result<String>;
+
+    /// Download a filter list unless it matches `validators`
+    ///
+    /// The default downloads unconditionally with [`fetch`](Self::fetch)
+    /// and reports no validators.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - List URL
+    /// * `validators` - Validators of the cached copy, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the list cannot be downloaded.
+    fn fetch_if_modified(&self, url: &str, validators: &ListValidators) -> Result<ListDownload> {
+        let _ = validators;
+        Ok(ListDownload::Modified {
+            content: self.fetch(url)?,
+            validators: ListValidators::default(),
+        })
+    }
+}
+
+/// Source of the current time for update scheduling
+pub trait Clock: Send + Sync {
+    /// Current time, in seconds since the Unix epoch
+    fn now(&self) -> u64;
+}
+
+/// [`Clock`] reading the system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+/// Result of [`AdBlockEngine::update_all`](crate::AdBlockEngine::update_all)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpdateReport {
+    /// Lists downloaded and installed
+    pub updated: Vec<SubscriptionId>,
+    /// Lists the server reported unchanged since the cached copy
+    pub unchanged: Vec<SubscriptionId>,
+    /// Lists that are disabled or not yet due
+    pub skipped: Vec<SubscriptionId>,
+    /// Lists whose download or validation failed, with the reason
+    pub failed: Vec<(SubscriptionId, String)>,
+}
+
+/// Name under which a subscription's list is cached in the store
+pub fn list_name(id: SubscriptionId) -> String {
+    format!("subscription{}", id)
+}
+
+/// Read the `! Title:` and `! Expires:` headers of a filter list
+///
+/// Only the comment block at the top of the list is searched.
+///
+/// # Arguments
+///
+/// * `content` - List in Adblock Plus syntax
+pub fn parse_metadata(content: &str) -> ListMetadata {
+    let mut metadata = ListMetadata::default();
+    let mut in_header = true;
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('[') {
+            continue;
+        }
+        let Some(comment) = line.strip_prefix('!') else {
+            in_header = false;
+            metadata.rules += 1;
+            continue;
+        };
+        if !in_header {
+            continue;
+        }
+        let Some((key, value)) = comment.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "title" if !value.is_empty() => metadata.title = Some(value.to_string()),
+            "expires" => metadata.expires = parse_expires(value),
+            _ => {}
+        }
+    }
+
+    metadata
+}
+
+/// Parse an `! Expires:` value such as `4 days (update frequency)`,
+/// `12 hours` or `1d`
+///
+/// A bare number counts as days, as in Adblock Plus.
+///
+/// # Returns
+///
+/// Returns the interval in seconds, or `None` if the value is not understood.
+pub fn parse_expires(value: &str) -> Option<u64> {
+    let value = value.split('(').next().unwrap_or("").trim();
+    let digits = value.len() - value.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let count: u64 = value[..digits].parse().ok()?;
+    let unit = match value[digits..].trim().to_ascii_lowercase().as_str() {
+        "" | "d" | "day" | "days" => 24 * 60 * 60,
+        "h" | "hour" | "hours" => 60 * 60,
+        _ => return None,
+    };
+    Some(count * unit).filter(|&secs| secs > 0)
+}
+
+/// Check that downloaded content is a usable filter list
+///
+/// # Returns
+///
+/// Returns the list's metadata.
+///
+/// # Errors
+///
+/// Returns `Error::FilterLoadError` if the content looks like an HTML page
+/// (a captive portal or error page) or has no rules.
+pub fn validate_list(content: &str) -> Result<ListMetadata> {
+    if content.trim_start().starts_with('<') {
+        return Err(Error::FilterLoadError(
+            "Downloaded content is HTML, not a filter list".to_string(),
+        ));
+    }
+    let metadata = parse_metadata(content);
+    if metadata.rules == 0 {
+        return Err(Error::FilterLoadError(
+            "Filter list has no rules".to_string(),
+        ));
+    }
+    Ok(metadata)
+}
+
+/// When a subscription is next due for a refresh
+///
+/// # Returns
+///
+/// Returns the time in seconds since the Unix epoch, or `None` if the list
+/// has never been downloaded (it is due now).
+pub fn next_update(subscription: &FilterListSubscription) -> Option<u64> {
+    let interval = subscription
+        .expires_hint
+        .unwrap_or(DEFAULT_EXPIRES_SECS)
+        .max(MIN_EXPIRES_SECS);
+    subscription
+        .last_updated
+        .map(|last| last.saturating_add(interval))
+}
+
+/// Whether a subscription should be refreshed at `now`
+pub fn is_due(subscription: &FilterListSubscription, now: u64) -> bool {
+    next_update(subscription).is_none_or(|due| now >= due)
+}
+
+/// On-disk form of a [`SubscriptionSet`]
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SubscriptionFile {
+    /// Identifier for the next subscription
+    next_id: SubscriptionId,
+    /// Subscriptions, in the order they were added
+    subscriptions: Vec<FilterListSubscription>,
+}
+
+/// The profile's filter list subscriptions
+#[derive(Debug, Clone, Default)]
+pub struct SubscriptionSet {
+    /// Backing file; `None` keeps the subscriptions in memory only
+    path: Option<PathBuf>,
+    /// Identifier for the next subscription
+    next_id: SubscriptionId,
+    /// Subscriptions, in the order they were added
+    subscriptions: Vec<FilterListSubscription>,
+}
+
+impl SubscriptionSet {
+    /// Open a subscription file
+    ///
+    /// A new profile (no file yet) starts with `defaults` subscribed but not
+    /// yet downloaded; the file is written on the first change or download.
+    /// An existing file is loaded as is, so removed defaults stay removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Subscription file (see [`SUBSCRIPTIONS_FILE`])
+    /// * `defaults` - List URLs for a new profile
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed, or a default
+    /// URL is not http(s).
+    pub fn open(path: &Path, defaults: &[String]) -> Result<Self> {
+        if path.exists() {
+            let content = fs::read_to_string(path)?;
+            let file: SubscriptionFile = serde_json::from_str(&content).map_err(|e| {
+                Error::FilterLoadError(format!("Invalid {}: {}", path.display(), e))
+            })?;
+            return Ok(Self {
+                path: Some(path.to_path_buf()),
+                next_id: file.next_id,
+                subscriptions: file.subscriptions,
+            });
+        }
+
+        let mut set = Self {
+            path: Some(path.to_path_buf()),
+            ..Self::default()
+        };
+        set.register_defaults(defaults)?;
+        Ok(set)
+    }
+
+    /// Create a set that is not backed by a file
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Subscriptions, in the order they were added
+    pub fn subscriptions(&self) -> &[FilterListSubscription] {
+        &self.subscriptions
+    }
+
+    /// Look up a subscription
+    pub fn get(&self, id: SubscriptionId) -> Option<&FilterListSubscription> {
+        self.subscriptions.iter().find(|sub| sub.id == id)
+    }
+
+    /// Whether a list URL is already subscribed
+    pub fn contains_url(&self, url: &str) -> bool {
+        self.subscriptions.iter().any(|sub| sub.url == url)
+    }
+
+    /// Add a subscription that has not been downloaded yet
+    ///
+    /// Its title is the URL until the list is fetched.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::FilterLoadError` if the URL is not http(s) or is
+    /// already subscribed.
+    pub fn register(&mut self, url: &str) -> Result<SubscriptionId> {
+        let url = url.trim();
+        if !(url.starts_with("https://") || url.starts_with("http://")) {
+            return Err(Error::FilterLoadError(format!(
+                "Filter list URL must be http(s): '{}'",
+                url
+            )));
+        }
+        if self.contains_url(url) {
+            return Err(Error::FilterLoadError(format!(
+                "Already subscribed to '{}'",
+                url
+            )));
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.subscriptions.push(FilterListSubscription {
+            id,
+            title: url.to_string(),
+            url: url.to_string(),
+            enabled: true,
+            last_updated: None,
+            rule_count: 0,
+            expires_hint: None,
+            etag: None,
+            last_modified: None,
+        });
+        Ok(id)
+    }
+
+    /// Add default subscriptions that are not subscribed yet
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::FilterLoadError` if a URL is not http(s).
+    pub fn register_defaults(&mut self, defaults: &[String]) -> Result<()> {
+        for url in defaults {
+            if !self.contains_url(url.trim()) {
+                self.register(url)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Record a successful download
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UnknownSubscription` if there is no such subscription.
+    pub fn record_update(
+        &mut self,
+        id: SubscriptionId,
+        metadata: &ListMetadata,
+        validators: &ListValidators,
+        now: u64,
+    ) -> Result<()> {
+        let sub = self.get_mut(id)?;
+        if let Some(title) = &metadata.title {
+            sub.title = title.clone();
+        }
+        sub.expires_hint = metadata.expires;
+        sub.rule_count = metadata.rules;
+        sub.etag = validators.etag.clone();
+        sub.last_modified = validators.last_modified.clone();
+        sub.last_updated = Some(now);
+        Ok(())
+    }
+
+    /// Record that the server confirmed the cached copy is current
+    ///
+    /// The list is not due again until its `Expires` interval has passed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UnknownSubscription` if there is no such subscription.
+    pub fn record_not_modified(&mut self, id: SubscriptionId, now: u64) -> Result<()> {
+        self.get_mut(id)?.last_updated = Some(now);
+        Ok(())
+    }
+
+    /// Enable or disable a subscription
+    ///
+    /// # Returns
+    ///
+    /// Returns whether the flag changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UnknownSubscription` if there is no such subscription.
+    pub fn set_enabled(&mut self, id: SubscriptionId, enabled: bool) -> Result<bool> {
+        let sub = self.get_mut(id)?;
+        let changed = sub.enabled != enabled;
+        sub.enabled = enabled;
+        Ok(changed)
+    }
+
+    /// Drop a subscription
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UnknownSubscription` if there is no such subscription.
+    pub fn remove(&mut self, id: SubscriptionId) -> Result<FilterListSubscription> {
+        let index = self
+            .subscriptions
+            .iter()
+            .position(|sub| sub.id == id)
+            .ok_or(Error::UnknownSubscription(id))?;
+        Ok(self.subscriptions.remove(index))
+    }
+
+    /// Write the subscriptions through to the file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let file = SubscriptionFile {
+            next_id: self.next_id,
+            subscriptions: self.subscriptions.clone(),
+        };
+        let json = serde_json::to_string_pretty(&file)
+            .map_err(|e| Error::Other(anyhow::anyhow!("Failed to encode subscriptions: {}", e)))?;
+
+        // Write then rename, so a crash never leaves a truncated file
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    fn get_mut(&mut self, id: SubscriptionId) -> Result<&mut FilterListSubscription> {
+        self.subscriptions
+            .iter_mut()
+            .find(|sub| sub.id == id)
+            .ok_or(Error::UnknownSubscription(id))
+    }
+}
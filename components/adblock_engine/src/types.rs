@@ -1,32 +1,104 @@
 //! Type definitions for adblock_engine component
 
+use crate::cosmetic::{generic_selectors, CosmeticResult};
 use crate::errors::{Error, Result};
+use crate::filter_lists::FilterListStore;
+use crate::stats::{AdBlockStats, BlockStats};
+use crate::subscriptions::{
+    is_due, list_name, validate_list, Clock, ListDownload, ListFetcher, ListValidators,
+    SubscriptionSet, SystemClock, UpdateReport,
+};
+use adblock::lists::{parse_filter, ParseOptions};
 use adblock::request::Request;
 use adblock::Engine;
-use config_manager::AdBlockConfig;
-use message_bus::MessageSender;
-use shared_types::ResourceType;
-use std::sync::{Arc, Mutex};
+use config_manager::{AdBlockConfig, Config};
+use message_bus::{MessageHandler, MessageSender};
+use shared_types::{
+    BrowserMessage, FilterListInfo, FilterListSubscription, ResourceType, SubscriptionId, TabId,
+};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use url::Url;
+
+/// A list to download: subscription, URL and the validators to send
+type DueList = (SubscriptionId, String, ListValidators);
+
+/// Outcome of downloading a [`DueList`]
+type Downloaded = (SubscriptionId, Result<ListDownload>);
+
+/// Downloads of a background refresh, with its report so far
+type PendingRefresh = (Receiver<Vec<Downloaded>>, UpdateReport);
 
 /// Ad blocking filter engine
 ///
-/// This engine uses the adblock crate to filter web requests based on EasyList
-/// and custom filter rules.
+/// This engine uses the adblock crate to filter web requests based on EasyList,
+/// the cached and subscribed filter lists, and custom filter rules. Clones
+/// share the compiled engine and the subscriptions; when the enabled rules
+/// change, a new engine is compiled and swapped in whole.
+///
+/// The engine is `Send + Sync`. Checks only take read locks, so clones on
+/// several threads check requests concurrently.
 #[derive(Clone)]
-#[allow(clippy::arc_with_non_send_sync)]
 pub struct AdBlockEngine {
-    /// Configuration for ad blocking
-    config: AdBlockConfig,
-
     /// Message sender for component communication
-    #[allow(dead_code)]
     sender: Arc<Box<dyn MessageSender>>,
 
-    /// The adblock engine (wrapped in Mutex for interior mutability)
-    engine: Arc<Mutex<Option<Engine>>>,
+    /// The adblock engine, read by every check and replaced on a rebuild
+    engine: Arc<RwLock<Option<Engine>>>,
+
+    /// Whether ad blocking is on, starting from `config.enabled`
+    enabled: Arc<AtomicBool>,
 
     /// Whether the engine has been initialized
-    initialized: Arc<Mutex<bool>>,
+    initialized: Arc<AtomicBool>,
+
+    /// Cached (seed or downloaded) filter lists loaded by `initialize`
+    filter_store: Arc<Mutex<Option<FilterListStore>>>,
+
+    /// Filter list subscriptions
+    subscriptions: Arc<Mutex<SubscriptionSet>>,
+
+    /// Custom filter rules, starting from `config.custom_filters`
+    custom_filters: Arc<Mutex<Vec<String>>>,
+
+    /// Sites never blocked, starting from `config.allowlisted_domains`
+    allowlist: Arc<RwLock<BTreeSet<String>>>,
+
+    /// Config file the allowlist is saved to; the lock orders the saves
+    config_file: Arc<Mutex<Option<PathBuf>>>,
+
+    /// Downloads subscribed lists
+    fetcher: Option<Arc<dyn ListFetcher>>,
+
+    /// Time source for update scheduling
+    clock: Arc<dyn Clock>,
+
+    /// Hash of the rules the current engine was compiled from
+    rules_key: Arc<Mutex<Option<u64>>>,
+
+    /// Number of times an engine has been compiled
+    generation: Arc<Mutex<u64>>,
+
+    /// Number of rules the current engine was compiled from
+    rule_count: Arc<Mutex<usize>>,
+
+    /// Selectors of the current engine's generic element hiding rules
+    generic_cosmetic: Arc<RwLock<HashSet<String>>>,
+
+    /// Downloads of a background refresh, with its report so far
+    pending: Arc<Mutex<Option<PendingRefresh>>>,
+
+    /// Whether `pending` holds a refresh, so checks can skip its lock
+    refresh_pending: Arc<AtomicBool>,
+
+    /// Checked and blocked request counters
+    stats: Arc<BlockStats>,
 }
 
 impl AdBlockEngine {
@@ -51,44 +123,658 @@ impl AdBlockEngine {
     ///     enabled: true,
     ///     update_filters_on_startup: false,
     ///     custom_filters: vec![],
+    ///     allowlisted_domains: vec![],
     /// };
     ///
     /// let engine = AdBlockEngine::new(config, sender)?;
     /// ```
-    #[allow(clippy::arc_with_non_send_sync)]
     pub fn new(config: AdBlockConfig, sender: Box<dyn MessageSender>) -> Result<Self> {
         Ok(Self {
-            config,
+            custom_filters: Arc::new(Mutex::new(config.custom_filters.clone())),
+            allowlist: Arc::new(RwLock::new(
+                config
+                    .allowlisted_domains
+                    .iter()
+                    .filter_map(|domain| normalize_domain(domain))
+                    .collect(),
+            )),
             sender: Arc::new(sender),
-            engine: Arc::new(Mutex::new(None)),
-            initialized: Arc::new(Mutex::new(false)),
+            engine: Arc::new(RwLock::new(None)),
+            enabled: Arc::new(AtomicBool::new(config.enabled)),
+            initialized: Arc::new(AtomicBool::new(false)),
+            filter_store: Arc::new(Mutex::new(None)),
+            subscriptions: Arc::new(Mutex::new(SubscriptionSet::in_memory())),
+            config_file: Arc::new(Mutex::new(None)),
+            fetcher: None,
+            clock: Arc::new(SystemClock),
+            rules_key: Arc::new(Mutex::new(None)),
+            generation: Arc::new(Mutex::new(0)),
+            rule_count: Arc::new(Mutex::new(0)),
+            generic_cosmetic: Arc::new(RwLock::new(HashSet::new())),
+            pending: Arc::new(Mutex::new(None)),
+            refresh_pending: Arc::new(AtomicBool::new(false)),
+            stats: Arc::new(BlockStats::new()),
         })
     }
 
-    /// Initialize the ad blocking engine
+    /// Set the cached filter lists to load on `initialize`
+    ///
+    /// # Arguments
+    ///
+    /// * `store` - Cached filter lists (typically seeded on first run)
+    pub fn set_filter_store(&mut self, store: FilterListStore) {
+        *self.filter_store.lock().unwrap() = Some(store);
+    }
+
+    /// Set the filter list subscriptions
+    ///
+    /// The content of subscribed lists is cached in the filter store (see
+    /// [`set_filter_store`](Self::set_filter_store)); this only records which
+    /// lists are subscribed and enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `subscriptions` - Subscriptions (typically from the profile's
+    ///   [`SUBSCRIPTIONS_FILE`](crate::SUBSCRIPTIONS_FILE))
+    pub fn set_subscriptions(&mut self, subscriptions: SubscriptionSet) {
+        *self.subscriptions.lock().unwrap() = subscriptions;
+    }
+
+    /// Set how subscribed lists are downloaded
+    ///
+    /// Without a fetcher, [`add_subscription`](Self::add_subscription) and
+    /// [`update_all`](Self::update_all) fail with `Error::NoFetcher`.
+    pub fn set_list_fetcher(&mut self, fetcher: Box<dyn ListFetcher>) {
+        self.fetcher = Some(Arc::from(fetcher));
+    }
+
+    /// Save allowlist changes to a config file
+    ///
+    /// Without one, [`allow_site`](Self::allow_site) and
+    /// [`disallow_site`](Self::disallow_site) only last until the browser
+    /// closes. Applies to every clone of the engine.
     ///
-    /// This loads filter rules from EasyList and custom filters.
-    /// If the EasyList file is missing, it will use only custom filters.
+    /// # Arguments
+    ///
+    /// * `path` - Config file whose `adblock.allowlisted_domains` is
+    ///   rewritten; other settings and comments are kept
+    pub fn set_config_file(&self, path: impl Into<PathBuf>) {
+        *self.config_file.lock().unwrap() = Some(path.into());
+    }
+
+    /// Set the time source for update scheduling (the system clock by default)
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = Arc::from(clock);
+    }
+
+    /// Name, provenance and size of the cached filter lists
+    pub fn filter_lists(&self) -> Vec<FilterListInfo> {
+        self.filter_store
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(FilterListStore::summaries)
+            .unwrap_or_default()
+    }
+
+    /// Filter list subscriptions, in the order they were added
+    pub fn list_subscriptions(&self) -> Vec<FilterListSubscription> {
+        self.subscriptions.lock().unwrap().subscriptions().to_vec()
+    }
+
+    /// Custom filter rules, in the order they were added
+    pub fn list_custom_filters(&self) -> Vec<String> {
+        self.custom_filters.lock().unwrap().clone()
+    }
+
+    /// Add a custom filter rule
+    ///
+    /// The rule takes effect for the next [`should_block`](Self::should_block)
+    /// call. Adding a rule that is already present changes nothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `rule` - Filter rule in Adblock Plus syntax
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::FilterParseError` if the rule is not a valid filter.
+    pub fn add_filter(&self, rule: &str) -> Result<()> {
+        let rule = check_filter(rule)?;
+        {
+            let mut custom_filters = self.custom_filters.lock().unwrap();
+            if custom_filters.contains(&rule) {
+                return Ok(());
+            }
+            custom_filters.push(rule);
+        }
+        self.rebuild()
+    }
+
+    /// Remove a custom filter rule
+    ///
+    /// # Arguments
+    ///
+    /// * `rule` - Rule as it was added (surrounding whitespace is ignored)
     ///
     /// # Returns
     ///
-    /// Returns `Ok(())` on success, or an error if initialization fails.
+    /// Returns `false` if there was no such rule.
+    pub fn remove_filter(&self, rule: &str) -> Result<bool> {
+        {
+            let mut custom_filters = self.custom_filters.lock().unwrap();
+            let Some(index) = custom_filters.iter().position(|r| r == rule.trim()) else {
+                return Ok(false);
+            };
+            custom_filters.remove(index);
+        }
+        self.rebuild()?;
+        Ok(true)
+    }
+
+    /// Replace every custom filter rule
+    ///
+    /// Invalid rules are left out and reported; the valid ones replace the
+    /// current custom filters, duplicates dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `rules` - Filter rules in Adblock Plus syntax
+    ///
+    /// # Returns
+    ///
+    /// Returns each rejected rule with the reason.
+    pub fn reload_filters(&self, rules: Vec<String>) -> Result<Vec<(String, String)>> {
+        let mut accepted: Vec<String> = Vec::new();
+        let mut rejected = Vec::new();
+        for rule in rules {
+            match check_filter(&rule) {
+                Ok(rule) if accepted.contains(&rule) => {}
+                Ok(rule) => accepted.push(rule),
+                Err(e) => rejected.push((rule, e.to_string())),
+            }
+        }
+        *self.custom_filters.lock().unwrap() = accepted;
+        self.rebuild()?;
+        Ok(rejected)
+    }
+
+    /// Allowlisted sites, sorted
+    pub fn allowlisted_domains(&self) -> Vec<String> {
+        self.allowlist.read().unwrap().iter().cloned().collect()
+    }
+
+    /// Turn ad blocking off for a site
+    ///
+    /// Nothing requested by the site's pages, or its subdomains' pages, is
+    /// blocked, and no elements are hidden on them. The allowlist is saved
+    /// to the [config file](Self::set_config_file), if there is one.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Site domain, e.g. `example.com`
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if the domain was allowlisted already or is blank.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the allowlist cannot be saved; the site stays
+    /// allowlisted for this session.
+    pub fn allow_site(&self, domain: &str) -> Result<bool> {
+        let added = match normalize_domain(domain) {
+            Some(domain) => self.allowlist.write().unwrap().insert(domain),
+            None => false,
+        };
+        if added {
+            self.save_allowlist()?;
+        }
+        Ok(added)
+    }
+
+    /// Turn ad blocking back on for an allowlisted site
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Domain as it was allowlisted
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if the domain was not allowlisted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the allowlist cannot be saved.
+    pub fn disallow_site(&self, domain: &str) -> Result<bool> {
+        let removed = match normalize_domain(domain) {
+            Some(domain) => self.allowlist.write().unwrap().remove(&domain),
+            None => false,
+        };
+        if removed {
+            self.save_allowlist()?;
+        }
+        Ok(removed)
+    }
+
+    /// Write the allowlist to the config file, if there is one
+    fn save_allowlist(&self) -> Result<()> {
+        let config_file = self.config_file.lock().unwrap();
+        let Some(path) = config_file.as_ref() else {
+            return Ok(());
+        };
+        // Read under the file lock, so the last save has the latest list
+        let domains = serde_json::to_string(&self.allowlisted_domains())
+            .map_err(|e| Error::Other(e.into()))?;
+        Config::set_in_file(path, "adblock.allowlisted_domains", &domains).map_err(|e| {
+            Error::Other(anyhow::anyhow!(
+                "Failed to save the allowlist to {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Whether a domain, or a parent domain, is allowlisted
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Domain to check, e.g. `www.example.com`
+    pub fn is_allowed(&self, domain: &str) -> bool {
+        let Some(domain) = normalize_domain(domain) else {
+            return false;
+        };
+        let allowlist = self.allowlist.read().unwrap();
+        let mut candidate = domain.as_str();
+        loop {
+            if allowlist.contains(candidate) {
+                return true;
+            }
+            match candidate.split_once('.') {
+                Some((_, parent)) => candidate = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// Whether the page at `url` is on an allowlisted site
+    fn is_allowed_page(&self, url: &str) -> bool {
+        Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| self.is_allowed(host)))
+            .unwrap_or(false)
+    }
+
+    /// Whether ad blocking is on
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    /// Turn ad blocking on or off for this engine and its clones
+    ///
+    /// The compiled filters are kept, so turning it back on blocks right
+    /// away.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Release);
+    }
+
+    /// Requests checked and blocked since the engine started or
+    /// [`reset_stats`](Self::reset_stats)
+    pub fn stats(&self) -> AdBlockStats {
+        self.stats.snapshot()
+    }
+
+    /// Set the request statistics back to zero
+    pub fn reset_stats(&self) {
+        self.stats.reset();
+    }
+
+    /// Set a tab's blocked request count back to zero
+    ///
+    /// Call when the tab navigates, for a count per page, or closes.
+    ///
+    /// # Arguments
+    ///
+    /// * `tab_id` - The tab
+    pub fn reset_tab_stats(&self, tab_id: TabId) {
+        self.stats.reset_tab(tab_id);
+    }
+
+    /// Number of times a filter engine has been compiled
+    ///
+    /// Unchanged when a subscription change leaves the enabled rules as
+    /// they were.
+    pub fn engine_generation(&self) -> u64 {
+        *self.generation.lock().unwrap()
+    }
+
+    /// Number of rules compiled into the engine
+    pub fn rule_count(&self) -> usize {
+        *self.rule_count.lock().unwrap()
+    }
+
+    /// Subscribe to a filter list
+    ///
+    /// The list is downloaded, validated and enabled right away.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - List URL
+    ///
+    /// # Returns
+    ///
+    /// Returns the new subscription's identifier.
     ///
     /// # Errors
     ///
     /// Returns an error if:
-    /// - The engine is already initialized
-    /// - Filter rules cannot be parsed
-    pub fn initialize(&mut self) -> Result<()> {
-        // Check if already initialized
+    /// - No list fetcher is configured
+    /// - The URL is not http(s) or is already subscribed
+    /// - The download fails or is not a filter list
+    /// - The list or the subscriptions cannot be saved
+    pub fn add_subscription(&self, url: &str) -> Result<SubscriptionId> {
+        let fetcher = self.fetcher.as_ref().ok_or(Error::NoFetcher)?;
+        if self.subscriptions.lock().unwrap().contains_url(url.trim()) {
+            return Err(Error::FilterLoadError(format!(
+                "Already subscribed to '{}'",
+                url.trim()
+            )));
+        }
+
+        let (content, validators) =
+            match fetcher.fetch_if_modified(url.trim(), &ListValidators::default())? {
+                ListDownload::Modified {
+                    content,
+                    validators,
+                } => (content, validators),
+                ListDownload::NotModified => {
+                    return Err(Error::FilterLoadError(format!(
+                        "'{}' answered 304 Not Modified to an unconditional request",
+                        url.trim()
+                    )))
+                }
+            };
+        let metadata = validate_list(&content)?;
+
+        let id = {
+            let mut subscriptions = self.subscriptions.lock().unwrap();
+            let id = subscriptions.register(url)?;
+            if let Err(e) = self.install_list(id, &content) {
+                subscriptions.remove(id)?;
+                return Err(e);
+            }
+            subscriptions.record_update(id, &metadata, &validators, self.clock.now())?;
+            subscriptions.save()?;
+            id
+        };
+
+        self.rebuild()?;
+        Ok(id)
+    }
+
+    /// Unsubscribe from a filter list, deleting its cached copy
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no such subscription or the change
+    /// cannot be saved.
+    pub fn remove_subscription(&self, id: SubscriptionId) -> Result<()> {
         {
-            let initialized = self.initialized.lock().unwrap();
-            if *initialized {
-                return Err(Error::AlreadyInitialized);
+            let mut subscriptions = self.subscriptions.lock().unwrap();
+            subscriptions.remove(id)?;
+            subscriptions.save()?;
+        }
+        if let Some(store) = self.filter_store.lock().unwrap().as_mut() {
+            store.remove(&list_name(id))?;
+        }
+        self.rebuild()
+    }
+
+    /// Enable or disable a subscribed list
+    ///
+    /// A disabled list stays cached but its rules are not compiled in.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no such subscription or the change
+    /// cannot be saved.
+    pub fn set_subscription_enabled(&self, id: SubscriptionId, enabled: bool) -> Result<()> {
+        {
+            let mut subscriptions = self.subscriptions.lock().unwrap();
+            if !subscriptions.set_enabled(id, enabled)? {
+                return Ok(());
+            }
+            subscriptions.save()?;
+        }
+        self.rebuild()
+    }
+
+    /// Refresh the enabled subscriptions
+    ///
+    /// A list is downloaded when it has never been fetched or its
+    /// `! Expires:` interval has elapsed; `force` downloads every enabled
+    /// list. Downloads are conditional on the cached copy's validators, and
+    /// a list that fails keeps its cached copy. The engine is recompiled
+    /// once, after all downloads, and `FilterListsUpdated` is sent if any
+    /// list changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `force` - Ignore the lists' `Expires` intervals
+    ///
+    /// # Returns
+    ///
+    /// Returns which lists were updated, unchanged, skipped or failed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no list fetcher is configured or the
+    /// subscriptions cannot be saved.
+    pub fn update_all(&self, force: bool) -> Result<UpdateReport> {
+        let fetcher = self.fetcher.as_ref().ok_or(Error::NoFetcher)?;
+        let mut report = UpdateReport::default();
+        let due = self.due_lists(force, &mut report);
+        let downloads = download_lists(fetcher.as_ref(), due);
+        self.apply_downloads(downloads, report)
+    }
+
+    /// Refresh the enabled subscriptions like [`update_all`](Self::update_all),
+    /// downloading on a background thread
+    ///
+    /// The engine keeps using the cached lists meanwhile. Once the
+    /// downloads are in, the next [`should_block`](Self::should_block) or
+    /// [`poll_updates`](Self::poll_updates) call installs them. Starting a
+    /// refresh while one is pending drops the pending one's results.
+    ///
+    /// # Arguments
+    ///
+    /// * `force` - Ignore the lists' `Expires` intervals
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no list fetcher is configured or the thread
+    /// cannot be started.
+    pub fn refresh_in_background(&self, force: bool) -> Result<()> {
+        let fetcher = self.fetcher.clone().ok_or(Error::NoFetcher)?;
+        let mut report = UpdateReport::default();
+        let due = self.due_lists(force, &mut report);
+        let (sender, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("filter-list-refresh".to_string())
+            .spawn(move || {
+                let _ = sender.send(download_lists(fetcher.as_ref(), due));
+            })?;
+        *self.pending.lock().unwrap() = Some((receiver, report));
+        self.refresh_pending.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Install the lists of a finished background refresh
+    ///
+    /// # Returns
+    ///
+    /// Returns the refresh's report, or `None` if no refresh has finished
+    /// since the last call.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the subscriptions cannot be saved.
+    pub fn poll_updates(&self) -> Result<Option<UpdateReport>> {
+        let (downloads, report) = {
+            let mut pending = self.pending.lock().unwrap();
+            let Some((receiver, _)) = pending.as_ref() else {
+                return Ok(None);
+            };
+            match receiver.try_recv() {
+                Ok(downloads) => {
+                    let (_, report) = pending.take().unwrap();
+                    self.refresh_pending.store(false, Ordering::Release);
+                    (downloads, report)
+                }
+                Err(TryRecvError::Empty) => return Ok(None),
+                // The download thread died; the cached lists stay
+                Err(TryRecvError::Disconnected) => {
+                    *pending = None;
+                    self.refresh_pending.store(false, Ordering::Release);
+                    return Ok(None);
+                }
+            }
+        };
+        self.apply_downloads(downloads, report).map(Some)
+    }
+
+    /// Enabled subscriptions due for a download; the rest are recorded as
+    /// skipped
+    fn due_lists(&self, force: bool, report: &mut UpdateReport) -> Vec<DueList> {
+        let now = self.clock.now();
+        let subscriptions = self.subscriptions.lock().unwrap();
+        subscriptions
+            .subscriptions()
+            .iter()
+            .filter_map(|sub| {
+                if sub.enabled && (force || is_due(sub, now)) {
+                    Some((sub.id, sub.url.clone(), ListValidators::of(sub)))
+                } else {
+                    report.skipped.push(sub.id);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Install downloaded lists, then save and recompile once
+    fn apply_downloads(
+        &self,
+        downloads: Vec<Downloaded>,
+        mut report: UpdateReport,
+    ) -> Result<UpdateReport> {
+        let now = self.clock.now();
+        for (id, download) in downloads {
+            let installed = download.and_then(|download| match download {
+                ListDownload::Modified {
+                    content,
+                    validators,
+                } => {
+                    let metadata = validate_list(&content)?;
+                    self.install_list(id, &content)?;
+                    let mut subscriptions = self.subscriptions.lock().unwrap();
+                    subscriptions.record_update(id, &metadata, &validators, now)?;
+                    Ok(true)
+                }
+                ListDownload::NotModified => {
+                    let mut subscriptions = self.subscriptions.lock().unwrap();
+                    subscriptions.record_not_modified(id, now)?;
+                    Ok(false)
+                }
+            });
+            match installed {
+                Ok(true) => report.updated.push(id),
+                Ok(false) => report.unchanged.push(id),
+                Err(e) => report.failed.push((id, e.to_string())),
+            }
+        }
+
+        if !report.updated.is_empty() || !report.unchanged.is_empty() {
+            self.subscriptions.lock().unwrap().save()?;
+        }
+        if !report.updated.is_empty() {
+            self.rebuild()?;
+            let _ = self.sender.send(BrowserMessage::FilterListsUpdated {
+                rule_count: self.rule_count(),
+            });
+        }
+        Ok(report)
+    }
+
+    /// Apply a subscription command from the message bus
+    ///
+    /// Handles `SetFilterSubscriptionEnabled` and `UpdateFilterSubscriptions`
+    /// (sent by the `about:adblock` page), `ToggleAdblockForSite`, and
+    /// `ShouldBlock`, which is answered by sending a `BlockDecision`. As a
+    /// bus handler the engine replies to `ShouldBlock` requests directly
+    /// instead.
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` for messages that are not subscription commands.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command fails.
+    pub fn handle_message(&self, message: &BrowserMessage) -> Result<bool> {
+        match message {
+            BrowserMessage::SetFilterSubscriptionEnabled { id, enabled } => {
+                self.set_subscription_enabled(*id, *enabled)?;
             }
+            BrowserMessage::UpdateFilterSubscriptions { force } => {
+                self.update_all(*force)?;
+            }
+            BrowserMessage::ShouldBlock {
+                url,
+                resource_type,
+                tab_id,
+            } => {
+                let _ = self
+                    .sender
+                    .send(self.block_decision(url, resource_type, *tab_id));
+            }
+            BrowserMessage::ToggleAdblockForSite { domain, allowed } => {
+                if *allowed {
+                    self.allow_site(domain)?;
+                } else {
+                    self.disallow_site(domain)?;
+                }
+            }
+            _ => return Ok(false),
         }
+        Ok(true)
+    }
 
-        // Create new adblock engine
+    /// The `BlockDecision` for a `ShouldBlock` message
+    fn block_decision(
+        &self,
+        url: &Url,
+        resource_type: &ResourceType,
+        tab_id: Option<TabId>,
+    ) -> BrowserMessage {
+        let block = self.should_block_in_tab(url.as_str(), "", resource_type.clone(), tab_id);
+        BrowserMessage::BlockDecision {
+            block,
+            reason: block.then(|| "Blocked by ad blocker".to_string()),
+        }
+    }
+
+    /// Cache a subscription's list content in the filter store
+    fn install_list(&self, id: SubscriptionId, content: &str) -> Result<()> {
+        self.filter_store
+            .lock()
+            .unwrap()
+            .get_or_insert_with(FilterListStore::in_memory)
+            .install_fetched(&list_name(id), content)
+    }
+
+    /// Rules from EasyList, the enabled cached lists and custom filters
+    fn collect_rules(&self) -> Vec<String> {
         let mut filter_rules = Vec::new();
 
         // Try to load EasyList from file
@@ -103,72 +789,195 @@ impl AdBlockEngine {
                 }
             }
         }
-        // Note: If EasyList doesn't exist, we continue with just custom filters
+        // Note: If EasyList doesn't exist, we continue with the other lists
+
+        // Add cached (seed or downloaded) lists, except disabled subscriptions
+        let disabled: Vec<String> = self
+            .subscriptions
+            .lock()
+            .unwrap()
+            .subscriptions()
+            .iter()
+            .filter(|sub| !sub.enabled)
+            .map(|sub| list_name(sub.id))
+            .collect();
+        if let Some(store) = self.filter_store.lock().unwrap().as_ref() {
+            for list in store.lists() {
+                if !disabled.contains(&list.name) {
+                    filter_rules.extend(list.rules.iter().cloned());
+                }
+            }
+        }
 
         // Add custom filters
-        for filter in &self.config.custom_filters {
-            filter_rules.push(filter.clone());
+        filter_rules.extend(self.custom_filters.lock().unwrap().iter().cloned());
+
+        filter_rules
+    }
+
+    /// Compile the current rules and swap the engine in
+    ///
+    /// The adblock crate's engine cannot change once built, so any rule
+    /// change compiles a new one. Skipped when the rules hash the same as
+    /// the current engine's.
+    fn compile(&self) {
+        let filter_rules = self.collect_rules();
+        let mut hasher = DefaultHasher::new();
+        filter_rules.hash(&mut hasher);
+        let key = hasher.finish();
+        if *self.rules_key.lock().unwrap() == Some(key) {
+            return;
         }
 
-        // Create adblock engine with filter rules
-        // Use default ParseOptions
-        let engine = Engine::from_rules(filter_rules, Default::default());
+        // Compile outside the lock; queries keep using the old engine
+        let rule_count = filter_rules.len();
+        let generic_cosmetic = generic_selectors(&filter_rules);
+        // Debug mode keeps the rule text, for the per-rule statistics
+        let engine = Engine::from_rules_debug(filter_rules, Default::default());
 
-        // Store the engine
-        {
-            let mut engine_guard = self.engine.lock().unwrap();
-            *engine_guard = Some(engine);
+        let mut engine_guard = self.engine.write().unwrap();
+        *engine_guard = Some(engine);
+        *self.rules_key.lock().unwrap() = Some(key);
+        *self.generation.lock().unwrap() += 1;
+        *self.rule_count.lock().unwrap() = rule_count;
+        *self.generic_cosmetic.write().unwrap() = generic_cosmetic;
+    }
+
+    /// Recompile after a change, if the engine has been initialized
+    fn rebuild(&self) -> Result<()> {
+        if self.initialized.load(Ordering::Acquire) {
+            self.compile();
         }
+        Ok(())
+    }
 
-        // Mark as initialized
-        {
-            let mut initialized = self.initialized.lock().unwrap();
-            *initialized = true;
+    /// Initialize the ad blocking engine
+    ///
+    /// This loads filter rules from EasyList, the cached filter lists (see
+    /// [`set_filter_store`](Self::set_filter_store)) except disabled
+    /// subscriptions, and custom filters. If the EasyList file is missing,
+    /// the other rules are used alone.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(())` on success, or an error if initialization fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The engine is already initialized
+    /// - Filter rules cannot be parsed
+    pub fn initialize(&mut self) -> Result<()> {
+        // Check if already initialized
+        if self.initialized.load(Ordering::Acquire) {
+            return Err(Error::AlreadyInitialized);
         }
 
+        // Create adblock engine with filter rules
+        self.compile();
+
+        // Mark as initialized
+        self.initialized.store(true, Ordering::Release);
+
         Ok(())
     }
 
     /// Check if a URL should be blocked
     ///
+    /// Lists downloaded by a finished
+    /// [`refresh_in_background`](Self::refresh_in_background) are installed
+    /// first.
+    ///
     /// # Arguments
     ///
     /// * `url` - The URL to check
+    /// * `source_url` - URL of the top-level page making the request; empty
+    ///   for a navigation, which counts as made by the page it loads
     /// * `resource_type` - The type of resource being loaded
     ///
     /// # Returns
     ///
     /// Returns `true` if the URL should be blocked, `false` otherwise.
+    /// Requests of allowlisted sites are never blocked.
     ///
     /// # Example
     ///
     /// ```ignore
-    /// use shared_types::ResourceType;
+    /// use shared_types::{FilterListInfo, ResourceType};
     ///
     /// let blocked = engine.should_block(
     ///     "https://ads.example.com/banner.js",
+    ///     "https://news.example.org/",
     ///     ResourceType::Script
     /// );
     /// ```
-    pub fn should_block(&self, url: &str, resource_type: ResourceType) -> bool {
+    pub fn should_block(&self, url: &str, source_url: &str, resource_type: ResourceType) -> bool {
+        self.should_block_in_tab(url, source_url, resource_type, None)
+    }
+
+    /// Check if a URL should be blocked, counting the decision for a tab
+    ///
+    /// Like [`should_block`](Self::should_block); each check is also
+    /// counted in [`stats`](Self::stats), blocked requests under `tab_id`.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to check
+    /// * `source_url` - URL of the top-level page making the request
+    /// * `resource_type` - The type of resource being loaded
+    /// * `tab_id` - Tab the request was made for, if known
+    pub fn should_block_in_tab(
+        &self,
+        url: &str,
+        source_url: &str,
+        resource_type: ResourceType,
+        tab_id: Option<TabId>,
+    ) -> bool {
         // If ad blocking is disabled, never block
-        if !self.config.enabled {
+        if !self.is_enabled() {
             return false;
         }
 
         // If not initialized, don't block (safe default)
-        {
-            let initialized = self.initialized.lock().unwrap();
-            if !*initialized {
-                return false;
-            }
+        if !self.initialized.load(Ordering::Acquire) {
+            return false;
+        }
+
+        let page = if source_url.is_empty() {
+            url
+        } else {
+            source_url
+        };
+        let (blocked, rule) = if self.is_allowed_page(page) {
+            (false, None)
+        } else {
+            self.match_request(url, source_url, resource_type)
+        };
+        self.stats.record(tab_id, blocked, rule.as_deref());
+        blocked
+    }
+
+    /// Match a request against the filter rules
+    ///
+    /// # Returns
+    ///
+    /// Returns whether the request is blocked, and by which rule.
+    fn match_request(
+        &self,
+        url: &str,
+        source_url: &str,
+        resource_type: ResourceType,
+    ) -> (bool, Option<String>) {
+        // There is no way to report a refresh that fails to save from here
+        if self.refresh_pending.load(Ordering::Acquire) {
+            let _ = self.poll_updates();
         }
 
         // Get the engine
-        let engine_guard = self.engine.lock().unwrap();
+        let engine_guard = self.engine.read().unwrap();
         let engine = match engine_guard.as_ref() {
             Some(e) => e,
-            None => return false,
+            None => return (false, None),
         };
 
         // Convert ResourceType to adblock resource type string
@@ -184,64 +993,108 @@ impl AdBlockEngine {
             ResourceType::Other => "other",
         };
 
-        // Create a Request object
-        // Use empty string as source URL (first-party request)
-        let request = match Request::new(url, "", adblock_resource_type) {
+        // Create a Request object; the source URL lets `$third-party` and
+        // `$domain=` options apply
+        let request = match Request::new(url, source_url, adblock_resource_type) {
             Ok(req) => req,
             Err(_) => {
                 // Invalid URL, don't block
-                return false;
+                return (false, None);
             }
         };
 
         // Check if URL should be blocked
         let check_result = engine.check_network_request(&request);
 
-        check_result.matched
+        (check_result.matched, check_result.filter)
     }
 
-    /// Get cosmetic filtering CSS selectors for a given URL
+    /// Element hiding selectors and scriptlets for a page
+    ///
+    /// Generic rules excepted for the page's site (`site#@#selector`) are
+    /// left out, as are all generic rules on sites with a `$generichide`
+    /// exception.
     ///
     /// # Arguments
     ///
-    /// * `url` - The URL to get selectors for
+    /// * `url` - URL of the page
     ///
     /// # Returns
     ///
-    /// Returns a vector of CSS selectors to hide elements.
-    pub fn get_cosmetic_filters(&self, url: &str) -> Vec<String> {
+    /// Returns an empty result if ad blocking is off, the engine is not
+    /// initialized or the site is allowlisted.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let css = engine
+    ///     .cosmetic_resources_for_url("https://news.example/")
+    ///     .as_stylesheet();
+    /// let config = WebViewConfig::new().with_element_hiding_css(&css);
+    /// ```
+    pub fn cosmetic_resources_for_url(&self, url: &str) -> CosmeticResult {
         // If ad blocking is disabled, return empty
-        if !self.config.enabled {
-            return vec![];
+        if !self.is_enabled() || self.is_allowed_page(url) {
+            return CosmeticResult::default();
         }
 
         // If not initialized, return empty
-        {
-            let initialized = self.initialized.lock().unwrap();
-            if !*initialized {
-                return vec![];
-            }
+        if !self.initialized.load(Ordering::Acquire) {
+            return CosmeticResult::default();
         }
 
         // Get the engine
-        let engine_guard = self.engine.lock().unwrap();
+        let engine_guard = self.engine.read().unwrap();
         let engine = match engine_guard.as_ref() {
             Some(e) => e,
-            None => return vec![],
+            None => return CosmeticResult::default(),
         };
 
-        // Get cosmetic filters for URL
-        let cosmetic = engine.url_cosmetic_resources(url);
+        // Site-specific selectors, with exceptions applied; the crate also
+        // includes generic selectors it has no class or id index for
+        let resources = engine.url_cosmetic_resources(url);
+        let generic = self.generic_cosmetic.read().unwrap();
+
+        let mut generic_selectors: Vec<String> = if resources.generichide {
+            vec![]
+        } else {
+            generic
+                .iter()
+                .filter(|selector| !resources.exceptions.contains(*selector))
+                .cloned()
+                .collect()
+        };
+        generic_selectors.sort();
 
-        // Combine hide selectors
-        let mut selectors = vec![];
+        let mut specific_selectors: Vec<String> = resources
+            .hide_selectors
+            .into_iter()
+            .filter(|selector| !generic.contains(selector))
+            .collect();
+        specific_selectors.sort();
 
-        // Add general hide selectors
-        for selector in cosmetic.hide_selectors {
-            selectors.push(selector);
+        CosmeticResult {
+            generic_selectors,
+            specific_selectors,
+            injected_script: resources.injected_script,
         }
+    }
 
-        selectors
+    /// Get cosmetic filtering CSS selectors for a given URL
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to get selectors for
+    ///
+    /// # Returns
+    ///
+    /// Returns the generic and site-specific selectors of
+    /// [`cosmetic_resources_for_url`](Self::cosmetic_resources_for_url).
+    pub fn get_cosmetic_filters(&self, url: &str) -> Vec<String> {
+        self.cosmetic_resources_for_url(url)
+            .selectors()
+            .cloned()
+            .collect()
     }
 
     /// Generate CSS stylesheet for element hiding
@@ -267,28 +1120,8 @@ impl AdBlockEngine {
     /// }
     /// ```
     pub fn get_element_hider_css(&self, url: &str) -> Option<String> {
-        let selectors = self.get_cosmetic_filters(url);
-
-        if selectors.is_empty() {
-            return None;
-        }
-
-        // Join selectors with comma and apply display: none
-        // Limit selectors per rule for browser compatibility
-        // Most browsers handle ~4096 selectors per rule well
-        const SELECTORS_PER_RULE: usize = 1000;
-
-        let mut css_rules = Vec::new();
-
-        for chunk in selectors.chunks(SELECTORS_PER_RULE) {
-            let rule = format!(
-                "{} {{ display: none !important; }}",
-                chunk.join(", ")
-            );
-            css_rules.push(rule);
-        }
-
-        Some(css_rules.join("\n"))
+        let css = self.cosmetic_resources_for_url(url).as_stylesheet();
+        (!css.is_empty()).then_some(css)
     }
 
     /// Generate a style tag with element hiding CSS
@@ -309,3 +1142,62 @@ impl AdBlockEngine {
         })
     }
 }
+
+impl MessageHandler for AdBlockEngine {
+    fn handle(&self, message: BrowserMessage) -> message_bus::Result<()> {
+        self.handle_message(&message)
+            .map(|_| ())
+            .map_err(|e| message_bus::Error::HandlerError(e.to_string()))
+    }
+
+    fn handle_request(
+        &self,
+        message: BrowserMessage,
+    ) -> message_bus::Result<Option<BrowserMessage>> {
+        match &message {
+            BrowserMessage::ShouldBlock {
+                url,
+                resource_type,
+                tab_id,
+            } => Ok(Some(self.block_decision(url, resource_type, *tab_id))),
+            _ => self.handle(message).map(|()| None),
+        }
+    }
+}
+
+/// Trim a custom filter rule and check that the adblock crate accepts it
+///
+/// # Errors
+///
+/// Returns `Error::FilterParseError` for blank lines, comments and rules
+/// that do not parse.
+fn check_filter(rule: &str) -> Result<String> {
+    let rule = rule.trim();
+    if rule.is_empty() || rule.starts_with('!') || rule.starts_with('[') {
+        return Err(Error::FilterParseError(format!(
+            "'{}' is not a filter rule",
+            rule
+        )));
+    }
+    parse_filter(rule, false, ParseOptions::default())
+        .map_err(|e| Error::FilterParseError(format!("'{}': {:?}", rule, e)))?;
+    Ok(rule.to_string())
+}
+
+/// Download each due list, asking the fetcher to skip unchanged ones
+fn download_lists(fetcher: &dyn ListFetcher, due: Vec<DueList>) -> Vec<Downloaded> {
+    due.into_iter()
+        .map(|(id, url, validators)| {
+            let download = fetcher.fetch_if_modified(&url, &validators);
+            (id, download)
+        })
+        .collect()
+}
+
+/// Lowercase a domain and drop surrounding whitespace and dots
+///
+/// Returns `None` for a blank domain.
+fn normalize_domain(domain: &str) -> Option<String> {
+    let domain = domain.trim().trim_matches('.').to_ascii_lowercase();
+    (!domain.is_empty()).then_some(domain)
+}
@@ -43,6 +43,7 @@ fn test_adblock_engine_new_with_enabled_config() {
         enabled: true,
         update_filters_on_startup: false,
         custom_filters: vec![],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -56,6 +57,7 @@ fn test_adblock_engine_new_with_disabled_config() {
         enabled: false,
         update_filters_on_startup: false,
         custom_filters: vec![],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -69,6 +71,7 @@ fn test_adblock_engine_new_with_custom_filters() {
         enabled: true,
         update_filters_on_startup: false,
         custom_filters: vec!["||ads.example.com^".to_string()],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -86,6 +89,7 @@ fn test_adblock_engine_initialize_with_missing_easylist() {
         enabled: true,
         update_filters_on_startup: false,
         custom_filters: vec![],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -102,6 +106,7 @@ fn test_adblock_engine_initialize_multiple_times_fails() {
         enabled: true,
         update_filters_on_startup: false,
         custom_filters: vec![],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -125,6 +130,7 @@ fn test_should_block_with_disabled_engine() {
         enabled: false,
         update_filters_on_startup: false,
         custom_filters: vec![],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -132,16 +138,44 @@ fn test_should_block_with_disabled_engine() {
     engine.initialize().unwrap();
 
     // When disabled, should not block anything
-    let blocked = engine.should_block("https://ads.example.com/banner.js", ResourceType::Script);
+    let blocked = engine.should_block(
+        "https://ads.example.com/banner.js",
+        "",
+        ResourceType::Script,
+    );
     assert!(!blocked);
 }
 
+#[test]
+fn test_set_enabled_toggles_blocking_for_clones() {
+    let config = AdBlockConfig {
+        enabled: false,
+        update_filters_on_startup: false,
+        custom_filters: vec!["||ads.example.com^".to_string()],
+        allowlisted_domains: vec![],
+    };
+    let sender = Box::new(MockMessageSender::new());
+
+    let mut engine = AdBlockEngine::new(config, sender).unwrap();
+    engine.initialize().unwrap();
+    let clone = engine.clone();
+    let url = "https://ads.example.com/banner.js";
+    assert!(!clone.should_block(url, "", ResourceType::Script));
+
+    engine.set_enabled(true);
+    assert!(clone.is_enabled());
+    assert!(clone.should_block(url, "", ResourceType::Script));
+    engine.set_enabled(false);
+    assert!(!clone.should_block(url, "", ResourceType::Script));
+}
+
 #[test]
 fn test_should_block_returns_false_for_safe_urls() {
     let config = AdBlockConfig {
         enabled: true,
         update_filters_on_startup: false,
         custom_filters: vec![],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -149,7 +183,7 @@ fn test_should_block_returns_false_for_safe_urls() {
     engine.initialize().unwrap();
 
     // Should not block legitimate sites
-    let blocked = engine.should_block("https://www.google.com/", ResourceType::Document);
+    let blocked = engine.should_block("https://www.google.com/", "", ResourceType::Document);
     assert!(!blocked);
 }
 
@@ -159,6 +193,7 @@ fn test_should_block_with_custom_filter() {
         enabled: true,
         update_filters_on_startup: false,
         custom_filters: vec!["||ads.example.com^".to_string()],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -166,7 +201,11 @@ fn test_should_block_with_custom_filter() {
     engine.initialize().unwrap();
 
     // Should block URL matching custom filter
-    let blocked = engine.should_block("https://ads.example.com/banner.js", ResourceType::Script);
+    let blocked = engine.should_block(
+        "https://ads.example.com/banner.js",
+        "",
+        ResourceType::Script,
+    );
     assert!(blocked);
 }
 
@@ -176,13 +215,14 @@ fn test_should_block_before_initialization() {
         enabled: true,
         update_filters_on_startup: false,
         custom_filters: vec![],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
     let engine = AdBlockEngine::new(config, sender).unwrap();
 
     // Before initialization, should not crash but return false
-    let blocked = engine.should_block("https://ads.example.com/", ResourceType::Script);
+    let blocked = engine.should_block("https://ads.example.com/", "", ResourceType::Script);
     assert!(!blocked);
 }
 
@@ -196,6 +236,7 @@ fn test_should_block_handles_different_resource_types() {
         enabled: true,
         update_filters_on_startup: false,
         custom_filters: vec!["||tracker.example.com^".to_string()],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -203,13 +244,19 @@ fn test_should_block_handles_different_resource_types() {
     engine.initialize().unwrap();
 
     // Test different resource types
-    assert!(engine.should_block("https://tracker.example.com/pixel.png", ResourceType::Image));
+    assert!(engine.should_block(
+        "https://tracker.example.com/pixel.png",
+        "",
+        ResourceType::Image
+    ));
     assert!(engine.should_block(
         "https://tracker.example.com/script.js",
+        "",
         ResourceType::Script
     ));
     assert!(engine.should_block(
         "https://tracker.example.com/style.css",
+        "",
         ResourceType::Stylesheet
     ));
 }
@@ -224,6 +271,7 @@ fn test_should_block_handles_invalid_urls() {
         enabled: true,
         update_filters_on_startup: false,
         custom_filters: vec![],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -231,7 +279,7 @@ fn test_should_block_handles_invalid_urls() {
     engine.initialize().unwrap();
 
     // Should handle invalid URLs gracefully
-    let blocked = engine.should_block("not a valid url", ResourceType::Script);
+    let blocked = engine.should_block("not a valid url", "", ResourceType::Script);
     // Should not crash, return false for invalid URLs
     assert!(!blocked);
 }
@@ -242,13 +290,14 @@ fn test_should_block_handles_empty_url() {
         enabled: true,
         update_filters_on_startup: false,
         custom_filters: vec![],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
     let mut engine = AdBlockEngine::new(config, sender).unwrap();
     engine.initialize().unwrap();
 
-    let blocked = engine.should_block("", ResourceType::Script);
+    let blocked = engine.should_block("", "", ResourceType::Script);
     assert!(!blocked);
 }
 
@@ -262,6 +311,7 @@ fn test_get_cosmetic_filters_with_disabled_engine() {
         enabled: false,
         update_filters_on_startup: false,
         custom_filters: vec![],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -278,6 +328,7 @@ fn test_get_cosmetic_filters_before_initialization() {
         enabled: true,
         update_filters_on_startup: false,
         custom_filters: vec![],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -297,6 +348,7 @@ fn test_adblock_engine_clone() {
         enabled: true,
         update_filters_on_startup: false,
         custom_filters: vec!["||ads.example.com^".to_string()],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -307,8 +359,16 @@ fn test_adblock_engine_clone() {
     let cloned_engine = engine.clone();
 
     // Both should block the same URLs
-    assert!(engine.should_block("https://ads.example.com/banner.js", ResourceType::Script));
-    assert!(cloned_engine.should_block("https://ads.example.com/banner.js", ResourceType::Script));
+    assert!(engine.should_block(
+        "https://ads.example.com/banner.js",
+        "",
+        ResourceType::Script
+    ));
+    assert!(cloned_engine.should_block(
+        "https://ads.example.com/banner.js",
+        "",
+        ResourceType::Script
+    ));
 }
 
 // ========================================
@@ -321,6 +381,7 @@ fn test_get_element_hider_css_with_disabled_engine() {
         enabled: false,
         update_filters_on_startup: false,
         custom_filters: vec![],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -337,6 +398,7 @@ fn test_get_element_hider_css_before_initialization() {
         enabled: true,
         update_filters_on_startup: false,
         custom_filters: vec![],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -356,6 +418,7 @@ fn test_get_element_hider_css_returns_valid_css() {
             "##.ad-banner".to_string(),
             "##.sponsored-content".to_string(),
         ],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -377,6 +440,7 @@ fn test_get_element_hider_style_tag_format() {
         enabled: true,
         update_filters_on_startup: false,
         custom_filters: vec!["##.ad-banner".to_string()],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -397,6 +461,7 @@ fn test_get_element_hider_style_tag_disabled() {
         enabled: false,
         update_filters_on_startup: false,
         custom_filters: vec![],
+        allowlisted_domains: vec![],
     };
     let sender = Box::new(MockMessageSender::new());
 
@@ -406,3 +471,1016 @@ fn test_get_element_hider_style_tag_disabled() {
     let style_tag = engine.get_element_hider_style_tag("https://example.com");
     assert!(style_tag.is_none());
 }
+
+// ========================================
+// Tests for filter list seeding
+// ========================================
+
+fn engine_with_store(store: FilterListStore) -> AdBlockEngine {
+    let config = AdBlockConfig {
+        enabled: true,
+        update_filters_on_startup: false,
+        custom_filters: vec![],
+        allowlisted_domains: vec![],
+    };
+    let mut engine = AdBlockEngine::new(config, Box::new(MockMessageSender::new())).unwrap();
+    engine.set_filter_store(store);
+    engine.initialize().unwrap();
+    engine
+}
+
+#[cfg(feature = "seed-filters")]
+#[test]
+fn test_first_run_seeding_blocks_immediately() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut store = FilterListStore::open(dir.path()).unwrap();
+
+    let outcome = store.seed().unwrap();
+
+    assert!(matches!(outcome, SeedOutcome::Seeded { rules } if rules > 0));
+    assert!(dir.path().join("seed.txt").exists());
+    let engine = engine_with_store(store);
+    // Tracker rules are in the seed list but not in EasyList
+    assert!(engine.should_block(
+        "https://www.google-analytics.com/analytics.js",
+        "",
+        ResourceType::Script
+    ));
+    let lists = engine.filter_lists();
+    assert_eq!(lists.len(), 1);
+    assert_eq!(lists[0].source, shared_types::FilterListSource::Seed);
+}
+
+#[cfg(feature = "seed-filters")]
+#[test]
+fn test_second_run_seeding_is_noop() {
+    let dir = tempfile::tempdir().unwrap();
+    FilterListStore::open(dir.path()).unwrap().seed().unwrap();
+
+    let mut store = FilterListStore::open(dir.path()).unwrap();
+    let rules = store.rules().count();
+
+    assert_eq!(store.seed().unwrap(), SeedOutcome::AlreadySeeded);
+    assert_eq!(store.lists().len(), 1);
+    assert_eq!(store.rules().count(), rules);
+}
+
+#[cfg(feature = "seed-filters")]
+#[test]
+fn test_fetched_list_supersedes_seed() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut store = FilterListStore::open(dir.path()).unwrap();
+    store.seed().unwrap();
+
+    store
+        .install_fetched("easyprivacy", "! Title: EasyPrivacy\n||tracker.example^\n")
+        .unwrap();
+
+    assert!(!dir.path().join("seed.txt").exists());
+    let reopened = FilterListStore::open(dir.path()).unwrap();
+    assert!(reopened.has_fetched());
+    let names: Vec<_> = reopened.lists().iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, vec!["easyprivacy"]);
+
+    let engine = engine_with_store(reopened);
+    assert!(engine.should_block("https://tracker.example/t.js", "", ResourceType::Script));
+    assert!(!engine.should_block(
+        "https://www.google-analytics.com/analytics.js",
+        "",
+        ResourceType::Script
+    ));
+}
+
+#[test]
+fn test_seeding_skipped_when_fetched_lists_exist() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("easylist.txt"), "||ads.example^\n").unwrap();
+    let mut store = FilterListStore::open(dir.path()).unwrap();
+
+    let outcome = store.seed().unwrap();
+
+    assert!(matches!(
+        outcome,
+        SeedOutcome::HasFetchedLists | SeedOutcome::NoSeedData
+    ));
+    assert!(!dir.path().join("seed.txt").exists());
+    assert_eq!(store.lists().len(), 1);
+}
+
+#[test]
+fn test_install_fetched_rejects_path_names() {
+    let mut store = FilterListStore::in_memory();
+    assert!(matches!(
+        store.install_fetched("../evil", "||a.example^"),
+        Err(Error::FilterLoadError(_))
+    ));
+}
+
+#[cfg(not(feature = "seed-filters"))]
+#[test]
+fn test_no_seed_build_has_working_engine() {
+    let mut store = FilterListStore::in_memory();
+    assert_eq!(store.seed().unwrap(), SeedOutcome::NoSeedData);
+    assert!(store.lists().is_empty());
+
+    let config = AdBlockConfig {
+        enabled: true,
+        update_filters_on_startup: false,
+        custom_filters: vec!["||ads.example.com^".to_string()],
+        allowlisted_domains: vec![],
+    };
+    let mut engine = AdBlockEngine::new(config, Box::new(MockMessageSender::new())).unwrap();
+    engine.set_filter_store(store);
+    engine.initialize().unwrap();
+    assert!(engine.should_block(
+        "https://ads.example.com/banner.js",
+        "",
+        ResourceType::Script
+    ));
+    assert!(!engine.should_block(
+        "https://www.google-analytics.com/analytics.js",
+        "",
+        ResourceType::Script
+    ));
+}
+
+// ========================================
+// Tests for filter list subscriptions
+// ========================================
+
+const EASYLIST_HEADER: &str = "[Adblock Plus 2.0]
+! Version: 202410160512
+! Title: EasyList
+! Last modified: 16 Oct 2024 05:12 UTC
+! Expires: 4 days (update frequency)
+! Homepage: https://easylist.to/
+! Licence: https://easylist.to/pages/licence.html
+!
+! Please report any unblocked adverts or problems
+";
+
+/// Fetcher serving lists from a shared map, counting downloads
+///
+/// A `conditional` fetcher tags each list with an ETag of its length and
+/// answers 304 Not Modified when it is sent back.
+#[derive(Clone, Default)]
+struct MockFetcher {
+    lists: Arc<Mutex<std::collections::HashMap<String, String>>>,
+    fetches: Arc<Mutex<usize>>,
+    conditional: bool,
+}
+
+impl MockFetcher {
+    fn serve(&self, url: &str, content: &str) {
+        self.lists
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), content.to_string());
+    }
+
+    fn fetches(&self) -> usize {
+        *self.fetches.lock().unwrap()
+    }
+}
+
+impl ListFetcher for MockFetcher {
+    fn fetch(&self, url: &str) -> Result<String> {
+        *self.fetches.lock().unwrap() += 1;
+        self.lists
+            .lock()
+            .unwrap()
+            .get(url)
+            .cloned()
+            .ok_or_else(|| Error::FilterLoadError(format!("404 for {}", url)))
+    }
+
+    fn fetch_if_modified(&self, url: &str, validators: &ListValidators) -> Result<ListDownload> {
+        let content = self.fetch(url)?;
+        if !self.conditional {
+            return Ok(ListDownload::Modified {
+                content,
+                validators: ListValidators::default(),
+            });
+        }
+        let etag = format!("\"{}\"", content.len());
+        if validators.etag.as_deref() == Some(etag.as_str()) {
+            return Ok(ListDownload::NotModified);
+        }
+        Ok(ListDownload::Modified {
+            content,
+            validators: ListValidators {
+                etag: Some(etag),
+                last_modified: None,
+            },
+        })
+    }
+}
+
+/// Clock whose time the test sets
+#[derive(Clone, Default)]
+struct MockClock(Arc<Mutex<u64>>);
+
+impl MockClock {
+    fn set(&self, now: u64) {
+        *self.0.lock().unwrap() = now;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> u64 {
+        *self.0.lock().unwrap()
+    }
+}
+
+fn subscription_engine(fetcher: &MockFetcher, clock: &MockClock) -> AdBlockEngine {
+    let config = AdBlockConfig {
+        enabled: true,
+        update_filters_on_startup: false,
+        custom_filters: vec![],
+        allowlisted_domains: vec![],
+    };
+    let mut engine = AdBlockEngine::new(config, Box::new(MockMessageSender::new())).unwrap();
+    engine.set_filter_store(FilterListStore::in_memory());
+    engine.set_list_fetcher(Box::new(fetcher.clone()));
+    engine.set_clock(Box::new(clock.clone()));
+    engine.initialize().unwrap();
+    engine
+}
+
+#[test]
+fn test_parse_metadata_real_world_headers() {
+    let easylist = subscriptions::parse_metadata(&format!("{}||ads.example^\n", EASYLIST_HEADER));
+    assert_eq!(easylist.title.as_deref(), Some("EasyList"));
+    assert_eq!(easylist.expires, Some(4 * 24 * 60 * 60));
+    assert_eq!(easylist.rules, 1);
+
+    // uBlock Origin style, hours and a trailing comment
+    let ubo = subscriptions::parse_metadata(
+        "! Title: uBlock filters – Privacy\n! Expires: 12 hours\n! Last modified: x\n||t.example^\n",
+    );
+    assert_eq!(ubo.title.as_deref(), Some("uBlock filters – Privacy"));
+    assert_eq!(ubo.expires, Some(12 * 60 * 60));
+
+    assert_eq!(subscriptions::parse_expires("1d"), Some(24 * 60 * 60));
+    assert_eq!(subscriptions::parse_expires("6h"), Some(6 * 60 * 60));
+    assert_eq!(subscriptions::parse_expires("2"), Some(2 * 24 * 60 * 60));
+    assert_eq!(subscriptions::parse_expires("soon"), None);
+    assert_eq!(subscriptions::parse_expires("0 days"), None);
+
+    // Comments after the first rule are not header metadata
+    let late = subscriptions::parse_metadata("||a.example^\n! Title: Not a header\n");
+    assert_eq!(late.title, None);
+}
+
+#[test]
+fn test_validate_list_rejects_non_lists() {
+    assert!(matches!(
+        subscriptions::validate_list("<!DOCTYPE html><html>Sign in to Wi-Fi</html>"),
+        Err(Error::FilterLoadError(_))
+    ));
+    assert!(matches!(
+        subscriptions::validate_list(EASYLIST_HEADER),
+        Err(Error::FilterLoadError(_))
+    ));
+}
+
+#[test]
+fn test_add_subscription_reads_metadata() {
+    let fetcher = MockFetcher::default();
+    let clock = MockClock::default();
+    clock.set(1_000);
+    fetcher.serve(
+        "https://lists.example/easylist.txt",
+        &format!("{}||ads.sub-test.example^\n##.banner\n", EASYLIST_HEADER),
+    );
+    let engine = subscription_engine(&fetcher, &clock);
+
+    let id = engine
+        .add_subscription("https://lists.example/easylist.txt")
+        .unwrap();
+
+    let subs = engine.list_subscriptions();
+    assert_eq!(subs.len(), 1);
+    assert_eq!(subs[0].id, id);
+    assert_eq!(subs[0].title, "EasyList");
+    assert_eq!(subs[0].rule_count, 2);
+    assert_eq!(subs[0].last_updated, Some(1_000));
+    assert_eq!(subs[0].expires_hint, Some(4 * 24 * 60 * 60));
+    assert!(engine.should_block(
+        "https://ads.sub-test.example/a.js",
+        "",
+        ResourceType::Script
+    ));
+
+    // Duplicates and failed downloads add nothing
+    assert!(engine
+        .add_subscription("https://lists.example/easylist.txt")
+        .is_err());
+    assert!(engine
+        .add_subscription("https://lists.example/missing.txt")
+        .is_err());
+    assert_eq!(engine.list_subscriptions().len(), 1);
+}
+
+#[test]
+fn test_subscription_enable_disable_affects_should_block() {
+    let fetcher = MockFetcher::default();
+    let clock = MockClock::default();
+    fetcher.serve("https://lists.example/a.txt", "||alpha.sub-test.example^\n");
+    fetcher.serve("https://lists.example/b.txt", "||beta.sub-test.example^\n");
+    let engine = subscription_engine(&fetcher, &clock);
+    let a = engine
+        .add_subscription("https://lists.example/a.txt")
+        .unwrap();
+    engine
+        .add_subscription("https://lists.example/b.txt")
+        .unwrap();
+
+    engine.set_subscription_enabled(a, false).unwrap();
+
+    assert!(!engine.should_block(
+        "https://alpha.sub-test.example/x.js",
+        "",
+        ResourceType::Script
+    ));
+    assert!(engine.should_block(
+        "https://beta.sub-test.example/x.js",
+        "",
+        ResourceType::Script
+    ));
+    // Clones share the swapped engine
+    let clone = engine.clone();
+    engine.set_subscription_enabled(a, true).unwrap();
+    assert!(clone.should_block(
+        "https://alpha.sub-test.example/x.js",
+        "",
+        ResourceType::Script
+    ));
+
+    engine.remove_subscription(a).unwrap();
+    assert!(!engine.should_block(
+        "https://alpha.sub-test.example/x.js",
+        "",
+        ResourceType::Script
+    ));
+    assert!(matches!(
+        engine.set_subscription_enabled(a, true),
+        Err(Error::UnknownSubscription(_))
+    ));
+}
+
+#[test]
+fn test_update_all_respects_expires() {
+    let fetcher = MockFetcher::default();
+    let clock = MockClock::default();
+    clock.set(10_000);
+    let url = "https://lists.example/daily.txt";
+    fetcher.serve(url, "! Expires: 1 day\n||old.sub-test.example^\n");
+    let engine = subscription_engine(&fetcher, &clock);
+    let id = engine.add_subscription(url).unwrap();
+    fetcher.serve(url, "! Expires: 1 day\n||new.sub-test.example^\n");
+    let fetches = fetcher.fetches();
+
+    // Not yet due
+    clock.set(10_000 + 23 * 60 * 60);
+    let report = engine.update_all(false).unwrap();
+    assert_eq!(report.skipped, vec![id]);
+    assert_eq!(fetcher.fetches(), fetches);
+    assert!(engine.should_block(
+        "https://old.sub-test.example/x.js",
+        "",
+        ResourceType::Script
+    ));
+
+    // Due once the Expires interval has passed
+    clock.set(10_000 + 24 * 60 * 60);
+    let report = engine.update_all(false).unwrap();
+    assert_eq!(report.updated, vec![id]);
+    assert!(engine.should_block(
+        "https://new.sub-test.example/x.js",
+        "",
+        ResourceType::Script
+    ));
+    assert!(!engine.should_block(
+        "https://old.sub-test.example/x.js",
+        "",
+        ResourceType::Script
+    ));
+
+    // Forced updates ignore the interval; disabled lists are never fetched
+    assert_eq!(engine.update_all(true).unwrap().updated, vec![id]);
+    engine.set_subscription_enabled(id, false).unwrap();
+    let fetches = fetcher.fetches();
+    assert_eq!(engine.update_all(true).unwrap().skipped, vec![id]);
+    assert_eq!(fetcher.fetches(), fetches);
+}
+
+#[test]
+fn test_update_failure_keeps_cached_list() {
+    let fetcher = MockFetcher::default();
+    let clock = MockClock::default();
+    let url = "https://lists.example/flaky.txt";
+    fetcher.serve(url, "||cached.sub-test.example^\n");
+    let engine = subscription_engine(&fetcher, &clock);
+    let id = engine.add_subscription(url).unwrap();
+    fetcher.serve(url, "<html>502 Bad Gateway</html>");
+
+    let report = engine.update_all(true).unwrap();
+
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0, id);
+    assert!(engine.should_block(
+        "https://cached.sub-test.example/x.js",
+        "",
+        ResourceType::Script
+    ));
+}
+
+#[test]
+fn test_engine_rebuilt_only_when_rules_change() {
+    let fetcher = MockFetcher::default();
+    let clock = MockClock::default();
+    let url = "https://lists.example/a.txt";
+    fetcher.serve(url, "||ads.sub-test.example^\n");
+    let engine = subscription_engine(&fetcher, &clock);
+    let initial = engine.engine_generation();
+
+    let id = engine.add_subscription(url).unwrap();
+    assert_eq!(engine.engine_generation(), initial + 1);
+
+    // Same content: nothing to recompile
+    engine.update_all(true).unwrap();
+    assert_eq!(engine.engine_generation(), initial + 1);
+
+    // Each real toggle recompiles; repeating one does not
+    engine.set_subscription_enabled(id, false).unwrap();
+    engine.set_subscription_enabled(id, false).unwrap();
+    assert_eq!(engine.engine_generation(), initial + 2);
+    engine.set_subscription_enabled(id, true).unwrap();
+    assert_eq!(engine.engine_generation(), initial + 3);
+
+    // Changed content is recompiled
+    fetcher.serve(url, "||ads.sub-test.example^\n||more.sub-test.example^\n");
+    engine.update_all(true).unwrap();
+    assert_eq!(engine.engine_generation(), initial + 4);
+    assert!(engine.should_block(
+        "https://more.sub-test.example/x.js",
+        "",
+        ResourceType::Script
+    ));
+}
+
+#[test]
+fn test_subscriptions_persist_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(SUBSCRIPTIONS_FILE);
+    let defaults = vec![
+        "https://easylist.to/easylist/easylist.txt".to_string(),
+        "https://easylist.to/easylist/easyprivacy.txt".to_string(),
+    ];
+
+    // A new profile starts with the defaults, not yet downloaded
+    let set = SubscriptionSet::open(&path, &defaults).unwrap();
+    assert_eq!(set.subscriptions().len(), 2);
+    assert!(set.subscriptions().iter().all(|s| s.last_updated.is_none()));
+    assert!(!path.exists());
+
+    let fetcher = MockFetcher::default();
+    let clock = MockClock::default();
+    clock.set(42);
+    fetcher.serve(
+        &defaults[0],
+        &format!("{}||ads.example^\n", EASYLIST_HEADER),
+    );
+    fetcher.serve(&defaults[1], "! Title: EasyPrivacy\n||tracker.example^\n");
+    fetcher.serve("https://lists.example/regional.txt", "||ads.example.de^\n");
+    let mut engine = subscription_engine(&fetcher, &clock);
+    engine.set_subscriptions(set);
+    engine.set_filter_store(FilterListStore::open(dir.path()).unwrap());
+    assert_eq!(engine.update_all(false).unwrap().updated.len(), 2);
+    let regional = engine
+        .add_subscription("https://lists.example/regional.txt")
+        .unwrap();
+    engine.set_subscription_enabled(regional, false).unwrap();
+    engine.remove_subscription(0).unwrap();
+
+    let reopened = SubscriptionSet::open(&path, &defaults).unwrap();
+    assert_eq!(
+        reopened.subscriptions(),
+        engine.list_subscriptions().as_slice()
+    );
+    let titles: Vec<_> = reopened
+        .subscriptions()
+        .iter()
+        .map(|s| s.title.as_str())
+        .collect();
+    assert_eq!(
+        titles,
+        vec!["EasyPrivacy", "https://lists.example/regional.txt"]
+    );
+    assert!(!reopened.get(regional).unwrap().enabled);
+    // Removed defaults are not re-added, and ids are not reused
+    assert!(!reopened.contains_url(&defaults[0]));
+    let mut reopened = reopened;
+    assert_eq!(reopened.register(&defaults[0]).unwrap(), regional + 1);
+}
+
+// ========================================
+// Tests for runtime custom filter changes
+// ========================================
+
+#[test]
+fn test_custom_filters_change_at_runtime() {
+    let config = AdBlockConfig {
+        enabled: true,
+        update_filters_on_startup: false,
+        custom_filters: vec!["||ads.custom-test.example^".to_string()],
+        allowlisted_domains: vec![],
+    };
+    let mut engine = AdBlockEngine::new(config, Box::new(MockMessageSender::new())).unwrap();
+    engine.initialize().unwrap();
+    let ad = "https://ads.custom-test.example/banner.js";
+    let tracker = "https://tracker.custom-test.example/pixel.gif";
+    assert!(engine.should_block(ad, "", ResourceType::Script));
+
+    engine
+        .add_filter(" ||tracker.custom-test.example^ ")
+        .unwrap();
+    assert!(engine.should_block(tracker, "", ResourceType::Image));
+    assert_eq!(
+        engine.list_custom_filters(),
+        vec![
+            "||ads.custom-test.example^".to_string(),
+            "||tracker.custom-test.example^".to_string(),
+        ]
+    );
+
+    // A URL blocked before its rule is removed loads after
+    assert!(engine.remove_filter("||ads.custom-test.example^").unwrap());
+    assert!(!engine.should_block(ad, "", ResourceType::Script));
+    assert!(!engine.remove_filter("||ads.custom-test.example^").unwrap());
+
+    assert!(matches!(
+        engine.add_filter("! just a comment"),
+        Err(Error::FilterParseError(_))
+    ));
+
+    // Reloading replaces the rules and reports the rejected ones
+    let rejected = engine
+        .reload_filters(vec![
+            "||ads.custom-test.example^".to_string(),
+            "".to_string(),
+            "||ads.custom-test.example^".to_string(),
+        ])
+        .unwrap();
+    assert_eq!(rejected.len(), 1);
+    assert_eq!(rejected[0].0, "");
+    assert_eq!(
+        engine.list_custom_filters(),
+        vec!["||ads.custom-test.example^".to_string()]
+    );
+    assert!(engine.should_block(ad, "", ResourceType::Script));
+    assert!(!engine.should_block(tracker, "", ResourceType::Image));
+}
+
+// ========================================
+// Tests for conditional and background list updates
+// ========================================
+
+/// Wait for a background refresh to finish and install its lists
+fn wait_for_refresh(engine: &AdBlockEngine) -> UpdateReport {
+    for _ in 0..500 {
+        if let Some(report) = engine.poll_updates().unwrap() {
+            return report;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    panic!("background refresh did not finish");
+}
+
+#[test]
+fn test_background_refresh_is_conditional_and_notifies() {
+    let fetcher = MockFetcher {
+        conditional: true,
+        ..MockFetcher::default()
+    };
+    let url = "https://lists.example/etag.txt";
+    fetcher.serve(url, "||old.refresh-test.example^\n");
+    let sender = MockMessageSender::new();
+    let messages = sender.messages.clone();
+    let config = AdBlockConfig {
+        enabled: true,
+        update_filters_on_startup: true,
+        custom_filters: vec![],
+        allowlisted_domains: vec![],
+    };
+    let mut engine = AdBlockEngine::new(config, Box::new(sender)).unwrap();
+    engine.set_filter_store(FilterListStore::in_memory());
+    engine.set_list_fetcher(Box::new(fetcher.clone()));
+    engine.set_clock(Box::new(MockClock::default()));
+    engine.initialize().unwrap();
+    let id = engine.add_subscription(url).unwrap();
+    assert_eq!(
+        engine.list_subscriptions()[0].etag.as_deref(),
+        Some("\"28\"")
+    );
+
+    // An unchanged list is confirmed, not installed again
+    let generation = engine.engine_generation();
+    engine.refresh_in_background(true).unwrap();
+    let report = wait_for_refresh(&engine);
+    assert_eq!(report.unchanged, vec![id]);
+    assert!(report.updated.is_empty());
+    assert_eq!(engine.engine_generation(), generation);
+    assert!(messages.lock().unwrap().is_empty());
+
+    // A changed list is installed and announced
+    fetcher.serve(
+        url,
+        "||new.refresh-test.example^\n||more.refresh-test.example^\n",
+    );
+    engine.refresh_in_background(true).unwrap();
+    assert_eq!(wait_for_refresh(&engine).updated, vec![id]);
+    assert!(engine.should_block(
+        "https://new.refresh-test.example/x.js",
+        "",
+        ResourceType::Script
+    ));
+    assert!(!engine.should_block(
+        "https://old.refresh-test.example/x.js",
+        "",
+        ResourceType::Script
+    ));
+    assert!(matches!(
+        messages.lock().unwrap().last(),
+        Some(BrowserMessage::FilterListsUpdated { rule_count }) if *rule_count == engine.rule_count()
+    ));
+
+    // A failed download keeps the previous list
+    fetcher.serve(url, "<html>502 Bad Gateway</html>");
+    engine.refresh_in_background(true).unwrap();
+    assert_eq!(wait_for_refresh(&engine).failed.len(), 1);
+    assert!(engine.should_block(
+        "https://new.refresh-test.example/x.js",
+        "",
+        ResourceType::Script
+    ));
+    assert_eq!(messages.lock().unwrap().len(), 1);
+}
+
+// ========================================
+// Tests for the per-site allowlist
+// ========================================
+
+#[test]
+fn test_allowlisted_site_is_not_blocked() {
+    let config = AdBlockConfig {
+        enabled: true,
+        update_filters_on_startup: false,
+        custom_filters: vec![
+            "||ads.allow-test.example^".to_string(),
+            "##.ad-banner".to_string(),
+        ],
+        allowlisted_domains: vec!["Broken.Example.".to_string()],
+    };
+    let mut engine = AdBlockEngine::new(config, Box::new(MockMessageSender::new())).unwrap();
+    engine.initialize().unwrap();
+    let ad = "https://ads.allow-test.example/banner.js";
+
+    // Configured domains are normalized, and cover their subdomains
+    assert_eq!(engine.allowlisted_domains(), vec!["broken.example"]);
+    assert!(engine.is_allowed("www.broken.example"));
+    assert!(!engine.is_allowed("notbroken.example"));
+    assert!(!engine.should_block(ad, "https://www.broken.example/", ResourceType::Script));
+    assert!(engine
+        .get_cosmetic_filters("https://broken.example/")
+        .is_empty());
+    assert!(engine.should_block(ad, "https://news.example/", ResourceType::Script));
+
+    // A navigation counts as made by the page it loads
+    assert!(engine.should_block(ad, "", ResourceType::Document));
+    assert!(engine.allow_site("allow-test.example").unwrap());
+    assert!(!engine.allow_site("allow-test.example").unwrap());
+    assert!(!engine.should_block(ad, "", ResourceType::Document));
+
+    engine
+        .handle_message(&BrowserMessage::ToggleAdblockForSite {
+            domain: "broken.example".to_string(),
+            allowed: false,
+        })
+        .unwrap();
+    assert!(engine.should_block(ad, "https://www.broken.example/", ResourceType::Script));
+    assert!(!engine.disallow_site("broken.example").unwrap());
+    assert_eq!(engine.allowlisted_domains(), vec!["allow-test.example"]);
+
+    engine
+        .handle_message(&BrowserMessage::ToggleAdblockForSite {
+            domain: "news.example".to_string(),
+            allowed: true,
+        })
+        .unwrap();
+    assert!(!engine.should_block(ad, "https://news.example/", ResourceType::Script));
+}
+
+#[test]
+fn test_allowlist_changes_are_saved_to_config_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    let settings = config_manager::Config::default_toml()
+        .replace("\"https://www.google.com\"", "\"https://home.example/\"");
+    std::fs::write(&path, format!("# my settings\n{}", settings)).unwrap();
+    let config = AdBlockConfig {
+        enabled: true,
+        update_filters_on_startup: false,
+        custom_filters: vec![],
+        allowlisted_domains: vec!["kept.example".to_string()],
+    };
+    let engine = AdBlockEngine::new(config, Box::new(MockMessageSender::new())).unwrap();
+    engine.set_config_file(&path);
+
+    assert!(engine.allow_site("Saved.Example").unwrap());
+    engine
+        .handle_message(&BrowserMessage::ToggleAdblockForSite {
+            domain: "kept.example".to_string(),
+            allowed: false,
+        })
+        .unwrap();
+
+    let saved = config_manager::Config::load_from_file(&path).unwrap();
+    assert_eq!(saved.adblock.allowlisted_domains, ["saved.example"]);
+    assert_eq!(saved.browser.homepage, "https://home.example/");
+    assert!(std::fs::read_to_string(&path)
+        .unwrap()
+        .contains("# my settings"));
+
+    // A failed save is reported, but the change still applies
+    engine.set_config_file(dir.path());
+    assert!(engine.allow_site("unsaved.example").is_err());
+    assert!(engine.is_allowed("unsaved.example"));
+}
+
+// ========================================
+// Tests for blocked request statistics
+// ========================================
+
+#[test]
+fn test_stats_count_per_tab_and_rule() {
+    let config = AdBlockConfig {
+        enabled: true,
+        update_filters_on_startup: false,
+        custom_filters: vec![
+            "||ads.stats-test.example^".to_string(),
+            "||tracker.stats-test.example^".to_string(),
+        ],
+        allowlisted_domains: vec![],
+    };
+    let sender = MockMessageSender::new();
+    let messages = sender.messages.clone();
+    let mut engine = AdBlockEngine::new(config, Box::new(sender)).unwrap();
+    engine.initialize().unwrap();
+    let page = "https://news.example/";
+    let ad = "https://ads.stats-test.example/a.js";
+    let tracker = "https://tracker.stats-test.example/t.gif";
+
+    assert!(engine.should_block_in_tab(ad, page, ResourceType::Script, Some(1)));
+    assert!(engine.should_block_in_tab(ad, page, ResourceType::Script, Some(1)));
+    assert!(engine.should_block_in_tab(tracker, page, ResourceType::Image, Some(2)));
+    assert!(!engine.should_block_in_tab(page, "", ResourceType::Document, Some(2)));
+    assert!(engine.should_block(tracker, page, ResourceType::Image));
+
+    let stats = engine.stats();
+    assert_eq!(stats.checked, 5);
+    assert_eq!(stats.blocked, 4);
+    assert_eq!(stats.blocked_in_tab(1), 2);
+    assert_eq!(stats.blocked_in_tab(2), 1);
+    assert_eq!(stats.blocked_in_tab(3), 0);
+    assert_eq!(
+        stats.top_rules,
+        vec![
+            RuleCount {
+                rule: "||ads.stats-test.example^".to_string(),
+                blocked: 2,
+            },
+            RuleCount {
+                rule: "||tracker.stats-test.example^".to_string(),
+                blocked: 2,
+            },
+        ]
+    );
+
+    // Resetting one tab leaves the others
+    engine.reset_tab_stats(1);
+    let stats = engine.stats();
+    assert_eq!(stats.blocked_in_tab(1), 0);
+    assert_eq!(stats.blocked_in_tab(2), 1);
+    assert_eq!(stats.blocked, 4);
+
+    // The message path counts under the tab it names
+    engine
+        .handle_message(&BrowserMessage::ShouldBlock {
+            url: url::Url::parse(ad).unwrap(),
+            resource_type: ResourceType::Script,
+            tab_id: Some(3),
+        })
+        .unwrap();
+    assert!(matches!(
+        messages.lock().unwrap().last(),
+        Some(BrowserMessage::BlockDecision { block: true, .. })
+    ));
+    assert_eq!(engine.stats().blocked_in_tab(3), 1);
+
+    engine.reset_stats();
+    assert_eq!(engine.stats(), AdBlockStats::default());
+}
+
+#[test]
+fn test_block_stats_concurrent_increments() {
+    let stats = Arc::new(BlockStats::new());
+    let threads: Vec<_> = (0..8)
+        .map(|tab| {
+            let stats = stats.clone();
+            std::thread::spawn(move || {
+                for i in 0..1000 {
+                    stats.record(Some(tab), i % 2 == 0, Some("||ads.example^"));
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.checked, 8000);
+    assert_eq!(snapshot.blocked, 4000);
+    assert_eq!(snapshot.top_rules[0].blocked, 4000);
+    assert_eq!(snapshot.tabs.len(), 8);
+    assert!(snapshot.tabs.values().all(|&count| count == 500));
+}
+
+// ========================================
+// Tests for cosmetic filtering
+// ========================================
+
+#[test]
+fn test_cosmetic_resources_apply_exceptions() {
+    let config = AdBlockConfig {
+        enabled: true,
+        update_filters_on_startup: false,
+        custom_filters: vec![
+            "##.ad-banner".to_string(),
+            "##.sponsored".to_string(),
+            "##.promo".to_string(),
+            "#@#.promo".to_string(),
+            "news.example##.news-ad".to_string(),
+            "news.example#@#.sponsored".to_string(),
+        ],
+        allowlisted_domains: vec!["allowed.example".to_string()],
+    };
+    let mut engine = AdBlockEngine::new(config, Box::new(MockMessageSender::new())).unwrap();
+    assert!(engine
+        .cosmetic_resources_for_url("https://news.example/")
+        .is_empty());
+    engine.initialize().unwrap();
+
+    // The repository's EasyList is loaded too; only this test's generic
+    // selectors are checked
+    let ours = |selectors: &[String]| -> Vec<String> {
+        selectors
+            .iter()
+            .filter(|selector| [".ad-banner", ".sponsored", ".promo"].contains(&selector.as_str()))
+            .cloned()
+            .collect()
+    };
+
+    let news = engine.cosmetic_resources_for_url("https://news.example/story");
+    assert_eq!(ours(&news.generic_selectors), vec![".ad-banner"]);
+    assert_eq!(news.specific_selectors, vec![".news-ad"]);
+
+    let other = engine.cosmetic_resources_for_url("https://other.example/");
+    assert_eq!(
+        ours(&other.generic_selectors),
+        vec![".ad-banner", ".sponsored"]
+    );
+    assert!(other.specific_selectors.is_empty());
+
+    assert_eq!(
+        engine.get_element_hider_css("https://news.example/story"),
+        Some(news.as_stylesheet())
+    );
+    let own = CosmeticResult {
+        generic_selectors: ours(&news.generic_selectors),
+        ..news
+    };
+    assert_eq!(
+        own.as_stylesheet(),
+        ".ad-banner, .news-ad { display: none !important; }"
+    );
+
+    assert!(engine
+        .cosmetic_resources_for_url("https://www.allowed.example/")
+        .is_empty());
+    assert_eq!(CosmeticResult::default().as_stylesheet(), "");
+}
+
+// ========================================
+// Tests for checks from several threads
+// ========================================
+
+#[test]
+fn test_engine_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<AdBlockEngine>();
+}
+
+#[test]
+fn test_concurrent_checks_share_engine() {
+    let config = AdBlockConfig {
+        enabled: true,
+        update_filters_on_startup: false,
+        custom_filters: vec!["||ads.thread-test.example^".to_string()],
+        allowlisted_domains: vec![],
+    };
+    let mut engine = AdBlockEngine::new(config, Box::new(MockMessageSender::new())).unwrap();
+    engine.initialize().unwrap();
+
+    let threads: Vec<_> = (0..4)
+        .map(|tab| {
+            let engine = engine.clone();
+            std::thread::spawn(move || {
+                for i in 0..250 {
+                    let url = if i % 2 == 0 {
+                        "https://ads.thread-test.example/a.js"
+                    } else {
+                        "https://cdn.thread-test.example/app.js"
+                    };
+                    engine.should_block_in_tab(
+                        url,
+                        "https://news.example/",
+                        ResourceType::Script,
+                        Some(tab),
+                    );
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let stats = engine.stats();
+    assert_eq!(stats.checked, 1000);
+    assert_eq!(stats.blocked, 500);
+    assert!((0..4).all(|tab| stats.blocked_in_tab(tab) == 125));
+}
+
+// ========================================
+// Tests for ShouldBlock requests over the message bus
+// ========================================
+
+#[test]
+fn test_should_block_request_through_bus() {
+    let mut bus = message_bus::MessageBus::new();
+    bus.start().unwrap();
+    let config = AdBlockConfig {
+        enabled: true,
+        update_filters_on_startup: false,
+        custom_filters: vec!["||ads.bus-test.example^".to_string()],
+        allowlisted_domains: vec![],
+    };
+    let mut engine = AdBlockEngine::new(config, bus.sender()).unwrap();
+    engine.initialize().unwrap();
+    bus.register_handler(Box::new(engine.clone()));
+
+    let threads: Vec<_> = (0..4)
+        .map(|tab_id| {
+            let sender = bus.sender();
+            std::thread::spawn(move || {
+                let host = if tab_id % 2 == 0 { "ads" } else { "cdn" };
+                let url = format!("https://{}.bus-test.example/x.js", host);
+                let reply = sender
+                    .request(BrowserMessage::ShouldBlock {
+                        url: url::Url::parse(&url).unwrap(),
+                        resource_type: ResourceType::Script,
+                        tab_id: Some(tab_id),
+                    })
+                    .unwrap();
+                match reply {
+                    BrowserMessage::BlockDecision { block, .. } => {
+                        assert_eq!(block, tab_id % 2 == 0)
+                    }
+                    other => panic!("unexpected reply {:?}", other),
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    let stats = engine.stats();
+    assert_eq!(stats.checked, 4);
+    assert_eq!(stats.blocked_in_tab(0), 1);
+    assert_eq!(stats.blocked_in_tab(1), 0);
+    bus.shutdown().unwrap();
+}
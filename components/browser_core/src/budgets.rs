@@ -0,0 +1,521 @@
+//! Navigation performance budgets and the slow page log
+//!
+//! When a navigation completes the engine sums the page up in a
+//! [`PageReport`] and checks it against the `[performance]` budgets. Each
+//! budget the page goes over becomes a [`SlowPageEvent`] in the
+//! `slow_pages` table of the diagnostics database, and `about:performance`
+//! lists the origins that go over most often from [`worst_pages`]. A
+//! budget of 0 is never checked.
+
+use crate::errors::Result;
+use crate::progress::ProgressInputs;
+use config_manager::PerformanceSettings;
+use network_stack::ResourceTiming;
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use url::Url;
+
+/// A measurement a page is budgeted on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BudgetMetric {
+    /// Time from navigation start to document loaded
+    PageLoad,
+    /// Time to the document's first byte
+    Ttfb,
+    /// Bytes transferred by the document and its subresources
+    TotalBytes,
+    /// Requests made besides the document
+    SubresourceCount,
+}
+
+impl BudgetMetric {
+    /// Every metric, in the order pages list them
+    pub const ALL: [BudgetMetric; 4] = [
+        BudgetMetric::PageLoad,
+        BudgetMetric::Ttfb,
+        BudgetMetric::TotalBytes,
+        BudgetMetric::SubresourceCount,
+    ];
+
+    /// Stable name, as stored and as in the `budget_*` settings
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BudgetMetric::PageLoad => "page_load_ms",
+            BudgetMetric::Ttfb => "ttfb_ms",
+            BudgetMetric::TotalBytes => "total_bytes",
+            BudgetMetric::SubresourceCount => "subresource_count",
+        }
+    }
+
+    /// Parse a name produced by [`as_str`](Self::as_str)
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|metric| metric.as_str() == name)
+    }
+
+    /// Unit the metric is measured in, for dashboard rows
+    pub fn unit(self) -> &'static str {
+        match self {
+            BudgetMetric::PageLoad | BudgetMetric::Ttfb => "ms",
+            BudgetMetric::TotalBytes => "bytes",
+            BudgetMetric::SubresourceCount => "count",
+        }
+    }
+
+    /// Heading on `about:performance`
+    pub fn label(self) -> &'static str {
+        match self {
+            BudgetMetric::PageLoad => "Page load",
+            BudgetMetric::Ttfb => "Time to first byte",
+            BudgetMetric::TotalBytes => "Bytes transferred",
+            BudgetMetric::SubresourceCount => "Subresources",
+        }
+    }
+
+    /// The configured budget; 0 means unchecked
+    pub fn budget(self, settings: &PerformanceSettings) -> u64 {
+        match self {
+            BudgetMetric::PageLoad => settings.budget_page_load_ms,
+            BudgetMetric::Ttfb => settings.budget_ttfb_ms,
+            BudgetMetric::TotalBytes => settings.budget_total_bytes,
+            BudgetMetric::SubresourceCount => settings.budget_subresource_count,
+        }
+    }
+}
+
+/// What one completed navigation cost
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageReport {
+    /// Document URL
+    pub url: Url,
+    /// Time from navigation start to document loaded
+    pub page_load_ms: u64,
+    /// Time to the document's first byte; 0 if it came from the cache
+    pub ttfb_ms: u64,
+    /// Bytes of the document and its subresources
+    pub total_bytes: u64,
+    /// Requests made besides the document
+    pub subresource_count: u64,
+    /// Trace of the user action that loaded the document, if any
+    pub trace_id: Option<u64>,
+    /// What the load's progress estimate was computed from, for pages
+    /// streamed by the engine
+    pub progress: Option<ProgressInputs>,
+}
+
+impl PageReport {
+    /// Sum up a page from the requests made for it
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Document URL; the request for it is the document
+    /// * `page_load_ms` - Time from navigation start to document loaded
+    /// * `timings` - Requests made for the page
+    pub fn from_timings(url: Url, page_load_ms: u64, timings: &[ResourceTiming]) -> Self {
+        let is_document = |timing: &&ResourceTiming| timing.url == url.as_str();
+        let document = timings.iter().find(is_document);
+        Self {
+            ttfb_ms: document.map_or(0, |t| t.ttfb_ms),
+            trace_id: document.and_then(|t| t.details.trace_id),
+            total_bytes: timings.iter().map(|t| t.size_bytes as u64).sum(),
+            subresource_count: timings.iter().filter(|t| !is_document(t)).count() as u64,
+            page_load_ms,
+            url,
+            progress: None,
+        }
+    }
+
+    /// The page's measurement of `metric`
+    pub fn value(&self, metric: BudgetMetric) -> u64 {
+        match metric {
+            BudgetMetric::PageLoad => self.page_load_ms,
+            BudgetMetric::Ttfb => self.ttfb_ms,
+            BudgetMetric::TotalBytes => self.total_bytes,
+            BudgetMetric::SubresourceCount => self.subresource_count,
+        }
+    }
+}
+
+/// One metric of a page held against its budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetCheck {
+    /// What was measured
+    pub metric: BudgetMetric,
+    /// The page's measurement
+    pub value: u64,
+    /// The budget it was held against
+    pub budget: u64,
+}
+
+impl BudgetCheck {
+    /// Whether the page stayed within the budget
+    pub fn passed(&self) -> bool {
+        self.value <= self.budget
+    }
+}
+
+/// Check a page against every budget that is set
+///
+/// # Arguments
+///
+/// * `report` - The completed page
+/// * `settings` - Budgets; metrics budgeted at 0 are skipped
+pub fn check_budgets(report: &PageReport, settings: &PerformanceSettings) -> Vec<BudgetCheck> {
+    BudgetMetric::ALL
+        .into_iter()
+        .filter_map(|metric| {
+            let budget = metric.budget(settings);
+            (budget > 0).then(|| BudgetCheck {
+                metric,
+                value: report.value(metric),
+                budget,
+            })
+        })
+        .collect()
+}
+
+/// A page that went over one of its budgets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowPageEvent {
+    /// Document URL
+    pub url: String,
+    /// The budget that was exceeded
+    pub metric: BudgetMetric,
+    /// The page's measurement
+    pub value: u64,
+    /// The budget
+    pub budget: u64,
+    /// When the page completed (Unix timestamp)
+    pub timestamp: i64,
+}
+
+impl SlowPageEvent {
+    /// Slow page events for the failed checks of a page
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Document URL
+    /// * `checks` - Result of [`check_budgets`]
+    /// * `timestamp` - When the page completed (Unix timestamp)
+    pub fn from_checks(url: &Url, checks: &[BudgetCheck], timestamp: i64) -> Vec<Self> {
+        checks
+            .iter()
+            .filter(|check| !check.passed())
+            .map(|check| Self {
+                url: url.to_string(),
+                metric: check.metric,
+                value: check.value,
+                budget: check.budget,
+                timestamp,
+            })
+            .collect()
+    }
+
+    /// How far over budget the page went
+    pub fn excess(&self) -> u64 {
+        self.value.saturating_sub(self.budget)
+    }
+}
+
+/// An origin whose pages went over one budget, for `about:performance`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowOrigin {
+    /// Serialized origin, e.g. `https://news.example`
+    pub origin: String,
+    /// Times a page of the origin went over the budget
+    pub events: u64,
+    /// Median amount over the budget
+    pub typical_excess: u64,
+    /// Page with the largest measurement
+    pub worst_url: String,
+    /// That page's measurement
+    pub worst_value: u64,
+}
+
+/// Create the `slow_pages` table if it does not exist
+pub fn init_slow_pages_schema(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS slow_pages (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            url TEXT NOT NULL,
+            origin TEXT NOT NULL,
+            metric TEXT NOT NULL,
+            value INTEGER NOT NULL,
+            budget INTEGER NOT NULL,
+            recorded_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_slow_pages_metric ON slow_pages(metric, origin)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Add a slow page to the log
+pub fn record_slow_page(conn: &Connection, event: &SlowPageEvent) -> Result<()> {
+    let origin = Url::parse(&event.url)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_else(|_| event.url.clone());
+    conn.execute(
+        "INSERT INTO slow_pages (url, origin, metric, value, budget, recorded_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            event.url,
+            origin,
+            event.metric.as_str(),
+            event.value as i64,
+            event.budget as i64,
+            event.timestamp
+        ],
+    )?;
+    Ok(())
+}
+
+/// Origins that went over a budget most often
+///
+/// Origins are ordered by how many times they went over, then by their
+/// typical excess.
+///
+/// # Arguments
+///
+/// * `conn` - Database holding the `slow_pages` table
+/// * `metric` - Budget to rank by
+/// * `limit` - Most origins to return
+pub fn worst_pages(
+    conn: &Connection,
+    metric: BudgetMetric,
+    limit: usize,
+) -> Result<Vec<SlowOrigin>> {
+    let mut stmt = conn.prepare(
+        "SELECT origin, url, value, budget FROM slow_pages WHERE metric = ?1 ORDER BY id",
+    )?;
+    let rows = stmt.query_map(params![metric.as_str()], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, i64>(3)?,
+        ))
+    })?;
+
+    let mut by_origin: HashMap<String, (Vec<u64>, String, u64)> = HashMap::new();
+    for row in rows {
+        let (origin, url, value, budget) = row?;
+        let (value, budget) = (value.max(0) as u64, budget.max(0) as u64);
+        let (excesses, worst_url, worst_value) = by_origin
+            .entry(origin)
+            .or_insert_with(|| (Vec::new(), url.clone(), value));
+        excesses.push(value.saturating_sub(budget));
+        if value > *worst_value {
+            *worst_url = url;
+            *worst_value = value;
+        }
+    }
+
+    let mut origins: Vec<SlowOrigin> = by_origin
+        .into_iter()
+        .map(|(origin, (mut excesses, worst_url, worst_value))| {
+            excesses.sort_unstable();
+            SlowOrigin {
+                origin,
+                events: excesses.len() as u64,
+                typical_excess: excesses[excesses.len() / 2],
+                worst_url,
+                worst_value,
+            }
+        })
+        .collect();
+    origins.sort_by(|a, b| {
+        b.events
+            .cmp(&a.events)
+            .then(b.typical_excess.cmp(&a.typical_excess))
+            .then(a.origin.cmp(&b.origin))
+    });
+    origins.truncate(limit);
+    Ok(origins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use network_stack::RequestDetails;
+    use std::time::Duration;
+
+    fn timing(url: &str, ttfb_ms: u64, size_bytes: usize) -> ResourceTiming {
+        ResourceTiming {
+            url: url.to_string(),
+            start_time: Duration::ZERO,
+            end_time: Duration::ZERO,
+            duration_ms: ttfb_ms,
+            ttfb_ms,
+            queued_ms: 0,
+            dns_ms: 0,
+            connect_ms: 0,
+            tls_ms: 0,
+            download_ms: 0,
+            size_bytes,
+            encoded_size: size_bytes,
+            decoded_size: size_bytes,
+            from_cache: false,
+            revalidated: false,
+            cancelled: false,
+            redirected_from: Vec::new(),
+            details: RequestDetails::default(),
+        }
+    }
+
+    fn report(page_load_ms: u64, ttfb_ms: u64, total_bytes: u64, subresources: u64) -> PageReport {
+        PageReport {
+            url: Url::parse("https://news.example/story").unwrap(),
+            page_load_ms,
+            ttfb_ms,
+            total_bytes,
+            subresource_count: subresources,
+            trace_id: None,
+            progress: None,
+        }
+    }
+
+    fn event(url: &str, value: u64, budget: u64) -> SlowPageEvent {
+        SlowPageEvent {
+            url: url.to_string(),
+            metric: BudgetMetric::PageLoad,
+            value,
+            budget,
+            timestamp: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn test_page_report_from_timings() {
+        let url = Url::parse("https://news.example/story").unwrap();
+        let mut timings = [
+            timing("https://cdn.example/app.js", 40, 30_000),
+            timing("https://news.example/story", 220, 12_000),
+            timing("https://cdn.example/logo.png", 35, 8_000),
+        ];
+        timings[0].details.trace_id = Some(3);
+        timings[1].details.trace_id = Some(9);
+        let report = PageReport::from_timings(url, 1_800, &timings);
+        assert_eq!(report.trace_id, Some(9));
+        assert_eq!(report.ttfb_ms, 220);
+        assert_eq!(report.total_bytes, 50_000);
+        assert_eq!(report.subresource_count, 2);
+        assert_eq!(report.value(BudgetMetric::PageLoad), 1_800);
+    }
+
+    #[test]
+    fn test_check_budgets_each_metric() {
+        let settings = PerformanceSettings::default();
+        let within = report(1_000, 100, 1_000, 10);
+        assert!(check_budgets(&within, &settings).iter().all(|c| c.passed()));
+        assert_eq!(check_budgets(&within, &settings).len(), 4);
+
+        let cases = [
+            (report(9_000, 100, 1_000, 10), BudgetMetric::PageLoad),
+            (report(1_000, 2_000, 1_000, 10), BudgetMetric::Ttfb),
+            (report(1_000, 100, 9 << 20, 10), BudgetMetric::TotalBytes),
+            (
+                report(1_000, 100, 1_000, 400),
+                BudgetMetric::SubresourceCount,
+            ),
+        ];
+        for (page, metric) in cases {
+            let checks = check_budgets(&page, &settings);
+            let failed: Vec<_> = checks.iter().filter(|c| !c.passed()).collect();
+            assert_eq!(failed.len(), 1, "{:?}", metric);
+            assert_eq!(failed[0].metric, metric);
+            assert_eq!(failed[0].budget, metric.budget(&settings));
+
+            let events = SlowPageEvent::from_checks(&page.url, &checks, 7);
+            assert_eq!(events.len(), 1);
+            assert_eq!(events[0].value, page.value(metric));
+            assert_eq!(events[0].timestamp, 7);
+        }
+
+        // A page exactly on budget passes
+        let on_budget = report(settings.budget_page_load_ms, 0, 0, 0);
+        assert!(check_budgets(&on_budget, &settings)[0].passed());
+    }
+
+    #[test]
+    fn test_zero_budget_disables_check() {
+        let settings = PerformanceSettings {
+            budget_page_load_ms: 0,
+            budget_total_bytes: 0,
+            ..PerformanceSettings::default()
+        };
+        let page = report(60_000, 100, u64::MAX, 10);
+        let checks = check_budgets(&page, &settings);
+        assert_eq!(
+            checks.iter().map(|c| c.metric).collect::<Vec<_>>(),
+            vec![BudgetMetric::Ttfb, BudgetMetric::SubresourceCount]
+        );
+        assert!(SlowPageEvent::from_checks(&page.url, &checks, 0).is_empty());
+    }
+
+    #[test]
+    fn test_worst_pages_groups_by_origin() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_slow_pages_schema(&conn).unwrap();
+        for e in [
+            event("https://slow.example/a", 9_000, 5_000),
+            event("https://slow.example/b", 12_000, 5_000),
+            event("https://slow.example/a", 6_000, 5_000),
+            event("https://heavy.example/", 30_000, 5_000),
+            event("https://news.example/1", 5_500, 5_000),
+            event("https://news.example/2", 5_200, 5_000),
+        ] {
+            record_slow_page(&conn, &e).unwrap();
+        }
+        record_slow_page(
+            &conn,
+            &SlowPageEvent {
+                metric: BudgetMetric::Ttfb,
+                ..event("https://ttfb.example/", 900, 800)
+            },
+        )
+        .unwrap();
+
+        let worst = worst_pages(&conn, BudgetMetric::PageLoad, 10).unwrap();
+        let origins: Vec<&str> = worst.iter().map(|o| o.origin.as_str()).collect();
+        assert_eq!(
+            origins,
+            vec![
+                "https://slow.example",
+                "https://news.example",
+                "https://heavy.example"
+            ]
+        );
+        assert_eq!(
+            worst[0],
+            SlowOrigin {
+                origin: "https://slow.example".to_string(),
+                events: 3,
+                typical_excess: 4_000,
+                worst_url: "https://slow.example/b".to_string(),
+                worst_value: 12_000,
+            }
+        );
+
+        assert_eq!(
+            worst_pages(&conn, BudgetMetric::PageLoad, 1).unwrap().len(),
+            1
+        );
+        let ttfb = worst_pages(&conn, BudgetMetric::Ttfb, 10).unwrap();
+        assert_eq!(ttfb.len(), 1);
+        assert_eq!(ttfb[0].typical_excess, 100);
+        assert!(worst_pages(&conn, BudgetMetric::TotalBytes, 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_metric_names_round_trip() {
+        for metric in BudgetMetric::ALL {
+            assert_eq!(BudgetMetric::parse(metric.as_str()), Some(metric));
+        }
+        assert_eq!(BudgetMetric::parse("fps"), None);
+    }
+}
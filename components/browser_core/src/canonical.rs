@@ -0,0 +1,154 @@
+//! URL canonicalization for history and bookmark keys
+//!
+//! `https://example.com/page`, `https://example.com/page/` and
+//! `https://example.com/page#section` are the same page as far as history
+//! and bookmarks are concerned. Rows are keyed by
+//! [`canonicalize_for_history`], while the exact URL last seen is kept
+//! alongside for display and navigation.
+
+use url::Url;
+
+/// Hosts (and their subdomains) that route on the fragment, so the
+/// fragment identifies a distinct page and is kept in the key
+pub const SPA_FRAGMENT_HOSTS: &[&str] = &[
+    "mail.google.com",
+    "calendar.google.com",
+    "web.whatsapp.com",
+    "app.slack.com",
+    "outlook.live.com",
+];
+
+/// Canonical storage key for a URL
+///
+/// - Scheme and host are lowercased and default ports dropped (the `url`
+///   crate already does this for special schemes)
+/// - The fragment is removed, unless the host routes on it (see
+///   [`fragment_is_route`])
+/// - A trailing slash is removed from path-only URLs, except for the root
+///
+/// # Arguments
+///
+/// * `url` - URL as navigated
+///
+/// # Returns
+///
+/// Returns the canonical URL; the input is left untouched.
+pub fn canonicalize_for_history(url: &Url) -> Url {
+    let mut canonical = url.clone();
+
+    if !fragment_is_route(url) {
+        canonical.set_fragment(None);
+    }
+
+    let path_only = canonical.query().is_none() && canonical.fragment().is_none();
+    if path_only && !canonical.cannot_be_a_base() {
+        let path = canonical.path();
+        if path.len() > 1 && path.ends_with('/') {
+            let trimmed = match path.trim_end_matches('/') {
+                "" => "/".to_string(),
+                trimmed => trimmed.to_string(),
+            };
+            canonical.set_path(&trimmed);
+        }
+    }
+
+    canonical
+}
+
+/// Canonical storage key for a URL string
+///
+/// Strings that do not parse as URLs are their own key.
+pub fn canonical_key(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(parsed) => canonicalize_for_history(&parsed).into(),
+        Err(_) => url.to_string(),
+    }
+}
+
+/// Whether a URL's fragment selects a page rather than a position in it
+///
+/// True for hosts in [`SPA_FRAGMENT_HOSTS`] and for route-like fragments
+/// (`#/inbox`, `#!/profile`), the hash-routing conventions of single-page
+/// apps.
+pub fn fragment_is_route(url: &Url) -> bool {
+    let Some(fragment) = url.fragment().filter(|f| !f.is_empty()) else {
+        return false;
+    };
+    if fragment.starts_with('/') || fragment.starts_with("!/") {
+        return true;
+    }
+
+    let host = url.host_str().unwrap_or_default();
+    SPA_FRAGMENT_HOSTS
+        .iter()
+        .any(|spa| host == *spa || host.ends_with(&format!(".{}", spa)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(url: &str) -> String {
+        canonicalize_for_history(&Url::parse(url).unwrap()).to_string()
+    }
+
+    #[test]
+    fn test_canonicalize_table() {
+        let cases = [
+            ("https://example.com/page", "https://example.com/page"),
+            ("https://example.com/page/", "https://example.com/page"),
+            (
+                "https://example.com/page#section",
+                "https://example.com/page",
+            ),
+            ("https://example.com/page/#top", "https://example.com/page"),
+            ("HTTPS://EXAMPLE.COM/Page", "https://example.com/Page"),
+            ("https://example.com:443/a", "https://example.com/a"),
+            ("http://example.com:80/a/", "http://example.com/a"),
+            ("http://example.com:8080/a/", "http://example.com:8080/a"),
+            ("https://example.com/", "https://example.com/"),
+            ("https://example.com", "https://example.com/"),
+            ("https://example.com//", "https://example.com/"),
+            ("https://example.com/a/?q=1", "https://example.com/a/?q=1"),
+            ("https://example.com/a?q=1#x", "https://example.com/a?q=1"),
+            (
+                "https://mail.google.com/mail/u/0/#inbox",
+                "https://mail.google.com/mail/u/0/#inbox",
+            ),
+            (
+                "https://app.example.com/#/settings",
+                "https://app.example.com/#/settings",
+            ),
+            (
+                "https://example.com/#!/profile",
+                "https://example.com/#!/profile",
+            ),
+            ("https://example.com/page#", "https://example.com/page"),
+            ("about:blank#x", "about:blank"),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(canonical(input), expected, "canonicalizing {}", input);
+        }
+    }
+
+    #[test]
+    fn test_canonicalize_keeps_input_intact() {
+        let url = Url::parse("https://example.com/page/#section").unwrap();
+        let _ = canonicalize_for_history(&url);
+        assert_eq!(url.as_str(), "https://example.com/page/#section");
+    }
+
+    #[test]
+    fn test_spa_host_subdomains() {
+        let url = Url::parse("https://x.app.slack.com/client#T1").unwrap();
+        assert!(fragment_is_route(&url));
+        let url = Url::parse("https://notslack.com/client#T1").unwrap();
+        assert!(!fragment_is_route(&url));
+    }
+
+    #[test]
+    fn test_canonical_key_unparseable() {
+        assert_eq!(canonical_key("not a url"), "not a url");
+        assert_eq!(canonical_key("https://a.com/b/"), "https://a.com/b");
+    }
+}
@@ -0,0 +1,345 @@
+//! SQLite connection setup and maintenance
+//!
+//! Every database connection is opened through [`DbOptions`], which sets:
+//!
+//! - `journal_mode=WAL`, so readers never wait for a writer and a killed
+//!   process leaves committed transactions in the `-wal` file instead of a
+//!   rollback journal to undo (in-memory databases stay on `memory`)
+//! - `busy_timeout` from `browser.db_busy_timeout_ms`, so a connection
+//!   retries a locked database for a while instead of failing at once
+//! - `synchronous=NORMAL`, which is durable across application crashes in
+//!   WAL mode and skips the fsync on every commit
+//! - `foreign_keys=ON`
+//!
+//! # Checkpoints
+//!
+//! Commits append pages to the `-wal` file; checkpoints copy them into the
+//! main file. SQLite runs a passive checkpoint by itself once the WAL holds
+//! about 1000 pages, but never shrinks the file, so two more are run with
+//! `TRUNCATE`, which leaves the WAL empty:
+//!
+//! - during graceful shutdown, before the connections are closed
+//!   ([`checkpoint`]), so the next start finds no WAL to replay;
+//! - opportunistically from periodic maintenance once the WAL has grown
+//!   past [`DbOptions::checkpoint_threshold`] ([`checkpoint_if_needed`]).
+//!   A checkpoint that finds readers still using old pages does what it
+//!   can and is retried on the next tick.
+
+use crate::errors::{Error, Result};
+use config_manager::Config;
+use rusqlite::{Connection, ErrorCode};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How long a query waits for a lock by default
+pub const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// WAL size past which periodic maintenance checkpoints, in bytes
+pub const DEFAULT_CHECKPOINT_THRESHOLD: u64 = 4 * 1024 * 1024;
+
+/// Settings applied to every database connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DbOptions {
+    /// How long a query waits for a lock held by another connection
+    pub busy_timeout: Duration,
+    /// WAL size in bytes past which [`checkpoint_if_needed`] checkpoints
+    pub checkpoint_threshold: u64,
+}
+
+impl Default for DbOptions {
+    fn default() -> Self {
+        Self {
+            busy_timeout: DEFAULT_BUSY_TIMEOUT,
+            checkpoint_threshold: DEFAULT_CHECKPOINT_THRESHOLD,
+        }
+    }
+}
+
+/// Size and checkpoint state of one database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DbStats {
+    /// Short name, e.g. `history`
+    pub name: String,
+    /// Database file; `None` for in-memory databases
+    pub path: Option<PathBuf>,
+    /// Pages in the main database file
+    pub page_count: u64,
+    /// Bytes per page
+    pub page_size: u64,
+    /// Current size of the `-wal` file in bytes
+    pub wal_bytes: u64,
+    /// When the last `TRUNCATE` checkpoint ran (Unix seconds), if ever
+    pub last_checkpoint: Option<i64>,
+}
+
+impl DbOptions {
+    /// Options for the configured busy timeout
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration; `browser.db_busy_timeout_ms` is read
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            busy_timeout: config.browser.db_busy_timeout(),
+            ..Self::default()
+        }
+    }
+
+    /// Set how long a query waits for a lock
+    pub fn with_busy_timeout(mut self, busy_timeout: Duration) -> Self {
+        self.busy_timeout = busy_timeout;
+        self
+    }
+
+    /// Set the WAL size past which periodic maintenance checkpoints
+    pub fn with_checkpoint_threshold(mut self, bytes: u64) -> Self {
+        self.checkpoint_threshold = bytes;
+        self
+    }
+
+    /// Open a database and apply the options
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Database file; `None` opens an in-memory database
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::DatabaseLocked` if another process holds the
+    /// database and SQLite refused to wait for it, `Error::DatabaseBusy` if
+    /// it stayed busy for the whole busy timeout, or `Error::DatabaseError`
+    /// for anything else.
+    pub fn open(&self, path: Option<&Path>) -> Result<Connection> {
+        let started = Instant::now();
+        let opened = match path {
+            Some(path) => Connection::open(path),
+            None => Connection::open_in_memory(),
+        }
+        .and_then(|conn| self.apply(&conn).map(|()| conn));
+        opened.map_err(|e| open_error(path, e, started.elapsed(), self.busy_timeout))
+    }
+
+    /// Apply the options to an open connection
+    fn apply(&self, conn: &Connection) -> rusqlite::Result<()> {
+        conn.busy_timeout(self.busy_timeout)?;
+        // Answers with the mode now in effect; in-memory databases keep
+        // `memory`
+        let _: String = conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        conn.pragma_update(None, "foreign_keys", true)?;
+        Ok(())
+    }
+}
+
+/// Tell a database held by another process from one that stayed busy
+///
+/// SQLite only reports `SQLITE_BUSY` after the busy handler gave up, so a
+/// busy error that arrives well before the timeout means the lock could
+/// not be waited for at all (an exclusive lock, or a hot journal being
+/// recovered by its owner).
+fn open_error(
+    path: Option<&Path>,
+    error: rusqlite::Error,
+    waited: Duration,
+    busy_timeout: Duration,
+) -> Error {
+    let name = path.map_or_else(|| ":memory:".to_string(), |p| p.display().to_string());
+    match error.sqlite_error_code() {
+        Some(ErrorCode::DatabaseBusy) if waited >= busy_timeout => Error::DatabaseBusy {
+            path: name,
+            waited_ms: waited.as_millis() as u64,
+        },
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked) => Error::DatabaseLocked(name),
+        _ => error.into(),
+    }
+}
+
+/// Database file behind a connection, if it has one
+fn db_path(conn: &Connection) -> Option<PathBuf> {
+    conn.path()
+        .map(PathBuf::from)
+        .filter(|path| !path.as_os_str().is_empty())
+}
+
+/// Current size of the connection's `-wal` file in bytes
+///
+/// Zero for in-memory databases and when there is no WAL file.
+pub fn wal_bytes(conn: &Connection) -> u64 {
+    let Some(path) = db_path(conn) else {
+        return 0;
+    };
+    let mut wal = path.into_os_string();
+    wal.push("-wal");
+    std::fs::metadata(wal).map_or(0, |meta| meta.len())
+}
+
+/// Copy the WAL into the database file and truncate it
+///
+/// # Returns
+///
+/// Returns `true` if every WAL page was copied; `false` if a reader still
+/// needed some of them (the WAL is left as it is).
+pub fn checkpoint(conn: &Connection) -> Result<bool> {
+    let busy: i64 = conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))?;
+    Ok(busy == 0)
+}
+
+/// Checkpoint if the WAL has grown past the threshold
+///
+/// # Returns
+///
+/// Returns `true` if a checkpoint ran and completed.
+pub fn checkpoint_if_needed(conn: &Connection, options: &DbOptions) -> Result<bool> {
+    if wal_bytes(conn) <= options.checkpoint_threshold {
+        return Ok(false);
+    }
+    checkpoint(conn)
+}
+
+/// Collect the size and checkpoint state of a database
+///
+/// # Arguments
+///
+/// * `name` - Short name shown in diagnostics
+/// * `conn` - Connection to the database
+/// * `last_checkpoint` - When the owner last checkpointed it
+pub fn db_stats(name: &str, conn: &Connection, last_checkpoint: Option<i64>) -> Result<DbStats> {
+    let page_count: i64 = conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+    let page_size: i64 = conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+    Ok(DbStats {
+        name: name.to_string(),
+        path: db_path(conn),
+        page_count: page_count as u64,
+        page_size: page_size as u64,
+        wal_bytes: wal_bytes(conn),
+        last_checkpoint,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn populate(conn: &Connection, rows: usize) {
+        conn.execute_batch("CREATE TABLE IF NOT EXISTS t (id INTEGER PRIMARY KEY, v TEXT)")
+            .unwrap();
+        let tx = conn.unchecked_transaction().unwrap();
+        for i in 0..rows {
+            tx.execute("INSERT INTO t (v) VALUES (?1)", [format!("{:0200}", i)])
+                .unwrap();
+        }
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_open_applies_pragmas() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = DbOptions::default()
+            .open(Some(&dir.path().join("a.db")))
+            .unwrap();
+
+        let mode: String = conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+        let synchronous: i64 = conn
+            .query_row("PRAGMA synchronous", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(synchronous, 1); // NORMAL
+        let foreign_keys: i64 = conn
+            .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(foreign_keys, 1);
+
+        let memory = DbOptions::default().open(None).unwrap();
+        let mode: String = memory
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "memory");
+        assert_eq!(wal_bytes(&memory), 0);
+    }
+
+    #[test]
+    fn test_reader_not_blocked_by_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.db");
+        let options = DbOptions::default().with_busy_timeout(Duration::from_secs(2));
+        let writer = options.open(Some(&path)).unwrap();
+        populate(&writer, 10);
+
+        writer.execute_batch("BEGIN IMMEDIATE").unwrap();
+        writer
+            .execute("INSERT INTO t (v) VALUES ('uncommitted')", [])
+            .unwrap();
+
+        let started = Instant::now();
+        let reader = options.open(Some(&path)).unwrap();
+        let count: i64 = reader
+            .query_row("SELECT COUNT(*) FROM t", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 10);
+        assert!(started.elapsed() < options.busy_timeout);
+
+        writer.execute_batch("COMMIT").unwrap();
+    }
+
+    #[test]
+    fn test_busy_database_gives_up_after_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.db");
+        // A connection that never switched to WAL, holding a write lock
+        let holder = Connection::open(&path).unwrap();
+        holder.execute_batch("CREATE TABLE t (v TEXT)").unwrap();
+        holder.execute_batch("BEGIN EXCLUSIVE").unwrap();
+
+        let options = DbOptions::default().with_busy_timeout(Duration::from_millis(100));
+        let error = options.open(Some(&path)).unwrap_err();
+        assert!(
+            matches!(error, Error::DatabaseBusy { waited_ms, .. } if waited_ms >= 100),
+            "{}",
+            error
+        );
+        holder.execute_batch("COMMIT").unwrap();
+    }
+
+    #[test]
+    fn test_lock_error_without_waiting() {
+        let path = Path::new("/profile/history.db");
+        let busy = || {
+            rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY),
+                None,
+            )
+        };
+        let timeout = Duration::from_secs(5);
+
+        let error = open_error(Some(path), busy(), Duration::from_millis(1), timeout);
+        assert!(matches!(error, Error::DatabaseLocked(ref p) if p == "/profile/history.db"));
+        let error = open_error(Some(path), busy(), timeout, timeout);
+        assert!(matches!(error, Error::DatabaseBusy { waited_ms, .. } if waited_ms == 5000));
+        let error = open_error(None, rusqlite::Error::InvalidQuery, timeout, timeout);
+        assert!(matches!(error, Error::DatabaseError(_)));
+    }
+
+    #[test]
+    fn test_checkpoint_truncates_wal() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = DbOptions::default().with_checkpoint_threshold(64 * 1024);
+        let conn = options.open(Some(&dir.path().join("a.db"))).unwrap();
+
+        populate(&conn, 10);
+        assert!(wal_bytes(&conn) > 0);
+        assert!(!checkpoint_if_needed(&conn, &options).unwrap());
+
+        populate(&conn, 1000);
+        let before = db_stats("test", &conn, None).unwrap();
+        assert!(before.wal_bytes > options.checkpoint_threshold);
+        assert!(checkpoint_if_needed(&conn, &options).unwrap());
+
+        let after = db_stats("test", &conn, Some(1)).unwrap();
+        assert_eq!(after.wal_bytes, 0);
+        assert!(after.page_count > 1);
+        assert!(after.path.unwrap().ends_with("a.db"));
+        assert_eq!(after.last_checkpoint, Some(1));
+    }
+}
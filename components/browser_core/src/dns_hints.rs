@@ -0,0 +1,214 @@
+//! DNS prefetch hints in a document's head
+//!
+//! While a document streams in, [`DnsHintScanner`] reads its `<head>` for
+//! `<link rel="dns-prefetch" href="...">` hints, whose hosts the network
+//! stack resolves ahead of use, and for
+//! `<meta http-equiv="x-dns-prefetch-control" content="off">`, which turns
+//! prefetching off for the rest of the page. A page that turns it off gets
+//! none of its hints prefetched either.
+
+use crate::link_preview::parse_attributes;
+use crate::title::find_tag;
+use url::Url;
+
+/// Most bytes of a document read for hints
+pub const DNS_HINT_SCAN_LIMIT: usize = 32 * 1024;
+
+/// What a document's head says about DNS prefetching
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DnsHints {
+    /// Distinct http(s) hosts of `dns-prefetch` links, in document order
+    pub hosts: Vec<String>,
+    /// The page turned prefetching off
+    pub prefetch_off: bool,
+}
+
+/// Find the DNS prefetch hints in the start of a document
+///
+/// # Arguments
+///
+/// * `html` - Start of the document body
+/// * `base` - URL of the document, for relative `href`s
+pub fn extract_dns_hints(html: &[u8], base: &Url) -> DnsHints {
+    let lower = html.to_ascii_lowercase();
+    let mut hints = DnsHints::default();
+
+    let mut at = 0;
+    while let Some(open) = find_next_tag(&lower, at) {
+        // `<link` and `<meta` are the same length
+        let is_meta = lower[open..].starts_with(b"<meta");
+        let Some(len) = lower[open..].iter().position(|&b| b == b'>') else {
+            break;
+        };
+        at = open + len + 1;
+        let tag = String::from_utf8_lossy(&html[open + b"<link".len()..open + len]);
+        let attributes = parse_attributes(&tag);
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.trim())
+        };
+
+        if is_meta {
+            let off = attribute("http-equiv")
+                .is_some_and(|v| v.eq_ignore_ascii_case("x-dns-prefetch-control"))
+                && attribute("content").is_some_and(|v| v.eq_ignore_ascii_case("off"));
+            hints.prefetch_off |= off;
+            continue;
+        }
+        let is_hint = attribute("rel").is_some_and(|rel| {
+            rel.split_ascii_whitespace()
+                .any(|token| token.eq_ignore_ascii_case("dns-prefetch"))
+        });
+        let host = attribute("href")
+            .filter(|_| is_hint)
+            .and_then(|href| base.join(href).ok())
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .and_then(|url| url.host_str().map(str::to_string));
+        if let Some(host) = host {
+            if !hints.hosts.contains(&host) {
+                hints.hosts.push(host);
+            }
+        }
+    }
+
+    if hints.prefetch_off {
+        hints.hosts.clear();
+    }
+    hints
+}
+
+/// Position of the next `<link` or `<meta` tag at or after `from`
+fn find_next_tag(lower: &[u8], from: usize) -> Option<usize> {
+    match (
+        find_tag(lower, b"<link", from),
+        find_tag(lower, b"<meta", from),
+    ) {
+        (Some(link), Some(meta)) => Some(link.min(meta)),
+        (link, meta) => link.or(meta),
+    }
+}
+
+/// Collects a document's head while it streams in
+///
+/// Feed each chunk as it arrives; the hints are reported once, as soon as
+/// the `<head>` has ended, the `<body>` has started or
+/// [`DNS_HINT_SCAN_LIMIT`] bytes have been read. Call
+/// [`finish`](Self::finish) when the document ends, for documents too short
+/// to get that far.
+#[derive(Debug)]
+pub struct DnsHintScanner {
+    base: Url,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl DnsHintScanner {
+    /// Scanner for the document at `base`
+    pub fn new(base: Url) -> Self {
+        Self {
+            base,
+            buffer: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Scan the next chunk of the body
+    ///
+    /// # Returns
+    ///
+    /// Returns the hints the first time the head is complete, `None`
+    /// otherwise.
+    pub fn feed(&mut self, chunk: &[u8]) -> Option<DnsHints> {
+        if self.done {
+            return None;
+        }
+        let room = DNS_HINT_SCAN_LIMIT - self.buffer.len();
+        self.buffer
+            .extend_from_slice(&chunk[..chunk.len().min(room)]);
+
+        let lower = self.buffer.to_ascii_lowercase();
+        let head_over = self.buffer.len() == DNS_HINT_SCAN_LIMIT
+            || find_tag(&lower, b"</head", 0).is_some()
+            || find_tag(&lower, b"<body", 0).is_some();
+        head_over.then(|| self.take())
+    }
+
+    /// Hints in whatever was read, unless they were already reported
+    pub fn finish(&mut self) -> Option<DnsHints> {
+        (!self.done).then(|| self.take())
+    }
+
+    fn take(&mut self) -> DnsHints {
+        self.done = true;
+        let hints = extract_dns_hints(&self.buffer, &self.base);
+        self.buffer = Vec::new();
+        hints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base() -> Url {
+        Url::parse("https://news.example/story").unwrap()
+    }
+
+    #[test]
+    fn test_extract_dns_hints() {
+        let html = br#"<html><head>
+            <link rel="stylesheet" href="https://style.example/a.css">
+            <link rel="DNS-Prefetch" href="//CDN.example.com">
+            <link href='https://img.example/' rel='preconnect dns-prefetch'>
+            <link rel=dns-prefetch href=//cdn.example.com>
+            <link rel="dns-prefetch" href="ftp://files.example/">
+            <link rel="dns-prefetch" href="/same-site">
+            </head><body><link rel="dns-prefetch" href="//late.example"></body>"#;
+        let hints = extract_dns_hints(html, &base());
+        assert!(!hints.prefetch_off);
+        assert_eq!(
+            hints.hosts,
+            vec![
+                "cdn.example.com",
+                "img.example",
+                "news.example",
+                "late.example"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_meta_off_switch_drops_hints() {
+        let html = br#"<head><link rel="dns-prefetch" href="//cdn.example.com">
+            <meta http-equiv="X-DNS-Prefetch-Control" content=" OFF "></head>"#;
+        let hints = extract_dns_hints(html, &base());
+        assert!(hints.prefetch_off);
+        assert!(hints.hosts.is_empty());
+
+        let on = br#"<meta http-equiv="x-dns-prefetch-control" content="on">"#;
+        assert!(!extract_dns_hints(on, &base()).prefetch_off);
+    }
+
+    #[test]
+    fn test_scanner_reports_once_head_ends() {
+        let mut scanner = DnsHintScanner::new(base());
+        assert_eq!(
+            scanner.feed(b"<head><link rel=dns-prefetch href=//cdn.example.com>"),
+            None
+        );
+        let hints = scanner.feed(b"</head><body>").unwrap();
+        assert_eq!(hints.hosts, vec!["cdn.example.com"]);
+        assert_eq!(scanner.feed(b"<p>more</p>"), None);
+        assert_eq!(scanner.finish(), None);
+
+        // Short documents report when they end
+        let mut scanner = DnsHintScanner::new(base());
+        assert_eq!(
+            scanner.feed(b"<link rel=dns-prefetch href=//a.example>"),
+            None
+        );
+        assert_eq!(scanner.finish().unwrap().hosts, vec!["a.example"]);
+    }
+}
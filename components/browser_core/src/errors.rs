@@ -17,14 +17,43 @@ pub enum Error {
     #[error("No forward history available for tab {0}")]
     NoForwardHistory(u32),
 
+    /// Back/forward stack index past the end of the stack
+    #[error("Tab {tab_id} has no history entry {index} (stack has {len})")]
+    NoStackEntry {
+        /// Tab whose stack was indexed
+        tab_id: u32,
+        /// Requested index
+        index: usize,
+        /// Number of entries in the stack
+        len: usize,
+    },
+
     /// No current page
     #[error("No current page for tab {0}")]
     NoCurrentPage(u32),
 
+    /// Current page was not produced by a form post
+    #[error("Current page of tab {0} was not a form post")]
+    NotResubmittable(u32),
+
     /// Database error
     #[error("Database error: {0}")]
     DatabaseError(String),
 
+    /// Database is held by another process (e.g. a second browser instance
+    /// on the same profile)
+    #[error("Database {0} is locked by another process")]
+    DatabaseLocked(String),
+
+    /// Database stayed busy for the whole busy timeout
+    #[error("Database {path} is busy; retried for {waited_ms} ms and gave up")]
+    DatabaseBusy {
+        /// Database file
+        path: String,
+        /// How long the query waited for the lock
+        waited_ms: u64,
+    },
+
     /// Network error
     #[error("Network error: {0}")]
     NetworkError(String),
@@ -53,6 +82,64 @@ pub enum Error {
     #[error("Navigation timeout")]
     Timeout,
 
+    /// Navigation refused by policy for its initiator
+    #[error("Navigation blocked: {0}")]
+    NavigationBlocked(String),
+
+    /// No bookmark exists for a URL
+    #[error("No bookmark for {0}")]
+    BookmarkNotFound(String),
+
+    /// Bookmark folder name is blank
+    #[error("Invalid folder name: {0:?}")]
+    InvalidFolderName(String),
+
+    /// Bookmark keyword is not a lowercase token
+    #[error("Invalid keyword: {0}")]
+    InvalidKeyword(String),
+
+    /// Bookmark keyword is already taken
+    #[error("Keyword '{keyword}' is already used by {owner}")]
+    KeywordConflict {
+        /// The keyword
+        keyword: String,
+        /// What already uses it
+        owner: String,
+    },
+
+    /// Search terms given to a keyword bookmark without a `%s` placeholder
+    #[error(
+        "Keyword '{0}' does not take search terms; add %s to its bookmark URL where they should go"
+    )]
+    KeywordTakesNoTerms(String),
+
+    /// An import or export file could not be read or written
+    #[error("Cannot access {path}: {reason}")]
+    FileAccess {
+        /// The file
+        path: String,
+        /// What went wrong
+        reason: String,
+    },
+
+    /// An import file is not in the expected format
+    #[error("Cannot import {path}: {reason}")]
+    InvalidImport {
+        /// The file
+        path: String,
+        /// What is wrong with it
+        reason: String,
+    },
+
+    /// A session file is corrupt or from another format version
+    #[error("Cannot restore session from {path}: {reason}")]
+    InvalidSession {
+        /// The file
+        path: String,
+        /// What is wrong with it
+        reason: String,
+    },
+
     /// Other errors
     #[error(transparent)]
     Other(#[from] anyhow::Error),
@@ -85,6 +172,12 @@ mod tests {
         assert_eq!(err.to_string(), "Tab 42 not found");
     }
 
+    #[test]
+    fn test_navigation_blocked_error() {
+        let err = Error::NavigationBlocked("file: from script".to_string());
+        assert_eq!(err.to_string(), "Navigation blocked: file: from script");
+    }
+
     #[test]
     fn test_no_history_error() {
         let err = Error::NoHistory(1);
@@ -103,6 +196,26 @@ mod tests {
         assert_eq!(err.to_string(), "No current page for tab 3");
     }
 
+    #[test]
+    fn test_not_resubmittable_error() {
+        let err = Error::NotResubmittable(4);
+        assert_eq!(err.to_string(), "Current page of tab 4 was not a form post");
+    }
+
+    #[test]
+    fn test_keyword_errors() {
+        let err = Error::KeywordConflict {
+            keyword: "ddg".to_string(),
+            owner: "the DuckDuckGo search engine".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Keyword 'ddg' is already used by the DuckDuckGo search engine"
+        );
+        let err = Error::KeywordTakesNoTerms("mail".to_string());
+        assert!(err.to_string().contains("add %s"));
+    }
+
     #[test]
     fn test_database_error() {
         let err = Error::DatabaseError("connection failed".to_string());
@@ -110,6 +223,23 @@ mod tests {
         assert!(err.to_string().contains("connection failed"));
     }
 
+    #[test]
+    fn test_database_lock_errors() {
+        let err = Error::DatabaseLocked("/profile/history.db".to_string());
+        assert_eq!(
+            err.to_string(),
+            "Database /profile/history.db is locked by another process"
+        );
+        let err = Error::DatabaseBusy {
+            path: "/profile/history.db".to_string(),
+            waited_ms: 5000,
+        };
+        assert_eq!(
+            err.to_string(),
+            "Database /profile/history.db is busy; retried for 5000 ms and gave up"
+        );
+    }
+
     #[test]
     fn test_network_error() {
         let err = Error::NetworkError("timeout".to_string());
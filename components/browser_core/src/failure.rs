@@ -0,0 +1,292 @@
+//! Transient vs permanent navigation failures
+//!
+//! A main-frame load that fails for a reason likely to clear up by itself
+//! (connection reset, DNS not ready after wake-from-sleep, no first byte in
+//! time) is retried once by the engine before the error page is shown.
+//! Everything else is permanent and reported immediately.
+//!
+//! The classifiers match every error variant explicitly, so adding a
+//! variant forces a decision here.
+
+use crate::navigation::NavigationError;
+use shared_types::TabId;
+use std::time::Duration;
+use url::Url;
+
+/// Pause before the automatic retry of a transient failure
+pub const AUTO_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Number of failures kept in the engine's navigation failure log
+pub const FAILURE_LOG_LIMIT: usize = 100;
+
+/// Lower-case fragments of network error messages that mark a blip
+const TRANSIENT_MARKERS: &[&str] = &[
+    "connection reset",
+    "connection closed before message completed",
+    "connection aborted",
+    "broken pipe",
+    "unexpected eof",
+    "temporary failure in name resolution",
+    "timed out",
+];
+
+/// Whether retrying a failed navigation may succeed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureClass {
+    /// Likely to succeed if retried right away
+    Transient,
+    /// Will fail again until something changes
+    Permanent,
+}
+
+impl FailureClass {
+    /// Lower-case name, as shown on the error page and in the log
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FailureClass::Transient => "transient",
+            FailureClass::Permanent => "permanent",
+        }
+    }
+}
+
+/// A failed main-frame load, as logged by the engine
+#[derive(Debug, Clone, PartialEq)]
+pub struct NavigationFailure {
+    /// Tab the load was for
+    pub tab_id: TabId,
+    /// URL that failed to load
+    pub url: Url,
+    /// What went wrong
+    pub error: NavigationError,
+    /// Transient or permanent
+    pub class: FailureClass,
+    /// The engine retried the load after this failure
+    pub auto_retry: bool,
+    /// This failure happened on the automatic retry
+    pub retried: bool,
+    /// Trace of the user action that started the load, if any
+    pub trace_id: Option<u64>,
+}
+
+/// Classify a network stack error
+///
+/// # Arguments
+///
+/// * `error` - Error returned by the network stack
+pub fn classify_network_error(error: &network_stack::Error) -> FailureClass {
+    use network_stack::Error;
+
+    match error {
+        Error::RequestFailed(reason) => classify_reason(reason),
+        // No response (or no first byte) in time
+        Error::Timeout => FailureClass::Transient,
+        Error::InvalidUrl(_) => FailureClass::Permanent,
+        Error::Blocked { .. } => FailureClass::Permanent,
+        // Stopped on purpose; there is nothing to retry
+        Error::Cancelled => FailureClass::Permanent,
+        Error::RedirectLoop { .. } => FailureClass::Permanent,
+        // The partial page stays up; reloading is left to the user
+        Error::StreamInterrupted { .. } => FailureClass::Permanent,
+        // Only lifted when the user resumes background transfers
+        Error::TransfersPaused => FailureClass::Permanent,
+        // Only lifted when the user goes back online
+        Error::Offline(_) => FailureClass::Permanent,
+        // A slot frees up when another connection to the host closes
+        Error::ConnectionLimit { .. } => FailureClass::Transient,
+        Error::DownloadError(_) => FailureClass::Permanent,
+        Error::DecodeError(_) => FailureClass::Permanent,
+        Error::WebSocket(_) => FailureClass::Permanent,
+        Error::InvalidCertificate(_) => FailureClass::Permanent,
+        Error::CacheError(_) => FailureClass::Permanent,
+        Error::CookieError(_) => FailureClass::Permanent,
+        Error::InitializationError(_) => FailureClass::Permanent,
+        Error::ConfigError(_) => FailureClass::Permanent,
+        Error::MessageBusError(_) => FailureClass::Permanent,
+        Error::Other(_) => FailureClass::Permanent,
+    }
+}
+
+/// Classify a navigation error
+///
+/// # Arguments
+///
+/// * `error` - Error shown on the error page
+pub fn classify_navigation_error(error: &NavigationError) -> FailureClass {
+    match error {
+        NavigationError::NetworkError(reason) => classify_reason(reason),
+        NavigationError::Timeout => FailureClass::Transient,
+        NavigationError::InvalidUrl(_) => FailureClass::Permanent,
+        NavigationError::UnsupportedProtocol(_) => FailureClass::Permanent,
+        NavigationError::SslError(_) => FailureClass::Permanent,
+        NavigationError::FileNotFound(_) => FailureClass::Permanent,
+        NavigationError::RedirectLoop => FailureClass::Permanent,
+        NavigationError::Offline => FailureClass::Permanent,
+    }
+}
+
+/// Describe a network stack error as a navigation error
+///
+/// # Arguments
+///
+/// * `error` - Error returned by the network stack
+pub fn navigation_error(error: &network_stack::Error) -> NavigationError {
+    match error {
+        network_stack::Error::Timeout => NavigationError::Timeout,
+        network_stack::Error::InvalidUrl(url) => NavigationError::InvalidUrl(url.clone()),
+        network_stack::Error::RedirectLoop { .. } => NavigationError::RedirectLoop,
+        network_stack::Error::Offline(_) => NavigationError::Offline,
+        other => NavigationError::NetworkError(other.to_string()),
+    }
+}
+
+/// Classify a failed request by its message
+///
+/// HTTP status errors, TLS validation failures, refused connections and
+/// anything unrecognised are permanent; only known blips are transient.
+fn classify_reason(reason: &str) -> FailureClass {
+    let reason = reason.to_ascii_lowercase();
+    if TRANSIENT_MARKERS
+        .iter()
+        .any(|marker| reason.contains(marker))
+    {
+        FailureClass::Transient
+    } else {
+        FailureClass::Permanent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_types::ResourceType;
+    use std::path::PathBuf;
+
+    // ========================================
+    // Tests for network error classification
+    // ========================================
+
+    #[test]
+    fn test_classify_transient_network_errors() {
+        let transient = [
+            network_stack::Error::Timeout,
+            network_stack::Error::RequestFailed(
+                "error sending request for url (http://a.test/): connection closed before message completed"
+                    .to_string(),
+            ),
+            network_stack::Error::RequestFailed(
+                "error sending request: Connection reset by peer (os error 104)".to_string(),
+            ),
+            network_stack::Error::RequestFailed(
+                "error trying to connect: dns error: failed to lookup address information: \
+                 Temporary failure in name resolution"
+                    .to_string(),
+            ),
+            network_stack::Error::ConnectionLimit {
+                host: "chat.test:443".to_string(),
+                limit: 6,
+            },
+        ];
+        for error in &transient {
+            assert_eq!(
+                classify_network_error(error),
+                FailureClass::Transient,
+                "{}",
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_classify_permanent_network_errors() {
+        let permanent = [
+            network_stack::Error::RequestFailed("HTTP error: 404".to_string()),
+            network_stack::Error::RequestFailed(
+                "error trying to connect: invalid peer certificate: UnknownIssuer".to_string(),
+            ),
+            network_stack::Error::RequestFailed(
+                "error trying to connect: tcp connect error: Connection refused (os error 111)"
+                    .to_string(),
+            ),
+            network_stack::Error::InvalidUrl("nope".to_string()),
+            network_stack::Error::Blocked {
+                resource_type: ResourceType::Document,
+                reason: "blocked by filter".to_string(),
+            },
+            network_stack::Error::StreamInterrupted {
+                received: 10,
+                reason: "connection reset".to_string(),
+            },
+            network_stack::Error::Offline("https://example.com/".to_string()),
+        ];
+        for error in &permanent {
+            assert_eq!(
+                classify_network_error(error),
+                FailureClass::Permanent,
+                "{}",
+                error
+            );
+        }
+    }
+
+    // ========================================
+    // Tests for navigation error classification
+    // ========================================
+
+    #[test]
+    fn test_classify_navigation_errors() {
+        use FailureClass::{Permanent, Transient};
+
+        let cases = [
+            (NavigationError::Timeout, Transient),
+            (
+                NavigationError::NetworkError("Connection reset by peer".to_string()),
+                Transient,
+            ),
+            (
+                NavigationError::NetworkError("HTTP error: 500".to_string()),
+                Permanent,
+            ),
+            (NavigationError::InvalidUrl("x".to_string()), Permanent),
+            (
+                NavigationError::UnsupportedProtocol("gopher".to_string()),
+                Permanent,
+            ),
+            (NavigationError::SslError("expired".to_string()), Permanent),
+            (
+                NavigationError::FileNotFound(PathBuf::from("/missing")),
+                Permanent,
+            ),
+            (NavigationError::RedirectLoop, Permanent),
+            (NavigationError::Offline, Permanent),
+        ];
+        for (error, class) in cases {
+            assert_eq!(classify_navigation_error(&error), class, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn test_navigation_error_from_network_error() {
+        assert_eq!(
+            navigation_error(&network_stack::Error::Timeout),
+            NavigationError::Timeout
+        );
+        assert_eq!(
+            navigation_error(&network_stack::Error::RedirectLoop {
+                url: "https://example.com/".to_string(),
+                redirects: 3,
+            }),
+            NavigationError::RedirectLoop
+        );
+        assert_eq!(
+            navigation_error(&network_stack::Error::Offline(
+                "https://example.com/".to_string()
+            )),
+            NavigationError::Offline
+        );
+        let error = navigation_error(&network_stack::Error::RequestFailed(
+            "HTTP error: 404".to_string(),
+        ));
+        assert!(matches!(error, NavigationError::NetworkError(ref msg) if msg.contains("404")));
+        assert_eq!(FailureClass::Transient.as_str(), "transient");
+    }
+}
@@ -0,0 +1,230 @@
+//! Find in page over fetched documents
+//!
+//! The engine has no DOM, so it searches the text of a tab's last document
+//! as fetched: the markup outside tags, comments, and the `<title>`,
+//! `<script>` and `<style>` elements. Character references are not decoded
+//! and a match never spans a tag. A webview searches the rendered page
+//! itself (see `webview_integration::find`).
+
+use shared_types::FindOptions;
+use std::ops::Range;
+
+/// Matches of a find in page query, with the highlighted one
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FindMatches {
+    /// Byte offset of each match in the document, in document order
+    pub offsets: Vec<usize>,
+    /// Index in `offsets` of the highlighted match; `None` if nothing
+    /// matched
+    pub active: Option<usize>,
+}
+
+impl FindMatches {
+    /// Matches highlighting the first one
+    ///
+    /// # Arguments
+    ///
+    /// * `offsets` - Byte offsets of the matches, in document order
+    pub fn new(offsets: Vec<usize>) -> Self {
+        let active = (!offsets.is_empty()).then_some(0);
+        Self { offsets, active }
+    }
+
+    /// Number of matches
+    pub fn total(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Position of the highlighted match counting from 1, as in "3 of 17";
+    /// 0 if nothing matched
+    pub fn current(&self) -> usize {
+        self.active.map_or(0, |index| index + 1)
+    }
+
+    /// Highlight the next match, wrapping around after the last
+    pub fn next(&mut self) {
+        if let Some(index) = self.active {
+            self.active = Some((index + 1) % self.offsets.len());
+        }
+    }
+
+    /// Highlight the previous match, wrapping around before the first
+    pub fn previous(&mut self) {
+        if let Some(index) = self.active {
+            let len = self.offsets.len();
+            self.active = Some((index + len - 1) % len);
+        }
+    }
+}
+
+/// Find a query in the text of an HTML document
+///
+/// Matches do not overlap: after a match the search continues at its end.
+///
+/// # Arguments
+///
+/// * `document` - The document's markup
+/// * `query` - Text to find; an empty query matches nothing
+/// * `options` - Case sensitivity and whole word matching
+///
+/// # Returns
+///
+/// Returns the byte offset of each match in `document`, in order.
+pub fn find_matches(document: &str, query: &str, options: &FindOptions) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    if query.is_empty() {
+        return offsets;
+    }
+    for run in text_runs(document) {
+        let text = &document[run.clone()];
+        let mut at = 0;
+        while let Some(c) = text[at..].chars().next() {
+            let found = match_len(&text[at..], query, options.case_sensitive)
+                .map(|len| at + len)
+                .filter(|&end| !options.whole_word || is_whole_word(text, at, end));
+            match found {
+                Some(end) => {
+                    offsets.push(run.start + at);
+                    at = end;
+                }
+                None => at += c.len_utf8(),
+            }
+        }
+    }
+    offsets
+}
+
+/// Length in bytes of the start of `text` that matches `query`
+fn match_len(text: &str, query: &str, case_sensitive: bool) -> Option<usize> {
+    let mut chars = text.char_indices();
+    for wanted in query.chars() {
+        let (_, c) = chars.next()?;
+        let same = c == wanted || (!case_sensitive && c.to_lowercase().eq(wanted.to_lowercase()));
+        if !same {
+            return None;
+        }
+    }
+    Some(chars.next().map_or(text.len(), |(end, _)| end))
+}
+
+/// Whether `text[start..end]` is not part of a longer word
+fn is_whole_word(text: &str, start: usize, end: usize) -> bool {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    !text[..start].chars().next_back().is_some_and(is_word)
+        && !text[end..].chars().next().is_some_and(is_word)
+}
+
+/// Byte ranges of the text a reader sees in an HTML document
+fn text_runs(document: &str) -> Vec<Range<usize>> {
+    // ASCII lowercasing keeps byte offsets
+    let lower = document.to_ascii_lowercase();
+    let bytes = lower.as_bytes();
+    let end_of = |from: usize, pattern: &str| {
+        lower[from..]
+            .find(pattern)
+            .map_or(lower.len(), |at| from + at + pattern.len())
+    };
+
+    let mut runs = Vec::new();
+    let mut start = 0;
+    let mut at = 0;
+    while let Some(found) = lower[at..].find('<') {
+        let open = at + found;
+        // A "<" not starting a tag, e.g. "a < b", is text
+        if !bytes
+            .get(open + 1)
+            .is_some_and(|&b| b.is_ascii_alphabetic() || matches!(b, b'/' | b'!' | b'?'))
+        {
+            at = open + 1;
+            continue;
+        }
+        if open > start {
+            runs.push(start..open);
+        }
+        let end = if lower[open..].starts_with("<!--") {
+            end_of(open + 4, "-->")
+        } else {
+            let close = end_of(open, ">");
+            let name: String = lower[open + 1..]
+                .chars()
+                .take_while(char::is_ascii_alphanumeric)
+                .collect();
+            if matches!(name.as_str(), "title" | "script" | "style") {
+                let closing = end_of(close, &format!("</{}", name));
+                end_of(closing, ">")
+            } else {
+                close
+            }
+        };
+        start = end;
+        at = end;
+    }
+    if start < document.len() {
+        runs.push(start..document.len());
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "<html><head><title>Cats</title><style>.cat { color: red }</style>\
+        </head><body><p>A cat sat.</p><!-- cat --><script>var cat;</script>\
+        <p>Concatenate CATS &amp; 1 < 2 cat</p></body></html>";
+
+    fn texts<'a>(document: &'a str, offsets: &[usize], len: usize) -> Vec<&'a str> {
+        offsets.iter().map(|&at| &document[at..at + len]).collect()
+    }
+
+    #[test]
+    fn test_find_skips_markup_scripts_and_comments() {
+        let offsets = find_matches(PAGE, "cat", &FindOptions::default());
+        assert_eq!(texts(PAGE, &offsets, 3), ["cat", "cat", "CAT", "cat"]);
+        assert_eq!(offsets[0], PAGE.find("cat sat").unwrap());
+        assert!(find_matches(PAGE, "color", &FindOptions::default()).is_empty());
+        assert_eq!(
+            find_matches(PAGE, "1 < 2", &FindOptions::default()).len(),
+            1
+        );
+        assert!(find_matches(PAGE, "", &FindOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_find_options() {
+        let case_sensitive = FindOptions {
+            case_sensitive: true,
+            ..FindOptions::default()
+        };
+        let offsets = find_matches(PAGE, "cat", &case_sensitive);
+        assert_eq!(texts(PAGE, &offsets, 3), ["cat", "cat", "cat"]);
+        assert_eq!(offsets[1], PAGE.find("catenate").unwrap());
+
+        let whole_word = FindOptions {
+            whole_word: true,
+            ..FindOptions::default()
+        };
+        let offsets = find_matches(PAGE, "cat", &whole_word);
+        assert_eq!(texts(PAGE, &offsets, 3), ["cat", "cat"]);
+
+        // Case folding works beyond ASCII and offsets stay on the original
+        let text = "<p>Ärger ärger</p>";
+        let offsets = find_matches(text, "ärger", &FindOptions::default());
+        assert_eq!(texts(text, &offsets, "ärger".len()), ["Ärger", "ärger"]);
+    }
+
+    #[test]
+    fn test_find_matches_cursor_wraps() {
+        let mut matches = FindMatches::new(vec![3, 9, 20]);
+        assert_eq!((matches.current(), matches.total()), (1, 3));
+        matches.previous();
+        assert_eq!(matches.current(), 3);
+        matches.next();
+        matches.next();
+        assert_eq!(matches.current(), 2);
+
+        let mut none = FindMatches::new(Vec::new());
+        none.next();
+        assert_eq!((none.current(), none.total()), (0, 0));
+    }
+}
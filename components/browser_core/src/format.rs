@@ -0,0 +1,541 @@
+//! Locale-aware formatting of timestamps, sizes, durations and counts
+//!
+//! Internal pages and metrics displays format numbers through a
+//! [`Formatter`] instead of printing raw epoch seconds and byte counts. The
+//! formatter combines a [`Locale`] bundle (decimal and thousands separators,
+//! relative-time phrases) with the engine's [`Clock`], so "3 minutes ago"
+//! is computed from injected time and tests are deterministic.
+//!
+//! Absolute timestamps use the ISO form `YYYY-MM-DD HH:MM` in every locale,
+//! shifted by the formatter's UTC offset.
+
+use shared_types::{Clock, SystemClock};
+use std::sync::Arc;
+
+/// Seconds in a day
+const DAY_SECS: i64 = 86_400;
+
+/// How to show a timestamp
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampStyle {
+    /// Always `YYYY-MM-DD HH:MM`
+    Absolute,
+    /// "just now", "3 minutes ago", "2 hours ago" or "Yesterday 14:02",
+    /// falling back to the absolute form for anything older
+    Relative,
+}
+
+/// Which multiple of bytes a kilobyte is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteUnits {
+    /// 1 KB = 1024 bytes
+    Binary,
+    /// 1 KB = 1000 bytes
+    Decimal,
+}
+
+/// Number formats and relative-time phrases for one language
+///
+/// Phrases with a count contain `{n}`, which is replaced by the formatted
+/// number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locale {
+    /// BCP 47 tag, e.g. `en-US`
+    pub tag: &'static str,
+    /// Separator between the integer and fractional part
+    pub decimal_separator: char,
+    /// Separator between groups of three digits
+    pub thousands_separator: char,
+    /// Less than a minute ago
+    pub just_now: &'static str,
+    /// Exactly one minute ago
+    pub minute_ago: &'static str,
+    /// `{n}` minutes ago
+    pub minutes_ago: &'static str,
+    /// Exactly one hour ago
+    pub hour_ago: &'static str,
+    /// `{n}` hours ago
+    pub hours_ago: &'static str,
+    /// Prefix for a time on the previous day
+    pub yesterday: &'static str,
+}
+
+/// English (United States)
+pub const EN_US: Locale = Locale {
+    tag: "en-US",
+    decimal_separator: '.',
+    thousands_separator: ',',
+    just_now: "just now",
+    minute_ago: "1 minute ago",
+    minutes_ago: "{n} minutes ago",
+    hour_ago: "1 hour ago",
+    hours_ago: "{n} hours ago",
+    yesterday: "Yesterday",
+};
+
+/// German (Germany)
+pub const DE_DE: Locale = Locale {
+    tag: "de-DE",
+    decimal_separator: ',',
+    thousands_separator: '.',
+    just_now: "gerade eben",
+    minute_ago: "vor 1 Minute",
+    minutes_ago: "vor {n} Minuten",
+    hour_ago: "vor 1 Stunde",
+    hours_ago: "vor {n} Stunden",
+    yesterday: "Gestern",
+};
+
+/// French (France)
+pub const FR_FR: Locale = Locale {
+    tag: "fr-FR",
+    decimal_separator: ',',
+    thousands_separator: '\u{202f}',
+    just_now: "à l’instant",
+    minute_ago: "il y a 1 minute",
+    minutes_ago: "il y a {n} minutes",
+    hour_ago: "il y a 1 heure",
+    hours_ago: "il y a {n} heures",
+    yesterday: "Hier",
+};
+
+/// Every built-in locale bundle
+pub const LOCALES: &[Locale] = &[EN_US, DE_DE, FR_FR];
+
+impl Locale {
+    /// Find the bundle for a language tag
+    ///
+    /// An exact (case-insensitive) match wins, then the first bundle with
+    /// the same language, then [`EN_US`].
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - BCP 47 tag such as `de-AT` or `fr`
+    pub fn for_tag(tag: &str) -> Locale {
+        let language = |t: &str| {
+            t.split(['-', '_'])
+                .next()
+                .unwrap_or("")
+                .to_ascii_lowercase()
+        };
+        LOCALES
+            .iter()
+            .find(|locale| locale.tag.eq_ignore_ascii_case(tag))
+            .or_else(|| {
+                LOCALES
+                    .iter()
+                    .find(|locale| language(locale.tag) == language(tag))
+            })
+            .copied()
+            .unwrap_or(EN_US)
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        EN_US
+    }
+}
+
+/// Formats values for display in one locale
+#[derive(Debug, Clone)]
+pub struct Formatter {
+    /// Separators and phrases
+    locale: Locale,
+    /// Source of "now" for relative timestamps
+    clock: Arc<dyn Clock>,
+    /// Offset of local time from UTC, in seconds
+    utc_offset_secs: i64,
+    /// Kilobyte size used by [`format_bytes`](Self::format_bytes)
+    byte_units: ByteUnits,
+}
+
+impl Formatter {
+    /// Create a formatter with UTC times and binary byte units
+    ///
+    /// # Arguments
+    ///
+    /// * `locale` - Separators and phrases
+    /// * `clock` - Source of "now" for relative timestamps
+    pub fn new(locale: Locale, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            locale,
+            clock,
+            utc_offset_secs: 0,
+            byte_units: ByteUnits::Binary,
+        }
+    }
+
+    /// Show times `minutes` ahead of UTC (negative for behind)
+    pub fn with_utc_offset_minutes(mut self, minutes: i32) -> Self {
+        self.utc_offset_secs = i64::from(minutes) * 60;
+        self
+    }
+
+    /// Set the kilobyte size
+    pub fn with_byte_units(mut self, units: ByteUnits) -> Self {
+        self.byte_units = units;
+        self
+    }
+
+    /// The locale bundle
+    pub fn locale(&self) -> &Locale {
+        &self.locale
+    }
+
+    /// Current time from the formatter's clock, in Unix seconds
+    pub fn now_unix(&self) -> i64 {
+        self.clock.unix_secs() as i64
+    }
+
+    /// Format a Unix timestamp
+    ///
+    /// Relative style gives "just now" under a minute, minutes under an
+    /// hour, hours on the same day, "Yesterday HH:MM" on the previous day
+    /// and the absolute form otherwise (including timestamps in the
+    /// future).
+    ///
+    /// # Arguments
+    ///
+    /// * `unix` - Seconds since the Unix epoch
+    /// * `style` - Absolute or relative
+    pub fn format_timestamp(&self, unix: i64, style: TimestampStyle) -> String {
+        if style == TimestampStyle::Absolute {
+            return self.absolute(unix);
+        }
+
+        let now = self.now_unix();
+        let ago = now - unix;
+        let day = |t: i64| (t + self.utc_offset_secs).div_euclid(DAY_SECS);
+        match ago {
+            0..=59 => self.locale.just_now.to_string(),
+            60..=119 => self.locale.minute_ago.to_string(),
+            120..=3_599 => self.phrase(self.locale.minutes_ago, ago / 60),
+            _ if ago > 0 && day(unix) == day(now) => match ago / 3_600 {
+                1 => self.locale.hour_ago.to_string(),
+                hours => self.phrase(self.locale.hours_ago, hours),
+            },
+            _ if ago > 0 && day(unix) == day(now) - 1 => {
+                let (_, _, _, hour, minute) = self.civil(unix);
+                format!("{} {:02}:{:02}", self.locale.yesterday, hour, minute)
+            }
+            _ => self.absolute(unix),
+        }
+    }
+
+    /// Format a byte count, e.g. `999 B`, `1.5 KB`, `3.0 GB`
+    ///
+    /// Counts below one kilobyte are shown exactly; larger ones with one
+    /// decimal in the largest unit that keeps the value at least 1.
+    pub fn format_bytes(&self, bytes: u64) -> String {
+        const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+        let base = match self.byte_units {
+            ByteUnits::Binary => 1024.0,
+            ByteUnits::Decimal => 1000.0,
+        };
+        if (bytes as f64) < base {
+            return format!("{} B", self.format_count(bytes));
+        }
+
+        let mut value = bytes as f64 / base;
+        let mut unit = 0;
+        // Compare the rounded value so 1023.96 KB shows as 1.0 MB
+        while (value * 10.0).round() / 10.0 >= base && unit < UNITS.len() - 1 {
+            value /= base;
+            unit += 1;
+        }
+        format!(
+            "{} {}",
+            self.decimal((value * 10.0).round() as u64),
+            UNITS[unit]
+        )
+    }
+
+    /// Format a duration, e.g. `850 ms`, `12.5 s`, `2 min 5 s`, `1 h 5 min`
+    ///
+    /// Seconds are truncated to one decimal, so 59 999 ms is `59.9 s`.
+    pub fn format_duration_ms(&self, ms: u64) -> String {
+        let secs = ms / 1_000;
+        match secs {
+            0 => format!("{} ms", ms),
+            1..=59 => format!("{} s", self.decimal(ms / 100)),
+            60..=3_599 => Self::pair(secs / 60, "min", secs % 60, "s"),
+            3_600..=86_399 => Self::pair(secs / 3_600, "h", secs % 3_600 / 60, "min"),
+            _ => Self::pair(secs / 86_400, "d", secs % 86_400 / 3_600, "h"),
+        }
+    }
+
+    /// Format a count with thousands separators, e.g. `1,234,567`
+    pub fn format_count(&self, n: u64) -> String {
+        let digits = n.to_string();
+        let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push(self.locale.thousands_separator);
+            }
+            out.push(digit);
+        }
+        out
+    }
+
+    /// Format tenths as a number with one decimal
+    fn decimal(&self, tenths: u64) -> String {
+        format!(
+            "{}{}{}",
+            self.format_count(tenths / 10),
+            self.locale.decimal_separator,
+            tenths % 10
+        )
+    }
+
+    /// Substitute a count into a phrase
+    fn phrase(&self, template: &str, n: i64) -> String {
+        template.replace("{n}", &self.format_count(n as u64))
+    }
+
+    /// `{major} {major_unit}`, followed by the minor part unless it is zero
+    fn pair(major: u64, major_unit: &str, minor: u64, minor_unit: &str) -> String {
+        if minor == 0 {
+            format!("{} {}", major, major_unit)
+        } else {
+            format!("{} {} {} {}", major, major_unit, minor, minor_unit)
+        }
+    }
+
+    /// `YYYY-MM-DD HH:MM` in local time
+    fn absolute(&self, unix: i64) -> String {
+        let (year, month, day, hour, minute) = self.civil(unix);
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            year, month, day, hour, minute
+        )
+    }
+
+    /// Local calendar date and time of a Unix timestamp
+    fn civil(&self, unix: i64) -> (i64, u32, u32, u32, u32) {
+        let local = unix + self.utc_offset_secs;
+        let (year, month, day) = civil_from_days(local.div_euclid(DAY_SECS));
+        let secs = local.rem_euclid(DAY_SECS);
+        (
+            year,
+            month,
+            day,
+            (secs / 3_600) as u32,
+            (secs % 3_600 / 60) as u32,
+        )
+    }
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self::new(EN_US, Arc::new(SystemClock))
+    }
+}
+
+/// Proleptic Gregorian date of a day number (days since 1970-01-01)
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_types::MockClock;
+    use std::time::Duration;
+
+    /// 2026-03-10 15:00:00 UTC
+    const NOW: i64 = 1_773_154_800;
+
+    fn formatter(locale: Locale) -> Formatter {
+        Formatter::new(
+            locale,
+            Arc::new(MockClock::new(Duration::from_secs(NOW as u64))),
+        )
+    }
+
+    #[test]
+    fn test_for_tag() {
+        assert_eq!(Locale::for_tag("de-DE"), DE_DE);
+        assert_eq!(Locale::for_tag("de_AT"), DE_DE);
+        assert_eq!(Locale::for_tag("FR"), FR_FR);
+        assert_eq!(Locale::for_tag("ja-JP"), EN_US);
+        assert_eq!(Locale::for_tag(""), EN_US);
+    }
+
+    #[test]
+    fn test_format_timestamp_absolute() {
+        let en = formatter(EN_US);
+        assert_eq!(
+            en.format_timestamp(NOW, TimestampStyle::Absolute),
+            "2026-03-10 15:00"
+        );
+        assert_eq!(
+            en.format_timestamp(0, TimestampStyle::Absolute),
+            "1970-01-01 00:00"
+        );
+        assert_eq!(
+            en.format_timestamp(951_782_400, TimestampStyle::Absolute),
+            "2000-02-29 00:00"
+        );
+        assert_eq!(
+            en.clone()
+                .with_utc_offset_minutes(-90)
+                .format_timestamp(NOW, TimestampStyle::Absolute),
+            "2026-03-10 13:30"
+        );
+    }
+
+    #[test]
+    fn test_format_timestamp_relative_per_locale() {
+        let cases: &[(i64, [&str; 3])] = &[
+            (0, ["just now", "gerade eben", "à l’instant"]),
+            (59, ["just now", "gerade eben", "à l’instant"]),
+            (61, ["1 minute ago", "vor 1 Minute", "il y a 1 minute"]),
+            (
+                3 * 60,
+                ["3 minutes ago", "vor 3 Minuten", "il y a 3 minutes"],
+            ),
+            (
+                3_599,
+                ["59 minutes ago", "vor 59 Minuten", "il y a 59 minutes"],
+            ),
+            (3_600, ["1 hour ago", "vor 1 Stunde", "il y a 1 heure"]),
+            (
+                14 * 3_600,
+                ["14 hours ago", "vor 14 Stunden", "il y a 14 heures"],
+            ),
+            (
+                DAY_SECS + 58 * 60,
+                ["Yesterday 14:02", "Gestern 14:02", "Hier 14:02"],
+            ),
+            (
+                3 * DAY_SECS,
+                ["2026-03-07 15:00", "2026-03-07 15:00", "2026-03-07 15:00"],
+            ),
+            (
+                365 * DAY_SECS,
+                ["2025-03-10 15:00", "2025-03-10 15:00", "2025-03-10 15:00"],
+            ),
+        ];
+        for (i, locale) in [EN_US, DE_DE, FR_FR].into_iter().enumerate() {
+            let f = formatter(locale);
+            for (ago, expected) in cases {
+                assert_eq!(
+                    f.format_timestamp(NOW - ago, TimestampStyle::Relative),
+                    expected[i],
+                    "{} {}s ago",
+                    locale.tag,
+                    ago
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_timestamp_relative_day_boundaries() {
+        let en = formatter(EN_US);
+        // 15 hours ago is 00:00 today; 15h01m ago is yesterday 23:59
+        assert_eq!(
+            en.format_timestamp(NOW - 15 * 3_600, TimestampStyle::Relative),
+            "15 hours ago"
+        );
+        assert_eq!(
+            en.format_timestamp(NOW - 15 * 3_600 - 60, TimestampStyle::Relative),
+            "Yesterday 23:59"
+        );
+        // In the future: absolute
+        assert_eq!(
+            en.format_timestamp(NOW + 60, TimestampStyle::Relative),
+            "2026-03-10 15:01"
+        );
+        // A UTC offset moves midnight
+        assert_eq!(
+            en.with_utc_offset_minutes(10 * 60)
+                .format_timestamp(NOW - 3 * 3_600, TimestampStyle::Relative),
+            "Yesterday 22:00"
+        );
+    }
+
+    #[test]
+    fn test_format_bytes_per_locale() {
+        let cases: &[(u64, [&str; 3])] = &[
+            (0, ["0 B", "0 B", "0 B"]),
+            (999, ["999 B", "999 B", "999 B"]),
+            (1_000, ["1,000 B", "1.000 B", "1\u{202f}000 B"]),
+            (1_023, ["1,023 B", "1.023 B", "1\u{202f}023 B"]),
+            (1_024, ["1.0 KB", "1,0 KB", "1,0 KB"]),
+            (1_536, ["1.5 KB", "1,5 KB", "1,5 KB"]),
+            (1_048_575, ["1.0 MB", "1,0 MB", "1,0 MB"]),
+            (3 << 30, ["3.0 GB", "3,0 GB", "3,0 GB"]),
+            (
+                5_000 << 40,
+                ["5,000.0 TB", "5.000,0 TB", "5\u{202f}000,0 TB"],
+            ),
+        ];
+        for (i, locale) in [EN_US, DE_DE, FR_FR].into_iter().enumerate() {
+            let f = formatter(locale);
+            for (bytes, expected) in cases {
+                assert_eq!(f.format_bytes(*bytes), expected[i], "{}", locale.tag);
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_bytes_decimal_units() {
+        let f = formatter(EN_US).with_byte_units(ByteUnits::Decimal);
+        assert_eq!(f.format_bytes(999), "999 B");
+        assert_eq!(f.format_bytes(1_000), "1.0 KB");
+        assert_eq!(f.format_bytes(1_500_000), "1.5 MB");
+        assert_eq!(f.format_bytes(999_999), "1.0 MB");
+    }
+
+    #[test]
+    fn test_format_duration_ms_per_locale() {
+        let cases: &[(u64, [&str; 3])] = &[
+            (0, ["0 ms", "0 ms", "0 ms"]),
+            (999, ["999 ms", "999 ms", "999 ms"]),
+            (1_000, ["1.0 s", "1,0 s", "1,0 s"]),
+            (12_345, ["12.3 s", "12,3 s", "12,3 s"]),
+            (59_999, ["59.9 s", "59,9 s", "59,9 s"]),
+            (61_000, ["1 min 1 s", "1 min 1 s", "1 min 1 s"]),
+            (120_000, ["2 min", "2 min", "2 min"]),
+            (3_900_000, ["1 h 5 min", "1 h 5 min", "1 h 5 min"]),
+            (3 * 86_400_000, ["3 d", "3 d", "3 d"]),
+        ];
+        for (i, locale) in [EN_US, DE_DE, FR_FR].into_iter().enumerate() {
+            let f = formatter(locale);
+            for (ms, expected) in cases {
+                assert_eq!(f.format_duration_ms(*ms), expected[i], "{}", locale.tag);
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_count_per_locale() {
+        let cases: &[(u64, [&str; 3])] = &[
+            (0, ["0", "0", "0"]),
+            (999, ["999", "999", "999"]),
+            (1_000, ["1,000", "1.000", "1\u{202f}000"]),
+            (70_000, ["70,000", "70.000", "70\u{202f}000"]),
+            (
+                1_234_567,
+                ["1,234,567", "1.234.567", "1\u{202f}234\u{202f}567"],
+            ),
+        ];
+        for (i, locale) in [EN_US, DE_DE, FR_FR].into_iter().enumerate() {
+            let f = formatter(locale);
+            for (n, expected) in cases {
+                assert_eq!(f.format_count(*n), expected[i], "{}", locale.tag);
+            }
+        }
+    }
+}
@@ -0,0 +1,489 @@
+//! Bookmark and history import/export
+//!
+//! Bookmarks are exchanged in the Netscape bookmark file format that
+//! Firefox, Chrome and Safari read and write: nested `<DL>` lists of
+//! `<H3>` folders and `<A>` links, with `ADD_DATE` in Unix seconds and
+//! Firefox's `TAGS` and `SHORTCUTURL` (keyword) attributes. History is
+//! exchanged as JSON in the schema of [`HistoryFile`]:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "entries": [
+//!     {
+//!       "url": "https://example.com/",
+//!       "title": "Example",
+//!       "visit_count": 3,
+//!       "typed_count": 1,
+//!       "last_visit": 1700000000
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Reading is lenient: an entry that cannot be read is reported in the
+//! [`ImportSummary`] and the rest of the file is still imported.
+
+use crate::internal_pages::escape_html;
+use crate::types::BookmarkFolder;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+
+/// Schema version written by history export; imports accept it and older
+pub const HISTORY_FILE_VERSION: u32 = 1;
+
+/// Start of every exported bookmark file, as the major browsers write it
+const BOOKMARKS_HEADER: &str = "<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file.
+     It will be read and overwritten.
+     DO NOT EDIT! -->
+<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+";
+
+/// What an import did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// Entries added
+    pub added: usize,
+    /// History entries whose visits were added to an existing entry for
+    /// the same URL
+    pub merged: usize,
+    /// Bookmarks left out because their URL is already bookmarked
+    pub skipped: usize,
+    /// Entries that could not be read
+    pub failed: usize,
+    /// Why each failed entry was left out, and other problems that did not
+    /// stop an entry from being imported
+    pub warnings: Vec<String>,
+}
+
+impl ImportSummary {
+    /// Count a failed entry
+    pub(crate) fn fail(&mut self, warning: String) {
+        self.failed += 1;
+        self.warnings.push(warning);
+    }
+}
+
+/// History export file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryFile {
+    /// Schema version, [`HISTORY_FILE_VERSION`] when written
+    pub version: u32,
+    /// History entries, most recently visited first
+    pub entries: Vec<HistoryRecord>,
+}
+
+/// One history entry in a [`HistoryFile`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    /// URL as last visited
+    pub url: String,
+    /// Page title; may be empty
+    #[serde(default)]
+    pub title: String,
+    /// Number of visits
+    #[serde(default = "one_visit")]
+    pub visit_count: i32,
+    /// Number of those visits typed into the address bar
+    #[serde(default)]
+    pub typed_count: i32,
+    /// Last visit time (Unix seconds)
+    pub last_visit: i64,
+}
+
+fn one_visit() -> i32 {
+    1
+}
+
+/// A bookmark folder read from a bookmark file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ParsedFolder {
+    /// Folder name, from its `<H3>`; empty for the file's top level
+    pub name: String,
+    /// `ADD_DATE` of the folder
+    pub add_date: Option<i64>,
+    /// Subfolders, in file order
+    pub folders: Vec<ParsedFolder>,
+    /// Links directly in the folder, in file order
+    pub bookmarks: Vec<ParsedBookmark>,
+}
+
+/// A link read from a bookmark file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct ParsedBookmark {
+    /// `HREF`, unvalidated
+    pub href: String,
+    /// Link text
+    pub title: String,
+    /// `ADD_DATE`
+    pub add_date: Option<i64>,
+    /// `TAGS`, split on commas
+    pub tags: Vec<String>,
+    /// `SHORTCUTURL`
+    pub keyword: Option<String>,
+}
+
+/// Write a bookmark tree as a Netscape bookmark file
+///
+/// # Arguments
+///
+/// * `root` - Tree to write, from
+///   [`BrowserEngine::get_bookmarks_tree`](crate::BrowserEngine::get_bookmarks_tree)
+pub(crate) fn write_bookmarks_html(root: &BookmarkFolder) -> String {
+    let mut out = BOOKMARKS_HEADER.to_string();
+    write_folder(&mut out, root, 0);
+    out
+}
+
+fn write_folder(out: &mut String, folder: &BookmarkFolder, depth: usize) {
+    let indent = "    ".repeat(depth);
+    let _ = writeln!(out, "{}<DL><p>", indent);
+    for child in &folder.folders {
+        let _ = writeln!(
+            out,
+            "{}    <DT><H3>{}</H3>",
+            indent,
+            escape_html(&child.name)
+        );
+        write_folder(out, child, depth + 1);
+    }
+    for bookmark in &folder.bookmarks {
+        let _ = write!(
+            out,
+            "{}    <DT><A HREF=\"{}\" ADD_DATE=\"{}\"",
+            indent,
+            escape_html(&bookmark.url),
+            bookmark.created_at
+        );
+        if !bookmark.tags.is_empty() {
+            let _ = write!(out, " TAGS=\"{}\"", escape_html(&bookmark.tags.join(",")));
+        }
+        if let Some(keyword) = &bookmark.keyword {
+            let _ = write!(out, " SHORTCUTURL=\"{}\"", escape_html(keyword));
+        }
+        let _ = writeln!(out, ">{}</A>", escape_html(&bookmark.title));
+    }
+    let _ = writeln!(out, "{}</DL><p>", indent);
+}
+
+/// Read a Netscape bookmark file
+///
+/// Anything but folders and links is ignored, and unclosed lists are
+/// closed at the end of the file, so partial or hand-edited files still
+/// yield what they hold.
+///
+/// # Arguments
+///
+/// * `html` - File contents
+/// * `summary` - Receives a failure for each link without an `HREF`
+///
+/// # Returns
+///
+/// Returns the file's top level, holding its folders and links.
+pub(crate) fn parse_bookmarks_html(html: &str, summary: &mut ImportSummary) -> ParsedFolder {
+    // Open folders, innermost last; the first is the top level
+    let mut stack = vec![ParsedFolder::default()];
+    // A folder whose <H3> was read but whose <DL> has not started
+    let mut pending: Option<ParsedFolder> = None;
+    // For each open <DL>, whether it opened a folder
+    let mut lists: Vec<bool> = Vec::new();
+
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+        let (name, attrs) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+
+        match name.to_ascii_uppercase().as_str() {
+            "H3" => {
+                let (text, after) = element_text(rest, "H3");
+                rest = after;
+                if let Some(folder) = pending.take() {
+                    stack.last_mut().unwrap().folders.push(folder);
+                }
+                pending = Some(ParsedFolder {
+                    name: unescape(text).trim().to_string(),
+                    add_date: attribute(attrs, "ADD_DATE").and_then(|date| date.parse().ok()),
+                    ..ParsedFolder::default()
+                });
+            }
+            "A" => {
+                let (text, after) = element_text(rest, "A");
+                rest = after;
+                let title = unescape(text).trim().to_string();
+                let Some(href) = attribute(attrs, "HREF") else {
+                    summary.fail(format!("Bookmark \"{}\" has no HREF", title));
+                    continue;
+                };
+                let tags = attribute(attrs, "TAGS").unwrap_or_default();
+                stack.last_mut().unwrap().bookmarks.push(ParsedBookmark {
+                    href,
+                    title,
+                    add_date: attribute(attrs, "ADD_DATE").and_then(|date| date.parse().ok()),
+                    tags: tags
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|tag| !tag.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                    keyword: attribute(attrs, "SHORTCUTURL").filter(|k| !k.trim().is_empty()),
+                });
+            }
+            "DL" => {
+                let folder = pending.take();
+                lists.push(folder.is_some());
+                stack.extend(folder);
+            }
+            "/DL" => {
+                let closes_folder = lists.pop() == Some(true);
+                if closes_folder {
+                    let folder = stack.pop().unwrap();
+                    stack.last_mut().unwrap().folders.push(folder);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // A folder with no list is empty
+    if let Some(folder) = pending {
+        stack.last_mut().unwrap().folders.push(folder);
+    }
+    while stack.len() > 1 {
+        let folder = stack.pop().unwrap();
+        stack.last_mut().unwrap().folders.push(folder);
+    }
+    stack.pop().unwrap()
+}
+
+/// Text of an element up to the next tag, and what follows its closing tag
+fn element_text<'a>(html: &'a str, name: &str) -> (&'a str, &'a str) {
+    let (text, rest) = html.split_at(html.find('<').unwrap_or(html.len()));
+    let after_close = rest.strip_prefix("</").and_then(|close| {
+        let (tag, after) = close.split_once('>')?;
+        tag.trim().eq_ignore_ascii_case(name).then_some(after)
+    });
+    (text, after_close.unwrap_or(rest))
+}
+
+/// Value of an attribute in a tag's attribute text, entities decoded
+fn attribute(attrs: &str, name: &str) -> Option<String> {
+    let mut rest = attrs.trim_start();
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let found = &rest[..name_end];
+        rest = rest[name_end..].trim_start();
+
+        let mut value = None;
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (raw, remaining) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let body = &after[1..];
+                    let end = body.find(quote).unwrap_or(body.len());
+                    (&body[..end], body.get(end + 1..).unwrap_or(""))
+                }
+                _ => after.split_at(after.find(char::is_whitespace).unwrap_or(after.len())),
+            };
+            value = Some(raw);
+            rest = remaining.trim_start();
+        }
+        if found.eq_ignore_ascii_case(name) {
+            return Some(unescape(value.unwrap_or("")));
+        }
+    }
+    None
+}
+
+/// Decode HTML character references
+///
+/// Named references other than the few exporters use are left as they are.
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                "nbsp" => '\u{a0}',
+                reference => {
+                    let number = reference.strip_prefix('#')?;
+                    let code = match number.strip_prefix(['x', 'X']) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => number.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end + 1))
+        });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Read a history export file
+///
+/// # Arguments
+///
+/// * `json` - File contents
+/// * `summary` - Receives a failure for each entry that cannot be read
+///
+/// # Returns
+///
+/// Returns the readable entries in file order.
+///
+/// # Errors
+///
+/// Returns why the file as a whole cannot be read: it is not JSON, lacks
+/// the `version` or `entries` fields, or is from a newer schema.
+pub(crate) fn parse_history_json(
+    json: &str,
+    summary: &mut ImportSummary,
+) -> std::result::Result<Vec<HistoryRecord>, String> {
+    #[derive(Deserialize)]
+    struct RawFile {
+        version: u32,
+        entries: Vec<serde_json::Value>,
+    }
+
+    let file: RawFile = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    if file.version > HISTORY_FILE_VERSION {
+        return Err(format!(
+            "schema version {} is newer than {}",
+            file.version, HISTORY_FILE_VERSION
+        ));
+    }
+    Ok(file
+        .entries
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, entry)| match serde_json::from_value(entry) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                summary.fail(format!("History entry {}: {}", i + 1, e));
+                None
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Shaped like a Firefox export
+    const FIREFOX_EXPORT: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks Menu</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1600000000" LAST_MODIFIED="1600000100">Mozilla &amp; Friends</H3>
+    <DL><p>
+        <DT><A HREF="https://www.mozilla.org/?a=1&amp;b=2" ADD_DATE="1600000050"
+            TAGS="web,Docs" SHORTCUTURL="moz">Mozilla</A>
+        <DT><H3>Empty</H3>
+        <DL><p>
+        </DL><p>
+    </DL><p>
+    <DT><a href='https://example.com/'>Caf&#233; &#x2615;</a>
+    <DT><A ADD_DATE="1">No link</A>
+</DL>
+"#;
+
+    #[test]
+    fn test_parse_bookmarks_html_reads_folders_and_attributes() {
+        let mut summary = ImportSummary::default();
+        let root = parse_bookmarks_html(FIREFOX_EXPORT, &mut summary);
+
+        assert_eq!(summary.failed, 1);
+        assert_eq!(summary.warnings, vec!["Bookmark \"No link\" has no HREF"]);
+        assert_eq!(root.bookmarks.len(), 1);
+        assert_eq!(root.bookmarks[0].href, "https://example.com/");
+        assert_eq!(root.bookmarks[0].title, "Café ☕");
+        assert_eq!(root.bookmarks[0].add_date, None);
+
+        assert_eq!(root.folders.len(), 1);
+        let folder = &root.folders[0];
+        assert_eq!(folder.name, "Mozilla & Friends");
+        assert_eq!(folder.add_date, Some(1600000000));
+        assert_eq!(
+            folder.bookmarks,
+            vec![ParsedBookmark {
+                href: "https://www.mozilla.org/?a=1&b=2".to_string(),
+                title: "Mozilla".to_string(),
+                add_date: Some(1600000050),
+                tags: vec!["web".to_string(), "Docs".to_string()],
+                keyword: Some("moz".to_string()),
+            }]
+        );
+        assert_eq!(folder.folders.len(), 1);
+        assert_eq!(folder.folders[0].name, "Empty");
+        assert!(folder.folders[0].bookmarks.is_empty());
+    }
+
+    #[test]
+    fn test_parse_bookmarks_html_keeps_unclosed_folders() {
+        let mut summary = ImportSummary::default();
+        let root = parse_bookmarks_html(
+            "<DL><DT><H3>Outer</H3><DL><DT><A HREF=\"https://a.example/\">A",
+            &mut summary,
+        );
+        assert_eq!(summary, ImportSummary::default());
+        assert_eq!(root.folders[0].name, "Outer");
+        assert_eq!(root.folders[0].bookmarks[0].title, "A");
+        assert_eq!(
+            parse_bookmarks_html("not bookmarks at all", &mut summary),
+            ParsedFolder::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_history_json_skips_bad_entries() {
+        let mut summary = ImportSummary::default();
+        let records = parse_history_json(
+            r#"{"version": 1, "entries": [
+                {"url": "https://a.example/", "last_visit": 5},
+                {"title": "no url", "last_visit": 6},
+                {"url": "https://b.example/", "title": "B", "visit_count": 4,
+                 "typed_count": 2, "last_visit": 7}
+            ]}"#,
+            &mut summary,
+        )
+        .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].visit_count, 1);
+        assert_eq!(records[0].title, "");
+        assert_eq!(records[1].typed_count, 2);
+        assert_eq!(summary.failed, 1);
+        assert!(summary.warnings[0].starts_with("History entry 2:"));
+
+        assert!(parse_history_json("[]", &mut summary).is_err());
+        assert!(parse_history_json(r#"{"version": 2, "entries": []}"#, &mut summary).is_err());
+    }
+}
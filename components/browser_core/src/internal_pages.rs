@@ -0,0 +1,1042 @@
+//! Templating helpers for internal (privileged) browser pages
+//!
+//! Error pages and `about:` pages are rendered by the browser itself and
+//! frequently interpolate untrusted strings such as URLs, page titles and
+//! error details. Everything in this module is built around [`SafeHtml`],
+//! a markup fragment that can only be constructed from static trusted
+//! markup or by escaping untrusted input for the position it lands in.
+//!
+//! Every page rendered through [`InternalPage`] carries a strict Content
+//! Security Policy ([`INTERNAL_PAGE_CSP`]) in a `<meta>` tag, and exposes the
+//! same policy via [`InternalPage::csp_header`] to be sent as a response
+//! header too: `WebViewWrapper::load_internal_page` serves the page through
+//! the webview's document protocol with it. Scripts are forbidden unless the
+//! page explicitly opts in with a nonce via [`InternalPage::with_script`].
+
+use base64::Engine as _;
+use shared_types::SubscriptionId;
+
+/// Content Security Policy applied to every internal page
+pub const INTERNAL_PAGE_CSP: &str = "default-src 'none'; style-src 'unsafe-inline'";
+
+/// Internal URL understood by the navigation layer as "go back one entry"
+pub const GO_BACK_URL: &str = "about:back";
+
+/// Internal URL understood by the navigation layer as "reload current page"
+pub const RELOAD_URL: &str = "about:reload";
+
+/// Internal URL of the "resubmit this form?" confirmation action; the
+/// shell answers it with `BrowserEngine::resubmit`
+pub const RESUBMIT_URL: &str = "about:resubmit-confirm";
+
+/// Internal URL understood by the navigation layer as "clear this origin's
+/// site data"; built with [`storage_clear_url`]
+pub const STORAGE_CLEAR_URL: &str = "about:storage-clear";
+
+/// Internal URL understood by the navigation layer as "purge from the HTTP
+/// cache"; built with [`cache_purge_url`] or [`cache_purge_host_url`]
+pub const CACHE_PURGE_URL: &str = "about:cache-purge";
+
+/// Internal URL understood by the navigation layer as "enable or disable a
+/// filter list subscription"; built with [`adblock_toggle_url`]
+pub const ADBLOCK_TOGGLE_URL: &str = "about:adblock-toggle";
+
+/// Internal URL understood by the navigation layer as "refresh the filter
+/// list subscriptions"; built with [`adblock_update_url`]
+pub const ADBLOCK_UPDATE_URL: &str = "about:adblock-update";
+
+/// Internal URL understood by the navigation layer as "apply a bulk action
+/// to the selected bookmarks"; the `about:bookmarks` form submits to it and
+/// [`bookmarks_action_url`] builds it
+pub const BOOKMARKS_ACTION_URL: &str = "about:bookmarks-action";
+
+/// Internal page showing the certificate chain a site presented; built with
+/// [`certificate_page_url`]
+pub const CERTIFICATE_PAGE_URL: &str = "about:certificate";
+
+/// Internal page showing one certificate (or a whole chain) as PEM for
+/// saving; built with [`certificate_pem_url`]
+pub const CERTIFICATE_PEM_URL: &str = "about:certificate-pem";
+
+/// URL substituted for links whose scheme is not safe to render
+const INERT_URL: &str = "about:blank";
+
+/// Schemes that may appear in `href`/`src` positions of internal pages
+const SAFE_URL_SCHEMES: &[&str] = &["http", "https", "file", "about"];
+
+/// Escape text for an HTML body position
+///
+/// # Arguments
+///
+/// * `input` - Untrusted text
+///
+/// # Returns
+///
+/// Returns the text with `&`, `<`, `>`, `"` and `'` replaced by entities.
+pub fn escape_html(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#x27;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escape text for a quoted HTML attribute position
+///
+/// In addition to the body escapes, backticks, `=` and control characters
+/// are encoded so the value cannot terminate an unquoted attribute either.
+///
+/// # Arguments
+///
+/// * `input` - Untrusted attribute value
+///
+/// # Returns
+///
+/// Returns the escaped attribute value (without surrounding quotes).
+pub fn escape_attr(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#x27;"),
+            '`' => out.push_str("&#x60;"),
+            '=' => out.push_str("&#x3D;"),
+            c if c.is_control() => out.push_str(&format!("&#x{:X};", c as u32)),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Sanitize a URL for an `href`/`src` position
+///
+/// URLs that fail to parse, or whose scheme is not in the internal-page
+/// allowlist (`javascript:`, `data:`, `vbscript:` ...), are replaced with
+/// `about:blank`. The result is attribute-escaped.
+///
+/// # Arguments
+///
+/// * `input` - Untrusted URL
+///
+/// # Returns
+///
+/// Returns an attribute-safe URL string.
+pub fn escape_url(input: &str) -> String {
+    match url::Url::parse(input.trim()) {
+        Ok(parsed) if SAFE_URL_SCHEMES.contains(&parsed.scheme()) => escape_attr(parsed.as_str()),
+        _ => INERT_URL.to_string(),
+    }
+}
+
+/// Build the action link that clears an origin's site data
+///
+/// # Arguments
+///
+/// * `origin` - Origin to clear (untrusted)
+///
+/// # Returns
+///
+/// Returns a [`STORAGE_CLEAR_URL`] link with the origin query-encoded.
+pub fn storage_clear_url(origin: &str) -> String {
+    let encoded: String = url::form_urlencoded::byte_serialize(origin.as_bytes()).collect();
+    format!("{}?origin={}", STORAGE_CLEAR_URL, encoded)
+}
+
+/// Extract the origin from a [`storage_clear_url`] link
+///
+/// # Returns
+///
+/// Returns `None` if the URL is not a storage-clear action or has no origin.
+pub fn parse_storage_clear_url(url: &url::Url) -> Option<String> {
+    if url.scheme() != "about" || url.path() != "storage-clear" {
+        return None;
+    }
+    url.query_pairs()
+        .find(|(key, _)| key == "origin")
+        .map(|(_, value)| value.into_owned())
+        .filter(|origin| !origin.is_empty())
+}
+
+/// Target of a [`CACHE_PURGE_URL`] action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CachePurge {
+    /// Purge a single cached URL
+    Url(String),
+    /// Purge every cached URL on a host
+    Host(String),
+}
+
+/// Build the action link that purges one URL from the HTTP cache
+///
+/// # Arguments
+///
+/// * `url` - Cached URL to purge (untrusted)
+///
+/// # Returns
+///
+/// Returns a [`CACHE_PURGE_URL`] link with the URL query-encoded.
+pub fn cache_purge_url(url: &str) -> String {
+    let encoded: String = url::form_urlencoded::byte_serialize(url.as_bytes()).collect();
+    format!("{}?url={}", CACHE_PURGE_URL, encoded)
+}
+
+/// Build the action link that purges a whole host from the HTTP cache
+///
+/// # Arguments
+///
+/// * `host` - Host to purge (untrusted)
+///
+/// # Returns
+///
+/// Returns a [`CACHE_PURGE_URL`] link with the host query-encoded.
+pub fn cache_purge_host_url(host: &str) -> String {
+    let encoded: String = url::form_urlencoded::byte_serialize(host.as_bytes()).collect();
+    format!("{}?host={}", CACHE_PURGE_URL, encoded)
+}
+
+/// Extract the target from a [`cache_purge_url`] / [`cache_purge_host_url`] link
+///
+/// # Returns
+///
+/// Returns `None` if the URL is not a cache-purge action or has no target.
+pub fn parse_cache_purge_url(url: &url::Url) -> Option<CachePurge> {
+    if url.scheme() != "about" || url.path() != "cache-purge" {
+        return None;
+    }
+    url.query_pairs()
+        .filter(|(_, value)| !value.is_empty())
+        .find_map(|(key, value)| match key.as_ref() {
+            "url" => Some(CachePurge::Url(value.into_owned())),
+            "host" => Some(CachePurge::Host(value.into_owned())),
+            _ => None,
+        })
+}
+
+/// Build the action link that enables or disables a filter list subscription
+///
+/// # Arguments
+///
+/// * `id` - Subscription to change
+/// * `enabled` - New state
+///
+/// # Returns
+///
+/// Returns an [`ADBLOCK_TOGGLE_URL`] link.
+pub fn adblock_toggle_url(id: SubscriptionId, enabled: bool) -> String {
+    format!(
+        "{}?id={}&enabled={}",
+        ADBLOCK_TOGGLE_URL,
+        id,
+        u8::from(enabled)
+    )
+}
+
+/// Extract the subscription and state from an [`adblock_toggle_url`] link
+///
+/// # Returns
+///
+/// Returns `None` if the URL is not a toggle action or is malformed.
+pub fn parse_adblock_toggle_url(url: &url::Url) -> Option<(SubscriptionId, bool)> {
+    if url.scheme() != "about" || url.path() != "adblock-toggle" {
+        return None;
+    }
+    let value = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+    let id = value("id")?.parse().ok()?;
+    let enabled = match value("enabled")?.as_str() {
+        "1" => true,
+        "0" => false,
+        _ => return None,
+    };
+    Some((id, enabled))
+}
+
+/// Build the action link that refreshes the filter list subscriptions
+///
+/// # Arguments
+///
+/// * `force` - Refresh every list, ignoring their `Expires` intervals
+///
+/// # Returns
+///
+/// Returns an [`ADBLOCK_UPDATE_URL`] link.
+pub fn adblock_update_url(force: bool) -> String {
+    if force {
+        format!("{}?force=1", ADBLOCK_UPDATE_URL)
+    } else {
+        ADBLOCK_UPDATE_URL.to_string()
+    }
+}
+
+/// Extract the `force` flag from an [`adblock_update_url`] link
+///
+/// # Returns
+///
+/// Returns `None` if the URL is not an update action.
+pub fn parse_adblock_update_url(url: &url::Url) -> Option<bool> {
+    if url.scheme() != "about" || url.path() != "adblock-update" {
+        return None;
+    }
+    Some(
+        url.query_pairs()
+            .any(|(key, value)| key == "force" && value == "1"),
+    )
+}
+
+/// Bulk action submitted from `about:bookmarks`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BookmarksAction {
+    /// Delete the bookmarks
+    Delete {
+        /// Selected bookmark ids
+        ids: Vec<i64>,
+    },
+    /// Add tags to the bookmarks
+    AddTags {
+        /// Selected bookmark ids
+        ids: Vec<i64>,
+        /// Tags to add
+        tags: Vec<String>,
+    },
+    /// Remove tags from the bookmarks
+    RemoveTags {
+        /// Selected bookmark ids
+        ids: Vec<i64>,
+        /// Tags to remove
+        tags: Vec<String>,
+    },
+    /// Move the bookmarks to a folder
+    Move {
+        /// Selected bookmark ids
+        ids: Vec<i64>,
+        /// Destination folder; `None` leaves them unfiled
+        folder: Option<String>,
+    },
+    /// Open every bookmark in a folder as background tabs
+    OpenFolder {
+        /// Folder to open
+        folder: String,
+    },
+}
+
+/// Build the link the `about:bookmarks` form submits for an action
+///
+/// Tags are joined with commas, the way the page's tag field takes them.
+///
+/// # Arguments
+///
+/// * `action` - Action and selected bookmarks
+///
+/// # Returns
+///
+/// Returns a [`BOOKMARKS_ACTION_URL`] link.
+pub fn bookmarks_action_url(action: &BookmarksAction) -> String {
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+    let ids: &[i64] = match action {
+        BookmarksAction::Delete { ids } => {
+            query.append_pair("op", "delete");
+            ids
+        }
+        BookmarksAction::AddTags { ids, tags } => {
+            query
+                .append_pair("op", "tag")
+                .append_pair("tags", &tags.join(","));
+            ids
+        }
+        BookmarksAction::RemoveTags { ids, tags } => {
+            query
+                .append_pair("op", "untag")
+                .append_pair("tags", &tags.join(","));
+            ids
+        }
+        BookmarksAction::Move { ids, folder } => {
+            query
+                .append_pair("op", "move")
+                .append_pair("folder", folder.as_deref().unwrap_or(""));
+            ids
+        }
+        BookmarksAction::OpenFolder { folder } => {
+            query
+                .append_pair("op", "open")
+                .append_pair("folder", folder);
+            &[]
+        }
+    };
+    for id in ids {
+        query.append_pair("id", &id.to_string());
+    }
+    format!("{}?{}", BOOKMARKS_ACTION_URL, query.finish())
+}
+
+/// Extract the action from a [`bookmarks_action_url`] link or form submission
+///
+/// Ids that are not numbers are dropped, as are empty tags.
+///
+/// # Returns
+///
+/// Returns `None` if the URL is not a bookmarks action or has an unknown
+/// `op`.
+pub fn parse_bookmarks_action_url(url: &url::Url) -> Option<BookmarksAction> {
+    if url.scheme() != "about" || url.path() != "bookmarks-action" {
+        return None;
+    }
+    let value = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.trim().to_string())
+            .unwrap_or_default()
+    };
+    let ids: Vec<i64> = url
+        .query_pairs()
+        .filter(|(key, _)| key == "id")
+        .filter_map(|(_, value)| value.parse().ok())
+        .collect();
+    let tags = || -> Vec<String> {
+        value("tags")
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+
+    match value("op").as_str() {
+        "delete" => Some(BookmarksAction::Delete { ids }),
+        "tag" => Some(BookmarksAction::AddTags { ids, tags: tags() }),
+        "untag" => Some(BookmarksAction::RemoveTags { ids, tags: tags() }),
+        "move" => {
+            let folder = Some(value("folder")).filter(|folder| !folder.is_empty());
+            Some(BookmarksAction::Move { ids, folder })
+        }
+        "open" => Some(value("folder"))
+            .filter(|folder| !folder.is_empty())
+            .map(|folder| BookmarksAction::OpenFolder { folder }),
+        _ => None,
+    }
+}
+
+/// Internal page that offers an action URL
+///
+/// Actions change browser state, so they are only honored when followed
+/// from the page that offers them.
+///
+/// # Returns
+///
+/// Returns the page's `about:` path, e.g. `storage` for a
+/// [`STORAGE_CLEAR_URL`] link, or `None` if the URL is not such an action.
+pub fn action_source_page(url: &url::Url) -> Option<&'static str> {
+    if url.scheme() != "about" {
+        return None;
+    }
+    match url.path() {
+        "storage-clear" => Some("storage"),
+        "cache-purge" => Some("cache"),
+        "adblock-toggle" | "adblock-update" => Some("adblock"),
+        "bookmarks-action" => Some("bookmarks"),
+        _ => None,
+    }
+}
+
+/// Build the link to a host's certificate page
+///
+/// # Arguments
+///
+/// * `host` - Host whose chain to show (untrusted)
+///
+/// # Returns
+///
+/// Returns a [`CERTIFICATE_PAGE_URL`] link with the host query-encoded.
+pub fn certificate_page_url(host: &str) -> String {
+    let encoded: String = url::form_urlencoded::byte_serialize(host.as_bytes()).collect();
+    format!("{}?host={}", CERTIFICATE_PAGE_URL, encoded)
+}
+
+/// Build the link that shows a certificate as PEM
+///
+/// # Arguments
+///
+/// * `host` - Host the chain belongs to (untrusted)
+/// * `index` - Position in the chain (0 is the leaf); `None` for the whole
+///   chain
+///
+/// # Returns
+///
+/// Returns a [`CERTIFICATE_PEM_URL`] link.
+pub fn certificate_pem_url(host: &str, index: Option<usize>) -> String {
+    let encoded: String = url::form_urlencoded::byte_serialize(host.as_bytes()).collect();
+    match index {
+        Some(index) => format!("{}?host={}&index={}", CERTIFICATE_PEM_URL, encoded, index),
+        None => format!("{}?host={}", CERTIFICATE_PEM_URL, encoded),
+    }
+}
+
+/// Extract the host from a [`certificate_page_url`] or
+/// [`certificate_pem_url`] link
+///
+/// # Returns
+///
+/// Returns `None` if the URL is not a certificate page or has no host.
+pub fn parse_certificate_host(url: &url::Url) -> Option<String> {
+    if url.scheme() != "about" || !matches!(url.path(), "certificate" | "certificate-pem") {
+        return None;
+    }
+    url.query_pairs()
+        .find(|(key, _)| key == "host")
+        .map(|(_, value)| value.trim().to_ascii_lowercase())
+        .filter(|host| !host.is_empty())
+}
+
+/// Extract the chain position from a [`certificate_pem_url`] link
+///
+/// # Returns
+///
+/// Returns `None` for the whole chain, or if the URL is not a PEM link.
+pub fn parse_certificate_pem_index(url: &url::Url) -> Option<usize> {
+    if url.scheme() != "about" || url.path() != "certificate-pem" {
+        return None;
+    }
+    url.query_pairs()
+        .find(|(key, _)| key == "index")
+        .and_then(|(_, value)| value.parse().ok())
+}
+
+/// Generate a random nonce suitable for a CSP `script-src 'nonce-…'` source
+///
+/// # Returns
+///
+/// Returns a base64-encoded 128-bit nonce read from the operating
+/// system's random number generator.
+///
+/// # Panics
+///
+/// Panics if the operating system cannot provide random bytes; a
+/// guessable nonce would defeat the policy.
+pub fn generate_nonce() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("OS random number generator unavailable");
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+/// A fragment of markup that is safe to embed in an internal page
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SafeHtml(String);
+
+impl SafeHtml {
+    /// Create an empty fragment
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wrap static markup written by the browser itself
+    ///
+    /// Only accepts `&'static str` so runtime strings cannot be smuggled in
+    /// without going through an escaping constructor.
+    pub fn trusted(markup: &'static str) -> Self {
+        Self(markup.to_string())
+    }
+
+    /// Create a fragment from untrusted text (HTML body context)
+    pub fn text(input: &str) -> Self {
+        Self(escape_html(input))
+    }
+
+    /// Create an element with escaped text content
+    ///
+    /// # Arguments
+    ///
+    /// * `tag` - Static tag name (e.g. `"div"`)
+    /// * `class` - Optional static class attribute
+    /// * `content` - Inner markup
+    pub fn element(tag: &'static str, class: Option<&'static str>, content: SafeHtml) -> Self {
+        match class {
+            Some(class) => Self(format!(
+                "<{tag} class=\"{}\">{}</{tag}>",
+                escape_attr(class),
+                content.0
+            )),
+            None => Self(format!("<{tag}>{}</{tag}>", content.0)),
+        }
+    }
+
+    /// Create a link with a sanitized `href` and escaped label
+    ///
+    /// # Arguments
+    ///
+    /// * `href` - Untrusted URL (unsafe schemes become `about:blank`)
+    /// * `label` - Untrusted link text
+    /// * `class` - Optional static class attribute
+    pub fn link(href: &str, label: &str, class: Option<&'static str>) -> Self {
+        let class_attr = class
+            .map(|c| format!(" class=\"{}\"", escape_attr(c)))
+            .unwrap_or_default();
+        Self(format!(
+            "<a href=\"{}\"{}>{}</a>",
+            escape_url(href),
+            class_attr,
+            escape_html(label)
+        ))
+    }
+
+    /// Create a GET search form with an escaped current value
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - Untrusted form target URL
+    /// * `name` - Static query parameter name
+    /// * `value` - Untrusted current search text
+    pub fn search_form(action: &str, name: &'static str, value: &str) -> Self {
+        Self(format!(
+            "<form method=\"get\" action=\"{}\"><input type=\"search\" name=\"{}\" value=\"{}\"> <button type=\"submit\">Search</button></form>",
+            escape_url(action),
+            escape_attr(name),
+            escape_attr(value)
+        ))
+    }
+
+    /// Create a GET form around `content`
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - Untrusted form target URL
+    /// * `content` - Form fields and buttons
+    pub fn form(action: &str, content: SafeHtml) -> Self {
+        Self(format!(
+            "<form method=\"get\" action=\"{}\">{}</form>",
+            escape_url(action),
+            content.0
+        ))
+    }
+
+    /// Create a checkbox submitting `value` under `name` when ticked
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Static field name
+    /// * `value` - Untrusted field value
+    pub fn checkbox(name: &'static str, value: &str) -> Self {
+        Self(format!(
+            "<input type=\"checkbox\" name=\"{}\" value=\"{}\">",
+            escape_attr(name),
+            escape_attr(value)
+        ))
+    }
+
+    /// Create a text field with a placeholder
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Static field name
+    /// * `placeholder` - Static hint shown while empty
+    pub fn text_input(name: &'static str, placeholder: &'static str) -> Self {
+        Self(format!(
+            "<input type=\"text\" name=\"{}\" placeholder=\"{}\">",
+            escape_attr(name),
+            escape_attr(placeholder)
+        ))
+    }
+
+    /// Create a submit button that sends `name=value` with its form
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Static field name
+    /// * `value` - Static field value
+    /// * `label` - Static button text
+    pub fn submit_button(name: &'static str, value: &'static str, label: &'static str) -> Self {
+        Self(format!(
+            "<button type=\"submit\" name=\"{}\" value=\"{}\">{}</button>",
+            escape_attr(name),
+            escape_attr(value),
+            escape_html(label)
+        ))
+    }
+
+    /// Append another fragment
+    pub fn push(&mut self, other: SafeHtml) -> &mut Self {
+        self.0.push_str(&other.0);
+        self
+    }
+
+    /// Borrow the rendered markup
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Consume the fragment, returning the rendered markup
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+/// Builder for a complete internal HTML document
+#[derive(Debug, Clone)]
+pub struct InternalPage {
+    title: String,
+    style: &'static str,
+    body: SafeHtml,
+    script: Option<(String, &'static str)>,
+}
+
+impl InternalPage {
+    /// Create a new page with an untrusted title
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            style: "",
+            body: SafeHtml::new(),
+            script: None,
+        }
+    }
+
+    /// Set the page's static inline stylesheet
+    pub fn with_style(mut self, style: &'static str) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Set the page body
+    pub fn with_body(mut self, body: SafeHtml) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Opt into a single nonce'd inline script
+    ///
+    /// The script source must be static; dynamic data should be passed via
+    /// escaped markup (e.g. `data-` attributes) rather than interpolation.
+    pub fn with_script(mut self, nonce: String, script: &'static str) -> Self {
+        self.script = Some((nonce, script));
+        self
+    }
+
+    /// Content Security Policy for this page
+    ///
+    /// Send it as the page's `Content-Security-Policy` response header, e.g.
+    /// with `WebViewWrapper::load_internal_page`.
+    ///
+    /// # Returns
+    ///
+    /// Returns [`INTERNAL_PAGE_CSP`], extended with a `script-src` nonce
+    /// source when the page opted into a script.
+    pub fn csp_header(&self) -> String {
+        match &self.script {
+            Some((nonce, _)) => format!("{}; script-src 'nonce-{}'", INTERNAL_PAGE_CSP, nonce),
+            None => INTERNAL_PAGE_CSP.to_string(),
+        }
+    }
+
+    /// Render the page to an HTML string
+    pub fn render(&self) -> String {
+        let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n");
+        html.push_str("    <meta charset=\"utf-8\">\n");
+        html.push_str(&format!(
+            "    <meta http-equiv=\"Content-Security-Policy\" content=\"{}\">\n",
+            escape_attr(&self.csp_header())
+        ));
+        html.push_str(&format!(
+            "    <title>{}</title>\n",
+            escape_html(&self.title)
+        ));
+        if !self.style.is_empty() {
+            html.push_str(&format!("    <style>{}</style>\n", self.style));
+        }
+        html.push_str("</head>\n<body>\n");
+        html.push_str(self.body.as_str());
+        if let Some((nonce, script)) = &self.script {
+            html.push_str(&format!(
+                "\n<script nonce=\"{}\">{}</script>",
+                escape_attr(nonce),
+                script
+            ));
+        }
+        html.push_str("\n</body>\n</html>");
+        html
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADVERSARIAL: &[&str] = &[
+        "</script><script>alert(1)</script>",
+        "\" onmouseover=\"alert(1)",
+        "' onerror='alert(1)",
+        "<img src=x onerror=alert(1)>",
+        "javascript:alert(1)",
+    ];
+
+    // ========================================
+    // Tests for escaping helpers
+    // ========================================
+
+    #[test]
+    fn test_escape_html_neutralizes_tags() {
+        let escaped = escape_html("</script><b>&'\"");
+        assert_eq!(escaped, "&lt;/script&gt;&lt;b&gt;&amp;&#x27;&quot;");
+    }
+
+    #[test]
+    fn test_escape_attr_neutralizes_quotes_and_equals() {
+        let escaped = escape_attr("\" onclick=`x`");
+        assert!(!escaped.contains('"'));
+        assert!(!escaped.contains('='));
+        assert!(!escaped.contains('`'));
+    }
+
+    #[test]
+    fn test_escape_url_rejects_script_schemes() {
+        assert_eq!(escape_url("javascript:alert(1)"), "about:blank");
+        assert_eq!(escape_url("  JavaScript:alert(1)"), "about:blank");
+        assert_eq!(escape_url("vbscript:msgbox(1)"), "about:blank");
+        assert_eq!(escape_url("data:text/html,<script>"), "about:blank");
+        assert_eq!(escape_url("not a url"), "about:blank");
+    }
+
+    #[test]
+    fn test_escape_url_keeps_safe_urls() {
+        assert_eq!(escape_url("https://example.com/"), "https://example.com/");
+        assert_eq!(escape_url(GO_BACK_URL), GO_BACK_URL);
+    }
+
+    #[test]
+    fn test_escape_url_escapes_quotes() {
+        let escaped = escape_url("https://example.com/\"onmouseover=\"x");
+        assert!(!escaped.contains('"'));
+    }
+
+    #[test]
+    fn test_generate_nonce_is_unique() {
+        let a = generate_nonce();
+        let b = generate_nonce();
+        assert!(!a.is_empty());
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_storage_clear_url_round_trip() {
+        let origin = "https://example.com:8443";
+        let link = storage_clear_url(origin);
+        assert!(link.starts_with(STORAGE_CLEAR_URL));
+
+        let parsed = url::Url::parse(&link).unwrap();
+        assert_eq!(parse_storage_clear_url(&parsed).as_deref(), Some(origin));
+
+        // Survives attribute escaping in a rendered link
+        let rendered = escape_url(&link);
+        assert!(rendered.starts_with(STORAGE_CLEAR_URL));
+
+        let other = url::Url::parse("about:storage").unwrap();
+        assert_eq!(parse_storage_clear_url(&other), None);
+        let empty = url::Url::parse(STORAGE_CLEAR_URL).unwrap();
+        assert_eq!(parse_storage_clear_url(&empty), None);
+    }
+
+    #[test]
+    fn test_cache_purge_url_round_trip() {
+        let target = "https://example.com/a?b=c&d=e";
+        let parsed = url::Url::parse(&cache_purge_url(target)).unwrap();
+        assert_eq!(
+            parse_cache_purge_url(&parsed),
+            Some(CachePurge::Url(target.to_string()))
+        );
+
+        let parsed = url::Url::parse(&cache_purge_host_url("example.com")).unwrap();
+        assert_eq!(
+            parse_cache_purge_url(&parsed),
+            Some(CachePurge::Host("example.com".to_string()))
+        );
+
+        let other = url::Url::parse("about:cache?url=x").unwrap();
+        assert_eq!(parse_cache_purge_url(&other), None);
+        let empty = url::Url::parse("about:cache-purge?url=").unwrap();
+        assert_eq!(parse_cache_purge_url(&empty), None);
+    }
+
+    #[test]
+    fn test_certificate_urls_round_trip() {
+        let page = url::Url::parse(&certificate_page_url("Example.COM")).unwrap();
+        assert_eq!(page.as_str(), "about:certificate?host=Example.COM");
+        assert_eq!(
+            parse_certificate_host(&page),
+            Some("example.com".to_string())
+        );
+        assert_eq!(parse_certificate_pem_index(&page), None);
+
+        let pem = url::Url::parse(&certificate_pem_url("a&b.test", Some(2))).unwrap();
+        assert_eq!(parse_certificate_host(&pem), Some("a&b.test".to_string()));
+        assert_eq!(parse_certificate_pem_index(&pem), Some(2));
+        let whole = url::Url::parse(&certificate_pem_url("a.test", None)).unwrap();
+        assert_eq!(parse_certificate_pem_index(&whole), None);
+
+        let other = url::Url::parse("about:cache?host=a.test").unwrap();
+        assert_eq!(parse_certificate_host(&other), None);
+        let empty = url::Url::parse("about:certificate?host=").unwrap();
+        assert_eq!(parse_certificate_host(&empty), None);
+    }
+
+    #[test]
+    fn test_adblock_action_urls_round_trip() {
+        let parsed = url::Url::parse(&adblock_toggle_url(7, false)).unwrap();
+        assert_eq!(parse_adblock_toggle_url(&parsed), Some((7, false)));
+        let parsed = url::Url::parse(&adblock_toggle_url(0, true)).unwrap();
+        assert_eq!(parse_adblock_toggle_url(&parsed), Some((0, true)));
+        let bad = url::Url::parse("about:adblock-toggle?id=x&enabled=1").unwrap();
+        assert_eq!(parse_adblock_toggle_url(&bad), None);
+
+        let parsed = url::Url::parse(&adblock_update_url(true)).unwrap();
+        assert_eq!(parse_adblock_update_url(&parsed), Some(true));
+        let parsed = url::Url::parse(&adblock_update_url(false)).unwrap();
+        assert_eq!(parse_adblock_update_url(&parsed), Some(false));
+        let other = url::Url::parse("about:adblock").unwrap();
+        assert_eq!(parse_adblock_update_url(&other), None);
+    }
+
+    #[test]
+    fn test_bookmarks_action_urls_round_trip() {
+        let actions = [
+            BookmarksAction::Delete { ids: vec![1, 2] },
+            BookmarksAction::AddTags {
+                ids: vec![3],
+                tags: vec!["rust".to_string(), "to read".to_string()],
+            },
+            BookmarksAction::RemoveTags {
+                ids: vec![4],
+                tags: vec!["old".to_string()],
+            },
+            BookmarksAction::Move {
+                ids: vec![5, 6],
+                folder: Some("Work/Docs".to_string()),
+            },
+            BookmarksAction::Move {
+                ids: vec![7],
+                folder: None,
+            },
+            BookmarksAction::OpenFolder {
+                folder: "Work/Docs".to_string(),
+            },
+        ];
+        for action in actions {
+            let parsed = url::Url::parse(&bookmarks_action_url(&action)).unwrap();
+            assert_eq!(parse_bookmarks_action_url(&parsed), Some(action));
+        }
+
+        // As submitted by the page's form: field order varies, junk is dropped
+        let submitted =
+            url::Url::parse("about:bookmarks-action?id=1&id=x&tags=a%2C+%2Cb+&folder=&op=tag&id=3")
+                .unwrap();
+        assert_eq!(
+            parse_bookmarks_action_url(&submitted),
+            Some(BookmarksAction::AddTags {
+                ids: vec![1, 3],
+                tags: vec!["a".to_string(), "b".to_string()],
+            })
+        );
+        let unknown = url::Url::parse("about:bookmarks-action?op=explode&id=1").unwrap();
+        assert_eq!(parse_bookmarks_action_url(&unknown), None);
+        let unnamed = url::Url::parse("about:bookmarks-action?op=open&folder=+").unwrap();
+        assert_eq!(parse_bookmarks_action_url(&unnamed), None);
+    }
+
+    #[test]
+    fn test_form_fields_escape_values() {
+        for input in ADVERSARIAL {
+            let checkbox = SafeHtml::checkbox("id", input).into_string();
+            assert!(checkbox.starts_with("<input type=\"checkbox\" name=\"id\" value=\""));
+            assert!(!checkbox.contains("<script"), "script injected by {input}");
+        }
+        let form = SafeHtml::form("javascript:alert(1)", SafeHtml::new()).into_string();
+        assert_eq!(form, "<form method=\"get\" action=\"about:blank\"></form>");
+    }
+
+    #[test]
+    fn test_search_form_escapes_value() {
+        for input in ADVERSARIAL {
+            let form = SafeHtml::search_form("about:cache", "q", input).into_string();
+            assert!(form.starts_with("<form method=\"get\" action=\"about:cache\">"));
+            assert!(!form.contains("<script"), "script injected by {input}");
+            assert!(
+                !form.contains("\" onmouseover"),
+                "attribute broken by {input}"
+            );
+        }
+    }
+
+    // ========================================
+    // Tests for InternalPage rendering
+    // ========================================
+
+    #[test]
+    fn test_render_includes_csp_meta() {
+        let html = InternalPage::new("Test").render();
+        assert!(html.contains("http-equiv=\"Content-Security-Policy\""));
+        assert!(html.contains("default-src &#x27;none&#x27;"));
+    }
+
+    #[test]
+    fn test_render_without_script_has_no_script_tag() {
+        let html = InternalPage::new("Test")
+            .with_body(SafeHtml::text("hello"))
+            .render();
+        assert!(!html.contains("<script"));
+        assert_eq!(InternalPage::new("Test").csp_header(), INTERNAL_PAGE_CSP);
+    }
+
+    #[test]
+    fn test_render_with_script_uses_nonce() {
+        let page = InternalPage::new("Test").with_script("abc123".to_string(), "void 0;");
+        assert!(page.csp_header().contains("script-src 'nonce-abc123'"));
+        assert!(page
+            .render()
+            .contains("<script nonce=\"abc123\">void 0;</script>"));
+    }
+
+    #[test]
+    fn test_csp_header_is_sent_with_page() {
+        let mut bus = message_bus::MessageBus::new();
+        bus.start().unwrap();
+        let mut webview = webview_integration::WebViewWrapper::new(bus.sender()).unwrap();
+        let page = InternalPage::new("Test").with_script(generate_nonce(), "void 0;");
+
+        let url = webview
+            .load_internal_page("about:test", &page.render(), &page.csp_header())
+            .unwrap();
+
+        let response = webview.document_protocol().respond(&url);
+        assert_eq!(
+            response.header("Content-Security-Policy"),
+            Some(page.csp_header().as_str())
+        );
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_adversarial_title_and_body_are_inert() {
+        for input in ADVERSARIAL {
+            let mut body = SafeHtml::element("h1", None, SafeHtml::text(input));
+            body.push(SafeHtml::link(input, input, Some("button")));
+            let html = InternalPage::new(input).with_body(body).render();
+
+            assert!(!html.contains("<script"), "script injected by {input}");
+            assert!(!html.contains("<img"), "element injected by {input}");
+            assert!(
+                !html.contains("\" onmouseover"),
+                "attribute broken by {input}"
+            );
+            assert!(!html.contains("href=\"javascript:"), "js url from {input}");
+        }
+    }
+}
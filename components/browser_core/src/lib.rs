@@ -18,7 +18,7 @@
 //! # Usage
 //!
 //! ```
-//! use browser_core::{BrowserEngine, Bookmark, HistoryEntry};
+//! use browser_core::{BrowserEngine, Bookmark, HistoryEntry, NavigationInitiator};
 //! use config_manager::Config;
 //! use network_stack::NetworkStack;
 //! use message_bus::MessageBus;
@@ -40,7 +40,9 @@
 //! let mut engine = BrowserEngine::new(config, network, bus2.sender()).unwrap();
 //!
 //! // Navigate
-//! engine.navigate(1, Url::parse("https://example.com").unwrap()).unwrap();
+//! engine
+//!     .navigate(1, Url::parse("https://example.com").unwrap(), NavigationInitiator::UserTyped)
+//!     .unwrap();
 //!
 //! // Add bookmark
 //! engine.add_bookmark(Url::parse("https://example.com").unwrap(), "Example".to_string()).unwrap();
@@ -50,16 +52,60 @@
 //! assert_eq!(bookmarks.len(), 1);
 //! ```
 
+pub mod budgets;
+pub mod canonical;
+pub mod db;
+pub mod dns_hints;
 pub mod errors;
+pub mod failure;
+pub mod find;
+pub mod format;
+pub mod import_export;
+pub mod internal_pages;
+pub mod link_preview;
+pub mod migrations;
 pub mod navigation;
+pub mod omnibox;
+pub mod progress;
+pub mod recovery;
+pub mod title;
 pub mod types;
 
 // Re-export main types for convenience
+pub use budgets::{
+    check_budgets, BudgetCheck, BudgetMetric, PageReport, SlowOrigin, SlowPageEvent,
+};
+pub use canonical::canonicalize_for_history;
+pub use db::{DbOptions, DbStats};
+pub use dns_hints::{extract_dns_hints, DnsHintScanner, DnsHints, DNS_HINT_SCAN_LIMIT};
 pub use errors::{Error, Result};
+pub use failure::{
+    classify_navigation_error, classify_network_error, FailureClass, NavigationFailure,
+};
+pub use find::{find_matches, FindMatches};
+pub use format::{ByteUnits, Formatter, Locale, TimestampStyle};
+pub use import_export::{HistoryFile, HistoryRecord, ImportSummary, HISTORY_FILE_VERSION};
+pub use internal_pages::{InternalPage, SafeHtml, INTERNAL_PAGE_CSP};
+pub use link_preview::{
+    extract_link_preview, LinkPreviewCache, LinkPreviewScanner, LINK_PREVIEW_BYTE_LIMIT,
+    LINK_PREVIEW_TTL,
+};
+pub use migrations::{migrate, Migration};
 pub use navigation::{NavigationError, NavigationState, Navigator, Protocol};
+pub use omnibox::{KeywordSuggestion, ResolvedInput, SearchEngine, UrlSuggestion, SEARCH_ENGINES};
+pub use progress::{
+    ProgressEstimator, ProgressInputs, SubresourceScanner, PROGRESS_INTERVAL, STREAMING_CAP,
+};
+pub use recovery::{open_or_recover, RecoveryReport, SalvagedTable};
+pub use shared_types::NavigationInitiator;
+pub use title::{extract_title, sanitize_title, TitleScanner, MAX_TITLE_CHARS, TITLE_GRACE_PERIOD};
 pub use types::{
-    Bookmark, BrowserEngine, BrowserMetrics, HistoryEntry, MetricsSnapshot, PerformanceMetric,
-    TestResult, TestResultDatabase, TestStatus, TestSummary,
+    Bookmark, BookmarkAllTabsReport, BookmarkFolder, BrowserEngine, BrowserMetrics, DeadBookmark,
+    DocumentLoadOutcome, DuplicateGroup, HistoryEntry, HistorySearchHandler, LinkStatus,
+    MetricsSnapshot, NavigationRequest, OpenFolderOptions, OpenFolderOutcome, PerformanceMetric,
+    SavedTab, SessionFile, StackEntry, TabCloseHandler, TabHistories, TabHistory, TestResult,
+    TestResultDatabase, TestStatus, TestSummary, OPEN_ALL_CONFIRM_THRESHOLD, RECENTLY_CLOSED_LIMIT,
+    SESSION_FILE, SESSION_FILE_VERSION,
 };
 
 #[cfg(test)]
@@ -69,4 +115,13 @@ mod tests {
         // Verify that main types are exported
         // This ensures our public API is accessible
     }
+
+    #[test]
+    fn test_engine_reads_time_from_environment() {
+        let source = include_str!("types.rs");
+        let code = &source[..source.find("#[cfg(test)]").unwrap()];
+        assert!(!code.contains("SystemTime::now()"));
+        assert!(!code.contains("Instant::now()"));
+        assert!(!code.contains("tokio::time::sleep"));
+    }
 }
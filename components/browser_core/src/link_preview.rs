@@ -0,0 +1,425 @@
+//! Link previews
+//!
+//! When a link stays hovered for the configured dwell time, the start of its
+//! target is fetched through the normal network stack (so ad blocking and
+//! the cache apply) and its Open Graph title, description and site name are
+//! picked out with the same pre-scanner that finds document titles. Only the
+//! first [`LINK_PREVIEW_BYTE_LIMIT`] bytes are read; the connection is
+//! dropped as soon as the `<head>` has been seen.
+//!
+//! Results, including targets without a usable title, are kept for
+//! [`LINK_PREVIEW_TTL`] per canonical URL so moving the pointer back and
+//! forth over a link fetches it once.
+
+use crate::canonical::canonicalize_for_history;
+use crate::title::{decode_entities, extract_title, find_tag, sanitize_title};
+use shared_types::LinkPreview;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// How much of a link's target is read for its preview
+pub const LINK_PREVIEW_BYTE_LIMIT: usize = 32 * 1024;
+
+/// How long a fetched preview is reused
+pub const LINK_PREVIEW_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// Most previews kept in a [`LinkPreviewCache`]
+pub const LINK_PREVIEW_CACHE_ENTRIES: usize = 256;
+
+/// Check whether a link may be fetched for a preview
+///
+/// Only http(s) links qualify, and only if their host is not one of
+/// `excluded_hosts` or a subdomain of one.
+///
+/// # Arguments
+///
+/// * `url` - Link target
+/// * `excluded_hosts` - Hosts from `browser.link_preview_excluded_hosts`
+pub fn is_previewable(url: &Url, excluded_hosts: &[String]) -> bool {
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    !excluded_hosts.iter().any(|excluded| {
+        let excluded = excluded.trim().trim_start_matches('.').to_ascii_lowercase();
+        !excluded.is_empty()
+            && host
+                .strip_suffix(excluded.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
+    })
+}
+
+/// Find the preview of an HTML document
+///
+/// The Open Graph title is preferred over the `<title>` element. Values
+/// are decoded and sanitized like titles.
+///
+/// # Arguments
+///
+/// * `html` - Start of the document body
+///
+/// # Returns
+///
+/// Returns `None` if the document has neither an Open Graph title nor a
+/// `<title>`.
+pub fn extract_link_preview(html: &[u8]) -> Option<LinkPreview> {
+    let lower = html.to_ascii_lowercase();
+    let mut og_title = None;
+    let mut description = None;
+    let mut site_name = None;
+
+    let mut at = 0;
+    while let Some(open) = find_tag(&lower, b"<meta", at) {
+        let Some(len) = lower[open..].iter().position(|&b| b == b'>') else {
+            break;
+        };
+        at = open + len + 1;
+        let tag = String::from_utf8_lossy(&html[open + b"<meta".len()..open + len]);
+        let attributes = parse_attributes(&tag);
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let Some(content) = attribute("content") else {
+            continue;
+        };
+        let slot = match attribute("property")
+            .or_else(|| attribute("name"))
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("og:title") => &mut og_title,
+            Some("og:description") => &mut description,
+            Some("og:site_name") => &mut site_name,
+            _ => continue,
+        };
+        if slot.is_none() {
+            let value = sanitize_title(&decode_entities(content));
+            *slot = (!value.is_empty()).then_some(value);
+        }
+    }
+
+    Some(LinkPreview {
+        title: og_title.or_else(|| extract_title(html))?,
+        description,
+        site_name,
+    })
+}
+
+/// Attributes of a tag, names lowercased, in document order
+///
+/// `tag` is the text between the tag name and the closing `>`.
+pub(crate) fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = tag;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+        let name_end = rest
+            .find(|c: char| c.is_ascii_whitespace() || c == '=' || c == '/')
+            .unwrap_or(rest.len());
+        if name_end == 0 {
+            return attributes;
+        }
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let body = &after[1..];
+                        let end = body.find(quote).unwrap_or(body.len());
+                        rest = body.get(end + 1..).unwrap_or("");
+                        &body[..end]
+                    }
+                    _ => {
+                        let end = after
+                            .find(|c: char| c.is_ascii_whitespace())
+                            .unwrap_or(after.len());
+                        rest = &after[end..];
+                        &after[..end]
+                    }
+                }
+            }
+            None => "",
+        };
+        attributes.push((name, value.to_string()));
+    }
+}
+
+/// Collects the start of a link's target while it streams in
+///
+/// Feed each chunk as it arrives until [`feed`](Self::feed) reports that
+/// enough has been read, then drop the stream and call
+/// [`finish`](Self::finish).
+#[derive(Debug, Default)]
+pub struct LinkPreviewScanner {
+    buffer: Vec<u8>,
+}
+
+impl LinkPreviewScanner {
+    /// Scanner for a new target
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the next chunk of the body
+    ///
+    /// # Returns
+    ///
+    /// Returns true once the `<head>` has ended or
+    /// [`LINK_PREVIEW_BYTE_LIMIT`] bytes have been read; further chunks
+    /// are ignored.
+    pub fn feed(&mut self, chunk: &[u8]) -> bool {
+        if self.is_done() {
+            return true;
+        }
+        let room = LINK_PREVIEW_BYTE_LIMIT - self.buffer.len();
+        self.buffer
+            .extend_from_slice(&chunk[..chunk.len().min(room)]);
+        self.is_done()
+    }
+
+    /// Number of body bytes kept so far
+    pub fn bytes_scanned(&self) -> usize {
+        self.buffer.len()
+    }
+
+    fn is_done(&self) -> bool {
+        if self.buffer.len() == LINK_PREVIEW_BYTE_LIMIT {
+            return true;
+        }
+        let lower = self.buffer.to_ascii_lowercase();
+        find_tag(&lower, b"</head", 0).is_some() || find_tag(&lower, b"<body", 0).is_some()
+    }
+
+    /// The preview found in what was read
+    pub fn finish(self) -> Option<LinkPreview> {
+        extract_link_preview(&self.buffer)
+    }
+}
+
+/// A fetched preview and when it was stored
+#[derive(Debug, Clone)]
+struct CachedPreview {
+    /// The preview; `None` if the target had no usable title
+    preview: Option<LinkPreview>,
+    /// When the preview was fetched
+    stored: Instant,
+}
+
+/// Link previews by canonical URL, each kept for a fixed time
+#[derive(Debug)]
+pub struct LinkPreviewCache {
+    entries: HashMap<Url, CachedPreview>,
+    ttl: Duration,
+}
+
+impl LinkPreviewCache {
+    /// Create an empty cache
+    ///
+    /// # Arguments
+    ///
+    /// * `ttl` - How long each preview is reused
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: HashMap::new(),
+            ttl,
+        }
+    }
+
+    /// Look up the preview of a link
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Link target; fragments and trailing slashes are ignored
+    /// * `now` - Current monotonic time
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the link has not been fetched within the TTL, and
+    /// `Some(None)` if it was fetched but had no usable title.
+    pub fn get(&self, url: &Url, now: Instant) -> Option<Option<LinkPreview>> {
+        let entry = self.entries.get(&canonicalize_for_history(url))?;
+        (now.saturating_duration_since(entry.stored) < self.ttl).then(|| entry.preview.clone())
+    }
+
+    /// Store the preview of a link
+    ///
+    /// When the cache is full, expired entries are dropped first, then the
+    /// oldest.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Link target
+    /// * `preview` - What was found; `None` if nothing usable
+    /// * `now` - Current monotonic time
+    pub fn insert(&mut self, url: &Url, preview: Option<LinkPreview>, now: Instant) {
+        let key = canonicalize_for_history(url);
+        if !self.entries.contains_key(&key) && self.entries.len() >= LINK_PREVIEW_CACHE_ENTRIES {
+            let ttl = self.ttl;
+            self.entries
+                .retain(|_, entry| now.saturating_duration_since(entry.stored) < ttl);
+            if self.entries.len() >= LINK_PREVIEW_CACHE_ENTRIES {
+                let oldest = self
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.stored)
+                    .map(|(url, _)| url.clone());
+                if let Some(oldest) = oldest {
+                    self.entries.remove(&oldest);
+                }
+            }
+        }
+        self.entries.insert(
+            key,
+            CachedPreview {
+                preview,
+                stored: now,
+            },
+        );
+    }
+
+    /// Number of cached previews, including expired ones not yet dropped
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether nothing is cached
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop every cached preview
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Default for LinkPreviewCache {
+    fn default() -> Self {
+        Self::new(LINK_PREVIEW_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_types::{Clock, MockClock};
+
+    const OG_FIXTURE: &str = r#"<!DOCTYPE html>
+<html><head>
+<title>Fallback title</title>
+<meta charset="utf-8">
+<META property="og:title" content="Rust 2024 &amp; beyond">
+<meta property='og:description' content='What changes
+  in the   new edition'/>
+<meta name=og:site_name content=Blog>
+<meta property="og:title" content="Second og:title is ignored">
+</head><body></body></html>"#;
+
+    #[test]
+    fn test_extract_open_graph_fields() {
+        let preview = extract_link_preview(OG_FIXTURE.as_bytes()).unwrap();
+        assert_eq!(preview.title, "Rust 2024 & beyond");
+        assert_eq!(
+            preview.description.as_deref(),
+            Some("What changes in the new edition")
+        );
+        assert_eq!(preview.site_name.as_deref(), Some("Blog"));
+    }
+
+    #[test]
+    fn test_extract_falls_back_to_title() {
+        let html =
+            b"<head><title>Plain page</title><meta property=\"og:title\" content=\"  \"></head>";
+        assert_eq!(
+            extract_link_preview(html),
+            Some(LinkPreview {
+                title: "Plain page".to_string(),
+                description: None,
+                site_name: None,
+            })
+        );
+
+        let untitled = b"<head><meta property=\"og:description\" content=\"Orphan\"></head>";
+        assert_eq!(extract_link_preview(untitled), None);
+        assert_eq!(extract_link_preview(b"<meta property=\"og:title\""), None);
+    }
+
+    #[test]
+    fn test_scanner_stops_at_head_or_limit() {
+        let mut scanner = LinkPreviewScanner::new();
+        assert!(!scanner.feed(b"<html><head><title>Split</ti"));
+        assert!(scanner.feed(b"tle></head><body>"));
+        assert_eq!(scanner.finish().unwrap().title, "Split");
+
+        let mut scanner = LinkPreviewScanner::new();
+        assert!(!scanner.feed(&vec![b' '; LINK_PREVIEW_BYTE_LIMIT - 1]));
+        assert!(scanner.feed(b"<title>Too late</title>"));
+        assert_eq!(scanner.bytes_scanned(), LINK_PREVIEW_BYTE_LIMIT);
+        assert_eq!(scanner.finish(), None);
+    }
+
+    #[test]
+    fn test_previewable_links() {
+        let excluded = vec!["intranet.example".to_string(), ".Bank.test".to_string()];
+        let check = |url: &str| is_previewable(&Url::parse(url).unwrap(), &excluded);
+
+        assert!(check("https://news.example/story"));
+        assert!(check("http://news.example/story"));
+        assert!(!check("ftp://news.example/file"));
+        assert!(!check("file:///etc/passwd"));
+        assert!(!check("about:config"));
+        assert!(!check("https://intranet.example/wiki"));
+        assert!(!check("https://hr.intranet.example/"));
+        assert!(!check("https://online.bank.test/"));
+        assert!(check("https://notintranet.example/"));
+    }
+
+    #[test]
+    fn test_cache_ttl() {
+        let clock = MockClock::default();
+        let mut cache = LinkPreviewCache::new(Duration::from_secs(60));
+        let url = Url::parse("https://news.example/story/#comments").unwrap();
+        let preview = extract_link_preview(OG_FIXTURE.as_bytes());
+
+        assert_eq!(cache.get(&url, clock.now_instant()), None);
+        cache.insert(&url, preview.clone(), clock.now_instant());
+        let canonical = Url::parse("https://news.example/story").unwrap();
+        assert_eq!(cache.get(&canonical, clock.now_instant()), Some(preview));
+
+        clock.advance(Duration::from_secs(59));
+        assert!(cache.get(&url, clock.now_instant()).is_some());
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(cache.get(&url, clock.now_instant()), None);
+
+        // Targets without a title are remembered too
+        let untitled = Url::parse("https://news.example/blank").unwrap();
+        cache.insert(&untitled, None, clock.now_instant());
+        assert_eq!(cache.get(&untitled, clock.now_instant()), Some(None));
+    }
+
+    #[test]
+    fn test_cache_evicts_oldest_when_full() {
+        let clock = MockClock::default();
+        let mut cache = LinkPreviewCache::default();
+        for i in 0..=LINK_PREVIEW_CACHE_ENTRIES {
+            let url = Url::parse(&format!("https://site.example/{}", i)).unwrap();
+            cache.insert(&url, None, clock.now_instant());
+            clock.advance(Duration::from_secs(1));
+        }
+
+        assert_eq!(cache.len(), LINK_PREVIEW_CACHE_ENTRIES);
+        let first = Url::parse("https://site.example/0").unwrap();
+        let second = Url::parse("https://site.example/1").unwrap();
+        assert_eq!(cache.get(&first, clock.now_instant()), None);
+        assert!(cache.get(&second, clock.now_instant()).is_some());
+    }
+}
@@ -0,0 +1,222 @@
+//! Schema migrations for the profile databases
+//!
+//! Each database has an ordered list of [`Migration`]s. [`migrate`] runs
+//! the ones not yet recorded in the database's `schema_migrations` table,
+//! in version order, and records each after it succeeds.
+//!
+//! Steps must be idempotent (`CREATE ... IF NOT EXISTS`, [`add_column`]):
+//! databases created before the table existed, and databases rebuilt by
+//! [`salvage`](crate::recovery::salvage), run every step again over tables
+//! that may already have the step's changes. A step interrupted between
+//! finishing and being recorded is simply run again on the next open.
+
+use crate::errors::{Error, Result};
+use rusqlite::Connection;
+
+/// One schema change
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    /// Position in the database's history; strictly increasing
+    pub version: u32,
+    /// What the step changes, recorded with it
+    pub description: &'static str,
+    /// Applies the change; must be safe to run again
+    pub apply: fn(&Connection) -> Result<()>,
+}
+
+/// Bring a database up to date
+///
+/// # Arguments
+///
+/// * `conn` - Database to migrate
+/// * `migrations` - Every step for this database, oldest first
+/// * `now` - Unix seconds recorded as the time each step was applied
+///
+/// # Returns
+///
+/// Returns the versions applied by this call, in order.
+///
+/// # Errors
+///
+/// Returns `Error::DatabaseError` if the versions are not strictly
+/// increasing or a step fails; the steps before it stay recorded.
+pub fn migrate(conn: &Connection, migrations: &[Migration], now: i64) -> Result<Vec<u32>> {
+    if let Some(pair) = migrations.windows(2).find(|w| w[0].version >= w[1].version) {
+        return Err(Error::DatabaseError(format!(
+            "migration {} is listed after {}",
+            pair[1].version, pair[0].version
+        )));
+    }
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    let current = schema_version(conn)?;
+
+    let mut applied = Vec::new();
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        (migration.apply)(conn).map_err(|e| {
+            Error::DatabaseError(format!(
+                "migration {} ({}) failed: {}",
+                migration.version, migration.description, e
+            ))
+        })?;
+        conn.execute(
+            "INSERT OR REPLACE INTO schema_migrations (version, description, applied_at)
+             VALUES (?1, ?2, ?3)",
+            rusqlite::params![migration.version, migration.description, now],
+        )?;
+        applied.push(migration.version);
+    }
+    Ok(applied)
+}
+
+/// Highest migration version recorded in the database; 0 if none
+pub fn schema_version(conn: &Connection) -> Result<u32> {
+    let exists: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master
+         WHERE type = 'table' AND name = 'schema_migrations'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Ok(0);
+    }
+    let version: Option<u32> =
+        conn.query_row("SELECT MAX(version) FROM schema_migrations", [], |row| {
+            row.get(0)
+        })?;
+    Ok(version.unwrap_or(0))
+}
+
+/// Whether `table` has a column named `column`
+pub fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info(\"{}\")", table))?;
+    let found = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|c| c.ok())
+        .any(|c| c == column);
+    Ok(found)
+}
+
+/// Add a column unless the table already has it
+///
+/// # Arguments
+///
+/// * `conn` - Database
+/// * `table` - Table to alter
+/// * `column` - Column name
+/// * `definition` - Type and constraints, e.g. `INTEGER NOT NULL DEFAULT 0`
+///
+/// # Returns
+///
+/// Returns `true` if the column was added.
+pub fn add_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<bool> {
+    if has_column(conn, table, column)? {
+        return Ok(false);
+    }
+    conn.execute(
+        &format!(
+            "ALTER TABLE \"{}\" ADD COLUMN {} {}",
+            table, column, definition
+        ),
+        [],
+    )?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_items(conn: &Connection) -> Result<()> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS items (id INTEGER PRIMARY KEY)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    fn add_name(conn: &Connection) -> Result<()> {
+        add_column(conn, "items", "name", "TEXT NOT NULL DEFAULT ''")?;
+        Ok(())
+    }
+
+    fn add_rank(conn: &Connection) -> Result<()> {
+        add_column(conn, "items", "rank", "INTEGER")?;
+        Ok(())
+    }
+
+    const STEPS: &[Migration] = &[
+        Migration {
+            version: 1,
+            description: "items table",
+            apply: create_items,
+        },
+        Migration {
+            version: 2,
+            description: "item names",
+            apply: add_name,
+        },
+        Migration {
+            version: 3,
+            description: "item ranks",
+            apply: add_rank,
+        },
+    ];
+
+    #[test]
+    fn test_migrate_runs_pending_steps_in_order() {
+        let conn = Connection::open_in_memory().unwrap();
+        assert_eq!(migrate(&conn, &STEPS[..2], 100).unwrap(), vec![1, 2]);
+        assert_eq!(schema_version(&conn).unwrap(), 2);
+
+        // Only the new step runs; running again does nothing
+        assert_eq!(migrate(&conn, STEPS, 200).unwrap(), vec![3]);
+        assert!(migrate(&conn, STEPS, 300).unwrap().is_empty());
+        assert!(has_column(&conn, "items", "rank").unwrap());
+
+        let recorded: Vec<(u32, String, i64)> = conn
+            .prepare(
+                "SELECT version, description, applied_at FROM schema_migrations ORDER BY version",
+            )
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(recorded[2], (3, "item ranks".to_string(), 200));
+    }
+
+    #[test]
+    fn test_migrate_is_idempotent_over_unrecorded_changes() {
+        // A database that already has the changes but no record of them,
+        // as after salvage or from before migrations were tracked
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT NOT NULL DEFAULT '');
+             INSERT INTO items (name) VALUES ('kept');",
+        )
+        .unwrap();
+
+        assert_eq!(migrate(&conn, STEPS, 100).unwrap(), vec![1, 2, 3]);
+        let name: String = conn
+            .query_row("SELECT name FROM items", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(name, "kept");
+    }
+
+    #[test]
+    fn test_migrate_rejects_unordered_steps() {
+        let conn = Connection::open_in_memory().unwrap();
+        let unordered = [STEPS[1], STEPS[0]];
+        let error = migrate(&conn, &unordered, 0).unwrap_err();
+        assert!(error.to_string().contains("migration 1 is listed after 2"));
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+    }
+}
@@ -2,12 +2,228 @@
 //!
 //! This module provides URL validation, protocol handling, and navigation state management.
 
+use crate::budgets::{BudgetMetric, SlowOrigin};
+use crate::db::DbStats;
 use crate::errors::{Error, Result};
+use crate::failure::NavigationFailure;
+use crate::format::{Formatter, TimestampStyle};
+use crate::internal_pages::{
+    adblock_toggle_url, adblock_update_url, bookmarks_action_url, cache_purge_host_url,
+    cache_purge_url, certificate_pem_url, storage_clear_url, BookmarksAction, InternalPage,
+    SafeHtml, BOOKMARKS_ACTION_URL, GO_BACK_URL, RELOAD_URL, RESUBMIT_URL,
+};
+use crate::recovery::RecoveryReport;
+use crate::types::{Bookmark, HistoryEntry};
+use config_manager::ConfigChange;
+use extension_api::ExtensionDiagnostics;
+use message_bus::Trace;
+use network_stack::{
+    CacheEntrySummary, CertChainInfo, CertValidation, CertificateInfo, ResolverStats, TabBandwidth,
+    EXPIRY_WARNING,
+};
+use shared_types::{
+    FilterListInfo, FilterListSource, FilterListSubscription, NavigationInitiator, StorageReport,
+    SubscriptionId, TabId,
+};
 use std::collections::HashSet;
 use std::path::PathBuf;
 use std::time::{Duration, Instant};
 use url::Url;
 
+/// Stylesheet for `about:version`
+const ABOUT_VERSION_STYLE: &str = r#"
+        body { font-family: Arial, sans-serif; margin: 40px; }
+        h1 { color: #333; }
+        .version { font-size: 18px; margin: 20px 0; }
+"#;
+
+/// Stylesheet for `about:storage`
+const ABOUT_STORAGE_STYLE: &str = r#"
+        body { font-family: Arial, sans-serif; margin: 40px; }
+        h1 { color: #333; }
+        .notice { background-color: #fff8e1; padding: 12px; border-radius: 4px; }
+        table { border-collapse: collapse; width: 100%; }
+        th, td { text-align: left; padding: 8px; border-bottom: 1px solid #e0e0e0; }
+        a.button { color: #d32f2f; text-decoration: none; }
+"#;
+
+/// Stylesheet for `about:cache`
+const ABOUT_CACHE_STYLE: &str = r#"
+        body { font-family: Arial, sans-serif; margin: 40px; }
+        h1 { color: #333; }
+        form { margin: 20px 0; }
+        table { border-collapse: collapse; width: 100%; }
+        th, td { text-align: left; padding: 8px; border-bottom: 1px solid #e0e0e0; }
+        td.url { font-family: monospace; word-break: break-all; }
+        a.button { color: #d32f2f; text-decoration: none; }
+"#;
+
+/// Stylesheet for `about:diagnostics`
+const ABOUT_DIAGNOSTICS_STYLE: &str = r#"
+        body { font-family: Arial, sans-serif; margin: 40px; }
+        h1 { color: #333; }
+        table { border-collapse: collapse; width: 100%; margin-bottom: 24px; }
+        th, td { text-align: left; padding: 8px; border-bottom: 1px solid #e0e0e0; }
+        .disabled { color: #757575; }
+        .panics { color: #d32f2f; }
+        .seed { color: #f57c00; }
+"#;
+
+/// Stylesheet for `about:adblock`
+const ABOUT_ADBLOCK_STYLE: &str = r#"
+        body { font-family: Arial, sans-serif; margin: 40px; }
+        h1 { color: #333; }
+        .actions { margin: 20px 0; }
+        .actions a { margin-right: 12px; color: #1a73e8; text-decoration: none; }
+        table { border-collapse: collapse; width: 100%; }
+        th, td { text-align: left; padding: 8px; border-bottom: 1px solid #e0e0e0; }
+        .url { font-family: monospace; font-size: 12px; color: #757575; word-break: break-all; }
+        .disabled { color: #757575; }
+        a.button { color: #1a73e8; text-decoration: none; }
+"#;
+
+/// Stylesheet for `about:bookmarks`
+const ABOUT_BOOKMARKS_STYLE: &str = r#"
+        body { font-family: Arial, sans-serif; margin: 40px; }
+        h1 { color: #333; }
+        .actions { margin: 20px 0; }
+        .actions input, .actions button { margin-right: 8px; }
+        table { border-collapse: collapse; width: 100%; }
+        th, td { text-align: left; padding: 8px; border-bottom: 1px solid #e0e0e0; }
+        .url { font-family: monospace; font-size: 12px; color: #757575; word-break: break-all; }
+        .folders li a { margin-left: 8px; }
+"#;
+
+/// Stylesheet for `about:history`
+const ABOUT_HISTORY_STYLE: &str = r#"
+        body { font-family: Arial, sans-serif; margin: 40px; }
+        h1 { color: #333; }
+        table { border-collapse: collapse; width: 100%; }
+        th, td { text-align: left; padding: 8px; border-bottom: 1px solid #e0e0e0; }
+        .url { font-family: monospace; font-size: 12px; color: #757575; word-break: break-all; }
+        td.when { white-space: nowrap; }
+        .redirect { font-size: 12px; color: #757575; }
+"#;
+
+/// Stylesheet for `about:performance`
+const ABOUT_PERFORMANCE_STYLE: &str = r#"
+        body { font-family: Arial, sans-serif; margin: 40px; }
+        h1 { color: #333; }
+        table { border-collapse: collapse; width: 100%; margin-bottom: 24px; }
+        th, td { text-align: left; padding: 8px; border-bottom: 1px solid #e0e0e0; }
+        .url { font-family: monospace; font-size: 12px; color: #757575; word-break: break-all; }
+        .disabled { color: #757575; }
+"#;
+
+/// Stylesheet for `about:recovery`
+const ABOUT_RECOVERY_STYLE: &str = r#"
+        body { font-family: Arial, sans-serif; margin: 40px; }
+        h1 { color: #333; }
+        section { border: 1px solid #f9a825; background-color: #fff8e1; border-radius: 4px; margin: 12px 0; padding: 8px 16px; }
+        table { border-collapse: collapse; margin: 8px 0; }
+        th, td { text-align: left; padding: 6px 8px; border-bottom: 1px solid #e0e0e0; }
+        .path { font-family: monospace; word-break: break-all; }
+        .lost { color: #d32f2f; }
+"#;
+
+/// Stylesheet for `about:certificate`
+const ABOUT_CERTIFICATE_STYLE: &str = r#"
+        body { font-family: Arial, sans-serif; margin: 40px; }
+        h1 { color: #333; }
+        details { border: 1px solid #e0e0e0; border-radius: 4px; margin: 12px 0; padding: 8px 12px; }
+        details.expiring { border-color: #f9a825; background-color: #fff8e1; }
+        details.expired { border-color: #d32f2f; background-color: #ffebee; }
+        summary { cursor: pointer; font-weight: bold; }
+        table { border-collapse: collapse; width: 100%; margin: 8px 0; }
+        th, td { text-align: left; padding: 6px 8px; border-bottom: 1px solid #e0e0e0; vertical-align: top; }
+        td.mono { font-family: monospace; word-break: break-all; }
+        .warning { color: #e65100; font-weight: bold; }
+        .exception { color: #d32f2f; font-weight: bold; }
+        pre { background-color: #f5f5f5; padding: 12px; overflow-x: auto; }
+"#;
+
+/// Stylesheet for `about:resubmit`
+const ABOUT_RESUBMIT_STYLE: &str = r#"
+        body { font-family: Arial, sans-serif; margin: 40px; max-width: 640px; }
+        h1 { color: #333; }
+        .url { font-family: monospace; word-break: break-all; }
+        .buttons a { display: inline-block; margin-right: 12px; padding: 8px 16px;
+                     border-radius: 4px; text-decoration: none; }
+        a.primary { background-color: #1a73e8; color: white; }
+        a.secondary { border: 1px solid #dadce0; color: #1a73e8; }
+"#;
+
+/// Stylesheet for navigation error pages
+const ERROR_PAGE_STYLE: &str = r#"
+        body {
+            font-family: Arial, sans-serif;
+            margin: 0;
+            padding: 0;
+            background-color: #f5f5f5;
+        }
+        .container {
+            max-width: 600px;
+            margin: 100px auto;
+            background-color: white;
+            padding: 40px;
+            border-radius: 8px;
+            box-shadow: 0 2px 4px rgba(0,0,0,0.1);
+        }
+        h1 {
+            color: #d32f2f;
+            margin-top: 0;
+        }
+        .message {
+            font-size: 16px;
+            margin: 20px 0;
+            color: #333;
+        }
+        .details {
+            background-color: #f5f5f5;
+            padding: 15px;
+            border-radius: 4px;
+            font-family: monospace;
+            font-size: 14px;
+            margin: 20px 0;
+            word-break: break-all;
+        }
+        .failure {
+            font-size: 14px;
+            color: #666;
+        }
+        .actions {
+            margin-top: 30px;
+        }
+        a.button {
+            display: inline-block;
+            background-color: #1976d2;
+            color: white;
+            text-decoration: none;
+            padding: 12px 24px;
+            font-size: 14px;
+            border-radius: 4px;
+            margin-right: 10px;
+        }
+        a.button:hover {
+            background-color: #1565c0;
+        }
+        .suggestions {
+            margin-top: 30px;
+            padding-top: 20px;
+            border-top: 1px solid #e0e0e0;
+        }
+        .suggestions h3 {
+            font-size: 16px;
+            margin-top: 0;
+        }
+        .suggestions ul {
+            padding-left: 20px;
+        }
+        .suggestions li {
+            margin: 8px 0;
+        }
+"#;
+
 /// Navigation state
 #[derive(Debug, Clone, PartialEq)]
 pub enum NavigationState {
@@ -38,6 +254,27 @@ pub enum NavigationError {
     FileNotFound(PathBuf),
     /// Redirect loop detected
     RedirectLoop,
+    /// Offline mode is on and the page is not in the cache
+    Offline,
+}
+
+impl std::fmt::Display for NavigationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NavigationError::InvalidUrl(url) => write!(f, "Invalid URL: {}", url),
+            NavigationError::UnsupportedProtocol(protocol) => {
+                write!(f, "Unsupported protocol: {}", protocol)
+            }
+            NavigationError::NetworkError(msg) => write!(f, "Network error: {}", msg),
+            NavigationError::Timeout => write!(f, "Connection timed out"),
+            NavigationError::SslError(msg) => write!(f, "SSL error: {}", msg),
+            NavigationError::FileNotFound(path) => {
+                write!(f, "File not found: {}", path.display())
+            }
+            NavigationError::RedirectLoop => write!(f, "Redirect loop"),
+            NavigationError::Offline => write!(f, "Offline and not in the cache"),
+        }
+    }
 }
 
 /// Protocol types supported by the browser
@@ -71,6 +308,51 @@ pub struct Navigator {
     /// Maximum number of redirects to follow
     #[allow(dead_code)]
     max_redirects: usize,
+    /// Latest per-origin storage snapshot for `about:storage`
+    storage_report: Option<StorageReport>,
+    /// Latest HTTP cache snapshot for `about:cache`
+    cache_entries: Vec<CacheEntrySummary>,
+    /// Search text the cache snapshot was filtered by
+    cache_filter: Option<String>,
+    /// Latest extension snapshot for `about:diagnostics`
+    extensions: Vec<ExtensionDiagnostics>,
+    /// Cached ad block filter lists for `about:diagnostics`
+    filter_lists: Vec<FilterListInfo>,
+    /// Session traffic for `about:diagnostics`
+    session_bandwidth: TabBandwidth,
+    /// Per-tab traffic for `about:diagnostics`, heaviest first
+    tab_bandwidth: Vec<(TabId, TabBandwidth)>,
+    /// Resolver lookups and DNS prefetch use for `about:diagnostics`
+    dns_stats: ResolverStats,
+    /// Settings that differ from their defaults, for `about:diagnostics`
+    non_default_settings: Vec<ConfigChange>,
+    /// Database sizes and checkpoints for `about:diagnostics`
+    databases: Vec<DbStats>,
+    /// Recent message traces for `about:diagnostics`, newest first
+    traces: Vec<Trace>,
+    /// Ad block filter list subscriptions for `about:adblock`
+    adblock_subscriptions: Vec<FilterListSubscription>,
+    /// Form post target shown on `about:resubmit`
+    resubmit_target: Option<String>,
+    /// Bookmark snapshot for `about:bookmarks`
+    bookmarks: Vec<Bookmark>,
+    /// History snapshot for `about:history`, most recent first
+    history: Vec<HistoryEntry>,
+    /// Worst origins per budget for `about:performance`: metric, budget
+    /// (0 when unchecked) and origins, worst first
+    slow_origins: Vec<(BudgetMetric, u64, Vec<SlowOrigin>)>,
+    /// Host shown on `about:certificate` and `about:certificate-pem`
+    certificate_host: String,
+    /// Chain that host presented, if it was reached over TLS
+    certificate_chain: Option<CertChainInfo>,
+    /// Certificate shown on `about:certificate-pem`; `None` for the chain
+    certificate_pem_index: Option<usize>,
+    /// Databases recovered at startup, for `about:recovery`
+    recovery_reports: Vec<RecoveryReport>,
+    /// Formats times, sizes and counts on internal pages
+    formatter: Formatter,
+    /// Content of the last page loaded, until taken
+    document: Option<Vec<u8>>,
 }
 
 impl Navigator {
@@ -101,6 +383,28 @@ impl Navigator {
             timeout_duration: Duration::from_secs(30),
             allowed_protocols,
             max_redirects: 10,
+            storage_report: None,
+            cache_entries: Vec::new(),
+            cache_filter: None,
+            extensions: Vec::new(),
+            filter_lists: Vec::new(),
+            session_bandwidth: TabBandwidth::default(),
+            tab_bandwidth: Vec::new(),
+            dns_stats: ResolverStats::default(),
+            non_default_settings: Vec::new(),
+            databases: Vec::new(),
+            traces: Vec::new(),
+            adblock_subscriptions: Vec::new(),
+            resubmit_target: None,
+            bookmarks: Vec::new(),
+            history: Vec::new(),
+            slow_origins: Vec::new(),
+            certificate_host: String::new(),
+            certificate_chain: None,
+            certificate_pem_index: None,
+            recovery_reports: Vec::new(),
+            formatter: Formatter::default(),
+            document: None,
         }
     }
 
@@ -109,6 +413,168 @@ impl Navigator {
         &self.state
     }
 
+    /// Take the content of the page the last navigation loaded
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the last navigation failed or its content was
+    /// already taken.
+    pub fn take_document(&mut self) -> Option<Vec<u8>> {
+        self.document.take()
+    }
+
+    /// Set the storage snapshot rendered by `about:storage`
+    pub fn set_storage_report(&mut self, report: StorageReport) {
+        self.storage_report = Some(report);
+    }
+
+    /// Drop an origin from the storage snapshot after its data was cleared
+    pub fn remove_storage_origin(&mut self, origin: &str) {
+        if let Some(report) = self.storage_report.as_mut() {
+            report.origins.retain(|u| u.origin != origin);
+        }
+    }
+
+    /// Set the HTTP cache snapshot rendered by `about:cache`
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - Search text the entries were filtered by
+    /// * `entries` - Matching cache entries
+    pub fn set_cache_snapshot(&mut self, filter: Option<String>, entries: Vec<CacheEntrySummary>) {
+        self.cache_filter = filter;
+        self.cache_entries = entries;
+    }
+
+    /// Search text of the current `about:cache` snapshot
+    pub fn cache_filter(&self) -> Option<&str> {
+        self.cache_filter.as_deref()
+    }
+
+    /// Set the extension snapshot rendered by `about:diagnostics`
+    pub fn set_extension_diagnostics(&mut self, extensions: Vec<ExtensionDiagnostics>) {
+        self.extensions = extensions;
+    }
+
+    /// Set the filter list snapshot rendered by `about:diagnostics`
+    pub fn set_filter_list_diagnostics(&mut self, lists: Vec<FilterListInfo>) {
+        self.filter_lists = lists;
+    }
+
+    /// Set the traffic snapshot rendered by `about:diagnostics`
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - Traffic of the whole session
+    /// * `tabs` - Traffic per tab, in the order to list them
+    pub fn set_bandwidth_diagnostics(
+        &mut self,
+        session: TabBandwidth,
+        tabs: Vec<(TabId, TabBandwidth)>,
+    ) {
+        self.session_bandwidth = session;
+        self.tab_bandwidth = tabs;
+    }
+
+    /// Set the resolver counters rendered by `about:diagnostics`
+    pub fn set_dns_diagnostics(&mut self, stats: ResolverStats) {
+        self.dns_stats = stats;
+    }
+
+    /// Set the customized settings rendered by `about:diagnostics`
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - Changes from the default configuration, as returned
+    ///   by `Config::non_default_fields`
+    pub fn set_config_diagnostics(&mut self, settings: Vec<ConfigChange>) {
+        self.non_default_settings = settings;
+    }
+
+    /// Set the database snapshot rendered by `about:diagnostics`
+    pub fn set_database_diagnostics(&mut self, databases: Vec<DbStats>) {
+        self.databases = databases;
+    }
+
+    /// Set the message traces rendered by `about:diagnostics`
+    pub fn set_trace_diagnostics(&mut self, traces: Vec<Trace>) {
+        self.traces = traces;
+    }
+
+    /// Set the filter list subscription snapshot rendered by `about:adblock`
+    pub fn set_adblock_subscriptions(&mut self, subscriptions: Vec<FilterListSubscription>) {
+        self.adblock_subscriptions = subscriptions;
+    }
+
+    /// Flip a subscription in the `about:adblock` snapshot after a toggle
+    pub fn set_adblock_subscription_enabled(&mut self, id: SubscriptionId, enabled: bool) {
+        if let Some(sub) = self.adblock_subscriptions.iter_mut().find(|s| s.id == id) {
+            sub.enabled = enabled;
+        }
+    }
+
+    /// Set the bookmark snapshot rendered by `about:bookmarks`
+    pub fn set_bookmarks_snapshot(&mut self, bookmarks: Vec<Bookmark>) {
+        self.bookmarks = bookmarks;
+    }
+
+    /// Set the history snapshot rendered by `about:history`
+    pub fn set_history_snapshot(&mut self, history: Vec<HistoryEntry>) {
+        self.history = history;
+    }
+
+    /// Set the slow page snapshot rendered by `about:performance`
+    ///
+    /// # Arguments
+    ///
+    /// * `slow_origins` - Per budget: the metric, its budget (0 when
+    ///   unchecked) and the worst origins from `BrowserEngine::worst_pages`
+    pub fn set_performance_snapshot(
+        &mut self,
+        slow_origins: Vec<(BudgetMetric, u64, Vec<SlowOrigin>)>,
+    ) {
+        self.slow_origins = slow_origins;
+    }
+
+    /// Set the certificate snapshot rendered by `about:certificate` and
+    /// `about:certificate-pem`
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - Host the page is about
+    /// * `chain` - Chain the host presented this session, if any
+    /// * `pem_index` - Certificate to show as PEM; `None` for the whole chain
+    pub fn set_certificate_snapshot(
+        &mut self,
+        host: String,
+        chain: Option<CertChainInfo>,
+        pem_index: Option<usize>,
+    ) {
+        self.certificate_host = host;
+        self.certificate_chain = chain;
+        self.certificate_pem_index = pem_index;
+    }
+
+    /// Set the recovered databases listed on `about:recovery`
+    pub fn set_recovery_reports(&mut self, reports: Vec<RecoveryReport>) {
+        self.recovery_reports = reports;
+    }
+
+    /// Set how internal pages format times, sizes and counts
+    pub fn set_formatter(&mut self, formatter: Formatter) {
+        self.formatter = formatter;
+    }
+
+    /// Formatter used by internal pages
+    pub fn formatter(&self) -> &Formatter {
+        &self.formatter
+    }
+
+    /// Set the form post target named by `about:resubmit`
+    pub fn set_resubmit_target(&mut self, url: &Url) {
+        self.resubmit_target = Some(url.to_string());
+    }
+
     /// Validate a URL
     ///
     /// # Arguments
@@ -192,6 +658,50 @@ impl Navigator {
         }
     }
 
+    /// Check whether a navigation is allowed for its initiator
+    ///
+    /// - `file:` URLs need a direct user action (typed, gesture-backed link,
+    ///   history traversal, restore) or a redirect from another `file:` URL;
+    ///   scripts and web redirects cannot open local files.
+    /// - Internal `about:` pages other than `about:blank` cannot be opened
+    ///   by scripts, redirects or links followed without a user gesture.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - URL to navigate to
+    /// * `initiator` - Why the navigation was started
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::NavigationBlocked` if policy forbids the navigation.
+    pub fn check_initiator(&self, url: &Url, initiator: &NavigationInitiator) -> Result<()> {
+        match Self::determine_protocol(url) {
+            Protocol::File => {
+                let allowed = match initiator {
+                    NavigationInitiator::Redirect { from } => from.scheme() == "file",
+                    other => other.is_user_initiated(),
+                };
+                if !allowed {
+                    return Err(Error::NavigationBlocked(format!(
+                        "{:?} may not open {}",
+                        initiator,
+                        url.as_str()
+                    )));
+                }
+            }
+            Protocol::About if url.path() != "blank" && !initiator.is_user_initiated() => {
+                return Err(Error::NavigationBlocked(format!(
+                    "{:?} may not open {}",
+                    initiator,
+                    url.as_str()
+                )));
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
     /// Navigate to a URL
     ///
     /// # Arguments
@@ -239,12 +749,14 @@ impl Navigator {
 
         // Update state based on result
         match result {
-            Ok(_content) => {
+            Ok(content) => {
+                self.document = Some(content);
                 let duration = start_time.elapsed();
                 self.state = NavigationState::Loaded(url.clone(), duration);
                 Ok(self.state.clone())
             }
             Err(e) => {
+                self.document = None;
                 let nav_error = self.error_to_navigation_error(&e);
                 self.state = NavigationState::Error(url.clone(), nav_error.clone());
                 Ok(self.state.clone())
@@ -362,33 +874,964 @@ impl Navigator {
     /// Returns an error if the about page is unknown.
     pub fn handle_about(&self, page: &str) -> Result<String> {
         match page {
-            "blank" | "" => Ok(String::from(
-                "<!DOCTYPE html><html><head><title>about:blank</title></head><body></body></html>",
+            "blank" | "" => Ok(InternalPage::new("about:blank").render()),
+            "version" => {
+                let mut body = SafeHtml::trusted("<h1>FrankenBrowser</h1>");
+                body.push(SafeHtml::element(
+                    "div",
+                    Some("version"),
+                    SafeHtml::text("Version: 0.1.0"),
+                ))
+                .push(SafeHtml::trusted(
+                    "<p>A modular browser implementation in Rust.</p>",
+                ));
+
+                Ok(InternalPage::new("About FrankenBrowser")
+                    .with_style(ABOUT_VERSION_STYLE)
+                    .with_body(body)
+                    .render())
+            }
+            "storage" => Ok(self.render_storage_page()),
+            "cache" => Ok(self.render_cache_page()),
+            "diagnostics" => Ok(self.render_diagnostics_page()),
+            "adblock" => Ok(self.render_adblock_page()),
+            "bookmarks" => Ok(self.render_bookmarks_page()),
+            "history" => Ok(self.render_history_page()),
+            "performance" => Ok(self.render_performance_page()),
+            "certificate" => Ok(self.render_certificate_page()),
+            "certificate-pem" => Ok(self.render_certificate_pem_page()),
+            "resubmit" => Ok(self.render_resubmit_page()),
+            "recovery" => Ok(self.render_recovery_page()),
+            _ => Err(Error::InvalidUrl(format!("Unknown about page: {}", page))),
+        }
+    }
+
+    /// Render `about:storage`
+    ///
+    /// Lists origins largest first with a "Clear" action per origin. When
+    /// enumeration is unsupported (or no snapshot was provided) a reduced
+    /// view with only cookie counts is shown.
+    fn render_storage_page(&self) -> String {
+        let report = self
+            .storage_report
+            .clone()
+            .unwrap_or_else(|| StorageReport {
+                origins: Vec::new(),
+                unsupported: Some("storage usage has not been reported".to_string()),
+            });
+        let reduced = report.unsupported.is_some();
+
+        let mut body = SafeHtml::trusted("<h1>Site Storage</h1>");
+        if let Some(reason) = &report.unsupported {
+            let mut notice = SafeHtml::text("Storage details are unavailable (");
+            notice
+                .push(SafeHtml::text(reason))
+                .push(SafeHtml::text("). Only cookie counts are shown."));
+            body.push(SafeHtml::element("p", Some("notice"), notice));
+        }
+
+        if report.origins.is_empty() {
+            body.push(SafeHtml::trusted("<p>No site data stored.</p>"));
+        } else {
+            let mut rows = SafeHtml::trusted(
+                "<tr><th>Origin</th><th>Data</th><th>Size</th><th>Cookies</th><th></th></tr>",
+            );
+            for usage in &report.origins {
+                let kinds: Vec<&str> = usage.kinds.iter().map(|k| k.label()).collect();
+                let size = if reduced {
+                    "-".to_string()
+                } else {
+                    self.formatter.format_bytes(usage.bytes)
+                };
+
+                let mut row = SafeHtml::element("td", None, SafeHtml::text(&usage.origin));
+                row.push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(&kinds.join(", ")),
+                ))
+                .push(SafeHtml::element("td", None, SafeHtml::text(&size)))
+                .push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(&self.formatter.format_count(usage.cookies as u64)),
+                ))
+                .push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::link(&storage_clear_url(&usage.origin), "Clear", Some("button")),
+                ));
+                rows.push(SafeHtml::element("tr", None, row));
+            }
+            body.push(SafeHtml::element("table", None, rows));
+        }
+
+        InternalPage::new("Site Storage")
+            .with_style(ABOUT_STORAGE_STYLE)
+            .with_body(body)
+            .render()
+    }
+
+    /// Render `about:resubmit`
+    ///
+    /// Shown when back/forward lands on a page produced by a form post, in
+    /// place of silently sending the form again.
+    fn render_resubmit_page(&self) -> String {
+        let target = self.resubmit_target.as_deref().unwrap_or("");
+
+        let mut body = SafeHtml::trusted("<h1>Confirm Form Resubmission</h1>");
+        body.push(SafeHtml::trusted(
+            "<p>This page was produced by information you submitted. Loading it again \
+             repeats the submission and any action it caused, such as a purchase.</p>",
+        ))
+        .push(SafeHtml::element("p", Some("url"), SafeHtml::text(target)));
+
+        let mut buttons = SafeHtml::link(RESUBMIT_URL, "Resubmit", Some("primary"));
+        buttons.push(SafeHtml::link(GO_BACK_URL, "Go Back", Some("secondary")));
+        body.push(SafeHtml::element("div", Some("buttons"), buttons));
+
+        InternalPage::new("Confirm Form Resubmission")
+            .with_style(ABOUT_RESUBMIT_STYLE)
+            .with_body(body)
+            .render()
+    }
+
+    /// Render `about:cache`
+    ///
+    /// Lists cache entries (most recently used first) and the site each was
+    /// cached for, with a search box and per-URL and per-host purge actions.
+    fn render_cache_page(&self) -> String {
+        let now = self.formatter.now_unix().max(0) as u64;
+        let f = &self.formatter;
+        let filter = self.cache_filter.as_deref().unwrap_or("");
+
+        let mut body = SafeHtml::trusted("<h1>HTTP Cache</h1>");
+        body.push(SafeHtml::search_form("about:cache", "q", filter));
+
+        if self.cache_entries.is_empty() {
+            if filter.is_empty() {
+                body.push(SafeHtml::trusted("<p>The cache is empty.</p>"));
+            } else {
+                body.push(SafeHtml::trusted("<p>No cached URLs match.</p>"));
+            }
+        } else {
+            let mut rows = SafeHtml::trusted(
+                "<tr><th>URL</th><th>Site</th><th>Size</th><th>Age</th><th>Expires</th><th>Hits</th><th>Validator</th><th></th></tr>",
+            );
+            for entry in &self.cache_entries {
+                let expires = if entry.expires_at > now {
+                    format!(
+                        "in {}",
+                        f.format_duration_ms((entry.expires_at - now) * 1_000)
+                    )
+                } else {
+                    "expired".to_string()
+                };
+                let host = Url::parse(&entry.url)
+                    .ok()
+                    .and_then(|u| u.host_str().map(str::to_string));
+
+                let mut actions =
+                    SafeHtml::link(&cache_purge_url(&entry.url), "Purge", Some("button"));
+                if let Some(host) = &host {
+                    actions.push(SafeHtml::trusted(" "));
+                    actions.push(SafeHtml::link(
+                        &cache_purge_host_url(host),
+                        "Purge host",
+                        Some("button"),
+                    ));
+                }
+
+                let mut row = SafeHtml::element("td", Some("url"), SafeHtml::text(&entry.url));
+                row.push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(entry.partition.as_deref().unwrap_or("shared")),
+                ))
+                .push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(&f.format_bytes(entry.size as u64)),
+                ))
+                .push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(
+                        &f.format_duration_ms(now.saturating_sub(entry.stored_at) * 1_000),
+                    ),
+                ))
+                .push(SafeHtml::element("td", None, SafeHtml::text(&expires)))
+                .push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(&f.format_count(entry.hit_count as u64)),
+                ))
+                .push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(entry.validator.as_deref().unwrap_or("-")),
+                ))
+                .push(SafeHtml::element("td", None, actions));
+                rows.push(SafeHtml::element("tr", None, row));
+            }
+            body.push(SafeHtml::element("table", None, rows));
+        }
+
+        InternalPage::new("HTTP Cache")
+            .with_style(ABOUT_CACHE_STYLE)
+            .with_body(body)
+            .render()
+    }
+
+    /// Render `about:adblock`
+    ///
+    /// Lists the filter list subscriptions with their rule counts and last
+    /// update, with per-list enable/disable actions and update actions.
+    fn render_adblock_page(&self) -> String {
+        let f = &self.formatter;
+
+        let mut body = SafeHtml::trusted("<h1>Ad Blocking</h1>");
+        let mut actions = SafeHtml::link(&adblock_update_url(false), "Update now", None);
+        actions.push(SafeHtml::link(
+            &adblock_update_url(true),
+            "Force update all",
+            None,
+        ));
+        body.push(SafeHtml::element("div", Some("actions"), actions));
+
+        if self.adblock_subscriptions.is_empty() {
+            body.push(SafeHtml::trusted("<p>No filter list subscriptions.</p>"));
+        } else {
+            let mut rows = SafeHtml::trusted(
+                "<tr><th>List</th><th>Rules</th><th>Last updated</th><th>Status</th><th></th></tr>",
+            );
+            for sub in &self.adblock_subscriptions {
+                let updated = match sub.last_updated {
+                    Some(at) => f.format_timestamp(at as i64, TimestampStyle::Relative),
+                    None => "never".to_string(),
+                };
+                let (status, class, toggle) = if sub.enabled {
+                    ("enabled", None, "Disable")
+                } else {
+                    ("disabled", Some("disabled"), "Enable")
+                };
+
+                let mut list = SafeHtml::text(&sub.title);
+                list.push(SafeHtml::element(
+                    "div",
+                    Some("url"),
+                    SafeHtml::text(&sub.url),
+                ));
+                let mut row = SafeHtml::element("td", class, list);
+                row.push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(&f.format_count(sub.rule_count as u64)),
+                ))
+                .push(SafeHtml::element("td", None, SafeHtml::text(&updated)))
+                .push(SafeHtml::element("td", class, SafeHtml::text(status)))
+                .push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::link(
+                        &adblock_toggle_url(sub.id, !sub.enabled),
+                        toggle,
+                        Some("button"),
+                    ),
+                ));
+                rows.push(SafeHtml::element("tr", None, row));
+            }
+            body.push(SafeHtml::element("table", None, rows));
+        }
+
+        InternalPage::new("Ad Blocking")
+            .with_style(ABOUT_ADBLOCK_STYLE)
+            .with_body(body)
+            .render()
+    }
+
+    /// Render `about:bookmarks`
+    ///
+    /// Lists the bookmarks in one form, each with a checkbox, so several
+    /// can be selected and deleted, tagged, untagged or moved to a folder
+    /// at once. The form submits to [`BOOKMARKS_ACTION_URL`]. Each folder
+    /// is listed above with a link that opens all its bookmarks in tabs.
+    fn render_bookmarks_page(&self) -> String {
+        let mut body = SafeHtml::trusted("<h1>Bookmarks</h1>");
+
+        if self.bookmarks.is_empty() {
+            body.push(SafeHtml::trusted("<p>No bookmarks.</p>"));
+        } else {
+            let folders: std::collections::BTreeSet<&str> = self
+                .bookmarks
+                .iter()
+                .filter_map(|bookmark| bookmark.folder.as_deref())
+                .collect();
+            if !folders.is_empty() {
+                let mut items = SafeHtml::default();
+                for folder in folders {
+                    let mut item = SafeHtml::text(folder);
+                    item.push(SafeHtml::link(
+                        &bookmarks_action_url(&BookmarksAction::OpenFolder {
+                            folder: folder.to_string(),
+                        }),
+                        "Open All in Tabs",
+                        None,
+                    ));
+                    items.push(SafeHtml::element("li", None, item));
+                }
+                body.push(SafeHtml::element("ul", Some("folders"), items));
+            }
+
+            let mut actions = SafeHtml::text_input("tags", "tag, tag");
+            actions
+                .push(SafeHtml::submit_button("op", "tag", "Add tags"))
+                .push(SafeHtml::submit_button("op", "untag", "Remove tags"))
+                .push(SafeHtml::text_input("folder", "Folder"))
+                .push(SafeHtml::submit_button("op", "move", "Move"))
+                .push(SafeHtml::submit_button("op", "delete", "Delete"));
+            let mut form = SafeHtml::element("div", Some("actions"), actions);
+
+            let mut rows = SafeHtml::trusted(
+                "<tr><th></th><th>Bookmark</th><th>Folder</th><th>Tags</th></tr>",
+            );
+            for bookmark in &self.bookmarks {
+                let mut title = SafeHtml::link(&bookmark.url, &bookmark.title, None);
+                title.push(SafeHtml::element(
+                    "div",
+                    Some("url"),
+                    SafeHtml::text(&bookmark.url),
+                ));
+
+                let mut row = SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::checkbox("id", &bookmark.id.to_string()),
+                );
+                row.push(SafeHtml::element("td", None, title))
+                    .push(SafeHtml::element(
+                        "td",
+                        None,
+                        SafeHtml::text(bookmark.folder.as_deref().unwrap_or("")),
+                    ))
+                    .push(SafeHtml::element(
+                        "td",
+                        None,
+                        SafeHtml::text(&bookmark.tags.join(", ")),
+                    ));
+                rows.push(SafeHtml::element("tr", None, row));
+            }
+            form.push(SafeHtml::element("table", None, rows));
+            body.push(SafeHtml::form(BOOKMARKS_ACTION_URL, form));
+        }
+
+        InternalPage::new("Bookmarks")
+            .with_style(ABOUT_BOOKMARKS_STYLE)
+            .with_body(body)
+            .render()
+    }
+
+    /// Render `about:history`
+    ///
+    /// Lists the history snapshot with visit counts; recent visits show as
+    /// relative times ("3 minutes ago"), older ones as dates. Entries
+    /// reached through a redirect name the URL they were redirected from.
+    fn render_history_page(&self) -> String {
+        let mut body = SafeHtml::trusted("<h1>History</h1>");
+
+        if self.history.is_empty() {
+            body.push(SafeHtml::trusted("<p>No history.</p>"));
+        } else {
+            let mut rows =
+                SafeHtml::trusted("<tr><th>Page</th><th>Visits</th><th>Last visited</th></tr>");
+            for entry in &self.history {
+                let title = if entry.title.is_empty() {
+                    &entry.url
+                } else {
+                    &entry.title
+                };
+                let mut page = SafeHtml::link(&entry.url, title, None);
+                page.push(SafeHtml::element(
+                    "div",
+                    Some("url"),
+                    SafeHtml::text(&entry.url),
+                ));
+                if let Some(from) = entry.redirect_source() {
+                    page.push(SafeHtml::element(
+                        "div",
+                        Some("redirect"),
+                        SafeHtml::text(&format!("Redirected from {from}")),
+                    ));
+                }
+
+                let mut row = SafeHtml::element("td", None, page);
+                row.push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(&self.formatter.format_count(entry.visit_count.max(0) as u64)),
+                ))
+                .push(SafeHtml::element(
+                    "td",
+                    Some("when"),
+                    SafeHtml::text(
+                        &self
+                            .formatter
+                            .format_timestamp(entry.last_visit, TimestampStyle::Relative),
+                    ),
+                ));
+                rows.push(SafeHtml::element("tr", None, row));
+            }
+            body.push(SafeHtml::element("table", None, rows));
+        }
+
+        InternalPage::new("History")
+            .with_style(ABOUT_HISTORY_STYLE)
+            .with_body(body)
+            .render()
+    }
+
+    /// Render `about:recovery`
+    ///
+    /// One section per profile database that was damaged at startup: what
+    /// was wrong, where the damaged file was kept, and how many rows of
+    /// each table were recovered.
+    fn render_recovery_page(&self) -> String {
+        let mut body = SafeHtml::trusted("<h1>Profile Recovery</h1>");
+        if self.recovery_reports.is_empty() {
+            body.push(SafeHtml::trusted(
+                "<p>No profile database needed recovery.</p>",
+            ));
+        }
+
+        for report in &self.recovery_reports {
+            let mut section = SafeHtml::element("h2", None, SafeHtml::text(&report.name));
+            let mut problem = SafeHtml::text("The database was damaged (");
+            problem
+                .push(SafeHtml::text(&report.problem))
+                .push(SafeHtml::text(
+                    ") and was replaced with the data that could be read. \
+                     The damaged file was kept as:",
+                ));
+            section
+                .push(SafeHtml::element("p", None, problem))
+                .push(SafeHtml::element(
+                    "p",
+                    Some("path"),
+                    SafeHtml::text(&report.moved_to.display().to_string()),
+                ));
+
+            if report.tables.is_empty() {
+                section.push(SafeHtml::element(
+                    "p",
+                    Some("lost"),
+                    SafeHtml::text("Nothing could be read; it starts empty."),
+                ));
+            } else {
+                let mut rows =
+                    SafeHtml::trusted("<tr><th>Table</th><th>Recovered</th><th>Lost</th></tr>");
+                for table in &report.tables {
+                    let lost = match (table.rejected, table.complete) {
+                        (0, true) => String::new(),
+                        (0, false) => "later rows unreadable".to_string(),
+                        (rejected, true) => self.formatter.format_count(rejected),
+                        (rejected, false) => format!(
+                            "{}, later rows unreadable",
+                            self.formatter.format_count(rejected)
+                        ),
+                    };
+                    let mut row = SafeHtml::element("td", None, SafeHtml::text(&table.name));
+                    row.push(SafeHtml::element(
+                        "td",
+                        None,
+                        SafeHtml::text(&self.formatter.format_count(table.recovered)),
+                    ))
+                    .push(SafeHtml::element(
+                        "td",
+                        Some("lost"),
+                        SafeHtml::text(&lost),
+                    ));
+                    rows.push(SafeHtml::element("tr", None, row));
+                }
+                section.push(SafeHtml::element("table", None, rows));
+            }
+            body.push(SafeHtml::element("section", None, section));
+        }
+
+        InternalPage::new("Profile Recovery")
+            .with_style(ABOUT_RECOVERY_STYLE)
+            .with_body(body)
+            .render()
+    }
+
+    /// Render `about:performance`
+    ///
+    /// One table per budget listing the origins whose pages went over it
+    /// most often, with how far over they typically were and their worst
+    /// page.
+    fn render_performance_page(&self) -> String {
+        let mut body = SafeHtml::trusted("<h1>Slow Pages</h1>");
+        if self.slow_origins.is_empty() {
+            body.push(SafeHtml::trusted("<p>No pages have been checked.</p>"));
+        }
+
+        for (metric, budget, origins) in &self.slow_origins {
+            let format = |value: u64| match metric {
+                BudgetMetric::PageLoad | BudgetMetric::Ttfb => {
+                    self.formatter.format_duration_ms(value)
+                }
+                BudgetMetric::TotalBytes => self.formatter.format_bytes(value),
+                BudgetMetric::SubresourceCount => self.formatter.format_count(value),
+            };
+            if *budget == 0 {
+                body.push(SafeHtml::element(
+                    "h2",
+                    Some("disabled"),
+                    SafeHtml::text(&format!("{} (not checked)", metric.label())),
+                ));
+                continue;
+            }
+            body.push(SafeHtml::element(
+                "h2",
+                None,
+                SafeHtml::text(&format!("{} (budget {})", metric.label(), format(*budget))),
+            ));
+            if origins.is_empty() {
+                body.push(SafeHtml::trusted("<p>No page went over this budget.</p>"));
+                continue;
+            }
+
+            let mut rows = SafeHtml::trusted(
+                "<tr><th>Origin</th><th>Times over</th><th>Typically over by</th>\
+                 <th>Worst page</th></tr>",
+            );
+            for origin in origins {
+                let mut worst = SafeHtml::text(&format(origin.worst_value));
+                worst.push(SafeHtml::element(
+                    "div",
+                    Some("url"),
+                    SafeHtml::text(&origin.worst_url),
+                ));
+
+                let mut row = SafeHtml::element("td", None, SafeHtml::text(&origin.origin));
+                row.push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(&self.formatter.format_count(origin.events)),
+                ))
+                .push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(&format(origin.typical_excess)),
+                ))
+                .push(SafeHtml::element("td", None, worst));
+                rows.push(SafeHtml::element("tr", None, row));
+            }
+            body.push(SafeHtml::element("table", None, rows));
+        }
+
+        InternalPage::new("Slow Pages")
+            .with_style(ABOUT_PERFORMANCE_STYLE)
+            .with_body(body)
+            .render()
+    }
+
+    /// Render `about:certificate`
+    ///
+    /// Shows how the chain was accepted, then one expandable section per
+    /// certificate (leaf first). Certificates expiring within
+    /// [`EXPIRY_WARNING`] or already expired are highlighted.
+    fn render_certificate_page(&self) -> String {
+        let host = &self.certificate_host;
+        let mut heading = SafeHtml::text("Certificate for ");
+        heading.push(SafeHtml::text(host));
+        let mut body = SafeHtml::element("h1", None, heading);
+
+        let Some(chain) = &self.certificate_chain else {
+            body.push(SafeHtml::trusted(
+                "<p>No certificate was recorded for this site. It has not been reached \
+                 over a secure connection this session, or was only loaded from the \
+                 cache.</p>",
+            ));
+            return InternalPage::new("Certificate")
+                .with_style(ABOUT_CERTIFICATE_STYLE)
+                .with_body(body)
+                .render();
+        };
+
+        let validation_class = match chain.validation {
+            CertValidation::SystemRoots => "validation",
+            CertValidation::PinnedException => "exception",
+        };
+        body.push(SafeHtml::element(
+            "p",
+            Some(validation_class),
+            SafeHtml::text(chain.validation.label()),
+        ));
+
+        let last = chain.certificates.len() - 1;
+        for (index, cert) in chain.certificates.iter().enumerate() {
+            let role = match index {
+                0 => "Server certificate",
+                _ if index == last && cert.is_self_issued() => "Root certificate",
+                _ => "Intermediate certificate",
+            };
+            body.push(self.render_certificate(host, index, role, cert));
+        }
+
+        body.push(SafeHtml::element(
+            "p",
+            None,
+            SafeHtml::link(
+                &certificate_pem_url(host, None),
+                "Download chain (PEM)",
+                Some("button"),
+            ),
+        ));
+
+        InternalPage::new("Certificate")
+            .with_style(ABOUT_CERTIFICATE_STYLE)
+            .with_body(body)
+            .render()
+    }
+
+    /// One certificate of `about:certificate` as a `<details>` section
+    fn render_certificate(
+        &self,
+        host: &str,
+        index: usize,
+        role: &str,
+        cert: &CertificateInfo,
+    ) -> SafeHtml {
+        let f = &self.formatter;
+        let now = f.now_unix();
+        let absolute = |unix: i64| f.format_timestamp(unix, TimestampStyle::Absolute);
+
+        let mut summary = SafeHtml::text(role);
+        summary
+            .push(SafeHtml::text(": "))
+            .push(SafeHtml::text(&cert.subject));
+        let mut section = SafeHtml::element("summary", None, summary);
+
+        let (class, warning) = if cert.is_expired(now) {
+            (
+                Some("expired"),
+                Some(format!("Expired {}", absolute(cert.not_after))),
+            )
+        } else if cert.expires_within(now, EXPIRY_WARNING) {
+            (
+                Some("expiring"),
+                Some(format!(
+                    "Expires in {}",
+                    f.format_duration_ms((cert.not_after - now) as u64 * 1_000)
+                )),
+            )
+        } else {
+            (None, None)
+        };
+        if let Some(warning) = &warning {
+            section.push(SafeHtml::element(
+                "p",
+                Some("warning"),
+                SafeHtml::text(warning),
+            ));
+        }
+
+        let key = match cert.key_bits {
+            Some(bits) => format!("{} {} bits", cert.key_type, bits),
+            None => cert.key_type.clone(),
+        };
+        let fields = [
+            ("Subject", cert.subject.clone()),
+            ("Issuer", cert.issuer.clone()),
+            ("Alternative names", cert.subject_alt_names.join(", ")),
+            ("Valid from", absolute(cert.not_before)),
+            ("Valid until", absolute(cert.not_after)),
+            ("Public key", key),
+            ("Signature algorithm", cert.signature_algorithm.clone()),
+            ("SHA-256 fingerprint", cert.sha256_fingerprint.clone()),
+        ];
+        let mut rows = SafeHtml::new();
+        for (label, value) in fields {
+            let mut row = SafeHtml::element("th", None, SafeHtml::text(label));
+            row.push(SafeHtml::element(
+                "td",
+                Some("mono"),
+                SafeHtml::text(&value),
+            ));
+            rows.push(SafeHtml::element("tr", None, row));
+        }
+        section
+            .push(SafeHtml::element("table", None, rows))
+            .push(SafeHtml::link(
+                &certificate_pem_url(host, Some(index)),
+                "Download PEM",
+                Some("button"),
+            ));
+
+        SafeHtml::element("details", class, section)
+    }
+
+    /// Render `about:certificate-pem`
+    ///
+    /// Shows the selected certificate, or the whole chain, as PEM text the
+    /// user can save.
+    fn render_certificate_pem_page(&self) -> String {
+        let chain = self.certificate_chain.as_ref();
+        let pem = match self.certificate_pem_index {
+            Some(index) => chain
+                .and_then(|chain| chain.certificates.get(index))
+                .map(CertificateInfo::pem),
+            None => chain.map(CertChainInfo::pem),
+        };
+
+        let mut body = SafeHtml::trusted("<h1>Certificate (PEM)</h1>");
+        match pem {
+            Some(pem) => {
+                body.push(SafeHtml::element("pre", None, SafeHtml::text(&pem)));
+            }
+            None => {
+                body.push(SafeHtml::trusted(
+                    "<p>No such certificate was recorded.</p>",
+                ));
+            }
+        }
+
+        InternalPage::new("Certificate (PEM)")
+            .with_style(ABOUT_CERTIFICATE_STYLE)
+            .with_body(body)
+            .render()
+    }
+
+    /// Render `about:diagnostics`
+    ///
+    /// Lists the loaded extensions with per-hook call counts, timings and
+    /// panics, the cached ad block filter lists with their provenance, the
+    /// network traffic per tab, the settings changed from their defaults,
+    /// the databases, then the most recent message traces as trees.
+    fn render_diagnostics_page(&self) -> String {
+        let mut body = SafeHtml::trusted("<h1>Diagnostics</h1><h2>Extensions</h2>");
+
+        if self.extensions.is_empty() {
+            body.push(SafeHtml::trusted("<p>No extensions are loaded.</p>"));
+        }
+        for extension in &self.extensions {
+            let (status, class) = if extension.enabled {
+                ("enabled", None)
+            } else {
+                ("disabled", Some("disabled"))
+            };
+            body.push(SafeHtml::element(
+                "h3",
+                class,
+                SafeHtml::text(&format!(
+                    "{} ({}) - {}",
+                    extension.name, extension.id, status
+                )),
+            ));
+
+            if extension.hooks.is_empty() {
+                body.push(SafeHtml::trusted("<p>No hooks called yet.</p>"));
+                continue;
+            }
+            let mut rows = SafeHtml::trusted(
+                "<tr><th>Hook</th><th>Calls</th><th>Avg</th><th>Max</th><th>Panics</th></tr>",
+            );
+            for (hook, stats) in &extension.hooks {
+                let panics_class = (stats.panics > 0).then_some("panics");
+                let mut row = SafeHtml::element("td", None, SafeHtml::text(hook.label()));
+                row.push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(&self.formatter.format_count(stats.calls)),
+                ))
+                .push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(&format!("{:?}", stats.average())),
+                ))
+                .push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(&format!("{:?}", stats.max_time)),
+                ))
+                .push(SafeHtml::element(
+                    "td",
+                    panics_class,
+                    SafeHtml::text(&stats.panics.to_string()),
+                ));
+                rows.push(SafeHtml::element("tr", None, row));
+            }
+            body.push(SafeHtml::element("table", None, rows));
+        }
+
+        body.push(SafeHtml::trusted("<h2>Filter lists</h2>"));
+        if self.filter_lists.is_empty() {
+            body.push(SafeHtml::trusted("<p>No cached filter lists.</p>"));
+        } else {
+            let mut rows = SafeHtml::trusted("<tr><th>List</th><th>Source</th><th>Rules</th></tr>");
+            for list in &self.filter_lists {
+                let source_class = (list.source == FilterListSource::Seed).then_some("seed");
+                let mut row = SafeHtml::element("td", None, SafeHtml::text(&list.name));
+                row.push(SafeHtml::element(
+                    "td",
+                    source_class,
+                    SafeHtml::text(list.source.label()),
+                ))
+                .push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(&self.formatter.format_count(list.rules as u64)),
+                ));
+                rows.push(SafeHtml::element("tr", None, row));
+            }
+            body.push(SafeHtml::element("table", None, rows));
+        }
+
+        body.push(SafeHtml::trusted("<h2>Bandwidth</h2>"));
+        let session = &self.session_bandwidth;
+        body.push(SafeHtml::element(
+            "p",
+            None,
+            SafeHtml::text(&format!(
+                "This session: {} sent, {} received ({} decoded) in {} requests",
+                self.formatter.format_bytes(session.sent),
+                self.formatter.format_bytes(session.received_encoded),
+                self.formatter.format_bytes(session.received_decoded),
+                self.formatter.format_count(session.requests)
             )),
-            "version" => Ok(format!(
-                r#"<!DOCTYPE html>
-<html>
-<head>
-    <title>About FrankenBrowser</title>
-    <style>
-        body {{ font-family: Arial, sans-serif; margin: 40px; }}
-        h1 {{ color: #333; }}
-        .version {{ font-size: 18px; margin: 20px 0; }}
-    </style>
-</head>
-<body>
-    <h1>FrankenBrowser</h1>
-    <div class="version">Version: 0.1.0</div>
-    <p>A modular browser implementation in Rust.</p>
-</body>
-</html>"#
+        ));
+        if self.tab_bandwidth.is_empty() {
+            body.push(SafeHtml::trusted("<p>No tab has used the network yet.</p>"));
+        } else {
+            let mut rows = SafeHtml::trusted(
+                "<tr><th>Tab</th><th>Requests</th><th>Sent</th><th>Received</th><th>Decoded</th></tr>",
+            );
+            for (tab_id, usage) in &self.tab_bandwidth {
+                let mut row = SafeHtml::element("td", None, SafeHtml::text(&tab_id.to_string()));
+                for cell in [
+                    self.formatter.format_count(usage.requests),
+                    self.formatter.format_bytes(usage.sent),
+                    self.formatter.format_bytes(usage.received_encoded),
+                    self.formatter.format_bytes(usage.received_decoded),
+                ] {
+                    row.push(SafeHtml::element("td", None, SafeHtml::text(&cell)));
+                }
+                rows.push(SafeHtml::element("tr", None, row));
+            }
+            body.push(SafeHtml::element("table", None, rows));
+        }
+
+        body.push(SafeHtml::trusted("<h2>DNS</h2>"));
+        let dns = &self.dns_stats;
+        body.push(SafeHtml::element(
+            "p",
+            None,
+            SafeHtml::text(&format!(
+                "{} lookups, {} from cache ({:.0}%), {} failed",
+                self.formatter.format_count(dns.lookups),
+                self.formatter.format_count(dns.cache_hits),
+                dns.cache_hit_rate() * 100.0,
+                self.formatter.format_count(dns.failures)
             )),
-            _ => Err(Error::InvalidUrl(format!("Unknown about page: {}", page))),
+        ));
+        body.push(SafeHtml::element(
+            "p",
+            None,
+            SafeHtml::text(&format!(
+                "Prefetched {} hosts, {} later used ({:.0}%)",
+                self.formatter.format_count(dns.prefetched),
+                self.formatter.format_count(dns.prefetch_hits),
+                dns.prefetch_hit_rate() * 100.0
+            )),
+        ));
+
+        body.push(SafeHtml::trusted("<h2>Non-default settings</h2>"));
+        if self.non_default_settings.is_empty() {
+            body.push(SafeHtml::trusted(
+                "<p>All settings have their default values.</p>",
+            ));
+        } else {
+            let mut rows =
+                SafeHtml::trusted("<tr><th>Setting</th><th>Value</th><th>Default</th></tr>");
+            for change in &self.non_default_settings {
+                let mut row = SafeHtml::element("td", None, SafeHtml::text(&change.path));
+                row.push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(&change.new_display()),
+                ))
+                .push(SafeHtml::element(
+                    "td",
+                    None,
+                    SafeHtml::text(&change.old_display()),
+                ));
+                rows.push(SafeHtml::element("tr", None, row));
+            }
+            body.push(SafeHtml::element("table", None, rows));
         }
+
+        body.push(SafeHtml::trusted("<h2>Databases</h2>"));
+        if self.databases.is_empty() {
+            body.push(SafeHtml::trusted("<p>No databases are open.</p>"));
+        } else {
+            let mut rows = SafeHtml::trusted(
+                "<tr><th>Database</th><th>Size</th><th>WAL</th><th>Last checkpoint</th></tr>",
+            );
+            let formatter = &self.formatter;
+            for database in &self.databases {
+                let mut row = SafeHtml::element("td", None, SafeHtml::text(&database.name));
+                let checkpoint = match database.last_checkpoint {
+                    Some(at) => formatter.format_timestamp(at, TimestampStyle::Relative),
+                    None => "never".to_string(),
+                };
+                for cell in [
+                    formatter.format_bytes(database.page_count * database.page_size),
+                    formatter.format_bytes(database.wal_bytes),
+                    checkpoint,
+                ] {
+                    row.push(SafeHtml::element("td", None, SafeHtml::text(&cell)));
+                }
+                rows.push(SafeHtml::element("tr", None, row));
+            }
+            body.push(SafeHtml::element("table", None, rows));
+        }
+
+        body.push(SafeHtml::trusted("<h2>Message traces</h2>"));
+        if self.traces.is_empty() {
+            body.push(SafeHtml::trusted(
+                "<p>No traces recorded. Set <code>browser.message_trace_buffer</code> to record them.</p>",
+            ));
+        }
+        for trace in &self.traces {
+            body.push(SafeHtml::element(
+                "h3",
+                None,
+                SafeHtml::text(&format!(
+                    "Trace {} - {} messages in {}ms",
+                    trace.trace_id,
+                    trace.spans.len(),
+                    trace.duration().as_millis()
+                )),
+            ));
+            body.push(SafeHtml::element(
+                "pre",
+                None,
+                SafeHtml::text(&trace.to_string()),
+            ));
+        }
+
+        InternalPage::new("Diagnostics")
+            .with_style(ABOUT_DIAGNOSTICS_STYLE)
+            .with_body(body)
+            .render()
     }
 
     /// Generate an error page for a navigation error
     ///
+    /// The "Go Back" and "Try Again" actions are plain links to
+    /// [`GO_BACK_URL`] and [`RELOAD_URL`], which the engine resolves itself;
+    /// the page carries no script.
+    ///
     /// # Arguments
     ///
     /// * `error` - The navigation error that occurred
@@ -397,6 +1840,32 @@ impl Navigator {
     ///
     /// Returns HTML content for the error page.
     pub fn generate_error_page(&self, error: &NavigationError) -> String {
+        self.render_error_page(error, None)
+    }
+
+    /// Generate the error page for a failed main-frame load
+    ///
+    /// Like [`generate_error_page`](Self::generate_error_page), plus whether
+    /// the failure looked transient or permanent and whether the load was
+    /// already retried.
+    ///
+    /// # Arguments
+    ///
+    /// * `failure` - The logged failure
+    ///
+    /// # Returns
+    ///
+    /// Returns HTML content for the error page.
+    pub fn generate_failure_page(&self, failure: &NavigationFailure) -> String {
+        let mut note = format!("Failure type: {}", failure.class.as_str());
+        if failure.retried {
+            note.push_str(" (retried once)");
+        }
+        self.render_error_page(&failure.error, Some(&note))
+    }
+
+    /// Render an error page, with an optional line about the failure
+    fn render_error_page(&self, error: &NavigationError, note: Option<&str>) -> String {
         let (title, message, details) = match error {
             NavigationError::InvalidUrl(url) => (
                 "Invalid URL",
@@ -433,103 +1902,53 @@ impl Navigator {
                 "The page is redirecting in a way that will never complete.",
                 "This usually happens when the server is misconfigured.".to_string(),
             ),
+            NavigationError::Offline => (
+                "You are offline",
+                "This page is not in the cache, so it cannot be shown offline.",
+                "Pages you have visited before may still open. Go back online and retry."
+                    .to_string(),
+            ),
         };
 
-        format!(
-            r#"<!DOCTYPE html>
-<html>
-<head>
-    <title>{title}</title>
-    <style>
-        body {{
-            font-family: Arial, sans-serif;
-            margin: 0;
-            padding: 0;
-            background-color: #f5f5f5;
-        }}
-        .container {{
-            max-width: 600px;
-            margin: 100px auto;
-            background-color: white;
-            padding: 40px;
-            border-radius: 8px;
-            box-shadow: 0 2px 4px rgba(0,0,0,0.1);
-        }}
-        h1 {{
-            color: #d32f2f;
-            margin-top: 0;
-        }}
-        .message {{
-            font-size: 16px;
-            margin: 20px 0;
-            color: #333;
-        }}
-        .details {{
-            background-color: #f5f5f5;
-            padding: 15px;
-            border-radius: 4px;
-            font-family: monospace;
-            font-size: 14px;
-            margin: 20px 0;
-            word-break: break-all;
-        }}
-        .actions {{
-            margin-top: 30px;
-        }}
-        button {{
-            background-color: #1976d2;
-            color: white;
-            border: none;
-            padding: 12px 24px;
-            font-size: 14px;
-            border-radius: 4px;
-            cursor: pointer;
-            margin-right: 10px;
-        }}
-        button:hover {{
-            background-color: #1565c0;
-        }}
-        .suggestions {{
-            margin-top: 30px;
-            padding-top: 20px;
-            border-top: 1px solid #e0e0e0;
-        }}
-        .suggestions h3 {{
-            font-size: 16px;
-            margin-top: 0;
-        }}
-        .suggestions ul {{
-            padding-left: 20px;
-        }}
-        .suggestions li {{
-            margin: 8px 0;
-        }}
-    </style>
-</head>
-<body>
-    <div class="container">
-        <h1>{title}</h1>
-        <div class="message">{message}</div>
-        <div class="details">{details}</div>
-        <div class="actions">
-            <button onclick="history.back()">Go Back</button>
-            <button onclick="location.reload()">Try Again</button>
-        </div>
-        <div class="suggestions">
+        let mut actions = SafeHtml::link(GO_BACK_URL, "Go Back", Some("button"));
+        actions.push(SafeHtml::link(RELOAD_URL, "Try Again", Some("button")));
+
+        let mut container = SafeHtml::element("h1", None, SafeHtml::text(title));
+        container
+            .push(SafeHtml::element(
+                "div",
+                Some("message"),
+                SafeHtml::text(message),
+            ))
+            .push(SafeHtml::element(
+                "div",
+                Some("details"),
+                SafeHtml::text(&details),
+            ));
+        if let Some(note) = note {
+            container.push(SafeHtml::element(
+                "div",
+                Some("failure"),
+                SafeHtml::text(note),
+            ));
+        }
+        container
+            .push(SafeHtml::element("div", Some("actions"), actions))
+            .push(SafeHtml::trusted(
+                r#"<div class="suggestions">
             <h3>Suggestions:</h3>
             <ul>
                 <li>Check the URL for typos</li>
                 <li>Check your internet connection</li>
                 <li>Try reloading the page later</li>
             </ul>
-        </div>
-    </div>
-</body>
-</html>"#,
-            title = title,
-            message = message,
-            details = details
-        )
+        </div>"#,
+            ));
+
+        InternalPage::new(title)
+            .with_style(ERROR_PAGE_STYLE)
+            .with_body(SafeHtml::element("div", Some("container"), container))
+            .render()
     }
 
     /// Follow redirects up to a maximum count
@@ -571,6 +1990,9 @@ impl Navigator {
 
     /// Cancel the current navigation
     ///
+    /// Only resets the navigator's state; the fetch itself is aborted by
+    /// [`BrowserEngine::stop_loading`](crate::BrowserEngine::stop_loading).
+    ///
     /// # Returns
     ///
     /// Returns `Ok(())` if cancelled successfully.
@@ -849,6 +2271,716 @@ mod tests {
         assert!(result.is_err());
     }
 
+    // ========================================
+    // Tests for initiator policy
+    // ========================================
+
+    fn all_initiators() -> Vec<NavigationInitiator> {
+        vec![
+            NavigationInitiator::UserTyped,
+            NavigationInitiator::LinkClick { user_gesture: true },
+            NavigationInitiator::LinkClick {
+                user_gesture: false,
+            },
+            NavigationInitiator::Redirect {
+                from: Url::parse("https://example.com/").unwrap(),
+            },
+            NavigationInitiator::Script,
+            NavigationInitiator::SessionRestore,
+            NavigationInitiator::Reload,
+            NavigationInitiator::BackForward,
+        ]
+    }
+
+    #[test]
+    fn test_check_initiator_web_urls_always_allowed() {
+        let navigator = Navigator::new();
+        let url = Url::parse("https://example.com/page").unwrap();
+        for initiator in all_initiators() {
+            assert!(navigator.check_initiator(&url, &initiator).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_check_initiator_file_urls() {
+        let navigator = Navigator::new();
+        let url = Url::parse("file:///etc/passwd").unwrap();
+
+        for initiator in all_initiators() {
+            let allowed = navigator.check_initiator(&url, &initiator).is_ok();
+            let expected = matches!(
+                initiator,
+                NavigationInitiator::UserTyped
+                    | NavigationInitiator::LinkClick { user_gesture: true }
+                    | NavigationInitiator::SessionRestore
+                    | NavigationInitiator::Reload
+                    | NavigationInitiator::BackForward
+            );
+            assert_eq!(allowed, expected, "{:?}", initiator);
+        }
+
+        let from_file = NavigationInitiator::Redirect {
+            from: Url::parse("file:///tmp/index.html").unwrap(),
+        };
+        assert!(navigator.check_initiator(&url, &from_file).is_ok());
+    }
+
+    #[test]
+    fn test_check_initiator_internal_pages() {
+        let navigator = Navigator::new();
+        let storage = Url::parse("about:storage").unwrap();
+        let blank = Url::parse("about:blank").unwrap();
+
+        assert!(matches!(
+            navigator.check_initiator(&storage, &NavigationInitiator::Script),
+            Err(Error::NavigationBlocked(_))
+        ));
+        assert!(navigator
+            .check_initiator(&storage, &NavigationInitiator::UserTyped)
+            .is_ok());
+        assert!(navigator
+            .check_initiator(&blank, &NavigationInitiator::Script)
+            .is_ok());
+    }
+
+    /// 2026-03-10 15:00:00 UTC
+    const TEST_NOW: u64 = 1_773_154_800;
+
+    /// Navigator whose internal pages are formatted at [`TEST_NOW`]
+    fn navigator_at_test_now() -> Navigator {
+        let mut navigator = Navigator::new();
+        navigator.set_formatter(Formatter::new(
+            crate::format::EN_US,
+            std::sync::Arc::new(shared_types::MockClock::new(Duration::from_secs(TEST_NOW))),
+        ));
+        navigator
+    }
+
+    fn cache_summary(url: &str, expires_in: i64) -> CacheEntrySummary {
+        let now = TEST_NOW;
+        CacheEntrySummary {
+            url: url.to_string(),
+            stored_at: now - 120,
+            expires_at: (now as i64 + expires_in) as u64,
+            size: 2048,
+            hit_count: 3,
+            validator: Some("ETag \"v1\"".to_string()),
+            variant_count: 1,
+            partition: None,
+        }
+    }
+
+    #[test]
+    fn test_handle_about_cache_lists_entries_with_purge_actions() {
+        let mut navigator = navigator_at_test_now();
+        navigator.set_cache_snapshot(
+            None,
+            vec![
+                cache_summary("https://example.com/app.js", 3_600),
+                CacheEntrySummary {
+                    partition: Some("https://news.example".to_string()),
+                    ..cache_summary("https://cdn.example.org/old.css", -10)
+                },
+            ],
+        );
+
+        let html = navigator.handle_about("cache").unwrap();
+        assert!(html.contains("<title>HTTP Cache</title>"));
+        assert!(html.contains("https://example.com/app.js"));
+        assert!(html.contains("<td>shared</td>"));
+        assert!(html.contains("<td>https://news.example</td>"));
+        assert!(html.contains("2.0 KB"));
+        assert!(html.contains("<td>2 min</td>"));
+        assert!(html.contains("<td>in 1 h</td>"));
+        assert!(html.contains("expired"));
+        assert!(html.contains("ETag &quot;v1&quot;"));
+        assert!(html.contains("about:cache-purge?url&#x3D;https%3A%2F%2Fexample.com%2Fapp.js"));
+        assert!(html.contains("about:cache-purge?host&#x3D;cdn.example.org"));
+        assert!(html.contains("<input type=\"search\" name=\"q\" value=\"\">"));
+    }
+
+    #[test]
+    fn test_handle_about_cache_empty_and_filtered() {
+        let mut navigator = Navigator::new();
+        assert!(navigator
+            .handle_about("cache")
+            .unwrap()
+            .contains("The cache is empty."));
+
+        navigator.set_cache_snapshot(Some("\"><script>".to_string()), Vec::new());
+        assert_eq!(navigator.cache_filter(), Some("\"><script>"));
+        let html = navigator.handle_about("cache").unwrap();
+        assert!(html.contains("No cached URLs match."));
+        assert!(!html.contains("<script>"));
+    }
+
+    fn certificate(subject: &str, issuer: &str, not_after: i64) -> CertificateInfo {
+        CertificateInfo {
+            subject: subject.to_string(),
+            issuer: issuer.to_string(),
+            subject_alt_names: vec!["site.test".to_string()],
+            not_before: TEST_NOW as i64 - 86_400 * 90,
+            not_after,
+            key_type: "EC".to_string(),
+            key_bits: Some(256),
+            signature_algorithm: "ecdsa-with-SHA256".to_string(),
+            sha256_fingerprint: "AB:CD".to_string(),
+            der: vec![1, 2, 3],
+        }
+    }
+
+    #[test]
+    fn test_handle_about_certificate_flags_expiring_and_exception() {
+        let now = TEST_NOW as i64;
+        let mut navigator = navigator_at_test_now();
+        navigator.set_certificate_snapshot(
+            "site.test".to_string(),
+            Some(CertChainInfo {
+                host: "site.test".to_string(),
+                certificates: vec![
+                    certificate("CN=site.test", "CN=Test CA", now + 86_400 * 10),
+                    certificate("CN=Test CA", "CN=Test CA", now + 86_400 * 365),
+                ],
+                validation: CertValidation::PinnedException,
+                captured_unix: now,
+            }),
+            None,
+        );
+
+        let html = navigator.handle_about("certificate").unwrap();
+        assert!(html.contains("Certificate for site.test"));
+        assert!(html.contains("<p class=\"exception\">Accepted by a certificate exception"));
+        assert!(
+            html.contains("<details class=\"expiring\"><summary>Server certificate: CN=site.test")
+        );
+        assert!(html.contains("<details><summary>Root certificate: CN=Test CA"));
+        assert_eq!(html.matches("Expires in 10 d").count(), 1);
+        assert!(html.contains("EC 256 bits"));
+        assert!(html.contains("about:certificate-pem?host&#x3D;site.test&amp;index&#x3D;1"));
+        assert!(html.contains("about:certificate-pem?host&#x3D;site.test\""));
+    }
+
+    #[test]
+    fn test_handle_about_certificate_escapes_subjects_and_handles_missing_chain() {
+        let mut navigator = navigator_at_test_now();
+        navigator.set_certificate_snapshot("plain.test".to_string(), None, None);
+        let html = navigator.handle_about("certificate").unwrap();
+        assert!(html.contains("No certificate was recorded for this site."));
+        assert!(navigator
+            .handle_about("certificate-pem")
+            .unwrap()
+            .contains("No such certificate was recorded."));
+
+        let weird = "CN=<script>alert(1)</script>, O=\"Evil & Co\"";
+        navigator.set_certificate_snapshot(
+            "<b>.test".to_string(),
+            Some(CertChainInfo {
+                host: "<b>.test".to_string(),
+                certificates: vec![certificate(weird, weird, TEST_NOW as i64 - 1)],
+                validation: CertValidation::SystemRoots,
+                captured_unix: 0,
+            }),
+            Some(0),
+        );
+        let html = navigator.handle_about("certificate").unwrap();
+        assert!(!html.contains("<script>"));
+        assert!(!html.contains("<b>"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(html.contains("<details class=\"expired\">"));
+
+        let pem = navigator.handle_about("certificate-pem").unwrap();
+        assert!(pem
+            .contains("<pre>-----BEGIN CERTIFICATE-----\nAQID\n-----END CERTIFICATE-----\n</pre>"));
+    }
+
+    #[test]
+    fn test_handle_about_diagnostics_lists_extensions() {
+        use extension_api::{Hook, HookStats};
+
+        let mut navigator = Navigator::new();
+        assert!(navigator
+            .handle_about("diagnostics")
+            .unwrap()
+            .contains("No extensions are loaded."));
+
+        let mut stats = HookStats::default();
+        stats.record(Duration::from_micros(40), false);
+        stats.record(Duration::from_micros(60), true);
+        navigator.set_extension_diagnostics(vec![
+            ExtensionDiagnostics {
+                id: "cookie-banner-rejecter".to_string(),
+                name: "Cookie <banner> rejecter".to_string(),
+                enabled: true,
+                hooks: vec![(Hook::UserScripts, stats)],
+            },
+            ExtensionDiagnostics {
+                id: "off".to_string(),
+                name: "Off".to_string(),
+                enabled: false,
+                hooks: Vec::new(),
+            },
+        ]);
+
+        let html = navigator.handle_about("diagnostics").unwrap();
+        assert!(html.contains("<title>Diagnostics</title>"));
+        assert!(html.contains("Cookie &lt;banner&gt; rejecter (cookie-banner-rejecter) - enabled"));
+        assert!(html.contains("<td>user_scripts</td><td>2</td><td>50µs</td><td>60µs</td>"));
+        assert!(html.contains("<td class=\"panics\">1</td>"));
+        assert!(html.contains("<h3 class=\"disabled\">Off (off) - disabled</h3>"));
+    }
+
+    #[test]
+    fn test_handle_about_diagnostics_lists_filter_provenance() {
+        let mut navigator = Navigator::new();
+        assert!(navigator
+            .handle_about("diagnostics")
+            .unwrap()
+            .contains("No cached filter lists."));
+
+        navigator.set_filter_list_diagnostics(vec![
+            FilterListInfo {
+                name: "easylist".to_string(),
+                source: FilterListSource::Fetched,
+                rules: 70_000,
+            },
+            FilterListInfo {
+                name: "seed".to_string(),
+                source: FilterListSource::Seed,
+                rules: 30,
+            },
+        ]);
+
+        let html = navigator.handle_about("diagnostics").unwrap();
+        assert!(html.contains("<td>easylist</td><td>fetched</td><td>70,000</td>"));
+        assert!(html.contains("<td>seed</td><td class=\"seed\">seed</td><td>30</td>"));
+    }
+
+    #[test]
+    fn test_handle_about_diagnostics_lists_bandwidth_by_tab() {
+        let mut navigator = Navigator::new();
+        assert!(navigator
+            .handle_about("diagnostics")
+            .unwrap()
+            .contains("No tab has used the network yet."));
+
+        let usage = |requests, received| TabBandwidth {
+            requests,
+            sent: 100,
+            received_encoded: received,
+            received_decoded: received / 2,
+        };
+        navigator.set_bandwidth_diagnostics(
+            usage(3, 4_096),
+            vec![(7, usage(2, 2_048)), (3, usage(1, 1_024))],
+        );
+
+        let html = navigator.handle_about("diagnostics").unwrap();
+        let heavy = html.find("<tr><td>7</td><td>2</td>").unwrap();
+        let light = html.find("<tr><td>3</td><td>1</td>").unwrap();
+        assert!(heavy < light);
+        assert!(html.contains("in 3 requests"));
+    }
+
+    #[test]
+    fn test_handle_about_diagnostics_renders_trace_tree() {
+        use message_bus::TraceSpan;
+
+        let mut navigator = Navigator::new();
+        assert!(navigator
+            .handle_about("diagnostics")
+            .unwrap()
+            .contains("No traces recorded."));
+
+        let span = |span_id, parent_span, message: &str, sent_ms, took_ms| TraceSpan {
+            span_id,
+            parent_span,
+            message: message.to_string(),
+            sent_at: Duration::from_millis(sent_ms),
+            handled_in: Some(Duration::from_millis(took_ms)),
+        };
+        navigator.set_trace_diagnostics(vec![Trace {
+            trace_id: 42,
+            spans: vec![
+                span(1, None, "NavigateRequest", 0, 2),
+                span(2, Some(1), "HttpRequest", 2, 30),
+                span(3, Some(2), "HttpResponse", 32, 1),
+            ],
+        }]);
+
+        let html = navigator.handle_about("diagnostics").unwrap();
+        assert!(html.contains("Trace 42 - 3 messages in 33ms"));
+        assert!(html
+            .contains("NavigateRequest +0ms (2ms)\n  HttpRequest +2ms (30ms)\n    HttpResponse"));
+    }
+
+    #[test]
+    fn test_handle_about_diagnostics_shows_dns_prefetch_use() {
+        let mut navigator = Navigator::new();
+        navigator.set_dns_diagnostics(ResolverStats {
+            lookups: 40,
+            cache_hits: 30,
+            prefetched: 8,
+            prefetch_hits: 6,
+            failures: 1,
+        });
+
+        let html = navigator.handle_about("diagnostics").unwrap();
+        assert!(html.contains("<h2>DNS</h2>"));
+        assert!(html.contains("40 lookups, 30 from cache (75%), 1 failed"));
+        assert!(html.contains("Prefetched 8 hosts, 6 later used (75%)"));
+    }
+
+    #[test]
+    fn test_handle_about_diagnostics_lists_non_default_settings() {
+        let mut navigator = Navigator::new();
+        assert!(navigator
+            .handle_about("diagnostics")
+            .unwrap()
+            .contains("All settings have their default values."));
+
+        let mut config = config_manager::Config::default();
+        config.appearance.theme = "<dark>".to_string();
+        config.network.cache_size_mb = 100;
+        navigator.set_config_diagnostics(config.non_default_fields());
+
+        let html = navigator.handle_about("diagnostics").unwrap();
+        assert!(html.contains(
+            "<tr><td>appearance.theme</td><td>&quot;&lt;dark&gt;&quot;</td><td>&quot;auto&quot;</td></tr>"
+        ));
+        assert!(html.contains("<tr><td>network.cache_size_mb</td><td>100</td><td>500</td></tr>"));
+    }
+
+    #[test]
+    fn test_handle_about_diagnostics_lists_databases() {
+        let mut navigator = navigator_at_test_now();
+        assert!(navigator
+            .handle_about("diagnostics")
+            .unwrap()
+            .contains("No databases are open."));
+
+        let stats = |name: &str, last_checkpoint| DbStats {
+            name: name.to_string(),
+            path: None,
+            page_count: 3,
+            page_size: 4096,
+            wal_bytes: 1536,
+            last_checkpoint,
+        };
+        navigator.set_database_diagnostics(vec![
+            stats("history", Some(TEST_NOW as i64 - 180)),
+            stats("bookmarks", None),
+        ]);
+
+        let html = navigator.handle_about("diagnostics").unwrap();
+        for row in [
+            "<tr><td>history</td><td>12.0 KB</td><td>1.5 KB</td><td>3 minutes ago</td></tr>",
+            "<tr><td>bookmarks</td><td>12.0 KB</td><td>1.5 KB</td><td>never</td></tr>",
+        ] {
+            assert!(html.contains(row), "{}", row);
+        }
+    }
+
+    #[test]
+    fn test_handle_about_adblock_lists_subscriptions() {
+        let mut navigator = navigator_at_test_now();
+        assert!(navigator
+            .handle_about("adblock")
+            .unwrap()
+            .contains("No filter list subscriptions."));
+
+        let now = TEST_NOW;
+        navigator.set_adblock_subscriptions(vec![
+            FilterListSubscription {
+                id: 0,
+                title: "EasyList".to_string(),
+                url: "https://easylist.to/easylist/easylist.txt".to_string(),
+                enabled: true,
+                last_updated: Some(now - 7_200),
+                rule_count: 70_000,
+                expires_hint: Some(4 * 86_400),
+                etag: Some("\"v70000\"".to_string()),
+                last_modified: None,
+            },
+            FilterListSubscription {
+                id: 1,
+                title: "<Regional>".to_string(),
+                url: "https://lists.example/regional.txt".to_string(),
+                enabled: false,
+                last_updated: None,
+                rule_count: 0,
+                expires_hint: None,
+                etag: None,
+                last_modified: None,
+            },
+        ]);
+
+        let html = navigator.handle_about("adblock").unwrap();
+        assert!(html.contains("<title>Ad Blocking</title>"));
+        assert!(html.contains("<td>70,000</td><td>2 hours ago</td><td>enabled</td>"));
+        assert!(html.contains("about:adblock-toggle?id&#x3D;0&amp;enabled&#x3D;0"));
+        assert!(html.contains("&lt;Regional&gt;"));
+        assert!(html.contains("<td>never</td><td class=\"disabled\">disabled</td>"));
+        assert!(html.contains("about:adblock-toggle?id&#x3D;1&amp;enabled&#x3D;1"));
+        assert!(html.contains("about:adblock-update?force&#x3D;1"));
+
+        navigator.set_adblock_subscription_enabled(1, true);
+        let html = navigator.handle_about("adblock").unwrap();
+        assert!(html.contains("about:adblock-toggle?id&#x3D;1&amp;enabled&#x3D;0"));
+    }
+
+    #[test]
+    fn test_handle_about_history_relative_and_absolute_times() {
+        let mut navigator = navigator_at_test_now();
+        assert!(navigator
+            .handle_about("history")
+            .unwrap()
+            .contains("No history."));
+
+        let entry = |url: &str, title: &str, visits, ago: i64| HistoryEntry {
+            id: 0,
+            url: url.to_string(),
+            canonical_url: url.to_string(),
+            title: title.to_string(),
+            visit_count: visits,
+            last_visit: TEST_NOW as i64 - ago,
+            initiator: NavigationInitiator::UserTyped,
+            typed_count: 0,
+            is_post: false,
+        };
+        navigator.set_history_snapshot(vec![
+            entry("https://recent.test/", "<Recent>", 1_200, 3 * 60),
+            entry("https://yesterday.test/", "Yesterday", 2, 86_400 + 58 * 60),
+            entry("https://old.test/", "", 1, 365 * 86_400),
+        ]);
+
+        let html = navigator.handle_about("history").unwrap();
+        assert!(html.contains("<title>History</title>"));
+        assert!(html.contains("&lt;Recent&gt;"));
+        assert!(html.contains("<td>1,200</td><td class=\"when\">3 minutes ago</td>"));
+        assert!(html.contains("<td class=\"when\">Yesterday 14:02</td>"));
+        assert!(html.contains("<td class=\"when\">2025-03-10 15:00</td>"));
+        // Untitled pages are listed by URL
+        assert!(html.contains(">https://old.test/</a>"));
+    }
+
+    #[test]
+    fn test_handle_about_history_shows_redirect_source() {
+        let mut navigator = navigator_at_test_now();
+        navigator.set_history_snapshot(vec![HistoryEntry {
+            id: 0,
+            url: "https://www.example.com/home".to_string(),
+            canonical_url: "https://www.example.com/home".to_string(),
+            title: "Home".to_string(),
+            visit_count: 1,
+            last_visit: TEST_NOW as i64,
+            initiator: NavigationInitiator::Redirect {
+                from: Url::parse("http://example.com/?a=1&b=2").unwrap(),
+            },
+            typed_count: 0,
+            is_post: false,
+        }]);
+
+        let html = navigator.handle_about("history").unwrap();
+        assert!(html.contains(">Home</a>"));
+        assert!(html.contains(
+            "<div class=\"redirect\">Redirected from http://example.com/?a=1&amp;b=2</div>"
+        ));
+    }
+
+    #[test]
+    fn test_handle_about_performance_lists_worst_origins() {
+        let mut navigator = Navigator::new();
+        assert!(navigator
+            .handle_about("performance")
+            .unwrap()
+            .contains("No pages have been checked."));
+
+        navigator.set_performance_snapshot(vec![
+            (
+                BudgetMetric::PageLoad,
+                5_000,
+                vec![SlowOrigin {
+                    origin: "https://slow.example".to_string(),
+                    events: 1_204,
+                    typical_excess: 2_500,
+                    worst_url: "https://slow.example/<huge>".to_string(),
+                    worst_value: 42_000,
+                }],
+            ),
+            (BudgetMetric::Ttfb, 800, vec![]),
+            (BudgetMetric::TotalBytes, 0, vec![]),
+        ]);
+
+        let html = navigator.handle_about("performance").unwrap();
+        assert!(html.contains("<title>Slow Pages</title>"));
+        assert!(html.contains("<h2>Page load (budget 5.0 s)</h2>"));
+        assert!(html
+            .contains("<tr><td>https://slow.example</td><td>1,204</td><td>2.5 s</td><td>42.0 s"));
+        assert!(html.contains("https://slow.example/&lt;huge&gt;"));
+        assert!(html.contains("No page went over this budget."));
+        assert!(html.contains("<h2 class=\"disabled\">Bytes transferred (not checked)</h2>"));
+    }
+
+    #[test]
+    fn test_handle_about_bookmarks_multi_select_form() {
+        let mut navigator = Navigator::new();
+        assert!(navigator
+            .handle_about("bookmarks")
+            .unwrap()
+            .contains("No bookmarks."));
+
+        navigator.set_bookmarks_snapshot(vec![Bookmark {
+            id: 7,
+            url: "https://example.com/".to_string(),
+            canonical_url: "https://example.com/".to_string(),
+            title: "<Example>".to_string(),
+            created_at: 0,
+            keyword: None,
+            folder: Some("Work".to_string()),
+            folder_id: Some(1),
+            position: 0,
+            tags: vec!["docs".to_string(), "rust".to_string()],
+        }]);
+
+        let html = navigator.handle_about("bookmarks").unwrap();
+        assert!(html.contains("<form method=\"get\" action=\"about:bookmarks-action\">"));
+        assert!(html.contains("<input type=\"checkbox\" name=\"id\" value=\"7\">"));
+        for op in ["tag", "untag", "move", "delete"] {
+            assert!(html.contains(&format!("name=\"op\" value=\"{}\"", op)));
+        }
+        assert!(html.contains("&lt;Example&gt;"));
+        assert!(html.contains("<td>Work</td><td>docs, rust</td>"));
+        assert!(html.contains(
+            "<li>Work<a href=\"about:bookmarks-action?op&#x3D;open&amp;folder&#x3D;Work\">Open All in Tabs</a></li>"
+        ));
+    }
+
+    #[test]
+    fn test_handle_about_recovery_lists_salvaged_tables() {
+        let mut navigator = Navigator::new();
+        assert!(navigator
+            .handle_about("recovery")
+            .unwrap()
+            .contains("No profile database needed recovery."));
+
+        navigator.set_recovery_reports(vec![RecoveryReport {
+            name: "bookmarks".to_string(),
+            path: PathBuf::from("/p/bookmarks.db"),
+            problem: "database disk image is malformed".to_string(),
+            moved_to: PathBuf::from("/p/bookmarks.db.corrupt-20260310-150405"),
+            tables: vec![
+                crate::recovery::SalvagedTable {
+                    name: "bookmarks".to_string(),
+                    recovered: 1204,
+                    rejected: 0,
+                    complete: false,
+                },
+                crate::recovery::SalvagedTable {
+                    name: "bookmark_tags".to_string(),
+                    recovered: 3,
+                    rejected: 0,
+                    complete: true,
+                },
+            ],
+        }]);
+
+        let html = navigator.handle_about("recovery").unwrap();
+        assert!(html.contains("<h2>bookmarks</h2>"));
+        assert!(html.contains("(database disk image is malformed)"));
+        assert!(html.contains("/p/bookmarks.db.corrupt-20260310-150405"));
+        assert!(html.contains(
+            "<tr><td>bookmarks</td><td>1,204</td><td class=\"lost\">later rows unreadable</td></tr>"
+        ));
+        assert!(html.contains("<tr><td>bookmark_tags</td><td>3</td><td class=\"lost\"></td></tr>"));
+    }
+
+    fn storage_usage(origin: &str, bytes: u64, cookies: u32) -> shared_types::OriginUsage {
+        shared_types::OriginUsage {
+            origin: origin.to_string(),
+            kinds: vec![shared_types::StorageKind::LocalStorage],
+            bytes,
+            cookies,
+        }
+    }
+
+    #[test]
+    fn test_handle_about_storage_lists_origins_with_clear_actions() {
+        let mut navigator = Navigator::new();
+        navigator.set_storage_report(StorageReport {
+            origins: vec![
+                storage_usage("https://big.com", 5 * 1024 * 1024, 0),
+                storage_usage("https://small.com", 2048, 3),
+            ],
+            unsupported: None,
+        });
+
+        let html = navigator.handle_about("storage").unwrap();
+        let big = html.find("https://big.com").unwrap();
+        let small = html.find("https://small.com").unwrap();
+        assert!(big < small);
+        assert!(html.contains("5.0 MB"));
+        assert!(html.contains("2.0 KB"));
+        assert!(html.contains("about:storage-clear?origin"));
+        assert!(!html.contains("class=\"notice\""));
+    }
+
+    #[test]
+    fn test_handle_about_storage_reduced_view() {
+        let mut navigator = Navigator::new();
+        navigator.set_storage_report(StorageReport {
+            origins: vec![storage_usage("https://cookies.com", 0, 4)],
+            unsupported: Some("storage enumeration".to_string()),
+        });
+
+        let html = navigator.handle_about("storage").unwrap();
+        assert!(html.contains("class=\"notice\""));
+        assert!(html.contains("storage enumeration"));
+        assert!(html.contains("https://cookies.com"));
+        assert!(!html.contains("0 B"));
+
+        // No snapshot yet renders the reduced view too
+        let html = Navigator::new().handle_about("storage").unwrap();
+        assert!(html.contains("class=\"notice\""));
+        assert!(html.contains("No site data stored."));
+    }
+
+    #[test]
+    fn test_handle_about_resubmit_offers_resubmit_and_back() {
+        let mut navigator = Navigator::new();
+        navigator.set_resubmit_target(&Url::parse("https://shop.example/order?x=<b>").unwrap());
+
+        let html = navigator.handle_about("resubmit").unwrap();
+        assert!(html.contains("Confirm Form Resubmission"));
+        assert!(html.contains("https://shop.example/order?x=%3Cb%3E"));
+        assert!(html.contains(&format!("href=\"{}\"", RESUBMIT_URL)));
+        assert!(html.contains(&format!("href=\"{}\"", GO_BACK_URL)));
+    }
+
+    #[test]
+    fn test_handle_about_storage_escapes_origins() {
+        let mut navigator = Navigator::new();
+        navigator.set_storage_report(StorageReport {
+            origins: vec![storage_usage("<script>alert(1)</script>", 1, 0)],
+            unsupported: None,
+        });
+
+        let html = navigator.handle_about("storage").unwrap();
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn test_remove_storage_origin() {
+        let mut navigator = Navigator::new();
+        navigator.set_storage_report(StorageReport {
+            origins: vec![storage_usage("https://gone.com", 10, 0)],
+            unsupported: None,
+        });
+        navigator.remove_storage_origin("https://gone.com");
+
+        let html = navigator.handle_about("storage").unwrap();
+        assert!(!html.contains("https://gone.com"));
+    }
+
     #[test]
     fn test_handle_data_plain() {
         let navigator = Navigator::new();
@@ -951,6 +3083,88 @@ mod tests {
         assert!(html.contains("redirecting"));
     }
 
+    #[test]
+    fn test_generate_error_page_offline() {
+        let navigator = Navigator::new();
+        let html = navigator.generate_error_page(&NavigationError::Offline);
+        assert!(html.contains("You are offline"));
+        assert!(html.contains(&format!("href=\"{}\"", RELOAD_URL)));
+    }
+
+    #[test]
+    fn test_generate_error_page_escapes_adversarial_details() {
+        let navigator = Navigator::new();
+        let payloads = [
+            "</script><script>alert(1)</script>",
+            "\"><img src=x onerror=alert(1)>",
+            "javascript:alert(document.cookie)",
+        ];
+
+        for payload in payloads {
+            let errors = [
+                NavigationError::InvalidUrl(payload.to_string()),
+                NavigationError::UnsupportedProtocol(payload.to_string()),
+                NavigationError::NetworkError(payload.to_string()),
+                NavigationError::SslError(payload.to_string()),
+                NavigationError::FileNotFound(PathBuf::from(payload)),
+            ];
+            for error in errors {
+                let html = navigator.generate_error_page(&error);
+                assert!(!html.contains("<script"), "script injected: {html}");
+                assert!(!html.contains("<img"), "element injected: {html}");
+                assert!(!html.contains("href=\"javascript:"));
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_error_page_has_no_inline_handlers() {
+        let navigator = Navigator::new();
+        let html = navigator.generate_error_page(&NavigationError::Timeout);
+        assert!(!html.contains("onclick"));
+        assert!(html.contains(&format!("href=\"{}\"", GO_BACK_URL)));
+        assert!(html.contains(&format!("href=\"{}\"", RELOAD_URL)));
+    }
+
+    #[test]
+    fn test_generate_failure_page_shows_classification() {
+        use crate::failure::{FailureClass, NavigationFailure};
+
+        let navigator = Navigator::new();
+        let mut failure = NavigationFailure {
+            tab_id: 1,
+            url: Url::parse("https://example.com/").unwrap(),
+            error: NavigationError::NetworkError("Connection reset by peer".to_string()),
+            class: FailureClass::Transient,
+            auto_retry: false,
+            retried: true,
+            trace_id: None,
+        };
+        let html = navigator.generate_failure_page(&failure);
+        assert!(html.contains("Network Error"));
+        assert!(html.contains("Failure type: transient (retried once)"));
+
+        failure.class = FailureClass::Permanent;
+        failure.retried = false;
+        let html = navigator.generate_failure_page(&failure);
+        assert!(html.contains("Failure type: permanent"));
+        assert!(!html.contains("retried"));
+    }
+
+    #[test]
+    fn test_internal_pages_carry_csp_meta() {
+        let navigator = Navigator::new();
+        let pages = [
+            navigator.handle_about("blank").unwrap(),
+            navigator.handle_about("version").unwrap(),
+            navigator.generate_error_page(&NavigationError::RedirectLoop),
+        ];
+        for html in pages {
+            assert!(html.contains("http-equiv=\"Content-Security-Policy\""));
+            assert!(!html.contains("<script"));
+        }
+    }
+
     // ========================================
     // Tests for Redirect Handling
     // ========================================
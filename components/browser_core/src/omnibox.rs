@@ -0,0 +1,513 @@
+//! URL bar input resolution and keyword bookmarks
+//!
+//! Text typed in the URL bar resolves, in order of precedence, to:
+//!
+//! 1. the URL itself, when it has a scheme (`https://…`, `about:…`)
+//! 2. a keyword bookmark, when the first word is a bookmark keyword
+//!    (`gh tokio channel` with `gh` → `https://github.com/search?q=%s`)
+//! 3. a search on a specific engine, when the first word is its keyword
+//! 4. an `https://` URL, when the input looks like a host
+//! 5. a search on the default engine
+//!
+//! Keywords are lowercase tokens shared by bookmarks and search engines, so
+//! a bookmark keyword may not shadow an engine keyword.
+//!
+//! The default engine is named by a built-in id or given as a search URL
+//! containing `%s`, such as `https://search.example/?q=%s`.
+
+use crate::errors::{Error, Result};
+use url::form_urlencoded;
+use url::Url;
+
+/// Placeholder replaced by the search terms in a keyword bookmark URL
+pub const KEYWORD_PLACEHOLDER: &str = "%s";
+
+/// Longest accepted keyword, in characters
+pub const MAX_KEYWORD_LEN: usize = 32;
+
+/// Schemes that make the input a URL even without `//`
+const URL_SCHEMES: &[&str] = &["http", "https", "file", "about", "data"];
+
+/// A built-in search engine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchEngine {
+    /// Identifier used by `browser.default_search_engine`
+    pub id: &'static str,
+    /// Display name
+    pub name: &'static str,
+    /// Keyword that searches this engine from the URL bar
+    pub keyword: &'static str,
+    /// Search URL with [`KEYWORD_PLACEHOLDER`] for the terms
+    pub url_template: &'static str,
+}
+
+/// Built-in search engines; the first is the fallback default
+pub const SEARCH_ENGINES: &[SearchEngine] = &[
+    SearchEngine {
+        id: "google",
+        name: "Google",
+        keyword: "g",
+        url_template: "https://www.google.com/search?q=%s",
+    },
+    SearchEngine {
+        id: "duckduckgo",
+        name: "DuckDuckGo",
+        keyword: "ddg",
+        url_template: "https://duckduckgo.com/?q=%s",
+    },
+    SearchEngine {
+        id: "bing",
+        name: "Bing",
+        keyword: "bing",
+        url_template: "https://www.bing.com/search?q=%s",
+    },
+    SearchEngine {
+        id: "wikipedia",
+        name: "Wikipedia",
+        keyword: "wp",
+        url_template: "https://en.wikipedia.org/wiki/Special:Search?search=%s",
+    },
+];
+
+/// Engine id reported for searches on a default engine given as a URL
+pub const CUSTOM_ENGINE_ID: &str = "custom";
+
+/// Look up a search engine by id, falling back to the first built-in one
+pub fn search_engine(id: &str) -> &'static SearchEngine {
+    SEARCH_ENGINES
+        .iter()
+        .find(|engine| engine.id.eq_ignore_ascii_case(id))
+        .unwrap_or(&SEARCH_ENGINES[0])
+}
+
+/// Search on the default engine
+///
+/// # Arguments
+///
+/// * `default_engine` - Built-in engine id, or a search URL containing
+///   [`KEYWORD_PLACEHOLDER`]; anything else means the first built-in engine
+/// * `terms` - Search terms, already trimmed
+///
+/// # Errors
+///
+/// Returns `Error::InvalidUrl` if the search URL cannot be built.
+pub fn default_search(default_engine: &str, terms: &str) -> Result<ResolvedInput> {
+    let default_engine = default_engine.trim();
+    if default_engine.contains(KEYWORD_PLACEHOLDER) {
+        if let Ok(url) = expand_keyword(CUSTOM_ENGINE_ID, default_engine, terms) {
+            if matches!(url.scheme(), "http" | "https") {
+                return Ok(ResolvedInput::Search {
+                    engine: CUSTOM_ENGINE_ID,
+                    url,
+                });
+            }
+        }
+    }
+    let engine = search_engine(default_engine);
+    Ok(ResolvedInput::Search {
+        engine: engine.id,
+        url: expand_keyword(engine.keyword, engine.url_template, terms)?,
+    })
+}
+
+/// What URL bar input resolved to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvedInput {
+    /// The input was a URL (or a host)
+    Url(Url),
+    /// The first word was a bookmark keyword
+    Keyword {
+        /// Keyword that matched
+        keyword: String,
+        /// Bookmark URL with the terms substituted
+        url: Url,
+    },
+    /// A search-engine search
+    Search {
+        /// Engine id
+        engine: &'static str,
+        /// Search URL
+        url: Url,
+    },
+}
+
+impl ResolvedInput {
+    /// URL to navigate to
+    pub fn url(&self) -> &Url {
+        match self {
+            ResolvedInput::Url(url) => url,
+            ResolvedInput::Keyword { url, .. } | ResolvedInput::Search { url, .. } => url,
+        }
+    }
+}
+
+/// A keyword bookmark offered in the URL bar dropdown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordSuggestion {
+    /// Bookmark keyword
+    pub keyword: String,
+    /// Bookmark title
+    pub title: String,
+    /// Bookmark URL (may contain [`KEYWORD_PLACEHOLDER`])
+    pub url: String,
+}
+
+impl KeywordSuggestion {
+    /// Dropdown text, e.g. `gh — GitHub search`
+    pub fn label(&self) -> String {
+        if self.title.is_empty() {
+            format!("{} \u{2014} {}", self.keyword, self.url)
+        } else {
+            format!("{} \u{2014} {}", self.keyword, self.title)
+        }
+    }
+}
+
+/// A row of the URL bar dropdown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UrlSuggestion {
+    /// A page from history
+    History {
+        /// Page URL
+        url: String,
+        /// Page title
+        title: String,
+        /// Number of visits
+        visit_count: i32,
+    },
+    /// A bookmarked page
+    Bookmark {
+        /// Bookmark URL
+        url: String,
+        /// Bookmark title
+        title: String,
+    },
+    /// Searching for the input on the default engine
+    Search {
+        /// The input, trimmed
+        query: String,
+        /// Search URL
+        url: Url,
+    },
+}
+
+impl UrlSuggestion {
+    /// URL to navigate to when the row is chosen
+    pub fn url(&self) -> &str {
+        match self {
+            UrlSuggestion::History { url, .. } | UrlSuggestion::Bookmark { url, .. } => url,
+            UrlSuggestion::Search { url, .. } => url.as_str(),
+        }
+    }
+}
+
+/// Check that a keyword is a lowercase token
+///
+/// Keywords consist of lowercase letters, digits, `-` and `_`, and are at
+/// most [`MAX_KEYWORD_LEN`] characters long.
+///
+/// # Errors
+///
+/// Returns `Error::InvalidKeyword` explaining what is wrong.
+pub fn validate_keyword(keyword: &str) -> Result<()> {
+    if keyword.is_empty() {
+        return Err(Error::InvalidKeyword("keyword is empty".to_string()));
+    }
+    if keyword.chars().count() > MAX_KEYWORD_LEN {
+        return Err(Error::InvalidKeyword(format!(
+            "'{}' is longer than {} characters",
+            keyword, MAX_KEYWORD_LEN
+        )));
+    }
+    if keyword.chars().any(char::is_whitespace) {
+        return Err(Error::InvalidKeyword(format!(
+            "'{}' contains whitespace",
+            keyword
+        )));
+    }
+    if keyword.chars().any(char::is_uppercase) {
+        return Err(Error::InvalidKeyword(format!(
+            "'{}' must be lowercase",
+            keyword
+        )));
+    }
+    if let Some(c) = keyword
+        .chars()
+        .find(|c| !(c.is_alphanumeric() || *c == '-' || *c == '_'))
+    {
+        return Err(Error::InvalidKeyword(format!(
+            "'{}' contains '{}'; use letters, digits, '-' or '_'",
+            keyword, c
+        )));
+    }
+    Ok(())
+}
+
+/// Reject a bookmark keyword that a search engine already uses
+///
+/// # Errors
+///
+/// Returns `Error::KeywordConflict` naming the engine.
+pub fn check_engine_conflict(keyword: &str) -> Result<()> {
+    match SEARCH_ENGINES
+        .iter()
+        .find(|engine| engine.keyword == keyword)
+    {
+        Some(engine) => Err(Error::KeywordConflict {
+            keyword: keyword.to_string(),
+            owner: format!("the {} search engine", engine.name),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Substitute search terms into a keyword URL
+///
+/// The terms are URL-encoded (spaces become `+`). Without terms, a URL
+/// with no placeholder is used as is.
+///
+/// # Arguments
+///
+/// * `keyword` - Keyword being expanded (for the error message)
+/// * `template` - Bookmark URL
+/// * `terms` - Text after the keyword, already trimmed
+///
+/// # Errors
+///
+/// Returns `Error::KeywordTakesNoTerms` if terms were given but the URL
+/// has no placeholder, or `Error::InvalidUrl` if the result is not a URL.
+pub fn expand_keyword(keyword: &str, template: &str, terms: &str) -> Result<Url> {
+    if !template.contains(KEYWORD_PLACEHOLDER) && !terms.is_empty() {
+        return Err(Error::KeywordTakesNoTerms(keyword.to_string()));
+    }
+    let encoded: String = form_urlencoded::byte_serialize(terms.as_bytes()).collect();
+    let expanded = template.replace(KEYWORD_PLACEHOLDER, &encoded);
+    Url::parse(&expanded).map_err(|e| Error::InvalidUrl(format!("{}: {}", expanded, e)))
+}
+
+/// Resolve URL bar input
+///
+/// # Arguments
+///
+/// * `input` - Text typed in the URL bar
+/// * `bookmark_keyword` - Looks up the URL of the bookmark with a keyword
+/// * `default_engine` - Id or search URL of the default search engine (see
+///   [`default_search`])
+///
+/// # Errors
+///
+/// Returns `Error::InvalidUrl` for empty input, or the error of
+/// [`expand_keyword`] when a keyword bookmark cannot take the terms.
+pub fn resolve_input(
+    input: &str,
+    bookmark_keyword: impl Fn(&str) -> Option<String>,
+    default_engine: &str,
+) -> Result<ResolvedInput> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(Error::InvalidUrl("empty input".to_string()));
+    }
+
+    if let Some(url) = parse_full_url(input) {
+        return Ok(ResolvedInput::Url(url));
+    }
+
+    let (first, terms) = match input.split_once(char::is_whitespace) {
+        Some((first, rest)) => (first, rest.trim()),
+        None => (input, ""),
+    };
+    let keyword = first.to_lowercase();
+    if let Some(template) = bookmark_keyword(&keyword) {
+        let url = expand_keyword(&keyword, &template, terms)?;
+        return Ok(ResolvedInput::Keyword { keyword, url });
+    }
+    if !terms.is_empty() {
+        if let Some(engine) = SEARCH_ENGINES.iter().find(|e| e.keyword == keyword) {
+            return Ok(ResolvedInput::Search {
+                engine: engine.id,
+                url: expand_keyword(engine.keyword, engine.url_template, terms)?,
+            });
+        }
+    }
+
+    if let Some(url) = parse_host_like(input) {
+        return Ok(ResolvedInput::Url(url));
+    }
+
+    default_search(default_engine, input)
+}
+
+/// Parse input that carries its own scheme
+fn parse_full_url(input: &str) -> Option<Url> {
+    if input.contains(char::is_whitespace) {
+        return None;
+    }
+    let url = Url::parse(input).ok()?;
+    (input.contains("://") || URL_SCHEMES.contains(&url.scheme())).then_some(url)
+}
+
+/// Treat `example.com/path` or `localhost:8080` as an `https://` URL
+fn parse_host_like(input: &str) -> Option<Url> {
+    if input.contains(char::is_whitespace) {
+        return None;
+    }
+    let url = Url::parse(&format!("https://{}", input)).ok()?;
+    let host = url.host_str()?;
+    let looks_like_host = host == "localhost"
+        || url
+            .host()
+            .is_some_and(|h| !matches!(h, url::Host::Domain(_)))
+        || host.split('.').filter(|label| !label.is_empty()).count() > 1;
+    looks_like_host.then_some(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keywords(keyword: &str) -> Option<String> {
+        match keyword {
+            "gh" => Some("https://github.com/search?q=%s".to_string()),
+            "mail" => Some("https://mail.example.com/".to_string()),
+            _ => None,
+        }
+    }
+
+    // ========================================
+    // Tests for expand_keyword
+    // ========================================
+
+    #[test]
+    fn test_expand_keyword_encodes_terms() {
+        let url = expand_keyword("gh", "https://github.com/search?q=%s", "tokio channel").unwrap();
+        assert_eq!(url.as_str(), "https://github.com/search?q=tokio+channel");
+
+        let url = expand_keyword("gh", "https://github.com/search?q=%s", "café & co").unwrap();
+        assert_eq!(url.as_str(), "https://github.com/search?q=caf%C3%A9+%26+co");
+    }
+
+    #[test]
+    fn test_expand_keyword_without_placeholder() {
+        let url = expand_keyword("mail", "https://mail.example.com/", "").unwrap();
+        assert_eq!(url.as_str(), "https://mail.example.com/");
+
+        let result = expand_keyword("mail", "https://mail.example.com/", "inbox");
+        assert!(matches!(result, Err(Error::KeywordTakesNoTerms(k)) if k == "mail"));
+    }
+
+    // ========================================
+    // Tests for keyword validation
+    // ========================================
+
+    #[test]
+    fn test_validate_keyword() {
+        assert!(validate_keyword("gh").is_ok());
+        assert!(validate_keyword("my-wiki_2").is_ok());
+        let long = "k".repeat(MAX_KEYWORD_LEN + 1);
+        for bad in ["", "Gh", "g h", "gh/", "a.b", long.as_str()] {
+            assert!(
+                matches!(validate_keyword(bad), Err(Error::InvalidKeyword(_))),
+                "{:?} accepted",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_engine_keyword_conflict() {
+        assert!(check_engine_conflict("gh").is_ok());
+        let err = check_engine_conflict("ddg").unwrap_err();
+        assert!(err.to_string().contains("DuckDuckGo"));
+    }
+
+    // ========================================
+    // Tests for resolve_input
+    // ========================================
+
+    #[test]
+    fn test_resolve_keyword_beats_search_fallback() {
+        let resolved = resolve_input("gh tokio channel", keywords, "google").unwrap();
+        assert_eq!(
+            resolved,
+            ResolvedInput::Keyword {
+                keyword: "gh".to_string(),
+                url: Url::parse("https://github.com/search?q=tokio+channel").unwrap(),
+            }
+        );
+
+        let resolved = resolve_input("GH tokio", keywords, "google").unwrap();
+        assert!(matches!(resolved, ResolvedInput::Keyword { .. }));
+    }
+
+    #[test]
+    fn test_resolve_bare_keyword() {
+        let resolved = resolve_input("mail", keywords, "google").unwrap();
+        assert_eq!(resolved.url().as_str(), "https://mail.example.com/");
+        assert!(resolve_input("mail inbox", keywords, "google").is_err());
+    }
+
+    #[test]
+    fn test_resolve_full_url_beats_keyword() {
+        let resolved = resolve_input("https://gh.example.com/x", keywords, "google").unwrap();
+        assert_eq!(
+            resolved,
+            ResolvedInput::Url(Url::parse("https://gh.example.com/x").unwrap())
+        );
+        let resolved = resolve_input("about:blank", keywords, "google").unwrap();
+        assert!(matches!(resolved, ResolvedInput::Url(_)));
+    }
+
+    #[test]
+    fn test_resolve_hosts_and_searches() {
+        let resolved = resolve_input("example.com/docs", keywords, "google").unwrap();
+        assert_eq!(resolved.url().as_str(), "https://example.com/docs");
+        let resolved = resolve_input("localhost:8080", keywords, "google").unwrap();
+        assert_eq!(resolved.url().as_str(), "https://localhost:8080/");
+        let resolved = resolve_input("[::1]:8080/status", keywords, "google").unwrap();
+        assert_eq!(resolved.url().as_str(), "https://[::1]:8080/status");
+        let resolved = resolve_input("192.168.1.1", keywords, "google").unwrap();
+        assert_eq!(resolved.url().as_str(), "https://192.168.1.1/");
+
+        let resolved = resolve_input("ddg rust lang", keywords, "google").unwrap();
+        assert_eq!(
+            resolved.url().as_str(),
+            "https://duckduckgo.com/?q=rust+lang"
+        );
+
+        let resolved = resolve_input("rust lang", keywords, "bing").unwrap();
+        assert!(matches!(
+            resolved,
+            ResolvedInput::Search { engine: "bing", .. }
+        ));
+        let resolved = resolve_input("rust", keywords, "unknown").unwrap();
+        assert_eq!(
+            resolved.url().as_str(),
+            "https://www.google.com/search?q=rust"
+        );
+    }
+
+    #[test]
+    fn test_resolve_with_custom_search_url() {
+        let custom = "https://search.example/find?q=%s";
+        let resolved = resolve_input("rust lang", keywords, custom).unwrap();
+        assert_eq!(
+            resolved,
+            ResolvedInput::Search {
+                engine: CUSTOM_ENGINE_ID,
+                url: Url::parse("https://search.example/find?q=rust+lang").unwrap(),
+            }
+        );
+
+        // Neither an id nor a usable web URL: the first built-in engine
+        for bad in ["search.example/?q=%s", "javascript:alert(%s)"] {
+            let resolved = resolve_input("rust", keywords, bad).unwrap();
+            assert!(matches!(
+                resolved,
+                ResolvedInput::Search {
+                    engine: "google",
+                    ..
+                }
+            ));
+        }
+    }
+}
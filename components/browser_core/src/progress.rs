@@ -0,0 +1,376 @@
+//! Per-tab loading progress
+//!
+//! Platforms report coarse progress or none at all, so the engine
+//! estimates its own from what it can see of a load:
+//!
+//! - the document download: bytes received against `Content-Length`, or
+//!   a curve that approaches (but never reaches) the end when the length
+//!   is unknown;
+//! - subresources: those the document references, counted by
+//!   [`SubresourceScanner`] as it streams in, against the requests made
+//!   for the tab that have completed.
+//!
+//! A platform signal, where there is one, is blended in by taking the
+//! larger value. The estimate never goes backwards and stays at or below
+//! [`STREAMING_CAP`] until the load completes, so a page that keeps
+//! streaming does not sit at 100%. [`ProgressEstimator::poll`] throttles
+//! what is published to one value per [`PROGRESS_INTERVAL`].
+
+use crate::title::find_tag;
+use std::time::{Duration, Instant};
+
+/// Shortest time between two published progress values of a tab
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Highest progress shown before the load completes
+pub const STREAMING_CAP: f32 = 0.95;
+
+/// Progress shown as soon as the request is on its way
+const STARTED: f32 = 0.05;
+
+/// Share of the remaining progress that the document itself accounts for
+const DOCUMENT_WEIGHT: f32 = 0.6;
+
+/// Bytes after which a document of unknown length counts as half received
+const UNKNOWN_LENGTH_HALF: u64 = 64 * 1024;
+
+/// Longest unfinished tag carried over between chunks by
+/// [`SubresourceScanner`]
+const MAX_PENDING_TAG: usize = 4 * 1024;
+
+/// What a progress estimate was computed from, for debugging
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProgressInputs {
+    /// Document body bytes received
+    pub document_bytes: u64,
+    /// Document length from `Content-Length`, if known
+    pub document_total: Option<u64>,
+    /// Subresources the document references
+    pub subresources_discovered: u64,
+    /// Requests made for the page that have completed
+    pub subresources_completed: u64,
+    /// Latest platform progress signal, in thousandths
+    pub platform_permille: Option<u16>,
+}
+
+/// Progress estimate for one load
+///
+/// Feed it what happens during the load and [`poll`](Self::poll) it for
+/// values to publish. [`reset`](Self::reset) it when the tab navigates
+/// again.
+#[derive(Debug, Clone, Default)]
+pub struct ProgressEstimator {
+    inputs: ProgressInputs,
+    /// Highest estimate so far
+    reported: f32,
+    /// The load has completed
+    complete: bool,
+    /// Last published value and when it was published
+    last_published: Option<(f32, Instant)>,
+}
+
+impl ProgressEstimator {
+    /// Estimator for a load that has just started
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start over for a new load
+    ///
+    /// The next value is published at once, however recently the previous
+    /// load published one.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Note how much of the document has arrived
+    ///
+    /// # Arguments
+    ///
+    /// * `received` - Body bytes received so far
+    /// * `total` - Body length from `Content-Length`, if known
+    pub fn document_progress(&mut self, received: u64, total: Option<u64>) {
+        self.inputs.document_bytes = received;
+        self.inputs.document_total = total.filter(|&total| total > 0);
+    }
+
+    /// Note subresources found in the document
+    pub fn subresources_discovered(&mut self, count: u64) {
+        self.inputs.subresources_discovered += count;
+    }
+
+    /// Note how many of the page's requests have completed
+    ///
+    /// Counts may arrive out of order; a lower count than one already seen
+    /// is ignored.
+    pub fn subresources_completed(&mut self, count: u64) {
+        self.inputs.subresources_completed = self.inputs.subresources_completed.max(count);
+    }
+
+    /// Note the platform's own progress signal
+    ///
+    /// # Arguments
+    ///
+    /// * `progress` - 0.0 to 1.0; values outside are clamped
+    pub fn platform_progress(&mut self, progress: f32) {
+        let permille = (progress.clamp(0.0, 1.0) * 1000.0).round() as u16;
+        self.inputs.platform_permille = Some(permille);
+    }
+
+    /// Note that the load completed; the progress becomes 1.0
+    pub fn finish(&mut self) {
+        self.complete = true;
+    }
+
+    /// What the estimate is computed from
+    pub fn inputs(&self) -> &ProgressInputs {
+        &self.inputs
+    }
+
+    /// Current estimate, 0.0 to 1.0
+    ///
+    /// Never lower than an earlier estimate of the same load.
+    pub fn progress(&mut self) -> f32 {
+        self.reported = self.reported.max(self.estimate());
+        self.reported
+    }
+
+    /// The value to publish now, if any
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Current time
+    ///
+    /// # Returns
+    ///
+    /// The current estimate if it changed since the last published value
+    /// and [`PROGRESS_INTERVAL`] has passed since then. Completion is
+    /// published without waiting.
+    pub fn poll(&mut self, now: Instant) -> Option<f32> {
+        let progress = self.progress();
+        let due = match self.last_published {
+            None => true,
+            Some((last, _)) if last == progress => false,
+            Some(_) if self.complete => true,
+            Some((_, at)) => now.saturating_duration_since(at) >= PROGRESS_INTERVAL,
+        };
+        if due {
+            self.last_published = Some((progress, now));
+        }
+        due.then_some(progress)
+    }
+
+    /// Estimate from the inputs alone, before smoothing
+    fn estimate(&self) -> f32 {
+        if self.complete {
+            return 1.0;
+        }
+        let inputs = &self.inputs;
+        let bytes = inputs.document_bytes as f32;
+        let document = match inputs.document_total {
+            Some(total) => (bytes / total as f32).min(1.0),
+            None => bytes / (bytes + UNKNOWN_LENGTH_HALF as f32),
+        };
+        let discovered = inputs
+            .subresources_discovered
+            .max(inputs.subresources_completed);
+        let subresources = if discovered == 0 {
+            // Nothing known to wait for beyond the document
+            document
+        } else {
+            inputs.subresources_completed as f32 / discovered as f32
+        };
+
+        let own = STARTED
+            + (1.0 - STARTED)
+                * (DOCUMENT_WEIGHT * document + (1.0 - DOCUMENT_WEIGHT) * subresources);
+        let platform = inputs
+            .platform_permille
+            .map_or(0.0, |permille| f32::from(permille) / 1000.0);
+        own.max(platform).min(STREAMING_CAP)
+    }
+}
+
+/// Counts the subresources a document references while it streams in
+///
+/// Counts `<img>`, `<iframe>`, `<video>`, `<audio>`, `<embed>` and
+/// `<script>` tags with a `src`, and `<link>` tags for stylesheets, icons
+/// and preloads. Tags split across chunks are counted once complete.
+#[derive(Debug, Default)]
+pub struct SubresourceScanner {
+    /// Unfinished tag at the end of the last chunk, lowercased
+    pending: Vec<u8>,
+}
+
+impl SubresourceScanner {
+    /// Scanner for a new document
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scan the next chunk of the body
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of subresources found in the chunk.
+    pub fn feed(&mut self, chunk: &[u8]) -> u64 {
+        let mut text = std::mem::take(&mut self.pending);
+        text.extend(chunk.iter().map(u8::to_ascii_lowercase));
+
+        let mut found = 0;
+        let mut at = 0;
+        while let Some(open) = text[at..].iter().position(|&b| b == b'<').map(|i| at + i) {
+            let Some(len) = text[open..].iter().position(|&b| b == b'>') else {
+                if text.len() - open <= MAX_PENDING_TAG {
+                    self.pending = text[open..].to_vec();
+                }
+                break;
+            };
+            let tag = &text[open..open + len + 1];
+            if is_subresource(tag) {
+                found += 1;
+            }
+            at = open + len + 1;
+        }
+        found
+    }
+}
+
+/// Whether a complete, lowercased tag loads a subresource
+fn is_subresource(tag: &[u8]) -> bool {
+    let named = |name: &[u8]| find_tag(tag, name, 0) == Some(0);
+    let has = |attribute: &[u8]| tag.windows(attribute.len()).any(|w| w == attribute);
+
+    if ["<img", "<iframe", "<video", "<audio", "<embed", "<script"]
+        .iter()
+        .any(|name| named(name.as_bytes()))
+    {
+        return has(b" src=") || has(b"\tsrc=") || has(b"\nsrc=");
+    }
+    named(b"<link")
+        && ["stylesheet", "icon", "preload", "modulepreload"]
+            .iter()
+            .any(|rel| has(rel.as_bytes()))
+        && has(b"href=")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequence(estimator: &mut ProgressEstimator) -> Vec<f32> {
+        let mut values = vec![estimator.progress()];
+        estimator.document_progress(50_000, Some(100_000));
+        estimator.subresources_discovered(4);
+        values.push(estimator.progress());
+        // Completions reported out of order
+        for completed in [3, 1, 4, 2] {
+            estimator.subresources_completed(completed);
+            values.push(estimator.progress());
+        }
+        estimator.document_progress(100_000, Some(100_000));
+        values.push(estimator.progress());
+        values
+    }
+
+    #[test]
+    fn test_progress_is_monotonic() {
+        let mut estimator = ProgressEstimator::new();
+        let values = sequence(&mut estimator);
+        assert!(values.windows(2).all(|w| w[0] <= w[1]), "{:?}", values);
+
+        // More subresources turning up lowers the raw estimate, not the
+        // published one
+        let before = estimator.progress();
+        estimator.subresources_discovered(20);
+        assert_eq!(estimator.progress(), before);
+        assert_eq!(estimator.inputs().subresources_completed, 4);
+    }
+
+    #[test]
+    fn test_platform_signal_blends_by_max() {
+        let mut estimator = ProgressEstimator::new();
+        estimator.document_progress(10_000, Some(100_000));
+        let own = estimator.progress();
+
+        estimator.platform_progress(0.7);
+        assert_eq!(estimator.progress(), 0.7);
+        assert_eq!(estimator.inputs().platform_permille, Some(700));
+
+        // A platform signal behind our own estimate changes nothing
+        let mut behind = ProgressEstimator::new();
+        behind.document_progress(10_000, Some(100_000));
+        behind.platform_progress(0.01);
+        assert_eq!(behind.progress(), own);
+    }
+
+    #[test]
+    fn test_streaming_forever_is_capped_until_complete() {
+        let mut estimator = ProgressEstimator::new();
+        estimator.platform_progress(1.0);
+        for mb in 1..=100 {
+            estimator.document_progress(mb * 1024 * 1024, None);
+            assert!(estimator.progress() <= STREAMING_CAP);
+        }
+        assert_eq!(estimator.progress(), STREAMING_CAP);
+
+        estimator.finish();
+        assert_eq!(estimator.progress(), 1.0);
+    }
+
+    #[test]
+    fn test_poll_throttles_publication() {
+        let start = Instant::now();
+        let mut estimator = ProgressEstimator::new();
+        assert_eq!(estimator.poll(start), Some(STARTED));
+
+        let mut published = 0;
+        for ms in 1..=500u64 {
+            estimator.document_progress(ms * 100, Some(100_000));
+            if estimator.poll(start + Duration::from_millis(ms)).is_some() {
+                published += 1;
+            }
+        }
+        assert_eq!(published, 5);
+
+        // Nothing new, nothing published
+        let later = start + Duration::from_secs(5);
+        assert_eq!(estimator.poll(later), None);
+
+        // Completion does not wait for the interval
+        estimator.finish();
+        assert_eq!(estimator.poll(later), Some(1.0));
+        assert_eq!(estimator.poll(later + PROGRESS_INTERVAL), None);
+    }
+
+    #[test]
+    fn test_reset_on_rapid_renavigation() {
+        let start = Instant::now();
+        let mut estimator = ProgressEstimator::new();
+        estimator.document_progress(10_000, Some(100_000));
+        estimator.platform_progress(0.8);
+        assert_eq!(estimator.poll(start), Some(0.8));
+
+        // A new navigation 10 ms later starts from the beginning and is
+        // published at once
+        estimator.reset();
+        assert_eq!(estimator.inputs(), &ProgressInputs::default());
+        assert_eq!(
+            estimator.poll(start + Duration::from_millis(10)),
+            Some(STARTED)
+        );
+    }
+
+    #[test]
+    fn test_scanner_counts_subresources_across_chunks() {
+        let mut scanner = SubresourceScanner::new();
+        let chunks: [&[u8]; 4] = [
+            b"<html><head><link rel=\"styleSheet\" href=\"a.css\"><scr",
+            b"ipt src=\"app.js\"></script><script>inline()</script>",
+            b"<link rel=\"canonical\" href=\"/\"></head><body><IMG SRC=\"x.png\">",
+            b"<p>text</p><img alt=\"no source\"><iframe src=\"/f\"></iframe>",
+        ];
+        let counts: Vec<u64> = chunks.iter().map(|chunk| scanner.feed(chunk)).collect();
+        assert_eq!(counts, vec![1, 1, 1, 1]);
+    }
+}
@@ -0,0 +1,489 @@
+//! Recovering corrupted profile databases
+//!
+//! A profile database that fails to open, or opens but fails
+//! `PRAGMA integrity_check`, would otherwise keep the browser from
+//! starting. [`open_or_recover`] instead:
+//!
+//! 1. moves the damaged file (and its `-wal`/`-shm` files) aside as
+//!    `<name>.corrupt-YYYYMMDD-HHMMSS`, see [`aside_path`];
+//! 2. opens a fresh database at the original path;
+//! 3. copies whatever rows can still be read from the damaged file into
+//!    it, table by table, see [`salvage`].
+//!
+//! The caller then runs the schema migrations over the result, which fill
+//! in any table that could not be salvaged. The returned
+//! [`RecoveryReport`] says what was kept and what was lost; the engine
+//! shows it once on `about:recovery`, and `frankenbrowser profile repair`
+//! prints it.
+
+use crate::db::DbOptions;
+use crate::errors::{Error, Result};
+use rusqlite::types::Value;
+use rusqlite::{Connection, OpenFlags};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Rows copied from one table of a damaged database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SalvagedTable {
+    /// Table name
+    pub name: String,
+    /// Rows copied into the new database
+    pub recovered: u64,
+    /// Rows read but rejected by the new database
+    pub rejected: u64,
+    /// Whether the table was read to the end; `false` when corruption
+    /// stopped the scan and later rows are lost
+    pub complete: bool,
+}
+
+/// What happened to one damaged database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryReport {
+    /// Short name, e.g. `history`
+    pub name: String,
+    /// Database file, now holding the recovered data
+    pub path: PathBuf,
+    /// Why the database was considered damaged
+    pub problem: String,
+    /// Where the damaged file was moved
+    pub moved_to: PathBuf,
+    /// Tables found in the damaged file; empty if its schema was unreadable
+    pub tables: Vec<SalvagedTable>,
+}
+
+impl RecoveryReport {
+    /// Total rows copied into the new database
+    pub fn recovered_rows(&self) -> u64 {
+        self.tables.iter().map(|table| table.recovered).sum()
+    }
+
+    /// Whether every row of every table was recovered
+    pub fn is_complete(&self) -> bool {
+        !self.tables.is_empty()
+            && self
+                .tables
+                .iter()
+                .all(|table| table.complete && table.rejected == 0)
+    }
+}
+
+impl fmt::Display for RecoveryReport {
+    /// The problem, where the damaged file went, then one line per table
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}: damaged ({})", self.name, self.problem)?;
+        writeln!(f, "  moved to {}", self.moved_to.display())?;
+        if self.tables.is_empty() {
+            writeln!(f, "  nothing could be read; started empty")?;
+        }
+        for table in &self.tables {
+            write!(f, "  {}: {} rows recovered", table.name, table.recovered)?;
+            if table.rejected > 0 {
+                write!(f, ", {} rejected", table.rejected)?;
+            }
+            if !table.complete {
+                write!(f, ", later rows unreadable")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Problems `PRAGMA integrity_check` finds; empty for a healthy database
+pub fn integrity_problems(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let lines = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(lines.into_iter().filter(|line| line != "ok").collect())
+}
+
+/// Where a damaged database is moved
+///
+/// # Arguments
+///
+/// * `path` - Database file
+/// * `now` - Unix seconds, rendered in UTC as `YYYYMMDD-HHMMSS`
+///
+/// # Returns
+///
+/// `<path>.corrupt-YYYYMMDD-HHMMSS`, with `-2`, `-3`, ... appended if a
+/// file of that name already exists.
+pub fn aside_path(path: &Path, now: i64) -> PathBuf {
+    let (year, month, day) = crate::format::civil_from_days(now.div_euclid(86_400));
+    let secs = now.rem_euclid(86_400);
+    let mut base = path.as_os_str().to_owned();
+    base.push(format!(
+        ".corrupt-{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    ));
+
+    let mut candidate = PathBuf::from(&base);
+    let mut n = 2;
+    while candidate.exists() {
+        let mut numbered = base.clone();
+        numbered.push(format!("-{}", n));
+        candidate = PathBuf::from(numbered);
+        n += 1;
+    }
+    candidate
+}
+
+/// Copy every readable row of `source` into `target`
+///
+/// Tables missing from `target` are created from their original
+/// definitions, and every table is filled in primary-key order. A row the new database rejects is counted
+/// and skipped; a read error ends that table. The `schema_migrations`
+/// table is not copied, so every migration runs again over the result.
+///
+/// # Arguments
+///
+/// * `source` - Damaged database file, opened read-only
+/// * `target` - Fresh database to fill
+///
+/// # Returns
+///
+/// One entry per table found; empty if `source` or its schema cannot be
+/// read.
+pub fn salvage(source: &Path, target: &Connection) -> Vec<SalvagedTable> {
+    let Ok(damaged) = Connection::open_with_flags(source, OpenFlags::SQLITE_OPEN_READ_ONLY) else {
+        return Vec::new();
+    };
+    // A truncated file is shorter than its header says, which SQLite
+    // refuses as corrupt unless the schema is writable; the connection is
+    // read-only, so nothing is written
+    let _ = damaged.pragma_update(None, "writable_schema", true);
+    let schema = damaged
+        .prepare(
+            "SELECT name, sql FROM sqlite_master
+             WHERE type = 'table' AND sql IS NOT NULL
+               AND name NOT LIKE 'sqlite_%' AND name != 'schema_migrations'",
+        )
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<Vec<(String, String)>>>()
+        });
+    let Ok(tables) = schema else {
+        return Vec::new();
+    };
+
+    tables
+        .into_iter()
+        .map(|(name, sql)| {
+            let mut salvaged = SalvagedTable {
+                name: name.clone(),
+                recovered: 0,
+                rejected: 0,
+                complete: false,
+            };
+            if target.execute(&sql, []).is_ok() || has_table(target, &name) {
+                copy_rows(&damaged, target, &mut salvaged);
+            }
+            salvaged
+        })
+        .collect()
+}
+
+/// Whether `conn` has a table called `name`
+fn has_table(conn: &Connection, name: &str) -> bool {
+    conn.query_row(
+        "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [name],
+        |_| Ok(()),
+    )
+    .is_ok()
+}
+
+/// Copy the rows of one table, counting into `salvaged`
+fn copy_rows(damaged: &Connection, target: &Connection, salvaged: &mut SalvagedTable) {
+    let Ok(mut select) = damaged.prepare(&format!("SELECT * FROM \"{}\"", salvaged.name)) else {
+        return;
+    };
+    let columns = select.column_count();
+    let placeholders = vec!["?"; columns].join(", ");
+    let Ok(tx) = target.unchecked_transaction() else {
+        return;
+    };
+    let Ok(mut insert) = tx.prepare(&format!(
+        "INSERT INTO \"{}\" VALUES ({})",
+        salvaged.name, placeholders
+    )) else {
+        return;
+    };
+    let Ok(mut rows) = select.query([]) else {
+        return;
+    };
+
+    salvaged.complete = loop {
+        match rows.next() {
+            Ok(Some(row)) => {
+                let values: rusqlite::Result<Vec<Value>> =
+                    (0..columns).map(|i| row.get::<_, Value>(i)).collect();
+                match values.map(|values| insert.execute(rusqlite::params_from_iter(values))) {
+                    Ok(Ok(_)) => salvaged.recovered += 1,
+                    _ => salvaged.rejected += 1,
+                }
+            }
+            Ok(None) => break true,
+            Err(_) => break false,
+        }
+    };
+    drop(insert);
+    if tx.commit().is_err() {
+        salvaged.recovered = 0;
+        salvaged.complete = false;
+    }
+}
+
+/// Open a profile database, recovering it if it is damaged
+///
+/// # Arguments
+///
+/// * `name` - Short name for the report, e.g. `history`
+/// * `path` - Database file; created if missing
+/// * `options` - Connection options
+/// * `now` - Unix seconds, used to name the damaged file
+///
+/// # Returns
+///
+/// The connection, and a report if the database had to be recovered.
+///
+/// # Errors
+///
+/// Returns `Error::DatabaseLocked` or `Error::DatabaseBusy` if another
+/// process holds the database (that is not damage), or
+/// `Error::DatabaseError` if the damaged file cannot be moved or the fresh
+/// database cannot be created.
+pub fn open_or_recover(
+    name: &str,
+    path: &Path,
+    options: &DbOptions,
+    now: i64,
+) -> Result<(Connection, Option<RecoveryReport>)> {
+    let problem = match options.open(Some(path)) {
+        Ok(conn) => match integrity_problems(&conn) {
+            Ok(problems) if problems.is_empty() => return Ok((conn, None)),
+            Ok(problems) => problems.join("; "),
+            Err(e) => e.to_string(),
+        },
+        Err(e @ (Error::DatabaseLocked(_) | Error::DatabaseBusy { .. })) => return Err(e),
+        Err(e) => e.to_string(),
+    };
+
+    let moved_to = aside_path(path, now);
+    move_aside(path, &moved_to)?;
+    let conn = options.open(Some(path))?;
+    let tables = salvage(&moved_to, &conn);
+    tracing::warn!(
+        "{} database was damaged ({}); moved to {} and recovered {} rows",
+        name,
+        problem,
+        moved_to.display(),
+        tables.iter().map(|table| table.recovered).sum::<u64>()
+    );
+
+    Ok((
+        conn,
+        Some(RecoveryReport {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            problem,
+            moved_to,
+            tables,
+        }),
+    ))
+}
+
+/// Rename a database and its `-wal`/`-shm` files to `to`
+fn move_aside(from: &Path, to: &Path) -> Result<()> {
+    std::fs::rename(from, to).map_err(|e| {
+        Error::DatabaseError(format!("cannot move {} aside: {}", from.display(), e))
+    })?;
+    for suffix in ["-wal", "-shm"] {
+        let mut sidecar = from.as_os_str().to_owned();
+        sidecar.push(suffix);
+        let mut moved = to.as_os_str().to_owned();
+        moved.push(suffix);
+        // Usually absent
+        let _ = std::fs::rename(sidecar, moved);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// 2026-03-10 15:04:05 UTC
+    const NOW: i64 = 1_773_155_045;
+
+    /// A database with `rows` bookmarks, written without a WAL
+    fn fixture(path: &Path, rows: usize) {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE bookmarks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                url TEXT NOT NULL UNIQUE,
+                title TEXT NOT NULL
+            );
+             CREATE TABLE bookmark_tags (bookmark_id INTEGER NOT NULL, tag TEXT NOT NULL);
+             INSERT INTO bookmark_tags VALUES (1, 'work');",
+        )
+        .unwrap();
+        let tx = conn.unchecked_transaction().unwrap();
+        for i in 0..rows {
+            tx.execute(
+                "INSERT INTO bookmarks (url, title) VALUES (?1, ?2)",
+                rusqlite::params![format!("https://site{}.example/", i), "x".repeat(200)],
+            )
+            .unwrap();
+        }
+        tx.commit().unwrap();
+    }
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_aside_path_naming() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.db");
+        let aside = aside_path(&path, NOW);
+        assert_eq!(
+            aside.file_name().unwrap(),
+            "history.db.corrupt-20260310-150405"
+        );
+
+        fs::write(&aside, b"").unwrap();
+        let second = aside_path(&path, NOW);
+        assert_eq!(
+            second.file_name().unwrap(),
+            "history.db.corrupt-20260310-150405-2"
+        );
+    }
+
+    #[test]
+    fn test_healthy_database_opens_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bookmarks.db");
+        fixture(&path, 10);
+
+        let (conn, report) =
+            open_or_recover("bookmarks", &path, &DbOptions::default(), NOW).unwrap();
+        assert!(report.is_none());
+        assert_eq!(count(&conn, "bookmarks"), 10);
+        assert!(fs::read_dir(dir.path()).unwrap().all(|entry| !entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .contains("corrupt")));
+    }
+
+    #[test]
+    fn test_truncated_database_partially_recovered() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bookmarks.db");
+        fixture(&path, 2_000);
+        let len = fs::metadata(&path).unwrap().len();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len / 2)
+            .unwrap();
+
+        let (conn, report) =
+            open_or_recover("bookmarks", &path, &DbOptions::default(), NOW).unwrap();
+        let report = report.unwrap();
+        assert_eq!(
+            report.moved_to,
+            dir.path().join("bookmarks.db.corrupt-20260310-150405")
+        );
+        assert!(report.moved_to.exists());
+        assert!(!report.is_complete());
+
+        let bookmarks = &report.tables[0];
+        assert_eq!(bookmarks.name, "bookmarks");
+        assert!(!bookmarks.complete);
+        assert!(bookmarks.recovered > 0 && bookmarks.recovered < 2_000);
+        assert_eq!(count(&conn, "bookmarks") as u64, bookmarks.recovered);
+        assert!(integrity_problems(&conn).unwrap().is_empty());
+
+        let text = report.to_string();
+        assert!(text.starts_with("bookmarks: damaged ("));
+        assert!(text.contains(&format!(
+            "  bookmarks: {} rows recovered, later rows unreadable\n",
+            bookmarks.recovered
+        )));
+    }
+
+    #[test]
+    fn test_garbage_header_starts_fresh() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.db");
+        fixture(&path, 10);
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[..100].fill(0xAB);
+        fs::write(&path, bytes).unwrap();
+
+        let (conn, report) = open_or_recover("history", &path, &DbOptions::default(), NOW).unwrap();
+        let report = report.unwrap();
+        assert!(report.tables.is_empty());
+        assert_eq!(report.recovered_rows(), 0);
+        assert!(report.to_string().contains("nothing could be read"));
+        let tables: i64 = conn
+            .query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(tables, 0);
+    }
+
+    #[test]
+    fn test_salvage_counts_rejected_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("old.db");
+        fixture(&source, 3);
+
+        // A target where one of the rows collides
+        let target = Connection::open_in_memory().unwrap();
+        target
+            .execute_batch(
+                "CREATE TABLE bookmarks (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    url TEXT NOT NULL UNIQUE,
+                    title TEXT NOT NULL
+                );
+                 INSERT INTO bookmarks VALUES (2, 'https://taken.example/', 't');",
+            )
+            .unwrap();
+
+        let tables = salvage(&source, &target);
+        assert_eq!(
+            tables,
+            vec![
+                SalvagedTable {
+                    name: "bookmarks".to_string(),
+                    recovered: 2,
+                    rejected: 1,
+                    complete: true,
+                },
+                SalvagedTable {
+                    name: "bookmark_tags".to_string(),
+                    recovered: 1,
+                    rejected: 0,
+                    complete: true,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,226 @@
+//! Page title extraction and sanitizing
+//!
+//! A document's `<title>` is picked out of the first bytes of its body while
+//! it streams in, so history and the tab strip have a title before the
+//! webview reports one. Every title, from the markup or from a later
+//! `document.title` change, goes through [`sanitize_title`] before it is
+//! stored or shown: control characters are removed, whitespace runs are
+//! collapsed and the length is capped.
+
+use std::time::Duration;
+
+/// Longest title kept, in characters
+pub const MAX_TITLE_CHARS: usize = 512;
+
+/// How long after a document finished loading a title change still updates
+/// its history entry
+///
+/// Pages often set `document.title` from a script right after load; later
+/// changes (unread counters, "now playing") only retitle the tab.
+pub const TITLE_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// How much of a document is scanned for its `<title>`
+pub const TITLE_SCAN_LIMIT: usize = 64 * 1024;
+
+/// Clean up a title for display and storage
+///
+/// Control characters and bidirectional overrides become spaces,
+/// whitespace runs collapse to one space, and the result is trimmed and cut
+/// to [`MAX_TITLE_CHARS`] characters.
+///
+/// # Arguments
+///
+/// * `raw` - Title as found in the page
+///
+/// # Returns
+///
+/// Returns the sanitized title, possibly empty.
+pub fn sanitize_title(raw: &str) -> String {
+    let cleaned: String = raw
+        .chars()
+        .map(|c| if is_stripped(c) { ' ' } else { c })
+        .collect();
+    let collapsed = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    match collapsed.char_indices().nth(MAX_TITLE_CHARS) {
+        Some((cut, _)) => collapsed[..cut].trim_end().to_string(),
+        None => collapsed,
+    }
+}
+
+/// Characters never kept in a title
+fn is_stripped(c: char) -> bool {
+    c.is_control() || matches!(c, '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Find the `<title>` of an HTML document
+///
+/// Only the first title element counts, and only once its closing tag has
+/// been seen. Character references are decoded and the text is passed
+/// through [`sanitize_title`].
+///
+/// # Arguments
+///
+/// * `html` - Start of the document body
+///
+/// # Returns
+///
+/// Returns `None` if there is no complete, non-empty title element.
+pub fn extract_title(html: &[u8]) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let open = find_tag(&lower, b"<title", 0)?;
+    let content_start = open + lower[open..].iter().position(|&b| b == b'>')? + 1;
+    let content_end = find_tag(&lower, b"</title", content_start)?;
+
+    let text = String::from_utf8_lossy(&html[content_start..content_end]);
+    let title = sanitize_title(&decode_entities(&text));
+    (!title.is_empty()).then_some(title)
+}
+
+/// Position of `tag` at or after `from`, when followed by the end of the
+/// tag name
+pub(crate) fn find_tag(lower: &[u8], tag: &[u8], from: usize) -> Option<usize> {
+    let mut at = from;
+    while at + tag.len() <= lower.len() {
+        let found = at + lower[at..].windows(tag.len()).position(|w| w == tag)?;
+        let after = lower.get(found + tag.len()).copied();
+        if after.is_some_and(|b| b == b'>' || b == b'/' || b.is_ascii_whitespace()) {
+            return Some(found);
+        }
+        at = found + 1;
+    }
+    None
+}
+
+/// Decode the character references that show up in titles
+///
+/// Named references other than the common few are left as written.
+pub(crate) fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| decode_entity(&rest[1..end]).map(|c| (c, end)));
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    let code = match name {
+        "amp" => return Some('&'),
+        "lt" => return Some('<'),
+        "gt" => return Some('>'),
+        "quot" => return Some('"'),
+        "apos" => return Some('\''),
+        "nbsp" => return Some('\u{A0}'),
+        _ => name.strip_prefix('#')?,
+    };
+    let value = match code.strip_prefix(['x', 'X']) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+        None => code.parse().ok()?,
+    };
+    char::from_u32(value)
+}
+
+/// Finds a document's title while its body streams in
+///
+/// Feed each chunk as it arrives; the title is reported once, as soon as
+/// its closing tag has arrived. Scanning stops after [`TITLE_SCAN_LIMIT`]
+/// bytes.
+#[derive(Debug, Default)]
+pub struct TitleScanner {
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl TitleScanner {
+    /// Scanner for a new document
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Scan the next chunk of the body
+    ///
+    /// # Returns
+    ///
+    /// Returns the title the first time it is complete, `None` otherwise.
+    pub fn feed(&mut self, chunk: &[u8]) -> Option<String> {
+        if self.done {
+            return None;
+        }
+        let room = TITLE_SCAN_LIMIT - self.buffer.len();
+        self.buffer
+            .extend_from_slice(&chunk[..chunk.len().min(room)]);
+
+        let title = extract_title(&self.buffer);
+        if title.is_some() || self.buffer.len() == TITLE_SCAN_LIMIT {
+            self.done = true;
+            self.buffer = Vec::new();
+        }
+        title
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_title() {
+        assert_eq!(
+            sanitize_title("  Inbox\t(3)\n\n- Mail  "),
+            "Inbox (3) - Mail"
+        );
+        assert_eq!(
+            sanitize_title("evil\u{0}\u{7}\u{202E}txt.exe"),
+            "evil txt.exe"
+        );
+        assert_eq!(sanitize_title("\u{1b}[31m"), "[31m");
+
+        let long = "é".repeat(MAX_TITLE_CHARS + 10);
+        let capped = sanitize_title(&long);
+        assert_eq!(capped.chars().count(), MAX_TITLE_CHARS);
+    }
+
+    #[test]
+    fn test_extract_title() {
+        let html = b"<!doctype html><HTML><head><Title lang=\"en\">\n  Caf&eacute; &amp; Bar &#8211; &#x1F600;\n</TITLE></head>";
+        assert_eq!(
+            extract_title(html).as_deref(),
+            Some("Caf&eacute; & Bar \u{2013} \u{1F600}")
+        );
+
+        assert_eq!(extract_title(b"<titles>x</titles>"), None);
+        assert_eq!(extract_title(b"<title>   </title>"), None);
+        assert_eq!(extract_title(b"<title>Unterminated"), None);
+        assert_eq!(extract_title(b"<html><body>No title</body>"), None);
+    }
+
+    #[test]
+    fn test_scanner_across_chunks() {
+        let mut scanner = TitleScanner::new();
+        assert_eq!(scanner.feed(b"<html><head><ti"), None);
+        assert_eq!(scanner.feed(b"tle>Split "), None);
+        assert_eq!(scanner.feed(b"page</tit"), None);
+        assert_eq!(scanner.feed(b"le>").as_deref(), Some("Split page"));
+        assert_eq!(scanner.feed(b"<title>Second</title>"), None);
+
+        let mut scanner = TitleScanner::new();
+        assert_eq!(scanner.feed(&vec![b' '; TITLE_SCAN_LIMIT]), None);
+        assert_eq!(scanner.feed(b"<title>Too late</title>"), None);
+    }
+}
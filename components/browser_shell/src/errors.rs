@@ -13,6 +13,9 @@ pub enum Error {
     #[error("No active tab")]
     NoActiveTab,
 
+    #[error("Invalid tab state: {0}")]
+    InvalidTabState(String),
+
     #[error("Window error: {0}")]
     WindowError(String),
 
@@ -31,6 +34,24 @@ pub enum Error {
     #[error("Initialization error: {0}")]
     Initialization(String),
 
+    #[error("Session error: {0}")]
+    SessionError(String),
+
+    #[error("Hibernation error: {0}")]
+    HibernationError(String),
+
+    /// Opening the tabs would pass the tab bar's limit
+    #[error("{not_opened} of {requested} tabs could not open: the limit is {limit} tabs")]
+    TooManyTabs {
+        requested: usize,
+        not_opened: usize,
+        limit: usize,
+    },
+
+    /// Printing failed or was cancelled
+    #[error(transparent)]
+    Print(#[from] webview_integration::PrintError),
+
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
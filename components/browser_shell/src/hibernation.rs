@@ -0,0 +1,237 @@
+//! Tab hibernation to disk
+//!
+//! A hibernated tab keeps only a stub in memory: its title, URL and the
+//! path of a [`TabSnapshot`] file holding everything needed to bring it
+//! back — the back/forward stack, scroll position, form contents, zoom and
+//! tab group. Snapshots are written like session snapshots: to a temporary
+//! file first, then renamed into place, so a crash never leaves half a tab
+//! behind. Files no longer referenced by any open or saved tab are removed
+//! by [`HibernationStore::collect_garbage`].
+//!
+//! Private windows never hibernate tabs; nothing from them may reach disk.
+
+use crate::errors::{Error, Result};
+use crate::snapshot::remove_if_present;
+use browser_core::TabHistory;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use webview_integration::PageState;
+
+/// Prefix of every hibernated tab file
+const SNAPSHOT_PREFIX: &str = "tab-";
+
+/// How hard the system is pressed for memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryPressure {
+    /// Free what is cheap to bring back: idle tabs are discarded
+    Moderate,
+    /// Free as much as possible: idle tabs are also hibernated
+    Critical,
+}
+
+/// Everything needed to bring a hibernated tab back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TabSnapshot {
+    /// Current URL
+    pub url: String,
+    /// Page title
+    pub title: String,
+    /// Favicon URL, if known
+    #[serde(default)]
+    pub favicon: Option<String>,
+    /// Back/forward stack
+    #[serde(default)]
+    pub history: TabHistory,
+    /// Scroll position and form contents of the current page
+    #[serde(default)]
+    pub page: PageState,
+    /// Page zoom factor
+    #[serde(default = "default_zoom")]
+    pub zoom: f64,
+    /// Tab group the tab belonged to
+    #[serde(default)]
+    pub group: Option<String>,
+    /// When the tab was hibernated, in seconds since the Unix epoch
+    #[serde(default)]
+    pub hibernated_at_unix: u64,
+}
+
+fn default_zoom() -> f64 {
+    1.0
+}
+
+/// Directory of hibernated tab files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HibernationStore {
+    dir: PathBuf,
+}
+
+impl HibernationStore {
+    /// Use `dir` for hibernated tabs; it is created on the first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Directory holding the hibernated tabs
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Write a tab's snapshot to a new file
+    ///
+    /// # Arguments
+    ///
+    /// * `tab_id` - Tab being hibernated, used in the file name
+    /// * `snapshot` - State to save
+    ///
+    /// # Returns
+    ///
+    /// Path of the written file
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::HibernationError` if the file cannot be written.
+    pub fn write(&self, tab_id: u32, snapshot: &TabSnapshot) -> Result<PathBuf> {
+        let io_error = |what: &str, path: &Path, e: std::io::Error| {
+            Error::HibernationError(format!("Cannot {} {}: {}", what, path.display(), e))
+        };
+        fs::create_dir_all(&self.dir).map_err(|e| io_error("create", &self.dir, e))?;
+
+        let json =
+            serde_json::to_vec(snapshot).map_err(|e| Error::HibernationError(e.to_string()))?;
+        // Tab IDs repeat across restarts, so the time keeps older files of
+        // the same ID (still referenced by a saved session) intact
+        let path = self.dir.join(format!(
+            "{}{}-{}.json",
+            SNAPSHOT_PREFIX, tab_id, snapshot.hibernated_at_unix
+        ));
+        let temp = path.with_extension("json.tmp");
+        let mut file = fs::File::create(&temp).map_err(|e| io_error("create", &temp, e))?;
+        file.write_all(&json)
+            .and_then(|()| file.sync_all())
+            .map_err(|e| io_error("write", &temp, e))?;
+        fs::rename(&temp, &path).map_err(|e| io_error("replace", &path, e))?;
+        Ok(path)
+    }
+
+    /// Read a hibernated tab back
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::HibernationError` if the file cannot be read or
+    /// parsed.
+    pub fn read(&self, path: &Path) -> Result<TabSnapshot> {
+        let json = fs::read(path).map_err(|e| {
+            Error::HibernationError(format!("Cannot read {}: {}", path.display(), e))
+        })?;
+        serde_json::from_slice(&json)
+            .map_err(|e| Error::HibernationError(format!("Cannot parse {}: {}", path.display(), e)))
+    }
+
+    /// Delete a hibernated tab's file once the tab is awake or closed
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::SessionError` if an existing file cannot be deleted.
+    pub fn remove(&self, path: &Path) -> Result<bool> {
+        remove_if_present(path)
+    }
+
+    /// Delete tab files that no open or saved tab refers to
+    ///
+    /// Leftover temporary files from interrupted writes are deleted too.
+    /// Files that cannot be deleted are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `referenced` - Files still in use
+    ///
+    /// # Returns
+    ///
+    /// The number of files deleted
+    pub fn collect_garbage(&self, referenced: &HashSet<PathBuf>) -> usize {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return 0;
+        };
+        entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(SNAPSHOT_PREFIX))
+            })
+            .filter(|path| !referenced.contains(path))
+            .filter(|path| matches!(remove_if_present(path), Ok(true)))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_snapshot() -> TabSnapshot {
+        TabSnapshot {
+            url: "https://docs.example/guide#install".to_string(),
+            title: "Guide".to_string(),
+            favicon: None,
+            history: TabHistory {
+                urls: vec![
+                    "https://docs.example/".to_string(),
+                    "https://docs.example/guide#install".to_string(),
+                ],
+                position: 1,
+                visited_at: vec![Some(1_700_000_000), Some(1_700_000_060)],
+            },
+            page: PageState {
+                scroll_x: 0.0,
+                scroll_y: 980.0,
+                form_fields: Vec::new(),
+            },
+            zoom: 1.25,
+            group: Some("Research".to_string()),
+            hibernated_at_unix: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn test_write_read_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HibernationStore::new(dir.path().join("hibernated"));
+        let snapshot = fixture_snapshot();
+
+        let path = store.write(3, &snapshot).unwrap();
+        assert_eq!(path.file_name().unwrap(), "tab-3-1700000000.json");
+        assert_eq!(store.read(&path).unwrap(), snapshot);
+
+        assert!(store.remove(&path).unwrap());
+        assert!(!store.remove(&path).unwrap());
+        assert!(matches!(store.read(&path), Err(Error::HibernationError(_))));
+    }
+
+    #[test]
+    fn test_collect_garbage_keeps_referenced_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HibernationStore::new(dir.path());
+        let mut snapshot = fixture_snapshot();
+        let kept = store.write(1, &snapshot).unwrap();
+        snapshot.hibernated_at_unix += 60;
+        let stale = store.write(1, &snapshot).unwrap();
+        fs::write(dir.path().join("tab-2-1.json.tmp"), b"{").unwrap();
+        fs::write(dir.path().join("unrelated.txt"), b"keep").unwrap();
+
+        let referenced: HashSet<PathBuf> = [kept.clone()].into_iter().collect();
+        assert_eq!(store.collect_garbage(&referenced), 2);
+        assert!(kept.exists());
+        assert!(!stale.exists());
+        assert!(dir.path().join("unrelated.txt").exists());
+
+        assert_eq!(
+            HibernationStore::new(dir.path().join("missing")).collect_garbage(&referenced),
+            0
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Keyboard events for the shell's key dispatch
+//!
+//! Key presses are matched against menu shortcuts by
+//! [`BrowserShell::handle_key_event`](crate::BrowserShell::handle_key_event).
+//! Releases matter only for chords that stay active while a modifier is
+//! held, such as the Ctrl+Tab tab switcher, which commits when Ctrl is
+//! released.
+
+use crate::menu::{KeyCode, KeyModifier, Shortcut};
+
+/// Whether a key went down or up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyState {
+    /// Key pressed (including auto-repeat)
+    Pressed,
+    /// Key released
+    Released,
+}
+
+/// A key press or release with the modifiers held at the time
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyEvent {
+    /// Key that changed state
+    pub key: KeyCode,
+    /// Modifiers held while the key changed state
+    pub modifiers: Vec<KeyModifier>,
+    /// Pressed or released
+    pub state: KeyState,
+}
+
+impl KeyEvent {
+    /// Create a key press event
+    ///
+    /// # Arguments
+    ///
+    /// * `modifiers` - Modifiers held
+    /// * `key` - Key pressed
+    pub fn press(modifiers: Vec<KeyModifier>, key: KeyCode) -> Self {
+        Self {
+            key,
+            modifiers,
+            state: KeyState::Pressed,
+        }
+    }
+
+    /// Create a key release event
+    ///
+    /// Releasing a modifier itself is `KeyCode::Modifier`, with the
+    /// modifiers still held after the release.
+    ///
+    /// # Arguments
+    ///
+    /// * `modifiers` - Modifiers still held
+    /// * `key` - Key released
+    pub fn release(modifiers: Vec<KeyModifier>, key: KeyCode) -> Self {
+        Self {
+            key,
+            modifiers,
+            state: KeyState::Released,
+        }
+    }
+
+    /// Whether this is a key press
+    pub fn is_press(&self) -> bool {
+        self.state == KeyState::Pressed
+    }
+
+    /// Whether `modifier` was held
+    pub fn has_modifier(&self, modifier: &KeyModifier) -> bool {
+        self.modifiers.contains(modifier)
+    }
+
+    /// The shortcut this event corresponds to
+    ///
+    /// Modifiers are put in the order `Shortcut::parse` produces
+    /// (Ctrl, Alt, Shift, Meta) so the result compares equal to the menu's
+    /// shortcuts however the platform reported them.
+    pub fn shortcut(&self) -> Shortcut {
+        let modifiers = [
+            KeyModifier::Ctrl,
+            KeyModifier::Alt,
+            KeyModifier::Shift,
+            KeyModifier::Meta,
+        ]
+        .into_iter()
+        .filter(|modifier| self.has_modifier(modifier))
+        .collect();
+        Shortcut::new(modifiers, self.key.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ========================================
+    // Tests for KeyEvent
+    // ========================================
+
+    #[test]
+    fn test_press_and_release() {
+        let press = KeyEvent::press(vec![KeyModifier::Ctrl], KeyCode::Tab);
+        assert!(press.is_press());
+        assert!(press.has_modifier(&KeyModifier::Ctrl));
+        assert!(!press.has_modifier(&KeyModifier::Shift));
+
+        let release = KeyEvent::release(vec![], KeyCode::Modifier(KeyModifier::Ctrl));
+        assert!(!release.is_press());
+        assert_eq!(release.state, KeyState::Released);
+    }
+
+    #[test]
+    fn test_shortcut_normalizes_modifier_order() {
+        let event = KeyEvent::press(vec![KeyModifier::Shift, KeyModifier::Ctrl], KeyCode::Tab);
+        assert_eq!(event.shortcut(), Shortcut::parse("Ctrl+Shift+Tab").unwrap());
+    }
+}
@@ -32,6 +32,8 @@
 //!     enable_devtools: true,
 //!     theme: "light".to_string(),
 //!     default_zoom: 1.0,
+//!     ctrl_tab_mru: false,
+//!     user_agent: None,
 //! };
 //!
 //! let mut bus = MessageBus::new();
@@ -56,17 +58,41 @@
 //! ```
 
 pub mod errors;
+pub mod hibernation;
+pub mod keyboard;
 pub mod menu;
+pub mod pinned;
+pub mod print;
+pub mod session;
+pub mod snapshot;
+pub mod theme;
 pub mod types;
 pub mod ui_components;
+pub mod window_title;
+pub mod zoom;
 
 // Re-export main types for convenience
 pub use errors::{Error, Result};
+pub use hibernation::{HibernationStore, MemoryPressure, TabSnapshot};
+pub use keyboard::{KeyEvent, KeyState};
 pub use menu::{
-    KeyCode, KeyModifier, Menu, MenuAction, MenuBar, MenuItem, MenuElement, Shortcut,
+    history_dropdown_menu, page_context_menu, tab_context_menu, tab_history_search_menu, KeyCode,
+    KeyModifier, Menu, MenuAction, MenuBar, MenuElement, MenuItem, Shortcut,
+    HISTORY_DROPDOWN_LIMIT,
 };
-pub use types::{BrowserShell, Tab};
+pub use pinned::{PinnedTab, PinnedTabStore, PINNED_TABS_FILE};
+pub use print::{pdf_file_name, print_status_message};
+pub use session::{Session, SessionTab, WindowGeometry};
+pub use snapshot::{
+    parse_recovery_action_url, recovery_action_url, render_recovery_page, RecoveryAction,
+    RecoveryOutcome, RecoveryPolicy, SessionSnapshots, SnapshotStore, RECOVERY_PAGE_URL,
+    SESSION_RESTORE_URL,
+};
+pub use theme::{ColorTokens, ThemeProvider, DARK_COLORS, LIGHT_COLORS};
+pub use types::{BrowserShell, Tab, TabLifecycle};
 pub use ui_components::{
-    NavigationButtons, SecurityState, StatusBar, TabBar, TabWidget, UiElement,
-    URLBar, ValidationState,
+    NavigationButtons, SecurityState, StatusBar, TabBar, TabSwitcher, TabWidget, URLBar, UiElement,
+    ValidationState,
 };
+pub use window_title::format_window_title;
+pub use zoom::{ZoomManager, MAX_ZOOM, MIN_ZOOM, ZOOM_LEVELS};
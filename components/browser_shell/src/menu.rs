@@ -5,6 +5,8 @@
 //! and event handling capabilities.
 
 use crate::errors::{Error, Result};
+use browser_core::StackEntry;
+use config_manager::EffectiveContentSettings;
 use std::collections::HashMap;
 use std::sync::Arc;
 
@@ -40,6 +42,12 @@ pub enum KeyCode {
     ArrowLeft,
     /// Right arrow
     ArrowRight,
+    /// Tab key
+    Tab,
+    /// Escape key
+    Escape,
+    /// A modifier key on its own (e.g. releasing Ctrl)
+    Modifier(KeyModifier),
 }
 
 /// Keyboard shortcut for menu items
@@ -150,6 +158,8 @@ impl Shortcut {
                 "," => KeyCode::Comma,
                 "Left" => KeyCode::ArrowLeft,
                 "Right" => KeyCode::ArrowRight,
+                "Tab" => KeyCode::Tab,
+                "Esc" => KeyCode::Escape,
                 _ => {
                     return Err(Error::ConfigError(format!("Unknown key: {}", key_str)))
                 }
@@ -164,12 +174,7 @@ impl Shortcut {
         let mut parts = Vec::new();
 
         for modifier in modifiers {
-            parts.push(match modifier {
-                KeyModifier::Ctrl => "Ctrl",
-                KeyModifier::Alt => "Alt",
-                KeyModifier::Shift => "Shift",
-                KeyModifier::Meta => "Meta",
-            });
+            parts.push(modifier_display(modifier));
         }
 
         let key_str = match key {
@@ -181,6 +186,9 @@ impl Shortcut {
             KeyCode::Comma => ",".to_string(),
             KeyCode::ArrowLeft => "Left".to_string(),
             KeyCode::ArrowRight => "Right".to_string(),
+            KeyCode::Tab => "Tab".to_string(),
+            KeyCode::Escape => "Esc".to_string(),
+            KeyCode::Modifier(modifier) => modifier_display(modifier).to_string(),
         };
         parts.push(&key_str);
 
@@ -188,6 +196,16 @@ impl Shortcut {
     }
 }
 
+/// Display name of a key modifier
+fn modifier_display(modifier: &KeyModifier) -> &'static str {
+    match modifier {
+        KeyModifier::Ctrl => "Ctrl",
+        KeyModifier::Alt => "Alt",
+        KeyModifier::Shift => "Shift",
+        KeyModifier::Meta => "Meta",
+    }
+}
+
 /// Menu action types
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum MenuAction {
@@ -199,6 +217,18 @@ pub enum MenuAction {
     CloseTab,
     /// Close current window
     CloseWindow,
+    /// Activate the next tab
+    NextTab,
+    /// Activate the previous tab
+    PreviousTab,
+    /// Duplicate the active tab, back/forward stack included
+    DuplicateActiveTab,
+    /// Duplicate a tab, back/forward stack included
+    DuplicateTab(u32),
+    /// Pin a tab as an app tab, or unpin it
+    TogglePinTab(u32),
+    /// Close every tab except this one and the pinned tabs
+    CloseOtherTabs(u32),
     /// Quit application
     Quit,
     /// Copy selection
@@ -221,6 +251,10 @@ pub enum MenuAction {
     FullScreen,
     /// Open developer tools
     DevTools,
+    /// Reload the active tab
+    Reload,
+    /// Reload the active tab, bypassing the HTTP cache
+    HardReload,
     /// Navigate back
     Back,
     /// Navigate forward
@@ -229,10 +263,36 @@ pub enum MenuAction {
     ShowHistory,
     /// Add bookmark
     AddBookmark,
+    /// Bookmark every tab into a new folder, named after the window unless
+    /// a name is given
+    BookmarkAllTabs(Option<String>),
+    /// Open every bookmark in a folder as background tabs
+    OpenBookmarkFolder(String),
     /// Show bookmarks
     ShowBookmarks,
     /// Show about dialog
     About,
+    /// Flip JavaScript for the active tab's site
+    ToggleSiteJavaScript,
+    /// Flip image loading for the active tab's site
+    ToggleSiteImages,
+    /// Save the active tab's page load as a HAR file
+    SavePageLoadHar,
+    /// Print the active tab's page
+    Print,
+    /// Save the active tab's page as a PDF
+    PrintToFile,
+    /// Write a background tab's state to disk and free its memory
+    HibernateTab(u32),
+    /// Move a tab to an entry of its back/forward stack
+    JumpToHistoryEntry {
+        /// Tab to move
+        tab_id: u32,
+        /// Stack index of the entry
+        index: usize,
+    },
+    /// Ask for text to search a tab's back/forward stack for
+    SearchTabHistory(u32),
     /// Custom action with identifier
     Custom(String),
 }
@@ -552,6 +612,40 @@ impl MenuBar {
 
         menu.add_separator();
 
+        menu.add_item(
+            MenuItem::new("Next Tab".to_string())
+                .with_shortcut(Shortcut::parse("Ctrl+Tab").unwrap())
+                .with_action(MenuAction::NextTab),
+        );
+
+        menu.add_item(
+            MenuItem::new("Previous Tab".to_string())
+                .with_shortcut(Shortcut::parse("Ctrl+Shift+Tab").unwrap())
+                .with_action(MenuAction::PreviousTab),
+        );
+
+        menu.add_item(
+            MenuItem::new("Duplicate Tab".to_string())
+                .with_shortcut(Shortcut::parse("Ctrl+Shift+K").unwrap())
+                .with_action(MenuAction::DuplicateActiveTab),
+        );
+
+        menu.add_separator();
+
+        menu.add_item(
+            MenuItem::new("Print...".to_string())
+                .with_shortcut(Shortcut::parse("Ctrl+P").unwrap())
+                .with_action(MenuAction::Print),
+        );
+
+        menu.add_item(
+            MenuItem::new("Save as PDF".to_string())
+                .with_shortcut(Shortcut::parse("Ctrl+Shift+P").unwrap())
+                .with_action(MenuAction::PrintToFile),
+        );
+
+        menu.add_separator();
+
         menu.add_item(
             MenuItem::new("Quit".to_string())
                 .with_shortcut(Shortcut::parse("Ctrl+Q").unwrap())
@@ -640,6 +734,20 @@ impl MenuBar {
                 .with_action(MenuAction::DevTools),
         );
 
+        menu.add_separator();
+
+        menu.add_item(
+            MenuItem::new("Reload".to_string())
+                .with_shortcut(Shortcut::parse("Ctrl+R").unwrap())
+                .with_action(MenuAction::Reload),
+        );
+
+        menu.add_item(
+            MenuItem::new("Hard Reload".to_string())
+                .with_shortcut(Shortcut::parse("Ctrl+Shift+R").unwrap())
+                .with_action(MenuAction::HardReload),
+        );
+
         menu
     }
 
@@ -680,6 +788,12 @@ impl MenuBar {
                 .with_action(MenuAction::AddBookmark),
         );
 
+        menu.add_item(
+            MenuItem::new("Bookmark All Tabs...".to_string())
+                .with_shortcut(Shortcut::parse("Ctrl+Shift+D").unwrap())
+                .with_action(MenuAction::BookmarkAllTabs(None)),
+        );
+
         menu.add_separator();
 
         menu.add_item(
@@ -710,6 +824,218 @@ impl Default for MenuBar {
     }
 }
 
+/// Build the page context menu
+///
+/// # Arguments
+///
+/// * `content` - Content settings in effect for the page's site, or `None`
+///   if the page has no web origin (the site toggles are then disabled)
+///
+/// # Returns
+///
+/// A menu whose toggle labels reflect the current settings.
+pub fn page_context_menu(content: Option<EffectiveContentSettings>) -> Menu {
+    let mut menu = Menu::new("Page".to_string());
+    let settings = content.unwrap_or(EffectiveContentSettings {
+        javascript: true,
+        images: true,
+    });
+
+    menu.add_item(
+        MenuItem::new("Back".to_string())
+            .with_shortcut(Shortcut::parse("Alt+Left").unwrap())
+            .with_action(MenuAction::Back),
+    );
+    menu.add_item(
+        MenuItem::new("Forward".to_string())
+            .with_shortcut(Shortcut::parse("Alt+Right").unwrap())
+            .with_action(MenuAction::Forward),
+    );
+    menu.add_separator();
+
+    let javascript = if settings.javascript {
+        "Disable JavaScript on this site"
+    } else {
+        "Enable JavaScript on this site"
+    };
+    menu.add_item(
+        MenuItem::new(javascript.to_string())
+            .with_action(MenuAction::ToggleSiteJavaScript)
+            .with_enabled(content.is_some()),
+    );
+
+    let images = if settings.images {
+        "Disable images on this site"
+    } else {
+        "Enable images on this site"
+    };
+    menu.add_item(
+        MenuItem::new(images.to_string())
+            .with_action(MenuAction::ToggleSiteImages)
+            .with_enabled(content.is_some()),
+    );
+    menu.add_separator();
+
+    menu.add_item(
+        MenuItem::new("Save page load as HAR".to_string()).with_action(MenuAction::SavePageLoadHar),
+    );
+
+    menu
+}
+
+/// Build the context menu of a tab in the tab strip
+///
+/// # Arguments
+///
+/// * `tab_id` - Tab the menu was opened on
+/// * `can_hibernate` - Whether the tab may be hibernated (a background tab
+///   in a non-private window with a hibernation directory)
+/// * `pinned` - Whether the tab is pinned, which offers "Unpin Tab" instead
+///   of "Pin Tab"
+pub fn tab_context_menu(tab_id: u32, can_hibernate: bool, pinned: bool) -> Menu {
+    let mut menu = Menu::new("Tab".to_string());
+    menu.add_item(
+        MenuItem::new("Duplicate Tab".to_string()).with_action(MenuAction::DuplicateTab(tab_id)),
+    );
+    menu.add_item(
+        MenuItem::new(if pinned { "Unpin Tab" } else { "Pin Tab" }.to_string())
+            .with_action(MenuAction::TogglePinTab(tab_id)),
+    );
+    menu.add_item(
+        MenuItem::new("Hibernate tab".to_string())
+            .with_action(MenuAction::HibernateTab(tab_id))
+            .with_enabled(can_hibernate),
+    );
+    menu.add_item(
+        MenuItem::new("Search this tab's history".to_string())
+            .with_action(MenuAction::SearchTabHistory(tab_id)),
+    );
+    menu.add_separator();
+    menu.add_item(
+        MenuItem::new("Close Other Tabs".to_string())
+            .with_action(MenuAction::CloseOtherTabs(tab_id)),
+    );
+    menu
+}
+
+/// Most entries listed in a Back or Forward dropdown
+pub const HISTORY_DROPDOWN_LIMIT: usize = 15;
+
+/// Build the dropdown shown on a long press of Back or Forward
+///
+/// # Arguments
+///
+/// * `tab_id` - Tab whose stack is listed
+/// * `entries` - The tab's stack, from `BrowserEngine::tab_stack_entries`
+/// * `forward` - List the entries after the current one instead of those
+///   before it
+///
+/// # Returns
+///
+/// A menu of the adjacent entries, nearest first, with at most
+/// [`HISTORY_DROPDOWN_LIMIT`] items.
+pub fn history_dropdown_menu(tab_id: u32, entries: &[StackEntry], forward: bool) -> Menu {
+    let current = entries.iter().position(|e| e.current).unwrap_or(0);
+    let (title, adjacent): (&str, Vec<&StackEntry>) = if forward {
+        ("Forward", entries.iter().skip(current + 1).collect())
+    } else {
+        ("Back", entries[..current].iter().rev().collect())
+    };
+
+    let mut menu = Menu::new(title.to_string());
+    for entry in adjacent.into_iter().take(HISTORY_DROPDOWN_LIMIT) {
+        menu.add_item(history_entry_item(tab_id, entry));
+    }
+    menu
+}
+
+/// Build the results of "Search this tab's history"
+///
+/// # Arguments
+///
+/// * `tab_id` - Tab whose stack is searched
+/// * `entries` - The tab's stack, from `BrowserEngine::tab_stack_entries`
+/// * `query` - Text to find in titles and URLs, ignoring case; empty lists
+///   every entry
+///
+/// # Returns
+///
+/// A menu of the matching entries, newest first. The current entry is
+/// listed but disabled.
+pub fn tab_history_search_menu(tab_id: u32, entries: &[StackEntry], query: &str) -> Menu {
+    let needle = query.trim().to_lowercase();
+    let mut menu = Menu::new("Tab history".to_string());
+    for entry in entries.iter().rev().filter(|entry| {
+        entry.title.to_lowercase().contains(&needle) || entry.url.to_lowercase().contains(&needle)
+    }) {
+        menu.add_item(history_entry_item(tab_id, entry).with_enabled(!entry.current));
+    }
+    menu
+}
+
+/// Menu item that jumps to a stack entry, labelled with its title or URL
+fn history_entry_item(tab_id: u32, entry: &StackEntry) -> MenuItem {
+    let label = if entry.title.is_empty() {
+        &entry.url
+    } else {
+        &entry.title
+    };
+    MenuItem::new(label.clone()).with_action(MenuAction::JumpToHistoryEntry {
+        tab_id,
+        index: entry.index,
+    })
+}
+
+/// Build the native GTK menu bar for a menu model
+///
+/// Activating an item with no submenu calls `on_action` with its action.
+/// Disabled items are shown greyed out.
+#[cfg(all(feature = "gui", target_os = "linux"))]
+pub(crate) fn gtk_menu_bar<F>(menu_bar: &MenuBar, on_action: F) -> gtk::MenuBar
+where
+    F: Fn(MenuAction) + Clone + 'static,
+{
+    use gtk::prelude::{GtkMenuItemExt, MenuShellExt, WidgetExt};
+
+    let bar = gtk::MenuBar::new();
+    for menu in &menu_bar.menus {
+        let title = gtk::MenuItem::with_label(&menu.title);
+        title.set_submenu(Some(&gtk_menu(&menu.items, &on_action)));
+        bar.append(&title);
+    }
+    bar.show_all();
+    bar
+}
+
+/// GTK menu for a list of items, with their submenus
+#[cfg(all(feature = "gui", target_os = "linux"))]
+fn gtk_menu<F>(items: &[MenuItem], on_action: &F) -> gtk::Menu
+where
+    F: Fn(MenuAction) + Clone + 'static,
+{
+    use gtk::prelude::{GtkMenuItemExt, MenuShellExt, WidgetExt};
+
+    let menu = gtk::Menu::new();
+    for item in items {
+        if item.is_separator {
+            menu.append(&gtk::SeparatorMenuItem::new());
+            continue;
+        }
+        let entry = gtk::MenuItem::with_label(&item.label);
+        entry.set_sensitive(item.enabled);
+        match &item.submenu {
+            Some(children) => entry.set_submenu(Some(&gtk_menu(children, on_action))),
+            None => {
+                let action = item.action.clone();
+                let on_action = on_action.clone();
+                entry.connect_activate(move |_| on_action(action.clone()));
+            }
+        }
+        menu.append(&entry);
+    }
+    menu
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -789,6 +1115,14 @@ mod tests {
 
         let comma = Shortcut::parse("Ctrl+,").unwrap();
         assert_eq!(comma.key, KeyCode::Comma);
+
+        let tab = Shortcut::parse("Ctrl+Shift+Tab").unwrap();
+        assert_eq!(tab.key, KeyCode::Tab);
+        assert_eq!(tab.display, "Ctrl+Shift+Tab");
+
+        let escape = Shortcut::parse("Esc").unwrap();
+        assert_eq!(escape.key, KeyCode::Escape);
+        assert_eq!(escape.display, "Esc");
     }
 
     #[test]
@@ -1096,8 +1430,10 @@ mod tests {
         let menu_bar = MenuBar::new();
         let file_menu = menu_bar.get_menu("File").unwrap();
 
-        // Should have: New Tab, New Window, Sep, Close Tab, Close Window, Sep, Quit
-        assert_eq!(file_menu.items.len(), 7);
+        // Should have: New Tab, New Window, Sep, Close Tab, Close Window, Sep,
+        // Next Tab, Previous Tab, Duplicate Tab, Sep, Print..., Save as PDF,
+        // Sep, Quit
+        assert_eq!(file_menu.items.len(), 14);
 
         assert_eq!(file_menu.items[0].label, "New Tab");
         assert_eq!(file_menu.items[0].action, MenuAction::NewTab);
@@ -1115,8 +1451,35 @@ mod tests {
 
         assert!(file_menu.items[5].is_separator);
 
-        assert_eq!(file_menu.items[6].label, "Quit");
-        assert_eq!(file_menu.items[6].action, MenuAction::Quit);
+        assert_eq!(file_menu.items[6].label, "Next Tab");
+        assert_eq!(file_menu.items[6].action, MenuAction::NextTab);
+
+        assert_eq!(file_menu.items[7].label, "Previous Tab");
+        assert_eq!(file_menu.items[7].action, MenuAction::PreviousTab);
+
+        assert_eq!(file_menu.items[8].label, "Duplicate Tab");
+        assert_eq!(file_menu.items[8].action, MenuAction::DuplicateActiveTab);
+        assert_eq!(
+            file_menu.items[8].shortcut,
+            Some(Shortcut::parse("Ctrl+Shift+K").unwrap())
+        );
+
+        assert!(file_menu.items[9].is_separator);
+
+        assert_eq!(file_menu.items[10].label, "Print...");
+        assert_eq!(file_menu.items[10].action, MenuAction::Print);
+        assert_eq!(
+            file_menu.items[10].shortcut,
+            Some(Shortcut::parse("Ctrl+P").unwrap())
+        );
+
+        assert_eq!(file_menu.items[11].label, "Save as PDF");
+        assert_eq!(file_menu.items[11].action, MenuAction::PrintToFile);
+
+        assert!(file_menu.items[12].is_separator);
+
+        assert_eq!(file_menu.items[13].label, "Quit");
+        assert_eq!(file_menu.items[13].action, MenuAction::Quit);
     }
 
     #[test]
@@ -1141,8 +1504,9 @@ mod tests {
         let menu_bar = MenuBar::new();
         let view_menu = menu_bar.get_menu("View").unwrap();
 
-        // Should have: Zoom In, Zoom Out, Zoom Reset, Sep, Full Screen, Sep, Developer Tools
-        assert_eq!(view_menu.items.len(), 7);
+        // Should have: Zoom In, Zoom Out, Zoom Reset, Sep, Full Screen, Sep, Developer Tools,
+        // Sep, Reload, Hard Reload
+        assert_eq!(view_menu.items.len(), 10);
 
         assert_eq!(view_menu.items[0].label, "Zoom In");
         assert_eq!(view_menu.items[1].label, "Zoom Out");
@@ -1151,6 +1515,9 @@ mod tests {
         assert_eq!(view_menu.items[4].label, "Full Screen");
         assert!(view_menu.items[5].is_separator);
         assert_eq!(view_menu.items[6].label, "Developer Tools");
+        assert!(view_menu.items[7].is_separator);
+        assert_eq!(view_menu.items[8].label, "Reload");
+        assert_eq!(view_menu.items[9].label, "Hard Reload");
     }
 
     #[test]
@@ -1172,12 +1539,13 @@ mod tests {
         let menu_bar = MenuBar::new();
         let bookmarks_menu = menu_bar.get_menu("Bookmarks").unwrap();
 
-        // Should have: Add Bookmark, Sep, Show All Bookmarks
-        assert_eq!(bookmarks_menu.items.len(), 3);
+        // Should have: Add Bookmark, Bookmark All Tabs, Sep, Show All Bookmarks
+        assert_eq!(bookmarks_menu.items.len(), 4);
 
         assert_eq!(bookmarks_menu.items[0].label, "Add Bookmark");
-        assert!(bookmarks_menu.items[1].is_separator);
-        assert_eq!(bookmarks_menu.items[2].label, "Show All Bookmarks");
+        assert_eq!(bookmarks_menu.items[1].label, "Bookmark All Tabs...");
+        assert!(bookmarks_menu.items[2].is_separator);
+        assert_eq!(bookmarks_menu.items[3].label, "Show All Bookmarks");
     }
 
     #[test]
@@ -1215,10 +1583,9 @@ mod tests {
             file_menu.items[4].shortcut.as_ref().unwrap().display,
             "Ctrl+Shift+W"
         );
-        assert_eq!(
-            file_menu.items[6].shortcut.as_ref().unwrap().display,
-            "Ctrl+Q"
-        );
+        // Quit stays last as tab, print and other items are added above it
+        let quit = file_menu.items.last().unwrap();
+        assert_eq!(quit.shortcut.as_ref().unwrap().display, "Ctrl+Q");
     }
 
     #[test]
@@ -1246,6 +1613,14 @@ mod tests {
             view_menu.items[6].shortcut.as_ref().unwrap().display,
             "F12"
         );
+        assert_eq!(
+            view_menu.items[8].shortcut.as_ref().unwrap().display,
+            "Ctrl+R"
+        );
+        assert_eq!(
+            view_menu.items[9].shortcut.as_ref().unwrap().display,
+            "Ctrl+Shift+R"
+        );
     }
 
     // ========================================
@@ -1301,6 +1676,144 @@ mod tests {
         assert!(!new_tab.is_separator);
     }
 
+    // ========================================
+    // Tests for the page context menu
+    // ========================================
+
+    #[test]
+    fn test_page_context_menu_labels_follow_settings() {
+        let menu = page_context_menu(Some(EffectiveContentSettings {
+            javascript: true,
+            images: false,
+        }));
+
+        let js = menu.get_item("Disable JavaScript on this site").unwrap();
+        assert_eq!(js.action, MenuAction::ToggleSiteJavaScript);
+        assert!(js.enabled);
+        let images = menu.get_item("Enable images on this site").unwrap();
+        assert_eq!(images.action, MenuAction::ToggleSiteImages);
+        assert!(images.enabled);
+    }
+
+    #[test]
+    fn test_page_context_menu_without_origin_disables_toggles() {
+        let menu = page_context_menu(None);
+
+        assert!(
+            !menu
+                .get_item("Disable JavaScript on this site")
+                .unwrap()
+                .enabled
+        );
+        assert!(
+            !menu
+                .get_item("Disable images on this site")
+                .unwrap()
+                .enabled
+        );
+        assert!(menu.get_item("Back").unwrap().enabled);
+        assert_eq!(
+            menu.get_item("Save page load as HAR").unwrap().action,
+            MenuAction::SavePageLoadHar
+        );
+    }
+
+    #[test]
+    fn test_tab_context_menu_targets_tab() {
+        let menu = tab_context_menu(4, false, false);
+        assert_eq!(
+            menu.get_item("Duplicate Tab").unwrap().action,
+            MenuAction::DuplicateTab(4)
+        );
+        assert_eq!(
+            menu.get_item("Pin Tab").unwrap().action,
+            MenuAction::TogglePinTab(4)
+        );
+        assert!(menu.get_item("Unpin Tab").is_none());
+        assert_eq!(
+            menu.get_item("Close Other Tabs").unwrap().action,
+            MenuAction::CloseOtherTabs(4)
+        );
+        let item = menu.get_item("Hibernate tab").unwrap();
+        assert_eq!(item.action, MenuAction::HibernateTab(4));
+        assert!(!item.enabled);
+        assert!(
+            tab_context_menu(4, true, true)
+                .get_item("Hibernate tab")
+                .unwrap()
+                .enabled
+        );
+        assert_eq!(
+            menu.get_item("Search this tab's history").unwrap().action,
+            MenuAction::SearchTabHistory(4)
+        );
+    }
+
+    fn stack(titles: &[&str], current: usize) -> Vec<StackEntry> {
+        titles
+            .iter()
+            .enumerate()
+            .map(|(index, title)| StackEntry {
+                index,
+                url: format!("https://site.example/{}", index),
+                title: title.to_string(),
+                visited_at: None,
+                current: index == current,
+            })
+            .collect()
+    }
+
+    fn jump_targets(menu: &Menu) -> Vec<usize> {
+        menu.items
+            .iter()
+            .map(|item| match item.action {
+                MenuAction::JumpToHistoryEntry { tab_id: 2, index } => index,
+                ref other => panic!("unexpected action {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_history_dropdown_lists_adjacent_entries() {
+        let entries = stack(&["Zero", "One", "", "Three", "Four"], 2);
+
+        let back = history_dropdown_menu(2, &entries, false);
+        assert_eq!(back.title, "Back");
+        assert_eq!(jump_targets(&back), [1, 0]);
+        assert_eq!(back.items[0].label, "One");
+
+        let forward = history_dropdown_menu(2, &entries, true);
+        assert_eq!(jump_targets(&forward), [3, 4]);
+
+        // Untitled entries are labelled with their URL
+        let untitled = stack(&["a", "", "c"], 2);
+        let back = history_dropdown_menu(2, &untitled, false);
+        assert_eq!(back.items[0].label, "https://site.example/1");
+        assert!(history_dropdown_menu(2, &untitled, true).items.is_empty());
+
+        let long: Vec<&str> = vec!["page"; HISTORY_DROPDOWN_LIMIT + 5];
+        let capped = history_dropdown_menu(2, &stack(&long, long.len() - 1), false);
+        assert_eq!(capped.items.len(), HISTORY_DROPDOWN_LIMIT);
+        assert_eq!(jump_targets(&capped)[0], long.len() - 2);
+    }
+
+    #[test]
+    fn test_tab_history_search_menu_filters_by_substring() {
+        let entries = stack(&["Rust lifetimes", "Inbox", "Lifetime value", "News"], 2);
+
+        let results = tab_history_search_menu(2, &entries, "LIFETIME");
+        assert_eq!(jump_targets(&results), [2, 0]);
+        assert!(!results.items[0].enabled);
+        assert!(results.items[1].enabled);
+
+        let by_url = tab_history_search_menu(2, &entries, "site.example/1");
+        assert_eq!(jump_targets(&by_url), [1]);
+        assert!(tab_history_search_menu(2, &entries, "nothing")
+            .items
+            .is_empty());
+        assert_eq!(tab_history_search_menu(2, &entries, "").items.len(), 4);
+    }
+
     #[test]
     fn test_separator_rendering() {
         let menu_bar = MenuBar::new();
@@ -0,0 +1,132 @@
+//! Pinned app tabs
+//!
+//! Pinned tabs are kept apart from the session: they are saved to their own
+//! file whenever one is pinned, unpinned or navigates, and restored at every
+//! start ahead of anything else, whatever the crash recovery policy does
+//! with the session. Discarding a session therefore never loses them. The
+//! file is written to a temporary file first and renamed into place, like
+//! session snapshots.
+
+use crate::errors::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Name of the pinned tab file in the session directory
+pub const PINNED_TABS_FILE: &str = "pinned_tabs.json";
+
+/// A pinned tab as saved
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PinnedTab {
+    /// URL to load when the tab is activated
+    pub url: String,
+    /// Title shown while the tab is pending
+    pub title: String,
+    /// Favicon URL, if known
+    #[serde(default)]
+    pub favicon: Option<String>,
+}
+
+/// File holding the pinned tabs in tab strip order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PinnedTabStore {
+    path: PathBuf,
+}
+
+impl PinnedTabStore {
+    /// Keep pinned tabs in `path`; its directory is created on first save
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// File holding the pinned tabs
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read the pinned tabs
+    ///
+    /// # Returns
+    ///
+    /// The pinned tabs in tab strip order; empty if nothing was saved yet
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::SessionError` if the file exists but cannot be read
+    /// or parsed.
+    pub fn load(&self) -> Result<Vec<PinnedTab>> {
+        let json = match fs::read(&self.path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(Error::SessionError(format!(
+                    "Cannot read {}: {}",
+                    self.path.display(),
+                    e
+                )))
+            }
+        };
+        serde_json::from_slice(&json).map_err(|e| {
+            Error::SessionError(format!("Cannot parse {}: {}", self.path.display(), e))
+        })
+    }
+
+    /// Replace the saved pinned tabs
+    ///
+    /// # Arguments
+    ///
+    /// * `tabs` - Pinned tabs in tab strip order
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::SessionError` if the file cannot be written.
+    pub fn save(&self, tabs: &[PinnedTab]) -> Result<()> {
+        let io_error = |what: &str, path: &Path, e: std::io::Error| {
+            Error::SessionError(format!("Cannot {} {}: {}", what, path.display(), e))
+        };
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| io_error("create", dir, e))?;
+        }
+
+        let json =
+            serde_json::to_vec_pretty(tabs).map_err(|e| Error::SessionError(e.to_string()))?;
+        let temp = self.path.with_extension("json.tmp");
+        let mut file = fs::File::create(&temp).map_err(|e| io_error("create", &temp, e))?;
+        file.write_all(&json)
+            .and_then(|()| file.sync_all())
+            .map_err(|e| io_error("write", &temp, e))?;
+        fs::rename(&temp, &self.path).map_err(|e| io_error("replace", &self.path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_load_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PinnedTabStore::new(dir.path().join("profile").join(PINNED_TABS_FILE));
+        assert!(store.load().unwrap().is_empty());
+
+        let tabs = vec![
+            PinnedTab {
+                url: "https://mail.example/inbox".to_string(),
+                title: "Inbox".to_string(),
+                favicon: Some("https://mail.example/favicon.ico".to_string()),
+            },
+            PinnedTab {
+                url: "https://chat.example/".to_string(),
+                title: "Chat".to_string(),
+                favicon: None,
+            },
+        ];
+        store.save(&tabs).unwrap();
+        assert_eq!(store.load().unwrap(), tabs);
+        assert!(!store.path().with_extension("json.tmp").exists());
+
+        fs::write(store.path(), "not json").unwrap();
+        assert!(matches!(store.load(), Err(Error::SessionError(_))));
+    }
+}
@@ -0,0 +1,98 @@
+//! Printing from the menu
+//!
+//! File › Print... (Ctrl+P) shows the platform print dialog for the active
+//! tab and File › Save as PDF (Ctrl+Shift+P) writes it to the PDF
+//! directory. Either way the result, including a cancelled dialog or a
+//! missing printer, ends up in the status bar.
+
+use webview_integration::{PrintError, PrintOutcome};
+
+/// File name used when a page title has no usable characters
+const UNTITLED_PDF_NAME: &str = "page";
+
+/// Longest file stem taken from a page title
+const MAX_PDF_STEM_CHARS: usize = 100;
+
+/// File name for a page saved as a PDF
+///
+/// Characters that are not allowed (or awkward) in file names on some
+/// platform are dropped and runs of whitespace collapsed, so
+/// `"Q&A: a/b"` becomes `"QA ab.pdf"`.
+///
+/// # Arguments
+///
+/// * `page_title` - Title of the page being saved
+pub fn pdf_file_name(page_title: &str) -> String {
+    let cleaned: String = page_title
+        .chars()
+        .filter(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.' | '(' | ')'))
+        .collect();
+    let stem: String = cleaned
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_matches('.')
+        .chars()
+        .take(MAX_PDF_STEM_CHARS)
+        .collect();
+
+    if stem.is_empty() {
+        format!("{}.pdf", UNTITLED_PDF_NAME)
+    } else {
+        format!("{}.pdf", stem.trim_end())
+    }
+}
+
+/// Status bar text for the result of a print request
+///
+/// # Arguments
+///
+/// * `result` - What the backend reported
+pub fn print_status_message(result: &Result<PrintOutcome, PrintError>) -> String {
+    match result {
+        Ok(PrintOutcome::Printed) => "Sent to printer".to_string(),
+        Ok(PrintOutcome::SavedToFile { path, .. }) => format!("Saved PDF to {}", path.display()),
+        Err(error) => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_pdf_file_name() {
+        assert_eq!(pdf_file_name("Example Domain"), "Example Domain.pdf");
+        assert_eq!(pdf_file_name("Q&A: a/b  \n c"), "QA ab c.pdf");
+        assert_eq!(pdf_file_name("..."), "page.pdf");
+        assert_eq!(pdf_file_name(""), "page.pdf");
+        assert_eq!(
+            pdf_file_name(&"x".repeat(300)),
+            format!("{}.pdf", "x".repeat(MAX_PDF_STEM_CHARS))
+        );
+    }
+
+    #[test]
+    fn test_print_status_message() {
+        assert_eq!(
+            print_status_message(&Ok(PrintOutcome::Printed)),
+            "Sent to printer"
+        );
+        assert_eq!(
+            print_status_message(&Ok(PrintOutcome::SavedToFile {
+                path: PathBuf::from("/tmp/a.pdf"),
+                bytes: 10,
+            })),
+            "Saved PDF to /tmp/a.pdf"
+        );
+        assert_eq!(
+            print_status_message(&Err(PrintError::Cancelled)),
+            "Printing cancelled"
+        );
+        assert_eq!(
+            print_status_message(&Err(PrintError::NoPrinter)),
+            "No printer available"
+        );
+    }
+}
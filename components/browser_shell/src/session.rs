@@ -0,0 +1,191 @@
+//! Saved browsing sessions
+//!
+//! A [`Session`] records the open tabs in tab strip order, which one was
+//! active, the most-recently-used order and the window geometry. Tab IDs
+//! are not stable across restarts, so the active tab and MRU order refer to
+//! tabs by position.
+
+use crate::errors::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A tab as saved in a session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionTab {
+    /// URL to load when the tab is activated
+    pub url: String,
+    /// Title shown while the tab is pending
+    pub title: String,
+    /// Favicon URL, if known
+    pub favicon: Option<String>,
+    /// File holding the tab's full state, if the tab was hibernated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hibernated: Option<PathBuf>,
+}
+
+/// Position and size of the browser window, in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    /// Left edge
+    pub x: i32,
+    /// Top edge
+    pub y: i32,
+    /// Inner width
+    pub width: u32,
+    /// Inner height
+    pub height: u32,
+}
+
+/// Open tabs saved for the next start
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// Tabs in tab strip order
+    pub tabs: Vec<SessionTab>,
+    /// Position of the active tab in `tabs`
+    pub active: Option<usize>,
+    /// Positions in `tabs`, most recently used first
+    pub mru: Vec<usize>,
+    /// Window position and size, if known
+    pub window: Option<WindowGeometry>,
+}
+
+impl Session {
+    /// Keep only some of the tabs
+    ///
+    /// The kept tabs stay in tab strip order; the active tab and MRU order
+    /// are renumbered, and the active tab is dropped if it was not kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `positions` - Positions in `tabs` to keep; unknown ones are ignored
+    pub fn subset(&self, positions: &[usize]) -> Session {
+        let kept: Vec<usize> = (0..self.tabs.len())
+            .filter(|position| positions.contains(position))
+            .collect();
+        let renumber = |position: usize| kept.iter().position(|p| *p == position);
+
+        Session {
+            tabs: kept.iter().map(|p| self.tabs[*p].clone()).collect(),
+            active: self.active.and_then(renumber),
+            mru: self.mru.iter().filter_map(|p| renumber(*p)).collect(),
+            window: self.window,
+        }
+    }
+
+    /// Load a session from a JSON file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Session file
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::SessionError` if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .map_err(|e| Error::SessionError(format!("Cannot read {}: {}", path.display(), e)))?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::SessionError(format!("Cannot parse {}: {}", path.display(), e)))
+    }
+
+    /// Save the session to a JSON file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Session file, replaced if it exists
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::SessionError` if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json =
+            serde_json::to_string_pretty(self).map_err(|e| Error::SessionError(e.to_string()))?;
+        fs::write(path, json)
+            .map_err(|e| Error::SessionError(format!("Cannot write {}: {}", path.display(), e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ========================================
+    // Tests for session files
+    // ========================================
+
+    #[test]
+    fn test_session_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        let session = Session {
+            tabs: vec![
+                SessionTab {
+                    url: "https://a.example/".to_string(),
+                    title: "A".to_string(),
+                    favicon: None,
+                    hibernated: None,
+                },
+                SessionTab {
+                    url: "https://b.example/".to_string(),
+                    title: "B".to_string(),
+                    favicon: Some("https://b.example/favicon.ico".to_string()),
+                    hibernated: Some(PathBuf::from("/tmp/tab-2-1700000000000.json")),
+                },
+            ],
+            active: Some(1),
+            mru: vec![1, 0],
+            window: Some(WindowGeometry {
+                x: 10,
+                y: 20,
+                width: 1280,
+                height: 720,
+            }),
+        };
+
+        session.save(&path).unwrap();
+        assert_eq!(Session::load(&path).unwrap(), session);
+    }
+
+    #[test]
+    fn test_session_subset_renumbers_positions() {
+        let tab = |name: &str| SessionTab {
+            url: format!("https://{}.example/", name),
+            title: name.to_string(),
+            favicon: None,
+            hibernated: None,
+        };
+        let session = Session {
+            tabs: vec![tab("a"), tab("b"), tab("c"), tab("d")],
+            active: Some(2),
+            mru: vec![2, 3, 0, 1],
+            window: None,
+        };
+
+        let subset = session.subset(&[3, 2, 9]);
+        assert_eq!(subset.tabs, vec![tab("c"), tab("d")]);
+        assert_eq!(subset.active, Some(0));
+        assert_eq!(subset.mru, vec![0, 1]);
+
+        let without_active = session.subset(&[0]);
+        assert_eq!(without_active.tabs, vec![tab("a")]);
+        assert_eq!(without_active.active, None);
+        assert_eq!(without_active.mru, vec![0]);
+    }
+
+    #[test]
+    fn test_session_load_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.json");
+        assert!(matches!(
+            Session::load(&missing),
+            Err(Error::SessionError(_))
+        ));
+
+        let corrupt = dir.path().join("corrupt.json");
+        fs::write(&corrupt, "{ not json").unwrap();
+        let err = Session::load(&corrupt).unwrap_err();
+        assert!(err.to_string().contains("corrupt.json"));
+    }
+}
@@ -0,0 +1,528 @@
+//! Periodic session snapshots and crash recovery
+//!
+//! While the browser runs, [`SessionSnapshots`] writes the session to
+//! [`SNAPSHOT_FILE`] whenever something changed since the last write and
+//! the snapshot interval has passed. Each write goes to a temporary file
+//! first; the current snapshot is then renamed to
+//! [`PREVIOUS_SNAPSHOT_FILE`] and the temporary file renamed into place, so
+//! a crash mid-write always leaves a complete snapshot behind. If the newer
+//! snapshot is corrupt anyway, the older one is used.
+//!
+//! The [`CLEAN_SHUTDOWN_MARKER`] is removed at startup and written during a
+//! graceful shutdown. Starting without it means the last run crashed; the
+//! [`RecoveryPolicy`] then decides whether the snapshot is reopened
+//! directly, offered on the recovery page, or dropped. The recovery page
+//! submits its choice to [`SESSION_RESTORE_URL`].
+
+use crate::errors::{Error, Result};
+use crate::session::Session;
+use browser_core::{InternalPage, SafeHtml};
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Newest session snapshot
+pub const SNAPSHOT_FILE: &str = "session-snapshot.json";
+
+/// The snapshot written before [`SNAPSHOT_FILE`]
+pub const PREVIOUS_SNAPSHOT_FILE: &str = "session-snapshot.prev.json";
+
+/// Present only while the browser is not running or after it shut down
+/// cleanly
+pub const CLEAN_SHUTDOWN_MARKER: &str = "clean-shutdown";
+
+/// Where a snapshot is written before it is renamed into place
+const SNAPSHOT_TEMP_FILE: &str = "session-snapshot.json.tmp";
+
+/// URL of the tab showing the recovery page
+pub const RECOVERY_PAGE_URL: &str = "about:restore-session";
+
+/// Internal URL the recovery page submits to; the shell answers it with
+/// `BrowserShell::apply_recovery_action`
+pub const SESSION_RESTORE_URL: &str = "about:session-restore";
+
+/// Page styles for the recovery page
+const RECOVERY_PAGE_STYLE: &str = "body { font-family: sans-serif; margin: 2em; } \
+     table { border-collapse: collapse; } \
+     td { padding: 0.2em 0.6em; } \
+     .url { color: #666; font-size: 0.85em; } \
+     .actions { margin: 1em 0; }";
+
+/// What to do with the previous session after a crash
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecoveryPolicy {
+    /// Show the recovery page and let the user choose
+    #[default]
+    Prompt,
+    /// Reopen every tab without asking
+    Restore,
+    /// Start with a fresh session
+    Discard,
+}
+
+impl RecoveryPolicy {
+    /// Parse the `browser.crash_recovery` setting
+    ///
+    /// # Returns
+    ///
+    /// Returns None for anything but "prompt", "restore" or "discard".
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "prompt" => Some(RecoveryPolicy::Prompt),
+            "restore" => Some(RecoveryPolicy::Restore),
+            "discard" => Some(RecoveryPolicy::Discard),
+            _ => None,
+        }
+    }
+}
+
+/// What happened to the previous session at startup
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoveryOutcome {
+    /// The last run shut down cleanly or left nothing to recover
+    NotNeeded,
+    /// The previous tabs were reopened; their IDs
+    Restored(Vec<u32>),
+    /// The recovery page was opened in this tab
+    Prompted(u32),
+    /// The previous session was thrown away
+    Discarded,
+}
+
+/// Choice submitted from the recovery page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecoveryAction {
+    /// Reopen every tab
+    RestoreAll,
+    /// Reopen the tabs at these positions in the snapshot
+    RestoreSelected(Vec<usize>),
+    /// Start with a fresh session
+    Discard,
+}
+
+/// Build the link the recovery page submits for an action
+///
+/// # Arguments
+///
+/// * `action` - Action and selected tabs
+///
+/// # Returns
+///
+/// Returns a [`SESSION_RESTORE_URL`] link.
+pub fn recovery_action_url(action: &RecoveryAction) -> String {
+    let mut query = url::form_urlencoded::Serializer::new(String::new());
+    match action {
+        RecoveryAction::RestoreAll => {
+            query.append_pair("action", "all");
+        }
+        RecoveryAction::RestoreSelected(positions) => {
+            query.append_pair("action", "selected");
+            for position in positions {
+                query.append_pair("tab", &position.to_string());
+            }
+        }
+        RecoveryAction::Discard => {
+            query.append_pair("action", "discard");
+        }
+    }
+    format!("{}?{}", SESSION_RESTORE_URL, query.finish())
+}
+
+/// Extract the action from a [`recovery_action_url`] link or form submission
+///
+/// Tab positions that are not numbers are dropped.
+///
+/// # Returns
+///
+/// Returns `None` if the URL is not a session restore action or has an
+/// unknown `action`.
+pub fn parse_recovery_action_url(url: &url::Url) -> Option<RecoveryAction> {
+    if url.scheme() != "about" || url.path() != "session-restore" {
+        return None;
+    }
+    let action = url
+        .query_pairs()
+        .find(|(key, _)| key == "action")
+        .map(|(_, value)| value.into_owned())?;
+
+    match action.as_str() {
+        "all" => Some(RecoveryAction::RestoreAll),
+        "selected" => Some(RecoveryAction::RestoreSelected(
+            url.query_pairs()
+                .filter(|(key, _)| key == "tab")
+                .filter_map(|(_, value)| value.parse().ok())
+                .collect(),
+        )),
+        "discard" => Some(RecoveryAction::Discard),
+        _ => None,
+    }
+}
+
+/// Render the "Restore previous session?" page
+///
+/// Lists the snapshot's tabs in one form, each with a checkbox, with
+/// buttons to restore all of them, restore the selected ones, or start
+/// fresh. The form submits to [`SESSION_RESTORE_URL`].
+///
+/// # Arguments
+///
+/// * `session` - Snapshot left by the crashed run
+pub fn render_recovery_page(session: &Session) -> String {
+    let mut body = SafeHtml::trusted("<h1>Restore previous session?</h1>");
+    body.push(SafeHtml::trusted(
+        "<p>FrankenBrowser did not shut down properly. \
+         These tabs were open when it stopped.</p>",
+    ));
+
+    let mut actions = SafeHtml::submit_button("action", "all", "Restore all");
+    actions
+        .push(SafeHtml::submit_button(
+            "action",
+            "selected",
+            "Restore selected",
+        ))
+        .push(SafeHtml::submit_button(
+            "action",
+            "discard",
+            "Start new session",
+        ));
+    let mut form = SafeHtml::new();
+
+    let mut rows = SafeHtml::new();
+    for (position, tab) in session.tabs.iter().enumerate() {
+        let mut title = SafeHtml::link(&tab.url, &tab.title, None);
+        title.push(SafeHtml::element(
+            "div",
+            Some("url"),
+            SafeHtml::text(&tab.url),
+        ));
+
+        let mut row =
+            SafeHtml::element("td", None, SafeHtml::checkbox("tab", &position.to_string()));
+        row.push(SafeHtml::element("td", None, title));
+        rows.push(SafeHtml::element("tr", None, row));
+    }
+    form.push(SafeHtml::element("table", None, rows))
+        .push(SafeHtml::element("div", Some("actions"), actions));
+    body.push(SafeHtml::form(SESSION_RESTORE_URL, form));
+
+    InternalPage::new("Restore previous session?")
+        .with_style(RECOVERY_PAGE_STYLE)
+        .with_body(body)
+        .render()
+}
+
+/// Snapshot files and the clean shutdown marker in one directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotStore {
+    dir: PathBuf,
+}
+
+impl SnapshotStore {
+    /// Use `dir` for the snapshots; it is created on the first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Directory holding the snapshots
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Write a snapshot, keeping the current one as the previous snapshot
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::SessionError` if a file cannot be written or
+    /// renamed.
+    pub fn write(&self, session: &Session) -> Result<()> {
+        let io_error = |what: &str, path: &Path, e: std::io::Error| {
+            Error::SessionError(format!("Cannot {} {}: {}", what, path.display(), e))
+        };
+        fs::create_dir_all(&self.dir).map_err(|e| io_error("create", &self.dir, e))?;
+
+        let json =
+            serde_json::to_vec_pretty(session).map_err(|e| Error::SessionError(e.to_string()))?;
+        let temp = self.dir.join(SNAPSHOT_TEMP_FILE);
+        let mut file = fs::File::create(&temp).map_err(|e| io_error("create", &temp, e))?;
+        file.write_all(&json)
+            .and_then(|()| file.sync_all())
+            .map_err(|e| io_error("write", &temp, e))?;
+
+        let current = self.dir.join(SNAPSHOT_FILE);
+        let previous = self.dir.join(PREVIOUS_SNAPSHOT_FILE);
+        match fs::rename(&current, &previous) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                return Err(io_error("rotate", &current, e));
+            }
+            _ => {}
+        }
+        fs::rename(&temp, &current).map_err(|e| io_error("replace", &current, e))
+    }
+
+    /// Load the newest readable snapshot
+    ///
+    /// # Returns
+    ///
+    /// Returns the newer snapshot, or the older one if the newer is
+    /// missing or corrupt; None if neither can be read.
+    pub fn load(&self) -> Option<Session> {
+        Session::load(&self.dir.join(SNAPSHOT_FILE))
+            .or_else(|_| Session::load(&self.dir.join(PREVIOUS_SNAPSHOT_FILE)))
+            .ok()
+    }
+
+    /// Delete both snapshots
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::SessionError` if an existing snapshot cannot be
+    /// deleted.
+    pub fn discard(&self) -> Result<()> {
+        for name in [SNAPSHOT_FILE, PREVIOUS_SNAPSHOT_FILE] {
+            remove_if_present(&self.dir.join(name))?;
+        }
+        Ok(())
+    }
+
+    /// Remove the clean shutdown marker at startup
+    ///
+    /// # Returns
+    ///
+    /// Returns whether the marker was there, i.e. whether the last run
+    /// shut down cleanly.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::SessionError` if the marker cannot be removed.
+    pub fn take_clean_shutdown_marker(&self) -> Result<bool> {
+        remove_if_present(&self.dir.join(CLEAN_SHUTDOWN_MARKER))
+    }
+
+    /// Record a graceful shutdown
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::SessionError` if the marker cannot be written.
+    pub fn mark_clean_shutdown(&self) -> Result<()> {
+        let marker = self.dir.join(CLEAN_SHUTDOWN_MARKER);
+        fs::create_dir_all(&self.dir)
+            .and_then(|()| fs::write(&marker, b""))
+            .map_err(|e| Error::SessionError(format!("Cannot write {}: {}", marker.display(), e)))
+    }
+}
+
+/// Delete a file, reporting whether it existed
+pub(crate) fn remove_if_present(path: &Path) -> Result<bool> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(Error::SessionError(format!(
+            "Cannot remove {}: {}",
+            path.display(),
+            e
+        ))),
+    }
+}
+
+/// Decides when the running session is written to its [`SnapshotStore`]
+///
+/// The shell marks the snapshots dirty on tab and navigation events; a
+/// snapshot is due once the session is dirty and the interval has passed
+/// since the last write.
+#[derive(Debug)]
+pub struct SessionSnapshots {
+    store: SnapshotStore,
+    interval: Duration,
+    dirty: bool,
+    last_write: Option<Instant>,
+}
+
+impl SessionSnapshots {
+    /// Snapshot to `store` at most once per `interval`
+    pub fn new(store: SnapshotStore, interval: Duration) -> Self {
+        Self {
+            store,
+            interval,
+            dirty: false,
+            last_write: None,
+        }
+    }
+
+    /// The files being written
+    pub fn store(&self) -> &SnapshotStore {
+        &self.store
+    }
+
+    /// Note that the session changed since the last write
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Whether the session changed since the last write
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Whether a snapshot should be written at `now`
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.dirty
+            && self
+                .last_write
+                .is_none_or(|at| now.saturating_duration_since(at) >= self.interval)
+    }
+
+    /// Write `session` and clear the dirty flag
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::SessionError` if the snapshot cannot be written; the
+    /// session stays dirty.
+    pub fn write(&mut self, session: &Session, now: Instant) -> Result<()> {
+        self.store.write(session)?;
+        self.dirty = false;
+        self.last_write = Some(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionTab;
+
+    fn fixture_session() -> Session {
+        let tab = |name: &str| SessionTab {
+            url: format!("https://{}.example/", name),
+            title: format!("{} <page>", name),
+            favicon: None,
+            hibernated: None,
+        };
+        Session {
+            tabs: vec![tab("alpha"), tab("beta"), tab("gamma")],
+            active: Some(1),
+            mru: vec![1, 0, 2],
+            window: None,
+        }
+    }
+
+    #[test]
+    fn test_snapshots_only_written_when_dirty() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut snapshots =
+            SessionSnapshots::new(SnapshotStore::new(dir.path()), Duration::from_secs(15));
+        let start = Instant::now();
+
+        assert!(!snapshots.is_due(start));
+        assert!(!dir.path().join(SNAPSHOT_FILE).exists());
+
+        snapshots.mark_dirty();
+        assert!(snapshots.is_due(start));
+        snapshots.write(&fixture_session(), start).unwrap();
+        assert!(!snapshots.is_dirty());
+
+        // Idle: nothing is due however long we wait
+        assert!(!snapshots.is_due(start + Duration::from_secs(600)));
+
+        // Changed again: due only once the interval has passed
+        snapshots.mark_dirty();
+        assert!(!snapshots.is_due(start + Duration::from_secs(5)));
+        assert!(snapshots.is_due(start + Duration::from_secs(15)));
+    }
+
+    #[test]
+    fn test_rotation_survives_truncated_newer_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SnapshotStore::new(dir.path());
+        let older = fixture_session();
+        let newer = older.subset(&[0]);
+
+        store.write(&older).unwrap();
+        store.write(&newer).unwrap();
+        assert_eq!(store.load(), Some(newer));
+        assert!(!dir.path().join(SNAPSHOT_TEMP_FILE).exists());
+
+        // Crash halfway through writing the newer file
+        let current = dir.path().join(SNAPSHOT_FILE);
+        let bytes = fs::read(&current).unwrap();
+        fs::write(&current, &bytes[..bytes.len() / 2]).unwrap();
+        assert_eq!(store.load(), Some(older));
+
+        store.discard().unwrap();
+        assert_eq!(store.load(), None);
+    }
+
+    #[test]
+    fn test_clean_shutdown_marker_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SnapshotStore::new(dir.path().join("sessions"));
+
+        // First run: no marker, nothing to recover
+        assert!(!store.take_clean_shutdown_marker().unwrap());
+
+        store.mark_clean_shutdown().unwrap();
+        assert!(store.dir().join(CLEAN_SHUTDOWN_MARKER).exists());
+        assert!(store.take_clean_shutdown_marker().unwrap());
+        assert!(!store.dir().join(CLEAN_SHUTDOWN_MARKER).exists());
+
+        // Running (or crashed): the marker stays gone
+        assert!(!store.take_clean_shutdown_marker().unwrap());
+    }
+
+    #[test]
+    fn test_recovery_page_lists_snapshot_tabs() {
+        let html = render_recovery_page(&fixture_session());
+
+        assert!(html.contains("<h1>Restore previous session?</h1>"));
+        assert!(html.contains(&format!("action=\"{}\"", SESSION_RESTORE_URL)));
+        for (position, name) in ["alpha", "beta", "gamma"].iter().enumerate() {
+            assert!(html.contains(&format!("value=\"{}\"", position)));
+            assert!(html.contains(&format!("https://{}.example/", name)));
+            assert!(html.contains(&format!("{} &lt;page&gt;", name)));
+        }
+        for action in ["all", "selected", "discard"] {
+            assert!(html.contains(&format!("name=\"action\" value=\"{}\"", action)));
+        }
+    }
+
+    #[test]
+    fn test_recovery_action_url_round_trip() {
+        let actions = [
+            RecoveryAction::RestoreAll,
+            RecoveryAction::RestoreSelected(vec![0, 2]),
+            RecoveryAction::Discard,
+        ];
+        for action in actions {
+            let url = url::Url::parse(&recovery_action_url(&action)).unwrap();
+            assert_eq!(parse_recovery_action_url(&url), Some(action));
+        }
+
+        let form = url::Url::parse("about:session-restore?tab=1&tab=x&action=selected").unwrap();
+        assert_eq!(
+            parse_recovery_action_url(&form),
+            Some(RecoveryAction::RestoreSelected(vec![1]))
+        );
+        let unknown = url::Url::parse("about:session-restore?action=maybe").unwrap();
+        assert_eq!(parse_recovery_action_url(&unknown), None);
+        let other = url::Url::parse("about:bookmarks-action?action=all").unwrap();
+        assert_eq!(parse_recovery_action_url(&other), None);
+    }
+
+    #[test]
+    fn test_recovery_policy_from_name() {
+        assert_eq!(
+            RecoveryPolicy::from_name("prompt"),
+            Some(RecoveryPolicy::Prompt)
+        );
+        assert_eq!(
+            RecoveryPolicy::from_name("restore"),
+            Some(RecoveryPolicy::Restore)
+        );
+        assert_eq!(
+            RecoveryPolicy::from_name("discard"),
+            Some(RecoveryPolicy::Discard)
+        );
+        assert_eq!(RecoveryPolicy::from_name("ask"), None);
+    }
+}
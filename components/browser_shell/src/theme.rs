@@ -0,0 +1,152 @@
+//! Color themes for the shell and pages
+//!
+//! [`ThemeProvider`] turns the `appearance.theme` setting into the colors
+//! the URL bar, tab bar and status bar are drawn with, resolving "auto" to
+//! the system theme once when the provider is made. Pages get the same
+//! choice as a `color-scheme` on their root element, so their default
+//! colors and form controls match, plus the colors as `--franken-*` CSS
+//! variables. Pages that set their own colors keep them.
+
+use config_manager::Theme;
+
+/// Colors of one theme, as CSS hex colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorTokens {
+    /// Background of bars and pages without their own
+    pub background: &'static str,
+    /// Text and icons
+    pub foreground: &'static str,
+    /// Highlights: the active tab, progress, focus rings
+    pub accent: &'static str,
+    /// Errors and warnings
+    pub error: &'static str,
+}
+
+/// Colors of the light theme
+pub const LIGHT_COLORS: ColorTokens = ColorTokens {
+    background: "#f9f9fb",
+    foreground: "#15141a",
+    accent: "#0061e0",
+    error: "#c50042",
+};
+
+/// Colors of the dark theme
+pub const DARK_COLORS: ColorTokens = ColorTokens {
+    background: "#1c1b22",
+    foreground: "#fbfbfe",
+    accent: "#00ddff",
+    error: "#ff848b",
+};
+
+/// The theme setting and the colors it resolves to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThemeProvider {
+    theme: Theme,
+    dark: bool,
+}
+
+impl ThemeProvider {
+    /// Provider for a theme setting, reading the system theme for "auto"
+    pub fn new(theme: Theme) -> Self {
+        Self {
+            theme,
+            dark: theme.resolve().is_dark(),
+        }
+    }
+
+    /// Provider for a theme setting, given the system theme
+    ///
+    /// # Arguments
+    ///
+    /// * `theme` - The theme setting
+    /// * `system` - The system theme, `None` if unknown
+    pub fn with_system_theme(theme: Theme, system: Option<Theme>) -> Self {
+        Self {
+            theme,
+            dark: theme.resolve_with(system).is_dark(),
+        }
+    }
+
+    /// The theme setting
+    pub fn theme(&self) -> Theme {
+        self.theme
+    }
+
+    /// Whether the colors are the dark theme's
+    pub fn is_dark(&self) -> bool {
+        self.dark
+    }
+
+    /// Colors to draw with
+    pub fn colors(&self) -> ColorTokens {
+        if self.dark {
+            DARK_COLORS
+        } else {
+            LIGHT_COLORS
+        }
+    }
+
+    /// Script giving a page the theme's color scheme and colors
+    ///
+    /// Meant to run at document start in every page, and again in the
+    /// current page when the theme changes.
+    pub fn page_script(&self) -> String {
+        let colors = self.colors();
+        PAGE_SCRIPT
+            .replace("__SCHEME__", if self.dark { "'dark'" } else { "'light'" })
+            .replace(
+                "__COLORS__",
+                &format!(
+                    "{{ background: '{}', foreground: '{}', accent: '{}', error: '{}' }}",
+                    colors.background, colors.foreground, colors.accent, colors.error
+                ),
+            )
+    }
+}
+
+const PAGE_SCRIPT: &str = r#"
+(function (scheme, colors) {
+    var root = document.documentElement;
+    if (!root) {
+        return;
+    }
+    root.style.colorScheme = scheme;
+    Object.keys(colors).forEach(function (name) {
+        root.style.setProperty('--franken-' + name, colors[name]);
+    });
+})(__SCHEME__, __COLORS__);
+"#;
+
+impl Default for ThemeProvider {
+    fn default() -> Self {
+        Self::new(Theme::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provider_resolves_auto_to_system_theme() {
+        let auto_dark = ThemeProvider::with_system_theme(Theme::Auto, Some(Theme::Dark));
+        assert!(auto_dark.is_dark());
+        assert_eq!(auto_dark.theme(), Theme::Auto);
+        assert_eq!(auto_dark.colors(), DARK_COLORS);
+
+        let unknown = ThemeProvider::with_system_theme(Theme::Auto, None);
+        assert_eq!(unknown.colors(), LIGHT_COLORS);
+        let light = ThemeProvider::with_system_theme(Theme::Light, Some(Theme::Dark));
+        assert!(!light.is_dark());
+    }
+
+    #[test]
+    fn test_page_script_sets_scheme_and_colors() {
+        let script = ThemeProvider::with_system_theme(Theme::Dark, None).page_script();
+        assert!(script.contains("})('dark', { background: '#1c1b22',"));
+        assert!(script.contains(&format!("accent: '{}'", DARK_COLORS.accent)));
+        assert!(!script.contains("__"));
+        let script = ThemeProvider::with_system_theme(Theme::Light, None).page_script();
+        assert!(script.contains("})('light', {"));
+    }
+}
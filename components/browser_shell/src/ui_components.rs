@@ -4,9 +4,12 @@
 //! - URLBar: URL input with validation and suggestions
 //! - NavigationButtons: Browser navigation controls
 //! - TabBar: Tab management UI
+//! - TabSwitcher: Most-recently-used tab switcher shown while Ctrl is held
 //! - StatusBar: Status information display
 
 use crate::errors::{Error, Result};
+use crate::theme::ColorTokens;
+use network_stack::MixedContentReport;
 use std::sync::Arc;
 use url::Url;
 
@@ -20,10 +23,44 @@ pub enum SecurityState {
     Secure,
     /// HTTP connection or invalid certificate
     Insecure,
+    /// HTTPS page that loaded or tried to load http subresources
+    MixedContent,
     /// Unknown or not applicable
     Unknown,
 }
 
+impl SecurityState {
+    /// Security state of a loaded page
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - URL of the page
+    /// * `mixed_content` - Mixed content requests made by the page
+    pub fn for_page(url: &Url, mixed_content: &MixedContentReport) -> Self {
+        match url.scheme() {
+            "https" if mixed_content.is_mixed() => SecurityState::MixedContent,
+            "https" => SecurityState::Secure,
+            "http" | "file" => SecurityState::Insecure,
+            _ => SecurityState::Unknown,
+        }
+    }
+
+    /// Security state of a page before its subresources are known
+    ///
+    /// Follows the scheme alone: `https` is secure, `http` and `file` are
+    /// not, and anything else (`about:`, `data:`, an unparsable URL) is
+    /// unknown.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - URL of the page
+    pub fn for_url(url: &str) -> Self {
+        Url::parse(url).map_or(SecurityState::Unknown, |url| {
+            Self::for_page(&url, &MixedContentReport::new())
+        })
+    }
+}
+
 /// URL validation state
 #[derive(Debug, Clone, PartialEq)]
 pub enum ValidationState {
@@ -63,6 +100,11 @@ pub enum UiElement {
         /// Text content
         content: String,
     },
+    /// De-emphasized text element (e.g. unloaded tab titles)
+    DimmedText {
+        /// Text content
+        content: String,
+    },
     /// Icon element
     Icon {
         /// Icon name
@@ -73,6 +115,24 @@ pub enum UiElement {
         /// Progress value (0.0 to 1.0)
         value: f32,
     },
+    /// Element drawn in a theme's colors
+    Themed {
+        /// Colors to draw the element and its children with
+        colors: ColorTokens,
+        /// The element
+        child: Box<UiElement>,
+    },
+}
+
+/// Wrap a widget's element in its theme colors, if it has any
+fn themed(colors: Option<ColorTokens>, element: UiElement) -> UiElement {
+    match colors {
+        Some(colors) => UiElement::Themed {
+            colors,
+            child: Box::new(element),
+        },
+        None => element,
+    }
 }
 
 // Manual implementation of PartialEq for UiElement
@@ -106,10 +166,23 @@ impl PartialEq for UiElement {
                 },
             ) => v1 == v2 && p1 == p2,
             (UiElement::Text { content: c1 }, UiElement::Text { content: c2 }) => c1 == c2,
+            (UiElement::DimmedText { content: c1 }, UiElement::DimmedText { content: c2 }) => {
+                c1 == c2
+            }
             (UiElement::Icon { name: n1 }, UiElement::Icon { name: n2 }) => n1 == n2,
             (UiElement::ProgressBar { value: v1 }, UiElement::ProgressBar { value: v2 }) => {
                 (v1 - v2).abs() < f32::EPSILON
             }
+            (
+                UiElement::Themed {
+                    colors: k1,
+                    child: c1,
+                },
+                UiElement::Themed {
+                    colors: k2,
+                    child: c2,
+                },
+            ) => k1 == k2 && c1 == c2,
             _ => false,
         }
     }
@@ -141,11 +214,20 @@ impl std::fmt::Debug for UiElement {
             UiElement::Text { content } => {
                 f.debug_struct("Text").field("content", content).finish()
             }
+            UiElement::DimmedText { content } => f
+                .debug_struct("DimmedText")
+                .field("content", content)
+                .finish(),
             UiElement::Icon { name } => f.debug_struct("Icon").field("name", name).finish(),
             UiElement::ProgressBar { value } => f
                 .debug_struct("ProgressBar")
                 .field("value", value)
                 .finish(),
+            UiElement::Themed { colors, child } => f
+                .debug_struct("Themed")
+                .field("colors", colors)
+                .field("child", child)
+                .finish(),
         }
     }
 }
@@ -171,6 +253,8 @@ pub struct URLBar {
     on_focus: Option<EventHandler>,
     /// onBlur event handler
     on_blur: Option<EventHandler>,
+    /// Theme colors; `None` draws in the toolkit's own
+    colors: Option<ColorTokens>,
 }
 
 impl URLBar {
@@ -186,6 +270,7 @@ impl URLBar {
             on_submit: None,
             on_focus: None,
             on_blur: None,
+            colors: None,
         }
     }
 
@@ -231,6 +316,21 @@ impl URLBar {
         }
     }
 
+    /// Put keyword bookmarks at the top of the suggestions
+    ///
+    /// # Arguments
+    ///
+    /// * `keywords` - Matches for the typed text, from
+    ///   `BrowserEngine::keyword_suggestions`
+    pub fn add_keyword_suggestions(&mut self, keywords: &[browser_core::KeywordSuggestion]) {
+        let labels: Vec<String> = keywords
+            .iter()
+            .map(|keyword| keyword.label())
+            .filter(|label| !self.suggestions.contains(label))
+            .collect();
+        self.suggestions.splice(0..0, labels);
+    }
+
     /// Clear all suggestions
     pub fn clear_suggestions(&mut self) {
         self.suggestions.clear();
@@ -291,16 +391,22 @@ impl URLBar {
         }
     }
 
+    /// Draw the URL bar in a theme's colors
+    pub fn set_colors(&mut self, colors: ColorTokens) {
+        self.colors = Some(colors);
+    }
+
     /// Render the URLBar as a UI element
     pub fn render(&self) -> UiElement {
-        UiElement::Container {
-            children: vec![
-                UiElement::Input {
+        themed(
+            self.colors,
+            UiElement::Container {
+                children: vec![UiElement::Input {
                     value: self.url.clone(),
                     placeholder: self.placeholder.clone(),
-                },
-            ],
-        }
+                }],
+            },
+        )
     }
 }
 
@@ -500,6 +606,14 @@ pub struct TabWidget {
     pub favicon: Option<String>,
     /// Whether tab is loading
     pub loading: bool,
+    /// Load progress (0.0 to 1.0) while loading, if reported
+    pub progress: Option<f32>,
+    /// Whether the tab's content is not loaded (pending or discarded)
+    pub dimmed: bool,
+    /// Whether the tab's state was written to disk
+    pub hibernated: bool,
+    /// Whether the tab is pinned as an app tab
+    pub pinned: bool,
 }
 
 impl TabWidget {
@@ -510,6 +624,10 @@ impl TabWidget {
             title,
             favicon: None,
             loading: false,
+            progress: None,
+            dimmed: false,
+            hibernated: false,
+            pinned: false,
         }
     }
 
@@ -521,6 +639,14 @@ impl TabWidget {
     /// Set loading state
     pub fn set_loading(&mut self, loading: bool) {
         self.loading = loading;
+        if !loading {
+            self.progress = None;
+        }
+    }
+
+    /// Set dimmed state
+    pub fn set_dimmed(&mut self, dimmed: bool) {
+        self.dimmed = dimmed;
     }
 }
 
@@ -539,6 +665,8 @@ pub struct TabBar {
     on_tab_close: Option<EventHandler>,
     /// New tab handler
     on_new_tab: Option<EventHandler>,
+    /// Theme colors; `None` draws in the toolkit's own
+    colors: Option<ColorTokens>,
 }
 
 impl TabBar {
@@ -551,6 +679,7 @@ impl TabBar {
             on_tab_click: None,
             on_tab_close: None,
             on_new_tab: None,
+            colors: None,
         }
     }
 
@@ -563,6 +692,7 @@ impl TabBar {
             on_tab_click: None,
             on_tab_close: None,
             on_new_tab: None,
+            colors: None,
         }
     }
 
@@ -586,6 +716,34 @@ impl TabBar {
         Ok(())
     }
 
+    /// Add a new tab right after another one
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::TabNotFound` if `after` is not in the bar, or
+    /// `Error::WindowError` if the bar is full.
+    pub fn insert_tab_after(&mut self, after: u32, id: u32, title: String) -> Result<()> {
+        let position = self
+            .tabs
+            .iter()
+            .position(|t| t.id == after)
+            .ok_or_else(|| Error::TabNotFound(after))?
+            + 1;
+        if self.tabs.len() >= self.max_tabs {
+            return Err(Error::WindowError(format!(
+                "Maximum tab limit ({}) reached",
+                self.max_tabs
+            )));
+        }
+
+        self.tabs.insert(position, TabWidget::new(id, title));
+        if let Some(active_idx) = self.active_tab_index.filter(|idx| *idx >= position) {
+            // Active tab shifted right
+            self.active_tab_index = Some(active_idx + 1);
+        }
+        Ok(())
+    }
+
     /// Remove a tab by ID
     pub fn remove_tab(&mut self, id: u32) -> Result<()> {
         let position = self
@@ -653,7 +811,78 @@ impl TabBar {
             .find(|t| t.id == id)
             .ok_or_else(|| Error::TabNotFound(id))?;
 
-        tab.loading = loading;
+        tab.set_loading(loading);
+        Ok(())
+    }
+
+    /// Set a loading tab's progress (0.0 to 1.0)
+    ///
+    /// Shown in place of the tab's loading spinner. Progress of 1.0 ends
+    /// the load; the tab stops showing as loading.
+    pub fn set_tab_progress(&mut self, id: u32, progress: f32) -> Result<()> {
+        let tab = self
+            .tabs
+            .iter_mut()
+            .find(|t| t.id == id)
+            .ok_or_else(|| Error::TabNotFound(id))?;
+
+        if progress >= 1.0 {
+            tab.set_loading(false);
+        } else {
+            tab.loading = true;
+            tab.progress = Some(progress.clamp(0.0, 1.0));
+        }
+        Ok(())
+    }
+
+    /// Set whether a tab is rendered dimmed (content not loaded)
+    pub fn set_tab_dimmed(&mut self, id: u32, dimmed: bool) -> Result<()> {
+        let tab = self
+            .tabs
+            .iter_mut()
+            .find(|t| t.id == id)
+            .ok_or_else(|| Error::TabNotFound(id))?;
+
+        tab.dimmed = dimmed;
+        Ok(())
+    }
+
+    /// Set whether a tab shows the hibernated indicator
+    pub fn set_tab_hibernated(&mut self, id: u32, hibernated: bool) -> Result<()> {
+        let tab = self
+            .tabs
+            .iter_mut()
+            .find(|t| t.id == id)
+            .ok_or_else(|| Error::TabNotFound(id))?;
+
+        tab.hibernated = hibernated;
+        Ok(())
+    }
+
+    /// Pin or unpin a tab
+    ///
+    /// Pinned tabs are kept together at the start of the strip: pinning
+    /// moves the tab to the end of the pinned block, unpinning moves it to
+    /// the first unpinned position. The active tab stays active.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::TabNotFound` if the tab is not in the bar.
+    pub fn set_tab_pinned(&mut self, id: u32, pinned: bool) -> Result<()> {
+        let position = self
+            .tabs
+            .iter()
+            .position(|t| t.id == id)
+            .ok_or_else(|| Error::TabNotFound(id))?;
+        let active_id = self.get_active_tab_id();
+
+        let mut tab = self.tabs.remove(position);
+        tab.pinned = pinned;
+        let boundary = self.tabs.iter().take_while(|t| t.pinned).count();
+        self.tabs.insert(boundary, tab);
+
+        self.active_tab_index =
+            active_id.and_then(|active| self.tabs.iter().position(|t| t.id == active));
         Ok(())
     }
 
@@ -662,11 +891,21 @@ impl TabBar {
         self.tabs.len()
     }
 
+    /// Get the maximum number of tabs
+    pub fn get_max_tabs(&self) -> usize {
+        self.max_tabs
+    }
+
     /// Get tab by ID
     pub fn get_tab(&self, id: u32) -> Option<&TabWidget> {
         self.tabs.iter().find(|t| t.id == id)
     }
 
+    /// Get tab IDs in tab strip order
+    pub fn tab_ids(&self) -> Vec<u32> {
+        self.tabs.iter().map(|t| t.id).collect()
+    }
+
     /// Set tab click handler
     pub fn on_tab_click(&mut self, handler: EventHandler) {
         self.on_tab_click = Some(handler);
@@ -689,28 +928,47 @@ impl TabBar {
         }
     }
 
+    /// Draw the tab bar in a theme's colors
+    pub fn set_colors(&mut self, colors: ColorTokens) {
+        self.colors = Some(colors);
+    }
+
     /// Render tab bar as UI element
     pub fn render(&self) -> UiElement {
         let mut children: Vec<UiElement> = self
             .tabs
             .iter()
-            .map(|tab| {
-                UiElement::Container {
-                    children: vec![
+            .map(|tab| UiElement::Container {
+                children: vec![
+                    if tab.dimmed {
+                        UiElement::DimmedText {
+                            content: tab.title.clone(),
+                        }
+                    } else {
                         UiElement::Text {
                             content: tab.title.clone(),
-                        },
-                        if tab.loading {
-                            UiElement::Icon {
-                                name: "loading".to_string(),
-                            }
-                        } else {
-                            UiElement::Icon {
-                                name: "close".to_string(),
-                            }
-                        },
-                    ],
-                }
+                        }
+                    },
+                    if let Some(value) = tab.progress.filter(|_| tab.loading) {
+                        UiElement::ProgressBar { value }
+                    } else if tab.loading {
+                        UiElement::Icon {
+                            name: "loading".to_string(),
+                        }
+                    } else if tab.hibernated {
+                        UiElement::Icon {
+                            name: "moon".to_string(),
+                        }
+                    } else if tab.pinned {
+                        UiElement::Icon {
+                            name: "pin".to_string(),
+                        }
+                    } else {
+                        UiElement::Icon {
+                            name: "close".to_string(),
+                        }
+                    },
+                ],
             })
             .collect();
 
@@ -721,7 +979,7 @@ impl TabBar {
             handler: self.on_new_tab.clone(),
         });
 
-        UiElement::Container { children }
+        themed(self.colors, UiElement::Container { children })
     }
 }
 
@@ -731,6 +989,117 @@ impl Default for TabBar {
     }
 }
 
+/// Tab switcher listing tabs in most-recently-used order
+///
+/// Opened by Ctrl+Tab when MRU cycling is enabled. While open, each further
+/// Tab press moves the selection; the shell commits the selection when Ctrl
+/// is released and cancels on Escape.
+#[derive(Debug, Clone, Default)]
+pub struct TabSwitcher {
+    /// Tabs, most recently used first
+    entries: Vec<TabWidget>,
+    /// Index of the selected entry; `None` while closed
+    selected: Option<usize>,
+}
+
+impl TabSwitcher {
+    /// Create a closed TabSwitcher
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open the switcher
+    ///
+    /// The selection starts on the previously used tab, or on the least
+    /// recently used one when `backwards` is set (Ctrl+Shift+Tab).
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - Tabs, most recently used first
+    /// * `backwards` - Start from the end of the list
+    pub fn open(&mut self, entries: Vec<TabWidget>, backwards: bool) {
+        self.selected = match entries.len() {
+            0 => None,
+            1 => Some(0),
+            len if backwards => Some(len - 1),
+            _ => Some(1),
+        };
+        self.entries = entries;
+    }
+
+    /// Whether the switcher is showing
+    pub fn is_open(&self) -> bool {
+        self.selected.is_some()
+    }
+
+    /// Move the selection down, wrapping to the top
+    pub fn select_next(&mut self) {
+        if let Some(index) = self.selected {
+            self.selected = Some((index + 1) % self.entries.len());
+        }
+    }
+
+    /// Move the selection up, wrapping to the bottom
+    pub fn select_previous(&mut self) {
+        if let Some(index) = self.selected {
+            let len = self.entries.len();
+            self.selected = Some((index + len - 1) % len);
+        }
+    }
+
+    /// ID of the selected tab
+    pub fn selected_tab_id(&self) -> Option<u32> {
+        self.selected
+            .and_then(|index| self.entries.get(index))
+            .map(|tab| tab.id)
+    }
+
+    /// Entries in the switcher, most recently used first
+    pub fn entries(&self) -> &[TabWidget] {
+        &self.entries
+    }
+
+    /// Close the switcher, returning the selected tab ID
+    pub fn commit(&mut self) -> Option<u32> {
+        let selected = self.selected_tab_id();
+        self.cancel();
+        selected
+    }
+
+    /// Close the switcher without selecting a tab
+    pub fn cancel(&mut self) {
+        self.entries.clear();
+        self.selected = None;
+    }
+
+    /// Render the switcher as a list of rows; empty while closed
+    ///
+    /// The selected row is marked with a `selected` icon.
+    pub fn render(&self) -> Vec<UiElement> {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(index, tab)| {
+                let mut children = vec![if tab.dimmed {
+                    UiElement::DimmedText {
+                        content: tab.title.clone(),
+                    }
+                } else {
+                    UiElement::Text {
+                        content: tab.title.clone(),
+                    }
+                }];
+                if self.selected == Some(index) {
+                    children.push(UiElement::Icon {
+                        name: "selected".to_string(),
+                    });
+                }
+                UiElement::Container { children }
+            })
+            .collect()
+    }
+}
+
 /// Status bar widget
 #[derive(Clone)]
 pub struct StatusBar {
@@ -742,6 +1111,8 @@ pub struct StatusBar {
     security_state: SecurityState,
     /// Progress (0.0 to 1.0)
     progress: Option<f32>,
+    /// Theme colors; `None` draws in the toolkit's own
+    colors: Option<ColorTokens>,
 }
 
 impl StatusBar {
@@ -752,6 +1123,7 @@ impl StatusBar {
             loading: false,
             security_state: SecurityState::Unknown,
             progress: None,
+            colors: None,
         }
     }
 
@@ -798,6 +1170,11 @@ impl StatusBar {
         self.progress
     }
 
+    /// Draw the status bar in a theme's colors
+    pub fn set_colors(&mut self, colors: ColorTokens) {
+        self.colors = Some(colors);
+    }
+
     /// Render status bar as UI element
     pub fn render(&self) -> UiElement {
         let mut children = vec![
@@ -805,6 +1182,7 @@ impl StatusBar {
                 name: match self.security_state {
                     SecurityState::Secure => "lock",
                     SecurityState::Insecure => "unlock",
+                    SecurityState::MixedContent => "warning",
                     SecurityState::Unknown => "info",
                 }
                 .to_string(),
@@ -824,7 +1202,7 @@ impl StatusBar {
             children.push(UiElement::ProgressBar { value: progress });
         }
 
-        UiElement::Container { children }
+        themed(self.colors, UiElement::Container { children })
     }
 }
 
@@ -903,6 +1281,25 @@ mod tests {
         assert_eq!(urlbar.get_suggestions().len(), 0);
     }
 
+    #[test]
+    fn test_urlbar_keyword_suggestions_come_first() {
+        let mut urlbar = URLBar::new();
+        urlbar.add_suggestion("https://github.com/".to_string());
+
+        let keyword = browser_core::KeywordSuggestion {
+            keyword: "gh".to_string(),
+            title: "GitHub search".to_string(),
+            url: "https://github.com/search?q=%s".to_string(),
+        };
+        urlbar.add_keyword_suggestions(std::slice::from_ref(&keyword));
+        urlbar.add_keyword_suggestions(&[keyword]);
+
+        assert_eq!(
+            urlbar.get_suggestions(),
+            &["gh \u{2014} GitHub search", "https://github.com/"]
+        );
+    }
+
     #[test]
     fn test_urlbar_focus() {
         let mut urlbar = URLBar::new();
@@ -1171,6 +1568,23 @@ mod tests {
         assert_eq!(tabbar.get_active_tab_id(), Some(2));
     }
 
+    #[test]
+    fn test_tabbar_insert_tab_after() {
+        let mut tabbar = TabBar::with_max_tabs(4);
+        tabbar.add_tab(1, "Tab 1".to_string()).unwrap();
+        tabbar.add_tab(2, "Tab 2".to_string()).unwrap();
+        tabbar.set_active_tab(2).unwrap();
+
+        tabbar.insert_tab_after(1, 3, "Tab 3".to_string()).unwrap();
+        assert_eq!(tabbar.tab_ids(), vec![1, 3, 2]);
+        assert_eq!(tabbar.get_active_tab_id(), Some(2));
+        tabbar.insert_tab_after(2, 4, "Tab 4".to_string()).unwrap();
+        assert_eq!(tabbar.tab_ids(), vec![1, 3, 2, 4]);
+
+        assert!(tabbar.insert_tab_after(9, 5, "Tab 5".to_string()).is_err());
+        assert!(tabbar.insert_tab_after(1, 5, "Tab 5".to_string()).is_err());
+    }
+
     #[test]
     fn test_tabbar_remove_tab_not_found() {
         let mut tabbar = TabBar::new();
@@ -1224,6 +1638,36 @@ mod tests {
         assert!(!tab.loading);
     }
 
+    #[test]
+    fn test_tabbar_progress_replaces_spinner() {
+        let mut tabbar = TabBar::new();
+        tabbar.add_tab(1, "Tab 1".to_string()).unwrap();
+        tabbar.set_tab_loading(1, true).unwrap();
+        tabbar.set_tab_progress(1, 0.4).unwrap();
+        assert!(tabbar.set_tab_progress(99, 0.4).is_err());
+
+        let indicator = |tabbar: &TabBar| match tabbar.render() {
+            UiElement::Container { children } => match &children[0] {
+                UiElement::Container { children } => children[1].clone(),
+                other => panic!("Expected tab container, got {:?}", other),
+            },
+            other => panic!("Expected Container element, got {:?}", other),
+        };
+        assert_eq!(indicator(&tabbar), UiElement::ProgressBar { value: 0.4 });
+
+        // Completion ends the load
+        tabbar.set_tab_progress(1, 1.0).unwrap();
+        let tab = tabbar.get_tab(1).unwrap();
+        assert!(!tab.loading);
+        assert_eq!(tab.progress, None);
+        assert_eq!(
+            indicator(&tabbar),
+            UiElement::Icon {
+                name: "close".to_string()
+            }
+        );
+    }
+
     #[test]
     fn test_tabbar_event_handlers() {
         let mut tabbar = TabBar::new();
@@ -1253,6 +1697,180 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tabbar_render_dimmed_tab() {
+        let mut tabbar = TabBar::new();
+        tabbar.add_tab(1, "Live".to_string()).unwrap();
+        tabbar.add_tab(2, "Pending".to_string()).unwrap();
+        tabbar.set_tab_dimmed(2, true).unwrap();
+        assert!(tabbar.set_tab_dimmed(99, true).is_err());
+
+        match tabbar.render() {
+            UiElement::Container { children } => {
+                let titles: Vec<&UiElement> = children
+                    .iter()
+                    .filter_map(|c| match c {
+                        UiElement::Container { children } => children.first(),
+                        _ => None,
+                    })
+                    .collect();
+                assert_eq!(
+                    titles[0],
+                    &UiElement::Text {
+                        content: "Live".to_string()
+                    }
+                );
+                assert_eq!(
+                    titles[1],
+                    &UiElement::DimmedText {
+                        content: "Pending".to_string()
+                    }
+                );
+            }
+            _ => panic!("Expected Container element"),
+        }
+    }
+
+    #[test]
+    fn test_tabbar_render_hibernated_tab() {
+        let mut tabbar = TabBar::new();
+        tabbar.add_tab(1, "Asleep".to_string()).unwrap();
+        tabbar.set_tab_dimmed(1, true).unwrap();
+        tabbar.set_tab_hibernated(1, true).unwrap();
+        assert!(tabbar.set_tab_hibernated(99, true).is_err());
+
+        match tabbar.render() {
+            UiElement::Container { children } => match &children[0] {
+                UiElement::Container { children } => {
+                    assert_eq!(
+                        children[1],
+                        UiElement::Icon {
+                            name: "moon".to_string()
+                        }
+                    );
+                }
+                _ => panic!("Expected tab Container"),
+            },
+            _ => panic!("Expected Container element"),
+        }
+    }
+
+    #[test]
+    fn test_tabbar_tab_ids_in_strip_order() {
+        let mut tabbar = TabBar::new();
+        tabbar.add_tab(3, "C".to_string()).unwrap();
+        tabbar.add_tab(1, "A".to_string()).unwrap();
+        tabbar.add_tab(2, "B".to_string()).unwrap();
+        tabbar.remove_tab(1).unwrap();
+
+        assert_eq!(tabbar.tab_ids(), vec![3, 2]);
+    }
+
+    #[test]
+    fn test_tabbar_pinned_tabs_lead_the_strip() {
+        let mut tabbar = TabBar::new();
+        for id in 1..=4 {
+            tabbar.add_tab(id, format!("Tab {}", id)).unwrap();
+        }
+        tabbar.set_active_tab(2).unwrap();
+
+        tabbar.set_tab_pinned(3, true).unwrap();
+        tabbar.set_tab_pinned(1, true).unwrap();
+        assert_eq!(tabbar.tab_ids(), vec![3, 1, 2, 4]);
+        assert_eq!(tabbar.get_active_tab_id(), Some(2));
+
+        tabbar.set_tab_pinned(3, false).unwrap();
+        assert_eq!(tabbar.tab_ids(), vec![1, 3, 2, 4]);
+        assert!(tabbar.get_tab(1).unwrap().pinned);
+        assert!(!tabbar.get_tab(3).unwrap().pinned);
+        assert!(tabbar.set_tab_pinned(9, true).is_err());
+    }
+
+    // ========================================
+    // TabSwitcher Tests
+    // ========================================
+
+    fn switcher_entries() -> Vec<TabWidget> {
+        vec![
+            TabWidget::new(3, "Three".to_string()),
+            TabWidget::new(1, "One".to_string()),
+            TabWidget::new(2, "Two".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_tab_switcher_cycles_and_commits() {
+        let mut switcher = TabSwitcher::new();
+        assert!(!switcher.is_open());
+        assert!(switcher.render().is_empty());
+
+        switcher.open(switcher_entries(), false);
+        assert!(switcher.is_open());
+        assert_eq!(switcher.selected_tab_id(), Some(1));
+
+        switcher.select_next();
+        assert_eq!(switcher.selected_tab_id(), Some(2));
+        switcher.select_next();
+        assert_eq!(switcher.selected_tab_id(), Some(3));
+        switcher.select_previous();
+        assert_eq!(switcher.selected_tab_id(), Some(2));
+
+        assert_eq!(switcher.commit(), Some(2));
+        assert!(!switcher.is_open());
+        assert!(switcher.entries().is_empty());
+    }
+
+    #[test]
+    fn test_tab_switcher_backwards_and_cancel() {
+        let mut switcher = TabSwitcher::new();
+        switcher.open(switcher_entries(), true);
+        assert_eq!(switcher.selected_tab_id(), Some(2));
+
+        switcher.cancel();
+        assert!(!switcher.is_open());
+        assert_eq!(switcher.commit(), None);
+    }
+
+    #[test]
+    fn test_tab_switcher_render_marks_selection() {
+        let mut switcher = TabSwitcher::new();
+        let mut entries = switcher_entries();
+        entries[2].set_dimmed(true);
+        switcher.open(entries, false);
+
+        let rows = switcher.render();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[0],
+            UiElement::Container {
+                children: vec![UiElement::Text {
+                    content: "Three".to_string()
+                }]
+            }
+        );
+        assert_eq!(
+            rows[1],
+            UiElement::Container {
+                children: vec![
+                    UiElement::Text {
+                        content: "One".to_string()
+                    },
+                    UiElement::Icon {
+                        name: "selected".to_string()
+                    },
+                ]
+            }
+        );
+        assert_eq!(
+            rows[2],
+            UiElement::Container {
+                children: vec![UiElement::DimmedText {
+                    content: "Two".to_string()
+                }]
+            }
+        );
+    }
+
     // ========================================
     // StatusBar Tests
     // ========================================
@@ -1294,6 +1912,89 @@ mod tests {
         assert_eq!(*statusbar.get_security_state(), SecurityState::Insecure);
     }
 
+    #[test]
+    fn test_security_state_for_page_with_mixed_content() {
+        use network_stack::{MixedContentEntry, MixedContentOutcome};
+        use shared_types::ResourceType;
+
+        let page = Url::parse("https://site.test/").unwrap();
+        let mut report = MixedContentReport::new();
+        assert_eq!(
+            SecurityState::for_page(&page, &report),
+            SecurityState::Secure
+        );
+
+        let entry = |outcome| MixedContentEntry {
+            url: Url::parse("http://cdn.test/app.js").unwrap(),
+            resource_type: ResourceType::Script,
+            outcome,
+        };
+        report.record(entry(MixedContentOutcome::Upgraded));
+        assert_eq!(
+            SecurityState::for_page(&page, &report),
+            SecurityState::Secure
+        );
+        report.record(entry(MixedContentOutcome::Blocked));
+        assert_eq!(
+            SecurityState::for_page(&page, &report),
+            SecurityState::MixedContent
+        );
+
+        let mut statusbar = StatusBar::new();
+        statusbar.set_security_state(SecurityState::for_page(&page, &report));
+        match statusbar.render() {
+            UiElement::Container { children } => match &children[0] {
+                UiElement::Icon { name } => assert_eq!(name, "warning"),
+                _ => panic!("expected security icon first"),
+            },
+            _ => panic!("expected Container"),
+        }
+
+        assert_eq!(
+            SecurityState::for_page(&Url::parse("http://site.test/").unwrap(), &report),
+            SecurityState::Insecure
+        );
+        assert_eq!(
+            SecurityState::for_page(&Url::parse("about:blank").unwrap(), &report),
+            SecurityState::Unknown
+        );
+
+        for (url, state) in [
+            ("https://site.test/", SecurityState::Secure),
+            ("http://site.test/", SecurityState::Insecure),
+            ("file:///tmp/page.html", SecurityState::Insecure),
+            ("data:text/html,hi", SecurityState::Unknown),
+            ("not a url", SecurityState::Unknown),
+        ] {
+            assert_eq!(SecurityState::for_url(url), state, "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_widgets_render_in_theme_colors() {
+        let mut statusbar = StatusBar::new();
+        let plain = statusbar.render();
+        statusbar.set_colors(crate::theme::DARK_COLORS);
+        assert_eq!(
+            statusbar.render(),
+            UiElement::Themed {
+                colors: crate::theme::DARK_COLORS,
+                child: Box::new(plain),
+            }
+        );
+
+        let mut urlbar = URLBar::new();
+        urlbar.set_colors(crate::theme::LIGHT_COLORS);
+        let mut tabbar = TabBar::new();
+        tabbar.set_colors(crate::theme::LIGHT_COLORS);
+        for element in [urlbar.render(), tabbar.render()] {
+            assert!(matches!(
+                element,
+                UiElement::Themed { colors, .. } if colors == crate::theme::LIGHT_COLORS
+            ));
+        }
+    }
+
     #[test]
     fn test_statusbar_set_progress() {
         let mut statusbar = StatusBar::new();
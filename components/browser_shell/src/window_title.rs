@@ -0,0 +1,88 @@
+//! Native window title
+//!
+//! The window title follows the active tab, so the taskbar and window
+//! switcher show what the window is displaying.
+
+/// Application name shown in every window title
+pub const APP_NAME: &str = "FrankenBrowser";
+
+/// Longest page title shown before it is cut off with an ellipsis
+pub const MAX_PAGE_TITLE_CHARS: usize = 80;
+
+/// Prefix marking a window whose active tab is loading
+const LOADING_PREFIX: &str = "⟳ ";
+
+/// Format the window title for the active tab
+///
+/// Produces `"{page title} — FrankenBrowser"`, or just the application
+/// name for an untitled page. Runs of whitespace in the page title are
+/// collapsed and titles longer than [`MAX_PAGE_TITLE_CHARS`] end in `…`.
+///
+/// # Arguments
+///
+/// * `page_title` - Title of the active tab's page
+/// * `loading` - Whether the active tab is loading
+/// * `private` - Whether the window is a private window
+pub fn format_window_title(page_title: &str, loading: bool, private: bool) -> String {
+    let collapsed = page_title.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut title = String::new();
+
+    if loading {
+        title.push_str(LOADING_PREFIX);
+    }
+    if !collapsed.is_empty() {
+        if collapsed.chars().count() > MAX_PAGE_TITLE_CHARS {
+            title.extend(collapsed.chars().take(MAX_PAGE_TITLE_CHARS - 1));
+            title.push('…');
+        } else {
+            title.push_str(&collapsed);
+        }
+        title.push_str(" — ");
+    }
+    title.push_str(APP_NAME);
+    if private {
+        title.push_str(" (Private)");
+    }
+    title
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_window_title_matrix() {
+        let cases = [
+            ("Example", false, false, "Example — FrankenBrowser"),
+            ("Example", true, false, "⟳ Example — FrankenBrowser"),
+            ("Example", false, true, "Example — FrankenBrowser (Private)"),
+            (
+                "Example",
+                true,
+                true,
+                "⟳ Example — FrankenBrowser (Private)",
+            ),
+            ("", false, false, "FrankenBrowser"),
+            ("  \n ", true, false, "⟳ FrankenBrowser"),
+            (" Two\n  lines ", false, false, "Two lines — FrankenBrowser"),
+        ];
+        for (page, loading, private, expected) in cases {
+            assert_eq!(format_window_title(page, loading, private), expected);
+        }
+    }
+
+    #[test]
+    fn test_format_window_title_truncates_long_titles() {
+        let exact = "a".repeat(MAX_PAGE_TITLE_CHARS);
+        assert_eq!(
+            format_window_title(&exact, false, false),
+            format!("{} — FrankenBrowser", exact)
+        );
+
+        let long = "é".repeat(MAX_PAGE_TITLE_CHARS + 20);
+        let title = format_window_title(&long, false, true);
+        let page = title.strip_suffix(" — FrankenBrowser (Private)").unwrap();
+        assert_eq!(page.chars().count(), MAX_PAGE_TITLE_CHARS);
+        assert!(page.ends_with("é…"));
+    }
+}
@@ -0,0 +1,98 @@
+//! Page zoom
+//!
+//! Zooming in and out moves through the same fixed levels as other
+//! browsers, from [`MIN_ZOOM`] to [`MAX_ZOOM`], rather than by a constant
+//! factor. Each tab keeps its own level in [`Tab::zoom`](crate::Tab::zoom);
+//! new tabs start at the configured default, which Zoom Reset returns to.
+
+/// Smallest zoom factor
+pub const MIN_ZOOM: f64 = 0.25;
+
+/// Largest zoom factor
+pub const MAX_ZOOM: f64 = 5.0;
+
+/// Zoom factors visited by Zoom In and Zoom Out, smallest first
+pub const ZOOM_LEVELS: [f64; 17] = [
+    0.25, 0.33, 0.5, 0.67, 0.75, 0.8, 0.9, 1.0, 1.1, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0, 4.0, 5.0,
+];
+
+/// Levels closer than this are the same level, so a default of e.g.
+/// 0.6667 steps like 0.67
+const SAME_LEVEL: f64 = 0.01;
+
+/// Zoom steps and the default level for new tabs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZoomManager {
+    default_zoom: f64,
+}
+
+impl ZoomManager {
+    /// Zoom starting new tabs at `default_zoom`, clamped to the allowed
+    /// range
+    pub fn new(default_zoom: f64) -> Self {
+        let default_zoom = if default_zoom.is_nan() {
+            1.0
+        } else {
+            default_zoom.clamp(MIN_ZOOM, MAX_ZOOM)
+        };
+        Self { default_zoom }
+    }
+
+    /// Level new tabs start at and Zoom Reset returns to
+    pub fn default_zoom(&self) -> f64 {
+        self.default_zoom
+    }
+
+    /// The next level up from `level`, or [`MAX_ZOOM`] at the top
+    pub fn zoom_in(&self, level: f64) -> f64 {
+        ZOOM_LEVELS
+            .iter()
+            .copied()
+            .find(|&step| step > level + SAME_LEVEL)
+            .unwrap_or(MAX_ZOOM)
+    }
+
+    /// The next level down from `level`, or [`MIN_ZOOM`] at the bottom
+    pub fn zoom_out(&self, level: f64) -> f64 {
+        ZOOM_LEVELS
+            .iter()
+            .rev()
+            .copied()
+            .find(|&step| step < level - SAME_LEVEL)
+            .unwrap_or(MIN_ZOOM)
+    }
+}
+
+impl Default for ZoomManager {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoom_steps_through_levels_and_clamps() {
+        let zoom = ZoomManager::default();
+        assert_eq!(zoom.zoom_in(1.0), 1.1);
+        assert_eq!(zoom.zoom_out(1.0), 0.9);
+        assert_eq!(zoom.zoom_in(MAX_ZOOM), MAX_ZOOM);
+        assert_eq!(zoom.zoom_out(MIN_ZOOM), MIN_ZOOM);
+
+        // Levels between steps move to the neighbouring step
+        assert_eq!(zoom.zoom_in(1.2), 1.25);
+        assert_eq!(zoom.zoom_out(1.2), 1.1);
+        assert_eq!(zoom.zoom_in(2.0 / 3.0), 0.75);
+        assert_eq!(zoom.zoom_out(2.0 / 3.0), 0.5);
+    }
+
+    #[test]
+    fn test_default_zoom_is_clamped() {
+        assert_eq!(ZoomManager::new(1.5).default_zoom(), 1.5);
+        assert_eq!(ZoomManager::new(10.0).default_zoom(), MAX_ZOOM);
+        assert_eq!(ZoomManager::new(0.0).default_zoom(), MIN_ZOOM);
+        assert_eq!(ZoomManager::new(f64::NAN).default_zoom(), 1.0);
+    }
+}
@@ -69,6 +69,8 @@ fn run_smoke_test() {
         enable_devtools: false,
         theme: "light".to_string(),
         default_zoom: 1.0,
+        ctrl_tab_mru: false,
+        user_agent: None,
     };
     println!("  ✓ Configuration created");
     println!("    Homepage: {}", config.homepage);
@@ -0,0 +1,231 @@
+//! `frankenbrowser config` subcommands
+//!
+//! - `config check` - load and validate the config file, print diagnostics
+//!   (and warnings, such as fonts that are not installed, which do not fail
+//!   the check)
+//! - `config get <path>` - print the effective value of a field
+//! - `config set <path> <value>` - type-checked write-back to the config file
+//! - `config dump [--defaults]` - print the effective (or default) config
+//!
+//! All subcommands accept `--config <file>` to override the config location
+//! (otherwise `FRANKENBROWSER_CONFIG` or the default path is used).
+
+use config_manager::Config;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Exit code for success
+pub const EXIT_OK: i32 = 0;
+/// Exit code when the command ran but found problems (invalid config, bad path/value)
+pub const EXIT_INVALID: i32 = 1;
+/// Exit code for usage errors
+pub const EXIT_USAGE: i32 = 2;
+
+const USAGE: &str = "usage: frankenbrowser config [--config <file>] <check | get <path> | set <path> <value> | dump [--defaults]>";
+
+/// Run a `config` subcommand
+///
+/// # Arguments
+///
+/// * `args` - Arguments following `config`
+/// * `out` - Stream for regular output
+/// * `err` - Stream for diagnostics
+///
+/// # Returns
+///
+/// Returns the process exit code.
+pub fn run(args: &[String], out: &mut dyn Write, err: &mut dyn Write) -> i32 {
+    let mut config_path: Option<PathBuf> = None;
+    let mut rest: Vec<&str> = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--config" {
+            match iter.next() {
+                Some(path) => config_path = Some(PathBuf::from(path)),
+                None => return usage(err),
+            }
+        } else {
+            rest.push(arg);
+        }
+    }
+
+    let path = config_path.unwrap_or_else(Config::default_path);
+
+    let load = |err: &mut dyn Write| -> Option<Config> {
+        if !path.exists() {
+            return Some(Config::default());
+        }
+        match Config::load_from_file(&path) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                let _ = writeln!(err, "{}: {}", path.display(), e);
+                None
+            }
+        }
+    };
+
+    match rest.as_slice() {
+        ["check"] => {
+            if !path.exists() {
+                let _ = writeln!(out, "{}: not found, using defaults", path.display());
+                return EXIT_OK;
+            }
+            let diagnostics = Config::check_file(&path);
+            for diagnostic in &diagnostics {
+                let _ = writeln!(err, "{}: {}", path.display(), diagnostic);
+            }
+            if let Ok(config) = Config::load_from_file(&path) {
+                for warning in config.font_warnings() {
+                    let _ = writeln!(err, "{}: warning: {}", path.display(), warning);
+                }
+            }
+            if diagnostics.is_empty() {
+                let _ = writeln!(out, "{}: OK", path.display());
+                EXIT_OK
+            } else {
+                EXIT_INVALID
+            }
+        }
+        ["get", key] => {
+            let Some(config) = load(err) else {
+                return EXIT_INVALID;
+            };
+            match config.get_path(key) {
+                Ok(value) => {
+                    let _ = writeln!(out, "{}", value);
+                    EXIT_OK
+                }
+                Err(e) => {
+                    let _ = writeln!(err, "{}", e);
+                    EXIT_INVALID
+                }
+            }
+        }
+        ["set", key, value] => match Config::set_in_file(&path, key, value) {
+            Ok(()) => EXIT_OK,
+            Err(e) => {
+                let _ = writeln!(err, "{}", e);
+                EXIT_INVALID
+            }
+        },
+        ["dump", "--defaults"] => {
+            let _ = write!(out, "{}", Config::default_toml());
+            EXIT_OK
+        }
+        ["dump"] => {
+            let Some(config) = load(err) else {
+                return EXIT_INVALID;
+            };
+            match toml::to_string_pretty(&config) {
+                Ok(text) => {
+                    let _ = write!(out, "{}", text);
+                    EXIT_OK
+                }
+                Err(e) => {
+                    let _ = writeln!(err, "Failed to serialize config: {}", e);
+                    EXIT_INVALID
+                }
+            }
+        }
+        _ => usage(err),
+    }
+}
+
+fn usage(err: &mut dyn Write) -> i32 {
+    let _ = writeln!(err, "{}", USAGE);
+    EXIT_USAGE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_with(path: &std::path::Path, args: &[&str]) -> (i32, String, String) {
+        let mut full = vec!["--config".to_string(), path.display().to_string()];
+        full.extend(args.iter().map(|a| a.to_string()));
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let code = run(&full, &mut out, &mut err);
+        (
+            code,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_check_valid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(&path, Config::default_toml()).unwrap();
+
+        let (code, out, _) = run_with(&path, &["check"]);
+        assert_eq!(code, EXIT_OK);
+        assert!(out.contains("OK"));
+    }
+
+    #[test]
+    fn test_check_invalid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            Config::default_toml().replace("theme = \"auto\"", "theme = \"neon\""),
+        )
+        .unwrap();
+
+        let (code, _, err) = run_with(&path, &["check"]);
+        assert_eq!(code, EXIT_INVALID);
+        assert!(err.contains("appearance.theme"));
+    }
+
+    #[test]
+    fn test_get_and_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        let (code, out, _) = run_with(&path, &["get", "network.timeout_seconds"]);
+        assert_eq!((code, out.trim()), (EXIT_OK, "30"));
+
+        let (code, _, _) = run_with(&path, &["set", "network.timeout_seconds", "45"]);
+        assert_eq!(code, EXIT_OK);
+
+        let (code, out, _) = run_with(&path, &["get", "network.timeout_seconds"]);
+        assert_eq!((code, out.trim()), (EXIT_OK, "45"));
+    }
+
+    #[test]
+    fn test_set_type_mismatch_and_unknown_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        let (code, _, err) = run_with(&path, &["set", "network.timeout_seconds", "abc"]);
+        assert_eq!(code, EXIT_INVALID);
+        assert!(err.contains("Type mismatch"));
+
+        let (code, _, err) = run_with(&path, &["get", "network.bogus"]);
+        assert_eq!(code, EXIT_INVALID);
+        assert!(err.contains("Unknown config path"));
+    }
+
+    #[test]
+    fn test_dump_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        let (code, out, _) = run_with(&path, &["dump", "--defaults"]);
+        assert_eq!(code, EXIT_OK);
+        assert_eq!(out, Config::default_toml());
+    }
+
+    #[test]
+    fn test_usage_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+
+        let (code, _, err) = run_with(&path, &["frobnicate"]);
+        assert_eq!(code, EXIT_USAGE);
+        assert!(err.contains("usage"));
+    }
+}
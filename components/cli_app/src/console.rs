@@ -0,0 +1,966 @@
+//! `frankenbrowser console` subcommand
+//!
+//! `console [--config <file>] [--json]` starts the browser without running
+//! its event loop and reads commands from the terminal, one per line:
+//! `open`, `tabs`, `switch`, `back`, `forward`, `reload`, `history search`,
+//! `bookmark add`, `adblock stats`, `cache stats`, `js`, `help` and `quit`
+//! (see `help` for their arguments).
+//!
+//! Arguments are split on whitespace; single or double quotes keep spaces
+//! together and a backslash escapes the next character. Each command prints
+//! a human-readable answer, or with `--json` exactly one JSON object per
+//! line (see [`Console::render`]) for scripts. Line editing and history come
+//! from rustyline; the history is kept next to the config file. Changes
+//! to the config file are applied before the next command.
+//!
+//! [`Console`] itself does no terminal I/O, so tests feed it command strings
+//! directly.
+
+use crate::config_cmd::{EXIT_INVALID, EXIT_OK, EXIT_USAGE};
+use crate::types::BrowserApp;
+use browser_core::NavigationInitiator;
+use config_manager::{Config, ConfigEvent};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::PathBuf;
+use thiserror::Error;
+use url::Url;
+
+const USAGE: &str = "usage: frankenbrowser console [--config <file>] [--json]";
+
+/// Most entries listed by `history search`
+const HISTORY_SEARCH_LIMIT: usize = 20;
+
+/// Prompt shown before each command
+const PROMPT: &str = "franken> ";
+
+const HELP: &str = "\
+open <url or search>        load a page in the active tab (opens one if needed)
+tabs                        list open tabs
+switch <id>                 activate a tab
+back | forward | reload     move the active tab through its history
+history search <query>      search visited pages
+bookmark add <url> <title>  bookmark a page
+adblock stats               filter lists and rules in use
+cache stats                 HTTP cache and traffic totals
+js <tab> <expr>             run a script in a tab's page
+help                        show this list
+quit                        leave the console";
+
+/// A console command, parsed from one line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsoleCommand {
+    /// Load a URL, or resolve keyword and search input, in the active tab
+    Open(String),
+    /// List open tabs
+    Tabs,
+    /// Activate a tab
+    Switch(u32),
+    /// Go back in the active tab
+    Back,
+    /// Go forward in the active tab
+    Forward,
+    /// Reload the active tab
+    Reload,
+    /// Search visited pages
+    HistorySearch(String),
+    /// Bookmark a page
+    BookmarkAdd {
+        /// Page to bookmark
+        url: String,
+        /// Bookmark title
+        title: String,
+    },
+    /// Filter list and rule counts
+    AdblockStats,
+    /// Cache size and traffic totals
+    CacheStats,
+    /// Run a script in a tab's page
+    Js {
+        /// Tab whose page runs the script
+        tab_id: u32,
+        /// JavaScript source
+        source: String,
+    },
+    /// List the commands
+    Help,
+    /// Leave the console
+    Quit,
+}
+
+/// Why a console command failed
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ConsoleError {
+    /// The line is not a valid command
+    #[error("{0}")]
+    Parse(String),
+
+    /// The command names a tab that is not open
+    #[error("No tab with id {0}; `tabs` lists the open tabs")]
+    UnknownTab(u32),
+
+    /// The command needs an active tab and none is open
+    #[error("No tab is open; use `open <url>` first")]
+    NoActiveTab,
+
+    /// The browser refused or failed the command
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl ConsoleCommand {
+    /// Parse one line of input
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(None)` for a blank line.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConsoleError::Parse` for unknown commands, missing or extra
+    /// arguments and unbalanced quotes.
+    pub fn parse(line: &str) -> Result<Option<Self>, ConsoleError> {
+        let words = split_words(line)?;
+        let Some((name, args)) = words.split_first() else {
+            return Ok(None);
+        };
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let usage = |form: &str| ConsoleError::Parse(format!("usage: {}", form));
+
+        let command = match (name.as_str(), args.as_slice()) {
+            ("open", []) => return Err(usage("open <url or search>")),
+            ("open", words) => ConsoleCommand::Open(words.join(" ")),
+            ("tabs", []) => ConsoleCommand::Tabs,
+            ("switch", [id]) => ConsoleCommand::Switch(parse_tab_id(id)?),
+            ("switch", _) => return Err(usage("switch <id>")),
+            ("back", []) => ConsoleCommand::Back,
+            ("forward", []) => ConsoleCommand::Forward,
+            ("reload", []) => ConsoleCommand::Reload,
+            ("history", ["search", query @ ..]) if !query.is_empty() => {
+                ConsoleCommand::HistorySearch(query.join(" "))
+            }
+            ("history", _) => return Err(usage("history search <query>")),
+            ("bookmark", ["add", url, title @ ..]) if !title.is_empty() => {
+                ConsoleCommand::BookmarkAdd {
+                    url: url.to_string(),
+                    title: title.join(" "),
+                }
+            }
+            ("bookmark", _) => return Err(usage("bookmark add <url> <title>")),
+            ("adblock", ["stats"]) => ConsoleCommand::AdblockStats,
+            ("adblock", _) => return Err(usage("adblock stats")),
+            ("cache", ["stats"]) => ConsoleCommand::CacheStats,
+            ("cache", _) => return Err(usage("cache stats")),
+            ("js", [tab_id, source @ ..]) if !source.is_empty() => ConsoleCommand::Js {
+                tab_id: parse_tab_id(tab_id)?,
+                source: source.join(" "),
+            },
+            ("js", _) => return Err(usage("js <tab> <expr>")),
+            ("help", []) => ConsoleCommand::Help,
+            ("quit" | "exit", []) => ConsoleCommand::Quit,
+            ("tabs" | "back" | "forward" | "reload" | "help" | "quit" | "exit", _) => {
+                return Err(ConsoleError::Parse(format!(
+                    "`{}` takes no arguments",
+                    name
+                )));
+            }
+            (other, _) => {
+                return Err(ConsoleError::Parse(format!(
+                    "Unknown command `{}`; `help` lists the commands",
+                    other
+                )));
+            }
+        };
+        Ok(Some(command))
+    }
+
+    /// Command name, as used in JSON output
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConsoleCommand::Open(_) => "open",
+            ConsoleCommand::Tabs => "tabs",
+            ConsoleCommand::Switch(_) => "switch",
+            ConsoleCommand::Back => "back",
+            ConsoleCommand::Forward => "forward",
+            ConsoleCommand::Reload => "reload",
+            ConsoleCommand::HistorySearch(_) => "history search",
+            ConsoleCommand::BookmarkAdd { .. } => "bookmark add",
+            ConsoleCommand::AdblockStats => "adblock stats",
+            ConsoleCommand::CacheStats => "cache stats",
+            ConsoleCommand::Js { .. } => "js",
+            ConsoleCommand::Help => "help",
+            ConsoleCommand::Quit => "quit",
+        }
+    }
+}
+
+fn parse_tab_id(word: &str) -> Result<u32, ConsoleError> {
+    word.parse()
+        .map_err(|_| ConsoleError::Parse(format!("`{}` is not a tab id", word)))
+}
+
+/// Split a line into words
+///
+/// Whitespace separates words. Inside single quotes everything is literal;
+/// inside double quotes, and outside quotes, a backslash escapes the next
+/// character.
+///
+/// # Errors
+///
+/// Returns `ConsoleError::Parse` for an unterminated quote or a trailing
+/// backslash.
+pub fn split_words(line: &str) -> Result<Vec<String>, ConsoleError> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                words.extend(word.take());
+            }
+            '\'' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err(ConsoleError::Parse("Unterminated ' quote".into())),
+                    }
+                }
+            }
+            '"' => {
+                let current = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => current.push(escaped(chars.next())?),
+                        Some(c) => current.push(c),
+                        None => return Err(ConsoleError::Parse("Unterminated \" quote".into())),
+                    }
+                }
+            }
+            '\\' => word
+                .get_or_insert_with(String::new)
+                .push(escaped(chars.next())?),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+fn escaped(next: Option<char>) -> Result<char, ConsoleError> {
+    next.ok_or_else(|| ConsoleError::Parse("Nothing to escape after \\".into()))
+}
+
+/// What a successful command produced
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reply {
+    /// Human-readable answer, possibly several lines
+    pub text: String,
+    /// The same answer for `--json` output
+    pub data: Value,
+}
+
+impl Reply {
+    fn new(text: impl Into<String>, data: Value) -> Self {
+        Self {
+            text: text.into(),
+            data,
+        }
+    }
+}
+
+/// Runs console commands against a browser
+pub struct Console {
+    /// The browser being driven
+    app: BrowserApp,
+    /// Render replies as JSON objects
+    json: bool,
+    /// Whether `quit` was given
+    finished: bool,
+}
+
+impl Console {
+    /// Drive `app`, which should already be started
+    pub fn new(app: BrowserApp) -> Self {
+        Self {
+            app,
+            json: false,
+            finished: false,
+        }
+    }
+
+    /// Render replies as one JSON object per command
+    pub fn with_json(mut self, json: bool) -> Self {
+        self.json = json;
+        self
+    }
+
+    /// The browser being driven
+    pub fn app(&self) -> &BrowserApp {
+        &self.app
+    }
+
+    /// The browser being driven, e.g. to attach a webview backend
+    pub fn app_mut(&mut self) -> &mut BrowserApp {
+        &mut self.app
+    }
+
+    /// Whether `quit` was given
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Parse, run and render one line
+    ///
+    /// # Returns
+    ///
+    /// Returns the text to print, or `None` for a blank line.
+    pub fn run_line(&mut self, line: &str) -> Option<String> {
+        let (name, result) = match ConsoleCommand::parse(line) {
+            Ok(None) => return None,
+            Ok(Some(command)) => (Some(command.name()), self.execute(&command)),
+            Err(e) => (None, Err(e)),
+        };
+        Some(self.render(name, &result))
+    }
+
+    /// Format a command's outcome
+    ///
+    /// In JSON mode every outcome is one line holding an object with
+    /// `command` (the command name, `null` if the line did not parse) and
+    /// `ok`, plus `result` on success or `error` on failure.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - Name of the command that ran
+    /// * `result` - What it produced
+    pub fn render(&self, command: Option<&str>, result: &Result<Reply, ConsoleError>) -> String {
+        match (self.json, result) {
+            (false, Ok(reply)) => reply.text.clone(),
+            (false, Err(e)) => format!("error: {}", e),
+            (true, Ok(reply)) => {
+                json!({ "command": command, "ok": true, "result": reply.data }).to_string()
+            }
+            (true, Err(e)) => {
+                json!({ "command": command, "ok": false, "error": e.to_string() }).to_string()
+            }
+        }
+    }
+
+    /// Run a command
+    ///
+    /// # Errors
+    ///
+    /// Returns `ConsoleError::UnknownTab` for a tab that is not open,
+    /// `ConsoleError::NoActiveTab` for tab commands while no tab is open, and
+    /// `ConsoleError::Failed` if the browser refuses the command.
+    pub fn execute(&mut self, command: &ConsoleCommand) -> Result<Reply, ConsoleError> {
+        match command {
+            ConsoleCommand::Open(input) => self.open(input),
+            ConsoleCommand::Tabs => Ok(self.tabs()),
+            ConsoleCommand::Switch(tab_id) => {
+                self.app
+                    .shell_mut()
+                    .switch_to_tab(*tab_id)
+                    .map_err(|e| shell_error(e, *tab_id))?;
+                Ok(Reply::new(
+                    format!("Switched to tab {}", tab_id),
+                    json!({ "tab": tab_id }),
+                ))
+            }
+            ConsoleCommand::Back => self.traverse(|engine, tab_id| engine.go_back(tab_id)),
+            ConsoleCommand::Forward => self.traverse(|engine, tab_id| engine.go_forward(tab_id)),
+            ConsoleCommand::Reload => {
+                let tab_id = self.active_tab()?;
+                self.app.browser_core_mut().reload(tab_id).map_err(failed)?;
+                Ok(Reply::new(
+                    format!("Reloaded tab {}", tab_id),
+                    json!({ "tab": tab_id }),
+                ))
+            }
+            ConsoleCommand::HistorySearch(query) => Ok(self.history_search(query)),
+            ConsoleCommand::BookmarkAdd { url, title } => {
+                let parsed = Url::parse(url)
+                    .map_err(|e| ConsoleError::Failed(format!("Invalid URL {}: {}", url, e)))?;
+                self.app
+                    .browser_core_mut()
+                    .add_bookmark(parsed.clone(), title.clone())
+                    .map_err(failed)?;
+                Ok(Reply::new(
+                    format!("Bookmarked {} as \"{}\"", parsed, title),
+                    json!({ "url": parsed.as_str(), "title": title }),
+                ))
+            }
+            ConsoleCommand::AdblockStats => Ok(self.adblock_stats()),
+            ConsoleCommand::CacheStats => Ok(self.cache_stats()),
+            ConsoleCommand::Js { tab_id, source } => {
+                self.app
+                    .shell()
+                    .run_script(*tab_id, source)
+                    .map_err(|e| shell_error(e, *tab_id))?;
+                Ok(Reply::new(
+                    format!("Script sent to tab {}", tab_id),
+                    json!({ "tab": tab_id }),
+                ))
+            }
+            ConsoleCommand::Help => Ok(Reply::new(HELP, json!({ "help": HELP }))),
+            ConsoleCommand::Quit => {
+                self.finished = true;
+                Ok(Reply::new("Bye", json!({})))
+            }
+        }
+    }
+
+    /// The active tab
+    fn active_tab(&self) -> Result<u32, ConsoleError> {
+        self.app
+            .shell()
+            .get_active_tab()
+            .ok_or(ConsoleError::NoActiveTab)
+    }
+
+    fn open(&mut self, input: &str) -> Result<Reply, ConsoleError> {
+        let url = self
+            .app
+            .browser_core()
+            .resolve_input(input)
+            .map_err(failed)?
+            .url()
+            .clone();
+        let tab_id = match self.app.shell().get_active_tab() {
+            Some(tab_id) => tab_id,
+            None => self.app.shell_mut().create_tab().map_err(failed)?,
+        };
+        self.app
+            .browser_core_mut()
+            .navigate(tab_id, url.clone(), NavigationInitiator::UserTyped)
+            .map_err(failed)?;
+        self.app
+            .shell_mut()
+            .set_tab_url(tab_id, url.to_string())
+            .map_err(failed)?;
+        Ok(Reply::new(
+            format!("Tab {}: {}", tab_id, url),
+            json!({ "tab": tab_id, "url": url.as_str() }),
+        ))
+    }
+
+    /// Move the active tab through its history and report where it is
+    fn traverse(
+        &mut self,
+        step: impl FnOnce(&mut browser_core::BrowserEngine, u32) -> browser_core::Result<()>,
+    ) -> Result<Reply, ConsoleError> {
+        let tab_id = self.active_tab()?;
+        step(self.app.browser_core_mut(), tab_id).map_err(failed)?;
+        let url = self
+            .app
+            .browser_core()
+            .tab_histories()
+            .get(tab_id)
+            .and_then(|history| history.current_url().map(str::to_string))
+            .unwrap_or_default();
+        self.app
+            .shell_mut()
+            .set_tab_url(tab_id, url.clone())
+            .map_err(failed)?;
+        Ok(Reply::new(
+            format!("Tab {}: {}", tab_id, url),
+            json!({ "tab": tab_id, "url": url }),
+        ))
+    }
+
+    fn tabs(&self) -> Reply {
+        let shell = self.app.shell();
+        let active = shell.get_active_tab();
+        let mut ids = shell.mru_order().to_vec();
+        ids.sort_unstable();
+
+        let tabs: Vec<_> = ids.iter().filter_map(|id| shell.get_tab(*id)).collect();
+        if tabs.is_empty() {
+            return Reply::new("No tabs open", json!({ "active": null, "tabs": [] }));
+        }
+        let text = tabs
+            .iter()
+            .map(|tab| {
+                let marker = if Some(tab.id) == active { '*' } else { ' ' };
+                let url = tab.url.as_deref().unwrap_or("");
+                format!("{} {:>3}  {}  {}", marker, tab.id, tab.title, url)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let data: Vec<Value> = tabs
+            .iter()
+            .map(|tab| {
+                json!({
+                    "id": tab.id,
+                    "title": tab.title,
+                    "url": tab.url,
+                    "active": Some(tab.id) == active,
+                })
+            })
+            .collect();
+        Reply::new(text, json!({ "active": active, "tabs": data }))
+    }
+
+    fn history_search(&self, query: &str) -> Reply {
+        let entries = self
+            .app
+            .browser_core()
+            .history_suggestions(query, HISTORY_SEARCH_LIMIT);
+        let text = if entries.is_empty() {
+            format!("No history matches \"{}\"", query)
+        } else {
+            entries
+                .iter()
+                .map(|entry| match entry.title.as_str() {
+                    "" => entry.url.clone(),
+                    title => format!("{} — {}", title, entry.url),
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let data: Vec<Value> = entries
+            .iter()
+            .map(|entry| {
+                json!({
+                    "url": entry.url,
+                    "title": entry.title,
+                    "visit_count": entry.visit_count,
+                    "last_visit": entry.last_visit,
+                })
+            })
+            .collect();
+        Reply::new(text, json!({ "query": query, "entries": data }))
+    }
+
+    fn adblock_stats(&self) -> Reply {
+        let adblock = self.app.adblock();
+        let lists = adblock.filter_lists();
+        let subscriptions = adblock.list_subscriptions();
+        let rules: usize = lists.iter().map(|list| list.rules).sum();
+        let enabled = subscriptions.iter().filter(|s| s.enabled).count();
+        Reply::new(
+            format!(
+                "{} filter lists, {} rules; {} of {} subscriptions enabled",
+                lists.len(),
+                rules,
+                enabled,
+                subscriptions.len()
+            ),
+            json!({
+                "lists": lists.len(),
+                "rules": rules,
+                "subscriptions": subscriptions.len(),
+                "enabled_subscriptions": enabled,
+            }),
+        )
+    }
+
+    fn cache_stats(&self) -> Reply {
+        let network = self.app.browser_core().network();
+        let traffic = network.session_bandwidth();
+        let traffic_text = format!(
+            "{} requests, {} bytes sent, {} bytes received",
+            traffic.requests, traffic.sent, traffic.received_encoded
+        );
+        let traffic_data = json!({
+            "requests": traffic.requests,
+            "sent": traffic.sent,
+            "received": traffic.received_encoded,
+        });
+        match network.cache() {
+            Some(cache) => {
+                let entries = cache.cache_entries(None, usize::MAX).len();
+                let bytes = cache.size();
+                Reply::new(
+                    format!(
+                        "Cache: {} entries, {} bytes\n{}",
+                        entries, bytes, traffic_text
+                    ),
+                    json!({
+                        "enabled": true,
+                        "entries": entries,
+                        "bytes": bytes,
+                        "traffic": traffic_data,
+                    }),
+                )
+            }
+            None => Reply::new(
+                format!("Cache disabled\n{}", traffic_text),
+                json!({ "enabled": false, "traffic": traffic_data }),
+            ),
+        }
+    }
+}
+
+/// Report a shell error, naming unknown tabs as such
+fn shell_error(error: browser_shell::Error, tab_id: u32) -> ConsoleError {
+    match error {
+        browser_shell::Error::TabNotFound(_) => ConsoleError::UnknownTab(tab_id),
+        other => ConsoleError::Failed(other.to_string()),
+    }
+}
+
+fn failed(error: impl std::fmt::Display) -> ConsoleError {
+    ConsoleError::Failed(error.to_string())
+}
+
+/// Run the `console` subcommand
+///
+/// # Arguments
+///
+/// * `args` - Arguments following `console`
+/// * `out` - Stream replies are written to
+/// * `err` - Stream for diagnostics
+///
+/// # Returns
+///
+/// Returns the process exit code.
+pub fn run(args: &[String], out: &mut dyn Write, err: &mut dyn Write) -> i32 {
+    let mut config_path: Option<PathBuf> = None;
+    let mut json = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--config" => match iter.next() {
+                Some(path) => config_path = Some(PathBuf::from(path)),
+                None => return usage(err),
+            },
+            "--json" => json = true,
+            _ => return usage(err),
+        }
+    }
+
+    let path = config_path.unwrap_or_else(Config::default_path);
+    let config = if path.exists() {
+        match Config::load_from_file(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                let _ = writeln!(err, "{}: {}", path.display(), e);
+                return EXIT_INVALID;
+            }
+        }
+    } else {
+        Config::default()
+    };
+    let app = BrowserApp::new(config).and_then(|mut app| {
+        app.start()?;
+        app.set_config_file(&path);
+        if path.exists() {
+            app.watch_config(&path)?;
+        }
+        Ok(app)
+    });
+    let mut console = match app {
+        Ok(app) => Console::new(app).with_json(json),
+        Err(e) => {
+            let _ = writeln!(err, "Failed to start the browser: {}", e);
+            return EXIT_INVALID;
+        }
+    };
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            let _ = writeln!(err, "Failed to open the terminal: {}", e);
+            return EXIT_INVALID;
+        }
+    };
+    let history = Config::default_console_history_path();
+    // No history yet on first use
+    let _ = editor.load_history(&history);
+
+    while !console.is_finished() {
+        let line = match editor.readline(PROMPT) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(e) => {
+                let _ = writeln!(err, "{}", e);
+                return EXIT_INVALID;
+            }
+        };
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+        }
+        for event in console.app_mut().apply_config_changes() {
+            match event {
+                ConfigEvent::Reloaded { delta, .. } if delta.requires_restart() => {
+                    let restart = delta.restart_required().join(", ");
+                    let _ = writeln!(err, "Restart to apply {}", restart);
+                }
+                ConfigEvent::Reloaded { .. } => {}
+                ConfigEvent::Invalid { error } => {
+                    let _ = writeln!(
+                        err,
+                        "{}: {}; keeping the current settings",
+                        path.display(),
+                        error
+                    );
+                }
+            }
+        }
+        if let Some(reply) = console.run_line(&line) {
+            let _ = writeln!(out, "{}", reply);
+        }
+        console.app_mut().tick_session_snapshot();
+    }
+
+    if let Err(e) = editor.save_history(&history) {
+        let _ = writeln!(err, "Failed to save {}: {}", history.display(), e);
+    }
+    EXIT_OK
+}
+
+fn usage(err: &mut dyn Write) -> i32 {
+    let _ = writeln!(err, "{}", USAGE);
+    EXIT_USAGE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn console() -> Console {
+        let mut config = Config::default();
+        config.browser.homepage = "https://home.test/".to_string();
+        let mut app = BrowserApp::new(config).unwrap();
+        app.start().unwrap();
+        Console::new(app)
+    }
+
+    fn parse(line: &str) -> Result<Option<ConsoleCommand>, ConsoleError> {
+        ConsoleCommand::parse(line)
+    }
+
+    #[test]
+    fn test_split_words_quoting() {
+        assert_eq!(
+            split_words(r#"  bookmark add https://a.test/ "Rust \"book\"" 'it''s' a\ b "#).unwrap(),
+            [
+                "bookmark",
+                "add",
+                "https://a.test/",
+                "Rust \"book\"",
+                "its",
+                "a b"
+            ]
+        );
+        assert_eq!(split_words("say ''").unwrap(), ["say", ""]);
+        assert!(split_words("   ").unwrap().is_empty());
+        assert!(matches!(
+            split_words("open 'x"),
+            Err(ConsoleError::Parse(_))
+        ));
+        assert!(matches!(
+            split_words("open \"x"),
+            Err(ConsoleError::Parse(_))
+        ));
+        assert!(matches!(
+            split_words("open x\\"),
+            Err(ConsoleError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(parse("").unwrap(), None);
+        assert_eq!(
+            parse("open rust lang").unwrap(),
+            Some(ConsoleCommand::Open("rust lang".into()))
+        );
+        assert_eq!(parse("tabs").unwrap(), Some(ConsoleCommand::Tabs));
+        assert_eq!(parse("switch 3").unwrap(), Some(ConsoleCommand::Switch(3)));
+        assert_eq!(parse("back").unwrap(), Some(ConsoleCommand::Back));
+        assert_eq!(parse("forward").unwrap(), Some(ConsoleCommand::Forward));
+        assert_eq!(parse("reload").unwrap(), Some(ConsoleCommand::Reload));
+        assert_eq!(
+            parse("history search 'rust docs'").unwrap(),
+            Some(ConsoleCommand::HistorySearch("rust docs".into()))
+        );
+        assert_eq!(
+            parse("bookmark add https://a.test/ The A site").unwrap(),
+            Some(ConsoleCommand::BookmarkAdd {
+                url: "https://a.test/".into(),
+                title: "The A site".into(),
+            })
+        );
+        assert_eq!(
+            parse("adblock stats").unwrap(),
+            Some(ConsoleCommand::AdblockStats)
+        );
+        assert_eq!(
+            parse("cache stats").unwrap(),
+            Some(ConsoleCommand::CacheStats)
+        );
+        assert_eq!(
+            parse("js 2 \"document.title = 'x'\"").unwrap(),
+            Some(ConsoleCommand::Js {
+                tab_id: 2,
+                source: "document.title = 'x'".into(),
+            })
+        );
+        assert_eq!(parse("help").unwrap(), Some(ConsoleCommand::Help));
+        assert_eq!(parse("exit").unwrap(), Some(ConsoleCommand::Quit));
+    }
+
+    #[test]
+    fn test_parse_bad_input() {
+        for line in [
+            "open",
+            "switch",
+            "switch one",
+            "switch 1 2",
+            "tabs now",
+            "history",
+            "history find x",
+            "bookmark add https://a.test/",
+            "adblock",
+            "cache clear",
+            "js x alert(1)",
+            "js 1",
+            "frobnicate",
+            "open 'unterminated",
+        ] {
+            assert!(
+                matches!(parse(line), Err(ConsoleError::Parse(_))),
+                "{} parsed",
+                line
+            );
+        }
+        assert_eq!(
+            parse("switch one").unwrap_err().to_string(),
+            "`one` is not a tab id"
+        );
+        assert_eq!(
+            parse("frobnicate").unwrap_err().to_string(),
+            "Unknown command `frobnicate`; `help` lists the commands"
+        );
+    }
+
+    #[test]
+    fn test_navigation_commands() {
+        let mut console = console();
+        assert_eq!(
+            console.run_line("open https://a.test/").unwrap(),
+            "Tab 1: https://a.test/"
+        );
+        console.run_line("open https://b.test/");
+        assert_eq!(console.run_line("back").unwrap(), "Tab 1: https://a.test/");
+        assert_eq!(
+            console.run_line("forward").unwrap(),
+            "Tab 1: https://b.test/"
+        );
+        assert_eq!(console.run_line("reload").unwrap(), "Reloaded tab 1");
+        assert!(console.run_line("forward").unwrap().starts_with("error: "));
+
+        let tabs = console.run_line("tabs").unwrap();
+        assert!(tabs.starts_with("*   1  "), "{}", tabs);
+        assert!(tabs.ends_with("https://b.test/"), "{}", tabs);
+
+        assert_eq!(
+            console.run_line("history search b.test").unwrap(),
+            "https://b.test/"
+        );
+        assert!(console
+            .run_line("bookmark add https://b.test/ B site")
+            .is_some());
+        assert_eq!(
+            console.app().browser_core().get_bookmarks()[0].title,
+            "B site"
+        );
+        assert!(!console.is_finished());
+        assert_eq!(console.run_line("quit").unwrap(), "Bye");
+        assert!(console.is_finished());
+    }
+
+    #[test]
+    fn test_unknown_tab_errors() {
+        let mut console = console();
+        assert_eq!(
+            console.run_line("switch 42").unwrap(),
+            "error: No tab with id 42; `tabs` lists the open tabs"
+        );
+        assert_eq!(
+            console.run_line("js 42 1 + 1").unwrap(),
+            "error: No tab with id 42; `tabs` lists the open tabs"
+        );
+
+        let backend = Arc::new(webview_integration::MockBackend::new());
+        console
+            .app_mut()
+            .shell_mut()
+            .set_window_backend(backend.clone());
+        assert_eq!(
+            console.run_line("js 1 document.title").unwrap(),
+            "Script sent to tab 1"
+        );
+        assert_eq!(
+            backend.user_scripts_run(),
+            vec![(1, "document.title".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_json_output_schema() {
+        let mut console = console().with_json(true);
+
+        let ok: Value = serde_json::from_str(&console.run_line("switch 1").unwrap()).unwrap();
+        assert_eq!(
+            ok,
+            json!({ "command": "switch", "ok": true, "result": { "tab": 1 } })
+        );
+
+        let failed: Value = serde_json::from_str(&console.run_line("switch 9").unwrap()).unwrap();
+        assert_eq!(
+            failed,
+            json!({
+                "command": "switch",
+                "ok": false,
+                "error": "No tab with id 9; `tabs` lists the open tabs",
+            })
+        );
+
+        let unparsed: Value = serde_json::from_str(&console.run_line("nope").unwrap()).unwrap();
+        assert_eq!(unparsed["command"], Value::Null);
+        assert_eq!(unparsed["ok"], false);
+
+        let tabs: Value = serde_json::from_str(&console.run_line("tabs").unwrap()).unwrap();
+        assert_eq!(tabs["result"]["active"], 1);
+        assert_eq!(
+            tabs["result"]["tabs"][0]
+                .as_object()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            ["active", "id", "title", "url"]
+        );
+
+        for line in ["adblock stats", "cache stats"] {
+            let reply: Value = serde_json::from_str(&console.run_line(line).unwrap()).unwrap();
+            assert_eq!(reply["command"], line);
+            assert_eq!(reply["ok"], true);
+        }
+        let cache: Value = serde_json::from_str(&console.run_line("cache stats").unwrap()).unwrap();
+        assert_eq!(cache["result"]["enabled"], true);
+        assert_eq!(cache["result"]["entries"], 0);
+    }
+
+    #[test]
+    fn test_run_usage_errors() {
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        assert_eq!(
+            run(&["--verbose".to_string()], &mut out, &mut err),
+            EXIT_USAGE
+        );
+        assert_eq!(
+            run(&["--config".to_string()], &mut out, &mut err),
+            EXIT_USAGE
+        );
+        assert!(String::from_utf8(err).unwrap().contains("usage:"));
+    }
+}
@@ -0,0 +1,101 @@
+//! Example extension: rejects cookie consent banners
+
+use extension_api::{BrowserExtension, ExtensionMenuItem, UserScript};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Script that clicks the "reject" button of common consent banners
+///
+/// Tries the well-known consent-manager selectors first, then falls back
+/// to any button labelled "Reject all" or "Decline". Retries for a few
+/// seconds because banners are often inserted after load.
+pub const REJECT_SCRIPT: &str = r#"(function () {
+  var selectors = [
+    '#onetrust-reject-all-handler',
+    '.cc-deny',
+    '#CybotCookiebotDialogBodyButtonDecline',
+    'button[data-testid="reject-all"]'
+  ];
+  var labels = ['reject all', 'decline', 'reject'];
+  function tryReject() {
+    for (var i = 0; i < selectors.length; i++) {
+      var el = document.querySelector(selectors[i]);
+      if (el) { el.click(); return true; }
+    }
+    var buttons = document.querySelectorAll('button');
+    for (var j = 0; j < buttons.length; j++) {
+      var text = (buttons[j].textContent || '').trim().toLowerCase();
+      if (labels.indexOf(text) !== -1) { buttons[j].click(); return true; }
+    }
+    return false;
+  }
+  var attempts = 0;
+  (function poll() {
+    if (tryReject() || ++attempts >= 10) { return; }
+    setTimeout(poll, 500);
+  })();
+})();"#;
+
+/// Auto-rejects cookie consent banners on every page
+///
+/// The "Pause" menu entry stops injection until chosen again.
+pub struct CookieBannerRejecter {
+    /// Whether injection is paused from the menu
+    paused: AtomicBool,
+}
+
+impl CookieBannerRejecter {
+    /// Extension ID, used in `extensions.disabled`
+    pub const ID: &'static str = "cookie-banner-rejecter";
+
+    /// Create the extension
+    pub fn new() -> Self {
+        Self {
+            paused: AtomicBool::new(false),
+        }
+    }
+
+    /// Registry constructor
+    pub fn boxed() -> Box<dyn BrowserExtension> {
+        Box::new(Self::new())
+    }
+}
+
+impl Default for CookieBannerRejecter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BrowserExtension for CookieBannerRejecter {
+    fn id(&self) -> &str {
+        Self::ID
+    }
+
+    fn name(&self) -> &str {
+        "Cookie banner rejecter"
+    }
+
+    fn user_scripts(&self) -> Vec<UserScript> {
+        if self.paused.load(Ordering::Relaxed) {
+            return Vec::new();
+        }
+        vec![UserScript {
+            name: "reject-cookie-banners".to_string(),
+            matches: Vec::new(),
+            source: REJECT_SCRIPT.to_string(),
+        }]
+    }
+
+    fn menu_items(&self) -> Vec<ExtensionMenuItem> {
+        vec![ExtensionMenuItem {
+            id: "toggle-pause".to_string(),
+            label: "Pause Cookie Banner Rejecter".to_string(),
+        }]
+    }
+
+    fn on_menu_action(&self, item_id: &str) {
+        if item_id == "toggle-pause" {
+            self.paused.fetch_xor(true, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,579 @@
+//! Built-in extension loading and hook dispatch
+//!
+//! Extensions are compiled in and listed in [`BUILTIN_EXTENSIONS`]; loading
+//! them from dynamic libraries is not supported. The [`ExtensionManager`]
+//! runs every enabled extension's hooks in registration order, times each
+//! call and contains panics so one misbehaving extension cannot take down
+//! the pipeline that called it.
+
+pub mod cookie_banner;
+
+use browser_shell::{MenuAction, MenuItem};
+use config_manager::ExtensionSettings;
+use extension_api::{
+    BrowserExtension, ExtensionDiagnostics, ExtensionFactory, Hook, HookDispatcher, HookStats,
+    NavigationContext, NavigationVerdict, RequestInfo, RequestVerdict,
+};
+use network_stack::request_handler::{Request, RequestInterceptor, Response};
+use shared_types::TabId;
+use std::collections::BTreeMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use url::Url;
+use webview_integration::UserScriptSource;
+
+pub use cookie_banner::CookieBannerRejecter;
+
+/// Extensions shipped with the browser, in registration order
+pub const BUILTIN_EXTENSIONS: &[ExtensionFactory] = &[CookieBannerRejecter::boxed];
+
+/// Prefix of the menu actions contributed by extensions
+const MENU_ACTION_PREFIX: &str = "extension:";
+
+/// A registered extension and its bookkeeping
+struct Slot {
+    /// The extension itself
+    ext: Box<dyn BrowserExtension>,
+    /// Whether hooks are dispatched to it
+    enabled: AtomicBool,
+    /// Per-hook call statistics
+    stats: Mutex<BTreeMap<Hook, HookStats>>,
+}
+
+/// Owns the loaded extensions and dispatches hooks to them
+pub struct ExtensionManager {
+    /// Extensions in registration order
+    slots: Vec<Slot>,
+}
+
+impl ExtensionManager {
+    /// Create a manager with no extensions
+    pub fn new() -> Self {
+        Self { slots: Vec::new() }
+    }
+
+    /// Create a manager from a registry, honouring `extensions.disabled`
+    ///
+    /// Disabled extensions are still registered (so they show up on
+    /// `about:diagnostics`) but receive no hook calls.
+    ///
+    /// # Arguments
+    ///
+    /// * `registry` - Extension constructors, in the order hooks should run
+    /// * `settings` - Extension settings from the config
+    pub fn from_registry(registry: &[ExtensionFactory], settings: &ExtensionSettings) -> Self {
+        let mut manager = Self::new();
+        for factory in registry {
+            let ext = factory();
+            let enabled = settings.is_enabled(ext.id());
+            manager.register(ext, enabled);
+        }
+        manager
+    }
+
+    /// Register an extension after the existing ones
+    ///
+    /// # Arguments
+    ///
+    /// * `ext` - Extension to register
+    /// * `enabled` - Whether hooks are dispatched to it
+    pub fn register(&mut self, ext: Box<dyn BrowserExtension>, enabled: bool) {
+        self.slots.push(Slot {
+            ext,
+            enabled: AtomicBool::new(enabled),
+            stats: Mutex::new(BTreeMap::new()),
+        });
+    }
+
+    /// Number of registered extensions
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Whether no extensions are registered
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Enable or disable an extension at runtime
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Extension ID
+    /// * `enabled` - New state
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if no extension has that ID.
+    pub fn set_enabled(&self, id: &str, enabled: bool) -> bool {
+        match self.slots.iter().find(|slot| slot.ext.id() == id) {
+            Some(slot) => {
+                slot.enabled.store(enabled, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Collect the user scripts that apply to a URL
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Page URL
+    ///
+    /// # Returns
+    ///
+    /// Script sources from every enabled extension, in registration order.
+    pub fn user_scripts_for(&self, url: &Url) -> Vec<String> {
+        let mut scripts = Vec::new();
+        for slot in self.enabled_slots() {
+            if let Some(list) = Self::call(slot, Hook::UserScripts, || slot.ext.user_scripts()) {
+                scripts.extend(
+                    list.into_iter()
+                        .filter(|script| script.applies_to(url))
+                        .map(|script| script.source),
+                );
+            }
+        }
+        scripts
+    }
+
+    /// Adapt [`user_scripts_for`](Self::user_scripts_for) for a webview
+    ///
+    /// # Returns
+    ///
+    /// A source to pass to `WebViewWrapper::set_user_scripts`
+    pub fn user_script_source(self: &Arc<Self>) -> UserScriptSource {
+        let manager = Arc::clone(self);
+        Arc::new(move |url: &str| match Url::parse(url) {
+            Ok(url) => manager.user_scripts_for(&url),
+            Err(_) => Vec::new(),
+        })
+    }
+
+    /// Build the Extensions menu entries
+    ///
+    /// # Returns
+    ///
+    /// One item per entry contributed by an enabled extension; each item's
+    /// action is `MenuAction::Custom("extension:<id>:<item>")`.
+    pub fn menu_items(&self) -> Vec<MenuItem> {
+        let mut items = Vec::new();
+        for slot in self.enabled_slots() {
+            let id = slot.ext.id();
+            if let Some(entries) = Self::call(slot, Hook::MenuItems, || slot.ext.menu_items()) {
+                items.extend(entries.into_iter().map(|entry| {
+                    MenuItem::new(entry.label).with_action(MenuAction::Custom(format!(
+                        "{}{}:{}",
+                        MENU_ACTION_PREFIX, id, entry.id
+                    )))
+                }));
+            }
+        }
+        items
+    }
+
+    /// Route an extension menu action to its extension
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - Custom action string from [`menu_items`](Self::menu_items)
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if the action does not belong to an enabled extension.
+    pub fn handle_menu_action(&self, action: &str) -> bool {
+        let Some((id, item)) = action
+            .strip_prefix(MENU_ACTION_PREFIX)
+            .and_then(|rest| rest.split_once(':'))
+        else {
+            return false;
+        };
+        match self.enabled_slots().find(|slot| slot.ext.id() == id) {
+            Some(slot) => {
+                Self::call(slot, Hook::MenuAction, || slot.ext.on_menu_action(item));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Enabled extensions, in registration order
+    fn enabled_slots(&self) -> impl Iterator<Item = &Slot> {
+        self.slots
+            .iter()
+            .filter(|slot| slot.enabled.load(Ordering::Relaxed))
+    }
+
+    /// Run one hook of one extension, recording its timing
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the hook panicked.
+    fn call<T>(slot: &Slot, hook: Hook, f: impl FnOnce() -> T) -> Option<T> {
+        let start = Instant::now();
+        let result = catch_unwind(AssertUnwindSafe(f));
+        let elapsed = start.elapsed();
+
+        slot.stats
+            .lock()
+            .unwrap()
+            .entry(hook)
+            .or_default()
+            .record(elapsed, result.is_err());
+
+        if result.is_err() {
+            tracing::warn!(
+                extension = slot.ext.id(),
+                hook = hook.label(),
+                "Extension hook panicked"
+            );
+        }
+        result.ok()
+    }
+}
+
+impl Default for ExtensionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HookDispatcher for ExtensionManager {
+    fn on_navigation(&self, ctx: &NavigationContext) -> NavigationVerdict {
+        let mut verdict = NavigationVerdict::Allow;
+        for slot in self.enabled_slots() {
+            let result = Self::call(slot, Hook::Navigation, || slot.ext.on_navigation(ctx));
+            if let (NavigationVerdict::Allow, Some(NavigationVerdict::Block { reason })) =
+                (&verdict, result)
+            {
+                verdict = NavigationVerdict::Block {
+                    reason: format!("{}: {}", slot.ext.id(), reason),
+                };
+            }
+        }
+        verdict
+    }
+
+    fn on_request(&self, request: &RequestInfo) -> RequestVerdict {
+        let mut verdict = RequestVerdict::Allow;
+        for slot in self.enabled_slots() {
+            let result = Self::call(slot, Hook::Request, || slot.ext.on_request(request));
+            if let (RequestVerdict::Allow, Some(RequestVerdict::Block { reason })) =
+                (&verdict, result)
+            {
+                verdict = RequestVerdict::Block {
+                    reason: format!("{}: {}", slot.ext.id(), reason),
+                };
+            }
+        }
+        verdict
+    }
+
+    fn on_page_loaded(&self, tab_id: TabId, url: &Url) {
+        for slot in self.enabled_slots() {
+            Self::call(slot, Hook::PageLoaded, || {
+                slot.ext.on_page_loaded(tab_id, url)
+            });
+        }
+    }
+
+    fn diagnostics(&self) -> Vec<ExtensionDiagnostics> {
+        self.slots
+            .iter()
+            .map(|slot| ExtensionDiagnostics {
+                id: slot.ext.id().to_string(),
+                name: slot.ext.name().to_string(),
+                enabled: slot.enabled.load(Ordering::Relaxed),
+                hooks: slot
+                    .stats
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|(hook, stats)| (*hook, *stats))
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+/// Runs extension request hooks in the network stack's interceptor chain
+pub struct ExtensionRequestInterceptor {
+    /// Manager to dispatch to
+    manager: Arc<ExtensionManager>,
+    /// Reason given by the last blocking extension
+    last_reason: Mutex<Option<String>>,
+}
+
+impl ExtensionRequestInterceptor {
+    /// Create a new extension request interceptor
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - Extension manager shared with the rest of the app
+    pub fn new(manager: Arc<ExtensionManager>) -> Self {
+        Self {
+            manager,
+            last_reason: Mutex::new(None),
+        }
+    }
+}
+
+impl RequestInterceptor for ExtensionRequestInterceptor {
+    fn pre_request(&mut self, _request: &mut Request) -> network_stack::Result<()> {
+        Ok(())
+    }
+
+    fn post_response(&mut self, _response: &mut Response) -> network_stack::Result<()> {
+        Ok(())
+    }
+
+    fn should_block(&self, request: &Request) -> bool {
+        let info = RequestInfo {
+            url: request.url.clone(),
+            method: request.method.as_str().to_string(),
+            resource_type: request.resource_type.clone(),
+            top_level_origin: request.top_level_origin.clone(),
+        };
+        match self.manager.on_request(&info) {
+            RequestVerdict::Allow => false,
+            RequestVerdict::Block { reason } => {
+                *self.last_reason.lock().unwrap() = Some(reason);
+                true
+            }
+        }
+    }
+
+    fn block_reason(&self, _request: &Request) -> String {
+        self.last_reason
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap_or_else(|| "Blocked by extension".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use message_bus::MessageBus;
+    use network_stack::request_handler::HttpMethod;
+    use shared_types::NavigationInitiator;
+    use webview_integration::{MockBackend, WebViewWrapper};
+
+    /// Test extension that logs its calls and returns a fixed verdict
+    struct Probe {
+        id: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+        block: bool,
+        panics: bool,
+    }
+
+    impl Probe {
+        fn boxed(
+            id: &'static str,
+            log: &Arc<Mutex<Vec<String>>>,
+            block: bool,
+            panics: bool,
+        ) -> Box<dyn BrowserExtension> {
+            Box::new(Self {
+                id,
+                log: Arc::clone(log),
+                block,
+                panics,
+            })
+        }
+    }
+
+    impl BrowserExtension for Probe {
+        fn id(&self) -> &str {
+            self.id
+        }
+
+        fn on_navigation(&self, _ctx: &NavigationContext) -> NavigationVerdict {
+            self.log.lock().unwrap().push(format!("{}:nav", self.id));
+            if self.panics {
+                panic!("probe panicked");
+            }
+            if self.block {
+                NavigationVerdict::Block {
+                    reason: "blocked".to_string(),
+                }
+            } else {
+                NavigationVerdict::Allow
+            }
+        }
+
+        fn on_request(&self, _request: &RequestInfo) -> RequestVerdict {
+            self.log.lock().unwrap().push(format!("{}:req", self.id));
+            if self.block {
+                RequestVerdict::Block {
+                    reason: "no".to_string(),
+                }
+            } else {
+                RequestVerdict::Allow
+            }
+        }
+    }
+
+    fn nav_ctx(url: &str) -> NavigationContext {
+        NavigationContext {
+            tab_id: 1,
+            url: Url::parse(url).unwrap(),
+            initiator: NavigationInitiator::UserTyped,
+        }
+    }
+
+    // ========================================
+    // Tests for hook dispatch
+    // ========================================
+
+    #[test]
+    fn test_hooks_run_in_registration_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut manager = ExtensionManager::new();
+        manager.register(Probe::boxed("a", &log, false, false), true);
+        manager.register(Probe::boxed("b", &log, false, false), true);
+        manager.register(Probe::boxed("c", &log, false, false), true);
+
+        let verdict = manager.on_navigation(&nav_ctx("https://example.com/"));
+
+        assert_eq!(verdict, NavigationVerdict::Allow);
+        assert_eq!(*log.lock().unwrap(), vec!["a:nav", "b:nav", "c:nav"]);
+    }
+
+    #[test]
+    fn test_any_block_wins_and_first_reason_is_kept() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut manager = ExtensionManager::new();
+        manager.register(Probe::boxed("a", &log, false, false), true);
+        manager.register(Probe::boxed("b", &log, true, false), true);
+        manager.register(Probe::boxed("c", &log, true, false), true);
+
+        let verdict = manager.on_navigation(&nav_ctx("https://example.com/"));
+
+        assert_eq!(
+            verdict,
+            NavigationVerdict::Block {
+                reason: "b: blocked".to_string()
+            }
+        );
+        // Later extensions still observe the navigation
+        assert_eq!(log.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_panicking_extension_does_not_affect_others() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut manager = ExtensionManager::new();
+        manager.register(Probe::boxed("bad", &log, false, true), true);
+        manager.register(Probe::boxed("good", &log, true, false), true);
+
+        let verdict = manager.on_navigation(&nav_ctx("https://example.com/"));
+
+        assert_eq!(
+            verdict,
+            NavigationVerdict::Block {
+                reason: "good: blocked".to_string()
+            }
+        );
+        let diagnostics = manager.diagnostics();
+        let bad = &diagnostics[0];
+        assert_eq!(bad.id, "bad");
+        assert_eq!(bad.hooks.len(), 1);
+        assert_eq!(bad.hooks[0].0, Hook::Navigation);
+        assert_eq!(bad.hooks[0].1.calls, 1);
+        assert_eq!(bad.hooks[0].1.panics, 1);
+        assert_eq!(diagnostics[1].hooks[0].1.panics, 0);
+    }
+
+    #[test]
+    fn test_disable_flag_honored() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut manager = ExtensionManager::new();
+        manager.register(Probe::boxed("a", &log, true, false), false);
+        manager.register(Probe::boxed("b", &log, false, false), true);
+
+        let verdict = manager.on_navigation(&nav_ctx("https://example.com/"));
+        assert_eq!(verdict, NavigationVerdict::Allow);
+        assert_eq!(*log.lock().unwrap(), vec!["b:nav"]);
+
+        assert!(manager.set_enabled("a", true));
+        assert!(!manager.set_enabled("missing", true));
+        assert!(matches!(
+            manager.on_navigation(&nav_ctx("https://example.com/")),
+            NavigationVerdict::Block { .. }
+        ));
+    }
+
+    #[test]
+    fn test_from_registry_uses_config_disabled_list() {
+        let settings = ExtensionSettings {
+            disabled: vec![CookieBannerRejecter::ID.to_string()],
+        };
+        let manager = ExtensionManager::from_registry(BUILTIN_EXTENSIONS, &settings);
+
+        let diagnostics = manager.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(!diagnostics[0].enabled);
+        let url = Url::parse("https://example.com/").unwrap();
+        assert!(manager.user_scripts_for(&url).is_empty());
+    }
+
+    #[test]
+    fn test_request_interceptor_reports_extension_reason() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut manager = ExtensionManager::new();
+        manager.register(Probe::boxed("filter", &log, true, false), true);
+        let interceptor = ExtensionRequestInterceptor::new(Arc::new(manager));
+
+        let request = Request::new(Url::parse("https://ads.com/x.js").unwrap(), HttpMethod::GET);
+        assert!(interceptor.should_block(&request));
+        assert_eq!(interceptor.block_reason(&request), "filter: no");
+    }
+
+    // ========================================
+    // Tests for the example extension
+    // ========================================
+
+    #[test]
+    fn test_cookie_banner_rejecter_end_to_end() {
+        let manager = Arc::new(ExtensionManager::from_registry(
+            BUILTIN_EXTENSIONS,
+            &ExtensionSettings::default(),
+        ));
+
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        wrapper.set_backend(backend.clone());
+        wrapper.set_user_scripts(manager.user_script_source());
+
+        wrapper.navigate("https://news.example.com/").unwrap();
+        wrapper.navigate("about:blank").unwrap();
+
+        let run = backend.user_scripts_run();
+        assert_eq!(run.len(), 1);
+        assert!(run[0].1.contains("#onetrust-reject-all-handler"));
+
+        // Pausing from the Extensions menu stops injection
+        let items = manager.menu_items();
+        assert_eq!(items.len(), 1);
+        let MenuAction::Custom(action) = &items[0].action else {
+            panic!("extension menu items use custom actions");
+        };
+        assert!(manager.handle_menu_action(action));
+        wrapper.navigate("https://news.example.com/").unwrap();
+        assert_eq!(backend.user_scripts_run().len(), 1);
+
+        let stats = &manager.diagnostics()[0].hooks;
+        assert!(stats
+            .iter()
+            .any(|(hook, s)| *hook == Hook::UserScripts && s.calls == 3));
+
+        bus.shutdown().unwrap();
+    }
+}
@@ -0,0 +1,246 @@
+//! `frankenbrowser fetch` subcommand
+//!
+//! `fetch [--config <file>] [--har <file>] [--include-sensitive-headers] <url>`
+//! fetches a URL through the network stack (interceptors, cache and cookies
+//! as configured), writes the body to stdout and, with `--har`, saves the
+//! requests made as a HAR file. Response bodies are embedded in the HAR only
+//! when `network.har_embed_bodies` is set; credential headers are redacted
+//! unless `--include-sensitive-headers` is given.
+
+use crate::config_cmd::{EXIT_INVALID, EXIT_OK, EXIT_USAGE};
+use config_manager::Config;
+use message_bus::MessageBus;
+use network_stack::{CustomHeaderInterceptor, FetchContext, HarOptions, NetworkStack};
+use shared_types::{SystemIds, TraceContext};
+use std::io::Write;
+use std::path::PathBuf;
+use url::Url;
+
+const USAGE: &str = "usage: frankenbrowser fetch [--config <file>] [--har <file>] [--include-sensitive-headers] <url>";
+
+/// Run the `fetch` subcommand
+///
+/// # Arguments
+///
+/// * `args` - Arguments following `fetch`
+/// * `out` - Stream the response body is written to
+/// * `err` - Stream for diagnostics
+///
+/// # Returns
+///
+/// Returns the process exit code.
+pub fn run(args: &[String], out: &mut dyn Write, err: &mut dyn Write) -> i32 {
+    let mut config_path: Option<PathBuf> = None;
+    let mut har_path: Option<PathBuf> = None;
+    let mut include_sensitive_headers = false;
+    let mut urls: Vec<&str> = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--config" => match iter.next() {
+                Some(path) => config_path = Some(PathBuf::from(path)),
+                None => return usage(err),
+            },
+            "--har" => match iter.next() {
+                Some(path) => har_path = Some(PathBuf::from(path)),
+                None => return usage(err),
+            },
+            "--include-sensitive-headers" => include_sensitive_headers = true,
+            _ => urls.push(arg),
+        }
+    }
+    let [url] = urls.as_slice() else {
+        return usage(err);
+    };
+    let url = match Url::parse(url) {
+        Ok(url) => url,
+        Err(e) => {
+            let _ = writeln!(err, "Invalid URL {}: {}", url, e);
+            return EXIT_USAGE;
+        }
+    };
+
+    let path = config_path.unwrap_or_else(Config::default_path);
+    let config = if path.exists() {
+        match Config::load_from_file(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                let _ = writeln!(err, "{}: {}", path.display(), e);
+                return EXIT_INVALID;
+            }
+        }
+    } else {
+        Config::default()
+    };
+
+    let custom_headers = match config.custom_headers() {
+        Ok(custom_headers) => custom_headers,
+        Err(e) => {
+            let _ = writeln!(err, "{}: {}", path.display(), e);
+            return EXIT_INVALID;
+        }
+    };
+
+    let mut bus = MessageBus::new();
+    let network = bus
+        .start()
+        .map_err(|e| e.to_string())
+        .and_then(|()| {
+            NetworkStack::new(config.network_config(), bus.sender()).map_err(|e| e.to_string())
+        })
+        .and_then(|mut network| {
+            network.set_har_options(HarOptions {
+                embed_bodies: config.network.har_embed_bodies,
+                include_sensitive_headers,
+            });
+            if !custom_headers.is_empty() {
+                network.add_interceptor(Box::new(CustomHeaderInterceptor::new(custom_headers)));
+            }
+            network.initialize().map_err(|e| e.to_string())?;
+            Ok(network)
+        });
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string());
+    let (network, runtime) = match (network, runtime) {
+        (Ok(network), Ok(runtime)) => (network, runtime),
+        (Err(e), _) | (_, Err(e)) => {
+            let _ = writeln!(err, "Failed to start the network stack: {}", e);
+            return EXIT_INVALID;
+        }
+    };
+
+    // The fetch is one user action; its HAR entries carry the trace id
+    let _trace = TraceContext::root(&SystemIds::new()).enter();
+    let fetched = runtime.block_on(async {
+        let (_, stream) = network
+            .fetch_streaming(url.clone(), FetchContext::document())
+            .await?;
+        stream.collect().await
+    });
+    let mut code = match fetched {
+        Ok(body) => {
+            let _ = out.write_all(&body);
+            EXIT_OK
+        }
+        Err(e) => {
+            let _ = writeln!(err, "{}: {}", url, e);
+            EXIT_INVALID
+        }
+    };
+
+    // Written even when the fetch failed, for whatever was recorded
+    if let Some(har_path) = har_path {
+        let written = network
+            .export_har_session()
+            .map_err(|e| e.to_string())
+            .and_then(|har| std::fs::write(&har_path, har).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            let _ = writeln!(err, "Failed to write {}: {}", har_path.display(), e);
+            code = EXIT_INVALID;
+        }
+    }
+    code
+}
+
+fn usage(err: &mut dyn Write) -> i32 {
+    let _ = writeln!(err, "{}", USAGE);
+    EXIT_USAGE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serve one response to every connection
+    fn test_server(response: &'static str) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { return };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        base
+    }
+
+    fn run_with(dir: &std::path::Path, args: &[&str]) -> (i32, String, String) {
+        let config = dir.join("config.toml");
+        let mut full = vec!["--config".to_string(), config.display().to_string()];
+        full.extend(args.iter().map(|a| a.to_string()));
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let code = run(&full, &mut out, &mut err);
+        (
+            code,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    // ========================================
+    // Tests for fetch
+    // ========================================
+
+    #[test]
+    fn test_fetch_writes_body_and_har() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = test_server(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+        );
+        let har_path = dir.path().join("out.har");
+
+        let (code, out, err) = run_with(
+            dir.path(),
+            &["--har", har_path.to_str().unwrap(), base.as_str()],
+        );
+        assert_eq!(code, EXIT_OK, "{}", err);
+        assert_eq!(out, "hello");
+
+        let har: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&har_path).unwrap()).unwrap();
+        let entries = har["log"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0]["request"]["url"], base.as_str());
+        assert_eq!(entries[0]["response"]["status"], 200);
+        assert!(entries[0]["_traceId"].is_u64());
+        // Bodies are not embedded by default
+        assert!(entries[0]["response"]["content"].get("text").is_none());
+    }
+
+    #[test]
+    fn test_fetch_failure_exit_code() {
+        let dir = tempfile::tempdir().unwrap();
+        let base =
+            test_server("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+
+        let (code, out, err) = run_with(dir.path(), &[base.as_str()]);
+        assert_eq!(code, EXIT_INVALID);
+        assert!(out.is_empty());
+        assert!(err.contains(base.as_str()));
+    }
+
+    #[test]
+    fn test_fetch_usage_errors() {
+        let dir = tempfile::tempdir().unwrap();
+
+        assert_eq!(run_with(dir.path(), &[]).0, EXIT_USAGE);
+        assert_eq!(run_with(dir.path(), &["--har"]).0, EXIT_USAGE);
+        assert_eq!(
+            run_with(dir.path(), &["https://a.test/", "https://b.test/"]).0,
+            EXIT_USAGE
+        );
+        let (code, _, err) = run_with(dir.path(), &["not a url"]);
+        assert_eq!(code, EXIT_USAGE);
+        assert!(err.contains("Invalid URL"));
+    }
+}
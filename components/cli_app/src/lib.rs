@@ -16,6 +16,10 @@
 //! - network_stack: HTTP client
 //! - adblock_engine: Ad blocking
 //! - browser_core: Browser engine
+//! - extension_api: Built-in extension hooks
+//!
+//! Construction and the first navigation are timed by a
+//! [`StartupTimeline`]; `frankenbrowser --startup-profile` prints it.
 //!
 //! # Usage
 //!
@@ -36,17 +40,28 @@
 //! }
 //! ```
 
+pub mod config_cmd;
+pub mod console;
 pub mod errors;
+pub mod extensions;
+pub mod fetch_cmd;
+pub mod list_fetcher;
+pub mod profile_cmd;
+pub mod startup;
 pub mod types;
 
 // Re-export main types for convenience
 pub use errors::{Error, Result};
+pub use extensions::{ExtensionManager, BUILTIN_EXTENSIONS};
+pub use startup::{StartupProfile, StartupSpan, StartupTimeline};
 pub use types::BrowserApp;
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use config_manager::Config;
+    use std::sync::{Arc, Mutex};
+    use url::Url;
 
     // ========================================
     // RED PHASE: Tests for BrowserApp::new()
@@ -126,6 +141,399 @@ mod tests {
         assert!(result.is_ok());
     }
 
-    // Note: BrowserApp is not Send due to AdBlockEngine's internal Rc types.
-    // This is acceptable for an application-level component that runs on the main thread.
+    #[test]
+    fn test_browser_app_loads_builtin_extensions() {
+        let app = BrowserApp::new(Config::default()).unwrap();
+        assert_eq!(app.extensions().len(), BUILTIN_EXTENSIONS.len());
+        assert!(!app.extensions().menu_items().is_empty());
+
+        let mut config = Config::default();
+        config.extensions.disabled = vec![extensions::CookieBannerRejecter::ID.to_string()];
+        let app = BrowserApp::new(config).unwrap();
+        assert!(app.extensions().menu_items().is_empty());
+    }
+
+    #[test]
+    fn test_browser_app_seeds_filter_lists_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.adblock.filter_list_dir = dir.path().to_string_lossy().into_owned();
+
+        let first = BrowserApp::new(config.clone()).unwrap();
+        let lists = first.adblock().filter_lists();
+        if adblock_engine::SEED_FILTERS.is_some() {
+            assert_eq!(lists.len(), 1);
+            assert_eq!(lists[0].source, shared_types::FilterListSource::Seed);
+            assert!(first.adblock().should_block(
+                "https://www.google-analytics.com/analytics.js",
+                "https://news.example/",
+                shared_types::ResourceType::Script
+            ));
+        } else {
+            assert!(lists.is_empty());
+        }
+
+        let second = BrowserApp::new(config).unwrap();
+        assert_eq!(second.adblock().filter_lists(), lists);
+    }
+
+    #[test]
+    fn test_browser_app_filter_seeding_can_be_disabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.adblock.filter_list_dir = dir.path().to_string_lossy().into_owned();
+        config.adblock.seed_filters = false;
+
+        let app = BrowserApp::new(config).unwrap();
+
+        assert!(app.adblock().filter_lists().is_empty());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_browser_app_subscribes_new_profile_to_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.adblock.filter_list_dir = dir.path().to_string_lossy().into_owned();
+        config.adblock.default_subscriptions = vec!["https://lists.example/a.txt".to_string()];
+
+        let app = BrowserApp::new(config).unwrap();
+
+        let subscriptions = app.adblock().list_subscriptions();
+        assert_eq!(subscriptions.len(), 1);
+        assert_eq!(subscriptions[0].url, "https://lists.example/a.txt");
+        assert!(subscriptions[0].enabled);
+        assert_eq!(subscriptions[0].last_updated, None);
+    }
+
+    #[test]
+    fn test_browser_app_opens_pinned_tabs_before_homepage() {
+        let dir = tempfile::tempdir().unwrap();
+        browser_shell::PinnedTabStore::new(dir.path().join(browser_shell::PINNED_TABS_FILE))
+            .save(&[
+                browser_shell::PinnedTab {
+                    url: "https://mail.example/".to_string(),
+                    title: "Mail".to_string(),
+                    favicon: None,
+                },
+                browser_shell::PinnedTab {
+                    url: "https://chat.example/".to_string(),
+                    title: "Chat".to_string(),
+                    favicon: None,
+                },
+            ])
+            .unwrap();
+        let mut config = Config::default();
+        config.browser.session_dir = dir.path().to_string_lossy().into_owned();
+        config.browser.homepage = "https://home.example/".to_string();
+
+        let mut app = BrowserApp::new(config).unwrap();
+        app.start().unwrap();
+
+        let shell = app.shell();
+        let titles: Vec<String> = shell
+            .tab_bar()
+            .tab_ids()
+            .iter()
+            .map(|id| shell.get_tab(*id).unwrap().title.clone())
+            .collect();
+        assert_eq!(titles, ["Mail", "Chat", "New Tab"]);
+        assert_eq!(shell.pinned_tabs().len(), 2);
+        let active = shell.get_active_tab().unwrap();
+        assert!(!shell.get_tab(active).unwrap().pinned);
+    }
+
+    #[test]
+    fn test_browser_app_restores_saved_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.browser.session_dir = dir.path().to_string_lossy().into_owned();
+        config.browser.restore_session = true;
+        config.browser.homepage = "https://home.example/".to_string();
+
+        let mut previous = BrowserApp::new(config.clone()).unwrap();
+        let engine = previous.browser_core_mut();
+        for url in ["https://one.example/", "https://two.example/"] {
+            engine
+                .navigate(
+                    9,
+                    url::Url::parse(url).unwrap(),
+                    browser_core::NavigationInitiator::UserTyped,
+                )
+                .unwrap();
+        }
+        engine
+            .save_session(&dir.path().join(browser_core::SESSION_FILE))
+            .unwrap();
+        drop(previous);
+
+        let mut app = BrowserApp::new(config).unwrap();
+        app.start().unwrap();
+
+        let shell = app.shell();
+        assert_eq!(shell.get_tab_count(), 1);
+        let tab_id = shell.get_active_tab().unwrap();
+        assert_eq!(
+            shell.get_tab(tab_id).unwrap().url.as_deref(),
+            Some("https://two.example/")
+        );
+        let history = app.browser_core().tab_histories().get(tab_id).unwrap();
+        assert_eq!(
+            history.urls,
+            ["https://one.example/", "https://two.example/"]
+        );
+        assert_eq!(history.position, 1);
+    }
+
+    #[test]
+    fn test_browser_app_ticks_session_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.browser.session_dir = dir.path().to_string_lossy().into_owned();
+        config.browser.session_snapshot_secs = 0;
+        let mut app = BrowserApp::new(config).unwrap();
+        app.start().unwrap();
+        app.tick_session_snapshot();
+        let snapshot = dir.path().join(browser_shell::snapshot::SNAPSHOT_FILE);
+        let _ = std::fs::remove_file(&snapshot);
+
+        app.shell_mut().create_tab().unwrap();
+        assert!(app.tick_session_snapshot());
+        assert!(snapshot.exists());
+        assert!(!app.tick_session_snapshot());
+    }
+
+    /// Request lines and bodies a test server received
+    type RequestLog = Arc<Mutex<Vec<(String, Vec<u8>)>>>;
+
+    /// Serve one HTML page to every request, recording request lines and bodies
+    fn recording_server() -> (Url, RequestLog) {
+        use std::io::{BufRead, BufReader, Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let server_log = log.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { return };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut length = 0;
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                    line.clear();
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                server_log
+                    .lock()
+                    .unwrap()
+                    .push((request_line.trim_end().to_string(), body));
+                let page = "<p>Thanks</p>";
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    page.len(),
+                    page
+                );
+            }
+        });
+        (base, log)
+    }
+
+    #[test]
+    fn test_browser_app_sends_window_form_posts_through_network_stack() {
+        let (base, log) = recording_server();
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.browser.session_dir = dir.path().to_string_lossy().into_owned();
+        let mut app = BrowserApp::new(config).unwrap();
+        app.start().unwrap();
+        let tab = app.shell().get_active_tab().unwrap();
+
+        // What the window's form capture script reports for a POST form
+        let shell = app.shell_mut();
+        let message = serde_json::json!({
+            "v": webview_integration::PROTOCOL_VERSION,
+            "token": shell.bridge_token(),
+            "seq": 1,
+            "type": webview_integration::forms::FORM_SUBMIT_CHANNEL,
+            "data": {
+                "method": "post",
+                "action": base.join("checkout").unwrap().as_str(),
+                "enctype": "application/x-www-form-urlencoded",
+                "fields": [["item", "42"], ["note", "a&b"]],
+            },
+        });
+        shell.handle_ipc_message(&message.to_string()).unwrap();
+        assert_eq!(app.send_form_submissions(), 1);
+
+        let requests = log.lock().unwrap().clone();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].0, "POST /checkout HTTP/1.1");
+        assert_eq!(requests[0].1, b"item=42&note=a%26b");
+        let checkout = base.join("checkout").unwrap();
+        assert_eq!(
+            app.shell().get_tab(tab).unwrap().url.as_deref(),
+            Some(checkout.as_str())
+        );
+        assert!(app
+            .browser_core()
+            .get_history()
+            .iter()
+            .any(|entry| entry.url == checkout.as_str() && entry.is_post));
+        assert_eq!(app.send_form_submissions(), 0);
+    }
+
+    #[test]
+    fn test_browser_app_renders_about_storage_from_window_site_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.browser.session_dir = dir.path().to_string_lossy().into_owned();
+        let mut app = BrowserApp::new(config).unwrap();
+        app.start().unwrap();
+        let backend = Arc::new(webview_integration::MockBackend::new());
+        backend.set_storage_usage(Some(vec![shared_types::OriginUsage {
+            origin: "a.example".to_string(),
+            kinds: vec![shared_types::StorageKind::IndexedDb],
+            bytes: 4096,
+            cookies: 0,
+        }]));
+        app.shell_mut().set_window_backend(backend.clone());
+        app.browser_core()
+            .network()
+            .cookies()
+            .set_cookie("k=v", &Url::parse("https://b.example/").unwrap());
+
+        let storage = Url::parse("about:storage").unwrap();
+        let tab = app
+            .shell_mut()
+            .open_in_new_tab(&storage, shared_types::NavigationInitiator::UserTyped)
+            .unwrap();
+        assert_eq!(app.show_internal_pages(), 1);
+        let (shown, _, page) = backend.documents().pop().unwrap();
+        let page = String::from_utf8(page).unwrap();
+        assert_eq!(shown, tab);
+        assert!(page.contains("a.example") && page.contains("b.example"));
+
+        // Clearing a site from the page clears the window's webview
+        let clear = browser_core::internal_pages::storage_clear_url("a.example");
+        assert!(app.shell_mut().handle_page_navigation(&clear).unwrap());
+        assert_eq!(app.show_internal_pages(), 1);
+        assert_eq!(
+            backend.cleared_storage(),
+            vec![(
+                "a.example".to_string(),
+                shared_types::StorageKind::ALL.to_vec()
+            )]
+        );
+        let (_, _, page) = backend.documents().pop().unwrap();
+        assert!(!String::from_utf8(page).unwrap().contains("a.example"));
+        assert_eq!(
+            app.shell().get_tab(tab).unwrap().url.as_deref(),
+            Some("about:storage")
+        );
+    }
+
+    #[test]
+    fn test_browser_app_applies_content_settings_to_window() {
+        let mut config = Config::default();
+        config.content.javascript = false;
+        let mut app = BrowserApp::new(config).unwrap();
+        app.start().unwrap();
+        let backend = Arc::new(webview_integration::MockBackend::new());
+        app.shell_mut().set_window_backend(backend.clone());
+        let tab = app.shell().get_active_tab().unwrap();
+
+        app.shell_mut().page_load_started("https://content.example/");
+        assert_eq!(backend.javascript_toggles(), vec![(tab, false)]);
+        assert_eq!(backend.image_toggles(), vec![(tab, true)]);
+    }
+
+    #[test]
+    fn test_browser_app_applies_config_reloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut config = Config::default();
+        config.appearance.theme = "light".to_string();
+        config.save_to_file(&path).unwrap();
+        let mut app = BrowserApp::new(config.clone()).unwrap();
+        app.watch_config(&path).unwrap();
+        assert!(app.adblock().is_enabled());
+        let backend = Arc::new(webview_integration::MockBackend::new());
+        app.shell_mut().set_window_backend(backend.clone());
+        app.shell_mut().create_tab().unwrap();
+
+        config.adblock.enabled = false;
+        config.appearance.theme = "dark".to_string();
+        config.accessibility.reduce_motion = true;
+        config.fonts.serif_family = "DejaVu Serif".to_string();
+        config.network.timeout_seconds += 5;
+        config.save_to_file(&path).unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        let events = loop {
+            let events = app.apply_config_changes();
+            if !events.is_empty() || std::time::Instant::now() > deadline {
+                break events;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        };
+
+        let [config_manager::ConfigEvent::Reloaded { delta, .. }] = events.as_slice() else {
+            panic!("expected one reload, got {:?}", events);
+        };
+        assert_eq!(delta.restart_required(), ["network.timeout_seconds"]);
+        assert!(!app.adblock().is_enabled());
+        assert!(app.shell().theme().is_dark());
+        let (_, css, _) = backend.user_stylesheets().pop().unwrap();
+        assert!(css.contains("animation: none !important"));
+        let (_, fonts, _) = backend.font_settings().pop().unwrap();
+        assert_eq!(fonts.serif_family, "DejaVu Serif");
+    }
+
+    #[test]
+    fn test_browser_app_routes_adblock_messages_to_engine() {
+        let mut config = Config::default();
+        config.adblock.seed_filters = false;
+        config.adblock.custom_filters = vec!["||ads.bus-test.example^".to_string()];
+        let app = BrowserApp::new(config).unwrap();
+        let sender = app.message_bus().sender();
+        let ad = url::Url::parse("https://ads.bus-test.example/banner.js").unwrap();
+        let should_block = shared_types::BrowserMessage::ShouldBlock {
+            url: ad,
+            resource_type: shared_types::ResourceType::Script,
+            tab_id: Some(1),
+        };
+
+        let reply = sender.request(should_block.clone()).unwrap();
+        assert!(matches!(
+            reply,
+            shared_types::BrowserMessage::BlockDecision { block: true, .. }
+        ));
+
+        sender
+            .send(shared_types::BrowserMessage::ToggleAdblockForSite {
+                domain: "ads.bus-test.example".to_string(),
+                allowed: true,
+            })
+            .unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while !app.adblock().is_allowed("ads.bus-test.example") {
+            assert!(std::time::Instant::now() < deadline, "toggle not handled");
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_browser_app_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<BrowserApp>();
+    }
 }
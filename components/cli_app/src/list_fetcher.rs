@@ -0,0 +1,236 @@
+//! Filter list downloads for the ad blocker
+//!
+//! [`NetworkListFetcher`] implements [`adblock_engine::ListFetcher`] on a
+//! network stack of its own, built on the first download so a startup
+//! without list updates does not pay for it. Downloads block the calling
+//! thread on the application's runtime, so they must not be started from
+//! inside that runtime. They send `If-None-Match` and `If-Modified-Since`
+//! for lists that are already cached.
+
+use adblock_engine::{Error, ListDownload, ListFetcher, ListValidators, Result};
+use config_manager::NetworkConfig;
+use message_bus::MessageSender;
+use network_stack::{FetchOptions, HttpMethod, NetworkStack};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::runtime::Runtime;
+use url::Url;
+
+/// Downloads filter lists through a [`NetworkStack`]
+pub struct NetworkListFetcher {
+    /// Network settings for the stack
+    config: NetworkConfig,
+    /// Sender handed to the stack when it is built
+    sender: Mutex<Option<Box<dyn MessageSender>>>,
+    /// Stack, built on first use
+    network: OnceLock<NetworkStack>,
+    /// Runtime the downloads run on
+    runtime: Arc<Runtime>,
+}
+
+impl NetworkListFetcher {
+    /// Create a fetcher
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Network settings (cookies, cache, timeouts)
+    /// * `sender` - Message sender for the network stack
+    /// * `runtime` - Runtime to run downloads on
+    pub fn new(
+        config: NetworkConfig,
+        sender: Box<dyn MessageSender>,
+        runtime: Arc<Runtime>,
+    ) -> Self {
+        Self {
+            config,
+            sender: Mutex::new(Some(sender)),
+            network: OnceLock::new(),
+            runtime,
+        }
+    }
+
+    /// The network stack, building it on first use
+    fn network(&self) -> Result<&NetworkStack> {
+        if let Some(network) = self.network.get() {
+            return Ok(network);
+        }
+        let sender = self
+            .sender
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| Error::FilterLoadError("Network stack unavailable".to_string()))?;
+        let mut network = NetworkStack::new(self.config.clone(), sender)
+            .map_err(|e| Error::FilterLoadError(e.to_string()))?;
+        network
+            .initialize()
+            .map_err(|e| Error::FilterLoadError(e.to_string()))?;
+        Ok(self.network.get_or_init(|| network))
+    }
+}
+
+impl ListFetcher for NetworkListFetcher {
+    fn fetch(&self, url: &str) -> Result<String> {
+        match self.fetch_if_modified(url, &ListValidators::default())? {
+            ListDownload::Modified { content, .. } => Ok(content),
+            ListDownload::NotModified => Err(Error::FilterLoadError(format!(
+                "{}: 304 Not Modified to an unconditional request",
+                url
+            ))),
+        }
+    }
+
+    fn fetch_if_modified(&self, url: &str, validators: &ListValidators) -> Result<ListDownload> {
+        let parsed = Url::parse(url)
+            .map_err(|e| Error::FilterLoadError(format!("Invalid list URL '{}': {}", url, e)))?;
+        let mut options = FetchOptions::new(HttpMethod::GET);
+        if let Some(etag) = &validators.etag {
+            options = options.with_header("If-None-Match", etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            options = options.with_header("If-Modified-Since", last_modified);
+        }
+        let network = self.network()?;
+        let response = self
+            .runtime
+            .block_on(network.fetch_with_options(parsed, options))
+            .map_err(|e| Error::FilterLoadError(format!("{}: {}", url, e)))?;
+        if response.status == 304 {
+            return Ok(ListDownload::NotModified);
+        }
+        if !response.is_success() {
+            return Err(Error::FilterLoadError(format!(
+                "{}: HTTP error: {}",
+                url, response.status
+            )));
+        }
+        let validators = ListValidators {
+            etag: response.header("etag").map(str::to_string),
+            last_modified: response.header("last-modified").map(str::to_string),
+        };
+        let content = String::from_utf8(response.body)
+            .map_err(|_| Error::FilterLoadError(format!("{}: list is not UTF-8", url)))?;
+        Ok(ListDownload::Modified {
+            content,
+            validators,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config_manager::Config;
+    use message_bus::MessageBus;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serve one response to every connection
+    fn test_server(response: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}/", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { return };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        base
+    }
+
+    fn fetcher() -> (MessageBus, NetworkListFetcher) {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let fetcher = NetworkListFetcher::new(
+            Config::default().network_config(),
+            bus.sender(),
+            Arc::new(Runtime::new().unwrap()),
+        );
+        (bus, fetcher)
+    }
+
+    // ========================================
+    // Tests for list downloads
+    // ========================================
+
+    #[test]
+    fn test_fetch_downloads_list() {
+        let base = test_server(
+            "HTTP/1.1 200 OK\r\nContent-Length: 34\r\nConnection: close\r\n\r\n! Title: Test list\n||ads.example^\n",
+        );
+        let (_bus, fetcher) = fetcher();
+
+        let list = fetcher.fetch(&format!("{}list.txt", base)).unwrap();
+
+        assert_eq!(list, "! Title: Test list\n||ads.example^\n");
+    }
+
+    #[test]
+    fn test_fetch_reports_http_errors() {
+        let base =
+            test_server("HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+        let (_bus, fetcher) = fetcher();
+
+        assert!(matches!(
+            fetcher.fetch(&format!("{}missing.txt", base)),
+            Err(Error::FilterLoadError(_))
+        ));
+        assert!(fetcher.fetch("not a url").is_err());
+    }
+
+    #[test]
+    fn test_fetch_if_modified_sends_validators() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/list.txt", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { return };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut conditional = false;
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    conditional |= line.eq_ignore_ascii_case("if-none-match: \"v1\"\r\n");
+                    line.clear();
+                }
+                let response = if conditional {
+                    "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n"
+                } else {
+                    "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nLast-Modified: Wed, 16 Oct 2024 05:12:00 GMT\r\n\
+                     Content-Length: 15\r\nConnection: close\r\n\r\n||ads.example^\n"
+                };
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        let (_bus, fetcher) = fetcher();
+
+        let validators = match fetcher
+            .fetch_if_modified(&url, &ListValidators::default())
+            .unwrap()
+        {
+            ListDownload::Modified {
+                content,
+                validators,
+            } => {
+                assert_eq!(content, "||ads.example^\n");
+                validators
+            }
+            ListDownload::NotModified => panic!("unconditional request answered 304"),
+        };
+        assert_eq!(validators.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(
+            validators.last_modified.as_deref(),
+            Some("Wed, 16 Oct 2024 05:12:00 GMT")
+        );
+
+        assert_eq!(
+            fetcher.fetch_if_modified(&url, &validators).unwrap(),
+            ListDownload::NotModified
+        );
+    }
+}
@@ -2,25 +2,97 @@
 //!
 //! This is the binary entry point for the FrankenBrowser application.
 //! It initializes logging, loads configuration, and runs the browser.
+//! `frankenbrowser config ...` runs the config subcommands instead,
+//! `frankenbrowser fetch ...` fetches a single URL (optionally saving a HAR
+//! file), `frankenbrowser console` drives a windowless browser from the
+//! terminal, `frankenbrowser profile repair` recovers damaged profile
+//! databases, and
+//! `--startup-profile` prints how long each startup phase took.
 
-use cli_app::{BrowserApp, Result};
+use cli_app::startup::{self, StartupTimeline};
+use cli_app::{config_cmd, console, fetch_cmd, profile_cmd, BrowserApp, Result};
 use config_manager::Config;
+use shared_types::{PublicSuffixList, PUBLIC_SUFFIX_LIST_FILE};
 
 fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("config") {
+        let code = config_cmd::run(&args[1..], &mut std::io::stdout(), &mut std::io::stderr());
+        std::process::exit(code);
+    }
+    if args.first().map(String::as_str) == Some("fetch") {
+        let code = fetch_cmd::run(&args[1..], &mut std::io::stdout(), &mut std::io::stderr());
+        std::process::exit(code);
+    }
+    if args.first().map(String::as_str) == Some("console") {
+        let code = console::run(&args[1..], &mut std::io::stdout(), &mut std::io::stderr());
+        std::process::exit(code);
+    }
+    if args.first().map(String::as_str) == Some("profile") {
+        let code = profile_cmd::run(&args[1..], &mut std::io::stdout(), &mut std::io::stderr());
+        std::process::exit(code);
+    }
+    let startup_profile = args.iter().any(|arg| arg == "--startup-profile");
+
     // Initialize logging
     tracing_subscriber::fmt::init();
 
     // Load configuration
-    let config = Config::load_or_default()?;
+    let timeline = StartupTimeline::new();
+    let mut config = timeline.time(startup::CONFIG_LOAD, None, Config::load_or_default)?;
+    if config.adblock.filter_list_dir.is_empty() {
+        config.adblock.filter_list_dir = Config::default_filter_list_dir()
+            .to_string_lossy()
+            .into_owned();
+    }
+    if config.browser.session_dir.is_empty() {
+        config.browser.session_dir = Config::default_session_dir().to_string_lossy().into_owned();
+    }
+    if config.network.download_dir.is_empty() {
+        config.network.download_dir = Config::default_download_dir()
+            .to_string_lossy()
+            .into_owned();
+    }
+    if config.browser.profile_dir.is_empty() {
+        config.browser.profile_dir = Config::default_profile_dir().to_string_lossy().into_owned();
+    }
+
+    // A newer Public Suffix List in the profile replaces the built-in one
+    let psl_path = std::path::Path::new(&config.browser.profile_dir).join(PUBLIC_SUFFIX_LIST_FILE);
+    if psl_path.exists() {
+        match PublicSuffixList::load(&psl_path) {
+            Ok(list) => {
+                tracing::info!("Using {} ({} rules)", psl_path.display(), list.len());
+                list.install();
+            }
+            Err(e) => tracing::warn!("Ignoring {}: {}", psl_path.display(), e),
+        }
+    }
+
+    for warning in config.font_warnings() {
+        tracing::warn!("{}", warning);
+    }
 
     tracing::info!("Starting FrankenBrowser...");
     tracing::debug!("Configuration loaded: {:?}", config);
 
-    // Create and run browser application
-    let app = BrowserApp::new(config)?;
+    // Create the browser application and load the homepage
+    let mut app = BrowserApp::with_timeline(config, timeline)?;
+    app.set_config_file(&Config::default_path());
+    app.start()?;
 
     tracing::info!("Browser application initialized, starting...");
 
+    if startup_profile {
+        let timeline = app.startup_timeline();
+        print!("{}", timeline.render_tree());
+        let path = startup::startup_profile_path();
+        match timeline.write_json(&path) {
+            Ok(()) => println!("Startup profile written to {}", path.display()),
+            Err(e) => eprintln!("Failed to write {}: {}", path.display(), e),
+        }
+    }
+
     app.run()?;
 
     tracing::info!("Browser application shutdown complete");
@@ -0,0 +1,170 @@
+//! `frankenbrowser profile` subcommands
+//!
+//! - `profile repair` - check the history and bookmarks databases and
+//!   recover any that are damaged, as the browser does at startup: the
+//!   damaged file is kept as `<name>.db.corrupt-<time>` and the readable
+//!   rows are copied into a fresh database
+//!
+//! The profile directory is `browser.profile_dir` from the config (see
+//! `--config <file>`), else the default; `--profile <dir>` overrides both.
+//! Run it while the browser is closed: a database the browser holds is
+//! reported as locked and left alone.
+
+use browser_core::{open_or_recover, DbOptions};
+use config_manager::Config;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Exit code for success, including databases that were repaired
+pub const EXIT_OK: i32 = 0;
+/// Exit code when a database could not be checked or repaired
+pub const EXIT_FAILED: i32 = 1;
+/// Exit code for usage errors
+pub const EXIT_USAGE: i32 = 2;
+
+/// Databases in the profile directory, as `<name>.db`
+const PROFILE_DATABASES: &[&str] = &["history", "bookmarks"];
+
+const USAGE: &str = "usage: frankenbrowser profile [--config <file>] [--profile <dir>] repair";
+
+/// Run a `profile` subcommand
+///
+/// # Arguments
+///
+/// * `args` - Arguments following `profile`
+/// * `out` - Stream for regular output
+/// * `err` - Stream for diagnostics
+///
+/// # Returns
+///
+/// Returns the process exit code.
+pub fn run(args: &[String], out: &mut dyn Write, err: &mut dyn Write) -> i32 {
+    let mut config_path: Option<PathBuf> = None;
+    let mut profile_dir: Option<PathBuf> = None;
+    let mut rest: Vec<&str> = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--config" => match iter.next() {
+                Some(path) => config_path = Some(PathBuf::from(path)),
+                None => return usage(err),
+            },
+            "--profile" => match iter.next() {
+                Some(dir) => profile_dir = Some(PathBuf::from(dir)),
+                None => return usage(err),
+            },
+            _ => rest.push(arg),
+        }
+    }
+    if rest.as_slice() != ["repair"] {
+        return usage(err);
+    }
+
+    let path = config_path.unwrap_or_else(Config::default_path);
+    let config = if path.exists() {
+        match Config::load_from_file(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                let _ = writeln!(err, "{}: {}", path.display(), e);
+                return EXIT_FAILED;
+            }
+        }
+    } else {
+        Config::default()
+    };
+    let dir = profile_dir.unwrap_or_else(|| {
+        if config.browser.profile_dir.is_empty() {
+            Config::default_profile_dir()
+        } else {
+            PathBuf::from(&config.browser.profile_dir)
+        }
+    });
+
+    let options = DbOptions::from_config(&config);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let mut code = EXIT_OK;
+    for name in PROFILE_DATABASES {
+        let db_path = dir.join(format!("{}.db", name));
+        if !db_path.exists() {
+            let _ = writeln!(out, "{}: not found", name);
+            continue;
+        }
+        match open_or_recover(name, &db_path, &options, now) {
+            Ok((_, None)) => {
+                let _ = writeln!(out, "{}: ok", name);
+            }
+            Ok((_, Some(report))) => {
+                let _ = write!(out, "{}", report);
+            }
+            Err(e) => {
+                let _ = writeln!(err, "{}: {}", db_path.display(), e);
+                code = EXIT_FAILED;
+            }
+        }
+    }
+    code
+}
+
+fn usage(err: &mut dyn Write) -> i32 {
+    let _ = writeln!(err, "{}", USAGE);
+    EXIT_USAGE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_with(dir: &std::path::Path, args: &[&str]) -> (i32, String, String) {
+        let mut full = vec![
+            "--config".to_string(),
+            dir.join("missing.toml").display().to_string(),
+            "--profile".to_string(),
+            dir.display().to_string(),
+        ];
+        full.extend(args.iter().map(|a| a.to_string()));
+        let mut out = Vec::new();
+        let mut err = Vec::new();
+        let code = run(&full, &mut out, &mut err);
+        (
+            code,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_repair_moves_damaged_database_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("bookmarks.db"), vec![0xAB; 4096]).unwrap();
+
+        let (code, out, _) = run_with(dir.path(), &["repair"]);
+        assert_eq!(code, EXIT_OK);
+        assert!(out.starts_with("history: not found\nbookmarks: damaged ("));
+        assert!(out.contains("  moved to "));
+        assert!(out.contains("nothing could be read; started empty"));
+        assert!(std::fs::read_dir(dir.path()).unwrap().any(|entry| {
+            let name = entry.unwrap().file_name();
+            name.to_string_lossy().starts_with("bookmarks.db.corrupt-")
+        }));
+
+        // The rebuilt database is healthy
+        let (code, out, _) = run_with(dir.path(), &["repair"]);
+        assert_eq!(
+            (code, out.as_str()),
+            (EXIT_OK, "history: not found\nbookmarks: ok\n")
+        );
+    }
+
+    #[test]
+    fn test_usage() {
+        let dir = tempfile::tempdir().unwrap();
+        let (code, _, err) = run_with(dir.path(), &["fix"]);
+        assert_eq!(code, EXIT_USAGE);
+        assert!(err.starts_with("usage: frankenbrowser profile"));
+    }
+}
@@ -0,0 +1,443 @@
+//! Startup instrumentation
+//!
+//! A [`StartupTimeline`] records named spans while the application is
+//! constructed and started: config load, each component's construction,
+//! and the first tab and navigation. `frankenbrowser --startup-profile`
+//! prints it as a tree and writes it as JSON next to the config file. When
+//! [`TEST_DB_ENV`] names a test result database, the totals are recorded
+//! there as metrics so regressions show up on the dashboard.
+
+use browser_core::TestResultDatabase;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Loading the configuration file
+pub const CONFIG_LOAD: &str = "config_load";
+/// Everything done by `BrowserApp::new`
+pub const APP_CONSTRUCTION: &str = "app_construction";
+/// Tokio runtime and message bus start
+pub const BUS_START: &str = "bus_start";
+/// Loading the built-in extensions
+pub const EXTENSIONS_LOAD: &str = "extensions_load";
+/// Network stack construction
+pub const NETWORK_INIT: &str = "network_init";
+/// Ad blocker construction and filter list compilation
+pub const ADBLOCK_INIT: &str = "adblock_init";
+/// Browser engine construction (history and bookmark databases)
+pub const ENGINE_INIT: &str = "engine_init";
+/// Browser shell construction (window and menus)
+pub const SHELL_INIT: &str = "shell_init";
+/// First tab ready to display content
+pub const FIRST_WEBVIEW_READY: &str = "first_webview_ready";
+/// First navigation (to the homepage) committed
+pub const FIRST_NAVIGATION_COMMITTED: &str = "first_navigation_committed";
+
+/// Phases recorded by `BrowserApp::new` and `BrowserApp::start`
+pub const APP_PHASES: &[&str] = &[
+    APP_CONSTRUCTION,
+    BUS_START,
+    EXTENSIONS_LOAD,
+    NETWORK_INIT,
+    ADBLOCK_INIT,
+    ENGINE_INIT,
+    SHELL_INIT,
+    FIRST_WEBVIEW_READY,
+    FIRST_NAVIGATION_COMMITTED,
+];
+
+/// Environment variable naming the test result database for startup metrics
+pub const TEST_DB_ENV: &str = "FRANKENBROWSER_TEST_DB";
+
+/// File name of the JSON profile written by `--startup-profile`
+pub const STARTUP_PROFILE_FILE: &str = "startup-profile.json";
+
+/// Version of the JSON profile layout
+pub const PROFILE_SCHEMA_VERSION: u32 = 1;
+
+/// One timed phase
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StartupSpan {
+    /// Phase name
+    pub name: String,
+    /// Enclosing phase, if any
+    pub parent: Option<String>,
+    /// Start, in milliseconds since the timeline was created
+    pub start_ms: f64,
+    /// Duration in milliseconds
+    pub duration_ms: f64,
+}
+
+impl StartupSpan {
+    /// End, in milliseconds since the timeline was created
+    pub fn end_ms(&self) -> f64 {
+        self.start_ms + self.duration_ms
+    }
+
+    fn overlaps(&self, other: &StartupSpan) -> bool {
+        self.start_ms < other.end_ms() && other.start_ms < self.end_ms()
+    }
+}
+
+/// Serialized form of a timeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StartupProfile {
+    /// Layout version ([`PROFILE_SCHEMA_VERSION`])
+    pub schema_version: u32,
+    /// Time from timeline creation to the end of the last phase
+    pub total_ms: f64,
+    /// Phases in start order
+    pub spans: Vec<StartupSpan>,
+}
+
+/// Named startup phases with their durations and ordering
+pub struct StartupTimeline {
+    /// Time zero of the timeline
+    origin: Instant,
+    /// Recorded phases
+    spans: Mutex<Vec<StartupSpan>>,
+    /// Artificial delays added to phases (for testing the instrumentation)
+    delays: HashMap<String, Duration>,
+}
+
+impl StartupTimeline {
+    /// Start a timeline now
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            spans: Mutex::new(Vec::new()),
+            delays: HashMap::new(),
+        }
+    }
+
+    /// Make a phase artificially slow
+    ///
+    /// Used to check that a slow phase is attributed correctly.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - Phase name
+    /// * `delay` - Time to sleep inside the phase
+    pub fn inject_delay(&mut self, phase: &str, delay: Duration) {
+        self.delays.insert(phase.to_string(), delay);
+    }
+
+    /// Run `f` as a named phase
+    ///
+    /// Safe to call from several threads at once; phases that ran in
+    /// parallel show up with overlapping times.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Phase name
+    /// * `parent` - Enclosing phase, if any
+    /// * `f` - The work to time
+    ///
+    /// # Returns
+    ///
+    /// Returns what `f` returned.
+    pub fn time<T>(&self, name: &str, parent: Option<&str>, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        if let Some(delay) = self.delays.get(name) {
+            std::thread::sleep(*delay);
+        }
+        let result = f();
+        let duration = start.elapsed();
+
+        self.spans.lock().unwrap().push(StartupSpan {
+            name: name.to_string(),
+            parent: parent.map(str::to_string),
+            start_ms: millis(start.duration_since(self.origin)),
+            duration_ms: millis(duration),
+        });
+        result
+    }
+
+    /// Recorded phases in start order
+    pub fn spans(&self) -> Vec<StartupSpan> {
+        let mut spans = self.spans.lock().unwrap().clone();
+        spans.sort_by(|a, b| a.start_ms.total_cmp(&b.start_ms));
+        spans
+    }
+
+    /// Look up a recorded phase by name
+    pub fn span(&self, name: &str) -> Option<StartupSpan> {
+        self.spans().into_iter().find(|span| span.name == name)
+    }
+
+    /// Time from timeline creation to the end of the last phase, in milliseconds
+    pub fn total_ms(&self) -> f64 {
+        self.spans()
+            .iter()
+            .map(StartupSpan::end_ms)
+            .fold(0.0, f64::max)
+    }
+
+    /// The timeline in its serialized form
+    pub fn profile(&self) -> StartupProfile {
+        StartupProfile {
+            schema_version: PROFILE_SCHEMA_VERSION,
+            total_ms: self.total_ms(),
+            spans: self.spans(),
+        }
+    }
+
+    /// The timeline as pretty-printed JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.profile()).unwrap_or_default()
+    }
+
+    /// Write the JSON profile, creating the parent directory if needed
+    ///
+    /// # Errors
+    ///
+    /// Returns an I/O error if the file cannot be written.
+    pub fn write_json(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_json())
+    }
+
+    /// Render the timeline as an indented tree
+    ///
+    /// Each line shows the phase's duration and its share of the total.
+    /// Phases that overlapped a sibling are marked `[parallel]`.
+    pub fn render_tree(&self) -> String {
+        let spans = self.spans();
+        let total = self.total_ms();
+        let mut out = format!("Startup profile: {:.1} ms total\n", total);
+        render_children(&spans, None, 0, total, &mut out);
+        out
+    }
+
+    /// Record the totals as metrics
+    ///
+    /// Writes `startup_time_ms` and `startup_<phase>_ms` for every phase.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a metric cannot be written.
+    pub fn record_metrics(&self, db: &TestResultDatabase) -> browser_core::Result<()> {
+        db.record_metric("startup_time_ms", self.total_ms(), "ms", Some("startup"))?;
+        for span in self.spans() {
+            db.record_metric(
+                &format!("startup_{}_ms", span.name),
+                span.duration_ms,
+                "ms",
+                Some("startup"),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for StartupTimeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where `--startup-profile` writes the JSON profile: next to the config file
+pub fn startup_profile_path() -> PathBuf {
+    config_manager::Config::default_path().with_file_name(STARTUP_PROFILE_FILE)
+}
+
+fn render_children(
+    spans: &[StartupSpan],
+    parent: Option<&str>,
+    depth: usize,
+    total: f64,
+    out: &mut String,
+) {
+    let siblings: Vec<&StartupSpan> = spans
+        .iter()
+        .filter(|span| span.parent.as_deref() == parent)
+        .collect();
+    for span in &siblings {
+        let parallel = siblings
+            .iter()
+            .any(|other| other.name != span.name && other.overlaps(span));
+        let percent = if total > 0.0 {
+            span.duration_ms / total * 100.0
+        } else {
+            0.0
+        };
+        out.push_str(&format!(
+            "{:<40} {:>9.1} ms {:>5.1}%{}\n",
+            format!("{}{}", "  ".repeat(depth), span.name),
+            span.duration_ms,
+            percent,
+            if parallel { "  [parallel]" } else { "" }
+        ));
+        render_children(spans, Some(&span.name), depth + 1, total, out);
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BrowserApp;
+    use config_manager::Config;
+
+    fn started_app(timeline: StartupTimeline) -> BrowserApp {
+        let mut app = BrowserApp::with_timeline(Config::default(), timeline).unwrap();
+        app.start().unwrap();
+        app
+    }
+
+    // ========================================
+    // Tests for StartupTimeline
+    // ========================================
+
+    #[test]
+    fn test_timeline_records_every_phase() {
+        let app = started_app(StartupTimeline::new());
+        let timeline = app.startup_timeline();
+
+        for phase in APP_PHASES {
+            assert!(timeline.span(phase).is_some(), "missing phase {}", phase);
+        }
+        for phase in [
+            BUS_START,
+            NETWORK_INIT,
+            ADBLOCK_INIT,
+            ENGINE_INIT,
+            SHELL_INIT,
+        ] {
+            assert_eq!(
+                timeline.span(phase).unwrap().parent.as_deref(),
+                Some(APP_CONSTRUCTION)
+            );
+        }
+        let bus = timeline.span(BUS_START).unwrap();
+        let engine = timeline.span(ENGINE_INIT).unwrap();
+        let navigation = timeline.span(FIRST_NAVIGATION_COMMITTED).unwrap();
+        assert!(bus.end_ms() <= engine.start_ms);
+        assert!(engine.end_ms() <= navigation.start_ms);
+    }
+
+    #[test]
+    fn test_network_and_adblock_init_run_in_parallel() {
+        let mut timeline = StartupTimeline::new();
+        timeline.inject_delay(NETWORK_INIT, Duration::from_millis(300));
+        timeline.inject_delay(ADBLOCK_INIT, Duration::from_millis(300));
+        let app = started_app(timeline);
+        let timeline = app.startup_timeline();
+
+        let network = timeline.span(NETWORK_INIT).unwrap();
+        let adblock = timeline.span(ADBLOCK_INIT).unwrap();
+        assert!(network.overlaps(&adblock));
+        assert!(timeline.render_tree().contains("[parallel]"));
+    }
+
+    #[test]
+    fn test_injected_slow_phase_shows_in_tree() {
+        let mut timeline = StartupTimeline::new();
+        timeline.inject_delay(ENGINE_INIT, Duration::from_millis(400));
+        let app = started_app(timeline);
+        let timeline = app.startup_timeline();
+
+        let engine = timeline.span(ENGINE_INIT).unwrap();
+        assert!(engine.duration_ms >= 400.0);
+        let slowest_child = timeline
+            .spans()
+            .into_iter()
+            .filter(|span| span.parent.as_deref() == Some(APP_CONSTRUCTION))
+            .filter(|span| span.name != ADBLOCK_INIT)
+            .max_by(|a, b| a.duration_ms.total_cmp(&b.duration_ms))
+            .unwrap();
+        assert_eq!(slowest_child.name, ENGINE_INIT);
+
+        let tree = timeline.render_tree();
+        let line = tree
+            .lines()
+            .find(|line| line.trim_start().starts_with(ENGINE_INIT))
+            .unwrap();
+        assert!(
+            line.starts_with("  engine_init"),
+            "nested under construction"
+        );
+        let percent: f64 = line
+            .split_whitespace()
+            .find(|field| field.ends_with('%'))
+            .unwrap()
+            .trim_end_matches('%')
+            .parse()
+            .unwrap();
+        let expected = engine.duration_ms / timeline.total_ms() * 100.0;
+        assert!((percent - expected).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_profile_json_schema_is_stable() {
+        let timeline = StartupTimeline::new();
+        timeline.time(CONFIG_LOAD, None, || ());
+        timeline.time(BUS_START, Some(APP_CONSTRUCTION), || ());
+
+        let json: serde_json::Value = serde_json::from_str(&timeline.to_json()).unwrap();
+        let mut keys: Vec<_> = json.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["schema_version", "spans", "total_ms"]);
+        assert_eq!(json["schema_version"], 1);
+
+        let span = &json["spans"][1];
+        let mut span_keys: Vec<_> = span.as_object().unwrap().keys().cloned().collect();
+        span_keys.sort();
+        assert_eq!(span_keys, vec!["duration_ms", "name", "parent", "start_ms"]);
+        assert_eq!(span["name"], BUS_START);
+        assert_eq!(span["parent"], APP_CONSTRUCTION);
+        assert!(json["spans"][0]["parent"].is_null());
+
+        // serde_json may parse a float one ulp away from what it wrote
+        let parsed: StartupProfile = serde_json::from_value(json).unwrap();
+        let profile = timeline.profile();
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert_eq!(parsed.schema_version, profile.schema_version);
+        assert!(close(parsed.total_ms, profile.total_ms));
+        assert_eq!(parsed.spans.len(), profile.spans.len());
+        for (parsed, span) in parsed.spans.iter().zip(&profile.spans) {
+            assert_eq!((&parsed.name, &parsed.parent), (&span.name, &span.parent));
+            assert!(close(parsed.start_ms, span.start_ms));
+            assert!(close(parsed.duration_ms, span.duration_ms));
+        }
+    }
+
+    #[test]
+    fn test_record_metrics_writes_total_and_phases() {
+        let timeline = StartupTimeline::new();
+        timeline.time(CONFIG_LOAD, None, || ());
+        timeline.time(ENGINE_INIT, Some(APP_CONSTRUCTION), || ());
+        let db = TestResultDatabase::new(None).unwrap();
+
+        timeline.record_metrics(&db).unwrap();
+
+        let names: Vec<String> = db
+            .get_metrics(None, None)
+            .into_iter()
+            .map(|metric| metric.name)
+            .collect();
+        assert!(names.contains(&"startup_time_ms".to_string()));
+        assert!(names.contains(&"startup_config_load_ms".to_string()));
+        assert!(names.contains(&"startup_engine_init_ms".to_string()));
+    }
+
+    #[test]
+    fn test_write_json_creates_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profile").join(STARTUP_PROFILE_FILE);
+        let timeline = StartupTimeline::new();
+        timeline.time(CONFIG_LOAD, None, || ());
+
+        timeline.write_json(&path).unwrap();
+
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.contains(CONFIG_LOAD));
+    }
+}
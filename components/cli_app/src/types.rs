@@ -1,34 +1,70 @@
 //! Type definitions for the CLI application
 
 use crate::errors::Result;
-use adblock_engine::AdBlockEngine;
-use browser_core::BrowserEngine;
-use browser_shell::BrowserShell;
-use config_manager::Config;
-use message_bus::MessageBus;
-use network_stack::NetworkStack;
-use std::sync::Arc;
+use crate::extensions::{ExtensionManager, ExtensionRequestInterceptor, BUILTIN_EXTENSIONS};
+use crate::list_fetcher::NetworkListFetcher;
+use crate::startup::{self, StartupTimeline};
+use adblock_engine::{
+    AdBlockEngine, FilterListStore, SeedOutcome, SubscriptionSet, SUBSCRIPTIONS_FILE,
+};
+use browser_core::{
+    BrowserEngine, NavigationInitiator, NavigationRequest, TestResultDatabase, SESSION_FILE,
+};
+use browser_shell::{
+    BrowserShell, HibernationStore, Menu, MenuAction, PinnedTabStore, RecoveryOutcome,
+    RecoveryPolicy, SessionSnapshots, SnapshotStore, PINNED_TABS_FILE,
+};
+use config_manager::{AdBlockSettings, Config, ConfigEvent, ContentSettings, Theme, WatchHandle};
+use message_bus::{MessageBus, MessageFilter, DEFAULT_SHUTDOWN_TIMEOUT};
+use network_stack::{
+    ContentSettingsInterceptor, CustomHeaderInterceptor, HarOptions, HttpMethod, NetworkStack,
+};
+use shared_types::{DocumentChunk, MessageKind};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
+use url::Url;
 
 /// Main browser application struct
 ///
 /// This struct wires together all components of the FrankenBrowser
 /// and manages their lifecycle.
 pub struct BrowserApp {
-    /// Tokio async runtime
-    /// Note: Not directly accessed but required for the shell's operation
-    #[allow(dead_code)]
+    /// Tokio async runtime, shared with the shell; runs the engine's
+    /// requests for the window
     runtime: Arc<Runtime>,
     /// Message bus for component communication
     message_bus: MessageBus,
     /// Ad blocking engine
     adblock: AdBlockEngine,
     /// Browser core engine (contains network stack)
-    /// Note: Initialized during construction but not directly accessed in run()
-    #[allow(dead_code)]
     browser_core: BrowserEngine,
     /// Browser shell (UI)
     shell: BrowserShell,
+    /// Built-in extensions, shared with the pipelines that call their hooks
+    extensions: Arc<ExtensionManager>,
+    /// Page loaded into the first tab
+    homepage: String,
+    /// Timing of construction and the first navigation
+    timeline: StartupTimeline,
+    /// Whether `start` has opened the first tab
+    started: bool,
+    /// Session snapshots, handed to the shell by `start`
+    snapshots: Option<SessionSnapshots>,
+    /// What `start` does with the tabs of a crashed run
+    crash_recovery: RecoveryPolicy,
+    /// Where the tabs are saved at shutdown and restored from at startup
+    /// (`browser.restore_session`)
+    session_file: Option<PathBuf>,
+    /// Delete all cookies when the browser closes
+    /// (`privacy.clear_cookies_on_exit`)
+    clear_cookies_on_exit: bool,
+    /// Reloads of the watched configuration file, not yet applied
+    config_events: Option<mpsc::Receiver<ConfigEvent>>,
+    /// Keeps the configuration file watcher running
+    config_watch: Option<WatchHandle>,
 }
 
 impl BrowserApp {
@@ -46,44 +82,693 @@ impl BrowserApp {
     ///
     /// Returns an error if any component fails to initialize.
     pub fn new(config: Config) -> Result<Self> {
-        // Create tokio runtime
-        let runtime = Arc::new(
-            Runtime::new().map_err(|e| anyhow::anyhow!("Failed to create runtime: {}", e))?,
-        );
+        Self::with_timeline(config, StartupTimeline::new())
+    }
+
+    /// Create a new browser application, timing each component
+    ///
+    /// Every step is recorded as a child of
+    /// [`APP_CONSTRUCTION`](startup::APP_CONSTRUCTION). The network stack is
+    /// built on a worker thread while the ad blocker compiles its filter
+    /// lists on this one; the ad blocker is not `Send`, so it stays here.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration for all components
+    /// * `timeline` - Timeline to record into (may already hold the config load)
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result<BrowserApp>` with the initialized application.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any component fails to initialize.
+    pub fn with_timeline(config: Config, timeline: StartupTimeline) -> Result<Self> {
+        let (runtime, message_bus, extensions, adblock, browser_core, shell) =
+            timeline.time(startup::APP_CONSTRUCTION, None, || {
+                Self::build_components(&config, &timeline)
+            })?;
+
+        Ok(Self {
+            runtime,
+            message_bus,
+            adblock,
+            browser_core,
+            shell,
+            extensions,
+            homepage: config.browser.homepage.clone(),
+            timeline,
+            started: false,
+            snapshots: Self::session_snapshots(&config),
+            crash_recovery: RecoveryPolicy::from_name(&config.browser.crash_recovery)
+                .unwrap_or_default(),
+            session_file: Self::session_file(&config),
+            clear_cookies_on_exit: config.privacy.clear_cookies_on_exit,
+            config_events: None,
+            config_watch: None,
+        })
+    }
 
-        // Create message bus
-        let mut message_bus = MessageBus::new();
+    /// Construct the components, recording each as a startup phase
+    #[allow(clippy::type_complexity)]
+    fn build_components(
+        config: &Config,
+        timeline: &StartupTimeline,
+    ) -> Result<(
+        Arc<Runtime>,
+        MessageBus,
+        Arc<ExtensionManager>,
+        AdBlockEngine,
+        BrowserEngine,
+        BrowserShell,
+    )> {
+        let parent = Some(startup::APP_CONSTRUCTION);
 
-        // Start message bus to get senders
-        message_bus.start()?;
+        // Create tokio runtime and start the message bus to get senders
+        let (runtime, message_bus) =
+            timeline.time(startup::BUS_START, parent, || -> Result<_> {
+                let runtime = Arc::new(
+                    Runtime::new()
+                        .map_err(|e| anyhow::anyhow!("Failed to create runtime: {}", e))?,
+                );
+                let mut message_bus = MessageBus::new().with_runtime(runtime.clone());
+                message_bus.enable_tracing(config.browser.message_trace_buffer as usize);
+                message_bus.start()?;
+                Ok((runtime, message_bus))
+            })?;
 
-        // Create network stack with its own sender
-        let network = NetworkStack::new(config.network_config(), message_bus.sender())?;
+        // Load built-in extensions
+        let extensions = timeline.time(startup::EXTENSIONS_LOAD, parent, || {
+            Arc::new(ExtensionManager::from_registry(
+                BUILTIN_EXTENSIONS,
+                &config.extensions,
+            ))
+        });
 
-        // Create adblock engine with its own sender
-        let adblock = AdBlockEngine::new(config.adblock_config(), message_bus.sender())?;
+        // The network stack and the ad blocker don't depend on each other:
+        // build the network stack on a worker while the filter lists compile
+        // here
+        let network_sender = message_bus.sender();
+        let custom_headers = config.custom_headers()?;
+        // Per-site content settings, shared by the network stack's image
+        // blocking and the window's JavaScript and image toggles
+        let content_settings = Arc::new(Mutex::new(Self::load_content_settings(config)));
+        let (network, adblock) = std::thread::scope(|scope| {
+            let network = scope.spawn(|| {
+                timeline.time(startup::NETWORK_INIT, parent, || {
+                    let mut network = NetworkStack::new(config.network_config(), network_sender)?;
+                    network.set_har_options(HarOptions {
+                        embed_bodies: config.network.har_embed_bodies,
+                        include_sensitive_headers: false,
+                    });
+                    network.set_allow_mixed_content(config.security.allow_mixed_content);
+                    network.set_bandwidth_budget(config.network.metered_budget_bytes());
+                    network.set_dns_prefetch(config.network.dns_prefetch);
+                    network.set_max_redirects(config.network.max_redirects as usize);
+                    network.set_partition_cache(config.privacy.partition_cache);
+                    network.set_block_third_party_cookies(config.privacy.block_third_party_cookies);
+                    network.set_do_not_track(config.privacy.do_not_track);
+                    network.set_user_agent(config.network.user_agent().map(str::to_string));
+                    network.set_extra_headers(
+                        config.network.extra_headers.clone().into_iter().collect(),
+                    );
+                    network.add_interceptor(Box::new(ExtensionRequestInterceptor::new(
+                        extensions.clone(),
+                    )));
+                    if !custom_headers.is_empty() {
+                        network.add_interceptor(Box::new(CustomHeaderInterceptor::new(
+                            custom_headers,
+                        )));
+                    }
+                    network.add_interceptor(Box::new(ContentSettingsInterceptor::new(
+                        content_settings.clone(),
+                    )));
+                    network.initialize()?;
+                    Ok::<_, network_stack::Error>(network)
+                })
+            });
+            let adblock = timeline.time(startup::ADBLOCK_INIT, parent, || {
+                let mut adblock =
+                    AdBlockEngine::new(config.adblock_config(), message_bus.sender())?;
+                adblock.set_filter_store(Self::open_filter_store(&config.adblock)?);
+                adblock.set_subscriptions(Self::open_subscriptions(&config.adblock)?);
+                adblock.set_list_fetcher(Box::new(NetworkListFetcher::new(
+                    config.network_config(),
+                    message_bus.sender(),
+                    runtime.clone(),
+                )));
+                adblock.initialize()?;
+                // The cached lists are in use already; updates land later
+                if config.adblock.update_filters_on_startup {
+                    if let Err(e) = adblock.refresh_in_background(false) {
+                        tracing::warn!("Failed to start updating filter lists: {}", e);
+                    }
+                }
+                Ok::<_, adblock_engine::Error>(adblock)
+            });
+            let network = network
+                .join()
+                .map_err(|_| anyhow::anyhow!("Network stack initialization panicked"));
+            (network, adblock)
+        });
+        let network = network??;
+        let adblock = adblock?;
+        message_bus.register_handler_filtered(
+            Box::new(adblock.clone()),
+            MessageFilter::kind(MessageKind::Adblock),
+        );
 
         // Create browser core engine
-        // Note: BrowserEngine takes NetworkStack by value, so we create it here
-        let browser_core = BrowserEngine::new(config.clone(), network, message_bus.sender())?;
+        // Note: BrowserEngine takes NetworkStack by value
+        let browser_core = timeline.time(startup::ENGINE_INIT, parent, || -> Result<_> {
+            let mut browser_core =
+                BrowserEngine::new(config.clone(), network, message_bus.sender())?;
+            browser_core.set_extension_hooks(extensions.clone());
+            browser_core.set_trace_log(message_bus.trace_log());
+            browser_core.set_filter_lists(adblock.filter_lists());
+            browser_core.set_adblock_subscriptions(adblock.list_subscriptions());
+            message_bus.register_handler(Box::new(browser_core.history_search_handler()));
+            message_bus.register_handler(Box::new(browser_core.tab_close_handler()));
+            message_bus
+                .register_async_handler(Box::new(browser_core.network().tab_suspend_handler()))?;
+            Ok(browser_core)
+        })?;
 
         // Create browser shell with its own sender
-        let shell =
-            BrowserShell::new(config.shell_config(), message_bus.sender(), runtime.clone())?;
+        let shell = timeline.time(startup::SHELL_INIT, parent, || -> Result<_> {
+            let mut shell =
+                BrowserShell::new(config.shell_config(), message_bus.sender(), runtime.clone())?;
+            Self::add_extensions_menu(&mut shell, &extensions);
+            shell.set_tab_histories(browser_core.tab_histories());
+            shell.set_duplicate_in_background(config.browser.duplicate_tab_in_background);
+            shell.set_confirm_close_pinned(config.browser.confirm_close_pinned_tabs);
+            shell.set_content_settings(
+                content_settings.clone(),
+                Some(ContentSettings::default_path()),
+            );
+            if let Err(e) = shell.set_accessibility(&config.accessibility) {
+                tracing::warn!("Accessibility stylesheet not applied: {}", e);
+            }
+            shell.set_fonts(&config.fonts, &config.content.default_encoding);
+            if !config.browser.session_dir.is_empty() {
+                shell.set_pinned_store(PinnedTabStore::new(
+                    Path::new(&config.browser.session_dir).join(PINNED_TABS_FILE),
+                ));
+            }
+            if !config.browser.hibernation_dir.is_empty() {
+                shell.set_hibernation_store(HibernationStore::new(&config.browser.hibernation_dir));
+                shell.set_hibernate_after(config.browser.hibernate_after());
+            }
+            Ok(shell)
+        })?;
 
-        Ok(Self {
+        Ok((
             runtime,
             message_bus,
+            extensions,
             adblock,
             browser_core,
             shell,
-        })
+        ))
+    }
+
+    /// Session snapshots in the configured directory
+    ///
+    /// An empty `session_dir` turns snapshots, and with them crash
+    /// recovery, off.
+    fn session_snapshots(config: &Config) -> Option<SessionSnapshots> {
+        let settings = &config.browser;
+        if settings.session_dir.is_empty() {
+            return None;
+        }
+        Some(SessionSnapshots::new(
+            SnapshotStore::new(&settings.session_dir),
+            Duration::from_secs(u64::from(settings.session_snapshot_secs)),
+        ))
+    }
+
+    /// The session file, if `browser.restore_session` is on
+    ///
+    /// The session is kept in `session_dir`; without one it is not saved.
+    fn session_file(config: &Config) -> Option<PathBuf> {
+        let settings = &config.browser;
+        if !settings.restore_session || settings.session_dir.is_empty() {
+            return None;
+        }
+        Some(Path::new(&settings.session_dir).join(SESSION_FILE))
+    }
+
+    /// Open the cached filter lists, seeding them on first run
+    ///
+    /// An empty `filter_list_dir` keeps the lists in memory, so the seed
+    /// still protects this session but nothing is written to disk.
+    fn open_filter_store(settings: &AdBlockSettings) -> adblock_engine::Result<FilterListStore> {
+        let mut store = if settings.filter_list_dir.is_empty() {
+            FilterListStore::in_memory()
+        } else {
+            FilterListStore::open(Path::new(&settings.filter_list_dir))?
+        };
+        if settings.seed_filters {
+            if let SeedOutcome::Seeded { rules } = store.seed()? {
+                tracing::info!("Seeded filter lists with {} embedded rules", rules);
+            }
+        }
+        Ok(store)
+    }
+
+    /// The `[content]` defaults with the saved per-site overrides
+    ///
+    /// An unreadable override file is logged and the defaults apply to
+    /// every site.
+    fn load_content_settings(config: &Config) -> ContentSettings {
+        let mut settings = config.content_settings();
+        if let Err(e) = settings.load_overrides(&ContentSettings::default_path()) {
+            tracing::warn!("Ignoring per-site content settings: {}", e);
+        }
+        settings
+    }
+
+    /// Open the filter list subscriptions, subscribing a new profile to
+    /// the configured defaults
+    ///
+    /// An empty `filter_list_dir` keeps the subscriptions in memory.
+    fn open_subscriptions(settings: &AdBlockSettings) -> adblock_engine::Result<SubscriptionSet> {
+        if settings.filter_list_dir.is_empty() {
+            let mut subscriptions = SubscriptionSet::in_memory();
+            subscriptions.register_defaults(&settings.default_subscriptions)?;
+            return Ok(subscriptions);
+        }
+        SubscriptionSet::open(
+            &Path::new(&settings.filter_list_dir).join(SUBSCRIPTIONS_FILE),
+            &settings.default_subscriptions,
+        )
+    }
+
+    /// Recover the previous session, or open the first tab and load the
+    /// homepage
+    ///
+    /// Pinned tabs are reopened first, whatever happens next, so they lead
+    /// the tab strip. If the last run crashed, its tabs are restored or offered on the
+    /// recovery page according to `browser.crash_recovery`, and the
+    /// homepage is skipped. Otherwise the tabs saved at the last shutdown
+    /// are reopened when `browser.restore_session` is on, again skipping
+    /// the homepage. Failing that, opening the first tab and loading the
+    /// homepage are recorded as the
+    /// [`FIRST_WEBVIEW_READY`](startup::FIRST_WEBVIEW_READY)
+    /// and [`FIRST_NAVIGATION_COMMITTED`](startup::FIRST_NAVIGATION_COMMITTED)
+    /// phases. A homepage that fails to load is logged, not fatal. When
+    /// [`TEST_DB_ENV`](startup::TEST_DB_ENV) is set, the startup times are
+    /// recorded in that test result database. Calling it again does nothing.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the first tab cannot be created.
+    pub fn start(&mut self) -> Result<()> {
+        if self.started {
+            return Ok(());
+        }
+        self.started = true;
+
+        self.restore_pinned_tabs();
+        let recovered = self.start_session_snapshots();
+        // Only tabs restored above (or offered for recovery) can still
+        // refer to hibernated tab files
+        let removed = self.shell.collect_hibernation_garbage();
+        if removed > 0 {
+            tracing::info!("Removed {} stale hibernated tab files", removed);
+        }
+        self.show_profile_recovery();
+        if recovered || self.restore_saved_session() {
+            return Ok(());
+        }
+
+        let tab_id = self.timeline.time(startup::FIRST_WEBVIEW_READY, None, || {
+            self.shell.create_tab()
+        })?;
+
+        let navigation = self.timeline.time(
+            startup::FIRST_NAVIGATION_COMMITTED,
+            None,
+            || -> Result<()> {
+                let url = Url::parse(&self.homepage)
+                    .map_err(|e| anyhow::anyhow!("Invalid homepage '{}': {}", self.homepage, e))?;
+                self.browser_core
+                    .navigate(tab_id, url, NavigationInitiator::UserTyped)?;
+                Ok(())
+            },
+        );
+        if let Err(e) = navigation {
+            tracing::warn!("Failed to load homepage: {}", e);
+        }
+
+        if let Ok(path) = std::env::var(startup::TEST_DB_ENV) {
+            let options = self.browser_core.db_options();
+            let recorded =
+                TestResultDatabase::with_options(Some(path.as_str()), options).and_then(|db| {
+                    self.timeline.record_metrics(&db)?;
+                    db.close()
+                });
+            if let Err(e) = recorded {
+                tracing::warn!("Failed to record startup metrics in {}: {}", path, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Open `about:recovery` in its own tab if a profile database was
+    /// recovered at startup
+    ///
+    /// Shown once: the next start finds the rebuilt database healthy.
+    /// Failures are logged, not fatal.
+    fn show_profile_recovery(&mut self) {
+        if self.browser_core.recovery_reports().is_empty() {
+            return;
+        }
+        let mut open = || -> Result<()> {
+            let tab_id = self.shell.create_tab()?;
+            let url = Url::parse("about:recovery").expect("valid internal URL");
+            self.browser_core
+                .navigate(tab_id, url, NavigationInitiator::UserTyped)?;
+            Ok(())
+        };
+        if let Err(e) = open() {
+            tracing::warn!("Failed to open about:recovery: {}", e);
+        }
+    }
+
+    /// Hand the session snapshots to the shell
+    ///
+    /// Snapshot errors are logged, not fatal.
+    ///
+    /// # Returns
+    ///
+    /// Whether tabs from a crashed run were opened.
+    fn start_session_snapshots(&mut self) -> bool {
+        let Some(snapshots) = self.snapshots.take() else {
+            return false;
+        };
+        match self
+            .shell
+            .start_session_snapshots(snapshots, self.crash_recovery)
+        {
+            Ok(RecoveryOutcome::Restored(tabs)) => {
+                tracing::info!("Restored {} tabs after a crash", tabs.len());
+                !tabs.is_empty()
+            }
+            Ok(RecoveryOutcome::Prompted(_)) => true,
+            Ok(RecoveryOutcome::NotNeeded | RecoveryOutcome::Discarded) => false,
+            Err(e) => {
+                tracing::warn!("Failed to start session snapshots: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Reopen the tabs saved by the last clean shutdown
+    ///
+    /// Each saved tab is reopened pending in the shell with its
+    /// back/forward stack, and the first one is activated, which loads its
+    /// current entry without adding a stack entry. A missing session file
+    /// leaves nothing to restore; a corrupt one, or one from another
+    /// version, is logged and ignored so the browser starts fresh.
+    ///
+    /// # Returns
+    ///
+    /// Whether any tab was reopened.
+    fn restore_saved_session(&mut self) -> bool {
+        let Some(path) = self.session_file.clone().filter(|path| path.exists()) else {
+            return false;
+        };
+        let tabs = match self.browser_core.restore_session(&path) {
+            Ok(tabs) => tabs,
+            Err(e) => {
+                tracing::warn!("Starting without the saved session: {}", e);
+                return false;
+            }
+        };
+
+        // The shell numbers its tabs itself, so every stack is taken out
+        // before any is put back under its new tab ID
+        let histories = self.browser_core.tab_histories();
+        let saved: Vec<_> = tabs
+            .into_iter()
+            .map(|(tab_id, url)| {
+                let title = self
+                    .browser_core
+                    .page_title(tab_id)
+                    .unwrap_or_else(|| url.to_string());
+                (url, title, histories.take(tab_id))
+            })
+            .collect();
+        let mut restored = Vec::with_capacity(saved.len());
+        for (url, title, history) in saved {
+            match self.shell.restore_tab(url.to_string(), title, None) {
+                Ok(tab_id) => {
+                    if let Some(history) = history {
+                        histories.restore(tab_id, &history);
+                    }
+                    restored.push(tab_id);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to reopen {}: {}", url, e);
+                    break;
+                }
+            }
+        }
+
+        if let Some(&first) = restored.first() {
+            if let Err(e) = self.shell.switch_to_tab(first) {
+                tracing::warn!("Failed to load restored tab: {}", e);
+            }
+        }
+        tracing::info!("Restored {} tabs from the last session", restored.len());
+        !restored.is_empty()
+    }
+
+    /// Reopen the pinned tabs and keep the engine from navigating them off
+    /// their sites
+    ///
+    /// A pinned tab file that cannot be read is logged, not fatal.
+    fn restore_pinned_tabs(&mut self) {
+        match self.shell.restore_pinned_tabs() {
+            Ok(tabs) => {
+                for tab_id in tabs {
+                    self.browser_core.set_tab_pinned(tab_id, true);
+                }
+            }
+            Err(e) => tracing::warn!("Failed to restore pinned tabs: {}", e),
+        }
+    }
+
+    /// Get the startup timeline
+    pub fn startup_timeline(&self) -> &StartupTimeline {
+        &self.timeline
+    }
+
+    /// Get the ad blocking engine
+    pub fn adblock(&self) -> &AdBlockEngine {
+        &self.adblock
+    }
+
+    /// Get the message bus the components talk over
+    pub fn message_bus(&self) -> &MessageBus {
+        &self.message_bus
+    }
+
+    /// Get the extension manager
+    pub fn extensions(&self) -> &Arc<ExtensionManager> {
+        &self.extensions
+    }
+
+    /// Get the browser engine
+    pub fn browser_core(&self) -> &BrowserEngine {
+        &self.browser_core
+    }
+
+    /// Get the browser engine for navigation and bookmark changes
+    pub fn browser_core_mut(&mut self) -> &mut BrowserEngine {
+        &mut self.browser_core
+    }
+
+    /// Get the browser shell
+    pub fn shell(&self) -> &BrowserShell {
+        &self.shell
+    }
+
+    /// Get the browser shell for tab changes
+    pub fn shell_mut(&mut self) -> &mut BrowserShell {
+        &mut self.shell
+    }
+
+    /// Save settings changed from within the browser to a config file
+    ///
+    /// Sites allowlisted for ad blocking are written to
+    /// `adblock.allowlisted_domains`, so they stay allowlisted after a
+    /// restart.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Configuration file the browser was started with; created
+    ///   on the first change if missing
+    pub fn set_config_file(&mut self, path: &Path) {
+        self.adblock.set_config_file(path);
+    }
+
+    /// Send the forms submitted in the window through the engine
+    ///
+    /// See [`send_form_submissions`]. Called by the run loop whenever the
+    /// window has handled events.
+    ///
+    /// # Returns
+    ///
+    /// Number of submissions sent.
+    pub fn send_form_submissions(&mut self) -> usize {
+        send_form_submissions(&mut self.shell, &mut self.browser_core, &self.runtime)
+    }
+
+    /// Render the internal pages requested in the window with the engine
+    ///
+    /// See [`show_internal_pages`]. Called by the run loop whenever the
+    /// window has handled events.
+    ///
+    /// # Returns
+    ///
+    /// Number of pages requested.
+    pub fn show_internal_pages(&mut self) -> usize {
+        show_internal_pages(&mut self.shell, &mut self.browser_core)
+    }
+
+    /// Write a session snapshot if one is due
+    ///
+    /// Call from the run loop after anything that may change the tabs;
+    /// the shell only writes when the session changed and
+    /// `browser.session_snapshot_secs` passed since the last snapshot.
+    /// Failures are logged, not fatal.
+    ///
+    /// # Returns
+    ///
+    /// Whether a snapshot was written.
+    pub fn tick_session_snapshot(&mut self) -> bool {
+        self.shell
+            .tick_session_snapshot(Instant::now())
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to write a session snapshot: {}", e);
+                false
+            })
+    }
+
+    /// Reload the configuration whenever its file changes
+    ///
+    /// The file is checked on a background thread; call
+    /// [`apply_config_changes`](Self::apply_config_changes) to apply what
+    /// it found. Watching another file stops watching the first.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Configuration file the browser was started with
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be loaded.
+    pub fn watch_config(&mut self, path: &Path) -> Result<()> {
+        let (sender, receiver) = mpsc::channel();
+        let (_, watch) = Config::watch(path, move |event| {
+            let _ = sender.send(event);
+        })?;
+        self.config_watch = Some(watch);
+        self.config_events = Some(receiver);
+        Ok(())
+    }
+
+    /// Apply the configuration reloads found since the last call
+    ///
+    /// Turning ad blocking on or off, switching the theme and changing the
+    /// accessibility settings take effect right away; the page shown keeps
+    /// its old stylesheet until reloaded. Font and encoding changes apply
+    /// from the next page. Changed network settings the
+    /// client was built with are logged as needing a restart. A file that
+    /// failed to load is logged and leaves the current configuration in
+    /// place.
+    ///
+    /// # Returns
+    ///
+    /// Returns the reloads and rejected files, oldest first.
+    pub fn apply_config_changes(&mut self) -> Vec<ConfigEvent> {
+        let events: Vec<ConfigEvent> = match &self.config_events {
+            Some(events) => events.try_iter().collect(),
+            None => return Vec::new(),
+        };
+        for event in &events {
+            match event {
+                ConfigEvent::Reloaded { config, delta } => {
+                    if delta.affects("adblock.enabled") {
+                        self.adblock.set_enabled(config.adblock.enabled);
+                    }
+                    if delta.affects("appearance.theme") {
+                        // Files with an unknown theme fail to load
+                        let theme = Theme::parse(&config.appearance.theme).unwrap_or_default();
+                        self.shell.set_theme(theme);
+                    }
+                    if delta.affects("accessibility") {
+                        if let Err(e) = self.shell.set_accessibility(&config.accessibility) {
+                            tracing::warn!("Keeping the accessibility stylesheet: {}", e);
+                        }
+                    }
+                    if delta.affects("fonts") || delta.affects("content.default_encoding") {
+                        self.shell
+                            .set_fonts(&config.fonts, &config.content.default_encoding);
+                    }
+                    let restart = delta.restart_required();
+                    if !restart.is_empty() {
+                        tracing::warn!("Restart to apply {}", restart.join(", "));
+                    }
+                }
+                ConfigEvent::Invalid { error } => {
+                    tracing::warn!("Keeping the current configuration: {}", error);
+                }
+            }
+        }
+        events
+    }
+
+    /// Add an Extensions menu for the entries contributed by extensions
+    ///
+    /// Nothing is added when no enabled extension contributes an entry.
+    fn add_extensions_menu(shell: &mut BrowserShell, extensions: &Arc<ExtensionManager>) {
+        let items = extensions.menu_items();
+        if items.is_empty() {
+            return;
+        }
+
+        let menu_bar = shell.menu_bar_mut();
+        let mut menu = Menu::new("Extensions".to_string());
+        for item in items {
+            if let MenuAction::Custom(action) = &item.action {
+                let manager = extensions.clone();
+                let action = action.clone();
+                menu_bar.register_handler(
+                    item.action.clone(),
+                    Arc::new(move || {
+                        manager.handle_menu_action(&action);
+                        Ok(())
+                    }),
+                );
+            }
+            menu.add_item(item);
+        }
+        menu_bar.add_menu(menu);
     }
 
     /// Run the browser application
     ///
-    /// This method starts all components and runs the browser shell event loop.
-    /// It blocks until the browser is closed by the user.
+    /// This method opens the first tab (see [`start`](Self::start)) and runs
+    /// the browser shell event loop. It blocks until the browser is closed by
+    /// the user.
+    /// The tabs are saved for the next start when
+    /// `browser.restore_session` is on, and cookies are deleted on the way
+    /// out when `privacy.clear_cookies_on_exit` is set.
     ///
     /// # Returns
     ///
@@ -93,16 +778,156 @@ impl BrowserApp {
     ///
     /// Returns an error if any component fails during initialization or runtime.
     pub fn run(mut self) -> Result<()> {
-        // Initialize components
-        // Note: NetworkStack is owned by BrowserEngine and initialized there
-        self.adblock.initialize()?;
+        // Components were initialized in `new`; open the first tab
+        self.start()?;
+
+        // Run the browser shell (blocks until exit); captured form posts
+        // and internal pages go through the engine as they come in
+        let (engine, runtime) = (&mut self.browser_core, &self.runtime);
+        self.shell.run_with(|shell| {
+            send_form_submissions(shell, engine, runtime);
+            show_internal_pages(shell, engine);
+        })?;
+
+        // Leave the clean shutdown marker so the next run doesn't recover
+        if let Err(e) = self.shell.finish_session_snapshots() {
+            tracing::warn!("Failed to write the final session snapshot: {}", e);
+        }
+
+        if let Some(path) = &self.session_file {
+            if let Err(e) = self.browser_core.save_session(path) {
+                tracing::warn!("Failed to save the session: {}", e);
+            }
+        }
 
-        // Run the browser shell (blocks until exit)
-        self.shell.run()?;
+        if self.clear_cookies_on_exit {
+            self.browser_core.network().cookies().clear();
+        }
 
-        // Cleanup: shutdown message bus
-        self.message_bus.shutdown()?;
+        // Fold the write-ahead logs into the database files
+        if let Err(e) = self.browser_core.flush_databases() {
+            tracing::warn!("Failed to flush databases: {}", e);
+        }
+
+        // Cleanup: let queued messages finish, then shut down the bus
+        let dropped = self
+            .message_bus
+            .shutdown_with_timeout(DEFAULT_SHUTDOWN_TIMEOUT)?;
+        if dropped > 0 {
+            tracing::warn!("Message bus shut down with {} messages unhandled", dropped);
+        }
 
         Ok(())
     }
 }
+
+/// Send the forms the shell captured through the engine
+///
+/// Each submission is navigated with
+/// [`BrowserEngine::navigate_with_request`], so the request goes through
+/// the network stack with the engine's blocking, referrer and history
+/// handling, and the response is shown in the tab it was submitted from.
+/// Failures are logged.
+///
+/// # Returns
+///
+/// Number of submissions sent.
+fn send_form_submissions(
+    shell: &mut BrowserShell,
+    engine: &mut BrowserEngine,
+    runtime: &Runtime,
+) -> usize {
+    let submissions = shell.take_form_submissions();
+    for (tab_id, submission) in &submissions {
+        let url = match Url::parse(&submission.url) {
+            Ok(url) => url,
+            Err(e) => {
+                tracing::warn!("Ignoring form submission to {}: {}", submission.url, e);
+                continue;
+            }
+        };
+        let request = if submission.method == "POST" {
+            NavigationRequest::post(url, &submission.content_type, submission.body.clone())
+        } else {
+            NavigationRequest {
+                url,
+                method: HttpMethod::GET,
+                headers: HashMap::new(),
+                body: None,
+            }
+        };
+
+        let mut document = (String::new(), Vec::new());
+        let result = runtime.block_on(engine.navigate_with_request(
+            *tab_id,
+            request,
+            NavigationInitiator::LinkClick { user_gesture: true },
+            &mut |chunk| match chunk {
+                DocumentChunk::Begin { url } => document = (url, Vec::new()),
+                DocumentChunk::Data(bytes) => document.1.extend(bytes),
+                _ => {}
+            },
+        ));
+        if let Err(e) = result {
+            tracing::warn!("Form submission to {} failed: {}", submission.url, e);
+            continue;
+        }
+        let (url, html) = document;
+        if let Err(e) = shell.show_document(*tab_id, &url, &html) {
+            tracing::warn!("Failed to show the response to {}: {}", submission.url, e);
+        }
+    }
+    submissions.len()
+}
+
+/// Render the internal pages the window asked for with the engine
+///
+/// `about:storage` is first given the window's site data merged with the
+/// cookie store. Clearing a site from it clears the window's webview too,
+/// and the page is rendered again without the site. Navigations the engine
+/// refuses, such as internal pages opened by web content, are logged.
+///
+/// # Returns
+///
+/// Number of pages requested.
+fn show_internal_pages(shell: &mut BrowserShell, engine: &mut BrowserEngine) -> usize {
+    let navigations = shell.take_internal_navigations();
+    for (tab_id, url, initiator) in &navigations {
+        if url.path() == "storage" {
+            engine.set_storage_report(shell.storage_report(&cookie_counts(engine)));
+        }
+        if let Err(e) = engine.navigate(*tab_id, url.clone(), initiator.clone()) {
+            tracing::warn!("Not opening {}: {}", url, e);
+            continue;
+        }
+        if let Some(origin) = browser_core::internal_pages::parse_storage_clear_url(url) {
+            if let Err(e) = shell.clear_site_data(&origin) {
+                tracing::warn!("Failed to clear the site data of {}: {}", origin, e);
+            }
+            // The action keeps the tab on about:storage
+            let _ = engine.reload(*tab_id);
+        }
+
+        let (Some(current), Some(document)) = (
+            engine.current_url(*tab_id),
+            engine.current_document(*tab_id),
+        ) else {
+            continue;
+        };
+        if let Err(e) = shell.show_document(*tab_id, current.as_str(), document.as_bytes()) {
+            tracing::warn!("Failed to show {}: {}", current, e);
+        }
+    }
+    navigations.len()
+}
+
+/// Cookies in the network stack's store per site
+///
+/// Keyed by cookie domain, the name WebKit files a site's data under.
+fn cookie_counts(engine: &BrowserEngine) -> HashMap<String, u32> {
+    let mut counts = HashMap::new();
+    for cookie in engine.network().cookies().cookies() {
+        *counts.entry(cookie.domain).or_insert(0) += 1;
+    }
+    counts
+}
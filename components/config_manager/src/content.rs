@@ -0,0 +1,333 @@
+//! Per-site content settings (JavaScript and image loading)
+//!
+//! Global defaults come from the `[content]` config section. Per-origin
+//! overrides live in their own file (`site_settings.toml` next to the
+//! config file by default) so toggling a site never rewrites the user's
+//! config:
+//!
+//! ```toml
+//! [sites."https://example.com"]
+//! javascript = false
+//! ```
+//!
+//! Origins are matched exactly on their ASCII serialization
+//! (`scheme://host[:port]`).
+
+use crate::{Config, ContentDefaults};
+use serde::{Deserialize, Serialize};
+use shared_types::{BrowserError, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A content category that can be toggled per site
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentKind {
+    /// JavaScript execution
+    JavaScript,
+    /// Image loading
+    Images,
+}
+
+impl ContentKind {
+    /// Human-readable label used in menus
+    pub fn label(self) -> &'static str {
+        match self {
+            ContentKind::JavaScript => "JavaScript",
+            ContentKind::Images => "images",
+        }
+    }
+}
+
+/// Per-origin override; `None` falls back to the global default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SiteContentOverride {
+    /// JavaScript override
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub javascript: Option<bool>,
+    /// Image loading override
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub images: Option<bool>,
+}
+
+impl SiteContentOverride {
+    /// Whether the override changes nothing
+    pub fn is_empty(&self) -> bool {
+        self.javascript.is_none() && self.images.is_none()
+    }
+}
+
+/// Settings in effect for an origin after applying overrides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EffectiveContentSettings {
+    /// Whether JavaScript runs
+    pub javascript: bool,
+    /// Whether images load
+    pub images: bool,
+}
+
+impl EffectiveContentSettings {
+    /// Whether a content kind is enabled
+    pub fn allows(&self, kind: ContentKind) -> bool {
+        match kind {
+            ContentKind::JavaScript => self.javascript,
+            ContentKind::Images => self.images,
+        }
+    }
+}
+
+/// On-disk layout of the override file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OverrideFile {
+    #[serde(default)]
+    sites: BTreeMap<String, SiteContentOverride>,
+}
+
+/// Resolver for per-site content settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContentSettings {
+    /// Global defaults from `[content]`
+    defaults: ContentDefaults,
+    /// Overrides keyed by origin
+    overrides: BTreeMap<String, SiteContentOverride>,
+}
+
+impl ContentSettings {
+    /// Create a resolver with no per-site overrides
+    ///
+    /// # Arguments
+    ///
+    /// * `defaults` - Global defaults
+    pub fn new(defaults: ContentDefaults) -> Self {
+        Self {
+            defaults,
+            overrides: BTreeMap::new(),
+        }
+    }
+
+    /// Default location of the override file
+    ///
+    /// # Returns
+    ///
+    /// Returns `site_settings.toml` in the config file's directory.
+    pub fn default_path() -> PathBuf {
+        Config::default_path().with_file_name("site_settings.toml")
+    }
+
+    /// Resolve the settings in effect for an origin
+    ///
+    /// Per-origin overrides take precedence over the global defaults.
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - Origin serialization, e.g. `https://example.com`
+    ///
+    /// # Returns
+    ///
+    /// Returns the effective settings.
+    pub fn settings_for(&self, origin: &str) -> EffectiveContentSettings {
+        let site = self.overrides.get(origin).copied().unwrap_or_default();
+        EffectiveContentSettings {
+            javascript: site.javascript.unwrap_or(self.defaults.javascript),
+            images: site.images.unwrap_or(self.defaults.images),
+        }
+    }
+
+    /// Get the override stored for an origin
+    pub fn site_override(&self, origin: &str) -> Option<&SiteContentOverride> {
+        self.overrides.get(origin)
+    }
+
+    /// Set or clear an override for an origin
+    ///
+    /// Overrides that end up empty are removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - Origin serialization
+    /// * `kind` - Content kind to override
+    /// * `enabled` - New value, or `None` to follow the global default
+    pub fn set_override(&mut self, origin: &str, kind: ContentKind, enabled: Option<bool>) {
+        let entry = self.overrides.entry(origin.to_string()).or_default();
+        match kind {
+            ContentKind::JavaScript => entry.javascript = enabled,
+            ContentKind::Images => entry.images = enabled,
+        }
+        if entry.is_empty() {
+            self.overrides.remove(origin);
+        }
+    }
+
+    /// Load per-site overrides from a file, replacing the current ones
+    ///
+    /// A missing file means "no overrides".
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Override file location
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or parsed.
+    pub fn load_overrides(&mut self, path: &Path) -> Result<()> {
+        if !path.exists() {
+            self.overrides.clear();
+            return Ok(());
+        }
+
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            BrowserError::Other(anyhow::anyhow!("Failed to read site settings: {}", e))
+        })?;
+        let file: OverrideFile = toml::from_str(&content).map_err(|e| {
+            BrowserError::Other(anyhow::anyhow!("Failed to parse site settings: {}", e))
+        })?;
+
+        self.overrides = file.sites;
+        self.overrides.retain(|_, site| !site.is_empty());
+        Ok(())
+    }
+
+    /// Save per-site overrides to a file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Override file location
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save_overrides(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                BrowserError::Other(anyhow::anyhow!("Failed to create config directory: {}", e))
+            })?;
+        }
+
+        let file = OverrideFile {
+            sites: self.overrides.clone(),
+        };
+        let text = toml::to_string_pretty(&file).map_err(|e| {
+            BrowserError::Other(anyhow::anyhow!("Failed to serialize site settings: {}", e))
+        })?;
+
+        std::fs::write(path, text).map_err(|e| {
+            BrowserError::Other(anyhow::anyhow!("Failed to write site settings: {}", e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults(javascript: bool, images: bool) -> ContentDefaults {
+        ContentDefaults {
+            javascript,
+            images,
+            ..ContentDefaults::default()
+        }
+    }
+
+    // ========================================
+    // Tests for resolution
+    // ========================================
+
+    #[test]
+    fn test_settings_for_uses_global_defaults() {
+        let settings = ContentSettings::new(defaults(true, false));
+        assert_eq!(
+            settings.settings_for("https://example.com"),
+            EffectiveContentSettings {
+                javascript: true,
+                images: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_per_origin_override_beats_global() {
+        let mut settings = ContentSettings::new(defaults(true, true));
+        settings.set_override("https://slow.com", ContentKind::JavaScript, Some(false));
+        settings.set_override("https://slow.com", ContentKind::Images, Some(false));
+
+        let slow = settings.settings_for("https://slow.com");
+        assert!(!slow.javascript);
+        assert!(!slow.images);
+        assert!(!slow.allows(ContentKind::Images));
+
+        // Other origins, including other ports and schemes, are unaffected
+        assert!(settings.settings_for("https://slow.com:8443").javascript);
+        assert!(settings.settings_for("http://slow.com").javascript);
+
+        // An override can also re-enable something disabled globally
+        let mut strict = ContentSettings::new(defaults(false, false));
+        strict.set_override("https://app.com", ContentKind::JavaScript, Some(true));
+        assert!(strict.settings_for("https://app.com").javascript);
+        assert!(!strict.settings_for("https://app.com").images);
+        assert!(!strict.settings_for("https://other.com").javascript);
+    }
+
+    #[test]
+    fn test_clearing_override_removes_site() {
+        let mut settings = ContentSettings::new(defaults(true, true));
+        settings.set_override("https://a.com", ContentKind::Images, Some(false));
+        assert!(settings.site_override("https://a.com").is_some());
+
+        settings.set_override("https://a.com", ContentKind::Images, None);
+        assert!(settings.site_override("https://a.com").is_none());
+        assert!(settings.settings_for("https://a.com").images);
+    }
+
+    // ========================================
+    // Tests for persistence
+    // ========================================
+
+    #[test]
+    fn test_overrides_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("site_settings.toml");
+
+        let mut settings = ContentSettings::new(defaults(true, true));
+        settings.set_override("https://a.com", ContentKind::JavaScript, Some(false));
+        settings.set_override("https://b.com:8080", ContentKind::Images, Some(false));
+        settings.save_overrides(&path).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("[sites.\"https://a.com\"]"));
+        // Unset fields are omitted rather than written as defaults
+        assert_eq!(text.matches("javascript = false").count(), 1);
+        assert_eq!(text.matches("images = false").count(), 1);
+
+        let mut loaded = ContentSettings::new(defaults(true, true));
+        loaded.load_overrides(&path).unwrap();
+        assert_eq!(loaded, settings);
+    }
+
+    #[test]
+    fn test_load_missing_file_clears_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut settings = ContentSettings::new(defaults(true, true));
+        settings.set_override("https://a.com", ContentKind::JavaScript, Some(false));
+
+        settings
+            .load_overrides(&dir.path().join("missing.toml"))
+            .unwrap();
+        assert!(settings.site_override("https://a.com").is_none());
+    }
+
+    #[test]
+    fn test_load_invalid_file_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("site_settings.toml");
+        std::fs::write(&path, "[sites.\"https://a.com\"]\njavascript = \"nope\"\n").unwrap();
+
+        let mut settings = ContentSettings::new(defaults(true, true));
+        assert!(settings.load_overrides(&path).is_err());
+    }
+
+    #[test]
+    fn test_default_path_is_next_to_config() {
+        let path = ContentSettings::default_path();
+        assert_eq!(path.file_name().unwrap(), "site_settings.toml");
+        assert_eq!(path.parent(), Config::default_path().parent());
+    }
+}
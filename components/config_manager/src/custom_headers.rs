@@ -0,0 +1,376 @@
+//! Per-host custom request headers
+//!
+//! Rules live in `network.custom_headers`:
+//!
+//! ```toml
+//! [[network.custom_headers]]
+//! host_pattern = "*.staging.example.com"
+//! headers = { "X-Dev-Token" = "${env:DEV_TOKEN}" }
+//! include_subresources = true
+//! ```
+//!
+//! `host_pattern` is an exact host (`portal.corp`) or `*.suffix`, which
+//! matches any subdomain of `suffix` at any depth but not `suffix` itself.
+//! Rules apply to top-level navigations, and to subresource requests only
+//! when `include_subresources` is set.
+//!
+//! Every matching rule applies, in file order: headers are merged and a
+//! later rule overrides an earlier one's value for the same header (names
+//! compare case-insensitively).
+//!
+//! Values may contain `${env:VAR}`, replaced by the environment variable
+//! when the rules are resolved; an unset variable is an error. Framing
+//! headers (`Host`, `Content-Length`, ...) cannot be set, and `Cookie` /
+//! `Authorization` need `allow_sensitive = true` on the rule.
+
+use serde::{Deserialize, Serialize};
+use shared_types::{BrowserError, Result};
+use std::collections::BTreeMap;
+
+/// Headers no rule may set
+const FORBIDDEN_HEADERS: &[&str] = &["host", "content-length", "transfer-encoding", "connection"];
+
+/// Headers a rule may only set with `allow_sensitive = true`
+const SENSITIVE_HEADERS: &[&str] = &["cookie", "authorization", "proxy-authorization"];
+
+/// One `network.custom_headers` rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomHeaderRule {
+    /// Exact host or `*.suffix`
+    pub host_pattern: String,
+    /// Header names and values; values may use `${env:VAR}`
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Also send the headers on subresource requests
+    #[serde(default)]
+    pub include_subresources: bool,
+    /// Allow `Cookie` and `Authorization`
+    #[serde(default)]
+    pub allow_sensitive: bool,
+}
+
+impl CustomHeaderRule {
+    /// Check whether the rule's pattern matches a host
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - Request host (case and a trailing dot are ignored)
+    pub fn matches_host(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let pattern = self.host_pattern.trim().to_ascii_lowercase();
+        match pattern.strip_prefix("*.") {
+            Some(suffix) => host
+                .strip_suffix(suffix)
+                .is_some_and(|rest| rest.len() > 1 && rest.ends_with('.')),
+            None => host == pattern,
+        }
+    }
+
+    /// Problems with the rule's pattern and header names
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        let pattern = self.host_pattern.trim();
+        let host = pattern.strip_prefix("*.").unwrap_or(pattern);
+        if host.is_empty() || host.contains(['*', '/', ':', ' ']) {
+            problems.push(format!(
+                "host_pattern: expected a host or \"*.suffix\", got \"{}\"",
+                self.host_pattern
+            ));
+        }
+
+        for name in self.headers.keys() {
+            if let Some(problem) = header_name_problem(name) {
+                problems.push(problem);
+            } else if is_sensitive(name) && !self.allow_sensitive {
+                problems.push(format!("header {} needs allow_sensitive = true", name));
+            }
+        }
+
+        problems
+    }
+}
+
+/// Custom header rules with `${env:VAR}` values resolved
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CustomHeaders {
+    /// Resolved rules, in file order
+    rules: Vec<CustomHeaderRule>,
+}
+
+impl CustomHeaders {
+    /// Validate rules and resolve their values
+    ///
+    /// # Arguments
+    ///
+    /// * `rules` - Rules as configured
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first offending rule if a pattern or
+    /// header is invalid or a referenced environment variable is not set.
+    pub fn resolve(rules: &[CustomHeaderRule]) -> Result<Self> {
+        if let Some(problem) = rule_problems(rules).into_iter().next() {
+            return Err(BrowserError::Other(anyhow::anyhow!(problem)));
+        }
+
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let headers: BTreeMap<String, String> = rule
+                    .headers
+                    .iter()
+                    .map(|(name, value)| Ok((name.clone(), substitute_env(value)?)))
+                    .collect::<std::result::Result<_, String>>()
+                    .map_err(|e| {
+                        BrowserError::Other(anyhow::anyhow!(
+                            "network.custom_headers ({}): {}",
+                            rule.host_pattern,
+                            e
+                        ))
+                    })?;
+                Ok(CustomHeaderRule {
+                    headers,
+                    ..rule.clone()
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Whether there are no rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Headers to add to a request
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - Request host
+    /// * `navigation` - Whether the request is a top-level navigation
+    ///
+    /// # Returns
+    ///
+    /// Returns the merged headers of every matching rule, later rules
+    /// overriding earlier ones.
+    pub fn headers_for(&self, host: &str, navigation: bool) -> BTreeMap<String, String> {
+        let mut merged: BTreeMap<String, String> = BTreeMap::new();
+        for rule in &self.rules {
+            if !(navigation || rule.include_subresources) || !rule.matches_host(host) {
+                continue;
+            }
+            for (name, value) in &rule.headers {
+                merged.retain(|existing, _| !existing.eq_ignore_ascii_case(name));
+                merged.insert(name.clone(), value.clone());
+            }
+        }
+        merged
+    }
+}
+
+/// Diagnostics for every rule, prefixed with the rule's position
+pub(crate) fn rule_problems(rules: &[CustomHeaderRule]) -> Vec<String> {
+    let mut diagnostics = Vec::new();
+    for (index, rule) in rules.iter().enumerate() {
+        for problem in rule.problems() {
+            diagnostics.push(format!("network.custom_headers[{}]: {}", index, problem));
+        }
+        for value in rule.headers.values() {
+            if let Err(e) = substitute_env(value) {
+                diagnostics.push(format!("network.custom_headers[{}]: {}", index, e));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Problems with `network.extra_headers`, the headers sent to every host
+///
+/// Besides the names no rule may set, `Cookie` and `Authorization` are
+/// refused (a per-host rule can send them), as is `User-Agent`, which has
+/// its own setting.
+pub(crate) fn extra_header_problems(headers: &BTreeMap<String, String>) -> Vec<String> {
+    headers
+        .keys()
+        .filter_map(|name| {
+            header_name_problem(name).or_else(|| {
+                if is_sensitive(name) {
+                    Some(format!(
+                        "header {} would go to every host; use network.custom_headers",
+                        name
+                    ))
+                } else if name.eq_ignore_ascii_case("user-agent") {
+                    Some(format!("header {}: set network.user_agent instead", name))
+                } else {
+                    None
+                }
+            })
+        })
+        .map(|problem| format!("network.extra_headers: {}", problem))
+        .collect()
+}
+
+/// Why a header name cannot be configured at all, if it cannot
+fn header_name_problem(name: &str) -> Option<String> {
+    if name.is_empty() || name.contains([':', ' ', '\r', '\n']) {
+        Some(format!("invalid header name \"{}\"", name))
+    } else if FORBIDDEN_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+        Some(format!("header {} cannot be set", name))
+    } else {
+        None
+    }
+}
+
+/// Whether a header carries credentials
+fn is_sensitive(name: &str) -> bool {
+    SENSITIVE_HEADERS.contains(&name.to_ascii_lowercase().as_str())
+}
+
+/// Replace `${env:VAR}` references with environment variable values
+fn substitute_env(value: &str) -> std::result::Result<String, String> {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${env:") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + "${env:".len()..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| format!("unterminated ${{env:...}} in \"{}\"", value))?;
+        let name = &after[..end];
+        let resolved =
+            std::env::var(name).map_err(|_| format!("environment variable {} is not set", name))?;
+        out.push_str(&resolved);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(host_pattern: &str, headers: &[(&str, &str)]) -> CustomHeaderRule {
+        CustomHeaderRule {
+            host_pattern: host_pattern.to_string(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            include_subresources: false,
+            allow_sensitive: false,
+        }
+    }
+
+    // ========================================
+    // Tests for host patterns
+    // ========================================
+
+    #[test]
+    fn test_exact_host_pattern() {
+        let rule = rule("portal.corp", &[]);
+        assert!(rule.matches_host("portal.corp"));
+        assert!(rule.matches_host("Portal.CORP."));
+        assert!(!rule.matches_host("www.portal.corp"));
+        assert!(!rule.matches_host("portal.corp.evil"));
+    }
+
+    #[test]
+    fn test_wildcard_host_pattern() {
+        let rule = rule("*.example.com", &[]);
+        assert!(rule.matches_host("api.example.com"));
+        assert!(rule.matches_host("a.b.example.com"));
+        assert!(!rule.matches_host("example.com"));
+        assert!(!rule.matches_host("badexample.com"));
+        assert!(!rule.matches_host(".example.com"));
+        assert!(!rule.matches_host("example.com.evil"));
+    }
+
+    // ========================================
+    // Tests for merging
+    // ========================================
+
+    #[test]
+    fn test_later_rules_override_earlier() {
+        let mut all_hosts = rule("*.corp", &[("X-Tenant", "default"), ("X-Trace", "1")]);
+        all_hosts.include_subresources = true;
+        let headers = CustomHeaders::resolve(&[
+            all_hosts,
+            rule("hr.corp", &[("x-tenant", "hr"), ("X-Extra", "yes")]),
+        ])
+        .unwrap();
+
+        let expected: BTreeMap<String, String> =
+            [("X-Extra", "yes"), ("X-Trace", "1"), ("x-tenant", "hr")]
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect();
+        assert_eq!(headers.headers_for("hr.corp", true), expected);
+
+        // The second rule is navigation-only
+        let subresource = headers.headers_for("hr.corp", false);
+        assert_eq!(
+            subresource.get("X-Tenant").map(String::as_str),
+            Some("default")
+        );
+        assert!(!subresource.contains_key("X-Extra"));
+
+        assert!(headers.headers_for("example.com", true).is_empty());
+    }
+
+    // ========================================
+    // Tests for validation
+    // ========================================
+
+    #[test]
+    fn test_forbidden_headers_rejected() {
+        let rules = [
+            rule("a.test", &[("Host", "b.test"), ("Content-Length", "1")]),
+            rule("a.test", &[("Cookie", "x=1")]),
+            rule("*.", &[("X-Ok", "1")]),
+        ];
+        let problems = rule_problems(&rules);
+        assert_eq!(problems.len(), 4);
+        assert!(problems[0].starts_with("network.custom_headers[0]: header Content-Length"));
+        assert!(problems[2].contains("Cookie needs allow_sensitive = true"));
+        assert!(problems[3].starts_with("network.custom_headers[2]: host_pattern"));
+        assert!(CustomHeaders::resolve(&rules).is_err());
+
+        let mut sensitive = rule("a.test", &[("Authorization", "Bearer t")]);
+        sensitive.allow_sensitive = true;
+        assert!(rule_problems(&[sensitive]).is_empty());
+    }
+
+    #[test]
+    fn test_env_substitution() {
+        std::env::set_var("FRANKENBROWSER_TEST_DEV_TOKEN", "s3cret");
+        let headers = CustomHeaders::resolve(&[rule(
+            "api.test",
+            &[("X-Dev-Token", "token ${env:FRANKENBROWSER_TEST_DEV_TOKEN}!")],
+        )])
+        .unwrap();
+        assert_eq!(
+            headers.headers_for("api.test", true)["X-Dev-Token"],
+            "token s3cret!"
+        );
+    }
+
+    #[test]
+    fn test_env_substitution_missing_variable() {
+        let rules = [rule(
+            "api.test",
+            &[("X-Dev-Token", "${env:FRANKENBROWSER_TEST_UNSET_VAR}")],
+        )];
+        let err = CustomHeaders::resolve(&rules).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("environment variable FRANKENBROWSER_TEST_UNSET_VAR is not set"));
+        assert_eq!(rule_problems(&rules).len(), 1);
+
+        assert_eq!(
+            substitute_env("${env:UNTERMINATED"),
+            Err("unterminated ${env:...} in \"${env:UNTERMINATED\"".to_string())
+        );
+    }
+}
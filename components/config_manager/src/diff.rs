@@ -0,0 +1,331 @@
+//! Structured differences between configurations
+//!
+//! Both configurations are serialized to TOML value trees and compared
+//! recursively, so new fields are picked up without touching this module.
+//! Paths are the dotted serde names also used by [`Config::get_path`].
+//!
+//! Tables are descended into; anything else, including arrays and arrays of
+//! tables such as `network.custom_headers`, is compared as a whole and
+//! reported as a single change. Array elements have no stable name a
+//! subscriber could ask about, so per-element entries would only be noise.
+
+use crate::Config;
+
+/// One setting that differs between two configurations
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigChange {
+    /// Dotted path of the setting, e.g. `network.cache_size_mb`
+    pub path: String,
+    /// Previous value; `None` if the setting was added
+    pub old: Option<toml::Value>,
+    /// New value; `None` if the setting was removed
+    pub new: Option<toml::Value>,
+}
+
+impl ConfigChange {
+    /// Previous value rendered as TOML, or an empty string if it was added
+    pub fn old_display(&self) -> String {
+        self.old
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default()
+    }
+
+    /// New value rendered as TOML, or an empty string if it was removed
+    pub fn new_display(&self) -> String {
+        self.new
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default()
+    }
+}
+
+/// The changes between an old and a new configuration
+///
+/// Handed to components along with a reloaded [`Config`] so each can skip
+/// reconfiguring itself when nothing it reads has changed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChangeSet {
+    changes: Vec<ConfigChange>,
+}
+
+impl ChangeSet {
+    /// Compute the changes from `old` to `new`
+    pub fn between(old: &Config, new: &Config) -> Self {
+        Self {
+            changes: old.diff(new),
+        }
+    }
+
+    /// Check whether any change is at or below a path
+    ///
+    /// Matching is by whole path segments: `network` and
+    /// `network.cache_size_mb` both match a change to
+    /// `network.cache_size_mb`, `net` does not. An empty prefix matches any
+    /// change.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - Dotted path of a section or setting
+    pub fn affects(&self, prefix: &str) -> bool {
+        self.changes
+            .iter()
+            .any(|change| is_under(&change.path, prefix))
+    }
+
+    /// Check whether the configurations were identical
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Number of changed settings
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// The changes, ordered by path
+    pub fn changes(&self) -> &[ConfigChange] {
+        &self.changes
+    }
+
+    /// Iterate over the changes, ordered by path
+    pub fn iter(&self) -> std::slice::Iter<'_, ConfigChange> {
+        self.changes.iter()
+    }
+}
+
+impl From<Vec<ConfigChange>> for ChangeSet {
+    fn from(changes: Vec<ConfigChange>) -> Self {
+        Self { changes }
+    }
+}
+
+impl<'a> IntoIterator for &'a ChangeSet {
+    type Item = &'a ConfigChange;
+    type IntoIter = std::slice::Iter<'a, ConfigChange>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.iter()
+    }
+}
+
+pub(crate) fn is_under(path: &str, prefix: &str) -> bool {
+    prefix.is_empty()
+        || path
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+impl Config {
+    /// Compare this configuration with another
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Configuration to compare against; its values are the
+    ///   `new` side of each change
+    ///
+    /// # Returns
+    ///
+    /// Returns one entry per differing setting, ordered by path.
+    pub fn diff(&self, other: &Config) -> Vec<ConfigChange> {
+        diff_values(&self.to_value(), &other.to_value())
+    }
+
+    /// List the settings that differ from their defaults
+    ///
+    /// # Returns
+    ///
+    /// Returns one entry per customized setting, ordered by path, with the
+    /// default as `old` and this configuration's value as `new`.
+    pub fn non_default_fields(&self) -> Vec<ConfigChange> {
+        Config::default().diff(self)
+    }
+}
+
+/// Compare two TOML value trees
+pub(crate) fn diff_values(old: &toml::Value, new: &toml::Value) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+    diff_into(String::new(), Some(old), Some(new), &mut changes);
+    changes
+}
+
+fn diff_into(
+    path: String,
+    old: Option<&toml::Value>,
+    new: Option<&toml::Value>,
+    changes: &mut Vec<ConfigChange>,
+) {
+    match (old, new) {
+        (Some(toml::Value::Table(old)), Some(toml::Value::Table(new))) => {
+            let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                diff_into(child, old.get(key), new.get(key), changes);
+            }
+        }
+        _ if old == new => {}
+        _ => changes.push(ConfigChange {
+            path,
+            old: old.cloned(),
+            new: new.cloned(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CustomHeaderRule, FieldType};
+
+    fn paths(changes: &[ConfigChange]) -> Vec<&str> {
+        changes.iter().map(|c| c.path.as_str()).collect()
+    }
+
+    #[test]
+    fn test_diff_scalar_fields() {
+        let old = Config::default();
+        let mut new = old.clone();
+        assert!(old.diff(&new).is_empty());
+
+        new.appearance.theme = "dark".to_string();
+        new.network.cache_size_mb = 100;
+        let changes = old.diff(&new);
+        assert_eq!(
+            paths(&changes),
+            ["appearance.theme", "network.cache_size_mb"]
+        );
+        assert_eq!(changes[0].old_display(), "\"auto\"");
+        assert_eq!(changes[0].new_display(), "\"dark\"");
+        assert_eq!(changes[1].new, Some(toml::Value::Integer(100)));
+    }
+
+    #[test]
+    fn test_diff_arrays_are_single_entries() {
+        let old = Config::default();
+        let mut new = old.clone();
+        new.adblock.custom_filters = vec!["||ads.example^".to_string(), "##.banner".to_string()];
+        new.network.custom_headers.push(CustomHeaderRule {
+            host_pattern: "*.example.com".to_string(),
+            headers: [("X-Team".to_string(), "web".to_string())].into(),
+            include_subresources: false,
+            allow_sensitive: false,
+        });
+        assert_eq!(
+            paths(&old.diff(&new)),
+            ["adblock.custom_filters", "network.custom_headers"]
+        );
+
+        // A change inside one element is still one entry for the array
+        let mut edited = new.clone();
+        edited.network.custom_headers[0]
+            .headers
+            .insert("X-Env".to_string(), "prod".to_string());
+        let changes = new.diff(&edited);
+        assert_eq!(paths(&changes), ["network.custom_headers"]);
+        assert!(changes[0].new_display().contains("X-Env"));
+    }
+
+    #[test]
+    fn test_diff_nested_and_optional_fields() {
+        let old: toml::Value =
+            toml::from_str("[site]\nname = \"a\"\nremoved = true\n[site.inner]\ndepth = 1\n")
+                .unwrap();
+        let new: toml::Value =
+            toml::from_str("[site]\nname = \"a\"\nadded = 2\n[site.inner]\ndepth = 2\n").unwrap();
+
+        let changes = diff_values(&old, &new);
+        assert_eq!(
+            paths(&changes),
+            ["site.added", "site.inner.depth", "site.removed"]
+        );
+        assert_eq!(changes[0].old, None);
+        assert_eq!(changes[0].new, Some(toml::Value::Integer(2)));
+        assert_eq!(changes[1].old, Some(toml::Value::Integer(1)));
+        assert_eq!(changes[2].old, Some(toml::Value::Boolean(true)));
+        assert_eq!(changes[2].new, None);
+        assert_eq!(changes[2].new_display(), "");
+    }
+
+    #[test]
+    fn test_change_set_affects() {
+        let old = Config::default();
+        let mut new = old.clone();
+        new.appearance.theme = "dark".to_string();
+        let changes = ChangeSet::between(&old, &new);
+
+        assert_eq!(changes.len(), 1);
+        assert!(changes.affects("appearance"));
+        assert!(changes.affects("appearance.theme"));
+        assert!(changes.affects(""));
+        assert!(!changes.affects("network"));
+        assert!(!changes.affects("network.cache_size_mb"));
+        assert!(!changes.affects("appear"));
+        assert!(!changes.affects("appearance.theme.extra"));
+
+        let unchanged = ChangeSet::between(&old, &old);
+        assert!(unchanged.is_empty());
+        assert!(!unchanged.affects(""));
+    }
+
+    #[test]
+    fn test_non_default_fields() {
+        assert!(Config::default().non_default_fields().is_empty());
+
+        let mut config = Config::default();
+        config.browser.homepage = "https://example.com".to_string();
+        config.privacy.do_not_track = false;
+        config.appearance.default_zoom = 1.25;
+
+        let fields = config.non_default_fields();
+        assert_eq!(
+            paths(&fields),
+            [
+                "appearance.default_zoom",
+                "browser.homepage",
+                "privacy.do_not_track"
+            ]
+        );
+        assert_eq!(fields[0].old_display(), "1.0");
+        assert_eq!(fields[0].new_display(), "1.25");
+        assert_eq!(fields[2].old, Some(toml::Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_paths_match_schema() {
+        let mut config = Config::default();
+        let mut expected = Vec::new();
+        for field in Config::schema() {
+            let raw = match field.ty {
+                FieldType::Bool => {
+                    let current = config.get_path(field.path).unwrap();
+                    if current == "true" { "false" } else { "true" }.to_string()
+                }
+                FieldType::Integer => "12345".to_string(),
+                FieldType::Float => "2.5".to_string(),
+                FieldType::String => "changed".to_string(),
+                FieldType::StringArray => "[\"changed\"]".to_string(),
+                FieldType::TableArray => continue,
+                // Tables are descended into, so the change is to the entry
+                FieldType::StringTable => {
+                    config
+                        .set_path(field.path, "{ X-Test = \"changed\" }")
+                        .unwrap();
+                    expected.push(format!("{}.X-Test", field.path));
+                    continue;
+                }
+            };
+            config.set_path(field.path, &raw).unwrap();
+            expected.push(field.path.to_string());
+        }
+        expected.sort();
+
+        let changed = config.non_default_fields();
+        assert_eq!(paths(&changed), expected);
+    }
+}
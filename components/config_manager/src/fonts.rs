@@ -0,0 +1,126 @@
+//! Installed font families
+//!
+//! Used to warn about `[fonts]` families that are not installed. Families
+//! are listed with fontconfig's `fc-list`, which exists on Linux and on
+//! most BSD and macOS systems with X11 or Homebrew tools. Where it cannot
+//! be run the installed fonts are unknown and no warnings are given.
+
+use std::collections::BTreeSet;
+use std::process::Command;
+
+/// CSS generic families, resolved by the webview whatever is installed
+const GENERIC_FAMILIES: &[&str] = &[
+    "serif",
+    "sans-serif",
+    "monospace",
+    "cursive",
+    "fantasy",
+    "system-ui",
+];
+
+/// Font families available on the system
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstalledFonts {
+    /// Family names, lowercased
+    families: BTreeSet<String>,
+}
+
+impl InstalledFonts {
+    /// Build the set from family names
+    ///
+    /// # Arguments
+    ///
+    /// * `families` - Installed family names, in any case
+    pub fn from_families<I, S>(families: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self {
+            families: families
+                .into_iter()
+                .map(|family| family.as_ref().trim().to_lowercase())
+                .filter(|family| !family.is_empty())
+                .collect(),
+        }
+    }
+
+    /// List the installed families with `fc-list`
+    ///
+    /// # Returns
+    ///
+    /// `None` if `fc-list` is missing or fails, so installed fonts cannot
+    /// be told apart from unknown ones.
+    pub fn detect() -> Option<Self> {
+        let output = Command::new("fc-list")
+            .args([":", "family"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        Some(Self::parse_fc_list(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
+
+    /// Parse `fc-list : family` output
+    ///
+    /// Each line lists one font's family names, separated by commas
+    /// (localized names and style-specific families).
+    fn parse_fc_list(output: &str) -> Self {
+        Self::from_families(output.lines().flat_map(|line| line.split(',')))
+    }
+
+    /// Whether `family` is installed or is a CSS generic family
+    ///
+    /// Matching ignores case and surrounding quotes.
+    ///
+    /// # Arguments
+    ///
+    /// * `family` - Family name as written in the config
+    pub fn contains(&self, family: &str) -> bool {
+        let family = family.trim().trim_matches(['"', '\'']).to_lowercase();
+        GENERIC_FAMILIES.contains(&family.as_str()) || self.families.contains(&family)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FontSettings;
+
+    #[test]
+    fn test_fc_list_output_matches_any_alias() {
+        let installed = InstalledFonts::parse_fc_list(
+            "DejaVu Sans,DejaVu Sans Condensed\nNoto Sans CJK JP,Noto Sans CJK JP Bold\n\n",
+        );
+        assert!(installed.contains("DejaVu Sans"));
+        assert!(installed.contains("dejavu sans condensed"));
+        assert!(installed.contains("\"Noto Sans CJK JP\""));
+        assert!(installed.contains("monospace"));
+        assert!(!installed.contains("Comic Sans MS"));
+    }
+
+    #[test]
+    fn test_unknown_families_warn() {
+        let installed = InstalledFonts::from_families(["DejaVu Serif", "DejaVu Sans Mono"]);
+        let fonts = FontSettings {
+            serif_family: "dejavu serif".to_string(),
+            sans_serif_family: "Helvetica Neue".to_string(),
+            monospace_family: "DejaVu Sans Mono".to_string(),
+            ..FontSettings::default()
+        };
+
+        let warnings = fonts.unknown_families(&installed);
+        assert_eq!(
+            warnings,
+            vec![
+                "fonts.sans_serif_family: \"Helvetica Neue\" is not installed; \
+                 the default font is used"
+                    .to_string()
+            ]
+        );
+        assert!(FontSettings::default()
+            .unknown_families(&installed)
+            .is_empty());
+    }
+}
@@ -19,12 +19,38 @@
 
 use serde::{Deserialize, Serialize};
 use shared_types::{BrowserError, Result};
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+mod content;
+mod custom_headers;
+mod diff;
+mod fonts;
+mod schema;
+mod theme;
+mod watch;
+
+pub use content::{ContentKind, ContentSettings, EffectiveContentSettings, SiteContentOverride};
+pub use custom_headers::{CustomHeaderRule, CustomHeaders};
+pub use diff::{ChangeSet, ConfigChange};
+pub use fonts::InstalledFonts;
+pub use schema::{FieldDescriptor, FieldType};
+pub use theme::{detect_system_theme, Theme};
+pub use watch::{
+    ConfigDelta, ConfigEvent, ConfigWatcher, WatchHandle, DEFAULT_DEBOUNCE, DEFAULT_POLL_INTERVAL,
+    RESTART_REQUIRED,
+};
 
 /// Main configuration structure for FrankenBrowser
 ///
 /// Contains all configuration sections for the browser.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+///
+/// A file must have the `browser`, `network`, `adblock`, `privacy` and
+/// `appearance` sections with their original settings; settings added
+/// since, and sections made only of those, take their defaults when left
+/// out.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct Config {
     /// Browser-specific settings
     pub browser: BrowserSettings,
@@ -34,8 +60,29 @@ pub struct Config {
     pub adblock: AdBlockSettings,
     /// Privacy settings
     pub privacy: PrivacySettings,
+    /// Security settings
+    #[serde(default)]
+    pub security: SecuritySettings,
+    /// Content loading defaults
+    #[serde(default)]
+    pub content: ContentDefaults,
     /// Appearance settings
     pub appearance: AppearanceSettings,
+    /// Accessibility overrides applied to every page
+    #[serde(default)]
+    pub accessibility: AccessibilitySettings,
+    /// Default fonts and sizes
+    #[serde(default)]
+    pub fonts: FontSettings,
+    /// Navigation performance budgets
+    #[serde(default)]
+    pub performance: PerformanceSettings,
+    /// Built-in extension settings
+    #[serde(default)]
+    pub extensions: ExtensionSettings,
+    /// WebDriver server settings
+    #[serde(default)]
+    pub webdriver: WebDriverSettings,
 }
 
 /// Browser-specific settings
@@ -47,6 +94,61 @@ pub struct BrowserSettings {
     pub enable_devtools: bool,
     /// Default search engine
     pub default_search_engine: String,
+    /// Ctrl+Tab cycles tabs in most-recently-used order instead of
+    /// tab-strip order
+    #[serde(default)]
+    pub ctrl_tab_mru: bool,
+    /// Language tag for dates and numbers on internal pages (e.g. "de-DE")
+    #[serde(default = "default_locale")]
+    pub locale: String,
+    /// Directory for session snapshots; empty disables them
+    #[serde(default)]
+    pub session_dir: String,
+    /// Seconds between session snapshots while tabs are changing
+    #[serde(default = "default_session_snapshot_secs")]
+    pub session_snapshot_secs: u32,
+    /// After a crash: "prompt" to offer the previous session, "restore" to
+    /// reopen it, "discard" to start fresh
+    #[serde(default = "default_crash_recovery")]
+    pub crash_recovery: String,
+    /// Reopen the tabs of the last clean shutdown, with their back/forward
+    /// stacks, at the next start; needs `session_dir`
+    #[serde(default)]
+    pub restore_session: bool,
+    /// Directory for hibernated tabs; empty disables hibernation
+    #[serde(default)]
+    pub hibernation_dir: String,
+    /// Hours a background tab stays idle before it is hibernated; 0 only
+    /// hibernates under memory pressure or on request
+    #[serde(default)]
+    pub hibernate_after_hours: u32,
+    /// Directory for the history and bookmarks databases; empty keeps them
+    /// in memory for the session
+    #[serde(default)]
+    pub profile_dir: String,
+    /// Milliseconds a profile database query waits for another connection's
+    /// lock before giving up
+    #[serde(default = "default_db_busy_timeout_ms")]
+    pub db_busy_timeout_ms: u32,
+    /// Fetch a title and summary for links hovered past the dwell time
+    #[serde(default)]
+    pub link_previews: bool,
+    /// Milliseconds a link must stay hovered before its preview is fetched
+    #[serde(default = "default_link_preview_dwell_ms")]
+    pub link_preview_dwell_ms: u32,
+    /// Hosts (and their subdomains) never fetched for a link preview
+    #[serde(default)]
+    pub link_preview_excluded_hosts: Vec<String>,
+    /// Leave the original tab active when duplicating a tab
+    #[serde(default)]
+    pub duplicate_tab_in_background: bool,
+    /// Ask before Ctrl+W closes a pinned tab
+    #[serde(default = "default_true")]
+    pub confirm_close_pinned_tabs: bool,
+    /// Recent message traces kept for `about:diagnostics`; 0 turns
+    /// recording off
+    #[serde(default)]
+    pub message_trace_buffer: u32,
 }
 
 /// Network configuration settings
@@ -56,12 +158,63 @@ pub struct NetworkSettings {
     pub max_connections_per_host: u32,
     /// Request timeout in seconds
     pub timeout_seconds: u32,
+    /// Redirects a single request may follow before it fails as a
+    /// redirect loop
+    #[serde(default = "default_max_redirects")]
+    pub max_redirects: u32,
     /// Enable cookies
     pub enable_cookies: bool,
     /// Enable cache
     pub enable_cache: bool,
     /// Cache size in megabytes
     pub cache_size_mb: u32,
+    /// Where cached responses live: "memory" for this session only, "disk"
+    /// to keep them across restarts
+    #[serde(default = "default_cache_backend")]
+    pub cache_backend: String,
+    /// Directory of the disk cache; empty means `http-cache` next to the
+    /// config file
+    #[serde(default)]
+    pub cache_dir: String,
+    /// SQLite file cookies with an expiry are kept in across restarts;
+    /// empty keeps all cookies in memory for the session
+    #[serde(default)]
+    pub cookie_file: String,
+    /// Folder downloads are saved to; empty means `~/Downloads`
+    #[serde(default)]
+    pub download_dir: String,
+    /// Retry a page load once when it fails for a transient reason
+    #[serde(default = "default_true")]
+    pub retry_transient_failures: bool,
+    /// Embed text response bodies in exported HAR files
+    #[serde(default)]
+    pub har_embed_bodies: bool,
+    /// Per-host request headers; see [`CustomHeaders`]
+    #[serde(default)]
+    pub custom_headers: Vec<CustomHeaderRule>,
+    /// Session traffic in megabytes after which downloads and prefetching
+    /// pause until acknowledged; 0 means no budget
+    #[serde(default)]
+    pub metered_budget_mb: u32,
+    /// Proxy for all traffic, as `http://`, `https://` or
+    /// `socks5://host:port`; empty connects directly
+    #[serde(default)]
+    pub proxy: String,
+    /// Hosts reached without the proxy: `host` matches that host only,
+    /// `.domain` (or `*.domain`) the domain and every subdomain, `*` all
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+    /// Resolve the hosts of links in view and `dns-prefetch` hints ahead of
+    /// a click
+    #[serde(default = "default_true")]
+    pub dns_prefetch: bool,
+    /// User-Agent sent by the network stack and the webview; empty keeps
+    /// the browser's own
+    #[serde(default)]
+    pub user_agent: String,
+    /// Headers sent with every request that does not set them itself
+    #[serde(default)]
+    pub extra_headers: BTreeMap<String, String>,
 }
 
 /// AdBlock configuration settings
@@ -73,6 +226,22 @@ pub struct AdBlockSettings {
     pub update_filters_on_startup: bool,
     /// Custom filter rules
     pub custom_filters: Vec<String>,
+    /// Seed an empty filter list directory from the embedded list
+    #[serde(default = "default_true")]
+    pub seed_filters: bool,
+    /// Directory of cached filter lists; empty means `filter-lists` next
+    /// to the config file
+    #[serde(default)]
+    pub filter_list_dir: String,
+    /// Filter list URLs subscribed when a profile has no subscriptions file
+    #[serde(default = "default_subscriptions")]
+    pub default_subscriptions: Vec<String>,
+    /// Replace blocked images and frames with a click-to-load placeholder
+    #[serde(default)]
+    pub show_placeholders: bool,
+    /// Sites, with their subdomains, whose pages are never ad blocked
+    #[serde(default)]
+    pub allowlisted_domains: Vec<String>,
 }
 
 /// Privacy settings
@@ -84,17 +253,232 @@ pub struct PrivacySettings {
     pub clear_cookies_on_exit: bool,
     /// Block third-party cookies
     pub block_third_party_cookies: bool,
+    /// Keep a separate HTTP cache for each top-level site, so one site
+    /// cannot tell from load timings what another has cached
+    #[serde(default = "default_true")]
+    pub partition_cache: bool,
+}
+
+/// Security settings
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecuritySettings {
+    /// Load http subresources on https pages even when the https upgrade
+    /// fails; the page is still reported as mixed content
+    pub allow_mixed_content: bool,
+}
+
+/// Content loading defaults
+///
+/// Per-site overrides are kept separately; see [`ContentSettings`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContentDefaults {
+    /// Run JavaScript on pages
+    pub javascript: bool,
+    /// Load images
+    pub images: bool,
+    /// Encoding for documents that declare no charset (e.g.
+    /// "windows-1252"); empty means UTF-8
+    pub default_encoding: String,
 }
 
 /// Appearance settings
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AppearanceSettings {
-    /// Theme: "light", "dark", or "auto"
+    /// Theme: "light", "dark", or "auto" (see [`Theme`])
     pub theme: String,
     /// Default zoom level
     pub default_zoom: f64,
 }
 
+/// Accessibility overrides applied to every page
+///
+/// Turned into a user stylesheet by the webview layer.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilitySettings {
+    /// Minimum font size in CSS pixels; 0 disables the minimum
+    pub min_font_px: u32,
+    /// Force a high-contrast color scheme
+    pub force_high_contrast: bool,
+    /// Disable animations and transitions
+    pub reduce_motion: bool,
+    /// Stylesheet appended after the generated rules; empty means none
+    pub custom_css_path: String,
+}
+
+impl AccessibilitySettings {
+    /// Path of the custom stylesheet, if one is configured
+    pub fn custom_css_path(&self) -> Option<PathBuf> {
+        let path = self.custom_css_path.trim();
+        (!path.is_empty()).then(|| PathBuf::from(path))
+    }
+}
+
+/// Default fonts and sizes
+///
+/// Used for text whose page sets no font of its own. An empty family
+/// leaves the webview's default in place; sizes are in CSS pixels.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FontSettings {
+    /// Family for text with no generic family, e.g. "DejaVu Serif"
+    pub standard_family: String,
+    /// Family for `serif`
+    pub serif_family: String,
+    /// Family for `sans-serif`
+    pub sans_serif_family: String,
+    /// Family for `monospace`
+    pub monospace_family: String,
+    /// Size of proportional text
+    pub default_font_size: u32,
+    /// Size of monospace text
+    pub default_monospace_size: u32,
+    /// Smallest size text is shown at; 0 disables the minimum
+    pub minimum_font_size: u32,
+}
+
+impl FontSettings {
+    /// Configured families with their setting names, skipping empty ones
+    pub fn families(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [
+            ("standard_family", self.standard_family.as_str()),
+            ("serif_family", self.serif_family.as_str()),
+            ("sans_serif_family", self.sans_serif_family.as_str()),
+            ("monospace_family", self.monospace_family.as_str()),
+        ]
+        .into_iter()
+        .filter(|(_, family)| !family.trim().is_empty())
+    }
+
+    /// Warnings for configured families that are not installed
+    ///
+    /// # Arguments
+    ///
+    /// * `installed` - Font families found on the system
+    ///
+    /// # Returns
+    ///
+    /// One warning per unknown family; the webview falls back to its own
+    /// default for those.
+    pub fn unknown_families(&self, installed: &InstalledFonts) -> Vec<String> {
+        self.families()
+            .filter(|(_, family)| !installed.contains(family))
+            .map(|(key, family)| {
+                format!(
+                    "fonts.{}: \"{}\" is not installed; the default font is used",
+                    key, family
+                )
+            })
+            .collect()
+    }
+}
+
+/// Navigation performance budgets
+///
+/// A completed navigation over any budget is logged as a slow page. A
+/// budget of 0 disables that check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PerformanceSettings {
+    /// Longest acceptable time from navigation start to document loaded
+    pub budget_page_load_ms: u64,
+    /// Longest acceptable time to the document's first byte
+    pub budget_ttfb_ms: u64,
+    /// Most bytes a page and its subresources should transfer
+    pub budget_total_bytes: u64,
+    /// Most subresources a page should request
+    pub budget_subresource_count: u64,
+}
+
+/// Built-in extension settings
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExtensionSettings {
+    /// IDs of extensions that should not be loaded
+    pub disabled: Vec<String>,
+}
+
+/// WebDriver server settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebDriverSettings {
+    /// Interface the server binds to
+    pub bind_address: String,
+    /// Accept connections from other machines; required to bind anywhere
+    /// but a loopback address
+    pub allow_remote: bool,
+    /// Bearer token every request must carry; empty means none (one is
+    /// generated when remote access is allowed)
+    pub auth_token: String,
+    /// Maximum number of concurrent sessions
+    pub max_sessions: u32,
+    /// Sustained requests per second allowed from one IP; 0 disables the
+    /// limit
+    pub requests_per_second: u32,
+    /// Requests one IP may make at once before the sustained rate applies
+    pub burst: u32,
+    /// Largest accepted request body in KiB
+    pub max_body_kb: u32,
+}
+
+impl BrowserSettings {
+    /// Idle period after which background tabs are hibernated, if any
+    pub fn hibernate_after(&self) -> Option<Duration> {
+        (self.hibernate_after_hours > 0)
+            .then(|| Duration::from_secs(u64::from(self.hibernate_after_hours) * 60 * 60))
+    }
+
+    /// How long a database query waits for a lock held by another connection
+    pub fn db_busy_timeout(&self) -> Duration {
+        Duration::from_millis(u64::from(self.db_busy_timeout_ms))
+    }
+
+    /// How long a link must stay hovered before its preview is fetched
+    pub fn link_preview_dwell(&self) -> Duration {
+        Duration::from_millis(u64::from(self.link_preview_dwell_ms))
+    }
+}
+
+impl NetworkSettings {
+    /// The metered connection budget in bytes, if any
+    pub fn metered_budget_bytes(&self) -> Option<u64> {
+        (self.metered_budget_mb > 0).then(|| u64::from(self.metered_budget_mb) * 1024 * 1024)
+    }
+
+    /// The configured proxy, if any
+    pub fn proxy_url(&self) -> Option<&str> {
+        let proxy = self.proxy.trim();
+        (!proxy.is_empty()).then_some(proxy)
+    }
+
+    /// The configured User-Agent, if any
+    pub fn user_agent(&self) -> Option<&str> {
+        let user_agent = self.user_agent.trim();
+        (!user_agent.is_empty()).then_some(user_agent)
+    }
+}
+
+impl WebDriverSettings {
+    /// The configured bearer token, if any
+    pub fn auth_token(&self) -> Option<&str> {
+        let token = self.auth_token.trim();
+        (!token.is_empty()).then_some(token)
+    }
+}
+
+impl ExtensionSettings {
+    /// Check whether an extension is enabled
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - Extension ID
+    pub fn is_enabled(&self, id: &str) -> bool {
+        !self.disabled.iter().any(|d| d == id)
+    }
+}
+
 /// Network configuration subset for network components
 #[derive(Debug, Clone, PartialEq)]
 pub struct NetworkConfig {
@@ -108,6 +492,23 @@ pub struct NetworkConfig {
     pub enable_cache: bool,
     /// Cache size in megabytes
     pub cache_size_mb: u32,
+    /// Where the cache keeps responses
+    pub cache_backend: CacheBackend,
+    /// Database persistent cookies are kept in; `None` for memory only
+    pub cookie_file: Option<PathBuf>,
+    /// Proxy URL for all traffic; `None` connects directly
+    pub proxy: Option<String>,
+    /// Hosts reached without the proxy
+    pub no_proxy: Vec<String>,
+}
+
+/// Where the HTTP cache keeps responses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CacheBackend {
+    /// In memory only; every session starts with an empty cache
+    Memory,
+    /// In a database in this directory, kept across restarts
+    Disk(PathBuf),
 }
 
 /// AdBlock configuration subset for adblock components
@@ -119,6 +520,8 @@ pub struct AdBlockConfig {
     pub update_filters_on_startup: bool,
     /// Custom filter rules
     pub custom_filters: Vec<String>,
+    /// Sites, with their subdomains, whose pages are never ad blocked
+    pub allowlisted_domains: Vec<String>,
 }
 
 /// Shell configuration subset for shell components
@@ -132,37 +535,186 @@ pub struct ShellConfig {
     pub theme: String,
     /// Default zoom level
     pub default_zoom: f64,
+    /// Ctrl+Tab cycles tabs in most-recently-used order
+    pub ctrl_tab_mru: bool,
+    /// User-Agent the webview sends, or `None` for its own
+    pub user_agent: Option<String>,
+}
+
+// Defaults of settings added after the first release, so that files
+// written before them still load. The `Default` impls use them too.
+
+fn default_true() -> bool {
+    true
 }
 
-impl Default for Config {
+fn default_locale() -> String {
+    "en-US".to_string()
+}
+
+fn default_session_snapshot_secs() -> u32 {
+    15
+}
+
+fn default_crash_recovery() -> String {
+    "prompt".to_string()
+}
+
+fn default_db_busy_timeout_ms() -> u32 {
+    5_000
+}
+
+fn default_link_preview_dwell_ms() -> u32 {
+    800
+}
+
+fn default_max_redirects() -> u32 {
+    20
+}
+
+fn default_cache_backend() -> String {
+    "memory".to_string()
+}
+
+fn default_subscriptions() -> Vec<String> {
+    vec![
+        "https://easylist.to/easylist/easylist.txt".to_string(),
+        "https://easylist.to/easylist/easyprivacy.txt".to_string(),
+    ]
+}
+
+impl Default for BrowserSettings {
     fn default() -> Self {
         Self {
-            browser: BrowserSettings {
-                homepage: "https://www.google.com".to_string(),
-                enable_devtools: true,
-                default_search_engine: "google".to_string(),
-            },
-            network: NetworkSettings {
-                max_connections_per_host: 6,
-                timeout_seconds: 30,
-                enable_cookies: true,
-                enable_cache: true,
-                cache_size_mb: 500,
-            },
-            adblock: AdBlockSettings {
-                enabled: true,
-                update_filters_on_startup: false,
-                custom_filters: vec![],
-            },
-            privacy: PrivacySettings {
-                do_not_track: true,
-                clear_cookies_on_exit: false,
-                block_third_party_cookies: false,
-            },
-            appearance: AppearanceSettings {
-                theme: "auto".to_string(),
-                default_zoom: 1.0,
-            },
+            homepage: "https://www.google.com".to_string(),
+            enable_devtools: true,
+            default_search_engine: "google".to_string(),
+            ctrl_tab_mru: false,
+            locale: default_locale(),
+            session_dir: String::new(),
+            session_snapshot_secs: default_session_snapshot_secs(),
+            crash_recovery: default_crash_recovery(),
+            restore_session: false,
+            hibernation_dir: String::new(),
+            hibernate_after_hours: 0,
+            profile_dir: String::new(),
+            db_busy_timeout_ms: default_db_busy_timeout_ms(),
+            link_previews: false,
+            link_preview_dwell_ms: default_link_preview_dwell_ms(),
+            link_preview_excluded_hosts: vec![],
+            duplicate_tab_in_background: false,
+            confirm_close_pinned_tabs: true,
+            message_trace_buffer: 0,
+        }
+    }
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            max_connections_per_host: 6,
+            timeout_seconds: 30,
+            max_redirects: default_max_redirects(),
+            enable_cookies: true,
+            enable_cache: true,
+            cache_size_mb: 500,
+            cache_backend: default_cache_backend(),
+            cache_dir: String::new(),
+            cookie_file: String::new(),
+            download_dir: String::new(),
+            retry_transient_failures: true,
+            har_embed_bodies: false,
+            custom_headers: vec![],
+            metered_budget_mb: 0,
+            proxy: String::new(),
+            no_proxy: vec![],
+            dns_prefetch: true,
+            user_agent: String::new(),
+            extra_headers: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for AdBlockSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            update_filters_on_startup: false,
+            custom_filters: vec![],
+            seed_filters: true,
+            filter_list_dir: String::new(),
+            default_subscriptions: default_subscriptions(),
+            show_placeholders: false,
+            allowlisted_domains: vec![],
+        }
+    }
+}
+
+impl Default for PrivacySettings {
+    fn default() -> Self {
+        Self {
+            do_not_track: true,
+            clear_cookies_on_exit: false,
+            block_third_party_cookies: false,
+            partition_cache: true,
+        }
+    }
+}
+
+impl Default for ContentDefaults {
+    fn default() -> Self {
+        Self {
+            javascript: true,
+            images: true,
+            default_encoding: String::new(),
+        }
+    }
+}
+
+impl Default for FontSettings {
+    fn default() -> Self {
+        Self {
+            standard_family: String::new(),
+            serif_family: String::new(),
+            sans_serif_family: String::new(),
+            monospace_family: String::new(),
+            default_font_size: 16,
+            default_monospace_size: 13,
+            minimum_font_size: 0,
+        }
+    }
+}
+
+impl Default for AppearanceSettings {
+    fn default() -> Self {
+        Self {
+            theme: "auto".to_string(),
+            default_zoom: 1.0,
+        }
+    }
+}
+
+impl Default for PerformanceSettings {
+    fn default() -> Self {
+        Self {
+            budget_page_load_ms: 5_000,
+            budget_ttfb_ms: 800,
+            budget_total_bytes: 5 * 1024 * 1024,
+            budget_subresource_count: 150,
+        }
+    }
+}
+
+impl Default for WebDriverSettings {
+    fn default() -> Self {
+        Self {
+            bind_address: "127.0.0.1".to_string(),
+            allow_remote: false,
+            auth_token: String::new(),
+            max_sessions: 4,
+            requests_per_second: 50,
+            burst: 100,
+            max_body_kb: 10 * 1024,
         }
     }
 }
@@ -181,19 +733,93 @@ impl Config {
     ///
     /// Returns an error if the file exists but contains invalid TOML.
     pub fn load_or_default() -> Result<Self> {
-        let default_path = std::env::var("FRANKENBROWSER_CONFIG")
-            .unwrap_or_else(|_| "~/.config/frankenbrowser/config.toml".to_string());
-
-        let expanded_path = shellexpand::tilde(&default_path);
-        let path = Path::new(expanded_path.as_ref());
+        let path = Self::default_path();
 
         if path.exists() {
-            Self::load_from_file(path)
+            Self::load_from_file(&path)
         } else {
             Ok(Self::default())
         }
     }
 
+    /// Resolve the configuration file location
+    ///
+    /// Uses `FRANKENBROWSER_CONFIG` when set, otherwise
+    /// `~/.config/frankenbrowser/config.toml`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the tilde-expanded path (which may not exist).
+    pub fn default_path() -> PathBuf {
+        let default_path = std::env::var("FRANKENBROWSER_CONFIG")
+            .unwrap_or_else(|_| "~/.config/frankenbrowser/config.toml".to_string());
+
+        PathBuf::from(shellexpand::tilde(&default_path).as_ref())
+    }
+
+    /// Default location of the cached filter lists
+    ///
+    /// # Returns
+    ///
+    /// Returns `filter-lists` in the config file's directory.
+    pub fn default_filter_list_dir() -> PathBuf {
+        Self::default_path().with_file_name("filter-lists")
+    }
+
+    /// Default location of the session snapshots
+    ///
+    /// # Returns
+    ///
+    /// Returns `sessions` in the config file's directory.
+    pub fn default_session_dir() -> PathBuf {
+        Self::default_path().with_file_name("sessions")
+    }
+
+    /// Default location of the disk cache
+    ///
+    /// # Returns
+    ///
+    /// Returns `http-cache` in the config file's directory.
+    pub fn default_cache_dir() -> PathBuf {
+        Self::default_path().with_file_name("http-cache")
+    }
+
+    /// Default location of hibernated tabs
+    ///
+    /// # Returns
+    ///
+    /// Returns `hibernated-tabs` in the config file's directory.
+    pub fn default_hibernation_dir() -> PathBuf {
+        Self::default_path().with_file_name("hibernated-tabs")
+    }
+
+    /// Default folder downloads are saved to
+    ///
+    /// # Returns
+    ///
+    /// Returns `Downloads` in the home directory.
+    pub fn default_download_dir() -> PathBuf {
+        PathBuf::from(shellexpand::tilde("~/Downloads").as_ref())
+    }
+
+    /// Default location of the history and bookmarks databases
+    ///
+    /// # Returns
+    ///
+    /// Returns `profile` in the config file's directory.
+    pub fn default_profile_dir() -> PathBuf {
+        Self::default_path().with_file_name("profile")
+    }
+
+    /// Default location of the `frankenbrowser console` line history
+    ///
+    /// # Returns
+    ///
+    /// Returns `console_history` in the config file's directory.
+    pub fn default_console_history_path() -> PathBuf {
+        Self::default_path().with_file_name("console_history")
+    }
+
     /// Load configuration from a specific file
     ///
     /// # Arguments
@@ -210,6 +836,7 @@ impl Config {
     /// - The file doesn't exist
     /// - The file cannot be read
     /// - The file contains invalid TOML
+    /// - `appearance.theme` is not a [`Theme`]
     pub fn load_from_file(path: &Path) -> Result<Self> {
         let content = std::fs::read_to_string(path).map_err(|e| {
             BrowserError::Other(anyhow::anyhow!("Failed to read config file: {}", e))
@@ -217,6 +844,7 @@ impl Config {
 
         let config: Config = toml::from_str(&content)
             .map_err(|e| BrowserError::Other(anyhow::anyhow!("Failed to parse TOML: {}", e)))?;
+        Theme::parse(&config.appearance.theme)?;
 
         Ok(config)
     }
@@ -266,6 +894,17 @@ impl Config {
             enable_cookies: self.network.enable_cookies,
             enable_cache: self.network.enable_cache,
             cache_size_mb: self.network.cache_size_mb,
+            cache_backend: match self.network.cache_backend.as_str() {
+                "disk" if self.network.cache_dir.is_empty() => {
+                    CacheBackend::Disk(Self::default_cache_dir())
+                }
+                "disk" => CacheBackend::Disk(PathBuf::from(&self.network.cache_dir)),
+                _ => CacheBackend::Memory,
+            },
+            cookie_file: (!self.network.cookie_file.is_empty())
+                .then(|| PathBuf::from(&self.network.cookie_file)),
+            proxy: self.network.proxy_url().map(str::to_string),
+            no_proxy: self.network.no_proxy.clone(),
         }
     }
 
@@ -277,9 +916,46 @@ impl Config {
             enabled: self.adblock.enabled,
             update_filters_on_startup: self.adblock.update_filters_on_startup,
             custom_filters: self.adblock.custom_filters.clone(),
+            allowlisted_domains: self.adblock.allowlisted_domains.clone(),
         }
     }
 
+    /// Resolve the `network.custom_headers` rules
+    ///
+    /// # Returns
+    ///
+    /// Returns the rules with `${env:VAR}` values substituted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a rule is invalid or references an unset
+    /// environment variable.
+    pub fn custom_headers(&self) -> Result<CustomHeaders> {
+        CustomHeaders::resolve(&self.network.custom_headers)
+    }
+
+    /// Build the content settings resolver from the `[content]` defaults
+    ///
+    /// Returns a `ContentSettings` without per-site overrides; load them
+    /// with [`ContentSettings::load_overrides`].
+    pub fn content_settings(&self) -> ContentSettings {
+        ContentSettings::new(self.content.clone())
+    }
+
+    /// Warnings for `[fonts]` families that are not installed
+    ///
+    /// Unknown families are not an error: the webview uses its default
+    /// font instead. Nothing is reported when the installed fonts cannot
+    /// be listed; see [`InstalledFonts::detect`].
+    pub fn font_warnings(&self) -> Vec<String> {
+        if self.fonts.families().next().is_none() {
+            return Vec::new();
+        }
+        InstalledFonts::detect()
+            .map(|installed| self.fonts.unknown_families(&installed))
+            .unwrap_or_default()
+    }
+
     /// Extract shell configuration subset
     ///
     /// Returns a `ShellConfig` containing settings relevant to the shell/UI.
@@ -289,6 +965,8 @@ impl Config {
             enable_devtools: self.browser.enable_devtools,
             theme: self.appearance.theme.clone(),
             default_zoom: self.appearance.default_zoom,
+            ctrl_tab_mru: self.browser.ctrl_tab_mru,
+            user_agent: self.network.user_agent().map(str::to_string),
         }
     }
 }
@@ -318,6 +996,10 @@ mod tests {
         assert!(config.network.enable_cookies);
         assert!(config.network.enable_cache);
         assert_eq!(config.network.cache_size_mb, 500);
+        assert!(config.network.retry_transient_failures);
+        assert!(!config.network.har_embed_bodies);
+        assert!(config.network.custom_headers.is_empty());
+        assert!(config.custom_headers().unwrap().is_empty());
     }
 
     #[test]
@@ -326,6 +1008,108 @@ mod tests {
         assert!(config.adblock.enabled);
         assert!(!config.adblock.update_filters_on_startup);
         assert!(config.adblock.custom_filters.is_empty());
+        assert_eq!(config.adblock.default_subscriptions.len(), 2);
+        assert!(config.adblock.allowlisted_domains.is_empty());
+    }
+
+    #[test]
+    fn test_config_default_filter_seeding() {
+        let config = Config::default();
+        assert!(config.adblock.seed_filters);
+        assert!(config.adblock.filter_list_dir.is_empty());
+        assert_eq!(
+            Config::default_filter_list_dir().parent(),
+            Config::default_path().parent()
+        );
+    }
+
+    #[test]
+    fn test_config_metered_budget() {
+        let mut config = Config::default();
+        assert_eq!(config.network.metered_budget_bytes(), None);
+        config.network.metered_budget_mb = 2;
+        assert_eq!(config.network.metered_budget_bytes(), Some(2 * 1024 * 1024));
+    }
+
+    #[test]
+    fn test_config_proxy() {
+        let mut config = Config::default();
+        assert_eq!(config.network.proxy_url(), None);
+        config.network.proxy = " http://proxy.lan:3128 ".to_string();
+        assert_eq!(config.network.proxy_url(), Some("http://proxy.lan:3128"));
+        assert!(config.validate().is_empty());
+
+        config.network.proxy = "socks5://proxy.lan:1080".to_string();
+        config.network.no_proxy = vec![".internal.example.com".to_string()];
+        assert!(config.validate().is_empty());
+        let network = config.network_config();
+        assert_eq!(network.proxy.as_deref(), Some("socks5://proxy.lan:1080"));
+        assert_eq!(network.no_proxy, vec![".internal.example.com"]);
+
+        config.network.proxy = "ftp://proxy.lan:21".to_string();
+        assert_eq!(config.validate().len(), 1);
+    }
+
+    #[test]
+    fn test_config_default_session_snapshots() {
+        let mut config = Config::default();
+        assert!(config.browser.session_dir.is_empty());
+        assert_eq!(config.browser.session_snapshot_secs, 15);
+        assert_eq!(config.browser.crash_recovery, "prompt");
+        assert_eq!(
+            Config::default_session_dir().parent(),
+            Config::default_path().parent()
+        );
+
+        config.browser.crash_recovery = "ask".to_string();
+        let diagnostics = config.validate();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].starts_with("browser.crash_recovery"));
+
+        config.browser.crash_recovery = "prompt".to_string();
+        config.browser.restore_session = true;
+        let diagnostics = config.validate();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].starts_with("browser.restore_session"));
+        config.browser.session_dir = "/tmp/sessions".to_string();
+        assert!(config.validate().is_empty());
+    }
+
+    #[test]
+    fn test_config_default_download_dir() {
+        let config = Config::default();
+        assert!(config.network.download_dir.is_empty());
+        assert!(Config::default_download_dir().ends_with("Downloads"));
+    }
+
+    #[test]
+    fn test_config_default_hibernation() {
+        let mut config = Config::default();
+        assert!(config.browser.hibernation_dir.is_empty());
+        assert_eq!(config.browser.hibernate_after(), None);
+        assert_eq!(
+            Config::default_hibernation_dir().parent(),
+            Config::default_path().parent()
+        );
+
+        config.browser.hibernate_after_hours = 48;
+        assert_eq!(
+            config.browser.hibernate_after(),
+            Some(Duration::from_secs(48 * 60 * 60))
+        );
+    }
+
+    #[test]
+    fn test_config_default_accessibility() {
+        let mut config = Config::default();
+        assert_eq!(config.accessibility, AccessibilitySettings::default());
+        assert_eq!(config.accessibility.custom_css_path(), None);
+
+        config.accessibility.custom_css_path = " /tmp/user.css ".to_string();
+        assert_eq!(
+            config.accessibility.custom_css_path(),
+            Some(PathBuf::from("/tmp/user.css"))
+        );
     }
 
     #[test]
@@ -334,6 +1118,55 @@ mod tests {
         assert!(config.privacy.do_not_track);
         assert!(!config.privacy.clear_cookies_on_exit);
         assert!(!config.privacy.block_third_party_cookies);
+        assert!(config.privacy.partition_cache);
+    }
+
+    #[test]
+    fn test_config_security_allow_mixed_content() {
+        let mut config = Config::default();
+        assert!(!config.security.allow_mixed_content);
+
+        config
+            .set_path("security.allow_mixed_content", "true")
+            .unwrap();
+        assert!(config.security.allow_mixed_content);
+    }
+
+    #[test]
+    fn test_config_webdriver_settings() {
+        let mut config = Config::default();
+        assert_eq!(config.webdriver.bind_address, "127.0.0.1");
+        assert!(!config.webdriver.allow_remote);
+        assert_eq!(config.webdriver.auth_token(), None);
+
+        config.set_path("webdriver.auth_token", " s3cret ").unwrap();
+        config.webdriver.max_sessions = 0;
+        config.webdriver.bind_address = "example.com".to_string();
+        assert_eq!(config.webdriver.auth_token(), Some("s3cret"));
+        assert_eq!(config.validate().len(), 2);
+    }
+
+    #[test]
+    fn test_extension_settings_disabled_list() {
+        let mut config = Config::default();
+        assert!(config.extensions.is_enabled("cookie-banner-rejecter"));
+
+        config
+            .set_path("extensions.disabled", r#"["cookie-banner-rejecter"]"#)
+            .unwrap();
+        assert!(!config.extensions.is_enabled("cookie-banner-rejecter"));
+        assert!(config.extensions.is_enabled("other"));
+    }
+
+    #[test]
+    fn test_config_default_content_settings() {
+        let config = Config::default();
+        assert!(config.content.javascript);
+        assert!(config.content.images);
+
+        let settings = config.content_settings();
+        let effective = settings.settings_for("https://example.com");
+        assert!(effective.javascript && effective.images);
     }
 
     #[test]
@@ -389,6 +1222,19 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_load_unknown_theme_returns_error() {
+        let temp_file = NamedTempFile::new().unwrap();
+        std::fs::write(
+            temp_file.path(),
+            Config::default_toml().replace("theme = \"auto\"", "theme = \"neon\""),
+        )
+        .unwrap();
+
+        let err = Config::load_from_file(temp_file.path()).unwrap_err();
+        assert!(err.to_string().contains("appearance.theme"));
+    }
+
     #[test]
     fn test_load_custom_config_values() {
         let custom_toml = r#"
@@ -482,6 +1328,25 @@ default_zoom = 1.5
         assert!(network_config.enable_cookies);
         assert!(network_config.enable_cache);
         assert_eq!(network_config.cache_size_mb, 500);
+        assert_eq!(network_config.cache_backend, CacheBackend::Memory);
+        assert_eq!(network_config.cookie_file, None);
+
+        let mut config = Config::default();
+        config.network.cache_backend = "disk".to_string();
+        assert_eq!(
+            config.network_config().cache_backend,
+            CacheBackend::Disk(Config::default_cache_dir())
+        );
+        config.network.cache_dir = "/tmp/cache".to_string();
+        assert_eq!(
+            config.network_config().cache_backend,
+            CacheBackend::Disk(PathBuf::from("/tmp/cache"))
+        );
+        config.network.cookie_file = "/tmp/cookies.db".to_string();
+        assert_eq!(
+            config.network_config().cookie_file,
+            Some(PathBuf::from("/tmp/cookies.db"))
+        );
     }
 
     #[test]
@@ -503,6 +1368,7 @@ default_zoom = 1.5
         assert!(shell_config.enable_devtools);
         assert_eq!(shell_config.theme, "auto");
         assert_eq!(shell_config.default_zoom, 1.0);
+        assert!(!shell_config.ctrl_tab_mru);
     }
 
     #[test]
@@ -511,6 +1377,9 @@ default_zoom = 1.5
         config.network.max_connections_per_host = 20;
         config.adblock.enabled = false;
         config.appearance.theme = "dark".to_string();
+        config.browser.ctrl_tab_mru = true;
+        assert_eq!(config.shell_config().user_agent, None);
+        config.network.user_agent = " FrankenTest/2.0 ".to_string();
 
         let network_config = config.network_config();
         let adblock_config = config.adblock_config();
@@ -519,6 +1388,8 @@ default_zoom = 1.5
         assert_eq!(network_config.max_connections_per_host, 20);
         assert!(!adblock_config.enabled);
         assert_eq!(shell_config.theme, "dark");
+        assert!(shell_config.ctrl_tab_mru);
+        assert_eq!(shell_config.user_agent.as_deref(), Some("FrankenTest/2.0"));
     }
 
     // ========================================
@@ -0,0 +1,1183 @@
+//! Configuration schema, path-based access, and validation
+//!
+//! The schema is a hand-maintained table describing every field of
+//! [`Config`]: its dotted path, value type and documentation. Defaults are
+//! not duplicated here; they are read from `Config::default()` so the two
+//! cannot drift. When adding a field to `Config`, add its descriptor to
+//! [`FIELDS`] as well — the schema completeness test will fail otherwise.
+
+use crate::custom_headers::{extra_header_problems, rule_problems};
+use crate::theme::{invalid_theme, Theme};
+use crate::Config;
+use shared_types::{BrowserError, Result};
+use std::path::Path;
+
+/// Value type of a configuration field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    /// `true` / `false`
+    Bool,
+    /// Non-negative integer
+    Integer,
+    /// Floating point number
+    Float,
+    /// String
+    String,
+    /// Array of strings
+    StringArray,
+    /// Array of tables
+    TableArray,
+    /// Table of string values
+    StringTable,
+}
+
+impl FieldType {
+    /// Human-readable type name used in diagnostics
+    pub fn name(self) -> &'static str {
+        match self {
+            FieldType::Bool => "boolean",
+            FieldType::Integer => "integer",
+            FieldType::Float => "float",
+            FieldType::String => "string",
+            FieldType::StringArray => "array of strings",
+            FieldType::TableArray => "array of tables",
+            FieldType::StringTable => "table of strings",
+        }
+    }
+}
+
+/// Description of a single configuration field
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDescriptor {
+    /// Dotted path, e.g. `network.timeout_seconds`
+    pub path: &'static str,
+    /// Value type
+    pub ty: FieldType,
+    /// Default value rendered as TOML
+    pub default: String,
+    /// Documentation for the field
+    pub doc: &'static str,
+}
+
+/// Section documentation, in file order
+const SECTIONS: &[(&str, &str)] = &[
+    ("browser", "Browser-specific settings"),
+    ("network", "Network configuration"),
+    ("adblock", "AdBlock configuration"),
+    ("privacy", "Privacy settings"),
+    ("security", "Security settings"),
+    ("content", "Content loading defaults (overridable per site)"),
+    ("appearance", "Appearance settings"),
+    ("accessibility", "Accessibility overrides for every page"),
+    ("fonts", "Default fonts and sizes"),
+    ("performance", "Navigation performance budgets"),
+    ("extensions", "Built-in extensions"),
+    ("webdriver", "WebDriver automation server"),
+];
+
+/// Every configuration field: (path, type, documentation)
+const FIELDS: &[(&str, FieldType, &str)] = &[
+    (
+        "browser.homepage",
+        FieldType::String,
+        "Default homepage URL",
+    ),
+    (
+        "browser.enable_devtools",
+        FieldType::Bool,
+        "Enable developer tools",
+    ),
+    (
+        "browser.default_search_engine",
+        FieldType::String,
+        "Default search engine",
+    ),
+    (
+        "browser.ctrl_tab_mru",
+        FieldType::Bool,
+        "Ctrl+Tab cycles tabs in most-recently-used order",
+    ),
+    (
+        "browser.locale",
+        FieldType::String,
+        "Language tag for dates and numbers on internal pages",
+    ),
+    (
+        "browser.session_dir",
+        FieldType::String,
+        "Directory for session snapshots (empty: no snapshots)",
+    ),
+    (
+        "browser.session_snapshot_secs",
+        FieldType::Integer,
+        "Seconds between session snapshots while tabs are changing",
+    ),
+    (
+        "browser.crash_recovery",
+        FieldType::String,
+        "After a crash: \"prompt\", \"restore\" or \"discard\"",
+    ),
+    (
+        "browser.restore_session",
+        FieldType::Bool,
+        "Reopen the last session's tabs and their back/forward history at startup",
+    ),
+    (
+        "browser.hibernation_dir",
+        FieldType::String,
+        "Directory for hibernated tabs (empty: no hibernation)",
+    ),
+    (
+        "browser.hibernate_after_hours",
+        FieldType::Integer,
+        "Hours before an idle background tab is hibernated (0: never)",
+    ),
+    (
+        "browser.profile_dir",
+        FieldType::String,
+        "Directory for history.db and bookmarks.db (empty: in memory)",
+    ),
+    (
+        "browser.db_busy_timeout_ms",
+        FieldType::Integer,
+        "Milliseconds a database query waits for a lock before giving up",
+    ),
+    (
+        "browser.link_previews",
+        FieldType::Bool,
+        "Fetch a title and summary for hovered links",
+    ),
+    (
+        "browser.link_preview_dwell_ms",
+        FieldType::Integer,
+        "Milliseconds a link must stay hovered before its preview is fetched",
+    ),
+    (
+        "browser.link_preview_excluded_hosts",
+        FieldType::StringArray,
+        "Hosts (and subdomains) never fetched for link previews",
+    ),
+    (
+        "browser.duplicate_tab_in_background",
+        FieldType::Bool,
+        "Leave the original tab active when duplicating a tab",
+    ),
+    (
+        "browser.confirm_close_pinned_tabs",
+        FieldType::Bool,
+        "Ask before Ctrl+W closes a pinned tab",
+    ),
+    (
+        "browser.message_trace_buffer",
+        FieldType::Integer,
+        "Recent message traces kept for about:diagnostics (0 = off)",
+    ),
+    (
+        "network.max_connections_per_host",
+        FieldType::Integer,
+        "Maximum connections per host",
+    ),
+    (
+        "network.timeout_seconds",
+        FieldType::Integer,
+        "Request timeout in seconds",
+    ),
+    (
+        "network.max_redirects",
+        FieldType::Integer,
+        "Redirects a request may follow before failing as a redirect loop",
+    ),
+    ("network.enable_cookies", FieldType::Bool, "Enable cookies"),
+    ("network.enable_cache", FieldType::Bool, "Enable cache"),
+    (
+        "network.cache_size_mb",
+        FieldType::Integer,
+        "Cache size in megabytes",
+    ),
+    (
+        "network.cache_backend",
+        FieldType::String,
+        "Keep cached responses in \"memory\" or on \"disk\" across restarts",
+    ),
+    (
+        "network.cache_dir",
+        FieldType::String,
+        "Directory of the disk cache (empty: http-cache next to the config file)",
+    ),
+    (
+        "network.cookie_file",
+        FieldType::String,
+        "SQLite file that keeps cookies across restarts (empty: memory only)",
+    ),
+    (
+        "network.download_dir",
+        FieldType::String,
+        "Folder downloads are saved to (empty: ~/Downloads)",
+    ),
+    (
+        "network.retry_transient_failures",
+        FieldType::Bool,
+        "Retry a page load once when it fails for a transient reason",
+    ),
+    (
+        "network.har_embed_bodies",
+        FieldType::Bool,
+        "Embed text response bodies in exported HAR files",
+    ),
+    (
+        "network.custom_headers",
+        FieldType::TableArray,
+        "Per-host request headers: { host_pattern, headers, include_subresources, allow_sensitive }",
+    ),
+    (
+        "network.metered_budget_mb",
+        FieldType::Integer,
+        "Session traffic in megabytes that pauses downloads and prefetching (0 = no budget)",
+    ),
+    (
+        "network.proxy",
+        FieldType::String,
+        "Proxy as http://, https:// or socks5://host:port (empty = connect directly)",
+    ),
+    (
+        "network.no_proxy",
+        FieldType::StringArray,
+        "Hosts reached without the proxy (host, .domain for it and its subdomains, or *)",
+    ),
+    (
+        "network.dns_prefetch",
+        FieldType::Bool,
+        "Resolve hosts of links in view and dns-prefetch hints ahead of a click",
+    ),
+    (
+        "network.user_agent",
+        FieldType::String,
+        "User-Agent for the network stack and the webview (empty = the browser's own)",
+    ),
+    (
+        "network.extra_headers",
+        FieldType::StringTable,
+        "Headers sent with every request unless it sets them: { \"X-Name\" = \"value\" }",
+    ),
+    ("adblock.enabled", FieldType::Bool, "Enable ad blocking"),
+    (
+        "adblock.update_filters_on_startup",
+        FieldType::Bool,
+        "Update filters on startup",
+    ),
+    (
+        "adblock.custom_filters",
+        FieldType::StringArray,
+        "Custom filter rules",
+    ),
+    (
+        "adblock.seed_filters",
+        FieldType::Bool,
+        "Seed an empty filter list directory from the embedded list",
+    ),
+    (
+        "adblock.filter_list_dir",
+        FieldType::String,
+        "Directory of cached filter lists (empty: next to the config file)",
+    ),
+    (
+        "adblock.default_subscriptions",
+        FieldType::StringArray,
+        "Filter list URLs subscribed for a new profile",
+    ),
+    (
+        "adblock.show_placeholders",
+        FieldType::Bool,
+        "Show a click-to-load placeholder for blocked images and frames",
+    ),
+    (
+        "adblock.allowlisted_domains",
+        FieldType::StringArray,
+        "Sites (and their subdomains) that are never ad blocked",
+    ),
+    (
+        "privacy.do_not_track",
+        FieldType::Bool,
+        "Send Do Not Track header",
+    ),
+    (
+        "privacy.clear_cookies_on_exit",
+        FieldType::Bool,
+        "Clear cookies on exit",
+    ),
+    (
+        "privacy.block_third_party_cookies",
+        FieldType::Bool,
+        "Block third-party cookies",
+    ),
+    (
+        "privacy.partition_cache",
+        FieldType::Bool,
+        "Keep a separate HTTP cache for each top-level site",
+    ),
+    (
+        "security.allow_mixed_content",
+        FieldType::Bool,
+        "Load http subresources of https pages when the upgrade fails",
+    ),
+    (
+        "content.javascript",
+        FieldType::Bool,
+        "Run JavaScript on pages",
+    ),
+    ("content.images", FieldType::Bool, "Load images"),
+    (
+        "content.default_encoding",
+        FieldType::String,
+        "Encoding for documents that declare no charset (empty: UTF-8)",
+    ),
+    (
+        "appearance.theme",
+        FieldType::String,
+        "Theme: \"light\", \"dark\", or \"auto\"",
+    ),
+    (
+        "appearance.default_zoom",
+        FieldType::Float,
+        "Default zoom level",
+    ),
+    (
+        "accessibility.min_font_px",
+        FieldType::Integer,
+        "Minimum font size in CSS pixels (0: no minimum)",
+    ),
+    (
+        "accessibility.force_high_contrast",
+        FieldType::Bool,
+        "Force a high-contrast color scheme",
+    ),
+    (
+        "accessibility.reduce_motion",
+        FieldType::Bool,
+        "Disable animations and transitions",
+    ),
+    (
+        "accessibility.custom_css_path",
+        FieldType::String,
+        "Stylesheet appended after the generated rules (empty: none)",
+    ),
+    (
+        "fonts.standard_family",
+        FieldType::String,
+        "Family for text with no generic family (empty: webview default)",
+    ),
+    (
+        "fonts.serif_family",
+        FieldType::String,
+        "Family for serif text (empty: webview default)",
+    ),
+    (
+        "fonts.sans_serif_family",
+        FieldType::String,
+        "Family for sans-serif text (empty: webview default)",
+    ),
+    (
+        "fonts.monospace_family",
+        FieldType::String,
+        "Family for monospace text (empty: webview default)",
+    ),
+    (
+        "fonts.default_font_size",
+        FieldType::Integer,
+        "Size of proportional text in CSS pixels",
+    ),
+    (
+        "fonts.default_monospace_size",
+        FieldType::Integer,
+        "Size of monospace text in CSS pixels",
+    ),
+    (
+        "fonts.minimum_font_size",
+        FieldType::Integer,
+        "Smallest text size in CSS pixels (0: no minimum)",
+    ),
+    (
+        "performance.budget_page_load_ms",
+        FieldType::Integer,
+        "Slowest acceptable page load in milliseconds (0: no budget)",
+    ),
+    (
+        "performance.budget_ttfb_ms",
+        FieldType::Integer,
+        "Slowest acceptable time to first byte in milliseconds (0: no budget)",
+    ),
+    (
+        "performance.budget_total_bytes",
+        FieldType::Integer,
+        "Most bytes a page should transfer (0: no budget)",
+    ),
+    (
+        "performance.budget_subresource_count",
+        FieldType::Integer,
+        "Most subresources a page should request (0: no budget)",
+    ),
+    (
+        "extensions.disabled",
+        FieldType::StringArray,
+        "IDs of extensions that should not be loaded",
+    ),
+    (
+        "webdriver.bind_address",
+        FieldType::String,
+        "Interface the WebDriver server binds to",
+    ),
+    (
+        "webdriver.allow_remote",
+        FieldType::Bool,
+        "Accept WebDriver connections from other machines",
+    ),
+    (
+        "webdriver.auth_token",
+        FieldType::String,
+        "Bearer token required on every request (empty: none)",
+    ),
+    (
+        "webdriver.max_sessions",
+        FieldType::Integer,
+        "Maximum number of concurrent sessions",
+    ),
+    (
+        "webdriver.requests_per_second",
+        FieldType::Integer,
+        "Sustained requests per second per IP (0: unlimited)",
+    ),
+    (
+        "webdriver.burst",
+        FieldType::Integer,
+        "Requests per IP allowed at once before the sustained rate applies",
+    ),
+    (
+        "webdriver.max_body_kb",
+        FieldType::Integer,
+        "Largest accepted request body in KiB",
+    ),
+];
+
+fn config_error(message: String) -> BrowserError {
+    BrowserError::Other(anyhow::anyhow!(message))
+}
+
+/// Walk a dotted path through a TOML value tree
+fn lookup<'a>(root: &'a toml::Value, path: &str) -> Option<&'a toml::Value> {
+    path.split('.')
+        .try_fold(root, |value, segment| value.as_table()?.get(segment))
+}
+
+/// Find the schema entry for a path
+fn find_field(path: &str) -> Result<(&'static str, FieldType)> {
+    FIELDS
+        .iter()
+        .find(|(p, _, _)| *p == path)
+        .map(|(p, ty, _)| (*p, *ty))
+        .ok_or_else(|| config_error(format!("Unknown config path: {}", path)))
+}
+
+/// Parse a raw command-line string into a TOML value of the given type
+fn parse_raw(path: &str, ty: FieldType, raw: &str) -> Result<toml::Value> {
+    let mismatch = || {
+        config_error(format!(
+            "Type mismatch for {}: expected {}, got '{}'",
+            path,
+            ty.name(),
+            raw
+        ))
+    };
+
+    match ty {
+        FieldType::Bool => raw
+            .trim()
+            .parse::<bool>()
+            .map(toml::Value::Boolean)
+            .map_err(|_| mismatch()),
+        FieldType::Integer => match raw.trim().parse::<i64>() {
+            Ok(n) if (0..=u32::MAX as i64).contains(&n) => Ok(toml::Value::Integer(n)),
+            _ => Err(mismatch()),
+        },
+        FieldType::Float => match raw.trim().parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(toml::Value::Float(f)),
+            _ => Err(mismatch()),
+        },
+        FieldType::String => Ok(toml::Value::String(raw.to_string())),
+        FieldType::StringArray | FieldType::TableArray => {
+            let parsed: toml::Table =
+                toml::from_str(&format!("v = {}", raw)).map_err(|_| mismatch())?;
+            let item_matches = |item: &toml::Value| match ty {
+                FieldType::StringArray => item.is_str(),
+                _ => item.is_table(),
+            };
+            match parsed.get("v") {
+                Some(toml::Value::Array(items)) if items.iter().all(item_matches) => {
+                    Ok(toml::Value::Array(items.clone()))
+                }
+                _ => Err(mismatch()),
+            }
+        }
+        FieldType::StringTable => {
+            let parsed: toml::Table =
+                toml::from_str(&format!("v = {}", raw)).map_err(|_| mismatch())?;
+            match parsed.get("v") {
+                Some(toml::Value::Table(table)) if table.values().all(toml::Value::is_str) => {
+                    Ok(toml::Value::Table(table.clone()))
+                }
+                _ => Err(mismatch()),
+            }
+        }
+    }
+}
+
+/// Render a field's value as it is written in a config file
+///
+/// Tables render inline, so every field fits on one `key = value` line.
+fn render(value: &toml::Value) -> String {
+    match value {
+        toml::Value::Table(_) => to_edit_value(value).to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Convert a TOML value into a `toml_edit` value (no decor)
+fn to_edit_value(value: &toml::Value) -> toml_edit::Value {
+    match value {
+        toml::Value::Boolean(b) => (*b).into(),
+        toml::Value::Integer(n) => (*n).into(),
+        toml::Value::Float(f) => (*f).into(),
+        toml::Value::String(s) => s.as_str().into(),
+        toml::Value::Array(items) => {
+            let mut array = toml_edit::Array::new();
+            for item in items {
+                array.push_formatted(to_edit_value(item));
+            }
+            toml_edit::Value::Array(array)
+        }
+        toml::Value::Table(table) => {
+            let mut inline = toml_edit::InlineTable::new();
+            for (key, item) in table {
+                inline.insert(key.as_str(), to_edit_value(item));
+            }
+            toml_edit::Value::InlineTable(inline)
+        }
+        other => other
+            .to_string()
+            .parse::<toml_edit::Value>()
+            .unwrap_or_else(|_| other.to_string().as_str().into()),
+    }
+}
+
+impl Config {
+    /// Describe every configuration field
+    ///
+    /// # Returns
+    ///
+    /// Returns one descriptor per field, in file order, with defaults taken
+    /// from `Config::default()`.
+    pub fn schema() -> Vec<FieldDescriptor> {
+        let defaults = Config::default().to_value();
+        FIELDS
+            .iter()
+            .map(|(path, ty, doc)| FieldDescriptor {
+                path,
+                ty: *ty,
+                default: lookup(&defaults, path).map(render).unwrap_or_default(),
+                doc,
+            })
+            .collect()
+    }
+
+    /// Serialize the configuration into a TOML value tree
+    pub(crate) fn to_value(&self) -> toml::Value {
+        toml::Value::try_from(self).expect("Config always serializes to TOML")
+    }
+
+    /// Get a field's value by dotted path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Dotted path, e.g. `network.timeout_seconds`
+    ///
+    /// # Returns
+    ///
+    /// Returns the value rendered as TOML (strings are quoted).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is not a known field.
+    pub fn get_path(&self, path: &str) -> Result<String> {
+        find_field(path)?;
+        lookup(&self.to_value(), path)
+            .map(render)
+            .ok_or_else(|| config_error(format!("Unknown config path: {}", path)))
+    }
+
+    /// Set a field's value by dotted path
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Dotted path, e.g. `network.timeout_seconds`
+    /// * `raw` - New value; arrays use TOML syntax (`["a", "b"]`)
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is unknown or the value does not match
+    /// the field's type.
+    pub fn set_path(&mut self, path: &str, raw: &str) -> Result<()> {
+        let (path, ty) = find_field(path)?;
+        let value = parse_raw(path, ty, raw)?;
+
+        let mut root = self.to_value();
+        let (section, key) = path.split_once('.').expect("schema paths are dotted");
+        root.get_mut(section)
+            .and_then(|s| s.as_table_mut())
+            .ok_or_else(|| config_error(format!("Unknown config section: {}", section)))?
+            .insert(key.to_string(), value);
+
+        *self = root
+            .try_into()
+            .map_err(|e| config_error(format!("Invalid value for {}: {}", path, e)))?;
+        Ok(())
+    }
+
+    /// Check the configuration for semantic problems
+    ///
+    /// # Returns
+    ///
+    /// Returns a list of human-readable diagnostics; empty when valid.
+    pub fn validate(&self) -> Vec<String> {
+        let mut diagnostics = Vec::new();
+
+        if Theme::parse(&self.appearance.theme).is_err() {
+            diagnostics.push(invalid_theme(&self.appearance.theme));
+        }
+        if !(0.25..=5.0).contains(&self.appearance.default_zoom) {
+            diagnostics.push(format!(
+                "appearance.default_zoom: must be between 0.25 and 5.0, got {}",
+                self.appearance.default_zoom
+            ));
+        }
+        if self.network.timeout_seconds == 0 {
+            diagnostics.push("network.timeout_seconds: must be greater than 0".to_string());
+        }
+        if self.network.max_connections_per_host == 0 {
+            diagnostics
+                .push("network.max_connections_per_host: must be greater than 0".to_string());
+        }
+        if !["memory", "disk"].contains(&self.network.cache_backend.as_str()) {
+            diagnostics.push(format!(
+                "network.cache_backend: expected \"memory\" or \"disk\", got \"{}\"",
+                self.network.cache_backend
+            ));
+        }
+        if self.browser.homepage.trim().is_empty() {
+            diagnostics.push("browser.homepage: must not be empty".to_string());
+        }
+        if !["prompt", "restore", "discard"].contains(&self.browser.crash_recovery.as_str()) {
+            diagnostics.push(format!(
+                "browser.crash_recovery: expected \"prompt\", \"restore\" or \"discard\", got \"{}\"",
+                self.browser.crash_recovery
+            ));
+        }
+        if self.browser.restore_session && self.browser.session_dir.is_empty() {
+            diagnostics.push(
+                "browser.restore_session: needs browser.session_dir to save the session in"
+                    .to_string(),
+            );
+        }
+        if let Some(path) = self.accessibility.custom_css_path() {
+            if let Err(e) = std::fs::read_to_string(&path) {
+                diagnostics.push(format!(
+                    "accessibility.custom_css_path: cannot read {}: {}",
+                    path.display(),
+                    e
+                ));
+            }
+        }
+        if self.webdriver.max_sessions == 0 {
+            diagnostics.push("webdriver.max_sessions: must be greater than 0".to_string());
+        }
+        if self.webdriver.max_body_kb == 0 {
+            diagnostics.push("webdriver.max_body_kb: must be greater than 0".to_string());
+        }
+        if let Err(e) = self.webdriver.bind_address.parse::<std::net::IpAddr>() {
+            diagnostics.push(format!(
+                "webdriver.bind_address: \"{}\" is not an IP address: {}",
+                self.webdriver.bind_address, e
+            ));
+        }
+        if let Some(proxy) = self.network.proxy_url() {
+            if !["http://", "https://", "socks5://"]
+                .iter()
+                .any(|scheme| proxy.starts_with(scheme))
+            {
+                diagnostics.push(format!(
+                    "network.proxy: expected an http://, https:// or socks5://host:port URL, \
+                     got \"{}\"",
+                    proxy
+                ));
+            }
+        }
+        diagnostics.extend(rule_problems(&self.network.custom_headers));
+        diagnostics.extend(extra_header_problems(&self.network.extra_headers));
+
+        diagnostics
+    }
+
+    /// Load a configuration file and collect every diagnostic
+    ///
+    /// Reports parse errors, unknown keys and semantic validation problems.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the configuration file
+    ///
+    /// # Returns
+    ///
+    /// Returns the diagnostics; empty when the file is valid.
+    pub fn check_file(path: &Path) -> Vec<String> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => return vec![format!("Failed to read config file: {}", e)],
+        };
+
+        let raw: toml::Table = match toml::from_str(&content) {
+            Ok(raw) => raw,
+            Err(e) => return vec![format!("Failed to parse TOML: {}", e)],
+        };
+
+        let mut diagnostics = Vec::new();
+        for (section, value) in &raw {
+            match value.as_table() {
+                Some(table) => {
+                    for key in table.keys() {
+                        let path = format!("{}.{}", section, key);
+                        if find_field(&path).is_err() {
+                            diagnostics.push(format!("Unknown config key: {}", path));
+                        }
+                    }
+                }
+                None => diagnostics.push(format!("Unknown config key: {}", section)),
+            }
+        }
+
+        match toml::from_str::<Config>(&content) {
+            Ok(config) => diagnostics.extend(config.validate()),
+            Err(e) => diagnostics.push(format!("Invalid config: {}", e)),
+        }
+
+        diagnostics
+    }
+
+    /// Set a field in a configuration file, preserving comments and layout
+    ///
+    /// If the file does not exist it is created from
+    /// [`default_toml`](Config::default_toml) first.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the configuration file
+    /// * `key` - Dotted field path
+    /// * `raw` - New value
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path is unknown, the value has the wrong
+    /// type, the file cannot be parsed, or the result would not load.
+    pub fn set_in_file(path: &Path, key: &str, raw: &str) -> Result<()> {
+        let (key, ty) = find_field(key)?;
+        let value = parse_raw(key, ty, raw)?;
+
+        let content = if path.exists() {
+            std::fs::read_to_string(path)
+                .map_err(|e| config_error(format!("Failed to read config file: {}", e)))?
+        } else {
+            Self::default_toml()
+        };
+
+        let mut doc = content
+            .parse::<toml_edit::DocumentMut>()
+            .map_err(|e| config_error(format!("Failed to parse TOML: {}", e)))?;
+
+        let (section, field) = key.split_once('.').expect("schema paths are dotted");
+        if doc.get(section).is_none() {
+            doc[section] = toml_edit::table();
+        }
+        let table = doc[section]
+            .as_table_like_mut()
+            .ok_or_else(|| config_error(format!("{} is not a table", section)))?;
+
+        // Replace the value in place so the key (and the comments attached
+        // to it) is kept; carry over the old value's trailing decor too.
+        let mut new_value = to_edit_value(&value);
+        match table.get_mut(field) {
+            Some(item) => {
+                if let Some(existing) = item.as_value() {
+                    *new_value.decor_mut() = existing.decor().clone();
+                }
+                *item = toml_edit::Item::Value(new_value);
+            }
+            None => {
+                table.insert(field, toml_edit::Item::Value(new_value));
+            }
+        }
+
+        let updated = doc.to_string();
+        toml::from_str::<Config>(&updated)
+            .map_err(|e| config_error(format!("Invalid value for {}: {}", key, e)))?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| config_error(format!("Failed to create config directory: {}", e)))?;
+        }
+        std::fs::write(path, updated)
+            .map_err(|e| config_error(format!("Failed to write config file: {}", e)))
+    }
+
+    /// Render a fully commented default configuration file
+    ///
+    /// # Returns
+    ///
+    /// Returns TOML text generated from [`schema`](Config::schema).
+    pub fn default_toml() -> String {
+        let schema = Self::schema();
+        let mut out = String::from("# FrankenBrowser configuration\n");
+
+        for (section, doc) in SECTIONS {
+            out.push_str(&format!("\n# {}\n[{}]\n", doc, section));
+            for field in schema
+                .iter()
+                .filter(|f| f.path.split('.').next() == Some(section))
+            {
+                let key = &field.path[section.len() + 1..];
+                out.push_str(&format!(
+                    "# {} ({}, default: {})\n{} = {}\n",
+                    field.doc,
+                    field.ty.name(),
+                    field.default,
+                    key,
+                    field.default
+                ));
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    /// Number of fields in `Config`. Update deliberately when adding fields
+    /// (and add their descriptors to `FIELDS`).
+    const EXPECTED_FIELD_COUNT: usize = 79;
+
+    /// Count fields below `path`; a table-valued field counts once
+    fn count_leaves(path: &str, value: &toml::Value) -> usize {
+        let is_field = find_field(path).is_ok();
+        match value.as_table() {
+            Some(table) if !is_field => table
+                .iter()
+                .map(|(key, value)| match path {
+                    "" => count_leaves(key, value),
+                    _ => count_leaves(&format!("{}.{}", path, key), value),
+                })
+                .sum(),
+            _ => 1,
+        }
+    }
+
+    // ========================================
+    // Tests for schema
+    // ========================================
+
+    #[test]
+    fn test_schema_is_complete() {
+        let schema = Config::schema();
+        assert_eq!(schema.len(), EXPECTED_FIELD_COUNT);
+        assert_eq!(
+            count_leaves("", &Config::default().to_value()),
+            EXPECTED_FIELD_COUNT
+        );
+
+        let defaults = Config::default().to_value();
+        for field in &schema {
+            assert!(lookup(&defaults, field.path).is_some(), "{}", field.path);
+            assert!(!field.default.is_empty(), "{}", field.path);
+        }
+    }
+
+    #[test]
+    fn test_schema_defaults() {
+        let schema = Config::schema();
+        let timeout = schema
+            .iter()
+            .find(|f| f.path == "network.timeout_seconds")
+            .unwrap();
+        assert_eq!(timeout.ty, FieldType::Integer);
+        assert_eq!(timeout.default, "30");
+    }
+
+    #[test]
+    fn test_default_toml_loads_as_default_config() {
+        let text = Config::default_toml();
+        assert!(text.contains("# Request timeout in seconds"));
+        let parsed: Config = toml::from_str(&text).unwrap();
+        assert_eq!(parsed, Config::default());
+    }
+
+    // ========================================
+    // Tests for get/set
+    // ========================================
+
+    #[test]
+    fn test_get_path() {
+        let config = Config::default();
+        assert_eq!(config.get_path("network.timeout_seconds").unwrap(), "30");
+        assert_eq!(config.get_path("appearance.theme").unwrap(), "\"auto\"");
+        assert_eq!(config.get_path("adblock.custom_filters").unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_get_unknown_path() {
+        let config = Config::default();
+        let err = config.get_path("network.nope").unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Unknown config path: network.nope"));
+        assert!(config.get_path("network").is_err());
+    }
+
+    #[test]
+    fn test_set_path_round_trip() {
+        let mut config = Config::default();
+        config.set_path("network.timeout_seconds", "60").unwrap();
+        config.set_path("appearance.default_zoom", "1.25").unwrap();
+        config
+            .set_path("browser.homepage", "https://example.com")
+            .unwrap();
+        config
+            .set_path(
+                "adblock.custom_filters",
+                r###"["||ads.com^", "##.banner"]"###,
+            )
+            .unwrap();
+
+        assert_eq!(config.network.timeout_seconds, 60);
+        assert_eq!(config.appearance.default_zoom, 1.25);
+        assert_eq!(config.browser.homepage, "https://example.com");
+        assert_eq!(
+            config.adblock.custom_filters,
+            vec!["||ads.com^", "##.banner"]
+        );
+        assert_eq!(
+            config.get_path("adblock.custom_filters").unwrap(),
+            r###"["||ads.com^", "##.banner"]"###
+        );
+    }
+
+    #[test]
+    fn test_set_path_type_mismatch() {
+        let mut config = Config::default();
+        let err = config
+            .set_path("network.timeout_seconds", "soon")
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Type mismatch for network.timeout_seconds: expected integer"));
+        assert!(config.set_path("network.enable_cache", "yes").is_err());
+        assert!(config.set_path("network.cache_size_mb", "-1").is_err());
+        assert!(config.set_path("adblock.custom_filters", "[1, 2]").is_err());
+        assert!(config
+            .set_path("network.custom_headers", r#"["a.test"]"#)
+            .is_err());
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn test_set_custom_headers() {
+        let raw = r#"[{ host_pattern = "*.corp", headers = { X-Tenant = "hr" } }]"#;
+        let mut config = Config::default();
+        config.set_path("network.custom_headers", raw).unwrap();
+        assert_eq!(config.network.custom_headers.len(), 1);
+        assert_eq!(config.network.custom_headers[0].headers["X-Tenant"], "hr");
+        assert!(!config.network.custom_headers[0].include_subresources);
+
+        let file = NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), Config::default_toml()).unwrap();
+        Config::set_in_file(file.path(), "network.custom_headers", raw).unwrap();
+        let loaded = Config::load_from_file(file.path()).unwrap();
+        assert_eq!(loaded.network.custom_headers, config.network.custom_headers);
+    }
+
+    #[test]
+    fn test_set_extra_headers() {
+        let mut config = Config::default();
+        config
+            .set_path(
+                "network.extra_headers",
+                r#"{ "X-Team" = "qa", Accept-Language = "fr" }"#,
+            )
+            .unwrap();
+        assert_eq!(config.network.extra_headers["X-Team"], "qa");
+        assert_eq!(config.network.extra_headers["Accept-Language"], "fr");
+        assert!(config.validate().is_empty());
+
+        // The rendered value reads back as the same table
+        let rendered = config.get_path("network.extra_headers").unwrap();
+        let mut copy = Config::default();
+        copy.set_path("network.extra_headers", &rendered).unwrap();
+        assert_eq!(copy.network.extra_headers, config.network.extra_headers);
+
+        for raw in [r#"{ X-Retries = 3 }"#, r#"["X-Team"]"#] {
+            assert!(
+                config.set_path("network.extra_headers", raw).is_err(),
+                "{}",
+                raw
+            );
+        }
+
+        config
+            .set_path(
+                "network.extra_headers",
+                r#"{ Cookie = "a=1", User-Agent = "x" }"#,
+            )
+            .unwrap();
+        assert_eq!(
+            config.validate(),
+            vec![
+                "network.extra_headers: header Cookie would go to every host; \
+                 use network.custom_headers",
+                "network.extra_headers: header User-Agent: set network.user_agent instead",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_set_in_file_preserves_comments() {
+        let file = NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), Config::default_toml()).unwrap();
+        let before = std::fs::read_to_string(file.path()).unwrap();
+        assert!(before.contains("# Request timeout in seconds"));
+
+        Config::set_in_file(file.path(), "network.timeout_seconds", "90").unwrap();
+
+        let after = std::fs::read_to_string(file.path()).unwrap();
+        assert!(after.contains("# Request timeout in seconds"));
+        assert!(after.contains("timeout_seconds = 90"));
+        let loaded = Config::load_from_file(file.path()).unwrap();
+        assert_eq!(loaded.network.timeout_seconds, 90);
+    }
+
+    #[test]
+    fn test_set_in_file_creates_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested/config.toml");
+
+        Config::set_in_file(&path, "adblock.custom_filters", r#"["a"]"#).unwrap();
+
+        let loaded = Config::load_from_file(&path).unwrap();
+        assert_eq!(loaded.adblock.custom_filters, vec!["a"]);
+    }
+
+    #[test]
+    fn test_set_in_file_rejects_unknown_path() {
+        let file = NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), Config::default_toml()).unwrap();
+        assert!(Config::set_in_file(file.path(), "network.bogus", "1").is_err());
+        assert_eq!(
+            std::fs::read_to_string(file.path()).unwrap(),
+            Config::default_toml()
+        );
+    }
+
+    // ========================================
+    // Tests for validation
+    // ========================================
+
+    #[test]
+    fn test_validate_default_is_clean() {
+        assert!(Config::default().validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_reports_all_problems() {
+        let mut config = Config::default();
+        config.appearance.theme = "neon".to_string();
+        config.appearance.default_zoom = 10.0;
+        config.network.timeout_seconds = 0;
+        assert_eq!(config.validate().len(), 3);
+    }
+
+    #[test]
+    fn test_validate_custom_css_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.accessibility.custom_css_path = dir
+            .path()
+            .join("missing.css")
+            .to_string_lossy()
+            .into_owned();
+        let diagnostics = config.validate();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].starts_with("accessibility.custom_css_path"));
+        assert!(diagnostics[0].contains("missing.css"));
+
+        let css = dir.path().join("user.css");
+        std::fs::write(&css, "body { color: red; }").unwrap();
+        config.accessibility.custom_css_path = css.to_string_lossy().into_owned();
+        assert!(config.validate().is_empty());
+    }
+
+    #[test]
+    fn test_validate_custom_headers() {
+        let file = NamedTempFile::new().unwrap();
+        std::fs::write(
+            file.path(),
+            Config::default_toml().replace(
+                "custom_headers = []",
+                "custom_headers = [{ host_pattern = \"portal.corp\", headers = { Host = \"x\", Cookie = \"a=1\" } }]",
+            ),
+        )
+        .unwrap();
+        let diagnostics = Config::check_file(file.path());
+        assert_eq!(
+            diagnostics,
+            vec![
+                "network.custom_headers[0]: header Cookie needs allow_sensitive = true",
+                "network.custom_headers[0]: header Host cannot be set",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_file() {
+        let file = NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), Config::default_toml()).unwrap();
+        assert!(Config::check_file(file.path()).is_empty());
+
+        std::fs::write(
+            file.path(),
+            Config::default_toml()
+                .replace("timeout_seconds = 30", "timeout_seconds = 0\nbogus = 1")
+                .replace("theme = \"auto\"", "theme = \"neon\""),
+        )
+        .unwrap();
+        let diagnostics = Config::check_file(file.path());
+        assert!(diagnostics.iter().any(|d| d.contains("network.bogus")));
+        assert!(diagnostics.iter().any(|d| d.contains("appearance.theme")));
+
+        // Required settings cannot be left out
+        std::fs::write(file.path(), "[appearance]\ntheme = \"dark\"\n").unwrap();
+        let diagnostics = Config::check_file(file.path());
+        assert!(diagnostics
+            .iter()
+            .any(|d| d.starts_with("Invalid config:") && d.contains("missing field")));
+
+        std::fs::write(file.path(), "not toml {{").unwrap();
+        assert_eq!(Config::check_file(file.path()).len(), 1);
+    }
+}
@@ -0,0 +1,260 @@
+//! Color theme
+//!
+//! `appearance.theme` is "light", "dark" or "auto". Auto follows the
+//! operating system's dark mode setting, read with
+//! [`detect_system_theme`]:
+//!
+//! - Linux: the GNOME `color-scheme` setting via `gsettings`, then a
+//!   `:dark` variant in `GTK_THEME`
+//! - macOS: `AppleInterfaceStyle` via `defaults`
+//! - Windows: `AppsUseLightTheme` via `reg`
+//!
+//! Where the setting cannot be read, auto means light.
+
+use shared_types::{BrowserError, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// Color theme of the browser's own UI and the pages' default colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Theme {
+    /// Dark text on light backgrounds
+    Light,
+    /// Light text on dark backgrounds
+    Dark,
+    /// Follow the operating system
+    #[default]
+    Auto,
+}
+
+impl Theme {
+    /// Values accepted for `appearance.theme`
+    pub const NAMES: [&'static str; 3] = ["light", "dark", "auto"];
+
+    /// Parse an `appearance.theme` value
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the accepted values if `value` is not one of
+    /// them. Matching is exact: "Dark" is not accepted.
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "light" => Ok(Theme::Light),
+            "dark" => Ok(Theme::Dark),
+            "auto" => Ok(Theme::Auto),
+            _ => Err(BrowserError::Other(anyhow::anyhow!(invalid_theme(value)))),
+        }
+    }
+
+    /// The `appearance.theme` value for this theme
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::Auto => "auto",
+        }
+    }
+
+    /// The theme to draw with: auto becomes the system theme
+    ///
+    /// # Returns
+    ///
+    /// Returns `Light` or `Dark`, never `Auto`.
+    pub fn resolve(self) -> Self {
+        match self {
+            Theme::Auto => self.resolve_with(detect_system_theme()),
+            theme => theme,
+        }
+    }
+
+    /// The theme to draw with, given the system theme
+    ///
+    /// # Arguments
+    ///
+    /// * `system` - The system theme, `None` if unknown (auto then means
+    ///   light)
+    pub fn resolve_with(self, system: Option<Theme>) -> Self {
+        match (self, system) {
+            (Theme::Auto, Some(Theme::Dark)) => Theme::Dark,
+            (Theme::Auto, _) => Theme::Light,
+            (theme, _) => theme,
+        }
+    }
+
+    /// Whether this is the dark theme
+    pub fn is_dark(self) -> bool {
+        self == Theme::Dark
+    }
+}
+
+impl FromStr for Theme {
+    type Err = BrowserError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Self::parse(value)
+    }
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Diagnostic for an `appearance.theme` value that is not a theme
+pub(crate) fn invalid_theme(value: &str) -> String {
+    format!(
+        "appearance.theme: expected \"light\", \"dark\" or \"auto\", got \"{}\"",
+        value
+    )
+}
+
+/// Read the operating system's dark mode setting
+///
+/// # Returns
+///
+/// Returns `Light` or `Dark`, or `None` if the setting cannot be read on
+/// this system.
+pub fn detect_system_theme() -> Option<Theme> {
+    #[cfg(target_os = "linux")]
+    {
+        command_output(
+            "gsettings",
+            &["get", "org.gnome.desktop.interface", "color-scheme"],
+        )
+        .and_then(|output| parse_gnome_color_scheme(&output))
+        .or_else(|| {
+            std::env::var("GTK_THEME")
+                .ok()
+                .map(|theme| parse_gtk_theme(&theme))
+        })
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        // The key only exists while dark mode is on
+        Some(
+            match command_output("defaults", &["read", "-g", "AppleInterfaceStyle"]) {
+                Some(style) if style.trim().eq_ignore_ascii_case("dark") => Theme::Dark,
+                _ => Theme::Light,
+            },
+        )
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        command_output(
+            "reg",
+            &[
+                "query",
+                r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+                "/v",
+                "AppsUseLightTheme",
+            ],
+        )
+        .and_then(|output| parse_windows_light_theme(&output))
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        None
+    }
+}
+
+/// Standard output of a command that succeeded
+#[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse `gsettings get org.gnome.desktop.interface color-scheme`
+///
+/// The value is quoted: `'prefer-dark'`, `'prefer-light'` or `'default'`.
+#[cfg(any(target_os = "linux", test))]
+fn parse_gnome_color_scheme(output: &str) -> Option<Theme> {
+    match output.trim().trim_matches('\'') {
+        "prefer-dark" => Some(Theme::Dark),
+        "prefer-light" | "default" => Some(Theme::Light),
+        _ => None,
+    }
+}
+
+/// Parse `GTK_THEME`, e.g. `Adwaita:dark`
+#[cfg(any(target_os = "linux", test))]
+fn parse_gtk_theme(theme: &str) -> Theme {
+    if theme.to_ascii_lowercase().ends_with(":dark") {
+        Theme::Dark
+    } else {
+        Theme::Light
+    }
+}
+
+/// Parse `reg query … /v AppsUseLightTheme`
+///
+/// The value line reads `AppsUseLightTheme    REG_DWORD    0x0`; 0 means
+/// apps use the dark theme.
+#[cfg(any(target_os = "windows", test))]
+fn parse_windows_light_theme(output: &str) -> Option<Theme> {
+    let value = output
+        .lines()
+        .find(|line| line.trim_start().starts_with("AppsUseLightTheme"))?
+        .split_whitespace()
+        .last()?;
+    match value {
+        "0x0" => Some(Theme::Dark),
+        "0x1" => Some(Theme::Light),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_themes() {
+        for name in Theme::NAMES {
+            assert_eq!(Theme::parse(name).unwrap().as_str(), name);
+        }
+        assert_eq!("dark".parse::<Theme>().unwrap(), Theme::Dark);
+        for bad in ["neon", "Dark", ""] {
+            let err = Theme::parse(bad).unwrap_err();
+            assert!(err.to_string().contains("appearance.theme"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_auto_resolves_to_system_theme() {
+        assert_eq!(Theme::Auto.resolve_with(Some(Theme::Dark)), Theme::Dark);
+        assert_eq!(Theme::Auto.resolve_with(Some(Theme::Light)), Theme::Light);
+        assert_eq!(Theme::Auto.resolve_with(None), Theme::Light);
+        assert_eq!(Theme::Light.resolve_with(Some(Theme::Dark)), Theme::Light);
+        assert_ne!(Theme::Auto.resolve(), Theme::Auto);
+    }
+
+    #[test]
+    fn test_parse_system_settings() {
+        assert_eq!(
+            parse_gnome_color_scheme("'prefer-dark'\n"),
+            Some(Theme::Dark)
+        );
+        assert_eq!(parse_gnome_color_scheme("'default'\n"), Some(Theme::Light));
+        assert_eq!(parse_gnome_color_scheme(""), None);
+        assert_eq!(parse_gtk_theme("Adwaita:dark"), Theme::Dark);
+        assert_eq!(parse_gtk_theme("Adwaita"), Theme::Light);
+
+        let reg = "\r\nHKEY_CURRENT_USER\\Software\\Microsoft\\Windows\\CurrentVersion\\\
+                   Themes\\Personalize\r\n    AppsUseLightTheme    REG_DWORD    0x0\r\n\r\n";
+        assert_eq!(parse_windows_light_theme(reg), Some(Theme::Dark));
+        assert_eq!(
+            parse_windows_light_theme(&reg.replace("0x0", "0x1")),
+            Some(Theme::Light)
+        );
+        assert_eq!(parse_windows_light_theme("ERROR"), None);
+    }
+}
@@ -0,0 +1,375 @@
+//! Reloading the configuration when its file changes
+//!
+//! [`ConfigWatcher`] polls the file's modification time and size. A change
+//! is only read once the file has been quiet for the debounce period, so
+//! an editor that writes a file twice (or truncates, then writes) causes a
+//! single reload. A file that no longer parses is reported and otherwise
+//! ignored: the previous configuration stays current until a later write
+//! fixes it.
+//!
+//! [`Config::watch`] runs a watcher on its own thread and hands each
+//! [`ConfigEvent`] to a callback.
+
+use crate::diff::is_under;
+use crate::{ChangeSet, Config};
+use shared_types::{BrowserError, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Quiet period after the last write before the file is read
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// How often [`Config::watch`] checks the file
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Settings read once, when the network client is built
+///
+/// Changing one of these only takes effect after a restart.
+pub const RESTART_REQUIRED: [&str; 11] = [
+    "network.max_connections_per_host",
+    "network.timeout_seconds",
+    "network.enable_cookies",
+    "network.enable_cache",
+    "network.cache_size_mb",
+    "network.cache_backend",
+    "network.cache_dir",
+    "network.cookie_file",
+    "network.proxy",
+    "network.no_proxy",
+    "network.custom_headers",
+];
+
+/// What changed in a reloaded configuration
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigDelta {
+    changes: ChangeSet,
+}
+
+impl ConfigDelta {
+    /// The changed settings
+    pub fn changes(&self) -> &ChangeSet {
+        &self.changes
+    }
+
+    /// Check whether any change is at or below a path
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - Dotted path of a section or setting; see
+    ///   [`ChangeSet::affects`]
+    pub fn affects(&self, prefix: &str) -> bool {
+        self.changes.affects(prefix)
+    }
+
+    /// Top-level sections with a changed setting, e.g. `adblock`, in path
+    /// order
+    pub fn sections(&self) -> Vec<&str> {
+        let mut sections: Vec<&str> = self
+            .changes
+            .iter()
+            .map(|change| change.path.split('.').next().unwrap_or_default())
+            .collect();
+        sections.dedup();
+        sections
+    }
+
+    /// Changed settings that only take effect after a restart
+    pub fn restart_required(&self) -> Vec<&str> {
+        self.changes
+            .iter()
+            .map(|change| change.path.as_str())
+            .filter(|path| {
+                RESTART_REQUIRED
+                    .iter()
+                    .any(|setting| is_under(path, setting))
+            })
+            .collect()
+    }
+
+    /// Whether a restart is needed for every change to take effect
+    pub fn requires_restart(&self) -> bool {
+        !self.restart_required().is_empty()
+    }
+}
+
+impl From<ChangeSet> for ConfigDelta {
+    fn from(changes: ChangeSet) -> Self {
+        Self { changes }
+    }
+}
+
+/// Outcome of reading a changed configuration file
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigEvent {
+    /// The file parsed and differs from the current configuration
+    Reloaded {
+        /// The new configuration
+        config: Box<Config>,
+        /// What changed
+        delta: ConfigDelta,
+    },
+    /// The file could not be read or parsed; the current configuration is
+    /// kept
+    Invalid {
+        /// Why the file was rejected
+        error: String,
+    },
+}
+
+/// Modification time and size of the watched file; `None` while missing
+type FileStamp = Option<(SystemTime, u64)>;
+
+/// Watches a configuration file for changes
+///
+/// Driven by [`poll`](Self::poll), so the caller decides on which thread
+/// and how often the file is checked.
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    path: PathBuf,
+    config: Config,
+    stamp: FileStamp,
+    changed_at: Option<Instant>,
+    debounce: Duration,
+}
+
+impl ConfigWatcher {
+    /// Watch `path`, starting from `config`
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Configuration file
+    /// * `config` - What the file holds now; reloads are compared with it
+    pub fn new(path: impl Into<PathBuf>, config: Config) -> Self {
+        let path = path.into();
+        Self {
+            stamp: file_stamp(&path),
+            path,
+            config,
+            changed_at: None,
+            debounce: DEFAULT_DEBOUNCE,
+        }
+    }
+
+    /// Set the quiet period after the last write ([`DEFAULT_DEBOUNCE`] by
+    /// default)
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// The watched file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The current configuration: the last one that loaded
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Check the file, reloading it once it has been quiet long enough
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - Current time
+    ///
+    /// # Returns
+    ///
+    /// Returns an event once per settled change: `Reloaded` if the file
+    /// parsed to a different configuration, which becomes current, or
+    /// `Invalid` if it did not. Returns `None` while nothing changed, while
+    /// a change is still settling, and for writes that leave the settings
+    /// as they were.
+    pub fn poll(&mut self, now: Instant) -> Option<ConfigEvent> {
+        let stamp = file_stamp(&self.path);
+        if stamp != self.stamp {
+            // Every further write restarts the quiet period
+            self.stamp = stamp;
+            self.changed_at = Some(now);
+            return None;
+        }
+        let changed_at = self.changed_at?;
+        if now.saturating_duration_since(changed_at) < self.debounce {
+            return None;
+        }
+        self.changed_at = None;
+
+        match Config::load_from_file(&self.path) {
+            Ok(config) if config == self.config => None,
+            Ok(config) => {
+                let delta = ConfigDelta::from(ChangeSet::between(&self.config, &config));
+                self.config = config.clone();
+                Some(ConfigEvent::Reloaded {
+                    config: Box::new(config),
+                    delta,
+                })
+            }
+            Err(e) => Some(ConfigEvent::Invalid {
+                error: e.to_string(),
+            }),
+        }
+    }
+}
+
+/// A [`Config::watch`] thread; dropping it stops the thread
+#[derive(Debug)]
+pub struct WatchHandle {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatchHandle {
+    /// Stop watching and wait for the thread to finish
+    pub fn stop(mut self) {
+        self.shut_down();
+    }
+
+    fn shut_down(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.shut_down();
+    }
+}
+
+impl Config {
+    /// Watch a configuration file on a background thread
+    ///
+    /// The file is loaded now; every later change that settles is reported
+    /// to `callback` on the watcher thread, as described for
+    /// [`ConfigWatcher::poll`].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Configuration file
+    /// * `callback` - Receives each reload or rejected file
+    ///
+    /// # Returns
+    ///
+    /// Returns the configuration the file holds now and the handle that
+    /// keeps the thread running.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be loaded now.
+    pub fn watch<F>(path: &Path, mut callback: F) -> Result<(Config, WatchHandle)>
+    where
+        F: FnMut(ConfigEvent) + Send + 'static,
+    {
+        let config = Config::load_from_file(path)?;
+        let mut watcher = ConfigWatcher::new(path, config.clone());
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = std::thread::Builder::new()
+            .name("config-watch".to_string())
+            .spawn({
+                let stop = stop.clone();
+                move || {
+                    while !stop.load(Ordering::Acquire) {
+                        std::thread::sleep(DEFAULT_POLL_INTERVAL);
+                        if let Some(event) = watcher.poll(Instant::now()) {
+                            callback(event);
+                        }
+                    }
+                }
+            })
+            .map_err(|e| {
+                BrowserError::Other(anyhow::anyhow!(
+                    "Failed to start watching {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+
+        Ok((
+            config,
+            WatchHandle {
+                stop,
+                thread: Some(thread),
+            },
+        ))
+    }
+}
+
+fn file_stamp(path: &Path) -> FileStamp {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, config: &Config) {
+        config.save_to_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_reload_waits_for_writes_to_settle() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        write(&path, &Config::default());
+        let mut watcher = ConfigWatcher::new(&path, Config::default());
+        let start = Instant::now();
+        assert_eq!(watcher.poll(start), None);
+
+        // An editor's two writes in a row give one reload of the second
+        let mut config = Config::default();
+        config.adblock.enabled = false;
+        write(&path, &config);
+        assert_eq!(watcher.poll(start), None);
+        config.appearance.theme = "dark".to_string();
+        config.network.proxy = "http://proxy.local:3128".to_string();
+        write(&path, &config);
+        assert_eq!(watcher.poll(start + Duration::from_millis(100)), None);
+        assert_eq!(watcher.poll(start + Duration::from_millis(200)), None);
+
+        let Some(ConfigEvent::Reloaded {
+            config: reloaded,
+            delta,
+        }) = watcher.poll(start + Duration::from_millis(600))
+        else {
+            panic!("expected a reload");
+        };
+        assert_eq!(*reloaded, config);
+        assert_eq!(watcher.config(), &config);
+        assert_eq!(delta.sections(), ["adblock", "appearance", "network"]);
+        assert!(delta.affects("adblock.enabled"));
+        assert_eq!(delta.restart_required(), ["network.proxy"]);
+        assert_eq!(watcher.poll(start + Duration::from_secs(5)), None);
+    }
+
+    #[test]
+    fn test_invalid_file_keeps_current_config() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml");
+        write(&path, &Config::default());
+        let mut watcher =
+            ConfigWatcher::new(&path, Config::default()).with_debounce(Duration::ZERO);
+        let start = Instant::now();
+
+        std::fs::write(&path, "[browser\nhomepage = ").unwrap();
+        assert_eq!(watcher.poll(start), None);
+        let Some(ConfigEvent::Invalid { error }) = watcher.poll(start) else {
+            panic!("expected the file to be rejected");
+        };
+        assert!(error.contains("TOML"), "{}", error);
+        assert_eq!(watcher.config(), &Config::default());
+        // Reported once, not on every poll
+        assert_eq!(watcher.poll(start), None);
+
+        // Saving the same settings again is not a change
+        write(&path, &Config::default());
+        assert_eq!(watcher.poll(start), None);
+        assert_eq!(watcher.poll(start), None);
+    }
+}
@@ -0,0 +1,46 @@
+//! Hook trait and types for built-in Rust extensions
+//!
+//! This is the extension_api component of the FrankenBrowser project.
+//!
+//! # Component Overview
+//!
+//! **Type**: library
+//! **Level**: 1
+//! **Token Budget**: 4000 tokens
+//!
+//! # Dependencies
+//!
+//! - shared_types: For TabId, NavigationInitiator, ResourceType
+//!
+//! # Usage
+//!
+//! ```
+//! use extension_api::{BrowserExtension, NavigationContext, NavigationVerdict};
+//!
+//! struct NoExampleOrg;
+//!
+//! impl BrowserExtension for NoExampleOrg {
+//!     fn id(&self) -> &str {
+//!         "no-example-org"
+//!     }
+//!
+//!     fn on_navigation(&self, ctx: &NavigationContext) -> NavigationVerdict {
+//!         if ctx.url.host_str() == Some("example.org") {
+//!             NavigationVerdict::Block {
+//!                 reason: "example.org is not allowed".to_string(),
+//!             }
+//!         } else {
+//!             NavigationVerdict::Allow
+//!         }
+//!     }
+//! }
+//! ```
+
+pub mod types;
+
+// Re-export main types for convenience
+pub use types::{
+    BrowserExtension, ExtensionDiagnostics, ExtensionFactory, ExtensionMenuItem, Hook,
+    HookDispatcher, HookStats, NavigationContext, NavigationVerdict, RequestInfo, RequestVerdict,
+    UserScript,
+};
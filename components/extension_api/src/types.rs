@@ -0,0 +1,334 @@
+//! Extension trait, hook contexts and verdicts
+
+use shared_types::{NavigationInitiator, ResourceType, TabId};
+use std::time::Duration;
+use url::Url;
+
+/// Context passed to [`BrowserExtension::on_navigation`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NavigationContext {
+    /// Tab being navigated
+    pub tab_id: TabId,
+    /// Target URL
+    pub url: Url,
+    /// Why the navigation was started
+    pub initiator: NavigationInitiator,
+}
+
+/// Outcome of a navigation hook
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum NavigationVerdict {
+    /// Let the navigation proceed
+    #[default]
+    Allow,
+    /// Refuse the navigation
+    Block {
+        /// Reason shown to the user
+        reason: String,
+    },
+}
+
+/// Request passed to [`BrowserExtension::on_request`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestInfo {
+    /// Request URL
+    pub url: Url,
+    /// HTTP method
+    pub method: String,
+    /// What the request is for
+    pub resource_type: ResourceType,
+    /// Origin of the page that issued the request, if known
+    pub top_level_origin: Option<String>,
+}
+
+/// Outcome of a request hook
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum RequestVerdict {
+    /// Let the request proceed
+    #[default]
+    Allow,
+    /// Refuse the request
+    Block {
+        /// Reason reported with the blocked request
+        reason: String,
+    },
+}
+
+/// Script injected into pages after they load
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserScript {
+    /// Name shown in diagnostics
+    pub name: String,
+    /// Hosts the script runs on; a pattern also matches its subdomains.
+    /// Empty means every http(s) page.
+    pub matches: Vec<String>,
+    /// JavaScript source
+    pub source: String,
+}
+
+impl UserScript {
+    /// Check whether the script should run on a URL
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Page URL
+    pub fn applies_to(&self, url: &Url) -> bool {
+        if !matches!(url.scheme(), "http" | "https") {
+            return false;
+        }
+        if self.matches.is_empty() {
+            return true;
+        }
+        let host = url.host_str().unwrap_or_default();
+        self.matches.iter().any(|pattern| {
+            host == pattern
+                || host
+                    .strip_suffix(pattern.as_str())
+                    .is_some_and(|rest| rest.ends_with('.'))
+        })
+    }
+}
+
+/// Menu entry contributed by an extension
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionMenuItem {
+    /// Identifier passed back to [`BrowserExtension::on_menu_action`]
+    pub id: String,
+    /// Label shown in the menu
+    pub label: String,
+}
+
+/// A built-in browser extension
+///
+/// Every hook has a no-op default, so extensions only implement what they
+/// need. Hooks run synchronously on the pipeline that triggers them and
+/// should return quickly; the extension manager records their timing and
+/// contains panics.
+pub trait BrowserExtension: Send + Sync {
+    /// Stable identifier, used in config (`extensions.disabled`)
+    fn id(&self) -> &str;
+
+    /// Human-readable name
+    fn name(&self) -> &str {
+        self.id()
+    }
+
+    /// Called before a navigation starts
+    fn on_navigation(&self, _ctx: &NavigationContext) -> NavigationVerdict {
+        NavigationVerdict::Allow
+    }
+
+    /// Called before a network request is sent
+    fn on_request(&self, _request: &RequestInfo) -> RequestVerdict {
+        RequestVerdict::Allow
+    }
+
+    /// Called after a navigation has committed
+    fn on_page_loaded(&self, _tab_id: TabId, _url: &Url) {}
+
+    /// Scripts to inject into matching pages
+    fn user_scripts(&self) -> Vec<UserScript> {
+        Vec::new()
+    }
+
+    /// Entries for the Extensions menu
+    fn menu_items(&self) -> Vec<ExtensionMenuItem> {
+        Vec::new()
+    }
+
+    /// Called when one of this extension's menu entries is chosen
+    fn on_menu_action(&self, _item_id: &str) {}
+}
+
+/// Constructor entry in a static extension registry
+pub type ExtensionFactory = fn() -> Box<dyn BrowserExtension>;
+
+/// Extension hook, for per-hook statistics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Hook {
+    /// [`BrowserExtension::on_navigation`]
+    Navigation,
+    /// [`BrowserExtension::on_request`]
+    Request,
+    /// [`BrowserExtension::on_page_loaded`]
+    PageLoaded,
+    /// [`BrowserExtension::user_scripts`]
+    UserScripts,
+    /// [`BrowserExtension::menu_items`]
+    MenuItems,
+    /// [`BrowserExtension::on_menu_action`]
+    MenuAction,
+}
+
+impl Hook {
+    /// All hooks, in display order
+    pub const ALL: [Hook; 6] = [
+        Hook::Navigation,
+        Hook::Request,
+        Hook::PageLoaded,
+        Hook::UserScripts,
+        Hook::MenuItems,
+        Hook::MenuAction,
+    ];
+
+    /// Hook name as shown in diagnostics
+    pub fn label(self) -> &'static str {
+        match self {
+            Hook::Navigation => "on_navigation",
+            Hook::Request => "on_request",
+            Hook::PageLoaded => "on_page_loaded",
+            Hook::UserScripts => "user_scripts",
+            Hook::MenuItems => "menu_items",
+            Hook::MenuAction => "on_menu_action",
+        }
+    }
+}
+
+/// Call statistics for one hook of one extension
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HookStats {
+    /// Number of calls, including ones that panicked
+    pub calls: u64,
+    /// Number of calls that panicked
+    pub panics: u64,
+    /// Total time spent in the hook
+    pub total_time: Duration,
+    /// Longest single call
+    pub max_time: Duration,
+}
+
+impl HookStats {
+    /// Record one call
+    ///
+    /// # Arguments
+    ///
+    /// * `elapsed` - Time the call took
+    /// * `panicked` - Whether the call panicked
+    pub fn record(&mut self, elapsed: Duration, panicked: bool) {
+        self.calls += 1;
+        self.panics += u64::from(panicked);
+        self.total_time += elapsed;
+        self.max_time = self.max_time.max(elapsed);
+    }
+
+    /// Mean time per call
+    pub fn average(&self) -> Duration {
+        match u32::try_from(self.calls) {
+            Ok(0) => Duration::ZERO,
+            Ok(calls) => self.total_time / calls,
+            Err(_) => Duration::from_secs_f64(self.total_time.as_secs_f64() / self.calls as f64),
+        }
+    }
+}
+
+/// Diagnostics for one loaded extension, shown on `about:diagnostics`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionDiagnostics {
+    /// Extension ID
+    pub id: String,
+    /// Extension name
+    pub name: String,
+    /// Whether hooks are currently dispatched to it
+    pub enabled: bool,
+    /// Statistics for every hook that has been called
+    pub hooks: Vec<(Hook, HookStats)>,
+}
+
+/// Dispatches hooks to loaded extensions
+///
+/// Implemented by the application's extension manager and handed to the
+/// components that own the pipelines (browser core for navigations), so
+/// they do not depend on the manager itself.
+pub trait HookDispatcher: Send + Sync {
+    /// Run every enabled extension's navigation hook
+    ///
+    /// # Returns
+    ///
+    /// Returns `Block` if any extension blocked the navigation.
+    fn on_navigation(&self, ctx: &NavigationContext) -> NavigationVerdict;
+
+    /// Run every enabled extension's request hook
+    ///
+    /// # Returns
+    ///
+    /// Returns `Block` if any extension blocked the request.
+    fn on_request(&self, request: &RequestInfo) -> RequestVerdict;
+
+    /// Notify every enabled extension that a page loaded
+    fn on_page_loaded(&self, tab_id: TabId, url: &Url);
+
+    /// Describe the loaded extensions and their hook statistics
+    fn diagnostics(&self) -> Vec<ExtensionDiagnostics>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Minimal;
+
+    impl BrowserExtension for Minimal {
+        fn id(&self) -> &str {
+            "minimal"
+        }
+    }
+
+    #[test]
+    fn test_default_hooks_allow_everything() {
+        let ext = Minimal;
+        let url = Url::parse("https://example.com/").unwrap();
+        let ctx = NavigationContext {
+            tab_id: 1,
+            url: url.clone(),
+            initiator: NavigationInitiator::UserTyped,
+        };
+        let request = RequestInfo {
+            url: url.clone(),
+            method: "GET".to_string(),
+            resource_type: ResourceType::Script,
+            top_level_origin: None,
+        };
+
+        assert_eq!(ext.name(), "minimal");
+        assert_eq!(ext.on_navigation(&ctx), NavigationVerdict::Allow);
+        assert_eq!(ext.on_request(&request), RequestVerdict::Allow);
+        assert!(ext.user_scripts().is_empty());
+        assert!(ext.menu_items().is_empty());
+    }
+
+    #[test]
+    fn test_user_script_matching() {
+        let script = UserScript {
+            name: "s".to_string(),
+            matches: vec!["example.com".to_string()],
+            source: "1".to_string(),
+        };
+        let applies = |url: &str| script.applies_to(&Url::parse(url).unwrap());
+
+        assert!(applies("https://example.com/"));
+        assert!(applies("https://www.example.com/a"));
+        assert!(!applies("https://notexample.com/"));
+        assert!(!applies("file:///example.com"));
+
+        let everywhere = UserScript {
+            matches: Vec::new(),
+            ..script.clone()
+        };
+        assert!(everywhere.applies_to(&Url::parse("http://any.org/").unwrap()));
+        assert!(!everywhere.applies_to(&Url::parse("about:blank").unwrap()));
+    }
+
+    #[test]
+    fn test_hook_stats_record_and_average() {
+        let mut stats = HookStats::default();
+        assert_eq!(stats.average(), Duration::ZERO);
+
+        stats.record(Duration::from_millis(2), false);
+        stats.record(Duration::from_millis(4), true);
+
+        assert_eq!(stats.calls, 2);
+        assert_eq!(stats.panics, 1);
+        assert_eq!(stats.average(), Duration::from_millis(3));
+        assert_eq!(stats.max_time, Duration::from_millis(4));
+    }
+}
@@ -0,0 +1,142 @@
+//! Handlers that do async work
+//!
+//! An [`AsyncMessageHandler`] registered with
+//! [`MessageBus::register_async_handler`](crate::MessageBus::register_async_handler)
+//! gets its own queue, drained by one task on the bus's Tokio runtime. The
+//! dispatcher only pushes each message onto the queue, so an awaiting
+//! handler never holds up other handlers, and the task awaits each message
+//! before taking the next, so the handler sees messages in send order.
+
+use crate::errors::Result;
+use crate::monitor::Monitor;
+use crate::types::MessageHandler;
+use shared_types::BrowserMessage;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Instant;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+
+/// Future returned by [`AsyncMessageHandler::handle`]
+pub type Handling<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+/// Trait for handling messages from the bus asynchronously
+///
+/// # Example
+///
+/// ```
+/// use message_bus::{AsyncMessageHandler, Handling};
+/// use shared_types::BrowserMessage;
+/// use std::time::Duration;
+///
+/// struct SlowHandler;
+/// impl AsyncMessageHandler for SlowHandler {
+///     fn handle(&self, message: BrowserMessage) -> Handling<'_> {
+///         Box::pin(async move {
+///             tokio::time::sleep(Duration::from_millis(10)).await;
+///             println!("Received: {:?}", message);
+///             Ok(())
+///         })
+///     }
+/// }
+/// ```
+pub trait AsyncMessageHandler: Send + Sync {
+    /// Handle a received message
+    ///
+    /// The next message is not handled until the returned future
+    /// completes. Requests are handled like other messages; async handlers
+    /// cannot reply to them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if message processing fails
+    fn handle(&self, message: BrowserMessage) -> Handling<'_>;
+}
+
+/// Messages queued for or being handled by async handlers, so flushing and
+/// shutting down the bus can wait for them
+#[derive(Default)]
+pub(crate) struct Backlog {
+    count: Mutex<usize>,
+    idle: Condvar,
+}
+
+impl Backlog {
+    fn add(&self) {
+        *self.count.lock().unwrap() += 1;
+    }
+
+    fn done(&self) {
+        let mut count = self.count.lock().unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.idle.notify_all();
+        }
+    }
+
+    /// Wait until every queued message has been handled
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if messages were still pending at `deadline`.
+    pub(crate) fn wait_idle(&self, deadline: Option<Instant>) -> bool {
+        let mut count = self.count.lock().unwrap();
+        while *count > 0 {
+            count = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return false;
+                    }
+                    self.idle.wait_timeout(count, left).unwrap().0
+                }
+                None => self.idle.wait(count).unwrap(),
+            };
+        }
+        true
+    }
+}
+
+/// Sync handler that queues messages for an async handler's task
+pub(crate) struct AsyncForwarder {
+    queue: UnboundedSender<BrowserMessage>,
+    backlog: Arc<Backlog>,
+}
+
+impl AsyncForwarder {
+    /// Spawn the task driving `handler` on `runtime`
+    ///
+    /// The task ends once the forwarder is dropped (the handler is
+    /// unregistered) and the queued messages are handled.
+    pub(crate) fn spawn(
+        handler: Box<dyn AsyncMessageHandler>,
+        runtime: &Handle,
+        backlog: Arc<Backlog>,
+        monitor: Arc<Monitor>,
+    ) -> Self {
+        let (queue, mut messages) = unbounded_channel::<BrowserMessage>();
+        let task_backlog = Arc::clone(&backlog);
+        runtime.spawn(async move {
+            while let Some(message) = messages.recv().await {
+                if let Err(e) = handler.handle(message.clone()).await {
+                    monitor.handler_failed(&message, &e);
+                }
+                task_backlog.done();
+            }
+        });
+        Self { queue, backlog }
+    }
+}
+
+impl MessageHandler for AsyncForwarder {
+    fn handle(&self, message: BrowserMessage) -> Result<()> {
+        self.backlog.add();
+        // The task only stops once this forwarder is gone, unless its
+        // runtime shut down
+        if self.queue.send(message).is_err() {
+            self.backlog.done();
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 //! Error types for message bus operations
 
+use crate::types::HandlerId;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Errors that can occur in the message bus
@@ -17,6 +19,14 @@ pub enum Error {
     #[error("Message bus not running")]
     NotRunning,
 
+    /// Bus is shutting down or shut down and accepts no more messages
+    #[error("Message bus is shutting down")]
+    BusShuttingDown,
+
+    /// `flush` was called from a handler, which would wait for itself
+    #[error("Cannot flush the message bus from a handler")]
+    FlushFromHandler,
+
     /// Channel disconnected
     #[error("Channel disconnected")]
     ChannelDisconnected,
@@ -28,6 +38,22 @@ pub enum Error {
     /// Send error
     #[error("Failed to send message: {0}")]
     SendError(String),
+
+    /// No handler is registered under this id
+    #[error("No such message handler: {0}")]
+    UnknownHandler(HandlerId),
+
+    /// No handler replied to a request within the timeout
+    #[error("No reply to request within {0:?}")]
+    RequestTimeout(Duration),
+
+    /// Every handler saw a request without replying
+    #[error("No handler replied to the request")]
+    NoReply,
+
+    /// The runtime for async handlers could not be started
+    #[error("Failed to start async handler runtime: {0}")]
+    Runtime(String),
 }
 
 /// Result type alias for message bus operations
@@ -32,7 +32,7 @@
 //!     }
 //! }
 //!
-//! bus.register_handler(Box::new(MyHandler));
+//! let id = bus.register_handler(Box::new(MyHandler));
 //!
 //! // Start the bus
 //! bus.start().unwrap();
@@ -41,22 +41,36 @@
 //! let sender = bus.sender();
 //! sender.send(BrowserMessage::Shutdown).unwrap();
 //!
+//! // Handlers can come and go while the bus runs
+//! bus.unregister_handler(id).unwrap();
+//!
 //! // Shutdown when done
 //! bus.shutdown().unwrap();
 //! ```
 
+pub mod async_handler;
 pub mod errors;
+pub mod monitor;
+pub mod trace_log;
 pub mod types;
 
 // Re-export main types for convenience
+pub use async_handler::{AsyncMessageHandler, Handling};
 pub use errors::{Error, Result};
-pub use types::{MessageBus, MessageHandler, MessageSender};
+pub use monitor::{BusStats, ErrorHandler};
+pub use trace_log::{Trace, TraceLog, TraceSpan};
+pub use types::{
+    HandlerId, MessageBus, MessageEnvelope, MessageFilter, MessageHandler, MessageSender,
+    DEFAULT_REQUEST_TIMEOUT, DEFAULT_SHUTDOWN_TIMEOUT,
+};
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use shared_types::BrowserMessage;
+    use shared_types::{BrowserMessage, MessageKind, NavigationInitiator};
+    use std::collections::HashMap;
     use std::sync::{Arc, Mutex};
+    use std::time::Duration;
     use url::Url;
 
     // ========================================
@@ -85,7 +99,7 @@ mod tests {
 
         // Sending after shutdown should fail
         let result = sender.send(BrowserMessage::Shutdown);
-        assert!(result.is_err());
+        assert!(matches!(result, Err(Error::BusShuttingDown)));
     }
 
     #[test]
@@ -202,7 +216,7 @@ mod tests {
 
     #[test]
     fn test_message_bus_register_handler() {
-        let mut bus = MessageBus::new();
+        let bus = MessageBus::new();
 
         let received = Arc::new(Mutex::new(Vec::new()));
         let handler = TestHandler {
@@ -231,9 +245,6 @@ mod tests {
             .unwrap();
         sender.send(BrowserMessage::CloseTab { tab_id: 2 }).unwrap();
 
-        // Give time for messages to be processed
-        std::thread::sleep(std::time::Duration::from_millis(50));
-
         bus.shutdown().unwrap();
 
         let msgs = received.lock().unwrap();
@@ -261,9 +272,6 @@ mod tests {
         let sender = bus.sender();
         sender.send(BrowserMessage::Shutdown).unwrap();
 
-        // Give time for messages to be processed
-        std::thread::sleep(std::time::Duration::from_millis(50));
-
         bus.shutdown().unwrap();
 
         // Both handlers should receive the message
@@ -289,10 +297,10 @@ mod tests {
             .send(BrowserMessage::NavigateRequest {
                 tab_id: 1,
                 url: url.clone(),
+                initiator: NavigationInitiator::UserTyped,
             })
             .unwrap();
 
-        std::thread::sleep(std::time::Duration::from_millis(50));
         bus.shutdown().unwrap();
 
         let msgs = received.lock().unwrap();
@@ -301,6 +309,7 @@ mod tests {
             BrowserMessage::NavigateRequest {
                 tab_id,
                 url: msg_url,
+                ..
             } => {
                 assert_eq!(*tab_id, 1);
                 assert_eq!(msg_url.as_str(), "https://example.com/");
@@ -346,7 +355,6 @@ mod tests {
         handle1.join().unwrap();
         handle2.join().unwrap();
 
-        std::thread::sleep(std::time::Duration::from_millis(100));
         bus.shutdown().unwrap();
 
         // Should receive all 20 messages
@@ -371,7 +379,710 @@ mod tests {
         // Should not panic even if handler returns error
         sender.send(BrowserMessage::Shutdown).unwrap();
 
-        std::thread::sleep(std::time::Duration::from_millis(50));
         bus.shutdown().unwrap();
     }
+
+    // ========================================
+    // Dynamic handler registration
+    // ========================================
+
+    /// Poll `done` until it holds, failing after two seconds
+    fn wait_for(done: impl Fn() -> bool) {
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(2);
+        while !done() {
+            assert!(std::time::Instant::now() < deadline, "timed out");
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    /// Handler that holds up the dispatcher until released
+    struct GateHandler {
+        gate: Mutex<crossbeam_channel::Receiver<()>>,
+    }
+
+    impl MessageHandler for GateHandler {
+        fn handle(&self, _message: BrowserMessage) -> Result<()> {
+            let _ = self.gate.lock().unwrap().recv();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_handler_registered_after_start_gets_only_later_messages() {
+        let mut bus = MessageBus::new();
+        let (release, gate) = crossbeam_channel::unbounded();
+        bus.register_handler(Box::new(GateHandler {
+            gate: Mutex::new(gate),
+        }));
+        bus.start().unwrap();
+        let sender = bus.sender();
+
+        // The first message is still being dispatched, the second queued
+        sender.send(BrowserMessage::CloseTab { tab_id: 1 }).unwrap();
+        sender.send(BrowserMessage::CloseTab { tab_id: 2 }).unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        bus.register_handler(Box::new(TestHandler {
+            received: Arc::clone(&received),
+        }));
+        sender.send(BrowserMessage::CloseTab { tab_id: 3 }).unwrap();
+        for _ in 0..3 {
+            release.send(()).unwrap();
+        }
+
+        wait_for(|| !received.lock().unwrap().is_empty());
+        bus.shutdown().unwrap();
+        let msgs = received.lock().unwrap();
+        assert_eq!(msgs.len(), 1);
+        assert!(matches!(msgs[0], BrowserMessage::CloseTab { tab_id: 3 }));
+    }
+
+    #[test]
+    fn test_unregister_during_flood_stops_delivery() {
+        struct CountingHandler {
+            count: Arc<std::sync::atomic::AtomicUsize>,
+        }
+        impl MessageHandler for CountingHandler {
+            fn handle(&self, _message: BrowserMessage) -> Result<()> {
+                // Widen the window in which unregister races a call
+                std::thread::yield_now();
+                self.count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok(())
+            }
+        }
+
+        for _ in 0..20 {
+            let mut bus = MessageBus::new();
+            bus.start().unwrap();
+            let count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+            let id = bus.register_handler(Box::new(CountingHandler {
+                count: Arc::clone(&count),
+            }));
+            let stop = Arc::new(std::sync::atomic::AtomicBool::new(false));
+            let flood = {
+                let sender = bus.sender();
+                let stop = Arc::clone(&stop);
+                std::thread::spawn(move || {
+                    while !stop.load(std::sync::atomic::Ordering::Relaxed) {
+                        let _ = sender.send(BrowserMessage::Shutdown);
+                    }
+                })
+            };
+
+            wait_for(|| count.load(std::sync::atomic::Ordering::SeqCst) > 100);
+            bus.unregister_handler(id).unwrap();
+            let at_return = count.load(std::sync::atomic::Ordering::SeqCst);
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            assert_eq!(count.load(std::sync::atomic::Ordering::SeqCst), at_return);
+
+            stop.store(true, std::sync::atomic::Ordering::Relaxed);
+            flood.join().unwrap();
+            bus.shutdown().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_double_unregister_is_an_error() {
+        let bus = MessageBus::new();
+        let id = bus.register_handler(Box::new(TestHandler {
+            received: Arc::new(Mutex::new(Vec::new())),
+        }));
+        assert_eq!(bus.handler_count(), 1);
+
+        bus.unregister_handler(id).unwrap();
+        assert!(matches!(
+            bus.unregister_handler(id),
+            Err(Error::UnknownHandler(other)) if other == id
+        ));
+        assert_eq!(bus.handler_count(), 0);
+    }
+
+    #[test]
+    fn test_handler_can_unregister_itself() {
+        struct OneShot {
+            bus: Arc<MessageBus>,
+            id: Arc<Mutex<Option<HandlerId>>>,
+            received: Arc<Mutex<Vec<BrowserMessage>>>,
+        }
+        impl MessageHandler for OneShot {
+            fn handle(&self, message: BrowserMessage) -> Result<()> {
+                self.received.lock().unwrap().push(message);
+                let id = self.id.lock().unwrap().unwrap();
+                self.bus.unregister_handler(id)
+            }
+        }
+
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let sender = bus.sender();
+        let bus = Arc::new(bus);
+        let id_slot = Arc::new(Mutex::new(None));
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let id = bus.register_handler(Box::new(OneShot {
+            bus: Arc::clone(&bus),
+            id: Arc::clone(&id_slot),
+            received: Arc::clone(&received),
+        }));
+        *id_slot.lock().unwrap() = Some(id);
+
+        sender.send(BrowserMessage::CloseTab { tab_id: 1 }).unwrap();
+        sender.send(BrowserMessage::CloseTab { tab_id: 2 }).unwrap();
+        bus.flush().unwrap();
+        assert_eq!(bus.handler_count(), 0);
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_follow_on_messages_join_the_trace() {
+        use shared_types::{SequentialIds, TraceContext};
+
+        struct Relay {
+            sender: Arc<Mutex<Option<Box<dyn MessageSender>>>>,
+            seen: Arc<Mutex<Vec<Option<TraceContext>>>>,
+        }
+        impl MessageHandler for Relay {
+            fn handle(&self, message: BrowserMessage) -> Result<()> {
+                self.seen.lock().unwrap().push(TraceContext::current());
+                if let BrowserMessage::Reload { tab_id } = message {
+                    let sender = self.sender.lock().unwrap();
+                    sender
+                        .as_ref()
+                        .unwrap()
+                        .send(BrowserMessage::GoBack { tab_id })?;
+                }
+                Ok(())
+            }
+        }
+
+        let mut bus = MessageBus::new();
+        bus.enable_tracing(4);
+        let relay_sender = Arc::new(Mutex::new(None));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        bus.register_handler(Box::new(Relay {
+            sender: Arc::clone(&relay_sender),
+            seen: Arc::clone(&seen),
+        }));
+        bus.start().unwrap();
+        *relay_sender.lock().unwrap() = Some(bus.sender());
+
+        let sender = bus.sender();
+        sender.send(BrowserMessage::CloseTab { tab_id: 9 }).unwrap();
+        let root = TraceContext::root(&SequentialIds::new(40));
+        {
+            let _trace = root.enter();
+            sender.send(BrowserMessage::Reload { tab_id: 1 }).unwrap();
+        }
+        wait_for(|| seen.lock().unwrap().len() == 3);
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen[0], None);
+        let reload_span = seen[1].unwrap().parent_span.unwrap();
+        assert_eq!(seen[1], Some(root.child(reload_span)));
+        assert_eq!(seen[2].unwrap().trace_id, 40);
+
+        let traces = bus.trace_log().recent(10);
+        assert_eq!(traces.len(), 1);
+        let spans = &traces[0].spans;
+        assert_eq!(spans.len(), 2);
+        assert_eq!(
+            (spans[0].message.as_str(), spans[0].parent_span),
+            ("Reload", None)
+        );
+        assert_eq!(spans[1].parent_span, Some(spans[0].span_id));
+        bus.shutdown().unwrap();
+    }
+
+    // ========================================
+    // Tests for request/reply
+    // ========================================
+
+    /// Answers `CloseTab { tab_id }` with `Reload { tab_id }`, after `delay`
+    struct ReplyingHandler {
+        delay: Duration,
+    }
+
+    impl MessageHandler for ReplyingHandler {
+        fn handle(&self, _message: BrowserMessage) -> Result<()> {
+            Ok(())
+        }
+
+        fn handle_request(&self, message: BrowserMessage) -> Result<Option<BrowserMessage>> {
+            std::thread::sleep(self.delay);
+            Ok(match message {
+                BrowserMessage::CloseTab { tab_id } => Some(BrowserMessage::Reload { tab_id }),
+                _ => None,
+            })
+        }
+    }
+
+    #[test]
+    fn test_concurrent_requests_get_their_own_replies() {
+        let mut bus = MessageBus::new();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        bus.register_handler(Box::new(TestHandler {
+            received: Arc::clone(&received),
+        }));
+        bus.register_handler(Box::new(ReplyingHandler {
+            delay: Duration::ZERO,
+        }));
+        bus.start().unwrap();
+
+        let threads: Vec<_> = (0..8)
+            .map(|tab_id| {
+                let sender = bus.sender();
+                std::thread::spawn(move || {
+                    for _ in 0..20 {
+                        let reply = sender.request(BrowserMessage::CloseTab { tab_id }).unwrap();
+                        assert!(matches!(
+                            reply,
+                            BrowserMessage::Reload { tab_id: id } if id == tab_id
+                        ));
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // One-way handlers see requests like any other message
+        assert_eq!(received.lock().unwrap().len(), 160);
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_request_times_out_or_gets_no_reply() {
+        let mut bus = MessageBus::new();
+        bus.register_handler(Box::new(ReplyingHandler {
+            delay: Duration::from_millis(200),
+        }));
+        bus.start().unwrap();
+        let sender = bus.sender();
+
+        let timeout = Duration::from_millis(20);
+        assert!(matches!(
+            sender.request_timeout(BrowserMessage::CloseTab { tab_id: 1 }, timeout),
+            Err(Error::RequestTimeout(t)) if t == timeout
+        ));
+        // Nobody replies to a message the handlers don't answer
+        assert!(matches!(
+            sender.request(BrowserMessage::Shutdown),
+            Err(Error::NoReply)
+        ));
+        bus.shutdown().unwrap();
+    }
+
+    // ========================================
+    // Tests for filtered handlers
+    // ========================================
+
+    #[test]
+    fn test_tab_handler_never_sees_http_messages() {
+        let mut bus = MessageBus::new();
+        let tab_messages = Arc::new(Mutex::new(Vec::new()));
+        let all_messages = Arc::new(Mutex::new(Vec::new()));
+        bus.register_handler_filtered(
+            Box::new(TestHandler {
+                received: Arc::clone(&tab_messages),
+            }),
+            MessageFilter::kind(MessageKind::Tab),
+        );
+        bus.register_handler(Box::new(TestHandler {
+            received: Arc::clone(&all_messages),
+        }));
+        bus.start().unwrap();
+        let sender = bus.sender();
+
+        for request_id in 0..10 {
+            sender
+                .send(BrowserMessage::HttpRequest {
+                    request_id,
+                    url: Url::parse("https://example.com/").unwrap(),
+                    method: "GET".to_string(),
+                    headers: HashMap::new(),
+                    body: None,
+                    initiator: None,
+                })
+                .unwrap();
+        }
+        sender.send(BrowserMessage::CloseTab { tab_id: 1 }).unwrap();
+
+        wait_for(|| all_messages.lock().unwrap().len() == 11);
+        bus.shutdown().unwrap();
+        let msgs = tab_messages.lock().unwrap();
+        assert_eq!(msgs.len(), 1);
+        assert!(matches!(msgs[0], BrowserMessage::CloseTab { tab_id: 1 }));
+    }
+
+    #[test]
+    fn test_filter_by_tab() {
+        let mut bus = MessageBus::new();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        bus.register_handler_filtered(
+            Box::new(TestHandler {
+                received: Arc::clone(&received),
+            }),
+            MessageFilter::kinds([MessageKind::Tab, MessageKind::Navigation]).with_tab(2),
+        );
+        bus.start().unwrap();
+        let sender = bus.sender();
+
+        sender.send(BrowserMessage::Reload { tab_id: 1 }).unwrap();
+        sender.send(BrowserMessage::Shutdown).unwrap();
+        sender.send(BrowserMessage::Reload { tab_id: 2 }).unwrap();
+        sender.send(BrowserMessage::CloseTab { tab_id: 2 }).unwrap();
+
+        wait_for(|| received.lock().unwrap().len() == 2);
+        bus.shutdown().unwrap();
+        let msgs = received.lock().unwrap();
+        assert!(matches!(msgs[0], BrowserMessage::Reload { tab_id: 2 }));
+        assert!(matches!(msgs[1], BrowserMessage::CloseTab { tab_id: 2 }));
+    }
+
+    // ========================================
+    // Tests for async handlers
+    // ========================================
+
+    /// Records `Reload` tab ids, sleeping on the first one
+    struct SleepyHandler {
+        received: Arc<Mutex<Vec<u32>>>,
+    }
+
+    impl AsyncMessageHandler for SleepyHandler {
+        fn handle(&self, message: BrowserMessage) -> Handling<'_> {
+            Box::pin(async move {
+                if let BrowserMessage::Reload { tab_id } = message {
+                    if tab_id == 0 {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                    }
+                    self.received.lock().unwrap().push(tab_id);
+                }
+                Ok(())
+            })
+        }
+    }
+
+    #[test]
+    fn test_async_handler_keeps_send_order_across_awaits() {
+        let runtime = Arc::new(tokio::runtime::Runtime::new().unwrap());
+        let mut bus = MessageBus::new().with_runtime(runtime);
+        let async_received = Arc::new(Mutex::new(Vec::new()));
+        let sync_received = Arc::new(Mutex::new(Vec::new()));
+        bus.register_async_handler(Box::new(SleepyHandler {
+            received: Arc::clone(&async_received),
+        }))
+        .unwrap();
+        bus.register_handler(Box::new(TestHandler {
+            received: Arc::clone(&sync_received),
+        }));
+        bus.start().unwrap();
+        let sender = bus.sender();
+
+        for tab_id in 0..10 {
+            sender.send(BrowserMessage::Reload { tab_id }).unwrap();
+        }
+
+        // The sync handler is not held up while the async one sleeps
+        wait_for(|| sync_received.lock().unwrap().len() == 10);
+        assert!(async_received.lock().unwrap().is_empty());
+
+        wait_for(|| async_received.lock().unwrap().len() == 10);
+        assert_eq!(*async_received.lock().unwrap(), (0..10).collect::<Vec<_>>());
+        bus.shutdown().unwrap();
+    }
+
+    // ========================================
+    // Tests for flushing and shutdown
+    // ========================================
+
+    /// Answers `CloseTab { tab_id }` with a `Reload { tab_id }` message
+    struct Relay {
+        sender: Box<dyn MessageSender>,
+    }
+
+    impl MessageHandler for Relay {
+        fn handle(&self, message: BrowserMessage) -> Result<()> {
+            if let BrowserMessage::CloseTab { tab_id } = message {
+                std::thread::sleep(Duration::from_millis(2));
+                self.sender.send(BrowserMessage::Reload { tab_id })?;
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_flush_waits_for_follow_on_messages() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        bus.register_handler(Box::new(Relay {
+            sender: bus.sender(),
+        }));
+        bus.register_handler(Box::new(TestHandler {
+            received: Arc::clone(&received),
+        }));
+        let sender = bus.sender();
+
+        for tab_id in 0..10 {
+            sender.send(BrowserMessage::CloseTab { tab_id }).unwrap();
+        }
+        bus.flush().unwrap();
+
+        // Ten requests and ten follow-ons, with the bus still running
+        assert_eq!(received.lock().unwrap().len(), 20);
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_shutdown_drains_queued_messages() {
+        let mut bus = MessageBus::new();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        bus.register_handler(Box::new(Relay {
+            sender: Box::new(NullSender),
+        }));
+        bus.register_handler(Box::new(TestHandler {
+            received: Arc::clone(&received),
+        }));
+        bus.start().unwrap();
+        let sender = bus.sender();
+
+        for tab_id in 0..20 {
+            sender.send(BrowserMessage::CloseTab { tab_id }).unwrap();
+        }
+        assert_eq!(bus.shutdown().unwrap(), 0);
+
+        assert_eq!(received.lock().unwrap().len(), 20);
+        assert!(matches!(
+            sender.send(BrowserMessage::Shutdown),
+            Err(Error::BusShuttingDown)
+        ));
+        assert!(matches!(bus.flush(), Err(Error::AlreadyShutdown)));
+    }
+
+    #[test]
+    fn test_shutdown_timeout_reports_dropped_messages() {
+        let mut bus = MessageBus::new();
+        let (release, gate) = crossbeam_channel::unbounded();
+        bus.register_handler(Box::new(GateHandler {
+            gate: Mutex::new(gate),
+        }));
+        bus.start().unwrap();
+        let sender = bus.sender();
+
+        for tab_id in 0..5 {
+            sender.send(BrowserMessage::CloseTab { tab_id }).unwrap();
+        }
+        // The first message is stuck in the handler, the rest are queued
+        let dropped = bus
+            .shutdown_with_timeout(Duration::from_millis(20))
+            .unwrap();
+        assert_eq!(dropped, 4);
+        drop(release);
+    }
+
+    /// Sender that discards messages
+    struct NullSender;
+
+    impl MessageSender for NullSender {
+        fn send(&self, _message: BrowserMessage) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    // ========================================
+    // Tests for error reporting and stats
+    // ========================================
+
+    /// Fails on `CloseTab` for odd tabs
+    struct OddTabsFail;
+
+    impl MessageHandler for OddTabsFail {
+        fn handle(&self, message: BrowserMessage) -> Result<()> {
+            match message {
+                BrowserMessage::CloseTab { tab_id } if tab_id % 2 == 1 => {
+                    Err(Error::HandlerError(format!("tab {}", tab_id)))
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_error_handler_sees_failing_message() {
+        let mut bus = MessageBus::new();
+        let failures = Arc::new(Mutex::new(Vec::new()));
+        let hook_failures = Arc::clone(&failures);
+        bus.set_error_handler(Box::new(move |message, error| {
+            hook_failures
+                .lock()
+                .unwrap()
+                .push((message.clone(), error.to_string()));
+        }));
+        bus.register_handler(Box::new(OddTabsFail));
+        bus.start().unwrap();
+        let sender = bus.sender();
+
+        for tab_id in 0..6 {
+            sender.send(BrowserMessage::CloseTab { tab_id }).unwrap();
+        }
+        bus.flush().unwrap();
+
+        let failures = failures.lock().unwrap();
+        assert_eq!(failures.len(), 3);
+        for ((message, error), expected) in failures.iter().zip([1, 3, 5]) {
+            assert!(matches!(message, BrowserMessage::CloseTab { tab_id } if *tab_id == expected));
+            assert!(error.ends_with(&format!("tab {}", expected)));
+        }
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_stats_are_accurate_under_concurrency() {
+        let mut bus = MessageBus::new();
+        bus.set_error_handler(Box::new(|_, _| {}));
+        bus.register_handler_filtered(Box::new(OddTabsFail), MessageFilter::kind(MessageKind::Tab));
+        bus.start().unwrap();
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let sender = bus.sender();
+                std::thread::spawn(move || {
+                    for tab_id in 0..100 {
+                        sender.send(BrowserMessage::CloseTab { tab_id }).unwrap();
+                        // No handler takes system messages
+                        if tab_id % 10 == 0 {
+                            sender.send(BrowserMessage::Shutdown).unwrap();
+                        }
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        bus.shutdown().unwrap();
+
+        let stats = bus.stats();
+        assert_eq!(stats.delivered, 400);
+        assert_eq!(stats.handler_errors, 200);
+        assert_eq!(stats.dropped, 40);
+    }
+
+    /// Fails its first `failures` calls
+    struct FlakyHandler {
+        failures: Mutex<u32>,
+        calls: Arc<Mutex<u32>>,
+    }
+
+    impl MessageHandler for FlakyHandler {
+        fn handle(&self, _message: BrowserMessage) -> Result<()> {
+            *self.calls.lock().unwrap() += 1;
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                return Err(Error::HandlerError("flaky".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_retries_come_before_the_error_handler() {
+        let mut bus = MessageBus::new();
+        let reported = Arc::new(Mutex::new(0));
+        let hook_reported = Arc::clone(&reported);
+        bus.set_error_handler(Box::new(move |_, _| *hook_reported.lock().unwrap() += 1));
+        let recovered_calls = Arc::new(Mutex::new(0));
+        bus.register_handler_with_retries(
+            Box::new(FlakyHandler {
+                failures: Mutex::new(2),
+                calls: Arc::clone(&recovered_calls),
+            }),
+            2,
+        );
+        let failed_calls = Arc::new(Mutex::new(0));
+        bus.register_handler_with_retries(
+            Box::new(FlakyHandler {
+                failures: Mutex::new(3),
+                calls: Arc::clone(&failed_calls),
+            }),
+            2,
+        );
+        bus.start().unwrap();
+
+        bus.sender().send(BrowserMessage::Shutdown).unwrap();
+        bus.shutdown().unwrap();
+
+        assert_eq!(*recovered_calls.lock().unwrap(), 3);
+        assert_eq!(*failed_calls.lock().unwrap(), 3);
+        assert_eq!(*reported.lock().unwrap(), 1);
+        assert_eq!(bus.stats().handler_errors, 1);
+    }
+
+    // ========================================
+    // Tests for message envelopes
+    // ========================================
+
+    /// Origin and sequence number of each recorded message
+    type Envelopes = Arc<Mutex<Vec<(Option<&'static str>, u64)>>>;
+
+    /// Records the origin and sequence number of every message
+    struct EnvelopeRecorder {
+        seen: Envelopes,
+    }
+
+    impl MessageHandler for EnvelopeRecorder {
+        fn handle(&self, _message: BrowserMessage) -> Result<()> {
+            panic!("envelopes are handled by handle_envelope");
+        }
+
+        fn handle_envelope(&self, envelope: MessageEnvelope) -> Result<()> {
+            self.seen
+                .lock()
+                .unwrap()
+                .push((envelope.origin, envelope.seq));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_scoped_senders_tag_origin_and_sequence() {
+        const COMPONENTS: [&str; 4] = ["network_stack", "adblock_engine", "browser_core", "shell"];
+        let mut bus = MessageBus::new();
+        bus.enable_envelope_logging(true);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        bus.register_handler(Box::new(EnvelopeRecorder {
+            seen: Arc::clone(&seen),
+        }));
+        bus.start().unwrap();
+
+        let threads: Vec<_> = COMPONENTS
+            .into_iter()
+            .map(|component| {
+                let sender = bus.sender_for(component);
+                std::thread::spawn(move || {
+                    for tab_id in 0..50 {
+                        sender.send(BrowserMessage::Reload { tab_id }).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        bus.sender().send(BrowserMessage::Shutdown).unwrap();
+        bus.shutdown().unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 201);
+        assert_eq!(seen.last().unwrap().0, None);
+        for component in COMPONENTS {
+            let seqs: Vec<u64> = seen
+                .iter()
+                .filter(|(origin, _)| *origin == Some(component))
+                .map(|&(_, seq)| seq)
+                .collect();
+            assert_eq!(seqs.len(), 50);
+            assert!(seqs.windows(2).all(|pair| pair[0] < pair[1]));
+        }
+    }
 }
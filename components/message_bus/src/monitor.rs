@@ -0,0 +1,72 @@
+//! Delivery statistics and handler error reporting
+//!
+//! The dispatcher and the async handler tasks report every delivered
+//! message, failed handler call and dropped message to a [`Monitor`].
+//! [`MessageBus::stats`](crate::MessageBus::stats) reads its counters, and
+//! the hook set with
+//! [`MessageBus::set_error_handler`](crate::MessageBus::set_error_handler)
+//! sees each failure with the message that caused it.
+
+use crate::errors::Error;
+use shared_types::BrowserMessage;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Hook called with a message and the error a handler returned for it
+pub type ErrorHandler = Box<dyn Fn(&BrowserMessage, &Error) + Send + Sync>;
+
+/// Message bus counters since the bus was created
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BusStats {
+    /// Messages handed to at least one handler
+    pub delivered: u64,
+    /// Handler calls that failed, after any retries
+    pub handler_errors: u64,
+    /// Messages no handler received: none was registered for them, or
+    /// shutdown timed out before they were dispatched
+    pub dropped: u64,
+}
+
+/// Counters and error hook shared by the dispatcher and async handlers
+#[derive(Default)]
+pub(crate) struct Monitor {
+    delivered: AtomicU64,
+    handler_errors: AtomicU64,
+    dropped: AtomicU64,
+    error_handler: RwLock<Option<Arc<ErrorHandler>>>,
+}
+
+impl Monitor {
+    pub(crate) fn delivered(&self) {
+        self.delivered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn dropped(&self, count: u64) {
+        self.dropped.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Count a failed handler call and pass it to the error hook
+    ///
+    /// Without a hook the error is printed, as before hooks existed.
+    pub(crate) fn handler_failed(&self, message: &BrowserMessage, error: &Error) {
+        self.handler_errors.fetch_add(1, Ordering::Relaxed);
+        // Cloned out so the hook can replace itself
+        let hook = self.error_handler.read().unwrap().clone();
+        match hook {
+            Some(hook) => hook(message, error),
+            None => eprintln!("Handler error: {}", error),
+        }
+    }
+
+    pub(crate) fn set_error_handler(&self, hook: ErrorHandler) {
+        *self.error_handler.write().unwrap() = Some(Arc::new(hook));
+    }
+
+    pub(crate) fn snapshot(&self) -> BusStats {
+        BusStats {
+            delivered: self.delivered.load(Ordering::Relaxed),
+            handler_errors: self.handler_errors.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
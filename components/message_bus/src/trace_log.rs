@@ -0,0 +1,296 @@
+//! Recent traces as seen by the bus
+//!
+//! When tracing is enabled the bus records, for every message that belongs
+//! to a trace, which message caused it and how long its handlers took.
+//! Only the most recent traces are kept: starting a trace beyond the
+//! capacity evicts the oldest one.
+
+use shared_types::{BrowserMessage, TraceContext};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Most spans kept for one trace; later messages of the trace are dropped
+pub const MAX_SPANS_PER_TRACE: usize = 256;
+
+/// One message of a trace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceSpan {
+    /// Span id, the message's sequence number on the bus
+    pub span_id: u64,
+    /// Span whose handling sent this message; `None` for the first
+    pub parent_span: Option<u64>,
+    /// Message kind, e.g. `NavigateRequest`
+    pub message: String,
+    /// When the message was sent, relative to the start of the trace
+    pub sent_at: Duration,
+    /// Time all handlers took for the message, once dispatched
+    pub handled_in: Option<Duration>,
+}
+
+/// The recorded messages of one user action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    /// Trace id shared by the messages
+    pub trace_id: u64,
+    /// Messages in send order
+    pub spans: Vec<TraceSpan>,
+}
+
+impl Trace {
+    /// Spans caused by `parent`, or the spans that start the trace for `None`
+    ///
+    /// A span whose parent was not recorded (evicted or sent before tracing
+    /// was enabled) counts as starting the trace.
+    pub fn children(&self, parent: Option<u64>) -> impl Iterator<Item = &TraceSpan> {
+        let recorded = |id: u64| self.spans.iter().any(|span| span.span_id == id);
+        self.spans.iter().filter(move |span| match parent {
+            Some(_) => span.parent_span == parent,
+            None => span.parent_span.is_none_or(|id| !recorded(id)),
+        })
+    }
+
+    /// Total time from the first message to the last handler finishing
+    pub fn duration(&self) -> Duration {
+        self.spans
+            .iter()
+            .map(|span| span.sent_at + span.handled_in.unwrap_or_default())
+            .max()
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for Trace {
+    /// One line per message, indented under the message that caused it
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_level(
+            trace: &Trace,
+            f: &mut fmt::Formatter<'_>,
+            parent: Option<u64>,
+            depth: usize,
+        ) -> fmt::Result {
+            for span in trace.children(parent) {
+                write!(
+                    f,
+                    "{:indent$}{} +{}ms",
+                    "",
+                    span.message,
+                    span.sent_at.as_millis(),
+                    indent = depth * 2
+                )?;
+                match span.handled_in {
+                    Some(took) => writeln!(f, " ({}ms)", took.as_millis())?,
+                    None => writeln!(f, " (pending)")?,
+                }
+                write_level(trace, f, Some(span.span_id), depth + 1)?;
+            }
+            Ok(())
+        }
+        write_level(self, f, None, 0)
+    }
+}
+
+/// A trace being recorded
+#[derive(Debug)]
+struct Recording {
+    /// When the first message of the trace was sent
+    started: Instant,
+    trace: Trace,
+}
+
+/// Bounded buffer of recent traces
+#[derive(Debug, Default)]
+struct TraceBuffer {
+    /// Traces to keep; 0 turns recording off
+    capacity: usize,
+    /// Oldest first
+    traces: VecDeque<Recording>,
+}
+
+/// Shared handle to the bus's recent traces
+///
+/// Clones share one buffer, so a handle taken from
+/// [`MessageBus::trace_log`](crate::MessageBus::trace_log) sees what the bus
+/// records afterwards.
+#[derive(Debug, Clone, Default)]
+pub struct TraceLog {
+    buffer: Arc<Mutex<TraceBuffer>>,
+}
+
+impl TraceLog {
+    /// Create a log keeping the last `capacity` traces; 0 records nothing
+    pub fn new(capacity: usize) -> Self {
+        let log = Self::default();
+        log.set_capacity(capacity);
+        log
+    }
+
+    /// Change how many traces are kept, evicting the oldest if needed
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Traces to keep; 0 turns recording off and clears the log
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.capacity = capacity;
+        let excess = buffer.traces.len().saturating_sub(capacity);
+        buffer.traces.drain(..excess);
+    }
+
+    /// Whether messages are being recorded
+    pub fn is_enabled(&self) -> bool {
+        self.buffer.lock().unwrap().capacity > 0
+    }
+
+    /// Record that a traced message was sent
+    ///
+    /// # Arguments
+    ///
+    /// * `span_id` - Sequence number the bus gave the message
+    /// * `trace` - Trace the message was sent in
+    /// * `message` - The message
+    pub fn record_sent(&self, span_id: u64, trace: TraceContext, message: &BrowserMessage) {
+        let mut buffer = self.buffer.lock().unwrap();
+        if buffer.capacity == 0 {
+            return;
+        }
+        let now = Instant::now();
+        let index = match buffer
+            .traces
+            .iter()
+            .position(|recording| recording.trace.trace_id == trace.trace_id)
+        {
+            Some(index) => index,
+            None => {
+                if buffer.traces.len() == buffer.capacity {
+                    buffer.traces.pop_front();
+                }
+                buffer.traces.push_back(Recording {
+                    started: now,
+                    trace: Trace {
+                        trace_id: trace.trace_id,
+                        spans: Vec::new(),
+                    },
+                });
+                buffer.traces.len() - 1
+            }
+        };
+        let recording = &mut buffer.traces[index];
+        if recording.trace.spans.len() < MAX_SPANS_PER_TRACE {
+            recording.trace.spans.push(TraceSpan {
+                span_id,
+                parent_span: trace.parent_span,
+                message: message_kind(message),
+                sent_at: now.saturating_duration_since(recording.started),
+                handled_in: None,
+            });
+        }
+    }
+
+    /// Record how long the handlers of a traced message took
+    ///
+    /// Does nothing if the trace has been evicted since the message was
+    /// sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `trace_id` - Trace the message belongs to
+    /// * `span_id` - Sequence number the bus gave the message
+    /// * `took` - Time spent in handlers
+    pub fn record_handled(&self, trace_id: u64, span_id: u64, took: Duration) {
+        let mut buffer = self.buffer.lock().unwrap();
+        let span = buffer
+            .traces
+            .iter_mut()
+            .find(|recording| recording.trace.trace_id == trace_id)
+            .and_then(|recording| {
+                recording
+                    .trace
+                    .spans
+                    .iter_mut()
+                    .find(|span| span.span_id == span_id)
+            });
+        if let Some(span) = span {
+            span.handled_in = Some(took);
+        }
+    }
+
+    /// The most recent traces, newest first
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - Most traces to return
+    pub fn recent(&self, limit: usize) -> Vec<Trace> {
+        let buffer = self.buffer.lock().unwrap();
+        buffer
+            .traces
+            .iter()
+            .rev()
+            .take(limit)
+            .map(|recording| recording.trace.clone())
+            .collect()
+    }
+}
+
+/// Variant name of a message, without its fields
+fn message_kind(message: &BrowserMessage) -> String {
+    let debug = format!("{:?}", message);
+    let end = debug.find([' ', '{', '(']).unwrap_or(debug.len());
+    debug[..end].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(trace_id: u64, parent_span: Option<u64>) -> TraceContext {
+        TraceContext {
+            trace_id,
+            parent_span,
+        }
+    }
+
+    #[test]
+    fn test_buffer_evicts_oldest_trace() {
+        let log = TraceLog::new(2);
+        for trace_id in 1..=3 {
+            log.record_sent(
+                trace_id * 10,
+                ctx(trace_id, None),
+                &BrowserMessage::Shutdown,
+            );
+        }
+        let ids: Vec<u64> = log.recent(10).iter().map(|t| t.trace_id).collect();
+        assert_eq!(ids, vec![3, 2]);
+
+        // Late messages of an evicted trace start it afresh
+        log.record_handled(1, 10, Duration::from_millis(5));
+        log.record_sent(11, ctx(1, Some(10)), &BrowserMessage::Reload { tab_id: 1 });
+        let ids: Vec<u64> = log.recent(10).iter().map(|t| t.trace_id).collect();
+        assert_eq!(ids, vec![1, 3]);
+
+        log.set_capacity(0);
+        assert!(!log.is_enabled());
+        assert!(log.recent(10).is_empty());
+        log.record_sent(40, ctx(4, None), &BrowserMessage::Shutdown);
+        assert!(log.recent(10).is_empty());
+    }
+
+    #[test]
+    fn test_trace_renders_as_tree() {
+        let log = TraceLog::new(1);
+        log.record_sent(1, ctx(7, None), &BrowserMessage::Reload { tab_id: 1 });
+        log.record_sent(2, ctx(7, Some(1)), &BrowserMessage::GoBack { tab_id: 1 });
+        log.record_sent(3, ctx(7, Some(2)), &BrowserMessage::GoForward { tab_id: 1 });
+        log.record_handled(7, 1, Duration::from_millis(3));
+
+        let trace = &log.recent(1)[0];
+        assert_eq!(trace.spans[1].message, "GoBack");
+        let lines: Vec<String> = trace.to_string().lines().map(str::to_string).collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("Reload +") && lines[0].ends_with("(3ms)"));
+        assert!(lines[1].starts_with("  GoBack +") && lines[1].ends_with("(pending)"));
+        assert!(lines[2].starts_with("    GoForward +"));
+    }
+}
@@ -1,12 +1,25 @@
 //! Core types for message bus
 
+use crate::async_handler::{AsyncForwarder, AsyncMessageHandler, Backlog};
 use crate::errors::{Error, Result};
-use crossbeam_channel::{unbounded, Receiver, Sender};
-use shared_types::BrowserMessage;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
-use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use crate::monitor::{BusStats, ErrorHandler, Monitor};
+use crate::trace_log::TraceLog;
+use crossbeam_channel::{bounded, unbounded, Receiver, RecvTimeoutError, Sender};
+use shared_types::{BrowserMessage, MessageKind, TabId, TraceContext, TracedMessage};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::thread::{self, JoinHandle, ThreadId};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::runtime::{Builder, Runtime};
+
+/// How long [`MessageSender::request`] waits for a reply
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long [`MessageBus::shutdown`] waits for queued messages to be
+/// handled
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Trait for sending messages to the bus
 pub trait MessageSender: Send + Sync {
@@ -15,9 +28,64 @@ pub trait MessageSender: Send + Sync {
     /// # Errors
     ///
     /// Returns an error if:
-    /// - The bus is shut down
+    /// - The bus is shutting down or shut down (`Error::BusShuttingDown`)
     /// - The channel is disconnected
     fn send(&self, message: BrowserMessage) -> Result<()>;
+
+    /// Send a message as part of `trace`
+    ///
+    /// `send` uses the trace current on the calling thread; this is for
+    /// forwarding a message whose trace is known explicitly. Senders that
+    /// do not carry traces send the bare message.
+    ///
+    /// # Errors
+    ///
+    /// As for [`send`](Self::send).
+    fn send_traced(&self, message: BrowserMessage, trace: Option<TraceContext>) -> Result<()> {
+        let _ = trace;
+        self.send(message)
+    }
+
+    /// Send a message and wait up to [`DEFAULT_REQUEST_TIMEOUT`] for the
+    /// reply, e.g. a `BlockDecision` for a `ShouldBlock`
+    ///
+    /// # Errors
+    ///
+    /// As for [`request_timeout`](Self::request_timeout).
+    fn request(&self, message: BrowserMessage) -> Result<BrowserMessage> {
+        self.request_timeout(message, DEFAULT_REQUEST_TIMEOUT)
+    }
+
+    /// Send a message and wait for the reply of a handler's
+    /// [`handle_request`](MessageHandler::handle_request)
+    ///
+    /// Each request gets its own reply channel, so concurrent requests
+    /// never receive each other's replies. Must not be called from a
+    /// handler: the bus delivers one message at a time, so the request
+    /// would only be handled after the timeout. Senders that cannot carry
+    /// replies send the message and return `Error::NoReply`.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The request
+    /// * `timeout` - How long to wait for the reply
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The message cannot be sent, as for [`send`](Self::send)
+    /// - No handler replies within `timeout` (`Error::RequestTimeout`)
+    /// - Every handler handled the request without replying
+    ///   (`Error::NoReply`)
+    fn request_timeout(
+        &self,
+        message: BrowserMessage,
+        timeout: Duration,
+    ) -> Result<BrowserMessage> {
+        let _ = timeout;
+        self.send(message)?;
+        Err(Error::NoReply)
+    }
 }
 
 /// Trait for handling messages from the bus
@@ -28,22 +96,385 @@ pub trait MessageHandler: Send + Sync {
     ///
     /// Returns an error if message processing fails
     fn handle(&self, message: BrowserMessage) -> Result<()>;
+
+    /// Handle a message sent with [`MessageSender::request`]
+    ///
+    /// The first reply returned by a handler, in registration order, goes
+    /// to the requester; later handlers still see the request. By default
+    /// the request is passed to [`handle`](Self::handle) and not replied
+    /// to, so one-way handlers see requests like any other message.
+    ///
+    /// # Returns
+    ///
+    /// Returns the reply, or `None` to leave the request to other handlers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if message processing fails
+    fn handle_request(&self, message: BrowserMessage) -> Result<Option<BrowserMessage>> {
+        self.handle(message)?;
+        Ok(None)
+    }
+
+    /// Handle a received message along with who sent it and when
+    ///
+    /// Called for every message not sent as a request. By default the
+    /// envelope is unwrapped and the message passed to
+    /// [`handle`](Self::handle); handlers that want the origin override
+    /// this instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if message processing fails
+    fn handle_envelope(&self, envelope: MessageEnvelope) -> Result<()> {
+        self.handle(envelope.message)
+    }
+}
+
+/// A message with where and when it was sent
+#[derive(Debug, Clone)]
+pub struct MessageEnvelope {
+    /// The message
+    pub message: BrowserMessage,
+    /// Component that sent it, for senders from
+    /// [`MessageBus::sender_for`]
+    pub origin: Option<&'static str>,
+    /// Position in the bus's send order; increases with every message
+    pub seq: u64,
+    /// When it was sent
+    pub sent_at: SystemTime,
+}
+
+/// Identifies a registered handler, for unregistering it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HandlerId(u64);
+
+impl fmt::Display for HandlerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "handler #{}", self.0)
+    }
+}
+
+/// Which messages a handler registered with
+/// [`MessageBus::register_handler_filtered`] receives
+///
+/// A message passes if its [`kind`](BrowserMessage::kind) is one of the
+/// filter's kinds (any kind if none were given) and, when the filter has a
+/// tab, its [`tab_id`](BrowserMessage::tab_id) is that tab. Messages not
+/// about any tab never pass a filter with a tab.
+///
+/// # Example
+///
+/// ```
+/// use message_bus::MessageFilter;
+/// use shared_types::{BrowserMessage, MessageKind};
+///
+/// let filter = MessageFilter::kind(MessageKind::Tab).with_tab(1);
+/// assert!(filter.matches(&BrowserMessage::CloseTab { tab_id: 1 }));
+/// assert!(!filter.matches(&BrowserMessage::CloseTab { tab_id: 2 }));
+/// assert!(!filter.matches(&BrowserMessage::Reload { tab_id: 1 }));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageFilter {
+    kinds: Vec<MessageKind>,
+    tab_id: Option<TabId>,
+}
+
+impl MessageFilter {
+    /// Filter passing messages of one kind
+    pub fn kind(kind: MessageKind) -> Self {
+        Self::default().with_kind(kind)
+    }
+
+    /// Filter passing messages of any of `kinds`
+    pub fn kinds(kinds: impl IntoIterator<Item = MessageKind>) -> Self {
+        kinds.into_iter().fold(Self::default(), Self::with_kind)
+    }
+
+    /// Filter passing messages of every kind about one tab
+    pub fn tab(tab_id: TabId) -> Self {
+        Self::default().with_tab(tab_id)
+    }
+
+    /// Also pass messages of `kind`
+    pub fn with_kind(mut self, kind: MessageKind) -> Self {
+        if !self.kinds.contains(&kind) {
+            self.kinds.push(kind);
+        }
+        self
+    }
+
+    /// Only pass messages about `tab_id`
+    pub fn with_tab(mut self, tab_id: TabId) -> Self {
+        self.tab_id = Some(tab_id);
+        self
+    }
+
+    /// Whether a handler with this filter receives `message`
+    pub fn matches(&self, message: &BrowserMessage) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&message.kind()))
+            && self
+                .tab_id
+                .is_none_or(|tab_id| message.tab_id() == Some(tab_id))
+    }
+}
+
+/// A message with its place in send order
+struct Sequenced {
+    seq: u64,
+    traced: TracedMessage,
+    /// Component the sender was made for
+    origin: Option<&'static str>,
+    sent_at: SystemTime,
+    /// Where the reply goes, if the message is a request
+    reply_to: Option<Sender<BrowserMessage>>,
+}
+
+/// What the dispatcher thread receives
+///
+/// Nearly every envelope is a message, so boxing it to shrink the rare
+/// flush and stop signals would only add an allocation per send.
+#[allow(clippy::large_enum_variant)]
+enum Envelope {
+    /// A message to dispatch
+    Message(Sequenced),
+    /// Signal once everything queued before this is dispatched
+    Flush(Sender<()>),
+    /// Stop once everything queued before this is dispatched
+    Stop,
+}
+
+/// Progress of the dispatcher, for counting what a timed-out shutdown
+/// dropped
+#[derive(Default)]
+struct Drain {
+    /// Messages dispatched (or being dispatched)
+    dispatched: u64,
+    /// Set when shutdown gave up waiting; nothing more is dispatched
+    aborted: bool,
+}
+
+/// A registered handler
+struct HandlerEntry {
+    handler: Box<dyn MessageHandler>,
+    /// Sequence number of the first message the handler receives
+    from_seq: u64,
+    /// Messages the handler receives; `None` for all of them
+    filter: Option<MessageFilter>,
+    /// Times a failed call is repeated before the error is reported
+    max_retries: u32,
+    /// Set when the handler is unregistered; checked before every call
+    removed: AtomicBool,
+    /// Held while `handle` runs, so unregistering can wait for the call
+    in_call: Mutex<()>,
+}
+
+/// Handlers shared by the bus and its dispatcher thread
+#[derive(Default)]
+struct Registry {
+    /// Handlers by id; ids increase, so this is registration order
+    handlers: RwLock<BTreeMap<HandlerId, Arc<HandlerEntry>>>,
+    /// Next handler id
+    next_id: AtomicU64,
+    /// Sequence number the next sent message gets
+    next_seq: Arc<AtomicU64>,
+    /// The dispatcher thread, once started
+    dispatcher: Mutex<Option<ThreadId>>,
+    /// Recent traces, when tracing is enabled
+    trace_log: TraceLog,
+    /// Dispatcher progress
+    drain: Mutex<Drain>,
+    /// Messages async handlers have yet to handle
+    async_backlog: Arc<Backlog>,
+    /// Delivery counters and the error hook
+    monitor: Arc<Monitor>,
+    /// Whether every message is logged at debug level
+    log_envelopes: AtomicBool,
+}
+
+impl Registry {
+    /// Count a message about to be dispatched
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` if shutdown gave up waiting and the message must be
+    /// dropped.
+    fn begin_dispatch(&self) -> bool {
+        let mut drain = self.drain.lock().unwrap();
+        if drain.aborted {
+            return false;
+        }
+        drain.dispatched += 1;
+        true
+    }
+
+    /// Stop dispatching
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of sent messages that were never dispatched.
+    fn abort(&self) -> u64 {
+        let mut drain = self.drain.lock().unwrap();
+        drain.aborted = true;
+        let dropped = self.next_seq.load(Ordering::SeqCst) - drain.dispatched;
+        self.monitor.dropped(dropped);
+        dropped
+    }
+
+    /// Deliver a message to every handler registered before it was sent
+    /// whose filter passes it
+    ///
+    /// The table lock is only held to copy the handler list, never while a
+    /// handler runs. A traced message's handlers run in its trace, with the
+    /// message as parent span, so whatever they send joins the trace.
+    /// Dropping a request's reply channel afterwards tells the requester
+    /// when nobody replied. A failed call is repeated up to the handler's
+    /// retry limit before it is reported to the monitor.
+    fn dispatch(&self, sequenced: Sequenced) {
+        let Sequenced {
+            seq,
+            traced: TracedMessage { message, trace },
+            origin,
+            sent_at,
+            mut reply_to,
+        } = sequenced;
+        let envelope = MessageEnvelope {
+            message,
+            origin,
+            seq,
+            sent_at,
+        };
+        let message = &envelope.message;
+        if self.log_envelopes.load(Ordering::Relaxed) {
+            tracing::debug!(
+                seq,
+                origin = origin.unwrap_or("-"),
+                ?sent_at,
+                ?message,
+                "message bus dispatch"
+            );
+        }
+        let _trace = trace.map(|trace| trace.child(seq).enter());
+        let started = Instant::now();
+        let entries: Vec<Arc<HandlerEntry>> = self
+            .handlers
+            .read()
+            .unwrap()
+            .values()
+            .filter(|entry| entry.from_seq <= seq)
+            .filter(|entry| entry.filter.as_ref().is_none_or(|f| f.matches(message)))
+            .cloned()
+            .collect();
+        let mut delivered = false;
+        for entry in entries {
+            let _call = entry.in_call.lock().unwrap();
+            if entry.removed.load(Ordering::Acquire) {
+                continue;
+            }
+            delivered = true;
+            // Handle each message, but don't stop on errors
+            // This ensures one failing handler doesn't break the bus
+            let mut attempts = 0;
+            let result = loop {
+                let result = match &reply_to {
+                    Some(_) => entry.handler.handle_request(message.clone()),
+                    None => entry
+                        .handler
+                        .handle_envelope(envelope.clone())
+                        .map(|()| None),
+                };
+                if result.is_ok() || attempts == entry.max_retries {
+                    break result;
+                }
+                attempts += 1;
+            };
+            match result {
+                Ok(Some(reply)) => {
+                    // The requester may have timed out and gone
+                    if let Some(reply_to) = reply_to.take() {
+                        let _ = reply_to.send(reply);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => self.monitor.handler_failed(message, &e),
+            }
+        }
+        if delivered {
+            self.monitor.delivered();
+        } else {
+            self.monitor.dropped(1);
+        }
+        drop(reply_to);
+        if let Some(trace) = trace {
+            self.trace_log
+                .record_handled(trace.trace_id, seq, started.elapsed());
+        }
+    }
 }
 
 /// Internal sender implementation
 struct BusSender {
-    sender: Sender<BrowserMessage>,
-    shutdown: Arc<AtomicBool>,
+    sender: Sender<Envelope>,
+    /// Set once the bus starts shutting down; held for reading while a
+    /// message is queued, so nothing is queued after shutdown starts
+    closed: Arc<RwLock<bool>>,
+    /// Shared with the registry, which stamps handlers with it
+    next_seq: Arc<AtomicU64>,
+    /// Shared with the registry
+    trace_log: TraceLog,
+    /// Component this sender was made for
+    origin: Option<&'static str>,
 }
 
 impl MessageSender for BusSender {
     fn send(&self, message: BrowserMessage) -> Result<()> {
-        if self.shutdown.load(Ordering::Relaxed) {
-            return Err(Error::NotRunning);
+        self.send_traced(message, TraceContext::current())
+    }
+
+    fn send_traced(&self, message: BrowserMessage, trace: Option<TraceContext>) -> Result<()> {
+        self.enqueue(message, trace, None)
+    }
+
+    fn request_timeout(
+        &self,
+        message: BrowserMessage,
+        timeout: Duration,
+    ) -> Result<BrowserMessage> {
+        let (reply_to, replies) = bounded(1);
+        self.enqueue(message, TraceContext::current(), Some(reply_to))?;
+        match replies.recv_timeout(timeout) {
+            Ok(reply) => Ok(reply),
+            Err(RecvTimeoutError::Timeout) => Err(Error::RequestTimeout(timeout)),
+            Err(RecvTimeoutError::Disconnected) => Err(Error::NoReply),
+        }
+    }
+}
+
+impl BusSender {
+    /// Stamp a message with its sequence number and queue it
+    fn enqueue(
+        &self,
+        message: BrowserMessage,
+        trace: Option<TraceContext>,
+        reply_to: Option<Sender<BrowserMessage>>,
+    ) -> Result<()> {
+        let closed = self.closed.read().unwrap();
+        if *closed {
+            return Err(Error::BusShuttingDown);
         }
 
+        let seq = self.next_seq.fetch_add(1, Ordering::SeqCst);
+        if let Some(trace) = trace {
+            self.trace_log.record_sent(seq, trace, &message);
+        }
         self.sender
-            .send(message)
+            .send(Envelope::Message(Sequenced {
+                seq,
+                traced: TracedMessage { message, trace },
+                origin: self.origin,
+                sent_at: SystemTime::now(),
+                reply_to,
+            }))
             .map_err(|e| Error::SendError(e.to_string()))
     }
 }
@@ -60,7 +491,18 @@ enum BusState {
 ///
 /// The message bus provides a channel-based messaging system that allows
 /// components to communicate asynchronously. Messages are routed to all
-/// registered handlers.
+/// registered handlers, in registration order, except those whose
+/// [`MessageFilter`] rejects them.
+///
+/// Handlers can be registered and unregistered at any time, before or
+/// after [`start`](Self::start). A handler receives exactly the messages
+/// whose `send` began after its registration returned; messages sent
+/// earlier are never delivered to it, even if they are still queued.
+/// Messages from a `send` that overlaps the registration may or may not
+/// be delivered.
+///
+/// [`flush`](Self::flush) waits for the queue to empty; shutting down
+/// rejects further sends and handles everything already queued first.
 ///
 /// # Example
 ///
@@ -87,17 +529,21 @@ enum BusState {
 /// ```
 pub struct MessageBus {
     /// Message sender channel
-    sender: Option<Sender<BrowserMessage>>,
+    sender: Option<Sender<Envelope>>,
     /// Message receiver channel
-    receiver: Option<Receiver<BrowserMessage>>,
+    receiver: Option<Receiver<Envelope>>,
     /// Registered message handlers
-    handlers: Arc<Mutex<Vec<Box<dyn MessageHandler>>>>,
+    registry: Arc<Registry>,
     /// Current state of the bus
     state: BusState,
     /// Worker thread handle
     worker: Option<JoinHandle<()>>,
+    /// Disconnected when the worker thread exits
+    worker_exited: Option<Receiver<()>>,
     /// Shutdown flag shared with senders
-    shutdown: Arc<AtomicBool>,
+    closed: Arc<RwLock<bool>>,
+    /// Runtime driving async handlers; created on first use unless given
+    runtime: OnceLock<Arc<Runtime>>,
 }
 
 impl MessageBus {
@@ -106,19 +552,51 @@ impl MessageBus {
         Self {
             sender: None,
             receiver: None,
-            handlers: Arc::new(Mutex::new(Vec::new())),
+            registry: Arc::new(Registry::default()),
             state: BusState::Created,
             worker: None,
-            shutdown: Arc::new(AtomicBool::new(false)),
+            worker_exited: None,
+            closed: Arc::new(RwLock::new(false)),
+            runtime: OnceLock::new(),
         }
     }
 
+    /// Drive async handlers on `runtime`, e.g. the browser's shared
+    /// runtime, instead of a runtime of the bus's own
+    ///
+    /// # Arguments
+    ///
+    /// * `runtime` - Tokio runtime for [`register_async_handler`](Self::register_async_handler)
+    pub fn with_runtime(self, runtime: Arc<Runtime>) -> Self {
+        let _ = self.runtime.set(runtime);
+        self
+    }
+
     /// Get a message sender for this bus
     ///
     /// # Panics
     ///
     /// Panics if the bus has not been started yet.
     pub fn sender(&self) -> Box<dyn MessageSender> {
+        self.make_sender(None)
+    }
+
+    /// Get a message sender that tags its messages with `component`
+    ///
+    /// Handlers see the name as the [`MessageEnvelope::origin`].
+    ///
+    /// # Arguments
+    ///
+    /// * `component` - Name of the sending component, e.g. "network_stack"
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bus has not been started yet.
+    pub fn sender_for(&self, component: &'static str) -> Box<dyn MessageSender> {
+        self.make_sender(Some(component))
+    }
+
+    fn make_sender(&self, origin: Option<&'static str>) -> Box<dyn MessageSender> {
         let sender = self
             .sender
             .as_ref()
@@ -127,16 +605,219 @@ impl MessageBus {
 
         Box::new(BusSender {
             sender,
-            shutdown: Arc::clone(&self.shutdown),
+            closed: Arc::clone(&self.closed),
+            next_seq: Arc::clone(&self.registry.next_seq),
+            trace_log: self.registry.trace_log.clone(),
+            origin,
         })
     }
 
+    /// Record the last `capacity` traces for [`trace_log`](Self::trace_log)
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Traces to keep; 0 turns recording off
+    pub fn enable_tracing(&self, capacity: usize) {
+        self.registry.trace_log.set_capacity(capacity);
+    }
+
+    /// Log every message at debug level through `tracing`, with its
+    /// origin, sequence number and send time
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether to log
+    pub fn enable_envelope_logging(&self, enabled: bool) {
+        self.registry
+            .log_envelopes
+            .store(enabled, Ordering::Relaxed);
+    }
+
+    /// Handle to the recent traces; empty unless tracing is enabled
+    pub fn trace_log(&self) -> TraceLog {
+        self.registry.trace_log.clone()
+    }
+
+    /// Call `hook` whenever a handler fails, sync or async
+    ///
+    /// The hook gets the message the handler failed on and its error,
+    /// after any retries, on the thread the handler ran on. It replaces
+    /// the previous hook; without one, errors are printed to stderr.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - Called with the failing message and the error
+    pub fn set_error_handler(&self, hook: ErrorHandler) {
+        self.registry.monitor.set_error_handler(hook);
+    }
+
+    /// Messages delivered and dropped and handler errors so far
+    pub fn stats(&self) -> BusStats {
+        self.registry.monitor.snapshot()
+    }
+
     /// Register a message handler
     ///
-    /// Handlers receive all messages sent to the bus. Multiple handlers
-    /// can be registered, and each will receive a copy of every message.
-    pub fn register_handler(&mut self, handler: Box<dyn MessageHandler>) {
-        self.handlers.lock().unwrap().push(handler);
+    /// Works whether or not the bus is running. The handler receives a
+    /// copy of every message sent after this returns, but none sent
+    /// before (see the ordering rules on [`MessageBus`]). Multiple handlers
+    /// can be registered; each message goes to them in registration order.
+    ///
+    /// # Returns
+    ///
+    /// Returns the id to pass to [`unregister_handler`](Self::unregister_handler).
+    pub fn register_handler(&self, handler: Box<dyn MessageHandler>) -> HandlerId {
+        self.insert_handler(handler, None, 0)
+    }
+
+    /// Register a message handler that only receives messages passing
+    /// `filter`
+    ///
+    /// Otherwise like [`register_handler`](Self::register_handler). The
+    /// filter is checked on the dispatcher thread before the handler is
+    /// called, so handlers interested in a few kinds of message are not
+    /// called for the rest. A request the filter rejects is not offered to
+    /// the handler either.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - The handler
+    /// * `filter` - Messages the handler receives
+    ///
+    /// # Returns
+    ///
+    /// Returns the id to pass to [`unregister_handler`](Self::unregister_handler).
+    pub fn register_handler_filtered(
+        &self,
+        handler: Box<dyn MessageHandler>,
+        filter: MessageFilter,
+    ) -> HandlerId {
+        self.insert_handler(handler, Some(filter), 0)
+    }
+
+    /// Register a message handler whose failed calls are repeated
+    ///
+    /// Otherwise like [`register_handler`](Self::register_handler). When
+    /// the handler returns an error it is called again with the same
+    /// message, right away, up to `max_retries` times; only if the last
+    /// attempt fails too is the error counted and passed to the
+    /// [error handler](Self::set_error_handler). Later handlers wait for
+    /// the retries.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - The handler
+    /// * `max_retries` - Calls to make after the first one fails
+    ///
+    /// # Returns
+    ///
+    /// Returns the id to pass to [`unregister_handler`](Self::unregister_handler).
+    pub fn register_handler_with_retries(
+        &self,
+        handler: Box<dyn MessageHandler>,
+        max_retries: u32,
+    ) -> HandlerId {
+        self.insert_handler(handler, None, max_retries)
+    }
+
+    /// Register a handler that does async work
+    ///
+    /// Works like [`register_handler`](Self::register_handler) and can be
+    /// mixed with sync handlers, but the handler runs on the runtime given
+    /// to [`with_runtime`](Self::with_runtime) (or one the bus starts on
+    /// first use) instead of the dispatcher thread. Each message is handled
+    /// only after the handler's future for the previous one completed, so
+    /// the handler sees messages in send order however long it awaits;
+    /// other handlers do not wait for it.
+    ///
+    /// # Returns
+    ///
+    /// Returns the id to pass to [`unregister_handler`](Self::unregister_handler).
+    /// Unregistering stops delivery; messages already queued for the
+    /// handler are still handled.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Runtime` if the bus has no runtime and cannot start
+    /// one.
+    pub fn register_async_handler(
+        &self,
+        handler: Box<dyn AsyncMessageHandler>,
+    ) -> Result<HandlerId> {
+        let runtime = match self.runtime.get() {
+            Some(runtime) => runtime,
+            None => {
+                let runtime = Builder::new_multi_thread()
+                    .worker_threads(1)
+                    .thread_name("message-bus-async")
+                    .enable_all()
+                    .build()
+                    .map_err(|e| Error::Runtime(e.to_string()))?;
+                self.runtime.get_or_init(|| Arc::new(runtime))
+            }
+        };
+        let forwarder = AsyncForwarder::spawn(
+            handler,
+            runtime.handle(),
+            Arc::clone(&self.registry.async_backlog),
+            Arc::clone(&self.registry.monitor),
+        );
+        Ok(self.insert_handler(Box::new(forwarder), None, 0))
+    }
+
+    fn insert_handler(
+        &self,
+        handler: Box<dyn MessageHandler>,
+        filter: Option<MessageFilter>,
+        max_retries: u32,
+    ) -> HandlerId {
+        let id = HandlerId(self.registry.next_id.fetch_add(1, Ordering::Relaxed));
+        let mut handlers = self.registry.handlers.write().unwrap();
+        let entry = HandlerEntry {
+            handler,
+            from_seq: self.registry.next_seq.load(Ordering::SeqCst),
+            filter,
+            max_retries,
+            removed: AtomicBool::new(false),
+            in_call: Mutex::new(()),
+        };
+        handlers.insert(id, Arc::new(entry));
+        id
+    }
+
+    /// Remove a message handler
+    ///
+    /// Once this returns the handler receives no further calls: a call in
+    /// progress on the dispatcher thread is waited for. Called from within
+    /// a handler (on the dispatcher thread) it does not wait, since no
+    /// other call can be in progress then.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UnknownHandler` if the handler was never registered
+    /// or has already been removed; nothing changes then.
+    pub fn unregister_handler(&self, id: HandlerId) -> Result<()> {
+        let entry = self
+            .registry
+            .handlers
+            .write()
+            .unwrap()
+            .remove(&id)
+            .ok_or(Error::UnknownHandler(id))?;
+        entry.removed.store(true, Ordering::Release);
+
+        let on_dispatcher =
+            *self.registry.dispatcher.lock().unwrap() == Some(thread::current().id());
+        if !on_dispatcher {
+            // Wait out a call that started before the removal flag was set
+            drop(entry.in_call.lock().unwrap());
+        }
+        Ok(())
+    }
+
+    /// Number of registered handlers
+    pub fn handler_count(&self) -> usize {
+        self.registry.handlers.read().unwrap().len()
     }
 
     /// Start processing messages
@@ -157,43 +838,33 @@ impl MessageBus {
         }
 
         // Create channels
-        let (sender, receiver) = unbounded::<BrowserMessage>();
+        let (sender, receiver) = unbounded::<Envelope>();
         self.sender = Some(sender);
         self.receiver = Some(receiver);
+        let (exited, worker_exited) = bounded::<()>(0);
+        self.worker_exited = Some(worker_exited);
 
         // Clone handlers for worker thread
-        let handlers = Arc::clone(&self.handlers);
+        let registry = Arc::clone(&self.registry);
         let receiver = self.receiver.as_ref().unwrap().clone();
-        let shutdown = Arc::clone(&self.shutdown);
 
         // Spawn worker thread
         let worker = thread::spawn(move || {
-            loop {
-                // Check for shutdown
-                if shutdown.load(Ordering::Relaxed) {
-                    break;
-                }
-
-                // Try to receive with timeout to periodically check shutdown
-                match receiver.recv_timeout(Duration::from_millis(100)) {
-                    Ok(message) => {
-                        let handlers = handlers.lock().unwrap();
-                        for handler in handlers.iter() {
-                            // Handle each message, but don't stop on errors
-                            // This ensures one failing handler doesn't break the bus
-                            if let Err(e) = handler.handle(message.clone()) {
-                                eprintln!("Handler error: {}", e);
-                            }
+            // Dropped on the way out, which tells shutdown the thread is done
+            let _exited = exited;
+            *registry.dispatcher.lock().unwrap() = Some(thread::current().id());
+            for envelope in receiver.iter() {
+                match envelope {
+                    Envelope::Message(sequenced) => {
+                        if !registry.begin_dispatch() {
+                            break;
                         }
+                        registry.dispatch(sequenced);
                     }
-                    Err(crossbeam_channel::RecvTimeoutError::Timeout) => {
-                        // Just continue to check shutdown flag
-                        continue;
-                    }
-                    Err(crossbeam_channel::RecvTimeoutError::Disconnected) => {
-                        // Channel closed, exit
-                        break;
+                    Envelope::Flush(done) => {
+                        let _ = done.send(());
                     }
+                    Envelope::Stop => break,
                 }
             }
         });
@@ -204,15 +875,84 @@ impl MessageBus {
         Ok(())
     }
 
-    /// Shutdown the message bus
+    /// Wait until every queued message has been handled
+    ///
+    /// Covers the messages sent before the call and whatever handlers
+    /// send while handling them, including messages queued for async
+    /// handlers, so tests can check the effects of a message without
+    /// sleeping. There is no timeout: a handler that never returns blocks
+    /// the flush, and so does a steady stream of sends from other threads.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The bus is not running (`Error::NotRunning` or
+    ///   `Error::AlreadyShutdown`)
+    /// - It is called from a handler, which would wait for itself
+    ///   (`Error::FlushFromHandler`)
+    pub fn flush(&self) -> Result<()> {
+        match self.state {
+            BusState::Running => {}
+            BusState::Created => return Err(Error::NotRunning),
+            BusState::ShutDown => return Err(Error::AlreadyShutdown),
+        }
+        if *self.registry.dispatcher.lock().unwrap() == Some(thread::current().id()) {
+            return Err(Error::FlushFromHandler);
+        }
+
+        let sender = self.sender.as_ref().ok_or(Error::NotRunning)?;
+        loop {
+            let sent = self.registry.next_seq.load(Ordering::SeqCst);
+            let (done, flushed) = bounded(1);
+            sender
+                .send(Envelope::Flush(done))
+                .map_err(|_| Error::ChannelDisconnected)?;
+            flushed.recv().map_err(|_| Error::ChannelDisconnected)?;
+            self.registry.async_backlog.wait_idle(None);
+            // Handlers may have sent follow-on messages meanwhile
+            if self.registry.next_seq.load(Ordering::SeqCst) == sent {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Shut down the message bus, waiting up to [`DEFAULT_SHUTDOWN_TIMEOUT`]
+    /// for queued messages
     ///
-    /// This closes the message channel and waits for the worker thread
-    /// to finish processing any remaining messages.
+    /// # Returns
+    ///
+    /// Returns the number of messages dropped, as for
+    /// [`shutdown_with_timeout`](Self::shutdown_with_timeout).
     ///
     /// # Errors
     ///
     /// Returns an error if the bus is not running or already shut down.
-    pub fn shutdown(&mut self) -> Result<()> {
+    pub fn shutdown(&mut self) -> Result<usize> {
+        self.shutdown_with_timeout(DEFAULT_SHUTDOWN_TIMEOUT)
+    }
+
+    /// Shut down the message bus
+    ///
+    /// From the moment this is called senders get
+    /// `Error::BusShuttingDown`, handlers' follow-on messages included.
+    /// Every message queued before that is still handled, in order, and
+    /// this returns once the handlers, async ones too, are done with them.
+    /// If that takes longer than `timeout`, the rest of the queue is
+    /// dropped; a handler call in progress is left to finish on its own.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long to wait for the queue to drain
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of queued messages dropped because the timeout
+    /// fired; 0 if everything was handled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bus is not running or already shut down.
+    pub fn shutdown_with_timeout(&mut self, timeout: Duration) -> Result<usize> {
         if self.state == BusState::ShutDown {
             return Err(Error::AlreadyShutdown);
         }
@@ -221,21 +961,31 @@ impl MessageBus {
             return Err(Error::NotRunning);
         }
 
-        // Set shutdown flag first to stop new messages
-        self.shutdown.store(true, Ordering::Relaxed);
-
-        // Drop sender to signal channel closure
-        self.sender = None;
-
-        // Wait for worker to finish
-        if let Some(worker) = self.worker.take() {
-            // Join will wait for the thread to complete
-            // The thread checks shutdown flag every 100ms
-            let _ = worker.join();
+        let deadline = Instant::now() + timeout;
+        // Waits for sends in progress; later ones are rejected
+        *self.closed.write().unwrap() = true;
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.send(Envelope::Stop);
         }
-
         self.state = BusState::ShutDown;
-        Ok(())
+
+        let worker_exited = self.worker_exited.take().ok_or(Error::NotRunning)?;
+        let left = deadline.saturating_duration_since(Instant::now());
+        let dropped = match worker_exited.recv_timeout(left) {
+            Err(RecvTimeoutError::Timeout) => {
+                // The worker stops after its current message
+                self.worker = None;
+                self.registry.abort()
+            }
+            _ => {
+                if let Some(worker) = self.worker.take() {
+                    let _ = worker.join();
+                }
+                0
+            }
+        };
+        self.registry.async_backlog.wait_idle(Some(deadline));
+        Ok(dropped as usize)
     }
 }
 
@@ -0,0 +1,418 @@
+//! Per-tab bandwidth accounting
+//!
+//! Every request that reaches the network is counted against the tab it
+//! was made for (requests without a tab only count towards the session
+//! total). Counters survive navigations and are only cleared by
+//! [`BandwidthMeter::reset`] or [`BandwidthMeter::reset_tab`]. Cache hits
+//! cost no traffic and are not counted.
+//!
+//! Sent bytes are estimated from the request line, the headers the browser
+//! set and the body. Received bytes are counted twice: `received_encoded`
+//! approximates the wire size (status line, headers and the body as
+//! received, [`ResourceTiming::encoded_size`]), `received_decoded` is the
+//! body after its `Content-Encoding` was undone
+//! ([`ResourceTiming::decoded_size`]), so the difference is what
+//! compression saved.
+//!
+//! With a budget set (`network.metered_budget_mb`), the first time the
+//! session total crosses it a `BrowserMessage::BandwidthBudgetExceeded` is
+//! sent and background transfers (downloads, prefetch) are paused until
+//! [`BandwidthMeter::resume_background_transfers`].
+
+use crate::types::ResourceTiming;
+use message_bus::MessageSender;
+use serde::{Deserialize, Serialize};
+use shared_types::{BrowserMessage, TabId};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+/// Bytes transferred for one tab (or the whole session)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TabBandwidth {
+    /// Requests that reached the network
+    pub requests: u64,
+    /// Estimated bytes sent (request lines, headers and bodies)
+    pub sent: u64,
+    /// Estimated bytes received on the wire
+    pub received_encoded: u64,
+    /// Response body bytes after decoding
+    pub received_decoded: u64,
+}
+
+impl TabBandwidth {
+    /// Bytes sent and received on the wire
+    pub fn total(&self) -> u64 {
+        self.sent + self.received_encoded
+    }
+}
+
+/// Lock-free counters behind a [`TabBandwidth`]
+#[derive(Debug, Default)]
+struct Counters {
+    requests: AtomicU64,
+    sent: AtomicU64,
+    received_encoded: AtomicU64,
+    received_decoded: AtomicU64,
+}
+
+impl Counters {
+    fn add(&self, usage: &TabBandwidth) {
+        self.requests.fetch_add(usage.requests, Ordering::Relaxed);
+        self.sent.fetch_add(usage.sent, Ordering::Relaxed);
+        self.received_encoded
+            .fetch_add(usage.received_encoded, Ordering::Relaxed);
+        self.received_decoded
+            .fetch_add(usage.received_decoded, Ordering::Relaxed);
+    }
+
+    fn load(&self) -> TabBandwidth {
+        TabBandwidth {
+            requests: self.requests.load(Ordering::Relaxed),
+            sent: self.sent.load(Ordering::Relaxed),
+            received_encoded: self.received_encoded.load(Ordering::Relaxed),
+            received_decoded: self.received_decoded.load(Ordering::Relaxed),
+        }
+    }
+
+    fn clear(&self) {
+        self.requests.store(0, Ordering::Relaxed);
+        self.sent.store(0, Ordering::Relaxed);
+        self.received_encoded.store(0, Ordering::Relaxed);
+        self.received_decoded.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Byte counters per tab and for the session, with an optional budget
+///
+/// Shared between the network stack and its in-flight streaming responses.
+pub struct BandwidthMeter {
+    /// Counters per tab; the map is only locked to find a tab's counters
+    tabs: Mutex<HashMap<TabId, Arc<Counters>>>,
+    /// Counters for every request, with or without a tab
+    session: Counters,
+    /// Session total in bytes that triggers the alert, if any
+    budget: Mutex<Option<u64>>,
+    /// Whether the alert fired since the last reset
+    budget_exceeded: AtomicBool,
+    /// Whether downloads and prefetching are paused
+    background_paused: AtomicBool,
+    /// Where the alert is sent
+    alerts: Option<Arc<dyn MessageSender>>,
+}
+
+impl BandwidthMeter {
+    /// Create a meter with no budget that sends no alerts
+    pub fn new() -> Self {
+        Self {
+            tabs: Mutex::new(HashMap::new()),
+            session: Counters::default(),
+            budget: Mutex::new(None),
+            budget_exceeded: AtomicBool::new(false),
+            background_paused: AtomicBool::new(false),
+            alerts: None,
+        }
+    }
+
+    /// Send the budget alert to `sender`
+    pub fn with_alerts(mut self, sender: Arc<dyn MessageSender>) -> Self {
+        self.alerts = Some(sender);
+        self
+    }
+
+    /// Set the session budget
+    ///
+    /// # Arguments
+    ///
+    /// * `budget_bytes` - Session total that triggers the alert; `None`
+    ///   turns the budget off
+    pub fn set_budget(&self, budget_bytes: Option<u64>) {
+        *self.budget.lock().unwrap() = budget_bytes;
+    }
+
+    /// The session budget in bytes, if any
+    pub fn budget(&self) -> Option<u64> {
+        *self.budget.lock().unwrap()
+    }
+
+    /// Count a request's traffic
+    ///
+    /// Crossing the budget for the first time pauses background transfers
+    /// and sends `BandwidthBudgetExceeded`.
+    ///
+    /// # Arguments
+    ///
+    /// * `tab_id` - Tab the request was made for, if known
+    /// * `usage` - Bytes to add
+    pub fn record(&self, tab_id: Option<TabId>, usage: &TabBandwidth) {
+        if let Some(tab_id) = tab_id {
+            let counters = self.tabs.lock().unwrap().entry(tab_id).or_default().clone();
+            counters.add(usage);
+        }
+        self.session.add(usage);
+        self.check_budget();
+    }
+
+    /// Count a completed request from its timing entry
+    ///
+    /// Cache hits are skipped; a revalidated hit counts the request and the
+    /// 304 response head, but not the body served from the cache.
+    pub fn record_timing(&self, timing: &ResourceTiming) {
+        if timing.from_cache && !timing.revalidated {
+            return;
+        }
+        let decoded = if timing.revalidated {
+            0
+        } else {
+            timing.decoded_size as u64
+        };
+        self.record(
+            timing.details.tab_id,
+            &TabBandwidth {
+                requests: 1,
+                sent: timing.request_bytes(),
+                received_encoded: timing.response_bytes(),
+                received_decoded: decoded,
+            },
+        );
+    }
+
+    /// Bytes transferred for a tab; zero for tabs with no traffic
+    pub fn tab(&self, tab_id: TabId) -> TabBandwidth {
+        self.tabs
+            .lock()
+            .unwrap()
+            .get(&tab_id)
+            .map(|counters| counters.load())
+            .unwrap_or_default()
+    }
+
+    /// Bytes transferred this session, including requests without a tab
+    pub fn session(&self) -> TabBandwidth {
+        self.session.load()
+    }
+
+    /// Usage of every tab with traffic, heaviest first
+    pub fn by_tab(&self) -> Vec<(TabId, TabBandwidth)> {
+        let mut tabs: Vec<(TabId, TabBandwidth)> = self
+            .tabs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(tab_id, counters)| (*tab_id, counters.load()))
+            .collect();
+        tabs.sort_by(|a, b| b.1.total().cmp(&a.1.total()).then(a.0.cmp(&b.0)));
+        tabs
+    }
+
+    /// Clear one tab's counters
+    ///
+    /// The session total is unchanged.
+    pub fn reset_tab(&self, tab_id: TabId) {
+        self.tabs.lock().unwrap().remove(&tab_id);
+    }
+
+    /// Clear every counter
+    ///
+    /// The budget alert can fire again once the new total crosses it.
+    pub fn reset(&self) {
+        self.tabs.lock().unwrap().clear();
+        self.session.clear();
+        self.budget_exceeded.store(false, Ordering::SeqCst);
+    }
+
+    /// Whether downloads and prefetching are paused by the budget
+    pub fn background_paused(&self) -> bool {
+        self.background_paused.load(Ordering::SeqCst)
+    }
+
+    /// Resume downloads and prefetching after the user acknowledged the
+    /// budget alert
+    ///
+    /// The alert does not fire again until the counters are reset.
+    pub fn resume_background_transfers(&self) {
+        self.background_paused.store(false, Ordering::SeqCst);
+    }
+
+    /// Pause background transfers and alert once the budget is crossed
+    fn check_budget(&self) {
+        let Some(budget_bytes) = self.budget() else {
+            return;
+        };
+        let used_bytes = self.session.load().total();
+        if used_bytes < budget_bytes || self.budget_exceeded.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        self.background_paused.store(true, Ordering::SeqCst);
+        if let Some(alerts) = &self.alerts {
+            let _ = alerts.send(BrowserMessage::BandwidthBudgetExceeded {
+                used_bytes,
+                budget_bytes,
+            });
+        }
+    }
+}
+
+impl Default for BandwidthMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Estimate the size of a request on the wire
+///
+/// Counts an HTTP/1.1 request line, a `Host` header, the given headers and
+/// the body. Headers added by the HTTP client itself (user agent, accepted
+/// encodings) are not included.
+///
+/// # Arguments
+///
+/// * `method` - HTTP method
+/// * `url` - Request URL
+/// * `headers` - Request headers set by the browser
+/// * `body_size` - Size of the request body in bytes
+pub fn estimate_request_bytes(
+    method: &str,
+    url: &Url,
+    headers: &HashMap<String, String>,
+    body_size: usize,
+) -> u64 {
+    let target = &url[url::Position::BeforePath..url::Position::AfterQuery];
+    // "METHOD target HTTP/1.1\r\n"
+    let request_line = method.len() + 1 + target.len() + " HTTP/1.1\r\n".len();
+    // "Host: example.com\r\n"
+    let host = "Host: ".len() + url[url::Position::BeforeHost..url::Position::AfterPort].len() + 2;
+    (request_line + host + header_bytes(headers) + 2 + body_size) as u64
+}
+
+/// Estimate the size of a response's status line and headers
+///
+/// # Arguments
+///
+/// * `http_version` - Version as recorded, e.g. "HTTP/1.1"
+/// * `status` - Status code
+/// * `headers` - Response headers
+pub fn estimate_response_head_bytes(
+    http_version: &str,
+    status: u16,
+    headers: &HashMap<String, String>,
+) -> u64 {
+    let reason = reqwest::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("");
+    // "HTTP/1.1 200 OK\r\n"
+    let status_line = http_version.len() + 1 + 3 + 1 + reason.len() + 2;
+    (status_line + header_bytes(headers) + 2) as u64
+}
+
+/// Size of `Name: value\r\n` lines
+fn header_bytes(headers: &HashMap<String, String>) -> usize {
+    headers
+        .iter()
+        .map(|(name, value)| name.len() + 2 + value.len() + 2)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct RecordingSender {
+        sent: Arc<Mutex<Vec<BrowserMessage>>>,
+    }
+
+    impl MessageSender for RecordingSender {
+        fn send(&self, message: BrowserMessage) -> message_bus::Result<()> {
+            self.sent.lock().unwrap().push(message);
+            Ok(())
+        }
+    }
+
+    fn usage(sent: u64, received: u64) -> TabBandwidth {
+        TabBandwidth {
+            requests: 1,
+            sent,
+            received_encoded: received,
+            received_decoded: received,
+        }
+    }
+
+    #[test]
+    fn test_request_estimate_counts_line_host_headers_and_body() {
+        let url = Url::parse("https://example.com:8443/a/b?q=1#frag").unwrap();
+        let mut headers = HashMap::new();
+        headers.insert("Accept".to_string(), "*/*".to_string());
+
+        // "POST /a/b?q=1 HTTP/1.1\r\n" + "Host: example.com:8443\r\n"
+        // + "Accept: */*\r\n" + "\r\n" + body
+        let expected = 24 + 24 + 13 + 2 + 5;
+        assert_eq!(estimate_request_bytes("POST", &url, &headers, 5), expected);
+    }
+
+    #[test]
+    fn test_response_head_estimate() {
+        let mut headers = HashMap::new();
+        headers.insert("content-length".to_string(), "11".to_string());
+        // "HTTP/1.1 200 OK\r\n" + "content-length: 11\r\n" + "\r\n"
+        assert_eq!(
+            estimate_response_head_bytes("HTTP/1.1", 200, &headers),
+            17 + 20 + 2
+        );
+    }
+
+    #[test]
+    fn test_counters_per_tab_and_session() {
+        let meter = BandwidthMeter::new();
+        meter.record(Some(1), &usage(100, 1_000));
+        meter.record(Some(2), &usage(50, 5_000));
+        meter.record(Some(1), &usage(100, 1_000));
+        meter.record(None, &usage(10, 10));
+
+        assert_eq!(meter.tab(1).total(), 2_200);
+        assert_eq!(meter.tab(1).requests, 2);
+        assert_eq!(meter.tab(3), TabBandwidth::default());
+        assert_eq!(meter.session().total(), 7_270);
+
+        let order: Vec<TabId> = meter.by_tab().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(order, vec![2, 1]);
+
+        meter.reset_tab(2);
+        assert_eq!(meter.tab(2), TabBandwidth::default());
+        assert_eq!(meter.session().total(), 7_270);
+        meter.reset();
+        assert_eq!(meter.session(), TabBandwidth::default());
+    }
+
+    #[test]
+    fn test_budget_alert_fires_once_and_pauses_background() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let meter =
+            BandwidthMeter::new().with_alerts(Arc::new(RecordingSender { sent: sent.clone() }));
+        meter.set_budget(Some(1_000));
+
+        meter.record(Some(1), &usage(100, 800));
+        assert!(sent.lock().unwrap().is_empty());
+        assert!(!meter.background_paused());
+
+        meter.record(Some(1), &usage(0, 200));
+        meter.record(Some(2), &usage(0, 5_000));
+        let alerts = sent.lock().unwrap().clone();
+        assert_eq!(alerts.len(), 1);
+        assert!(matches!(
+            alerts[0],
+            BrowserMessage::BandwidthBudgetExceeded {
+                used_bytes: 1_100,
+                budget_bytes: 1_000
+            }
+        ));
+        assert!(meter.background_paused());
+
+        meter.resume_background_transfers();
+        assert!(!meter.background_paused());
+        meter.record(Some(2), &usage(0, 5_000));
+        assert_eq!(sent.lock().unwrap().len(), 1);
+        assert!(!meter.background_paused());
+    }
+}
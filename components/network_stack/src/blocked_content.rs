@@ -0,0 +1,154 @@
+//! Blocked element content per tab
+//!
+//! When an interceptor blocks an image, media file, frame or embed that a
+//! tab asked for, the request is recorded against the tab so the page can
+//! show a click-to-load placeholder where the element would have been.
+//! Scripts, stylesheets and other types without a placeholder are not
+//! recorded (see [`ResourceType::shows_placeholder`]).
+//!
+//! Clicking a placeholder allows that exact URL for the tab until the tab
+//! is closed; later blocks of the same URL in the same tab are overridden.
+
+use shared_types::{ResourceType, TabId};
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+/// A request an interceptor blocked on behalf of a tab
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockedResource {
+    /// URL the page asked for
+    pub url: Url,
+    /// What the response was for
+    pub resource_type: ResourceType,
+    /// Reason given by the interceptor
+    pub reason: String,
+}
+
+/// Blocked requests and click-to-load overrides, by tab
+#[derive(Debug, Default)]
+pub struct BlockedContent {
+    /// Blocked requests per tab, in the order they were first blocked
+    blocked: HashMap<TabId, Vec<BlockedResource>>,
+    /// URLs the user chose to load anyway, per tab
+    allowed: HashMap<TabId, HashSet<Url>>,
+}
+
+impl BlockedContent {
+    /// Create an empty record
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a blocked request
+    ///
+    /// Types without a placeholder are ignored, as are repeated blocks of a
+    /// URL already recorded for the tab.
+    pub fn record(&mut self, tab_id: TabId, resource: BlockedResource) {
+        if !resource.resource_type.shows_placeholder() {
+            return;
+        }
+        let blocked = self.blocked.entry(tab_id).or_default();
+        if !blocked.iter().any(|r| r.url == resource.url) {
+            blocked.push(resource);
+        }
+    }
+
+    /// Check whether the user chose to load `url` in the tab
+    pub fn is_allowed(&self, tab_id: TabId, url: &Url) -> bool {
+        self.allowed
+            .get(&tab_id)
+            .is_some_and(|allowed| allowed.contains(url))
+    }
+
+    /// Allow a blocked URL for the rest of the tab's session
+    ///
+    /// # Returns
+    ///
+    /// Returns `false` (and allows nothing) if `url` was not blocked in the
+    /// tab, so a page cannot lift blocks on URLs it never showed.
+    pub fn allow(&mut self, tab_id: TabId, url: &Url) -> bool {
+        let Some(blocked) = self.blocked.get_mut(&tab_id) else {
+            return false;
+        };
+        let before = blocked.len();
+        blocked.retain(|r| &r.url != url);
+        if blocked.len() == before {
+            return false;
+        }
+        self.allowed.entry(tab_id).or_default().insert(url.clone());
+        true
+    }
+
+    /// Blocked requests of a tab that have not been allowed
+    pub fn resources(&self, tab_id: TabId) -> Vec<BlockedResource> {
+        self.blocked.get(&tab_id).cloned().unwrap_or_default()
+    }
+
+    /// Forget a tab's blocked requests, e.g. when it navigates
+    ///
+    /// Overrides stay in place until [`BlockedContent::forget_tab`].
+    pub fn clear(&mut self, tab_id: TabId) {
+        self.blocked.remove(&tab_id);
+    }
+
+    /// Forget everything recorded for a closed tab
+    pub fn forget_tab(&mut self, tab_id: TabId) {
+        self.blocked.remove(&tab_id);
+        self.allowed.remove(&tab_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocked(url: &str, resource_type: ResourceType) -> BlockedResource {
+        BlockedResource {
+            url: Url::parse(url).unwrap(),
+            resource_type,
+            reason: "ad".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_records_placeholder_types_once() {
+        let mut content = BlockedContent::new();
+        for (tab_id, url, resource_type) in [
+            (1, "https://ads.example/a.png", ResourceType::Image),
+            (1, "https://ads.example/a.png", ResourceType::Image),
+            (1, "https://ads.example/a.js", ResourceType::Script),
+            (1, "https://ads.example/a.css", ResourceType::Stylesheet),
+            (2, "https://ads.example/f.html", ResourceType::Document),
+        ] {
+            content.record(tab_id, blocked(url, resource_type));
+        }
+
+        let urls: Vec<String> = content
+            .resources(1)
+            .iter()
+            .map(|r| r.url.to_string())
+            .collect();
+        assert_eq!(urls, ["https://ads.example/a.png"]);
+        assert_eq!(content.resources(2).len(), 1);
+        assert!(content.resources(3).is_empty());
+    }
+
+    #[test]
+    fn test_allow_only_blocked_urls() {
+        let mut content = BlockedContent::new();
+        let image = Url::parse("https://ads.example/a.png").unwrap();
+        content.record(1, blocked(image.as_str(), ResourceType::Image));
+
+        assert!(!content.allow(2, &image));
+        assert!(!content.allow(1, &Url::parse("https://ads.example/b.png").unwrap()));
+        assert!(content.allow(1, &image));
+        assert!(content.is_allowed(1, &image));
+        assert!(!content.is_allowed(2, &image));
+        assert!(content.resources(1).is_empty());
+
+        content.clear(1);
+        assert!(content.is_allowed(1, &image));
+        content.forget_tab(1);
+        assert!(!content.is_allowed(1, &image));
+    }
+}
@@ -3,22 +3,38 @@
 //! This module provides HTTP caching capabilities following RFC 7234.
 //! Features include:
 //! - In-memory LRU cache
-//! - Optional disk-based cache using SQLite
+//! - Optional disk cache in SQLite that keeps entries across restarts
 //! - Cache-Control header parsing and enforcement
 //! - ETag and Last-Modified support for conditional requests
 //! - Automatic cache invalidation
-
+//! - Entry inspection, lookup explanations and manual purging (`about:cache`)
+//! - Optional partitioning by top-level site (see [`crate::partition`])
+//!
+//! Entries are keyed by URL within a partition. Entries stored without a
+//! partition form the shared partition every unpartitioned lookup sees.
+//! All partitions draw on one memory budget and one LRU order.
+//!
+//! With a [`DiskCache`], every stored entry is also written to the
+//! database and lookups that miss memory fall back to it, so responses
+//! cached in an earlier session are served from disk (and then kept in
+//! memory again). The database has the same size budget and evicts least
+//! recently used entries the same way. A damaged database is replaced by
+//! an empty one when the cache opens.
+
+use crate::errors::{Error, Result};
+use crate::partition::PartitionKey;
 use lru::LruCache;
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use shared_types::{Clock, SystemClock};
+use std::collections::{HashMap, HashSet};
 use std::num::NonZeroUsize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
 use url::Url;
 
-#[cfg(test)]
-use std::time::Duration;
+/// File name of the disk cache database in the cache directory
+pub const DISK_CACHE_FILE: &str = "http_cache.db";
 
 /// Cache-Control directive values
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -101,16 +117,20 @@ pub struct CacheEntry {
     pub size_bytes: usize,
     /// Timestamp when cached (seconds since UNIX_EPOCH)
     pub cached_at: u64,
+    /// Top-level site the entry was cached for; `None` if shared
+    #[serde(default)]
+    pub partition: Option<PartitionKey>,
 }
 
 impl CacheEntry {
-    /// Create a new cache entry
+    /// Create a new cache entry stored now
     pub fn new(url: String, body: Vec<u8>, headers: HashMap<String, String>) -> Self {
+        Self::new_at(url, body, headers, SystemClock.unix_secs())
+    }
+
+    /// Create a new cache entry stored at `now` (seconds since UNIX_EPOCH)
+    pub fn new_at(url: String, body: Vec<u8>, headers: HashMap<String, String>, now: u64) -> Self {
         let size_bytes = body.len();
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
 
         // Extract ETag
         let etag = headers.get("etag").or_else(|| headers.get("ETag")).cloned();
@@ -151,27 +171,171 @@ impl CacheEntry {
             hit_count: 0,
             size_bytes,
             cached_at: now,
+            partition: None,
         }
     }
 
     /// Check if cache entry is expired
     pub fn is_expired(&self) -> bool {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
+        self.is_expired_at(SystemClock.unix_secs())
+    }
+
+    /// Check if cache entry is expired at `now` (seconds since UNIX_EPOCH)
+    pub fn is_expired_at(&self, now: u64) -> bool {
         now >= self.expires_at
     }
 
     /// Check if entry can be used without revalidation
     pub fn can_use_without_revalidation(&self) -> bool {
-        !self.is_expired() && self.cache_control.allows_cached_response()
+        self.can_use_without_revalidation_at(SystemClock.unix_secs())
+    }
+
+    /// Check if entry can be used without revalidation at `now` (seconds
+    /// since UNIX_EPOCH)
+    pub fn can_use_without_revalidation_at(&self, now: u64) -> bool {
+        !self.is_expired_at(now) && self.cache_control.allows_cached_response()
     }
 
     /// Increment hit count
     pub fn increment_hit(&mut self) {
         self.hit_count += 1;
     }
+
+    /// Validator usable for a conditional request, preferring the ETag
+    ///
+    /// # Returns
+    ///
+    /// Returns e.g. `ETag "abc"` or `Last-Modified <date>`, or None.
+    pub fn validator(&self) -> Option<String> {
+        self.etag
+            .as_ref()
+            .map(|etag| format!("ETag {}", etag))
+            .or_else(|| {
+                self.last_modified
+                    .as_ref()
+                    .map(|date| format!("Last-Modified {}", date))
+            })
+    }
+
+    /// Summarize the entry for inspection
+    pub fn summary(&self) -> CacheEntrySummary {
+        CacheEntrySummary {
+            url: self.url.clone(),
+            stored_at: self.cached_at,
+            expires_at: self.expires_at,
+            size: self.size_bytes,
+            hit_count: self.hit_count,
+            validator: self.validator(),
+            variant_count: 1,
+            partition: self.partition.as_ref().map(|p| p.site().to_string()),
+        }
+    }
+
+    /// Explain the decision for a lookup that found this entry
+    fn explain_lookup(&self, now: u64) -> LookupExplanation {
+        if self.can_use_without_revalidation_at(now) {
+            return LookupExplanation::Hit {
+                age_secs: now.saturating_sub(self.cached_at),
+            };
+        }
+
+        let reason = if self.is_expired_at(now) {
+            format!("expired {}s ago", now.saturating_sub(self.expires_at))
+        } else if self.cache_control.no_cache {
+            "no-cache".to_string()
+        } else {
+            "must-revalidate".to_string()
+        };
+        LookupExplanation::StaleNeedsRevalidation {
+            reason,
+            validator: self.validator(),
+        }
+    }
+}
+
+/// Inspection view of a cached entry (no body)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEntrySummary {
+    /// URL of the cached resource
+    pub url: String,
+    /// Timestamp when cached (seconds since UNIX_EPOCH)
+    pub stored_at: u64,
+    /// Expiration timestamp (seconds since UNIX_EPOCH)
+    pub expires_at: u64,
+    /// Body size in bytes
+    pub size: usize,
+    /// Number of times the entry was looked up
+    pub hit_count: usize,
+    /// Validator available for revalidation (see [`CacheEntry::validator`])
+    pub validator: Option<String>,
+    /// Number of stored variants (always 1 until Vary is supported)
+    pub variant_count: usize,
+    /// Top-level site the entry was cached for; `None` if shared
+    #[serde(default)]
+    pub partition: Option<String>,
+}
+
+/// Size of the cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    /// Number of entries across all partitions
+    pub entries: usize,
+    /// Bytes of response bodies held in memory
+    pub size_bytes: usize,
+    /// Number of distinct top-level sites with entries, not counting the
+    /// shared partition
+    pub partitions: usize,
+}
+
+/// Decision path taken by the most recent lookup of a URL
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LookupExplanation {
+    /// The URL has not been looked up (or was purged since)
+    NotLookedUp,
+    /// Nothing was stored for the URL
+    Miss,
+    /// A fresh entry was served
+    Hit {
+        /// Seconds since the entry was stored
+        age_secs: u64,
+    },
+    /// An entry exists but must be revalidated before use
+    StaleNeedsRevalidation {
+        /// Why the entry is not fresh (expiry, no-cache, must-revalidate)
+        reason: String,
+        /// Validator a conditional request would carry
+        validator: Option<String>,
+    },
+    /// Nothing was stored because the response exceeds the memory budget
+    TooLargeToAdmit {
+        /// Response body size in bytes
+        size: usize,
+        /// Memory cache budget in bytes
+        limit: usize,
+    },
+}
+
+impl LookupExplanation {
+    /// Human-readable description of the decision
+    pub fn describe(&self) -> String {
+        match self {
+            LookupExplanation::NotLookedUp => "not looked up".to_string(),
+            LookupExplanation::Miss => "miss: no entry stored".to_string(),
+            LookupExplanation::Hit { age_secs } => {
+                format!("hit: fresh entry, stored {}s ago", age_secs)
+            }
+            LookupExplanation::StaleNeedsRevalidation { reason, validator } => match validator {
+                Some(validator) => {
+                    format!("stale ({}): needs revalidation with {}", reason, validator)
+                }
+                None => format!("stale ({}): needs refetch, no validator", reason),
+            },
+            LookupExplanation::TooLargeToAdmit { size, limit } => format!(
+                "miss: response of {} bytes exceeds the {} byte cache budget",
+                size, limit
+            ),
+        }
+    }
 }
 
 /// Simplified HTTP date parser (for Expires header)
@@ -184,20 +348,38 @@ fn parse_http_date(date_str: &str) -> Option<u64> {
     None
 }
 
+/// Key of a cache entry: its partition and URL
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    partition: Option<PartitionKey>,
+    url: String,
+}
+
+impl CacheKey {
+    fn new(partition: Option<&PartitionKey>, url: &str) -> Self {
+        Self {
+            partition: partition.cloned(),
+            url: url.to_string(),
+        }
+    }
+}
+
 /// HTTP cache with LRU eviction and optional disk persistence
 pub struct HttpCache {
-    /// In-memory LRU cache
-    memory_cache: Arc<Mutex<LruCache<String, CacheEntry>>>,
+    /// In-memory LRU cache, shared by all partitions
+    memory_cache: Arc<Mutex<LruCache<CacheKey, CacheEntry>>>,
     /// Maximum memory cache size in bytes
     max_memory_bytes: usize,
     /// Current memory cache size in bytes
     current_memory_size: Arc<Mutex<usize>>,
-    /// Optional disk cache path (reserved for future use)
-    #[allow(dead_code)]
-    disk_cache_path: Option<PathBuf>,
-    /// Disk cache connection (lazy initialized, reserved for future use)
-    #[allow(dead_code)]
-    disk_cache: Arc<Mutex<Option<rusqlite::Connection>>>,
+    /// Database every entry is also written to, if the cache persists
+    disk: Option<DiskCache>,
+    /// Explanation of the most recent lookup per URL
+    lookups: Arc<Mutex<LruCache<String, LookupExplanation>>>,
+    /// Size of responses last refused for exceeding the memory budget
+    oversized: Arc<Mutex<LruCache<String, usize>>>,
+    /// Time source for storage and expiry
+    clock: Arc<dyn Clock>,
 }
 
 impl HttpCache {
@@ -210,24 +392,51 @@ impl HttpCache {
     ///
     /// # Returns
     ///
-    /// A new HttpCache instance
+    /// A new HttpCache instance. If the disk cache cannot be opened, the
+    /// cache keeps entries in memory only (see [`HttpCache::disk_cache`]).
     pub fn new(max_memory_mb: usize, disk_cache_path: Option<PathBuf>) -> Self {
+        Self::with_clock(max_memory_mb, disk_cache_path, Arc::new(SystemClock))
+    }
+
+    /// Create a new HTTP cache reading time from `clock`
+    ///
+    /// # Arguments
+    ///
+    /// * `max_memory_mb` - Maximum memory cache size in megabytes
+    /// * `disk_cache_path` - Optional path for disk cache database
+    /// * `clock` - Time source for storage and expiry
+    pub fn with_clock(
+        max_memory_mb: usize,
+        disk_cache_path: Option<PathBuf>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
         let max_memory_bytes = max_memory_mb * 1024 * 1024;
         let capacity = NonZeroUsize::new(1000).unwrap();
-
-        // Disk cache is lazy-initialized when needed
-        let disk_cache = Arc::new(Mutex::new(None));
+        let disk = disk_cache_path.and_then(|path| DiskCache::open(&path, max_memory_bytes).ok());
 
         Self {
             memory_cache: Arc::new(Mutex::new(LruCache::new(capacity))),
             max_memory_bytes,
             current_memory_size: Arc::new(Mutex::new(0)),
-            disk_cache_path,
-            disk_cache,
+            disk,
+            lookups: Arc::new(Mutex::new(LruCache::new(capacity))),
+            oversized: Arc::new(Mutex::new(LruCache::new(capacity))),
+            clock,
         }
     }
 
-    /// Get cached response for a URL
+    /// The disk cache, if entries persist across restarts
+    pub fn disk_cache(&self) -> Option<&DiskCache> {
+        self.disk.as_ref()
+    }
+
+    /// Whether an entry returned by [`get`](Self::get) can be served without
+    /// revalidation, by this cache's clock
+    pub fn is_fresh(&self, entry: &CacheEntry) -> bool {
+        entry.can_use_without_revalidation_at(self.clock.unix_secs())
+    }
+
+    /// Get cached response for a URL from the shared partition
     ///
     /// # Arguments
     ///
@@ -237,20 +446,61 @@ impl HttpCache {
     ///
     /// Returns Some(CacheEntry) if found and valid, None otherwise
     pub fn get(&self, url: &Url) -> Option<CacheEntry> {
+        self.get_in(None, url)
+    }
+
+    /// Get cached response for a URL from one partition
+    ///
+    /// # Arguments
+    ///
+    /// * `partition` - Top-level site the lookup is for; `None` for the
+    ///   shared partition
+    /// * `url` - The URL to look up
+    ///
+    /// # Returns
+    ///
+    /// Returns the entry cached for the URL in that partition, if any.
+    pub fn get_in(&self, partition: Option<&PartitionKey>, url: &Url) -> Option<CacheEntry> {
         let url_str = url.as_str();
+        let key = CacheKey::new(partition, url_str);
         let mut cache = self.memory_cache.lock().unwrap();
 
-        if let Some(mut entry) = cache.get(url_str).cloned() {
-            entry.increment_hit();
-            cache.put(url_str.to_string(), entry.clone());
-            return Some(entry);
-        }
+        let found = match cache.get_mut(&key) {
+            Some(entry) => {
+                entry.increment_hit();
+                if let Some(disk) = &self.disk {
+                    disk.touch(&key);
+                }
+                Some(entry.clone())
+            }
+            None => {
+                // Entries stored in an earlier session are only on disk
+                let entry = self.disk.as_ref().and_then(|disk| disk.get(&key));
+                if let Some(entry) = &entry {
+                    self.insert_in_memory(&mut cache, key, entry.clone());
+                }
+                entry
+            }
+        };
+        let explanation = match &found {
+            Some(entry) => entry.explain_lookup(self.clock.unix_secs()),
+            None => match self.oversized.lock().unwrap().get(url_str) {
+                Some(&size) => LookupExplanation::TooLargeToAdmit {
+                    size,
+                    limit: self.max_memory_bytes,
+                },
+                None => LookupExplanation::Miss,
+            },
+        };
+        self.lookups
+            .lock()
+            .unwrap()
+            .put(url_str.to_string(), explanation);
 
-        // TODO: Check disk cache if enabled
-        None
+        found
     }
 
-    /// Store response in cache
+    /// Store response in the shared partition
     ///
     /// # Arguments
     ///
@@ -258,16 +508,112 @@ impl HttpCache {
     /// * `body` - Response body
     /// * `headers` - Response headers
     pub fn put(&self, url: Url, body: Vec<u8>, headers: HashMap<String, String>) {
-        let url_str = url.as_str().to_string();
-        let entry = CacheEntry::new(url_str.clone(), body, headers);
+        self.put_in(None, url, body, headers);
+    }
+
+    /// Store response in one partition
+    ///
+    /// # Arguments
+    ///
+    /// * `partition` - Top-level site the response was fetched for; `None`
+    ///   for the shared partition
+    /// * `url` - The URL of the resource
+    /// * `body` - Response body
+    /// * `headers` - Response headers
+    pub fn put_in(
+        &self,
+        partition: Option<PartitionKey>,
+        url: Url,
+        body: Vec<u8>,
+        headers: HashMap<String, String>,
+    ) {
+        let mut entry = CacheEntry::new_at(
+            url.as_str().to_string(),
+            body,
+            headers,
+            self.clock.unix_secs(),
+        );
+        entry.partition = partition;
+        self.store(entry);
+    }
 
+    /// Refresh a stale entry the server confirmed with 304 Not Modified
+    ///
+    /// The stored headers are updated from the 304 response (except
+    /// Content-Length) and the entry is stored again as of now, so its
+    /// freshness lifetime restarts. A 304 that says `no-store` drops the
+    /// entry instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `stale` - Entry the conditional request was made for
+    /// * `headers` - Headers of the 304 response
+    ///
+    /// # Returns
+    ///
+    /// Returns the refreshed entry, to serve in place of the response.
+    pub fn revalidate(&self, stale: &CacheEntry, headers: &HashMap<String, String>) -> CacheEntry {
+        let mut merged = stale.headers.clone();
+        for (name, value) in headers {
+            if name.eq_ignore_ascii_case("content-length") {
+                continue;
+            }
+            merged.retain(|stored, _| !stored.eq_ignore_ascii_case(name));
+            merged.insert(name.to_ascii_lowercase(), value.clone());
+        }
+
+        let mut entry = CacheEntry::new_at(
+            stale.url.clone(),
+            stale.body.clone(),
+            merged,
+            self.clock.unix_secs(),
+        );
+        entry.partition = stale.partition.clone();
+        entry.hit_count = stale.hit_count;
+        if entry.cache_control.allows_caching() {
+            self.store(entry.clone());
+        } else {
+            let key = CacheKey::new(entry.partition.as_ref(), &entry.url);
+            self.remove_matching(|stored| *stored == key);
+        }
+        entry
+    }
+
+    /// Admit an entry to the cache, in memory and on disk
+    fn store(&self, entry: CacheEntry) {
         // Check if response is cacheable
         if !entry.cache_control.allows_caching() {
             return;
         }
 
         let size = entry.size_bytes;
+        if size > self.max_memory_bytes {
+            // Refuse up front rather than evicting everything for nothing
+            self.oversized.lock().unwrap().put(entry.url, size);
+            return;
+        }
+        self.oversized.lock().unwrap().pop(&entry.url);
+
+        let key = CacheKey {
+            partition: entry.partition.clone(),
+            url: entry.url.clone(),
+        };
+        if let Some(disk) = &self.disk {
+            disk.put(&key, &entry);
+        }
         let mut cache = self.memory_cache.lock().unwrap();
+        self.insert_in_memory(&mut cache, key, entry);
+    }
+
+    /// Add an entry to the memory cache, evicting least recently used
+    /// entries until it fits the budget
+    fn insert_in_memory(
+        &self,
+        cache: &mut LruCache<CacheKey, CacheEntry>,
+        key: CacheKey,
+        entry: CacheEntry,
+    ) {
+        let size = entry.size_bytes;
         let mut current_size = self.current_memory_size.lock().unwrap();
 
         // Evict entries until there's room
@@ -277,18 +623,13 @@ impl HttpCache {
             }
         }
 
-        // Add entry if it fits
-        if size <= self.max_memory_bytes {
-            if let Some(old_entry) = cache.put(url_str, entry) {
-                *current_size -= old_entry.size_bytes;
-            }
-            *current_size += size;
+        if let Some(old_entry) = cache.put(key, entry) {
+            *current_size -= old_entry.size_bytes;
         }
-
-        // TODO: Store in disk cache if enabled
+        *current_size += size;
     }
 
-    /// Invalidate cache entry for a URL
+    /// Invalidate cache entries for a URL in every partition
     ///
     /// Called when a POST/PUT/DELETE request is made to a URL
     ///
@@ -296,25 +637,153 @@ impl HttpCache {
     ///
     /// * `url` - The URL to invalidate
     pub fn invalidate(&self, url: &Url) {
-        let url_str = url.as_str();
+        self.remove_matching(|key| key.url == url.as_str());
+    }
+
+    /// Clear all cached entries, in memory and on disk
+    pub fn clear(&self) {
         let mut cache = self.memory_cache.lock().unwrap();
         let mut current_size = self.current_memory_size.lock().unwrap();
+        cache.clear();
+        *current_size = 0;
+        self.lookups.lock().unwrap().clear();
+        self.oversized.lock().unwrap().clear();
+        if let Some(disk) = &self.disk {
+            disk.clear();
+        }
+    }
 
-        if let Some(entry) = cache.pop(url_str) {
-            *current_size -= entry.size_bytes;
+    /// List cached entries, most recently used first
+    ///
+    /// Entries in memory come first, then those only on disk.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - Optional case-insensitive substring the URL must contain
+    /// * `limit` - Maximum number of entries to return
+    ///
+    /// # Returns
+    ///
+    /// Returns a summary per matching entry.
+    pub fn cache_entries(&self, filter: Option<&str>, limit: usize) -> Vec<CacheEntrySummary> {
+        let filter = filter.map(|f| f.to_lowercase());
+        let matches = |url: &str| match &filter {
+            Some(f) => url.to_lowercase().contains(f.as_str()),
+            None => true,
+        };
+        let cache = self.memory_cache.lock().unwrap();
+        let mut entries: Vec<CacheEntrySummary> = cache
+            .iter()
+            .filter(|(key, _)| matches(&key.url))
+            .take(limit)
+            .map(|(_, entry)| entry.summary())
+            .collect();
+        if let Some(disk) = &self.disk {
+            let on_disk = disk
+                .summaries()
+                .into_iter()
+                .filter(|(key, _)| matches(&key.url) && !cache.contains(key))
+                .map(|(_, summary)| summary);
+            entries.extend(on_disk.take(limit.saturating_sub(entries.len())));
         }
+        entries
+    }
 
-        // TODO: Invalidate disk cache entry if enabled
+    /// Explain the most recent lookup of a URL
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to explain
+    ///
+    /// # Returns
+    ///
+    /// Returns the decision path of the last [`HttpCache::get`] for the URL.
+    pub fn cache_lookup_explain(&self, url: &Url) -> LookupExplanation {
+        self.lookups
+            .lock()
+            .unwrap()
+            .peek(url.as_str())
+            .cloned()
+            .unwrap_or(LookupExplanation::NotLookedUp)
     }
 
-    /// Clear all cached entries
-    pub fn clear(&self) {
+    /// Remove a single URL from the cache, in every partition
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to purge
+    ///
+    /// # Returns
+    ///
+    /// Returns true if an entry was removed.
+    pub fn purge(&self, url: &Url) -> bool {
+        self.lookups.lock().unwrap().pop(url.as_str());
+        !self
+            .remove_matching(|key| key.url == url.as_str())
+            .is_empty()
+    }
+
+    /// Remove every cached URL on a host
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - Host name to purge (exact match)
+    /// * `partition` - Only purge entries cached for this top-level site;
+    ///   `None` purges the host from every partition
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of entries removed.
+    pub fn purge_host(&self, host: &str, partition: Option<&PartitionKey>) -> usize {
+        let on_host = |url: &str| {
+            Url::parse(url)
+                .map(|u| u.host_str() == Some(host))
+                .unwrap_or(false)
+        };
+        let removed = self.remove_matching(|key| {
+            partition.is_none_or(|p| key.partition.as_ref() == Some(p)) && on_host(&key.url)
+        });
+
+        let mut lookups = self.lookups.lock().unwrap();
+        for key in &removed {
+            lookups.pop(&key.url);
+        }
+        removed.len()
+    }
+
+    /// Remove every entry of the shared partition
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of entries removed.
+    pub fn purge_shared(&self) -> usize {
+        self.remove_matching(|key| key.partition.is_none()).len()
+    }
+
+    /// Remove the entries whose key matches, returning their keys
+    fn remove_matching(&self, matches: impl Fn(&CacheKey) -> bool) -> Vec<CacheKey> {
         let mut cache = self.memory_cache.lock().unwrap();
         let mut current_size = self.current_memory_size.lock().unwrap();
-        cache.clear();
-        *current_size = 0;
 
-        // TODO: Clear disk cache if enabled
+        let mut targets: Vec<CacheKey> = cache
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| matches(key))
+            .cloned()
+            .collect();
+        for key in &targets {
+            if let Some(entry) = cache.pop(key) {
+                *current_size -= entry.size_bytes;
+            }
+        }
+        if let Some(disk) = &self.disk {
+            for key in disk.remove_matching(&matches) {
+                if !targets.contains(&key) {
+                    targets.push(key);
+                }
+            }
+        }
+        targets
     }
 
     /// Get current cache size in bytes
@@ -322,6 +791,31 @@ impl HttpCache {
         *self.current_memory_size.lock().unwrap()
     }
 
+    /// Number of top-level sites with cached entries
+    pub fn partition_count(&self) -> usize {
+        self.stats().partitions
+    }
+
+    /// Entry count, size and partition count
+    ///
+    /// With a disk cache these describe the database, which holds every
+    /// entry the cache has kept.
+    pub fn stats(&self) -> CacheStats {
+        if let Some(disk) = &self.disk {
+            return disk.stats();
+        }
+        let cache = self.memory_cache.lock().unwrap();
+        let partitions: HashSet<&PartitionKey> = cache
+            .iter()
+            .filter_map(|(key, _)| key.partition.as_ref())
+            .collect();
+        CacheStats {
+            entries: cache.len(),
+            size_bytes: *self.current_memory_size.lock().unwrap(),
+            partitions: partitions.len(),
+        }
+    }
+
     /// Check if a response with given status code and headers is cacheable
     ///
     /// # Arguments
@@ -353,9 +847,311 @@ impl HttpCache {
     }
 }
 
+/// Rows of the disk cache; ETag, Last-Modified and Cache-Control are read
+/// back from the stored headers
+const DISK_CACHE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS entries (
+        partition TEXT NOT NULL,
+        url TEXT NOT NULL,
+        headers TEXT NOT NULL,
+        body BLOB NOT NULL,
+        size INTEGER NOT NULL,
+        stored_at INTEGER NOT NULL,
+        hit_count INTEGER NOT NULL DEFAULT 0,
+        last_used INTEGER NOT NULL,
+        PRIMARY KEY (partition, url)
+    );
+    CREATE INDEX IF NOT EXISTS idx_entries_last_used ON entries(last_used);
+";
+
+/// Next value of the `last_used` counter that orders entries by recency
+const NEXT_USE: &str = "(SELECT COALESCE(MAX(last_used), 0) + 1 FROM entries)";
+
+/// HTTP cache entries kept in a SQLite database across restarts
+///
+/// Holds at most `max_bytes` of response bodies; storing past that evicts
+/// the least recently used entries, as the memory cache does. Deleted rows
+/// are overwritten, so cleared entries do not linger in the file.
+pub struct DiskCache {
+    conn: Mutex<Connection>,
+    path: PathBuf,
+    max_bytes: usize,
+    recreated: bool,
+}
+
+impl DiskCache {
+    /// Open the database, creating it (and its directory) if needed
+    ///
+    /// A file that is not a healthy SQLite database is deleted and an
+    /// empty cache created in its place.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Database file, normally [`DISK_CACHE_FILE`] in the cache
+    ///   directory
+    /// * `max_bytes` - Budget for response bodies
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CacheError` if the directory cannot be created or
+    /// the database cannot be created even after removing a damaged one.
+    pub fn open(path: &Path, max_bytes: usize) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| Error::CacheError(format!("{}: {}", dir.display(), e)))?;
+        }
+        let (conn, recreated) = match Self::open_connection(path) {
+            Ok(conn) => (conn, false),
+            Err(_) => {
+                // Cached responses can always be fetched again: start over
+                for suffix in ["", "-journal", "-wal", "-shm"] {
+                    let mut file = path.as_os_str().to_owned();
+                    file.push(suffix);
+                    let _ = std::fs::remove_file(file);
+                }
+                let conn = Self::open_connection(path)
+                    .map_err(|e| Error::CacheError(format!("{}: {}", path.display(), e)))?;
+                (conn, true)
+            }
+        };
+        Ok(Self {
+            conn: Mutex::new(conn),
+            path: path.to_path_buf(),
+            max_bytes,
+            recreated,
+        })
+    }
+
+    /// Open and check the database, creating the schema if needed
+    fn open_connection(path: &Path) -> rusqlite::Result<Connection> {
+        let conn = Connection::open(path)?;
+        let status: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+        if status != "ok" {
+            return Err(rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CORRUPT),
+                Some(status),
+            ));
+        }
+        let _: i64 = conn.query_row("PRAGMA secure_delete = ON", [], |row| row.get(0))?;
+        conn.execute_batch(DISK_CACHE_SCHEMA)?;
+        Ok(conn)
+    }
+
+    /// Database file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether a damaged database was replaced by an empty one on open
+    pub fn was_recreated(&self) -> bool {
+        self.recreated
+    }
+
+    /// Entry count, body bytes and partition count
+    pub fn stats(&self) -> CacheStats {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(size), 0), COUNT(DISTINCT NULLIF(partition, ''))
+             FROM entries",
+            [],
+            |row| {
+                Ok(CacheStats {
+                    entries: row.get::<_, i64>(0)? as usize,
+                    size_bytes: row.get::<_, i64>(1)? as usize,
+                    partitions: row.get::<_, i64>(2)? as usize,
+                })
+            },
+        )
+        .unwrap_or_default()
+    }
+
+    /// Delete every entry
+    pub fn clear(&self) {
+        let _ = self.conn.lock().unwrap().execute("DELETE FROM entries", []);
+    }
+
+    /// Read an entry, counting the hit
+    fn get(&self, key: &CacheKey) -> Option<CacheEntry> {
+        let conn = self.conn.lock().unwrap();
+        let partition = partition_column(key);
+        let (headers, body, stored_at, hit_count) = conn
+            .query_row(
+                "SELECT headers, body, stored_at, hit_count FROM entries
+                 WHERE partition = ?1 AND url = ?2",
+                params![partition, key.url],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, Vec<u8>>(1)?,
+                        row.get::<_, i64>(2)?,
+                        row.get::<_, i64>(3)?,
+                    ))
+                },
+            )
+            .optional()
+            .ok()??;
+        Self::touch_locked(&conn, key);
+
+        let headers: HashMap<String, String> = serde_json::from_str(&headers).ok()?;
+        let mut entry = CacheEntry::new_at(key.url.clone(), body, headers, stored_at as u64);
+        entry.hit_count = hit_count as usize + 1;
+        entry.partition = key.partition.clone();
+        Some(entry)
+    }
+
+    /// Count a hit served from memory, keeping the entry recently used
+    fn touch(&self, key: &CacheKey) {
+        Self::touch_locked(&self.conn.lock().unwrap(), key);
+    }
+
+    fn touch_locked(conn: &Connection, key: &CacheKey) {
+        let _ = conn.execute(
+            &format!(
+                "UPDATE entries SET hit_count = hit_count + 1, last_used = {}
+                 WHERE partition = ?1 AND url = ?2",
+                NEXT_USE
+            ),
+            params![partition_column(key), key.url],
+        );
+    }
+
+    /// Store an entry, then evict least recently used entries until the
+    /// bodies fit the budget
+    fn put(&self, key: &CacheKey, entry: &CacheEntry) {
+        let Ok(headers) = serde_json::to_string(&entry.headers) else {
+            return;
+        };
+        let mut conn = self.conn.lock().unwrap();
+        let Ok(tx) = conn.transaction() else {
+            return;
+        };
+        let stored = tx.execute(
+            &format!(
+                "INSERT OR REPLACE INTO entries
+                 (partition, url, headers, body, size, stored_at, hit_count, last_used)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, {})",
+                NEXT_USE
+            ),
+            params![
+                partition_column(key),
+                key.url,
+                headers,
+                entry.body,
+                entry.size_bytes as i64,
+                entry.cached_at as i64,
+                entry.hit_count as i64,
+            ],
+        );
+        if stored.is_ok() && Self::evict(&tx, self.max_bytes).is_ok() {
+            let _ = tx.commit();
+        }
+    }
+
+    fn evict(conn: &Connection, max_bytes: usize) -> rusqlite::Result<()> {
+        let mut total: i64 =
+            conn.query_row("SELECT COALESCE(SUM(size), 0) FROM entries", [], |row| {
+                row.get(0)
+            })?;
+        while total as usize > max_bytes {
+            let Some((rowid, size)) = conn
+                .query_row(
+                    "SELECT rowid, size FROM entries ORDER BY last_used LIMIT 1",
+                    [],
+                    |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+                )
+                .optional()?
+            else {
+                break;
+            };
+            conn.execute("DELETE FROM entries WHERE rowid = ?1", [rowid])?;
+            total -= size;
+        }
+        Ok(())
+    }
+
+    /// Delete the entries whose key matches, returning their keys
+    fn remove_matching(&self, matches: impl Fn(&CacheKey) -> bool) -> Vec<CacheKey> {
+        let conn = self.conn.lock().unwrap();
+        let keys: Vec<CacheKey> = match conn.prepare("SELECT partition, url FROM entries") {
+            Ok(mut stmt) => stmt
+                .query_map([], |row| {
+                    Ok(cache_key(
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                    ))
+                })
+                .map(|rows| rows.filter_map(|row| row.ok()).collect())
+                .unwrap_or_default(),
+            Err(_) => Vec::new(),
+        };
+        let mut removed = Vec::new();
+        for key in keys.into_iter().filter(|key| matches(key)) {
+            let deleted = conn.execute(
+                "DELETE FROM entries WHERE partition = ?1 AND url = ?2",
+                params![partition_column(&key), key.url],
+            );
+            if deleted.is_ok() {
+                removed.push(key);
+            }
+        }
+        removed
+    }
+
+    /// Summaries of every entry, most recently used first
+    fn summaries(&self) -> Vec<(CacheKey, CacheEntrySummary)> {
+        let conn = self.conn.lock().unwrap();
+        let Ok(mut stmt) = conn.prepare(
+            "SELECT partition, url, headers, size, stored_at, hit_count FROM entries
+             ORDER BY last_used DESC",
+        ) else {
+            return Vec::new();
+        };
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                cache_key(row.get::<_, String>(0)?, row.get::<_, String>(1)?),
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, i64>(4)?,
+                row.get::<_, i64>(5)?,
+            ))
+        });
+        let Ok(rows) = rows else {
+            return Vec::new();
+        };
+        rows.filter_map(|row| row.ok())
+            .filter_map(|(key, headers, size, stored_at, hit_count)| {
+                let headers = serde_json::from_str(&headers).ok()?;
+                // Headers alone give expiry and validators; the body stays
+                // on disk
+                let mut entry =
+                    CacheEntry::new_at(key.url.clone(), Vec::new(), headers, stored_at as u64);
+                entry.size_bytes = size as usize;
+                entry.hit_count = hit_count as usize;
+                entry.partition = key.partition.clone();
+                Some((key, entry.summary()))
+            })
+            .collect()
+    }
+}
+
+/// `partition` column value of a key: the site, or empty when shared
+fn partition_column(key: &CacheKey) -> &str {
+    key.partition.as_ref().map(|p| p.site()).unwrap_or("")
+}
+
+/// Key of a row from its `partition` and `url` columns
+fn cache_key(partition: String, url: String) -> CacheKey {
+    CacheKey {
+        partition: (!partition.is_empty()).then(|| PartitionKey::from_site(&partition)),
+        url,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use shared_types::MockClock;
+    use std::time::Duration;
 
     // ========================================
     // RED PHASE: Tests for CacheControl
@@ -475,11 +1271,16 @@ mod tests {
         let mut headers = HashMap::new();
         headers.insert("cache-control".to_string(), "max-age=0".to_string());
 
-        let entry = CacheEntry::new("https://example.com".to_string(), vec![1, 2, 3], headers);
+        let entry = CacheEntry::new_at(
+            "https://example.com".to_string(),
+            vec![1, 2, 3],
+            headers,
+            1_000,
+        );
 
-        // Entry with max-age=0 should be expired immediately
-        std::thread::sleep(Duration::from_millis(10));
-        assert!(entry.is_expired());
+        // Entry with max-age=0 is expired as soon as it is stored
+        assert!(entry.is_expired_at(1_000));
+        assert!(!entry.can_use_without_revalidation_at(1_000));
     }
 
     #[test]
@@ -537,9 +1338,12 @@ mod tests {
 
     #[test]
     fn test_http_cache_new_with_disk_path() {
-        let path = PathBuf::from("/tmp/test_cache.db");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache").join(DISK_CACHE_FILE);
         let cache = HttpCache::new(100, Some(path.clone()));
-        assert_eq!(cache.disk_cache_path, Some(path));
+        assert_eq!(cache.disk_cache().unwrap().path(), path);
+        assert!(path.exists());
+        assert!(HttpCache::new(100, None).disk_cache().is_none());
     }
 
     #[test]
@@ -676,6 +1480,461 @@ mod tests {
         assert_eq!(entry2.hit_count, 2);
     }
 
+    // ========================================
+    // Tests for inspection, explanations and purging
+    // ========================================
+
+    fn cache_headers(cache_control: &str) -> HashMap<String, String> {
+        let mut headers = HashMap::new();
+        headers.insert("cache-control".to_string(), cache_control.to_string());
+        headers
+    }
+
+    #[test]
+    fn test_explain_not_looked_up_and_miss() {
+        let cache = HttpCache::new(1, None);
+        let url = Url::parse("https://example.com/missing").unwrap();
+
+        assert_eq!(
+            cache.cache_lookup_explain(&url),
+            LookupExplanation::NotLookedUp
+        );
+        assert!(cache.get(&url).is_none());
+        assert_eq!(cache.cache_lookup_explain(&url), LookupExplanation::Miss);
+    }
+
+    #[test]
+    fn test_explain_hit() {
+        let clock = Arc::new(MockClock::default());
+        let cache = HttpCache::with_clock(1, None, clock.clone());
+        let url = Url::parse("https://example.com/fresh").unwrap();
+        cache.put(url.clone(), vec![1, 2, 3], cache_headers("max-age=3600"));
+        clock.advance(Duration::from_secs(5));
+
+        assert!(cache.get(&url).is_some());
+        assert_eq!(
+            cache.cache_lookup_explain(&url),
+            LookupExplanation::Hit { age_secs: 5 }
+        );
+    }
+
+    #[test]
+    fn test_http_cache_expiry_follows_clock() {
+        let clock = Arc::new(MockClock::default());
+        let cache = HttpCache::with_clock(1, None, clock.clone());
+        let url = Url::parse("https://example.com/a").unwrap();
+        cache.put(url.clone(), vec![1], cache_headers("max-age=60"));
+
+        let entry = cache.get(&url).unwrap();
+        assert_eq!(entry.cached_at, MockClock::DEFAULT_START_UNIX_SECS);
+        assert!(cache.is_fresh(&entry));
+
+        clock.advance(Duration::from_secs(59));
+        assert!(cache.is_fresh(&cache.get(&url).unwrap()));
+
+        clock.advance(Duration::from_secs(31));
+        assert!(!cache.is_fresh(&cache.get(&url).unwrap()));
+        assert_eq!(
+            cache.cache_lookup_explain(&url),
+            LookupExplanation::StaleNeedsRevalidation {
+                reason: "expired 30s ago".to_string(),
+                validator: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_revalidate_merges_headers_and_restarts_lifetime() {
+        let clock = Arc::new(MockClock::default());
+        let cache = HttpCache::with_clock(1, None, clock.clone());
+        let url = Url::parse("https://example.com/a").unwrap();
+        let mut headers = cache_headers("max-age=60");
+        headers.insert("ETag".to_string(), "\"v1\"".to_string());
+        headers.insert("Content-Type".to_string(), "text/css".to_string());
+        cache.put(url.clone(), vec![1, 2, 3], headers);
+
+        clock.advance(Duration::from_secs(90));
+        let stale = cache.get(&url).unwrap();
+        assert!(!cache.is_fresh(&stale));
+
+        let mut not_modified = HashMap::new();
+        not_modified.insert("ETag".to_string(), "\"v2\"".to_string());
+        not_modified.insert("Content-Length".to_string(), "0".to_string());
+        let refreshed = cache.revalidate(&stale, &not_modified);
+        assert_eq!(refreshed.body, vec![1, 2, 3]);
+        assert_eq!(refreshed.etag.as_deref(), Some("\"v2\""));
+        assert_eq!(refreshed.headers.get("Content-Type").unwrap(), "text/css");
+        assert!(!refreshed.headers.contains_key("ETag"));
+        assert!(cache.is_fresh(&cache.get(&url).unwrap()));
+
+        // A 304 that forbids storing drops the entry
+        clock.advance(Duration::from_secs(90));
+        let stale = cache.get(&url).unwrap();
+        cache.revalidate(&stale, &cache_headers("no-store"));
+        assert!(cache.get(&url).is_none());
+    }
+
+    #[test]
+    fn test_explain_stale_needs_revalidation() {
+        let cache = HttpCache::new(1, None);
+        let expired = Url::parse("https://example.com/expired").unwrap();
+        let mut headers = cache_headers("max-age=0");
+        headers.insert("etag".to_string(), "\"v1\"".to_string());
+        cache.put(expired.clone(), vec![1], headers);
+
+        let no_cache = Url::parse("https://example.com/no-cache").unwrap();
+        cache.put(
+            no_cache.clone(),
+            vec![1],
+            cache_headers("max-age=3600, no-cache"),
+        );
+
+        cache.get(&expired);
+        cache.get(&no_cache);
+
+        match cache.cache_lookup_explain(&expired) {
+            LookupExplanation::StaleNeedsRevalidation { reason, validator } => {
+                assert!(reason.starts_with("expired"));
+                assert_eq!(validator, Some("ETag \"v1\"".to_string()));
+            }
+            other => panic!("unexpected explanation: {:?}", other),
+        }
+        assert_eq!(
+            cache.cache_lookup_explain(&no_cache),
+            LookupExplanation::StaleNeedsRevalidation {
+                reason: "no-cache".to_string(),
+                validator: None,
+            }
+        );
+        assert!(cache
+            .cache_lookup_explain(&no_cache)
+            .describe()
+            .contains("no validator"));
+    }
+
+    #[test]
+    fn test_explain_too_large_to_admit() {
+        let cache = HttpCache::new(1, None);
+        let small = Url::parse("https://example.com/small").unwrap();
+        let huge = Url::parse("https://example.com/huge").unwrap();
+        cache.put(small.clone(), vec![0; 10], cache_headers("max-age=3600"));
+        cache.put(
+            huge.clone(),
+            vec![0; 2 * 1024 * 1024],
+            cache_headers("max-age=3600"),
+        );
+
+        assert!(cache.get(&huge).is_none());
+        assert_eq!(
+            cache.cache_lookup_explain(&huge),
+            LookupExplanation::TooLargeToAdmit {
+                size: 2 * 1024 * 1024,
+                limit: 1024 * 1024,
+            }
+        );
+        // Refusing the oversized response must not evict existing entries
+        assert!(cache.get(&small).is_some());
+        assert_eq!(cache.size(), 10);
+    }
+
+    #[test]
+    fn test_cache_entries_summary_filter_and_limit() {
+        let cache = HttpCache::new(1, None);
+        let mut headers = cache_headers("max-age=60");
+        headers.insert(
+            "last-modified".to_string(),
+            "Mon, 01 Jan 2024 00:00:00 GMT".to_string(),
+        );
+        cache.put(
+            Url::parse("https://a.com/app.js").unwrap(),
+            vec![0; 4],
+            headers,
+        );
+        cache.put(
+            Url::parse("https://b.com/style.css").unwrap(),
+            vec![0; 8],
+            cache_headers("max-age=60"),
+        );
+        cache.get(&Url::parse("https://a.com/app.js").unwrap());
+
+        let all = cache.cache_entries(None, 10);
+        assert_eq!(all.len(), 2);
+        // Most recently used first
+        assert_eq!(all[0].url, "https://a.com/app.js");
+        assert_eq!(all[0].size, 4);
+        assert_eq!(all[0].hit_count, 1);
+        assert_eq!(all[0].variant_count, 1);
+        assert_eq!(all[0].expires_at, all[0].stored_at + 60);
+        assert_eq!(
+            all[0].validator,
+            Some("Last-Modified Mon, 01 Jan 2024 00:00:00 GMT".to_string())
+        );
+
+        let css = cache.cache_entries(Some("STYLE"), 10);
+        assert_eq!(css.len(), 1);
+        assert_eq!(css[0].url, "https://b.com/style.css");
+        assert_eq!(cache.cache_entries(None, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_purge_removes_only_target() {
+        let cache = HttpCache::new(1, None);
+        let keep = Url::parse("https://example.com/keep").unwrap();
+        let purged = Url::parse("https://example.com/drop").unwrap();
+        cache.put(keep.clone(), vec![0; 3], cache_headers("max-age=60"));
+        cache.put(purged.clone(), vec![0; 5], cache_headers("max-age=60"));
+        cache.get(&purged);
+
+        assert!(cache.purge(&purged));
+        assert!(!cache.purge(&purged));
+        assert_eq!(cache.size(), 3);
+        assert_eq!(
+            cache.cache_lookup_explain(&purged),
+            LookupExplanation::NotLookedUp
+        );
+        assert!(cache.get(&keep).is_some());
+    }
+
+    #[test]
+    fn test_purge_host_removes_exactly_that_host() {
+        let cache = HttpCache::new(1, None);
+        for url in [
+            "https://a.com/1",
+            "https://a.com/2",
+            "http://a.com/3",
+            "https://sub.a.com/4",
+            "https://b.com/5",
+        ] {
+            cache.put(
+                Url::parse(url).unwrap(),
+                vec![0; 1],
+                cache_headers("max-age=60"),
+            );
+        }
+
+        assert_eq!(cache.purge_host("a.com", None), 3);
+        assert_eq!(cache.purge_host("a.com", None), 0);
+
+        let mut remaining: Vec<String> = cache
+            .cache_entries(None, 10)
+            .into_iter()
+            .map(|e| e.url)
+            .collect();
+        remaining.sort();
+        assert_eq!(remaining, vec!["https://b.com/5", "https://sub.a.com/4"]);
+        assert_eq!(cache.size(), 2);
+    }
+
+    fn site(url: &str) -> PartitionKey {
+        PartitionKey::for_site(&Url::parse(url).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_partitions_are_double_keyed() {
+        let cache = HttpCache::new(1, None);
+        let script = Url::parse("https://cdn.example/lib.js").unwrap();
+        let (a, b) = (site("https://a.com"), site("https://b.com"));
+        cache.put_in(
+            Some(a.clone()),
+            script.clone(),
+            vec![0; 4],
+            cache_headers("max-age=60"),
+        );
+
+        // Site B cannot see what site A cached, nor can the shared partition
+        assert!(cache.get_in(Some(&b), &script).is_none());
+        assert!(cache.get(&script).is_none());
+        let entry = cache
+            .get_in(Some(&site("https://www.a.com")), &script)
+            .unwrap();
+        assert_eq!(entry.partition, Some(a.clone()));
+
+        // The same URL is stored once per partition, within one budget
+        cache.put_in(
+            Some(b.clone()),
+            script.clone(),
+            vec![0; 6],
+            cache_headers("max-age=60"),
+        );
+        assert_eq!(cache.get_in(Some(&b), &script).unwrap().body.len(), 6);
+        assert_eq!(cache.get_in(Some(&a), &script).unwrap().body.len(), 4);
+        let sites: Vec<Option<String>> = cache
+            .cache_entries(None, 10)
+            .into_iter()
+            .map(|e| e.partition)
+            .collect();
+        assert_eq!(
+            sites,
+            vec![
+                Some("https://a.com".to_string()),
+                Some("https://b.com".to_string())
+            ]
+        );
+
+        // Writes to the URL invalidate it everywhere
+        cache.invalidate(&script);
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[test]
+    fn test_purge_host_in_one_partition() {
+        let cache = HttpCache::new(1, None);
+        let (a, b) = (site("https://a.com"), site("https://b.com"));
+        for partition in [Some(a.clone()), Some(b.clone()), None] {
+            for url in [
+                "https://cdn.example/1",
+                "https://cdn.example/2",
+                "https://other.example/3",
+            ] {
+                cache.put_in(
+                    partition.clone(),
+                    Url::parse(url).unwrap(),
+                    vec![0; 1],
+                    cache_headers("max-age=60"),
+                );
+            }
+        }
+
+        assert_eq!(cache.purge_host("cdn.example", Some(&a)), 2);
+        assert_eq!(cache.purge_host("cdn.example", Some(&a)), 0);
+        let cdn = Url::parse("https://cdn.example/1").unwrap();
+        assert!(cache.get_in(Some(&b), &cdn).is_some());
+        assert!(cache.get(&cdn).is_some());
+
+        assert_eq!(cache.purge_host("cdn.example", None), 4);
+        assert_eq!(cache.size(), 3);
+        assert!(cache.purge(&Url::parse("https://other.example/3").unwrap()));
+        assert_eq!(cache.size(), 0);
+    }
+
+    #[test]
+    fn test_stats_count_partitions() {
+        let cache = HttpCache::new(1, None);
+        assert_eq!(cache.stats(), CacheStats::default());
+
+        let url = |path: &str| Url::parse(&format!("https://cdn.example/{}", path)).unwrap();
+        let headers = || cache_headers("max-age=60");
+        cache.put_in(Some(site("https://a.com")), url("1"), vec![0; 2], headers());
+        cache.put_in(
+            Some(site("https://news.a.com")),
+            url("2"),
+            vec![0; 3],
+            headers(),
+        );
+        cache.put_in(Some(site("https://b.com")), url("1"), vec![0; 4], headers());
+        cache.put(url("3"), vec![0; 5], headers());
+
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                entries: 4,
+                size_bytes: 14,
+                partitions: 2,
+            }
+        );
+        cache.purge_host("cdn.example", Some(&site("https://b.com")));
+        assert_eq!(cache.partition_count(), 1);
+        cache.clear();
+        assert_eq!(cache.stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn test_disk_cache_survives_restart_with_same_eviction() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DISK_CACHE_FILE);
+        let url = |n: u32| Url::parse(&format!("https://cdn.example/{}", n)).unwrap();
+        let chunk = 400 * 1024;
+        {
+            let cache = HttpCache::new(1, Some(path.clone()));
+            cache.put(url(1), vec![1; chunk], cache_headers("max-age=600"));
+            cache.put_in(
+                Some(site("https://a.com")),
+                url(2),
+                vec![2; chunk],
+                cache_headers("max-age=600"),
+            );
+            // Using the first entry makes the second the least recently used
+            assert!(cache.get(&url(1)).is_some());
+            cache.put(url(3), vec![3; chunk], cache_headers("max-age=600"));
+        }
+
+        let cache = HttpCache::new(1, Some(path));
+        assert_eq!(cache.size(), 0);
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                entries: 2,
+                size_bytes: 2 * chunk,
+                partitions: 0,
+            }
+        );
+        let urls: Vec<String> = cache
+            .cache_entries(None, 10)
+            .into_iter()
+            .map(|e| e.url)
+            .collect();
+        assert_eq!(urls, vec![url(3).to_string(), url(1).to_string()]);
+
+        let entry = cache.get(&url(1)).unwrap();
+        assert_eq!(entry.body, vec![1; chunk]);
+        assert_eq!(entry.hit_count, 2);
+        assert!(cache.is_fresh(&entry));
+        assert!(cache
+            .get_in(Some(&site("https://a.com")), &url(2))
+            .is_none());
+        // Served from disk once, then from memory
+        assert_eq!(cache.size(), chunk);
+        assert!(matches!(
+            cache.cache_lookup_explain(&url(1)),
+            LookupExplanation::Hit { .. }
+        ));
+    }
+
+    #[test]
+    fn test_disk_cache_clear_and_purge_delete_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DISK_CACHE_FILE);
+        let url = |path: &str| Url::parse(&format!("https://cdn.example/{}", path)).unwrap();
+        let cache = HttpCache::new(1, Some(path.clone()));
+        for name in ["a", "b", "c"] {
+            cache.put(url(name), vec![0; 3], cache_headers("max-age=60"));
+        }
+        drop(cache);
+
+        // Rows only on disk are purged too
+        let cache = HttpCache::new(1, Some(path.clone()));
+        assert!(cache.purge(&url("a")));
+        assert_eq!(cache.purge_host("cdn.example", None), 2);
+        assert_eq!(cache.stats(), CacheStats::default());
+
+        cache.put(url("d"), vec![0; 3], cache_headers("max-age=60"));
+        cache.clear();
+        drop(cache);
+        let cache = HttpCache::new(1, Some(path));
+        assert!(cache.get(&url("d")).is_none());
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_disk_cache_recreates_damaged_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(DISK_CACHE_FILE);
+        std::fs::write(&path, vec![0xAB; 4096]).unwrap();
+
+        let disk = DiskCache::open(&path, 1024).unwrap();
+        assert!(disk.was_recreated());
+        assert_eq!(disk.stats(), CacheStats::default());
+        drop(disk);
+        assert!(!DiskCache::open(&path, 1024).unwrap().was_recreated());
+
+        std::fs::write(&path, "not a database\n".repeat(300)).unwrap();
+        let cache = HttpCache::new(1, Some(path));
+        assert!(cache.disk_cache().unwrap().was_recreated());
+        let url = Url::parse("https://example.com/").unwrap();
+        cache.put(url.clone(), vec![1], cache_headers("max-age=60"));
+        assert_eq!(cache.stats().entries, 1);
+    }
+
     #[test]
     fn test_is_cacheable_success_status() {
         let headers = HashMap::new();
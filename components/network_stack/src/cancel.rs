@@ -0,0 +1,85 @@
+//! Cancelling in-flight fetches
+//!
+//! A [`FetchHandle`] is shared between whoever starts a fetch and whoever
+//! may want to stop it, such as the Stop button or a newer navigation in
+//! the same tab. [`NetworkStack::cancellable`](crate::NetworkStack::cancellable)
+//! runs a fetch until it completes or the handle is cancelled; cancelling
+//! drops the request, which closes its connection, and the fetch fails
+//! with `Error::Cancelled`. The timing log keeps an entry for it with
+//! [`cancelled`](crate::ResourceTiming::cancelled) set, as it does for a
+//! [`ByteStream`](crate::ByteStream) dropped before its body was read.
+
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Cancels the fetch (or fetches) it was given to
+///
+/// Clones share the same cancellation state. Cancelling is permanent: a
+/// fetch started with an already cancelled handle fails straight away.
+#[derive(Debug, Clone)]
+pub struct FetchHandle {
+    /// Set to true once cancelled
+    cancel: Arc<watch::Sender<bool>>,
+}
+
+impl FetchHandle {
+    /// Create a handle that has not been cancelled
+    pub fn new() -> Self {
+        Self {
+            cancel: Arc::new(watch::channel(false).0),
+        }
+    }
+
+    /// Abort the fetches using this handle
+    pub fn cancel(&self) {
+        self.cancel.send_replace(true);
+    }
+
+    /// Whether [`cancel`](Self::cancel) has been called
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancel.borrow()
+    }
+
+    /// Wait until the handle is cancelled
+    ///
+    /// Returns at once if it already is; never returns otherwise. Meant
+    /// for `tokio::select!` alongside work that should stop on cancel.
+    pub async fn cancelled(&self) {
+        let mut receiver = self.cancel.subscribe();
+        // The sender lives as long as `self`, so waiting cannot fail
+        let _ = receiver.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+impl Default for FetchHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_cancel_wakes_every_clone() {
+        let handle = FetchHandle::new();
+        let waiter = handle.clone();
+        assert!(!waiter.is_cancelled());
+
+        let waiting = tokio::spawn(async move { waiter.cancelled().await });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!waiting.is_finished());
+
+        handle.cancel();
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(handle.is_cancelled());
+
+        // Already cancelled: returns immediately
+        handle.cancelled().await;
+    }
+}
@@ -0,0 +1,412 @@
+//! Certificate chains presented by TLS servers
+//!
+//! When a TLS connection succeeds the stack keeps the DER certificates the
+//! server presented, keyed by host, for the rest of the session. They are
+//! parsed with x509-parser into a [`CertChainInfo`] for the
+//! `about:certificate` page. Hosts only reached over http, or only served
+//! from cache, have no chain.
+//!
+//! Chains from live connections are everything the server sent in the
+//! handshake, leaf and intermediates (and the root, if it sent one), as
+//! recorded by the HTTP client's certificate verifier. Those and chains
+//! recorded by other means (see
+//! [`NetworkStack::record_certificate_chain`](crate::NetworkStack::record_certificate_chain))
+//! are put in leaf-to-root order by [`CertChainInfo::from_der_chain`].
+
+use crate::errors::{Error, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+use x509_parser::extensions::GeneralName;
+use x509_parser::objects::{oid2sn, oid_registry};
+use x509_parser::oid_registry::Oid;
+use x509_parser::public_key::PublicKey;
+
+/// Certificates expiring within this window are flagged on the page
+pub const EXPIRY_WARNING: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// How a chain was accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CertValidation {
+    /// Verified against the system root store
+    SystemRoots,
+    /// Accepted because the user pinned an exception for the host
+    PinnedException,
+}
+
+impl CertValidation {
+    /// Human-readable description for the certificate page
+    pub fn label(&self) -> &'static str {
+        match self {
+            CertValidation::SystemRoots => "Verified by a trusted root certificate",
+            CertValidation::PinnedException => "Accepted by a certificate exception you added",
+        }
+    }
+}
+
+/// Fields extracted from one X.509 certificate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertificateInfo {
+    /// Subject distinguished name (`CN=example.com, O=...`)
+    pub subject: String,
+    /// Issuer distinguished name
+    pub issuer: String,
+    /// DNS names and IP addresses from the subjectAltName extension
+    pub subject_alt_names: Vec<String>,
+    /// Start of the validity window (Unix seconds)
+    pub not_before: i64,
+    /// End of the validity window (Unix seconds)
+    pub not_after: i64,
+    /// Public key algorithm (`RSA`, `EC`, ...)
+    pub key_type: String,
+    /// Public key size in bits, when known for the algorithm
+    pub key_bits: Option<u32>,
+    /// Signature algorithm (`sha256WithRSAEncryption`, ...)
+    pub signature_algorithm: String,
+    /// SHA-256 of the DER encoding, as colon-separated uppercase hex
+    pub sha256_fingerprint: String,
+    /// The certificate as presented
+    #[serde(skip)]
+    pub der: Vec<u8>,
+}
+
+impl CertificateInfo {
+    /// Parse a DER-encoded certificate
+    ///
+    /// # Arguments
+    ///
+    /// * `der` - The certificate as sent in the TLS handshake
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCertificate` if `der` is not an X.509
+    /// certificate.
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let (_, cert) = x509_parser::parse_x509_certificate(der)
+            .map_err(|e| Error::InvalidCertificate(e.to_string()))?;
+
+        let subject_alt_names = match cert.subject_alternative_name() {
+            Ok(Some(san)) => san
+                .value
+                .general_names
+                .iter()
+                .filter_map(general_name_to_string)
+                .collect(),
+            Ok(None) => Vec::new(),
+            Err(e) => return Err(Error::InvalidCertificate(e.to_string())),
+        };
+
+        let spki = cert.public_key();
+        let (key_type, key_bits) = match spki.parsed() {
+            Ok(PublicKey::RSA(key)) => ("RSA".to_string(), Some(key.key_size() as u32)),
+            Ok(PublicKey::EC(point)) => ("EC".to_string(), Some(point.key_size() as u32)),
+            _ => (oid_name(&spki.algorithm.algorithm), None),
+        };
+
+        Ok(Self {
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            subject_alt_names,
+            not_before: cert.validity().not_before.timestamp(),
+            not_after: cert.validity().not_after.timestamp(),
+            key_type,
+            key_bits,
+            signature_algorithm: oid_name(&cert.signature_algorithm.algorithm),
+            sha256_fingerprint: sha256_fingerprint(der),
+            der: der.to_vec(),
+        })
+    }
+
+    /// The certificate in PEM form
+    pub fn pem(&self) -> String {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&self.der);
+        let mut pem = String::from("-----BEGIN CERTIFICATE-----\n");
+        for line in encoded.as_bytes().chunks(64) {
+            pem.push_str(std::str::from_utf8(line).unwrap_or_default());
+            pem.push('\n');
+        }
+        pem.push_str("-----END CERTIFICATE-----\n");
+        pem
+    }
+
+    /// Whether the certificate was issued by its own subject
+    pub fn is_self_issued(&self) -> bool {
+        self.subject == self.issuer
+    }
+
+    /// Whether `now_unix` is past the end of the validity window
+    pub fn is_expired(&self, now_unix: i64) -> bool {
+        now_unix > self.not_after
+    }
+
+    /// Whether the certificate is still valid at `now_unix` but expires
+    /// within `window`
+    ///
+    /// # Arguments
+    ///
+    /// * `now_unix` - Current time (Unix seconds)
+    /// * `window` - How far ahead to look, usually [`EXPIRY_WARNING`]
+    pub fn expires_within(&self, now_unix: i64, window: Duration) -> bool {
+        !self.is_expired(now_unix) && self.not_after - now_unix <= window.as_secs() as i64
+    }
+}
+
+/// The certificates a host presented, leaf first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CertChainInfo {
+    /// Host the chain was presented for
+    pub host: String,
+    /// Leaf certificate followed by its issuers
+    pub certificates: Vec<CertificateInfo>,
+    /// How the chain was accepted
+    pub validation: CertValidation,
+    /// When the chain was captured (Unix seconds)
+    pub captured_unix: i64,
+}
+
+impl CertChainInfo {
+    /// Parse the DER certificates a server presented
+    ///
+    /// Certificates are reordered so each one is followed by its issuer,
+    /// starting from the one that issued nothing else in the set (the
+    /// leaf). Certificates that do not fit the chain are kept at the end in
+    /// the order given.
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - Host the chain was presented for
+    /// * `ders` - DER certificates in any order
+    /// * `validation` - How the chain was accepted
+    /// * `captured_unix` - Capture time (Unix seconds)
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidCertificate` if `ders` is empty or any entry
+    /// fails to parse.
+    pub fn from_der_chain(
+        host: &str,
+        ders: &[Vec<u8>],
+        validation: CertValidation,
+        captured_unix: i64,
+    ) -> Result<Self> {
+        if ders.is_empty() {
+            return Err(Error::InvalidCertificate(format!(
+                "no certificates presented by {}",
+                host
+            )));
+        }
+        let mut remaining = ders
+            .iter()
+            .map(|der| CertificateInfo::from_der(der))
+            .collect::<Result<Vec<_>>>()?;
+
+        let issued_something = |candidate: &CertificateInfo, all: &[CertificateInfo]| {
+            all.iter()
+                .any(|other| !other.is_self_issued() && other.issuer == candidate.subject)
+        };
+        let leaf = remaining
+            .iter()
+            .position(|cert| !issued_something(cert, &remaining))
+            .unwrap_or(0);
+
+        let mut certificates = vec![remaining.remove(leaf)];
+        loop {
+            let current = certificates.last().unwrap();
+            if current.is_self_issued() {
+                break;
+            }
+            let Some(next) = remaining
+                .iter()
+                .position(|cert| cert.subject == current.issuer)
+            else {
+                break;
+            };
+            certificates.push(remaining.remove(next));
+        }
+        certificates.append(&mut remaining);
+
+        Ok(Self {
+            host: host.to_string(),
+            certificates,
+            validation,
+            captured_unix,
+        })
+    }
+
+    /// The server's own certificate
+    pub fn leaf(&self) -> &CertificateInfo {
+        &self.certificates[0]
+    }
+
+    /// The whole chain in PEM form, leaf first
+    pub fn pem(&self) -> String {
+        self.certificates.iter().map(CertificateInfo::pem).collect()
+    }
+}
+
+/// SHA-256 fingerprint of a DER certificate as `AB:CD:...`
+///
+/// # Arguments
+///
+/// * `der` - DER-encoded certificate
+pub fn sha256_fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der)
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Short name of an OID, or its dotted form if the registry lacks it
+fn oid_name(oid: &Oid) -> String {
+    oid2sn(oid, oid_registry())
+        .map(str::to_string)
+        .unwrap_or_else(|_| oid.to_id_string())
+}
+
+/// subjectAltName entry as shown to the user; unusual kinds are skipped
+fn general_name_to_string(name: &GeneralName) -> Option<String> {
+    match name {
+        GeneralName::DNSName(dns) => Some(dns.to_string()),
+        GeneralName::RFC822Name(email) => Some(email.to_string()),
+        GeneralName::URI(uri) => Some(uri.to_string()),
+        GeneralName::IPAddress(bytes) => match bytes.len() {
+            4 => {
+                let octets: [u8; 4] = (*bytes).try_into().ok()?;
+                Some(Ipv4Addr::from(octets).to_string())
+            }
+            16 => {
+                let octets: [u8; 16] = (*bytes).try_into().ok()?;
+                Some(Ipv6Addr::from(octets).to_string())
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{
+        BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, IsCa, SanType,
+    };
+
+    fn named(common_name: &str) -> DistinguishedName {
+        let mut name = DistinguishedName::new();
+        name.push(DnType::CommonName, common_name);
+        name
+    }
+
+    fn ca(common_name: &str) -> Certificate {
+        let mut params = CertificateParams::new(Vec::new());
+        params.distinguished_name = named(common_name);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        Certificate::from_params(params).unwrap()
+    }
+
+    /// Root, intermediate and leaf for `host`, as DER
+    fn chain(host: &str) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let root = ca("Test Root");
+        let intermediate = ca("Test Intermediate");
+        let mut params = CertificateParams::new(vec![host.to_string()]);
+        params.distinguished_name = named(host);
+        params
+            .subject_alt_names
+            .push(SanType::IpAddress("127.0.0.1".parse().unwrap()));
+        params.not_before = rcgen::date_time_ymd(2023, 1, 1);
+        params.not_after = rcgen::date_time_ymd(2024, 1, 1);
+        let leaf = Certificate::from_params(params).unwrap();
+        (
+            root.serialize_der().unwrap(),
+            intermediate.serialize_der_with_signer(&root).unwrap(),
+            leaf.serialize_der_with_signer(&intermediate).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_certificate_fields_extracted() {
+        let (_, _, leaf) = chain("site.test");
+        let info = CertificateInfo::from_der(&leaf).unwrap();
+
+        assert_eq!(info.subject, "CN=site.test");
+        assert_eq!(info.issuer, "CN=Test Intermediate");
+        assert_eq!(info.subject_alt_names, vec!["site.test", "127.0.0.1"]);
+        assert_eq!(info.not_before, 1_672_531_200);
+        assert_eq!(info.not_after, 1_704_067_200);
+        // rcgen signs with ECDSA P-256 by default
+        assert_eq!(info.key_type, "EC");
+        assert_eq!(info.key_bits, Some(256));
+        assert!(info.signature_algorithm.to_lowercase().contains("sha256"));
+        assert_eq!(info.sha256_fingerprint, sha256_fingerprint(&leaf));
+        assert_eq!(info.sha256_fingerprint.len(), 32 * 3 - 1);
+        assert!(!info.is_self_issued());
+    }
+
+    #[test]
+    fn test_pem_round_trips_der() {
+        let (_, _, leaf) = chain("site.test");
+        let pem = CertificateInfo::from_der(&leaf).unwrap().pem();
+
+        assert!(pem.starts_with("-----BEGIN CERTIFICATE-----\n"));
+        assert!(pem.ends_with("-----END CERTIFICATE-----\n"));
+        assert!(pem.lines().all(|line| line.len() <= 64));
+        let body: String = pem.lines().filter(|l| !l.starts_with("-----")).collect();
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(body)
+            .unwrap();
+        assert_eq!(decoded, leaf);
+    }
+
+    #[test]
+    fn test_chain_ordered_leaf_to_root() {
+        let (root, intermediate, leaf) = chain("site.test");
+        let info = CertChainInfo::from_der_chain(
+            "site.test",
+            &[intermediate, root, leaf],
+            CertValidation::SystemRoots,
+            0,
+        )
+        .unwrap();
+
+        let subjects: Vec<&str> = info
+            .certificates
+            .iter()
+            .map(|c| c.subject.as_str())
+            .collect();
+        assert_eq!(
+            subjects,
+            vec!["CN=site.test", "CN=Test Intermediate", "CN=Test Root"]
+        );
+        assert_eq!(info.leaf().subject, "CN=site.test");
+    }
+
+    #[test]
+    fn test_chain_rejects_empty_and_garbage() {
+        let empty = CertChainInfo::from_der_chain("a.test", &[], CertValidation::SystemRoots, 0);
+        assert!(matches!(empty, Err(Error::InvalidCertificate(_))));
+
+        let garbage = CertChainInfo::from_der_chain(
+            "a.test",
+            &[b"not a certificate".to_vec()],
+            CertValidation::SystemRoots,
+            0,
+        );
+        assert!(matches!(garbage, Err(Error::InvalidCertificate(_))));
+    }
+
+    #[test]
+    fn test_expiry_window() {
+        let (_, _, leaf) = chain("site.test");
+        let info = CertificateInfo::from_der(&leaf).unwrap();
+        let day = 24 * 60 * 60;
+
+        assert!(!info.expires_within(info.not_after - 15 * day, EXPIRY_WARNING));
+        assert!(info.expires_within(info.not_after - 14 * day, EXPIRY_WARNING));
+        assert!(info.expires_within(info.not_after, EXPIRY_WARNING));
+        assert!(!info.expires_within(info.not_after + 1, EXPIRY_WARNING));
+        assert!(info.is_expired(info.not_after + 1));
+    }
+}
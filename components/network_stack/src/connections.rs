@@ -0,0 +1,140 @@
+//! Per-host connection limits
+//!
+//! At most `network.max_connections_per_host` requests are in flight to a
+//! `host:port` at once. Further fetches to that host wait for a slot, in
+//! the order they arrived, while fetches to other hosts go ahead. A slot is
+//! held until the response body has been read, and each hop of a redirect
+//! takes its own slot, so a redirect back to the same host never waits on
+//! itself. The time a request spent waiting is recorded as its
+//! [`queued_ms`](crate::ResourceTiming::queued_ms).
+//!
+//! WebSockets have separate slots under the same limit and fail with
+//! `Error::ConnectionLimit` instead of waiting, since a socket may stay
+//! open for as long as the page does.
+
+use crate::errors::{Error, Result};
+use shared_types::Clock;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use url::Url;
+
+/// Open connections per host, capped at `network.max_connections_per_host`
+pub(crate) struct HostLimits {
+    /// Connections allowed per host
+    limit: u32,
+    /// One semaphore per `host:port`
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+/// A connection slot, freed when dropped
+pub(crate) struct ConnectionSlot {
+    /// The reserved slot
+    _permit: OwnedSemaphorePermit,
+    /// How long the request waited for the slot
+    pub(crate) queued: Duration,
+    /// Time the request holding the slot spent resolving host names, set
+    /// once it has been sent
+    pub(crate) dns: Duration,
+}
+
+impl HostLimits {
+    /// Allow `limit` connections per host (at least one)
+    pub(crate) fn new(limit: u32) -> Self {
+        Self {
+            limit: limit.max(1),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Semaphore counting connections to `host`
+    fn semaphore(&self, host: &str) -> Arc<Semaphore> {
+        self.hosts
+            .lock()
+            .unwrap()
+            .entry(host.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.limit as usize)))
+            .clone()
+    }
+
+    /// Reserve a connection to `host`; the slot is freed when the permit
+    /// is dropped
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::ConnectionLimit` if every slot is taken.
+    pub(crate) fn try_acquire(&self, host: &str) -> Result<OwnedSemaphorePermit> {
+        self.semaphore(host)
+            .try_acquire_owned()
+            .map_err(|_| Error::ConnectionLimit {
+                host: host.to_string(),
+                limit: self.limit,
+            })
+    }
+
+    /// Wait for a connection slot to `host`
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - `host:port` key from [`host_key`]
+    /// * `clock` - Clock the wait is measured with
+    pub(crate) async fn acquire(&self, host: &str, clock: &dyn Clock) -> ConnectionSlot {
+        let start = clock.now_instant();
+        let permit = self
+            .semaphore(host)
+            .acquire_owned()
+            .await
+            .expect("connection semaphores are never closed");
+        ConnectionSlot {
+            _permit: permit,
+            queued: clock.elapsed_since(start),
+            dns: Duration::ZERO,
+        }
+    }
+}
+
+/// `host:port` a connection to `url` is counted against
+pub(crate) fn host_key(url: &Url) -> Result<String> {
+    let host = url
+        .host_str()
+        .ok_or_else(|| Error::InvalidUrl(format!("{} has no host", url)))?;
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| Error::InvalidUrl(format!("{} has no port", url)))?;
+    Ok(format!("{}:{}", host, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_types::SystemClock;
+
+    #[tokio::test]
+    async fn test_slots_are_per_host_and_freed_on_drop() {
+        let limits = HostLimits::new(2);
+        let clock = SystemClock;
+        let first = limits.acquire("a.test:443", &clock).await;
+        let _second = limits.acquire("a.test:443", &clock).await;
+        assert!(matches!(
+            limits.try_acquire("a.test:443"),
+            Err(Error::ConnectionLimit { limit: 2, .. })
+        ));
+
+        // Another host is not affected
+        let _other = limits.acquire("b.test:443", &clock).await;
+
+        drop(first);
+        assert!(limits.try_acquire("a.test:443").is_ok());
+    }
+
+    #[test]
+    fn test_host_key_includes_default_port() {
+        let url = Url::parse("https://Example.com/path").unwrap();
+        assert_eq!(host_key(&url).unwrap(), "example.com:443");
+        let url = Url::parse("http://example.com:8080/").unwrap();
+        assert_eq!(host_key(&url).unwrap(), "example.com:8080");
+        let url = Url::parse("data:text/plain,x").unwrap();
+        assert!(matches!(host_key(&url), Err(Error::InvalidUrl(_))));
+    }
+}
@@ -0,0 +1,401 @@
+//! Response body decoding
+//!
+//! The HTTP client is built with its own decompression turned off: every
+//! request advertises [`ACCEPT_ENCODING`] and the stack decodes bodies
+//! itself according to `Content-Encoding`, so the wire size of a body is
+//! known and recorded as [`ResourceTiming::encoded_size`] next to its
+//! [`decoded_size`](crate::ResourceTiming::decoded_size).
+//!
+//! Bodies are decoded before they reach interceptors, the cache or the
+//! caller. The `Content-Encoding` header, and the `Content-Length` that
+//! described the encoded body, are then removed from the headers that are
+//! returned and cached, so a cached entry always holds the decoded bytes
+//! and nothing downstream tries to decode them a second time. The timing
+//! entry keeps the headers as received.
+//!
+//! `gzip` (and `x-gzip`), `deflate` and `br` are decoded, including lists
+//! such as `gzip, br` applied one after the other. `deflate` is accepted
+//! both zlib-wrapped, as the specification says, and as the raw deflate
+//! stream some servers send. A body that fails to decode is an
+//! `Error::DecodeError` rather than a corrupt page.
+//!
+//! [`ResourceTiming::encoded_size`]: crate::ResourceTiming::encoded_size
+
+use crate::errors::{Error, Result};
+use flate2::write::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// `Accept-Encoding` sent with every request except downloads
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br";
+
+/// Size of the brotli decoder's internal buffer
+const BROTLI_BUFFER: usize = 4096;
+
+/// One content coding being undone
+enum Layer {
+    /// `gzip` or `x-gzip`
+    Gzip(GzDecoder<Vec<u8>>),
+    /// `deflate` with the zlib wrapper
+    Zlib(ZlibDecoder<Vec<u8>>),
+    /// `deflate` sent as a bare deflate stream
+    RawDeflate(DeflateDecoder<Vec<u8>>),
+    /// `deflate` before the two bytes that tell the variants apart arrived
+    Deflate(Vec<u8>),
+    /// `br`
+    Brotli(Box<brotli::DecompressorWriter<Vec<u8>>>),
+}
+
+impl Layer {
+    /// Decoder for a content coding; `None` for `identity`
+    fn for_coding(coding: &str) -> Result<Option<Self>> {
+        match coding.to_ascii_lowercase().as_str() {
+            "identity" => Ok(None),
+            "gzip" | "x-gzip" => Ok(Some(Self::Gzip(GzDecoder::new(Vec::new())))),
+            "deflate" => Ok(Some(Self::Deflate(Vec::new()))),
+            "br" => Ok(Some(Self::Brotli(Box::new(
+                brotli::DecompressorWriter::new(Vec::new(), BROTLI_BUFFER),
+            )))),
+            other => Err(Error::DecodeError(format!(
+                "unsupported content encoding \"{}\"",
+                other
+            ))),
+        }
+    }
+
+    /// Name of the coding, for error messages
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Gzip(_) => "gzip",
+            Self::Zlib(_) | Self::RawDeflate(_) | Self::Deflate(_) => "deflate",
+            Self::Brotli(_) => "brotli",
+        }
+    }
+
+    /// Feed encoded bytes in, returning what could be decoded so far
+    fn write(&mut self, input: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip(decoder) => {
+                decoder.write_all(input)?;
+                decoder.flush()?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+            Self::Zlib(decoder) => {
+                decoder.write_all(input)?;
+                decoder.flush()?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+            Self::RawDeflate(decoder) => {
+                decoder.write_all(input)?;
+                decoder.flush()?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+            Self::Deflate(head) => {
+                head.extend_from_slice(input);
+                if head.len() < 2 {
+                    return Ok(Vec::new());
+                }
+                let head = std::mem::take(head);
+                *self = if is_zlib_header(&head) {
+                    Self::Zlib(ZlibDecoder::new(Vec::new()))
+                } else {
+                    Self::RawDeflate(DeflateDecoder::new(Vec::new()))
+                };
+                self.write(&head)
+            }
+            Self::Brotli(decoder) => {
+                decoder.write_all(input)?;
+                decoder.flush()?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+        }
+    }
+
+    /// End of the encoded input: return the rest of the output, or an
+    /// error if the stream was cut short
+    fn finish(self) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip(decoder) => decoder.finish(),
+            Self::Zlib(decoder) => decoder.finish(),
+            Self::RawDeflate(decoder) => decoder.finish(),
+            Self::Deflate(head) => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("stream ends after {} bytes", head.len()),
+            )),
+            Self::Brotli(decoder) => decoder
+                .into_inner()
+                .map_err(|_| io::Error::new(io::ErrorKind::UnexpectedEof, "stream is incomplete")),
+        }
+    }
+}
+
+/// Whether two bytes start a zlib stream (RFC 1950): deflate compression
+/// method and a header checksum that divides by 31
+fn is_zlib_header(head: &[u8]) -> bool {
+    head[0] & 0x0f == 8 && ((u16::from(head[0]) << 8) | u16::from(head[1])) % 31 == 0
+}
+
+/// Incremental decoder for a response body
+///
+/// Feed the body through [`push`](Self::push) as it arrives and call
+/// [`finish`](Self::finish) at the end. With no `Content-Encoding` the
+/// bytes pass through unchanged, which is also what the default decoder
+/// does.
+#[derive(Default)]
+pub struct BodyDecoder {
+    /// Codings to undo, in the order they are undone
+    layers: Vec<Layer>,
+    /// Encoded bytes taken in
+    encoded_size: usize,
+    /// Decoded bytes handed out
+    decoded_size: usize,
+}
+
+impl BodyDecoder {
+    /// Create a decoder for a response's `Content-Encoding`
+    ///
+    /// # Arguments
+    ///
+    /// * `headers` - Response headers as received
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::DecodeError` if a coding is not one the stack
+    /// decodes.
+    pub fn new(headers: &HashMap<String, String>) -> Result<Self> {
+        let mut layers = Vec::new();
+        if let Some(value) = content_encoding(headers) {
+            // Codings are listed in the order they were applied
+            let codings = value.split(',').map(str::trim).filter(|c| !c.is_empty());
+            for coding in codings.rev() {
+                if let Some(layer) = Layer::for_coding(coding)? {
+                    layers.push(layer);
+                }
+            }
+        }
+        Ok(Self {
+            layers,
+            encoded_size: 0,
+            decoded_size: 0,
+        })
+    }
+
+    /// Decode the next chunk of the body
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - Encoded bytes, as received
+    ///
+    /// # Returns
+    ///
+    /// Returns the bytes decoded so far, which may be empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::DecodeError` if the body is not valid for its
+    /// encoding.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<u8>> {
+        self.encoded_size += chunk.len();
+        let mut data = chunk.to_vec();
+        for layer in &mut self.layers {
+            data = layer.write(&data).map_err(|e| malformed(layer.name(), e))?;
+        }
+        self.decoded_size += data.len();
+        Ok(data)
+    }
+
+    /// Decode whatever is left once the whole body has been received
+    ///
+    /// An empty body (a 204, or a HEAD response) decodes to nothing
+    /// whatever its `Content-Encoding`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the last decoded bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::DecodeError` if the encoded body was cut short.
+    pub fn finish(&mut self) -> Result<Vec<u8>> {
+        let layers = std::mem::take(&mut self.layers);
+        if self.encoded_size == 0 {
+            return Ok(Vec::new());
+        }
+        let mut data = Vec::new();
+        for mut layer in layers {
+            let name = layer.name();
+            let mut out = layer.write(&data).map_err(|e| malformed(name, e))?;
+            out.extend(layer.finish().map_err(|e| malformed(name, e))?);
+            data = out;
+        }
+        self.decoded_size += data.len();
+        Ok(data)
+    }
+
+    /// Encoded bytes taken in so far
+    pub fn encoded_size(&self) -> usize {
+        self.encoded_size
+    }
+
+    /// Decoded bytes handed out so far
+    pub fn decoded_size(&self) -> usize {
+        self.decoded_size
+    }
+}
+
+fn malformed(coding: &str, error: io::Error) -> Error {
+    Error::DecodeError(format!("malformed {} body: {}", coding, error))
+}
+
+/// The `Content-Encoding` header, if any
+fn content_encoding(headers: &HashMap<String, String>) -> Option<&str> {
+    headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-encoding"))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Decode a complete response body
+///
+/// # Arguments
+///
+/// * `headers` - Response headers as received
+/// * `body` - Body as received
+///
+/// # Returns
+///
+/// Returns the decoded body and the decoder, for its sizes.
+///
+/// # Errors
+///
+/// Returns `Error::DecodeError` if the encoding is unsupported or the body
+/// is malformed.
+pub fn decode_body(
+    headers: &HashMap<String, String>,
+    body: &[u8],
+) -> Result<(Vec<u8>, BodyDecoder)> {
+    let mut decoder = BodyDecoder::new(headers)?;
+    let mut decoded = decoder.push(body)?;
+    decoded.extend(decoder.finish()?);
+    Ok((decoded, decoder))
+}
+
+/// Remove the headers that describe the encoded body
+///
+/// Drops `Content-Encoding` and, if there was one, `Content-Length`, which
+/// gave the encoded size. Headers of an unencoded body are left alone.
+///
+/// # Arguments
+///
+/// * `headers` - Response headers, to be returned and cached with the
+///   decoded body
+pub fn strip_content_encoding(headers: &mut HashMap<String, String>) {
+    if content_encoding(headers).is_none() {
+        return;
+    }
+    headers.retain(|name, _| {
+        !name.eq_ignore_ascii_case("content-encoding")
+            && !name.eq_ignore_ascii_case("content-length")
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+
+    const TEXT: &[u8] = b"<html><body>compressed hello, compressed hello</body></html>";
+
+    fn encoding(value: &str) -> HashMap<String, String> {
+        HashMap::from([
+            ("Content-Encoding".to_string(), value.to_string()),
+            ("content-length".to_string(), "42".to_string()),
+        ])
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn brotli(data: &[u8]) -> Vec<u8> {
+        let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+        encoder.write_all(data).unwrap();
+        encoder.into_inner()
+    }
+
+    #[test]
+    fn test_decodes_each_coding_in_chunks() {
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(TEXT).unwrap();
+        let mut raw = DeflateEncoder::new(Vec::new(), Compression::default());
+        raw.write_all(TEXT).unwrap();
+
+        let bodies = [
+            ("gzip", gzip(TEXT)),
+            ("deflate", zlib.finish().unwrap()),
+            ("deflate", raw.finish().unwrap()),
+            ("br", brotli(TEXT)),
+            ("gzip, br", brotli(&gzip(TEXT))),
+            ("identity", TEXT.to_vec()),
+        ];
+        for (coding, body) in bodies {
+            let mut decoder = BodyDecoder::new(&encoding(coding)).unwrap();
+            let mut decoded = Vec::new();
+            // One byte at a time, as the worst case of network chunking
+            for byte in &body {
+                decoded.extend(decoder.push(std::slice::from_ref(byte)).unwrap());
+            }
+            decoded.extend(decoder.finish().unwrap());
+            assert_eq!(decoded, TEXT, "{}", coding);
+            assert_eq!(decoder.encoded_size(), body.len());
+            assert_eq!(decoder.decoded_size(), TEXT.len());
+        }
+
+        let (decoded, decoder) = decode_body(&HashMap::new(), TEXT).unwrap();
+        assert_eq!(decoded, TEXT);
+        assert_eq!(decoder.encoded_size(), decoder.decoded_size());
+        // Empty bodies, as for a HEAD request, are not an error
+        assert!(decode_body(&encoding("gzip"), b"").unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn test_malformed_bodies_are_decode_errors() {
+        let mut truncated = gzip(TEXT);
+        truncated.truncate(truncated.len() - 4);
+        let cases = [
+            (
+                "gzip",
+                b"definitely not gzip".to_vec(),
+                "malformed gzip body",
+            ),
+            ("gzip", truncated, "malformed gzip body"),
+            ("br", vec![0xff; 32], "malformed brotli body"),
+            ("compress", TEXT.to_vec(), "unsupported content encoding"),
+        ];
+        for (coding, body, expected) in cases {
+            match decode_body(&encoding(coding), &body) {
+                Err(Error::DecodeError(message)) => {
+                    assert!(message.contains(expected), "{}: {}", coding, message)
+                }
+                Err(other) => panic!("{}: expected DecodeError, got {:?}", coding, other),
+                Ok(_) => panic!("{}: decoded a malformed body", coding),
+            }
+        }
+    }
+
+    #[test]
+    fn test_strip_content_encoding() {
+        let mut headers = encoding("gzip");
+        headers.insert("content-type".to_string(), "text/html".to_string());
+        strip_content_encoding(&mut headers);
+        assert_eq!(headers.keys().collect::<Vec<_>>(), vec!["content-type"]);
+
+        // An unencoded body keeps its length
+        let mut plain = HashMap::from([("content-length".to_string(), "5".to_string())]);
+        strip_content_encoding(&mut plain);
+        assert_eq!(plain.len(), 1);
+    }
+}
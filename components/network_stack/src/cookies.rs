@@ -0,0 +1,872 @@
+//! Cookie storage (RFC 6265)
+//!
+//! [`CookieStore`] is the jar the HTTP client reads and writes through
+//! reqwest's cookie provider hook: `Set-Cookie` headers on every response
+//! are parsed with their `Domain`, `Path`, `Expires`/`Max-Age`, `Secure`
+//! and `HttpOnly` attributes, and matching cookies go out in the `Cookie`
+//! header of later requests.
+//!
+//! A `Domain` attribute naming a public suffix (`co.uk`, `github.io`) is
+//! refused unless it is the host itself, in which case the cookie is
+//! host-only. `Secure` cookies are only accepted from and sent to https
+//! URLs.
+//!
+//! With `privacy.block_third_party_cookies` on, a request made for a
+//! document on another site neither sends nor stores cookies. Sites are
+//! compared by scheme and registrable domain, like cache partitions, so
+//! `https://sub.example.com` is first-party to `https://example.com` while
+//! `alice.github.io` is third-party to `bob.github.io`, and so is
+//! `http://example.com` to `https://example.com`. The HTTP client only shows the jar each hop's URL, so
+//! the stack runs every send inside [`with_initiator`], which records the
+//! document's URL for the hops of that request.
+//!
+//! With `network.cookie_file` set, cookies that have an expiry are kept in
+//! a SQLite database and loaded again on the next start; session cookies
+//! live in memory only.
+
+use crate::errors::{Error, Result};
+use reqwest::header::HeaderValue;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use shared_types::{registrable_domain, Clock, Origin};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use url::{Host, Url};
+
+/// Usual file name of the cookie database in the profile directory
+pub const COOKIE_DB_FILE: &str = "cookies.db";
+
+const COOKIE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS cookies (
+        domain TEXT NOT NULL,
+        path TEXT NOT NULL,
+        name TEXT NOT NULL,
+        value TEXT NOT NULL,
+        host_only INTEGER NOT NULL,
+        expires INTEGER NOT NULL,
+        secure INTEGER NOT NULL,
+        http_only INTEGER NOT NULL,
+        created INTEGER NOT NULL,
+        PRIMARY KEY (domain, path, name)
+    );
+";
+
+tokio::task_local! {
+    /// URL of the document the request sent by this task is for, while
+    /// third-party cookies are blocked
+    static INITIATOR: Option<Url>;
+}
+
+/// A stored cookie
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cookie {
+    /// Cookie name
+    pub name: String,
+    /// Cookie value
+    pub value: String,
+    /// Lowercase domain without a leading dot
+    pub domain: String,
+    /// Sent to `domain` only, not to its subdomains (no `Domain` attribute)
+    pub host_only: bool,
+    /// Path prefix the cookie is sent for
+    pub path: String,
+    /// Expiry in Unix seconds; `None` for a session cookie
+    pub expires: Option<u64>,
+    /// Only sent over https
+    pub secure: bool,
+    /// Hidden from `document.cookie`
+    pub http_only: bool,
+    /// When the cookie was first set, in Unix seconds
+    pub created: u64,
+}
+
+impl Cookie {
+    /// Parse a `Set-Cookie` header value received from `url`
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - `Set-Cookie` value, e.g. `id=1; Path=/; HttpOnly`
+    /// * `url` - URL of the response that set it
+    /// * `now` - Current time in Unix seconds, for `Max-Age`
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the header has no name, its `Domain` does not
+    /// cover the host or is a public suffix, or it is `Secure` but was not
+    /// received over https.
+    pub fn parse(header: &str, url: &Url, now: u64) -> Option<Self> {
+        let host = url.host_str()?.to_ascii_lowercase();
+        let mut parts = header.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let mut cookie = Self {
+            name: name.to_string(),
+            value: value.trim().to_string(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url),
+            expires: None,
+            secure: false,
+            http_only: false,
+            created: now,
+        };
+
+        let (mut max_age, mut expires, mut domain) = (None, None, None);
+        for attribute in parts {
+            let (key, value) = attribute.split_once('=').unwrap_or((attribute, ""));
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "expires" => expires = parse_cookie_date(value).or(expires),
+                "max-age" if value.starts_with(|c: char| c.is_ascii_digit() || c == '-') => {
+                    max_age = value.parse::<i64>().ok().or(max_age)
+                }
+                "domain" if !value.is_empty() => {
+                    domain = Some(value.trim_start_matches('.').to_ascii_lowercase())
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_string(),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                _ => {}
+            }
+        }
+
+        // Max-Age wins over Expires; zero or less expires the cookie now
+        cookie.expires = match max_age {
+            Some(seconds) if seconds <= 0 => Some(0),
+            Some(seconds) => Some(now.saturating_add(seconds as u64)),
+            None => expires,
+        };
+
+        if let Some(domain) = domain {
+            let is_ip = matches!(url.host(), Some(Host::Ipv4(_) | Host::Ipv6(_)));
+            if !domain_matches(&host, &domain, is_ip) {
+                return None;
+            }
+            // A cookie for a public suffix would reach every site under it
+            let public_suffix = !is_ip && registrable_domain(&domain).is_none();
+            if public_suffix && domain != host {
+                return None;
+            }
+            if !public_suffix {
+                cookie.domain = domain;
+                cookie.host_only = false;
+            }
+        }
+
+        if cookie.secure && !is_secure(url) {
+            return None;
+        }
+        Some(cookie)
+    }
+
+    /// Whether the cookie has expired at `now` (Unix seconds)
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+
+    /// Whether the cookie is sent with a request to `url` at `now`
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Request URL
+    /// * `now` - Current time in Unix seconds
+    pub fn matches(&self, url: &Url, now: u64) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let is_ip = matches!(url.host(), Some(Host::Ipv4(_) | Host::Ipv6(_)));
+        let domain_ok = if self.host_only {
+            host.eq_ignore_ascii_case(&self.domain)
+        } else {
+            domain_matches(&host.to_ascii_lowercase(), &self.domain, is_ip)
+        };
+        domain_ok
+            && path_matches(&self.path, url.path())
+            && (!self.secure || is_secure(url))
+            && !self.is_expired(now)
+    }
+
+    /// Whether `other` replaces this cookie (same name, domain and path)
+    fn same_key(&self, other: &Cookie) -> bool {
+        self.name == other.name && self.domain == other.domain && self.path == other.path
+    }
+}
+
+/// Cookie jar of the HTTP client, optionally persisted in SQLite
+#[derive(Debug)]
+pub struct CookieStore {
+    /// Cookies in the order they were first set
+    cookies: Mutex<Vec<Cookie>>,
+    /// Database persistent cookies are written to, if any
+    db: Option<Mutex<Connection>>,
+    /// Database file, if any
+    path: Option<PathBuf>,
+    /// Clock expiry is checked against
+    clock: Arc<dyn Clock>,
+}
+
+impl CookieStore {
+    /// Create an empty in-memory cookie store
+    ///
+    /// # Arguments
+    ///
+    /// * `clock` - Clock for `Max-Age` and expiry
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            cookies: Mutex::new(Vec::new()),
+            db: None,
+            path: None,
+            clock,
+        }
+    }
+
+    /// Open a cookie database, creating it (and its directory) if needed,
+    /// and load the cookies in it that have not expired
+    ///
+    /// Unlike the disk cache, a damaged database is not replaced: the
+    /// cookies in it are the user's sign-ins.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Database file, normally [`COOKIE_DB_FILE`] in the
+    ///   profile directory
+    /// * `clock` - Clock for `Max-Age` and expiry
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::CookieError` if the directory or database cannot be
+    /// created, or the database is damaged.
+    pub fn open(path: &Path, clock: Arc<dyn Clock>) -> Result<Self> {
+        let error =
+            |e: &dyn std::fmt::Display| Error::CookieError(format!("{}: {}", path.display(), e));
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| error(&e))?;
+        }
+        let conn = Self::open_connection(path).map_err(|e| error(&e))?;
+        let now = clock.unix_secs();
+        conn.execute(
+            "DELETE FROM cookies WHERE expires <= ?1",
+            params![now as i64],
+        )
+        .map_err(|e| error(&e))?;
+        let cookies = Self::load(&conn).map_err(|e| error(&e))?;
+        Ok(Self {
+            cookies: Mutex::new(cookies),
+            db: Some(Mutex::new(conn)),
+            path: Some(path.to_path_buf()),
+            clock,
+        })
+    }
+
+    /// Open and check the database, creating the schema if needed
+    fn open_connection(path: &Path) -> rusqlite::Result<Connection> {
+        let conn = Connection::open(path)?;
+        let status: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+        if status != "ok" {
+            return Err(rusqlite::Error::SqliteFailure(
+                rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_CORRUPT),
+                Some(status),
+            ));
+        }
+        let _: i64 = conn.query_row("PRAGMA secure_delete = ON", [], |row| row.get(0))?;
+        conn.execute_batch(COOKIE_SCHEMA)?;
+        Ok(conn)
+    }
+
+    /// Read every stored cookie, oldest first
+    fn load(conn: &Connection) -> rusqlite::Result<Vec<Cookie>> {
+        let mut statement = conn.prepare(
+            "SELECT name, value, domain, host_only, path, expires, secure, http_only, created
+             FROM cookies ORDER BY created, rowid",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok(Cookie {
+                name: row.get(0)?,
+                value: row.get(1)?,
+                domain: row.get(2)?,
+                host_only: row.get(3)?,
+                path: row.get(4)?,
+                expires: Some(row.get::<_, i64>(5)? as u64),
+                secure: row.get(6)?,
+                http_only: row.get(7)?,
+                created: row.get::<_, i64>(8)? as u64,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Database file, if cookies persist across restarts
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Store a cookie from a `Set-Cookie` header
+    ///
+    /// A cookie with the same name, domain and path is replaced, keeping
+    /// its creation time; an already expired cookie deletes it.
+    ///
+    /// # Arguments
+    ///
+    /// * `header` - `Set-Cookie` value
+    /// * `url` - URL of the response that set it
+    ///
+    /// # Returns
+    ///
+    /// Returns whether a cookie was stored (false if the header was refused
+    /// or deleted a cookie).
+    pub fn set_cookie(&self, header: &str, url: &Url) -> bool {
+        let now = self.clock.unix_secs();
+        let Some(mut cookie) = Cookie::parse(header, url, now) else {
+            return false;
+        };
+        let mut cookies = self.cookies.lock().unwrap();
+        let existing = cookies.iter().position(|stored| stored.same_key(&cookie));
+        if cookie.is_expired(now) {
+            if let Some(index) = existing {
+                let removed = cookies.remove(index);
+                self.forget(&removed);
+            }
+            return false;
+        }
+        match existing {
+            Some(index) => {
+                cookie.created = cookies[index].created;
+                cookies[index] = cookie.clone();
+            }
+            None => cookies.push(cookie.clone()),
+        }
+        if cookie.expires.is_some() {
+            self.persist(&cookie);
+        } else {
+            // A session cookie replacing a persistent one
+            self.forget(&cookie);
+        }
+        true
+    }
+
+    /// Cookies sent with a request to `url`
+    ///
+    /// # Returns
+    ///
+    /// Returns the cookies in `Cookie` header order: longer paths first,
+    /// then the oldest first.
+    pub fn get_cookies_for_url(&self, url: &Url) -> Vec<Cookie> {
+        let now = self.clock.unix_secs();
+        let mut matching: Vec<Cookie> = self
+            .cookies
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|cookie| cookie.matches(url, now))
+            .cloned()
+            .collect();
+        matching.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        matching
+    }
+
+    /// Every cookie that has not expired, oldest first
+    pub fn cookies(&self) -> Vec<Cookie> {
+        let now = self.clock.unix_secs();
+        self.cookies
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|cookie| !cookie.is_expired(now))
+            .cloned()
+            .collect()
+    }
+
+    /// Number of cookies held, including any expired but not yet removed
+    pub fn len(&self) -> usize {
+        self.cookies.lock().unwrap().len()
+    }
+
+    /// Whether the store holds no cookies
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Delete every cookie, including those in the database
+    pub fn clear(&self) {
+        self.cookies.lock().unwrap().clear();
+        if let Some(db) = &self.db {
+            let _ = db.lock().unwrap().execute("DELETE FROM cookies", []);
+        }
+    }
+
+    /// Write a persistent cookie to the database; a failed write only
+    /// costs the cookie on the next start
+    fn persist(&self, cookie: &Cookie) {
+        let Some(db) = &self.db else {
+            return;
+        };
+        let _ = db.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO cookies
+             (domain, path, name, value, host_only, expires, secure, http_only, created)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                cookie.domain,
+                cookie.path,
+                cookie.name,
+                cookie.value,
+                cookie.host_only,
+                cookie.expires.unwrap_or(0) as i64,
+                cookie.secure,
+                cookie.http_only,
+                cookie.created as i64,
+            ],
+        );
+    }
+
+    /// Delete a cookie from the database
+    fn forget(&self, cookie: &Cookie) {
+        let Some(db) = &self.db else {
+            return;
+        };
+        let _ = db.lock().unwrap().execute(
+            "DELETE FROM cookies WHERE domain = ?1 AND path = ?2 AND name = ?3",
+            params![cookie.domain, cookie.path, cookie.name],
+        );
+    }
+}
+
+impl reqwest::cookie::CookieStore for CookieStore {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        if blocked_for_initiator(url) {
+            return;
+        }
+        for header in cookie_headers {
+            if let Ok(header) = header.to_str() {
+                self.set_cookie(header, url);
+            }
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        if blocked_for_initiator(url) {
+            return None;
+        }
+        let header = self
+            .get_cookies_for_url(url)
+            .iter()
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if header.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&header).ok()
+    }
+}
+
+/// Whether a request to `url` made for a document at `initiator` is
+/// third-party
+///
+/// The URLs' origins are compared with [`Origin::is_same_site`], the check
+/// cache partitions use too: the scheme must match and the hosts must
+/// share a registrable domain (eTLD+1); the port is ignored. A host that is
+/// itself a public suffix is its own site.
+///
+/// # Arguments
+///
+/// * `url` - Request URL
+/// * `initiator` - URL of the top-level document the request is for
+///
+/// # Returns
+///
+/// Returns true if the sites differ, including when only one of the URLs
+/// has a host (an `about:` or `data:` document loading from the web).
+pub fn is_third_party(url: &Url, initiator: &Url) -> bool {
+    match (Origin::from_url(url), Origin::from_url(initiator)) {
+        (Some(url), Some(initiator)) => !url.is_same_site(&initiator),
+        // Neither has a host, so there is no other site involved
+        (None, None) => false,
+        // Only one has a host
+        _ => true,
+    }
+}
+
+/// Send a request with third-party cookies blocked for `initiator`
+///
+/// The jar neither sends cookies to nor stores cookies from any hop of
+/// `request` that is third-party to `initiator`. With `None`, every hop
+/// is treated as first-party.
+///
+/// `request` must not have touched the jar yet: pass
+/// `async move { builder.send().await }`, not `builder.send()`, which
+/// reads the cookies for the first hop as soon as it is called.
+///
+/// # Arguments
+///
+/// * `initiator` - URL of the top-level document, if cookies are
+///   restricted to its site
+/// * `request` - Future that sends the request
+pub(crate) async fn with_initiator<T>(
+    initiator: Option<Url>,
+    request: impl Future<Output = T>,
+) -> T {
+    INITIATOR.scope(initiator, request).await
+}
+
+/// Whether cookies for `url` are blocked for the request this task sends
+fn blocked_for_initiator(url: &Url) -> bool {
+    INITIATOR
+        .try_with(|initiator| {
+            initiator
+                .as_ref()
+                .is_some_and(|initiator| is_third_party(url, initiator))
+        })
+        .unwrap_or(false)
+}
+
+/// Parse a cookie date (RFC 6265 section 5.1.1)
+///
+/// Accepts the formats servers send in `Expires`: IMF-fixdate
+/// (`Sun, 06 Nov 1994 08:49:37 GMT`), RFC 850 (`Sunday, 06-Nov-94
+/// 08:49:37 GMT`) and asctime (`Sun Nov  6 08:49:37 1994`).
+///
+/// # Returns
+///
+/// Returns Unix seconds (0 for dates before 1970), or `None` if the text
+/// is not a valid date.
+pub fn parse_cookie_date(text: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    let is_delimiter = |c: char| matches!(c, '\t' | ' '..='/' | ';'..='@' | '['..='`' | '{'..='~');
+    let (mut time, mut day, mut month, mut year) = (None, None, None, None);
+    for token in text.split(is_delimiter).filter(|token| !token.is_empty()) {
+        let digits = token.bytes().take_while(u8::is_ascii_digit).count();
+        if time.is_none() {
+            if let Some(parsed) = parse_time(token) {
+                time = Some(parsed);
+                continue;
+            }
+        }
+        if day.is_none() && (1..=2).contains(&digits) {
+            day = token[..digits].parse::<u32>().ok();
+            continue;
+        }
+        if month.is_none() {
+            let prefix = token.get(..3).unwrap_or_default();
+            if let Some(index) = MONTHS.iter().position(|m| prefix.eq_ignore_ascii_case(m)) {
+                month = Some(index as u32 + 1);
+                continue;
+            }
+        }
+        if year.is_none() && (2..=4).contains(&digits) {
+            year = token[..digits].parse::<i64>().ok();
+        }
+    }
+
+    let ((hour, minute, second), day, month, mut year) = (time?, day?, month?, year?);
+    if (70..=99).contains(&year) {
+        year += 1900;
+    } else if (0..=69).contains(&year) {
+        year += 2000;
+    }
+    if year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    if day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    let seconds = days_from_civil(year, month, day) * 86_400
+        + i64::from(hour) * 3600
+        + i64::from(minute) * 60
+        + i64::from(second);
+    Some(seconds.max(0) as u64)
+}
+
+/// Parse an `hh:mm:ss` token; the seconds may be followed by non-digits
+fn parse_time(token: &str) -> Option<(u32, u32, u32)> {
+    let mut fields = token.splitn(3, ':');
+    let mut next = |last: bool| -> Option<u32> {
+        let field = fields.next()?;
+        let digits = field.bytes().take_while(u8::is_ascii_digit).count();
+        if !(1..=2).contains(&digits) || (!last && digits != field.len()) {
+            return None;
+        }
+        field[..digits].parse().ok()
+    };
+    Some((next(false)?, next(false)?, next(true)?))
+}
+
+/// Number of days in `month` (1-12) of `year`
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days from 1970-01-01 to a proleptic Gregorian date
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Path a cookie without a `Path` attribute applies to: the request
+/// path up to, but not including, its last `/`
+fn default_path(url: &Url) -> String {
+    let path = url.path();
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => path[..index].to_string(),
+    }
+}
+
+/// Whether `host` is `domain` or, for a name, a subdomain of it
+fn domain_matches(host: &str, domain: &str, is_ip: bool) -> bool {
+    host == domain
+        || (!is_ip
+            && host.len() > domain.len()
+            && host.ends_with(domain)
+            && host.as_bytes()[host.len() - domain.len() - 1] == b'.')
+}
+
+/// Whether a request path is within a cookie's path
+fn path_matches(cookie_path: &str, request_path: &str) -> bool {
+    match request_path.strip_prefix(cookie_path) {
+        Some(rest) => rest.is_empty() || cookie_path.ends_with('/') || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// Whether `url` is fetched over a secure channel
+fn is_secure(url: &Url) -> bool {
+    matches!(url.scheme(), "https" | "wss")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_types::MockClock;
+    use std::time::Duration;
+
+    fn url(input: &str) -> Url {
+        Url::parse(input).unwrap()
+    }
+
+    fn names(cookies: &[Cookie]) -> Vec<&str> {
+        cookies.iter().map(|cookie| cookie.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse_cookie_date_formats() {
+        // 1994-11-06 08:49:37 UTC
+        let expected = Some(784_111_777);
+        assert_eq!(parse_cookie_date("Sun, 06 Nov 1994 08:49:37 GMT"), expected);
+        assert_eq!(
+            parse_cookie_date("Sunday, 06-Nov-94 08:49:37 GMT"),
+            expected
+        );
+        assert_eq!(parse_cookie_date("Sun Nov  6 08:49:37 1994"), expected);
+        assert_eq!(parse_cookie_date("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(
+            parse_cookie_date("Wed, 29 Feb 2024 12:00:00 GMT"),
+            Some(1_709_208_000)
+        );
+        assert_eq!(parse_cookie_date("Fri, 31 Feb 2023 12:00:00 GMT"), None);
+        assert_eq!(parse_cookie_date("Sun, 06 Nov 1994 25:00:00 GMT"), None);
+        assert_eq!(parse_cookie_date("tomorrow"), None);
+    }
+
+    #[test]
+    fn test_parse_attributes() {
+        let now = 1_000;
+        let page = url("https://www.example.com/shop/cart");
+
+        let cookie = Cookie::parse("id=42", &page, now).unwrap();
+        assert_eq!(cookie.domain, "www.example.com");
+        assert!(cookie.host_only);
+        assert_eq!(cookie.path, "/shop");
+        assert_eq!(cookie.expires, None);
+
+        let cookie = Cookie::parse(
+            "id=42; Domain=.Example.com; Path=/; Secure; HttpOnly; \
+             Expires=Sun, 06 Nov 1994 08:49:37 GMT; Max-Age=60",
+            &page,
+            now,
+        )
+        .unwrap();
+        assert_eq!(cookie.domain, "example.com");
+        assert!(!cookie.host_only);
+        assert_eq!(cookie.path, "/");
+        assert!(cookie.secure && cookie.http_only);
+        assert_eq!(cookie.expires, Some(1_060));
+
+        let cookie = Cookie::parse("id=1; Path=relative; Max-Age=-1", &page, now).unwrap();
+        assert_eq!(cookie.path, "/shop");
+        assert!(cookie.is_expired(now));
+
+        // Another site, a public suffix, and Secure over http are refused
+        assert!(Cookie::parse("id=1; Domain=other.com", &page, now).is_none());
+        assert!(Cookie::parse("id=1; Domain=com", &page, now).is_none());
+        let pages = url("https://alice.github.io/");
+        assert!(Cookie::parse("id=1; Domain=github.io", &pages, now).is_none());
+        let plain = url("http://www.example.com/");
+        assert!(Cookie::parse("id=1; Secure", &plain, now).is_none());
+        assert!(Cookie::parse("=nameless", &plain, now).is_none());
+    }
+
+    #[test]
+    fn test_cookies_for_url_match_domain_path_and_scheme() {
+        let store = CookieStore::new(Arc::new(MockClock::default()));
+        let page = url("https://www.example.com/docs/index.html");
+        assert!(store.set_cookie("host=1", &page));
+        assert!(store.set_cookie("site=1; Domain=example.com; Path=/", &page));
+        assert!(store.set_cookie("secret=1; Path=/; Secure", &page));
+
+        let cookies = store.get_cookies_for_url(&url("https://www.example.com/docs/a"));
+        assert_eq!(names(&cookies), ["host", "site", "secret"]);
+        let cookies = store.get_cookies_for_url(&url("http://www.example.com/docs"));
+        assert_eq!(names(&cookies), ["host", "site"]);
+        let cookies = store.get_cookies_for_url(&url("https://api.example.com/docs"));
+        assert_eq!(names(&cookies), ["site"]);
+        let cookies = store.get_cookies_for_url(&url("https://www.example.com/docsets"));
+        assert_eq!(names(&cookies), ["site", "secret"]);
+        assert!(store
+            .get_cookies_for_url(&url("https://example.org/"))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_replace_expire_and_delete() {
+        let clock = Arc::new(MockClock::default());
+        let store = CookieStore::new(clock.clone());
+        let page = url("https://example.com/");
+        store.set_cookie("a=1; Max-Age=60", &page);
+        let created = store.cookies()[0].created;
+
+        clock.advance(Duration::from_secs(10));
+        store.set_cookie("a=2; Max-Age=60", &page);
+        let cookies = store.cookies();
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(
+            (cookies[0].value.as_str(), cookies[0].created),
+            ("2", created)
+        );
+
+        clock.advance(Duration::from_secs(61));
+        assert!(store.get_cookies_for_url(&page).is_empty());
+
+        store.set_cookie("b=1", &page);
+        assert!(!store.set_cookie("b=; Max-Age=0", &page));
+        assert!(store.cookies().is_empty());
+    }
+
+    #[test]
+    fn test_persistent_cookies_survive_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profile").join(COOKIE_DB_FILE);
+        let clock = Arc::new(MockClock::default());
+        let page = url("https://example.com/");
+        {
+            let store = CookieStore::open(&path, clock.clone()).unwrap();
+            store.set_cookie("kept=1; Max-Age=3600; HttpOnly", &page);
+            store.set_cookie("session=1", &page);
+            store.set_cookie("short=1; Max-Age=5", &page);
+        }
+
+        clock.advance(Duration::from_secs(10));
+        let store = CookieStore::open(&path, clock.clone()).unwrap();
+        let cookies = store.cookies();
+        assert_eq!(names(&cookies), ["kept"]);
+        assert!(cookies[0].http_only);
+
+        store.clear();
+        assert!(store.is_empty());
+        let store = CookieStore::open(&path, clock).unwrap();
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_third_party_by_registrable_domain() {
+        let cases = [
+            ("https://sub.example.com/a", "https://example.com/", false),
+            (
+                "https://cdn.example.com:8080/",
+                "https://www.example.com/",
+                false,
+            ),
+            ("http://example.com/", "https://example.com/", true),
+            ("https://news.bbc.co.uk/", "https://www.bbc.co.uk/", false),
+            ("https://tracker.test/pixel", "https://example.com/", true),
+            ("https://itv.co.uk/", "https://bbc.co.uk/", true),
+            ("https://bob.github.io/", "https://alice.github.io/", true),
+            ("http://127.0.0.1:3000/", "http://127.0.0.1:4000/", false),
+            ("http://127.0.0.1/", "http://localhost/", true),
+            ("https://example.com/", "about:blank", true),
+            ("about:blank", "https://example.com/", true),
+            ("data:text/plain,x", "about:blank", false),
+        ];
+        for (request, initiator, third_party) in cases {
+            assert_eq!(
+                is_third_party(&url(request), &url(initiator)),
+                third_party,
+                "{} from {}",
+                request,
+                initiator
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_initiator_scope_blocks_cross_site_hops() {
+        use reqwest::cookie::CookieStore as _;
+
+        let store = CookieStore::new(Arc::new(MockClock::default()));
+        let tracker = url("https://tracker.test/pixel");
+        let headers = [HeaderValue::from_static("id=1")];
+        let initiator = Some(url("https://example.com/"));
+
+        with_initiator(initiator.clone(), async {
+            store.set_cookies(&mut headers.iter(), &tracker);
+        })
+        .await;
+        assert!(store.is_empty());
+
+        // Outside a scope, or with no initiator, the jar is unrestricted
+        store.set_cookies(&mut headers.iter(), &tracker);
+        assert_eq!(store.len(), 1);
+        let sent = with_initiator(None, async {
+            reqwest::cookie::CookieStore::cookies(&store, &tracker)
+        })
+        .await;
+        assert!(sent.is_some());
+        let sent = with_initiator(initiator, async {
+            reqwest::cookie::CookieStore::cookies(&store, &tracker)
+        })
+        .await;
+        assert!(sent.is_none());
+    }
+
+    #[test]
+    fn test_reqwest_cookie_header() {
+        use reqwest::cookie::CookieStore as _;
+
+        let store = CookieStore::new(Arc::new(MockClock::default()));
+        let page = url("https://example.com/app/");
+        let headers = [
+            HeaderValue::from_static("a=1; Path=/"),
+            HeaderValue::from_static("b=2"),
+        ];
+        store.set_cookies(&mut headers.iter(), &page);
+        let header = reqwest::cookie::CookieStore::cookies(&store, &page).unwrap();
+        assert_eq!(header, "b=2; a=1");
+        assert!(reqwest::cookie::CookieStore::cookies(&store, &url("https://x.com/")).is_none());
+    }
+}
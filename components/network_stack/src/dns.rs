@@ -0,0 +1,634 @@
+//! Host name resolution with a shared cache and speculative prefetch
+//!
+//! Every connection the HTTP client opens resolves its host through the
+//! stack's [`Resolver`], which keeps answers for [`DNS_CACHE_TTL`]. Pages
+//! can also have host names resolved ahead of a click: the hosts of links
+//! scrolled into view and `<link rel="dns-prefetch">` hints. Those lookups
+//! are marked as prefetched, so [`ResolverStats`] can tell how many of them
+//! a later request actually used.
+//!
+//! Prefetching is best effort. Prefetched hosts are resolved one at a time
+//! and yield to lookups for real requests; a failed prefetch is only counted.
+//! A tab that is discarded or hibernated has its pending prefetches
+//! dropped and prefetches nothing more until it loads a page again.
+//!
+//! Answers are cached per [`PartitionKey`] like HTTP responses (see
+//! [`partition`](crate::partition)): a page cannot tell from lookup timings
+//! which hosts other sites made the browser resolve. Lookups outside any
+//! partition share one cache.
+
+use crate::partition::PartitionKey;
+use serde::{Deserialize, Serialize};
+use shared_types::{Clock, TabId};
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::AbortHandle;
+
+/// How long a resolved address stays in the cache
+pub const DNS_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Most distinct hosts prefetched for one page
+pub const DNS_PREFETCH_PAGE_CAP: usize = 32;
+
+/// Cached hosts kept before expired entries are swept
+const MAX_CACHED_HOSTS: usize = 1024;
+
+tokio::task_local! {
+    /// Time the request sent by this task has spent resolving host names
+    static LOOKUP_TIME: Cell<Duration>;
+    /// Partition the request sent by this task caches its lookups in
+    static PARTITION: Option<PartitionKey>;
+}
+
+/// Future returned by [`Resolve::resolve`]
+pub type Resolving = Pin<Box<dyn Future<Output = io::Result<Vec<IpAddr>>> + Send>>;
+
+/// Source of host name lookups behind the [`Resolver`] cache
+pub trait Resolve: Send + Sync {
+    /// Look up the addresses of `host`
+    fn resolve(&self, host: &str) -> Resolving;
+}
+
+/// The operating system resolver
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl Resolve for SystemResolver {
+    fn resolve(&self, host: &str) -> Resolving {
+        let host = host.to_string();
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((host.as_str(), 0)).await?;
+            Ok(addrs.map(|addr| addr.ip()).collect())
+        })
+    }
+}
+
+/// Resolver answering from a fixed table, for tests
+///
+/// Every lookup is recorded; hosts without an answer fail with
+/// `NotFound`.
+#[derive(Debug, Default)]
+pub struct MockResolver {
+    /// Addresses per host
+    answers: HashMap<String, Vec<IpAddr>>,
+    /// Hosts looked up, in order
+    lookups: Mutex<Vec<String>>,
+}
+
+impl MockResolver {
+    /// Create a resolver that knows no hosts
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer lookups of `host` with `ip`
+    pub fn with_host(mut self, host: &str, ip: IpAddr) -> Self {
+        self.answers
+            .entry(host.to_ascii_lowercase())
+            .or_default()
+            .push(ip);
+        self
+    }
+
+    /// Hosts looked up so far, in order
+    pub fn lookups(&self) -> Vec<String> {
+        self.lookups.lock().unwrap().clone()
+    }
+}
+
+impl Resolve for MockResolver {
+    fn resolve(&self, host: &str) -> Resolving {
+        self.lookups.lock().unwrap().push(host.to_string());
+        let answer = self.answers.get(host).cloned().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, format!("no such host: {}", host))
+        });
+        Box::pin(async move { answer })
+    }
+}
+
+/// Lookup counters shown on `about:diagnostics`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolverStats {
+    /// Lookups made for requests
+    pub lookups: u64,
+    /// Request lookups answered from the cache
+    pub cache_hits: u64,
+    /// Hosts resolved by a prefetch
+    pub prefetched: u64,
+    /// Prefetched hosts a request later used
+    pub prefetch_hits: u64,
+    /// Lookups of either kind that failed
+    pub failures: u64,
+}
+
+impl ResolverStats {
+    /// Share of request lookups answered from the cache (0.0 - 1.0)
+    pub fn cache_hit_rate(&self) -> f64 {
+        ratio(self.cache_hits, self.lookups)
+    }
+
+    /// Share of prefetched hosts a request later used (0.0 - 1.0)
+    pub fn prefetch_hit_rate(&self) -> f64 {
+        ratio(self.prefetch_hits, self.prefetched)
+    }
+}
+
+fn ratio(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+/// A cached lookup result
+#[derive(Debug, Clone)]
+struct CachedAddrs {
+    addrs: Vec<IpAddr>,
+    resolved_at: Instant,
+    /// Resolved by a prefetch and not used by a request yet
+    unused_prefetch: bool,
+}
+
+/// Cache key: partition and lowercase host
+type CacheKey = (Option<PartitionKey>, String);
+
+/// Caching resolver shared by the HTTP client and the prefetcher
+pub struct Resolver {
+    backend: Arc<dyn Resolve>,
+    clock: Arc<dyn Clock>,
+    entries: Mutex<HashMap<CacheKey, CachedAddrs>>,
+    stats: Mutex<ResolverStats>,
+    /// Held by the one prefetch lookup allowed at a time
+    prefetch_slot: tokio::sync::Mutex<()>,
+}
+
+impl Resolver {
+    /// Create a resolver
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - Where cache misses are looked up
+    /// * `clock` - Clock for cache expiry
+    pub fn new(backend: Arc<dyn Resolve>, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            backend,
+            clock,
+            entries: Mutex::new(HashMap::new()),
+            stats: Mutex::new(ResolverStats::default()),
+            prefetch_slot: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Resolve `host` for a request, from the shared cache if possible
+    ///
+    /// # Errors
+    ///
+    /// Returns the backend's error if the host is not cached and cannot be
+    /// resolved.
+    pub async fn lookup(&self, host: &str) -> io::Result<Vec<IpAddr>> {
+        self.lookup_in(None, host).await
+    }
+
+    /// Resolve `host` for a request, from the partition's cache if possible
+    ///
+    /// # Arguments
+    ///
+    /// * `partition` - Partition of the request; `None` for the shared one
+    /// * `host` - Host name to resolve
+    ///
+    /// # Errors
+    ///
+    /// Returns the backend's error if the host is not cached and cannot be
+    /// resolved.
+    pub async fn lookup_in(
+        &self,
+        partition: Option<&PartitionKey>,
+        host: &str,
+    ) -> io::Result<Vec<IpAddr>> {
+        let key = (partition.cloned(), host.to_ascii_lowercase());
+        let now = self.clock.now_instant();
+        {
+            let mut entries = self.entries.lock().unwrap();
+            let mut stats = self.stats.lock().unwrap();
+            stats.lookups += 1;
+            if let Some(entry) = entries.get_mut(&key).filter(|e| is_fresh(e, now)) {
+                stats.cache_hits += 1;
+                if entry.unused_prefetch {
+                    entry.unused_prefetch = false;
+                    stats.prefetch_hits += 1;
+                }
+                return Ok(entry.addrs.clone());
+            }
+        }
+
+        match self.backend.resolve(&key.1).await {
+            Ok(addrs) => {
+                self.insert(key, addrs.clone(), false);
+                Ok(addrs)
+            }
+            Err(error) => {
+                self.stats.lock().unwrap().failures += 1;
+                Err(error)
+            }
+        }
+    }
+
+    /// Resolve `host` into the shared cache ahead of a request that may
+    /// follow
+    ///
+    /// Waits for any other prefetch to finish first.
+    ///
+    /// # Returns
+    ///
+    /// Returns `true` if the host was resolved now, `false` if it was
+    /// already cached or the lookup failed.
+    pub async fn prefetch(&self, host: &str) -> bool {
+        self.prefetch_in(None, host).await
+    }
+
+    /// Resolve `host` into the partition's cache ahead of a request that
+    /// may follow
+    ///
+    /// As [`prefetch`](Self::prefetch), for requests made in `partition`.
+    pub async fn prefetch_in(&self, partition: Option<&PartitionKey>, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        if self.is_cached_in(partition, &host) {
+            return false;
+        }
+        let _slot = self.prefetch_slot.lock().await;
+        // Let lookups for real requests go first
+        tokio::task::yield_now().await;
+        if self.is_cached_in(partition, &host) {
+            return false;
+        }
+
+        match self.backend.resolve(&host).await {
+            Ok(addrs) => {
+                self.insert((partition.cloned(), host), addrs, true);
+                self.stats.lock().unwrap().prefetched += 1;
+                true
+            }
+            Err(_) => {
+                self.stats.lock().unwrap().failures += 1;
+                false
+            }
+        }
+    }
+
+    /// Whether `host` has an unexpired entry in the shared cache
+    pub fn is_cached(&self, host: &str) -> bool {
+        self.is_cached_in(None, host)
+    }
+
+    /// Whether `host` has an unexpired entry in the partition's cache
+    pub fn is_cached_in(&self, partition: Option<&PartitionKey>, host: &str) -> bool {
+        let now = self.clock.now_instant();
+        self.entries
+            .lock()
+            .unwrap()
+            .get(&(partition.cloned(), host.to_ascii_lowercase()))
+            .is_some_and(|entry| is_fresh(entry, now))
+    }
+
+    /// Lookup counters since the resolver was created
+    pub fn stats(&self) -> ResolverStats {
+        *self.stats.lock().unwrap()
+    }
+
+    /// Drop every cached address
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Drop the addresses cached outside any partition
+    pub fn purge_shared(&self) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|(partition, _), _| partition.is_some());
+    }
+
+    fn insert(&self, key: CacheKey, addrs: Vec<IpAddr>, prefetched: bool) {
+        let now = self.clock.now_instant();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_CACHED_HOSTS {
+            entries.retain(|_, entry| is_fresh(entry, now));
+        }
+        entries.insert(
+            key,
+            CachedAddrs {
+                addrs,
+                resolved_at: now,
+                unused_prefetch: prefetched,
+            },
+        );
+    }
+}
+
+fn is_fresh(entry: &CachedAddrs, now: Instant) -> bool {
+    now.saturating_duration_since(entry.resolved_at) < DNS_CACHE_TTL
+}
+
+/// Lets the HTTP client resolve through a shared [`Resolver`]
+pub(crate) struct ClientResolver(pub(crate) Arc<Resolver>);
+
+impl reqwest::dns::Resolve for ClientResolver {
+    fn resolve(&self, name: hyper::client::connect::dns::Name) -> reqwest::dns::Resolving {
+        let resolver = self.0.clone();
+        Box::pin(async move {
+            let partition = PARTITION.try_with(Clone::clone).ok().flatten();
+            let start = resolver.clock.now_instant();
+            let addrs = resolver.lookup_in(partition.as_ref(), name.as_str()).await;
+            let elapsed = resolver.clock.elapsed_since(start);
+            let _ = LOOKUP_TIME.try_with(|total| total.set(total.get() + elapsed));
+            let addrs = addrs?;
+            let addrs: reqwest::dns::Addrs =
+                Box::new(addrs.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+/// Send a request, measuring the time spent resolving host names for it
+///
+/// The client only resolves a host when it opens a connection, so the time
+/// is zero when a pooled connection was reused, and close to zero when the
+/// address was cached.
+///
+/// # Arguments
+///
+/// * `request` - Future that sends the request
+///
+/// # Returns
+///
+/// Returns the request's output and the lookup time.
+pub(crate) async fn time_lookups<T>(request: impl Future<Output = T>) -> (T, Duration) {
+    LOOKUP_TIME
+        .scope(Cell::new(Duration::ZERO), async {
+            let output = request.await;
+            (output, LOOKUP_TIME.with(Cell::get))
+        })
+        .await
+}
+
+/// Send a request, caching its host lookups in `partition`
+///
+/// # Arguments
+///
+/// * `partition` - Partition of the request; `None` for the shared one
+/// * `request` - Future that sends the request
+pub(crate) async fn with_partition<T>(
+    partition: Option<PartitionKey>,
+    request: impl Future<Output = T>,
+) -> T {
+    PARTITION.scope(partition, request).await
+}
+
+/// Normalize a host name a page asked to prefetch
+///
+/// # Returns
+///
+/// Returns the lowercased name, or `None` for IP literals, names without a
+/// dot (`localhost`, intranet names) and anything that is not a host name.
+pub fn prefetch_host(host: &str) -> Option<String> {
+    let host = host.trim().trim_end_matches('.');
+    match url::Host::parse(host) {
+        Ok(url::Host::Domain(domain)) if domain.contains('.') => Some(domain),
+        _ => None,
+    }
+}
+
+/// What each tab's current page has asked to prefetch
+#[derive(Debug, Default)]
+pub(crate) struct PrefetchPages {
+    pages: HashMap<TabId, PrefetchPage>,
+}
+
+#[derive(Debug, Default)]
+struct PrefetchPage {
+    /// Partition of the page; its prefetched hosts are cached there
+    partition: Option<PartitionKey>,
+    /// Hosts already queued for this page
+    requested: HashSet<String>,
+    /// The page turned prefetching off (`x-dns-prefetch-control: off`),
+    /// or the tab was suspended
+    disabled: bool,
+    /// Prefetch tasks started for this page
+    tasks: Vec<AbortHandle>,
+}
+
+impl PrefetchPages {
+    /// Forget the tab's previous page
+    ///
+    /// # Arguments
+    ///
+    /// * `tab_id` - Tab loading a document
+    /// * `partition` - Partition of the document
+    pub(crate) fn begin_page(&mut self, tab_id: TabId, partition: Option<PartitionKey>) {
+        self.pages.insert(
+            tab_id,
+            PrefetchPage {
+                partition,
+                ..PrefetchPage::default()
+            },
+        );
+    }
+
+    /// Partition of the tab's page
+    pub(crate) fn partition(&self, tab_id: TabId) -> Option<PartitionKey> {
+        self.pages.get(&tab_id)?.partition.clone()
+    }
+
+    /// Turn prefetching off for the rest of the tab's page
+    pub(crate) fn disable(&mut self, tab_id: TabId) {
+        self.pages.entry(tab_id).or_default().disabled = true;
+    }
+
+    /// Keep a prefetch task of the tab's page, so suspending the tab can
+    /// stop it
+    pub(crate) fn track(&mut self, tab_id: TabId, task: AbortHandle) {
+        let page = self.pages.entry(tab_id).or_default();
+        page.tasks.retain(|task| !task.is_finished());
+        page.tasks.push(task);
+    }
+
+    /// Stop the tab's prefetch tasks and turn prefetching off for the rest
+    /// of its page
+    pub(crate) fn suspend(&mut self, tab_id: TabId) {
+        let page = self.pages.entry(tab_id).or_default();
+        for task in page.tasks.drain(..) {
+            task.abort();
+        }
+        page.disabled = true;
+    }
+
+    /// Whether the tab's page turned prefetching off
+    #[cfg(test)]
+    pub(crate) fn is_disabled(&self, tab_id: TabId) -> bool {
+        self.pages.get(&tab_id).is_some_and(|page| page.disabled)
+    }
+
+    /// Hosts not yet requested by the tab's page, up to the page cap
+    pub(crate) fn admit(&mut self, tab_id: TabId, hosts: Vec<String>) -> Vec<String> {
+        let page = self.pages.entry(tab_id).or_default();
+        if page.disabled {
+            return Vec::new();
+        }
+        let mut admitted = Vec::new();
+        for host in hosts {
+            if page.requested.len() >= DNS_PREFETCH_PAGE_CAP {
+                break;
+            }
+            if page.requested.insert(host.clone()) {
+                admitted.push(host);
+            }
+        }
+        admitted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_types::MockClock;
+
+    fn ip(last: u8) -> IpAddr {
+        IpAddr::from([192, 0, 2, last])
+    }
+
+    fn resolver() -> (Resolver, Arc<MockResolver>, Arc<MockClock>) {
+        let backend = Arc::new(
+            MockResolver::new()
+                .with_host("example.com", ip(1))
+                .with_host("cdn.example.com", ip(2)),
+        );
+        let clock = Arc::new(MockClock::default());
+        let resolver = Resolver::new(backend.clone(), clock.clone());
+        (resolver, backend, clock)
+    }
+
+    #[tokio::test]
+    async fn test_lookup_is_cached_until_ttl() {
+        let (resolver, backend, clock) = resolver();
+
+        assert_eq!(resolver.lookup("Example.com").await.unwrap(), vec![ip(1)]);
+        assert_eq!(resolver.lookup("example.com").await.unwrap(), vec![ip(1)]);
+        assert_eq!(backend.lookups(), vec!["example.com"]);
+
+        clock.advance(DNS_CACHE_TTL);
+        resolver.lookup("example.com").await.unwrap();
+        assert_eq!(backend.lookups().len(), 2);
+
+        let stats = resolver.stats();
+        assert_eq!(stats.lookups, 3);
+        assert_eq!(stats.cache_hits, 1);
+        assert_eq!(stats.prefetched, 0);
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_hit_counted_once() {
+        let (resolver, backend, _clock) = resolver();
+
+        assert!(resolver.prefetch("cdn.example.com").await);
+        assert!(!resolver.prefetch("cdn.example.com").await);
+        assert!(resolver.is_cached("cdn.example.com"));
+
+        resolver.lookup("cdn.example.com").await.unwrap();
+        resolver.lookup("cdn.example.com").await.unwrap();
+        assert_eq!(backend.lookups(), vec!["cdn.example.com"]);
+
+        let stats = resolver.stats();
+        assert_eq!(stats.prefetched, 1);
+        assert_eq!(stats.prefetch_hits, 1);
+        assert_eq!(stats.cache_hits, 2);
+        assert_eq!(stats.prefetch_hit_rate(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_failures_are_counted_not_cached() {
+        let (resolver, backend, _clock) = resolver();
+
+        assert!(!resolver.prefetch("missing.example").await);
+        assert!(resolver.lookup("missing.example").await.is_err());
+        assert_eq!(backend.lookups().len(), 2);
+        assert_eq!(resolver.stats().failures, 2);
+        assert_eq!(resolver.stats().prefetched, 0);
+    }
+
+    #[tokio::test]
+    async fn test_partitions_do_not_share_lookups() {
+        let (resolver, backend, _clock) = resolver();
+        let site_a = PartitionKey::for_site(&url::Url::parse("https://a.test/").unwrap());
+        let site_b = PartitionKey::for_site(&url::Url::parse("https://b.test/").unwrap());
+
+        resolver
+            .lookup_in(site_a.as_ref(), "cdn.example.com")
+            .await
+            .unwrap();
+        assert!(resolver.is_cached_in(site_a.as_ref(), "cdn.example.com"));
+        assert!(!resolver.is_cached_in(site_b.as_ref(), "cdn.example.com"));
+        assert!(!resolver.is_cached("cdn.example.com"));
+
+        // Site B's lookup is not answered from site A's entry
+        resolver
+            .lookup_in(site_b.as_ref(), "cdn.example.com")
+            .await
+            .unwrap();
+        assert_eq!(backend.lookups().len(), 2);
+        assert_eq!(resolver.stats().cache_hits, 0);
+
+        // A prefetch for one page warms only its own partition
+        assert!(resolver.prefetch_in(site_a.as_ref(), "example.com").await);
+        assert!(!resolver.is_cached_in(site_b.as_ref(), "example.com"));
+
+        resolver.lookup("example.com").await.unwrap();
+        resolver.purge_shared();
+        assert!(!resolver.is_cached("example.com"));
+        assert!(resolver.is_cached_in(site_a.as_ref(), "example.com"));
+    }
+
+    #[test]
+    fn test_prefetch_host_filters_non_names() {
+        assert_eq!(
+            prefetch_host("CDN.Example.com."),
+            Some("cdn.example.com".to_string())
+        );
+        assert_eq!(prefetch_host("localhost"), None);
+        assert_eq!(prefetch_host("192.168.1.1"), None);
+        assert_eq!(prefetch_host("[::1]"), None);
+        assert_eq!(prefetch_host("exa mple.com"), None);
+        assert_eq!(prefetch_host(""), None);
+    }
+
+    #[test]
+    fn test_pages_dedup_cap_and_off_switch() {
+        let mut pages = PrefetchPages::default();
+        let hosts = |range: std::ops::Range<usize>| -> Vec<String> {
+            range.map(|i| format!("h{}.example", i)).collect()
+        };
+
+        assert_eq!(pages.admit(1, hosts(0..4)).len(), 4);
+        assert_eq!(pages.admit(1, hosts(2..6)), hosts(4..6));
+        assert_eq!(
+            pages.admit(1, hosts(0..100)).len(),
+            DNS_PREFETCH_PAGE_CAP - 6
+        );
+        assert!(pages.admit(1, hosts(100..101)).is_empty());
+
+        // A new page starts over; the off switch lasts for the page
+        pages.begin_page(1, None);
+        pages.disable(1);
+        assert!(pages.is_disabled(1));
+        assert!(pages.admit(1, hosts(0..1)).is_empty());
+        assert!(!pages.is_disabled(2));
+        pages.begin_page(1, None);
+        assert_eq!(pages.admit(1, hosts(0..1)).len(), 1);
+    }
+}
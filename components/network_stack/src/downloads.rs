@@ -0,0 +1,838 @@
+//! Downloads
+//!
+//! [`NetworkStack::start_download`](crate::NetworkStack::start_download)
+//! saves a response to disk in the background, chunk by chunk, so a large
+//! file never sits in memory. Progress goes out on the message bus as
+//! `BrowserMessage::DownloadProgress`, when the response arrives and then
+//! at most every [`PROGRESS_STEP`] bytes, followed by `DownloadComplete`
+//! or `DownloadFailed`.
+//!
+//! The body is written to `<name>.part` and renamed once complete; a
+//! failed download's partial file is removed. When the destination is a
+//! folder the file is named after the response's `Content-Disposition`
+//! filename, else the last segment of the URL, and an existing file is
+//! never overwritten: ` (1)`, ` (2)`… is added to the name instead.
+//!
+//! A download whose server sent `Accept-Ranges: bytes` can be paused and
+//! resumed. Resuming asks for the rest of the file with a `Range` request,
+//! guarded by `If-Range` with the response's `ETag` (or `Last-Modified`),
+//! and appends to the partial file; a server that sends the whole body
+//! instead starts the file over. Downloads ask for the body unencoded
+//! (`Accept-Encoding: identity`) so that byte offsets match the file.
+//!
+//! Downloads started for a tab pause with it when the tab is discarded or
+//! hibernated (`BrowserMessage::TabSuspended`) and continue when it is
+//! activated again (`TabResumed`). Downloads that cannot be resumed keep
+//! going, as stopping them would lose what they saved.
+
+use crate::bandwidth::BandwidthMeter;
+use crate::cancel::FetchHandle;
+use crate::connections::{self, HostLimits};
+use crate::dns;
+use crate::errors::{Error, Result};
+use crate::fetch_options::check_redirect;
+use crate::request_handler::RedirectInterceptor;
+use crate::types::{RequestDetails, ResourceTiming};
+use message_bus::MessageSender;
+use reqwest::{Client, StatusCode};
+use shared_types::{BrowserMessage, Clock, TabId};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
+use url::Url;
+
+/// Bytes saved between two `DownloadProgress` messages
+pub const PROGRESS_STEP: u64 = 256 * 1024;
+
+/// Added to a download's file name until it is complete
+pub const PART_SUFFIX: &str = ".part";
+
+/// File name used when neither the response nor the URL suggests one
+const DEFAULT_FILE_NAME: &str = "download";
+
+/// Time allowed for a whole download; the client's own timeout also
+/// covers the body, which is far too short for a large file
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Identifies a download in messages and queries
+pub type DownloadId = u64;
+
+/// Where a download stands
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadState {
+    /// Being transferred
+    InProgress,
+    /// Stopped by the user; the partial file is kept for resuming
+    Paused,
+    /// Saved to [`DownloadInfo::path`]
+    Complete,
+    /// Gave up, with the reason
+    Failed(String),
+}
+
+/// A download, as shown in a downloads list
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadInfo {
+    /// Download id
+    pub id: DownloadId,
+    /// URL the download was started for
+    pub url: Url,
+    /// Tab the download was started from, if any
+    pub tab_id: Option<TabId>,
+    /// File the download is saved to; until the response arrives, the
+    /// destination it was started with
+    pub path: PathBuf,
+    /// Bytes saved so far
+    pub received: u64,
+    /// Size of the whole file, if the server said
+    pub total: Option<u64>,
+    /// Whether the server accepts range requests, so the download can be
+    /// paused and resumed
+    pub resumable: bool,
+    /// Progress of the download
+    pub state: DownloadState,
+}
+
+/// A download and what resuming it needs
+struct Download {
+    /// What is shown to the user
+    info: DownloadInfo,
+    /// Destination the download was started with
+    dest: PathBuf,
+    /// Request headers after the interceptor chain, sent again on resume
+    headers: HashMap<String, String>,
+    /// `ETag` or `Last-Modified` of the response, sent as `If-Range`
+    validator: Option<String>,
+    /// Partial file, once the response has named it
+    part: Option<PathBuf>,
+    /// Stops the running transfer
+    handle: FetchHandle,
+    /// The running transfer
+    task: Option<JoinHandle<()>>,
+    /// What the transfer shares with the network stack, kept so the
+    /// download can continue with its tab
+    transfer: Transfer,
+    /// Paused because its tab was suspended, not by the user
+    suspended: bool,
+}
+
+/// What a download task shares with the network stack
+#[derive(Clone)]
+pub(crate) struct Transfer {
+    /// HTTP client; it returns redirects, which the task follows
+    pub(crate) client: Client,
+    /// Per-host connection slots
+    pub(crate) connections: Arc<HostLimits>,
+    /// Traffic counters
+    pub(crate) bandwidth: Arc<BandwidthMeter>,
+    /// Timing log the finished download is added to
+    pub(crate) timing_data: Arc<Mutex<Vec<ResourceTiming>>>,
+    /// Clock for timing
+    pub(crate) clock: Arc<dyn Clock>,
+    /// Redirects the download may follow
+    pub(crate) max_redirects: usize,
+}
+
+/// Downloads of a network stack, shared with their tasks
+pub(crate) struct DownloadManager {
+    /// Id of the next download
+    next_id: AtomicU64,
+    /// Every download this session, by id
+    downloads: Mutex<HashMap<DownloadId, Download>>,
+    /// Where progress messages are sent
+    sender: Arc<dyn MessageSender>,
+}
+
+impl DownloadManager {
+    /// Create a manager that reports progress to `sender`
+    pub(crate) fn new(sender: Arc<dyn MessageSender>) -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            downloads: Mutex::new(HashMap::new()),
+            sender,
+        }
+    }
+
+    /// Start downloading `url` in the background
+    ///
+    /// # Arguments
+    ///
+    /// * `tab_id` - Tab the download was started from, if any
+    /// * `url` - URL to download, after the interceptor chain
+    /// * `dest` - File to save to, or a folder to save into
+    /// * `headers` - Request headers after the interceptor chain
+    /// * `transfer` - What the task needs from the network stack
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::DownloadError` if there is no Tokio runtime to run
+    /// the download on.
+    pub(crate) fn start(
+        self: &Arc<Self>,
+        tab_id: Option<TabId>,
+        url: Url,
+        dest: &Path,
+        headers: HashMap<String, String>,
+        transfer: Transfer,
+    ) -> Result<DownloadId> {
+        let runtime = Handle::try_current()
+            .map_err(|_| Error::DownloadError("no async runtime to download on".to_string()))?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let download = Download {
+            info: DownloadInfo {
+                id,
+                url,
+                tab_id,
+                path: dest.to_path_buf(),
+                received: 0,
+                total: None,
+                resumable: false,
+                state: DownloadState::InProgress,
+            },
+            dest: dest.to_path_buf(),
+            headers,
+            validator: None,
+            part: None,
+            handle: FetchHandle::new(),
+            task: None,
+            transfer,
+            suspended: false,
+        };
+        self.downloads.lock().unwrap().insert(id, download);
+        self.spawn(&runtime, id);
+        Ok(id)
+    }
+
+    /// Run a download's transfer on `runtime` with a fresh handle
+    fn spawn(self: &Arc<Self>, runtime: &Handle, id: DownloadId) {
+        let handle = FetchHandle::new();
+        let mut downloads = self.downloads.lock().unwrap();
+        let Some(download) = downloads.get_mut(&id) else {
+            return;
+        };
+        download.handle = handle.clone();
+        let transfer = download.transfer.clone();
+        download.task = Some(runtime.spawn(self.clone().run(id, handle, transfer)));
+    }
+
+    /// Stop a download, keeping its partial file
+    ///
+    /// Returns once the transfer has stopped writing.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::DownloadError` if there is no such download, it is
+    /// not in progress, or its server does not accept range requests.
+    pub(crate) async fn pause(&self, id: DownloadId) -> Result<()> {
+        let task = {
+            let mut downloads = self.downloads.lock().unwrap();
+            let download = downloads
+                .get_mut(&id)
+                .ok_or_else(|| Error::DownloadError(format!("no download {}", id)))?;
+            if download.info.state != DownloadState::InProgress {
+                return Err(Error::DownloadError(format!(
+                    "download {} is not in progress",
+                    id
+                )));
+            }
+            if !download.info.resumable {
+                return Err(Error::DownloadError(format!(
+                    "download {} cannot be resumed, so it cannot be paused",
+                    id
+                )));
+            }
+            download.handle.cancel();
+            download.info.state = DownloadState::Paused;
+            download.task.take()
+        };
+        if let Some(task) = task {
+            let _ = task.await;
+        }
+        Ok(())
+    }
+
+    /// Pause the tab's downloads that can be resumed
+    ///
+    /// Returns once they have stopped writing. The user's own pauses are
+    /// left alone by [`resume_tab`](Self::resume_tab).
+    ///
+    /// # Returns
+    ///
+    /// The number of downloads paused
+    pub(crate) async fn suspend_tab(&self, tab_id: TabId) -> usize {
+        let tasks: Vec<Option<JoinHandle<()>>> = {
+            let mut downloads = self.downloads.lock().unwrap();
+            downloads
+                .values_mut()
+                .filter(|download| {
+                    download.info.tab_id == Some(tab_id)
+                        && download.info.state == DownloadState::InProgress
+                        && download.info.resumable
+                })
+                .map(|download| {
+                    download.handle.cancel();
+                    download.info.state = DownloadState::Paused;
+                    download.suspended = true;
+                    download.task.take()
+                })
+                .collect()
+        };
+        let paused = tasks.len();
+        for task in tasks.into_iter().flatten() {
+            let _ = task.await;
+        }
+        paused
+    }
+
+    /// Continue the downloads [`suspend_tab`](Self::suspend_tab) paused
+    ///
+    /// # Returns
+    ///
+    /// The number of downloads continued
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::DownloadError` if there is no Tokio runtime to run
+    /// them on.
+    pub(crate) fn resume_tab(self: &Arc<Self>, tab_id: TabId) -> Result<usize> {
+        let runtime = Handle::try_current()
+            .map_err(|_| Error::DownloadError("no async runtime to download on".to_string()))?;
+        let suspended: Vec<DownloadId> = {
+            let mut downloads = self.downloads.lock().unwrap();
+            downloads
+                .values_mut()
+                .filter(|download| {
+                    download.suspended
+                        && download.info.tab_id == Some(tab_id)
+                        && download.info.state == DownloadState::Paused
+                })
+                .map(|download| {
+                    download.suspended = false;
+                    download.info.state = DownloadState::InProgress;
+                    download.info.id
+                })
+                .collect()
+        };
+        for &id in &suspended {
+            self.spawn(&runtime, id);
+        }
+        Ok(suspended.len())
+    }
+
+    /// Continue a paused download where it stopped
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::DownloadError` if there is no such download, it is
+    /// not paused, or there is no Tokio runtime to run it on.
+    pub(crate) fn resume(self: &Arc<Self>, id: DownloadId, transfer: Transfer) -> Result<()> {
+        let runtime = Handle::try_current()
+            .map_err(|_| Error::DownloadError("no async runtime to download on".to_string()))?;
+        {
+            let mut downloads = self.downloads.lock().unwrap();
+            let download = downloads
+                .get_mut(&id)
+                .ok_or_else(|| Error::DownloadError(format!("no download {}", id)))?;
+            if download.info.state != DownloadState::Paused {
+                return Err(Error::DownloadError(format!(
+                    "download {} is not paused",
+                    id
+                )));
+            }
+            download.info.state = DownloadState::InProgress;
+            download.suspended = false;
+            download.transfer = transfer;
+        }
+        self.spawn(&runtime, id);
+        Ok(())
+    }
+
+    /// A download's current state
+    pub(crate) fn get(&self, id: DownloadId) -> Option<DownloadInfo> {
+        let downloads = self.downloads.lock().unwrap();
+        downloads.get(&id).map(|download| download.info.clone())
+    }
+
+    /// Every download this session, oldest first
+    pub(crate) fn list(&self) -> Vec<DownloadInfo> {
+        let downloads = self.downloads.lock().unwrap();
+        let mut list: Vec<DownloadInfo> = downloads
+            .values()
+            .map(|download| download.info.clone())
+            .collect();
+        list.sort_by_key(|info| info.id);
+        list
+    }
+
+    /// Transfer a download until it completes, fails or is paused, and
+    /// report how it ended
+    async fn run(self: Arc<Self>, id: DownloadId, handle: FetchHandle, transfer: Transfer) {
+        let result = tokio::select! {
+            biased;
+            // Paused: `pause` already updated the state
+            _ = handle.cancelled() => return,
+            result = self.transfer(id, &transfer) => result,
+        };
+        let message = match result {
+            Ok(path) => {
+                self.update(id, |download| download.info.state = DownloadState::Complete);
+                BrowserMessage::DownloadComplete { id, path }
+            }
+            Err(error) => {
+                let reason = error.to_string();
+                self.update(id, |download| {
+                    if let Some(part) = download.part.take() {
+                        let _ = std::fs::remove_file(part);
+                    }
+                    download.info.received = 0;
+                    download.info.state = DownloadState::Failed(reason.clone());
+                });
+                BrowserMessage::DownloadFailed { id, reason }
+            }
+        };
+        let _ = self.sender.send(message);
+    }
+
+    /// Request the rest of a download and write it to its partial file
+    ///
+    /// Files are written synchronously between chunks, so a pause (which
+    /// drops this future while it waits for the network) always leaves
+    /// exactly `received` bytes in the partial file.
+    ///
+    /// # Returns
+    ///
+    /// Returns the file the download was saved to.
+    async fn transfer(&self, id: DownloadId, transfer: &Transfer) -> Result<PathBuf> {
+        let (mut url, dest, headers, offset, validator, part) = {
+            let downloads = self.downloads.lock().unwrap();
+            let download = downloads
+                .get(&id)
+                .ok_or_else(|| Error::DownloadError(format!("no download {}", id)))?;
+            (
+                download.info.url.clone(),
+                download.dest.clone(),
+                download.headers.clone(),
+                download.info.received,
+                download.validator.clone(),
+                download.part.clone(),
+            )
+        };
+        let clock = transfer.clock.as_ref();
+        let start = clock.now_instant();
+        let started_unix_ms = clock.unix_millis();
+        let mut redirected_from = Vec::new();
+        let mut queued = Duration::ZERO;
+        let mut lookups = Duration::ZERO;
+
+        let (mut response, _slot) = loop {
+            let slot = transfer
+                .connections
+                .acquire(&connections::host_key(&url)?, clock)
+                .await;
+            queued += slot.queued;
+            let mut builder = transfer
+                .client
+                .get(url.clone())
+                .timeout(DOWNLOAD_TIMEOUT)
+                .header("Accept-Encoding", "identity");
+            for (name, value) in &headers {
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+            if offset > 0 {
+                builder = builder.header("Range", format!("bytes={}-", offset));
+                if let Some(ref validator) = validator {
+                    builder = builder.header("If-Range", validator);
+                }
+            }
+            let (response, dns) = dns::time_lookups(builder.send()).await;
+            let response = response.map_err(request_error)?;
+            lookups += dns;
+
+            let status = response.status().as_u16();
+            let location = response
+                .headers()
+                .get("location")
+                .and_then(|location| location.to_str().ok())
+                .filter(|_| RedirectInterceptor::is_redirect_status(status))
+                .and_then(|location| url.join(location).ok());
+            let Some(location) = location else {
+                break (response, slot);
+            };
+            check_redirect(&redirected_from, &url, &location, transfer.max_redirects)?;
+            redirected_from.push(std::mem::replace(&mut url, location));
+        };
+        let ttfb = clock.elapsed_since(start);
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Error::RequestFailed(format!(
+                "HTTP error: {}",
+                status.as_u16()
+            )));
+        }
+        let resumed = offset > 0 && status == StatusCode::PARTIAL_CONTENT;
+        let mut response_headers = HashMap::new();
+        for (name, value) in response.headers().iter() {
+            if let Ok(value_str) = value.to_str() {
+                response_headers.insert(name.as_str().to_string(), value_str.to_string());
+            }
+        }
+        let header = |name: &str| response_headers.get(name).map(String::as_str);
+        let start_at = if resumed { offset } else { 0 };
+        let total = response.content_length().map(|length| start_at + length);
+        let resumable = header("accept-ranges")
+            .is_some_and(|ranges| ranges.to_ascii_lowercase().contains("bytes"));
+        let validator = header("etag")
+            .or_else(|| header("last-modified"))
+            .map(str::to_string);
+        let (path, part) = match part {
+            Some(part) => (self.get(id).map_or(dest, |info| info.path), part),
+            None => {
+                let path = destination(&dest, header("content-disposition"), &url);
+                let part = part_path(&path);
+                (path, part)
+            }
+        };
+        self.update(id, |download| {
+            download.info.path = path.clone();
+            download.info.received = start_at;
+            download.info.total = total;
+            download.info.resumable = resumable;
+            download.validator = validator;
+            download.part = Some(part.clone());
+        });
+
+        let file_error =
+            |e: std::io::Error| Error::DownloadError(format!("{}: {}", part.display(), e));
+        let mut file = if resumed {
+            let file = OpenOptions::new()
+                .append(true)
+                .open(&part)
+                .map_err(file_error)?;
+            file.set_len(offset).map_err(file_error)?;
+            file
+        } else {
+            File::create(&part).map_err(file_error)?
+        };
+
+        let mut received = start_at;
+        let mut reported = received;
+        let mut size_bytes = 0;
+        self.progress(id, received, total);
+        while let Some(chunk) = response.chunk().await.map_err(request_error)? {
+            file.write_all(&chunk).map_err(file_error)?;
+            received += chunk.len() as u64;
+            size_bytes += chunk.len();
+            self.update(id, |download| download.info.received = received);
+            if received - reported >= PROGRESS_STEP {
+                self.progress(id, received, total);
+                reported = received;
+            }
+        }
+        if received != reported {
+            self.progress(id, received, total);
+        }
+        file.sync_all().map_err(file_error)?;
+        drop(file);
+        std::fs::rename(&part, &path).map_err(file_error)?;
+        self.update(id, |download| download.part = None);
+
+        let duration = clock.elapsed_since(start);
+        let timing = ResourceTiming {
+            url: url.to_string(),
+            start_time: Duration::from_secs(0),
+            end_time: duration,
+            duration_ms: duration.as_millis() as u64,
+            ttfb_ms: ttfb.as_millis() as u64,
+            queued_ms: queued.as_millis() as u64,
+            dns_ms: lookups.as_millis() as u64,
+            connect_ms: 0,
+            tls_ms: 0,
+            download_ms: duration.saturating_sub(ttfb).as_millis() as u64,
+            size_bytes,
+            encoded_size: size_bytes,
+            decoded_size: size_bytes,
+            from_cache: false,
+            revalidated: false,
+            cancelled: false,
+            redirected_from: redirected_from.iter().map(Url::to_string).collect(),
+            details: RequestDetails {
+                started_unix_ms,
+                method: "GET".to_string(),
+                request_headers: headers,
+                status: status.as_u16(),
+                http_version: format!("{:?}", response.version()),
+                response_headers,
+                ..RequestDetails::default()
+            },
+        };
+        transfer.bandwidth.record_timing(&timing);
+        transfer.timing_data.lock().unwrap().push(timing);
+        Ok(path)
+    }
+
+    /// Change a download's record, if it still exists
+    fn update(&self, id: DownloadId, change: impl FnOnce(&mut Download)) {
+        if let Some(download) = self.downloads.lock().unwrap().get_mut(&id) {
+            change(download);
+        }
+    }
+
+    /// Send a `DownloadProgress` message
+    fn progress(&self, id: DownloadId, received: u64, total: Option<u64>) {
+        let _ = self.sender.send(BrowserMessage::DownloadProgress {
+            id,
+            received,
+            total,
+        });
+    }
+}
+
+/// Describe a failed send or body read
+fn request_error(error: reqwest::Error) -> Error {
+    if error.is_timeout() {
+        Error::Timeout
+    } else {
+        Error::RequestFailed(error.to_string())
+    }
+}
+
+/// File name suggested by a `Content-Disposition` header
+///
+/// `filename*` (RFC 6266, UTF-8 or ISO-8859-1) wins over `filename`. Any
+/// folder part is dropped, along with control characters and leading
+/// dots, so the name cannot point outside the download folder or hide
+/// the file.
+///
+/// # Arguments
+///
+/// * `value` - Header value, e.g. `attachment; filename="report.pdf"`
+///
+/// # Returns
+///
+/// Returns `None` if the header names no usable file.
+pub fn content_disposition_filename(value: &str) -> Option<String> {
+    let mut plain = None;
+    let mut extended = None;
+    for (name, value) in disposition_parameters(value) {
+        match name.to_ascii_lowercase().as_str() {
+            "filename*" if extended.is_none() => extended = decode_ext_value(&value),
+            "filename" if plain.is_none() => plain = Some(value),
+            _ => {}
+        }
+    }
+    extended
+        .and_then(|name| sanitize_file_name(&name))
+        .or_else(|| plain.and_then(|name| sanitize_file_name(&name)))
+}
+
+/// `name=value` parameters after the disposition type, with quoted values
+/// unescaped
+fn disposition_parameters(value: &str) -> Vec<(String, String)> {
+    let mut parameters = Vec::new();
+    let mut rest = value.split_once(';').map_or("", |(_, rest)| rest);
+    loop {
+        rest = rest.trim_start_matches([';', ' ', '\t']);
+        let Some((name, after)) = rest.split_once('=') else {
+            break;
+        };
+        let after = after.trim_start();
+        let (value, remaining) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut value = String::new();
+                let mut end = quoted.len();
+                let mut chars = quoted.char_indices();
+                while let Some((index, c)) = chars.next() {
+                    match c {
+                        '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                        '"' => {
+                            end = index + 1;
+                            break;
+                        }
+                        c => value.push(c),
+                    }
+                }
+                let remaining = &quoted[end..];
+                (value, remaining.find(';').map_or("", |i| &remaining[i..]))
+            }
+            None => {
+                let end = after.find(';').unwrap_or(after.len());
+                (after[..end].trim().to_string(), &after[end..])
+            }
+        };
+        parameters.push((name.trim().to_string(), value));
+        rest = remaining;
+    }
+    parameters
+}
+
+/// Decode an RFC 5987 `charset'language'percent-encoded` value
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?;
+    let encoded = parts.nth(1)?;
+    let bytes = percent_decode(encoded);
+    if charset.eq_ignore_ascii_case("utf-8") {
+        String::from_utf8(bytes).ok()
+    } else if charset.eq_ignore_ascii_case("iso-8859-1") {
+        Some(bytes.into_iter().map(char::from).collect())
+    } else {
+        None
+    }
+}
+
+/// Decode `%XX` escapes; malformed escapes are kept as they are
+fn percent_decode(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' {
+            let hex = text.get(index + 1..index + 3);
+            if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                index += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    decoded
+}
+
+/// Make a suggested name safe to create in the download folder
+fn sanitize_file_name(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    let name = name.trim().trim_start_matches('.').trim_start();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// File a download is saved to
+///
+/// # Arguments
+///
+/// * `dest` - Destination the download was started with
+/// * `content_disposition` - The response's `Content-Disposition` header
+/// * `url` - Final URL of the response
+fn destination(dest: &Path, content_disposition: Option<&str>, url: &Url) -> PathBuf {
+    if !dest.is_dir() {
+        return dest.to_path_buf();
+    }
+    let name = content_disposition
+        .and_then(content_disposition_filename)
+        .or_else(|| {
+            let segment = url.path_segments()?.next_back()?;
+            sanitize_file_name(&String::from_utf8_lossy(&percent_decode(segment)))
+        })
+        .unwrap_or_else(|| DEFAULT_FILE_NAME.to_string());
+    unique_path(&dest.join(name))
+}
+
+/// `path`, or `name (n).ext` beside it if `path` or its partial file
+/// already exists
+fn unique_path(path: &Path) -> PathBuf {
+    let taken = |path: &Path| path.exists() || part_path(path).exists();
+    if !taken(path) {
+        return path.to_path_buf();
+    }
+    let stem = path
+        .file_stem()
+        .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+    let extension = path
+        .extension()
+        .map_or_else(String::new, |ext| format!(".{}", ext.to_string_lossy()));
+    (1..)
+        .map(|n| path.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !taken(candidate))
+        .expect("some numbered name is free")
+}
+
+/// Partial file a download to `path` is written to
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(PART_SUFFIX);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_disposition_filename() {
+        let cases = [
+            ("attachment; filename=\"report.pdf\"", Some("report.pdf")),
+            ("attachment; filename=plain.txt; size=10", Some("plain.txt")),
+            (
+                "attachment; filename=\"fallback.txt\"; filename*=UTF-8''na%C3%AFve%20file.txt",
+                Some("naïve file.txt"),
+            ),
+            (
+                "attachment; filename*=iso-8859-1'en'caf%E9.txt",
+                Some("café.txt"),
+            ),
+            (
+                "attachment; filename=\"quote\\\"d;name.txt\"",
+                Some("quote\"d;name.txt"),
+            ),
+            // Folders and hidden names are stripped
+            ("attachment; filename=\"../../etc/passwd\"", Some("passwd")),
+            (
+                "attachment; filename=\"C:\\\\Windows\\\\evil.exe\"",
+                Some("evil.exe"),
+            ),
+            ("attachment; filename=\".bashrc\"", Some("bashrc")),
+            ("attachment; filename=\"..\"", None),
+            ("attachment", None),
+            ("inline; filename*=unknown''x.txt", None),
+        ];
+        for (header, expected) in cases {
+            assert_eq!(
+                content_disposition_filename(header).as_deref(),
+                expected,
+                "{}",
+                header
+            );
+        }
+    }
+
+    #[test]
+    fn test_destination_in_folder_never_overwrites() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = Url::parse("https://example.com/files/annual%20report.pdf?v=2").unwrap();
+
+        let named = destination(dir.path(), None, &url);
+        assert_eq!(named, dir.path().join("annual report.pdf"));
+        std::fs::write(&named, b"old").unwrap();
+        assert_eq!(
+            destination(dir.path(), None, &url),
+            dir.path().join("annual report (1).pdf")
+        );
+        // A download still in progress holds its name too
+        std::fs::write(part_path(&dir.path().join("data.csv")), b"").unwrap();
+        assert_eq!(
+            destination(dir.path(), Some("attachment; filename=data.csv"), &url),
+            dir.path().join("data (1).csv")
+        );
+
+        let bare = Url::parse("https://example.com/").unwrap();
+        assert_eq!(
+            destination(dir.path(), None, &bare),
+            dir.path().join(DEFAULT_FILE_NAME)
+        );
+        // A file destination is used as given
+        let file = dir.path().join("chosen.bin");
+        assert_eq!(
+            destination(&file, Some("attachment; filename=x"), &url),
+            file
+        );
+    }
+}
@@ -1,5 +1,6 @@
 //! Error types for network stack component
 
+use shared_types::ResourceType;
 use thiserror::Error;
 
 /// Errors that can occur in the network stack
@@ -17,6 +18,74 @@ pub enum Error {
     #[error("Request timeout")]
     Timeout,
 
+    /// Request was refused by an interceptor (ad blocking, content settings)
+    #[error("Request blocked ({resource_type:?}): {reason}")]
+    Blocked {
+        /// Type of the refused resource
+        resource_type: ResourceType,
+        /// Why it was refused
+        reason: String,
+    },
+
+    /// Connection failed after part of the body was received
+    #[error("Response interrupted after {received} bytes: {reason}")]
+    StreamInterrupted {
+        /// Body bytes received before the failure
+        received: usize,
+        /// Underlying error
+        reason: String,
+    },
+
+    /// A response body could not be decoded for its `Content-Encoding`
+    #[error("Failed to decode response body: {0}")]
+    DecodeError(String),
+
+    /// The fetch was cancelled through its
+    /// [`FetchHandle`](crate::FetchHandle) before it completed
+    #[error("Request cancelled")]
+    Cancelled,
+
+    /// A download or prefetch was refused because the bandwidth budget was
+    /// exceeded and the user has not resumed background transfers
+    #[error("Background transfers are paused: bandwidth budget exceeded")]
+    TransfersPaused,
+
+    /// Offline mode is on and the request could not be answered from the
+    /// cache
+    #[error("Offline: {0} is not in the cache")]
+    Offline(String),
+
+    /// Opening another connection would exceed
+    /// `network.max_connections_per_host`
+    #[error("Too many connections to {host} (limit {limit})")]
+    ConnectionLimit {
+        /// Host (and port) of the refused connection
+        host: String,
+        /// Connections allowed per host
+        limit: u32,
+    },
+
+    /// A redirect chain revisited a URL or went past the redirect limit
+    #[error("Redirect loop at {url} after {redirects} redirects")]
+    RedirectLoop {
+        /// URL the next redirect pointed to
+        url: String,
+        /// Redirects followed before giving up
+        redirects: usize,
+    },
+
+    /// A download could not be started, paused, resumed or saved
+    #[error("Download error: {0}")]
+    DownloadError(String),
+
+    /// WebSocket handshake or framing error
+    #[error("WebSocket error: {0}")]
+    WebSocket(String),
+
+    /// A server presented something that is not an X.509 certificate
+    #[error("Invalid certificate: {0}")]
+    InvalidCertificate(String),
+
     /// Cache error
     #[error("Cache error: {0}")]
     CacheError(String),
@@ -29,6 +98,10 @@ pub enum Error {
     #[error("Initialization error: {0}")]
     InitializationError(String),
 
+    /// Network settings that cannot be used, such as an unparsable proxy
+    #[error("Configuration error: {0}")]
+    ConfigError(String),
+
     /// Message bus error
     #[error("Message bus error: {0}")]
     MessageBusError(String),
@@ -52,6 +125,27 @@ mod tests {
         assert!(err.to_string().contains("Connection refused"));
     }
 
+    #[test]
+    fn test_error_blocked() {
+        let err = Error::Blocked {
+            resource_type: ResourceType::Image,
+            reason: "images disabled for https://example.com".to_string(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Request blocked (Image): images disabled for https://example.com"
+        );
+    }
+
+    #[test]
+    fn test_error_offline() {
+        let err = Error::Offline("https://example.com/".to_string());
+        assert_eq!(
+            err.to_string(),
+            "Offline: https://example.com/ is not in the cache"
+        );
+    }
+
     #[test]
     fn test_error_invalid_url() {
         let err = Error::InvalidUrl("not a url".to_string());
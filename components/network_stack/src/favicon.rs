@@ -0,0 +1,109 @@
+//! Favicons fetched for pages, kept per top-level site
+//!
+//! Tabs, bookmarks and history show the icon of each page, so the same
+//! icon is asked for again and again. [`FaviconCache`] keeps the fetched
+//! bytes for the session, keyed by icon URL and by the [`PartitionKey`] of
+//! the page the icon was fetched for, like the HTTP cache (see
+//! [`partition`](crate::partition)): a site cannot tell whether the user
+//! visited another site from how fast that site's icon loads.
+
+use crate::partition::PartitionKey;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use url::Url;
+
+/// Icons kept before the cache starts over
+const MAX_FAVICONS: usize = 512;
+
+/// Cache key: partition and icon URL
+type CacheKey = (Option<PartitionKey>, Url);
+
+/// Favicon bytes by partition and icon URL
+#[derive(Debug, Default)]
+pub struct FaviconCache {
+    entries: Mutex<HashMap<CacheKey, Vec<u8>>>,
+}
+
+impl FaviconCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The icon at `url` fetched in `partition`
+    ///
+    /// # Arguments
+    ///
+    /// * `partition` - Partition of the page; `None` for the shared one
+    /// * `url` - URL of the icon
+    pub fn get_in(&self, partition: Option<&PartitionKey>, url: &Url) -> Option<Vec<u8>> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(&(partition.cloned(), url.clone()))
+            .cloned()
+    }
+
+    /// Keep the icon at `url` fetched in `partition`
+    ///
+    /// Once [`MAX_FAVICONS`] icons are kept, the cache is emptied before
+    /// the next one is added.
+    pub fn put_in(&self, partition: Option<PartitionKey>, url: Url, icon: Vec<u8>) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_FAVICONS {
+            entries.clear();
+        }
+        entries.insert((partition, url), icon);
+    }
+
+    /// Number of icons kept, over all partitions
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Whether no icon is kept
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every icon
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Drop the icons kept outside any partition
+    pub fn purge_shared(&self) {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|(partition, _), _| partition.is_some());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_partitions_do_not_share_icons() {
+        let cache = FaviconCache::new();
+        let icon = url("https://cdn.example/favicon.ico");
+        let site_a = PartitionKey::for_site(&url("https://a.test/"));
+        let site_b = PartitionKey::for_site(&url("https://b.test/"));
+
+        cache.put_in(site_a.clone(), icon.clone(), b"icon".to_vec());
+        assert_eq!(cache.get_in(site_a.as_ref(), &icon), Some(b"icon".to_vec()));
+        assert_eq!(cache.get_in(site_b.as_ref(), &icon), None);
+        assert_eq!(cache.get_in(None, &icon), None);
+
+        cache.put_in(None, icon.clone(), b"shared".to_vec());
+        assert_eq!(cache.len(), 2);
+        cache.purge_shared();
+        assert_eq!(cache.get_in(None, &icon), None);
+        assert_eq!(cache.len(), 1);
+    }
+}
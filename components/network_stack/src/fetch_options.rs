@@ -0,0 +1,664 @@
+//! Fetches with an explicit method, headers and body
+//!
+//! [`NetworkStack::fetch_with_options`](crate::NetworkStack::fetch_with_options)
+//! is the entry point for navigations that carry a request body, such as
+//! form posts. Redirects are followed here rather than inside the HTTP
+//! client so the method rewrite rules are ours:
+//!
+//! - 303 always continues as GET without a body
+//! - 301 and 302 turn a POST into a GET (what every browser does)
+//! - 307 and 308 repeat the original method and body
+//!
+//! [`fetch`](crate::NetworkStack::fetch) and
+//! [`fetch_with_metadata`](crate::NetworkStack::fetch_with_metadata) follow
+//! redirects by the same rules, and the latter reports the chain in a
+//! [`FetchResponse`].
+//!
+//! A chain that comes back to a URL it already visited, or that goes on
+//! past `network.max_redirects` hops, fails with `Error::RedirectLoop`
+//! rather than bouncing until the limit runs out.
+
+use crate::errors::{Error, Result};
+use crate::request_handler::HttpMethod;
+use shared_types::BrowserMessage;
+use std::collections::HashMap;
+use std::time::Duration;
+use url::Url;
+
+/// Default maximum number of redirects followed by a single fetch
+pub const MAX_REDIRECTS: usize = 20;
+
+/// Method, headers and body of a fetch
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchOptions {
+    /// HTTP method
+    pub method: HttpMethod,
+    /// Request headers
+    pub headers: HashMap<String, String>,
+    /// Request body (optional)
+    pub body: Option<Vec<u8>>,
+    /// Time allowed for each hop, instead of `network.timeout_seconds`
+    pub timeout: Option<Duration>,
+    /// URL of the top-level document the request is made for; `None` for
+    /// a navigation
+    pub initiator: Option<Url>,
+}
+
+impl FetchOptions {
+    /// Create options for a bodiless request
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - HTTP method
+    pub fn new(method: HttpMethod) -> Self {
+        Self {
+            method,
+            headers: HashMap::new(),
+            body: None,
+            timeout: None,
+            initiator: None,
+        }
+    }
+
+    /// Create options for a POST with a body
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - Request body
+    /// * `content_type` - Value of the `Content-Type` header
+    pub fn post(body: Vec<u8>, content_type: &str) -> Self {
+        Self::new(HttpMethod::POST)
+            .with_header("Content-Type", content_type)
+            .with_body(body)
+    }
+
+    /// Add a request header
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Set request body
+    pub fn with_body(mut self, body: Vec<u8>) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    /// Override the configured timeout for this fetch
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Make the request on behalf of the top-level document at `initiator`
+    ///
+    /// Interceptors, the cache partition and third-party cookie blocking
+    /// see the document's origin, as for a subresource.
+    pub fn with_initiator(mut self, initiator: Url) -> Self {
+        self.initiator = Some(initiator);
+        self
+    }
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self::new(HttpMethod::GET)
+    }
+}
+
+/// Complete response to [`FetchOptions`] or
+/// [`NetworkStack::fetch_with_metadata`](crate::NetworkStack::fetch_with_metadata)
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchResponse {
+    /// Final URL, after redirects
+    pub url: Url,
+    /// HTTP status code of the final response
+    pub status: u16,
+    /// Response headers
+    pub headers: HashMap<String, String>,
+    /// Response body
+    pub body: Vec<u8>,
+    /// Method of the final request (GET if a redirect rewrote a POST)
+    pub method: HttpMethod,
+    /// URLs that redirected, in the order they were visited
+    pub redirected_from: Vec<Url>,
+    /// Whether the body was served from the HTTP cache; the headers are
+    /// then the ones stored with it
+    pub from_cache: bool,
+    /// Whether the cached body was past its freshness lifetime; only
+    /// served that way while offline
+    pub stale: bool,
+}
+
+impl FetchResponse {
+    /// Whether the server answered with a 2xx status
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Value of a response header, matched case-insensitively
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Header name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The response as a message for the message bus
+    ///
+    /// # Arguments
+    ///
+    /// * `request_id` - Id of the `HttpRequest` this answers
+    pub fn into_message(self, request_id: u64) -> BrowserMessage {
+        BrowserMessage::HttpResponse {
+            request_id,
+            status: self.status,
+            headers: self.headers,
+            final_url: self.url,
+            from_cache: self.from_cache,
+            body: self.body,
+        }
+    }
+}
+
+/// Rewrite a request for the next hop of a redirect
+///
+/// # Arguments
+///
+/// * `status` - Redirect status code
+/// * `options` - The request that was redirected
+///
+/// # Returns
+///
+/// Returns the request to send to the redirect target.
+pub fn follow_redirect(status: u16, options: &FetchOptions) -> FetchOptions {
+    let becomes_get = match status {
+        303 => options.method != HttpMethod::HEAD,
+        301 | 302 => options.method == HttpMethod::POST,
+        _ => false,
+    };
+    if !becomes_get {
+        return options.clone();
+    }
+
+    // The body and the headers describing it don't survive the rewrite
+    let mut next = FetchOptions::new(HttpMethod::GET);
+    next.timeout = options.timeout;
+    next.initiator = options.initiator.clone();
+    next.headers = options
+        .headers
+        .iter()
+        .filter(|(name, _)| {
+            !name.eq_ignore_ascii_case("content-type")
+                && !name.eq_ignore_ascii_case("content-length")
+        })
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    next
+}
+
+/// Check that a redirect may be followed
+///
+/// # Arguments
+///
+/// * `redirected_from` - URLs that already redirected, in order
+/// * `current` - URL whose response is the redirect
+/// * `next` - URL the redirect points to
+/// * `max_redirects` - Redirects allowed for the whole chain
+///
+/// # Errors
+///
+/// Returns `Error::RedirectLoop` if `next` was already visited or
+/// following it would exceed `max_redirects`.
+pub(crate) fn check_redirect(
+    redirected_from: &[Url],
+    current: &Url,
+    next: &Url,
+    max_redirects: usize,
+) -> Result<()> {
+    let redirects = redirected_from.len() + 1;
+    if next == current || redirected_from.contains(next) || redirects > max_redirects {
+        return Err(Error::RedirectLoop {
+            url: next.to_string(),
+            redirects,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkStack;
+    use config_manager::{CacheBackend, NetworkConfig};
+    use message_bus::{MessageBus, MessageHandler};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    /// Requests seen by [`test_server`]: method, path and body
+    type RequestLog = Arc<Mutex<Vec<(String, String, Vec<u8>)>>>;
+
+    /// Serve canned responses by path, logging every request
+    ///
+    /// Each route maps a path to a raw status line plus headers; a 200
+    /// echoes the request body back.
+    fn test_server(routes: Vec<(&'static str, &'static str)>) -> (Url, RequestLog) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let log: RequestLog = Arc::new(Mutex::new(Vec::new()));
+        let server_log = log.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { return };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut parts = request_line.split_whitespace();
+                let method = parts.next().unwrap_or_default().to_string();
+                let path = parts.next().unwrap_or_default().to_string();
+
+                let mut length = 0;
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                    line.clear();
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+
+                let head = routes
+                    .iter()
+                    .find(|(route, _)| *route == path)
+                    .map(|(_, head)| *head)
+                    .unwrap_or("HTTP/1.1 404 Not Found");
+                let reply = if head.contains(" 200 ") {
+                    body.clone()
+                } else {
+                    Vec::new()
+                };
+                server_log.lock().unwrap().push((method, path, body));
+                let _ = write!(
+                    stream,
+                    "{}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    head,
+                    reply.len()
+                );
+                let _ = stream.write_all(&reply);
+            }
+        });
+
+        (base, log)
+    }
+
+    fn test_stack() -> NetworkStack {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut stack = NetworkStack::new(test_config(), bus.sender()).unwrap();
+        stack.initialize().unwrap();
+        stack
+    }
+
+    fn test_config() -> NetworkConfig {
+        NetworkConfig {
+            max_connections_per_host: 6,
+            timeout_seconds: 30,
+            enable_cookies: false,
+            enable_cache: true,
+            cache_size_mb: 10,
+            cache_backend: CacheBackend::Memory,
+            cookie_file: None,
+            proxy: None,
+            no_proxy: Vec::new(),
+        }
+    }
+
+    // ========================================
+    // Tests for follow_redirect
+    // ========================================
+
+    #[test]
+    fn test_follow_redirect_method_rules() {
+        let post = FetchOptions::post(b"a=1".to_vec(), "application/x-www-form-urlencoded")
+            .with_header("Referer", "https://example.com/");
+
+        let after_303 = follow_redirect(303, &post);
+        assert_eq!(after_303.method, HttpMethod::GET);
+        assert_eq!(after_303.body, None);
+        assert!(!after_303.headers.contains_key("Content-Type"));
+        assert!(after_303.headers.contains_key("Referer"));
+
+        assert_eq!(follow_redirect(302, &post).method, HttpMethod::GET);
+        assert_eq!(follow_redirect(307, &post), post);
+        assert_eq!(follow_redirect(308, &post), post);
+
+        let put = FetchOptions::new(HttpMethod::PUT);
+        assert_eq!(follow_redirect(302, &put).method, HttpMethod::PUT);
+        let head = FetchOptions::new(HttpMethod::HEAD);
+        assert_eq!(follow_redirect(303, &head).method, HttpMethod::HEAD);
+    }
+
+    #[test]
+    fn test_check_redirect_catches_repeats_and_limit() {
+        let url = |path: &str| {
+            Url::parse("https://example.com")
+                .unwrap()
+                .join(path)
+                .unwrap()
+        };
+        let visited = vec![url("/a"), url("/b")];
+
+        assert!(check_redirect(&visited, &url("/c"), &url("/d"), 3).is_ok());
+        assert!(matches!(
+            check_redirect(&visited, &url("/c"), &url("/a"), 20),
+            Err(Error::RedirectLoop { redirects: 3, .. })
+        ));
+        assert!(matches!(
+            check_redirect(&visited, &url("/c"), &url("/c"), 20),
+            Err(Error::RedirectLoop { .. })
+        ));
+        assert!(matches!(
+            check_redirect(&visited, &url("/c"), &url("/d"), 2),
+            Err(Error::RedirectLoop { url, redirects: 3 }) if url == "https://example.com/d"
+        ));
+    }
+
+    // ========================================
+    // Tests for fetch_with_options
+    // ========================================
+
+    #[tokio::test]
+    async fn test_post_body_delivered_intact() {
+        let (base, log) = test_server(vec![("/submit", "HTTP/1.1 200 OK")]);
+        let stack = test_stack();
+        let body = b"name=J%C3%B6rg&comment=a+b%26c".to_vec();
+
+        let response = stack
+            .fetch_with_options(
+                base.join("submit").unwrap(),
+                FetchOptions::post(body.clone(), "application/x-www-form-urlencoded"),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status, 200);
+        assert_eq!(response.method, HttpMethod::POST);
+        assert_eq!(response.body, body);
+        let log = log.lock().unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0], ("POST".to_string(), "/submit".to_string(), body));
+    }
+
+    #[tokio::test]
+    async fn test_post_303_redirect_becomes_get() {
+        let (base, log) = test_server(vec![
+            ("/submit", "HTTP/1.1 303 See Other\r\nLocation: /done"),
+            ("/done", "HTTP/1.1 200 OK"),
+        ]);
+        let stack = test_stack();
+
+        let response = stack
+            .fetch_with_options(
+                base.join("submit").unwrap(),
+                FetchOptions::post(b"a=1".to_vec(), "application/x-www-form-urlencoded"),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.url, base.join("done").unwrap());
+        assert_eq!(response.method, HttpMethod::GET);
+        assert_eq!(response.redirected_from, vec![base.join("submit").unwrap()]);
+        let log = log.lock().unwrap();
+        let methods: Vec<_> = log
+            .iter()
+            .map(|(m, p, _)| (m.as_str(), p.as_str()))
+            .collect();
+        assert_eq!(methods, vec![("POST", "/submit"), ("GET", "/done")]);
+        assert!(log[1].2.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_post_307_redirect_repeats_post() {
+        let (base, log) = test_server(vec![
+            ("/old", "HTTP/1.1 307 Temporary Redirect\r\nLocation: /new"),
+            ("/new", "HTTP/1.1 200 OK"),
+        ]);
+        let stack = test_stack();
+
+        let response = stack
+            .fetch_with_options(
+                base.join("old").unwrap(),
+                FetchOptions::post(b"a=1".to_vec(), "application/x-www-form-urlencoded"),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.method, HttpMethod::POST);
+        assert_eq!(response.body, b"a=1");
+        let log = log.lock().unwrap();
+        assert_eq!(
+            log[1],
+            ("POST".to_string(), "/new".to_string(), b"a=1".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_json_post_bypasses_cache_and_records_method() {
+        let (base, log) = test_server(vec![("/items", "HTTP/1.1 200 OK")]);
+        let stack = test_stack();
+        let url = base.join("items").unwrap();
+        let mut headers = HashMap::new();
+        headers.insert("cache-control".to_string(), "max-age=600".to_string());
+        stack
+            .cache()
+            .unwrap()
+            .put(url.clone(), b"[]".to_vec(), headers);
+        let json = br#"{"name":"milk","qty":2}"#.to_vec();
+
+        let response = stack
+            .fetch_with_options(
+                url.clone(),
+                FetchOptions::post(json.clone(), "application/json"),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.body, json);
+        assert_eq!(log.lock().unwrap().len(), 1);
+        assert!(stack.cache().unwrap().get(&url).is_none());
+        let timing = stack.get_timing_data().pop().unwrap();
+        assert!(!timing.from_cache);
+        assert_eq!(timing.details.method, "POST");
+        assert_eq!(timing.details.request_body_size, json.len());
+        assert_eq!(
+            timing.details.request_headers.get("Content-Type").unwrap(),
+            "application/json"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timeout_override() {
+        // Accepts connections but never answers
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/slow", listener.local_addr().unwrap())).unwrap();
+        thread::spawn(move || {
+            let _held: Vec<_> = listener.incoming().collect();
+        });
+        let stack = test_stack();
+
+        let result = stack
+            .fetch_with_options(
+                url,
+                FetchOptions::new(HttpMethod::DELETE).with_timeout(Duration::from_millis(200)),
+            )
+            .await;
+
+        assert!(matches!(result, Err(crate::Error::Timeout)));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_with_metadata_reports_redirects_and_cached_headers() {
+        let (base, log) = test_server(vec![
+            ("/latest", "HTTP/1.1 302 Found\r\nLocation: /report.pdf"),
+            (
+                "/report.pdf",
+                "HTTP/1.1 200 OK\r\nContent-Type: application/pdf\r\nCache-Control: max-age=60",
+            ),
+        ]);
+        let stack = test_stack();
+        let latest = base.join("latest").unwrap();
+        let pdf = base.join("report.pdf").unwrap();
+
+        let response = stack.fetch_with_metadata(latest.clone()).await.unwrap();
+        assert_eq!(response.url, pdf);
+        assert_eq!(response.redirected_from, vec![latest]);
+        assert_eq!(response.status, 200);
+        assert_eq!(response.header("Content-Type"), Some("application/pdf"));
+        assert!(!response.from_cache);
+
+        stack.fetch_with_metadata(pdf.clone()).await.unwrap();
+        let cached = stack.fetch_with_metadata(pdf.clone()).await.unwrap();
+        assert_eq!(log.lock().unwrap().len(), 3);
+        assert!(cached.from_cache);
+        assert_eq!(cached.url, pdf);
+        assert_eq!(cached.header("content-type"), Some("application/pdf"));
+
+        match cached.into_message(7) {
+            BrowserMessage::HttpResponse {
+                request_id,
+                headers,
+                final_url,
+                from_cache,
+                ..
+            } => {
+                assert_eq!(request_id, 7);
+                assert_eq!(headers.get("content-type").unwrap(), "application/pdf");
+                assert_eq!(final_url, pdf);
+                assert!(from_cache);
+            }
+            other => panic!("unexpected message {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redirect_loop_is_an_error() {
+        let (base, _) = test_server(vec![("/loop", "HTTP/1.1 302 Found\r\nLocation: /loop")]);
+        let stack = test_stack();
+
+        let result = stack
+            .fetch_with_options(base.join("loop").unwrap(), FetchOptions::default())
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::RedirectLoop { redirects: 1, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_redirect_limit_and_chain_in_timing() {
+        let (base, _) = test_server(vec![
+            ("/a", "HTTP/1.1 301 Moved Permanently\r\nLocation: /b"),
+            ("/b", "HTTP/1.1 302 Found\r\nLocation: /c"),
+            ("/c", "HTTP/1.1 200 OK\r\nCache-Control: no-store"),
+        ]);
+        let mut stack = test_stack();
+        let hops = vec![base.join("a").unwrap(), base.join("b").unwrap()];
+
+        stack.set_max_redirects(1);
+        let result = stack.fetch_with_metadata(hops[0].clone()).await;
+        assert!(matches!(
+            result,
+            Err(Error::RedirectLoop { redirects: 2, .. })
+        ));
+
+        stack.set_max_redirects(2);
+        let response = stack.fetch_with_metadata(hops[0].clone()).await.unwrap();
+        assert_eq!(response.url, base.join("c").unwrap());
+        assert_eq!(response.redirected_from, hops);
+
+        let timing = stack.get_timing_data().pop().unwrap();
+        assert_eq!(timing.url, response.url.as_str());
+        assert_eq!(
+            timing.redirected_from,
+            hops.iter().map(Url::to_string).collect::<Vec<_>>()
+        );
+
+        // Streaming fetches follow the same chain
+        let (head, mut stream) = stack
+            .fetch_streaming(hops[0].clone(), crate::FetchContext::document())
+            .await
+            .unwrap();
+        assert_eq!(head.url, response.url);
+        assert_eq!(head.redirected_from, hops);
+        while stream.next_chunk().await.is_some() {}
+        let timing = stack.get_timing_data().pop().unwrap();
+        assert_eq!(timing.redirected_from.len(), 2);
+    }
+
+    /// Keeps the `HttpResponse` messages sent on the bus
+    struct Responses(Arc<Mutex<Vec<BrowserMessage>>>);
+
+    impl MessageHandler for Responses {
+        fn handle(&self, message: BrowserMessage) -> message_bus::Result<()> {
+            if matches!(message, BrowserMessage::HttpResponse { .. }) {
+                self.0.lock().unwrap().push(message);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_http_request_message_posts_body() {
+        let (base, log) = test_server(vec![("/api", "HTTP/1.1 200 OK")]);
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let responses = Arc::new(Mutex::new(Vec::new()));
+        bus.register_handler(Box::new(Responses(responses.clone())));
+        let mut stack = NetworkStack::new(test_config(), bus.sender()).unwrap();
+        stack.initialize().unwrap();
+        bus.register_async_handler(Box::new(stack)).unwrap();
+
+        let body = br#"{"name":"value"}"#.to_vec();
+        bus.sender()
+            .send(BrowserMessage::HttpRequest {
+                request_id: 42,
+                url: base.join("api").unwrap(),
+                method: "POST".to_string(),
+                headers: HashMap::from([(
+                    "Content-Type".to_string(),
+                    "application/json".to_string(),
+                )]),
+                body: Some(body.clone()),
+                initiator: None,
+            })
+            .unwrap();
+        bus.flush().unwrap();
+        bus.shutdown().unwrap();
+
+        let responses = responses.lock().unwrap();
+        match responses.as_slice() {
+            [BrowserMessage::HttpResponse {
+                request_id: 42,
+                status: 200,
+                body: echoed,
+                ..
+            }] => assert_eq!(echoed, &body),
+            other => panic!("expected one HttpResponse, got {:?}", other),
+        }
+        assert_eq!(
+            log.lock().unwrap().as_slice(),
+            [("POST".to_string(), "/api".to_string(), body)]
+        );
+    }
+}
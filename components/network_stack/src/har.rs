@@ -0,0 +1,908 @@
+//! HAR (HTTP Archive) export
+//!
+//! [`HarExporter`] turns recorded [`ResourceTiming`] entries into a HAR 1.2
+//! document that browser devtools and WebPageTest tooling can open.
+//!
+//! The stack measures the wait for a connection slot, host name
+//! resolution, time to first byte and total duration, so the timing
+//! breakdown has `blocked` = the wait for a slot, `dns` = the lookup time
+//! (-1 for cache hits), `send` = 0, `wait` = the rest of the time to first
+//! byte and `receive` = the rest; `connect` and `ssl` are -1 (not
+//! available). An entry's `time` is the sum of the available phases.
+//!
+//! Credentials (`Authorization`, `Cookie`, `Set-Cookie`, ...) are redacted
+//! unless [`HarOptions::include_sensitive_headers`] is set. Response bodies
+//! are only embedded when [`HarOptions::embed_bodies`] is set, and only for
+//! text content, capped at [`MAX_EMBEDDED_BODY_BYTES`].
+
+use crate::errors::{Error, Result};
+use crate::types::ResourceTiming;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use url::Url;
+
+/// HAR format version produced
+pub const HAR_VERSION: &str = "1.2";
+
+/// Largest response body, in bytes, captured for embedding
+pub const MAX_EMBEDDED_BODY_BYTES: usize = 256 * 1024;
+
+/// Value that replaces redacted header values
+pub const REDACTED: &str = "[redacted]";
+
+/// Headers that carry credentials (lower case)
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+/// What a HAR export includes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HarOptions {
+    /// Capture and embed text response bodies
+    pub embed_bodies: bool,
+    /// Keep credential headers and cookies instead of redacting them
+    pub include_sensitive_headers: bool,
+}
+
+/// A HAR document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Har {
+    /// The archive
+    pub log: HarLog,
+}
+
+/// Root of a HAR document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HarLog {
+    /// Format version ("1.2")
+    pub version: String,
+    /// Application that wrote the archive
+    pub creator: HarCreator,
+    /// One entry per request, in start order
+    pub entries: Vec<HarEntry>,
+}
+
+/// Application that wrote the archive
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HarCreator {
+    /// Application name
+    pub name: String,
+    /// Application version
+    pub version: String,
+}
+
+/// A request and its response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarEntry {
+    /// Start of the request (ISO 8601)
+    pub started_date_time: String,
+    /// Total time in milliseconds (sum of the available phases)
+    pub time: f64,
+    /// The request
+    pub request: HarRequest,
+    /// The response
+    pub response: HarResponse,
+    /// Cache state (not tracked; always empty)
+    pub cache: HarCache,
+    /// Timing breakdown
+    pub timings: HarTimings,
+    /// "memory" when served from the HTTP cache, as written by Chrome
+    #[serde(
+        rename = "_fromCache",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub from_cache: Option<String>,
+    /// Trace of the user action that issued the request
+    #[serde(rename = "_traceId", default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<u64>,
+    /// "net::ERR_ABORTED" for a cancelled request, as written by Chrome
+    #[serde(rename = "_error", default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Request part of an entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarRequest {
+    /// HTTP method
+    pub method: String,
+    /// Absolute URL
+    pub url: String,
+    /// HTTP version
+    pub http_version: String,
+    /// Cookies sent
+    pub cookies: Vec<HarNameValue>,
+    /// Headers sent by the browser
+    pub headers: Vec<HarNameValue>,
+    /// Query string parameters
+    pub query_string: Vec<HarNameValue>,
+    /// Header size in bytes (-1: not available)
+    pub headers_size: i64,
+    /// Body size in bytes
+    pub body_size: i64,
+}
+
+/// Response part of an entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarResponse {
+    /// Status code
+    pub status: u16,
+    /// Reason phrase
+    pub status_text: String,
+    /// HTTP version
+    pub http_version: String,
+    /// Cookies set
+    pub cookies: Vec<HarNameValue>,
+    /// Response headers
+    pub headers: Vec<HarNameValue>,
+    /// Response body
+    pub content: HarContent,
+    /// Target of a redirect response
+    #[serde(rename = "redirectURL")]
+    pub redirect_url: String,
+    /// Header size in bytes (-1: not available)
+    pub headers_size: i64,
+    /// Bytes received, before decoding (0 when served from cache)
+    pub body_size: i64,
+}
+
+/// Response body
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarContent {
+    /// Decoded body size in bytes
+    pub size: i64,
+    /// MIME type from `Content-Type`
+    pub mime_type: String,
+    /// Body text, if embedded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Note on the embedded text (e.g. truncation)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Bytes saved by the response's `Content-Encoding`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<i64>,
+}
+
+/// Cache state of an entry
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HarCache {}
+
+/// Timing breakdown in milliseconds; -1 means not available
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HarTimings {
+    /// Queued before sending
+    pub blocked: f64,
+    /// DNS resolution
+    pub dns: f64,
+    /// Connection setup (includes `ssl`)
+    pub connect: f64,
+    /// Sending the request
+    pub send: f64,
+    /// Waiting for the first byte
+    pub wait: f64,
+    /// Reading the response
+    pub receive: f64,
+    /// TLS handshake
+    pub ssl: f64,
+}
+
+impl HarTimings {
+    /// Break down a recorded timing
+    fn from_timing(timing: &ResourceTiming) -> Self {
+        let total = timing
+            .end_time
+            .saturating_sub(timing.start_time)
+            .as_secs_f64()
+            * 1000.0;
+        let blocked = (timing.queued_ms as f64).min(total);
+        let dns = if timing.from_cache {
+            0.0
+        } else {
+            (timing.dns_ms as f64).min(total - blocked)
+        };
+        let ttfb = (timing.ttfb_ms as f64).clamp(blocked + dns, total);
+        Self {
+            blocked,
+            dns: if timing.from_cache { -1.0 } else { dns },
+            connect: -1.0,
+            send: 0.0,
+            wait: ttfb - blocked - dns,
+            receive: total - ttfb,
+            ssl: -1.0,
+        }
+    }
+
+    /// Sum of the available phases, as required for the entry's `time`
+    ///
+    /// `ssl` is not added since it is part of `connect`.
+    pub fn total(&self) -> f64 {
+        [
+            self.blocked,
+            self.dns,
+            self.connect,
+            self.send,
+            self.wait,
+            self.receive,
+        ]
+        .iter()
+        .filter(|phase| **phase >= 0.0)
+        .sum()
+    }
+}
+
+/// A name/value pair (header, query parameter or cookie)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HarNameValue {
+    /// Name
+    pub name: String,
+    /// Value
+    pub value: String,
+}
+
+impl HarNameValue {
+    fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+}
+
+/// Converts recorded requests to HAR
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HarExporter {
+    options: HarOptions,
+}
+
+impl HarExporter {
+    /// Create an exporter
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Body embedding and header redaction settings
+    pub fn new(options: HarOptions) -> Self {
+        Self { options }
+    }
+
+    /// Build a HAR document
+    ///
+    /// # Arguments
+    ///
+    /// * `timings` - Recorded requests, in any order
+    pub fn build(&self, timings: &[ResourceTiming]) -> Har {
+        let mut ordered: Vec<&ResourceTiming> = timings.iter().collect();
+        ordered.sort_by_key(|timing| timing.details.started_unix_ms);
+
+        Har {
+            log: HarLog {
+                version: HAR_VERSION.to_string(),
+                creator: HarCreator {
+                    name: "FrankenBrowser".to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                },
+                entries: ordered.into_iter().map(|t| self.entry(t)).collect(),
+            },
+        }
+    }
+
+    /// Build a HAR document and serialize it as JSON
+    ///
+    /// # Arguments
+    ///
+    /// * `timings` - Recorded requests, in any order
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Other` if serialization fails.
+    pub fn export(&self, timings: &[ResourceTiming]) -> Result<String> {
+        serde_json::to_string_pretty(&self.build(timings)).map_err(|e| Error::Other(e.into()))
+    }
+
+    fn entry(&self, timing: &ResourceTiming) -> HarEntry {
+        let details = &timing.details;
+        let timings = HarTimings::from_timing(timing);
+        let method = if details.method.is_empty() {
+            "GET".to_string()
+        } else {
+            details.method.clone()
+        };
+        let query_string = Url::parse(&timing.url)
+            .map(|url| {
+                url.query_pairs()
+                    .map(|(name, value)| HarNameValue::new(name, value))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let text = details
+            .response_text
+            .clone()
+            .filter(|_| self.options.embed_bodies);
+        let comment = text
+            .as_ref()
+            .filter(|text| text.len() < timing.size_bytes)
+            .map(|text| format!("Body truncated to {} bytes", text.len()));
+
+        HarEntry {
+            started_date_time: format_timestamp(details.started_unix_ms),
+            time: timings.total(),
+            request: HarRequest {
+                method,
+                url: timing.url.clone(),
+                http_version: details.http_version.clone(),
+                cookies: self.cookies(&details.request_headers, "cookie"),
+                headers: self.headers(&details.request_headers),
+                query_string,
+                headers_size: -1,
+                body_size: details.request_body_size as i64,
+            },
+            response: HarResponse {
+                status: details.status,
+                status_text: reqwest::StatusCode::from_u16(details.status)
+                    .ok()
+                    .and_then(|status| status.canonical_reason())
+                    .unwrap_or_default()
+                    .to_string(),
+                http_version: details.http_version.clone(),
+                cookies: self.cookies(&details.response_headers, "set-cookie"),
+                headers: self.headers(&details.response_headers),
+                content: HarContent {
+                    size: timing.size_bytes as i64,
+                    mime_type: header(&details.response_headers, "content-type")
+                        .unwrap_or("x-unknown")
+                        .to_string(),
+                    text,
+                    comment,
+                    compression: (timing.encoded_size < timing.decoded_size)
+                        .then(|| (timing.decoded_size - timing.encoded_size) as i64),
+                },
+                redirect_url: header(&details.response_headers, "location")
+                    .unwrap_or_default()
+                    .to_string(),
+                headers_size: -1,
+                body_size: if timing.from_cache {
+                    0
+                } else {
+                    timing.encoded_size as i64
+                },
+            },
+            cache: HarCache::default(),
+            timings,
+            from_cache: timing.from_cache.then(|| "memory".to_string()),
+            trace_id: details.trace_id,
+            error: timing.cancelled.then(|| "net::ERR_ABORTED".to_string()),
+        }
+    }
+
+    /// Headers sorted by name, with credentials redacted unless included
+    fn headers(&self, headers: &HashMap<String, String>) -> Vec<HarNameValue> {
+        let mut list: Vec<HarNameValue> = headers
+            .iter()
+            .map(|(name, value)| {
+                if is_sensitive(name) && !self.options.include_sensitive_headers {
+                    HarNameValue::new(name.as_str(), REDACTED)
+                } else {
+                    HarNameValue::new(name.as_str(), value.as_str())
+                }
+            })
+            .collect();
+        list.sort_by(|a, b| a.name.cmp(&b.name));
+        list
+    }
+
+    /// Cookies from a `Cookie` or `Set-Cookie` header; none when redacting
+    fn cookies(&self, headers: &HashMap<String, String>, name: &str) -> Vec<HarNameValue> {
+        if !self.options.include_sensitive_headers {
+            return Vec::new();
+        }
+        let Some(value) = header(headers, name) else {
+            return Vec::new();
+        };
+        // Set-Cookie carries one cookie followed by attributes
+        let pairs: Vec<&str> = if name == "set-cookie" {
+            value.split(';').take(1).collect()
+        } else {
+            value.split(';').collect()
+        };
+        pairs
+            .into_iter()
+            .filter_map(|pair| pair.trim().split_once('='))
+            .map(|(name, value)| HarNameValue::new(name, value))
+            .collect()
+    }
+}
+
+/// Whether a header carries credentials
+fn is_sensitive(name: &str) -> bool {
+    SENSITIVE_HEADERS
+        .iter()
+        .any(|sensitive| name.eq_ignore_ascii_case(sensitive))
+}
+
+/// Look up a header case-insensitively
+fn header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Whether a `Content-Type` denotes text
+fn is_text_mime(content_type: &str) -> bool {
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence.as_str(),
+            "application/json"
+                | "application/javascript"
+                | "application/xml"
+                | "application/x-www-form-urlencoded"
+        )
+}
+
+/// Capture a text response body for embedding
+///
+/// # Returns
+///
+/// The body as text, cut to [`MAX_EMBEDDED_BODY_BYTES`] on a character
+/// boundary, or `None` if it is not text.
+pub(crate) fn capture_text(headers: &HashMap<String, String>, body: &[u8]) -> Option<String> {
+    if !header(headers, "content-type").is_some_and(is_text_mime) {
+        return None;
+    }
+    let capped = &body[..body.len().min(MAX_EMBEDDED_BODY_BYTES)];
+    match std::str::from_utf8(capped) {
+        Ok(text) => Some(text.to_string()),
+        // The cap fell inside a multi-byte character
+        Err(e) if e.error_len().is_none() && capped.len() < body.len() => {
+            std::str::from_utf8(&capped[..e.valid_up_to()])
+                .ok()
+                .map(str::to_string)
+        }
+        Err(_) => None,
+    }
+}
+
+/// Format a Unix timestamp as ISO 8601 in UTC (e.g. `2023-11-14T22:13:20.123Z`)
+fn format_timestamp(unix_ms: u64) -> String {
+    let secs = unix_ms / 1000;
+    let (hour, minute, second) = (secs % 86_400 / 3600, secs % 3600 / 60, secs % 60);
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+        unix_ms % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::RequestDetails;
+    use serde_json::Value;
+    use std::time::Duration;
+
+    fn header_map(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn timing(url: &str, started_unix_ms: u64, duration_ms: u64, ttfb_ms: u64) -> ResourceTiming {
+        let body = "<html>hello</html>";
+        ResourceTiming {
+            url: url.to_string(),
+            start_time: Duration::from_secs(0),
+            end_time: Duration::from_millis(duration_ms),
+            duration_ms,
+            ttfb_ms,
+            queued_ms: 0,
+            dns_ms: 0,
+            connect_ms: 0,
+            tls_ms: 0,
+            download_ms: duration_ms.saturating_sub(ttfb_ms),
+            size_bytes: body.len(),
+            encoded_size: body.len(),
+            decoded_size: body.len(),
+            from_cache: false,
+            revalidated: false,
+            cancelled: false,
+            redirected_from: Vec::new(),
+            details: RequestDetails {
+                tab_id: Some(1),
+                started_unix_ms,
+                method: "GET".to_string(),
+                request_headers: header_map(&[
+                    ("authorization", "Bearer secret"),
+                    ("cookie", "session=abc; theme=dark"),
+                    ("dnt", "1"),
+                ]),
+                request_body_size: 0,
+                status: 200,
+                http_version: "HTTP/1.1".to_string(),
+                response_headers: header_map(&[
+                    ("content-type", "text/html; charset=utf-8"),
+                    ("set-cookie", "session=abc; HttpOnly"),
+                ]),
+                response_text: Some(body.to_string()),
+                trace_id: None,
+            },
+        }
+    }
+
+    /// Parse an exported HAR document back into its types
+    fn load_har(json: &str) -> Har {
+        serde_json::from_str(json).expect("valid HAR")
+    }
+
+    /// Check the fields HAR 1.2 requires, returning the first one missing
+    fn missing_required_field(har: &Value) -> Option<String> {
+        let require = |value: &Value, path: &str, fields: &[&str]| -> Option<String> {
+            fields
+                .iter()
+                .find(|field| value.get(**field).is_none())
+                .map(|field| format!("{}.{}", path, field))
+        };
+
+        let log = har.get("log")?;
+        let missing = require(log, "log", &["version", "creator", "entries"])
+            .or_else(|| require(&log["creator"], "log.creator", &["name", "version"]));
+        if missing.is_some() {
+            return missing;
+        }
+        for entry in log["entries"].as_array()? {
+            let missing = require(
+                entry,
+                "entry",
+                &[
+                    "startedDateTime",
+                    "time",
+                    "request",
+                    "response",
+                    "cache",
+                    "timings",
+                ],
+            )
+            .or_else(|| {
+                require(
+                    &entry["request"],
+                    "entry.request",
+                    &[
+                        "method",
+                        "url",
+                        "httpVersion",
+                        "cookies",
+                        "headers",
+                        "queryString",
+                        "headersSize",
+                        "bodySize",
+                    ],
+                )
+            })
+            .or_else(|| {
+                require(
+                    &entry["response"],
+                    "entry.response",
+                    &[
+                        "status",
+                        "statusText",
+                        "httpVersion",
+                        "cookies",
+                        "headers",
+                        "content",
+                        "redirectURL",
+                        "headersSize",
+                        "bodySize",
+                    ],
+                )
+            })
+            .or_else(|| {
+                require(
+                    &entry["response"]["content"],
+                    "entry.response.content",
+                    &["size", "mimeType"],
+                )
+            })
+            .or_else(|| {
+                require(
+                    &entry["timings"],
+                    "entry.timings",
+                    &["send", "wait", "receive"],
+                )
+            });
+            if missing.is_some() {
+                return missing;
+            }
+        }
+        None
+    }
+
+    // ========================================
+    // Tests for HAR structure
+    // ========================================
+
+    #[test]
+    fn test_export_has_required_fields() {
+        let json = HarExporter::default()
+            .export(&[
+                timing("https://a.test/page?q=1&lang=en", 2_000, 120, 40),
+                timing("https://a.test/style.css", 1_000, 30, 10),
+            ])
+            .unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(missing_required_field(&value), None);
+        assert_eq!(value["log"]["version"], "1.2");
+        assert_eq!(value["log"]["creator"]["name"], "FrankenBrowser");
+
+        let entries = value["log"]["entries"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        // Entries are in start order
+        assert_eq!(entries[0]["request"]["url"], "https://a.test/style.css");
+        assert_eq!(entries[1]["startedDateTime"], "1970-01-01T00:00:02.000Z");
+        assert_eq!(entries[1]["request"]["queryString"][1]["name"], "lang");
+        assert_eq!(entries[1]["response"]["statusText"], "OK");
+        assert_eq!(
+            entries[1]["response"]["content"]["mimeType"],
+            "text/html; charset=utf-8"
+        );
+    }
+
+    #[test]
+    fn test_missing_field_detected() {
+        let mut value: Value = serde_json::from_str(
+            &HarExporter::default()
+                .export(&[timing("https://a.test/", 0, 10, 5)])
+                .unwrap(),
+        )
+        .unwrap();
+        value["log"]["entries"][0]["response"]
+            .as_object_mut()
+            .unwrap()
+            .remove("redirectURL");
+
+        assert_eq!(
+            missing_required_field(&value),
+            Some("entry.response.redirectURL".to_string())
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let exporter = HarExporter::new(HarOptions {
+            embed_bodies: true,
+            include_sensitive_headers: true,
+        });
+        let mut cached = timing("https://a.test/cached.js", 5, 2, 2);
+        cached.from_cache = true;
+        cached.details.trace_id = Some(77);
+        let mut aborted = timing("https://a.test/slow", 9, 3, 0);
+        aborted.cancelled = true;
+        let timings = [timing("https://a.test/", 0, 50, 20), cached, aborted];
+
+        let json = exporter.export(&timings).unwrap();
+        assert!(json.contains(r#""_traceId": 77"#));
+        assert!(json.contains(r#""_error": "net::ERR_ABORTED""#));
+        let har = load_har(&json);
+        assert_eq!(har, exporter.build(&timings));
+        assert_eq!(har.log.entries[1].from_cache.as_deref(), Some("memory"));
+        assert_eq!(har.log.entries[0].trace_id, None);
+        assert_eq!(har.log.entries[1].trace_id, Some(77));
+        assert_eq!(har.log.entries[1].response.body_size, 0);
+        assert_eq!(har.log.entries[0].error, None);
+        assert_eq!(
+            har.log.entries[0].request.cookies,
+            vec![
+                HarNameValue::new("session", "abc"),
+                HarNameValue::new("theme", "dark")
+            ]
+        );
+        assert_eq!(
+            har.log.entries[0].response.cookies,
+            vec![HarNameValue::new("session", "abc")]
+        );
+    }
+
+    // ========================================
+    // Tests for timings
+    // ========================================
+
+    #[test]
+    fn test_entry_time_is_sum_of_phases() {
+        let mut queued = timing("https://a.test/queued", 3, 120, 50);
+        queued.queued_ms = 30;
+        queued.dns_ms = 15;
+        let mut cached = timing("https://a.test/cached", 4, 3, 3);
+        cached.from_cache = true;
+        let har = HarExporter::default().build(&[
+            timing("https://a.test/", 0, 120, 40),
+            timing("https://a.test/fast", 1, 7, 7),
+            // Legacy record whose ttfb exceeds the total
+            timing("https://a.test/odd", 2, 5, 9),
+            queued,
+            cached,
+        ]);
+
+        for entry in &har.log.entries {
+            let timings = &entry.timings;
+            let phases = timings.blocked
+                + timings.dns.max(0.0)
+                + timings.send
+                + timings.wait
+                + timings.receive;
+            assert!((entry.time - phases).abs() < 1e-6, "{:?}", entry);
+            assert!(timings.receive >= 0.0);
+            assert!(timings.wait >= 0.0);
+            assert_eq!(timings.connect, -1.0);
+        }
+        assert_eq!(har.log.entries[0].time, 120.0);
+        assert_eq!(har.log.entries[0].timings.wait, 40.0);
+        assert_eq!(har.log.entries[0].timings.receive, 80.0);
+        assert_eq!(har.log.entries[0].timings.blocked, 0.0);
+
+        let queued = &har.log.entries[3].timings;
+        assert_eq!(
+            (queued.blocked, queued.dns, queued.wait, queued.receive),
+            (30.0, 15.0, 5.0, 70.0)
+        );
+        assert_eq!(har.log.entries[4].timings.dns, -1.0);
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            format_timestamp(1_700_000_000_123),
+            "2023-11-14T22:13:20.123Z"
+        );
+        assert_eq!(
+            format_timestamp(951_782_400_000),
+            "2000-02-29T00:00:00.000Z"
+        );
+    }
+
+    // ========================================
+    // Tests for redaction
+    // ========================================
+
+    #[test]
+    fn test_sensitive_headers_redacted_by_default() {
+        let har = HarExporter::default().build(&[timing("https://a.test/", 0, 10, 5)]);
+        let entry = &har.log.entries[0];
+
+        assert_eq!(
+            entry.request.headers,
+            vec![
+                HarNameValue::new("authorization", REDACTED),
+                HarNameValue::new("cookie", REDACTED),
+                HarNameValue::new("dnt", "1"),
+            ]
+        );
+        assert!(entry.request.cookies.is_empty());
+        assert!(entry.response.cookies.is_empty());
+        let set_cookie = entry
+            .response
+            .headers
+            .iter()
+            .find(|h| h.name == "set-cookie")
+            .unwrap();
+        assert_eq!(set_cookie.value, REDACTED);
+    }
+
+    #[test]
+    fn test_sensitive_headers_included_on_request() {
+        let exporter = HarExporter::new(HarOptions {
+            include_sensitive_headers: true,
+            ..HarOptions::default()
+        });
+        let har = exporter.build(&[timing("https://a.test/", 0, 10, 5)]);
+
+        assert_eq!(
+            har.log.entries[0].request.headers[0],
+            HarNameValue::new("authorization", "Bearer secret")
+        );
+    }
+
+    // ========================================
+    // Tests for body embedding
+    // ========================================
+
+    #[test]
+    fn test_bodies_omitted_unless_enabled() {
+        let entry = &HarExporter::default()
+            .build(&[timing("https://a.test/", 0, 10, 5)])
+            .log
+            .entries[0];
+        assert_eq!(entry.response.content.text, None);
+
+        let exporter = HarExporter::new(HarOptions {
+            embed_bodies: true,
+            ..HarOptions::default()
+        });
+        let entry = &exporter
+            .build(&[timing("https://a.test/", 0, 10, 5)])
+            .log
+            .entries[0];
+        assert_eq!(
+            entry.response.content.text.as_deref(),
+            Some("<html>hello</html>")
+        );
+        assert_eq!(entry.response.content.comment, None);
+    }
+
+    #[test]
+    fn test_capture_text_cap() {
+        let text_headers = header_map(&[("Content-Type", "application/json")]);
+        let body = vec![b'a'; MAX_EMBEDDED_BODY_BYTES + 10];
+        let captured = capture_text(&text_headers, &body).unwrap();
+        assert_eq!(captured.len(), MAX_EMBEDDED_BODY_BYTES);
+
+        // The cap never splits a character
+        let mut body = vec![b'a'; MAX_EMBEDDED_BODY_BYTES - 1];
+        body.extend_from_slice("é and more".as_bytes());
+        let captured = capture_text(&text_headers, &body).unwrap();
+        assert_eq!(captured.len(), MAX_EMBEDDED_BODY_BYTES - 1);
+
+        let mut timing = timing("https://a.test/big.json", 0, 10, 5);
+        timing.size_bytes = body.len();
+        timing.details.response_text = Some(captured);
+        let exporter = HarExporter::new(HarOptions {
+            embed_bodies: true,
+            ..HarOptions::default()
+        });
+        let content = &exporter.build(&[timing]).log.entries[0].response.content;
+        assert_eq!(
+            content.comment,
+            Some(format!(
+                "Body truncated to {} bytes",
+                MAX_EMBEDDED_BODY_BYTES - 1
+            ))
+        );
+    }
+
+    #[test]
+    fn test_capture_text_only() {
+        let png = header_map(&[("content-type", "image/png")]);
+        assert_eq!(capture_text(&png, b"\x89PNG"), None);
+        assert_eq!(capture_text(&HashMap::new(), b"plain"), None);
+
+        let html = header_map(&[("content-type", "text/html")]);
+        assert_eq!(capture_text(&html, b"\xff\xfe"), None);
+        assert_eq!(capture_text(&html, b"<p>").as_deref(), Some("<p>"));
+
+        let svg = header_map(&[("content-type", "image/svg+xml")]);
+        assert!(capture_text(&svg, b"<svg/>").is_some());
+    }
+}
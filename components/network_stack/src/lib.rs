@@ -16,21 +16,87 @@
 //!
 //! # Features
 //!
-//! - **HTTP Client**: GET, POST, PUT, DELETE requests with timeout and compression support
+//! - **HTTP Client**: GET, POST, PUT, DELETE requests with timeout support
+//! - **Compression**: gzip, deflate and brotli bodies decoded by the stack
+//!   before they are returned or cached, with the wire and decoded sizes
+//!   recorded as `ResourceTiming::encoded_size` and `decoded_size`
 //! - **HTTP Caching**: RFC 7234 compliant caching with LRU eviction
 //!   - In-memory cache with configurable size limits
-//!   - Optional disk-based cache using SQLite (planned)
+//!   - Optional disk cache in SQLite (`network.cache_backend = "disk"`)
+//!     that keeps responses across restarts
 //!   - Cache-Control header parsing (max-age, no-cache, no-store, must-revalidate)
-//!   - ETag and Last-Modified support for conditional requests (planned)
+//!   - Stale entries revalidated with If-None-Match / If-Modified-Since;
+//!     a 304 Not Modified refreshes the entry and is served from cache
 //!   - Automatic cache invalidation on POST/PUT/DELETE requests
-//! - **Cookie Management**: Automatic cookie store
-//! - **Performance Tracking**: Resource timing data collection
+//!   - Entry listing, lookup explanations and per-URL/per-host purging
+//!   - Entries partitioned by top-level site (`privacy.partition_cache`),
+//!     as are DNS answers and favicons
+//!   - Offline mode (`BrowserMessage::SetOffline`) answering only from the
+//!     cache, stale entries included, and failing with `Error::Offline`
+//! - **Certificates**: Chains presented by TLS servers kept per host for
+//!   the session, parsed for the `about:certificate` page
+//! - **Cookie Management**: Cookies set by responses are sent back per
+//!   RFC 6265 (Domain, Path, Expires/Max-Age, Secure, HttpOnly), listed
+//!   through `cookies()`, and kept in SQLite across restarts when
+//!   `network.cookie_file` is set; with
+//!   `privacy.block_third_party_cookies`, requests made for another
+//!   site's document (by scheme and registrable domain) carry and keep none
+//! - **Connection Limits**: At most `network.max_connections_per_host`
+//!   requests in flight per host; the rest queue, and the wait is recorded
+//!   as `ResourceTiming::queued_ms`
+//! - **DNS**: Lookups cached for [`DNS_CACHE_TTL`]; hosts of links in view
+//!   and `dns-prefetch` hints resolved ahead of a click, with per-page caps,
+//!   an off switch per page and no lookups for blocked hosts
+//! - **Content Settings**: Per-site image blocking via `ContentSettingsInterceptor`
+//! - **Blocked Content**: Images, media, frames and embeds blocked for a
+//!   tab are listed for click-to-load placeholders; a click allows that URL
+//!   in the tab for the session
+//! - **Mixed Content**: http subresources of https pages are upgraded to
+//!   https, and blocked (active) or flagged (passive) if that fails
+//! - **Proxies**: All traffic through an `http://`, `https://` or
+//!   `socks5://` proxy (`network.proxy`), except to hosts and domain
+//!   suffixes in `network.no_proxy`
+//! - **Custom Headers**: Per-host request headers from `network.custom_headers`
+//!   via `CustomHeaderInterceptor`
+//! - **Identity**: The `User-Agent` from `network.user_agent` and the
+//!   headers in `network.extra_headers` on every request that does not set
+//!   them itself
+//! - **Privacy Headers**: `DNT: 1` and `Sec-GPC: 1` on every request and
+//!   WebSocket handshake while `privacy.do_not_track` is on
+//! - **Response Metadata**: `fetch_with_metadata` returns the status,
+//!   headers, final URL and redirect chain with the body
+//! - **Streaming**: `fetch_streaming` hands the body over chunk by chunk,
+//!   through `ByteStream::next_chunk` or as a `futures` `Stream`
+//! - **Downloads**: `start_download` saves a response to disk in the
+//!   background with progress messages on the bus, named after its
+//!   `Content-Disposition`, and pauses and resumes with range requests;
+//!   `start_tab_download` ties a download to a tab, so it pauses while the
+//!   tab is suspended (`TabSuspendHandler`)
+//! - **Cancellation**: `fetch_cancellable` and `cancellable` abort a fetch
+//!   through a `FetchHandle`, failing it with `Error::Cancelled`
+//! - **Request Bodies**: `fetch_with_options` and `send` send POST bodies
+//!   and apply the 303/307 redirect method rules themselves, with an
+//!   optional per-request timeout; registered as an async bus handler, the
+//!   stack answers `HttpRequest` messages the same way
+//! - **Redirects**: Every fetch follows redirects itself, up to
+//!   `network.max_redirects`, failing with `Error::RedirectLoop` when a
+//!   chain revisits a URL; resource timing records the chain
+//! - **Performance Tracking**: Resource timing data collection, with DNS,
+//!   time to first byte and download phases summarized per host by
+//!   `timing_summary`
+//! - **Bandwidth Accounting**: Bytes sent and received per tab and per
+//!   session, with an optional budget that pauses downloads and prefetching
+//! - **WebSockets**: `websocket_connect` opens sockets through the
+//!   interceptor chain and the configured proxy, within the per-host
+//!   connection limit, counting frame bytes per tab
+//! - **HAR Export**: Recorded requests exported as HTTP Archive 1.2, per tab
+//!   or for the whole session, with credentials redacted by default
 //!
 //! # Usage
 //!
 //! ```no_run
 //! use network_stack::{NetworkStack, Result};
-//! use config_manager::NetworkConfig;
+//! use config_manager::{CacheBackend, NetworkConfig};
 //! use message_bus::MessageBus;
 //! use url::Url;
 //!
@@ -47,6 +113,10 @@
 //!     enable_cookies: true,
 //!     enable_cache: true,
 //!     cache_size_mb: 500,  // 500MB cache
+//!     cache_backend: CacheBackend::Memory,
+//!     cookie_file: None,
+//!     proxy: None,
+//!     no_proxy: Vec::new(),
 //! };
 //!
 //! // Create and initialize network stack
@@ -73,28 +143,81 @@
 //! # }
 //! ```
 
+pub mod bandwidth;
+pub mod blocked_content;
 pub mod cache;
+pub mod cancel;
+pub mod certificate;
+mod connections;
+pub mod content_encoding;
+pub mod cookies;
 pub mod csp;
+pub mod dns;
+pub mod downloads;
 pub mod errors;
+pub mod favicon;
+pub mod fetch_options;
+pub mod har;
+pub mod mixed_content;
+pub mod partition;
+pub mod proxy;
 pub mod request_handler;
+pub mod streaming;
+pub mod timing;
+mod tls;
 pub mod types;
+pub mod websocket;
 
 // Re-export main types for convenience
-pub use cache::{CacheControl, CacheEntry, HttpCache};
+pub use bandwidth::{
+    estimate_request_bytes, estimate_response_head_bytes, BandwidthMeter, TabBandwidth,
+};
+pub use blocked_content::{BlockedContent, BlockedResource};
+pub use cache::{
+    CacheControl, CacheEntry, CacheEntrySummary, CacheStats, DiskCache, HttpCache,
+    LookupExplanation, DISK_CACHE_FILE,
+};
+pub use cancel::FetchHandle;
+pub use certificate::{
+    sha256_fingerprint, CertChainInfo, CertValidation, CertificateInfo, EXPIRY_WARNING,
+};
+pub use content_encoding::{decode_body, BodyDecoder, ACCEPT_ENCODING};
+pub use cookies::{is_third_party, parse_cookie_date, Cookie, CookieStore, COOKIE_DB_FILE};
 pub use csp::{
     ContentSecurityPolicy, CspDirective, CspInterceptor, CspManager, CspSource, ResourceType,
 };
+pub use dns::{
+    prefetch_host, MockResolver, Resolve, Resolver, ResolverStats, Resolving, SystemResolver,
+    DNS_CACHE_TTL, DNS_PREFETCH_PAGE_CAP,
+};
+pub use downloads::{
+    content_disposition_filename, DownloadId, DownloadInfo, DownloadState, PROGRESS_STEP,
+};
 pub use errors::{Error, Result};
+pub use favicon::FaviconCache;
+pub use fetch_options::{FetchOptions, FetchResponse, MAX_REDIRECTS};
+pub use har::{Har, HarExporter, HarOptions, MAX_EMBEDDED_BODY_BYTES};
+pub use mixed_content::{
+    MixedContentEntry, MixedContentKind, MixedContentOutcome, MixedContentReport,
+};
+pub use partition::PartitionKey;
+pub use proxy::{ProxyConfig, PROXY_SCHEMES};
 pub use request_handler::{
-    AdBlockInterceptor, HeaderInjectorInterceptor, HttpMethod, RedirectInterceptor, Request,
-    RequestAction, RequestHandler, RequestInterceptor, Response,
+    AdBlockInterceptor, ContentSettingsInterceptor, CustomHeaderInterceptor,
+    HeaderInjectorInterceptor, HttpMethod, RedirectInterceptor, Request, RequestAction,
+    RequestHandler, RequestInterceptor, Response,
 };
-pub use types::{NetworkStack, ResourceTiming};
+pub use streaming::{ByteStream, FetchContext, ResponseHead};
+pub use timing::{HostTiming, PhaseStats, TimingSummary};
+pub use types::{
+    NetworkStack, RequestDetails, ResourceTiming, TabSuspendHandler, DEFAULT_USER_AGENT,
+};
+pub use websocket::{frame_bytes, WsClose, WsConnection, WsMessage, CLOSE_NORMAL};
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use config_manager::NetworkConfig;
+    use config_manager::{CacheBackend, NetworkConfig};
     use message_bus::MessageBus;
     use url::Url;
 
@@ -106,6 +229,10 @@ mod tests {
             enable_cookies: true,
             enable_cache: true,
             cache_size_mb: 10, // Small cache for testing
+            cache_backend: CacheBackend::Memory,
+            cookie_file: None,
+            proxy: None,
+            no_proxy: Vec::new(),
         }
     }
 
@@ -306,4 +433,21 @@ mod tests {
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), Error::RequestFailed(_)));
     }
+
+    #[test]
+    fn test_clock_driven_modules_do_not_read_system_time() {
+        // Cache expiry and request timing must go through the injected
+        // Clock so tests can drive them
+        let modules = [
+            ("cache.rs", include_str!("cache.rs")),
+            ("streaming.rs", include_str!("streaming.rs")),
+            ("types.rs", include_str!("types.rs")),
+        ];
+        for (name, source) in modules {
+            let code = source.split("#[cfg(test)]").next().unwrap();
+            for call in ["SystemTime::now()", "Instant::now()"] {
+                assert!(!code.contains(call), "{} calls {} directly", name, call);
+            }
+        }
+    }
 }
@@ -0,0 +1,235 @@
+//! Mixed content: http subresources of https pages
+//!
+//! A subresource is mixed content when its URL is `http:` (or `ws:`) and
+//! the top-level document's origin is `https:`. The stack first retries
+//! http URLs as `https:`. If that fails, active content (scripts, stylesheets, XHR,
+//! WebSockets and anything else that can alter the page) is refused with
+//! `Error::Blocked`, while passive content (images, media) is loaded over
+//! http and flagged. `security.allow_mixed_content` lets active content
+//! through as well, still flagged.
+//!
+//! Every mixed request is recorded in a [`MixedContentReport`] for its
+//! top-level origin, which the UI uses to downgrade the security indicator
+//! and to list the affected URLs.
+
+use shared_types::ResourceType;
+use url::Url;
+
+/// How much harm an insecure subresource can do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MixedContentKind {
+    /// Can read or change the page (script, stylesheet, XHR, WebSocket...)
+    Active,
+    /// Only displayed (image, audio, video)
+    Passive,
+}
+
+impl MixedContentKind {
+    /// Classify a resource type
+    ///
+    /// Only images and media are passive; everything else is treated as
+    /// active.
+    ///
+    /// # Arguments
+    ///
+    /// * `resource_type` - What the response will be used for
+    pub fn of(resource_type: &ResourceType) -> Self {
+        match resource_type {
+            ResourceType::Image | ResourceType::Media => MixedContentKind::Passive,
+            _ => MixedContentKind::Active,
+        }
+    }
+}
+
+/// What happened to a mixed content request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MixedContentOutcome {
+    /// Loaded over https instead; the page stays secure
+    Upgraded,
+    /// Refused after the upgrade failed
+    Blocked,
+    /// Loaded over http after the upgrade failed
+    Loaded,
+}
+
+/// A mixed content request made by a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct MixedContentEntry {
+    /// The http URL the page asked for
+    pub url: Url,
+    /// What the response was for
+    pub resource_type: ResourceType,
+    /// What the stack did with it
+    pub outcome: MixedContentOutcome,
+}
+
+/// Mixed content requests made on behalf of one top-level origin
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MixedContentReport {
+    /// Requests in the order they were made
+    entries: Vec<MixedContentEntry>,
+}
+
+impl MixedContentReport {
+    /// Create an empty report
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a request
+    pub fn record(&mut self, entry: MixedContentEntry) {
+        self.entries.push(entry);
+    }
+
+    /// All recorded requests, upgraded ones included
+    pub fn entries(&self) -> &[MixedContentEntry] {
+        &self.entries
+    }
+
+    /// Whether anything was blocked or loaded over http
+    ///
+    /// Successfully upgraded requests do not count.
+    pub fn is_mixed(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|entry| entry.outcome != MixedContentOutcome::Upgraded)
+    }
+
+    /// URLs that were refused
+    pub fn blocked_urls(&self) -> Vec<&Url> {
+        self.urls_with(MixedContentOutcome::Blocked)
+    }
+
+    /// URLs that were loaded over http
+    pub fn loaded_urls(&self) -> Vec<&Url> {
+        self.urls_with(MixedContentOutcome::Loaded)
+    }
+
+    fn urls_with(&self, outcome: MixedContentOutcome) -> Vec<&Url> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.outcome == outcome)
+            .map(|entry| &entry.url)
+            .collect()
+    }
+}
+
+/// Check whether a request is mixed content
+///
+/// # Arguments
+///
+/// * `url` - URL being requested
+/// * `top_level_origin` - Origin of the top-level document, if any
+pub fn is_mixed_content(url: &Url, top_level_origin: Option<&str>) -> bool {
+    matches!(url.scheme(), "http" | "ws")
+        && top_level_origin.is_some_and(|origin| origin.starts_with("https://"))
+}
+
+/// The https variant of an http URL
+///
+/// An explicit non-default port is kept.
+///
+/// # Returns
+///
+/// Returns None if the URL is not `http:`.
+pub fn upgrade_to_https(url: &Url) -> Option<Url> {
+    if url.scheme() != "http" {
+        return None;
+    }
+    let mut upgraded = url.clone();
+    upgraded.set_scheme("https").ok()?;
+    Some(upgraded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_classification() {
+        assert_eq!(
+            MixedContentKind::of(&ResourceType::Image),
+            MixedContentKind::Passive
+        );
+        assert_eq!(
+            MixedContentKind::of(&ResourceType::Media),
+            MixedContentKind::Passive
+        );
+        for active in [
+            ResourceType::Script,
+            ResourceType::Stylesheet,
+            ResourceType::Xhr,
+            ResourceType::Websocket,
+            ResourceType::Font,
+        ] {
+            assert_eq!(MixedContentKind::of(&active), MixedContentKind::Active);
+        }
+    }
+
+    #[test]
+    fn test_is_mixed_content() {
+        let http = url("http://cdn.test/a.js");
+        assert!(is_mixed_content(&http, Some("https://site.test")));
+        assert!(!is_mixed_content(&http, Some("http://site.test")));
+        assert!(!is_mixed_content(&http, None));
+        assert!(is_mixed_content(
+            &url("ws://chat.test/live"),
+            Some("https://site.test")
+        ));
+        assert!(!is_mixed_content(
+            &url("https://cdn.test/a.js"),
+            Some("https://site.test")
+        ));
+    }
+
+    #[test]
+    fn test_upgrade_to_https() {
+        assert_eq!(
+            upgrade_to_https(&url("http://cdn.test/a.js?v=1")),
+            Some(url("https://cdn.test/a.js?v=1"))
+        );
+        assert_eq!(
+            upgrade_to_https(&url("http://cdn.test:80/a.js")),
+            Some(url("https://cdn.test/a.js"))
+        );
+        assert_eq!(
+            upgrade_to_https(&url("http://cdn.test:8080/a.js")),
+            Some(url("https://cdn.test:8080/a.js"))
+        );
+        assert_eq!(upgrade_to_https(&url("https://cdn.test/")), None);
+    }
+
+    #[test]
+    fn test_report() {
+        let mut report = MixedContentReport::new();
+        report.record(MixedContentEntry {
+            url: url("http://cdn.test/upgraded.js"),
+            resource_type: ResourceType::Script,
+            outcome: MixedContentOutcome::Upgraded,
+        });
+        assert!(!report.is_mixed());
+
+        report.record(MixedContentEntry {
+            url: url("http://cdn.test/app.js"),
+            resource_type: ResourceType::Script,
+            outcome: MixedContentOutcome::Blocked,
+        });
+        report.record(MixedContentEntry {
+            url: url("http://cdn.test/photo.jpg"),
+            resource_type: ResourceType::Image,
+            outcome: MixedContentOutcome::Loaded,
+        });
+
+        assert!(report.is_mixed());
+        assert_eq!(report.entries().len(), 3);
+        assert_eq!(report.blocked_urls(), vec![&url("http://cdn.test/app.js")]);
+        assert_eq!(
+            report.loaded_urls(),
+            vec![&url("http://cdn.test/photo.jpg")]
+        );
+    }
+}
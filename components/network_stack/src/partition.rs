@@ -0,0 +1,170 @@
+//! Partitioning per-site state by top-level site
+//!
+//! A cache shared by every site lets a page tell from load timings whether
+//! the user visited another site that uses the same resource. With
+//! `privacy.partition_cache` on, the HTTP cache keys each entry by the
+//! [`PartitionKey`] of the top-level document it was fetched for as well as
+//! by URL, so a page only ever sees what was cached while browsing its own
+//! site. The DNS cache (see [`dns`](crate::dns)) and the
+//! [`FaviconCache`](crate::FaviconCache) are keyed the same way.
+//!
+//! A site is the scheme and registrable domain of the top-level document
+//! (see [`shared_types::Origin::site`]): `https://news.example.com` and
+//! `https://shop.example.com` share a partition, `https://example.org` and
+//! `https://alice.github.io` and `https://bob.github.io` do not. Partitions
+//! follow the same Public Suffix List lookups as every other same-site
+//! decision in the browser.
+
+use serde::{Deserialize, Serialize};
+use shared_types::Origin;
+use std::fmt;
+use url::Url;
+
+/// Top-level site a piece of per-site state belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PartitionKey(String);
+
+impl PartitionKey {
+    /// Partition of the site a top-level document belongs to
+    ///
+    /// # Arguments
+    ///
+    /// * `top_level` - URL of the top-level document
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` for URLs with an opaque origin (`about:`, `data:`,
+    /// files).
+    pub fn for_site(top_level: &Url) -> Option<Self> {
+        Origin::from_url(top_level).map(|origin| Self(origin.site().to_string()))
+    }
+
+    /// Partition a request belongs to
+    ///
+    /// Subresources belong to the site of their top-level document; a
+    /// request with no top-level origin is a navigation and belongs to its
+    /// own site.
+    ///
+    /// # Arguments
+    ///
+    /// * `top_level_origin` - Serialized origin of the top-level document
+    /// * `url` - URL being requested
+    pub fn for_request(top_level_origin: Option<&str>, url: &Url) -> Option<Self> {
+        top_level_origin
+            .and_then(|origin| Url::parse(origin).ok())
+            .and_then(|origin| Self::for_site(&origin))
+            .or_else(|| Self::for_site(url))
+    }
+
+    /// The site, e.g. `https://example.co.uk`
+    pub fn site(&self) -> &str {
+        &self.0
+    }
+
+    /// Key of a site serialized by [`site`](Self::site), e.g. when read
+    /// back from the disk cache
+    pub(crate) fn from_site(site: &str) -> Self {
+        Self(site.to_string())
+    }
+}
+
+impl fmt::Display for PartitionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_partition_for_request() {
+        let script = url("https://cdn.tracker.example/t.js");
+        let key = PartitionKey::for_request(Some("https://www.news.example"), &script).unwrap();
+        assert_eq!(key.site(), "https://news.example");
+
+        // Subdomains of one site share a partition; schemes do not
+        assert_eq!(
+            PartitionKey::for_site(&url("https://a.news.example/")),
+            Some(key.clone())
+        );
+        assert_ne!(
+            PartitionKey::for_site(&url("http://news.example/")),
+            Some(key)
+        );
+
+        // Navigations belong to their own site
+        let page = url("https://blog.shop.example/post");
+        assert_eq!(
+            PartitionKey::for_request(None, &page).unwrap().to_string(),
+            "https://shop.example"
+        );
+        assert_eq!(
+            PartitionKey::for_request(Some("null"), &page)
+                .unwrap()
+                .site(),
+            "https://shop.example"
+        );
+        assert_eq!(PartitionKey::for_site(&url("about:blank")), None);
+    }
+
+    /// URLs whose pairs the same-site checks must agree on
+    const SITE_URLS: &[&str] = &[
+        "https://www.bbc.co.uk/news",
+        "https://shop.bbc.co.uk/",
+        "http://www.bbc.co.uk/",
+        "https://www.itv.co.uk/",
+        "https://alice.github.io/",
+        "https://bob.github.io/",
+        "https://docs.alice.github.io/",
+        "http://127.0.0.1:8080/",
+        "http://127.0.0.1:3000/",
+        "http://[::1]/",
+        "http://localhost/",
+        "http://intranet/",
+    ];
+
+    #[test]
+    fn test_partitions_agree_with_same_site() {
+        for &a in SITE_URLS {
+            for &b in SITE_URLS {
+                let same_site = Origin::from_url(&url(a))
+                    .unwrap()
+                    .is_same_site(&Origin::from_url(&url(b)).unwrap());
+                assert_eq!(
+                    PartitionKey::for_site(&url(a)) == PartitionKey::for_site(&url(b)),
+                    same_site,
+                    "{} vs {}",
+                    a,
+                    b
+                );
+            }
+        }
+        assert_eq!(
+            PartitionKey::for_site(&url("https://alice.github.io/x"))
+                .unwrap()
+                .site(),
+            "https://alice.github.io"
+        );
+    }
+
+    #[test]
+    fn test_third_party_cookies_agree_with_partitions() {
+        for &a in SITE_URLS {
+            for &b in SITE_URLS {
+                assert_eq!(
+                    crate::cookies::is_third_party(&url(a), &url(b)),
+                    PartitionKey::for_site(&url(a)) != PartitionKey::for_site(&url(b)),
+                    "{} from {}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! Proxy settings
+//!
+//! All traffic can go through one proxy (`network.proxy`), reached over
+//! `http://`, `https://` or `socks5://`. Hosts listed in
+//! `network.no_proxy` are connected to directly:
+//!
+//! - `intranet` or `10.0.0.7` matches that host only
+//! - `.internal.example.com` or `*.internal.example.com` matches
+//!   `internal.example.com` and every host under it
+//! - `*` matches every host, which turns the proxy off
+//!
+//! Matching ignores case and ports. WebSockets tunnel through `http://`
+//! proxies only; see [`websocket`](crate::websocket).
+
+use crate::errors::{Error, Result};
+use url::Url;
+
+/// Proxy URL schemes the HTTP client can use
+pub const PROXY_SCHEMES: &[&str] = &["http", "https", "socks5"];
+
+/// A proxy and the hosts that bypass it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProxyConfig {
+    /// Proxy all other traffic goes through
+    url: Url,
+    /// `network.no_proxy` entries, lowercased
+    no_proxy: Vec<String>,
+}
+
+impl ProxyConfig {
+    /// Create proxy settings
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Proxy to use, with a scheme from [`PROXY_SCHEMES`]
+    /// * `no_proxy` - Hosts to reach directly, as described in the
+    ///   [module docs](self)
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::ConfigError` if the URL has another scheme or no
+    /// host.
+    pub fn new(url: Url, no_proxy: &[String]) -> Result<Self> {
+        if !PROXY_SCHEMES.contains(&url.scheme()) || url.host_str().is_none() {
+            return Err(Error::ConfigError(format!(
+                "network.proxy: expected an http://, https:// or socks5://host:port URL, got {}",
+                url
+            )));
+        }
+        Ok(Self {
+            url,
+            no_proxy: no_proxy
+                .iter()
+                .map(|entry| entry.trim().to_ascii_lowercase())
+                .filter(|entry| !entry.is_empty())
+                .collect(),
+        })
+    }
+
+    /// Parse proxy settings from configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - `network.proxy`
+    /// * `no_proxy` - `network.no_proxy`
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::ConfigError` if the proxy URL does not parse or is
+    /// not one the client can use.
+    pub fn parse(url: &str, no_proxy: &[String]) -> Result<Self> {
+        let parsed = Url::parse(url.trim())
+            .map_err(|e| Error::ConfigError(format!("network.proxy: \"{}\": {}", url, e)))?;
+        Self::new(parsed, no_proxy)
+    }
+
+    /// The proxy URL
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Hosts reached without the proxy
+    pub fn no_proxy(&self) -> &[String] {
+        &self.no_proxy
+    }
+
+    /// Whether a request to `url` skips the proxy
+    pub fn bypasses(&self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        // IPv6 hosts come bracketed from the URL
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        self.no_proxy
+            .iter()
+            .any(|entry| no_proxy_matches(entry, host))
+    }
+
+    /// The proxy to use for `url`, or `None` to connect directly
+    pub fn proxy_for(&self, url: &Url) -> Option<&Url> {
+        (!self.bypasses(url)).then_some(&self.url)
+    }
+
+    /// The proxy as the HTTP client takes it
+    pub(crate) fn client_proxy(&self) -> reqwest::Proxy {
+        let proxy = self.clone();
+        reqwest::Proxy::custom(move |url| proxy.proxy_for(url).cloned())
+    }
+}
+
+/// Whether a `network.no_proxy` entry covers a host
+///
+/// # Arguments
+///
+/// * `entry` - Lowercased entry
+/// * `host` - Host of the request, without brackets or port
+fn no_proxy_matches(entry: &str, host: &str) -> bool {
+    let host = host.to_ascii_lowercase();
+    if entry == "*" {
+        return true;
+    }
+    match entry.strip_prefix("*.").or_else(|| entry.strip_prefix('.')) {
+        Some(domain) => {
+            host == domain
+                || host
+                    .strip_suffix(domain)
+                    .is_some_and(|rest| rest.ends_with('.'))
+        }
+        None => host == entry,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_proxy_schemes() {
+        for proxy in [
+            "http://proxy.lan:3128",
+            "https://proxy.lan:443",
+            "socks5://127.0.0.1:1080",
+        ] {
+            let config = ProxyConfig::parse(proxy, &[]).unwrap();
+            assert_eq!(config.url(), &url(proxy));
+        }
+        for proxy in ["ftp://proxy.lan:21", "proxy.lan:3128", "http://"] {
+            assert!(
+                matches!(ProxyConfig::parse(proxy, &[]), Err(Error::ConfigError(_))),
+                "{}",
+                proxy
+            );
+        }
+    }
+
+    #[test]
+    fn test_no_proxy_hosts_and_suffixes() {
+        let no_proxy = [
+            "intranet".to_string(),
+            ".internal.example.com".to_string(),
+            "*.Corp.test".to_string(),
+            "10.0.0.7".to_string(),
+            "::1".to_string(),
+        ];
+        let config = ProxyConfig::parse("socks5://127.0.0.1:1080", &no_proxy).unwrap();
+        for direct in [
+            "http://intranet/",
+            "http://INTRANET:8080/",
+            "https://internal.example.com/",
+            "https://wiki.internal.example.com/",
+            "https://a.b.corp.test/",
+            "http://10.0.0.7:8000/",
+            "http://[::1]:8000/",
+        ] {
+            assert!(config.bypasses(&url(direct)), "{}", direct);
+            assert_eq!(config.proxy_for(&url(direct)), None);
+        }
+        for proxied in [
+            "http://intranet.example.com/",
+            "https://notinternal.example.com/",
+            "https://example.com/",
+            "http://10.0.0.70/",
+        ] {
+            assert!(!config.bypasses(&url(proxied)), "{}", proxied);
+            assert_eq!(config.proxy_for(&url(proxied)), Some(config.url()));
+        }
+
+        let everything = ProxyConfig::parse("http://proxy.lan:3128", &["*".to_string()]).unwrap();
+        assert!(everything.bypasses(&url("https://example.com/")));
+    }
+}
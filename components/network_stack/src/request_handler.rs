@@ -4,9 +4,12 @@
 //! modification and filtering of HTTP requests and responses.
 
 use crate::errors::{Error, Result};
+use config_manager::{ContentSettings, CustomHeaders};
 use serde::{Deserialize, Serialize};
+use shared_types::ResourceType;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use url::Url;
 
 /// HTTP method
@@ -51,6 +54,13 @@ pub struct Request {
     pub timestamp: u64,
     /// Unique request ID
     pub request_id: String,
+    /// What the response will be used for
+    pub resource_type: ResourceType,
+    /// Origin of the top-level document that issued the request (None for
+    /// top-level navigations)
+    pub top_level_origin: Option<String>,
+    /// Time allowed for each hop, instead of `network.timeout_seconds`
+    pub timeout: Option<Duration>,
 }
 
 impl Request {
@@ -69,6 +79,9 @@ impl Request {
             body: None,
             timestamp,
             request_id: format!("req_{}", timestamp),
+            resource_type: ResourceType::Document,
+            top_level_origin: None,
+            timeout: None,
         }
     }
 
@@ -89,6 +102,24 @@ impl Request {
         self.request_id = id;
         self
     }
+
+    /// Set the resource type
+    pub fn with_resource_type(mut self, resource_type: ResourceType) -> Self {
+        self.resource_type = resource_type;
+        self
+    }
+
+    /// Set the top-level document origin (for subresource requests)
+    pub fn with_top_level_origin(mut self, origin: String) -> Self {
+        self.top_level_origin = Some(origin);
+        self
+    }
+
+    /// Override the configured timeout for this request
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
 }
 
 /// HTTP response structure
@@ -125,6 +156,9 @@ impl Response {
 /// Type alias for URL blocking callback function
 pub type ShouldBlockFn = Arc<dyn Fn(&str) -> bool + Send + Sync>;
 
+/// URL blocking callback that also sees the resource type
+pub type TypedShouldBlockFn = Arc<dyn Fn(&str, &ResourceType) -> bool + Send + Sync>;
+
 /// Action to take after processing a request
 #[derive(Debug, Clone)]
 pub enum RequestAction {
@@ -175,6 +209,14 @@ pub trait RequestInterceptor: Send + Sync {
     ///
     /// Returns true if the request should be blocked
     fn should_block(&self, request: &Request) -> bool;
+
+    /// Explain why a request was blocked
+    ///
+    /// Only called when [`should_block`](RequestInterceptor::should_block)
+    /// returned true.
+    fn block_reason(&self, _request: &Request) -> String {
+        "Blocked by interceptor".to_string()
+    }
 }
 
 /// Ad blocking interceptor
@@ -183,7 +225,7 @@ pub trait RequestInterceptor: Send + Sync {
 /// Note: Due to thread safety requirements, this interceptor uses a callback approach
 pub struct AdBlockInterceptor {
     /// Callback function for checking if URL should be blocked
-    should_block_fn: Option<ShouldBlockFn>,
+    should_block_fn: Option<TypedShouldBlockFn>,
     /// Whether ad blocking is enabled
     enabled: bool,
 }
@@ -197,6 +239,23 @@ impl AdBlockInterceptor {
     pub fn new<F>(should_block_fn: F) -> Self
     where
         F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        Self::with_resource_types(move |url, _| should_block_fn(url))
+    }
+
+    /// Create an ad block interceptor whose callback also sees the
+    /// resource type
+    ///
+    /// Lets filter rules with type options (`$websocket`, `$image`, ...)
+    /// apply, e.g. by passing the type on to `AdBlockEngine::should_block`.
+    ///
+    /// # Arguments
+    ///
+    /// * `should_block_fn` - Function that determines if a URL, requested
+    ///   as the given type, should be blocked
+    pub fn with_resource_types<F>(should_block_fn: F) -> Self
+    where
+        F: Fn(&str, &ResourceType) -> bool + Send + Sync + 'static,
     {
         Self {
             should_block_fn: Some(Arc::new(should_block_fn)),
@@ -230,7 +289,7 @@ impl RequestInterceptor for AdBlockInterceptor {
         }
 
         if let Some(ref check_fn) = self.should_block_fn {
-            check_fn(request.url.as_str())
+            check_fn(request.url.as_str(), &request.resource_type)
         } else {
             false
         }
@@ -345,6 +404,100 @@ impl RequestInterceptor for RedirectInterceptor {
     }
 }
 
+/// Per-site content settings interceptor
+///
+/// Blocks image subresources when images are disabled (globally or for the
+/// site) for the request's top-level origin. JavaScript toggles are applied
+/// by the webview, not here.
+pub struct ContentSettingsInterceptor {
+    /// Shared content settings resolver
+    settings: Arc<Mutex<ContentSettings>>,
+}
+
+impl ContentSettingsInterceptor {
+    /// Create a new content settings interceptor
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - Resolver shared with the UI that edits it
+    pub fn new(settings: Arc<Mutex<ContentSettings>>) -> Self {
+        Self { settings }
+    }
+}
+
+impl RequestInterceptor for ContentSettingsInterceptor {
+    fn pre_request(&mut self, _request: &mut Request) -> Result<()> {
+        Ok(())
+    }
+
+    fn post_response(&mut self, _response: &mut Response) -> Result<()> {
+        Ok(())
+    }
+
+    fn should_block(&self, request: &Request) -> bool {
+        if request.resource_type != ResourceType::Image {
+            return false;
+        }
+        match &request.top_level_origin {
+            Some(origin) => !self.settings.lock().unwrap().settings_for(origin).images,
+            None => false,
+        }
+    }
+
+    fn block_reason(&self, request: &Request) -> String {
+        format!(
+            "images disabled for {}",
+            request.top_level_origin.as_deref().unwrap_or("this site")
+        )
+    }
+}
+
+/// Per-host custom header interceptor
+///
+/// Adds the headers of the `network.custom_headers` rules matching the
+/// request's host, replacing any header of the same name. Subresource
+/// requests (those with a top-level origin) only get rules that set
+/// `include_subresources`.
+pub struct CustomHeaderInterceptor {
+    /// Resolved rules
+    headers: CustomHeaders,
+}
+
+impl CustomHeaderInterceptor {
+    /// Create a new custom header interceptor
+    ///
+    /// # Arguments
+    ///
+    /// * `headers` - Rules resolved from the config
+    pub fn new(headers: CustomHeaders) -> Self {
+        Self { headers }
+    }
+}
+
+impl RequestInterceptor for CustomHeaderInterceptor {
+    fn pre_request(&mut self, request: &mut Request) -> Result<()> {
+        let Some(host) = request.url.host_str() else {
+            return Ok(());
+        };
+        let navigation = request.top_level_origin.is_none();
+        for (name, value) in self.headers.headers_for(host, navigation) {
+            request
+                .headers
+                .retain(|existing, _| !existing.eq_ignore_ascii_case(&name));
+            request.headers.insert(name, value);
+        }
+        Ok(())
+    }
+
+    fn post_response(&mut self, _response: &mut Response) -> Result<()> {
+        Ok(())
+    }
+
+    fn should_block(&self, _request: &Request) -> bool {
+        false
+    }
+}
+
 /// Request handler with interceptor chain
 pub struct RequestHandler {
     /// Chain of interceptors
@@ -382,7 +535,7 @@ impl RequestHandler {
         for interceptor in &self.interceptors {
             if interceptor.should_block(request) {
                 return Ok(RequestAction::Block {
-                    reason: "Blocked by interceptor".to_string(),
+                    reason: interceptor.block_reason(request),
                 });
             }
         }
@@ -396,6 +549,20 @@ impl RequestHandler {
         Ok(RequestAction::Allow)
     }
 
+    /// Check whether the interceptor chain would block a request
+    ///
+    /// Unlike [`process_request`](Self::process_request), no interceptor
+    /// sees or changes the request.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request to check
+    pub fn would_block(&self, request: &Request) -> bool {
+        self.interceptors
+            .iter()
+            .any(|interceptor| interceptor.should_block(request))
+    }
+
     /// Process a response through the interceptor chain
     ///
     /// # Arguments
@@ -480,6 +647,8 @@ mod tests {
         assert!(request.body.is_none());
         assert!(request.timestamp > 0);
         assert!(request.request_id.starts_with("req_"));
+        assert_eq!(request.resource_type, ResourceType::Document);
+        assert_eq!(request.top_level_origin, None);
     }
 
     #[test]
@@ -638,6 +807,19 @@ mod tests {
         assert!(!interceptor.should_block(&allowed_request));
     }
 
+    #[test]
+    fn test_adblock_interceptor_with_resource_types() {
+        let interceptor = AdBlockInterceptor::with_resource_types(|url, resource_type| {
+            *resource_type == ResourceType::Websocket && url.contains("tracker")
+        });
+        let url = Url::parse("wss://tracker.example.com/live").unwrap();
+
+        let socket =
+            Request::new(url.clone(), HttpMethod::GET).with_resource_type(ResourceType::Websocket);
+        assert!(interceptor.should_block(&socket));
+        assert!(!interceptor.should_block(&Request::new(url, HttpMethod::GET)));
+    }
+
     #[test]
     fn test_adblock_interceptor_pre_request() {
         let mut interceptor = AdBlockInterceptor::disabled();
@@ -657,6 +839,139 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    // ========================================
+    // Tests for ContentSettingsInterceptor
+    // ========================================
+
+    fn images_disabled_for(origin: &str) -> Arc<Mutex<ContentSettings>> {
+        let mut settings = config_manager::Config::default().content_settings();
+        settings.set_override(origin, config_manager::ContentKind::Images, Some(false));
+        Arc::new(Mutex::new(settings))
+    }
+
+    fn subresource(url: &str, resource_type: ResourceType, top_level: &str) -> Request {
+        Request::new(Url::parse(url).unwrap(), HttpMethod::GET)
+            .with_resource_type(resource_type)
+            .with_top_level_origin(top_level.to_string())
+    }
+
+    #[test]
+    fn test_content_settings_interceptor_blocks_images_for_site() {
+        let interceptor = ContentSettingsInterceptor::new(images_disabled_for("https://news.com"));
+
+        let image = subresource(
+            "https://cdn.com/a.png",
+            ResourceType::Image,
+            "https://news.com",
+        );
+        assert!(interceptor.should_block(&image));
+        assert_eq!(
+            interceptor.block_reason(&image),
+            "images disabled for https://news.com"
+        );
+
+        // Same image embedded by another site is allowed
+        let elsewhere = subresource(
+            "https://cdn.com/a.png",
+            ResourceType::Image,
+            "https://blog.com",
+        );
+        assert!(!interceptor.should_block(&elsewhere));
+
+        // Other resource types on the disabled site are allowed
+        for resource_type in [
+            ResourceType::Script,
+            ResourceType::Stylesheet,
+            ResourceType::Document,
+        ] {
+            let request = subresource("https://cdn.com/a", resource_type, "https://news.com");
+            assert!(!interceptor.should_block(&request));
+        }
+
+        // Top-level navigations carry no top-level origin and are never blocked
+        let navigation = Request::new(
+            Url::parse("https://news.com/photo.png").unwrap(),
+            HttpMethod::GET,
+        )
+        .with_resource_type(ResourceType::Image);
+        assert!(!interceptor.should_block(&navigation));
+    }
+
+    #[test]
+    fn test_content_settings_interceptor_sees_live_changes() {
+        let settings = Arc::new(Mutex::new(
+            config_manager::Config::default().content_settings(),
+        ));
+        let interceptor = ContentSettingsInterceptor::new(Arc::clone(&settings));
+        let image = subresource(
+            "https://cdn.com/a.png",
+            ResourceType::Image,
+            "https://news.com",
+        );
+        assert!(!interceptor.should_block(&image));
+
+        settings.lock().unwrap().set_override(
+            "https://news.com",
+            config_manager::ContentKind::Images,
+            Some(false),
+        );
+        assert!(interceptor.should_block(&image));
+    }
+
+    #[test]
+    fn test_request_handler_reports_interceptor_block_reason() {
+        let mut handler = RequestHandler::new();
+        handler.add_interceptor(Box::new(ContentSettingsInterceptor::new(
+            images_disabled_for("https://news.com"),
+        )));
+
+        let mut image = subresource(
+            "https://cdn.com/a.png",
+            ResourceType::Image,
+            "https://news.com",
+        );
+        match handler.process_request(&mut image).unwrap() {
+            RequestAction::Block { reason } => {
+                assert_eq!(reason, "images disabled for https://news.com")
+            }
+            other => panic!("expected Block, got {:?}", other),
+        }
+    }
+
+    // ========================================
+    // Tests for CustomHeaderInterceptor
+    // ========================================
+
+    #[test]
+    fn test_custom_header_interceptor_replaces_existing_header() {
+        let headers = CustomHeaders::resolve(&[config_manager::CustomHeaderRule {
+            host_pattern: "*.corp".to_string(),
+            headers: [("X-Tenant".to_string(), "hr".to_string())].into(),
+            include_subresources: false,
+            allow_sensitive: false,
+        }])
+        .unwrap();
+        let mut interceptor = CustomHeaderInterceptor::new(headers);
+
+        let mut navigation =
+            Request::new(Url::parse("https://portal.corp/").unwrap(), HttpMethod::GET)
+                .with_headers([("x-tenant".to_string(), "old".to_string())].into());
+        interceptor.pre_request(&mut navigation).unwrap();
+        assert_eq!(
+            navigation.headers,
+            [("X-Tenant".to_string(), "hr".to_string())].into()
+        );
+
+        let mut script = subresource(
+            "https://portal.corp/app.js",
+            ResourceType::Script,
+            "https://portal.corp",
+        );
+        interceptor.pre_request(&mut script).unwrap();
+        assert!(script.headers.is_empty());
+        assert!(!interceptor.should_block(&script));
+    }
+
     // ========================================
     // RED PHASE: Tests for HeaderInjectorInterceptor
     // ========================================
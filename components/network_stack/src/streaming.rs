@@ -0,0 +1,740 @@
+//! Streaming responses
+//!
+//! [`NetworkStack::fetch_streaming`](crate::NetworkStack::fetch_streaming)
+//! returns as soon as the response head arrives; the body is read chunk by
+//! chunk from a [`ByteStream`]. The stream assembles the full body as it
+//! goes so it can be admitted to the cache once complete, and records
+//! timing with separate time-to-first-byte and full-download figures.
+//! Dropping the stream closes the connection, which is how a stopped or
+//! superseded navigation cancels its download; the timing entry is still
+//! recorded, marked as cancelled.
+//!
+//! [`ByteStream`] is also a [`Stream`](futures_util::Stream) of chunks, so
+//! it works with `StreamExt` combinators; polling it that way behaves
+//! exactly like [`next_chunk`](ByteStream::next_chunk).
+//!
+//! Chunks are decoded for the response's `Content-Encoding` as they
+//! arrive (see [`content_encoding`](crate::content_encoding)), so consumers
+//! and the cache only ever see decoded bytes.
+
+use crate::bandwidth::BandwidthMeter;
+use crate::cache::HttpCache;
+use crate::connections::ConnectionSlot;
+use crate::content_encoding::BodyDecoder;
+use crate::errors::{Error, Result};
+use crate::partition::PartitionKey;
+use crate::types::{RequestDetails, ResourceTiming};
+use futures_util::{Stream, StreamExt};
+use shared_types::{Clock, ResourceType, TabId, TraceContext};
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// What a streaming fetch is for
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchContext {
+    /// What the response will be used for
+    pub resource_type: ResourceType,
+    /// Origin of the top-level document, for subresources
+    pub top_level_origin: Option<String>,
+    /// Tab the fetch is for, recorded for per-tab HAR export and
+    /// bandwidth accounting
+    pub tab_id: Option<TabId>,
+    /// Whether this is a download or prefetch, which the bandwidth budget
+    /// can pause
+    pub background: bool,
+    /// Trace of the user action the fetch is for, recorded in its HAR entry
+    pub trace: Option<TraceContext>,
+    /// Whether to skip the HTTP cache and ask servers and proxies for a
+    /// fresh copy, as for a hard reload
+    pub bypass_cache: bool,
+}
+
+impl FetchContext {
+    /// Context for a top-level document navigation
+    ///
+    /// Both constructors take the trace current on the calling thread, so
+    /// it survives the fetch moving to another task.
+    pub fn document() -> Self {
+        Self {
+            resource_type: ResourceType::Document,
+            top_level_origin: None,
+            tab_id: None,
+            background: false,
+            trace: TraceContext::current(),
+            bypass_cache: false,
+        }
+    }
+
+    /// Context for a subresource of a document
+    ///
+    /// # Arguments
+    ///
+    /// * `resource_type` - What the response will be used for
+    /// * `top_level` - URL of the document issuing the request
+    pub fn subresource(resource_type: ResourceType, top_level: &Url) -> Self {
+        Self {
+            resource_type,
+            top_level_origin: Some(top_level.origin().ascii_serialization()),
+            tab_id: None,
+            background: false,
+            trace: TraceContext::current(),
+            bypass_cache: false,
+        }
+    }
+
+    /// Attribute the fetch to a tab
+    ///
+    /// # Arguments
+    ///
+    /// * `tab_id` - Tab the fetch is for
+    pub fn with_tab(mut self, tab_id: TabId) -> Self {
+        self.tab_id = Some(tab_id);
+        self
+    }
+
+    /// Attribute the fetch to a trace other than the current one
+    ///
+    /// # Arguments
+    ///
+    /// * `trace` - Trace of the user action the fetch is for
+    pub fn with_trace(mut self, trace: Option<TraceContext>) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Mark the fetch as a download or prefetch
+    ///
+    /// Background fetches are refused with `Error::TransfersPaused` while
+    /// the bandwidth budget has paused them.
+    pub fn in_background(mut self) -> Self {
+        self.background = true;
+        self
+    }
+
+    /// Fetch from the network even if the cache holds a fresh copy
+    ///
+    /// The request carries `Cache-Control: no-cache` so proxies revalidate
+    /// too; the response still replaces the cached copy.
+    pub fn bypassing_cache(mut self) -> Self {
+        self.bypass_cache = true;
+        self
+    }
+}
+
+impl Default for FetchContext {
+    fn default() -> Self {
+        Self::document()
+    }
+}
+
+/// Status and headers of a streaming response
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseHead {
+    /// URL the response came from, after redirects
+    pub url: Url,
+    /// HTTP status code
+    pub status: u16,
+    /// Response headers
+    pub headers: HashMap<String, String>,
+    /// Whether the body comes from the cache
+    pub from_cache: bool,
+    /// Whether the cached body was past its freshness lifetime; only
+    /// served that way while offline
+    pub stale: bool,
+    /// Time from the start of the fetch until the head arrived
+    pub ttfb: Duration,
+    /// URLs that redirected to `url`, in the order they were visited
+    pub redirected_from: Vec<Url>,
+}
+
+/// Body chunks of a live network response
+type NetworkBody = Pin<Box<dyn Stream<Item = reqwest::Result<Vec<u8>>> + Send>>;
+
+/// Where the body bytes come from
+enum Source {
+    /// Live network response
+    Network(NetworkBody),
+    /// Cached body, delivered as a single chunk
+    Cached(Option<Vec<u8>>),
+}
+
+/// Body of a streaming response
+///
+/// Read with [`next_chunk`](ByteStream::next_chunk) until it returns `None`,
+/// or poll it as a [`Stream`].
+pub struct ByteStream {
+    /// Response URL, for cache admission and timing
+    url: Url,
+    /// URLs that redirected to the response, for timing
+    redirected_from: Vec<Url>,
+    /// Body source
+    source: Source,
+    /// Response status code
+    status: u16,
+    /// Response headers, for cache admission
+    headers: HashMap<String, String>,
+    /// Response headers as received, for the timing entry
+    wire_headers: HashMap<String, String>,
+    /// Undoes the response's `Content-Encoding`
+    decoder: BodyDecoder,
+    /// Bytes received so far
+    body: Vec<u8>,
+    /// Cache to admit the assembled body to
+    cache: Option<Arc<HttpCache>>,
+    /// Cache partition the body is admitted to
+    partition: Option<PartitionKey>,
+    /// Timing log of the owning network stack
+    timing: Arc<Mutex<Vec<ResourceTiming>>>,
+    /// Byte counters of the owning network stack
+    bandwidth: Option<Arc<BandwidthMeter>>,
+    /// Clock the fetch is timed with
+    clock: Arc<dyn Clock>,
+    /// When the fetch started
+    start: Instant,
+    /// Time to first byte
+    ttfb: Duration,
+    /// Request metadata, completed when the body has been read
+    details: RequestDetails,
+    /// HTTP version of the response; empty if served from cache
+    http_version: String,
+    /// Whether to capture the body as text for HAR export
+    capture_body: bool,
+    /// Consumers reading along via [`tee`](ByteStream::tee)
+    tees: Vec<Sender<Vec<u8>>>,
+    /// Whether the stream has ended (successfully or not)
+    finished: bool,
+    /// Slot in the per-host connection limit, held until the stream ends
+    slot: Option<ConnectionSlot>,
+    /// Time spent waiting for the slot
+    queued: Duration,
+    /// Time spent resolving host names
+    dns: Duration,
+}
+
+impl ByteStream {
+    /// Create a stream over a live network response
+    pub(crate) fn network(
+        head: &ResponseHead,
+        response: reqwest::Response,
+        cache: Option<Arc<HttpCache>>,
+        timing: Arc<Mutex<Vec<ResourceTiming>>>,
+        clock: Arc<dyn Clock>,
+        start: Instant,
+    ) -> Self {
+        // Fused, since the decoder may hand out a last chunk after the
+        // response has ended
+        let body = response
+            .bytes_stream()
+            .map(|chunk| chunk.map(|bytes| bytes.to_vec()))
+            .fuse();
+        let source = Source::Network(Box::pin(body));
+        Self::with_source(head, source, cache, timing, clock, start)
+    }
+
+    /// Create a stream over a cached body
+    pub(crate) fn cached(
+        head: &ResponseHead,
+        body: Vec<u8>,
+        timing: Arc<Mutex<Vec<ResourceTiming>>>,
+        clock: Arc<dyn Clock>,
+        start: Instant,
+    ) -> Self {
+        Self::with_source(head, Source::Cached(Some(body)), None, timing, clock, start)
+    }
+
+    fn with_source(
+        head: &ResponseHead,
+        source: Source,
+        cache: Option<Arc<HttpCache>>,
+        timing: Arc<Mutex<Vec<ResourceTiming>>>,
+        clock: Arc<dyn Clock>,
+        start: Instant,
+    ) -> Self {
+        Self {
+            url: head.url.clone(),
+            redirected_from: head.redirected_from.clone(),
+            source,
+            status: head.status,
+            headers: head.headers.clone(),
+            wire_headers: head.headers.clone(),
+            decoder: BodyDecoder::default(),
+            body: Vec::new(),
+            cache,
+            partition: None,
+            timing,
+            bandwidth: None,
+            clock,
+            start,
+            ttfb: head.ttfb,
+            details: RequestDetails::default(),
+            http_version: String::new(),
+            capture_body: false,
+            tees: Vec::new(),
+            finished: false,
+            slot: None,
+            queued: Duration::ZERO,
+            dns: Duration::ZERO,
+        }
+    }
+
+    /// Attach the request metadata recorded with the timing entry
+    pub(crate) fn with_details(
+        mut self,
+        details: RequestDetails,
+        http_version: String,
+        capture_body: bool,
+    ) -> Self {
+        self.details = details;
+        self.http_version = http_version;
+        self.capture_body = capture_body;
+        self
+    }
+
+    /// Decode the body for the headers it was received with
+    ///
+    /// # Arguments
+    ///
+    /// * `decoder` - Decoder for the response's `Content-Encoding`
+    /// * `wire_headers` - Headers as received, before the encoding headers
+    ///   were removed from the response head
+    pub(crate) fn with_decoder(
+        mut self,
+        decoder: BodyDecoder,
+        wire_headers: HashMap<String, String>,
+    ) -> Self {
+        self.decoder = decoder;
+        self.wire_headers = wire_headers;
+        self
+    }
+
+    /// Admit the body to a partition of the cache rather than the shared one
+    pub(crate) fn with_partition(mut self, partition: Option<PartitionKey>) -> Self {
+        self.partition = partition;
+        self
+    }
+
+    /// Hold a per-host connection slot until the body is complete
+    pub(crate) fn with_connection_slot(mut self, slot: ConnectionSlot) -> Self {
+        self.queued = slot.queued;
+        self.dns = slot.dns;
+        self.slot = Some(slot);
+        self
+    }
+
+    /// Count the response's traffic when the body is complete
+    pub(crate) fn with_bandwidth(mut self, bandwidth: Arc<BandwidthMeter>) -> Self {
+        self.bandwidth = Some(bandwidth);
+        self
+    }
+
+    /// Read the next chunk of the body
+    ///
+    /// When the body is complete it is admitted to the cache (if
+    /// cacheable) and the fetch's timing is recorded.
+    ///
+    /// # Returns
+    ///
+    /// Returns the next chunk, or `None` once the body is complete.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::StreamInterrupted` if the connection fails part-way,
+    /// or `Error::DecodeError` if the body does not decode; nothing is
+    /// cached in either case and the stream is finished.
+    pub async fn next_chunk(&mut self) -> Option<Result<Vec<u8>>> {
+        self.next().await
+    }
+
+    /// Take in what the body source produced next
+    fn receive(&mut self, next: Result<Option<Vec<u8>>>) -> Option<Result<Vec<u8>>> {
+        match next {
+            Ok(Some(chunk)) => {
+                self.body.extend_from_slice(&chunk);
+                self.tees.retain(|tee| tee.send(chunk.clone()).is_ok());
+                Some(Ok(chunk))
+            }
+            Ok(None) => {
+                self.finish();
+                None
+            }
+            Err(e) => {
+                self.finished = true;
+                self.tees.clear();
+                self.slot = None;
+                Some(Err(e))
+            }
+        }
+    }
+
+    /// Read along with the main consumer
+    ///
+    /// The receiver gets a copy of every chunk read after this call and is
+    /// disconnected when the stream ends, so a secondary consumer (e.g.
+    /// resource discovery) does not have to wait for the full body.
+    ///
+    /// # Returns
+    ///
+    /// A receiver of body chunks
+    pub fn tee(&mut self) -> Receiver<Vec<u8>> {
+        let (sender, receiver) = channel();
+        self.tees.push(sender);
+        receiver
+    }
+
+    /// Number of body bytes received so far
+    pub fn bytes_received(&self) -> usize {
+        self.body.len()
+    }
+
+    /// Read the rest of the body
+    ///
+    /// # Returns
+    ///
+    /// Returns the full body, including chunks already read.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::StreamInterrupted` if the connection fails part-way.
+    pub async fn collect(mut self) -> Result<Vec<u8>> {
+        while let Some(chunk) = self.next_chunk().await {
+            chunk?;
+        }
+        Ok(std::mem::take(&mut self.body))
+    }
+
+    /// Admit the assembled body to the cache and record timing and traffic
+    fn finish(&mut self) {
+        self.finished = true;
+        self.tees.clear();
+        self.slot = None;
+
+        if let Some(cache) = &self.cache {
+            if HttpCache::is_cacheable(self.status, &self.headers) {
+                cache.put_in(
+                    self.partition.clone(),
+                    self.url.clone(),
+                    self.body.clone(),
+                    self.headers.clone(),
+                );
+            }
+        }
+        self.record_timing(false);
+    }
+
+    /// Record the fetch in the timing log and the byte counters
+    ///
+    /// # Arguments
+    ///
+    /// * `cancelled` - The stream was dropped before the body was read
+    fn record_timing(&mut self, cancelled: bool) {
+        let duration = self.clock.elapsed_since(self.start);
+        let details = std::mem::take(&mut self.details).complete(
+            self.status,
+            std::mem::take(&mut self.http_version),
+            &self.wire_headers,
+            &self.body,
+            self.capture_body,
+        );
+        let from_cache = matches!(self.source, Source::Cached(_));
+        let (encoded_size, decoded_size, download) = if from_cache {
+            (0, self.body.len(), Duration::ZERO)
+        } else {
+            (
+                self.decoder.encoded_size(),
+                self.decoder.decoded_size(),
+                duration.saturating_sub(self.ttfb),
+            )
+        };
+        let timing = ResourceTiming {
+            url: self.url.as_str().to_string(),
+            start_time: Duration::from_secs(0),
+            end_time: duration,
+            duration_ms: duration.as_millis() as u64,
+            ttfb_ms: self.ttfb.as_millis() as u64,
+            queued_ms: self.queued.as_millis() as u64,
+            dns_ms: self.dns.as_millis() as u64,
+            connect_ms: 0,
+            tls_ms: 0,
+            download_ms: download.as_millis() as u64,
+            size_bytes: self.body.len(),
+            encoded_size,
+            decoded_size,
+            from_cache,
+            revalidated: false,
+            cancelled,
+            redirected_from: self.redirected_from.iter().map(Url::to_string).collect(),
+            details,
+        };
+        if let Some(bandwidth) = &self.bandwidth {
+            bandwidth.record_timing(&timing);
+        }
+        self.timing.lock().unwrap().push(timing);
+    }
+}
+
+impl Stream for ByteStream {
+    type Item = Result<Vec<u8>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(None);
+        }
+
+        let next = match &mut this.source {
+            // Chunks that decode to nothing yet are not handed out
+            Source::Network(body) => loop {
+                match body.as_mut().poll_next(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Some(Ok(chunk))) => match this.decoder.push(&chunk) {
+                        Ok(decoded) if decoded.is_empty() => continue,
+                        decoded => break decoded.map(Some),
+                    },
+                    Poll::Ready(Some(Err(e))) => {
+                        break Err(Error::StreamInterrupted {
+                            received: this.body.len(),
+                            reason: e.to_string(),
+                        })
+                    }
+                    Poll::Ready(None) => match this.decoder.finish() {
+                        Ok(rest) if rest.is_empty() => break Ok(None),
+                        rest => break rest.map(Some),
+                    },
+                }
+            },
+            Source::Cached(body) => Ok(body.take()),
+        };
+        Poll::Ready(this.receive(next))
+    }
+}
+
+impl Drop for ByteStream {
+    /// A stream dropped before its body was read is a cancelled fetch:
+    /// nothing is cached, but its timing entry is kept, marked cancelled
+    fn drop(&mut self) {
+        if !self.finished {
+            self.finished = true;
+            self.record_timing(true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NetworkStack;
+    use config_manager::{CacheBackend, NetworkConfig};
+    use message_bus::MessageBus;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    /// Serve one chunked response, sleeping before each chunk
+    ///
+    /// Returns the URL and a counter of chunks the server managed to write.
+    /// With `truncate`, the connection is closed without the final chunk.
+    fn slow_server(
+        chunks: Vec<&'static str>,
+        delay: Duration,
+        truncate: bool,
+    ) -> (Url, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/doc", listener.local_addr().unwrap())).unwrap();
+        let written = Arc::new(AtomicUsize::new(0));
+        let counter = written.clone();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            let head = "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\n\
+                        Cache-Control: max-age=60\r\nTransfer-Encoding: chunked\r\n\r\n";
+            if stream.write_all(head.as_bytes()).is_err() {
+                return;
+            }
+            for chunk in chunks {
+                thread::sleep(delay);
+                let framed = format!("{:x}\r\n{}\r\n", chunk.len(), chunk);
+                if stream.write_all(framed.as_bytes()).is_err() || stream.flush().is_err() {
+                    return;
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+            if !truncate {
+                let _ = stream.write_all(b"0\r\n\r\n");
+            }
+        });
+
+        (url, written)
+    }
+
+    fn test_stack() -> NetworkStack {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let config = NetworkConfig {
+            max_connections_per_host: 6,
+            timeout_seconds: 30,
+            enable_cookies: false,
+            enable_cache: true,
+            cache_size_mb: 10,
+            cache_backend: CacheBackend::Memory,
+            cookie_file: None,
+            proxy: None,
+            no_proxy: Vec::new(),
+        };
+        let mut stack = NetworkStack::new(config, bus.sender()).unwrap();
+        stack.initialize().unwrap();
+        stack
+    }
+
+    // ========================================
+    // Tests for fetch_streaming
+    // ========================================
+
+    #[tokio::test]
+    async fn test_first_chunk_arrives_before_download_completes() {
+        let (url, _) = slow_server(
+            vec!["<p>1</p>", "<p>2</p>", "<p>3</p>", "<p>4</p>"],
+            Duration::from_millis(100),
+            false,
+        );
+        let stack = test_stack();
+        let start = Instant::now();
+
+        let (head, mut stream) = stack
+            .fetch_streaming(url.clone(), FetchContext::document())
+            .await
+            .unwrap();
+        assert_eq!(head.status, 200);
+        assert!(!head.from_cache);
+
+        let first = stream.next_chunk().await.unwrap().unwrap();
+        let first_at = start.elapsed();
+        assert_eq!(first, b"<p>1</p>");
+
+        stream.collect().await.unwrap();
+        let total = start.elapsed();
+        assert!(first_at * 2 < total, "{:?} vs {:?}", first_at, total);
+
+        let timing = stack.get_timing_data().pop().unwrap();
+        assert!(timing.ttfb_ms < timing.duration_ms);
+        assert_eq!(timing.size_bytes, 32);
+    }
+
+    #[tokio::test]
+    async fn test_assembled_body_is_cached() {
+        let (url, _) = slow_server(vec!["ab", "cd", "ef"], Duration::from_millis(10), false);
+        let stack = test_stack();
+
+        let (_, stream) = stack
+            .fetch_streaming(url.clone(), FetchContext::document())
+            .await
+            .unwrap();
+        assert_eq!(stream.collect().await.unwrap(), b"abcdef");
+        assert_eq!(stack.cache().unwrap().get(&url).unwrap().body, b"abcdef");
+
+        // Served from cache the second time (the server only answers once)
+        let (head, stream) = stack
+            .fetch_streaming(url, FetchContext::document())
+            .await
+            .unwrap();
+        assert!(head.from_cache);
+        assert_eq!(stream.collect().await.unwrap(), b"abcdef");
+        assert!(stack.get_timing_data().pop().unwrap().from_cache);
+    }
+
+    #[tokio::test]
+    async fn test_stream_combinators_read_and_cache_the_body() {
+        let (url, _) = slow_server(vec!["ab", "cd", "ef"], Duration::from_millis(10), false);
+        let stack = test_stack();
+
+        for from_cache in [false, true] {
+            let (head, stream) = stack
+                .fetch_streaming(url.clone(), FetchContext::document())
+                .await
+                .unwrap();
+            assert_eq!(head.from_cache, from_cache);
+            let chunks: Vec<Vec<u8>> = stream.map(Result::unwrap).collect().await;
+            assert_eq!(chunks.concat(), b"abcdef");
+        }
+        let timing = stack.get_timing_data();
+        assert_eq!(timing.len(), 2);
+        assert!(timing.iter().all(|timing| !timing.cancelled));
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_stream_is_not_cached() {
+        let (url, _) = slow_server(vec!["ab", "cd"], Duration::from_millis(10), true);
+        let stack = test_stack();
+
+        let (_, mut stream) = stack
+            .fetch_streaming(url.clone(), FetchContext::document())
+            .await
+            .unwrap();
+        let mut received = Vec::new();
+        let error = loop {
+            match stream.next_chunk().await {
+                Some(Ok(chunk)) => received.extend(chunk),
+                Some(Err(e)) => break e,
+                None => panic!("truncated response should fail"),
+            }
+        };
+
+        assert_eq!(received, b"abcd");
+        assert!(matches!(
+            error,
+            Error::StreamInterrupted { received: 4, .. }
+        ));
+        assert!(stream.next_chunk().await.is_none());
+        assert!(stack.cache().unwrap().get(&url).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dropping_stream_stops_server_writes() {
+        let chunk = "x".repeat(64 * 1024).leak();
+        let (url, written) = slow_server(vec![chunk; 40], Duration::from_millis(20), false);
+        let stack = test_stack();
+
+        let (_, mut stream) = stack
+            .fetch_streaming(url, FetchContext::document())
+            .await
+            .unwrap();
+        stream.next_chunk().await.unwrap().unwrap();
+        drop(stream);
+        let timing = stack.get_timing_data().pop().unwrap();
+        assert!(timing.cancelled);
+        assert!(timing.size_bytes > 0);
+
+        // Uncancelled, the server would have finished all 40 chunks by now
+        tokio::time::sleep(Duration::from_millis(1200)).await;
+        let after_cancel = written.load(Ordering::SeqCst);
+        assert!(after_cancel < 40, "server wrote {} chunks", after_cancel);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(written.load(Ordering::SeqCst), after_cancel);
+    }
+
+    #[tokio::test]
+    async fn test_tee_sees_every_chunk() {
+        let (url, _) = slow_server(vec!["a", "b", "c"], Duration::from_millis(10), false);
+        let stack = test_stack();
+
+        let (_, mut stream) = stack
+            .fetch_streaming(url, FetchContext::document())
+            .await
+            .unwrap();
+        let tee = stream.tee();
+        stream.collect().await.unwrap();
+
+        let seen: Vec<u8> = tee.iter().flatten().collect();
+        assert_eq!(seen, b"abc");
+    }
+}
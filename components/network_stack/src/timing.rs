@@ -0,0 +1,212 @@
+//! Per-host summaries of the timing log
+//!
+//! [`TimingSummary`] condenses recorded [`ResourceTiming`] entries into the
+//! mean, median and 95th percentile of each phase, for every host and for
+//! all hosts together. Only requests that reached the network are
+//! measured: cache hits are counted but left out of the statistics, since
+//! their phases are all zero, and cancelled fetches are skipped.
+//!
+//! Percentiles use the nearest-rank method, so they are always one of the
+//! recorded values.
+
+use crate::types::ResourceTiming;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use url::Url;
+
+/// Distribution of one phase, in milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PhaseStats {
+    /// Arithmetic mean
+    pub mean_ms: f64,
+    /// 50th percentile
+    pub median_ms: u64,
+    /// 95th percentile
+    pub p95_ms: u64,
+}
+
+impl PhaseStats {
+    /// Summarize a set of samples; all zero when there are none
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Phase durations in milliseconds, in any order
+    pub fn from_samples(samples: &[u64]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let sum: u64 = sorted.iter().sum();
+        Self {
+            mean_ms: sum as f64 / sorted.len() as f64,
+            median_ms: percentile(&sorted, 50),
+            p95_ms: percentile(&sorted, 95),
+        }
+    }
+}
+
+/// Timing statistics for the requests to one host (or to all of them)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HostTiming {
+    /// Requests that reached the network
+    pub requests: u64,
+    /// Requests answered from the cache, not included in the statistics
+    pub cache_hits: u64,
+    /// Whole request, from start to the end of the body
+    pub duration: PhaseStats,
+    /// Host name resolution
+    pub dns: PhaseStats,
+    /// Time to first byte
+    pub ttfb: PhaseStats,
+    /// Response headers to the end of the body
+    pub download: PhaseStats,
+    /// Estimated bytes received, headers included
+    pub response_bytes: u64,
+}
+
+impl HostTiming {
+    /// Summarize the timing entries of one host
+    fn from_entries(entries: &[&ResourceTiming]) -> Self {
+        let network: Vec<&ResourceTiming> = entries
+            .iter()
+            .copied()
+            .filter(|timing| !timing.from_cache)
+            .collect();
+        let phase = |field: fn(&ResourceTiming) -> u64| {
+            let samples: Vec<u64> = network.iter().map(|timing| field(timing)).collect();
+            PhaseStats::from_samples(&samples)
+        };
+        Self {
+            requests: network.len() as u64,
+            cache_hits: (entries.len() - network.len()) as u64,
+            duration: phase(|timing| timing.duration_ms),
+            dns: phase(|timing| timing.dns_ms),
+            ttfb: phase(|timing| timing.ttfb_ms),
+            download: phase(|timing| timing.download_ms),
+            response_bytes: network.iter().map(|timing| timing.response_bytes()).sum(),
+        }
+    }
+}
+
+/// Timing statistics per host
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimingSummary {
+    /// Every host together
+    pub overall: HostTiming,
+    /// Each host, by lowercase host name
+    pub hosts: BTreeMap<String, HostTiming>,
+}
+
+impl TimingSummary {
+    /// Summarize a timing log
+    ///
+    /// # Arguments
+    ///
+    /// * `timings` - Entries as recorded by the network stack
+    pub fn from_timings(timings: &[ResourceTiming]) -> Self {
+        let completed: Vec<&ResourceTiming> =
+            timings.iter().filter(|timing| !timing.cancelled).collect();
+        let mut by_host: BTreeMap<String, Vec<&ResourceTiming>> = BTreeMap::new();
+        for timing in &completed {
+            let host = Url::parse(&timing.url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+                .unwrap_or_default();
+            by_host.entry(host).or_default().push(timing);
+        }
+        Self {
+            overall: HostTiming::from_entries(&completed),
+            hosts: by_host
+                .into_iter()
+                .map(|(host, entries)| (host, HostTiming::from_entries(&entries)))
+                .collect(),
+        }
+    }
+
+    /// Statistics for one host, if any of its requests were recorded
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - Host name (case is ignored)
+    pub fn host(&self, host: &str) -> Option<&HostTiming> {
+        self.hosts.get(&host.to_ascii_lowercase())
+    }
+}
+
+/// Nearest-rank percentile of sorted, non-empty samples
+fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    let rank = (sorted.len() * percent).div_ceil(100);
+    sorted[rank.saturating_sub(1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::RequestDetails;
+    use std::time::Duration;
+
+    fn timing(url: &str, duration_ms: u64, dns_ms: u64, ttfb_ms: u64) -> ResourceTiming {
+        ResourceTiming {
+            url: url.to_string(),
+            start_time: Duration::ZERO,
+            end_time: Duration::from_millis(duration_ms),
+            duration_ms,
+            ttfb_ms,
+            queued_ms: 0,
+            dns_ms,
+            connect_ms: 0,
+            tls_ms: 0,
+            download_ms: duration_ms - ttfb_ms,
+            size_bytes: 100,
+            encoded_size: 100,
+            decoded_size: 100,
+            from_cache: false,
+            revalidated: false,
+            cancelled: false,
+            redirected_from: Vec::new(),
+            details: RequestDetails::default(),
+        }
+    }
+
+    #[test]
+    fn test_phase_stats() {
+        let stats = PhaseStats::from_samples(&[30, 10, 20, 40]);
+        assert_eq!(stats.mean_ms, 25.0);
+        assert_eq!(stats.median_ms, 20);
+        assert_eq!(stats.p95_ms, 40);
+
+        let samples: Vec<u64> = (1..=100).collect();
+        let stats = PhaseStats::from_samples(&samples);
+        assert_eq!((stats.median_ms, stats.p95_ms), (50, 95));
+
+        assert_eq!(PhaseStats::from_samples(&[]), PhaseStats::default());
+    }
+
+    #[test]
+    fn test_summary_per_host() {
+        let mut cached = timing("https://a.test/cached", 1, 0, 1);
+        cached.from_cache = true;
+        let mut cancelled = timing("https://a.test/cancelled", 500, 0, 0);
+        cancelled.cancelled = true;
+        let summary = TimingSummary::from_timings(&[
+            timing("https://a.test/", 100, 20, 60),
+            timing("https://A.test/app.js", 50, 0, 30),
+            timing("https://cdn.test/lib.js", 300, 40, 200),
+            cached,
+            cancelled,
+        ]);
+
+        let a = summary.host("a.test").unwrap();
+        assert_eq!((a.requests, a.cache_hits), (2, 1));
+        assert_eq!(a.duration.mean_ms, 75.0);
+        assert_eq!((a.dns.median_ms, a.dns.p95_ms), (0, 20));
+        assert_eq!(a.download.p95_ms, 40);
+        assert_eq!(summary.host("cdn.test").unwrap().ttfb.median_ms, 200);
+        assert_eq!(summary.hosts.len(), 2);
+
+        assert_eq!(summary.overall.requests, 3);
+        assert_eq!(summary.overall.duration.median_ms, 100);
+        assert_eq!(summary.overall.duration.p95_ms, 300);
+    }
+}
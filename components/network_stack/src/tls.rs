@@ -0,0 +1,145 @@
+//! TLS client configuration that keeps the presented certificate chains
+//!
+//! reqwest only reports the leaf certificate of a connection. The HTTP
+//! client therefore runs on a rustls configuration whose verifier wraps
+//! the standard webpki one: every chain that verifies is recorded in
+//! [`PresentedChains`] under the server name, leaf first and then the
+//! intermediates and any root exactly as the server sent them. The stack
+//! turns those into [`CertChainInfo`](crate::CertChainInfo)s.
+
+use rustls::client::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier, WebPkiVerifier,
+};
+use rustls::{
+    Certificate, ClientConfig, DigitallySignedStruct, Error as TlsError, RootCertStore, ServerName,
+    SignatureScheme,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+/// DER chains from the latest verified handshake with each server
+#[derive(Debug, Default)]
+pub(crate) struct PresentedChains {
+    /// Chains keyed by lowercase server name; IPv6 addresses without brackets
+    chains: Mutex<HashMap<String, Vec<Vec<u8>>>>,
+}
+
+impl PresentedChains {
+    /// The chain `host` presented, leaf first, as sent
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - Host as in a URL; brackets around IPv6 addresses are
+    ///   ignored
+    pub(crate) fn get(&self, host: &str) -> Option<Vec<Vec<u8>>> {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        self.chains
+            .lock()
+            .unwrap()
+            .get(&host.to_ascii_lowercase())
+            .cloned()
+    }
+
+    fn record(&self, server_name: &ServerName, chain: Vec<Vec<u8>>) {
+        let host = match server_name {
+            ServerName::DnsName(name) => name.as_ref().to_ascii_lowercase(),
+            ServerName::IpAddress(ip) => ip.to_string(),
+            _ => return,
+        };
+        self.chains.lock().unwrap().insert(host, chain);
+    }
+}
+
+/// Verifies like webpki, then records the chain
+struct RecordingVerifier {
+    inner: WebPkiVerifier,
+    chains: Arc<PresentedChains>,
+}
+
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, TlsError> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        )?;
+        let chain = std::iter::once(end_entity)
+            .chain(intermediates)
+            .map(|cert| cert.0.clone())
+            .collect();
+        self.chains.record(server_name, chain);
+        Ok(verified)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &Certificate,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TlsError> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &Certificate,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, TlsError> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+
+    fn request_scts(&self) -> bool {
+        self.inner.request_scts()
+    }
+}
+
+/// Client configuration verifying against `roots` and recording into
+/// `chains`
+pub(crate) fn client_config(
+    roots: Arc<RootCertStore>,
+    chains: Arc<PresentedChains>,
+) -> ClientConfig {
+    let verifier = RecordingVerifier {
+        inner: WebPkiVerifier::new(roots, None),
+        chains,
+    };
+    ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth()
+}
+
+/// The operating system's trusted roots, loaded once
+///
+/// Certificates the store cannot parse are skipped, like the platform TLS
+/// libraries do.
+pub(crate) fn system_roots() -> Arc<RootCertStore> {
+    static ROOTS: OnceLock<Arc<RootCertStore>> = OnceLock::new();
+    ROOTS
+        .get_or_init(|| {
+            let mut roots = RootCertStore::empty();
+            if let Ok(certs) = rustls_native_certs::load_native_certs() {
+                let ders: Vec<Vec<u8>> = certs.into_iter().map(|cert| cert.0).collect();
+                roots.add_parsable_certificates(&ders);
+            }
+            Arc::new(roots)
+        })
+        .clone()
+}
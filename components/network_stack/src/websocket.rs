@@ -0,0 +1,682 @@
+//! WebSocket connections
+//!
+//! [`NetworkStack::websocket_connect`](crate::NetworkStack::websocket_connect)
+//! opens sockets for the engine's own use and for page sockets the platform
+//! lets the browser take over. A socket is checked like any other request
+//! first: the interceptor chain sees it as a [`ResourceType::Websocket`]
+//! request, so ad block `$websocket` rules and content settings apply, and
+//! a refused socket never reaches the network. The handshake goes through
+//! the configured proxy (a `CONNECT` tunnel, so only `http://` proxies;
+//! hosts in `network.no_proxy` are reached directly), at most
+//! `network.max_connections_per_host` sockets are open to a host at once,
+//! and frame bytes in both directions count towards the tab's bandwidth.
+//!
+//! Sockets the platform webview opens by itself never pass through here;
+//! where the platform reports them,
+//! [`NetworkStack::check_websocket`](crate::NetworkStack::check_websocket)
+//! gives the same verdict without connecting.
+//!
+//! [`ResourceType::Websocket`]: shared_types::ResourceType::Websocket
+
+use crate::bandwidth::{
+    estimate_request_bytes, estimate_response_head_bytes, BandwidthMeter, TabBandwidth,
+};
+use crate::errors::{Error, Result};
+use crate::request_handler::Request;
+use futures_util::{SinkExt, StreamExt};
+use shared_types::TabId;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{HeaderMap, HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use url::Url;
+
+/// Close code sent by [`WsConnection::close`] callers for a normal close
+pub const CLOSE_NORMAL: u16 = 1000;
+
+/// How long [`WsConnection::close`] waits for the peer to confirm
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest proxy response head accepted while opening a tunnel
+const MAX_PROXY_RESPONSE: usize = 8 * 1024;
+
+/// A data message sent or received on a WebSocket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WsMessage {
+    /// UTF-8 text
+    Text(String),
+    /// Binary data
+    Binary(Vec<u8>),
+}
+
+impl WsMessage {
+    /// Payload size in bytes
+    pub fn len(&self) -> usize {
+        match self {
+            WsMessage::Text(text) => text.len(),
+            WsMessage::Binary(data) => data.len(),
+        }
+    }
+
+    /// Whether the payload is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// How the peer closed a connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WsClose {
+    /// Close code; 1005 when the peer sent none
+    pub code: u16,
+    /// Close reason, possibly empty
+    pub reason: String,
+}
+
+/// Size of a frame on the wire
+///
+/// Client frames are masked, adding four bytes.
+///
+/// # Arguments
+///
+/// * `payload` - Payload size in bytes
+/// * `masked` - Whether the frame is sent by the client
+pub fn frame_bytes(payload: usize, masked: bool) -> u64 {
+    let extended_length = match payload {
+        0..=125 => 0,
+        126..=0xFFFF => 2,
+        _ => 8,
+    };
+    let mask = if masked { 4 } else { 0 };
+    (2 + extended_length + mask + payload) as u64
+}
+
+/// An open WebSocket
+///
+/// Read with [`receive`](Self::receive) until it returns `None`, and end
+/// with [`close`](Self::close). Dropping the connection without closing it
+/// drops the TCP connection; either way its slot in the per-host limit is
+/// freed.
+pub struct WsConnection {
+    /// The socket
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    /// URL the socket was opened to
+    url: Url,
+    /// Subprotocol the server chose
+    protocol: Option<String>,
+    /// Tab the traffic is counted against
+    tab_id: Option<TabId>,
+    /// Bandwidth counters
+    bandwidth: Arc<BandwidthMeter>,
+    /// How the peer closed the connection, once it has
+    closed_by_peer: Option<WsClose>,
+    /// Slot in the per-host connection limit
+    _permit: OwnedSemaphorePermit,
+}
+
+/// What [`connect`] needs besides the checked request
+pub(crate) struct ConnectOptions<'a> {
+    /// Subprotocols offered in `Sec-WebSocket-Protocol`
+    pub protocols: &'a [&'a str],
+    /// HTTP proxy to tunnel through
+    pub proxy: Option<&'a Url>,
+    /// Tab the traffic is counted against
+    pub tab_id: Option<TabId>,
+    /// Bandwidth counters
+    pub bandwidth: Arc<BandwidthMeter>,
+    /// Slot in the per-host connection limit
+    pub permit: OwnedSemaphorePermit,
+    /// Time allowed for the connection and handshake
+    pub timeout: Duration,
+}
+
+/// Open a socket for a request the interceptor chain allowed
+///
+/// # Errors
+///
+/// Returns `Error::Timeout` if the handshake does not finish within
+/// `options.timeout`, `Error::RequestFailed` if the host or proxy cannot be
+/// reached or the server refuses the upgrade, or `Error::WebSocket` for a
+/// malformed handshake.
+pub(crate) async fn connect(
+    request: &Request,
+    options: ConnectOptions<'_>,
+) -> Result<WsConnection> {
+    let ConnectOptions {
+        protocols,
+        proxy,
+        tab_id,
+        bandwidth,
+        permit,
+        timeout,
+    } = options;
+    let handshake = async {
+        let url = &request.url;
+        let host = url
+            .host_str()
+            .ok_or_else(|| Error::InvalidUrl(format!("{} has no host", url)))?;
+        let port = url
+            .port_or_known_default()
+            .ok_or_else(|| Error::InvalidUrl(format!("{} has no port", url)))?;
+        let tcp = match proxy {
+            Some(proxy) => open_tunnel(proxy, host, port).await?,
+            None => TcpStream::connect((bare_host(host), port))
+                .await
+                .map_err(|e| Error::RequestFailed(format!("Cannot connect to {}: {}", host, e)))?,
+        };
+
+        let mut handshake = url.as_str().into_client_request().map_err(ws_error)?;
+        let headers = handshake.headers_mut();
+        for (name, value) in &request.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.insert(name, value);
+            }
+        }
+        if !protocols.is_empty() {
+            let offered = HeaderValue::from_str(&protocols.join(", "))
+                .map_err(|e| Error::WebSocket(format!("Invalid subprotocol: {}", e)))?;
+            headers.insert("Sec-WebSocket-Protocol", offered);
+        }
+        let mut sent_headers = header_map(headers);
+        // Counted with the request line by the estimate
+        sent_headers.remove("host");
+
+        let (stream, response) =
+            tokio_tungstenite::client_async_tls_with_config(handshake, tcp, None, None)
+                .await
+                .map_err(ws_error)?;
+        Ok::<_, Error>((stream, sent_headers, header_map(response.headers())))
+    };
+    let (stream, sent_headers, received_headers) = tokio::time::timeout(timeout, handshake)
+        .await
+        .map_err(|_| Error::Timeout)??;
+
+    bandwidth.record(
+        tab_id,
+        &TabBandwidth {
+            requests: 1,
+            sent: estimate_request_bytes("GET", &request.url, &sent_headers, 0),
+            received_encoded: estimate_response_head_bytes("HTTP/1.1", 101, &received_headers),
+            received_decoded: 0,
+        },
+    );
+    Ok(WsConnection {
+        stream,
+        url: request.url.clone(),
+        protocol: received_headers.get("sec-websocket-protocol").cloned(),
+        tab_id,
+        bandwidth,
+        closed_by_peer: None,
+        _permit: permit,
+    })
+}
+
+impl WsConnection {
+    /// URL the socket was opened to
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// Subprotocol the server chose, if any
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    /// How the peer closed the connection, once [`receive`](Self::receive)
+    /// has returned `None` because of it
+    pub fn close_reason(&self) -> Option<&WsClose> {
+        self.closed_by_peer.as_ref()
+    }
+
+    /// Send a message
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::WebSocket` if the connection is closed or broken.
+    pub async fn send(&mut self, message: WsMessage) -> Result<()> {
+        let payload = message.len();
+        let message = match message {
+            WsMessage::Text(text) => Message::Text(text),
+            WsMessage::Binary(data) => Message::Binary(data),
+        };
+        self.stream.send(message).await.map_err(ws_error)?;
+        self.count(frame_bytes(payload, true), 0, 0);
+        Ok(())
+    }
+
+    /// Wait for the next message
+    ///
+    /// Pings are answered and control frames skipped. When the peer closes
+    /// the connection its close code is kept (see
+    /// [`close_reason`](Self::close_reason)).
+    ///
+    /// # Returns
+    ///
+    /// Returns the next text or binary message, an error if the connection
+    /// broke, or `None` once it is closed.
+    pub async fn receive(&mut self) -> Option<Result<WsMessage>> {
+        loop {
+            let message = match self.stream.next().await? {
+                Ok(message) => message,
+                Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                    return None
+                }
+                Err(e) => return Some(Err(ws_error(e))),
+            };
+            match message {
+                Message::Text(text) => {
+                    self.count(0, frame_bytes(text.len(), false), text.len() as u64);
+                    return Some(Ok(WsMessage::Text(text)));
+                }
+                Message::Binary(data) => {
+                    self.count(0, frame_bytes(data.len(), false), data.len() as u64);
+                    return Some(Ok(WsMessage::Binary(data)));
+                }
+                Message::Ping(payload) | Message::Pong(payload) => {
+                    self.count(0, frame_bytes(payload.len(), false), 0);
+                }
+                Message::Close(frame) => {
+                    let close = frame.map_or(
+                        WsClose {
+                            code: CloseCode::Status.into(),
+                            reason: String::new(),
+                        },
+                        |frame| WsClose {
+                            code: frame.code.into(),
+                            reason: frame.reason.into_owned(),
+                        },
+                    );
+                    let payload = if close.code == 1005 {
+                        0
+                    } else {
+                        2 + close.reason.len()
+                    };
+                    self.count(0, frame_bytes(payload, false), 0);
+                    // The reply is queued; the stream ends once it is sent
+                    self.closed_by_peer = Some(close);
+                }
+                Message::Frame(_) => {}
+            }
+        }
+    }
+
+    /// Close the connection
+    ///
+    /// Sends a close frame and waits briefly for the peer to confirm, so
+    /// the connection shuts down cleanly. Messages still arriving are
+    /// discarded.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - Close code, usually [`CLOSE_NORMAL`]
+    /// * `reason` - Close reason for the peer, possibly empty
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::WebSocket` if the close frame cannot be sent.
+    pub async fn close(mut self, code: u16, reason: &str) -> Result<()> {
+        let frame = CloseFrame {
+            code: CloseCode::from(code),
+            reason: reason.into(),
+        };
+        match self.stream.close(Some(frame)).await {
+            Ok(()) => self.count(frame_bytes(2 + reason.len(), true), 0, 0),
+            Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => {
+                return Ok(())
+            }
+            Err(e) => return Err(ws_error(e)),
+        }
+        let _ = tokio::time::timeout(CLOSE_TIMEOUT, async {
+            while self.receive().await.is_some() {}
+        })
+        .await;
+        Ok(())
+    }
+
+    /// Add traffic to the tab's bandwidth counters
+    fn count(&self, sent: u64, received_encoded: u64, received_decoded: u64) {
+        self.bandwidth.record(
+            self.tab_id,
+            &TabBandwidth {
+                requests: 0,
+                sent,
+                received_encoded,
+                received_decoded,
+            },
+        );
+    }
+}
+
+/// Open a `CONNECT` tunnel to `host:port` through an HTTP proxy
+async fn open_tunnel(proxy: &Url, host: &str, port: u16) -> Result<TcpStream> {
+    let proxy_host = proxy
+        .host_str()
+        .ok_or_else(|| Error::InvalidUrl(format!("Proxy {} has no host", proxy)))?;
+    let proxy_port = proxy.port_or_known_default().unwrap_or(80);
+    let unreachable = |e: std::io::Error| {
+        Error::RequestFailed(format!("Cannot reach proxy {}: {}", proxy_host, e))
+    };
+    let mut stream = TcpStream::connect((bare_host(proxy_host), proxy_port))
+        .await
+        .map_err(unreachable)?;
+
+    let authority = format!("{}:{}", host, port);
+    let connect = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n\r\n", authority);
+    stream
+        .write_all(connect.as_bytes())
+        .await
+        .map_err(unreachable)?;
+
+    // Read byte by byte so nothing after the head is taken from the tunnel
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() == MAX_PROXY_RESPONSE
+            || stream.read(&mut byte).await.map_err(unreachable)? == 0
+        {
+            return Err(Error::RequestFailed(format!(
+                "Proxy {} closed the tunnel to {}",
+                proxy_host, authority
+            )));
+        }
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let status_line = head.lines().next().unwrap_or_default();
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+    if !status.starts_with('2') {
+        return Err(Error::RequestFailed(format!(
+            "Proxy {} refused the tunnel to {}: {}",
+            proxy_host, authority, status_line
+        )));
+    }
+    Ok(stream)
+}
+
+/// Host without the brackets of an IPv6 literal, for connecting
+fn bare_host(host: &str) -> &str {
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+/// Headers as a map of lowercase names, for bandwidth estimates
+fn header_map(headers: &HeaderMap) -> HashMap<String, String> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| {
+            Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+        })
+        .collect()
+}
+
+/// Describe a tungstenite error
+fn ws_error(error: tungstenite::Error) -> Error {
+    match error {
+        tungstenite::Error::Http(response) => {
+            Error::RequestFailed(format!("HTTP error: {}", response.status().as_u16()))
+        }
+        tungstenite::Error::Io(e) => Error::RequestFailed(e.to_string()),
+        other => Error::WebSocket(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request_handler::AdBlockInterceptor;
+    use crate::streaming::FetchContext;
+    use crate::NetworkStack;
+    use config_manager::{CacheBackend, NetworkConfig};
+    use message_bus::MessageBus;
+    use shared_types::ResourceType;
+    use std::sync::Mutex;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::tungstenite::handshake::server::{
+        ErrorResponse, Request as HandshakeRequest, Response as HandshakeResponse,
+    };
+
+    fn test_stack(max_connections_per_host: u32) -> NetworkStack {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let config = NetworkConfig {
+            max_connections_per_host,
+            timeout_seconds: 5,
+            enable_cookies: false,
+            enable_cache: false,
+            cache_size_mb: 1,
+            cache_backend: CacheBackend::Memory,
+            cookie_file: None,
+            proxy: None,
+            no_proxy: Vec::new(),
+        };
+        let mut stack = NetworkStack::new(config, bus.sender()).unwrap();
+        stack.initialize().unwrap();
+        stack
+    }
+
+    /// Agree to the first subprotocol the client offers
+    ///
+    /// The signature is tungstenite's handshake callback, error type included.
+    #[allow(clippy::result_large_err)]
+    fn choose_protocol(
+        request: &HandshakeRequest,
+        mut response: HandshakeResponse,
+    ) -> std::result::Result<HandshakeResponse, ErrorResponse> {
+        if let Some(offered) = request.headers().get("sec-websocket-protocol") {
+            let first = offered.to_str().unwrap().split(',').next().unwrap();
+            response
+                .headers_mut()
+                .insert("sec-websocket-protocol", first.trim().parse().unwrap());
+        }
+        Ok(response)
+    }
+
+    /// Echo every data message back
+    async fn echo_server() -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((tcp, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let Ok(mut ws) =
+                        tokio_tungstenite::accept_hdr_async(tcp, choose_protocol).await
+                    else {
+                        return;
+                    };
+                    while let Some(Ok(message)) = ws.next().await {
+                        if (message.is_text() || message.is_binary())
+                            && ws.send(message).await.is_err()
+                        {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        Url::parse(&format!("ws://{}/echo", addr)).unwrap()
+    }
+
+    #[test]
+    fn test_frame_bytes() {
+        assert_eq!(frame_bytes(5, false), 7);
+        assert_eq!(frame_bytes(5, true), 11);
+        assert_eq!(frame_bytes(126, false), 130);
+        assert_eq!(frame_bytes(70_000, true), 70_014);
+    }
+
+    #[tokio::test]
+    async fn test_connect_send_receive_close() {
+        let url = echo_server().await;
+        let stack = test_stack(6);
+        let ctx = FetchContext::document().with_tab(7);
+
+        let mut socket = stack
+            .websocket_connect(url.clone(), ctx, &["chat", "superchat"])
+            .await
+            .unwrap();
+        assert_eq!(socket.url(), &url);
+        assert_eq!(socket.protocol(), Some("chat"));
+
+        socket
+            .send(WsMessage::Text("hello".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(
+            socket.receive().await.unwrap().unwrap(),
+            WsMessage::Text("hello".to_string())
+        );
+        socket.send(WsMessage::Binary(vec![1, 2, 3])).await.unwrap();
+        assert_eq!(
+            socket.receive().await.unwrap().unwrap(),
+            WsMessage::Binary(vec![1, 2, 3])
+        );
+        socket.close(CLOSE_NORMAL, "done").await.unwrap();
+
+        let usage = stack.tab_bandwidth(7);
+        assert_eq!(usage.requests, 1);
+        assert_eq!(usage.received_decoded, 8);
+        assert!(usage.received_encoded >= frame_bytes(5, false) + frame_bytes(3, false));
+        assert!(usage.sent > frame_bytes(5, true) + frame_bytes(3, true));
+    }
+
+    #[tokio::test]
+    async fn test_blocked_and_mixed_content_sockets_never_connect() {
+        let mut stack = test_stack(6);
+        stack.add_interceptor(Box::new(AdBlockInterceptor::with_resource_types(
+            |url, resource_type| {
+                *resource_type == ResourceType::Websocket && url.contains("tracker")
+            },
+        )));
+
+        // Port 1 is closed: reaching the network would fail differently
+        let tracker = Url::parse("ws://127.0.0.1:1/tracker").unwrap();
+        let result = stack
+            .websocket_connect(tracker.clone(), FetchContext::document(), &[])
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::Blocked {
+                resource_type: ResourceType::Websocket,
+                ..
+            })
+        ));
+        assert!(matches!(
+            stack.check_websocket(tracker, FetchContext::document()),
+            Err(Error::Blocked { .. })
+        ));
+
+        let page = Url::parse("https://site.test/").unwrap();
+        let insecure = Url::parse("ws://127.0.0.1:1/live").unwrap();
+        let ctx = FetchContext::subresource(ResourceType::Websocket, &page);
+        assert!(matches!(
+            stack.check_websocket(insecure.clone(), ctx.clone()),
+            Err(Error::Blocked { .. })
+        ));
+        assert_eq!(
+            stack.mixed_content_report(&page).blocked_urls(),
+            [&insecure]
+        );
+        assert!(stack
+            .check_websocket(Url::parse("wss://127.0.0.1:1/live").unwrap(), ctx)
+            .is_ok());
+
+        assert!(matches!(
+            stack.check_websocket(page, FetchContext::document()),
+            Err(Error::InvalidUrl(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_connect_through_proxy_tunnel() {
+        let url = echo_server().await;
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let connect_lines = Arc::new(Mutex::new(Vec::new()));
+        let log = connect_lines.clone();
+        tokio::spawn(async move {
+            let (mut client, _) = listener.accept().await.unwrap();
+            let mut head = Vec::new();
+            let mut byte = [0u8; 1];
+            while !head.ends_with(b"\r\n\r\n") {
+                client.read_exact(&mut byte).await.unwrap();
+                head.push(byte[0]);
+            }
+            let head = String::from_utf8(head).unwrap();
+            let line = head.lines().next().unwrap().to_string();
+            let target = line.split_whitespace().nth(1).unwrap().to_string();
+            log.lock().unwrap().push(line);
+
+            let mut upstream = TcpStream::connect(target).await.unwrap();
+            client
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                .await
+                .unwrap();
+            let _ = tokio::io::copy_bidirectional(&mut client, &mut upstream).await;
+        });
+
+        let mut stack = test_stack(6);
+        assert!(matches!(
+            stack.set_proxy(Some(Url::parse("ftp://127.0.0.1:21").unwrap())),
+            Err(Error::ConfigError(_))
+        ));
+        stack.set_proxy(Some(proxy.clone())).unwrap();
+        assert_eq!(stack.proxy(), Some(&proxy));
+
+        let mut socket = stack
+            .websocket_connect(url.clone(), FetchContext::document(), &[])
+            .await
+            .unwrap();
+        socket
+            .send(WsMessage::Text("via proxy".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(
+            socket.receive().await.unwrap().unwrap(),
+            WsMessage::Text("via proxy".to_string())
+        );
+        socket.close(CLOSE_NORMAL, "").await.unwrap();
+
+        assert_eq!(
+            *connect_lines.lock().unwrap(),
+            [format!(
+                "CONNECT 127.0.0.1:{} HTTP/1.1",
+                url.port().unwrap()
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connections_per_host_limited() {
+        let url = echo_server().await;
+        let stack = test_stack(1);
+
+        let first = stack
+            .websocket_connect(url.clone(), FetchContext::document(), &[])
+            .await
+            .unwrap();
+        let refused = stack
+            .websocket_connect(url.clone(), FetchContext::document(), &[])
+            .await;
+        assert!(matches!(
+            refused,
+            Err(Error::ConnectionLimit { limit: 1, .. })
+        ));
+
+        first.close(CLOSE_NORMAL, "").await.unwrap();
+        let second = stack
+            .websocket_connect(url, FetchContext::document(), &[])
+            .await
+            .unwrap();
+        second.close(CLOSE_NORMAL, "").await.unwrap();
+    }
+}
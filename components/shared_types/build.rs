@@ -0,0 +1,82 @@
+//! Build script for shared-types component
+//!
+//! Compiles the Public Suffix List into a sorted rule table in `OUT_DIR`
+//! for `include!` by `psl.rs`. The list is the snapshot in
+//! `resources/public_suffix_list.dat`; set `PUBLIC_SUFFIX_LIST` to the path
+//! of a newer copy of https://publicsuffix.org/list/public_suffix_list.dat
+//! to build with that instead. A missing list, or one without rules,
+//! fails the build.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const LIST_PATH: &str = "resources/public_suffix_list.dat";
+const LIST_ENV: &str = "PUBLIC_SUFFIX_LIST";
+const TABLE_OUT: &str = "public_suffixes.rs";
+
+/// Rule flags; keep in step with `psl.rs`
+const NORMAL: u8 = 1;
+const WILDCARD: u8 = 2;
+const EXCEPTION: u8 = 4;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed={}", LIST_ENV);
+
+    let list_path = match env::var_os(LIST_ENV) {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join(LIST_PATH),
+    };
+    println!("cargo:rerun-if-changed={}", list_path.display());
+
+    let content = fs::read_to_string(&list_path).unwrap_or_else(|e| {
+        panic!(
+            "Public suffix list {} is missing ({}); restore it or point {} at a copy",
+            list_path.display(),
+            e,
+            LIST_ENV
+        )
+    });
+
+    let mut rules: BTreeMap<String, u8> = BTreeMap::new();
+    for (index, line) in content.lines().enumerate() {
+        // A rule is the first word of a line; the rest is ignored
+        let Some(rule) = line.split_whitespace().next() else {
+            continue;
+        };
+        if rule.starts_with("//") {
+            continue;
+        }
+        let (name, flag) = if let Some(name) = rule.strip_prefix('!') {
+            (name, EXCEPTION)
+        } else if let Some(name) = rule.strip_prefix("*.") {
+            (name, WILDCARD)
+        } else {
+            (rule, NORMAL)
+        };
+        let ascii = match url::Host::parse(name) {
+            Ok(url::Host::Domain(domain)) => domain,
+            _ => panic!(
+                "Public suffix list {} line {}: {:?} is not a domain",
+                list_path.display(),
+                index + 1,
+                rule
+            ),
+        };
+        *rules.entry(ascii).or_default() |= flag;
+    }
+    if rules.is_empty() {
+        panic!("Public suffix list {} has no rules", list_path.display());
+    }
+
+    let mut table = String::from("&[\n");
+    for (name, flags) in &rules {
+        table.push_str(&format!("    ({:?}, {}),\n", name, flags));
+    }
+    table.push(']');
+
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap()).join(TABLE_OUT);
+    fs::write(&out_path, table).expect("Failed to write public suffix table");
+}
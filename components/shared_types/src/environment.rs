@@ -0,0 +1,308 @@
+//! Clock and ID sources injected into components
+//!
+//! Components that timestamp, expire, back off or hand out identifiers take
+//! their time and ids from an [`Environment`] rather than reading the system
+//! clock directly. Production code uses [`Environment::system`]; tests use
+//! [`Environment::mock`] to move time by hand (sleeps return at once) and to
+//! get the same ids on every run.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Future returned by [`Clock::sleep`]
+pub type Sleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Source of wall-clock time, monotonic time and delays
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Wall-clock time since the Unix epoch
+    fn now_unix(&self) -> Duration;
+
+    /// Monotonic time, for measuring durations
+    fn now_instant(&self) -> Instant;
+
+    /// Wait for `duration`
+    ///
+    /// The system clock needs a Tokio runtime to drive the returned future.
+    fn sleep(&self, duration: Duration) -> Sleep;
+
+    /// Wall-clock time in whole seconds since the Unix epoch
+    fn unix_secs(&self) -> u64 {
+        self.now_unix().as_secs()
+    }
+
+    /// Wall-clock time in milliseconds since the Unix epoch
+    fn unix_millis(&self) -> u64 {
+        self.now_unix().as_millis() as u64
+    }
+
+    /// Monotonic time elapsed since `start` (zero if `start` is later)
+    fn elapsed_since(&self, start: Instant) -> Duration {
+        self.now_instant().saturating_duration_since(start)
+    }
+}
+
+/// The operating system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_unix(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+
+    fn now_instant(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Time as seen by a [`MockClock`]
+#[derive(Debug)]
+struct MockTime {
+    /// Wall-clock time since the Unix epoch
+    unix: Duration,
+    /// Monotonic time since the clock was created
+    elapsed: Duration,
+    /// Every duration passed to `sleep`, in order
+    sleeps: Vec<Duration>,
+}
+
+/// Manually advanced clock for tests
+///
+/// Time only moves through [`advance`](MockClock::advance) and `sleep`,
+/// which advances the clock by the requested duration and completes
+/// immediately.
+#[derive(Debug)]
+pub struct MockClock {
+    /// Real instant the monotonic time is measured from
+    origin: Instant,
+    /// Current time
+    time: Mutex<MockTime>,
+}
+
+impl MockClock {
+    /// Wall-clock start of [`MockClock::default`] (2023-11-14T22:13:20Z)
+    pub const DEFAULT_START_UNIX_SECS: u64 = 1_700_000_000;
+
+    /// Create a clock reading `unix` since the Unix epoch
+    ///
+    /// # Arguments
+    ///
+    /// * `unix` - Initial wall-clock time
+    pub fn new(unix: Duration) -> Self {
+        Self {
+            origin: Instant::now(),
+            time: Mutex::new(MockTime {
+                unix,
+                elapsed: Duration::ZERO,
+                sleeps: Vec::new(),
+            }),
+        }
+    }
+
+    /// Move both wall-clock and monotonic time forward
+    ///
+    /// # Arguments
+    ///
+    /// * `by` - How far to move
+    pub fn advance(&self, by: Duration) {
+        let mut time = self.time.lock().unwrap();
+        time.unix += by;
+        time.elapsed += by;
+    }
+
+    /// Durations passed to [`Clock::sleep`] so far, in order
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.time.lock().unwrap().sleeps.clone()
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(Self::DEFAULT_START_UNIX_SECS))
+    }
+}
+
+impl Clock for MockClock {
+    fn now_unix(&self) -> Duration {
+        self.time.lock().unwrap().unix
+    }
+
+    fn now_instant(&self) -> Instant {
+        self.origin + self.time.lock().unwrap().elapsed
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        self.time.lock().unwrap().sleeps.push(duration);
+        self.advance(duration);
+        Box::pin(std::future::ready(()))
+    }
+}
+
+/// Source of identifiers (request ids, tab ids, correlation ids)
+pub trait IdGenerator: Send + Sync + fmt::Debug {
+    /// Next identifier; never repeats for the generator's lifetime
+    fn next_id(&self) -> u64;
+}
+
+/// Production identifiers, unique within the process and unlikely to repeat
+/// across runs
+///
+/// The counter starts from the creation time in milliseconds shifted left
+/// by 16 bits, leaving room for 65536 ids per millisecond of uptime.
+#[derive(Debug)]
+pub struct SystemIds {
+    /// Next identifier to hand out
+    next: AtomicU64,
+}
+
+impl SystemIds {
+    /// Create a generator seeded from the system clock
+    pub fn new() -> Self {
+        Self {
+            next: AtomicU64::new(SystemClock.unix_millis() << 16),
+        }
+    }
+}
+
+impl Default for SystemIds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdGenerator for SystemIds {
+    fn next_id(&self) -> u64 {
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/// Identifiers counting up from a fixed start, for tests and replays
+#[derive(Debug)]
+pub struct SequentialIds {
+    /// Next identifier to hand out
+    next: AtomicU64,
+}
+
+impl SequentialIds {
+    /// Create a generator whose first id is `start`
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - First identifier
+    pub fn new(start: u64) -> Self {
+        Self {
+            next: AtomicU64::new(start),
+        }
+    }
+}
+
+impl Default for SequentialIds {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl IdGenerator for SequentialIds {
+    fn next_id(&self) -> u64 {
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+/// Clock and id source shared by the components of one browser
+#[derive(Debug, Clone)]
+pub struct Environment {
+    /// Time source
+    pub clock: Arc<dyn Clock>,
+    /// Identifier source
+    pub ids: Arc<dyn IdGenerator>,
+}
+
+impl Environment {
+    /// Create an environment from explicit sources
+    ///
+    /// # Arguments
+    ///
+    /// * `clock` - Time source
+    /// * `ids` - Identifier source
+    pub fn new(clock: Arc<dyn Clock>, ids: Arc<dyn IdGenerator>) -> Self {
+        Self { clock, ids }
+    }
+
+    /// The real clock and process-unique ids
+    pub fn system() -> Self {
+        Self::new(Arc::new(SystemClock), Arc::new(SystemIds::new()))
+    }
+
+    /// A [`MockClock`] at its default start and ids counting from 1
+    ///
+    /// # Returns
+    ///
+    /// Returns the environment and a handle for advancing its clock.
+    pub fn mock() -> (Self, Arc<MockClock>) {
+        let clock = Arc::new(MockClock::default());
+        let env = Self::new(clock.clone(), Arc::new(SequentialIds::default()));
+        (env, clock)
+    }
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::system()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_only_moves_when_advanced() {
+        let clock = MockClock::default();
+        let start = clock.now_instant();
+        assert_eq!(clock.unix_secs(), MockClock::DEFAULT_START_UNIX_SECS);
+        assert_eq!(clock.elapsed_since(start), Duration::ZERO);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.unix_secs(), MockClock::DEFAULT_START_UNIX_SECS + 90);
+        assert_eq!(clock.elapsed_since(start), Duration::from_secs(90));
+    }
+
+    #[tokio::test]
+    async fn test_mock_clock_sleep_advances_and_completes() {
+        let clock = MockClock::new(Duration::from_millis(1_000));
+        let started = Instant::now();
+        clock.sleep(Duration::from_secs(60)).await;
+
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(clock.unix_millis(), 61_000);
+        assert_eq!(clock.sleeps(), vec![Duration::from_secs(60)]);
+    }
+
+    #[test]
+    fn test_sequential_ids() {
+        let ids = SequentialIds::new(10);
+        assert_eq!(ids.next_id(), 10);
+        assert_eq!(ids.next_id(), 11);
+        let (env, _) = Environment::mock();
+        assert_eq!(env.ids.next_id(), 1);
+        assert_eq!(env.ids.next_id(), 2);
+    }
+
+    #[test]
+    fn test_system_ids_are_unique() {
+        let ids = SystemIds::new();
+        let first = ids.next_id();
+        assert!(first > 0);
+        assert_ne!(first, ids.next_id());
+    }
+}
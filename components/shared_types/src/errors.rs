@@ -21,6 +21,14 @@ pub enum BrowserError {
     #[error("Tab not found: {0}")]
     TabNotFound(u32),
 
+    /// Text that is not a tuple origin such as `https://example.com`
+    #[error("Invalid origin: {0}")]
+    InvalidOrigin(String),
+
+    /// A Public Suffix List that cannot be read or parsed
+    #[error("Public suffix list error: {0}")]
+    PublicSuffixList(String),
+
     /// Generic error from anyhow
     #[error("Error: {0}")]
     Other(#[from] anyhow::Error),
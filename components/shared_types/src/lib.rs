@@ -15,25 +15,40 @@
 //! # Usage
 //!
 //! ```rust
-//! use shared_types::{BrowserMessage, ResourceType, TabId, RequestId};
+//! use shared_types::{BrowserMessage, NavigationInitiator, ResourceType, TabId, RequestId};
 //! use url::Url;
 //!
 //! // Create a navigate request
 //! let msg = BrowserMessage::NavigateRequest {
 //!     tab_id: 1,
 //!     url: Url::parse("https://example.com").unwrap(),
+//!     initiator: NavigationInitiator::UserTyped,
 //! };
 //!
 //! // Check resource type
 //! let resource = ResourceType::Script;
 //! ```
 
+pub mod environment;
 pub mod errors;
+pub mod origin;
+pub mod psl;
+pub mod trace;
 pub mod types;
 
 // Re-export main types for convenience
+pub use environment::{
+    Clock, Environment, IdGenerator, MockClock, SequentialIds, Sleep, SystemClock, SystemIds,
+};
 pub use errors::{BrowserError, Result};
-pub use types::{BrowserMessage, RequestId, ResourceType, TabId};
+pub use origin::{registrable_domain, Origin, Site};
+pub use psl::{PublicSuffixList, PUBLIC_SUFFIX_LIST_FILE};
+pub use trace::{TraceContext, TraceGuard, TracedMessage};
+pub use types::{
+    BrowserMessage, DocumentChunk, FilterListInfo, FilterListSource, FilterListSubscription,
+    FindOptions, LinkPreview, MessageKind, NavigationInitiator, OriginUsage, RequestId,
+    ResourceType, StorageKind, StorageReport, SubscriptionId, TabId,
+};
 
 #[cfg(test)]
 mod tests {
@@ -108,12 +123,14 @@ mod tests {
         let msg = BrowserMessage::NavigateRequest {
             tab_id: 1,
             url: url.clone(),
+            initiator: NavigationInitiator::UserTyped,
         };
 
         match msg {
             BrowserMessage::NavigateRequest {
                 tab_id,
                 url: msg_url,
+                ..
             } => {
                 assert_eq!(tab_id, 1);
                 assert_eq!(msg_url, url);
@@ -167,18 +184,39 @@ mod tests {
         let msg = BrowserMessage::HttpRequest {
             request_id: 123,
             url: url.clone(),
+            method: "POST".to_string(),
             headers: headers.clone(),
+            body: Some(b"{}".to_vec()),
+            initiator: Some(Url::parse("https://example.com/page").unwrap()),
         };
 
         match msg {
             BrowserMessage::HttpRequest {
                 request_id,
                 url: msg_url,
+                method,
                 headers: msg_headers,
+                body,
+                initiator,
             } => {
                 assert_eq!(request_id, 123);
                 assert_eq!(msg_url, url);
+                assert_eq!(method, "POST");
                 assert_eq!(msg_headers, headers);
+                assert_eq!(body.as_deref(), Some(&b"{}"[..]));
+                assert_eq!(initiator.unwrap().host_str(), Some("example.com"));
+            }
+            _ => panic!("Expected HttpRequest"),
+        }
+    }
+
+    #[test]
+    fn test_browser_message_http_request_defaults_to_get() {
+        let json = r#"{"HttpRequest":{"request_id":1,"url":"https://example.com/","headers":{}}}"#;
+        match serde_json::from_str(json).unwrap() {
+            BrowserMessage::HttpRequest { method, body, .. } => {
+                assert_eq!(method, "GET");
+                assert_eq!(body, None);
             }
             _ => panic!("Expected HttpRequest"),
         }
@@ -187,9 +225,14 @@ mod tests {
     #[test]
     fn test_browser_message_http_response() {
         let body = vec![5, 6, 7, 8];
+        let mut headers = HashMap::new();
+        headers.insert("content-type".to_string(), "text/html".to_string());
         let msg = BrowserMessage::HttpResponse {
             request_id: 123,
             status: 200,
+            headers: headers.clone(),
+            final_url: Url::parse("https://example.com/").unwrap(),
+            from_cache: true,
             body: body.clone(),
         };
 
@@ -197,10 +240,16 @@ mod tests {
             BrowserMessage::HttpResponse {
                 request_id,
                 status,
+                headers: msg_headers,
+                final_url,
+                from_cache,
                 body: msg_body,
             } => {
                 assert_eq!(request_id, 123);
                 assert_eq!(status, 200);
+                assert_eq!(msg_headers, headers);
+                assert_eq!(final_url.as_str(), "https://example.com/");
+                assert!(from_cache);
                 assert_eq!(msg_body, body);
             }
             _ => panic!("Expected HttpResponse"),
@@ -213,15 +262,18 @@ mod tests {
         let msg = BrowserMessage::ShouldBlock {
             url: url.clone(),
             resource_type: ResourceType::Script,
+            tab_id: Some(3),
         };
 
         match msg {
             BrowserMessage::ShouldBlock {
                 url: msg_url,
                 resource_type,
+                tab_id,
             } => {
                 assert_eq!(msg_url, url);
                 assert!(matches!(resource_type, ResourceType::Script));
+                assert_eq!(tab_id, Some(3));
             }
             _ => panic!("Expected ShouldBlock"),
         }
@@ -283,6 +335,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_browser_message_content_settings_changed() {
+        let msg = BrowserMessage::ContentSettingsChanged {
+            origin: "https://example.com".to_string(),
+        };
+
+        let json = serde_json::to_string(&msg).unwrap();
+        let decoded: BrowserMessage = serde_json::from_str(&json).unwrap();
+        match decoded {
+            BrowserMessage::ContentSettingsChanged { origin } => {
+                assert_eq!(origin, "https://example.com");
+            }
+            _ => panic!("Expected ContentSettingsChanged"),
+        }
+    }
+
+    #[test]
+    fn test_browser_message_filter_subscription_commands() {
+        let msg = BrowserMessage::SetFilterSubscriptionEnabled {
+            id: 2,
+            enabled: false,
+        };
+
+        let json = serde_json::to_string(&msg).unwrap();
+        let decoded: BrowserMessage = serde_json::from_str(&json).unwrap();
+        assert!(matches!(
+            decoded,
+            BrowserMessage::SetFilterSubscriptionEnabled {
+                id: 2,
+                enabled: false
+            }
+        ));
+    }
+
     #[test]
     fn test_browser_message_switch_tab() {
         let msg = BrowserMessage::SwitchTab { tab_id: 3 };
@@ -340,20 +426,46 @@ mod tests {
     #[test]
     fn test_browser_message_serialization() {
         let url = Url::parse("https://example.com").unwrap();
-        let msg = BrowserMessage::NavigateRequest { tab_id: 1, url };
+        let msg = BrowserMessage::NavigateRequest {
+            tab_id: 1,
+            url,
+            initiator: NavigationInitiator::UserTyped,
+        };
 
         let json = serde_json::to_string(&msg).unwrap();
         assert!(json.contains("NavigateRequest"));
     }
 
+    #[test]
+    fn test_navigate_request_initiator_round_trip() {
+        let from = Url::parse("http://example.com/old").unwrap();
+        let msg = BrowserMessage::NavigateRequest {
+            tab_id: 1,
+            url: Url::parse("https://example.com/new").unwrap(),
+            initiator: NavigationInitiator::Redirect { from: from.clone() },
+        };
+
+        let json = serde_json::to_string(&msg).unwrap();
+        match serde_json::from_str(&json).unwrap() {
+            BrowserMessage::NavigateRequest { initiator, .. } => {
+                assert_eq!(initiator, NavigationInitiator::Redirect { from });
+            }
+            _ => panic!("Expected NavigateRequest"),
+        }
+    }
+
     #[test]
     fn test_browser_message_deserialization() {
         let json = r#"{"NavigateRequest":{"tab_id":1,"url":"https://example.com/"}}"#;
         let msg: BrowserMessage = serde_json::from_str(json).unwrap();
 
+        // Messages without an initiator (older producers) still parse
         match msg {
-            BrowserMessage::NavigateRequest { tab_id, .. } => {
+            BrowserMessage::NavigateRequest {
+                tab_id, initiator, ..
+            } => {
                 assert_eq!(tab_id, 1);
+                assert_eq!(initiator, NavigationInitiator::UserTyped);
             }
             _ => panic!("Expected NavigateRequest"),
         }
@@ -386,6 +498,34 @@ mod tests {
         assert_send_sync::<BrowserMessage>();
     }
 
+    #[test]
+    fn test_browser_message_kind_and_tab() {
+        let url = Url::parse("https://example.com").unwrap();
+        let request = BrowserMessage::HttpRequest {
+            request_id: 1,
+            url: url.clone(),
+            method: "GET".to_string(),
+            headers: HashMap::new(),
+            body: None,
+            initiator: None,
+        };
+        assert_eq!(request.kind(), MessageKind::Http);
+        assert_eq!(request.tab_id(), None);
+
+        let close = BrowserMessage::CloseTab { tab_id: 4 };
+        assert_eq!(close.kind(), MessageKind::Tab);
+        assert_eq!(close.tab_id(), Some(4));
+
+        let block = BrowserMessage::ShouldBlock {
+            url,
+            resource_type: ResourceType::Image,
+            tab_id: Some(2),
+        };
+        assert_eq!(block.kind(), MessageKind::Adblock);
+        assert_eq!(block.tab_id(), Some(2));
+        assert_eq!(BrowserMessage::Shutdown.kind(), MessageKind::System);
+    }
+
     // ========================================
     // RED PHASE: Tests for Type Aliases
     // ========================================
@@ -0,0 +1,318 @@
+//! Origins and sites
+//!
+//! An [`Origin`] is the scheme, host and port a URL's content runs under;
+//! the host is normalized the way URLs are parsed (lowercase, punycode for
+//! internationalized names) and the port defaults by scheme, so
+//! `HTTPS://Bücher.example:443/` and `https://xn--bcher-kva.example` are
+//! the same origin.
+//!
+//! Two origins are *same site* when their schemes match and their hosts
+//! share a registrable domain: the host's public suffix plus one label,
+//! found with the Public Suffix List (see [`crate::psl`]). IP addresses
+//! and `localhost` are their own registrable domain. A host that is itself
+//! a public suffix, such as `github.io` or a single label like `intranet`,
+//! has none and is a site on its own.
+
+use crate::errors::{BrowserError, Result};
+use crate::psl::PublicSuffixList;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+use url::{Host, Url};
+
+/// Scheme, host and port of a URL
+///
+/// Serialized as text, e.g. `"https://example.com:8443"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Origin {
+    scheme: String,
+    host: String,
+    port: u16,
+}
+
+impl Origin {
+    /// Origin of a URL
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Any URL; `blob:` URLs have the origin of the URL inside
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` for URLs whose origin is opaque (`about:`, `data:`,
+    /// `file:` and other schemes without a host and default port).
+    pub fn from_url(url: &Url) -> Option<Self> {
+        match url.origin() {
+            url::Origin::Tuple(scheme, host, port) => Some(Self {
+                scheme,
+                host: host.to_string(),
+                port,
+            }),
+            url::Origin::Opaque(_) => None,
+        }
+    }
+
+    /// Scheme, e.g. `https`
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// Host: a punycode domain, an IPv4 address or a bracketed IPv6 address
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Port, the scheme's default unless the URL gave another
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Whether the host is an IP address
+    pub fn is_ip(&self) -> bool {
+        self.host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .is_ok()
+    }
+
+    /// Registrable domain of the host (see [`registrable_domain`])
+    pub fn registrable_domain(&self) -> Option<String> {
+        registrable_domain(&self.host)
+    }
+
+    /// Site the origin belongs to
+    pub fn site(&self) -> Site {
+        Site {
+            scheme: self.scheme.clone(),
+            domain: self
+                .registrable_domain()
+                .unwrap_or_else(|| self.host.clone()),
+        }
+    }
+
+    /// Whether both origins belong to the same site
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Origin to compare with
+    pub fn is_same_site(&self, other: &Origin) -> bool {
+        self.site() == other.site()
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://{}", self.scheme, self.host)?;
+        if default_port(&self.scheme) != Some(self.port) {
+            write!(f, ":{}", self.port)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Origin {
+    type Err = BrowserError;
+
+    /// Parse an origin, or the origin of a URL
+    fn from_str(input: &str) -> Result<Self> {
+        Url::parse(input)
+            .ok()
+            .and_then(|url| Self::from_url(&url))
+            .ok_or_else(|| BrowserError::InvalidOrigin(input.to_string()))
+    }
+}
+
+impl TryFrom<String> for Origin {
+    type Error = BrowserError;
+
+    fn try_from(input: String) -> Result<Self> {
+        input.parse()
+    }
+}
+
+impl From<Origin> for String {
+    fn from(origin: Origin) -> Self {
+        origin.to_string()
+    }
+}
+
+/// Scheme and registrable domain that same-site decisions compare
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Site {
+    scheme: String,
+    domain: String,
+}
+
+impl Site {
+    /// Scheme, e.g. `https`
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// Registrable domain, or the whole host if it has none
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+}
+
+impl fmt::Display for Site {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://{}", self.scheme, self.domain)
+    }
+}
+
+/// Registrable domain of a host
+///
+/// The host is normalized as in a URL first, so case, a trailing dot and
+/// Unicode labels do not matter.
+///
+/// # Arguments
+///
+/// * `host` - Host name or IP address (IPv6 in brackets)
+///
+/// # Returns
+///
+/// Returns the public suffix plus one label (`news.bbc.co.uk` gives
+/// `bbc.co.uk`), the address itself for IP addresses, and `localhost` for
+/// `localhost`. Returns `None` for hosts that are public suffixes and for
+/// text that is not a host.
+pub fn registrable_domain(host: &str) -> Option<String> {
+    match Host::parse(host.trim_end_matches('.')).ok()? {
+        Host::Ipv4(address) => Some(address.to_string()),
+        Host::Ipv6(address) => Some(format!("[{}]", address)),
+        Host::Domain(domain) if domain == "localhost" => Some(domain),
+        Host::Domain(domain) => PublicSuffixList::current()
+            .registrable_domain(&domain)
+            .map(str::to_string),
+    }
+}
+
+/// Port a scheme uses when a URL gives none
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        "ftp" => Some(21),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origin(input: &str) -> Origin {
+        input.parse().unwrap()
+    }
+
+    #[test]
+    fn test_from_url_normalizes_host_and_port() {
+        let parsed = origin("HTTPS://Bücher.EXAMPLE:443/path?q#f");
+        assert_eq!(parsed.scheme(), "https");
+        assert_eq!(parsed.host(), "xn--bcher-kva.example");
+        assert_eq!(parsed.port(), 443);
+        assert_eq!(parsed, origin("https://xn--bcher-kva.example"));
+        assert_eq!(parsed.to_string(), "https://xn--bcher-kva.example");
+
+        assert_eq!(
+            origin("http://example.com:8080/").to_string(),
+            "http://example.com:8080"
+        );
+        assert_eq!(
+            origin("blob:https://example.com/1234").to_string(),
+            "https://example.com"
+        );
+        assert!(origin("http://[::1]:3000/").is_ip());
+
+        for opaque in [
+            "about:blank",
+            "data:text/plain,x",
+            "file:///tmp/a",
+            "not a url",
+        ] {
+            assert!(matches!(
+                opaque.parse::<Origin>(),
+                Err(BrowserError::InvalidOrigin(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_registrable_domain_of_literals() {
+        assert_eq!(
+            registrable_domain("192.168.1.20").as_deref(),
+            Some("192.168.1.20")
+        );
+        assert_eq!(registrable_domain("[::1]").as_deref(), Some("[::1]"));
+        assert_eq!(
+            registrable_domain("LOCALHOST.").as_deref(),
+            Some("localhost")
+        );
+        assert_eq!(
+            registrable_domain("News.BBC.co.uk.").as_deref(),
+            Some("bbc.co.uk")
+        );
+        assert_eq!(
+            registrable_domain("www.食狮.公司.cn").as_deref(),
+            Some("xn--85x722f.xn--55qx5d.cn")
+        );
+        assert_eq!(registrable_domain("github.io"), None);
+        assert_eq!(registrable_domain("intranet"), None);
+        assert_eq!(registrable_domain(""), None);
+    }
+
+    #[test]
+    fn test_same_site_matrix() {
+        let cases = [
+            (
+                "https://news.example.com",
+                "https://shop.example.com:8443",
+                true,
+            ),
+            ("https://example.com", "http://example.com", false),
+            ("https://a.bbc.co.uk", "https://b.bbc.co.uk", true),
+            ("https://bbc.co.uk", "https://itv.co.uk", false),
+            ("https://alice.github.io", "https://bob.github.io", false),
+            (
+                "https://alice.github.io",
+                "https://www.alice.github.io",
+                true,
+            ),
+            (
+                "https://a.s3.amazonaws.com",
+                "https://b.s3.amazonaws.com",
+                false,
+            ),
+            ("http://127.0.0.1:3000", "http://127.0.0.1:4000", true),
+            ("http://127.0.0.1", "http://127.0.0.2", false),
+            ("http://localhost:3000", "http://localhost:4000", true),
+            ("http://intranet", "http://intranet:8080", true),
+            ("http://intranet", "http://wiki", false),
+        ];
+        for (a, b, same) in cases {
+            assert_eq!(origin(a).is_same_site(&origin(b)), same, "{} vs {}", a, b);
+            assert_eq!(origin(b).is_same_site(&origin(a)), same, "{} vs {}", b, a);
+        }
+        assert_eq!(
+            origin("https://a.b.bbc.co.uk").site().to_string(),
+            "https://bbc.co.uk"
+        );
+        assert_eq!(origin("http://intranet").site().domain(), "intranet");
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let parsed = origin("https://Example.com:8443/x");
+        let json = serde_json::to_string(&parsed).unwrap();
+        assert_eq!(json, "\"https://example.com:8443\"");
+        assert_eq!(serde_json::from_str::<Origin>(&json).unwrap(), parsed);
+        assert!(serde_json::from_str::<Origin>("\"data:,x\"").is_err());
+
+        let site = parsed.site();
+        let json = serde_json::to_string(&site).unwrap();
+        assert_eq!(serde_json::from_str::<Site>(&json).unwrap(), site);
+    }
+}
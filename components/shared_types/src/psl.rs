@@ -0,0 +1,269 @@
+//! Public Suffix List lookups
+//!
+//! Whether two hosts belong to the same site depends on where the part of
+//! a name that anyone can register under ends: `bbc.co.uk` and
+//! `news.bbc.co.uk` are one site, `alice.github.io` and `bob.github.io`
+//! are two. The [Public Suffix List](https://publicsuffix.org/) records
+//! those suffixes, including private ones such as `github.io`.
+//!
+//! A snapshot of the list (`resources/public_suffix_list.dat`) is compiled
+//! into the binary by `build.rs`. To update it, replace that file with
+//! https://publicsuffix.org/list/public_suffix_list.dat, or build with
+//! `PUBLIC_SUFFIX_LIST` set to the path of a newer copy. A running browser
+//! can also switch to a newer list with [`PublicSuffixList::install`]; the
+//! CLI loads [`PUBLIC_SUFFIX_LIST_FILE`] from the profile directory.
+
+use crate::errors::{BrowserError, Result};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// File name of a newer list in the profile directory
+pub const PUBLIC_SUFFIX_LIST_FILE: &str = "public_suffix_list.dat";
+
+/// Rule flags; keep in step with `build.rs`
+const NORMAL: u8 = 1;
+const WILDCARD: u8 = 2;
+const EXCEPTION: u8 = 4;
+
+/// The embedded snapshot, sorted by name: (punycode name, rule flags)
+static EMBEDDED_RULES: &[(&str, u8)] = include!(concat!(env!("OUT_DIR"), "/public_suffixes.rs"));
+
+static EMBEDDED: OnceLock<Arc<PublicSuffixList>> = OnceLock::new();
+static INSTALLED: RwLock<Option<Arc<PublicSuffixList>>> = RwLock::new(None);
+
+/// A parsed Public Suffix List
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicSuffixList {
+    /// Rule flags by punycode name; wildcard rules (`*.ck`) are kept under
+    /// the name they apply below (`ck`)
+    rules: HashMap<String, u8>,
+}
+
+impl PublicSuffixList {
+    /// The list compiled into the binary
+    pub fn embedded() -> Arc<Self> {
+        EMBEDDED
+            .get_or_init(|| {
+                Arc::new(Self {
+                    rules: EMBEDDED_RULES
+                        .iter()
+                        .map(|(name, flags)| (name.to_string(), *flags))
+                        .collect(),
+                })
+            })
+            .clone()
+    }
+
+    /// The list in use: the installed one, else the embedded snapshot
+    pub fn current() -> Arc<Self> {
+        match INSTALLED.read().unwrap().as_ref() {
+            Some(list) => list.clone(),
+            None => Self::embedded(),
+        }
+    }
+
+    /// Use this list instead of the embedded snapshot from now on
+    pub fn install(self) {
+        *INSTALLED.write().unwrap() = Some(Arc::new(self));
+    }
+
+    /// Parse a list in the `public_suffix_list.dat` format
+    ///
+    /// Comments (`//`) and blank lines are skipped, and only the first word
+    /// of a line is read. Internationalized rules are converted to
+    /// punycode.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - List contents
+    ///
+    /// # Errors
+    ///
+    /// Returns `BrowserError::PublicSuffixList` if a rule is not a domain
+    /// name or the list has no rules.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut rules: HashMap<String, u8> = HashMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let Some(rule) = line.split_whitespace().next() else {
+                continue;
+            };
+            if rule.starts_with("//") {
+                continue;
+            }
+            let (name, flag) = if let Some(name) = rule.strip_prefix('!') {
+                (name, EXCEPTION)
+            } else if let Some(name) = rule.strip_prefix("*.") {
+                (name, WILDCARD)
+            } else {
+                (rule, NORMAL)
+            };
+            let ascii = match url::Host::parse(name) {
+                Ok(url::Host::Domain(domain)) => domain,
+                _ => {
+                    return Err(BrowserError::PublicSuffixList(format!(
+                        "line {}: {:?} is not a domain",
+                        index + 1,
+                        rule
+                    )))
+                }
+            };
+            *rules.entry(ascii).or_default() |= flag;
+        }
+        if rules.is_empty() {
+            return Err(BrowserError::PublicSuffixList(
+                "the list has no rules".to_string(),
+            ));
+        }
+        Ok(Self { rules })
+    }
+
+    /// Read and parse a list file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File in the `public_suffix_list.dat` format
+    ///
+    /// # Errors
+    ///
+    /// Returns `BrowserError::PublicSuffixList` if the file cannot be read
+    /// or parsed.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| BrowserError::PublicSuffixList(format!("{}: {}", path.display(), e)))?;
+        Self::parse(&text)
+    }
+
+    /// Number of names with rules
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Whether the list has no rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Public suffix of a domain name
+    ///
+    /// The longest matching rule wins, exceptions over everything; a name
+    /// no rule matches has its last label as the suffix.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Lowercase punycode domain without a trailing dot
+    pub fn public_suffix<'a>(&self, domain: &'a str) -> &'a str {
+        let starts = label_starts(domain);
+        let count = self.suffix_labels(domain, &starts);
+        &domain[starts[starts.len() - count]..]
+    }
+
+    /// Registrable domain of a domain name: its public suffix plus one
+    /// label
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - Lowercase punycode domain without a trailing dot
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` if the name is itself a public suffix.
+    pub fn registrable_domain<'a>(&self, domain: &'a str) -> Option<&'a str> {
+        let starts = label_starts(domain);
+        let count = self.suffix_labels(domain, &starts);
+        (starts.len() > count).then(|| &domain[starts[starts.len() - count - 1]..])
+    }
+
+    /// Number of labels at the end of `domain` that form its public suffix
+    fn suffix_labels(&self, domain: &str, starts: &[usize]) -> usize {
+        let flags = |name: &str| self.rules.get(name).copied().unwrap_or(0);
+        for (index, &start) in starts.iter().enumerate() {
+            let remaining = starts.len() - index;
+            let name = &domain[start..];
+            if flags(name) & EXCEPTION != 0 {
+                return remaining - 1;
+            }
+            if flags(name) & NORMAL != 0 {
+                return remaining;
+            }
+            if let Some(&parent) = starts.get(index + 1) {
+                if flags(&domain[parent..]) & WILDCARD != 0 {
+                    return remaining;
+                }
+            }
+        }
+        1
+    }
+}
+
+/// Byte offsets at which each label of `domain` starts
+fn label_starts(domain: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(domain.match_indices('.').map(|(index, _)| index + 1))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_list_tricky_suffixes() {
+        let list = PublicSuffixList::embedded();
+        let cases = [
+            // ICANN suffixes of two labels
+            ("www.bbc.co.uk", Some("bbc.co.uk")),
+            ("co.uk", None),
+            ("uk", None),
+            // Private suffixes
+            ("alice.github.io", Some("alice.github.io")),
+            ("github.io", None),
+            ("bucket.s3.amazonaws.com", Some("bucket.s3.amazonaws.com")),
+            ("s3.amazonaws.com", None),
+            ("www.amazonaws.com", Some("amazonaws.com")),
+            // Wildcards and their exceptions
+            ("shop.example.ck", Some("shop.example.ck")),
+            ("example.ck", None),
+            ("www.ck", Some("www.ck")),
+            ("a.b.city.kawasaki.jp", Some("city.kawasaki.jp")),
+            // Internationalized suffix 公司.cn
+            (
+                "xn--85x722f.xn--55qx5d.cn",
+                Some("xn--85x722f.xn--55qx5d.cn"),
+            ),
+            ("xn--55qx5d.cn", None),
+            // Unlisted TLDs fall back to the last label
+            ("a.example.unlisted", Some("example.unlisted")),
+            ("intranet", None),
+        ];
+        for (domain, expected) in cases {
+            assert_eq!(list.registrable_domain(domain), expected, "{}", domain);
+        }
+        assert_eq!(list.public_suffix("shop.example.ck"), "example.ck");
+        assert_eq!(list.public_suffix("news.bbc.co.uk"), "co.uk");
+    }
+
+    #[test]
+    fn test_parse_custom_list() {
+        let list = PublicSuffixList::parse(
+            "// comment\n\ncom\n*.test  trailing words\n!keep.test\nBÜCHER.example\n",
+        )
+        .unwrap();
+        assert_eq!(list.len(), 4);
+        assert_eq!(list.registrable_domain("a.b.test"), Some("a.b.test"));
+        assert_eq!(list.registrable_domain("a.keep.test"), Some("keep.test"));
+        assert_eq!(
+            list.registrable_domain("shop.xn--bcher-kva.example"),
+            Some("shop.xn--bcher-kva.example")
+        );
+
+        assert!(matches!(
+            PublicSuffixList::parse("// nothing\n"),
+            Err(BrowserError::PublicSuffixList(_))
+        ));
+        assert!(matches!(
+            PublicSuffixList::parse("com\nbad/rule\n"),
+            Err(BrowserError::PublicSuffixList(message)) if message.starts_with("line 2:")
+        ));
+    }
+}
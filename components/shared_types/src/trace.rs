@@ -0,0 +1,286 @@
+//! Correlating the messages of one user action
+//!
+//! The component where a user-visible action starts (the shell on a click
+//! or shortcut, webdriver on a command, the CLI on a fetch) opens a trace
+//! with [`TraceContext::root`]. While the returned guard is alive every
+//! message sent on that thread carries the trace, and the message bus
+//! re-enters it (as a child of the message) around each handler call, so
+//! follow-on messages a handler sends belong to the same trace without the
+//! handler doing anything. Work moved to another thread keeps the trace
+//! with [`bind`].
+//!
+//! Entering a context also enters a `tracing` span with a `trace_id`
+//! field, so log lines written while handling can be filtered by trace.
+
+use crate::environment::IdGenerator;
+use crate::types::BrowserMessage;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+
+thread_local! {
+    static CURRENT: Cell<Option<TraceContext>> = const { Cell::new(None) };
+}
+
+/// Where a message sits in a trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TraceContext {
+    /// Identifies the user action every message of the trace belongs to
+    pub trace_id: u64,
+    /// Span (bus message) whose handling caused this one; `None` for the
+    /// message that started the action
+    #[serde(default)]
+    pub parent_span: Option<u64>,
+}
+
+impl TraceContext {
+    /// Start a new trace
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - Source of the trace id
+    pub fn root(ids: &dyn IdGenerator) -> Self {
+        Self {
+            trace_id: ids.next_id(),
+            parent_span: None,
+        }
+    }
+
+    /// The same trace, continued from `span`
+    ///
+    /// # Arguments
+    ///
+    /// * `span` - Span whose handling emits the follow-on messages
+    pub fn child(self, span: u64) -> Self {
+        Self {
+            trace_id: self.trace_id,
+            parent_span: Some(span),
+        }
+    }
+
+    /// The context entered on this thread, if any
+    pub fn current() -> Option<Self> {
+        CURRENT.with(Cell::get)
+    }
+
+    /// The context entered on this thread, or a new trace if there is none
+    ///
+    /// An action started while handling another (a shortcut replayed by a
+    /// script, say) joins the existing trace rather than opening its own.
+    ///
+    /// # Arguments
+    ///
+    /// * `ids` - Source of the trace id when a new trace is needed
+    pub fn current_or_root(ids: &dyn IdGenerator) -> Self {
+        Self::current().unwrap_or_else(|| Self::root(ids))
+    }
+
+    /// Make this the current context until the guard is dropped
+    ///
+    /// The previous context is restored on drop, so guards nest.
+    pub fn enter(self) -> TraceGuard {
+        let previous = CURRENT.with(|current| current.replace(Some(self)));
+        let span = tracing::info_span!(
+            "trace",
+            trace_id = self.trace_id,
+            parent_span = self.parent_span
+        )
+        .entered();
+        TraceGuard {
+            previous,
+            _span: span,
+        }
+    }
+}
+
+/// Keeps a [`TraceContext`] current; see [`TraceContext::enter`]
+#[must_use = "the context is left as soon as the guard is dropped"]
+pub struct TraceGuard {
+    /// Context to restore on drop
+    previous: Option<TraceContext>,
+    /// Span carrying the `trace_id` log field
+    _span: tracing::span::EnteredSpan,
+}
+
+impl Drop for TraceGuard {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.previous));
+    }
+}
+
+/// Wrap `f` so it runs in the context current now, wherever it is called
+///
+/// For handing work to another thread without losing the trace:
+/// `thread::spawn(trace::bind(move || ...))`.
+///
+/// # Arguments
+///
+/// * `f` - Work to run in the captured context
+pub fn bind<F, R>(f: F) -> impl FnOnce() -> R
+where
+    F: FnOnce() -> R,
+{
+    let trace = TraceContext::current();
+    move || {
+        let _trace = trace.map(TraceContext::enter);
+        f()
+    }
+}
+
+/// A message with the trace it belongs to, as carried on the bus
+///
+/// The trace travels beside the message rather than inside each
+/// `BrowserMessage` variant. Encoded messages without a trace, including
+/// bare `BrowserMessage`s from older producers, decode with `trace: None`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "TracedMessageRepr")]
+pub struct TracedMessage {
+    /// The message itself
+    pub message: BrowserMessage,
+    /// Trace the message belongs to, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<TraceContext>,
+}
+
+impl TracedMessage {
+    /// Wrap `message` in the context current on this thread
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - Message about to be sent
+    pub fn current(message: BrowserMessage) -> Self {
+        Self {
+            message,
+            trace: TraceContext::current(),
+        }
+    }
+}
+
+impl From<BrowserMessage> for TracedMessage {
+    fn from(message: BrowserMessage) -> Self {
+        Self {
+            message,
+            trace: None,
+        }
+    }
+}
+
+/// Accepted encodings of a [`TracedMessage`]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TracedMessageRepr {
+    Traced {
+        message: BrowserMessage,
+        #[serde(default)]
+        trace: Option<TraceContext>,
+    },
+    Bare(BrowserMessage),
+}
+
+impl From<TracedMessageRepr> for TracedMessage {
+    fn from(repr: TracedMessageRepr) -> Self {
+        match repr {
+            TracedMessageRepr::Traced { message, trace } => Self { message, trace },
+            TracedMessageRepr::Bare(message) => message.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::SequentialIds;
+
+    #[test]
+    fn test_enter_nests_and_restores() {
+        let ids = SequentialIds::new(7);
+        assert_eq!(TraceContext::current(), None);
+
+        let root = TraceContext::root(&ids);
+        let outer = root.enter();
+        assert_eq!(TraceContext::current_or_root(&ids), root);
+        {
+            let _inner = root.child(3).enter();
+            assert_eq!(
+                TraceContext::current(),
+                Some(TraceContext {
+                    trace_id: 7,
+                    parent_span: Some(3)
+                })
+            );
+        }
+        assert_eq!(TraceContext::current(), Some(root));
+
+        let bound = bind(TraceContext::current);
+        drop(outer);
+        assert_eq!(TraceContext::current(), None);
+        assert_eq!(std::thread::spawn(bound).join().unwrap(), Some(root));
+    }
+
+    #[test]
+    fn test_messages_without_trace_still_decode() {
+        let bare: TracedMessage = serde_json::from_str(r#"{"CloseTab":{"tab_id":4}}"#).unwrap();
+        assert!(matches!(
+            bare.message,
+            BrowserMessage::CloseTab { tab_id: 4 }
+        ));
+        assert_eq!(bare.trace, None);
+
+        let unit: TracedMessage = serde_json::from_str(r#""Shutdown""#).unwrap();
+        assert!(matches!(unit.message, BrowserMessage::Shutdown));
+
+        let untraced: TracedMessage =
+            serde_json::from_str(r#"{"message":{"Reload":{"tab_id":2}}}"#).unwrap();
+        assert_eq!(untraced.trace, None);
+        assert_eq!(
+            serde_json::to_string(&untraced).unwrap(),
+            r#"{"message":{"Reload":{"tab_id":2}}}"#
+        );
+
+        let traced = TracedMessage {
+            message: BrowserMessage::Reload { tab_id: 2 },
+            trace: Some(TraceContext {
+                trace_id: 9,
+                parent_span: None,
+            }),
+        };
+        let decoded: TracedMessage =
+            serde_json::from_str(&serde_json::to_string(&traced).unwrap()).unwrap();
+        assert_eq!(decoded.trace, traced.trace);
+    }
+
+    #[test]
+    fn test_log_lines_carry_trace_id() {
+        #[derive(Clone, Default)]
+        struct Capture(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+        impl std::io::Write for Capture {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("before");
+            let _trace = TraceContext {
+                trace_id: 4242,
+                parent_span: Some(5),
+            }
+            .enter();
+            tracing::info!("during");
+        });
+
+        let output = String::from_utf8(capture.0.lock().unwrap().clone()).unwrap();
+        let line = |text: &str| output.lines().find(|line| line.ends_with(text)).unwrap();
+        assert!(!line("before").contains("trace_id"));
+        assert!(line("during").contains("trace_id=4242"));
+    }
+}
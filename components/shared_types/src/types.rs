@@ -5,6 +5,7 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
 use url::Url;
 
 /// Type alias for tab identifiers
@@ -13,6 +14,9 @@ pub type TabId = u32;
 /// Type alias for request identifiers
 pub type RequestId = u64;
 
+/// Type alias for ad block filter list subscription identifiers
+pub type SubscriptionId = u32;
+
 /// Enum representing different types of web resources
 ///
 /// This is used to categorize resources for blocking and filtering decisions.
@@ -38,6 +42,295 @@ pub enum ResourceType {
     Other,
 }
 
+impl ResourceType {
+    /// Whether a blocked resource of this type can be replaced by a
+    /// click-to-load placeholder
+    ///
+    /// Only element content qualifies (images, media, frames, embeds).
+    /// Scripts and stylesheets are never loaded on a click: half of a
+    /// page's scripts or styles breaks it worse than none.
+    pub fn shows_placeholder(&self) -> bool {
+        matches!(
+            self,
+            ResourceType::Image
+                | ResourceType::Media
+                | ResourceType::Document
+                | ResourceType::Other
+        )
+    }
+}
+
+/// Why a navigation was started
+///
+/// Navigation policy (e.g. which initiators may open `file:` URLs) and
+/// history recording (visit counts, frecency) depend on the initiator.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NavigationInitiator {
+    /// URL typed or pasted into the address bar (also WebDriver navigation)
+    #[default]
+    UserTyped,
+    /// Link followed from page content
+    LinkClick {
+        /// Whether the click came with a user gesture
+        user_gesture: bool,
+    },
+    /// HTTP or meta-refresh redirect
+    Redirect {
+        /// URL that redirected
+        from: Url,
+    },
+    /// Script-driven navigation (`location.href = ...`)
+    Script,
+    /// Tab restored from a previous session
+    SessionRestore,
+    /// Reload of the current entry
+    Reload,
+    /// Back/forward traversal
+    BackForward,
+}
+
+impl NavigationInitiator {
+    /// Whether the user directly asked for this navigation
+    pub fn is_user_initiated(&self) -> bool {
+        matches!(
+            self,
+            NavigationInitiator::UserTyped
+                | NavigationInitiator::LinkClick { user_gesture: true }
+                | NavigationInitiator::SessionRestore
+                | NavigationInitiator::Reload
+                | NavigationInitiator::BackForward
+        )
+    }
+
+    /// Whether the navigation revisits an existing entry rather than
+    /// producing a new visit (history visit counts are not incremented)
+    pub fn is_revisit(&self) -> bool {
+        matches!(
+            self,
+            NavigationInitiator::SessionRestore
+                | NavigationInitiator::Reload
+                | NavigationInitiator::BackForward
+        )
+    }
+}
+
+/// Kind of site data stored for an origin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StorageKind {
+    /// Cookies (from the network stack's cookie store)
+    Cookies,
+    /// `localStorage` / `sessionStorage`
+    LocalStorage,
+    /// IndexedDB databases
+    IndexedDb,
+    /// Cache API storage
+    CacheStorage,
+}
+
+impl StorageKind {
+    /// All storage kinds
+    pub const ALL: [StorageKind; 4] = [
+        StorageKind::Cookies,
+        StorageKind::LocalStorage,
+        StorageKind::IndexedDb,
+        StorageKind::CacheStorage,
+    ];
+
+    /// Human-readable label
+    pub fn label(self) -> &'static str {
+        match self {
+            StorageKind::Cookies => "Cookies",
+            StorageKind::LocalStorage => "Local storage",
+            StorageKind::IndexedDb => "IndexedDB",
+            StorageKind::CacheStorage => "Cache storage",
+        }
+    }
+}
+
+/// Site data stored by a single origin
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OriginUsage {
+    /// Origin (e.g. `https://example.com`)
+    pub origin: String,
+    /// Kinds of data present
+    pub kinds: Vec<StorageKind>,
+    /// Bytes used by webview storage
+    pub bytes: u64,
+    /// Number of cookies held for the origin
+    pub cookies: u32,
+}
+
+/// Per-origin storage snapshot, as shown on `about:storage`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageReport {
+    /// Origins, largest first
+    pub origins: Vec<OriginUsage>,
+    /// Why webview storage could not be enumerated, if it could not
+    ///
+    /// When set, `origins` only reflects cookie counts.
+    pub unsupported: Option<String>,
+}
+
+/// Where a cached filter list came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FilterListSource {
+    /// Compiled into the binary; replaced by the first download
+    Seed,
+    /// Downloaded (or placed in the filter list directory by hand)
+    Fetched,
+}
+
+impl FilterListSource {
+    /// Human-readable label
+    pub fn label(self) -> &'static str {
+        match self {
+            FilterListSource::Seed => "seed",
+            FilterListSource::Fetched => "fetched",
+        }
+    }
+}
+
+/// A cached filter list, as shown on `about:diagnostics`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilterListInfo {
+    /// List name (file name without extension)
+    pub name: String,
+    /// Provenance of the rules
+    pub source: FilterListSource,
+    /// Number of rules in the list
+    pub rules: usize,
+}
+
+/// A subscribed ad block filter list, as shown on `about:adblock`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilterListSubscription {
+    /// Subscription identifier, stable across restarts
+    pub id: SubscriptionId,
+    /// List title from its `! Title:` header (the URL until first fetched)
+    pub title: String,
+    /// Download URL
+    pub url: String,
+    /// Whether the list's rules are compiled into the engine
+    pub enabled: bool,
+    /// Last successful download, in seconds since the Unix epoch
+    pub last_updated: Option<u64>,
+    /// Number of rules in the downloaded list
+    pub rule_count: usize,
+    /// Refresh interval from the list's `! Expires:` header, in seconds
+    pub expires_hint: Option<u64>,
+    /// `ETag` of the downloaded list, sent back as `If-None-Match`
+    #[serde(default)]
+    pub etag: Option<String>,
+    /// `Last-Modified` of the downloaded list, sent back as
+    /// `If-Modified-Since`
+    #[serde(default)]
+    pub last_modified: Option<String>,
+}
+
+/// Title and summary of a hovered link's target
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkPreview {
+    /// Open Graph title, or the document's `<title>`
+    pub title: String,
+    /// Open Graph description
+    pub description: Option<String>,
+    /// Open Graph site name
+    pub site_name: Option<String>,
+}
+
+impl LinkPreview {
+    /// One-line form for the status bar: "Title — site"
+    ///
+    /// Without an Open Graph site name, the host of `url` stands in.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - URL the preview is for
+    pub fn status_text(&self, url: &str) -> String {
+        let host = url::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string));
+        match self.site_name.clone().or(host) {
+            Some(site) => format!("{} \u{2014} {}", self.title, site),
+            None => self.title.clone(),
+        }
+    }
+}
+
+/// How find in page matches the query
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FindOptions {
+    /// Match letter case exactly; otherwise "Rust" also finds "rust"
+    pub case_sensitive: bool,
+    /// Only match whole words; otherwise "cat" also finds "concatenate"
+    pub whole_word: bool,
+}
+
+/// A piece of a document being streamed to a webview
+///
+/// A stream is `Begin`, any number of `Data`, then `End` or `Failed`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DocumentChunk {
+    /// A new document starts; earlier content is replaced
+    Begin {
+        /// Document URL, used as the base URL
+        url: String,
+    },
+    /// Next bytes of the document
+    Data(Vec<u8>),
+    /// The document is complete
+    End,
+    /// The download failed part-way; what was delivered stays visible
+    Failed {
+        /// Error shown in the error bar
+        reason: String,
+    },
+}
+
+/// Category of a [`BrowserMessage`], for subscribing to related messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MessageKind {
+    /// Loading pages in a tab: navigation, history, reloads, load progress,
+    /// page titles and find in page
+    Navigation,
+    /// HTTP requests and responses
+    Http,
+    /// Block decisions, filter lists and per-site ad block settings
+    Adblock,
+    /// Creating, closing, switching, pinning and zooming tabs
+    Tab,
+    /// Download progress and results
+    Download,
+    /// Bookmarking tabs and opening bookmark folders
+    Bookmark,
+    /// Bandwidth budget, offline mode and DNS prefetching
+    Network,
+    /// Stored site data and per-site content settings
+    SiteData,
+    /// Browser-wide commands and state: shutdown and theme changes
+    System,
+}
+
+impl MessageKind {
+    /// All message kinds
+    pub const ALL: [MessageKind; 9] = [
+        MessageKind::Navigation,
+        MessageKind::Http,
+        MessageKind::Adblock,
+        MessageKind::Tab,
+        MessageKind::Download,
+        MessageKind::Bookmark,
+        MessageKind::Network,
+        MessageKind::SiteData,
+        MessageKind::System,
+    ];
+}
+
+/// Method of an `HttpRequest` message that does not name one
+fn default_http_method() -> String {
+    "GET".to_string()
+}
+
 /// Enum representing all possible messages that can be sent between browser components
 ///
 /// This enum serves as the communication protocol for the modular browser architecture.
@@ -49,6 +342,10 @@ pub enum BrowserMessage {
         tab_id: u32,
         /// The URL to navigate to
         url: Url,
+        /// Why the navigation was started (defaults to `UserTyped` for
+        /// messages that predate the field)
+        #[serde(default)]
+        initiator: NavigationInitiator,
     },
 
     /// Response with the content of a navigation
@@ -73,8 +370,18 @@ pub enum BrowserMessage {
         request_id: u64,
         /// URL to request
         url: Url,
+        /// HTTP method, e.g. `GET` or `POST`
+        #[serde(default = "default_http_method")]
+        method: String,
         /// HTTP headers
         headers: HashMap<String, String>,
+        /// Request body, e.g. an encoded form
+        #[serde(default)]
+        body: Option<Vec<u8>>,
+        /// URL of the top-level document the request is for; `None` for a
+        /// navigation. Decides whether the request is third-party.
+        #[serde(default)]
+        initiator: Option<Url>,
     },
 
     /// HTTP response message
@@ -83,6 +390,12 @@ pub enum BrowserMessage {
         request_id: u64,
         /// HTTP status code
         status: u16,
+        /// Response headers; those stored with the entry for a cached response
+        headers: HashMap<String, String>,
+        /// URL the response came from, after redirects
+        final_url: Url,
+        /// Whether the response was served from the HTTP cache
+        from_cache: bool,
         /// Response body
         body: Vec<u8>,
     },
@@ -93,6 +406,9 @@ pub enum BrowserMessage {
         url: Url,
         /// Type of resource
         resource_type: ResourceType,
+        /// Tab the resource is loaded for, so blocked requests are counted
+        /// per tab
+        tab_id: Option<TabId>,
     },
 
     /// Decision on whether to block a resource
@@ -124,12 +440,26 @@ pub enum BrowserMessage {
     /// Request to shut down the browser
     Shutdown,
 
+    /// The browser's color theme changed
+    ThemeChanged {
+        /// The theme setting: "light", "dark" or "auto"
+        theme: String,
+        /// Whether the theme now drawn is dark (for "auto", the system's)
+        dark: bool,
+    },
+
     /// Request to reload a tab
     Reload {
         /// Tab ID to reload
         tab_id: u32,
     },
 
+    /// Request to reload a tab, bypassing the HTTP cache
+    ReloadIgnoreCache {
+        /// Tab ID to reload
+        tab_id: u32,
+    },
+
     /// Request to go back in history
     GoBack {
         /// Tab ID to navigate back
@@ -141,11 +471,414 @@ pub enum BrowserMessage {
         /// Tab ID to navigate forward
         tab_id: u32,
     },
+
+    /// Request to clear site data for an origin
+    ClearOriginStorage {
+        /// Origin whose data should be cleared
+        origin: String,
+        /// Kinds of data to clear
+        kinds: Vec<StorageKind>,
+    },
+
+    /// Per-site content settings (JavaScript, images) changed for an origin;
+    /// tabs showing that origin should reload to apply them
+    ContentSettingsChanged {
+        /// Origin whose settings changed
+        origin: String,
+    },
+
+    /// Request to enable or disable an ad block filter list subscription
+    SetFilterSubscriptionEnabled {
+        /// Subscription to change
+        id: SubscriptionId,
+        /// Whether the list's rules should apply
+        enabled: bool,
+    },
+
+    /// Request to refresh ad block filter list subscriptions
+    UpdateFilterSubscriptions {
+        /// Refresh every list, even those whose `Expires` interval has not
+        /// elapsed
+        force: bool,
+    },
+
+    /// Filter list subscriptions were refreshed and the ad blocker now
+    /// uses the new rules
+    FilterListsUpdated {
+        /// Rules compiled into the ad blocker
+        rule_count: usize,
+    },
+
+    /// Turn ad blocking off (or back on) for a site, e.g. from a toolbar
+    /// button
+    ToggleAdblockForSite {
+        /// Site domain; its subdomains are included
+        domain: String,
+        /// Whether the site's pages may load everything
+        allowed: bool,
+    },
+
+    /// The session's network traffic crossed `network.metered_budget_mb`;
+    /// downloads and prefetching are paused until `ResumeBackgroundTransfers`
+    BandwidthBudgetExceeded {
+        /// Bytes sent and received this session
+        used_bytes: u64,
+        /// The configured budget in bytes
+        budget_bytes: u64,
+    },
+
+    /// The user acknowledged the bandwidth budget alert; resume downloads
+    /// and prefetching
+    ResumeBackgroundTransfers,
+
+    /// The user switched offline mode; while offline the network stack
+    /// answers only from the cache
+    SetOffline {
+        /// Whether to go offline
+        offline: bool,
+    },
+
+    /// Bytes a download has saved so far; sent as it starts and then at
+    /// most every 256 KiB
+    DownloadProgress {
+        /// Download, as returned by `NetworkStack::start_download`
+        id: u64,
+        /// Bytes saved, including those saved before a pause
+        received: u64,
+        /// Size of the whole file, if the server said
+        total: Option<u64>,
+    },
+
+    /// A download finished and was moved into place
+    DownloadComplete {
+        /// Download, as returned by `NetworkStack::start_download`
+        id: u64,
+        /// File the download was saved to
+        path: PathBuf,
+    },
+
+    /// A download failed; its partial file was removed
+    DownloadFailed {
+        /// Download, as returned by `NetworkStack::start_download`
+        id: u64,
+        /// What went wrong
+        reason: String,
+    },
+
+    /// A tab's page title changed (from the document's `<title>` or a later
+    /// `document.title` update)
+    PageTitleChanged {
+        /// Tab whose page changed its title
+        tab_id: u32,
+        /// Sanitized title; empty if the page has none
+        title: String,
+    },
+
+    /// A tab moved within its back/forward stack (back, forward, or a
+    /// jump to an entry)
+    HistoryTraversed {
+        /// Tab that moved
+        tab_id: u32,
+        /// URL of the entry now current
+        url: String,
+        /// Title the entry was last visited with; empty if unknown
+        title: String,
+        /// Whether there are entries before the current one
+        can_go_back: bool,
+        /// Whether there are entries after the current one
+        can_go_forward: bool,
+    },
+
+    /// A tab navigated to a new entry; sent after every committed
+    /// navigation so the toolbar can follow the active tab
+    TabStateChanged {
+        /// Tab that navigated
+        tab_id: u32,
+        /// Whether there are entries before the current one
+        can_go_back: bool,
+        /// Whether there are entries after the current one
+        can_go_forward: bool,
+        /// URL of the entry now current
+        url: String,
+    },
+
+    /// The user clicked a blocked-content placeholder; load that exact URL
+    /// in the tab for the rest of the session
+    LoadBlockedResource {
+        /// Tab showing the placeholder
+        tab_id: u32,
+        /// URL of the blocked resource
+        url: String,
+    },
+
+    /// A hovered link's preview is available
+    LinkPreviewReady {
+        /// Tab the link was hovered in
+        tab_id: u32,
+        /// Link target, as hovered
+        url: String,
+        /// Title and summary of the target
+        preview: LinkPreview,
+    },
+
+    /// The pointer moved onto or off a link
+    LinkHovered {
+        /// Tab the pointer is in
+        tab_id: u32,
+        /// Target of the hovered link; `None` once the pointer left it
+        url: Option<Url>,
+    },
+
+    /// Find in page matched the query in a tab
+    FindResult {
+        /// Tab searched
+        tab_id: u32,
+        /// Position of the highlighted match, counting from 1; 0 if nothing
+        /// matched
+        current: usize,
+        /// Number of matches
+        total: usize,
+    },
+
+    /// Links to these hosts came into view; resolve them ahead of a click
+    PrefetchDns {
+        /// Tab showing the links
+        tab_id: u32,
+        /// Host names of the links
+        hosts: Vec<String>,
+    },
+
+    /// A pinned tab tried to leave its site; open the URL in a new regular
+    /// tab instead
+    OpenInNewTab {
+        /// Pinned tab the navigation started in
+        opener: u32,
+        /// URL to open
+        url: Url,
+        /// Why the navigation was started
+        initiator: NavigationInitiator,
+    },
+
+    /// A tab was pinned or unpinned
+    TabPinChanged {
+        /// Tab that changed
+        tab_id: u32,
+        /// Whether the tab is now pinned
+        pinned: bool,
+    },
+
+    /// A tab's page zoom changed
+    ZoomChanged {
+        /// Tab that changed
+        tab_id: u32,
+        /// New zoom factor (1.0 = 100%)
+        level: f64,
+    },
+
+    /// Closing a pinned tab needs the user's confirmation; answer with
+    /// `PinnedTabCloseConfirmed`
+    ConfirmClosePinnedTab {
+        /// Pinned tab about to be closed
+        tab_id: u32,
+    },
+
+    /// The user confirmed closing a pinned tab
+    PinnedTabCloseConfirmed {
+        /// Tab to close
+        tab_id: u32,
+    },
+
+    /// A background tab was discarded or hibernated; its downloads pause
+    /// and its prefetching stops until `TabResumed`
+    TabSuspended {
+        /// Tab that was suspended
+        tab_id: u32,
+    },
+
+    /// A suspended tab was activated and reloads
+    TabResumed {
+        /// Tab that was resumed
+        tab_id: u32,
+    },
+
+    /// A tab's page load progressed; sent at most about ten times a second
+    /// per tab, and with 1.0 once the load completes
+    LoadProgress {
+        /// Tab that is loading
+        tab_id: u32,
+        /// Estimated progress, 0.0 to 1.0; never lower than the last value
+        /// sent for the same load
+        progress: f32,
+    },
+
+    /// Request to bookmark a window's tabs into a new folder; answered with
+    /// `TabsBookmarked`
+    BookmarkTabs {
+        /// Folder name; a number is added if the name is taken
+        folder: String,
+        /// URL and title of each tab, in tab strip order
+        tabs: Vec<(Url, String)>,
+    },
+
+    /// Request to bookmark a page
+    AddBookmark {
+        /// Page to bookmark
+        url: Url,
+        /// Bookmark title
+        title: String,
+    },
+
+    /// Tabs were bookmarked into a new folder
+    TabsBookmarked {
+        /// Folder the bookmarks were filed in
+        folder: String,
+        /// Bookmarks created
+        added: usize,
+        /// Tabs skipped because their page was already bookmarked
+        already_bookmarked: usize,
+        /// Tabs skipped because they show an internal `about:` page
+        internal: usize,
+    },
+
+    /// Request to open every bookmark in a folder as background tabs
+    OpenBookmarkFolder {
+        /// Folder to open
+        folder: String,
+        /// The user confirmed opening a large folder
+        confirmed: bool,
+    },
+
+    /// Opening a folder needs the user's confirmation; answer with
+    /// `OpenBookmarkFolder` and `confirmed` set
+    ConfirmOpenBookmarkFolder {
+        /// Folder about to be opened
+        folder: String,
+        /// Number of tabs it would open
+        count: usize,
+    },
+
+    /// Open pages as background tabs after the active tab
+    OpenTabsInBackground {
+        /// URL and title of each page, in the order to open them
+        tabs: Vec<(Url, String)>,
+    },
+
+    /// Ask for history entries matching text typed in the address bar;
+    /// answered with `HistorySearchResponse` when sent as a request
+    HistorySearchRequest {
+        /// Text to find in entry URLs and titles
+        query: String,
+        /// Maximum number of entries to return
+        limit: usize,
+    },
+
+    /// History entries matching a `HistorySearchRequest`
+    HistorySearchResponse {
+        /// The query searched for
+        query: String,
+        /// URL and title of each match, most recently visited first
+        entries: Vec<(String, String)>,
+    },
+}
+
+impl BrowserMessage {
+    /// The category the message belongs to
+    pub fn kind(&self) -> MessageKind {
+        use BrowserMessage::*;
+        match self {
+            NavigateRequest { .. }
+            | NavigateResponse { .. }
+            | NavigateError { .. }
+            | Reload { .. }
+            | ReloadIgnoreCache { .. }
+            | GoBack { .. }
+            | GoForward { .. }
+            | PageTitleChanged { .. }
+            | HistoryTraversed { .. }
+            | TabStateChanged { .. }
+            | HistorySearchRequest { .. }
+            | HistorySearchResponse { .. }
+            | LinkPreviewReady { .. }
+            | LinkHovered { .. }
+            | FindResult { .. }
+            | LoadProgress { .. } => MessageKind::Navigation,
+            HttpRequest { .. } | HttpResponse { .. } => MessageKind::Http,
+            ShouldBlock { .. }
+            | BlockDecision { .. }
+            | SetFilterSubscriptionEnabled { .. }
+            | UpdateFilterSubscriptions { .. }
+            | FilterListsUpdated { .. }
+            | ToggleAdblockForSite { .. }
+            | LoadBlockedResource { .. } => MessageKind::Adblock,
+            CreateTab { .. }
+            | CloseTab { .. }
+            | SwitchTab { .. }
+            | OpenInNewTab { .. }
+            | TabPinChanged { .. }
+            | ZoomChanged { .. }
+            | ConfirmClosePinnedTab { .. }
+            | PinnedTabCloseConfirmed { .. }
+            | TabSuspended { .. }
+            | TabResumed { .. }
+            | OpenTabsInBackground { .. } => MessageKind::Tab,
+            DownloadProgress { .. } | DownloadComplete { .. } | DownloadFailed { .. } => {
+                MessageKind::Download
+            }
+            BookmarkTabs { .. }
+            | AddBookmark { .. }
+            | TabsBookmarked { .. }
+            | OpenBookmarkFolder { .. }
+            | ConfirmOpenBookmarkFolder { .. } => MessageKind::Bookmark,
+            BandwidthBudgetExceeded { .. }
+            | ResumeBackgroundTransfers
+            | SetOffline { .. }
+            | PrefetchDns { .. } => MessageKind::Network,
+            ClearOriginStorage { .. } | ContentSettingsChanged { .. } => MessageKind::SiteData,
+            Shutdown | ThemeChanged { .. } => MessageKind::System,
+        }
+    }
+
+    /// The tab the message is about, if any
+    ///
+    /// For `OpenInNewTab` this is the tab the navigation started in.
+    pub fn tab_id(&self) -> Option<TabId> {
+        use BrowserMessage::*;
+        match self {
+            NavigateRequest { tab_id, .. }
+            | NavigateResponse { tab_id, .. }
+            | NavigateError { tab_id, .. }
+            | CloseTab { tab_id }
+            | SwitchTab { tab_id }
+            | Reload { tab_id }
+            | ReloadIgnoreCache { tab_id }
+            | GoBack { tab_id }
+            | GoForward { tab_id }
+            | PageTitleChanged { tab_id, .. }
+            | HistoryTraversed { tab_id, .. }
+            | TabStateChanged { tab_id, .. }
+            | LoadBlockedResource { tab_id, .. }
+            | LinkPreviewReady { tab_id, .. }
+            | LinkHovered { tab_id, .. }
+            | FindResult { tab_id, .. }
+            | PrefetchDns { tab_id, .. }
+            | OpenInNewTab { opener: tab_id, .. }
+            | TabPinChanged { tab_id, .. }
+            | ZoomChanged { tab_id, .. }
+            | ConfirmClosePinnedTab { tab_id }
+            | PinnedTabCloseConfirmed { tab_id }
+            | TabSuspended { tab_id }
+            | TabResumed { tab_id }
+            | LoadProgress { tab_id, .. } => Some(*tab_id),
+            ShouldBlock { tab_id, .. } => *tab_id,
+            _ => None,
+        }
+    }
 }
 
 // Ensure Send + Sync for thread safety
 static_assertions::assert_impl_all!(ResourceType: Send, Sync);
 static_assertions::assert_impl_all!(BrowserMessage: Send, Sync);
+static_assertions::assert_impl_all!(StorageReport: Send, Sync);
 
 #[cfg(test)]
 mod tests {
@@ -157,6 +890,27 @@ mod tests {
         assert_send_sync::<ResourceType>();
     }
 
+    #[test]
+    fn test_placeholder_resource_types() {
+        for ty in [
+            ResourceType::Image,
+            ResourceType::Media,
+            ResourceType::Document,
+            ResourceType::Other,
+        ] {
+            assert!(ty.shows_placeholder(), "{:?}", ty);
+        }
+        for ty in [
+            ResourceType::Script,
+            ResourceType::Stylesheet,
+            ResourceType::Font,
+            ResourceType::Xhr,
+            ResourceType::Websocket,
+        ] {
+            assert!(!ty.shows_placeholder(), "{:?}", ty);
+        }
+    }
+
     #[test]
     fn test_browser_message_is_send_sync() {
         fn assert_send_sync<T: Send + Sync>() {}
@@ -174,4 +928,51 @@ mod tests {
         let id: RequestId = 12345;
         assert_eq!(id, 12345u64);
     }
+
+    #[test]
+    fn test_navigation_initiator_classification() {
+        assert_eq!(
+            NavigationInitiator::default(),
+            NavigationInitiator::UserTyped
+        );
+        assert!(NavigationInitiator::UserTyped.is_user_initiated());
+        assert!(NavigationInitiator::LinkClick { user_gesture: true }.is_user_initiated());
+        assert!(!NavigationInitiator::LinkClick {
+            user_gesture: false
+        }
+        .is_user_initiated());
+        assert!(!NavigationInitiator::Script.is_user_initiated());
+
+        assert!(NavigationInitiator::Reload.is_revisit());
+        assert!(NavigationInitiator::BackForward.is_revisit());
+        assert!(NavigationInitiator::SessionRestore.is_revisit());
+        assert!(!NavigationInitiator::UserTyped.is_revisit());
+        assert!(!NavigationInitiator::Script.is_revisit());
+    }
+
+    #[test]
+    fn test_link_preview_status_text() {
+        let mut preview = LinkPreview {
+            title: "Release notes".to_string(),
+            description: None,
+            site_name: Some("Example Blog".to_string()),
+        };
+        assert_eq!(
+            preview.status_text("https://blog.example/notes"),
+            "Release notes \u{2014} Example Blog"
+        );
+        preview.site_name = None;
+        assert_eq!(
+            preview.status_text("https://blog.example/notes"),
+            "Release notes \u{2014} blog.example"
+        );
+        assert_eq!(preview.status_text("not a url"), "Release notes");
+    }
+
+    #[test]
+    fn test_storage_kind_labels_are_distinct() {
+        let labels: std::collections::HashSet<_> =
+            StorageKind::ALL.iter().map(|k| k.label()).collect();
+        assert_eq!(labels.len(), StorageKind::ALL.len());
+    }
 }
@@ -0,0 +1,609 @@
+//! Access control for the WebDriver HTTP server
+//!
+//! The WebDriver protocol has no authentication of its own and a session
+//! can drive the whole browser, so every request passes through [`enforce`]
+//! before it reaches a handler:
+//!
+//! 1. Unless remote access is allowed, the peer must be on a loopback
+//!    address and the `Host` header must name one (`localhost`,
+//!    `127.0.0.0/8`, `::1`). The header check stops DNS rebinding, where a
+//!    web page resolves its own domain to 127.0.0.1 and posts to the server.
+//! 2. Each peer IP draws from a token bucket; requests beyond it get
+//!    `429 Too Many Requests` with `Retry-After`.
+//! 3. When a bearer token is configured, `Authorization: Bearer <token>`
+//!    must carry it. Allowing remote access without a token generates one.
+//! 4. Request bodies larger than the cap are refused with
+//!    `invalid argument`.
+//!
+//! Every rejection is logged with the peer address. The session limit is
+//! enforced by [`SessionManager`](crate::SessionManager).
+
+use crate::errors::{Error, Result, WebDriverErrorResponse};
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use config_manager::WebDriverSettings;
+use shared_types::Clock;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Port the server listens on unless told otherwise
+pub const DEFAULT_PORT: u16 = 4444;
+
+/// Command-line flag that permits binding to non-loopback interfaces
+pub const ALLOW_REMOTE_FLAG: &str = "--webdriver-allow-remote";
+
+/// Peers tracked by the rate limiter before idle ones are forgotten
+const MAX_TRACKED_PEERS: usize = 4096;
+
+/// How the WebDriver server is exposed and limited
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerConfig {
+    /// Interface to bind to
+    pub bind_address: IpAddr,
+    /// Port to listen on
+    pub port: u16,
+    /// Accept non-loopback peers and Host headers
+    pub allow_remote: bool,
+    /// Bearer token every request must carry
+    pub auth_token: Option<String>,
+    /// Most concurrent sessions
+    pub max_sessions: usize,
+    /// Sustained requests per second per IP; 0 disables the limit
+    pub requests_per_second: u32,
+    /// Requests one IP may make at once before the sustained rate applies
+    pub burst: u32,
+    /// Largest accepted request body in bytes
+    pub max_body_bytes: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: DEFAULT_PORT,
+            allow_remote: false,
+            auth_token: None,
+            max_sessions: 4,
+            requests_per_second: 50,
+            burst: 100,
+            max_body_bytes: 10 * 1024 * 1024,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Build the configuration from the `[webdriver]` config section
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - The `[webdriver]` section
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidArgument` if `bind_address` is not an IP
+    /// address.
+    pub fn from_settings(settings: &WebDriverSettings) -> Result<Self> {
+        let bind_address = settings.bind_address.trim().parse().map_err(|e| {
+            Error::InvalidArgument(format!(
+                "Invalid bind address {}: {}",
+                settings.bind_address, e
+            ))
+        })?;
+
+        Ok(Self {
+            bind_address,
+            port: DEFAULT_PORT,
+            allow_remote: settings.allow_remote,
+            auth_token: settings.auth_token().map(str::to_string),
+            max_sessions: settings.max_sessions as usize,
+            requests_per_second: settings.requests_per_second,
+            burst: settings.burst,
+            max_body_bytes: settings.max_body_kb as usize * 1024,
+        })
+    }
+
+    /// Set the port
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Set the interface to bind to
+    pub fn with_bind_address(mut self, address: IpAddr) -> Self {
+        self.bind_address = address;
+        self
+    }
+
+    /// Set whether remote peers are accepted
+    pub fn with_allow_remote(mut self, allow: bool) -> Self {
+        self.allow_remote = allow;
+        self
+    }
+
+    /// Require `token` on every request
+    pub fn with_auth_token(mut self, token: impl Into<String>) -> Self {
+        self.auth_token = Some(token.into());
+        self
+    }
+
+    /// Set the session limit
+    pub fn with_max_sessions(mut self, max: usize) -> Self {
+        self.max_sessions = max;
+        self
+    }
+
+    /// Set the per-IP rate limit; a rate of 0 disables it
+    pub fn with_rate_limit(mut self, requests_per_second: u32, burst: u32) -> Self {
+        self.requests_per_second = requests_per_second;
+        self.burst = burst;
+        self
+    }
+
+    /// Set the largest accepted request body
+    pub fn with_max_body_bytes(mut self, bytes: usize) -> Self {
+        self.max_body_bytes = bytes;
+        self
+    }
+
+    /// Address the server listens on
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind_address, self.port)
+    }
+
+    /// Apply command-line overrides
+    ///
+    /// Understands `--port <port>`, `--host <address>`,
+    /// `--webdriver-allow-remote` and `--webdriver-token <token>`.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Arguments after the program name
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidArgument` for an unknown option, a missing
+    /// value or a value that does not parse.
+    pub fn apply_args(&mut self, args: &[String]) -> Result<()> {
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .ok_or_else(|| Error::InvalidArgument(format!("{} needs a value", arg)))
+            };
+            match arg.as_str() {
+                "--port" => {
+                    let raw = value()?;
+                    self.port = raw
+                        .parse()
+                        .map_err(|_| Error::InvalidArgument(format!("Invalid port: {}", raw)))?;
+                }
+                "--host" => {
+                    let raw = value()?;
+                    self.bind_address = raw.parse().map_err(|_| {
+                        Error::InvalidArgument(format!("Invalid bind address: {}", raw))
+                    })?;
+                }
+                "--webdriver-token" => self.auth_token = Some(value()?.clone()),
+                ALLOW_REMOTE_FLAG => self.allow_remote = true,
+                other => return Err(Error::InvalidArgument(format!("Unknown option: {}", other))),
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the configuration is safe to serve
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidArgument` if the bind address is not loopback
+    /// and remote access is not allowed, or a limit is zero.
+    pub fn validate(&self) -> Result<()> {
+        if !self.bind_address.is_loopback() && !self.allow_remote {
+            return Err(Error::InvalidArgument(format!(
+                "Binding to {} requires {}",
+                self.bind_address, ALLOW_REMOTE_FLAG
+            )));
+        }
+        if self.max_sessions == 0 {
+            return Err(Error::InvalidArgument(
+                "max_sessions must be greater than 0".to_string(),
+            ));
+        }
+        if self.max_body_bytes == 0 {
+            return Err(Error::InvalidArgument(
+                "max_body_bytes must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Generate a bearer token if remote access is allowed without one
+    ///
+    /// # Returns
+    ///
+    /// Returns the generated token so it can be shown to the user, or None
+    /// if remote access is off or a token was already set.
+    pub fn ensure_token(&mut self) -> Option<String> {
+        if !self.allow_remote || self.auth_token.is_some() {
+            return None;
+        }
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        self.auth_token = Some(token.clone());
+        Some(token)
+    }
+}
+
+/// Requests an IP may still make, refilled at a steady rate
+#[derive(Debug, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn full(capacity: f64, now: Instant) -> Self {
+        Self {
+            tokens: capacity,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant, rate: f64, capacity: f64) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(capacity);
+        self.updated = now;
+    }
+
+    /// Take one token, or report how long until one is available
+    fn take(
+        &mut self,
+        now: Instant,
+        rate: f64,
+        capacity: f64,
+    ) -> std::result::Result<(), Duration> {
+        self.refill(now, rate, capacity);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+}
+
+/// A refused request
+#[derive(Debug)]
+struct Rejection {
+    status: StatusCode,
+    error: Error,
+    retry_after: Option<Duration>,
+}
+
+impl Rejection {
+    fn new(status: StatusCode, error: Error) -> Self {
+        Self {
+            status,
+            error,
+            retry_after: None,
+        }
+    }
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        let status = self.status;
+        let mut response = (status, Json(WebDriverErrorResponse::from(self.error))).into_response();
+        let headers = response.headers_mut();
+        if status == StatusCode::UNAUTHORIZED {
+            headers.insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        }
+        if let Some(wait) = self.retry_after {
+            // Whole seconds, rounded up so a client that obeys never hits
+            // the limit again straight away
+            let secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+            headers.insert(header::RETRY_AFTER, HeaderValue::from(secs.max(1)));
+        }
+        response
+    }
+}
+
+/// Admission checks shared by every request
+#[derive(Debug)]
+pub struct AccessControl {
+    config: ServerConfig,
+    clock: Arc<dyn Clock>,
+    buckets: Mutex<HashMap<IpAddr, TokenBucket>>,
+}
+
+impl AccessControl {
+    /// Create the checks for `config`
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Server configuration
+    /// * `clock` - Time source for the rate limiter
+    pub fn new(config: ServerConfig, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            config,
+            clock,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The configuration being enforced
+    pub fn config(&self) -> &ServerConfig {
+        &self.config
+    }
+
+    async fn admit(
+        &self,
+        peer: Option<SocketAddr>,
+        request: Request,
+    ) -> std::result::Result<Request, Rejection> {
+        self.check_origin(peer, request.headers())?;
+        self.check_rate(peer.map_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED), |addr| addr.ip()))?;
+        self.check_token(request.headers())?;
+        self.limit_body(request).await
+    }
+
+    fn check_origin(
+        &self,
+        peer: Option<SocketAddr>,
+        headers: &HeaderMap,
+    ) -> std::result::Result<(), Rejection> {
+        if self.config.allow_remote {
+            return Ok(());
+        }
+        if let Some(peer) = peer.filter(|peer| !peer.ip().is_loopback()) {
+            return Err(Rejection::new(
+                StatusCode::FORBIDDEN,
+                Error::AccessDenied(format!("Remote peer {} not allowed", peer.ip())),
+            ));
+        }
+
+        let host = headers
+            .get(header::HOST)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("");
+        if is_local_host(host) {
+            Ok(())
+        } else {
+            Err(Rejection::new(
+                StatusCode::FORBIDDEN,
+                Error::AccessDenied(format!("Host header \"{}\" is not a local address", host)),
+            ))
+        }
+    }
+
+    fn check_rate(&self, ip: IpAddr) -> std::result::Result<(), Rejection> {
+        if self.config.requests_per_second == 0 {
+            return Ok(());
+        }
+        let rate = f64::from(self.config.requests_per_second);
+        let capacity = f64::from(self.config.burst.max(1));
+        let now = self.clock.now_instant();
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_TRACKED_PEERS && !buckets.contains_key(&ip) {
+            // Peers whose bucket has refilled have nothing to remember
+            buckets.retain(|_, bucket| {
+                bucket.refill(now, rate, capacity);
+                bucket.tokens < capacity
+            });
+        }
+        buckets
+            .entry(ip)
+            .or_insert_with(|| TokenBucket::full(capacity, now))
+            .take(now, rate, capacity)
+            .map_err(|wait| Rejection {
+                status: StatusCode::TOO_MANY_REQUESTS,
+                error: Error::RateLimited(format!(
+                    "More than {} requests per second from {}",
+                    self.config.requests_per_second, ip
+                )),
+                retry_after: Some(wait),
+            })
+    }
+
+    fn check_token(&self, headers: &HeaderMap) -> std::result::Result<(), Rejection> {
+        let Some(expected) = &self.config.auth_token else {
+            return Ok(());
+        };
+        let presented = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("Bearer"))
+            .map(|(_, token)| token.trim());
+
+        match presented {
+            Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+            Some(_) => Err(Rejection::new(
+                StatusCode::UNAUTHORIZED,
+                Error::AccessDenied("Invalid bearer token".to_string()),
+            )),
+            None => Err(Rejection::new(
+                StatusCode::UNAUTHORIZED,
+                Error::AccessDenied("Missing bearer token".to_string()),
+            )),
+        }
+    }
+
+    async fn limit_body(&self, request: Request) -> std::result::Result<Request, Rejection> {
+        let max = self.config.max_body_bytes;
+        let too_large = || {
+            Rejection::new(
+                StatusCode::BAD_REQUEST,
+                Error::InvalidArgument(format!("Request body larger than {} bytes", max)),
+            )
+        };
+
+        let declared = request
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok());
+        if declared.is_some_and(|length| length > max as u64) {
+            return Err(too_large());
+        }
+
+        // Chunked bodies carry no length, so read up to the cap
+        let (parts, body) = request.into_parts();
+        let bytes = to_bytes(body, max).await.map_err(|_| too_large())?;
+        Ok(Request::from_parts(parts, Body::from(bytes)))
+    }
+}
+
+/// Middleware that runs the [`AccessControl`] checks
+///
+/// Requests that pass continue to the router; the rest get a WebDriver
+/// error response and a warning in the log.
+pub async fn enforce(
+    State(access): State<Arc<AccessControl>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|info| info.0);
+    let target = format!("{} {}", request.method(), request.uri().path());
+
+    match access.admit(peer, request).await {
+        Ok(request) => next.run(request).await,
+        Err(rejection) => {
+            tracing::warn!(
+                "Rejected WebDriver request {} from {}: {}",
+                target,
+                peer.map_or_else(|| "unknown peer".to_string(), |addr| addr.to_string()),
+                rejection.error
+            );
+            rejection.into_response()
+        }
+    }
+}
+
+/// Whether a Host header value names a loopback address
+fn is_local_host(host: &str) -> bool {
+    let name = match host.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or(""),
+        None => host.rsplit_once(':').map_or(host, |(name, _)| name),
+    };
+    name.eq_ignore_ascii_case("localhost")
+        || name.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Compare without exiting at the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_types::MockClock;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_is_local_host() {
+        for host in [
+            "localhost",
+            "LOCALHOST:4444",
+            "127.0.0.1:4444",
+            "127.1.2.3",
+            "[::1]:4444",
+        ] {
+            assert!(is_local_host(host), "{}", host);
+        }
+        for host in [
+            "",
+            "evil.example",
+            "evil.example:4444",
+            "10.0.0.2:4444",
+            "[fe80::1]",
+        ] {
+            assert!(!is_local_host(host), "{}", host);
+        }
+    }
+
+    #[test]
+    fn test_apply_args_and_validate() {
+        let mut config = ServerConfig::default();
+        config
+            .apply_args(&args(&["--port", "9515", "--host", "0.0.0.0"]))
+            .unwrap();
+        assert_eq!(config.socket_addr(), "0.0.0.0:9515".parse().unwrap());
+        assert!(matches!(config.validate(), Err(Error::InvalidArgument(_))));
+
+        config.apply_args(&args(&[ALLOW_REMOTE_FLAG])).unwrap();
+        assert!(config.validate().is_ok());
+
+        assert!(config.apply_args(&args(&["--port"])).is_err());
+        assert!(config.apply_args(&args(&["--port", "http"])).is_err());
+        assert!(config.apply_args(&args(&["--verbose"])).is_err());
+    }
+
+    #[test]
+    fn test_from_settings() {
+        let mut settings = WebDriverSettings::default();
+        assert_eq!(
+            ServerConfig::from_settings(&settings).unwrap(),
+            ServerConfig::default()
+        );
+
+        settings.auth_token = "s3cret".to_string();
+        settings.max_body_kb = 1;
+        let config = ServerConfig::from_settings(&settings).unwrap();
+        assert_eq!(config.auth_token.as_deref(), Some("s3cret"));
+        assert_eq!(config.max_body_bytes, 1024);
+
+        settings.bind_address = "localhost".to_string();
+        assert!(ServerConfig::from_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn test_ensure_token_only_for_remote() {
+        let mut local = ServerConfig::default();
+        assert_eq!(local.ensure_token(), None);
+        assert_eq!(local.auth_token, None);
+
+        let mut remote = ServerConfig::default().with_allow_remote(true);
+        let token = remote.ensure_token().unwrap();
+        assert_eq!(remote.auth_token.as_deref(), Some(token.as_str()));
+        assert_eq!(remote.ensure_token(), None);
+    }
+
+    #[test]
+    fn test_rate_limit_refills() {
+        let clock = Arc::new(MockClock::default());
+        let access =
+            AccessControl::new(ServerConfig::default().with_rate_limit(2, 2), clock.clone());
+        let ip = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+        assert!(access.check_rate(ip).is_ok());
+        assert!(access.check_rate(ip).is_ok());
+        let rejection = access.check_rate(ip).unwrap_err();
+        assert_eq!(rejection.status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(rejection.retry_after, Some(Duration::from_millis(500)));
+
+        // Another peer has its own bucket
+        assert!(access
+            .check_rate(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)))
+            .is_ok());
+
+        clock.advance(Duration::from_millis(500));
+        assert!(access.check_rate(ip).is_ok());
+        assert!(access.check_rate(ip).is_err());
+    }
+}
@@ -0,0 +1,593 @@
+//! Input actions: the WebDriver "Perform Actions" command
+//!
+//! A request holds one action sequence per input source. The n-th action
+//! of every sequence makes up tick n; ticks run in order, and each lasts
+//! as long as its longest pause or pointer move. Pointer and key input is
+//! delivered by [`Session::dispatch_pointer_events`] and
+//! [`Session::dispatch_key_events`], so it uses trusted native injection
+//! whenever the session's backend supports it.
+//!
+//! Sources of the same type share one [`InputState`] per session: the
+//! pointer position and the buttons and keys still held, which "Release
+//! Actions" lets go of. Pointer moves jump straight to their target at the
+//! end of the move's duration rather than interpolating.
+//!
+//! [`Session::dispatch_pointer_events`]: crate::Session::dispatch_pointer_events
+//! [`Session::dispatch_key_events`]: crate::Session::dispatch_key_events
+
+use crate::errors::{Error, Result};
+use serde::Deserialize;
+use std::time::Duration;
+use webview_integration::input::{
+    KeyEventKind, KeyEventRaw, KeyModifiers, PointerButton, PointerEvent,
+};
+
+/// JSON key identifying a web element reference
+pub const ELEMENT_KEY: &str = "element-6066-11e4-a52e-4f735466cecf";
+
+/// Actions of one input source
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ActionSequence {
+    /// Source that can only pause
+    None {
+        /// Source id
+        id: String,
+        /// The source's action for each tick
+        actions: Vec<Action>,
+    },
+    /// Keyboard
+    Key {
+        /// Source id
+        id: String,
+        /// The source's action for each tick
+        actions: Vec<Action>,
+    },
+    /// Mouse, pen or touch; all are driven as a mouse
+    Pointer {
+        /// Source id
+        id: String,
+        /// The source's action for each tick
+        actions: Vec<Action>,
+    },
+}
+
+impl ActionSequence {
+    /// The source's action for each tick
+    pub fn actions(&self) -> &[Action] {
+        match self {
+            Self::None { actions, .. }
+            | Self::Key { actions, .. }
+            | Self::Pointer { actions, .. } => actions,
+        }
+    }
+
+    /// Check that every action is one the source can perform
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidArgument` naming the first action that is not.
+    pub fn validate(&self) -> Result<()> {
+        let (source, allowed): (&str, fn(&Action) -> bool) = match self {
+            Self::None { id, .. } => (id, |action| matches!(action, Action::Pause { .. })),
+            Self::Key { id, .. } => (id, |action| {
+                matches!(
+                    action,
+                    Action::Pause { .. } | Action::KeyDown { .. } | Action::KeyUp { .. }
+                )
+            }),
+            Self::Pointer { id, .. } => (id, |action| {
+                !matches!(action, Action::KeyDown { .. } | Action::KeyUp { .. })
+            }),
+        };
+        match self.actions().iter().find(|action| !allowed(action)) {
+            Some(action) => Err(Error::InvalidArgument(format!(
+                "Input source {} cannot perform {:?}",
+                source, action
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// One action of an input source
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Action {
+    /// Do nothing for the tick
+    Pause {
+        /// Milliseconds the tick lasts at least
+        #[serde(default)]
+        duration: u64,
+    },
+    /// Press a key
+    KeyDown {
+        /// The key: one character, or a WebDriver special key
+        value: String,
+    },
+    /// Release a key
+    KeyUp {
+        /// The key: one character, or a WebDriver special key
+        value: String,
+    },
+    /// Move the pointer
+    PointerMove {
+        /// Horizontal offset from the origin, in CSS pixels
+        x: f64,
+        /// Vertical offset from the origin, in CSS pixels
+        y: f64,
+        /// Milliseconds the move takes
+        #[serde(default)]
+        duration: u64,
+        /// What the offset is relative to
+        #[serde(default)]
+        origin: PointerOrigin,
+    },
+    /// Press a pointer button
+    PointerDown {
+        /// DOM button number
+        button: u8,
+    },
+    /// Release a pointer button
+    PointerUp {
+        /// DOM button number
+        button: u8,
+    },
+}
+
+impl Action {
+    /// How long the tick must last for this action
+    pub fn duration(&self) -> Duration {
+        match self {
+            Self::Pause { duration } | Self::PointerMove { duration, .. } => {
+                Duration::from_millis(*duration)
+            }
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// What a pointer move's offset is relative to
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(try_from = "RawOrigin")]
+pub enum PointerOrigin {
+    /// The top left of the viewport
+    #[default]
+    Viewport,
+    /// The pointer's current position
+    Pointer,
+    /// The center of an element, by element id
+    Element(String),
+}
+
+/// Wire form of [`PointerOrigin`]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawOrigin {
+    Named(String),
+    Element(serde_json::Map<String, serde_json::Value>),
+}
+
+impl TryFrom<RawOrigin> for PointerOrigin {
+    type Error = String;
+
+    fn try_from(raw: RawOrigin) -> std::result::Result<Self, String> {
+        match raw {
+            RawOrigin::Named(name) if name == "viewport" => Ok(Self::Viewport),
+            RawOrigin::Named(name) if name == "pointer" => Ok(Self::Pointer),
+            RawOrigin::Named(name) => Err(format!("unknown pointer origin {}", name)),
+            RawOrigin::Element(object) => object
+                .get(ELEMENT_KEY)
+                .and_then(|id| id.as_str())
+                .map(|id| Self::Element(id.to_string()))
+                .ok_or_else(|| "pointer origin is not an element reference".to_string()),
+        }
+    }
+}
+
+/// Input to deliver for one action
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    /// Pointer event
+    Pointer(PointerEvent),
+    /// Key event
+    Key(KeyEventRaw),
+}
+
+/// State of a session's input sources between actions
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputState {
+    /// Pointer position, in CSS pixels relative to the viewport
+    pub pointer: (f64, f64),
+    /// Buttons held down, in the order they were pressed
+    pub buttons: Vec<PointerButton>,
+    /// Keys held down, in the order they were pressed
+    pub keys: Vec<KeyEventRaw>,
+}
+
+impl InputState {
+    /// Apply an action
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - Action to apply
+    /// * `element_center` - Looks up the viewport position of an element's
+    ///   center, for moves relative to an element
+    ///
+    /// # Returns
+    ///
+    /// The input to deliver, or `None` for pauses and for pressing what is
+    /// already held or releasing what is not.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidArgument` for an unknown button, a key value
+    /// that is not a single character, or a move outside the viewport's
+    /// top left corner, and whatever `element_center` returns.
+    pub fn apply(
+        &mut self,
+        action: &Action,
+        element_center: impl FnOnce(&str) -> Result<(f64, f64)>,
+    ) -> Result<Option<Input>> {
+        match action {
+            Action::Pause { .. } => Ok(None),
+            Action::PointerMove { x, y, origin, .. } => {
+                let (origin_x, origin_y) = match origin {
+                    PointerOrigin::Viewport => (0.0, 0.0),
+                    PointerOrigin::Pointer => self.pointer,
+                    PointerOrigin::Element(id) => element_center(id)?,
+                };
+                let target = (origin_x + x, origin_y + y);
+                if target.0 < 0.0 || target.1 < 0.0 {
+                    return Err(Error::InvalidArgument(format!(
+                        "Pointer move to ({}, {}) is out of bounds",
+                        target.0, target.1
+                    )));
+                }
+                self.pointer = target;
+                Ok(Some(Input::Pointer(PointerEvent::moved(
+                    target.0, target.1,
+                ))))
+            }
+            Action::PointerDown { button } => {
+                let button = pointer_button(*button)?;
+                if self.buttons.contains(&button) {
+                    return Ok(None);
+                }
+                self.buttons.push(button);
+                let (x, y) = self.pointer;
+                Ok(Some(Input::Pointer(PointerEvent::down(x, y, button))))
+            }
+            Action::PointerUp { button } => {
+                let button = pointer_button(*button)?;
+                let Some(index) = self.buttons.iter().position(|held| *held == button) else {
+                    return Ok(None);
+                };
+                self.buttons.remove(index);
+                let (x, y) = self.pointer;
+                Ok(Some(Input::Pointer(PointerEvent::up(x, y, button))))
+            }
+            Action::KeyDown { value } => {
+                let (key, code) = normalize_key(value)?;
+                if self.keys.iter().any(|held| held.key == key) {
+                    return Ok(None);
+                }
+                // A modifier applies to its own keydown, as in browsers
+                let mut event = KeyEventRaw::new(KeyEventKind::Down, key, code);
+                self.keys.push(event.clone());
+                event.modifiers = self.modifiers();
+                Ok(Some(Input::Key(event)))
+            }
+            Action::KeyUp { value } => {
+                let (key, _) = normalize_key(value)?;
+                let Some(index) = self.keys.iter().position(|held| held.key == key) else {
+                    return Ok(None);
+                };
+                let mut event = self.keys.remove(index);
+                event.kind = KeyEventKind::Up;
+                event.modifiers = self.modifiers();
+                Ok(Some(Input::Key(event)))
+            }
+        }
+    }
+
+    /// Let go of every held key and button
+    ///
+    /// # Returns
+    ///
+    /// The inputs releasing them: keys first, most recently pressed first,
+    /// then buttons in the same order.
+    pub fn release(&mut self) -> Vec<Input> {
+        let mut inputs = Vec::new();
+        while let Some(mut event) = self.keys.pop() {
+            event.kind = KeyEventKind::Up;
+            event.modifiers = self.modifiers();
+            inputs.push(Input::Key(event));
+        }
+        let (x, y) = self.pointer;
+        while let Some(button) = self.buttons.pop() {
+            inputs.push(Input::Pointer(PointerEvent::up(x, y, button)));
+        }
+        inputs
+    }
+
+    /// Modifier keys currently held
+    fn modifiers(&self) -> KeyModifiers {
+        let held = |name: &str| self.keys.iter().any(|event| event.key == name);
+        KeyModifiers {
+            shift: held("Shift"),
+            ctrl: held("Control"),
+            alt: held("Alt"),
+            meta: held("Meta"),
+        }
+    }
+}
+
+/// Pointer button for a DOM button number
+fn pointer_button(button: u8) -> Result<PointerButton> {
+    match button {
+        0 => Ok(PointerButton::Primary),
+        1 => Ok(PointerButton::Auxiliary),
+        2 => Ok(PointerButton::Secondary),
+        other => Err(Error::InvalidArgument(format!(
+            "Pointer button {} is not supported",
+            other
+        ))),
+    }
+}
+
+/// DOM `key` and `code` for a WebDriver key value
+///
+/// Special keys are the code points WebDriver assigns from U+E000; other
+/// characters stand for themselves, with a code when they have an obvious
+/// one on a US layout.
+fn normalize_key(value: &str) -> Result<(String, String)> {
+    let mut chars = value.chars();
+    let (Some(c), None) = (chars.next(), chars.next()) else {
+        return Err(Error::InvalidArgument(format!(
+            "Key value {:?} is not a single character",
+            value
+        )));
+    };
+    let special = |key: &str, code: &str| Ok((key.to_string(), code.to_string()));
+    match c {
+        '\u{E003}' => special("Backspace", "Backspace"),
+        '\u{E004}' => special("Tab", "Tab"),
+        '\u{E006}' | '\u{E007}' => special("Enter", "Enter"),
+        '\u{E008}' => special("Shift", "ShiftLeft"),
+        '\u{E009}' => special("Control", "ControlLeft"),
+        '\u{E00A}' => special("Alt", "AltLeft"),
+        '\u{E00C}' => special("Escape", "Escape"),
+        '\u{E00D}' => special(" ", "Space"),
+        '\u{E00E}' => special("PageUp", "PageUp"),
+        '\u{E00F}' => special("PageDown", "PageDown"),
+        '\u{E010}' => special("End", "End"),
+        '\u{E011}' => special("Home", "Home"),
+        '\u{E012}' => special("ArrowLeft", "ArrowLeft"),
+        '\u{E013}' => special("ArrowUp", "ArrowUp"),
+        '\u{E014}' => special("ArrowRight", "ArrowRight"),
+        '\u{E015}' => special("ArrowDown", "ArrowDown"),
+        '\u{E016}' => special("Insert", "Insert"),
+        '\u{E017}' => special("Delete", "Delete"),
+        '\u{E031}'..='\u{E03C}' => {
+            let n = c as u32 - 0xE030;
+            Ok((format!("F{}", n), format!("F{}", n)))
+        }
+        '\u{E03D}' => special("Meta", "MetaLeft"),
+        '\u{E000}'..='\u{F8FF}' => special("Unidentified", ""),
+        ' ' => special(" ", "Space"),
+        c if c.is_ascii_alphabetic() => {
+            Ok((c.to_string(), format!("Key{}", c.to_ascii_uppercase())))
+        }
+        c if c.is_ascii_digit() => Ok((c.to_string(), format!("Digit{}", c))),
+        c => Ok((c.to_string(), String::new())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn no_elements(id: &str) -> Result<(f64, f64)> {
+        Err(Error::NoSuchElement(id.to_string()))
+    }
+
+    #[test]
+    fn test_parse_action_sequences() {
+        let sequences: Vec<ActionSequence> = serde_json::from_value(json!([
+            { "type": "none", "id": "wait", "actions": [{ "type": "pause", "duration": 5 }] },
+            {
+                "type": "pointer",
+                "id": "mouse",
+                "parameters": { "pointerType": "mouse" },
+                "actions": [
+                    { "type": "pointerMove", "x": 10, "y": 20, "origin": "pointer" },
+                    { "type": "pointerMove", "x": 1, "y": 2, "origin": { ELEMENT_KEY: "e1" } },
+                    { "type": "pointerDown", "button": 0 }
+                ]
+            },
+            { "type": "key", "id": "keyboard", "actions": [{ "type": "keyDown", "value": "a" }] }
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            sequences[0].actions()[0].duration(),
+            Duration::from_millis(5)
+        );
+        assert_eq!(
+            sequences[1].actions()[0],
+            Action::PointerMove {
+                x: 10.0,
+                y: 20.0,
+                duration: 0,
+                origin: PointerOrigin::Pointer,
+            }
+        );
+        assert!(matches!(
+            &sequences[1].actions()[1],
+            Action::PointerMove { origin: PointerOrigin::Element(id), .. } if id == "e1"
+        ));
+        assert!(sequences.iter().all(|sequence| sequence.validate().is_ok()));
+
+        let bad_origin = serde_json::from_value::<Action>(
+            json!({ "type": "pointerMove", "x": 0, "y": 0, "origin": "window" }),
+        );
+        assert!(bad_origin.is_err());
+    }
+
+    #[test]
+    fn test_sources_only_perform_their_actions() {
+        let key_in_pointer: ActionSequence = serde_json::from_value(json!({
+            "type": "pointer",
+            "id": "mouse",
+            "actions": [{ "type": "keyDown", "value": "a" }]
+        }))
+        .unwrap();
+        assert!(matches!(
+            key_in_pointer.validate(),
+            Err(Error::InvalidArgument(_))
+        ));
+
+        let move_in_none: ActionSequence = serde_json::from_value(json!({
+            "type": "none",
+            "id": "wait",
+            "actions": [{ "type": "pointerMove", "x": 0, "y": 0 }]
+        }))
+        .unwrap();
+        assert!(move_in_none.validate().is_err());
+    }
+
+    #[test]
+    fn test_pointer_actions_track_position_and_buttons() {
+        let mut state = InputState::default();
+        let moved = state
+            .apply(
+                &Action::PointerMove {
+                    x: 5.0,
+                    y: 5.0,
+                    duration: 0,
+                    origin: PointerOrigin::Element("e1".to_string()),
+                },
+                |_| Ok((100.0, 50.0)),
+            )
+            .unwrap();
+        assert_eq!(
+            moved,
+            Some(Input::Pointer(PointerEvent::moved(105.0, 55.0)))
+        );
+
+        let relative = Action::PointerMove {
+            x: -5.0,
+            y: 10.0,
+            duration: 0,
+            origin: PointerOrigin::Pointer,
+        };
+        state.apply(&relative, no_elements).unwrap();
+        assert_eq!(state.pointer, (100.0, 65.0));
+
+        let down = Action::PointerDown { button: 2 };
+        assert_eq!(
+            state.apply(&down, no_elements).unwrap(),
+            Some(Input::Pointer(PointerEvent::down(
+                100.0,
+                65.0,
+                PointerButton::Secondary
+            )))
+        );
+        // Pressing a held button again does nothing
+        assert_eq!(state.apply(&down, no_elements).unwrap(), None);
+        assert_eq!(
+            state.release(),
+            [Input::Pointer(PointerEvent::up(
+                100.0,
+                65.0,
+                PointerButton::Secondary
+            ))]
+        );
+        assert_eq!(
+            state
+                .apply(&Action::PointerUp { button: 2 }, no_elements)
+                .unwrap(),
+            None
+        );
+
+        let off_screen = Action::PointerMove {
+            x: -1.0,
+            y: 0.0,
+            duration: 0,
+            origin: PointerOrigin::Viewport,
+        };
+        assert!(state.apply(&off_screen, no_elements).is_err());
+        assert!(state
+            .apply(&Action::PointerDown { button: 7 }, no_elements)
+            .is_err());
+    }
+
+    #[test]
+    fn test_key_actions_carry_modifiers() {
+        let mut state = InputState::default();
+        let shift = Action::KeyDown {
+            value: "\u{E008}".to_string(),
+        };
+        let Some(Input::Key(event)) = state.apply(&shift, no_elements).unwrap() else {
+            panic!("expected a key event");
+        };
+        assert_eq!(
+            (event.key.as_str(), event.code.as_str()),
+            ("Shift", "ShiftLeft")
+        );
+        assert!(event.modifiers.shift);
+
+        let a = Action::KeyDown {
+            value: "A".to_string(),
+        };
+        let Some(Input::Key(event)) = state.apply(&a, no_elements).unwrap() else {
+            panic!("expected a key event");
+        };
+        assert_eq!((event.key.as_str(), event.code.as_str()), ("A", "KeyA"));
+        assert!(event.modifiers.shift);
+
+        // Released in reverse order; shift no longer held for its own keyup
+        let released = state.release();
+        let keys: Vec<_> = released
+            .iter()
+            .map(|input| match input {
+                Input::Key(event) => (event.kind, event.key.as_str(), event.modifiers.shift),
+                Input::Pointer(_) => panic!("no buttons were held"),
+            })
+            .collect();
+        assert_eq!(
+            keys,
+            [
+                (KeyEventKind::Up, "A", true),
+                (KeyEventKind::Up, "Shift", false),
+            ]
+        );
+
+        let two = Action::KeyDown {
+            value: "ab".to_string(),
+        };
+        assert!(matches!(
+            state.apply(&two, no_elements),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_normalize_special_keys() {
+        assert_eq!(
+            normalize_key("\u{E007}").unwrap(),
+            ("Enter".to_string(), "Enter".to_string())
+        );
+        assert_eq!(
+            normalize_key("\u{E03C}").unwrap(),
+            ("F12".to_string(), "F12".to_string())
+        );
+        assert_eq!(normalize_key("7").unwrap().1, "Digit7");
+        assert_eq!(
+            normalize_key("é").unwrap(),
+            ("é".to_string(), String::new())
+        );
+    }
+}
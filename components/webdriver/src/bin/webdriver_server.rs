@@ -1,19 +1,43 @@
 //! WebDriver server binary
 //!
-//! Starts the WebDriver HTTP server on port 4444 for testing
+//! Starts the WebDriver HTTP server, on 127.0.0.1:4444 unless the
+//! `[webdriver]` config section or the command line say otherwise:
+//!
+//! - `--port <port>`
+//! - `--host <address>` (anything but loopback needs
+//!   `--webdriver-allow-remote`)
+//! - `--webdriver-allow-remote`
+//! - `--webdriver-token <token>`
 
-use webdriver::server::start_server;
+use config_manager::Config;
+use webdriver::access::ServerConfig;
+use webdriver::server::start_server_with_config;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize logging
     tracing_subscriber::fmt::init();
 
-    println!("Starting FrankenBrowser WebDriver server on port 4444...");
+    let settings = Config::load_or_default()?.webdriver;
+    let mut config = ServerConfig::from_settings(&settings)?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    config.apply_args(&args)?;
+    config.validate()?;
+
+    if let Some(token) = config.ensure_token() {
+        println!(
+            "Remote access allowed; clients must send \"Authorization: Bearer {}\"",
+            token
+        );
+    }
+    println!(
+        "Starting FrankenBrowser WebDriver server on {}...",
+        config.socket_addr()
+    );
     println!("Press Ctrl+C to stop");
 
     // Start server
-    start_server(4444).await?;
+    start_server_with_config(config).await?;
 
     Ok(())
 }
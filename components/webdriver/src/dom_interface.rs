@@ -115,11 +115,14 @@ impl DomInterface {
             .execute_script(&script)
             .map_err(|e| Error::JavaScriptError(format!("Failed to find elements: {}", e)))?;
 
-        // Parse result as integer (count of elements found)
-        let count: usize = result
-            .trim()
-            .parse()
-            .map_err(|_| Error::JavaScriptError("Invalid element count".to_string()))?;
+        // Parse result as integer (count of elements found); a session
+        // without a document answers null, which matches nothing
+        let count: usize = match result.trim() {
+            "null" => 0,
+            count => count
+                .parse()
+                .map_err(|_| Error::JavaScriptError("Invalid element count".to_string()))?,
+        };
 
         // Create element references for each found element
         let selector = self.selector_to_css(&locator, value);
@@ -427,6 +430,26 @@ impl DomInterface {
         )
     }
 
+    /// Generate script to get the viewport position of an element's center
+    ///
+    /// The script returns `[x, y]` in CSS pixels, or `null` if the element
+    /// is gone.
+    pub fn generate_center_script(&self, selector: &str, index: usize) -> String {
+        format!(
+            r#"(function() {{
+                var elements = document.querySelectorAll('{}');
+                if (elements.length > {}) {{
+                    var rect = elements[{}].getBoundingClientRect();
+                    return [rect.left + rect.width / 2, rect.top + rect.height / 2];
+                }}
+                return null;
+            }})()"#,
+            Self::escape_js_string(selector),
+            index,
+            index
+        )
+    }
+
     /// Generate script to check if element is selected
     pub fn generate_is_selected_script(&self, selector: &str, index: usize) -> String {
         format!(
@@ -732,6 +755,16 @@ mod tests {
         assert!(script.contains("checked"));
     }
 
+    #[test]
+    fn test_generate_center_script() {
+        let interface = DomInterface::new();
+
+        let script = interface.generate_center_script("#target", 1);
+        assert!(script.contains("#target"));
+        assert!(script.contains("getBoundingClientRect"));
+        assert!(script.contains("return null"));
+    }
+
     #[test]
     fn test_js_escape_complex() {
         // Test complex string with multiple escape characters
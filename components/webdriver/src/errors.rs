@@ -13,6 +13,15 @@ pub enum Error {
     #[error("Invalid argument: {0}")]
     InvalidArgument(String),
 
+    #[error("Session not created: {0}")]
+    SessionNotCreated(String),
+
+    #[error("Access denied: {0}")]
+    AccessDenied(String),
+
+    #[error("Too many requests: {0}")]
+    RateLimited(String),
+
     #[error("No such element: {0}")]
     NoSuchElement(String),
 
@@ -42,6 +51,12 @@ pub enum Error {
 
     #[error("Not implemented: {0}")]
     NotImplemented(String),
+
+    #[error("Unknown command: {0}")]
+    UnknownCommand(String),
+
+    #[error("Unknown method: {0}")]
+    UnknownMethod(String),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -65,6 +80,9 @@ impl From<Error> for WebDriverErrorResponse {
             Error::SessionNotFound(_) => "invalid session id",
             Error::InvalidSession(_) => "invalid session id",
             Error::InvalidArgument(_) => "invalid argument",
+            Error::SessionNotCreated(_) => "session not created",
+            Error::AccessDenied(_) => "unknown error",
+            Error::RateLimited(_) => "unknown error",
             Error::NoSuchElement(_) => "no such element",
             Error::StaleElementReference(_) => "stale element reference",
             Error::NoSuchWindow(_) => "no such window",
@@ -75,6 +93,8 @@ impl From<Error> for WebDriverErrorResponse {
             Error::NavigationError(_) => "unknown error",
             Error::ServerError(_) => "unknown error",
             Error::NotImplemented(_) => "unsupported operation",
+            Error::UnknownCommand(_) => "unknown command",
+            Error::UnknownMethod(_) => "unknown method",
         };
 
         WebDriverErrorResponse {
@@ -105,6 +125,16 @@ mod tests {
         assert!(response.value.message.contains("button#submit"));
     }
 
+    #[test]
+    fn test_unknown_command_and_method_codes() {
+        let response: WebDriverErrorResponse =
+            Error::UnknownCommand("GET /session/x/cookie".to_string()).into();
+        assert_eq!(response.value.error, "unknown command");
+        let response: WebDriverErrorResponse =
+            Error::UnknownMethod("PUT /status".to_string()).into();
+        assert_eq!(response.value.error, "unknown method");
+    }
+
     #[test]
     fn test_invalid_argument_error() {
         let error = Error::InvalidArgument("Invalid selector".to_string());
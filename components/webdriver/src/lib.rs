@@ -41,12 +41,28 @@
 //! - `POST /session/{session_id}/execute/sync` - Execute JavaScript
 //! - `GET /session/{session_id}/screenshot` - Take screenshot
 //! - `GET /session/{session_id}/window` - Get window handle
+//! - `POST /session/{session_id}/actions` - Perform input actions
+//! - `DELETE /session/{session_id}/actions` - Release held keys and buttons
+//!
+//! Unknown endpoints answer `unknown command` (404) and known endpoints
+//! called with the wrong method `unknown method` (405). The conformance
+//! harness in `tests/conformance` checks every endpoint against the
+//! spec's status codes and `error` strings.
+//!
+//! # Access Control
+//!
+//! The server binds to 127.0.0.1 unless remote access is explicitly
+//! allowed, rejects non-local Host headers, and can require a bearer
+//! token. Sessions, per-IP request rates and body sizes are capped; see
+//! [`access`].
 //!
 //! # W3C WebDriver Specification
 //!
 //! This implementation follows the W3C WebDriver specification:
 //! https://w3c.github.io/webdriver/
 
+pub mod access;
+pub mod actions;
 pub mod dom_interface;
 pub mod element;
 pub mod errors;
@@ -55,10 +71,12 @@ pub mod server;
 pub mod session;
 
 // Re-export main types
+pub use access::{AccessControl, ServerConfig};
+pub use actions::{Action, ActionSequence, InputState, PointerOrigin};
 pub use dom_interface::{DomInterface, LocatorStrategy};
 pub use element::{CachedElement, ElementCache, ElementReference};
 pub use errors::{Error, Result};
-pub use server::{start_server, WebDriverState};
+pub use server::{serve, start_server, start_server_with_config, WebDriverState};
 pub use session::{Capabilities, Session, SessionManager};
 
 #[cfg(test)]
@@ -1,21 +1,33 @@
 //! WebDriver HTTP server implementation
 //!
 //! This module provides the W3C WebDriver protocol HTTP server using axum.
-
+//!
+//! Every response is a WebDriver JSON body: commands without a result
+//! return `{"value": null}`, unknown URLs get `unknown command` (404), a
+//! known URL with the wrong HTTP method gets `unknown method` (405), and a
+//! body that is not valid JSON or lacks a required field gets
+//! `invalid argument` (400).
+
+use crate::access::{enforce, AccessControl, ServerConfig};
+use crate::actions::ActionSequence;
 use crate::dom_interface::DomInterface;
 use crate::errors::{Error, Result, WebDriverErrorResponse};
 use crate::script_args::{parse_script_result, ScriptArgument};
 use crate::session::{Capabilities, SessionManager};
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{rejection::JsonRejection, DefaultBodyLimit, Path, Request, State},
+    http::{Method, StatusCode, Uri},
+    middleware::{self, Next},
     response::{IntoResponse, Response},
     routing::{delete, get, post},
     Json, Router,
 };
 use base64::Engine;
 use serde::{Deserialize, Serialize};
+use shared_types::{Clock, SystemClock};
 use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::TcpListener;
 use tokio::time::{timeout, Duration};
 use tower_http::cors::{Any, CorsLayer};
 
@@ -31,6 +43,13 @@ impl WebDriverState {
             session_manager: SessionManager::new(),
         }
     }
+
+    /// Create state that enforces the session limit in `config`
+    pub fn with_config(config: &ServerConfig) -> Self {
+        Self {
+            session_manager: SessionManager::new().with_max_sessions(config.max_sessions),
+        }
+    }
 }
 
 impl Default for WebDriverState {
@@ -40,21 +59,80 @@ impl Default for WebDriverState {
 }
 
 /// Start WebDriver server on the specified port
+///
+/// Listens on 127.0.0.1 with the default limits; see
+/// [`start_server_with_config`] for anything else.
 pub async fn start_server(port: u16) -> Result<()> {
-    let state = WebDriverState::new();
-
-    let app = create_router(state);
+    start_server_with_config(ServerConfig::default().with_port(port)).await
+}
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
-    let listener = tokio::net::TcpListener::bind(addr)
+/// Start WebDriver server with explicit address, access and limits
+///
+/// # Errors
+///
+/// Returns `Error::InvalidArgument` if the configuration is rejected by
+/// [`ServerConfig::validate`] and `Error::ServerError` if the address
+/// cannot be bound or serving fails.
+pub async fn start_server_with_config(config: ServerConfig) -> Result<()> {
+    config.validate()?;
+
+    let addr = config.socket_addr();
+    let listener = TcpListener::bind(addr)
         .await
         .map_err(|e| Error::ServerError(format!("Failed to bind to {}: {}", addr, e)))?;
 
     tracing::info!("WebDriver server listening on {}", addr);
 
-    axum::serve(listener, app)
-        .await
-        .map_err(|e| Error::ServerError(format!("Server error: {}", e)))?;
+    serve(listener, config, Arc::new(SystemClock)).await
+}
+
+/// Serve WebDriver requests on an already bound listener
+///
+/// The address and port in `config` are replaced by the listener's. If
+/// remote access is allowed without a token, one is generated and logged.
+///
+/// # Arguments
+///
+/// * `listener` - Bound listener
+/// * `config` - Access settings and limits
+/// * `clock` - Time source for the rate limiter
+///
+/// # Errors
+///
+/// Returns `Error::InvalidArgument` if the listener is not on a loopback
+/// address and remote access is not allowed, and `Error::ServerError` if
+/// serving fails.
+pub async fn serve(
+    listener: TcpListener,
+    mut config: ServerConfig,
+    clock: Arc<dyn Clock>,
+) -> Result<()> {
+    let local = listener
+        .local_addr()
+        .map_err(|e| Error::ServerError(format!("Listener has no address: {}", e)))?;
+    config.bind_address = local.ip();
+    config.port = local.port();
+    config.validate()?;
+    if let Some(token) = config.ensure_token() {
+        tracing::warn!(
+            "Remote WebDriver access allowed without a token; generated bearer token {}",
+            token
+        );
+    }
+
+    let state = WebDriverState::with_config(&config);
+    let access = Arc::new(AccessControl::new(config, clock));
+    let app = create_router(state)
+        // Bodies are capped by the access checks with a WebDriver error
+        .layer(DefaultBodyLimit::disable())
+        .layer(middleware::from_fn_with_state(access, enforce));
+
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .map_err(|e| Error::ServerError(format!("Server error: {}", e)))?;
 
     Ok(())
 }
@@ -136,10 +214,41 @@ fn create_router(state: WebDriverState) -> Router {
         .route("/session/:session_id/window/maximize", post(maximize_window_handler))
         .route("/session/:session_id/window/minimize", post(minimize_window_handler))
         .route("/session/:session_id/window/fullscreen", post(fullscreen_window_handler))
+        .route("/session/:session_id/actions", post(perform_actions_handler))
+        .route("/session/:session_id/actions", delete(release_actions_handler))
+        .fallback(unknown_command_handler)
+        .layer(middleware::from_fn(reject_unknown_method))
         .layer(cors)
         .with_state(state)
 }
 
+/// Any URL without a route - Unknown command
+async fn unknown_command_handler(method: Method, uri: Uri) -> WebDriverError {
+    WebDriverError::from(Error::UnknownCommand(format!("{} {}", method, uri.path())))
+}
+
+/// Turn axum's bare 405 for a known URL into an `unknown method` error
+async fn reject_unknown_method(request: Request, next: Next) -> Response {
+    let target = format!("{} {}", request.method(), request.uri().path());
+    let response = next.run(request).await;
+    if response.status() == StatusCode::METHOD_NOT_ALLOWED {
+        WebDriverError::from(Error::UnknownMethod(target)).into_response()
+    } else {
+        response
+    }
+}
+
+/// Unwrap a JSON request body
+///
+/// # Errors
+///
+/// Returns `invalid argument` if the body is not JSON, is not of the
+/// command's shape, or lacks a required field.
+fn json_body<T>(body: JsonBody<T>) -> WebDriverResult<T> {
+    body.map(|Json(value)| value)
+        .map_err(|rejection| Error::InvalidArgument(rejection.body_text()).into())
+}
+
 // ============================================================================
 // Handler Functions
 // ============================================================================
@@ -157,8 +266,10 @@ async fn status_handler() -> Json<StatusResponse> {
 /// POST /session - Create new session
 async fn create_session_handler(
     State(state): State<WebDriverState>,
-    Json(req): Json<CreateSessionRequest>,
+    body: JsonBody<CreateSessionRequest>,
 ) -> WebDriverResult<Json<CreateSessionResponse>> {
+    let req = json_body(body)?;
+
     // Negotiate capabilities (simplified: use alwaysMatch or first of firstMatch)
     let capabilities = req
         .capabilities
@@ -184,30 +295,31 @@ async fn create_session_handler(
 async fn delete_session_handler(
     State(state): State<WebDriverState>,
     Path(session_id): Path<String>,
-) -> WebDriverResult<StatusCode> {
+) -> WebDriverResult<Json<NullResponse>> {
     state
         .session_manager
         .delete_session(&session_id)
         .map_err(WebDriverError::from)?;
 
-    Ok(StatusCode::OK)
+    Ok(Json(NullResponse::default()))
 }
 
 /// POST /session/:session_id/url - Navigate to URL
 async fn navigate_handler(
     State(state): State<WebDriverState>,
     Path(session_id): Path<String>,
-    Json(req): Json<NavigateRequest>,
-) -> WebDriverResult<StatusCode> {
+    body: JsonBody<NavigateRequest>,
+) -> WebDriverResult<Json<NullResponse>> {
     let session_arc = state
         .session_manager
         .get_session(&session_id)
         .map_err(WebDriverError::from)?;
+    let req = json_body(body)?;
 
     let mut session = session_arc.lock().unwrap();
     session.navigate(req.url).map_err(WebDriverError::from)?;
 
-    Ok(StatusCode::OK)
+    Ok(Json(NullResponse::default()))
 }
 
 /// GET /session/:session_id/url - Get current URL
@@ -221,26 +333,25 @@ async fn get_url_handler(
         .map_err(WebDriverError::from)?;
 
     let session = session_arc.lock().unwrap();
+    // A session starts out on about:blank
     let url = session
         .get_url()
-        .ok_or_else(|| Error::InvalidSession("No URL set".to_string()))
-        .map_err(WebDriverError::from)?;
+        .unwrap_or_else(|| "about:blank".to_string());
 
-    Ok(Json(UrlResponse {
-        value: url.to_string(),
-    }))
+    Ok(Json(UrlResponse { value: url }))
 }
 
 /// POST /session/:session_id/element - Find element
 async fn find_element_handler(
     State(state): State<WebDriverState>,
     Path(session_id): Path<String>,
-    Json(req): Json<FindElementRequest>,
+    body: JsonBody<FindElementRequest>,
 ) -> WebDriverResult<Json<FindElementResponse>> {
     let session_arc = state
         .session_manager
         .get_session(&session_id)
         .map_err(WebDriverError::from)?;
+    let req = json_body(body)?;
 
     let session = session_arc.lock().unwrap();
 
@@ -268,12 +379,13 @@ async fn find_element_handler(
 async fn find_elements_handler(
     State(state): State<WebDriverState>,
     Path(session_id): Path<String>,
-    Json(req): Json<FindElementRequest>,
+    body: JsonBody<FindElementRequest>,
 ) -> WebDriverResult<Json<FindElementsResponse>> {
     let session_arc = state
         .session_manager
         .get_session(&session_id)
         .map_err(WebDriverError::from)?;
+    let req = json_body(body)?;
 
     let session = session_arc.lock().unwrap();
 
@@ -307,7 +419,7 @@ async fn find_elements_handler(
 async fn click_element_handler(
     State(state): State<WebDriverState>,
     Path((session_id, element_id)): Path<(String, String)>,
-) -> WebDriverResult<StatusCode> {
+) -> WebDriverResult<Json<NullResponse>> {
     let session_arc = state
         .session_manager
         .get_session(&session_id)
@@ -335,7 +447,7 @@ async fn click_element_handler(
 
     // Check if click succeeded
     if result.trim() == "true" {
-        Ok(StatusCode::OK)
+        Ok(Json(NullResponse::default()))
     } else {
         Err(WebDriverError::from(Error::NoSuchElement(format!(
             "Element not found: {}",
@@ -348,12 +460,13 @@ async fn click_element_handler(
 async fn send_keys_handler(
     State(state): State<WebDriverState>,
     Path((session_id, element_id)): Path<(String, String)>,
-    Json(req): Json<SendKeysRequest>,
-) -> WebDriverResult<StatusCode> {
+    body: JsonBody<SendKeysRequest>,
+) -> WebDriverResult<Json<NullResponse>> {
     let session_arc = state
         .session_manager
         .get_session(&session_id)
         .map_err(WebDriverError::from)?;
+    let req = json_body(body)?;
 
     let session = session_arc.lock().unwrap();
 
@@ -376,14 +489,14 @@ async fn send_keys_handler(
         .execute_script(&script)
         .map_err(WebDriverError::from)?;
 
-    Ok(StatusCode::OK)
+    Ok(Json(NullResponse::default()))
 }
 
 /// POST /session/:session_id/element/:element_id/clear - Clear element
 async fn clear_element_handler(
     State(state): State<WebDriverState>,
     Path((session_id, element_id)): Path<(String, String)>,
-) -> WebDriverResult<StatusCode> {
+) -> WebDriverResult<Json<NullResponse>> {
     let session_arc = state
         .session_manager
         .get_session(&session_id)
@@ -409,7 +522,7 @@ async fn clear_element_handler(
         .execute_script(&script)
         .map_err(WebDriverError::from)?;
 
-    Ok(StatusCode::OK)
+    Ok(Json(NullResponse::default()))
 }
 
 /// GET /session/:session_id/element/:element_id/text - Get element text
@@ -531,12 +644,13 @@ async fn is_element_displayed_handler(
 async fn execute_script_handler(
     State(state): State<WebDriverState>,
     Path(session_id): Path<String>,
-    Json(req): Json<ExecuteScriptRequest>,
+    body: JsonBody<ExecuteScriptRequest>,
 ) -> WebDriverResult<Json<ExecuteScriptResponse>> {
     let session_arc = state
         .session_manager
         .get_session(&session_id)
         .map_err(WebDriverError::from)?;
+    let req = json_body(body)?;
 
     let session = session_arc.lock().unwrap();
 
@@ -582,12 +696,13 @@ async fn execute_script_handler(
 async fn execute_async_script_handler(
     State(state): State<WebDriverState>,
     Path(session_id): Path<String>,
-    Json(req): Json<ExecuteScriptRequest>,
+    body: JsonBody<ExecuteScriptRequest>,
 ) -> WebDriverResult<Json<ExecuteScriptResponse>> {
     let session_arc = state
         .session_manager
         .get_session(&session_id)
         .map_err(WebDriverError::from)?;
+    let req = json_body(body)?;
 
     // Get script timeout from session capabilities
     let script_timeout = {
@@ -778,19 +893,20 @@ async fn get_all_window_handles_handler(
 async fn switch_to_window_handler(
     State(state): State<WebDriverState>,
     Path(session_id): Path<String>,
-    Json(req): Json<SwitchToWindowRequest>,
-) -> WebDriverResult<StatusCode> {
+    body: JsonBody<SwitchToWindowRequest>,
+) -> WebDriverResult<Json<NullResponse>> {
     let session_arc = state
         .session_manager
         .get_session(&session_id)
         .map_err(WebDriverError::from)?;
+    let req = json_body(body)?;
 
     let mut session = session_arc.lock().unwrap();
     session
         .switch_to_window(&req.handle)
         .map_err(WebDriverError::from)?;
 
-    Ok(StatusCode::OK)
+    Ok(Json(NullResponse::default()))
 }
 
 /// DELETE /session/:session_id/window - Close current window
@@ -834,12 +950,13 @@ async fn close_window_handler(
 async fn new_window_handler(
     State(state): State<WebDriverState>,
     Path(session_id): Path<String>,
-    Json(req): Json<NewWindowRequest>,
+    body: JsonBody<NewWindowRequest>,
 ) -> WebDriverResult<Json<NewWindowResponse>> {
     let session_arc = state
         .session_manager
         .get_session(&session_id)
         .map_err(WebDriverError::from)?;
+    let req = json_body(body)?;
 
     let mut session = session_arc.lock().unwrap();
 
@@ -895,13 +1012,14 @@ async fn get_window_rect_handler(
 async fn set_window_rect_handler(
     State(state): State<WebDriverState>,
     Path(session_id): Path<String>,
-    Json(req): Json<WindowRectRequest>,
+    body: JsonBody<WindowRectRequest>,
 ) -> WebDriverResult<Json<WindowRectResponse>> {
     // Verify session exists
     let _session = state
         .session_manager
         .get_session(&session_id)
         .map_err(WebDriverError::from)?;
+    let req = json_body(body)?;
 
     // TODO: Integrate with actual window system to set dimensions
     // For now, return the requested rect (or defaults if not provided)
@@ -984,6 +1102,42 @@ async fn fullscreen_window_handler(
     }))
 }
 
+/// POST /session/:session_id/actions - Perform actions
+async fn perform_actions_handler(
+    State(state): State<WebDriverState>,
+    Path(session_id): Path<String>,
+    body: JsonBody<ActionsRequest>,
+) -> WebDriverResult<Json<NullResponse>> {
+    let session_arc = state
+        .session_manager
+        .get_session(&session_id)
+        .map_err(WebDriverError::from)?;
+    let req = json_body(body)?;
+
+    let mut session = session_arc.lock().unwrap();
+    session
+        .perform_actions(&req.actions)
+        .map_err(WebDriverError::from)?;
+
+    Ok(Json(NullResponse::default()))
+}
+
+/// DELETE /session/:session_id/actions - Release actions
+async fn release_actions_handler(
+    State(state): State<WebDriverState>,
+    Path(session_id): Path<String>,
+) -> WebDriverResult<Json<NullResponse>> {
+    let session_arc = state
+        .session_manager
+        .get_session(&session_id)
+        .map_err(WebDriverError::from)?;
+
+    let mut session = session_arc.lock().unwrap();
+    session.release_actions().map_err(WebDriverError::from)?;
+
+    Ok(Json(NullResponse::default()))
+}
+
 // ============================================================================
 // Error handling
 // ============================================================================
@@ -1003,6 +1157,8 @@ impl IntoResponse for WebDriverError {
         let status = match error_response.value.error.as_str() {
             "invalid session id" => StatusCode::NOT_FOUND,
             "invalid argument" => StatusCode::BAD_REQUEST,
+            "unknown command" => StatusCode::NOT_FOUND,
+            "unknown method" => StatusCode::METHOD_NOT_ALLOWED,
             "no such element" => StatusCode::NOT_FOUND,
             "no such window" => StatusCode::NOT_FOUND,
             "script timeout" => StatusCode::REQUEST_TIMEOUT,
@@ -1018,6 +1174,9 @@ impl IntoResponse for WebDriverError {
 /// Result type for WebDriver handlers
 type WebDriverResult<T> = std::result::Result<T, WebDriverError>;
 
+/// JSON request body, or why it could not be read
+type JsonBody<T> = std::result::Result<Json<T>, JsonRejection>;
+
 // ============================================================================
 // Request/Response types per W3C WebDriver specification
 // ============================================================================
@@ -1070,6 +1229,12 @@ pub struct ExecuteScriptRequest {
     pub args: Vec<serde_json::Value>,
 }
 
+/// Response of a command without a result: `{"value": null}`
+#[derive(Serialize, Debug, Default)]
+pub struct NullResponse {
+    pub value: (),
+}
+
 /// Status response
 #[derive(Serialize, Debug)]
 pub struct StatusResponse {
@@ -1131,6 +1296,12 @@ pub struct SendKeysRequest {
     pub text: String,
 }
 
+/// Perform actions request
+#[derive(Deserialize, Debug)]
+pub struct ActionsRequest {
+    pub actions: Vec<ActionSequence>,
+}
+
 /// Text response
 #[derive(Serialize, Debug)]
 pub struct TextResponse {
@@ -1231,6 +1402,12 @@ mod tests {
         assert!(json.contains("capabilities"));
     }
 
+    #[test]
+    fn test_null_response_serialization() {
+        let json = serde_json::to_value(NullResponse::default()).unwrap();
+        assert_eq!(json, json!({ "value": null }));
+    }
+
     #[test]
     fn test_status_response_serialization() {
         let status = StatusResponse {
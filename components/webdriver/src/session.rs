@@ -1,15 +1,28 @@
 //! WebDriver session management
 
+use crate::actions::{ActionSequence, Input, InputState};
+use crate::dom_interface::DomInterface;
 use crate::element::ElementCache;
 use crate::errors::{Error, Result};
 use browser_core::BrowserEngine;
-use webview_integration::WebViewWrapper;
+use webview_integration::input::{key_event_script, pointer_event_script, KeyEventRaw, PointerEvent};
+use webview_integration::{select_input_path, InputPath, WebViewBackend, WebViewWrapper};
 use serde::{Deserialize, Serialize};
+use shared_types::{SystemIds, TraceContext, TraceGuard};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::sync::atomic::{AtomicU32, Ordering};
 use uuid::Uuid;
 
+/// Open the trace a WebDriver command runs in
+///
+/// Messages sent while the guard is alive carry the trace, so the engine
+/// and network work done for the command can be followed back to it.
+fn command_trace() -> TraceGuard {
+    static IDS: OnceLock<SystemIds> = OnceLock::new();
+    TraceContext::current_or_root(IDS.get_or_init(SystemIds::new)).enter()
+}
+
 /// Represents a window handle with associated metadata
 #[derive(Debug, Clone)]
 pub struct WindowHandle {
@@ -45,8 +58,12 @@ pub struct Session {
     pub browser_engine: Option<Arc<Mutex<BrowserEngine>>>,
     /// WebView wrapper for rendering and script execution (headless compatible)
     pub webview: Option<Arc<Mutex<WebViewWrapper>>>,
+    /// Backend for trusted (native) input injection, if available
+    pub input_backend: Option<Arc<dyn WebViewBackend>>,
     /// Element cache for managing element references
     pub element_cache: ElementCache,
+    /// Pointer position and held keys and buttons left by input actions
+    pub input_state: InputState,
     /// Map of window handles (UUID) to WindowHandle metadata
     window_handles: HashMap<String, WindowHandle>,
     /// Currently active window handle
@@ -73,7 +90,9 @@ impl Session {
             window_handle: window_handle.clone(),
             browser_engine: None,
             webview: None,
+            input_backend: None,
             element_cache: ElementCache::new(),
+            input_state: InputState::default(),
             window_handles,
             current_window: Some(window_handle),
             next_tab_id: AtomicU32::new(2), // Start at 2 since we used 1 for initial window
@@ -100,8 +119,10 @@ impl Session {
             current_url: None,
             window_handle: window_handle.clone(),
             browser_engine: Some(Arc::new(Mutex::new(browser_engine))),
+            input_backend: Some(webview.backend()),
             webview: Some(Arc::new(Mutex::new(webview))),
             element_cache: ElementCache::new(),
+            input_state: InputState::default(),
             window_handles,
             current_window: Some(window_handle),
             next_tab_id: AtomicU32::new(2),
@@ -110,6 +131,7 @@ impl Session {
 
     /// Navigate to a URL
     pub fn navigate(&mut self, url: String) -> Result<()> {
+        let _trace = command_trace();
         // Validate URL
         url::Url::parse(&url)
             .map_err(|e| Error::InvalidArgument(format!("Invalid URL: {}", e)))?;
@@ -144,6 +166,7 @@ impl Session {
 
     /// Execute JavaScript in the session's webview
     pub fn execute_script(&self, script: &str) -> Result<String> {
+        let _trace = command_trace();
         if let Some(webview) = &self.webview {
             let mut webview = webview.lock().unwrap();
             webview.execute_script(script)
@@ -177,6 +200,158 @@ impl Session {
         }
     }
 
+    /// Attach a backend for trusted input injection
+    pub fn set_input_backend(&mut self, backend: Arc<dyn WebViewBackend>) {
+        self.input_backend = Some(backend);
+    }
+
+    /// Dispatch a sequence of pointer events to the current window
+    ///
+    /// Uses trusted native injection when the attached backend supports it,
+    /// otherwise falls back to (untrusted) bridge dispatch via script.
+    ///
+    /// # Returns
+    ///
+    /// The input path that was used.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if injection or script execution fails.
+    pub fn dispatch_pointer_events(&self, events: &[PointerEvent]) -> Result<InputPath> {
+        let _trace = command_trace();
+        let path = select_input_path(self.input_backend.as_deref());
+        let tab_id = self.current_tab_id()?;
+
+        for event in events {
+            match (path, &self.input_backend) {
+                (InputPath::Trusted, Some(backend)) => backend
+                    .inject_pointer(tab_id, *event)
+                    .map_err(|e| Error::ServerError(format!("Input injection failed: {}", e)))?,
+                _ => {
+                    self.execute_script(&pointer_event_script(event))?;
+                }
+            }
+        }
+
+        Ok(path)
+    }
+
+    /// Dispatch a sequence of key events to the current window
+    ///
+    /// Uses the same input path as
+    /// [`dispatch_pointer_events`](Self::dispatch_pointer_events).
+    ///
+    /// # Returns
+    ///
+    /// The input path that was used.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if injection or script execution fails.
+    pub fn dispatch_key_events(&self, events: &[KeyEventRaw]) -> Result<InputPath> {
+        let _trace = command_trace();
+        let path = select_input_path(self.input_backend.as_deref());
+        let tab_id = self.current_tab_id()?;
+
+        for event in events {
+            match (path, &self.input_backend) {
+                (InputPath::Trusted, Some(backend)) => backend
+                    .inject_key(tab_id, event.clone())
+                    .map_err(|e| Error::ServerError(format!("Input injection failed: {}", e)))?,
+                _ => {
+                    self.execute_script(&key_event_script(event))?;
+                }
+            }
+        }
+
+        Ok(path)
+    }
+
+    /// Perform input actions, tick by tick
+    ///
+    /// See [`actions`](crate::actions) for how ticks are formed. Keys and
+    /// buttons still held afterwards stay held until
+    /// [`release_actions`](Self::release_actions).
+    ///
+    /// # Arguments
+    ///
+    /// * `sequences` - One action sequence per input source
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::InvalidArgument` if a source is given an action it
+    /// cannot perform, before anything is dispatched, and otherwise the
+    /// first error from resolving an element origin or dispatching input.
+    pub fn perform_actions(&mut self, sequences: &[ActionSequence]) -> Result<()> {
+        let _trace = command_trace();
+        for sequence in sequences {
+            sequence.validate()?;
+        }
+
+        let ticks = sequences
+            .iter()
+            .map(|sequence| sequence.actions().len())
+            .max()
+            .unwrap_or(0);
+        // Element origins are resolved through the session while the state
+        // is updated, so it is held outside the session meanwhile
+        let mut state = std::mem::take(&mut self.input_state);
+        let result = (0..ticks).try_for_each(|tick| {
+            let mut duration = std::time::Duration::ZERO;
+            for action in sequences.iter().filter_map(|s| s.actions().get(tick)) {
+                duration = duration.max(action.duration());
+                if let Some(input) = state.apply(action, |id| self.element_center(id))? {
+                    self.dispatch_input(input)?;
+                }
+            }
+            std::thread::sleep(duration);
+            Ok(())
+        });
+        self.input_state = state;
+        result
+    }
+
+    /// Release every key and button held by earlier input actions
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if dispatching a release fails; the input state is
+    /// reset regardless.
+    pub fn release_actions(&mut self) -> Result<()> {
+        let _trace = command_trace();
+        self.input_state
+            .release()
+            .into_iter()
+            .try_for_each(|input| self.dispatch_input(input))
+    }
+
+    fn dispatch_input(&self, input: Input) -> Result<()> {
+        match input {
+            Input::Pointer(event) => self.dispatch_pointer_events(&[event]),
+            Input::Key(event) => self.dispatch_key_events(&[event]),
+        }
+        .map(|_| ())
+    }
+
+    /// Viewport position of a cached element's center
+    fn element_center(&self, element_id: &str) -> Result<(f64, f64)> {
+        let cached = self.element_cache.get_element(element_id)?;
+        let script = DomInterface::new()
+            .generate_center_script(&cached.reference.selector, cached.reference.index);
+        let result = self.execute_script(&script)?;
+        serde_json::from_str::<Option<(f64, f64)>>(result.trim())
+            .map_err(|e| Error::JavaScriptError(format!("Invalid element position: {}", e)))?
+            .ok_or_else(|| Error::NoSuchElement(format!("Element not found: {}", element_id)))
+    }
+
+    fn current_tab_id(&self) -> Result<u32> {
+        self.current_window
+            .as_ref()
+            .and_then(|h| self.window_handles.get(h))
+            .map(|h| h.tab_id)
+            .ok_or_else(|| Error::NoSuchWindow("No current window".to_string()))
+    }
+
     // =================================================================
     // Window Management Methods
     // =================================================================
@@ -387,6 +562,8 @@ impl Default for Timeouts {
 #[derive(Clone)]
 pub struct SessionManager {
     sessions: Arc<Mutex<HashMap<String, Arc<Mutex<Session>>>>>,
+    /// Most sessions allowed at once; None means unlimited
+    max_sessions: Option<usize>,
 }
 
 impl SessionManager {
@@ -394,31 +571,59 @@ impl SessionManager {
     pub fn new() -> Self {
         Self {
             sessions: Arc::new(Mutex::new(HashMap::new())),
+            max_sessions: None,
         }
     }
 
+    /// Limit the number of concurrent sessions
+    ///
+    /// # Arguments
+    ///
+    /// * `max` - Most sessions allowed at once
+    pub fn with_max_sessions(mut self, max: usize) -> Self {
+        self.max_sessions = Some(max);
+        self
+    }
+
     /// Create a new session
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::SessionNotCreated` if the session limit is reached.
     pub fn create_session(&self, capabilities: Capabilities) -> Result<String> {
-        let session = Session::new(capabilities);
-        let session_id = session.id.clone();
-
-        let mut sessions = self.sessions.lock().unwrap();
-        sessions.insert(session_id.clone(), Arc::new(Mutex::new(session)));
-
-        Ok(session_id)
+        self.insert_session(Session::new(capabilities))
     }
 
     /// Create a new session with browser components
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::SessionNotCreated` if the session limit is reached.
     pub fn create_session_with_browser(
         &self,
         capabilities: Capabilities,
         browser_engine: BrowserEngine,
         webview: WebViewWrapper,
     ) -> Result<String> {
-        let session = Session::new_with_browser(capabilities, browser_engine, webview);
+        self.insert_session(Session::new_with_browser(
+            capabilities,
+            browser_engine,
+            webview,
+        ))
+    }
+
+    fn insert_session(&self, session: Session) -> Result<String> {
         let session_id = session.id.clone();
 
         let mut sessions = self.sessions.lock().unwrap();
+        if let Some(max) = self.max_sessions {
+            if sessions.len() >= max {
+                return Err(Error::SessionNotCreated(format!(
+                    "Maximum of {} concurrent sessions reached",
+                    max
+                )));
+            }
+        }
         sessions.insert(session_id.clone(), Arc::new(Mutex::new(session)));
 
         Ok(session_id)
@@ -530,6 +735,20 @@ mod tests {
         assert_eq!(manager.session_count(), 0);
     }
 
+    #[test]
+    fn test_session_manager_max_sessions() {
+        let manager = SessionManager::new().with_max_sessions(1);
+
+        let session_id = manager.create_session(Capabilities::default()).unwrap();
+        assert!(matches!(
+            manager.create_session(Capabilities::default()),
+            Err(Error::SessionNotCreated(_))
+        ));
+
+        manager.delete_session(&session_id).unwrap();
+        assert!(manager.create_session(Capabilities::default()).is_ok());
+    }
+
     #[test]
     fn test_session_manager_get_nonexistent() {
         let manager = SessionManager::new();
@@ -694,6 +913,148 @@ mod tests {
         assert!(session.get_current_window().is_none());
     }
 
+    #[test]
+    fn test_dispatch_pointer_events_prefers_trusted_backend() {
+        use webview_integration::input::{InjectedInput, MockBackend};
+
+        let mut session = Session::new(Capabilities::default());
+        let backend = Arc::new(MockBackend::new());
+        session.set_input_backend(backend.clone());
+
+        let path = session
+            .dispatch_pointer_events(&PointerEvent::click_sequence(4.0, 2.0))
+            .unwrap();
+
+        assert_eq!(path, InputPath::Trusted);
+        let injected = backend.injected();
+        assert_eq!(injected.len(), 3);
+        assert!(matches!(
+            injected[0],
+            InjectedInput::Pointer { tab_id: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn test_dispatch_pointer_events_falls_back_to_bridge() {
+        use webview_integration::input::MockBackend;
+
+        let mut session = Session::new(Capabilities::default());
+        let path = session
+            .dispatch_pointer_events(&PointerEvent::click_sequence(0.0, 0.0))
+            .unwrap();
+        assert_eq!(path, InputPath::Bridge);
+
+        let backend = Arc::new(MockBackend::with_trusted_input(false));
+        session.set_input_backend(backend.clone());
+        let path = session
+            .dispatch_pointer_events(&PointerEvent::click_sequence(0.0, 0.0))
+            .unwrap();
+        assert_eq!(path, InputPath::Bridge);
+        assert!(backend.injected().is_empty());
+    }
+
+    #[test]
+    fn test_perform_actions_clicks_through_trusted_backend() {
+        use webview_integration::input::{InjectedInput, MockBackend, PointerEventKind};
+
+        let mut session = Session::new(Capabilities::default());
+        let backend = Arc::new(MockBackend::new());
+        session.set_input_backend(backend.clone());
+        let sequences: Vec<ActionSequence> = serde_json::from_value(serde_json::json!([
+            {
+                "type": "pointer",
+                "id": "mouse",
+                "actions": [
+                    { "type": "pointerMove", "x": 30, "y": 40 },
+                    { "type": "pointerDown", "button": 0 },
+                    { "type": "pointerUp", "button": 0 }
+                ]
+            },
+            {
+                "type": "key",
+                "id": "keyboard",
+                "actions": [{ "type": "keyDown", "value": "\u{E009}" }]
+            }
+        ]))
+        .unwrap();
+
+        session.perform_actions(&sequences).unwrap();
+
+        let kinds: Vec<_> = backend
+            .injected()
+            .into_iter()
+            .map(|input| match input {
+                InjectedInput::Pointer { event, .. } => match event.kind {
+                    PointerEventKind::Move => "move".to_string(),
+                    PointerEventKind::Down => "down".to_string(),
+                    PointerEventKind::Up => "up".to_string(),
+                },
+                InjectedInput::Key { event, .. } => event.key,
+            })
+            .collect();
+        assert_eq!(kinds, ["move", "Control", "down", "up"]);
+        assert_eq!(session.input_state.pointer, (30.0, 40.0));
+
+        // The control key stays held until the actions are released
+        backend.clear();
+        session.release_actions().unwrap();
+        assert!(matches!(
+            backend.injected().as_slice(),
+            [InjectedInput::Key { event, .. }] if event.key == "Control"
+        ));
+        assert!(session.input_state.keys.is_empty());
+    }
+
+    #[test]
+    fn test_perform_actions_rejects_invalid_sequences_up_front() {
+        use webview_integration::input::MockBackend;
+
+        let mut session = Session::new(Capabilities::default());
+        let backend = Arc::new(MockBackend::new());
+        session.set_input_backend(backend.clone());
+        let sequences: Vec<ActionSequence> = serde_json::from_value(serde_json::json!([
+            {
+                "type": "pointer",
+                "id": "mouse",
+                "actions": [{ "type": "pointerDown", "button": 0 }]
+            },
+            {
+                "type": "none",
+                "id": "wait",
+                "actions": [{ "type": "keyDown", "value": "a" }]
+            }
+        ]))
+        .unwrap();
+
+        assert!(matches!(
+            session.perform_actions(&sequences),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(backend.injected().is_empty());
+        assert!(session.input_state.buttons.is_empty());
+    }
+
+    #[test]
+    fn test_perform_actions_element_origin_needs_a_known_element() {
+        let mut session = Session::new(Capabilities::default());
+        let sequences: Vec<ActionSequence> = serde_json::from_value(serde_json::json!([{
+            "type": "pointer",
+            "id": "mouse",
+            "actions": [{
+                "type": "pointerMove",
+                "x": 0,
+                "y": 0,
+                "origin": { crate::actions::ELEMENT_KEY: "missing" }
+            }]
+        }]))
+        .unwrap();
+
+        assert!(matches!(
+            session.perform_actions(&sequences),
+            Err(Error::NoSuchElement(_))
+        ));
+    }
+
     #[test]
     fn test_window_handle_struct() {
         let handle = WindowHandle::new("test-uuid".to_string(), 42);
@@ -0,0 +1,177 @@
+//! Integration tests for WebDriver access control
+//!
+//! Each test serves the real router on an ephemeral loopback port and talks
+//! to it over HTTP, checking Host header, token, session, rate and body
+//! limits.
+
+use serde_json::Value;
+use shared_types::{Clock, MockClock};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use webdriver::{serve, ServerConfig};
+
+/// Serve `config` on 127.0.0.1 with a mock clock
+async fn spawn_server(config: ServerConfig, clock: Arc<MockClock>) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let clock: Arc<dyn Clock> = clock;
+    tokio::spawn(serve(listener, config, clock));
+    addr
+}
+
+/// A server with the rate limit turned off
+async fn spawn_unlimited(config: ServerConfig) -> SocketAddr {
+    spawn_server(config.with_rate_limit(0, 0), Arc::new(MockClock::default())).await
+}
+
+async fn error_code(response: reqwest::Response) -> String {
+    let body: Value = serde_json::from_str(&response.text().await.unwrap()).unwrap();
+    body["value"]["error"].as_str().unwrap().to_string()
+}
+
+fn new_session(client: &reqwest::Client, addr: SocketAddr) -> reqwest::RequestBuilder {
+    client
+        .post(format!("http://{}/session", addr))
+        .header("Content-Type", "application/json")
+        .body(r#"{"capabilities":{}}"#)
+}
+
+#[tokio::test]
+async fn test_non_local_host_header_rejected() {
+    let addr = spawn_unlimited(ServerConfig::default()).await;
+    let client = reqwest::Client::new();
+    let status_url = format!("http://{}/status", addr);
+
+    let response = client
+        .get(&status_url)
+        .header("Host", format!("rebind.example:{}", addr.port()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+    assert_eq!(error_code(response).await, "unknown error");
+
+    for host in [
+        "localhost".to_string(),
+        format!("127.0.0.1:{}", addr.port()),
+    ] {
+        let response = client
+            .get(&status_url)
+            .header("Host", host)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+    }
+}
+
+#[tokio::test]
+async fn test_remote_allowed_accepts_any_host_but_requires_token() {
+    let addr = spawn_unlimited(ServerConfig::default().with_allow_remote(true)).await;
+    let client = reqwest::Client::new();
+
+    // A token was generated because none was configured
+    let response = client
+        .get(format!("http://{}/status", addr))
+        .header("Host", "browser-farm.example")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+}
+
+#[tokio::test]
+async fn test_bearer_token_required_rejected_and_accepted() {
+    let addr = spawn_unlimited(ServerConfig::default().with_auth_token("s3cret")).await;
+    let client = reqwest::Client::new();
+    let status_url = format!("http://{}/status", addr);
+
+    let response = client.get(&status_url).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    assert_eq!(response.headers()["www-authenticate"], "Bearer");
+
+    let response = client
+        .get(&status_url)
+        .bearer_auth("wrong")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+
+    let response = client
+        .get(&status_url)
+        .bearer_auth("s3cret")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn test_session_cap_returns_session_not_created() {
+    let addr = spawn_unlimited(ServerConfig::default().with_max_sessions(1)).await;
+    let client = reqwest::Client::new();
+
+    let first = new_session(&client, addr).send().await.unwrap();
+    assert_eq!(first.status(), 200);
+    let body: Value = serde_json::from_str(&first.text().await.unwrap()).unwrap();
+    let session_id = body["value"]["sessionId"].as_str().unwrap().to_string();
+
+    let second = new_session(&client, addr).send().await.unwrap();
+    assert_eq!(second.status(), 500);
+    assert_eq!(error_code(second).await, "session not created");
+
+    // Ending a session frees its slot
+    let deleted = client
+        .delete(format!("http://{}/session/{}", addr, session_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(deleted.status(), 200);
+    let third = new_session(&client, addr).send().await.unwrap();
+    assert_eq!(third.status(), 200);
+}
+
+#[tokio::test]
+async fn test_rate_limit_triggers_and_recovers() {
+    let clock = Arc::new(MockClock::default());
+    let addr = spawn_server(ServerConfig::default().with_rate_limit(1, 2), clock.clone()).await;
+    let client = reqwest::Client::new();
+    let status_url = format!("http://{}/status", addr);
+
+    for _ in 0..2 {
+        let response = client.get(&status_url).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
+    let limited = client.get(&status_url).send().await.unwrap();
+    assert_eq!(limited.status(), 429);
+    assert_eq!(limited.headers()["retry-after"], "1");
+    assert_eq!(error_code(limited).await, "unknown error");
+
+    clock.advance(Duration::from_secs(1));
+    let response = client.get(&status_url).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn test_oversized_body_is_invalid_argument() {
+    let addr = spawn_unlimited(ServerConfig::default().with_max_body_bytes(256)).await;
+    let client = reqwest::Client::new();
+
+    let script = format!(r#"{{"script":"return '{}';","args":[]}}"#, "x".repeat(1024));
+    let response = client
+        .post(format!("http://{}/session/any/execute/sync", addr))
+        .header("Content-Type", "application/json")
+        .body(script)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    assert_eq!(error_code(response).await, "invalid argument");
+
+    // Small bodies still reach the handlers
+    let response = new_session(&client, addr).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+}
@@ -0,0 +1,922 @@
+//! Conformance case table
+//!
+//! [`table`] holds the hand-written cases, grouped by spec chapter, and
+//! [`routes`] lists every implemented endpoint for
+//! [`negative_cases`](super::negative_cases). Chapters the server does not
+//! implement yet are covered by probes that must answer `unknown command`.
+
+use super::{Case, Chapter, Route, Shape};
+use reqwest::Method;
+use serde_json::json;
+
+const RECT_KEYS: &[&str] = &["x", "y", "width", "height"];
+
+/// Every implemented endpoint
+pub fn routes() -> Vec<Route> {
+    use Chapter::*;
+
+    let locator = json!({ "using": "css selector", "value": "#main" });
+    let script = json!({ "script": "return 1;", "args": [] });
+    vec![
+        Route::new(Sessions, Method::GET, "/status"),
+        Route::new(Sessions, Method::POST, "/session")
+            .with_body(json!({ "capabilities": {} }), &["capabilities"]),
+        Route::new(Sessions, Method::DELETE, "/session/{session}"),
+        Route::new(Navigation, Method::GET, "/session/{session}/url"),
+        Route::new(Navigation, Method::POST, "/session/{session}/url")
+            .with_body(json!({ "url": "https://example.com/" }), &["url"]),
+        Route::new(Contexts, Method::GET, "/session/{session}/window"),
+        Route::new(Contexts, Method::POST, "/session/{session}/window")
+            .with_body(json!({ "handle": "{window}" }), &["handle"]),
+        Route::new(Contexts, Method::DELETE, "/session/{session}/window"),
+        Route::new(Contexts, Method::GET, "/session/{session}/window/handles"),
+        Route::new(Contexts, Method::POST, "/session/{session}/window/new")
+            .with_body(json!({ "type": "tab" }), &["type"]),
+        Route::new(Contexts, Method::GET, "/session/{session}/window/rect"),
+        Route::new(Contexts, Method::POST, "/session/{session}/window/rect")
+            .with_body(json!({ "width": 800, "height": 600 }), &[]),
+        Route::new(Contexts, Method::POST, "/session/{session}/window/maximize"),
+        Route::new(Contexts, Method::POST, "/session/{session}/window/minimize"),
+        Route::new(
+            Contexts,
+            Method::POST,
+            "/session/{session}/window/fullscreen",
+        ),
+        Route::new(Elements, Method::POST, "/session/{session}/element")
+            .with_body(locator.clone(), &["using", "value"]),
+        Route::new(Elements, Method::POST, "/session/{session}/elements")
+            .with_body(locator, &["using", "value"]),
+        Route::new(
+            Elements,
+            Method::POST,
+            "/session/{session}/element/{element}/click",
+        ),
+        Route::new(
+            Elements,
+            Method::POST,
+            "/session/{session}/element/{element}/value",
+        )
+        .with_body(json!({ "text": "hello" }), &["text"]),
+        Route::new(
+            Elements,
+            Method::POST,
+            "/session/{session}/element/{element}/clear",
+        ),
+        Route::new(
+            Elements,
+            Method::GET,
+            "/session/{session}/element/{element}/text",
+        ),
+        Route::new(
+            Elements,
+            Method::GET,
+            "/session/{session}/element/{element}/attribute/href",
+        ),
+        Route::new(
+            Elements,
+            Method::GET,
+            "/session/{session}/element/{element}/displayed",
+        ),
+        Route::new(Document, Method::POST, "/session/{session}/execute/sync")
+            .with_body(script.clone(), &["script", "args"]),
+        Route::new(Document, Method::POST, "/session/{session}/execute/async")
+            .with_body(script, &["script", "args"]),
+        Route::new(Actions, Method::POST, "/session/{session}/actions")
+            .with_body(json!({ "actions": [] }), &["actions"]),
+        Route::new(Actions, Method::DELETE, "/session/{session}/actions"),
+        Route::new(Screenshots, Method::GET, "/session/{session}/screenshot"),
+    ]
+}
+
+/// The hand-written cases
+pub fn table() -> Vec<Case> {
+    let mut cases = Vec::new();
+    cases.extend(sessions());
+    cases.extend(navigation());
+    cases.extend(contexts());
+    cases.extend(elements());
+    cases.extend(document());
+    cases.extend(actions());
+    cases.extend(unimplemented());
+    cases.extend(screenshots());
+    cases
+}
+
+fn sessions() -> Vec<Case> {
+    use Chapter::Sessions;
+    let created = Shape::Object(&["sessionId", "capabilities"]);
+
+    vec![
+        Case::new(
+            Sessions,
+            "status is ready",
+            Method::GET,
+            "/status",
+            200,
+            Shape::Object(&["ready", "message"]),
+        ),
+        Case::new(
+            Sessions,
+            "new session with empty capabilities",
+            Method::POST,
+            "/session",
+            200,
+            created.clone(),
+        )
+        .with_json(json!({ "capabilities": {} })),
+        Case::new(
+            Sessions,
+            "new session with alwaysMatch",
+            Method::POST,
+            "/session",
+            200,
+            created.clone(),
+        )
+        .with_json(
+            json!({ "capabilities": { "alwaysMatch": { "browserName": "frankenbrowser" } } }),
+        ),
+        Case::new(
+            Sessions,
+            "new session with firstMatch",
+            Method::POST,
+            "/session",
+            200,
+            created,
+        )
+        .with_json(json!({ "capabilities": { "firstMatch": [{}] } })),
+        Case::new(
+            Sessions,
+            "new session with a script timeout",
+            Method::POST,
+            "/session",
+            200,
+            Shape::Object(&["sessionId", "capabilities"]),
+        )
+        .with_json(json!({ "capabilities": { "alwaysMatch": { "timeouts": { "script": 100 } } } })),
+        Case::new(
+            Sessions,
+            "new session with non-object capabilities",
+            Method::POST,
+            "/session",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({ "capabilities": 5 })),
+        Case::new(
+            Sessions,
+            "new session without a body",
+            Method::POST,
+            "/session",
+            400,
+            Shape::Error("invalid argument"),
+        ),
+        Case::new(
+            Sessions,
+            "delete session",
+            Method::DELETE,
+            "/session/{session}",
+            200,
+            Shape::Null,
+        ),
+        Case::new(
+            Sessions,
+            "deleted session is gone",
+            Method::GET,
+            "/session/{session}/url",
+            404,
+            Shape::Error("invalid session id"),
+        )
+        .with_setup(Method::DELETE, "/session/{session}", json!({})),
+        Case::new(
+            Sessions,
+            "unknown top-level command",
+            Method::GET,
+            "/sessions",
+            404,
+            Shape::Error("unknown command"),
+        ),
+        Case::new(
+            Sessions,
+            "unknown session command",
+            Method::POST,
+            "/session/{session}/no-such-command",
+            404,
+            Shape::Error("unknown command"),
+        ),
+    ]
+}
+
+fn navigation() -> Vec<Case> {
+    use Chapter::Navigation;
+
+    vec![
+        Case::new(
+            Navigation,
+            "new session is on about:blank",
+            Method::GET,
+            "/session/{session}/url",
+            200,
+            Shape::Equals(json!("about:blank")),
+        ),
+        Case::new(
+            Navigation,
+            "navigate to https",
+            Method::POST,
+            "/session/{session}/url",
+            200,
+            Shape::Null,
+        )
+        .with_json(json!({ "url": "https://example.com/" })),
+        Case::new(
+            Navigation,
+            "navigate to a data URL",
+            Method::POST,
+            "/session/{session}/url",
+            200,
+            Shape::Null,
+        )
+        .with_json(json!({ "url": "data:text/html,<p>hi</p>" })),
+        Case::new(
+            Navigation,
+            "current URL follows navigation",
+            Method::GET,
+            "/session/{session}/url",
+            200,
+            Shape::Equals(json!("https://example.com/")),
+        )
+        .with_setup(
+            Method::POST,
+            "/session/{session}/url",
+            json!({ "url": "https://example.com/" }),
+        ),
+        Case::new(
+            Navigation,
+            "navigate back to about:blank",
+            Method::GET,
+            "/session/{session}/url",
+            200,
+            Shape::Equals(json!("about:blank")),
+        )
+        .with_setup(
+            Method::POST,
+            "/session/{session}/url",
+            json!({ "url": "https://example.com/" }),
+        )
+        .with_setup(
+            Method::POST,
+            "/session/{session}/url",
+            json!({ "url": "about:blank" }),
+        ),
+        Case::new(
+            Navigation,
+            "navigate with an empty URL",
+            Method::POST,
+            "/session/{session}/url",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({ "url": "" })),
+        Case::new(
+            Navigation,
+            "navigate to a relative URL",
+            Method::POST,
+            "/session/{session}/url",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({ "url": "/relative/path" })),
+        Case::new(
+            Navigation,
+            "navigate with a non-string URL",
+            Method::POST,
+            "/session/{session}/url",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({ "url": 5 })),
+        Case::unsupported(Navigation, "back", Method::POST, "/session/{session}/back"),
+        Case::unsupported(
+            Navigation,
+            "forward",
+            Method::POST,
+            "/session/{session}/forward",
+        ),
+        Case::unsupported(
+            Navigation,
+            "refresh",
+            Method::POST,
+            "/session/{session}/refresh",
+        ),
+        Case::unsupported(Navigation, "title", Method::GET, "/session/{session}/title"),
+    ]
+}
+
+fn contexts() -> Vec<Case> {
+    use Chapter::Contexts;
+    let new_tab = json!({ "type": "tab" });
+
+    vec![
+        Case::new(
+            Contexts,
+            "window handle",
+            Method::GET,
+            "/session/{session}/window",
+            200,
+            Shape::String,
+        ),
+        Case::new(
+            Contexts,
+            "window handles",
+            Method::GET,
+            "/session/{session}/window/handles",
+            200,
+            Shape::StringArray,
+        ),
+        Case::new(
+            Contexts,
+            "switch to the current window",
+            Method::POST,
+            "/session/{session}/window",
+            200,
+            Shape::Null,
+        )
+        .with_json(json!({ "handle": "{window}" })),
+        Case::new(
+            Contexts,
+            "switch to an unknown window",
+            Method::POST,
+            "/session/{session}/window",
+            404,
+            Shape::Error("no such window"),
+        )
+        .with_json(json!({ "handle": "no-such-window" })),
+        Case::new(
+            Contexts,
+            "new tab",
+            Method::POST,
+            "/session/{session}/window/new",
+            200,
+            Shape::Object(&["handle", "type"]),
+        )
+        .with_json(new_tab.clone()),
+        Case::new(
+            Contexts,
+            "new window",
+            Method::POST,
+            "/session/{session}/window/new",
+            200,
+            Shape::Object(&["handle", "type"]),
+        )
+        .with_json(json!({ "type": "window" })),
+        Case::new(
+            Contexts,
+            "window handles include a new tab",
+            Method::GET,
+            "/session/{session}/window/handles",
+            200,
+            Shape::StringArray,
+        )
+        .with_setup(
+            Method::POST,
+            "/session/{session}/window/new",
+            new_tab.clone(),
+        ),
+        Case::new(
+            Contexts,
+            "switch back after opening a tab",
+            Method::POST,
+            "/session/{session}/window",
+            200,
+            Shape::Null,
+        )
+        .with_json(json!({ "handle": "{window}" }))
+        .with_setup(
+            Method::POST,
+            "/session/{session}/window/new",
+            new_tab.clone(),
+        ),
+        Case::new(
+            Contexts,
+            "switch with a non-string handle",
+            Method::POST,
+            "/session/{session}/window",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({ "handle": 1 })),
+        Case::new(
+            Contexts,
+            "new window of an unknown type",
+            Method::POST,
+            "/session/{session}/window/new",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({ "type": "popup" })),
+        Case::new(
+            Contexts,
+            "close the last window",
+            Method::DELETE,
+            "/session/{session}/window",
+            200,
+            Shape::Equals(json!([])),
+        ),
+        Case::new(
+            Contexts,
+            "close one of two windows",
+            Method::DELETE,
+            "/session/{session}/window",
+            200,
+            Shape::StringArray,
+        )
+        .with_setup(Method::POST, "/session/{session}/window/new", new_tab),
+        Case::new(
+            Contexts,
+            "window rect",
+            Method::GET,
+            "/session/{session}/window/rect",
+            200,
+            Shape::Object(RECT_KEYS),
+        ),
+        Case::new(
+            Contexts,
+            "set window rect",
+            Method::POST,
+            "/session/{session}/window/rect",
+            200,
+            Shape::Equals(json!({ "x": 0, "y": 0, "width": 800, "height": 600 })),
+        )
+        .with_json(json!({ "width": 800, "height": 600 })),
+        Case::new(
+            Contexts,
+            "set window position",
+            Method::POST,
+            "/session/{session}/window/rect",
+            200,
+            Shape::Equals(json!({ "x": 10, "y": 20, "width": 1280, "height": 720 })),
+        )
+        .with_json(json!({ "x": 10, "y": 20 })),
+        Case::new(
+            Contexts,
+            "set window rect with nothing",
+            Method::POST,
+            "/session/{session}/window/rect",
+            200,
+            Shape::Object(RECT_KEYS),
+        )
+        .with_json(json!({})),
+        Case::new(
+            Contexts,
+            "set window rect with a negative width",
+            Method::POST,
+            "/session/{session}/window/rect",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({ "width": -1 })),
+        Case::new(
+            Contexts,
+            "maximize",
+            Method::POST,
+            "/session/{session}/window/maximize",
+            200,
+            Shape::Object(RECT_KEYS),
+        ),
+        Case::new(
+            Contexts,
+            "minimize",
+            Method::POST,
+            "/session/{session}/window/minimize",
+            200,
+            Shape::Object(RECT_KEYS),
+        ),
+        Case::new(
+            Contexts,
+            "fullscreen",
+            Method::POST,
+            "/session/{session}/window/fullscreen",
+            200,
+            Shape::Object(RECT_KEYS),
+        ),
+        Case::unsupported(
+            Contexts,
+            "switch to frame",
+            Method::POST,
+            "/session/{session}/frame",
+        ),
+    ]
+}
+
+fn elements() -> Vec<Case> {
+    use Chapter::Elements;
+    let missing = Shape::Error("no such element");
+
+    vec![
+        Case::new(
+            Elements,
+            "find element in an empty document",
+            Method::POST,
+            "/session/{session}/element",
+            404,
+            missing.clone(),
+        )
+        .with_json(json!({ "using": "css selector", "value": "#main" })),
+        Case::new(
+            Elements,
+            "find element by xpath",
+            Method::POST,
+            "/session/{session}/element",
+            404,
+            missing.clone(),
+        )
+        .with_json(json!({ "using": "xpath", "value": "//main" })),
+        Case::new(
+            Elements,
+            "find element by link text",
+            Method::POST,
+            "/session/{session}/element",
+            404,
+            missing.clone(),
+        )
+        .with_json(json!({ "using": "link text", "value": "Home" })),
+        Case::new(
+            Elements,
+            "find element with a non-string selector",
+            Method::POST,
+            "/session/{session}/element",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({ "using": "css selector", "value": 7 })),
+        Case::new(
+            Elements,
+            "find element with an unknown strategy",
+            Method::POST,
+            "/session/{session}/element",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({ "using": "magic", "value": "x" })),
+        Case::new(
+            Elements,
+            "find elements in an empty document",
+            Method::POST,
+            "/session/{session}/elements",
+            200,
+            Shape::Equals(json!([])),
+        )
+        .with_json(json!({ "using": "tag name", "value": "a" })),
+        Case::new(
+            Elements,
+            "find elements by css selector",
+            Method::POST,
+            "/session/{session}/elements",
+            200,
+            Shape::Equals(json!([])),
+        )
+        .with_json(json!({ "using": "css selector", "value": "li > a" })),
+        Case::new(
+            Elements,
+            "find elements with an unknown strategy",
+            Method::POST,
+            "/session/{session}/elements",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({ "using": "magic", "value": "x" })),
+        Case::new(
+            Elements,
+            "click an unknown element",
+            Method::POST,
+            "/session/{session}/element/{element}/click",
+            404,
+            missing.clone(),
+        ),
+        Case::new(
+            Elements,
+            "send keys to an unknown element",
+            Method::POST,
+            "/session/{session}/element/{element}/value",
+            404,
+            missing.clone(),
+        )
+        .with_json(json!({ "text": "hello" })),
+        Case::new(
+            Elements,
+            "send non-string keys",
+            Method::POST,
+            "/session/{session}/element/{element}/value",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({ "text": ["a", "b"] })),
+        Case::new(
+            Elements,
+            "clear an unknown element",
+            Method::POST,
+            "/session/{session}/element/{element}/clear",
+            404,
+            missing.clone(),
+        ),
+        Case::new(
+            Elements,
+            "text of an unknown element",
+            Method::GET,
+            "/session/{session}/element/{element}/text",
+            404,
+            missing.clone(),
+        ),
+        Case::new(
+            Elements,
+            "attribute of an unknown element",
+            Method::GET,
+            "/session/{session}/element/{element}/attribute/href",
+            404,
+            missing.clone(),
+        ),
+        Case::new(
+            Elements,
+            "displayedness of an unknown element",
+            Method::GET,
+            "/session/{session}/element/{element}/displayed",
+            404,
+            missing,
+        ),
+        Case::unsupported(
+            Elements,
+            "active element",
+            Method::GET,
+            "/session/{session}/element/active",
+        ),
+        Case::unsupported(
+            Elements,
+            "element rect",
+            Method::GET,
+            "/session/{session}/element/{element}/rect",
+        ),
+    ]
+}
+
+fn document() -> Vec<Case> {
+    use Chapter::Document;
+
+    vec![
+        Case::new(
+            Document,
+            "execute script without a document",
+            Method::POST,
+            "/session/{session}/execute/sync",
+            200,
+            Shape::Null,
+        )
+        .with_json(json!({ "script": "return 1;", "args": [] })),
+        Case::new(
+            Document,
+            "execute script with arguments",
+            Method::POST,
+            "/session/{session}/execute/sync",
+            200,
+            Shape::Null,
+        )
+        .with_json(json!({ "script": "return arguments[0];", "args": [1, "two", null] })),
+        Case::new(
+            Document,
+            "execute script with an element argument",
+            Method::POST,
+            "/session/{session}/execute/sync",
+            200,
+            Shape::Null,
+        )
+        .with_json(json!({
+            "script": "return arguments[0].tagName;",
+            "args": [{ "element-6066-11e4-a52e-4f735466cecf": "{element}" }]
+        })),
+        Case::new(
+            Document,
+            "execute an empty script",
+            Method::POST,
+            "/session/{session}/execute/sync",
+            200,
+            Shape::Null,
+        )
+        .with_json(json!({ "script": "", "args": [] })),
+        Case::new(
+            Document,
+            "execute script with a non-string script",
+            Method::POST,
+            "/session/{session}/execute/sync",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({ "script": 5, "args": [] })),
+        Case::new(
+            Document,
+            "execute script with non-array arguments",
+            Method::POST,
+            "/session/{session}/execute/sync",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({ "script": "return 1;", "args": {} })),
+        Case::new(
+            Document,
+            "execute async script with a non-string script",
+            Method::POST,
+            "/session/{session}/execute/async",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({ "script": [], "args": [] })),
+        Case::unsupported(
+            Document,
+            "page source",
+            Method::GET,
+            "/session/{session}/source",
+        ),
+    ]
+}
+
+fn actions() -> Vec<Case> {
+    use Chapter::Actions;
+
+    vec![
+        Case::new(
+            Actions,
+            "perform no actions",
+            Method::POST,
+            "/session/{session}/actions",
+            200,
+            Shape::Null,
+        )
+        .with_json(json!({ "actions": [] })),
+        Case::new(
+            Actions,
+            "perform a click",
+            Method::POST,
+            "/session/{session}/actions",
+            200,
+            Shape::Null,
+        )
+        .with_json(json!({
+            "actions": [{
+                "type": "pointer",
+                "id": "mouse",
+                "parameters": { "pointerType": "mouse" },
+                "actions": [
+                    { "type": "pointerMove", "x": 10, "y": 10 },
+                    { "type": "pointerDown", "button": 0 },
+                    { "type": "pause", "duration": 1 },
+                    { "type": "pointerUp", "button": 0 }
+                ]
+            }]
+        })),
+        Case::new(
+            Actions,
+            "perform a key action from a pointer source",
+            Method::POST,
+            "/session/{session}/actions",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({
+            "actions": [{
+                "type": "pointer",
+                "id": "mouse",
+                "actions": [{ "type": "keyDown", "value": "a" }]
+            }]
+        })),
+        Case::new(
+            Actions,
+            "perform an unknown action",
+            Method::POST,
+            "/session/{session}/actions",
+            400,
+            Shape::Error("invalid argument"),
+        )
+        .with_json(json!({
+            "actions": [{
+                "type": "key",
+                "id": "keyboard",
+                "actions": [{ "type": "keyPress", "value": "a" }]
+            }]
+        })),
+        Case::new(
+            Actions,
+            "release actions",
+            Method::DELETE,
+            "/session/{session}/actions",
+            200,
+            Shape::Null,
+        ),
+    ]
+}
+
+fn unimplemented() -> Vec<Case> {
+    use Chapter::{Alerts, Cookies, Timeouts};
+
+    vec![
+        Case::unsupported(
+            Timeouts,
+            "get timeouts",
+            Method::GET,
+            "/session/{session}/timeouts",
+        ),
+        Case::unsupported(
+            Timeouts,
+            "set timeouts",
+            Method::POST,
+            "/session/{session}/timeouts",
+        )
+        .with_json(json!({ "script": 1000 })),
+        Case::unsupported(
+            Cookies,
+            "all cookies",
+            Method::GET,
+            "/session/{session}/cookie",
+        ),
+        Case::unsupported(
+            Cookies,
+            "named cookie",
+            Method::GET,
+            "/session/{session}/cookie/name",
+        ),
+        Case::unsupported(
+            Cookies,
+            "add cookie",
+            Method::POST,
+            "/session/{session}/cookie",
+        )
+        .with_json(json!({ "cookie": { "name": "a", "value": "b" } })),
+        Case::unsupported(
+            Cookies,
+            "delete all cookies",
+            Method::DELETE,
+            "/session/{session}/cookie",
+        ),
+        Case::unsupported(
+            Alerts,
+            "dismiss alert",
+            Method::POST,
+            "/session/{session}/alert/dismiss",
+        ),
+        Case::unsupported(
+            Alerts,
+            "accept alert",
+            Method::POST,
+            "/session/{session}/alert/accept",
+        ),
+        Case::unsupported(
+            Alerts,
+            "alert text",
+            Method::GET,
+            "/session/{session}/alert/text",
+        ),
+    ]
+}
+
+fn screenshots() -> Vec<Case> {
+    use Chapter::Screenshots;
+
+    vec![
+        Case::new(
+            Screenshots,
+            "take screenshot",
+            Method::GET,
+            "/session/{session}/screenshot",
+            200,
+            Shape::Png,
+        ),
+        Case::new(
+            Screenshots,
+            "take screenshot after navigating",
+            Method::GET,
+            "/session/{session}/screenshot",
+            200,
+            Shape::Png,
+        )
+        .with_setup(
+            Method::POST,
+            "/session/{session}/url",
+            json!({ "url": "https://example.com/" }),
+        ),
+        Case::new(
+            Screenshots,
+            "take screenshot after opening a tab",
+            Method::GET,
+            "/session/{session}/screenshot",
+            200,
+            Shape::Png,
+        )
+        .with_setup(
+            Method::POST,
+            "/session/{session}/window/new",
+            json!({ "type": "tab" }),
+        ),
+        Case::unsupported(
+            Screenshots,
+            "element screenshot",
+            Method::GET,
+            "/session/{session}/element/{element}/screenshot",
+        ),
+    ]
+}
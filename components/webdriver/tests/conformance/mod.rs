@@ -0,0 +1,584 @@
+//! WebDriver conformance harness
+//!
+//! Serves the full router on an ephemeral loopback port with headless
+//! sessions (no webview, so scripts answer null and no element is ever
+//! found) and runs table cases against it over HTTP. Each case names a
+//! spec chapter, a method, a path template, a body, the expected status
+//! and the shape of the `value` in the response; error cases must carry
+//! the spec's `error` string exactly.
+//!
+//! Path and body templates may use `{session}` (a fresh session per case),
+//! `{window}` (that session's window handle) and `{element}` (an element
+//! id the session has never returned). [`negative_cases`] derives the
+//! error paths of every [`Route`], and [`record_summary`] writes one
+//! result per chapter into a [`TestResultDatabase`] for the dashboard.
+
+pub mod cases;
+
+use base64::Engine;
+use browser_core::{TestResultDatabase, TestStatus};
+use reqwest::Method;
+use serde_json::{json, Value};
+use shared_types::{Clock, MockClock};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::net::TcpListener;
+use webdriver::{serve, ServerConfig};
+
+/// Suite name the chapter results are recorded under
+pub const SUITE: &str = "webdriver-conformance";
+
+/// Session id no server ever hands out
+pub const UNKNOWN_SESSION: &str = "00000000-0000-0000-0000-000000000000";
+
+/// Element id no session ever hands out
+pub const UNKNOWN_ELEMENT: &str = "element-unknown";
+
+/// Body that is not JSON
+pub const MALFORMED_JSON: &str = r#"{"url": "#;
+
+/// Chapters of the WebDriver spec the cases are grouped by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Chapter {
+    Sessions,
+    Navigation,
+    Contexts,
+    Elements,
+    Document,
+    Timeouts,
+    Cookies,
+    Actions,
+    Alerts,
+    Screenshots,
+}
+
+impl Chapter {
+    /// Every chapter, in spec order
+    pub const ALL: [Chapter; 10] = [
+        Chapter::Sessions,
+        Chapter::Navigation,
+        Chapter::Contexts,
+        Chapter::Elements,
+        Chapter::Document,
+        Chapter::Timeouts,
+        Chapter::Cookies,
+        Chapter::Actions,
+        Chapter::Alerts,
+        Chapter::Screenshots,
+    ];
+
+    /// Name the chapter is recorded under
+    pub fn label(self) -> &'static str {
+        match self {
+            Chapter::Sessions => "sessions",
+            Chapter::Navigation => "navigation",
+            Chapter::Contexts => "contexts",
+            Chapter::Elements => "elements",
+            Chapter::Document => "document",
+            Chapter::Timeouts => "timeouts",
+            Chapter::Cookies => "cookies",
+            Chapter::Actions => "actions",
+            Chapter::Alerts => "alerts",
+            Chapter::Screenshots => "screenshots",
+        }
+    }
+}
+
+/// Request body of a case
+#[derive(Debug, Clone)]
+pub enum Body {
+    None,
+    Json(Value),
+    Raw(&'static str),
+}
+
+/// Expected shape of the response's `value`
+#[derive(Debug, Clone)]
+pub enum Shape {
+    /// `null`
+    Null,
+    /// An error object with this `error` string
+    Error(&'static str),
+    /// Exactly this value
+    Equals(Value),
+    String,
+    StringArray,
+    /// An object with at least these keys
+    Object(&'static [&'static str]),
+    /// A base64 encoded PNG
+    Png,
+}
+
+impl Shape {
+    /// Check `value` against the shape
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the mismatch.
+    pub fn check(&self, value: &Value) -> Result<(), String> {
+        let matches = match self {
+            Shape::Null => value.is_null(),
+            Shape::Error(code) => {
+                value["error"] == *code
+                    && value["message"].is_string()
+                    && value["stacktrace"].is_string()
+            }
+            Shape::Equals(expected) => value == expected,
+            Shape::String => value.is_string(),
+            Shape::StringArray => value
+                .as_array()
+                .is_some_and(|items| items.iter().all(Value::is_string)),
+            Shape::Object(keys) => value
+                .as_object()
+                .is_some_and(|object| keys.iter().all(|key| object.contains_key(*key))),
+            Shape::Png => value
+                .as_str()
+                .and_then(|data| base64::engine::general_purpose::STANDARD.decode(data).ok())
+                .is_some_and(|png| png.starts_with(b"\x89PNG\r\n\x1a\n")),
+        };
+        if matches {
+            Ok(())
+        } else {
+            Err(format!("expected {:?}, got {}", self, value))
+        }
+    }
+}
+
+/// One request and the response the spec requires
+#[derive(Debug, Clone)]
+pub struct Case {
+    pub chapter: Chapter,
+    pub name: String,
+    pub method: Method,
+    pub path: String,
+    pub body: Body,
+    pub status: u16,
+    pub shape: Shape,
+    /// Requests made on the case's session first; each must succeed
+    pub setup: Vec<(Method, String, Body)>,
+    /// Probe of a command the server does not implement yet
+    pub unsupported: bool,
+}
+
+impl Case {
+    /// A case expecting `status` and a `value` of `shape`
+    pub fn new(
+        chapter: Chapter,
+        name: &str,
+        method: Method,
+        path: &str,
+        status: u16,
+        shape: Shape,
+    ) -> Self {
+        Self {
+            chapter,
+            name: name.to_string(),
+            method,
+            path: path.to_string(),
+            body: Body::None,
+            status,
+            shape,
+            setup: Vec::new(),
+            unsupported: false,
+        }
+    }
+
+    /// Probe of an unimplemented command, which must be `unknown command`
+    pub fn unsupported(chapter: Chapter, name: &str, method: Method, path: &str) -> Self {
+        Self {
+            unsupported: true,
+            ..Self::new(
+                chapter,
+                name,
+                method,
+                path,
+                404,
+                Shape::Error("unknown command"),
+            )
+        }
+    }
+
+    /// Send `body` as JSON
+    pub fn with_json(mut self, body: Value) -> Self {
+        self.body = Body::Json(body);
+        self
+    }
+
+    /// Send `body` verbatim as `application/json`
+    pub fn with_raw(mut self, body: &'static str) -> Self {
+        self.body = Body::Raw(body);
+        self
+    }
+
+    /// Make a request on the case's session before the case itself
+    pub fn with_setup(mut self, method: Method, path: &str, body: Value) -> Self {
+        self.setup
+            .push((method, path.to_string(), Body::Json(body)));
+        self
+    }
+}
+
+/// An implemented endpoint, with a valid body and its required fields
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub chapter: Chapter,
+    pub method: Method,
+    pub path: &'static str,
+    pub body: Option<Value>,
+    pub required: &'static [&'static str],
+}
+
+impl Route {
+    pub fn new(chapter: Chapter, method: Method, path: &'static str) -> Self {
+        Self {
+            chapter,
+            method,
+            path,
+            body: None,
+            required: &[],
+        }
+    }
+
+    /// Give the route a valid body whose `required` fields must be present
+    pub fn with_body(mut self, body: Value, required: &'static [&'static str]) -> Self {
+        self.body = Some(body);
+        self.required = required;
+        self
+    }
+}
+
+/// Generate the error paths of `routes`
+///
+/// Every session-scoped route is called with an unknown session id,
+/// every route with a body is sent malformed JSON and the body without
+/// each required field, and every path is called with the methods it does
+/// not support.
+pub fn negative_cases(routes: &[Route]) -> Vec<Case> {
+    let mut cases = Vec::new();
+
+    for route in routes {
+        let label = format!("{} {}", route.method, route.path);
+        let body = route.body.clone().map_or(Body::None, Body::Json);
+
+        if route.path.contains("{session}") {
+            let mut case = Case::new(
+                route.chapter,
+                &format!("{} with unknown session", label),
+                route.method.clone(),
+                &route.path.replace("{session}", UNKNOWN_SESSION),
+                404,
+                Shape::Error("invalid session id"),
+            );
+            case.body = body;
+            cases.push(case);
+        }
+
+        if let Some(valid) = &route.body {
+            cases.push(
+                Case::new(
+                    route.chapter,
+                    &format!("{} with malformed JSON", label),
+                    route.method.clone(),
+                    route.path,
+                    400,
+                    Shape::Error("invalid argument"),
+                )
+                .with_raw(MALFORMED_JSON),
+            );
+
+            for field in route.required {
+                let mut partial = valid.clone();
+                if let Some(object) = partial.as_object_mut() {
+                    object.remove(*field);
+                }
+                cases.push(
+                    Case::new(
+                        route.chapter,
+                        &format!("{} without {}", label, field),
+                        route.method.clone(),
+                        route.path,
+                        400,
+                        Shape::Error("invalid argument"),
+                    )
+                    .with_json(partial),
+                );
+            }
+        }
+    }
+
+    let mut methods: BTreeMap<&str, (Chapter, Vec<Method>)> = BTreeMap::new();
+    for route in routes {
+        methods
+            .entry(route.path)
+            .or_insert_with(|| (route.chapter, Vec::new()))
+            .1
+            .push(route.method.clone());
+    }
+    for (path, (chapter, supported)) in methods {
+        for method in [Method::GET, Method::POST, Method::DELETE] {
+            if !supported.contains(&method) {
+                cases.push(Case::new(
+                    chapter,
+                    &format!("{} {} is not allowed", method, path),
+                    method,
+                    path,
+                    405,
+                    Shape::Error("unknown method"),
+                ));
+            }
+        }
+    }
+
+    cases
+}
+
+/// Outcome of one case
+#[derive(Debug, Clone)]
+pub struct CaseResult {
+    pub chapter: Chapter,
+    pub name: String,
+    pub unsupported: bool,
+    pub duration_ms: i64,
+    /// Why the case failed, if it did
+    pub failure: Option<String>,
+}
+
+impl CaseResult {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// A server on an ephemeral port and a client for it
+pub struct Harness {
+    addr: SocketAddr,
+    client: reqwest::Client,
+}
+
+impl Harness {
+    /// Serve the router on 127.0.0.1 with a mock clock and no rate limit
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let clock: Arc<dyn Clock> = Arc::new(MockClock::default());
+        let config = ServerConfig::default()
+            .with_rate_limit(0, 0)
+            .with_max_sessions(8);
+        tokio::spawn(serve(listener, config, clock));
+        Self {
+            addr,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Run `cases` one after another
+    pub async fn run_all(&self, cases: &[Case]) -> Vec<CaseResult> {
+        let mut results = Vec::with_capacity(cases.len());
+        for case in cases {
+            results.push(self.run(case).await);
+        }
+        results
+    }
+
+    /// Run one case on a fresh session, deleting the session afterwards
+    pub async fn run(&self, case: &Case) -> CaseResult {
+        let started = Instant::now();
+        let failure = self.check(case).await.err();
+        CaseResult {
+            chapter: case.chapter,
+            name: case.name.clone(),
+            unsupported: case.unsupported,
+            duration_ms: started.elapsed().as_millis() as i64,
+            failure,
+        }
+    }
+
+    async fn check(&self, case: &Case) -> Result<(), String> {
+        let (session, window) = self.new_session().await?;
+        let fill = |template: &str| {
+            template
+                .replace("{session}", &session)
+                .replace("{window}", &window)
+                .replace("{element}", UNKNOWN_ELEMENT)
+        };
+
+        for (method, path, body) in &case.setup {
+            let (status, value) = self.send(method.clone(), &fill(path), body, &fill).await?;
+            if !(200..300).contains(&status) {
+                self.delete_session(&session).await;
+                return Err(format!(
+                    "setup {} {} failed with {}: {}",
+                    method, path, status, value
+                ));
+            }
+        }
+
+        let response = self
+            .send(case.method.clone(), &fill(&case.path), &case.body, &fill)
+            .await;
+        self.delete_session(&session).await;
+        let (status, value) = response?;
+
+        // Sessions the case created itself are cleaned up too
+        if let Some(created) = value["sessionId"].as_str() {
+            self.delete_session(created).await;
+        }
+
+        if status != case.status {
+            return Err(format!(
+                "expected status {}, got {}: {}",
+                case.status, status, value
+            ));
+        }
+        case.shape.check(&value)
+    }
+
+    /// Send one request and return its status and `value`
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        body: &Body,
+        fill: &dyn Fn(&str) -> String,
+    ) -> Result<(u16, Value), String> {
+        let mut request = self
+            .client
+            .request(method, format!("http://{}{}", self.addr, path));
+        request = match body {
+            Body::None => request,
+            Body::Json(value) => request
+                .header("Content-Type", "application/json")
+                .body(fill(&value.to_string())),
+            Body::Raw(raw) => request
+                .header("Content-Type", "application/json")
+                .body(*raw),
+        };
+
+        let response = request.send().await.map_err(|e| e.to_string())?;
+        let status = response.status().as_u16();
+        let text = response.text().await.map_err(|e| e.to_string())?;
+        let body: Value = serde_json::from_str(&text)
+            .map_err(|e| format!("status {} with a non-JSON body {:?}: {}", status, text, e))?;
+        match body.get("value") {
+            Some(value) => Ok((status, value.clone())),
+            None => Err(format!("status {} without a value: {}", status, body)),
+        }
+    }
+
+    /// Create a session and return its id and window handle
+    async fn new_session(&self) -> Result<(String, String), String> {
+        let (status, value) = self
+            .send(
+                Method::POST,
+                "/session",
+                &Body::Json(json!({ "capabilities": {} })),
+                &|body| body.to_string(),
+            )
+            .await?;
+        let session = value["sessionId"]
+            .as_str()
+            .ok_or_else(|| format!("session not created ({}): {}", status, value))?
+            .to_string();
+
+        let path = format!("/session/{}/window", session);
+        let (_, window) = self
+            .send(Method::GET, &path, &Body::None, &|body| body.to_string())
+            .await?;
+        let window = window
+            .as_str()
+            .ok_or_else(|| format!("no window handle: {}", window))?
+            .to_string();
+        Ok((session, window))
+    }
+
+    async fn delete_session(&self, session: &str) {
+        let url = format!("http://{}/session/{}", self.addr, session);
+        let _ = self.client.delete(url).send().await;
+    }
+}
+
+/// Per-chapter outcome as recorded on the dashboard
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChapterSummary {
+    pub chapter: Chapter,
+    pub status: TestStatus,
+    pub cases: usize,
+    pub failed: usize,
+}
+
+/// Summarize `results` by chapter
+///
+/// A chapter fails if any of its cases failed and is skipped if it has
+/// nothing but probes of unimplemented commands.
+pub fn summarize(results: &[CaseResult]) -> Vec<ChapterSummary> {
+    Chapter::ALL
+        .iter()
+        .filter_map(|&chapter| {
+            let cases: Vec<&CaseResult> = results
+                .iter()
+                .filter(|result| result.chapter == chapter)
+                .collect();
+            if cases.is_empty() {
+                return None;
+            }
+            let failed = cases.iter().filter(|result| !result.passed()).count();
+            let status = if failed > 0 {
+                TestStatus::Failed
+            } else if cases.iter().all(|result| result.unsupported) {
+                TestStatus::Skipped
+            } else {
+                TestStatus::Passed
+            };
+            Some(ChapterSummary {
+                chapter,
+                status,
+                cases: cases.len(),
+                failed,
+            })
+        })
+        .collect()
+}
+
+/// Record one result per chapter under [`SUITE`]
+///
+/// # Errors
+///
+/// Returns the database error if a result cannot be recorded.
+pub fn record_summary(
+    db: &TestResultDatabase,
+    results: &[CaseResult],
+    browser_version: &str,
+) -> browser_core::Result<Vec<ChapterSummary>> {
+    let summaries = summarize(results);
+    for summary in &summaries {
+        let chapter_results = results
+            .iter()
+            .filter(|result| result.chapter == summary.chapter);
+        let duration_ms = chapter_results
+            .clone()
+            .map(|result| result.duration_ms)
+            .sum();
+        let message = (summary.failed > 0).then(|| {
+            let first = chapter_results
+                .filter_map(|result| Some((&result.name, result.failure.as_ref()?)))
+                .map(|(name, failure)| format!("{}: {}", name, failure))
+                .next()
+                .unwrap_or_default();
+            format!(
+                "{} of {} failed; first: {}",
+                summary.failed, summary.cases, first
+            )
+        });
+        db.record_test_result(
+            SUITE,
+            summary.chapter.label(),
+            summary.status,
+            duration_ms,
+            message.as_deref(),
+            browser_version,
+        )?;
+    }
+    Ok(summaries)
+}
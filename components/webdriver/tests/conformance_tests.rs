@@ -0,0 +1,226 @@
+//! WebDriver conformance tests
+//!
+//! Runs the case table in `conformance/cases.rs` chapter by chapter, the
+//! generated negative paths of every implemented endpoint, and records the
+//! per-chapter summary the dashboard tracks. When `FRANKENBROWSER_TEST_DB`
+//! names a database the summary is recorded there as well.
+
+mod conformance;
+
+use browser_core::{TestResultDatabase, TestStatus};
+use conformance::{cases, negative_cases, record_summary, Case, CaseResult, Chapter, Harness};
+use std::collections::BTreeSet;
+
+/// Environment variable naming the dashboard's test result database
+const TEST_DB_ENV: &str = "FRANKENBROWSER_TEST_DB";
+
+fn failures(results: &[CaseResult]) -> Vec<String> {
+    results
+        .iter()
+        .filter_map(|result| {
+            let failure = result.failure.as_ref()?;
+            Some(format!(
+                "[{}] {}: {}",
+                result.chapter.label(),
+                result.name,
+                failure
+            ))
+        })
+        .collect()
+}
+
+async fn assert_chapter_conforms(chapter: Chapter) {
+    let cases: Vec<Case> = cases::table()
+        .into_iter()
+        .filter(|case| case.chapter == chapter)
+        .collect();
+    assert!(!cases.is_empty(), "no cases for {}", chapter.label());
+
+    let results = Harness::start().await.run_all(&cases).await;
+    let failed = failures(&results);
+    assert!(failed.is_empty(), "{}", failed.join("\n"));
+}
+
+#[test]
+fn test_table_covers_every_route() {
+    let table = cases::table();
+    let implemented = table.iter().filter(|case| !case.unsupported).count();
+    assert!(implemented >= 60, "only {} cases", implemented);
+
+    let covered: BTreeSet<(String, &str)> = table
+        .iter()
+        .map(|case| (case.method.to_string(), case.path.as_str()))
+        .collect();
+    for route in cases::routes() {
+        assert!(
+            covered.contains(&(route.method.to_string(), route.path)),
+            "no case for {} {}",
+            route.method,
+            route.path
+        );
+    }
+
+    for chapter in Chapter::ALL {
+        assert!(
+            table.iter().any(|case| case.chapter == chapter),
+            "no cases for {}",
+            chapter.label()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_sessions_chapter() {
+    assert_chapter_conforms(Chapter::Sessions).await;
+}
+
+#[tokio::test]
+async fn test_navigation_chapter() {
+    assert_chapter_conforms(Chapter::Navigation).await;
+}
+
+#[tokio::test]
+async fn test_contexts_chapter() {
+    assert_chapter_conforms(Chapter::Contexts).await;
+}
+
+#[tokio::test]
+async fn test_elements_chapter() {
+    assert_chapter_conforms(Chapter::Elements).await;
+}
+
+#[tokio::test]
+async fn test_document_chapter() {
+    assert_chapter_conforms(Chapter::Document).await;
+}
+
+#[tokio::test]
+async fn test_timeouts_chapter() {
+    assert_chapter_conforms(Chapter::Timeouts).await;
+}
+
+#[tokio::test]
+async fn test_cookies_chapter() {
+    assert_chapter_conforms(Chapter::Cookies).await;
+}
+
+#[tokio::test]
+async fn test_actions_chapter() {
+    assert_chapter_conforms(Chapter::Actions).await;
+}
+
+#[tokio::test]
+async fn test_alerts_chapter() {
+    assert_chapter_conforms(Chapter::Alerts).await;
+}
+
+#[tokio::test]
+async fn test_screenshots_chapter() {
+    assert_chapter_conforms(Chapter::Screenshots).await;
+}
+
+#[tokio::test]
+async fn test_negative_paths() {
+    let routes = cases::routes();
+    let cases = negative_cases(&routes);
+
+    // Every session-scoped route gets an unknown session, every body a
+    // malformed one and each required field goes missing once
+    let scoped = routes
+        .iter()
+        .filter(|route| route.path.contains("{session}"))
+        .count();
+    let with_body = routes.iter().filter(|route| route.body.is_some()).count();
+    let required: usize = routes.iter().map(|route| route.required.len()).sum();
+    let count = |suffix: &str| {
+        cases
+            .iter()
+            .filter(|case| case.name.ends_with(suffix))
+            .count()
+    };
+    assert_eq!(count("with unknown session"), scoped);
+    assert_eq!(count("with malformed JSON"), with_body);
+    assert_eq!(
+        cases
+            .iter()
+            .filter(|case| case.name.contains(" without "))
+            .count(),
+        required
+    );
+    assert!(count("is not allowed") > 0);
+
+    let results = Harness::start().await.run_all(&cases).await;
+    let failed = failures(&results);
+    assert!(failed.is_empty(), "{}", failed.join("\n"));
+}
+
+#[tokio::test]
+async fn test_summary_recorded_per_chapter() {
+    let mut cases = cases::table();
+    cases.extend(negative_cases(&cases::routes()));
+    let results = Harness::start().await.run_all(&cases).await;
+
+    let db = TestResultDatabase::new(None).unwrap();
+    let version = env!("CARGO_PKG_VERSION");
+    let summaries = record_summary(&db, &results, version).unwrap();
+    assert_eq!(summaries.len(), Chapter::ALL.len());
+
+    // Chapters the server does not implement are skipped, not failed
+    for summary in &summaries {
+        let expected = match summary.chapter {
+            Chapter::Timeouts | Chapter::Cookies | Chapter::Alerts => TestStatus::Skipped,
+            _ => TestStatus::Passed,
+        };
+        assert_eq!(summary.status, expected, "{}", summary.chapter.label());
+    }
+    let summary = db.get_test_summary(conformance::SUITE);
+    assert_eq!(summary.total, 10);
+    assert_eq!(summary.passed, 7);
+    assert_eq!(summary.skipped, 3);
+
+    if let Ok(path) = std::env::var(TEST_DB_ENV) {
+        let dashboard = TestResultDatabase::new(Some(path.as_str())).unwrap();
+        record_summary(&dashboard, &results, version).unwrap();
+    }
+}
+
+#[test]
+fn test_failed_case_fails_its_chapter() {
+    let result = |chapter, unsupported, failure: Option<&str>| CaseResult {
+        chapter,
+        name: "case".to_string(),
+        unsupported,
+        duration_ms: 1,
+        failure: failure.map(str::to_string),
+    };
+    let results = vec![
+        result(Chapter::Sessions, false, None),
+        result(
+            Chapter::Sessions,
+            false,
+            Some("expected status 200, got 500"),
+        ),
+        result(Chapter::Navigation, false, None),
+        result(Chapter::Alerts, true, None),
+    ];
+
+    let db = TestResultDatabase::new(None).unwrap();
+    let summaries = record_summary(&db, &results, "test").unwrap();
+    let statuses: Vec<(Chapter, TestStatus)> = summaries
+        .iter()
+        .map(|summary| (summary.chapter, summary.status))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            (Chapter::Sessions, TestStatus::Failed),
+            (Chapter::Navigation, TestStatus::Passed),
+            (Chapter::Alerts, TestStatus::Skipped),
+        ]
+    );
+
+    let recorded = db.get_test_results(Some(conformance::SUITE), None);
+    let sessions = recorded.iter().find(|r| r.name == "sessions").unwrap();
+    let message = sessions.error_message.as_deref().unwrap();
+    assert!(message.starts_with("1 of 2 failed"), "{}", message);
+}
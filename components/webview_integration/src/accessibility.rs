@@ -0,0 +1,446 @@
+//! Accessibility user stylesheet
+//!
+//! The `[accessibility]` settings are turned into a user stylesheet that is
+//! applied at document start, before the page's own styles load. Backends
+//! with a native user-stylesheet API receive the CSS directly; the others
+//! get [`stylesheet_injector_script`], which inserts it as a `<style>`
+//! element. Every generated rule is `!important` so site styles of equal
+//! specificity cannot undo it.
+//!
+//! The stylesheet also enforces `[fonts] minimum_font_size` on backends
+//! without a native minimum font size; see [`UserStylesheet::set_font_floor`].
+//!
+//! A configured custom stylesheet is appended after the generated rules.
+//! [`UserStylesheet`] remembers the file's modification time so a changed
+//! file can be picked up without restarting.
+
+use crate::errors::{Error, Result};
+use config_manager::AccessibilitySettings;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// `id` of the `<style>` element inserted by the injector script
+pub const USER_STYLESHEET_ID: &str = "frankenbrowser-user-stylesheet";
+
+/// First line of every non-empty user stylesheet
+const HEADER: &str = "/* FrankenBrowser accessibility overrides */\n";
+
+const HIGH_CONTRAST_CSS: &str = "\
+*, *::before, *::after {
+  background-color: #000 !important;
+  background-image: none !important;
+  color: #fff !important;
+  border-color: #fff !important;
+  text-shadow: none !important;
+  box-shadow: none !important;
+}
+a, a * {
+  color: #ff0 !important;
+}
+:focus {
+  outline: 2px solid #ff0 !important;
+}
+";
+
+const REDUCE_MOTION_CSS: &str = "\
+*, *::before, *::after {
+  animation: none !important;
+  transition: none !important;
+  scroll-behavior: auto !important;
+}
+";
+
+/// Generate the CSS for the accessibility settings
+///
+/// The custom stylesheet is not included; see [`UserStylesheet`].
+///
+/// # Arguments
+///
+/// * `settings` - Accessibility settings
+///
+/// # Returns
+///
+/// The generated rules, or an empty string when no override is enabled.
+pub fn generate_css(settings: &AccessibilitySettings) -> String {
+    let mut css = String::new();
+    if settings.min_font_px > 0 {
+        let px = settings.min_font_px;
+        css.push_str(&format!(
+            ":root {{\n  font-size: max(100%, {px}px) !important;\n}}\n\
+             body, body :not(h1, h2, h3, h4, h5, h6) {{\n  font-size: max(1em, {px}px) !important;\n}}\n"
+        ));
+    }
+    if settings.force_high_contrast {
+        css.push_str(HIGH_CONTRAST_CSS);
+    }
+    if settings.reduce_motion {
+        css.push_str(REDUCE_MOTION_CSS);
+    }
+
+    if css.is_empty() {
+        css
+    } else {
+        format!("{}{}", HEADER, css)
+    }
+}
+
+/// Script that inserts `css` into the page as a `<style>` element
+///
+/// The element is added as soon as the document element exists and moved
+/// to the end of the document once parsing finishes, so it follows the
+/// site's own stylesheets. Running the script again replaces the CSS.
+///
+/// # Arguments
+///
+/// * `css` - Stylesheet to insert
+pub fn stylesheet_injector_script(css: &str) -> String {
+    style_element_script(USER_STYLESHEET_ID, css)
+}
+
+/// Script that inserts `css` as the `<style>` element with the given id,
+/// like [`stylesheet_injector_script`]
+pub(crate) fn style_element_script(id: &str, css: &str) -> String {
+    let literal = serde_json::Value::String(css.to_string()).to_string();
+    STYLESHEET_INJECTOR_SCRIPT
+        .replace("__STYLE_ID__", id)
+        .replace("__CSS__", &literal)
+}
+
+const STYLESHEET_INJECTOR_SCRIPT: &str = r#"
+(function () {
+    var css = __CSS__;
+    var install = function () {
+        var root = document.documentElement;
+        if (!root) {
+            return false;
+        }
+        var style = document.getElementById('__STYLE_ID__');
+        if (!style) {
+            style = document.createElement('style');
+            style.id = '__STYLE_ID__';
+        }
+        style.textContent = css;
+        root.appendChild(style);
+        return true;
+    };
+    if (!install() || document.readyState === 'loading') {
+        document.addEventListener('DOMContentLoaded', install, { once: true });
+    }
+})();
+"#;
+
+/// Custom stylesheet file and the modification time it was read at
+#[derive(Debug, Clone)]
+struct CustomCss {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+/// User stylesheet built from the accessibility settings
+#[derive(Debug, Clone)]
+pub struct UserStylesheet {
+    settings: AccessibilitySettings,
+    /// Minimum font size from the font settings, in CSS pixels
+    font_floor: u32,
+    custom: Option<CustomCss>,
+    css: String,
+}
+
+impl UserStylesheet {
+    /// Build the stylesheet, reading the custom stylesheet if one is set
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - Accessibility settings
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UserStylesheet` if the custom stylesheet cannot be
+    /// read.
+    pub fn load(settings: &AccessibilitySettings) -> Result<Self> {
+        let mut stylesheet = Self {
+            settings: settings.clone(),
+            font_floor: 0,
+            custom: settings.custom_css_path().map(|path| CustomCss {
+                path,
+                modified: None,
+            }),
+            css: String::new(),
+        };
+        stylesheet.rebuild()?;
+        Ok(stylesheet)
+    }
+
+    /// The complete stylesheet; empty when there is nothing to apply
+    pub fn css(&self) -> &str {
+        &self.css
+    }
+
+    /// Whether the stylesheet has no rules
+    pub fn is_empty(&self) -> bool {
+        self.css.is_empty()
+    }
+
+    /// Settings the stylesheet was built from
+    pub fn settings(&self) -> &AccessibilitySettings {
+        &self.settings
+    }
+
+    /// Minimum font size enforced on behalf of the font settings
+    pub fn font_floor(&self) -> u32 {
+        self.font_floor
+    }
+
+    /// Enforce a minimum font size the webview cannot apply natively
+    ///
+    /// The larger of `px` and the accessibility `min_font_px` is used.
+    ///
+    /// # Arguments
+    ///
+    /// * `px` - Minimum font size in CSS pixels; 0 removes the floor
+    ///
+    /// # Returns
+    ///
+    /// Whether the CSS changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UserStylesheet` if the custom stylesheet cannot be
+    /// read; the previous CSS is kept.
+    pub fn set_font_floor(&mut self, px: u32) -> Result<bool> {
+        if px == self.font_floor {
+            return Ok(false);
+        }
+        let previous = (self.font_floor, self.css.clone());
+        self.font_floor = px;
+        if let Err(e) = self.rebuild() {
+            self.font_floor = previous.0;
+            return Err(e);
+        }
+        Ok(self.css != previous.1)
+    }
+
+    /// Re-read the custom stylesheet if it changed on disk
+    ///
+    /// # Returns
+    ///
+    /// Whether the CSS changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UserStylesheet` if the custom stylesheet cannot be
+    /// read; the previous CSS is kept.
+    pub fn reload(&mut self) -> Result<bool> {
+        let Some(custom) = &self.custom else {
+            return Ok(false);
+        };
+        if modified(&custom.path) == custom.modified && custom.modified.is_some() {
+            return Ok(false);
+        }
+
+        let previous = self.css.clone();
+        self.rebuild()?;
+        Ok(self.css != previous)
+    }
+
+    /// Regenerate the CSS and append the custom stylesheet
+    fn rebuild(&mut self) -> Result<()> {
+        let mut css = generate_css(&AccessibilitySettings {
+            min_font_px: self.settings.min_font_px.max(self.font_floor),
+            ..self.settings.clone()
+        });
+        if let Some(custom) = &mut self.custom {
+            let modified = modified(&custom.path);
+            let text = fs::read_to_string(&custom.path).map_err(|e| {
+                Error::UserStylesheet(format!("Cannot read {}: {}", custom.path.display(), e))
+            })?;
+            custom.modified = modified;
+
+            if !text.trim().is_empty() {
+                if css.is_empty() {
+                    css.push_str(HEADER);
+                }
+                css.push_str(&format!("/* {} */\n{}", custom.path.display(), text));
+                if !css.ends_with('\n') {
+                    css.push('\n');
+                }
+            }
+        }
+        self.css = css;
+        Ok(())
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN_FONT_18: &str = "\
+:root {
+  font-size: max(100%, 18px) !important;
+}
+body, body :not(h1, h2, h3, h4, h5, h6) {
+  font-size: max(1em, 18px) !important;
+}
+";
+
+    fn settings(min_font_px: u32, contrast: bool, motion: bool) -> AccessibilitySettings {
+        AccessibilitySettings {
+            min_font_px,
+            force_high_contrast: contrast,
+            reduce_motion: motion,
+            custom_css_path: String::new(),
+        }
+    }
+
+    // ========================================
+    // Tests for CSS generation
+    // ========================================
+
+    #[test]
+    fn test_generate_css_defaults_empty() {
+        assert_eq!(generate_css(&AccessibilitySettings::default()), "");
+    }
+
+    #[test]
+    fn test_generate_css_snapshots() {
+        for min_font in [0, 18] {
+            for contrast in [false, true] {
+                for motion in [false, true] {
+                    let mut expected = String::new();
+                    if min_font > 0 {
+                        expected.push_str(MIN_FONT_18);
+                    }
+                    if contrast {
+                        expected.push_str(HIGH_CONTRAST_CSS);
+                    }
+                    if motion {
+                        expected.push_str(REDUCE_MOTION_CSS);
+                    }
+                    if !expected.is_empty() {
+                        expected.insert_str(0, HEADER);
+                    }
+
+                    let css = generate_css(&settings(min_font, contrast, motion));
+                    assert_eq!(css, expected, "{} {} {}", min_font, contrast, motion);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated_rules_are_important() {
+        let css = generate_css(&settings(16, true, true));
+        for declaration in css.lines().filter(|line| line.ends_with(';')) {
+            assert!(declaration.ends_with("!important;"), "{}", declaration);
+        }
+    }
+
+    #[test]
+    fn test_injector_script_escapes_css() {
+        let script = stylesheet_injector_script("a::after { content: \"'</style>\"; }");
+        assert!(script.contains(USER_STYLESHEET_ID));
+        assert!(script.contains(r#"var css = "a::after { content: \"'</style>\"; }";"#));
+    }
+
+    #[test]
+    fn test_font_floor_raises_minimum() {
+        let mut stylesheet = UserStylesheet::load(&AccessibilitySettings::default()).unwrap();
+        assert!(stylesheet.set_font_floor(18).unwrap());
+        assert_eq!(stylesheet.css(), format!("{}{}", HEADER, MIN_FONT_18));
+        assert!(!stylesheet.set_font_floor(18).unwrap());
+        assert!(stylesheet.set_font_floor(0).unwrap());
+        assert!(stylesheet.is_empty());
+
+        // A larger accessibility minimum wins
+        let mut stylesheet = UserStylesheet::load(&settings(18, false, false)).unwrap();
+        assert!(!stylesheet.set_font_floor(12).unwrap());
+        assert_eq!(stylesheet.font_floor(), 12);
+        assert!(stylesheet.css().contains("max(100%, 18px)"));
+    }
+
+    // ========================================
+    // Tests for the custom stylesheet
+    // ========================================
+
+    #[test]
+    fn test_custom_css_appended_and_reloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("user.css");
+        fs::write(&path, "p { color: red; }").unwrap();
+
+        let mut accessibility = settings(0, false, true);
+        accessibility.custom_css_path = path.to_string_lossy().into_owned();
+        let mut stylesheet = UserStylesheet::load(&accessibility).unwrap();
+        let generated = generate_css(&accessibility);
+        assert!(stylesheet.css().starts_with(&generated));
+        assert!(stylesheet.css().ends_with("p { color: red; }\n"));
+
+        // Unchanged file: nothing to do
+        assert!(!stylesheet.reload().unwrap());
+
+        fs::write(&path, "p { color: blue; }\n").unwrap();
+        let later = SystemTime::now() + std::time::Duration::from_secs(5);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert!(stylesheet.reload().unwrap());
+        assert!(stylesheet.css().ends_with("p { color: blue; }\n"));
+        assert!(!stylesheet.css().contains("red"));
+    }
+
+    #[test]
+    fn test_custom_css_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("user.css");
+        fs::write(&path, "p { color: red; }\n").unwrap();
+
+        let accessibility = AccessibilitySettings {
+            custom_css_path: path.to_string_lossy().into_owned(),
+            ..AccessibilitySettings::default()
+        };
+        let stylesheet = UserStylesheet::load(&accessibility).unwrap();
+        assert!(stylesheet.css().starts_with(HEADER));
+        assert!(stylesheet.css().ends_with("p { color: red; }\n"));
+    }
+
+    #[test]
+    fn test_unreadable_custom_css() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut accessibility = settings(18, false, false);
+        accessibility.custom_css_path = dir
+            .path()
+            .join("missing.css")
+            .to_string_lossy()
+            .into_owned();
+
+        let err = UserStylesheet::load(&accessibility).unwrap_err();
+        assert!(matches!(err, Error::UserStylesheet(_)));
+        assert!(err.to_string().contains("missing.css"));
+    }
+
+    #[test]
+    fn test_reload_keeps_css_when_file_disappears() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("user.css");
+        fs::write(&path, "p { color: red; }\n").unwrap();
+
+        let accessibility = AccessibilitySettings {
+            custom_css_path: path.to_string_lossy().into_owned(),
+            ..AccessibilitySettings::default()
+        };
+        let mut stylesheet = UserStylesheet::load(&accessibility).unwrap();
+
+        fs::remove_file(&path).unwrap();
+        assert!(stylesheet.reload().is_err());
+        assert!(stylesheet.css().ends_with("p { color: red; }\n"));
+    }
+}
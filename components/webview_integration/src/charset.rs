@@ -0,0 +1,144 @@
+//! Character encoding of streamed documents
+//!
+//! Documents served through the document protocol get an explicit
+//! charset, which takes precedence over anything the webview would
+//! detect itself. The charset is taken from the document where it
+//! declares one — a byte order mark, or a `<meta charset>` or
+//! `<meta http-equiv="Content-Type">` within the first [`PRESCAN_BYTES`]
+//! bytes, as in the HTML prescan — and from the configured
+//! `[content] default_encoding` otherwise, falling back to UTF-8.
+
+/// How far into a document a `<meta>` charset declaration is looked for
+pub const PRESCAN_BYTES: usize = 1024;
+
+/// Encoding used when neither the document nor the config names one
+pub const FALLBACK_ENCODING: &str = "utf-8";
+
+/// Charset the document declares at its start
+///
+/// # Arguments
+///
+/// * `head` - First bytes of the document; only [`PRESCAN_BYTES`] are read
+///
+/// # Returns
+///
+/// The lowercased encoding label, or `None` if the document declares none.
+pub fn declared_charset(head: &[u8]) -> Option<String> {
+    if head.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return Some("utf-8".to_string());
+    }
+    if head.starts_with(&[0xFE, 0xFF]) {
+        return Some("utf-16be".to_string());
+    }
+    if head.starts_with(&[0xFF, 0xFE]) {
+        return Some("utf-16le".to_string());
+    }
+
+    let head = &head[..head.len().min(PRESCAN_BYTES)];
+    let text = String::from_utf8_lossy(head).to_ascii_lowercase();
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find("<meta") {
+        let tag = &rest[start + "<meta".len()..];
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        if let Some(label) = charset_attribute(tag) {
+            return Some(label);
+        }
+        rest = &rest[start + "<meta".len()..];
+    }
+    None
+}
+
+/// Charset to serve a document with
+///
+/// # Arguments
+///
+/// * `head` - First bytes of the document
+/// * `default_encoding` - Configured `[content] default_encoding`; empty
+///   or malformed means UTF-8
+///
+/// # Returns
+///
+/// The declared charset, else the configured default, else UTF-8.
+pub fn document_charset(head: &[u8], default_encoding: &str) -> String {
+    declared_charset(head)
+        .or_else(|| clean_label(default_encoding))
+        .unwrap_or_else(|| FALLBACK_ENCODING.to_string())
+}
+
+/// Value of a `charset=` inside a lowercased `<meta>` tag's attributes
+///
+/// Covers both `charset="x"` and `content="text/html; charset=x"`.
+fn charset_attribute(tag: &str) -> Option<String> {
+    let mut rest = tag;
+    while let Some(at) = rest.find("charset") {
+        let after = rest[at + "charset".len()..].trim_start();
+        if let Some(value) = after.strip_prefix('=') {
+            let value = value.trim_start().trim_start_matches(['"', '\'']);
+            let end = value
+                .find(|c: char| c == '"' || c == '\'' || c == ';' || c.is_whitespace())
+                .unwrap_or(value.len());
+            if let Some(label) = clean_label(&value[..end]) {
+                return Some(label);
+            }
+        }
+        rest = &rest[at + "charset".len()..];
+    }
+    None
+}
+
+/// Lowercased encoding label, or `None` unless it looks like one
+///
+/// Labels end up in a response header, so anything beyond the characters
+/// encoding names use is rejected.
+fn clean_label(label: &str) -> Option<String> {
+    let label = label.trim();
+    let valid = !label.is_empty()
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+    valid.then(|| label.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_declared_charset_forms() {
+        assert_eq!(
+            declared_charset(b"<html><head><META CharSet=\"Shift_JIS\">").as_deref(),
+            Some("shift_jis")
+        );
+        assert_eq!(
+            declared_charset(
+                b"<meta http-equiv=\"Content-Type\" content=\"text/html; charset=ISO-8859-2\">"
+            )
+            .as_deref(),
+            Some("iso-8859-2")
+        );
+        assert_eq!(
+            declared_charset(b"\xEF\xBB\xBF<meta charset=windows-1252>").as_deref(),
+            Some("utf-8")
+        );
+        assert_eq!(
+            declared_charset(b"<meta name=\"viewport\" content=\"width=device-width\">"),
+            None
+        );
+
+        // Declarations past the prescan window do not count
+        let mut late = vec![b' '; PRESCAN_BYTES];
+        late.extend_from_slice(b"<meta charset=koi8-r>");
+        assert_eq!(declared_charset(&late), None);
+    }
+
+    #[test]
+    fn test_default_encoding_applies_only_without_declaration() {
+        let undeclared = b"<html><body>caf\xE9</body></html>";
+        assert_eq!(document_charset(undeclared, "windows-1252"), "windows-1252");
+        assert_eq!(document_charset(undeclared, ""), "utf-8");
+        assert_eq!(document_charset(undeclared, "x\r\nSet-Cookie: a"), "utf-8");
+
+        let declared = b"<meta charset=\"euc-kr\"><p>";
+        assert_eq!(document_charset(declared, "windows-1252"), "euc-kr");
+    }
+}
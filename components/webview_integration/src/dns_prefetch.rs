@@ -0,0 +1,185 @@
+//! Reporting the hosts of links in view, for DNS prefetch
+//!
+//! The viewport link script watches the main frame's `<a href>` links with
+//! an `IntersectionObserver` and, shortly after scrolling settles, reports
+//! the distinct http(s) host names of links that came into view over the
+//! IPC bridge. The network stack resolves them ahead of a click.
+//!
+//! The script reports nothing on pages that opt out with
+//! `<meta http-equiv="x-dns-prefetch-control" content="off">`, and is never
+//! installed in private tabs.
+
+use crate::errors::{Error, Result};
+use serde_json::Value as JsonValue;
+use shared_types::TabId;
+use std::sync::Arc;
+
+/// IPC channel carrying the hosts of links in view
+pub const VIEWPORT_LINKS_CHANNEL: &str = "viewportLinks";
+
+/// Most hosts one report carries
+pub const MAX_HOSTS_PER_REPORT: usize = 16;
+
+/// Receives the hosts of links that came into view
+pub type ViewportLinksHandler = Arc<dyn Fn(ViewportLinks) + Send + Sync>;
+
+/// Links to these hosts came into view
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewportLinks {
+    /// Tab showing the links
+    pub tab_id: TabId,
+    /// Distinct lowercased host names, at most [`MAX_HOSTS_PER_REPORT`]
+    pub hosts: Vec<String>,
+}
+
+/// Script that reports the hosts of links scrolled into view
+pub fn viewport_links_script() -> String {
+    VIEWPORT_LINKS_SCRIPT.replace("__MAX_HOSTS__", &MAX_HOSTS_PER_REPORT.to_string())
+}
+
+const VIEWPORT_LINKS_SCRIPT: &str = r#"
+(function () {
+    if (window.top !== window || window.__frankenViewportLinks ||
+        !window.IntersectionObserver) {
+        return;
+    }
+    window.__frankenViewportLinks = true;
+    var optedOut = function () {
+        var meta = document.querySelector('meta[http-equiv="x-dns-prefetch-control" i]');
+        return meta && (meta.content || '').trim().toLowerCase() === 'off';
+    };
+    var seen = {};
+    var pending = [];
+    var timer = null;
+
+    var flush = function () {
+        timer = null;
+        if (!window.ipc || optedOut() || pending.length === 0) {
+            pending = [];
+            return;
+        }
+        window.ipc.send('viewportLinks', { hosts: pending.splice(0, __MAX_HOSTS__) });
+        if (pending.length > 0) {
+            timer = setTimeout(flush, 250);
+        }
+    };
+
+    var observer = new IntersectionObserver(function (entries) {
+        entries.forEach(function (entry) {
+            if (!entry.isIntersecting) {
+                return;
+            }
+            observer.unobserve(entry.target);
+            var link = entry.target;
+            if ((link.protocol === 'http:' || link.protocol === 'https:') &&
+                link.hostname && link.hostname !== location.hostname &&
+                !seen[link.hostname]) {
+                seen[link.hostname] = true;
+                pending.push(link.hostname);
+            }
+        });
+        if (pending.length > 0 && timer === null) {
+            timer = setTimeout(flush, 250);
+        }
+    });
+
+    var watch = function (root) {
+        if (root.querySelectorAll) {
+            root.querySelectorAll('a[href]').forEach(function (a) { observer.observe(a); });
+        }
+    };
+    new MutationObserver(function (mutations) {
+        mutations.forEach(function (m) {
+            m.addedNodes.forEach(function (node) {
+                if (node.tagName === 'A') {
+                    observer.observe(node);
+                }
+                watch(node);
+            });
+        });
+    }).observe(document, { subtree: true, childList: true });
+    watch(document);
+})();
+"#;
+
+/// Decode a viewport link report
+///
+/// Host names are lowercased and deduplicated; anything past
+/// [`MAX_HOSTS_PER_REPORT`] is dropped.
+///
+/// # Arguments
+///
+/// * `tab_id` - Tab the reporting webview belongs to
+/// * `data` - Payload of a [`VIEWPORT_LINKS_CHANNEL`] message
+///
+/// # Errors
+///
+/// Returns `Error::Ipc` if the payload has no `hosts` array of strings.
+pub fn parse_viewport_links(tab_id: TabId, data: &JsonValue) -> Result<ViewportLinks> {
+    let invalid = || Error::Ipc("Viewport links missing 'hosts'".to_string());
+    let reported = data
+        .get("hosts")
+        .and_then(|v| v.as_array())
+        .ok_or_else(invalid)?;
+
+    let mut hosts: Vec<String> = Vec::new();
+    for host in reported {
+        let host = host
+            .as_str()
+            .ok_or_else(invalid)?
+            .trim()
+            .to_ascii_lowercase();
+        if host.is_empty() || hosts.contains(&host) {
+            continue;
+        }
+        if hosts.len() == MAX_HOSTS_PER_REPORT {
+            break;
+        }
+        hosts.push(host);
+    }
+    Ok(ViewportLinks { tab_id, hosts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_script_reports_on_channel_and_honours_opt_out() {
+        let script = viewport_links_script();
+        assert!(script.contains("'viewportLinks'"));
+        assert!(script.contains("IntersectionObserver"));
+        assert!(script.contains("x-dns-prefetch-control"));
+        assert!(script.contains(&format!("splice(0, {})", MAX_HOSTS_PER_REPORT)));
+    }
+
+    #[test]
+    fn test_parse_viewport_links() {
+        let links = parse_viewport_links(
+            2,
+            &json!({"hosts": ["CDN.example.com", "cdn.example.com", " ", "news.example"]}),
+        )
+        .unwrap();
+        assert_eq!(
+            links,
+            ViewportLinks {
+                tab_id: 2,
+                hosts: vec!["cdn.example.com".to_string(), "news.example".to_string()],
+            }
+        );
+
+        let many: Vec<String> = (0..40).map(|i| format!("h{}.example", i)).collect();
+        let links = parse_viewport_links(2, &json!({ "hosts": many })).unwrap();
+        assert_eq!(links.hosts.len(), MAX_HOSTS_PER_REPORT);
+
+        assert!(matches!(
+            parse_viewport_links(2, &json!({})),
+            Err(Error::Ipc(_))
+        ));
+        assert!(matches!(
+            parse_viewport_links(2, &json!({"hosts": [1]})),
+            Err(Error::Ipc(_))
+        ));
+    }
+}
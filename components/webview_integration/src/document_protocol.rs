@@ -0,0 +1,541 @@
+//! Custom protocol for streamed documents
+//!
+//! Fallback for backends without incremental HTML loading: each streamed
+//! document gets an unguessable [`DOCUMENT_SCHEME`] URL, the webview loads
+//! that URL, and the protocol handler answers once the document has been
+//! fed in. Backends that render chunks as they arrive get them through
+//! [`WebViewBackend::load_html_streaming`](crate::WebViewBackend::load_html_streaming)
+//! instead.
+//!
+//! The URL's host is derived from the document's origin, so documents from
+//! different sites never share an origin, and with it cookies, storage and
+//! same-origin access: `https://example.com/a` is served from
+//! `franken-doc://https.443.example.com/stream/…`. A document without a
+//! host, such as an `about:` page, gets a host of its own, like the opaque
+//! origin a browser gives it.
+//!
+//! The response names the document's charset (see [`crate::charset`]),
+//! and a `<base>` just inside the document's `<head>` resolves relative
+//! URLs against the real document URL.
+
+use crate::charset::{document_charset, PRESCAN_BYTES};
+use crate::errors::{Error, Result};
+use shared_types::DocumentChunk;
+use std::collections::HashMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use url::Url;
+
+/// Scheme the webview loads streamed documents from
+pub const DOCUMENT_SCHEME: &str = "franken-doc";
+
+/// A registered document, waiting to be requested
+struct PendingDocument {
+    /// The document's chunks
+    chunks: Receiver<DocumentChunk>,
+    /// Charset to assume if the document declares none
+    default_encoding: String,
+    /// Headers to send besides the content type
+    headers: Vec<(String, String)>,
+}
+
+/// Streams registered but not yet requested by the webview, keyed by
+/// [`stream_key`]
+type PendingDocuments = Arc<Mutex<HashMap<String, PendingDocument>>>;
+
+/// Serves streamed documents to the webview's custom protocol handler
+///
+/// Clones share the registered streams, so one clone can be moved into
+/// the handler while another opens streams.
+#[derive(Clone, Default)]
+pub struct DocumentProtocol {
+    /// Streams waiting to be requested
+    pending: PendingDocuments,
+}
+
+impl DocumentProtocol {
+    /// Create a protocol with no streams
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new document stream
+    ///
+    /// # Arguments
+    ///
+    /// * `document_url` - Real URL of the document; its origin picks the
+    ///   host the document is served from
+    /// * `default_encoding` - Charset if the document declares none; empty
+    ///   means UTF-8
+    ///
+    /// # Returns
+    ///
+    /// A handle to feed chunks into; its URL is what the webview loads.
+    pub fn open_stream(&self, document_url: &str, default_encoding: &str) -> DocumentStream {
+        self.open_stream_with_headers(document_url, default_encoding, Vec::new())
+    }
+
+    /// Register a new document stream served with extra response headers,
+    /// e.g. the `Content-Security-Policy` of an internal page
+    ///
+    /// # Arguments
+    ///
+    /// * `document_url` - Real URL of the document; its origin picks the
+    ///   host the document is served from
+    /// * `default_encoding` - Charset if the document declares none; empty
+    ///   means UTF-8
+    /// * `headers` - Headers to send besides `Content-Type` and
+    ///   `Cache-Control`
+    ///
+    /// # Returns
+    ///
+    /// A handle to feed chunks into; its URL is what the webview loads.
+    pub fn open_stream_with_headers(
+        &self,
+        document_url: &str,
+        default_encoding: &str,
+        headers: Vec<(String, String)>,
+    ) -> DocumentStream {
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        let host = origin_host(document_url).unwrap_or_else(|| format!("opaque-{}", token));
+        let url = stream_url(&host, &token);
+        let (sender, receiver) = channel();
+        self.pending.lock().unwrap().insert(
+            format!("{}/{}", host, token),
+            PendingDocument {
+                chunks: receiver,
+                default_encoding: default_encoding.to_string(),
+                headers,
+            },
+        );
+        DocumentStream { url, sender }
+    }
+
+    /// Answer a request for a streamed document
+    ///
+    /// Blocks until the document has been fed in full, so call it off the
+    /// UI thread. Each stream can only be requested once.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_url` - URL the webview requested
+    ///
+    /// # Returns
+    ///
+    /// The document, or a 404 if no stream was opened for the URL.
+    pub fn respond(&self, request_url: &str) -> DocumentResponse {
+        let pending =
+            stream_key(request_url).and_then(|key| self.pending.lock().unwrap().remove(&key));
+        let Some(pending) = pending else {
+            return DocumentResponse {
+                status: 404,
+                headers: Vec::new(),
+                body: Vec::new(),
+            };
+        };
+
+        // A closed channel (feeder dropped) ends the document like `End`
+        let mut base_url = None;
+        let mut body = Vec::new();
+        for chunk in pending.chunks.iter() {
+            match chunk {
+                DocumentChunk::Begin { url } => base_url = Some(url),
+                DocumentChunk::Data(data) => body.extend_from_slice(&data),
+                DocumentChunk::End => break,
+                DocumentChunk::Failed { reason } => {
+                    body.extend_from_slice(error_bar_html(&reason).as_bytes());
+                    break;
+                }
+            }
+        }
+
+        let charset = document_charset(
+            &body[..body.len().min(PRESCAN_BYTES)],
+            &pending.default_encoding,
+        );
+        if let Some(url) = base_url {
+            // Resolve relative URLs against the real document URL
+            let base = format!("<base href=\"{}\">", escape_html(&url));
+            let at = base_position(&body);
+            body.splice(at..at, base.into_bytes());
+        }
+        let mut headers = vec![
+            (
+                "Content-Type".to_string(),
+                format!("text/html; charset={}", charset),
+            ),
+            ("Cache-Control".to_string(), "no-store".to_string()),
+        ];
+        headers.extend(pending.headers);
+        DocumentResponse {
+            status: 200,
+            headers,
+            body,
+        }
+    }
+}
+
+/// Answer to a request for a streamed document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentResponse {
+    /// HTTP status code
+    pub status: u16,
+    /// Response headers, in order
+    pub headers: Vec<(String, String)>,
+    /// Document bytes
+    pub body: Vec<u8>,
+}
+
+impl DocumentResponse {
+    /// Value of a response header, compared case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Convert to the response type of the webview's protocol handler
+    #[cfg(feature = "gui")]
+    pub(crate) fn into_http(self) -> wry::http::Response<Vec<u8>> {
+        let mut response = wry::http::Response::builder().status(self.status);
+        for (name, value) in self.headers {
+            response = response.header(name, value);
+        }
+        response
+            .body(self.body)
+            .expect("document response headers are valid")
+    }
+}
+
+/// Feeding end of one streamed document
+pub struct DocumentStream {
+    /// URL the webview should load
+    url: String,
+    /// Chunks for the request serving this document
+    sender: Sender<DocumentChunk>,
+}
+
+impl DocumentStream {
+    /// URL the webview should load to receive this document
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Forward a chunk to the webview
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Navigation` if the webview has stopped reading
+    /// (e.g. it navigated elsewhere).
+    pub fn send(&self, chunk: DocumentChunk) -> Result<()> {
+        self.sender
+            .send(chunk)
+            .map_err(|_| Error::Navigation("Document stream closed by webview".to_string()))
+    }
+}
+
+/// Markup appended to a partial document when its download fails
+///
+/// # Arguments
+///
+/// * `reason` - Error to show
+pub fn error_bar_html(reason: &str) -> String {
+    format!(
+        "<div role=\"alert\" style=\"position:fixed;top:0;left:0;right:0;z-index:2147483647;\
+         padding:8px 12px;background:#fce8e6;color:#a50e0e;font:14px sans-serif;\
+         border-bottom:1px solid #f28b82\">Page did not finish loading: {}</div>",
+        escape_html(reason)
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Host standing for a document's origin: scheme, port and host, e.g.
+/// `https.443.example.com`; `None` for URLs without a host
+fn origin_host(document_url: &str) -> Option<String> {
+    let url = Url::parse(document_url).ok()?;
+    let host = match url.host()? {
+        url::Host::Domain(domain) => domain.to_string(),
+        url::Host::Ipv4(ip) => ip.to_string(),
+        url::Host::Ipv6(ip) => format!("ipv6-{}", ip.to_string().replace(':', "-")),
+    };
+    let port = url.port_or_known_default().unwrap_or(0);
+    Some(format!("{}.{}.{}", url.scheme(), port, host))
+}
+
+/// URL the webview loads a stream from
+///
+/// WebView2 serves custom protocols as `http://<scheme>.<host>`; the
+/// other webviews use the scheme itself.
+fn stream_url(host: &str, token: &str) -> String {
+    if cfg!(target_os = "windows") {
+        format!("http://{}.{}/stream/{}", DOCUMENT_SCHEME, host, token)
+    } else {
+        format!("{}://{}/stream/{}", DOCUMENT_SCHEME, host, token)
+    }
+}
+
+/// Key of the stream a request is for: the origin host and the token
+///
+/// Accepts both URL forms of [`stream_url`], since WebView2 may hand the
+/// request over in either.
+fn stream_key(request_url: &str) -> Option<String> {
+    let url = Url::parse(request_url).ok()?;
+    let host = url.host_str()?;
+    let host = match url.scheme() {
+        DOCUMENT_SCHEME => host,
+        "http" => host.strip_prefix(DOCUMENT_SCHEME)?.strip_prefix('.')?,
+        _ => return None,
+    };
+    let token = url.path().strip_prefix("/stream/")?;
+    Some(format!("{}/{}", host, token))
+}
+
+/// Where a `<base>` goes: just inside `<head>`, or where the parser opens
+/// the head when the document has no tag for it. Never before the
+/// doctype, which would put the page in quirks mode.
+fn base_position(document: &[u8]) -> usize {
+    let mut at = if document.starts_with(b"\xEF\xBB\xBF") {
+        3
+    } else {
+        0
+    };
+    at = skip_prolog(document, at);
+    if tag_at(document, at, b"html") {
+        at = skip_prolog(document, tag_end(document, at));
+    }
+    if tag_at(document, at, b"head") {
+        at = tag_end(document, at);
+    }
+    at
+}
+
+/// Skip whitespace, comments and the doctype
+fn skip_prolog(document: &[u8], mut at: usize) -> usize {
+    loop {
+        while document.get(at).is_some_and(u8::is_ascii_whitespace) {
+            at += 1;
+        }
+        let rest = &document[at..];
+        if rest.starts_with(b"<!--") {
+            at = match find(rest, b"-->") {
+                Some(end) => at + end + 3,
+                None => document.len(),
+            };
+        } else if rest.starts_with(b"<!") || rest.starts_with(b"<?") {
+            at = tag_end(document, at);
+        } else {
+            return at;
+        }
+    }
+}
+
+/// Whether a start tag for `name` begins at `at`
+fn tag_at(document: &[u8], at: usize, name: &[u8]) -> bool {
+    let rest = &document[at..];
+    rest.first() == Some(&b'<')
+        && rest.len() > name.len() + 1
+        && rest[1..=name.len()].eq_ignore_ascii_case(name)
+        && matches!(
+            rest[name.len() + 1],
+            b'>' | b'/' | b' ' | b'\t' | b'\n' | b'\r' | b'\x0C'
+        )
+}
+
+/// Position just past the `>` closing the tag that begins at `at`
+fn tag_end(document: &[u8], at: usize) -> usize {
+    match find(&document[at..], b">") {
+        Some(end) => at + end + 1,
+        None => document.len(),
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn serve(protocol: &DocumentProtocol, url: &str, chunks: Vec<DocumentChunk>) -> String {
+        let stream = protocol.open_stream(url, "");
+        for chunk in chunks {
+            stream.send(chunk).unwrap();
+        }
+        let response = protocol.respond(stream.url());
+        assert_eq!(response.status, 200);
+        String::from_utf8(response.body).unwrap()
+    }
+
+    #[test]
+    fn test_document_relays_chunks_in_order() {
+        let protocol = DocumentProtocol::new();
+        let body = serve(
+            &protocol,
+            "https://example.com/a",
+            vec![
+                DocumentChunk::Begin {
+                    url: "https://example.com/a".to_string(),
+                },
+                DocumentChunk::Data(b"<p>one</p>".to_vec()),
+                DocumentChunk::Data(b"<p>two</p>".to_vec()),
+                DocumentChunk::End,
+            ],
+        );
+
+        let base = body.find("<base href=\"https://example.com/a\">").unwrap();
+        let one = body.find("<p>one</p>").unwrap();
+        let two = body.find("<p>two</p>").unwrap();
+        assert!(base < one && one < two);
+    }
+
+    #[test]
+    fn test_base_goes_inside_head_after_doctype() {
+        let begin = || DocumentChunk::Begin {
+            url: "https://example.com/".to_string(),
+        };
+        let base = "<base href=\"https://example.com/\">";
+        let protocol = DocumentProtocol::new();
+        let cases = [
+            (
+                "<!DOCTYPE html>\n<!-- <head> -->\n<html lang=\"en\">\n<HEAD data-x>\n<title>t</title>",
+                "<!DOCTYPE html>\n<!-- <head> -->\n<html lang=\"en\">\n<HEAD data-x>BASE\n<title>t</title>",
+            ),
+            (
+                "<!doctype html><header>h</header>",
+                "<!doctype html>BASE<header>h</header>",
+            ),
+            ("<p>no prolog</p>", "BASE<p>no prolog</p>"),
+        ];
+        for (document, expected) in cases {
+            let body = serve(
+                &protocol,
+                "https://example.com/",
+                vec![
+                    begin(),
+                    DocumentChunk::Data(document.as_bytes().to_vec()),
+                    DocumentChunk::End,
+                ],
+            );
+            assert_eq!(body, expected.replace("BASE", base));
+        }
+    }
+
+    #[test]
+    fn test_documents_are_served_per_origin() {
+        let protocol = DocumentProtocol::new();
+        let first = protocol.open_stream("https://example.com/a", "");
+        let second = protocol.open_stream("https://example.com/b?q", "");
+        let other = protocol.open_stream("https://other.example/", "");
+        let insecure = protocol.open_stream("http://example.com/", "");
+        let internal = protocol.open_stream("about:settings", "");
+        let another_internal = protocol.open_stream("about:settings", "");
+
+        let origin = |stream: &DocumentStream| {
+            let url = Url::parse(stream.url()).unwrap();
+            url.host_str().unwrap().to_string()
+        };
+        assert!(origin(&first).contains("https.443.example.com"));
+        assert_eq!(origin(&first), origin(&second));
+        assert_ne!(origin(&first), origin(&other));
+        assert_ne!(origin(&first), origin(&insecure));
+        assert_ne!(origin(&internal), origin(&another_internal));
+        assert_ne!(first.url(), second.url());
+    }
+
+    #[test]
+    fn test_failure_appends_error_bar() {
+        let protocol = DocumentProtocol::new();
+        let body = serve(
+            &protocol,
+            "https://example.com/",
+            vec![
+                DocumentChunk::Data(b"<p>partial</p>".to_vec()),
+                DocumentChunk::Failed {
+                    reason: "connection <reset>".to_string(),
+                },
+            ],
+        );
+
+        assert!(body.contains("<p>partial</p>"));
+        assert!(body.contains("Page did not finish loading: connection &lt;reset&gt;"));
+    }
+
+    #[test]
+    fn test_response_names_charset() {
+        let protocol = DocumentProtocol::new();
+        let undeclared = protocol.open_stream("https://example.com/", "windows-1252");
+        undeclared
+            .send(DocumentChunk::Data(b"<p>plain</p>".to_vec()))
+            .unwrap();
+        undeclared.send(DocumentChunk::End).unwrap();
+        let response = protocol.respond(undeclared.url());
+        assert_eq!(
+            response.header("content-type"),
+            Some("text/html; charset=windows-1252")
+        );
+        assert_eq!(response.header("Cache-Control"), Some("no-store"));
+
+        let declared = protocol.open_stream("https://example.com/", "windows-1252");
+        declared
+            .send(DocumentChunk::Data(
+                b"<meta charset=\"shift_jis\">".to_vec(),
+            ))
+            .unwrap();
+        declared.send(DocumentChunk::End).unwrap();
+        assert_eq!(
+            protocol.respond(declared.url()).header("Content-Type"),
+            Some("text/html; charset=shift_jis")
+        );
+    }
+
+    #[test]
+    fn test_stream_headers_are_sent() {
+        let protocol = DocumentProtocol::new();
+        let stream = protocol.open_stream_with_headers(
+            "about:settings",
+            "",
+            vec![(
+                "Content-Security-Policy".to_string(),
+                "default-src 'none'".to_string(),
+            )],
+        );
+        stream.send(DocumentChunk::End).unwrap();
+
+        let response = protocol.respond(stream.url());
+        assert_eq!(
+            response.header("content-security-policy"),
+            Some("default-src 'none'")
+        );
+        assert!(response.header("Content-Type").is_some());
+    }
+
+    #[test]
+    fn test_unknown_stream_is_404() {
+        let protocol = DocumentProtocol::new();
+        let stream = protocol.open_stream("https://example.com/", "");
+        let bogus = stream.url().replace("/stream/", "/stream/x");
+
+        assert_eq!(protocol.respond(&bogus).status, 404);
+        let windows_form = stream
+            .url()
+            .replace("franken-doc://", "http://franken-doc.");
+        assert_eq!(stream_key(&windows_form), stream_key(stream.url()));
+        // Another origin cannot read the stream
+        let elsewhere = stream.url().replace("example.com", "evil.example");
+        assert_eq!(protocol.respond(&elsewhere).status, 404);
+        // Each stream can only be requested once
+        stream.send(DocumentChunk::End).unwrap();
+        assert_eq!(protocol.respond(stream.url()).status, 200);
+        assert_eq!(protocol.respond(stream.url()).status, 404);
+    }
+}
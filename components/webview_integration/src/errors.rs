@@ -1,5 +1,6 @@
 //! Error types for WebView integration
 
+use crate::print::PrintError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -24,6 +25,18 @@ pub enum Error {
 
     #[error("Not implemented yet")]
     NotImplemented,
+
+    /// The platform or webview version lacks the required API
+    #[error("Unsupported on this platform: {0}")]
+    Unsupported(String),
+
+    /// The accessibility user stylesheet cannot be built
+    #[error("User stylesheet error: {0}")]
+    UserStylesheet(String),
+
+    /// Printing failed or was cancelled
+    #[error(transparent)]
+    Print(#[from] PrintError),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -66,6 +79,27 @@ mod tests {
         assert!(matches!(error, Error::Platform(_)));
     }
 
+    #[test]
+    fn test_webview_error_unsupported() {
+        let error = Error::Unsupported("storage enumeration".to_string());
+        assert!(matches!(error, Error::Unsupported(_)));
+        assert!(error.to_string().contains("storage enumeration"));
+    }
+
+    #[test]
+    fn test_webview_error_user_stylesheet() {
+        let error = Error::UserStylesheet("Cannot read user.css".to_string());
+        assert!(matches!(error, Error::UserStylesheet(_)));
+        assert!(error.to_string().contains("user.css"));
+    }
+
+    #[test]
+    fn test_webview_error_print() {
+        let error = Error::from(PrintError::Cancelled);
+        assert!(matches!(error, Error::Print(PrintError::Cancelled)));
+        assert_eq!(error.to_string(), "Printing cancelled");
+    }
+
     #[test]
     fn test_webview_error_display() {
         let error = Error::Navigation("Test error".to_string());
@@ -0,0 +1,239 @@
+//! Find in page
+//!
+//! The find script searches the text the page renders, skipping scripts,
+//! styles and form fields, and wraps every match in a `<mark>`. The first
+//! match is highlighted and scrolled into view; the step scripts move the
+//! highlight forwards or backwards, wrapping around. After every search or
+//! step the script reports the highlighted match and the match count over
+//! the IPC bridge, so the status bar can show "3 of 17". A match never
+//! spans elements.
+
+use crate::errors::{Error, Result};
+use serde_json::Value as JsonValue;
+use shared_types::{FindOptions, TabId};
+use std::sync::Arc;
+
+/// IPC channel carrying find results
+pub const FIND_RESULT_CHANNEL: &str = "findResult";
+
+/// Receives find results
+pub type FindReportHandler = Arc<dyn Fn(FindReport) + Send + Sync>;
+
+/// Outcome of a search or a step through its matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FindReport {
+    /// Tab that was searched
+    pub tab_id: TabId,
+    /// Position of the highlighted match counting from 1; 0 if nothing
+    /// matched
+    pub current: usize,
+    /// Number of matches
+    pub total: usize,
+}
+
+/// Script that highlights the matches of `query` in the current page
+///
+/// Any earlier search is cleared first; an empty query only clears it.
+///
+/// # Arguments
+///
+/// * `query` - Text to find
+/// * `options` - Case sensitivity and whole word matching
+pub fn find_script(query: &str, options: &FindOptions) -> String {
+    let query = JsonValue::String(query.to_string()).to_string();
+    format!(
+        "{}window.__frankenFind.start({}, {}, {});",
+        FIND_SCRIPT, query, options.case_sensitive, options.whole_word
+    )
+}
+
+/// Script that highlights the next or previous match, wrapping around
+///
+/// # Arguments
+///
+/// * `forward` - Whether to move to the next match rather than the
+///   previous one
+pub fn find_step_script(forward: bool) -> &'static str {
+    if forward {
+        "window.__frankenFind && window.__frankenFind.step(1);"
+    } else {
+        "window.__frankenFind && window.__frankenFind.step(-1);"
+    }
+}
+
+/// Script that removes the highlights of the current search
+pub fn stop_finding_script() -> &'static str {
+    "window.__frankenFind && window.__frankenFind.clear();"
+}
+
+const FIND_SCRIPT: &str = r#"
+(function () {
+    if (window.__frankenFind) {
+        return;
+    }
+    var marks = [];
+    var active = -1;
+    var skipped = { SCRIPT: 1, STYLE: 1, NOSCRIPT: 1, TEXTAREA: 1, INPUT: 1, SELECT: 1 };
+
+    var report = function () {
+        if (window.ipc) {
+            window.ipc.send('findResult', { current: active + 1, total: marks.length });
+        }
+    };
+    var highlight = function () {
+        marks.forEach(function (mark, index) {
+            mark.style.background = index === active ? '#ff9632' : '#ffff00';
+            mark.style.color = '#000';
+        });
+        if (active >= 0) {
+            marks[active].scrollIntoView({ block: 'center' });
+        }
+    };
+    var clear = function () {
+        marks.forEach(function (mark) {
+            var parent = mark.parentNode;
+            if (parent) {
+                parent.replaceChild(document.createTextNode(mark.textContent), mark);
+                parent.normalize();
+            }
+        });
+        marks = [];
+        active = -1;
+    };
+    var textNodes = function () {
+        var root = document.body || document.documentElement;
+        var walker = document.createTreeWalker(root, NodeFilter.SHOW_TEXT, {
+            acceptNode: function (node) {
+                var parent = node.parentNode;
+                return parent && skipped[parent.nodeName]
+                    ? NodeFilter.FILTER_REJECT
+                    : NodeFilter.FILTER_ACCEPT;
+            }
+        });
+        var nodes = [];
+        while (walker.nextNode()) {
+            nodes.push(walker.currentNode);
+        }
+        return nodes;
+    };
+    var markMatches = function (node, pattern) {
+        var text = node.nodeValue;
+        var ranges = [];
+        var match;
+        pattern.lastIndex = 0;
+        while ((match = pattern.exec(text)) !== null) {
+            ranges.push([match.index, match.index + match[0].length]);
+        }
+        // From the end, so the node keeps the text before each match
+        var found = [];
+        for (var i = ranges.length - 1; i >= 0; i--) {
+            var hit = node.splitText(ranges[i][0]);
+            hit.splitText(ranges[i][1] - ranges[i][0]);
+            var mark = document.createElement('mark');
+            mark.setAttribute('data-franken-find', '');
+            hit.parentNode.replaceChild(mark, hit);
+            mark.appendChild(hit);
+            found.unshift(mark);
+        }
+        marks = marks.concat(found);
+    };
+
+    window.__frankenFind = {
+        start: function (query, caseSensitive, wholeWord) {
+            clear();
+            if (query) {
+                var source = query.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
+                if (wholeWord) {
+                    source = '(?<![\\p{L}\\p{N}_])' + source + '(?![\\p{L}\\p{N}_])';
+                }
+                var pattern = new RegExp(source, caseSensitive ? 'gu' : 'giu');
+                textNodes().forEach(function (node) {
+                    markMatches(node, pattern);
+                });
+                active = marks.length ? 0 : -1;
+                highlight();
+            }
+            report();
+        },
+        step: function (delta) {
+            if (marks.length) {
+                active = (active + delta + marks.length) % marks.length;
+                highlight();
+            }
+            report();
+        },
+        clear: function () {
+            clear();
+        }
+    };
+})();
+"#;
+
+/// Decode a find result reported by the find script
+///
+/// # Arguments
+///
+/// * `tab_id` - Tab the reporting webview belongs to
+/// * `data` - Payload of a [`FIND_RESULT_CHANNEL`] message
+///
+/// # Errors
+///
+/// Returns `Error::Ipc` if the payload lacks a numeric `current` or
+/// `total`, or `current` is past `total`.
+pub fn parse_find_report(tab_id: TabId, data: &JsonValue) -> Result<FindReport> {
+    let field = |name: &str| {
+        data.get(name)
+            .and_then(|v| v.as_u64())
+            .map(|n| n as usize)
+            .ok_or_else(|| Error::Ipc(format!("Find result missing '{}'", name)))
+    };
+    let current = field("current")?;
+    let total = field("total")?;
+    if current > total {
+        return Err(Error::Ipc(format!(
+            "Find result {} is past its {} matches",
+            current, total
+        )));
+    }
+
+    Ok(FindReport {
+        tab_id,
+        current,
+        total,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_find_script_embeds_query_and_options() {
+        let options = FindOptions {
+            case_sensitive: true,
+            whole_word: false,
+        };
+        let script = find_script("it's \"quoted\"</script>", &options);
+        assert!(script.contains(r#"start("it's \"quoted\"</script>", true, false);"#));
+        assert!(script.contains("'findResult'"));
+        assert!(find_step_script(true).contains("step(1)"));
+        assert!(find_step_script(false).contains("step(-1)"));
+    }
+
+    #[test]
+    fn test_parse_find_report() {
+        let report = parse_find_report(2, &json!({"current": 3, "total": 17})).unwrap();
+        assert_eq!(
+            report,
+            FindReport {
+                tab_id: 2,
+                current: 3,
+                total: 17,
+            }
+        );
+        for data in [json!({"total": 1}), json!({"current": 2, "total": 1})] {
+            assert!(matches!(parse_find_report(2, &data), Err(Error::Ipc(_))));
+        }
+    }
+}
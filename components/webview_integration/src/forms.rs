@@ -0,0 +1,325 @@
+//! Main-frame form submission capture
+//!
+//! Form posts are normally performed by the webview itself, bypassing the
+//! engine's history, blocking and referrer logic. The capture script
+//! cancels main-frame submissions and reports them over the IPC bridge so
+//! the engine can issue the request instead. Submissions it cannot
+//! reproduce — forms with selected files, or bodies over
+//! [`FORM_BODY_CAP`] — are left to the webview and reported on a separate
+//! channel so the limitation is logged.
+
+use crate::errors::{Error, Result};
+use serde_json::Value as JsonValue;
+use std::sync::Arc;
+
+/// Largest form body the engine takes over from the webview, in bytes
+pub const FORM_BODY_CAP: usize = 1024 * 1024;
+
+/// IPC channel carrying intercepted submissions
+pub const FORM_SUBMIT_CHANNEL: &str = "formSubmit";
+
+/// IPC channel reporting submissions left to the webview
+pub const FORM_NATIVE_CHANNEL: &str = "formSubmitNative";
+
+/// Receives intercepted form submissions
+pub type FormSubmitHandler = Arc<dyn Fn(FormSubmission) + Send + Sync>;
+
+/// A main-frame form submission, encoded as the request the browser sends
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormSubmission {
+    /// HTTP method, upper case (`GET` or `POST`)
+    pub method: String,
+    /// Request URL; for GET forms the fields are already in the query
+    pub url: String,
+    /// Value of the `Content-Type` header (empty for GET)
+    pub content_type: String,
+    /// Request body (empty for GET)
+    pub body: Vec<u8>,
+}
+
+/// Script that captures main-frame form submissions
+///
+/// # Arguments
+///
+/// * `cap` - Largest estimated body size to intercept
+pub fn form_capture_script(cap: usize) -> String {
+    FORM_CAPTURE_SCRIPT.replace("__FORM_BODY_CAP__", &cap.to_string())
+}
+
+const FORM_CAPTURE_SCRIPT: &str = r#"
+(function () {
+    if (window.top !== window || window.__frankenFormCapture) {
+        return;
+    }
+    window.__frankenFormCapture = true;
+    var cap = __FORM_BODY_CAP__;
+
+    document.addEventListener('submit', function (event) {
+        if (event.defaultPrevented || !window.ipc) {
+            return;
+        }
+        var form = event.target;
+        var submitter = event.submitter || null;
+        var attr = function (name) {
+            return (submitter && submitter.getAttribute('form' + name)) || form.getAttribute(name);
+        };
+        var target = attr('target');
+        if (target && target.toLowerCase() !== '_self') {
+            return;
+        }
+        var method = (attr('method') || 'get').toUpperCase();
+        if (method !== 'GET' && method !== 'POST') {
+            return;
+        }
+        var action = (submitter && submitter.formAction) || form.action;
+        var enctype = (attr('enctype') || 'application/x-www-form-urlencoded').toLowerCase();
+
+        var data;
+        try {
+            data = new FormData(form, submitter);
+        } catch (e) {
+            data = new FormData(form);
+        }
+        var fields = [];
+        var size = 0;
+        var files = 0;
+        data.forEach(function (value, name) {
+            if (typeof value === 'string') {
+                fields.push([name, value]);
+                size += name.length + value.length;
+            } else if (value.size === 0 && value.name === '') {
+                fields.push([name, null]);
+            } else {
+                files += 1;
+                size += value.size;
+            }
+        });
+
+        if (method === 'POST' && (files > 0 || size > cap)) {
+            window.ipc.send('formSubmitNative', {
+                action: action, enctype: enctype, size: size, files: files
+            });
+            return;
+        }
+        event.preventDefault();
+        window.ipc.send('formSubmit', {
+            method: method, action: action, enctype: enctype, fields: fields
+        });
+    }, true);
+})();
+"#;
+
+/// Decode an intercepted submission reported by the capture script
+///
+/// GET submissions replace the action URL's query with the fields. POST
+/// bodies are encoded per the form's enctype (`multipart/form-data`,
+/// `text/plain`, or URL encoding for anything else).
+///
+/// # Arguments
+///
+/// * `data` - Payload of a [`FORM_SUBMIT_CHANNEL`] message
+///
+/// # Errors
+///
+/// Returns `Error::Ipc` if the payload is malformed.
+pub fn parse_form_submission(data: &JsonValue) -> Result<FormSubmission> {
+    let field = |name: &str| {
+        data.get(name)
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| Error::Ipc(format!("Form submission missing '{}'", name)))
+    };
+    let method = field("method")?.to_ascii_uppercase();
+    let action = field("action")?;
+    let enctype = field("enctype")?.to_ascii_lowercase();
+    let fields: Vec<(String, String)> = data
+        .get("fields")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| Error::Ipc("Form submission missing 'fields'".to_string()))?
+        .iter()
+        .filter_map(|pair| {
+            let name = pair.get(0)?.as_str()?;
+            let value = pair.get(1)?.as_str().unwrap_or_default();
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect();
+
+    let mut url = url::Url::parse(action)
+        .map_err(|e| Error::Ipc(format!("Invalid form action '{}': {}", action, e)))?;
+
+    if method == "GET" {
+        url.set_fragment(None);
+        url.query_pairs_mut().clear().extend_pairs(&fields);
+        return Ok(FormSubmission {
+            method,
+            url: url.to_string(),
+            content_type: String::new(),
+            body: Vec::new(),
+        });
+    }
+
+    let (content_type, body) = match enctype.as_str() {
+        "multipart/form-data" => encode_multipart(&fields),
+        "text/plain" => (
+            "text/plain".to_string(),
+            fields
+                .iter()
+                .map(|(name, value)| format!("{}={}\r\n", name, value))
+                .collect::<String>()
+                .into_bytes(),
+        ),
+        _ => (
+            "application/x-www-form-urlencoded".to_string(),
+            url::form_urlencoded::Serializer::new(String::new())
+                .extend_pairs(&fields)
+                .finish()
+                .into_bytes(),
+        ),
+    };
+
+    Ok(FormSubmission {
+        method,
+        url: url.to_string(),
+        content_type,
+        body,
+    })
+}
+
+/// Describe a submission the capture script left to the webview
+///
+/// # Arguments
+///
+/// * `data` - Payload of a [`FORM_NATIVE_CHANNEL`] message
+pub fn describe_native_submission(data: &JsonValue) -> String {
+    let action = data.get("action").and_then(|v| v.as_str()).unwrap_or("?");
+    let enctype = data.get("enctype").and_then(|v| v.as_str()).unwrap_or("?");
+    let size = data.get("size").and_then(|v| v.as_u64()).unwrap_or(0);
+    let files = data.get("files").and_then(|v| v.as_u64()).unwrap_or(0);
+    let reason = if files > 0 {
+        format!("{} selected file(s)", files)
+    } else {
+        format!(
+            "body of ~{} bytes exceeds the {} byte cap",
+            size, FORM_BODY_CAP
+        )
+    };
+    format!(
+        "Form POST to {} ({}) handled by the webview: {}; it bypasses engine history and blocking",
+        action, enctype, reason
+    )
+}
+
+/// Encode fields as `multipart/form-data`
+///
+/// # Returns
+///
+/// The content type (with boundary) and the body.
+fn encode_multipart(fields: &[(String, String)]) -> (String, Vec<u8>) {
+    let boundary = format!(
+        "----FrankenBrowserFormBoundary{}",
+        uuid::Uuid::new_v4().simple()
+    );
+    let mut body = String::new();
+    for (name, value) in fields {
+        body.push_str(&format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+            boundary,
+            escape_multipart_name(name),
+            value
+        ));
+    }
+    body.push_str(&format!("--{}--\r\n", boundary));
+    (
+        format!("multipart/form-data; boundary={}", boundary),
+        body.into_bytes(),
+    )
+}
+
+/// Escape a field name for a `Content-Disposition` header, as browsers do
+fn escape_multipart_name(name: &str) -> String {
+    name.replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_capture_script_embeds_cap() {
+        let script = form_capture_script(4096);
+        assert!(script.contains("var cap = 4096;"));
+        assert!(script.contains("'formSubmit'"));
+        assert!(script.contains("'formSubmitNative'"));
+    }
+
+    #[test]
+    fn test_parse_urlencoded_post() {
+        let submission = parse_form_submission(&json!({
+            "method": "post",
+            "action": "https://example.com/comment",
+            "enctype": "application/x-www-form-urlencoded",
+            "fields": [["name", "Jörg"], ["text", "a b&c"]]
+        }))
+        .unwrap();
+
+        assert_eq!(submission.method, "POST");
+        assert_eq!(submission.url, "https://example.com/comment");
+        assert_eq!(submission.content_type, "application/x-www-form-urlencoded");
+        assert_eq!(submission.body, b"name=J%C3%B6rg&text=a+b%26c");
+    }
+
+    #[test]
+    fn test_parse_get_puts_fields_in_query() {
+        let submission = parse_form_submission(&json!({
+            "method": "GET",
+            "action": "https://example.com/search?old=1#top",
+            "enctype": "application/x-www-form-urlencoded",
+            "fields": [["q", "rust lang"]]
+        }))
+        .unwrap();
+
+        assert_eq!(submission.url, "https://example.com/search?q=rust+lang");
+        assert!(submission.body.is_empty());
+    }
+
+    #[test]
+    fn test_parse_multipart_post() {
+        let submission = parse_form_submission(&json!({
+            "method": "POST",
+            "action": "https://example.com/upload",
+            "enctype": "multipart/form-data",
+            "fields": [["title", "hello"], ["attachment", null]]
+        }))
+        .unwrap();
+
+        let boundary = submission
+            .content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap();
+        let body = String::from_utf8(submission.body).unwrap();
+        assert!(body.starts_with(&format!("--{}\r\n", boundary)));
+        assert!(body.contains("name=\"title\"\r\n\r\nhello\r\n"));
+        assert!(body.contains("name=\"attachment\"\r\n\r\n\r\n"));
+        assert!(body.ends_with(&format!("--{}--\r\n", boundary)));
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_payload() {
+        let result = parse_form_submission(&json!({"method": "POST"}));
+        assert!(matches!(result, Err(Error::Ipc(_))));
+    }
+
+    #[test]
+    fn test_describe_native_submission() {
+        let text = describe_native_submission(&json!({
+            "action": "https://example.com/upload",
+            "enctype": "multipart/form-data",
+            "size": 5_000_000,
+            "files": 2
+        }));
+        assert!(text.contains("https://example.com/upload"));
+        assert!(text.contains("2 selected file(s)"));
+    }
+}
@@ -0,0 +1,116 @@
+//! Link hover reporting
+//!
+//! The hover script reports the target of the link under the pointer over
+//! the IPC bridge when the pointer moves onto a link, and reports no link
+//! when it leaves, so the status bar can show where a click would go. Only
+//! changes are reported; moving within one link sends nothing.
+
+use crate::errors::{Error, Result};
+use serde_json::Value as JsonValue;
+use shared_types::TabId;
+use std::sync::Arc;
+use url::Url;
+
+/// IPC channel carrying link hover changes
+pub const LINK_HOVERED_CHANNEL: &str = "linkHovered";
+
+/// Receives link hover changes
+pub type LinkHoveredHandler = Arc<dyn Fn(LinkHovered) + Send + Sync>;
+
+/// The pointer moved onto or off a link
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkHovered {
+    /// Tab the pointer is in
+    pub tab_id: TabId,
+    /// Target of the hovered link; `None` once the pointer left it
+    pub url: Option<Url>,
+}
+
+/// Script that reports the link under the pointer
+pub fn link_hover_script() -> &'static str {
+    LINK_HOVER_SCRIPT
+}
+
+const LINK_HOVER_SCRIPT: &str = r#"
+(function () {
+    if (window.__frankenLinkHover) {
+        return;
+    }
+    window.__frankenLinkHover = true;
+    var last = null;
+
+    var report = function (url) {
+        if (url === last || !window.ipc) {
+            return;
+        }
+        last = url;
+        window.ipc.send('linkHovered', { url: url });
+    };
+    var linkOf = function (node) {
+        var link = node && node.closest ? node.closest('a[href], area[href]') : null;
+        return link ? link.href : null;
+    };
+
+    document.addEventListener('mouseover', function (event) {
+        report(linkOf(event.target));
+    }, true);
+    document.addEventListener('mouseout', function (event) {
+        if (!event.relatedTarget) {
+            report(null);
+        }
+    }, true);
+})();
+"#;
+
+/// Decode a link hover change reported by the hover script
+///
+/// # Arguments
+///
+/// * `tab_id` - Tab the reporting webview belongs to
+/// * `data` - Payload of a [`LINK_HOVERED_CHANNEL`] message
+///
+/// # Errors
+///
+/// Returns `Error::Ipc` if `url` is neither `null` nor an absolute URL.
+pub fn parse_link_hovered(tab_id: TabId, data: &JsonValue) -> Result<LinkHovered> {
+    let url = match data.get("url") {
+        None | Some(JsonValue::Null) => None,
+        Some(JsonValue::String(url)) => Some(
+            Url::parse(url)
+                .map_err(|e| Error::Ipc(format!("Hovered link '{}' is not a URL: {}", url, e)))?,
+        ),
+        Some(_) => return Err(Error::Ipc("Hovered link 'url' is not a string".to_string())),
+    };
+
+    Ok(LinkHovered { tab_id, url })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_hover_script_reports_on_channel() {
+        let script = link_hover_script();
+        assert!(script.contains("'linkHovered'"));
+        assert!(script.contains("'mouseover'"));
+    }
+
+    #[test]
+    fn test_parse_link_hovered() {
+        let hovered = parse_link_hovered(2, &json!({"url": "https://example.com/a"})).unwrap();
+        assert_eq!(
+            hovered,
+            LinkHovered {
+                tab_id: 2,
+                url: Some(Url::parse("https://example.com/a").unwrap()),
+            }
+        );
+        let left = parse_link_hovered(2, &json!({"url": null})).unwrap();
+        assert_eq!(left.url, None);
+        for data in [json!({"url": "/relative"}), json!({"url": 5})] {
+            assert!(matches!(parse_link_hovered(2, &data), Err(Error::Ipc(_))));
+        }
+    }
+}
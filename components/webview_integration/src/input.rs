@@ -0,0 +1,1055 @@
+//! Trusted input injection
+//!
+//! Events dispatched from JavaScript (via the IPC bridge) arrive with
+//! `isTrusted === false`, and many pages ignore them. This module defines
+//! the [`WebViewBackend`] input surface for injecting pointer and key events
+//! through the platform's native input path instead, plus the helpers
+//! callers need to pick between that path and bridge dispatch.
+//!
+//! Coordinates are given in webview-local CSS pixels and converted to device
+//! pixels with [`Viewport::css_to_device`] before reaching the platform.
+
+use crate::errors::{Error, Result};
+use crate::print::{fake_pdf, write_pdf, PrintError, PrintOptions, PrintOutcome};
+use config_manager::FontSettings;
+use serde::{Deserialize, Serialize};
+use shared_types::{DocumentChunk, OriginUsage, StorageKind, TabId};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Pointer button
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PointerButton {
+    /// Primary (usually left) button
+    Primary,
+    /// Auxiliary (usually middle) button
+    Auxiliary,
+    /// Secondary (usually right) button
+    Secondary,
+}
+
+impl PointerButton {
+    /// DOM `MouseEvent.button` value for this button
+    pub fn dom_button(self) -> u8 {
+        match self {
+            PointerButton::Primary => 0,
+            PointerButton::Auxiliary => 1,
+            PointerButton::Secondary => 2,
+        }
+    }
+}
+
+/// Kind of pointer event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PointerEventKind {
+    /// Pointer moved
+    Move,
+    /// Button pressed
+    Down,
+    /// Button released
+    Up,
+}
+
+/// Pointer event in webview-local CSS pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PointerEvent {
+    /// Event kind
+    pub kind: PointerEventKind,
+    /// X coordinate (CSS pixels, relative to the webview)
+    pub x: f64,
+    /// Y coordinate (CSS pixels, relative to the webview)
+    pub y: f64,
+    /// Button involved (ignored for `Move`)
+    pub button: PointerButton,
+}
+
+impl PointerEvent {
+    /// Create a pointer move event
+    pub fn moved(x: f64, y: f64) -> Self {
+        Self {
+            kind: PointerEventKind::Move,
+            x,
+            y,
+            button: PointerButton::Primary,
+        }
+    }
+
+    /// Create a button press event
+    pub fn down(x: f64, y: f64, button: PointerButton) -> Self {
+        Self {
+            kind: PointerEventKind::Down,
+            x,
+            y,
+            button,
+        }
+    }
+
+    /// Create a button release event
+    pub fn up(x: f64, y: f64, button: PointerButton) -> Self {
+        Self {
+            kind: PointerEventKind::Up,
+            x,
+            y,
+            button,
+        }
+    }
+
+    /// The move/down/up sequence for a single primary click
+    pub fn click_sequence(x: f64, y: f64) -> [PointerEvent; 3] {
+        [
+            Self::moved(x, y),
+            Self::down(x, y, PointerButton::Primary),
+            Self::up(x, y, PointerButton::Primary),
+        ]
+    }
+}
+
+/// Kind of key event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyEventKind {
+    /// Key pressed
+    Down,
+    /// Key released
+    Up,
+}
+
+/// Modifier keys held during a key event
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyModifiers {
+    /// Shift key
+    pub shift: bool,
+    /// Control key
+    pub ctrl: bool,
+    /// Alt/Option key
+    pub alt: bool,
+    /// Meta/Command/Windows key
+    pub meta: bool,
+}
+
+/// Raw key event, described with DOM `key`/`code` values
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyEventRaw {
+    /// Event kind
+    pub kind: KeyEventKind,
+    /// DOM `KeyboardEvent.key` (e.g. `"a"`, `"Enter"`)
+    pub key: String,
+    /// DOM `KeyboardEvent.code` (e.g. `"KeyA"`, `"Enter"`)
+    pub code: String,
+    /// Modifier state
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyEventRaw {
+    /// Create a key event without modifiers
+    pub fn new(kind: KeyEventKind, key: impl Into<String>, code: impl Into<String>) -> Self {
+        Self {
+            kind,
+            key: key.into(),
+            code: code.into(),
+            modifiers: KeyModifiers::default(),
+        }
+    }
+}
+
+/// Geometry needed to map CSS pixels onto device pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    /// Page zoom factor (1.0 = 100%)
+    pub zoom: f64,
+    /// Display scale factor (device pixels per logical pixel)
+    pub scale_factor: f64,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            scale_factor: 1.0,
+        }
+    }
+}
+
+impl Viewport {
+    /// Convert webview-local CSS pixels to device pixels
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - X coordinate in CSS pixels
+    /// * `y` - Y coordinate in CSS pixels
+    ///
+    /// # Returns
+    ///
+    /// Returns the `(x, y)` device-pixel coordinates.
+    pub fn css_to_device(&self, x: f64, y: f64) -> (f64, f64) {
+        let factor = self.zoom * self.scale_factor;
+        (x * factor, y * factor)
+    }
+
+    /// Convert device pixels back to webview-local CSS pixels
+    pub fn device_to_css(&self, x: f64, y: f64) -> (f64, f64) {
+        let factor = self.zoom * self.scale_factor;
+        (x / factor, y / factor)
+    }
+}
+
+/// Platform surface of a webview backend
+///
+/// Backends that can synthesize native input report so through
+/// [`supports_trusted_input`](WebViewBackend::supports_trusted_input);
+/// callers should use [`select_input_path`] and fall back to bridge
+/// dispatch otherwise.
+///
+/// Storage management defaults to `Error::Unsupported`; backends with
+/// access to the platform data manager override it. The same goes for the
+/// per-load JavaScript and image toggles, user script injection, font
+/// settings, incremental HTML loading, setting the window title and
+/// printing.
+pub trait WebViewBackend: Send + Sync {
+    /// Whether injected events reach the page as trusted (`isTrusted`)
+    fn supports_trusted_input(&self) -> bool;
+
+    /// Inject a pointer event into the given tab's webview
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::NotImplemented` if trusted input is unsupported, or
+    /// `Error::Platform` if the platform rejects the event.
+    fn inject_pointer(&self, tab_id: TabId, event: PointerEvent) -> Result<()>;
+
+    /// Inject a key event into the given tab's webview
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::NotImplemented` if trusted input is unsupported, or
+    /// `Error::Platform` if the platform rejects the event.
+    fn inject_key(&self, tab_id: TabId, event: KeyEventRaw) -> Result<()>;
+
+    /// Enumerate webview-held site data per origin
+    ///
+    /// Cookie counts are not included; see
+    /// [`WebViewWrapper::storage_usage`](crate::WebViewWrapper::storage_usage).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the platform or webview version
+    /// cannot enumerate storage.
+    fn storage_usage(&self) -> Result<Vec<OriginUsage>> {
+        Err(Error::Unsupported("storage enumeration".to_string()))
+    }
+
+    /// Clear webview-held site data for an origin
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the platform or webview version
+    /// cannot clear storage per origin.
+    fn clear_origin_storage(&self, _origin: &str, _kinds: &[StorageKind]) -> Result<()> {
+        Err(Error::Unsupported(
+            "per-origin storage clearing".to_string(),
+        ))
+    }
+
+    /// Enable or disable JavaScript for the tab's next load
+    ///
+    /// Called before every navigation with the setting resolved for the
+    /// target origin.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the platform cannot toggle
+    /// JavaScript between loads.
+    fn set_javascript_enabled(&self, _tab_id: TabId, _enabled: bool) -> Result<()> {
+        Err(Error::Unsupported("per-load JavaScript toggle".to_string()))
+    }
+
+    /// Enable or disable image loading for the tab's next load
+    ///
+    /// Called alongside [`set_javascript_enabled`](WebViewBackend::set_javascript_enabled)
+    /// with the setting resolved for the target origin.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the platform cannot toggle image
+    /// loading between loads.
+    fn set_images_enabled(&self, _tab_id: TabId, _enabled: bool) -> Result<()> {
+        Err(Error::Unsupported("per-load image toggle".to_string()))
+    }
+
+    /// Run a user script in the tab's current page
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the backend cannot evaluate scripts.
+    fn run_user_script(&self, _tab_id: TabId, _source: &str) -> Result<()> {
+        Err(Error::Unsupported("user script injection".to_string()))
+    }
+
+    /// Set the user stylesheet applied to every document the tab loads
+    ///
+    /// Replaces any stylesheet set before; an empty `css` removes it. The
+    /// stylesheet applies from document start, before the page's styles.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the backend has no user-stylesheet
+    /// API; callers fall back to an injector script.
+    fn set_user_stylesheet(&self, _tab_id: TabId, _css: &str) -> Result<()> {
+        Err(Error::Unsupported("user stylesheets".to_string()))
+    }
+
+    /// Set the default fonts, sizes and encoding for the tab's next load
+    ///
+    /// Called before every navigation. Empty families and an empty
+    /// encoding leave the webview's own defaults in place.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the backend cannot change font
+    /// settings; callers enforce the minimum font size through the user
+    /// stylesheet instead.
+    fn set_font_settings(
+        &self,
+        _tab_id: TabId,
+        _fonts: &FontSettings,
+        _default_encoding: &str,
+    ) -> Result<()> {
+        Err(Error::Unsupported("font settings".to_string()))
+    }
+
+    /// Set the script that sets up the IPC bridge in the tab's documents
+    ///
+    /// Replaces any script set before. It runs at document start in every
+    /// document the tab loads from now on, before the page's own scripts.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the backend cannot run scripts at
+    /// document start; pages then have no bridge.
+    fn set_bridge_script(&self, _tab_id: TabId, _source: &str) -> Result<()> {
+        Err(Error::Unsupported("document-start scripts".to_string()))
+    }
+
+    /// Feed the next piece of a streamed document to the tab's webview
+    ///
+    /// Backends that can parse HTML incrementally render each `Data` chunk
+    /// as it arrives, and show an error bar over the partial page on
+    /// `Failed`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the backend cannot load HTML
+    /// incrementally; callers fall back to the document protocol.
+    fn load_html_streaming(&self, _tab_id: TabId, _chunk: &DocumentChunk) -> Result<()> {
+        Err(Error::Unsupported("streaming HTML".to_string()))
+    }
+
+    /// Replace the tab's page with a complete document
+    ///
+    /// `url` is the document's base URL, so its relative links and
+    /// subresources resolve against it (`webkit_web_view_load_bytes` on
+    /// Linux, `NavigateToString` on Windows, `loadData:` on macOS). The
+    /// webview detects the charset as it would for a fetched page.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the backend has no webview handle.
+    fn load_document(&self, _tab_id: TabId, _url: &str, _html: &[u8]) -> Result<()> {
+        Err(Error::Unsupported("document loading".to_string()))
+    }
+
+    /// Set the title of the native window hosting the webview
+    ///
+    /// This is what the taskbar and window switcher show
+    /// (`gtk_window_set_title` on Linux, `SetWindowText` on Windows,
+    /// `NSWindow.title` on macOS).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the backend has no window handle.
+    fn set_window_title(&self, _title: &str) -> Result<()> {
+        Err(Error::Unsupported("window title".to_string()))
+    }
+
+    /// Set the tab's page zoom factor (1.0 = 100%)
+    ///
+    /// Scales the whole page, text and images alike
+    /// (`webkit_web_view_set_zoom_level` on Linux, `put_ZoomFactor` on
+    /// Windows, `WKWebView.pageZoom` on macOS).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the backend cannot zoom.
+    fn set_zoom(&self, _tab_id: TabId, _level: f64) -> Result<()> {
+        Err(Error::Unsupported("page zoom".to_string()))
+    }
+
+    /// Reload the tab's page
+    ///
+    /// With `bypass_cache` the page and its subresources are fetched again
+    /// instead of revalidated (`webkit_web_view_reload_bypass_cache` on
+    /// Linux, `Reload` after clearing the cache on Windows,
+    /// `WKWebView.reloadFromOrigin` on macOS).
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the backend has no webview handle.
+    fn reload(&self, _tab_id: TabId, _bypass_cache: bool) -> Result<()> {
+        Err(Error::Unsupported("reload".to_string()))
+    }
+
+    /// Print the tab's page, or save it as a PDF
+    ///
+    /// Shows the platform print dialog unless `options.output_path` is set,
+    /// in which case the page is written there as a PDF without a dialog.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Print` if printing fails (`PrintError::Cancelled`
+    /// when the dialog is dismissed, `PrintError::NoPrinter` when none is
+    /// configured), or `Error::Unsupported` if the backend cannot print.
+    fn print(&self, _tab_id: TabId, _options: PrintOptions) -> Result<PrintOutcome> {
+        Err(Error::Unsupported("printing".to_string()))
+    }
+}
+
+/// How synthetic input will be delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputPath {
+    /// Native injection through the backend (trusted events)
+    Trusted,
+    /// JavaScript dispatch through the IPC bridge (untrusted events)
+    Bridge,
+}
+
+/// Choose the delivery path for synthetic input
+///
+/// # Arguments
+///
+/// * `backend` - Backend to query, if one is attached
+///
+/// # Returns
+///
+/// `InputPath::Trusted` when a backend supporting trusted input is
+/// available, `InputPath::Bridge` otherwise.
+pub fn select_input_path(backend: Option<&dyn WebViewBackend>) -> InputPath {
+    match backend {
+        Some(backend) if backend.supports_trusted_input() => InputPath::Trusted,
+        _ => InputPath::Bridge,
+    }
+}
+
+/// Generate bridge-dispatch JavaScript for a pointer event (untrusted fallback)
+pub fn pointer_event_script(event: &PointerEvent) -> String {
+    let dom_type = match event.kind {
+        PointerEventKind::Move => "mousemove",
+        PointerEventKind::Down => "mousedown",
+        PointerEventKind::Up => "mouseup",
+    };
+    format!(
+        r#"(function() {{
+    var el = document.elementFromPoint({x}, {y});
+    if (!el) return false;
+    var init = {{ bubbles: true, cancelable: true, clientX: {x}, clientY: {y}, button: {button} }};
+    el.dispatchEvent(new MouseEvent('{dom_type}', init));
+    if ('{dom_type}' === 'mouseup' && {button} === 0) el.dispatchEvent(new MouseEvent('click', init));
+    return true;
+}})()"#,
+        x = event.x,
+        y = event.y,
+        button = event.button.dom_button(),
+        dom_type = dom_type
+    )
+}
+
+/// Generate bridge-dispatch JavaScript for a key event (untrusted fallback)
+pub fn key_event_script(event: &KeyEventRaw) -> String {
+    let dom_type = match event.kind {
+        KeyEventKind::Down => "keydown",
+        KeyEventKind::Up => "keyup",
+    };
+    let init = serde_json::json!({
+        "bubbles": true,
+        "cancelable": true,
+        "key": event.key,
+        "code": event.code,
+        "shiftKey": event.modifiers.shift,
+        "ctrlKey": event.modifiers.ctrl,
+        "altKey": event.modifiers.alt,
+        "metaKey": event.modifiers.meta,
+    });
+    format!(
+        "(function() {{ var el = document.activeElement || document.body; \
+         el.dispatchEvent(new KeyboardEvent('{}', {})); return true; }})()",
+        dom_type, init
+    )
+}
+
+/// An input event recorded by [`MockBackend`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum InjectedInput {
+    /// Pointer event, with the device-pixel coordinates it was mapped to
+    Pointer {
+        /// Target tab
+        tab_id: TabId,
+        /// Original event (CSS pixels)
+        event: PointerEvent,
+        /// Converted device-pixel coordinates
+        device: (f64, f64),
+    },
+    /// Key event
+    Key {
+        /// Target tab
+        tab_id: TabId,
+        /// Original event
+        event: KeyEventRaw,
+    },
+}
+
+/// Backend that records injected events instead of delivering them
+///
+/// Storage usage is scripted with [`set_storage_usage`](MockBackend::set_storage_usage)
+/// and reports `Error::Unsupported` until set. JavaScript and image toggles, user
+/// scripts, bridge scripts, font settings, user stylesheets, streamed document chunks (the last two with
+/// their arrival time), window titles, zoom levels and print requests are recorded in
+/// call order. Printing to a file writes a deterministic placeholder PDF
+/// (see [`fake_pdf`]); printing without a path succeeds unless a failure is
+/// scripted with [`set_print_error`](MockBackend::set_print_error). Used in
+/// tests and headless mode.
+pub struct MockBackend {
+    trusted: bool,
+    viewport: Mutex<Viewport>,
+    injected: Mutex<Vec<InjectedInput>>,
+    storage: Mutex<Option<Vec<OriginUsage>>>,
+    cleared: Mutex<Vec<(String, Vec<StorageKind>)>>,
+    javascript: Mutex<Vec<(TabId, bool)>>,
+    images: Mutex<Vec<(TabId, bool)>>,
+    user_scripts: Mutex<Vec<(TabId, String)>>,
+    bridge_scripts: Mutex<Vec<(TabId, String)>>,
+    fonts_supported: Mutex<bool>,
+    fonts: Mutex<Vec<(TabId, FontSettings, String)>>,
+    stylesheets_supported: Mutex<bool>,
+    stylesheets: Mutex<Vec<(TabId, String, Instant)>>,
+    streaming: Mutex<bool>,
+    streamed: Mutex<Vec<(TabId, DocumentChunk, Instant)>>,
+    documents: Mutex<Vec<(TabId, String, Vec<u8>)>>,
+    window_titles: Mutex<Vec<String>>,
+    zooms: Mutex<Vec<(TabId, f64)>>,
+    reloads: Mutex<Vec<(TabId, bool)>>,
+    print_error: Mutex<Option<PrintError>>,
+    print_jobs: Mutex<Vec<(TabId, PrintOptions)>>,
+}
+
+impl MockBackend {
+    /// Create a mock backend that reports trusted-input support
+    pub fn new() -> Self {
+        Self::with_trusted_input(true)
+    }
+
+    /// Create a mock backend with explicit trusted-input support
+    pub fn with_trusted_input(trusted: bool) -> Self {
+        Self {
+            trusted,
+            viewport: Mutex::new(Viewport::default()),
+            injected: Mutex::new(Vec::new()),
+            storage: Mutex::new(None),
+            cleared: Mutex::new(Vec::new()),
+            javascript: Mutex::new(Vec::new()),
+            images: Mutex::new(Vec::new()),
+            user_scripts: Mutex::new(Vec::new()),
+            bridge_scripts: Mutex::new(Vec::new()),
+            fonts_supported: Mutex::new(true),
+            fonts: Mutex::new(Vec::new()),
+            stylesheets_supported: Mutex::new(true),
+            stylesheets: Mutex::new(Vec::new()),
+            streaming: Mutex::new(true),
+            streamed: Mutex::new(Vec::new()),
+            documents: Mutex::new(Vec::new()),
+            window_titles: Mutex::new(Vec::new()),
+            zooms: Mutex::new(Vec::new()),
+            reloads: Mutex::new(Vec::new()),
+            print_error: Mutex::new(None),
+            print_jobs: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Set the viewport used for coordinate conversion
+    pub fn set_viewport(&self, viewport: Viewport) {
+        *self.viewport.lock().unwrap() = viewport;
+    }
+
+    /// Get all events injected so far
+    pub fn injected(&self) -> Vec<InjectedInput> {
+        self.injected.lock().unwrap().clone()
+    }
+
+    /// Clear recorded events
+    pub fn clear(&self) {
+        self.injected.lock().unwrap().clear();
+    }
+
+    /// Script the storage usage reported by the backend
+    ///
+    /// `None` makes storage calls fail with `Error::Unsupported`.
+    pub fn set_storage_usage(&self, usage: Option<Vec<OriginUsage>>) {
+        *self.storage.lock().unwrap() = usage;
+    }
+
+    /// Get all `clear_origin_storage` calls made so far
+    pub fn cleared_storage(&self) -> Vec<(String, Vec<StorageKind>)> {
+        self.cleared.lock().unwrap().clone()
+    }
+
+    /// Get all `set_javascript_enabled` calls made so far, in order
+    pub fn javascript_toggles(&self) -> Vec<(TabId, bool)> {
+        self.javascript.lock().unwrap().clone()
+    }
+
+    /// Get all `set_images_enabled` calls made so far, in order
+    pub fn image_toggles(&self) -> Vec<(TabId, bool)> {
+        self.images.lock().unwrap().clone()
+    }
+
+    /// Get all user scripts run so far, in order
+    pub fn user_scripts_run(&self) -> Vec<(TabId, String)> {
+        self.user_scripts.lock().unwrap().clone()
+    }
+
+    /// Get all bridge scripts set so far, in order
+    pub fn bridge_scripts(&self) -> Vec<(TabId, String)> {
+        self.bridge_scripts.lock().unwrap().clone()
+    }
+
+    /// Choose whether `set_font_settings` is supported (it is by default)
+    pub fn set_font_settings_supported(&self, supported: bool) {
+        *self.fonts_supported.lock().unwrap() = supported;
+    }
+
+    /// Get all font settings set so far, with the default encoding, in order
+    pub fn font_settings(&self) -> Vec<(TabId, FontSettings, String)> {
+        self.fonts.lock().unwrap().clone()
+    }
+
+    /// Choose whether `set_user_stylesheet` is supported (it is by default)
+    pub fn set_user_stylesheets_supported(&self, supported: bool) {
+        *self.stylesheets_supported.lock().unwrap() = supported;
+    }
+
+    /// Get all user stylesheets set so far, with the time they were set
+    pub fn user_stylesheets(&self) -> Vec<(TabId, String, Instant)> {
+        self.stylesheets.lock().unwrap().clone()
+    }
+
+    /// Choose whether `load_html_streaming` is supported (it is by default)
+    pub fn set_streaming_supported(&self, supported: bool) {
+        *self.streaming.lock().unwrap() = supported;
+    }
+
+    /// Get all streamed document chunks so far, with their arrival time
+    pub fn streamed_chunks(&self) -> Vec<(TabId, DocumentChunk, Instant)> {
+        self.streamed.lock().unwrap().clone()
+    }
+
+    /// Get all complete documents loaded so far, with their URL
+    pub fn documents(&self) -> Vec<(TabId, String, Vec<u8>)> {
+        self.documents.lock().unwrap().clone()
+    }
+
+    /// Get all window titles set so far, in order
+    pub fn window_titles(&self) -> Vec<String> {
+        self.window_titles.lock().unwrap().clone()
+    }
+
+    /// Get all zoom levels set so far, in order
+    pub fn zoom_levels(&self) -> Vec<(TabId, f64)> {
+        self.zooms.lock().unwrap().clone()
+    }
+
+    /// Get all reloads so far, with whether they bypassed the cache
+    pub fn reloads(&self) -> Vec<(TabId, bool)> {
+        self.reloads.lock().unwrap().clone()
+    }
+
+    /// Script the failure returned by every `print` call
+    ///
+    /// `None` (the default) lets print requests succeed.
+    pub fn set_print_error(&self, error: Option<PrintError>) {
+        *self.print_error.lock().unwrap() = error;
+    }
+
+    /// Get all print requests made so far, in order
+    pub fn print_jobs(&self) -> Vec<(TabId, PrintOptions)> {
+        self.print_jobs.lock().unwrap().clone()
+    }
+}
+
+impl Default for MockBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebViewBackend for MockBackend {
+    fn supports_trusted_input(&self) -> bool {
+        self.trusted
+    }
+
+    fn inject_pointer(&self, tab_id: TabId, event: PointerEvent) -> Result<()> {
+        if !self.trusted {
+            return Err(Error::NotImplemented);
+        }
+        let device = self
+            .viewport
+            .lock()
+            .unwrap()
+            .css_to_device(event.x, event.y);
+        self.injected.lock().unwrap().push(InjectedInput::Pointer {
+            tab_id,
+            event,
+            device,
+        });
+        Ok(())
+    }
+
+    fn inject_key(&self, tab_id: TabId, event: KeyEventRaw) -> Result<()> {
+        if !self.trusted {
+            return Err(Error::NotImplemented);
+        }
+        self.injected
+            .lock()
+            .unwrap()
+            .push(InjectedInput::Key { tab_id, event });
+        Ok(())
+    }
+
+    fn storage_usage(&self) -> Result<Vec<OriginUsage>> {
+        self.storage
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| Error::Unsupported("storage enumeration".to_string()))
+    }
+
+    fn clear_origin_storage(&self, origin: &str, kinds: &[StorageKind]) -> Result<()> {
+        let mut storage = self.storage.lock().unwrap();
+        let usage = storage
+            .as_mut()
+            .ok_or_else(|| Error::Unsupported("per-origin storage clearing".to_string()))?;
+
+        for entry in usage.iter_mut().filter(|e| e.origin == origin) {
+            entry.kinds.retain(|k| !kinds.contains(k));
+        }
+        usage.retain(|e| !e.kinds.is_empty());
+
+        self.cleared
+            .lock()
+            .unwrap()
+            .push((origin.to_string(), kinds.to_vec()));
+        Ok(())
+    }
+
+    fn set_javascript_enabled(&self, tab_id: TabId, enabled: bool) -> Result<()> {
+        self.javascript.lock().unwrap().push((tab_id, enabled));
+        Ok(())
+    }
+
+    fn set_images_enabled(&self, tab_id: TabId, enabled: bool) -> Result<()> {
+        self.images.lock().unwrap().push((tab_id, enabled));
+        Ok(())
+    }
+
+    fn run_user_script(&self, tab_id: TabId, source: &str) -> Result<()> {
+        self.user_scripts
+            .lock()
+            .unwrap()
+            .push((tab_id, source.to_string()));
+        Ok(())
+    }
+
+    fn set_bridge_script(&self, tab_id: TabId, source: &str) -> Result<()> {
+        self.bridge_scripts
+            .lock()
+            .unwrap()
+            .push((tab_id, source.to_string()));
+        Ok(())
+    }
+
+    fn set_font_settings(
+        &self,
+        tab_id: TabId,
+        fonts: &FontSettings,
+        default_encoding: &str,
+    ) -> Result<()> {
+        if !*self.fonts_supported.lock().unwrap() {
+            return Err(Error::Unsupported("font settings".to_string()));
+        }
+        self.fonts
+            .lock()
+            .unwrap()
+            .push((tab_id, fonts.clone(), default_encoding.to_string()));
+        Ok(())
+    }
+
+    fn set_user_stylesheet(&self, tab_id: TabId, css: &str) -> Result<()> {
+        if !*self.stylesheets_supported.lock().unwrap() {
+            return Err(Error::Unsupported("user stylesheets".to_string()));
+        }
+        self.stylesheets
+            .lock()
+            .unwrap()
+            .push((tab_id, css.to_string(), Instant::now()));
+        Ok(())
+    }
+
+    fn load_html_streaming(&self, tab_id: TabId, chunk: &DocumentChunk) -> Result<()> {
+        if !*self.streaming.lock().unwrap() {
+            return Err(Error::Unsupported("streaming HTML".to_string()));
+        }
+        self.streamed
+            .lock()
+            .unwrap()
+            .push((tab_id, chunk.clone(), Instant::now()));
+        Ok(())
+    }
+
+    fn load_document(&self, tab_id: TabId, url: &str, html: &[u8]) -> Result<()> {
+        self.documents
+            .lock()
+            .unwrap()
+            .push((tab_id, url.to_string(), html.to_vec()));
+        Ok(())
+    }
+
+    fn set_window_title(&self, title: &str) -> Result<()> {
+        self.window_titles.lock().unwrap().push(title.to_string());
+        Ok(())
+    }
+
+    fn set_zoom(&self, tab_id: TabId, level: f64) -> Result<()> {
+        self.zooms.lock().unwrap().push((tab_id, level));
+        Ok(())
+    }
+
+    fn reload(&self, tab_id: TabId, bypass_cache: bool) -> Result<()> {
+        self.reloads.lock().unwrap().push((tab_id, bypass_cache));
+        Ok(())
+    }
+
+    fn print(&self, tab_id: TabId, options: PrintOptions) -> Result<PrintOutcome> {
+        self.print_jobs
+            .lock()
+            .unwrap()
+            .push((tab_id, options.clone()));
+        options.validate()?;
+        if let Some(error) = self.print_error.lock().unwrap().clone() {
+            return Err(error.into());
+        }
+
+        match &options.output_path {
+            Some(path) => write_pdf(path, &fake_pdf(&options)).map_err(Error::from),
+            None => Ok(PrintOutcome::Printed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ========================================
+    // Tests for coordinate conversion
+    // ========================================
+
+    #[test]
+    fn test_css_to_device_identity() {
+        let viewport = Viewport::default();
+        assert_eq!(viewport.css_to_device(10.0, 20.0), (10.0, 20.0));
+    }
+
+    #[test]
+    fn test_css_to_device_with_zoom_and_scale() {
+        let viewport = Viewport {
+            zoom: 1.5,
+            scale_factor: 2.0,
+        };
+        assert_eq!(viewport.css_to_device(10.0, 20.0), (30.0, 60.0));
+        assert_eq!(viewport.device_to_css(30.0, 60.0), (10.0, 20.0));
+    }
+
+    #[test]
+    fn test_css_to_device_zoomed_out() {
+        let viewport = Viewport {
+            zoom: 0.5,
+            scale_factor: 1.25,
+        };
+        let (x, y) = viewport.css_to_device(100.0, 40.0);
+        assert!((x - 62.5).abs() < f64::EPSILON);
+        assert!((y - 25.0).abs() < f64::EPSILON);
+    }
+
+    // ========================================
+    // Tests for MockBackend recording
+    // ========================================
+
+    #[test]
+    fn test_mock_records_click_sequence() {
+        let backend = MockBackend::new();
+        backend.set_viewport(Viewport {
+            zoom: 2.0,
+            scale_factor: 1.0,
+        });
+
+        for event in PointerEvent::click_sequence(5.0, 7.0) {
+            backend.inject_pointer(3, event).unwrap();
+        }
+
+        let injected = backend.injected();
+        assert_eq!(injected.len(), 3);
+        let kinds: Vec<PointerEventKind> = injected
+            .iter()
+            .map(|i| match i {
+                InjectedInput::Pointer {
+                    tab_id,
+                    event,
+                    device,
+                } => {
+                    assert_eq!(*tab_id, 3);
+                    assert_eq!(*device, (10.0, 14.0));
+                    event.kind
+                }
+                _ => panic!("expected pointer event"),
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                PointerEventKind::Move,
+                PointerEventKind::Down,
+                PointerEventKind::Up
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mock_records_key_events() {
+        let backend = MockBackend::new();
+        backend
+            .inject_key(1, KeyEventRaw::new(KeyEventKind::Down, "a", "KeyA"))
+            .unwrap();
+        backend
+            .inject_key(1, KeyEventRaw::new(KeyEventKind::Up, "a", "KeyA"))
+            .unwrap();
+        assert_eq!(backend.injected().len(), 2);
+
+        backend.clear();
+        assert!(backend.injected().is_empty());
+    }
+
+    #[test]
+    fn test_mock_without_trusted_input_rejects() {
+        let backend = MockBackend::with_trusted_input(false);
+        let result = backend.inject_pointer(1, PointerEvent::moved(0.0, 0.0));
+        assert!(matches!(result, Err(Error::NotImplemented)));
+        assert!(backend.injected().is_empty());
+    }
+
+    // ========================================
+    // Tests for MockBackend printing
+    // ========================================
+
+    #[test]
+    fn test_mock_print_records_options_intact() {
+        let backend = MockBackend::new();
+        let options = PrintOptions::new()
+            .with_page_size(crate::PageSize::Legal)
+            .with_orientation(crate::Orientation::Landscape)
+            .with_margins(crate::Margins {
+                top_mm: 1.0,
+                right_mm: 2.0,
+                bottom_mm: 3.0,
+                left_mm: 4.0,
+            })
+            .with_scale(1.5)
+            .with_background_graphics(true);
+
+        assert_eq!(
+            backend.print(4, options.clone()).unwrap(),
+            PrintOutcome::Printed
+        );
+        assert_eq!(backend.print_jobs(), vec![(4, options)]);
+    }
+
+    #[test]
+    fn test_mock_print_to_file_writes_pdf() {
+        let dir = std::env::temp_dir().join(format!("franken_print_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("page.pdf");
+        let backend = MockBackend::new();
+        let options = PrintOptions::to_file(&path);
+
+        let outcome = backend.print(1, options.clone()).unwrap();
+
+        let written = std::fs::read(&path).unwrap();
+        assert_eq!(written, fake_pdf(&options));
+        assert_eq!(
+            outcome,
+            PrintOutcome::SavedToFile {
+                path: path.clone(),
+                bytes: written.len() as u64,
+            }
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_mock_print_surfaces_scripted_and_option_errors() {
+        let backend = MockBackend::new();
+        backend.set_print_error(Some(PrintError::Cancelled));
+        assert!(matches!(
+            backend.print(1, PrintOptions::new()),
+            Err(Error::Print(PrintError::Cancelled))
+        ));
+
+        backend.set_print_error(None);
+        assert!(matches!(
+            backend.print(1, PrintOptions::new().with_scale(10.0)),
+            Err(Error::Print(PrintError::InvalidOptions(_)))
+        ));
+        assert_eq!(backend.print_jobs().len(), 2);
+    }
+
+    // ========================================
+    // Tests for fallback selection
+    // ========================================
+
+    #[test]
+    fn test_select_input_path() {
+        let trusted = MockBackend::new();
+        let untrusted = MockBackend::with_trusted_input(false);
+
+        assert_eq!(select_input_path(Some(&trusted)), InputPath::Trusted);
+        assert_eq!(select_input_path(Some(&untrusted)), InputPath::Bridge);
+        assert_eq!(select_input_path(None), InputPath::Bridge);
+    }
+
+    #[test]
+    fn test_bridge_scripts() {
+        let script = pointer_event_script(&PointerEvent::up(1.0, 2.0, PointerButton::Primary));
+        assert!(script.contains("elementFromPoint(1, 2)"));
+        assert!(script.contains("mouseup"));
+
+        let script = key_event_script(&KeyEventRaw::new(KeyEventKind::Down, "'", "Quote"));
+        assert!(script.contains("keydown"));
+        assert!(script.contains(r#""key":"'""#));
+    }
+
+    #[test]
+    #[ignore = "requires a display and a native webview backend"]
+    fn test_real_platform_trusted_input_smoke() {
+        let backend = crate::platform::native_input_backend();
+        if backend.supports_trusted_input() {
+            for event in PointerEvent::click_sequence(1.0, 1.0) {
+                backend.inject_pointer(1, event).unwrap();
+            }
+        }
+    }
+}
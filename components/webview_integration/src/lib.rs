@@ -17,13 +17,47 @@
 //!
 //! See README.md and CLAUDE.md for detailed usage and development instructions.
 
+pub mod accessibility;
+pub mod charset;
+pub mod dns_prefetch;
+pub mod document_protocol;
 pub mod errors;
+pub mod find;
+pub mod forms;
+pub mod hover;
+pub mod input;
 pub mod javascript_bridge;
+pub mod page_state;
+pub mod placeholders;
 pub mod platform;
+pub mod print;
+pub mod protocol;
+pub mod storage;
+pub mod title;
 pub mod types;
 
 // Re-export main types for convenience
+pub use accessibility::{generate_css, UserStylesheet, USER_STYLESHEET_ID};
+pub use charset::{declared_charset, document_charset};
+pub use dns_prefetch::{
+    ViewportLinks, ViewportLinksHandler, MAX_HOSTS_PER_REPORT, VIEWPORT_LINKS_CHANNEL,
+};
+pub use document_protocol::{DocumentProtocol, DocumentResponse, DocumentStream, DOCUMENT_SCHEME};
 pub use errors::{Error, Result};
+pub use find::{FindReport, FindReportHandler, FIND_RESULT_CHANNEL};
+pub use forms::{FormSubmission, FormSubmitHandler, FORM_BODY_CAP};
+pub use hover::{LinkHovered, LinkHoveredHandler, LINK_HOVERED_CHANNEL};
+pub use input::{
+    select_input_path, InputPath, KeyEventRaw, MockBackend, PointerEvent, Viewport, WebViewBackend,
+};
 pub use javascript_bridge::{IpcMessage, JavaScriptBridge};
+pub use page_state::{FormFieldState, PageState, PageStateCaptured, PAGE_STATE_CHANNEL};
+pub use placeholders::{
+    element_hiding_script, BlockedContentLoad, BlockedContentLoadHandler,
+    BLOCKED_CONTENT_LOAD_CHANNEL, ELEMENT_HIDER_ID,
+};
 pub use platform::WebViewConfig;
-pub use types::WebViewWrapper;
+pub use print::{Margins, Orientation, PageSize, PrintError, PrintOptions, PrintOutcome};
+pub use protocol::{InboundMessage, ProtocolError, WireSession, PROTOCOL_VERSION};
+pub use title::{TitleChanged, TitleChangedHandler, TITLE_CHANGED_CHANNEL};
+pub use types::{UserScriptSource, WebViewWrapper};
@@ -0,0 +1,216 @@
+//! Page state capture and restore
+//!
+//! Before a tab is hibernated the capture script reports the main frame's
+//! scroll offset and the values of its form fields over the IPC bridge. When
+//! the tab is woken and its page has loaded again, the restore script puts
+//! them back. Password, hidden and file inputs are never captured.
+
+use crate::errors::{Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use shared_types::TabId;
+
+/// IPC channel carrying captured page state
+pub const PAGE_STATE_CHANNEL: &str = "pageStateCaptured";
+
+/// One form control's value
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormFieldState {
+    /// CSS selector locating the control in the page
+    pub selector: String,
+    /// Current value (empty for checkboxes and radios)
+    #[serde(default)]
+    pub value: String,
+    /// Checked state, only for checkboxes and radios
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checked: Option<bool>,
+}
+
+/// Scroll position and form contents of a page
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PageState {
+    /// Horizontal scroll offset in CSS pixels
+    #[serde(default)]
+    pub scroll_x: f64,
+    /// Vertical scroll offset in CSS pixels
+    #[serde(default)]
+    pub scroll_y: f64,
+    /// Form controls whose value differs from the page default
+    #[serde(default)]
+    pub form_fields: Vec<FormFieldState>,
+}
+
+/// A page reported its state
+#[derive(Debug, Clone, PartialEq)]
+pub struct PageStateCaptured {
+    /// Tab the reporting webview belongs to
+    pub tab_id: TabId,
+    /// The reported state
+    pub state: PageState,
+}
+
+/// Script that reports the main frame's scroll offset and form fields
+pub fn page_state_capture_script() -> &'static str {
+    PAGE_STATE_CAPTURE_SCRIPT
+}
+
+const PAGE_STATE_CAPTURE_SCRIPT: &str = r#"
+(function () {
+    if (window.top !== window || !window.ipc) {
+        return;
+    }
+    var selectorFor = function (el) {
+        if (el.id) {
+            return '#' + CSS.escape(el.id);
+        }
+        var path = [];
+        for (var node = el; node && node.nodeType === 1; node = node.parentElement) {
+            var index = 1;
+            for (var sib = node.previousElementSibling; sib; sib = sib.previousElementSibling) {
+                if (sib.tagName === node.tagName) {
+                    index++;
+                }
+            }
+            path.unshift(node.tagName.toLowerCase() + ':nth-of-type(' + index + ')');
+        }
+        return path.join(' > ');
+    };
+    var fields = [];
+    document.querySelectorAll('input, textarea, select').forEach(function (el) {
+        var type = (el.type || '').toLowerCase();
+        if (type === 'password' || type === 'hidden' || type === 'file') {
+            return;
+        }
+        if (type === 'checkbox' || type === 'radio') {
+            if (el.checked !== el.defaultChecked) {
+                fields.push({ selector: selectorFor(el), checked: el.checked });
+            }
+        } else if (el.tagName === 'SELECT' || el.value !== el.defaultValue) {
+            fields.push({ selector: selectorFor(el), value: el.value });
+        }
+    });
+    window.ipc.send('pageStateCaptured', {
+        scroll_x: window.scrollX,
+        scroll_y: window.scrollY,
+        form_fields: fields
+    });
+})();
+"#;
+
+/// Decode page state reported by the capture script
+///
+/// # Arguments
+///
+/// * `tab_id` - Tab the reporting webview belongs to
+/// * `data` - Payload of a [`PAGE_STATE_CHANNEL`] message
+///
+/// # Errors
+///
+/// Returns `Error::Ipc` if the payload is not a page state object.
+pub fn parse_page_state(tab_id: TabId, data: &JsonValue) -> Result<PageStateCaptured> {
+    let state = serde_json::from_value(data.clone())
+        .map_err(|e| Error::Ipc(format!("Malformed page state: {}", e)))?;
+    Ok(PageStateCaptured { tab_id, state })
+}
+
+/// Script that puts `state` back into the freshly loaded page
+///
+/// The script runs once per document; fields that no longer exist are
+/// skipped. Each restored field fires `input` and `change` so page scripts
+/// see the value.
+///
+/// # Arguments
+///
+/// * `state` - State captured before the tab was hibernated
+///
+/// # Returns
+///
+/// JavaScript source to run in the tab's main frame
+pub fn page_state_restore_script(state: &PageState) -> String {
+    // serde_json escapes quotes and control characters; `<` is escaped too so
+    // a value can never close a surrounding script element
+    let json = serde_json::to_string(state)
+        .unwrap_or_else(|_| "{}".to_string())
+        .replace('<', "\\u003c");
+    format!(
+        r#"
+(function (state) {{
+    if (window.top !== window || window.__frankenPageStateRestored) {{
+        return;
+    }}
+    window.__frankenPageStateRestored = true;
+    (state.form_fields || []).forEach(function (field) {{
+        var el = document.querySelector(field.selector);
+        if (!el) {{
+            return;
+        }}
+        if (field.checked !== undefined) {{
+            el.checked = field.checked;
+        }} else {{
+            el.value = field.value;
+        }}
+        el.dispatchEvent(new Event('input', {{ bubbles: true }}));
+        el.dispatchEvent(new Event('change', {{ bubbles: true }}));
+    }});
+    window.scrollTo(state.scroll_x || 0, state.scroll_y || 0);
+}})({json});
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_capture_script_skips_sensitive_inputs() {
+        let script = page_state_capture_script();
+        assert!(script.contains("'pageStateCaptured'"));
+        assert!(script.contains("'password'"));
+        assert!(script.contains("'hidden'"));
+        assert!(script.contains("'file'"));
+    }
+
+    #[test]
+    fn test_parse_page_state() {
+        let captured = parse_page_state(
+            4,
+            &json!({
+                "scroll_x": 0,
+                "scroll_y": 1200.5,
+                "form_fields": [
+                    {"selector": "#q", "value": "rust"},
+                    {"selector": "#remember", "checked": true}
+                ]
+            }),
+        )
+        .unwrap();
+        assert_eq!(captured.tab_id, 4);
+        assert_eq!(captured.state.scroll_y, 1200.5);
+        assert_eq!(captured.state.form_fields[1].checked, Some(true));
+        assert_eq!(captured.state.form_fields[1].value, "");
+
+        assert!(matches!(
+            parse_page_state(4, &json!("nope")),
+            Err(Error::Ipc(_))
+        ));
+    }
+
+    #[test]
+    fn test_restore_script_embeds_state_safely() {
+        let state = PageState {
+            scroll_x: 0.0,
+            scroll_y: 640.0,
+            form_fields: vec![FormFieldState {
+                selector: "#note".to_string(),
+                value: "</script><b>'hi'".to_string(),
+                checked: None,
+            }],
+        };
+        let script = page_state_restore_script(&state);
+        assert!(script.contains("\"scroll_y\":640.0"));
+        assert!(script.contains("\\u003c/script>"));
+        assert!(!script.contains("</script>"));
+    }
+}
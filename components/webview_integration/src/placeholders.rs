@@ -0,0 +1,263 @@
+//! Click-to-load placeholders for blocked content
+//!
+//! Once a page has loaded, the placeholder script looks for the elements
+//! whose source is one of the tab's blocked URLs and swaps each for an
+//! inline box of the same size labelled "Blocked by FrankenBrowser — click
+//! to load". The original element is kept aside; clicking the box reports
+//! the URL over the IPC bridge, and once the engine has allowed it the
+//! restore script puts the element back and points it at the URL again so
+//! it is fetched anew.
+//!
+//! Only images, media, frames and embeds get placeholders. Blocked scripts
+//! and stylesheets are never offered (see
+//! [`ResourceType::shows_placeholder`]).
+//!
+//! Ad elements the page loads itself are hidden instead, by the ad
+//! blocker's element hiding CSS (see [`element_hiding_script`]).
+
+use crate::accessibility::style_element_script;
+use crate::errors::{Error, Result};
+use serde_json::{json, Value as JsonValue};
+use shared_types::{ResourceType, TabId};
+use std::sync::Arc;
+
+/// IPC channel carrying placeholder clicks
+pub const BLOCKED_CONTENT_LOAD_CHANNEL: &str = "blockedContentLoad";
+
+/// Text shown in each placeholder
+pub const PLACEHOLDER_LABEL: &str = "Blocked by FrankenBrowser \u{2014} click to load";
+
+/// Id of the `<style>` element holding the element hiding CSS
+pub const ELEMENT_HIDER_ID: &str = "adblock-element-hider";
+
+/// Receives placeholder clicks
+pub type BlockedContentLoadHandler = Arc<dyn Fn(BlockedContentLoad) + Send + Sync>;
+
+/// The user asked to load a blocked resource
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockedContentLoad {
+    /// Tab showing the placeholder
+    pub tab_id: TabId,
+    /// URL of the blocked resource
+    pub url: String,
+}
+
+/// Elements that load a resource of the given type
+///
+/// # Returns
+///
+/// Returns a CSS selector, or `None` for types that never get a
+/// placeholder.
+pub fn placeholder_selector(resource_type: &ResourceType) -> Option<&'static str> {
+    match resource_type {
+        ResourceType::Image => Some("img, input[type=image]"),
+        ResourceType::Media => Some("video, audio, source"),
+        ResourceType::Document => Some("iframe, frame"),
+        ResourceType::Other => Some("embed, object"),
+        _ => None,
+    }
+}
+
+/// Script that replaces the elements loading blocked URLs with placeholders
+///
+/// # Arguments
+///
+/// * `blocked` - Blocked URLs of the tab with their resource types
+///
+/// # Returns
+///
+/// Returns `None` if none of the URLs is of a type with a placeholder.
+pub fn placeholder_script(blocked: &[(String, ResourceType)]) -> Option<String> {
+    let targets: Vec<JsonValue> = blocked
+        .iter()
+        .filter_map(|(url, resource_type)| {
+            let selector = placeholder_selector(resource_type)?;
+            Some(json!({ "url": url, "selector": selector }))
+        })
+        .collect();
+    if targets.is_empty() {
+        return None;
+    }
+
+    let label = JsonValue::String(PLACEHOLDER_LABEL.to_string()).to_string();
+    Some(
+        PLACEHOLDER_SCRIPT
+            .replace("__TARGETS__", &JsonValue::Array(targets).to_string())
+            .replace("__LABEL__", &label),
+    )
+}
+
+const PLACEHOLDER_SCRIPT: &str = r#"
+(function () {
+    var targets = __TARGETS__;
+    var stash = window.__frankenBlocked = window.__frankenBlocked || {};
+
+    var sourceOf = function (el) {
+        return el.tagName === 'OBJECT' ? el.data : el.src;
+    };
+    var size = function (el, name) {
+        var attr = parseInt(el.getAttribute(name), 10);
+        if (attr > 0) {
+            return attr + 'px';
+        }
+        var rect = el.getBoundingClientRect();
+        var measured = name === 'width' ? rect.width : rect.height;
+        return measured > 0 ? measured + 'px' : (name === 'width' ? '240px' : '40px');
+    };
+
+    targets.forEach(function (target) {
+        document.querySelectorAll(target.selector).forEach(function (el) {
+            if (sourceOf(el) !== target.url) {
+                return;
+            }
+            if (el.tagName === 'SOURCE') {
+                el = el.parentElement;
+            }
+            if (!el || !el.parentNode) {
+                return;
+            }
+            var box = document.createElement('div');
+            box.setAttribute('data-franken-blocked', target.url);
+            box.title = target.url;
+            box.textContent = '⛔ ' + __LABEL__;
+            box.style.cssText = 'box-sizing:border-box;display:inline-flex;' +
+                'align-items:center;justify-content:center;overflow:hidden;' +
+                'cursor:pointer;font:12px sans-serif;color:#555;' +
+                'background:#f2f2f2;border:1px dashed #999;' +
+                'width:' + size(el, 'width') + ';height:' + size(el, 'height') + ';';
+            box.addEventListener('click', function (event) {
+                event.preventDefault();
+                event.stopPropagation();
+                if (window.ipc) {
+                    window.ipc.send('blockedContentLoad', { url: target.url });
+                }
+            });
+            el.replaceWith(box);
+            (stash[target.url] = stash[target.url] || []).push({ element: el, box: box });
+        });
+    });
+})();
+"#;
+
+/// Script that puts back the elements replaced for `url` and loads it
+///
+/// # Arguments
+///
+/// * `url` - Blocked URL the user chose to load
+pub fn restore_script(url: &str) -> String {
+    let literal = JsonValue::String(url.to_string()).to_string();
+    RESTORE_SCRIPT.replace("__URL__", &literal)
+}
+
+const RESTORE_SCRIPT: &str = r#"
+(function () {
+    var url = __URL__;
+    var stash = window.__frankenBlocked || {};
+    (stash[url] || []).forEach(function (entry) {
+        var el = entry.element;
+        entry.box.replaceWith(el);
+        if (el.tagName === 'OBJECT') {
+            el.data = url;
+        } else if (el.tagName === 'VIDEO' || el.tagName === 'AUDIO') {
+            el.load();
+        } else {
+            el.src = url;
+        }
+    });
+    delete stash[url];
+})();
+"#;
+
+/// Script that inserts the ad blocker's element hiding CSS into the page
+///
+/// Suits an initialization script (see
+/// [`WebViewConfig::with_element_hiding_css`](crate::WebViewConfig::with_element_hiding_css)),
+/// so ads are hidden before the page first renders.
+///
+/// # Arguments
+///
+/// * `css` - Stylesheet from the ad blocker's cosmetic filters
+pub fn element_hiding_script(css: &str) -> String {
+    style_element_script(ELEMENT_HIDER_ID, css)
+}
+
+/// Decode a placeholder click
+///
+/// # Arguments
+///
+/// * `tab_id` - Tab the reporting webview belongs to
+/// * `data` - Payload of a [`BLOCKED_CONTENT_LOAD_CHANNEL`] message
+///
+/// # Errors
+///
+/// Returns `Error::Ipc` if the payload has no string `url`.
+pub fn parse_blocked_content_load(tab_id: TabId, data: &JsonValue) -> Result<BlockedContentLoad> {
+    let url = data
+        .get("url")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::Ipc("Blocked content load missing 'url'".to_string()))?;
+
+    Ok(BlockedContentLoad {
+        tab_id,
+        url: url.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocked(file: &str, resource_type: ResourceType) -> (String, ResourceType) {
+        (format!("https://ads.example/{}", file), resource_type)
+    }
+
+    #[test]
+    fn test_placeholder_script_targets_element_types() {
+        let script = placeholder_script(&[
+            blocked("a.png", ResourceType::Image),
+            blocked("f.html", ResourceType::Document),
+            blocked("a.js", ResourceType::Script),
+            blocked("a.css", ResourceType::Stylesheet),
+        ])
+        .unwrap();
+
+        for expected in [
+            r#""url":"https://ads.example/a.png""#,
+            r#""selector":"img, input[type=image]""#,
+            r#""url":"https://ads.example/f.html""#,
+            r#""selector":"iframe, frame""#,
+        ] {
+            assert!(script.contains(expected), "{}", expected);
+        }
+        assert!(!script.contains("a.js"));
+        assert!(!script.contains("a.css"));
+        assert!(script.contains("Blocked by FrankenBrowser \u{2014} click to load"));
+        assert!(script.contains("'blockedContentLoad'"));
+    }
+
+    #[test]
+    fn test_no_script_without_placeholder_types() {
+        assert_eq!(placeholder_script(&[]), None);
+        let scripts_and_styles = [
+            blocked("a.js", ResourceType::Script),
+            blocked("a.css", ResourceType::Stylesheet),
+        ];
+        assert_eq!(placeholder_script(&scripts_and_styles), None);
+    }
+
+    #[test]
+    fn test_parse_blocked_content_load() {
+        let load = parse_blocked_content_load(2, &json!({"url": "https://ads.example/a.png"}));
+        assert_eq!(
+            load.unwrap(),
+            BlockedContentLoad {
+                tab_id: 2,
+                url: "https://ads.example/a.png".to_string(),
+            }
+        );
+        assert!(matches!(
+            parse_blocked_content_load(2, &json!({})),
+            Err(Error::Ipc(_))
+        ));
+    }
+}
@@ -3,10 +3,29 @@
 //! This module provides WebKit2GTK-specific WebView integration for Linux systems.
 
 use crate::errors::{Error, Result};
+use crate::input::PointerButton;
 use crate::platform::WebViewConfig;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
+#[cfg(feature = "gui")]
+use crate::input::{
+    KeyEventKind, KeyEventRaw, KeyModifiers, PointerEvent, PointerEventKind, Viewport,
+    WebViewBackend,
+};
+#[cfg(feature = "gui")]
+use crate::print::{Orientation, PageSize, PrintError, PrintOptions, PrintOutcome};
+#[cfg(feature = "gui")]
+use config_manager::FontSettings;
+#[cfg(feature = "gui")]
+use shared_types::{OriginUsage, StorageKind, TabId};
+#[cfg(feature = "gui")]
+use std::cell::RefCell;
+#[cfg(feature = "gui")]
+use std::collections::HashMap;
+#[cfg(feature = "gui")]
+use std::rc::Rc;
+
 /// Linux WebView wrapper using WebKit2GTK
 ///
 /// This struct wraps the wry WebView and provides Linux-specific functionality
@@ -227,7 +246,7 @@ impl LinuxWebView {
         {
             // Navigate using wry
             self.webview
-                .load_url(parsed_url.as_str())
+                .load_url(url)
                 .map_err(|e| Error::Navigation(format!("Failed to navigate: {}", e)))?;
         }
 
@@ -396,6 +415,647 @@ pub fn get_webkit_version() -> String {
     LinuxWebView::get_webkit_version()
 }
 
+/// GDK button number for a pointer button
+pub fn gdk_button(button: PointerButton) -> u32 {
+    match button {
+        PointerButton::Primary => 1,
+        PointerButton::Auxiliary => 2,
+        PointerButton::Secondary => 3,
+    }
+}
+
+/// GDK keyval (X keysym) for a DOM `KeyboardEvent.key` value
+///
+/// Single characters map through the keysym encoding of Unicode (Latin-1
+/// directly, everything else offset by `0x0100_0000`), and the named keys
+/// WebDriver sends map to their keysyms.
+///
+/// # Returns
+///
+/// `None` for named keys GDK has no keysym for.
+pub fn gdk_keyval(key: &str) -> Option<u32> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        let code = c as u32;
+        return Some(match code {
+            0x20..=0x7e | 0xa0..=0xff => code,
+            _ => 0x0100_0000 | code,
+        });
+    }
+    let keyval = match key {
+        "Backspace" => 0xff08,
+        "Tab" => 0xff09,
+        "Enter" => 0xff0d,
+        "Pause" => 0xff13,
+        "Escape" => 0xff1b,
+        "Home" => 0xff50,
+        "ArrowLeft" => 0xff51,
+        "ArrowUp" => 0xff52,
+        "ArrowRight" => 0xff53,
+        "ArrowDown" => 0xff54,
+        "PageUp" => 0xff55,
+        "PageDown" => 0xff56,
+        "End" => 0xff57,
+        "Insert" => 0xff63,
+        "ContextMenu" => 0xff67,
+        "Shift" => 0xffe1,
+        "Control" => 0xffe3,
+        "CapsLock" => 0xffe5,
+        "Alt" => 0xffe9,
+        "Meta" => 0xffeb,
+        "Delete" => 0xffff,
+        _ => {
+            let n: u32 = key.strip_prefix('F')?.parse().ok()?;
+            if !(1..=24).contains(&n) {
+                return None;
+            }
+            0xffbe + n - 1
+        }
+    };
+    Some(keyval)
+}
+
+#[cfg(feature = "gui")]
+thread_local! {
+    /// Webviews of the GTK backends, on the GTK thread
+    static GTK_WEBVIEWS: RefCell<HashMap<u64, webkit2gtk::WebView>> = RefCell::new(HashMap::new());
+}
+
+/// Native backend for a WebKitGTK webview
+///
+/// Synthesized input goes through GDK: each event is built with
+/// `gdk_event_new` on the webview's `GdkWindow`, carrying the seat's pointer
+/// or keyboard, and delivered with `gtk_main_do_event`, so the page sees it
+/// as trusted. GDK positions are logical pixels and GDK applies the monitor
+/// scale itself, so CSS pixels are only scaled by the page zoom.
+///
+/// Site data is enumerated and cleared through the webview's
+/// `WebKitWebsiteDataManager`. WebKit groups it by site name, normally the
+/// host, so the origins reported are bare host names. The per-site
+/// JavaScript and image toggles set the webview's `WebKitSettings`, which
+/// take effect from the next load, as do the font settings and the default
+/// charset. The user stylesheet is a user-level
+/// `WebKitUserStyleSheet` in every frame, so its `!important` rules win
+/// over the page's. Printing runs a `WebKitPrintOperation`: through the
+/// GTK print dialog, or straight to GTK's Print to File printer when the
+/// options name an output path.
+///
+/// GTK objects live on the GTK thread. Calls from that thread run
+/// directly; calls from other threads are queued on the default main
+/// context and wait for it. The backend drives one webview, so it serves
+/// whichever tab that webview is showing.
+#[cfg(feature = "gui")]
+pub struct GtkBackend {
+    /// Key of the webview in `GTK_WEBVIEWS`
+    id: u64,
+    /// Thread the webview was created on
+    gtk_thread: std::thread::ThreadId,
+}
+
+#[cfg(feature = "gui")]
+impl GtkBackend {
+    /// Create a backend for a wry webview
+    ///
+    /// Must be called on the GTK thread, where the webview was built.
+    pub fn new(webview: &wry::WebView) -> Self {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use wry::WebViewExtUnix;
+
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        GTK_WEBVIEWS.with(|webviews| webviews.borrow_mut().insert(id, webview.webview()));
+        Self {
+            id,
+            gtk_thread: std::thread::current().id(),
+        }
+    }
+
+    /// Run `f` with the webview on the GTK thread and return its result
+    fn with_webview<R, F>(&self, f: F) -> Result<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&webkit2gtk::WebView) -> Result<R> + Send + 'static,
+    {
+        let id = self.id;
+        let run = move || {
+            let webview = GTK_WEBVIEWS.with(|webviews| webviews.borrow().get(&id).cloned());
+            match webview {
+                Some(webview) => f(&webview),
+                None => Err(Error::Platform("webview was destroyed".to_string())),
+            }
+        };
+        if std::thread::current().id() == self.gtk_thread {
+            return run();
+        }
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        gtk::glib::idle_add_once(move || {
+            let _ = tx.send(run());
+        });
+        rx.recv()
+            .map_err(|_| Error::Platform("GTK main loop is not running".to_string()))?
+    }
+}
+
+#[cfg(feature = "gui")]
+impl Drop for GtkBackend {
+    fn drop(&mut self) {
+        let id = self.id;
+        let forget = move || {
+            GTK_WEBVIEWS.with(|webviews| webviews.borrow_mut().remove(&id));
+        };
+        if std::thread::current().id() == self.gtk_thread {
+            forget();
+        } else {
+            gtk::glib::idle_add_once(forget);
+        }
+    }
+}
+
+/// GDK modifier mask for the held modifier keys
+#[cfg(feature = "gui")]
+fn gdk_modifiers(modifiers: KeyModifiers) -> gtk::gdk::ModifierType {
+    use gtk::gdk::ModifierType;
+
+    let mut state = ModifierType::empty();
+    if modifiers.shift {
+        state |= ModifierType::SHIFT_MASK;
+    }
+    if modifiers.ctrl {
+        state |= ModifierType::CONTROL_MASK;
+    }
+    if modifiers.alt {
+        state |= ModifierType::MOD1_MASK;
+    }
+    if modifiers.meta {
+        state |= ModifierType::SUPER_MASK;
+    }
+    state
+}
+
+/// The webview's `GdkWindow` and the event time for a synthesized event
+#[cfg(feature = "gui")]
+fn event_target(webview: &webkit2gtk::WebView) -> Result<(gtk::gdk::Window, u32)> {
+    use gtk::prelude::WidgetExt;
+
+    let window = webview
+        .window()
+        .ok_or_else(|| Error::Platform("webview is not realized".to_string()))?;
+    let time = match gtk::current_event_time() {
+        0 => (gtk::glib::monotonic_time() / 1000) as u32,
+        time => time,
+    };
+    Ok((window, time))
+}
+
+/// Wait on the GTK thread for an asynchronous WebKit call to complete
+///
+/// `start` begins the call and hands its result to the callback it is
+/// given. A nested main loop runs meanwhile, as `gtk_dialog_run` does, so
+/// the webview keeps handling events.
+#[cfg(feature = "gui")]
+fn wait_for<T: Send + 'static>(start: impl FnOnce(Box<dyn FnOnce(T) + Send>)) -> T {
+    let main_loop = gtk::glib::MainLoop::new(None, false);
+    let slot = Arc::new(Mutex::new(None));
+    let done = {
+        let (main_loop, slot) = (main_loop.clone(), slot.clone());
+        Box::new(move |value: T| {
+            *slot.lock().unwrap() = Some(value);
+            main_loop.quit();
+        })
+    };
+    start(done);
+    // The call may have completed right away
+    if slot.lock().unwrap().is_none() {
+        main_loop.run();
+    }
+    let value = slot.lock().unwrap().take();
+    value.expect("main loop stopped before the call completed")
+}
+
+/// GTK page setup for the paper, orientation and margins of a print job
+#[cfg(feature = "gui")]
+fn page_setup(options: &PrintOptions) -> gtk::PageSetup {
+    let paper = match options.page_size {
+        PageSize::A4 => gtk::PaperSize::new(Some("iso_a4")),
+        PageSize::Letter => gtk::PaperSize::new(Some("na_letter")),
+        PageSize::Legal => gtk::PaperSize::new(Some("na_legal")),
+        PageSize::Custom {
+            width_mm,
+            height_mm,
+        } => gtk::PaperSize::new_custom("custom", "Custom", width_mm, height_mm, gtk::Unit::Mm),
+    };
+    let setup = gtk::PageSetup::new();
+    setup.set_paper_size(&paper);
+    setup.set_orientation(match options.orientation {
+        Orientation::Portrait => gtk::PageOrientation::Portrait,
+        Orientation::Landscape => gtk::PageOrientation::Landscape,
+    });
+    let margins = &options.margins;
+    setup.set_top_margin(margins.top_mm, gtk::Unit::Mm);
+    setup.set_right_margin(margins.right_mm, gtk::Unit::Mm);
+    setup.set_bottom_margin(margins.bottom_mm, gtk::Unit::Mm);
+    setup.set_left_margin(margins.left_mm, gtk::Unit::Mm);
+    setup
+}
+
+/// WebKit data types holding a storage kind
+#[cfg(feature = "gui")]
+fn website_data_types(kind: StorageKind) -> webkit2gtk::WebsiteDataTypes {
+    use webkit2gtk::WebsiteDataTypes;
+
+    match kind {
+        StorageKind::Cookies => WebsiteDataTypes::COOKIES,
+        StorageKind::LocalStorage => {
+            WebsiteDataTypes::LOCAL_STORAGE | WebsiteDataTypes::SESSION_STORAGE
+        }
+        StorageKind::IndexedDb => WebsiteDataTypes::INDEXEDDB_DATABASES,
+        StorageKind::CacheStorage => WebsiteDataTypes::DOM_CACHE,
+    }
+}
+
+/// Usage per site from WebKit's website data, largest first
+///
+/// Only webview-held kinds are reported; cookies are counted from the
+/// network stack's store.
+#[cfg(feature = "gui")]
+fn origin_usage(data: &[webkit2gtk::WebsiteData]) -> Vec<OriginUsage> {
+    let mut usage: Vec<OriginUsage> = data
+        .iter()
+        .filter_map(|site| {
+            let types = site.types();
+            let kinds: Vec<StorageKind> = crate::storage::WEBVIEW_KINDS
+                .into_iter()
+                .filter(|&kind| types.intersects(website_data_types(kind)))
+                .collect();
+            if kinds.is_empty() {
+                return None;
+            }
+            Some(OriginUsage {
+                origin: site.name()?.to_string(),
+                kinds,
+                bytes: site.size(types),
+                cookies: 0,
+            })
+        })
+        .collect();
+    crate::storage::sort_by_size(&mut usage);
+    usage
+}
+
+/// Site name WebKit files an origin's data under
+#[cfg(feature = "gui")]
+fn website_data_name(origin: &str) -> String {
+    url::Url::parse(origin)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| origin.to_string())
+}
+
+#[cfg(feature = "gui")]
+impl WebViewBackend for GtkBackend {
+    fn supports_trusted_input(&self) -> bool {
+        true
+    }
+
+    fn inject_pointer(&self, _tab_id: TabId, event: PointerEvent) -> Result<()> {
+        self.with_webview(move |webview| {
+            use gtk::gdk::{EventType, ModifierType};
+            use gtk::glib::translate::{IntoGlib, ToGlibPtr, ToGlibPtrMut};
+            use gtk::prelude::{SeatExt, WidgetExt};
+            use webkit2gtk::WebViewExt;
+
+            let (window, time) = event_target(webview)?;
+            let viewport = Viewport {
+                zoom: webview.zoom_level(),
+                scale_factor: 1.0,
+            };
+            let (x, y) = viewport.css_to_device(event.x, event.y);
+            let (_, origin_x, origin_y) = window.origin();
+            let button = gdk_button(event.button);
+            // Releases carry the button that was down, as X reports them
+            let state = match (event.kind, event.button) {
+                (PointerEventKind::Up, PointerButton::Primary) => ModifierType::BUTTON1_MASK,
+                (PointerEventKind::Up, PointerButton::Auxiliary) => ModifierType::BUTTON2_MASK,
+                (PointerEventKind::Up, PointerButton::Secondary) => ModifierType::BUTTON3_MASK,
+                _ => ModifierType::empty(),
+            };
+
+            let mut gdk_event = gtk::gdk::Event::new(match event.kind {
+                PointerEventKind::Move => EventType::MotionNotify,
+                PointerEventKind::Down => EventType::ButtonPress,
+                PointerEventKind::Up => EventType::ButtonRelease,
+            });
+            // SAFETY: the event was created with the type whose union
+            // member is written, and it takes over the window reference
+            unsafe {
+                let raw: *mut gtk::gdk::ffi::GdkEvent = gdk_event.to_glib_none_mut().0;
+                if event.kind == PointerEventKind::Move {
+                    let motion = &mut (*raw).motion;
+                    motion.window = window.to_glib_full();
+                    motion.time = time;
+                    motion.x = x;
+                    motion.y = y;
+                    motion.x_root = x + f64::from(origin_x);
+                    motion.y_root = y + f64::from(origin_y);
+                    motion.state = state.into_glib();
+                } else {
+                    let press = &mut (*raw).button;
+                    press.window = window.to_glib_full();
+                    press.time = time;
+                    press.x = x;
+                    press.y = y;
+                    press.x_root = x + f64::from(origin_x);
+                    press.y_root = y + f64::from(origin_y);
+                    press.state = state.into_glib();
+                    press.button = button;
+                }
+            }
+            let pointer = webview
+                .display()
+                .default_seat()
+                .and_then(|seat| seat.pointer());
+            gdk_event.set_device(pointer.as_ref());
+            gtk::main_do_event(&mut gdk_event);
+            Ok(())
+        })
+    }
+
+    fn inject_key(&self, _tab_id: TabId, event: KeyEventRaw) -> Result<()> {
+        self.with_webview(move |webview| {
+            use gtk::gdk::EventType;
+            use gtk::glib::translate::{IntoGlib, ToGlibPtr, ToGlibPtrMut};
+            use gtk::prelude::{SeatExt, WidgetExt};
+
+            let keyval = gdk_keyval(&event.key)
+                .ok_or_else(|| Error::Platform(format!("no GDK key for \"{}\"", event.key)))?;
+            let (window, time) = event_target(webview)?;
+            let display = webview.display();
+            let keycode = gtk::gdk::Keymap::for_display(&display)
+                .and_then(|keymap| {
+                    keymap
+                        .entries_for_keyval(keyval)
+                        .first()
+                        .map(|key| key.keycode())
+                })
+                .unwrap_or(0);
+            let is_modifier = matches!(event.key.as_str(), "Shift" | "Control" | "Alt" | "Meta");
+
+            let mut gdk_event = gtk::gdk::Event::new(match event.kind {
+                KeyEventKind::Down => EventType::KeyPress,
+                KeyEventKind::Up => EventType::KeyRelease,
+            });
+            // SAFETY: as for pointer events; the string stays null, which
+            // GDK accepts and frees as such
+            unsafe {
+                let raw: *mut gtk::gdk::ffi::GdkEvent = gdk_event.to_glib_none_mut().0;
+                let key = &mut (*raw).key;
+                key.window = window.to_glib_full();
+                key.time = time;
+                key.state = gdk_modifiers(event.modifiers).into_glib();
+                key.keyval = keyval;
+                key.hardware_keycode = keycode as u16;
+                key.is_modifier = u32::from(is_modifier);
+            }
+            let keyboard = display.default_seat().and_then(|seat| seat.keyboard());
+            gdk_event.set_device(keyboard.as_ref());
+            gtk::main_do_event(&mut gdk_event);
+            Ok(())
+        })
+    }
+
+    fn storage_usage(&self) -> Result<Vec<OriginUsage>> {
+        self.with_webview(|webview| {
+            use webkit2gtk::{WebViewExt, WebsiteDataManagerExt};
+
+            let manager = webview
+                .website_data_manager()
+                .ok_or_else(|| Error::Unsupported("storage enumeration".to_string()))?;
+            let types = crate::storage::WEBVIEW_KINDS
+                .into_iter()
+                .fold(webkit2gtk::WebsiteDataTypes::empty(), |types, kind| {
+                    types | website_data_types(kind)
+                });
+            wait_for(move |done| {
+                manager.fetch(types, None::<&gtk::gio::Cancellable>, move |result| {
+                    done(result.map(|data| origin_usage(&data)));
+                });
+            })
+            .map_err(|e| Error::Platform(format!("failed to fetch site data: {}", e)))
+        })
+    }
+
+    fn clear_origin_storage(&self, origin: &str, kinds: &[StorageKind]) -> Result<()> {
+        let name = website_data_name(origin);
+        let types = kinds
+            .iter()
+            .fold(webkit2gtk::WebsiteDataTypes::empty(), |types, &kind| {
+                types | website_data_types(kind)
+            });
+        self.with_webview(move |webview| {
+            use webkit2gtk::{WebViewExt, WebsiteDataManagerExt, WebsiteDataManagerExtManual};
+
+            let manager = webview
+                .website_data_manager()
+                .ok_or_else(|| Error::Unsupported("per-origin storage clearing".to_string()))?;
+            let remover = manager.clone();
+            wait_for(move |done| {
+                manager.fetch(types, None::<&gtk::gio::Cancellable>, move |result| {
+                    let data = match result {
+                        Ok(data) => data,
+                        Err(e) => return done(Err(e)),
+                    };
+                    let site: Vec<&webkit2gtk::WebsiteData> = data
+                        .iter()
+                        .filter(|site| site.name().as_deref() == Some(name.as_str()))
+                        .collect();
+                    if site.is_empty() {
+                        return done(Ok(()));
+                    }
+                    remover.remove(types, &site, None::<&gtk::gio::Cancellable>, done);
+                });
+            })
+            .map_err(|e| Error::Platform(format!("failed to clear site data: {}", e)))
+        })
+    }
+
+    fn set_javascript_enabled(&self, _tab_id: TabId, enabled: bool) -> Result<()> {
+        self.with_webview(move |webview| {
+            use webkit2gtk::{SettingsExt, WebViewExt};
+
+            let settings = webview
+                .settings()
+                .ok_or_else(|| Error::Unsupported("per-load JavaScript toggle".to_string()))?;
+            settings.set_enable_javascript(enabled);
+            Ok(())
+        })
+    }
+
+    fn set_images_enabled(&self, _tab_id: TabId, enabled: bool) -> Result<()> {
+        self.with_webview(move |webview| {
+            use webkit2gtk::{SettingsExt, WebViewExt};
+
+            let settings = webview
+                .settings()
+                .ok_or_else(|| Error::Unsupported("per-load image toggle".to_string()))?;
+            settings.set_auto_load_images(enabled);
+            Ok(())
+        })
+    }
+
+    fn set_user_stylesheet(&self, _tab_id: TabId, css: &str) -> Result<()> {
+        let css = css.to_string();
+        self.with_webview(move |webview| {
+            use webkit2gtk::{
+                UserContentInjectedFrames, UserContentManagerExt, UserStyleLevel, UserStyleSheet,
+                WebViewExt,
+            };
+
+            let manager = webview
+                .user_content_manager()
+                .ok_or_else(|| Error::Unsupported("user stylesheets".to_string()))?;
+            manager.remove_all_style_sheets();
+            if !css.is_empty() {
+                manager.add_style_sheet(&UserStyleSheet::new(
+                    &css,
+                    UserContentInjectedFrames::AllFrames,
+                    UserStyleLevel::User,
+                    &[],
+                    &[],
+                ));
+            }
+            Ok(())
+        })
+    }
+
+    fn set_font_settings(
+        &self,
+        _tab_id: TabId,
+        fonts: &FontSettings,
+        default_encoding: &str,
+    ) -> Result<()> {
+        let fonts = fonts.clone();
+        let encoding = default_encoding.to_string();
+        self.with_webview(move |webview| {
+            use webkit2gtk::{SettingsExt, WebViewExt};
+
+            let settings = webview
+                .settings()
+                .ok_or_else(|| Error::Unsupported("font settings".to_string()))?;
+            if !fonts.standard_family.is_empty() {
+                settings.set_default_font_family(&fonts.standard_family);
+            }
+            if !fonts.serif_family.is_empty() {
+                settings.set_serif_font_family(&fonts.serif_family);
+            }
+            if !fonts.sans_serif_family.is_empty() {
+                settings.set_sans_serif_font_family(&fonts.sans_serif_family);
+            }
+            if !fonts.monospace_family.is_empty() {
+                settings.set_monospace_font_family(&fonts.monospace_family);
+            }
+            if fonts.default_font_size > 0 {
+                settings.set_default_font_size(fonts.default_font_size);
+            }
+            if fonts.default_monospace_size > 0 {
+                settings.set_default_monospace_font_size(fonts.default_monospace_size);
+            }
+            settings.set_minimum_font_size(fonts.minimum_font_size);
+            if !encoding.is_empty() {
+                settings.set_default_charset(&encoding);
+            }
+            Ok(())
+        })
+    }
+
+    fn print(&self, _tab_id: TabId, options: PrintOptions) -> Result<PrintOutcome> {
+        options.validate()?;
+        self.with_webview(move |webview| {
+            use gtk::prelude::{Cast, WidgetExt};
+            use webkit2gtk::{
+                PrintOperation, PrintOperationExt, PrintOperationResponse, SettingsExt, WebViewExt,
+            };
+
+            if let Some(settings) = WebViewExt::settings(webview) {
+                settings.set_print_backgrounds(options.background_graphics);
+            }
+            let operation = PrintOperation::new(webview);
+            operation.set_page_setup(&page_setup(&options));
+            let print_settings = gtk::PrintSettings::new();
+            print_settings.set_scale(options.scale * 100.0);
+
+            let Some(path) = options.output_path else {
+                operation.set_print_settings(&print_settings);
+                let parent = webview
+                    .toplevel()
+                    .and_then(|widget| widget.downcast::<gtk::Window>().ok());
+                return match operation.run_dialog(parent.as_ref()) {
+                    PrintOperationResponse::Print => Ok(PrintOutcome::Printed),
+                    _ => Err(PrintError::Cancelled.into()),
+                };
+            };
+
+            // Print to File writes the PDF without a dialog
+            let uri = std::path::absolute(&path)
+                .ok()
+                .and_then(|path| url::Url::from_file_path(path).ok())
+                .ok_or_else(|| PrintError::Output(format!("{}: invalid path", path.display())))?;
+            print_settings.set_printer("Print to File");
+            print_settings.set(gtk::PRINT_SETTINGS_OUTPUT_FILE_FORMAT.as_str(), Some("pdf"));
+            print_settings.set(gtk::PRINT_SETTINGS_OUTPUT_URI.as_str(), Some(uri.as_str()));
+            operation.set_print_settings(&print_settings);
+            let job = operation.clone();
+            wait_for(move |done| {
+                // `failed` is followed by `finished`; report the first
+                let done = Rc::new(RefCell::new(Some(done)));
+                let on_failed = done.clone();
+                job.connect_failed(move |_, error| {
+                    if let Some(done) = on_failed.borrow_mut().take() {
+                        done(Err(error.to_string()));
+                    }
+                });
+                job.connect_finished(move |_| {
+                    if let Some(done) = done.borrow_mut().take() {
+                        done(Ok(()));
+                    }
+                });
+                job.print();
+            })
+            .map_err(PrintError::Failed)?;
+            drop(operation);
+
+            let bytes = std::fs::metadata(&path)
+                .map_err(|e| PrintError::Output(format!("{}: {}", path.display(), e)))?
+                .len();
+            Ok(PrintOutcome::SavedToFile { path, bytes })
+        })
+    }
+
+    fn load_document(&self, _tab_id: TabId, url: &str, html: &[u8]) -> Result<()> {
+        let url = url.to_string();
+        let bytes = gtk::glib::Bytes::from(html);
+        self.with_webview(move |webview| {
+            use webkit2gtk::WebViewExt;
+
+            webview.load_bytes(&bytes, Some("text/html"), None, Some(&url));
+            Ok(())
+        })
+    }
+
+    fn reload(&self, _tab_id: TabId, bypass_cache: bool) -> Result<()> {
+        self.with_webview(move |webview| {
+            use webkit2gtk::WebViewExt;
+
+            if bypass_cache {
+                webview.reload_bypass_cache();
+            } else {
+                webview.reload();
+            }
+            Ok(())
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -716,4 +1376,101 @@ mod tests {
         // Cleanup
         let _ = std::fs::remove_dir_all(&temp_dir);
     }
+
+    // ========================================
+    // Tests for GDK input synthesis
+    // ========================================
+
+    #[test]
+    fn test_gdk_button_numbers() {
+        assert_eq!(gdk_button(PointerButton::Primary), 1);
+        assert_eq!(gdk_button(PointerButton::Auxiliary), 2);
+        assert_eq!(gdk_button(PointerButton::Secondary), 3);
+    }
+
+    #[test]
+    fn test_gdk_keyval_characters() {
+        assert_eq!(gdk_keyval("a"), Some(0x61));
+        assert_eq!(gdk_keyval("A"), Some(0x41));
+        assert_eq!(gdk_keyval(" "), Some(0x20));
+        assert_eq!(gdk_keyval("é"), Some(0xe9));
+        assert_eq!(gdk_keyval("€"), Some(0x0100_20ac));
+    }
+
+    #[test]
+    fn test_gdk_keyval_named_keys() {
+        assert_eq!(gdk_keyval("Enter"), Some(0xff0d));
+        assert_eq!(gdk_keyval("Backspace"), Some(0xff08));
+        assert_eq!(gdk_keyval("ArrowLeft"), Some(0xff51));
+        assert_eq!(gdk_keyval("Shift"), Some(0xffe1));
+        assert_eq!(gdk_keyval("F1"), Some(0xffbe));
+        assert_eq!(gdk_keyval("F12"), Some(0xffc9));
+        assert_eq!(gdk_keyval("F25"), None);
+        assert_eq!(gdk_keyval("Fn"), None);
+        assert_eq!(gdk_keyval("Unidentified"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "gui")]
+    #[ignore] // Ignored by default - needs a display and WebKitGTK
+    fn test_gtk_backend_injects_trusted_input() {
+        use crate::input::{KeyEventKind, KeyEventRaw, PointerEvent, WebViewBackend};
+        use gtk::prelude::*;
+        use std::sync::mpsc;
+        use std::time::{Duration, Instant};
+        use wry::{WebViewBuilder, WebViewBuilderExtUnix};
+
+        gtk::init().unwrap();
+        let window = gtk::Window::new(gtk::WindowType::Toplevel);
+        window.set_default_size(400, 300);
+        let (tx, rx) = mpsc::channel();
+        let webview = WebViewBuilder::new()
+            .with_html(
+                r#"<body style="margin:0">
+<button id="b" style="width:200px;height:100px">b</button>
+<script>
+document.getElementById('b').addEventListener('click', e =>
+    window.ipc.postMessage('click:' + e.isTrusted));
+document.addEventListener('keydown', e =>
+    window.ipc.postMessage('key:' + e.key + ':' + e.isTrusted));
+window.ipc.postMessage('ready');
+</script>
+</body>"#,
+            )
+            .with_ipc_handler(move |request| {
+                let _ = tx.send(request.body().clone());
+            })
+            .build_gtk(&window)
+            .unwrap();
+        window.show_all();
+        let backend = GtkBackend::new(&webview);
+        assert!(backend.supports_trusted_input());
+
+        let context = gtk::glib::MainContext::default();
+        let next_message = || {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            loop {
+                while context.iteration(false) {}
+                if let Ok(message) = rx.try_recv() {
+                    return message;
+                }
+                assert!(Instant::now() < deadline, "page did not answer");
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        };
+        assert_eq!(next_message(), "ready");
+
+        for event in PointerEvent::click_sequence(50.0, 50.0) {
+            backend.inject_pointer(1, event).unwrap();
+        }
+        assert_eq!(next_message(), "click:true");
+
+        backend
+            .inject_key(1, KeyEventRaw::new(KeyEventKind::Down, "a", "KeyA"))
+            .unwrap();
+        backend
+            .inject_key(1, KeyEventRaw::new(KeyEventKind::Up, "a", "KeyA"))
+            .unwrap();
+        assert_eq!(next_message(), "key:a:true");
+    }
 }
@@ -8,6 +8,9 @@
 //! Each platform has its own module with a platform-specific WebView wrapper.
 
 use crate::errors::Result;
+use crate::input::{KeyEventRaw, PointerEvent, WebViewBackend};
+use crate::placeholders::element_hiding_script;
+use shared_types::TabId;
 use std::path::PathBuf;
 
 // Platform-specific modules with conditional compilation
@@ -30,6 +33,9 @@ pub use windows::WindowsWebView as PlatformWebView;
 #[cfg(target_os = "macos")]
 pub use macos::MacOSWebView as PlatformWebView;
 
+#[cfg(all(target_os = "linux", feature = "gui"))]
+pub use linux::GtkBackend;
+
 /// Configuration for WebView creation
 ///
 /// This struct contains all the configuration options for creating a WebView
@@ -118,6 +124,23 @@ impl WebViewConfig {
         self
     }
 
+    /// Hide ad elements with the ad blocker's element hiding CSS
+    ///
+    /// The stylesheet is added to the initialization script, after the
+    /// script set with [`with_init_script`](Self::with_init_script), which
+    /// must therefore be set first. Empty CSS changes nothing.
+    pub fn with_element_hiding_css(mut self, css: &str) -> Self {
+        if css.is_empty() {
+            return self;
+        }
+        let script = element_hiding_script(css);
+        self.init_script = Some(match self.init_script.take() {
+            Some(init_script) => format!("{}\n{}", init_script, script),
+            None => script,
+        });
+        self
+    }
+
     /// Set whether to enable local storage
     pub fn with_local_storage(mut self, enable: bool) -> Self {
         self.enable_local_storage = enable;
@@ -192,6 +215,74 @@ pub fn get_webview_version() -> String {
     return "Unknown".to_string();
 }
 
+/// Backend for webviews without a native handle
+///
+/// Used when no platform webview exists (headless builds) and on
+/// platforms without a native backend yet. With the `gui` feature on
+/// Linux, webviews get a [`GtkBackend`](linux::GtkBackend) instead, which
+/// synthesizes trusted input through GDK. `SendInput` to the WebView2
+/// child window on Windows and `NSEvent`s delivered to the `WKWebView` on
+/// macOS are not implemented, so this backend reports no trusted-input
+/// support and `inject_pointer`/`inject_key` return
+/// `Error::NotImplemented`. Callers, including the WebDriver Actions
+/// command, fall back to bridge dispatch via
+/// [`select_input_path`](crate::input::select_input_path).
+///
+/// The platform data managers are likewise out of reach without a native
+/// handle (the Linux backend goes through `WebKitWebsiteDataManager`), so
+/// storage enumeration and per-origin clearing report `Error::Unsupported`.
+///
+/// The same applies to the per-load JavaScript and image toggles
+/// (WebKitSettings `enable-javascript` and `auto-load-images` on Linux,
+/// which the Linux backend sets; `ICoreWebView2Settings::IsScriptEnabled`
+/// on Windows; `WKWebpagePreferences.allowsContentJavaScript` on macOS).
+/// New webviews honor [`WebViewConfig::enable_javascript`] at creation
+/// instead.
+///
+/// User stylesheets (`WebKitUserContentManager` on Linux, which the Linux
+/// backend uses; document-created scripts on Windows;
+/// `WKUserContentController` on macOS) are also not exposed; the
+/// accessibility stylesheet is injected by script instead.
+///
+/// Font settings map to WebKitSettings `default-font-family`,
+/// `serif-font-family`, `sans-serif-font-family`, `monospace-font-family`,
+/// `default-font-size`, `default-monospace-font-size`, `minimum-font-size`
+/// and `default-charset` on Linux, which the Linux backend sets, and to
+/// `WKPreferences.minimumFontSize` on macOS, which has no default-family or
+/// encoding preference. WebView2 has no font or encoding settings at all.
+/// None of these are reachable through wry, so font settings report
+/// `Error::Unsupported` and the minimum font size is enforced by the user
+/// stylesheet.
+///
+/// The native window belongs to the shell, which sets its title through
+/// tao; this backend reports `Error::Unsupported` for window titles.
+///
+/// Printing needs the print operation APIs (`webkit_print_operation` on
+/// Linux, which the Linux backend uses; `PrintAsync`/`PrintToPdfAsync` on
+/// Windows; `NSPrintOperation` and `createPDF` on macOS), which wry does
+/// not expose either. The shell opens the print dialog through wry's
+/// `WebView::print` instead; print-to-file reports `Error::Unsupported`.
+pub struct NativeInputBackend;
+
+impl WebViewBackend for NativeInputBackend {
+    fn supports_trusted_input(&self) -> bool {
+        false
+    }
+
+    fn inject_pointer(&self, _tab_id: TabId, _event: PointerEvent) -> Result<()> {
+        Err(crate::errors::Error::NotImplemented)
+    }
+
+    fn inject_key(&self, _tab_id: TabId, _event: KeyEventRaw) -> Result<()> {
+        Err(crate::errors::Error::NotImplemented)
+    }
+}
+
+/// Get the native input backend for the current platform
+pub fn native_input_backend() -> Box<dyn WebViewBackend> {
+    Box::new(NativeInputBackend)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -236,6 +327,21 @@ mod tests {
         assert_eq!(config.user_agent, Some("CustomAgent/1.0".to_string()));
     }
 
+    #[test]
+    fn test_webview_config_element_hiding_css_follows_init_script() {
+        let css = ".ad-banner { display: none !important; }";
+        let config = WebViewConfig::new()
+            .with_init_script("window.ready = true;".to_string())
+            .with_element_hiding_css(css);
+        let script = config.init_script.unwrap();
+        assert!(script.starts_with("window.ready = true;\n"));
+        assert!(script.contains(crate::ELEMENT_HIDER_ID));
+        assert!(script.contains(".ad-banner"));
+
+        let config = WebViewConfig::new().with_element_hiding_css("");
+        assert_eq!(config.init_script, None);
+    }
+
     #[test]
     fn test_webview_config_builder_cache_dir() {
         let path = PathBuf::from("/tmp/test_cache");
@@ -0,0 +1,362 @@
+//! Printing and print-to-PDF
+//!
+//! [`PrintOptions`] describe the printed page and are handed unchanged to
+//! [`WebViewBackend::print`](crate::WebViewBackend::print). Without an
+//! output path the platform print dialog is shown
+//! (`webkit_print_operation_run_dialog` on Linux, `PrintAsync` on Windows,
+//! `NSPrintOperation` on macOS); with one the page is written to that path
+//! as a PDF (`webkit_print_operation_print` with a file printer,
+//! `PrintToPdfAsync`, `WKWebView.createPDF`).
+
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// Smallest accepted print scale
+pub const MIN_PRINT_SCALE: f64 = 0.1;
+
+/// Largest accepted print scale
+pub const MAX_PRINT_SCALE: f64 = 2.0;
+
+/// Paper size
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PageSize {
+    /// ISO A4, 210 × 297 mm
+    A4,
+    /// US Letter, 215.9 × 279.4 mm
+    Letter,
+    /// US Legal, 215.9 × 355.6 mm
+    Legal,
+    /// Any other size, in portrait orientation
+    Custom {
+        /// Width in millimetres
+        width_mm: f64,
+        /// Height in millimetres
+        height_mm: f64,
+    },
+}
+
+impl PageSize {
+    /// Width and height in millimetres, portrait
+    pub fn dimensions_mm(self) -> (f64, f64) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::Letter => (215.9, 279.4),
+            PageSize::Legal => (215.9, 355.6),
+            PageSize::Custom {
+                width_mm,
+                height_mm,
+            } => (width_mm, height_mm),
+        }
+    }
+}
+
+/// Page orientation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    /// Taller than wide
+    Portrait,
+    /// Wider than tall
+    Landscape,
+}
+
+/// Page margins in millimetres
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Margins {
+    /// Top margin
+    pub top_mm: f64,
+    /// Right margin
+    pub right_mm: f64,
+    /// Bottom margin
+    pub bottom_mm: f64,
+    /// Left margin
+    pub left_mm: f64,
+}
+
+impl Margins {
+    /// The same margin on every side
+    pub fn uniform(mm: f64) -> Self {
+        Self {
+            top_mm: mm,
+            right_mm: mm,
+            bottom_mm: mm,
+            left_mm: mm,
+        }
+    }
+}
+
+impl Default for Margins {
+    fn default() -> Self {
+        Self::uniform(10.0)
+    }
+}
+
+/// How to print a page
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrintOptions {
+    /// Paper size
+    pub page_size: PageSize,
+    /// Page orientation
+    pub orientation: Orientation,
+    /// Page margins
+    pub margins: Margins,
+    /// Content scale, between [`MIN_PRINT_SCALE`] and [`MAX_PRINT_SCALE`]
+    pub scale: f64,
+    /// Print background colors and images
+    pub background_graphics: bool,
+    /// Write a PDF here instead of showing the print dialog
+    pub output_path: Option<PathBuf>,
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::A4,
+            orientation: Orientation::Portrait,
+            margins: Margins::default(),
+            scale: 1.0,
+            background_graphics: false,
+            output_path: None,
+        }
+    }
+}
+
+impl PrintOptions {
+    /// Default options (A4 portrait, 10 mm margins, print dialog)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Options that save the page as a PDF at `path`
+    pub fn to_file(path: impl Into<PathBuf>) -> Self {
+        Self::default().with_output_path(path)
+    }
+
+    /// Set the paper size
+    pub fn with_page_size(mut self, page_size: PageSize) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Set the orientation
+    pub fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Set the margins
+    pub fn with_margins(mut self, margins: Margins) -> Self {
+        self.margins = margins;
+        self
+    }
+
+    /// Set the content scale
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Set whether backgrounds are printed
+    pub fn with_background_graphics(mut self, enable: bool) -> Self {
+        self.background_graphics = enable;
+        self
+    }
+
+    /// Save to a PDF at `path` instead of showing the print dialog
+    pub fn with_output_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.output_path = Some(path.into());
+        self
+    }
+
+    /// Paper width and height in millimetres, after orientation
+    pub fn paper_mm(&self) -> (f64, f64) {
+        let (width, height) = self.page_size.dimensions_mm();
+        match self.orientation {
+            Orientation::Portrait => (width, height),
+            Orientation::Landscape => (height, width),
+        }
+    }
+
+    /// Check the options describe a printable page
+    ///
+    /// # Errors
+    ///
+    /// Returns `PrintError::InvalidOptions` if the paper size is not
+    /// positive, a margin is negative, the margins leave no printable
+    /// area, or the scale is out of range.
+    pub fn validate(&self) -> std::result::Result<(), PrintError> {
+        let (width, height) = self.paper_mm();
+        if width.is_nan() || height.is_nan() || width <= 0.0 || height <= 0.0 {
+            return Err(PrintError::InvalidOptions(format!(
+                "paper size {}x{} mm",
+                width, height
+            )));
+        }
+
+        let m = &self.margins;
+        if [m.top_mm, m.right_mm, m.bottom_mm, m.left_mm]
+            .iter()
+            .any(|margin| margin.is_nan() || *margin < 0.0)
+        {
+            return Err(PrintError::InvalidOptions(
+                "margins must not be negative".to_string(),
+            ));
+        }
+        if m.left_mm + m.right_mm >= width || m.top_mm + m.bottom_mm >= height {
+            return Err(PrintError::InvalidOptions(
+                "margins leave no printable area".to_string(),
+            ));
+        }
+
+        if !(MIN_PRINT_SCALE..=MAX_PRINT_SCALE).contains(&self.scale) {
+            return Err(PrintError::InvalidOptions(format!(
+                "scale {} outside {}-{}",
+                self.scale, MIN_PRINT_SCALE, MAX_PRINT_SCALE
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// What a print request did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrintOutcome {
+    /// The page was sent to a printer from the print dialog
+    Printed,
+    /// The page was saved as a PDF
+    SavedToFile {
+        /// Where the PDF was written
+        path: PathBuf,
+        /// Size of the PDF in bytes
+        bytes: u64,
+    },
+}
+
+/// Why a print request failed
+///
+/// Carried by `Error::Print`; the messages are short enough for a status
+/// bar.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PrintError {
+    /// The user closed the print dialog without printing
+    #[error("Printing cancelled")]
+    Cancelled,
+
+    /// No printer is configured
+    #[error("No printer available")]
+    NoPrinter,
+
+    /// The options describe no printable page
+    #[error("Invalid print options: {0}")]
+    InvalidOptions(String),
+
+    /// The PDF could not be written
+    #[error("Could not save PDF: {0}")]
+    Output(String),
+
+    /// The platform reported another failure
+    #[error("Printing failed: {0}")]
+    Failed(String),
+}
+
+/// Build the deterministic placeholder PDF the mock backend writes
+///
+/// The document records the paper size, margins, scale and background
+/// setting, so tests can check the options arrived intact. The same
+/// options always give the same bytes.
+///
+/// # Arguments
+///
+/// * `options` - Options the page was printed with
+pub fn fake_pdf(options: &PrintOptions) -> Vec<u8> {
+    let (width, height) = options.paper_mm();
+    let m = &options.margins;
+    format!(
+        "%PDF-1.4\n\
+         % FrankenBrowser mock print\n\
+         % paper {:.1}x{:.1}mm margins {:.1} {:.1} {:.1} {:.1} scale {:.2} background {}\n\
+         1 0 obj << /Type /Catalog /Pages 2 0 R >> endobj\n\
+         2 0 obj << /Type /Pages /Kids [] /Count 0 >> endobj\n\
+         trailer << /Root 1 0 R >>\n\
+         %%EOF\n",
+        width,
+        height,
+        m.top_mm,
+        m.right_mm,
+        m.bottom_mm,
+        m.left_mm,
+        options.scale,
+        options.background_graphics
+    )
+    .into_bytes()
+}
+
+/// Write a PDF to `path`
+///
+/// # Returns
+///
+/// Returns `PrintOutcome::SavedToFile` for the written file.
+///
+/// # Errors
+///
+/// Returns `PrintError::Output` if the file cannot be written.
+pub fn write_pdf(path: &Path, pdf: &[u8]) -> std::result::Result<PrintOutcome, PrintError> {
+    std::fs::write(path, pdf)
+        .map_err(|e| PrintError::Output(format!("{}: {}", path.display(), e)))?;
+    Ok(PrintOutcome::SavedToFile {
+        path: path.to_path_buf(),
+        bytes: pdf.len() as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_print_options_builders_and_orientation() {
+        let options = PrintOptions::to_file("/tmp/page.pdf")
+            .with_page_size(PageSize::Letter)
+            .with_orientation(Orientation::Landscape)
+            .with_margins(Margins::uniform(5.0))
+            .with_scale(0.8)
+            .with_background_graphics(true);
+
+        assert_eq!(options.paper_mm(), (279.4, 215.9));
+        assert_eq!(options.output_path, Some(PathBuf::from("/tmp/page.pdf")));
+        assert!(options.validate().is_ok());
+        assert!(PrintOptions::new().output_path.is_none());
+    }
+
+    #[test]
+    fn test_print_options_validation() {
+        let invalid = [
+            PrintOptions::new().with_scale(0.0),
+            PrintOptions::new().with_scale(2.5),
+            PrintOptions::new().with_margins(Margins::uniform(-1.0)),
+            PrintOptions::new().with_margins(Margins::uniform(105.0)),
+            PrintOptions::new().with_page_size(PageSize::Custom {
+                width_mm: 0.0,
+                height_mm: 100.0,
+            }),
+        ];
+        for options in invalid {
+            assert!(matches!(
+                options.validate(),
+                Err(PrintError::InvalidOptions(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_fake_pdf_is_deterministic() {
+        let options = PrintOptions::new().with_orientation(Orientation::Landscape);
+        let pdf = fake_pdf(&options);
+
+        assert_eq!(pdf, fake_pdf(&options));
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(String::from_utf8(pdf)
+            .unwrap()
+            .contains("paper 297.0x210.0mm"));
+    }
+}
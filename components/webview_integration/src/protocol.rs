@@ -0,0 +1,858 @@
+//! Wire protocol between the injected bridge and Rust
+//!
+//! Every message a page's scripts send to Rust travels in an envelope:
+//!
+//! ```json
+//! {"v": 2, "token": "…", "seq": 7, "type": "titleChanged", "data": {"title": "Inbox"}}
+//! ```
+//!
+//! [`InboundMessage`] is the schema: each variant is one message type, tagged
+//! by its wire name, with a typed payload. The bridge bootstrap script built
+//! by [`bridge_bootstrap_script`] is generated from the same list of types,
+//! so the JavaScript side cannot send a type Rust does not know.
+//!
+//! [`WireSession`] checks every envelope before anything is dispatched:
+//!
+//! - Input over [`MAX_BATCH_BYTES`], batches over [`MAX_BATCH_MESSAGES`]
+//!   and messages over [`MAX_MESSAGE_BYTES`] are refused unparsed.
+//! - The envelope must have exactly the fields above, with the right types.
+//! - The token must be the random one handed to the page's bootstrap when
+//!   it was injected. It lives in the bootstrap's closure, so a page cannot
+//!   post messages straight to the platform handler.
+//! - Sequence numbers must increase, so replayed and reordered messages are
+//!   dropped.
+//! - Unknown types and payloads with missing or mistyped fields are dropped.
+//!
+//! Version 1 was the unversioned `{channel, data, timestamp}` envelope,
+//! which carried no token and is no longer accepted. Messages from a newer
+//! bridge are accepted as long as their type is known here; fields this
+//! version does not know are ignored.
+//!
+//! The bootstrap makes `window.ipc` read-only so a page cannot intercept
+//! what the browser's own scripts send, but the page can still call
+//! `window.ipc.send` itself. Everything received is untrusted page input.
+
+use crate::forms::FORM_BODY_CAP;
+use crate::page_state::PageState;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Protocol version spoken by this build
+pub const PROTOCOL_VERSION: u64 = 2;
+
+/// Oldest protocol version accepted from a page
+pub const MIN_PROTOCOL_VERSION: u64 = 2;
+
+/// Largest single message, in bytes of JSON
+///
+/// Leaves room for a form submission at [`FORM_BODY_CAP`] after escaping.
+pub const MAX_MESSAGE_BYTES: usize = 2 * FORM_BODY_CAP;
+
+/// Largest input handed over at once, in bytes of JSON
+pub const MAX_BATCH_BYTES: usize = 4 * FORM_BODY_CAP;
+
+/// Most messages in one batch
+pub const MAX_BATCH_MESSAGES: usize = 64;
+
+/// Fields of an envelope
+const ENVELOPE_FIELDS: [&str; 5] = ["v", "token", "seq", "type", "data"];
+
+/// Why a message was dropped
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+    #[error("{0} bytes is over the {1}-byte limit")]
+    TooLarge(usize, usize),
+
+    #[error("batch of {0} messages is over the limit of {1}")]
+    BatchTooLarge(usize, usize),
+
+    #[error("malformed envelope: {0}")]
+    Malformed(String),
+
+    #[error("protocol version {0} is no longer supported")]
+    UnsupportedVersion(u64),
+
+    #[error("wrong bridge token")]
+    BadToken,
+
+    /// Replayed or reordered
+    #[error("sequence number {seq} does not follow {last}")]
+    OutOfOrder { seq: u64, last: u64 },
+
+    #[error("unknown message type '{0}'")]
+    UnknownType(String),
+
+    #[error("invalid '{kind}' payload: {reason}")]
+    InvalidPayload { kind: String, reason: String },
+}
+
+/// A message from a page's scripts, by type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum InboundMessage {
+    /// Console output
+    #[serde(rename = "console")]
+    Console {
+        level: String,
+        message: String,
+        url: Option<String>,
+    },
+    /// Uncaught error or unhandled promise rejection
+    #[serde(rename = "error")]
+    ScriptError {
+        #[serde(rename = "type")]
+        kind: String,
+        message: Option<String>,
+        filename: Option<String>,
+        lineno: Option<u64>,
+        colno: Option<u64>,
+        stack: Option<String>,
+        reason: Option<String>,
+        url: Option<String>,
+    },
+    #[serde(rename = "dom-ready")]
+    DomReady(PageEvent),
+    #[serde(rename = "page-load")]
+    PageLoad(PageEvent),
+    #[serde(rename = "before-unload")]
+    BeforeUnload(PageEvent),
+    #[serde(rename = "init-complete")]
+    InitComplete(PageEvent),
+    #[serde(rename = "navigate")]
+    Navigate { url: String },
+    #[serde(rename = "navigate-back")]
+    NavigateBack {},
+    #[serde(rename = "navigate-forward")]
+    NavigateForward {},
+    #[serde(rename = "reload")]
+    Reload {},
+    #[serde(rename = "open-devtools")]
+    OpenDevtools {},
+    /// A function made by `JavaScriptBridge::create_callback_bridge` was called
+    #[serde(rename = "callback")]
+    Callback {
+        #[serde(rename = "callbackId")]
+        callback_id: String,
+        args: Vec<JsonValue>,
+    },
+    /// Captured form submission; a `null` value is an empty file input
+    #[serde(rename = "formSubmit")]
+    FormSubmit {
+        method: String,
+        action: String,
+        enctype: String,
+        fields: Vec<(String, Option<String>)>,
+    },
+    /// Form submission left to the webview
+    #[serde(rename = "formSubmitNative")]
+    FormSubmitNative {
+        action: String,
+        enctype: String,
+        size: u64,
+        files: u64,
+    },
+    #[serde(rename = "titleChanged")]
+    TitleChanged { title: String },
+    #[serde(rename = "viewportLinks")]
+    ViewportLinks { hosts: Vec<String> },
+    #[serde(rename = "blockedContentLoad")]
+    BlockedContentLoad { url: String },
+    #[serde(rename = "findResult")]
+    FindResult { current: u64, total: u64 },
+    #[serde(rename = "linkHovered")]
+    LinkHovered { url: Option<String> },
+    #[serde(rename = "pageStateCaptured")]
+    PageStateCaptured(PageState),
+}
+
+/// Document lifecycle event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageEvent {
+    pub url: String,
+    pub title: Option<String>,
+    #[serde(rename = "readyState")]
+    pub ready_state: Option<String>,
+    #[serde(rename = "loadTime")]
+    pub load_time: Option<f64>,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+}
+
+impl InboundMessage {
+    /// Wire names of every message type
+    pub const TYPES: [&'static str; 20] = [
+        "console",
+        "error",
+        "dom-ready",
+        "page-load",
+        "before-unload",
+        "init-complete",
+        "navigate",
+        "navigate-back",
+        "navigate-forward",
+        "reload",
+        "open-devtools",
+        "callback",
+        "formSubmit",
+        "formSubmitNative",
+        "titleChanged",
+        "viewportLinks",
+        "blockedContentLoad",
+        "findResult",
+        "linkHovered",
+        "pageStateCaptured",
+    ];
+
+    /// Wire name of the message's type, which is also its bridge channel
+    pub fn message_type(&self) -> &'static str {
+        match self {
+            Self::Console { .. } => "console",
+            Self::ScriptError { .. } => "error",
+            Self::DomReady(_) => "dom-ready",
+            Self::PageLoad(_) => "page-load",
+            Self::BeforeUnload(_) => "before-unload",
+            Self::InitComplete(_) => "init-complete",
+            Self::Navigate { .. } => "navigate",
+            Self::NavigateBack {} => "navigate-back",
+            Self::NavigateForward {} => "navigate-forward",
+            Self::Reload {} => "reload",
+            Self::OpenDevtools {} => "open-devtools",
+            Self::Callback { .. } => "callback",
+            Self::FormSubmit { .. } => "formSubmit",
+            Self::FormSubmitNative { .. } => "formSubmitNative",
+            Self::TitleChanged { .. } => "titleChanged",
+            Self::ViewportLinks { .. } => "viewportLinks",
+            Self::BlockedContentLoad { .. } => "blockedContentLoad",
+            Self::FindResult { .. } => "findResult",
+            Self::LinkHovered { .. } => "linkHovered",
+            Self::PageStateCaptured(_) => "pageStateCaptured",
+        }
+    }
+
+    /// The payload, with only the fields of the schema
+    pub fn data(&self) -> JsonValue {
+        match serde_json::to_value(self) {
+            Ok(JsonValue::Object(mut message)) => message.remove("data").unwrap_or_default(),
+            _ => JsonValue::Null,
+        }
+    }
+}
+
+/// A decoded message, or why it was dropped
+pub type DecodedMessage = std::result::Result<InboundMessage, ProtocolError>;
+
+/// Receiving end of one page's bridge
+///
+/// Holds the token given to the page's bootstrap and the last sequence
+/// number accepted from it.
+#[derive(Debug)]
+pub struct WireSession {
+    token: String,
+    last_seq: u64,
+}
+
+impl WireSession {
+    /// Session with a fresh random token
+    pub fn new() -> Self {
+        Self {
+            token: Uuid::new_v4().simple().to_string(),
+            last_seq: 0,
+        }
+    }
+
+    /// Token the page's bootstrap must put on every message
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Start over for a new page that keeps the token
+    ///
+    /// Used where the bootstrap is fixed for the life of the webview, so
+    /// each page's sequence numbers start from 1 again.
+    pub fn restart(&mut self) {
+        self.last_seq = 0;
+    }
+
+    /// Decode what a page sent: one envelope or an array of them
+    ///
+    /// # Arguments
+    ///
+    /// * `raw` - JSON received from the platform IPC handler
+    ///
+    /// # Returns
+    ///
+    /// One result per envelope, in order; messages that fail a check are
+    /// `Err` and must be dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ProtocolError::TooLarge`, `ProtocolError::BatchTooLarge` or
+    /// `ProtocolError::Malformed` if the input as a whole is refused.
+    pub fn decode(&mut self, raw: &str) -> std::result::Result<Vec<DecodedMessage>, ProtocolError> {
+        if raw.len() > MAX_BATCH_BYTES {
+            return Err(ProtocolError::TooLarge(raw.len(), MAX_BATCH_BYTES));
+        }
+        let parsed: JsonValue =
+            serde_json::from_str(raw).map_err(|e| ProtocolError::Malformed(e.to_string()))?;
+
+        match parsed {
+            JsonValue::Array(batch) if batch.len() > MAX_BATCH_MESSAGES => Err(
+                ProtocolError::BatchTooLarge(batch.len(), MAX_BATCH_MESSAGES),
+            ),
+            JsonValue::Array(batch) => Ok(batch
+                .into_iter()
+                .map(|envelope| {
+                    let size = envelope.to_string().len();
+                    if size > MAX_MESSAGE_BYTES {
+                        return Err(ProtocolError::TooLarge(size, MAX_MESSAGE_BYTES));
+                    }
+                    self.decode_envelope(envelope)
+                })
+                .collect()),
+            JsonValue::Object(_) if raw.len() > MAX_MESSAGE_BYTES => {
+                Err(ProtocolError::TooLarge(raw.len(), MAX_MESSAGE_BYTES))
+            }
+            envelope @ JsonValue::Object(_) => Ok(vec![self.decode_envelope(envelope)]),
+            _ => Err(ProtocolError::Malformed(
+                "expected a message or an array of messages".to_string(),
+            )),
+        }
+    }
+
+    /// Check one envelope and decode its payload
+    fn decode_envelope(&mut self, envelope: JsonValue) -> DecodedMessage {
+        let JsonValue::Object(mut envelope) = envelope else {
+            return Err(ProtocolError::Malformed("not an object".to_string()));
+        };
+
+        // The unversioned envelope had no `v`
+        let version = match envelope.get("v") {
+            None => 1,
+            Some(v) => v
+                .as_u64()
+                .ok_or_else(|| ProtocolError::Malformed("'v' is not an integer".to_string()))?,
+        };
+        if version < MIN_PROTOCOL_VERSION {
+            return Err(ProtocolError::UnsupportedVersion(version));
+        }
+        if let Some(field) = envelope
+            .keys()
+            .find(|key| !ENVELOPE_FIELDS.contains(&key.as_str()))
+        {
+            return Err(ProtocolError::Malformed(format!(
+                "unexpected field '{}'",
+                field
+            )));
+        }
+
+        let token = envelope
+            .get("token")
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| ProtocolError::Malformed("'token' is not a string".to_string()))?;
+        if !tokens_match(token, &self.token) {
+            return Err(ProtocolError::BadToken);
+        }
+
+        let seq = envelope
+            .get("seq")
+            .and_then(|s| s.as_u64())
+            .ok_or_else(|| ProtocolError::Malformed("'seq' is not an integer".to_string()))?;
+        if seq <= self.last_seq {
+            return Err(ProtocolError::OutOfOrder {
+                seq,
+                last: self.last_seq,
+            });
+        }
+        // A genuine message with a bad payload still uses up its number
+        self.last_seq = seq;
+
+        let kind = match envelope.remove("type") {
+            Some(JsonValue::String(kind)) => kind,
+            _ => {
+                return Err(ProtocolError::Malformed(
+                    "'type' is not a string".to_string(),
+                ))
+            }
+        };
+        if !InboundMessage::TYPES.contains(&kind.as_str()) {
+            return Err(ProtocolError::UnknownType(kind));
+        }
+        let data = envelope
+            .remove("data")
+            .ok_or_else(|| ProtocolError::Malformed("missing 'data'".to_string()))?;
+        // Serde would also take a struct as an array of its fields
+        if !data.is_object() {
+            return Err(ProtocolError::InvalidPayload {
+                kind,
+                reason: "payload is not an object".to_string(),
+            });
+        }
+
+        let mut message = serde_json::Map::new();
+        message.insert("type".to_string(), JsonValue::String(kind.clone()));
+        message.insert("data".to_string(), data);
+        serde_json::from_value(JsonValue::Object(message)).map_err(|e| {
+            ProtocolError::InvalidPayload {
+                kind,
+                reason: e.to_string(),
+            }
+        })
+    }
+}
+
+impl Default for WireSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compare tokens without stopping at the first differing byte
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Script that sets up `window.ipc` in a document
+///
+/// Must run at document start, before the page's own scripts. It captures
+/// the platform IPC handler and `JSON.stringify` before the page can
+/// replace them, and defines `window.ipc` as a read-only property whose
+/// `send(type, data)` stamps each message with the protocol version,
+/// `token` and the next sequence number. Types not in
+/// [`InboundMessage::TYPES`] and messages over [`MAX_MESSAGE_BYTES`] are
+/// refused before they are sent. `window.ipc.on` and `window.ipc._dispatch`
+/// carry events from Rust to the page's listeners.
+///
+/// # Arguments
+///
+/// * `token` - Token of the [`WireSession`] that will receive the messages
+pub fn bridge_bootstrap_script(token: &str) -> String {
+    let types = serde_json::to_string(&InboundMessage::TYPES).unwrap_or_else(|_| "[]".into());
+    let token = serde_json::to_string(token).unwrap_or_else(|_| "\"\"".into());
+    BRIDGE_BOOTSTRAP_SCRIPT
+        .replace("__TOKEN__", &token)
+        .replace("__VERSION__", &PROTOCOL_VERSION.to_string())
+        .replace("__TYPES__", &types)
+        .replace("__MAX_BYTES__", &MAX_MESSAGE_BYTES.to_string())
+}
+
+const BRIDGE_BOOTSTRAP_SCRIPT: &str = r#"
+(function (token, version, types, maxBytes) {
+    var existing = Object.getOwnPropertyDescriptor(window, 'ipc');
+    if (existing && !existing.configurable) {
+        return;
+    }
+    var stringify = JSON.stringify;
+    var post = null;
+    if (window.webkit && window.webkit.messageHandlers && window.webkit.messageHandlers.ipc) {
+        var handler = window.webkit.messageHandlers.ipc;
+        post = function (body) { handler.postMessage(body); };
+    } else if (window.chrome && window.chrome.webview) {
+        var webview = window.chrome.webview;
+        post = function (body) { webview.postMessage(body); };
+    } else if (window.ipc && typeof window.ipc.postMessage === 'function') {
+        var native = window.ipc;
+        post = function (body) { native.postMessage(body); };
+    }
+    var seq = 0;
+    var listeners = {};
+
+    var bridge = Object.freeze({
+        version: version,
+        types: Object.freeze(types.slice()),
+        send: function (type, data) {
+            if (!post || types.indexOf(type) < 0) {
+                return false;
+            }
+            var body = stringify({
+                v: version, token: token, seq: seq + 1, type: type,
+                data: data === undefined ? {} : data
+            });
+            if (body.length > maxBytes) {
+                return false;
+            }
+            seq += 1;
+            post(body);
+            return true;
+        },
+        on: function (type, callback) {
+            (listeners[type] = listeners[type] || []).push(callback);
+        },
+        _dispatch: function (type, data) {
+            (listeners[type] || []).forEach(function (callback) {
+                try {
+                    callback(data);
+                } catch (e) {
+                    // One failing listener must not stop the others
+                }
+            });
+        }
+    });
+    Object.defineProperty(window, 'ipc', {
+        value: bridge, writable: false, configurable: false, enumerable: false
+    });
+})(__TOKEN__, __VERSION__, __TYPES__, __MAX_BYTES__);
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::javascript_bridge::JavaScriptBridge;
+    use proptest::prelude::*;
+    use serde_json::json;
+
+    fn envelope(token: &str, seq: u64, kind: &str, data: JsonValue) -> String {
+        json!({
+            "v": PROTOCOL_VERSION,
+            "token": token,
+            "seq": seq,
+            "type": kind,
+            "data": data,
+        })
+        .to_string()
+    }
+
+    fn decode_one(session: &mut WireSession, raw: &str) -> DecodedMessage {
+        let mut decoded = session.decode(raw)?;
+        assert_eq!(decoded.len(), 1);
+        decoded.remove(0)
+    }
+
+    /// Types the browser's own scripts send
+    fn sent_types(script: &str) -> Vec<String> {
+        script
+            .split("ipc.send('")
+            .skip(1)
+            .filter_map(|rest| rest.split('\'').next())
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_schema_covers_every_script() {
+        let blocked = [(
+            "https://ads.example/a.png".to_string(),
+            shared_types::ResourceType::Image,
+        )];
+        let scripts = [
+            JavaScriptBridge::new().generate_ipc_script(),
+            crate::title::title_observer_script().to_string(),
+            crate::dns_prefetch::viewport_links_script(),
+            crate::forms::form_capture_script(FORM_BODY_CAP),
+            crate::page_state::page_state_capture_script().to_string(),
+            crate::placeholders::placeholder_script(&blocked).unwrap(),
+            crate::find::find_script("cat", &Default::default()),
+            crate::hover::link_hover_script().to_string(),
+            JavaScriptBridge::new().create_callback_bridge("cb"),
+        ];
+        let sent: Vec<String> = scripts.iter().flat_map(|s| sent_types(s)).collect();
+        assert!(sent.len() > 15);
+        for kind in &sent {
+            assert!(InboundMessage::TYPES.contains(&kind.as_str()), "{}", kind);
+        }
+
+        // Every type decodes and keeps its wire name
+        let mut session = WireSession::new();
+        let samples = [
+            InboundMessage::TitleChanged {
+                title: "Inbox".to_string(),
+            },
+            InboundMessage::NavigateBack {},
+            InboundMessage::PageStateCaptured(PageState::default()),
+            InboundMessage::FormSubmit {
+                method: "POST".to_string(),
+                action: "https://example.com/".to_string(),
+                enctype: "multipart/form-data".to_string(),
+                fields: vec![
+                    ("a".to_string(), Some("1".to_string())),
+                    ("f".to_string(), None),
+                ],
+            },
+        ];
+        for (seq, message) in samples.into_iter().enumerate() {
+            let kind = message.message_type();
+            assert!(InboundMessage::TYPES.contains(&kind));
+            let raw = envelope(session.token(), seq as u64 + 1, kind, message.data());
+            assert_eq!(decode_one(&mut session, &raw), Ok(message));
+        }
+    }
+
+    #[test]
+    fn test_version_negotiation() {
+        let mut session = WireSession::new();
+
+        // The unversioned bridge of older pages is refused
+        let legacy = json!({
+            "channel": "titleChanged",
+            "data": {"title": "Inbox"},
+            "timestamp": 1_700_000_000_000_u64,
+        });
+        assert_eq!(
+            decode_one(&mut session, &legacy.to_string()),
+            Err(ProtocolError::UnsupportedVersion(1))
+        );
+        let old = json!({
+            "v": 1, "token": session.token(), "seq": 1,
+            "type": "titleChanged", "data": {"title": "Inbox"},
+        });
+        assert_eq!(
+            decode_one(&mut session, &old.to_string()),
+            Err(ProtocolError::UnsupportedVersion(1))
+        );
+
+        // A newer bridge is understood as far as this version goes
+        let newer = json!({
+            "v": PROTOCOL_VERSION + 1, "token": session.token(), "seq": 1,
+            "type": "titleChanged", "data": {"title": "Inbox", "direction": "rtl"},
+        });
+        let message = decode_one(&mut session, &newer.to_string()).unwrap();
+        assert_eq!(message.data(), json!({"title": "Inbox"}));
+        let unknown = json!({
+            "v": PROTOCOL_VERSION + 1, "token": session.token(), "seq": 2,
+            "type": "selectionChanged", "data": {},
+        });
+        assert_eq!(
+            decode_one(&mut session, &unknown.to_string()),
+            Err(ProtocolError::UnknownType("selectionChanged".to_string()))
+        );
+
+        // The page learns the version and types from its bootstrap
+        let script = bridge_bootstrap_script(session.token());
+        assert!(script.contains(&format!(", {}, [\"console\"", PROTOCOL_VERSION)));
+        assert!(script.contains("\"pageStateCaptured\"]"));
+        assert!(!script.contains("__"));
+    }
+
+    #[test]
+    fn test_strict_validation_drops_bad_messages() {
+        let mut session = WireSession::new();
+        let token = session.token().to_string();
+        let cases = [
+            (json!({"title": 5}), "titleChanged"),
+            (json!({}), "titleChanged"),
+            (json!({"hosts": "a.example"}), "viewportLinks"),
+            (json!({"hosts": [1]}), "viewportLinks"),
+            (json!([]), "navigate-back"),
+            (
+                json!({"action": "/", "enctype": "x", "size": -1, "files": 0}),
+                "formSubmitNative",
+            ),
+            (json!({"scroll_y": "top"}), "pageStateCaptured"),
+        ];
+        for (seq, (data, kind)) in cases.into_iter().enumerate() {
+            let raw = envelope(session.token(), seq as u64 + 1, kind, data);
+            assert!(
+                matches!(
+                    decode_one(&mut session, &raw),
+                    Err(ProtocolError::InvalidPayload { .. })
+                ),
+                "{}",
+                raw
+            );
+        }
+
+        let malformed = [
+            json!({"v": 2, "token": token, "seq": 20, "type": "reload"}),
+            json!({"v": 2, "token": token, "seq": 21, "type": "reload", "data": {}, "extra": 1}),
+            json!({"v": "2", "token": token, "seq": 22, "type": "reload", "data": {}}),
+            json!({"v": 2, "token": token, "seq": -1, "type": "reload", "data": {}}),
+            json!({"v": 2, "token": token, "seq": 23, "type": 7, "data": {}}),
+            json!({"v": 2, "seq": 24, "type": "reload", "data": {}}),
+        ];
+        for raw in malformed {
+            assert!(
+                matches!(
+                    decode_one(&mut session, &raw.to_string()),
+                    Err(ProtocolError::Malformed(_))
+                ),
+                "{}",
+                raw
+            );
+        }
+
+        // Whole inputs that are refused
+        assert!(matches!(
+            session.decode("not json"),
+            Err(ProtocolError::Malformed(_))
+        ));
+        assert!(matches!(
+            session.decode("\"reload\""),
+            Err(ProtocolError::Malformed(_))
+        ));
+        let big = envelope(
+            session.token(),
+            30,
+            "titleChanged",
+            json!({"title": "x".repeat(MAX_MESSAGE_BYTES)}),
+        );
+        assert!(matches!(
+            session.decode(&big),
+            Err(ProtocolError::TooLarge(_, MAX_MESSAGE_BYTES))
+        ));
+        let huge = format!("[{}]", "0,".repeat(MAX_BATCH_BYTES / 2));
+        assert!(matches!(
+            session.decode(&huge),
+            Err(ProtocolError::TooLarge(_, MAX_BATCH_BYTES))
+        ));
+        let many = JsonValue::Array(vec![json!({}); MAX_BATCH_MESSAGES + 1]);
+        assert_eq!(
+            session.decode(&many.to_string()),
+            Err(ProtocolError::BatchTooLarge(
+                MAX_BATCH_MESSAGES + 1,
+                MAX_BATCH_MESSAGES
+            ))
+        );
+
+        // An oversized message in a batch is dropped on its own
+        let batch = format!(
+            "[{},{}]",
+            big,
+            envelope(session.token(), 31, "reload", json!({}))
+        );
+        let decoded = session.decode(&batch).unwrap();
+        assert!(matches!(decoded[0], Err(ProtocolError::TooLarge(..))));
+        assert_eq!(decoded[1], Ok(InboundMessage::Reload {}));
+    }
+
+    #[test]
+    fn test_token_enforcement() {
+        let mut session = WireSession::new();
+        let other = WireSession::new();
+        assert_ne!(session.token(), other.token());
+        assert_eq!(session.token().len(), 32);
+
+        let spoofed = envelope(
+            other.token(),
+            1,
+            "navigate",
+            json!({"url": "https://evil.example/"}),
+        );
+        assert_eq!(
+            decode_one(&mut session, &spoofed),
+            Err(ProtocolError::BadToken)
+        );
+        let guessed = spoofed.replace(other.token(), "");
+        assert_eq!(
+            decode_one(&mut session, &guessed),
+            Err(ProtocolError::BadToken)
+        );
+        // Rejected messages do not use up sequence numbers
+        let genuine = envelope(
+            session.token(),
+            1,
+            "navigate",
+            json!({"url": "https://example.com/"}),
+        );
+        assert!(decode_one(&mut session, &genuine).is_ok());
+
+        // The token only appears in the bootstrap, as a string literal
+        let script = bridge_bootstrap_script(session.token());
+        assert!(script.contains(&format!("(\"{}\", ", session.token())));
+        assert!(script.contains("configurable: false"));
+    }
+
+    #[test]
+    fn test_sequence_handling() {
+        let mut session = WireSession::new();
+        let token = session.token().to_string();
+        let reload = |seq: u64| envelope(&token, seq, "reload", json!({}));
+
+        assert!(decode_one(&mut session, &reload(1)).is_ok());
+        assert_eq!(
+            decode_one(&mut session, &reload(1)),
+            Err(ProtocolError::OutOfOrder { seq: 1, last: 1 })
+        );
+        // Gaps are fine, going back is not
+        assert!(decode_one(&mut session, &reload(3)).is_ok());
+        assert_eq!(
+            decode_one(&mut session, &reload(2)),
+            Err(ProtocolError::OutOfOrder { seq: 2, last: 3 })
+        );
+
+        // A bad payload still uses up its number
+        let bad = envelope(session.token(), 4, "titleChanged", json!({}));
+        assert!(decode_one(&mut session, &bad).is_err());
+        assert!(decode_one(&mut session, &reload(4)).is_err());
+
+        // Batches are checked in order
+        let batch = format!("[{},{},{}]", reload(6), reload(5), reload(7));
+        let decoded = session.decode(&batch).unwrap();
+        assert!(decoded[0].is_ok());
+        assert!(decoded[1].is_err());
+        assert!(decoded[2].is_ok());
+
+        // A new page starts counting again
+        session.restart();
+        assert!(decode_one(&mut session, &reload(1)).is_ok());
+    }
+
+    fn arbitrary_json() -> impl Strategy<Value = JsonValue> {
+        let leaf = prop_oneof![
+            Just(JsonValue::Null),
+            any::<bool>().prop_map(JsonValue::from),
+            any::<i32>().prop_map(JsonValue::from),
+            // Whole numbers, which survive a round trip through JSON exactly
+            (-1e6f64..1e6).prop_map(|f| JsonValue::from(f.trunc())),
+            ".{0,12}".prop_map(JsonValue::from),
+        ];
+        leaf.prop_recursive(4, 48, 6, |inner| {
+            let key = prop_oneof![
+                Just("v".to_string()),
+                Just("token".to_string()),
+                Just("seq".to_string()),
+                Just("type".to_string()),
+                Just("data".to_string()),
+                Just("title".to_string()),
+                Just("hosts".to_string()),
+                "[a-z]{1,8}",
+            ];
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..6).prop_map(JsonValue::Array),
+                prop::collection::btree_map(key, inner, 0..6)
+                    .prop_map(|map| JsonValue::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(256))]
+
+        #[test]
+        fn fuzz_arbitrary_input_never_panics(raw in ".{0,256}") {
+            let _ = WireSession::new().decode(&raw);
+        }
+
+        #[test]
+        fn fuzz_arbitrary_json_never_panics(value in arbitrary_json()) {
+            let _ = WireSession::new().decode(&value.to_string());
+        }
+
+        #[test]
+        fn fuzz_envelopes_with_arbitrary_payloads(
+            kind in prop::sample::select(InboundMessage::TYPES.to_vec()),
+            seq in any::<u64>(),
+            data in arbitrary_json(),
+        ) {
+            let mut session = WireSession::new();
+            let raw = envelope(session.token(), seq, kind, data);
+            match session.decode(&raw).unwrap().remove(0) {
+                // What gets through decodes the same way again
+                Ok(message) => {
+                    prop_assert_eq!(message.message_type(), kind);
+                    let mut again = WireSession::new();
+                    let raw = envelope(again.token(), 1, kind, message.data());
+                    prop_assert_eq!(again.decode(&raw).unwrap().remove(0), Ok(message));
+                }
+                Err(ProtocolError::OutOfOrder { seq: 0, last: 0 }) => prop_assert_eq!(seq, 0),
+                Err(ProtocolError::InvalidPayload { .. }) => {}
+                Err(e) => prop_assert!(false, "unexpected rejection: {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Per-origin site data reporting
+//!
+//! Webview storage (localStorage, IndexedDB, Cache API) is enumerated through
+//! the [`WebViewBackend`](crate::WebViewBackend), backed by the platform data
+//! managers (WebKitWebsiteDataManager, WebView2 profile, WKWebsiteDataStore).
+//! Cookies live in the network stack's cookie store and are merged in by
+//! count with [`merge_cookie_counts`].
+
+use shared_types::{OriginUsage, StorageKind};
+use std::collections::HashMap;
+
+/// Storage kinds held by the webview (everything except cookies)
+pub const WEBVIEW_KINDS: [StorageKind; 3] = [
+    StorageKind::LocalStorage,
+    StorageKind::IndexedDb,
+    StorageKind::CacheStorage,
+];
+
+/// Merge cookie-store counts into webview usage
+///
+/// Origins that only hold cookies are added with zero bytes. The result is
+/// sorted with [`sort_by_size`].
+///
+/// # Arguments
+///
+/// * `usage` - Usage reported by the webview backend
+/// * `cookie_counts` - Cookie count per origin
+///
+/// # Returns
+///
+/// Returns the merged usage, largest origin first.
+pub fn merge_cookie_counts(
+    mut usage: Vec<OriginUsage>,
+    cookie_counts: &HashMap<String, u32>,
+) -> Vec<OriginUsage> {
+    for (origin, &count) in cookie_counts {
+        if count == 0 {
+            continue;
+        }
+        let index = match usage.iter().position(|u| &u.origin == origin) {
+            Some(index) => index,
+            None => {
+                usage.push(OriginUsage {
+                    origin: origin.clone(),
+                    kinds: Vec::new(),
+                    bytes: 0,
+                    cookies: 0,
+                });
+                usage.len() - 1
+            }
+        };
+        let entry = &mut usage[index];
+        entry.cookies = count;
+        if !entry.kinds.contains(&StorageKind::Cookies) {
+            entry.kinds.insert(0, StorageKind::Cookies);
+        }
+    }
+
+    sort_by_size(&mut usage);
+    usage
+}
+
+/// Sort usage largest first
+///
+/// Orders by bytes, then cookie count (both descending), then origin.
+pub fn sort_by_size(usage: &mut [OriginUsage]) {
+    usage.sort_by(|a, b| {
+        b.bytes
+            .cmp(&a.bytes)
+            .then(b.cookies.cmp(&a.cookies))
+            .then_with(|| a.origin.cmp(&b.origin))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(origin: &str, bytes: u64) -> OriginUsage {
+        OriginUsage {
+            origin: origin.to_string(),
+            kinds: vec![StorageKind::LocalStorage],
+            bytes,
+            cookies: 0,
+        }
+    }
+
+    #[test]
+    fn test_sort_by_size_largest_first() {
+        let mut list = vec![
+            usage("https://b.com", 10),
+            usage("https://c.com", 500),
+            usage("https://a.com", 10),
+        ];
+        sort_by_size(&mut list);
+
+        let origins: Vec<_> = list.iter().map(|u| u.origin.as_str()).collect();
+        assert_eq!(
+            origins,
+            vec!["https://c.com", "https://a.com", "https://b.com"]
+        );
+    }
+
+    #[test]
+    fn test_merge_cookie_counts_existing_and_new_origins() {
+        let mut cookies = HashMap::new();
+        cookies.insert("https://a.com".to_string(), 3);
+        cookies.insert("https://cookie-only.com".to_string(), 7);
+        cookies.insert("https://empty.com".to_string(), 0);
+
+        let merged = merge_cookie_counts(vec![usage("https://a.com", 100)], &cookies);
+
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].origin, "https://a.com");
+        assert_eq!(merged[0].cookies, 3);
+        assert_eq!(
+            merged[0].kinds,
+            vec![StorageKind::Cookies, StorageKind::LocalStorage]
+        );
+        assert_eq!(merged[1].origin, "https://cookie-only.com");
+        assert_eq!(merged[1].bytes, 0);
+        assert_eq!(merged[1].kinds, vec![StorageKind::Cookies]);
+    }
+}
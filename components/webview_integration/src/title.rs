@@ -0,0 +1,106 @@
+//! Page title tracking
+//!
+//! The title observer script reports the main frame's `document.title` over
+//! the IPC bridge once the page has loaded and again whenever the page
+//! changes it, so the tab strip and the native window title follow pages
+//! that retitle themselves (unread counters, media players, ...).
+
+use crate::errors::{Error, Result};
+use serde_json::Value as JsonValue;
+use shared_types::TabId;
+use std::sync::Arc;
+
+/// IPC channel carrying title changes
+pub const TITLE_CHANGED_CHANNEL: &str = "titleChanged";
+
+/// Receives page title changes
+pub type TitleChangedHandler = Arc<dyn Fn(TitleChanged) + Send + Sync>;
+
+/// A tab's page title changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TitleChanged {
+    /// Tab whose page changed its title
+    pub tab_id: TabId,
+    /// New value of `document.title` (may be empty)
+    pub title: String,
+}
+
+/// Script that reports the main frame's title and every change to it
+pub fn title_observer_script() -> &'static str {
+    TITLE_OBSERVER_SCRIPT
+}
+
+const TITLE_OBSERVER_SCRIPT: &str = r#"
+(function () {
+    if (window.top !== window || window.__frankenTitleObserver) {
+        return;
+    }
+    window.__frankenTitleObserver = true;
+    var last = null;
+
+    var report = function () {
+        var title = document.title;
+        if (title === last || !window.ipc) {
+            return;
+        }
+        last = title;
+        window.ipc.send('titleChanged', { title: title });
+    };
+
+    new MutationObserver(report).observe(document, {
+        subtree: true, childList: true, characterData: true
+    });
+    report();
+})();
+"#;
+
+/// Decode a title change reported by the observer script
+///
+/// # Arguments
+///
+/// * `tab_id` - Tab the reporting webview belongs to
+/// * `data` - Payload of a [`TITLE_CHANGED_CHANNEL`] message
+///
+/// # Errors
+///
+/// Returns `Error::Ipc` if the payload has no string `title`.
+pub fn parse_title_changed(tab_id: TabId, data: &JsonValue) -> Result<TitleChanged> {
+    let title = data
+        .get("title")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| Error::Ipc("Title change missing 'title'".to_string()))?;
+
+    Ok(TitleChanged {
+        tab_id,
+        title: title.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_observer_script_reports_on_channel() {
+        let script = title_observer_script();
+        assert!(script.contains("'titleChanged'"));
+        assert!(script.contains("MutationObserver"));
+    }
+
+    #[test]
+    fn test_parse_title_changed() {
+        let change = parse_title_changed(3, &json!({"title": "(2) Inbox"})).unwrap();
+        assert_eq!(
+            change,
+            TitleChanged {
+                tab_id: 3,
+                title: "(2) Inbox".to_string(),
+            }
+        );
+        assert!(matches!(
+            parse_title_changed(3, &json!({})),
+            Err(Error::Ipc(_))
+        ));
+    }
+}
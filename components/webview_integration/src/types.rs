@@ -1,9 +1,42 @@
 //! Type definitions for WebView integration
 
+use crate::accessibility::{stylesheet_injector_script, UserStylesheet};
+use crate::dns_prefetch::{
+    parse_viewport_links, viewport_links_script, ViewportLinksHandler, VIEWPORT_LINKS_CHANNEL,
+};
+use crate::document_protocol::{DocumentProtocol, DocumentStream};
 use crate::errors::{Error, Result};
-use crate::javascript_bridge::JavaScriptBridge;
+use crate::find::{
+    find_script, find_step_script, parse_find_report, stop_finding_script, FindReportHandler,
+    FIND_RESULT_CHANNEL,
+};
+use crate::forms::{
+    describe_native_submission, form_capture_script, parse_form_submission, FormSubmitHandler,
+    FORM_BODY_CAP, FORM_NATIVE_CHANNEL, FORM_SUBMIT_CHANNEL,
+};
+use crate::hover::{
+    link_hover_script, parse_link_hovered, LinkHoveredHandler, LINK_HOVERED_CHANNEL,
+};
+use crate::input::WebViewBackend;
+use crate::javascript_bridge::{IpcMessage, JavaScriptBridge};
+use crate::placeholders::{
+    parse_blocked_content_load, placeholder_script, restore_script, BlockedContentLoadHandler,
+    BLOCKED_CONTENT_LOAD_CHANNEL,
+};
+use crate::print::{PrintOptions, PrintOutcome};
+use crate::protocol::{bridge_bootstrap_script, WireSession};
+use crate::storage::{merge_cookie_counts, WEBVIEW_KINDS};
+use crate::title::{
+    parse_title_changed, title_observer_script, TitleChangedHandler, TITLE_CHANGED_CHANNEL,
+};
+use config_manager::{AccessibilitySettings, ContentSettings, FontSettings};
 use message_bus::MessageSender;
 use serde_json::Value as JsonValue;
+use shared_types::{
+    DocumentChunk, FindOptions, Origin, OriginUsage, ResourceType, StorageKind, StorageReport,
+    TabId,
+};
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 // WRY and tao imports for GUI mode
@@ -13,9 +46,14 @@ use tao::{
     window::{Window, WindowBuilder},
 };
 
+#[cfg(feature = "gui")]
+use crate::document_protocol::DOCUMENT_SCHEME;
 #[cfg(feature = "gui")]
 use wry::WebViewBuilder;
 
+/// Supplies the user scripts to run on a page, given its URL
+pub type UserScriptSource = Arc<dyn Fn(&str) -> Vec<String> + Send + Sync>;
+
 /// WebView wrapper that handles platform-specific implementations
 pub struct WebViewWrapper {
     #[allow(dead_code)] // Will be used for IPC communication in GUI mode
@@ -23,6 +61,41 @@ pub struct WebViewWrapper {
     current_url: Option<String>,
     /// JavaScript bridge for IPC communication
     bridge: Arc<Mutex<JavaScriptBridge>>,
+    /// Receiving end of the current page's bridge
+    wire: Mutex<WireSession>,
+    /// Platform backend (storage management)
+    backend: Arc<dyn WebViewBackend>,
+    /// Cookie counts per origin, supplied by the network stack
+    cookie_counts: Mutex<HashMap<String, u32>>,
+    /// Tab this webview belongs to
+    tab_id: TabId,
+    /// Per-site content settings, applied before each navigation
+    content_settings: Option<Arc<Mutex<ContentSettings>>>,
+    /// User scripts to run after each navigation
+    user_scripts: Option<UserScriptSource>,
+    /// Accessibility stylesheet applied to every document
+    user_stylesheet: Option<UserStylesheet>,
+    /// Default fonts and sizes, applied before each navigation
+    fonts: Option<FontSettings>,
+    /// Encoding for documents that declare no charset; empty means UTF-8
+    default_encoding: String,
+    /// Whether main-frame form submissions are captured
+    capture_forms: bool,
+    /// Whether page title changes are reported
+    observe_title: bool,
+    /// Whether the link under the pointer is reported
+    report_link_hover: bool,
+    /// Whether the hosts of links in view are reported for DNS prefetch
+    report_viewport_links: bool,
+    /// Whether blocked content is replaced by click-to-load placeholders
+    show_placeholders: bool,
+    /// Whether the tab is private (no blocked-content or viewport link
+    /// reporting)
+    private: bool,
+    /// Serves streamed documents to the webview's custom protocol
+    documents: DocumentProtocol,
+    /// Document currently streamed through the custom protocol
+    document_stream: Option<DocumentStream>,
     // GUI mode: actual window, webview, and event loop
     #[cfg(feature = "gui")]
     #[allow(dead_code)]
@@ -56,16 +129,55 @@ impl WebViewWrapper {
                 .build(&event_loop)
                 .map_err(|e| Error::Initialization(format!("Failed to create window: {}", e)))?;
 
-            // Create WebView with blank page initially
+            // Create WebView with blank page initially. The bridge script
+            // is fixed for the life of the webview, so its pages share a token
+            let wire = WireSession::new();
+            let documents = DocumentProtocol::new();
             let webview = WebViewBuilder::new()
                 .with_url("about:blank")
+                .with_initialization_script(&bridge_bootstrap_script(wire.token()))
+                .with_asynchronous_custom_protocol(DOCUMENT_SCHEME.to_string(), {
+                    let documents = documents.clone();
+                    move |_id, request, responder| {
+                        // Answering waits for the whole document, so not
+                        // on the UI thread
+                        let documents = documents.clone();
+                        let url = request.uri().to_string();
+                        std::thread::spawn(move || {
+                            responder.respond(documents.respond(&url).into_http())
+                        });
+                    }
+                })
                 .build(&window)
                 .map_err(|e| Error::Initialization(format!("Failed to create webview: {}", e)))?;
+            #[cfg(target_os = "linux")]
+            let backend: Arc<dyn WebViewBackend> =
+                Arc::new(crate::platform::GtkBackend::new(&webview));
+            #[cfg(not(target_os = "linux"))]
+            let backend: Arc<dyn WebViewBackend> =
+                Arc::from(crate::platform::native_input_backend());
 
             Ok(Self {
                 sender,
                 current_url: Some("about:blank".to_string()),
                 bridge,
+                wire: Mutex::new(wire),
+                backend,
+                cookie_counts: Mutex::new(HashMap::new()),
+                tab_id: 0,
+                content_settings: None,
+                user_scripts: None,
+                user_stylesheet: None,
+                fonts: None,
+                default_encoding: String::new(),
+                capture_forms: false,
+                observe_title: false,
+                report_link_hover: false,
+                report_viewport_links: false,
+                show_placeholders: false,
+                private: false,
+                documents,
+                document_stream: None,
                 event_loop: Some(event_loop),
                 window: Some(window),
                 webview: Some(webview),
@@ -79,6 +191,23 @@ impl WebViewWrapper {
                 sender,
                 current_url: None,
                 bridge,
+                wire: Mutex::new(WireSession::new()),
+                backend: Arc::from(crate::platform::native_input_backend()),
+                cookie_counts: Mutex::new(HashMap::new()),
+                tab_id: 0,
+                content_settings: None,
+                user_scripts: None,
+                user_stylesheet: None,
+                fonts: None,
+                default_encoding: String::new(),
+                capture_forms: false,
+                observe_title: false,
+                report_link_hover: false,
+                report_viewport_links: false,
+                show_placeholders: false,
+                private: false,
+                documents: DocumentProtocol::new(),
+                document_stream: None,
                 #[cfg(feature = "gui")]
                 event_loop: None,
                 #[cfg(feature = "gui")]
@@ -96,6 +225,11 @@ impl WebViewWrapper {
             return Err(Error::Navigation("URL cannot be empty".to_string()));
         }
 
+        self.apply_content_settings(url)?;
+        self.apply_font_settings()?;
+        let native_stylesheet = self.apply_user_stylesheet()?;
+        self.install_bridge()?;
+
         #[cfg(feature = "gui")]
         {
             // In GUI mode, actually navigate the WebView (if initialized)
@@ -103,21 +237,777 @@ impl WebViewWrapper {
                 webview
                     .load_url(url)
                     .map_err(|e| Error::Navigation(format!("Failed to load URL: {}", e)))?;
-                self.current_url = Some(url.to_string());
-                Ok(())
-            } else {
-                // Fallback for test mode or when WebView not initialized
-                self.current_url = Some(url.to_string());
-                Ok(())
             }
+            // Without a WebView (test mode), just track the URL
+            self.current_url = Some(url.to_string());
         }
 
         #[cfg(not(feature = "gui"))]
         {
             // In headless mode, just track the URL
             self.current_url = Some(url.to_string());
-            Ok(())
         }
+
+        if !native_stylesheet {
+            self.inject_user_stylesheet()?;
+        }
+        self.run_user_scripts(url)?;
+        self.install_form_capture()?;
+        self.install_title_observer()?;
+        self.install_link_hover_reporter()?;
+        self.install_viewport_link_reporter()
+    }
+
+    /// Attach per-site content settings
+    ///
+    /// Once attached, every navigation first resolves the JavaScript setting
+    /// for the target origin and pushes it to the backend.
+    ///
+    /// # Arguments
+    ///
+    /// * `tab_id` - Tab this webview belongs to
+    /// * `settings` - Content settings shared with the shell
+    pub fn set_content_settings(&mut self, tab_id: TabId, settings: Arc<Mutex<ContentSettings>>) {
+        self.tab_id = tab_id;
+        self.content_settings = Some(settings);
+    }
+
+    /// Attach a source of user scripts
+    ///
+    /// After every navigation the scripts returned for the URL are run in
+    /// the page, in order.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Returns the script sources for a URL
+    pub fn set_user_scripts(&mut self, source: UserScriptSource) {
+        self.user_scripts = Some(source);
+    }
+
+    /// Apply accessibility settings
+    ///
+    /// The stylesheet is built right away and applied to every document
+    /// loaded from now on. The page already shown keeps its styles until it
+    /// is reloaded, so the shell should offer a reload when this returns
+    /// `true`.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - Accessibility settings
+    ///
+    /// # Returns
+    ///
+    /// Whether a page is shown and its stylesheet is now out of date.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UserStylesheet` if the custom stylesheet cannot be
+    /// read; the previous stylesheet stays in effect.
+    pub fn set_accessibility(&mut self, settings: &AccessibilitySettings) -> Result<bool> {
+        let mut stylesheet = UserStylesheet::load(settings)?;
+        if let Some(previous) = &self.user_stylesheet {
+            stylesheet.set_font_floor(previous.font_floor())?;
+        }
+        let previous = self.user_stylesheet.as_ref().map_or("", |s| s.css());
+        let changed = stylesheet.css() != previous;
+        self.user_stylesheet = Some(stylesheet);
+        Ok(changed && self.current_url.is_some())
+    }
+
+    /// Set the default fonts, sizes and page encoding
+    ///
+    /// They are pushed to the backend before every navigation from now on,
+    /// so a reloaded config takes effect with the next page. Backends
+    /// without font settings get the minimum font size through the user
+    /// stylesheet instead; the encoding then only reaches documents
+    /// streamed through the document protocol.
+    ///
+    /// # Arguments
+    ///
+    /// * `fonts` - Font settings
+    /// * `default_encoding` - Encoding for documents that declare no
+    ///   charset; empty means UTF-8
+    pub fn set_fonts(&mut self, fonts: &FontSettings, default_encoding: &str) {
+        self.fonts = Some(fonts.clone());
+        self.default_encoding = default_encoding.to_string();
+    }
+
+    /// Re-read the custom stylesheet if it changed on disk
+    ///
+    /// Navigations pick up a changed file on their own; call this when the
+    /// config is reloaded to learn whether the shown page is out of date.
+    ///
+    /// # Returns
+    ///
+    /// Whether a page is shown and its stylesheet is now out of date.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::UserStylesheet` if the custom stylesheet cannot be
+    /// read; the previous stylesheet stays in effect.
+    pub fn reload_user_stylesheet(&mut self) -> Result<bool> {
+        let Some(stylesheet) = &mut self.user_stylesheet else {
+            return Ok(false);
+        };
+        Ok(stylesheet.reload()? && self.current_url.is_some())
+    }
+
+    /// Route main-frame form submissions to `handler`
+    ///
+    /// After every navigation a capture script is installed that cancels
+    /// form submissions and reports them over the IPC bridge; `handler`
+    /// receives each one encoded as a request. Submissions with selected
+    /// files or bodies over [`FORM_BODY_CAP`] stay with the webview and
+    /// are logged as bypassing the engine.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - Issues the request for a captured submission
+    pub fn set_form_handler(&mut self, handler: FormSubmitHandler) {
+        let bridge = self.bridge.lock().unwrap();
+        bridge.register_handler(
+            FORM_SUBMIT_CHANNEL,
+            Box::new(move |msg| {
+                handler(parse_form_submission(&msg.data)?);
+                Ok(JsonValue::Null)
+            }),
+        );
+        bridge.register_handler(
+            FORM_NATIVE_CHANNEL,
+            Box::new(|msg| {
+                eprintln!("[Forms] {}", describe_native_submission(&msg.data));
+                Ok(JsonValue::Null)
+            }),
+        );
+        self.capture_forms = true;
+    }
+
+    /// Report page title changes to `handler`
+    ///
+    /// After every navigation an observer script is installed that reports
+    /// the main frame's title once and then on every change. Changes are
+    /// attributed to the tab id set when the handler is attached.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - Receives each title change
+    pub fn set_title_handler(&mut self, handler: TitleChangedHandler) {
+        let tab_id = self.tab_id;
+        self.bridge.lock().unwrap().register_handler(
+            TITLE_CHANGED_CHANNEL,
+            Box::new(move |msg| {
+                handler(parse_title_changed(tab_id, &msg.data)?);
+                Ok(JsonValue::Null)
+            }),
+        );
+        self.observe_title = true;
+    }
+
+    /// Report the link under the pointer to `handler`
+    ///
+    /// After every navigation a script is installed that reports the
+    /// target of a link when the pointer moves onto it and no link when it
+    /// moves off, for the status bar. Changes are attributed to the tab id
+    /// set when the handler is attached.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - Receives each hover change
+    pub fn set_link_hover_handler(&mut self, handler: LinkHoveredHandler) {
+        let tab_id = self.tab_id;
+        self.bridge.lock().unwrap().register_handler(
+            LINK_HOVERED_CHANNEL,
+            Box::new(move |msg| {
+                handler(parse_link_hovered(tab_id, &msg.data)?);
+                Ok(JsonValue::Null)
+            }),
+        );
+        self.report_link_hover = true;
+    }
+
+    /// Report find in page results to `handler`
+    ///
+    /// Every search and step through its matches reports the highlighted
+    /// match and the match count, attributed to the tab id set when the
+    /// handler is attached.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - Receives each find result
+    pub fn set_find_handler(&mut self, handler: FindReportHandler) {
+        let tab_id = self.tab_id;
+        self.bridge.lock().unwrap().register_handler(
+            FIND_RESULT_CHANNEL,
+            Box::new(move |msg| {
+                handler(parse_find_report(tab_id, &msg.data)?);
+                Ok(JsonValue::Null)
+            }),
+        );
+    }
+
+    /// Highlight the matches of `query` in the current page
+    ///
+    /// The first match is scrolled into view. The result arrives at the
+    /// handler set with [`set_find_handler`](Self::set_find_handler).
+    /// Backends that cannot run scripts are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Text to find; an empty query ends the search
+    /// * `options` - Case sensitivity and whole word matching
+    pub fn find_in_page(&self, query: &str, options: &FindOptions) -> Result<()> {
+        self.run_find_script(&find_script(query, options))
+    }
+
+    /// Highlight the next match of the current search, wrapping around
+    pub fn find_next(&self) -> Result<()> {
+        self.run_find_script(find_step_script(true))
+    }
+
+    /// Highlight the previous match of the current search, wrapping around
+    pub fn find_previous(&self) -> Result<()> {
+        self.run_find_script(find_step_script(false))
+    }
+
+    /// Remove the highlights of the current search
+    pub fn stop_finding(&self) -> Result<()> {
+        self.run_find_script(stop_finding_script())
+    }
+
+    fn run_find_script(&self, script: &str) -> Result<()> {
+        #[cfg(feature = "gui")]
+        if let Some(webview) = &self.webview {
+            return webview
+                .evaluate_script(script)
+                .map_err(|e| Error::ScriptExecution(format!("Failed to find in page: {}", e)));
+        }
+
+        match self.backend.run_user_script(self.tab_id, script) {
+            Ok(()) | Err(Error::Unsupported(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Report the hosts of links scrolled into view to `handler`
+    ///
+    /// After every navigation a script is installed that reports the
+    /// distinct hosts of links as they come into view, for the network
+    /// stack to prefetch their DNS. Private tabs and pages with
+    /// `x-dns-prefetch-control: off` report nothing. Reports are attributed
+    /// to the tab id set when the handler is attached.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - Receives each report
+    pub fn set_viewport_links_handler(&mut self, handler: ViewportLinksHandler) {
+        let tab_id = self.tab_id;
+        self.bridge.lock().unwrap().register_handler(
+            VIEWPORT_LINKS_CHANNEL,
+            Box::new(move |msg| {
+                handler(parse_viewport_links(tab_id, &msg.data)?);
+                Ok(JsonValue::Null)
+            }),
+        );
+        self.report_viewport_links = true;
+    }
+
+    /// Show click-to-load placeholders for blocked content
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Value of `adblock.show_placeholders`
+    pub fn set_show_placeholders(&mut self, enabled: bool) {
+        self.show_placeholders = enabled;
+    }
+
+    /// Mark the webview as belonging to a private tab
+    ///
+    /// Private tabs never report blocked content, so they get no
+    /// placeholders whatever `adblock.show_placeholders` says, and never
+    /// report links in view for DNS prefetch.
+    ///
+    /// # Arguments
+    ///
+    /// * `private` - Whether the tab is private
+    pub fn set_private(&mut self, private: bool) {
+        self.private = private;
+    }
+
+    /// Report placeholder clicks to `handler`
+    ///
+    /// The handler should allow the URL for the tab (by sending
+    /// `BrowserMessage::LoadBlockedResource`) and then call
+    /// [`WebViewWrapper::load_blocked_resource`]. Clicks are attributed to
+    /// the tab id set when the handler is attached.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - Receives each placeholder click
+    pub fn set_blocked_content_handler(&mut self, handler: BlockedContentLoadHandler) {
+        let tab_id = self.tab_id;
+        self.bridge.lock().unwrap().register_handler(
+            BLOCKED_CONTENT_LOAD_CHANNEL,
+            Box::new(move |msg| {
+                handler(parse_blocked_content_load(tab_id, &msg.data)?);
+                Ok(JsonValue::Null)
+            }),
+        );
+    }
+
+    /// Replace the elements loading blocked URLs with placeholders
+    ///
+    /// Call once the page has loaded, with the tab's blocked requests from
+    /// the network stack. Nothing runs when placeholders are off, the tab
+    /// is private, or no URL is of a type that gets a placeholder (scripts
+    /// and stylesheets never do). Backends that cannot run scripts are
+    /// skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `blocked` - Blocked URLs with their resource types
+    ///
+    /// # Returns
+    ///
+    /// Whether the placeholder script was run.
+    pub fn show_blocked_placeholders(&self, blocked: &[(String, ResourceType)]) -> Result<bool> {
+        if !self.show_placeholders || self.private {
+            return Ok(false);
+        }
+        let Some(script) = placeholder_script(blocked) else {
+            return Ok(false);
+        };
+
+        #[cfg(feature = "gui")]
+        if let Some(webview) = &self.webview {
+            webview.evaluate_script(&script).map_err(|e| {
+                Error::ScriptExecution(format!("Failed to show placeholders: {}", e))
+            })?;
+            return Ok(true);
+        }
+
+        match self.backend.run_user_script(self.tab_id, &script) {
+            Ok(()) => Ok(true),
+            Err(Error::Unsupported(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Put back the elements replaced for `url` and fetch it again
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Blocked URL the user chose to load; it must already be
+    ///   allowed for the tab or the fetch is blocked again
+    pub fn load_blocked_resource(&self, url: &str) -> Result<()> {
+        let script = restore_script(url);
+
+        #[cfg(feature = "gui")]
+        if let Some(webview) = &self.webview {
+            return webview.evaluate_script(&script).map_err(|e| {
+                Error::ScriptExecution(format!("Failed to load blocked content: {}", e))
+            });
+        }
+
+        match self.backend.run_user_script(self.tab_id, &script) {
+            Ok(()) | Err(Error::Unsupported(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Give the tab's documents a bridge with a new token
+    ///
+    /// Runs before each navigation so that anything still arriving from the
+    /// previous page is dropped. A GUI webview's bridge script was fixed
+    /// when it was built; its pages keep the token and only restart their
+    /// sequence numbers. Backends without document-start scripts are
+    /// skipped; their pages have no bridge.
+    fn install_bridge(&mut self) -> Result<()> {
+        let mut wire = self.wire.lock().unwrap();
+
+        #[cfg(feature = "gui")]
+        if self.webview.is_some() {
+            wire.restart();
+            return Ok(());
+        }
+
+        *wire = WireSession::new();
+        let script = bridge_bootstrap_script(wire.token());
+        match self.backend.set_bridge_script(self.tab_id, &script) {
+            Ok(()) | Err(Error::Unsupported(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Install the title observer script in the current page
+    ///
+    /// Backends that cannot run scripts are skipped; their title changes
+    /// go unreported.
+    fn install_title_observer(&self) -> Result<()> {
+        if !self.observe_title {
+            return Ok(());
+        }
+        let script = title_observer_script();
+
+        #[cfg(feature = "gui")]
+        if let Some(webview) = &self.webview {
+            return webview.evaluate_script(script).map_err(|e| {
+                Error::ScriptExecution(format!("Failed to install title observer: {}", e))
+            });
+        }
+
+        match self.backend.run_user_script(self.tab_id, script) {
+            Ok(()) | Err(Error::Unsupported(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Install the link hover reporter in the current page
+    ///
+    /// Backends that cannot run scripts are skipped; their hovered links
+    /// go unreported.
+    fn install_link_hover_reporter(&self) -> Result<()> {
+        if !self.report_link_hover {
+            return Ok(());
+        }
+        let script = link_hover_script();
+
+        #[cfg(feature = "gui")]
+        if let Some(webview) = &self.webview {
+            return webview.evaluate_script(script).map_err(|e| {
+                Error::ScriptExecution(format!("Failed to install link hover reporter: {}", e))
+            });
+        }
+
+        match self.backend.run_user_script(self.tab_id, script) {
+            Ok(()) | Err(Error::Unsupported(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Install the viewport link reporter in the current page
+    ///
+    /// Skipped for private tabs. Backends that cannot run scripts are
+    /// skipped too; their links are not prefetched.
+    fn install_viewport_link_reporter(&self) -> Result<()> {
+        if !self.report_viewport_links || self.private {
+            return Ok(());
+        }
+        let script = viewport_links_script();
+
+        #[cfg(feature = "gui")]
+        if let Some(webview) = &self.webview {
+            return webview.evaluate_script(&script).map_err(|e| {
+                Error::ScriptExecution(format!("Failed to install link reporter: {}", e))
+            });
+        }
+
+        match self.backend.run_user_script(self.tab_id, &script) {
+            Ok(()) | Err(Error::Unsupported(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Install the form capture script in the current page
+    ///
+    /// Backends that cannot run scripts are skipped; their submissions
+    /// stay with the webview.
+    fn install_form_capture(&self) -> Result<()> {
+        if !self.capture_forms {
+            return Ok(());
+        }
+        let script = form_capture_script(FORM_BODY_CAP);
+
+        #[cfg(feature = "gui")]
+        if let Some(webview) = &self.webview {
+            return webview.evaluate_script(&script).map_err(|e| {
+                Error::ScriptExecution(format!("Failed to install form capture: {}", e))
+            });
+        }
+
+        match self.backend.run_user_script(self.tab_id, &script) {
+            Ok(()) | Err(Error::Unsupported(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Hand the font settings to the backend before a document loads
+    ///
+    /// When the backend has no font settings, the minimum font size is
+    /// enforced by the user stylesheet, which is created if needed.
+    fn apply_font_settings(&mut self) -> Result<()> {
+        let Some(fonts) = &self.fonts else {
+            return Ok(());
+        };
+        let floor = match self
+            .backend
+            .set_font_settings(self.tab_id, fonts, &self.default_encoding)
+        {
+            Ok(()) => 0,
+            Err(Error::Unsupported(_)) => fonts.minimum_font_size,
+            Err(e) => return Err(e),
+        };
+
+        if self.user_stylesheet.is_none() {
+            if floor == 0 {
+                return Ok(());
+            }
+            self.user_stylesheet = Some(UserStylesheet::load(&AccessibilitySettings::default())?);
+        }
+        if let Some(stylesheet) = &mut self.user_stylesheet {
+            if let Err(e) = stylesheet.set_font_floor(floor) {
+                eprintln!("[Accessibility] {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Hand the user stylesheet to the backend before a document loads
+    ///
+    /// A changed custom stylesheet is re-read first; if that fails the
+    /// previous CSS is kept.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the backend has no user-stylesheet API and the stylesheet
+    /// must be injected into the page instead.
+    fn apply_user_stylesheet(&mut self) -> Result<bool> {
+        let Some(stylesheet) = &mut self.user_stylesheet else {
+            return Ok(true);
+        };
+        if let Err(e) = stylesheet.reload() {
+            eprintln!("[Accessibility] {}", e);
+        }
+
+        match self
+            .backend
+            .set_user_stylesheet(self.tab_id, stylesheet.css())
+        {
+            Ok(()) => Ok(true),
+            Err(Error::Unsupported(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Insert the user stylesheet into the current page
+    ///
+    /// Runs ahead of the user scripts; backends that cannot run scripts
+    /// are skipped.
+    fn inject_user_stylesheet(&self) -> Result<()> {
+        let Some(stylesheet) = self.user_stylesheet.as_ref().filter(|s| !s.is_empty()) else {
+            return Ok(());
+        };
+        let script = stylesheet_injector_script(stylesheet.css());
+
+        #[cfg(feature = "gui")]
+        if let Some(webview) = &self.webview {
+            return webview.evaluate_script(&script).map_err(|e| {
+                Error::ScriptExecution(format!("Failed to inject user stylesheet: {}", e))
+            });
+        }
+
+        match self.backend.run_user_script(self.tab_id, &script) {
+            Ok(()) | Err(Error::Unsupported(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Run the user scripts for `url`
+    ///
+    /// Scripts go through the WebView when one exists, otherwise through
+    /// the backend; backends that cannot run scripts are skipped.
+    fn run_user_scripts(&self, url: &str) -> Result<()> {
+        let Some(source) = &self.user_scripts else {
+            return Ok(());
+        };
+
+        for script in source(url) {
+            #[cfg(feature = "gui")]
+            if let Some(webview) = &self.webview {
+                webview.evaluate_script(&script).map_err(|e| {
+                    Error::ScriptExecution(format!("Failed to run user script: {}", e))
+                })?;
+                continue;
+            }
+
+            match self.backend.run_user_script(self.tab_id, &script) {
+                Ok(()) | Err(Error::Unsupported(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Feed the next piece of a streamed document
+    ///
+    /// Chunks go to the backend's incremental loader when it has one.
+    /// Otherwise `Begin` points the webview at a fresh document protocol
+    /// URL for the document's origin, and the webview is served the
+    /// document once it has been fed in full. A `Begin`
+    /// abandons any document still being streamed, and is preceded by the
+    /// font settings and the user stylesheet.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - Next piece of the document
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Navigation` if the webview cannot load the document
+    /// URL or stopped reading the document.
+    pub fn feed_document(&mut self, chunk: DocumentChunk) -> Result<()> {
+        let native_stylesheet = match &chunk {
+            DocumentChunk::Begin { .. } => {
+                self.apply_font_settings()?;
+                self.apply_user_stylesheet()?
+            }
+            _ => true,
+        };
+
+        match self.backend.load_html_streaming(self.tab_id, &chunk) {
+            Ok(()) => {
+                if let DocumentChunk::Begin { url } = &chunk {
+                    self.current_url = Some(url.clone());
+                    if !native_stylesheet {
+                        self.inject_user_stylesheet()?;
+                    }
+                }
+                return Ok(());
+            }
+            Err(Error::Unsupported(_)) => {}
+            Err(e) => return Err(e),
+        }
+
+        if let DocumentChunk::Begin { url } = &chunk {
+            let stream = self.documents.open_stream(url, &self.default_encoding);
+            self.load_document_stream(&stream, url, native_stylesheet)?;
+            self.document_stream = Some(stream);
+        }
+
+        let finished = matches!(chunk, DocumentChunk::End | DocumentChunk::Failed { .. });
+        let result = match &self.document_stream {
+            Some(stream) => stream.send(chunk),
+            None => Ok(()),
+        };
+        if finished {
+            self.document_stream = None;
+        }
+        result
+    }
+
+    /// Show a page the browser rendered itself, such as an error or
+    /// `about:` page
+    ///
+    /// The page always goes through the document protocol, the only path
+    /// that can attach response headers, so its Content Security Policy
+    /// is sent as a header as well as in its `<meta>` tag.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - Page URL, e.g. `about:history`
+    /// * `html` - The rendered page
+    /// * `content_security_policy` - The page's policy, e.g. from
+    ///   `InternalPage::csp_header`
+    ///
+    /// # Returns
+    ///
+    /// The document protocol URL the webview loads the page from.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Navigation` if the webview cannot load the page.
+    pub fn load_internal_page(
+        &mut self,
+        url: &str,
+        html: &str,
+        content_security_policy: &str,
+    ) -> Result<String> {
+        self.apply_font_settings()?;
+        let native_stylesheet = self.apply_user_stylesheet()?;
+        let stream = self.documents.open_stream_with_headers(
+            url,
+            "",
+            vec![(
+                "Content-Security-Policy".to_string(),
+                content_security_policy.to_string(),
+            )],
+        );
+        // The whole page is at hand, so nothing stays current afterwards
+        self.document_stream = None;
+        self.load_document_stream(&stream, url, native_stylesheet)?;
+        stream.send(DocumentChunk::Begin {
+            url: url.to_string(),
+        })?;
+        stream.send(DocumentChunk::Data(html.as_bytes().to_vec()))?;
+        stream.send(DocumentChunk::End)?;
+        Ok(stream.url().to_string())
+    }
+
+    /// Point the webview at a document protocol stream
+    fn load_document_stream(
+        &mut self,
+        stream: &DocumentStream,
+        url: &str,
+        native_stylesheet: bool,
+    ) -> Result<()> {
+        #[cfg(feature = "gui")]
+        if let Some(webview) = &self.webview {
+            webview
+                .load_url(stream.url())
+                .map_err(|e| Error::Navigation(format!("Failed to load URL: {}", e)))?;
+        }
+        #[cfg(not(feature = "gui"))]
+        let _ = stream; // Suppress unused variable warning
+
+        self.current_url = Some(url.to_string());
+        if !native_stylesheet {
+            self.inject_user_stylesheet()?;
+        }
+        Ok(())
+    }
+
+    /// URL the webview loads the current streamed document from, when it
+    /// goes through the document protocol
+    pub fn document_stream_url(&self) -> Option<&str> {
+        self.document_stream.as_ref().map(|stream| stream.url())
+    }
+
+    /// Protocol serving documents that go through the custom protocol
+    ///
+    /// Backends that register the [`DOCUMENT_SCHEME`](crate::DOCUMENT_SCHEME)
+    /// handler themselves answer its requests with
+    /// [`DocumentProtocol::respond`].
+    pub fn document_protocol(&self) -> &DocumentProtocol {
+        &self.documents
+    }
+
+    /// Push the JavaScript and image settings for `url`'s origin to the
+    /// backend
+    ///
+    /// Backends that cannot toggle them between loads are skipped.
+    fn apply_content_settings(&self, url: &str) -> Result<()> {
+        let Some(settings) = &self.content_settings else {
+            return Ok(());
+        };
+        let Ok(parsed) = url::Url::parse(url) else {
+            return Ok(());
+        };
+
+        let origin = Origin::from_url(&parsed)
+            .map(|origin| origin.to_string())
+            .unwrap_or_else(|| "null".to_string());
+        let content = settings.lock().unwrap().settings_for(&origin);
+
+        for result in [
+            self.backend
+                .set_javascript_enabled(self.tab_id, content.javascript),
+            self.backend.set_images_enabled(self.tab_id, content.images),
+        ] {
+            match result {
+                Ok(()) | Err(Error::Unsupported(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
     }
 
     /// Execute JavaScript in the WebView
@@ -182,15 +1072,24 @@ impl WebViewWrapper {
 
     /// Handle incoming IPC message from JavaScript
     ///
-    /// Processes an IPC message through the JavaScript bridge and returns the result.
+    /// Checks the input against the [wire protocol](crate::protocol) and
+    /// dispatches each accepted message through the JavaScript bridge on
+    /// the channel named by its type. Messages that fail a check are logged
+    /// and dropped.
     ///
     /// # Arguments
     ///
-    /// * `json` - JSON string containing the IPC message
+    /// * `json` - One envelope or an array of envelopes, as sent by the
+    ///   page's bridge
+    ///
+    /// # Returns
+    ///
+    /// The handlers' results for each accepted message, in order.
     ///
     /// # Errors
     ///
-    /// Returns an error if message parsing fails or dispatch fails.
+    /// Returns `Error::Ipc` if the input as a whole is refused (not JSON,
+    /// or over the size limits) or dispatch fails.
     ///
     /// # Example
     ///
@@ -201,54 +1100,238 @@ impl WebViewWrapper {
     /// # bus.start().unwrap();
     /// # let sender = bus.sender();
     /// # let mut wrapper = WebViewWrapper::new(sender).unwrap();
-    /// let message_json = r#"{"channel":"console","data":{"level":"log","message":"Hello"},"timestamp":1234567890,"message_id":"550e8400-e29b-41d4-a716-446655440000"}"#;
-    /// let result = wrapper.handle_ipc_message(message_json).unwrap();
+    /// # use serde_json::json;
+    /// let message_json = json!({
+    ///     "v": webview_integration::PROTOCOL_VERSION,
+    ///     "token": wrapper.bridge_token(),
+    ///     "seq": 1,
+    ///     "type": "console",
+    ///     "data": {"level": "log", "message": "Hello"},
+    /// });
+    /// let result = wrapper.handle_ipc_message(&message_json.to_string()).unwrap();
     /// # bus.shutdown().unwrap();
     /// ```
     pub fn handle_ipc_message(&self, json: &str) -> Result<JsonValue> {
+        let decoded = self
+            .wire
+            .lock()
+            .unwrap()
+            .decode(json)
+            .map_err(|e| Error::Ipc(format!("Refused IPC input: {}", e)))?;
+
+        let bridge = self.bridge.lock().unwrap();
+        let mut results = Vec::new();
+        for message in decoded {
+            match message {
+                Ok(message) => {
+                    results.push(bridge.dispatch_message(IpcMessage::new(
+                        message.message_type(),
+                        message.data(),
+                    ))?)
+                }
+                Err(e) => eprintln!("[IPC] Dropped message from tab {}: {}", self.tab_id, e),
+            }
+        }
+        Ok(JsonValue::Array(results))
+    }
+
+    /// Token the current page's bridge puts on every message
+    pub fn bridge_token(&self) -> String {
+        self.wire.lock().unwrap().token().to_string()
+    }
+
+    /// Emit an event from Rust to JavaScript
+    ///
+    /// Creates JavaScript code that dispatches an event to registered listeners.
+    /// The generated code should be executed in the WebView context.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel` - Event channel name
+    /// * `data` - Event data (must be JSON-serializable)
+    ///
+    /// # Returns
+    ///
+    /// JavaScript code string that can be executed via `execute_script()`
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use webview_integration::WebViewWrapper;
+    /// # use message_bus::MessageBus;
+    /// # use serde_json::json;
+    /// # let mut bus = MessageBus::new();
+    /// # bus.start().unwrap();
+    /// # let sender = bus.sender();
+    /// # let mut wrapper = WebViewWrapper::new(sender).unwrap();
+    /// let script = wrapper.emit_event("custom-event", json!({"key": "value"})).unwrap();
+    /// wrapper.execute_script(&script).unwrap();
+    /// # bus.shutdown().unwrap();
+    /// ```
+    pub fn emit_event(&self, channel: &str, data: JsonValue) -> Result<String> {
         let bridge = self.bridge.lock().unwrap();
-        let message = bridge.parse_ipc_message(json)?;
-        bridge.dispatch_message(message)
+        bridge.emit_event(channel, data)
+    }
+
+    /// Get the JavaScript bridge instance
+    ///
+    /// Returns a reference to the bridge for advanced usage.
+    pub fn bridge(&self) -> Arc<Mutex<JavaScriptBridge>> {
+        Arc::clone(&self.bridge)
+    }
+
+    /// Replace the platform backend
+    ///
+    /// Used to attach a backend with data-manager access, or a
+    /// [`MockBackend`](crate::MockBackend) in tests.
+    pub fn set_backend(&mut self, backend: Arc<dyn WebViewBackend>) {
+        self.backend = backend;
+    }
+
+    /// The platform backend
+    ///
+    /// Shared with callers that deliver input outside the wrapper, such as
+    /// WebDriver sessions.
+    pub fn backend(&self) -> Arc<dyn WebViewBackend> {
+        Arc::clone(&self.backend)
+    }
+
+    /// Print this webview's page, or save it as a PDF
+    ///
+    /// The single entry point for printing, shared by the shell's menu and
+    /// automation clients.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Print` if printing fails or is cancelled, or
+    /// `Error::Unsupported` if the backend cannot print.
+    pub fn print(&self, options: PrintOptions) -> Result<PrintOutcome> {
+        self.backend.print(self.tab_id, options)
+    }
+
+    /// Zoom this webview's page
+    ///
+    /// # Arguments
+    ///
+    /// * `level` - Zoom factor (1.0 = 100%)
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Platform` if the webview fails to zoom, or
+    /// `Error::Unsupported` if the backend cannot zoom.
+    pub fn set_zoom(&self, level: f64) -> Result<()> {
+        #[cfg(feature = "gui")]
+        if let Some(webview) = &self.webview {
+            return webview
+                .zoom(level)
+                .map_err(|e| Error::Platform(format!("Failed to zoom: {}", e)));
+        }
+
+        self.backend.set_zoom(self.tab_id, level)
+    }
+
+    /// Update the per-origin cookie counts from the network stack's cookie store
+    pub fn set_cookie_counts(&self, counts: HashMap<String, u32>) {
+        *self.cookie_counts.lock().unwrap() = counts;
+    }
+
+    /// Get per-origin site data usage
+    ///
+    /// Combines webview storage reported by the backend with cookie-store
+    /// counts, largest origin first.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the platform or webview version cannot
+    /// enumerate storage.
+    pub fn storage_usage(&self) -> Result<Vec<OriginUsage>> {
+        let usage = self.backend.storage_usage()?;
+        Ok(merge_cookie_counts(
+            usage,
+            &self.cookie_counts.lock().unwrap(),
+        ))
+    }
+
+    /// Get a storage snapshot for `about:storage`
+    ///
+    /// When enumeration is unsupported the report is reduced to cookie counts
+    /// and carries the reason in `unsupported`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if enumeration fails for a reason other than being
+    /// unsupported.
+    pub fn storage_report(&self) -> Result<StorageReport> {
+        match self.storage_usage() {
+            Ok(origins) => Ok(StorageReport {
+                origins,
+                unsupported: None,
+            }),
+            Err(Error::Unsupported(reason)) => Ok(StorageReport {
+                origins: merge_cookie_counts(Vec::new(), &self.cookie_counts.lock().unwrap()),
+                unsupported: Some(reason),
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Clear site data for an origin
+    ///
+    /// Webview kinds are cleared through the backend; cookies are dropped from
+    /// the tracked counts (the network stack clears its own store on
+    /// `BrowserMessage::ClearOriginStorage`).
+    ///
+    /// # Arguments
+    ///
+    /// * `origin` - Origin to clear
+    /// * `kinds` - Kinds of data to clear
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if webview kinds were requested and the
+    /// platform cannot clear them per origin.
+    pub fn clear_origin_storage(&self, origin: &str, kinds: &[StorageKind]) -> Result<()> {
+        if kinds.contains(&StorageKind::Cookies) {
+            self.cookie_counts.lock().unwrap().remove(origin);
+        }
+
+        let webview_kinds: Vec<StorageKind> = kinds
+            .iter()
+            .copied()
+            .filter(|k| WEBVIEW_KINDS.contains(k))
+            .collect();
+        if webview_kinds.is_empty() {
+            return Ok(());
+        }
+        self.backend.clear_origin_storage(origin, &webview_kinds)
     }
 
-    /// Emit an event from Rust to JavaScript
-    ///
-    /// Creates JavaScript code that dispatches an event to registered listeners.
-    /// The generated code should be executed in the WebView context.
-    ///
-    /// # Arguments
+    /// Clear cookies and site data for every origin
     ///
-    /// * `channel` - Event channel name
-    /// * `data` - Event data (must be JSON-serializable)
+    /// Backs the "cookies and site data" option of clearing browsing data so
+    /// it covers webview storage as well as the cookie store.
     ///
     /// # Returns
     ///
-    /// JavaScript code string that can be executed via `execute_script()`
+    /// Returns the number of origins cleared.
     ///
-    /// # Example
+    /// # Errors
     ///
-    /// ```no_run
-    /// # use webview_integration::WebViewWrapper;
-    /// # use message_bus::MessageBus;
-    /// # use serde_json::json;
-    /// # let mut bus = MessageBus::new();
-    /// # bus.start().unwrap();
-    /// # let sender = bus.sender();
-    /// # let mut wrapper = WebViewWrapper::new(sender).unwrap();
-    /// let script = wrapper.emit_event("custom-event", json!({"key": "value"})).unwrap();
-    /// wrapper.execute_script(&script).unwrap();
-    /// # bus.shutdown().unwrap();
-    /// ```
-    pub fn emit_event(&self, channel: &str, data: JsonValue) -> Result<String> {
-        let bridge = self.bridge.lock().unwrap();
-        bridge.emit_event(channel, data)
-    }
+    /// Returns `Error::Unsupported` (after dropping cookie counts) if the
+    /// platform cannot enumerate or clear webview storage.
+    pub fn clear_all_site_data(&self) -> Result<usize> {
+        let origins = match self.storage_usage() {
+            Ok(origins) => origins,
+            Err(e) => {
+                self.cookie_counts.lock().unwrap().clear();
+                return Err(e);
+            }
+        };
 
-    /// Get the JavaScript bridge instance
-    ///
-    /// Returns a reference to the bridge for advanced usage.
-    pub fn bridge(&self) -> Arc<Mutex<JavaScriptBridge>> {
-        Arc::clone(&self.bridge)
+        for usage in &origins {
+            self.clear_origin_storage(&usage.origin, &StorageKind::ALL)?;
+        }
+        Ok(origins.len())
     }
 
     /// Take a screenshot of the WebView
@@ -342,8 +1425,21 @@ fn create_placeholder_png() -> Vec<u8> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::input::MockBackend;
     use message_bus::MessageBus;
 
+    /// Envelope the page's bridge would send
+    fn bridge_message(wrapper: &WebViewWrapper, seq: u64, kind: &str, data: JsonValue) -> String {
+        serde_json::json!({
+            "v": crate::PROTOCOL_VERSION,
+            "token": wrapper.bridge_token(),
+            "seq": seq,
+            "type": kind,
+            "data": data,
+        })
+        .to_string()
+    }
+
     // ========================================
     // RED PHASE: Tests for WebViewWrapper::new()
     // ========================================
@@ -529,16 +1625,15 @@ mod tests {
 
         let wrapper = WebViewWrapper::new(sender).unwrap();
 
-        let message_json = json!({
-            "channel": "console",
-            "data": {
+        let message_json = bridge_message(
+            &wrapper,
+            1,
+            "console",
+            json!({
                 "level": "log",
                 "message": "Test message"
-            },
-            "timestamp": 1234567890_u64,
-            "message_id": "550e8400-e29b-41d4-a716-446655440000"
-        })
-        .to_string();
+            }),
+        );
 
         let result = wrapper.handle_ipc_message(&message_json);
         assert!(result.is_ok());
@@ -605,16 +1700,14 @@ mod tests {
 
         let wrapper = WebViewWrapper::new(sender).unwrap();
 
-        let message_json = json!({
-            "channel": "navigation",
-            "data": {
-                "command": "navigate",
+        let message_json = bridge_message(
+            &wrapper,
+            1,
+            "navigate",
+            json!({
                 "url": "https://example.com"
-            },
-            "timestamp": 1234567890_u64,
-            "message_id": "550e8400-e29b-41d4-a716-446655440000"
-        })
-        .to_string();
+            }),
+        );
 
         let result = wrapper.handle_ipc_message(&message_json);
         assert!(result.is_ok());
@@ -632,18 +1725,17 @@ mod tests {
 
         let wrapper = WebViewWrapper::new(sender).unwrap();
 
-        let message_json = json!({
-            "channel": "error",
-            "data": {
+        let message_json = bridge_message(
+            &wrapper,
+            1,
+            "error",
+            json!({
                 "type": "error",
                 "message": "Test error",
                 "filename": "test.js",
                 "lineno": 42
-            },
-            "timestamp": 1234567890_u64,
-            "message_id": "550e8400-e29b-41d4-a716-446655440000"
-        })
-        .to_string();
+            }),
+        );
 
         let result = wrapper.handle_ipc_message(&message_json);
         assert!(result.is_ok());
@@ -682,6 +1774,661 @@ mod tests {
         bus.shutdown().unwrap();
     }
 
+    // ========================================
+    // Tests for storage management
+    // ========================================
+
+    fn usage(origin: &str, kinds: Vec<StorageKind>, bytes: u64) -> OriginUsage {
+        OriginUsage {
+            origin: origin.to_string(),
+            kinds,
+            bytes,
+            cookies: 0,
+        }
+    }
+
+    fn create_storage_wrapper(bus: &MessageBus) -> (WebViewWrapper, Arc<MockBackend>) {
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        backend.set_storage_usage(Some(vec![
+            usage("https://small.com", vec![StorageKind::LocalStorage], 10),
+            usage(
+                "https://big.com",
+                vec![StorageKind::IndexedDb, StorageKind::CacheStorage],
+                4096,
+            ),
+        ]));
+        wrapper.set_backend(backend.clone());
+        (wrapper, backend)
+    }
+
+    #[test]
+    fn test_storage_usage_merges_cookie_counts_and_sorts() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let (wrapper, _backend) = create_storage_wrapper(&bus);
+
+        let mut cookies = HashMap::new();
+        cookies.insert("https://small.com".to_string(), 2);
+        cookies.insert("https://cookies.com".to_string(), 5);
+        wrapper.set_cookie_counts(cookies);
+
+        let origins: Vec<_> = wrapper
+            .storage_usage()
+            .unwrap()
+            .into_iter()
+            .map(|u| (u.origin, u.cookies))
+            .collect();
+        assert_eq!(
+            origins,
+            vec![
+                ("https://big.com".to_string(), 0),
+                ("https://small.com".to_string(), 2),
+                ("https://cookies.com".to_string(), 5),
+            ]
+        );
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_clear_origin_storage_forwards_webview_kinds() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let (wrapper, backend) = create_storage_wrapper(&bus);
+
+        let mut cookies = HashMap::new();
+        cookies.insert("https://big.com".to_string(), 1);
+        wrapper.set_cookie_counts(cookies);
+
+        wrapper
+            .clear_origin_storage("https://big.com", &StorageKind::ALL)
+            .unwrap();
+
+        assert_eq!(
+            backend.cleared_storage(),
+            vec![("https://big.com".to_string(), WEBVIEW_KINDS.to_vec())]
+        );
+        let origins: Vec<_> = wrapper
+            .storage_usage()
+            .unwrap()
+            .into_iter()
+            .map(|u| u.origin)
+            .collect();
+        assert_eq!(origins, vec!["https://small.com".to_string()]);
+
+        // Cookie-only clears never reach the backend
+        wrapper
+            .clear_origin_storage("https://small.com", &[StorageKind::Cookies])
+            .unwrap();
+        assert_eq!(backend.cleared_storage().len(), 1);
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_clear_all_site_data() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let (wrapper, backend) = create_storage_wrapper(&bus);
+
+        assert_eq!(wrapper.clear_all_site_data().unwrap(), 2);
+        assert_eq!(backend.cleared_storage().len(), 2);
+        assert!(wrapper.storage_usage().unwrap().is_empty());
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_storage_report_reduced_when_unsupported() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+
+        let mut cookies = HashMap::new();
+        cookies.insert("https://cookies.com".to_string(), 4);
+        wrapper.set_cookie_counts(cookies);
+
+        assert!(matches!(
+            wrapper.storage_usage(),
+            Err(Error::Unsupported(_))
+        ));
+        let report = wrapper.storage_report().unwrap();
+        assert!(report.unsupported.is_some());
+        assert_eq!(report.origins.len(), 1);
+        assert_eq!(report.origins[0].cookies, 4);
+
+        assert!(matches!(
+            wrapper.clear_origin_storage("https://a.com", &[StorageKind::LocalStorage]),
+            Err(Error::Unsupported(_))
+        ));
+
+        bus.shutdown().unwrap();
+    }
+
+    // ========================================
+    // CONTENT SETTINGS TESTS
+    // ========================================
+
+    #[test]
+    fn test_navigate_applies_javascript_setting_per_origin() {
+        use config_manager::{ContentDefaults, ContentKind};
+
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        wrapper.set_backend(backend.clone());
+
+        // Without settings attached, the backend is left alone
+        wrapper.navigate("https://example.com/").unwrap();
+        assert!(backend.javascript_toggles().is_empty());
+
+        let mut settings = ContentSettings::new(ContentDefaults::default());
+        settings.set_override("https://noscript.com", ContentKind::JavaScript, Some(false));
+        settings.set_override("https://example.com", ContentKind::Images, Some(false));
+        wrapper.set_content_settings(7, Arc::new(Mutex::new(settings)));
+
+        wrapper.navigate("https://example.com/a").unwrap();
+        wrapper.navigate("https://noscript.com/page").unwrap();
+        wrapper.navigate("https://example.com/b").unwrap();
+
+        assert_eq!(
+            backend.javascript_toggles(),
+            vec![(7, true), (7, false), (7, true)]
+        );
+        assert_eq!(
+            backend.image_toggles(),
+            vec![(7, false), (7, true), (7, false)]
+        );
+        assert_eq!(wrapper.current_url(), Some("https://example.com/b"));
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_navigate_tolerates_unsupported_javascript_toggle() {
+        use config_manager::ContentDefaults;
+
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        wrapper.set_content_settings(
+            1,
+            Arc::new(Mutex::new(ContentSettings::new(ContentDefaults::default()))),
+        );
+
+        // The default native backend cannot toggle JavaScript
+        assert!(wrapper.navigate("https://example.com/").is_ok());
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_feed_document_uses_streaming_backend() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        wrapper.set_backend(backend.clone());
+
+        wrapper
+            .feed_document(DocumentChunk::Begin {
+                url: "https://example.com/".to_string(),
+            })
+            .unwrap();
+        wrapper
+            .feed_document(DocumentChunk::Data(b"<p>hi</p>".to_vec()))
+            .unwrap();
+        wrapper.feed_document(DocumentChunk::End).unwrap();
+
+        let chunks: Vec<DocumentChunk> = backend
+            .streamed_chunks()
+            .into_iter()
+            .map(|(_, chunk, _)| chunk)
+            .collect();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[1], DocumentChunk::Data(b"<p>hi</p>".to_vec()));
+        assert_eq!(wrapper.current_url(), Some("https://example.com/"));
+        assert!(wrapper.document_stream_url().is_none());
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_internal_page_is_served_with_csp_header() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        wrapper.set_backend(backend.clone());
+
+        let url = wrapper
+            .load_internal_page("about:history", "<p>history</p>", "default-src 'none'")
+            .unwrap();
+        // Even a backend that streams HTML cannot attach headers
+        assert!(backend.streamed_chunks().is_empty());
+        assert_eq!(wrapper.current_url(), Some("about:history"));
+        assert!(wrapper.document_stream_url().is_none());
+
+        let response = wrapper.document_protocol().respond(&url);
+        assert_eq!(
+            response.header("Content-Security-Policy"),
+            Some("default-src 'none'")
+        );
+        assert!(String::from_utf8(response.body)
+            .unwrap()
+            .contains("<p>history</p>"));
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_feed_document_falls_back_to_document_protocol() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        backend.set_streaming_supported(false);
+        wrapper.set_backend(backend.clone());
+
+        wrapper
+            .feed_document(DocumentChunk::Begin {
+                url: "https://example.com/".to_string(),
+            })
+            .unwrap();
+        let url = wrapper.document_stream_url().unwrap().to_string();
+        assert!(url.contains("https.443.example.com"));
+        wrapper
+            .feed_document(DocumentChunk::Data(b"<p>hi</p>".to_vec()))
+            .unwrap();
+        wrapper.feed_document(DocumentChunk::End).unwrap();
+        assert!(backend.streamed_chunks().is_empty());
+
+        // Request the document the way the webview would
+        let response = wrapper.document_protocol().respond(&url);
+        assert_eq!(response.status, 200);
+        assert!(String::from_utf8(response.body)
+            .unwrap()
+            .contains("<p>hi</p>"));
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_navigate_runs_user_scripts_for_url() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        wrapper.set_backend(backend.clone());
+        wrapper.set_user_scripts(Arc::new(|url: &str| {
+            if url.contains("example.com") {
+                vec!["first()".to_string(), "second()".to_string()]
+            } else {
+                Vec::new()
+            }
+        }));
+
+        wrapper.navigate("https://example.com/").unwrap();
+        wrapper.navigate("https://other.org/").unwrap();
+
+        assert_eq!(
+            backend.user_scripts_run(),
+            vec![(0, "first()".to_string()), (0, "second()".to_string())]
+        );
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_form_handler_receives_captured_submissions() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        wrapper.set_backend(backend.clone());
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        wrapper.set_form_handler(Arc::new(move |submission| {
+            sink.lock().unwrap().push(submission);
+        }));
+
+        wrapper.navigate("https://example.com/form").unwrap();
+        let scripts = backend.user_scripts_run();
+        assert_eq!(scripts.len(), 1);
+        assert!(scripts[0].1.contains("'formSubmit'"));
+
+        let message = bridge_message(
+            &wrapper,
+            1,
+            "formSubmit",
+            serde_json::json!({
+                "method": "POST",
+                "action": "https://example.com/login",
+                "enctype": "application/x-www-form-urlencoded",
+                "fields": [["user", "ada"]]
+            }),
+        );
+        wrapper.handle_ipc_message(&message).unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].method, "POST");
+        assert_eq!(received[0].url, "https://example.com/login");
+        assert_eq!(received[0].body, b"user=ada");
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_find_in_page_runs_scripts_and_reports() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        wrapper.set_backend(backend.clone());
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        wrapper.set_find_handler(Arc::new(move |report| {
+            sink.lock().unwrap().push((report.current, report.total));
+        }));
+
+        let options = FindOptions {
+            whole_word: true,
+            ..FindOptions::default()
+        };
+        wrapper.find_in_page("cat", &options).unwrap();
+        wrapper.find_next().unwrap();
+        wrapper.find_previous().unwrap();
+        wrapper.stop_finding().unwrap();
+        let scripts = backend.user_scripts_run();
+        assert_eq!(scripts.len(), 4);
+        assert!(scripts[0].1.contains(r#"start("cat", false, true);"#));
+        assert!(scripts[1].1.contains("step(1)"));
+        assert!(scripts[2].1.contains("step(-1)"));
+        assert!(scripts[3].1.contains("clear()"));
+
+        for (seq, current) in [(1, 1), (2, 2)] {
+            let message = bridge_message(
+                &wrapper,
+                seq,
+                "findResult",
+                serde_json::json!({ "current": current, "total": 17 }),
+            );
+            wrapper.handle_ipc_message(&message).unwrap();
+        }
+        assert_eq!(*received.lock().unwrap(), [(1, 17), (2, 17)]);
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_title_handler_receives_title_changes() {
+        use config_manager::ContentDefaults;
+
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        wrapper.set_backend(backend.clone());
+        wrapper.set_content_settings(
+            4,
+            Arc::new(Mutex::new(ContentSettings::new(ContentDefaults::default()))),
+        );
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        wrapper.set_title_handler(Arc::new(move |change| {
+            sink.lock().unwrap().push(change);
+        }));
+
+        wrapper.navigate("https://example.com/inbox").unwrap();
+        let scripts = backend.user_scripts_run();
+        assert_eq!(scripts.len(), 1);
+        assert!(scripts[0].1.contains("'titleChanged'"));
+
+        for (seq, title) in [(1, "Inbox"), (2, "(1) Inbox")] {
+            let message = bridge_message(
+                &wrapper,
+                seq,
+                "titleChanged",
+                serde_json::json!({ "title": title }),
+            );
+            wrapper.handle_ipc_message(&message).unwrap();
+        }
+
+        let received = received.lock().unwrap();
+        assert_eq!(
+            *received,
+            vec![
+                crate::TitleChanged {
+                    tab_id: 4,
+                    title: "Inbox".to_string(),
+                },
+                crate::TitleChanged {
+                    tab_id: 4,
+                    title: "(1) Inbox".to_string(),
+                },
+            ]
+        );
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_link_hover_handler_receives_hovered_links() {
+        use config_manager::ContentDefaults;
+
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        wrapper.set_backend(backend.clone());
+        wrapper.set_content_settings(
+            5,
+            Arc::new(Mutex::new(ContentSettings::new(ContentDefaults::default()))),
+        );
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        wrapper.set_link_hover_handler(Arc::new(move |hovered| {
+            sink.lock().unwrap().push(hovered);
+        }));
+
+        wrapper.navigate("https://example.com/").unwrap();
+        let scripts = backend.user_scripts_run();
+        assert_eq!(scripts.len(), 1);
+        assert!(scripts[0].1.contains("'linkHovered'"));
+
+        let hovers = [
+            serde_json::json!({ "url": "https://example.com/next" }),
+            serde_json::json!({ "url": null }),
+        ];
+        for (seq, data) in hovers.into_iter().enumerate() {
+            let message = bridge_message(&wrapper, seq as u64 + 1, "linkHovered", data);
+            wrapper.handle_ipc_message(&message).unwrap();
+        }
+
+        let received = received.lock().unwrap();
+        assert_eq!(
+            *received,
+            vec![
+                crate::LinkHovered {
+                    tab_id: 5,
+                    url: Some(url::Url::parse("https://example.com/next").unwrap()),
+                },
+                crate::LinkHovered {
+                    tab_id: 5,
+                    url: None,
+                },
+            ]
+        );
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_blocked_content_placeholders() {
+        use config_manager::ContentDefaults;
+
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        wrapper.set_backend(backend.clone());
+        wrapper.set_content_settings(
+            5,
+            Arc::new(Mutex::new(ContentSettings::new(ContentDefaults::default()))),
+        );
+        let clicks = Arc::new(Mutex::new(Vec::new()));
+        let sink = clicks.clone();
+        wrapper.set_blocked_content_handler(Arc::new(move |load| {
+            sink.lock().unwrap().push(load);
+        }));
+        let image = "https://ads.example/banner.png";
+        let report = vec![
+            (image.to_string(), ResourceType::Image),
+            ("https://ads.example/a.js".to_string(), ResourceType::Script),
+        ];
+
+        // Off by default
+        assert!(!wrapper.show_blocked_placeholders(&report).unwrap());
+        wrapper.set_show_placeholders(true);
+        let scripts_only = &report[1..];
+        assert!(!wrapper.show_blocked_placeholders(scripts_only).unwrap());
+        assert!(backend.user_scripts_run().is_empty());
+
+        assert!(wrapper.show_blocked_placeholders(&report).unwrap());
+        let scripts = backend.user_scripts_run();
+        assert_eq!(scripts.len(), 1);
+        assert_eq!(scripts[0].0, 5);
+        assert!(scripts[0].1.contains(image));
+        assert!(!scripts[0].1.contains("a.js"));
+
+        let message = bridge_message(
+            &wrapper,
+            1,
+            "blockedContentLoad",
+            serde_json::json!({ "url": image }),
+        );
+        wrapper.handle_ipc_message(&message).unwrap();
+        assert_eq!(
+            *clicks.lock().unwrap(),
+            vec![crate::BlockedContentLoad {
+                tab_id: 5,
+                url: image.to_string(),
+            }]
+        );
+
+        wrapper.load_blocked_resource(image).unwrap();
+        let scripts = backend.user_scripts_run();
+        assert_eq!(scripts.len(), 2);
+        assert!(scripts[1].1.contains(&format!("var url = \"{}\";", image)));
+
+        // Private tabs never show placeholders
+        wrapper.set_private(true);
+        assert!(!wrapper.show_blocked_placeholders(&report).unwrap());
+        assert_eq!(backend.user_scripts_run().len(), 2);
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_viewport_links_reported_except_in_private_tabs() {
+        use config_manager::ContentDefaults;
+
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        wrapper.set_backend(backend.clone());
+        wrapper.set_content_settings(
+            6,
+            Arc::new(Mutex::new(ContentSettings::new(ContentDefaults::default()))),
+        );
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        wrapper.set_viewport_links_handler(Arc::new(move |links| {
+            sink.lock().unwrap().push(links);
+        }));
+
+        wrapper.navigate("https://news.example/").unwrap();
+        let scripts = backend.user_scripts_run();
+        assert_eq!(scripts.len(), 1);
+        assert!(scripts[0].1.contains("'viewportLinks'"));
+
+        let message = bridge_message(
+            &wrapper,
+            1,
+            "viewportLinks",
+            serde_json::json!({ "hosts": ["CDN.example.com", "cdn.example.com"] }),
+        );
+        wrapper.handle_ipc_message(&message).unwrap();
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![crate::ViewportLinks {
+                tab_id: 6,
+                hosts: vec!["cdn.example.com".to_string()],
+            }]
+        );
+
+        wrapper.set_private(true);
+        wrapper.navigate("https://news.example/2").unwrap();
+        assert_eq!(backend.user_scripts_run().len(), 1);
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_bridge_token_changes_with_each_page() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        wrapper.set_backend(backend.clone());
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = received.clone();
+        wrapper.set_title_handler(Arc::new(move |change| {
+            sink.lock().unwrap().push(change.title);
+        }));
+
+        wrapper.navigate("https://example.com/a").unwrap();
+        let first = wrapper.bridge_token();
+        let stale = bridge_message(
+            &wrapper,
+            1,
+            "titleChanged",
+            serde_json::json!({"title": "A"}),
+        );
+        wrapper.navigate("https://example.com/b").unwrap();
+        assert_ne!(wrapper.bridge_token(), first);
+
+        // Each page's bootstrap carries its own token
+        let scripts = backend.bridge_scripts();
+        assert_eq!(scripts.len(), 2);
+        assert!(scripts[0].1.contains(&first));
+        assert!(scripts[1].1.contains(&wrapper.bridge_token()));
+
+        // The previous page, spoofed tokens and replays are dropped
+        let spoofed = stale.replace(&first, &"0".repeat(32));
+        let current = bridge_message(
+            &wrapper,
+            1,
+            "titleChanged",
+            serde_json::json!({"title": "B"}),
+        );
+        for raw in [&stale, &spoofed, &current, &current] {
+            wrapper.handle_ipc_message(raw).unwrap();
+        }
+        assert_eq!(*received.lock().unwrap(), vec!["B".to_string()]);
+
+        assert!(matches!(
+            wrapper.handle_ipc_message(&format!("[{}]", vec![current; 65].join(","))),
+            Err(Error::Ipc(_))
+        ));
+
+        bus.shutdown().unwrap();
+    }
+
     // ========================================
     // GUI MODE NOTES
     // ========================================
@@ -694,4 +2441,224 @@ mod tests {
     // - Compilation with --features gui succeeds
     // - Integration tests in browser_shell component
     // - Manual testing with actual application
+
+    // ========================================
+    // USER STYLESHEET TESTS
+    // ========================================
+
+    fn reduce_motion() -> AccessibilitySettings {
+        AccessibilitySettings {
+            reduce_motion: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_user_stylesheet_set_before_document() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        wrapper.set_backend(backend.clone());
+
+        // Nothing shown yet, so no reload is needed
+        assert!(!wrapper.set_accessibility(&reduce_motion()).unwrap());
+
+        wrapper
+            .feed_document(DocumentChunk::Begin {
+                url: "https://example.com/".to_string(),
+            })
+            .unwrap();
+        wrapper
+            .feed_document(DocumentChunk::Data(b"<style>p {}</style>".to_vec()))
+            .unwrap();
+        wrapper.feed_document(DocumentChunk::End).unwrap();
+
+        let stylesheets = backend.user_stylesheets();
+        assert_eq!(stylesheets.len(), 1);
+        assert_eq!(stylesheets[0].1, crate::generate_css(&reduce_motion()));
+        let chunks = backend.streamed_chunks();
+        assert!(stylesheets[0].2 <= chunks[0].2);
+
+        wrapper.navigate("https://example.com/next").unwrap();
+        assert_eq!(backend.user_stylesheets().len(), 2);
+        // The native stylesheet makes the injector unnecessary
+        assert!(backend.user_scripts_run().is_empty());
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_user_stylesheet_injected_before_user_scripts() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        backend.set_user_stylesheets_supported(false);
+        wrapper.set_backend(backend.clone());
+        wrapper.set_user_scripts(Arc::new(|_: &str| vec!["first()".to_string()]));
+        wrapper.set_accessibility(&reduce_motion()).unwrap();
+
+        wrapper.navigate("https://example.com/").unwrap();
+
+        let scripts = backend.user_scripts_run();
+        assert_eq!(scripts.len(), 2);
+        assert!(scripts[0].1.contains(crate::USER_STYLESHEET_ID));
+        assert!(scripts[0].1.contains("animation: none !important;"));
+        assert_eq!(scripts[1].1, "first()");
+        assert!(backend.user_stylesheets().is_empty());
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_set_accessibility_asks_for_reload() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        wrapper.set_backend(Arc::new(MockBackend::new()));
+        wrapper.navigate("https://example.com/").unwrap();
+
+        assert!(wrapper.set_accessibility(&reduce_motion()).unwrap());
+        assert!(!wrapper.set_accessibility(&reduce_motion()).unwrap());
+        assert!(wrapper
+            .set_accessibility(&AccessibilitySettings::default())
+            .unwrap());
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_custom_stylesheet_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("user.css");
+        std::fs::write(&path, "p { color: red; }\n").unwrap();
+
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        wrapper.set_backend(backend.clone());
+        let settings = AccessibilitySettings {
+            custom_css_path: path.to_string_lossy().into_owned(),
+            ..reduce_motion()
+        };
+        wrapper.set_accessibility(&settings).unwrap();
+        wrapper.navigate("https://example.com/").unwrap();
+        assert!(!wrapper.reload_user_stylesheet().unwrap());
+
+        std::fs::write(&path, "p { color: blue; }\n").unwrap();
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(5);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert!(wrapper.reload_user_stylesheet().unwrap());
+
+        wrapper.navigate("https://example.com/").unwrap();
+        let stylesheets = backend.user_stylesheets();
+        assert!(stylesheets[0].1.ends_with("p { color: red; }\n"));
+        assert!(stylesheets[1].1.ends_with("p { color: blue; }\n"));
+
+        // An unreadable file is rejected and the old stylesheet kept
+        let missing = AccessibilitySettings {
+            custom_css_path: dir
+                .path()
+                .join("missing.css")
+                .to_string_lossy()
+                .into_owned(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            wrapper.set_accessibility(&missing),
+            Err(Error::UserStylesheet(_))
+        ));
+        wrapper.navigate("https://example.com/").unwrap();
+        assert!(backend.user_stylesheets()[2].1.contains("color: blue"));
+
+        bus.shutdown().unwrap();
+    }
+
+    // ========================================
+    // FONT SETTINGS TESTS
+    // ========================================
+
+    #[test]
+    fn test_font_settings_pushed_before_each_navigation() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        wrapper.set_backend(backend.clone());
+        let fonts = FontSettings {
+            serif_family: "DejaVu Serif".to_string(),
+            monospace_family: "Fira Mono".to_string(),
+            default_font_size: 18,
+            minimum_font_size: 11,
+            ..FontSettings::default()
+        };
+        wrapper.set_fonts(&fonts, "windows-1252");
+        wrapper.navigate("https://example.com/").unwrap();
+
+        // A reloaded config applies to the next navigation
+        let reloaded = FontSettings {
+            default_monospace_size: 15,
+            ..fonts.clone()
+        };
+        wrapper.set_fonts(&reloaded, "");
+        wrapper
+            .feed_document(DocumentChunk::Begin {
+                url: "https://example.com/next".to_string(),
+            })
+            .unwrap();
+
+        assert_eq!(
+            backend.font_settings(),
+            vec![
+                (0, fonts, "windows-1252".to_string()),
+                (0, reloaded, String::new())
+            ]
+        );
+        // The native minimum needs no stylesheet
+        assert!(backend.user_stylesheets().is_empty());
+
+        bus.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_minimum_font_size_falls_back_to_stylesheet() {
+        let mut bus = MessageBus::new();
+        bus.start().unwrap();
+        let mut wrapper = WebViewWrapper::new(bus.sender()).unwrap();
+        let backend = Arc::new(MockBackend::new());
+        backend.set_font_settings_supported(false);
+        wrapper.set_backend(backend.clone());
+        let fonts = FontSettings {
+            minimum_font_size: 18,
+            ..FontSettings::default()
+        };
+        wrapper.set_fonts(&fonts, "");
+        wrapper.navigate("https://example.com/").unwrap();
+
+        let stylesheets = backend.user_stylesheets();
+        assert_eq!(stylesheets.len(), 1);
+        assert_eq!(
+            stylesheets[0].1,
+            crate::generate_css(&AccessibilitySettings {
+                min_font_px: 18,
+                ..Default::default()
+            })
+        );
+
+        // Accessibility settings changed later keep the floor
+        wrapper.set_accessibility(&reduce_motion()).unwrap();
+        wrapper.navigate("https://example.com/").unwrap();
+        let css = &backend.user_stylesheets()[1].1;
+        assert!(css.contains("max(100%, 18px)") && css.contains("animation: none"));
+        assert!(backend.font_settings().is_empty());
+
+        bus.shutdown().unwrap();
+    }
 }
@@ -5,7 +5,6 @@
 
 use message_bus::MessageBus;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
 
 /// Test helper to create a message bus with handlers
 pub fn setup_message_bus() -> MessageBus {
@@ -14,9 +13,9 @@ pub fn setup_message_bus() -> MessageBus {
     bus
 }
 
-/// Test helper to wait for async operations
-pub fn wait_for_processing() {
-    std::thread::sleep(Duration::from_millis(100));
+/// Test helper to wait until the bus has handled every queued message
+pub fn wait_for_processing(bus: &MessageBus) {
+    bus.flush().expect("Failed to flush message bus");
 }
 
 /// Test helper to create a network config for testing
@@ -27,6 +26,10 @@ pub fn test_network_config() -> config_manager::NetworkConfig {
         enable_cookies: true,
         enable_cache: true,
         cache_size_mb: 10, // Small cache for testing
+        cache_backend: config_manager::CacheBackend::Memory,
+        cookie_file: None,
+        proxy: None,
+        no_proxy: Vec::new(),
     }
 }
 
@@ -40,6 +43,7 @@ pub fn test_adblock_config() -> config_manager::AdBlockConfig {
             "||ads.example.com^".to_string(),
             "||doubleclick.net^".to_string(),
         ],
+        allowlisted_domains: vec![],
     }
 }
 
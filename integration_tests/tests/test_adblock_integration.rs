@@ -50,6 +50,7 @@ fn test_adblock_engine_blocks_custom_filter() {
     // Test custom filter: ||ads.example.com^
     let should_block = engine.should_block(
         "https://ads.example.com/banner.js",
+        "",
         ResourceType::Script,
     );
 
@@ -70,6 +71,7 @@ fn test_adblock_engine_blocks_doubleclick() {
     // Test custom filter: ||doubleclick.net^
     let should_block = engine.should_block(
         "https://doubleclick.net/ad.js",
+        "",
         ResourceType::Script,
     );
 
@@ -90,6 +92,7 @@ fn test_adblock_engine_allows_non_ad_url() {
     // Regular URL should not be blocked
     let should_block = engine.should_block(
         "https://example.com/script.js",
+        "",
         ResourceType::Script,
     );
 
@@ -110,9 +113,9 @@ fn test_adblock_engine_handles_different_resource_types() {
     // Test same URL with different resource types
     let ad_url = "https://ads.example.com/banner";
 
-    let blocked_script = engine.should_block(ad_url, ResourceType::Script);
-    let blocked_image = engine.should_block(ad_url, ResourceType::Image);
-    let blocked_stylesheet = engine.should_block(ad_url, ResourceType::Stylesheet);
+    let blocked_script = engine.should_block(ad_url, "", ResourceType::Script);
+    let blocked_image = engine.should_block(ad_url, "", ResourceType::Image);
+    let blocked_stylesheet = engine.should_block(ad_url, "", ResourceType::Stylesheet);
 
     // All should be blocked regardless of type
     assert!(blocked_script, "Script should be blocked");
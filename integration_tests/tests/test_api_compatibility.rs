@@ -6,7 +6,7 @@
 mod common;
 
 use common::{setup_message_bus, test_adblock_config, test_network_config};
-use shared_types::{BrowserMessage, ResourceType};
+use shared_types::{BrowserMessage, NavigationInitiator, ResourceType};
 
 #[test]
 fn test_resource_type_recognized_by_all_components() {
@@ -38,6 +38,7 @@ fn test_browser_message_variants_work_across_components() {
     let _nav_req = BrowserMessage::NavigateRequest {
         tab_id: 1,
         url: url.clone(),
+        initiator: NavigationInitiator::UserTyped,
     };
     let _nav_resp = BrowserMessage::NavigateResponse {
         tab_id: 1,
@@ -52,11 +53,17 @@ fn test_browser_message_variants_work_across_components() {
     let _http_req = BrowserMessage::HttpRequest {
         request_id: 1,
         url: url.clone(),
+        method: "GET".to_string(),
         headers: std::collections::HashMap::new(),
+        body: None,
+        initiator: None,
     };
     let _http_resp = BrowserMessage::HttpResponse {
         request_id: 1,
         status: 200,
+        headers: std::collections::HashMap::new(),
+        final_url: url.clone(),
+        from_cache: false,
         body: vec![],
     };
 
@@ -64,6 +71,7 @@ fn test_browser_message_variants_work_across_components() {
     let _should_block = BrowserMessage::ShouldBlock {
         url: url.clone(),
         resource_type: ResourceType::Script,
+        tab_id: None,
     };
     let _block_decision = BrowserMessage::BlockDecision {
         block: true,
@@ -161,6 +169,7 @@ fn test_url_type_compatible_across_all_components() {
     let _msg = BrowserMessage::NavigateRequest {
         tab_id: 1,
         url: url.clone(),
+        initiator: NavigationInitiator::UserTyped,
     };
 
     // Used by NetworkStack (fetch method takes Url)
@@ -203,6 +212,7 @@ fn test_serialization_compatibility() {
     let msg = BrowserMessage::NavigateRequest {
         tab_id: 1,
         url: Url::parse("https://example.com").unwrap(),
+        initiator: NavigationInitiator::UserTyped,
     };
 
     // Serialize
@@ -213,7 +223,7 @@ fn test_serialization_compatibility() {
 
     // Should match
     match deserialized {
-        BrowserMessage::NavigateRequest { tab_id, url } => {
+        BrowserMessage::NavigateRequest { tab_id, url, .. } => {
             assert_eq!(tab_id, 1);
             assert_eq!(url.as_str(), "https://example.com/");
         }
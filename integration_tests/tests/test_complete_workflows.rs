@@ -6,7 +6,7 @@
 mod common;
 
 use common::{setup_message_bus, wait_for_processing, MessageCollector};
-use shared_types::{BrowserMessage, ResourceType};
+use shared_types::{BrowserMessage, NavigationInitiator, ResourceType};
 use url::Url;
 
 #[test]
@@ -26,6 +26,7 @@ fn test_complete_navigation_workflow() {
         .send(BrowserMessage::NavigateRequest {
             tab_id,
             url: url.clone(),
+            initiator: NavigationInitiator::UserTyped,
         })
         .unwrap();
 
@@ -37,7 +38,7 @@ fn test_complete_navigation_workflow() {
         })
         .unwrap();
 
-    wait_for_processing();
+    wait_for_processing(&bus);
     bus.shutdown().unwrap();
 
     // Verify workflow messages
@@ -45,7 +46,11 @@ fn test_complete_navigation_workflow() {
     assert_eq!(messages.len(), 2, "Should have 2 messages in workflow");
 
     match &messages[0] {
-        BrowserMessage::NavigateRequest { tab_id: tid, url: u } => {
+        BrowserMessage::NavigateRequest {
+            tab_id: tid,
+            url: u,
+            ..
+        } => {
             assert_eq!(*tid, tab_id);
             assert_eq!(u.as_str(), "https://example.com/");
         }
@@ -76,12 +81,16 @@ fn test_tab_management_workflow() {
 
     let url = Url::parse("https://example.com").unwrap();
     sender
-        .send(BrowserMessage::NavigateRequest { tab_id: 2, url })
+        .send(BrowserMessage::NavigateRequest {
+            tab_id: 2,
+            url,
+            initiator: NavigationInitiator::UserTyped,
+        })
         .unwrap();
 
     sender.send(BrowserMessage::CloseTab { tab_id: 2 }).unwrap();
 
-    wait_for_processing();
+    wait_for_processing(&bus);
     bus.shutdown().unwrap();
 
     let messages = collector.get_messages();
@@ -110,6 +119,7 @@ fn test_adblock_workflow() {
         .send(BrowserMessage::ShouldBlock {
             url: ad_url.clone(),
             resource_type: ResourceType::Script,
+            tab_id: Some(1),
         })
         .unwrap();
 
@@ -120,16 +130,21 @@ fn test_adblock_workflow() {
         })
         .unwrap();
 
-    wait_for_processing();
+    wait_for_processing(&bus);
     bus.shutdown().unwrap();
 
     let messages = collector.get_messages();
     assert_eq!(messages.len(), 2);
 
     match &messages[0] {
-        BrowserMessage::ShouldBlock { url, resource_type } => {
+        BrowserMessage::ShouldBlock {
+            url,
+            resource_type,
+            tab_id,
+        } => {
             assert_eq!(url.as_str(), "https://ads.example.com/banner.js");
             assert!(matches!(resource_type, ResourceType::Script));
+            assert_eq!(*tab_id, Some(1));
         }
         _ => panic!("Expected ShouldBlock"),
     }
@@ -159,6 +174,7 @@ fn test_navigation_with_error_workflow() {
         .send(BrowserMessage::NavigateRequest {
             tab_id,
             url: url.clone(),
+            initiator: NavigationInitiator::UserTyped,
         })
         .unwrap();
 
@@ -170,7 +186,7 @@ fn test_navigation_with_error_workflow() {
         })
         .unwrap();
 
-    wait_for_processing();
+    wait_for_processing(&bus);
     bus.shutdown().unwrap();
 
     let messages = collector.get_messages();
@@ -203,6 +219,7 @@ fn test_browser_history_workflow() {
         .send(BrowserMessage::NavigateRequest {
             tab_id,
             url: url1,
+            initiator: NavigationInitiator::UserTyped,
         })
         .unwrap();
 
@@ -212,6 +229,7 @@ fn test_browser_history_workflow() {
         .send(BrowserMessage::NavigateRequest {
             tab_id,
             url: url2,
+            initiator: NavigationInitiator::UserTyped,
         })
         .unwrap();
 
@@ -221,7 +239,7 @@ fn test_browser_history_workflow() {
     // Go forward
     sender.send(BrowserMessage::GoForward { tab_id }).unwrap();
 
-    wait_for_processing();
+    wait_for_processing(&bus);
     bus.shutdown().unwrap();
 
     let messages = collector.get_messages();
@@ -249,13 +267,14 @@ fn test_reload_workflow() {
         .send(BrowserMessage::NavigateRequest {
             tab_id,
             url: url.clone(),
+            initiator: NavigationInitiator::UserTyped,
         })
         .unwrap();
 
     // Reload
     sender.send(BrowserMessage::Reload { tab_id }).unwrap();
 
-    wait_for_processing();
+    wait_for_processing(&bus);
     bus.shutdown().unwrap();
 
     let messages = collector.get_messages();
@@ -288,12 +307,20 @@ fn test_multiple_tabs_workflow() {
     // Navigate in different tabs
     let url1 = Url::parse("https://tab1.com").unwrap();
     sender
-        .send(BrowserMessage::NavigateRequest { tab_id: 1, url: url1 })
+        .send(BrowserMessage::NavigateRequest {
+            tab_id: 1,
+            url: url1,
+            initiator: NavigationInitiator::UserTyped,
+        })
         .unwrap();
 
     let url2 = Url::parse("https://tab2.com").unwrap();
     sender
-        .send(BrowserMessage::NavigateRequest { tab_id: 2, url: url2 })
+        .send(BrowserMessage::NavigateRequest {
+            tab_id: 2,
+            url: url2,
+            initiator: NavigationInitiator::UserTyped,
+        })
         .unwrap();
 
     // Switch tabs
@@ -303,7 +330,7 @@ fn test_multiple_tabs_workflow() {
     // Close middle tab
     sender.send(BrowserMessage::CloseTab { tab_id: 2 }).unwrap();
 
-    wait_for_processing();
+    wait_for_processing(&bus);
     bus.shutdown().unwrap();
 
     let messages = collector.get_messages();
@@ -324,7 +351,7 @@ fn test_shutdown_workflow() {
     sender.send(BrowserMessage::CloseTab { tab_id: 2 }).unwrap();
     sender.send(BrowserMessage::Shutdown).unwrap();
 
-    wait_for_processing();
+    wait_for_processing(&bus);
     bus.shutdown().unwrap();
 
     let messages = collector.get_messages();
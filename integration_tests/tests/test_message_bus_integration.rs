@@ -6,7 +6,12 @@
 mod common;
 
 use common::{setup_message_bus, wait_for_processing, MessageCollector};
-use shared_types::{BrowserMessage, ResourceType};
+use message_bus::{MessageBus, MessageHandler, MessageSender};
+use shared_types::{
+    BrowserMessage, NavigationInitiator, ResourceType, SequentialIds, TraceContext,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 use url::Url;
 
 #[test]
@@ -22,10 +27,14 @@ fn test_message_bus_routes_navigate_request() {
     // Send NavigateRequest message
     let url = Url::parse("https://example.com").unwrap();
     sender
-        .send(BrowserMessage::NavigateRequest { tab_id: 1, url: url.clone() })
+        .send(BrowserMessage::NavigateRequest {
+            tab_id: 1,
+            url: url.clone(),
+            initiator: NavigationInitiator::UserTyped,
+        })
         .expect("Failed to send message");
 
-    wait_for_processing();
+    wait_for_processing(&bus);
     bus.shutdown().expect("Failed to shutdown bus");
 
     // Verify message was routed to handler
@@ -33,7 +42,11 @@ fn test_message_bus_routes_navigate_request() {
     assert_eq!(messages.len(), 1);
 
     match &messages[0] {
-        BrowserMessage::NavigateRequest { tab_id, url: msg_url } => {
+        BrowserMessage::NavigateRequest {
+            tab_id,
+            url: msg_url,
+            ..
+        } => {
             assert_eq!(*tab_id, 1);
             assert_eq!(msg_url.as_str(), "https://example.com/");
         }
@@ -56,10 +69,11 @@ fn test_message_bus_routes_should_block_message() {
         .send(BrowserMessage::ShouldBlock {
             url: url.clone(),
             resource_type: ResourceType::Script,
+            tab_id: Some(1),
         })
         .expect("Failed to send message");
 
-    wait_for_processing();
+    wait_for_processing(&bus);
     bus.shutdown().expect("Failed to shutdown bus");
 
     // Verify message was routed
@@ -67,9 +81,14 @@ fn test_message_bus_routes_should_block_message() {
     assert_eq!(messages.len(), 1);
 
     match &messages[0] {
-        BrowserMessage::ShouldBlock { url: msg_url, resource_type } => {
+        BrowserMessage::ShouldBlock {
+            url: msg_url,
+            resource_type,
+            tab_id,
+        } => {
             assert_eq!(msg_url.as_str(), "https://ads.example.com/banner.js");
             assert!(matches!(resource_type, ResourceType::Script));
+            assert_eq!(*tab_id, Some(1));
         }
         _ => panic!("Expected ShouldBlock"),
     }
@@ -91,7 +110,7 @@ fn test_message_bus_routes_to_multiple_handlers() {
         .send(BrowserMessage::CreateTab { parent_window: 1 })
         .expect("Failed to send message");
 
-    wait_for_processing();
+    wait_for_processing(&bus);
     bus.shutdown().expect("Failed to shutdown bus");
 
     // Both handlers should receive the message
@@ -120,7 +139,7 @@ fn test_message_bus_handles_tab_management_messages() {
     sender.send(BrowserMessage::SwitchTab { tab_id: 2 }).unwrap();
     sender.send(BrowserMessage::CloseTab { tab_id: 1 }).unwrap();
 
-    wait_for_processing();
+    wait_for_processing(&bus);
     bus.shutdown().expect("Failed to shutdown bus");
 
     let messages = collector.get_messages();
@@ -145,10 +164,13 @@ fn test_message_bus_handles_navigation_workflow() {
     let url = Url::parse("https://example.com").unwrap();
 
     // Step 1: Navigate request
-    sender.send(BrowserMessage::NavigateRequest {
-        tab_id: 1,
-        url: url.clone()
-    }).unwrap();
+    sender
+        .send(BrowserMessage::NavigateRequest {
+            tab_id: 1,
+            url: url.clone(),
+            initiator: NavigationInitiator::UserTyped,
+        })
+        .unwrap();
 
     // Step 2: Navigate response (simulated)
     sender.send(BrowserMessage::NavigateResponse {
@@ -156,7 +178,7 @@ fn test_message_bus_handles_navigation_workflow() {
         content: vec![72, 101, 108, 108, 111], // "Hello"
     }).unwrap();
 
-    wait_for_processing();
+    wait_for_processing(&bus);
     bus.shutdown().expect("Failed to shutdown bus");
 
     let messages = collector.get_messages();
@@ -210,7 +232,7 @@ fn test_message_bus_concurrent_message_sending() {
     handle1.join().unwrap();
     handle2.join().unwrap();
 
-    wait_for_processing();
+    wait_for_processing(&bus);
     bus.shutdown().expect("Failed to shutdown bus");
 
     // All 10 messages should be received
@@ -229,10 +251,102 @@ fn test_message_bus_shutdown_message() {
     // Send shutdown message
     sender.send(BrowserMessage::Shutdown).unwrap();
 
-    wait_for_processing();
+    wait_for_processing(&bus);
     bus.shutdown().expect("Failed to shutdown bus");
 
     let messages = collector.get_messages();
     assert_eq!(messages.len(), 1);
     assert!(matches!(messages[0], BrowserMessage::Shutdown));
 }
+
+/// Answers each step of a page load with the next one, like the engine and
+/// network stack do
+struct PageLoadRelay {
+    sender: Arc<Mutex<Option<Box<dyn MessageSender>>>>,
+}
+
+impl MessageHandler for PageLoadRelay {
+    fn handle(&self, message: BrowserMessage) -> message_bus::Result<()> {
+        let next = match message {
+            BrowserMessage::NavigateRequest { url, .. } => BrowserMessage::HttpRequest {
+                request_id: 1,
+                url,
+                method: "GET".to_string(),
+                headers: HashMap::new(),
+                body: None,
+                initiator: None,
+            },
+            BrowserMessage::HttpRequest {
+                request_id, url, ..
+            } => BrowserMessage::HttpResponse {
+                request_id,
+                status: 200,
+                headers: HashMap::new(),
+                final_url: url,
+                from_cache: false,
+                body: b"<html></html>".to_vec(),
+            },
+            BrowserMessage::HttpResponse { body, .. } => BrowserMessage::NavigateResponse {
+                tab_id: 1,
+                content: body,
+            },
+            _ => return Ok(()),
+        };
+        let sender = self.sender.lock().unwrap();
+        sender.as_ref().expect("relay not wired").send(next)
+    }
+}
+
+#[test]
+fn test_trace_follows_navigate_fetch_response_chain() {
+    let mut bus = MessageBus::new();
+    bus.enable_tracing(8);
+    let relay_sender = Arc::new(Mutex::new(None));
+    bus.register_handler(Box::new(PageLoadRelay {
+        sender: Arc::clone(&relay_sender),
+    }));
+    bus.start().unwrap();
+    *relay_sender.lock().unwrap() = Some(bus.sender());
+
+    let sender = bus.sender();
+    // Untraced traffic stays out of the log
+    sender.send(BrowserMessage::Reload { tab_id: 2 }).unwrap();
+
+    let root = TraceContext::root(&SequentialIds::new(500));
+    {
+        let _trace = root.enter();
+        sender
+            .send(BrowserMessage::NavigateRequest {
+                tab_id: 1,
+                url: Url::parse("https://example.com/").unwrap(),
+                initiator: NavigationInitiator::UserTyped,
+            })
+            .unwrap();
+    }
+    wait_for_processing(&bus);
+    bus.shutdown().expect("Failed to shutdown bus");
+
+    let traces = bus.trace_log().recent(8);
+    assert_eq!(traces.len(), 1);
+    let trace = &traces[0];
+    assert_eq!(trace.trace_id, 500);
+    let kinds: Vec<&str> = trace
+        .spans
+        .iter()
+        .map(|span| span.message.as_str())
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            "NavigateRequest",
+            "HttpRequest",
+            "HttpResponse",
+            "NavigateResponse"
+        ]
+    );
+    assert_eq!(trace.spans[0].parent_span, None);
+    for pair in trace.spans.windows(2) {
+        assert_eq!(pair[1].parent_span, Some(pair[0].span_id));
+    }
+    assert!(trace.spans.iter().all(|span| span.handled_in.is_some()));
+}
@@ -3,7 +3,6 @@
  *
  * This script is injected into every page loaded by the browser.
  * It provides:
- * - IPC communication channel between WebView and Rust
  * - Console redirection for debugging
  * - Error handling and reporting
  * - Browser API polyfills
@@ -19,76 +18,15 @@
     window.__frankenbrowser_initialized = true;
 
     /**
-     * IPC Message Handler
-     * Provides bidirectional communication between JavaScript and Rust
+     * IPC
+     * window.ipc (send, on, _dispatch) is set up by the bridge bootstrap
+     * from webview_integration/src/protocol.rs, which runs first and stamps
+     * every message with the protocol version, the page's token and a
+     * sequence number. Without it nothing here can report anything.
      */
-    window.ipc = {
-        /**
-         * Send a message to Rust backend
-         * @param {string} channel - Message channel/type
-         * @param {*} data - Message payload (must be JSON-serializable)
-         */
-        send: function(channel, data) {
-            try {
-                const message = {
-                    channel: channel,
-                    data: data,
-                    timestamp: Date.now()
-                };
-
-                // Use platform-specific IPC mechanism
-                if (window.webkit && window.webkit.messageHandlers && window.webkit.messageHandlers.ipc) {
-                    // macOS/iOS WebKit
-                    window.webkit.messageHandlers.ipc.postMessage(message);
-                } else if (window.chrome && window.chrome.webview) {
-                    // Windows WebView2
-                    window.chrome.webview.postMessage(message);
-                } else if (window.external && window.external.invoke) {
-                    // Legacy Windows/Linux
-                    window.external.invoke(JSON.stringify(message));
-                } else {
-                    console.error('FrankenBrowser: No IPC mechanism available');
-                }
-            } catch (error) {
-                console.error('FrankenBrowser IPC send error:', error);
-            }
-        },
-
-        /**
-         * Register a callback for messages from Rust
-         * @param {string} channel - Message channel to listen on
-         * @param {function} callback - Callback function(data)
-         */
-        on: function(channel, callback) {
-            if (!window.__ipc_callbacks) {
-                window.__ipc_callbacks = {};
-            }
-
-            if (!window.__ipc_callbacks[channel]) {
-                window.__ipc_callbacks[channel] = [];
-            }
-
-            window.__ipc_callbacks[channel].push(callback);
-        },
-
-        /**
-         * Internal: Dispatch incoming message to registered callbacks
-         * @private
-         */
-        _dispatch: function(channel, data) {
-            if (!window.__ipc_callbacks || !window.__ipc_callbacks[channel]) {
-                return;
-            }
-
-            window.__ipc_callbacks[channel].forEach(function(callback) {
-                try {
-                    callback(data);
-                } catch (error) {
-                    console.error('FrankenBrowser callback error:', error);
-                }
-            });
-        }
-    };
+    if (!window.ipc) {
+        return;
+    }
 
     /**
      * Console Redirection
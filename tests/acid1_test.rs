@@ -41,6 +41,8 @@ mod acid1_tests {
             enable_devtools: false,
             theme: "light".to_string(),
             default_zoom: 1.0,
+            ctrl_tab_mru: false,
+            user_agent: None,
         };
 
         // Create browser shell
@@ -150,19 +150,24 @@ fn test_build_script_exists() {
 fn test_init_js_ipc_interface() {
     let init_js = Path::new("resources/init.js");
     let content = std::fs::read_to_string(init_js).expect("Failed to read init.js");
+    assert!(
+        content.contains("window.ipc.send"),
+        "init.js should report through window.ipc"
+    );
 
-    // Verify IPC interface methods
+    // window.ipc itself comes from the bridge bootstrap, which runs first
+    let bootstrap = webview_integration::protocol::bridge_bootstrap_script("token");
     assert!(
-        content.contains("send:") || content.contains("ipc.send"),
-        "init.js should define ipc.send method"
+        bootstrap.contains("send:"),
+        "bridge bootstrap should define ipc.send method"
     );
     assert!(
-        content.contains("on:") || content.contains("ipc.on"),
-        "init.js should define ipc.on method for callbacks"
+        bootstrap.contains("on:"),
+        "bridge bootstrap should define ipc.on method for callbacks"
     );
     assert!(
-        content.contains("_dispatch:") || content.contains("ipc._dispatch"),
-        "init.js should define internal dispatch method"
+        bootstrap.contains("_dispatch:"),
+        "bridge bootstrap should define internal dispatch method"
     );
 }
 
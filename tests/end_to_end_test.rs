@@ -8,7 +8,7 @@
 //! 5. Content is delivered to WebView
 //! 6. Page renders
 
-use browser_core::BrowserEngine;
+use browser_core::{BrowserEngine, NavigationInitiator};
 use browser_shell::BrowserShell;
 use config_manager::{Config, ShellConfig};
 use message_bus::MessageBus;
@@ -44,7 +44,7 @@ fn test_navigation_pipeline_components_integration() {
     // Step 5: Test navigation at engine level
     let url = Url::parse("https://www.example.com").expect("Invalid URL");
     engine
-        .navigate(1, url.clone())
+        .navigate(1, url.clone(), NavigationInitiator::UserTyped)
         .expect("Navigation failed");
 
     // Step 6: Verify navigation was recorded in history
@@ -169,7 +169,9 @@ fn test_browser_core_history_tracking() {
 
     for url_str in &urls {
         let url = Url::parse(url_str).expect("Invalid URL");
-        engine.navigate(1, url).expect("Navigation failed");
+        engine
+            .navigate(1, url, NavigationInitiator::UserTyped)
+            .expect("Navigation failed");
     }
 
     // Verify history
@@ -208,6 +210,8 @@ fn test_full_navigation_with_gui() {
         enable_devtools: false,
         theme: "light".to_string(),
         default_zoom: 1.0,
+        ctrl_tab_mru: false,
+        user_agent: None,
     };
 
     let sender = bus.sender();
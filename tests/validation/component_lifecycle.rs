@@ -59,6 +59,7 @@ fn test_adblock_engine_lifecycle() {
     // Engine should be functional
     let should_block = engine.should_block(
         "https://example.com/page.html",
+        "",
         shared_types::ResourceType::Document,
     );
 
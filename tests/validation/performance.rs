@@ -5,7 +5,7 @@
 
 use config_manager::Config;
 use message_bus::MessageBus;
-use network_stack::{NetworkStack, ResourceTiming};
+use network_stack::{NetworkStack, RequestDetails, ResourceTiming};
 use std::time::{Duration, Instant};
 
 /// Performance target: Page load should complete in under 3 seconds
@@ -62,8 +62,20 @@ fn test_resource_timing_structure() {
         start_time: Duration::from_millis(0),
         end_time: Duration::from_millis(200),
         duration_ms: 200,
+        ttfb_ms: 80,
+        queued_ms: 0,
+        dns_ms: 10,
+        connect_ms: 0,
+        tls_ms: 0,
+        download_ms: 120,
         size_bytes: 1024,
+        encoded_size: 1024,
+        decoded_size: 1024,
         from_cache: false,
+        revalidated: false,
+        cancelled: false,
+        redirected_from: Vec::new(),
+        details: RequestDetails::default(),
     };
 
     assert_eq!(timing.duration_ms, 200);
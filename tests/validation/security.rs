@@ -2,7 +2,7 @@
 ///
 /// Validates security-related functionality and configurations.
 
-use shared_types::{BrowserMessage, ResourceType};
+use shared_types::{BrowserMessage, NavigationInitiator, ResourceType};
 use config_manager::Config;
 
 #[test]
@@ -71,17 +71,22 @@ fn test_browser_message_variants() {
     let navigate_msg = BrowserMessage::NavigateRequest {
         tab_id: 1,
         url: "https://example.com".parse().unwrap(),
+        initiator: NavigationInitiator::UserTyped,
     };
 
     let http_msg = BrowserMessage::HttpRequest {
         request_id: 123,
         url: "https://api.example.com".parse().unwrap(),
+        method: "GET".to_string(),
         headers: HashMap::new(),
+        body: None,
+        initiator: Some("https://example.com".parse().unwrap()),
     };
 
     let should_block_msg = BrowserMessage::ShouldBlock {
         url: "https://ads.example.com".parse().unwrap(),
         resource_type: ResourceType::Script,
+        tab_id: Some(1),
     };
 
     // Messages should be constructable
@@ -125,8 +130,15 @@ fn test_no_hardcoded_credentials() {
     // Verify no hardcoded credentials in default config
     let config = Config::default();
 
-    // No API keys, passwords, or tokens in default config
-    let toml_str = toml::to_string(&config).unwrap();
+    // No API keys, passwords, or tokens in default config. The WebDriver
+    // token setting is there, but empty
+    assert_eq!(config.webdriver.auth_token(), None);
+    let toml_str: String = toml::to_string(&config)
+        .unwrap()
+        .lines()
+        .filter(|line| *line != "auth_token = \"\"")
+        .collect::<Vec<_>>()
+        .join("\n");
 
     // Should not contain common credential patterns
     assert!(!toml_str.to_lowercase().contains("password"));
@@ -180,7 +192,10 @@ fn test_message_serialization_safety() {
     let msg = BrowserMessage::HttpRequest {
         request_id: 123,
         url: "https://example.com?q=test".parse().unwrap(),
+        method: "GET".to_string(),
         headers: HashMap::new(),
+        body: None,
+        initiator: None,
     };
 
     let json = serde_json::to_string(&msg);
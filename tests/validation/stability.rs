@@ -2,7 +2,7 @@
 //!
 //! Validates stability targets including long-running session stability (FEAT-034).
 
-use browser_core::{BrowserEngine, Navigator};
+use browser_core::{BrowserEngine, NavigationInitiator, Navigator};
 use config_manager::Config;
 use message_bus::MessageBus;
 use network_stack::NetworkStack;
@@ -277,7 +277,7 @@ fn test_tab_operations_stability() {
         // Multiple tab operations
         for tab_id in 1..=5 {
             let url = Url::parse("https://example.com").unwrap();
-            let _ = engine.navigate(tab_id, url);
+            let _ = engine.navigate(tab_id, url, NavigationInitiator::UserTyped);
         }
 
         // Get bookmarks and history (should be stable even if empty)
@@ -322,7 +322,7 @@ fn test_extended_stability() {
         let url = Url::parse(url_str).unwrap();
 
         // Simulate navigation
-        let _ = engine.navigate(1, url);
+        let _ = engine.navigate(1, url, NavigationInitiator::UserTyped);
 
         iteration += 1;
 